@@ -0,0 +1,48 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <color.glsl>
+
+layout(location = 0) in vec2 fragTexCoord;
+
+layout(location = 0) out vec4 outColor;
+
+// Offscreen SDF target in linear HDR (rendered into its top-left corner at the current scale)
+layout(set = 0, binding = 0) uniform sampler2D sceneColor;
+
+layout(push_constant) uniform CompositeConstants {
+    // Fraction of the offscreen target that contains the rendered scene
+    vec2 uvScale;
+    // Linear exposure multiplier
+    float exposure;
+    // 0 = none, 1 = Reinhard, 2 = ACES (TonemapOperator)
+    uint operator;
+    // 0 = SDR, 1 = scRGB, 2 = HDR10, 3 = SDR on a UNORM swapchain (DisplayOutput)
+    uint outputMode;
+    // Brightness of 1.0 in HDR outputs
+    float paperWhiteNits;
+} composite;
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 tonemapAces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 tonemap(vec3 color) {
+    color = max(color, vec3(0.0));
+    if (composite.operator == 1u) {
+        return color / (1.0 + color);
+    }
+    if (composite.operator == 2u) {
+        return tonemapAces(color);
+    }
+    return color;
+}
+
+void main() {
+    // Keep bilinear taps inside the rendered region to avoid edge bleeding
+    vec2 halfTexel = 0.5 / vec2(textureSize(sceneColor, 0));
+    vec2 uv = min(fragTexCoord * composite.uvScale, composite.uvScale - halfTexel);
+    vec3 color = texture(sceneColor, uv).rgb * composite.exposure;
+    outColor = vec4(encodeOutput(tonemap(color), int(composite.outputMode), composite.paperWhiteNits), 1.0);
+}
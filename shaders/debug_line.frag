@@ -0,0 +1,18 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <color.glsl>
+
+// Display encoding of the swapchain (OutputSpecialization):
+// 0 = SDR, 1 = scRGB, 2 = HDR10, 3 = SDR on a UNORM swapchain
+layout(constant_id = 0) const int OUTPUT_MODE = 0;
+// Brightness of line white in HDR outputs
+layout(constant_id = 1) const float PAPER_WHITE = 200.0;
+
+layout(location = 0) in vec4 fragColor;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    outColor = vec4(encodeOutput(fragColor.rgb, OUTPUT_MODE, PAPER_WHITE), fragColor.a);
+}
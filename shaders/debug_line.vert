@@ -0,0 +1,40 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <camera.glsl>
+
+// Debug line vertex shader, projects world space lines with the SDF camera
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec4 inColor;
+
+layout(location = 0) out vec4 fragColor;
+
+layout(push_constant) uniform DebugLineConstants {
+    // xyz = camera origin of the viewport, w = orthographic half height (0 = perspective)
+    vec4 cameraOrigin;
+    // xyz = camera forward direction, w = roll about it in radians
+    vec4 cameraForward;
+    // xy = half width and height, zw = center of the view (ViewCamera::lens)
+    vec4 cameraLens;
+} debugLine;
+
+void main() {
+    // Same camera basis as the raymarcher in sdf.frag
+    vec3 forward = normalize(debugLine.cameraForward.xyz);
+    vec3 right;
+    vec3 up;
+    cameraBasis(forward, debugLine.cameraForward.w, right, up);
+    
+    vec3 q = inPosition - debugLine.cameraOrigin.xyz;
+    vec3 v = vec3(dot(q, right), dot(q, up), dot(q, forward));
+    vec4 lens = debugLine.cameraLens;
+    float orthoHalfHeight = debugLine.cameraOrigin.w;
+    
+    if (orthoHalfHeight > 0.0) {
+        gl_Position = vec4((v.xy / orthoHalfHeight - lens.zw) / lens.xy, 0.0, 1.0);
+    } else {
+        // Perspective: rd = normalize(right * uv.x + up * uv.y + forward), uv = lens.zw + ndc * lens.xy
+        gl_Position = vec4((v.xy - lens.zw * v.z) / lens.xy, 0.0, v.z);
+    }
+    fragColor = inColor;
+}
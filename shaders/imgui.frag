@@ -1,4 +1,13 @@
 #version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <color.glsl>
+
+// Display encoding of the swapchain (OutputSpecialization):
+// 0 = SDR, 1 = scRGB, 2 = HDR10, 3 = SDR on a UNORM swapchain
+layout(constant_id = 0) const int OUTPUT_MODE = 0;
+// Brightness of UI white in HDR outputs
+layout(constant_id = 1) const float PAPER_WHITE = 200.0;
 
 // Input from vertex shader
 layout(location = 0) in vec2 frag_texcoord;
@@ -7,13 +16,40 @@ layout(location = 1) in vec4 frag_color;
 // Output color
 layout(location = 0) out vec4 out_color;
 
-// Font texture sampler
+// Font texture sampler, or a texture of the ImGui user-texture registry
 layout(set = 0, binding = 0) uniform sampler2D font_texture;
 
+// How the bound texture is shown (TextureViewConstants), after the vertex shader's matrix
+layout(push_constant) uniform PushConstants {
+    // 0 = RGBA, 1-4 = R/G/B/A as grayscale, 5 = linearized depth
+    layout(offset = 64) int view_mode;
+    float depth_near;
+    float depth_far;
+} pc;
+
+// Isolate a channel of the texture for the GPU Textures panel
+vec4 view_texel(vec4 texel) {
+    if (pc.view_mode >= 1 && pc.view_mode <= 4) {
+        return vec4(vec3(texel[pc.view_mode - 1]), 1.0);
+    }
+    if (pc.view_mode == 5) {
+        // Perspective depth 1 - near / z (see sdf.frag) back to the view distance,
+        // shown from black at the near plane to white at depth_far
+        float n = pc.depth_near;
+        float eye = n / max(1.0 - texel.r, 1e-6);
+        return vec4(vec3(clamp((eye - n) / (pc.depth_far - n), 0.0, 1.0)), 1.0);
+    }
+    return texel;
+}
+
 void main() {
-    vec4 tex_color = texture(font_texture, frag_texcoord);
+    vec4 tex_color = view_texel(texture(font_texture, frag_texcoord));
+    
+    // ImGui colors are sRGB (see the color module); shade in linear
+    vec4 color = vec4(srgbToLinear(frag_color.rgb), frag_color.a);
     
     // Standard ImGui font rendering: multiply vertex color by texture
     // Font textures are typically white with varying alpha for the glyphs
-    out_color = frag_color * tex_color;
-}
\ No newline at end of file
+    color *= tex_color;
+    out_color = vec4(encodeOutput(color.rgb, OUTPUT_MODE, PAPER_WHITE), color.a);
+}
@@ -0,0 +1,22 @@
+// Viewport camera basis, shared by the raymarcher and the rasterized passes
+//
+// Include with `#include <camera.glsl>` (see config::shader::INCLUDE_DIR).
+// Matches ViewCamera::basis in src/vulkan/viewport.rs, which picking and
+// culling use; keep the two in step.
+
+#ifndef CAMERA_GLSL
+#define CAMERA_GLSL
+
+// Right and up axes of a camera looking along a normalized direction
+//
+// The top view looks along Y so it uses Z as up; the roll (radians, headset
+// eyes only) then turns both about the forward axis.
+void cameraBasis(vec3 forward, float roll, out vec3 right, out vec3 up) {
+    vec3 worldUp = abs(forward.y) > 0.99 ? vec3(0.0, 0.0, 1.0) : vec3(0.0, 1.0, 0.0);
+    vec3 levelRight = normalize(cross(worldUp, forward));
+    vec3 levelUp = cross(forward, levelRight);
+    right = levelRight * cos(roll) + levelUp * sin(roll);
+    up = cross(forward, right);
+}
+
+#endif
@@ -0,0 +1,62 @@
+// sRGB transfer functions and swapchain output encoding
+//
+// Include with `#include <color.glsl>` (see config::shader::INCLUDE_DIR).
+// The output modes match DisplayOutput in src/vulkan/tonemap.rs and the
+// sRGB functions the color module in src/color.rs; keep them in step.
+
+#ifndef COLOR_GLSL
+#define COLOR_GLSL
+
+// Output modes of the swapchain (DisplayOutput)
+#define OUTPUT_SDR 0
+#define OUTPUT_SCRGB 1
+#define OUTPUT_HDR10 2
+#define OUTPUT_SDR_UNORM 3
+
+// sRGB encoded color to linear
+vec3 srgbToLinear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+// sRGB transfer function, for swapchains without an _SRGB format
+vec3 linearToSrgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+// SMPTE ST 2084 (PQ) encoding of absolute luminance normalized to 10000 nits
+vec3 encodePq(vec3 normalized) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+    vec3 y = pow(clamp(normalized, 0.0, 1.0), vec3(m1));
+    return pow((c1 + c2 * y) / (1.0 + c3 * y), vec3(m2));
+}
+
+// Encode a linear color for the swapchain
+//
+// outputMode is one of the OUTPUT_* modes; paperWhite is the brightness of
+// 1.0 in nits for the HDR outputs.
+vec3 encodeOutput(vec3 c, int outputMode, float paperWhite) {
+    if (outputMode == OUTPUT_SCRGB) {
+        // scRGB: 1.0 is 80 nits
+        return c * paperWhite / 80.0;
+    }
+    if (outputMode == OUTPUT_HDR10) {
+        // Rec.709 to Rec.2020 primaries (column-major)
+        const mat3 toRec2020 = mat3(
+            0.6274, 0.0691, 0.0164,
+            0.3293, 0.9195, 0.0880,
+            0.0433, 0.0114, 0.8956
+        );
+        return encodePq(toRec2020 * c * paperWhite / 10000.0);
+    }
+    if (outputMode == OUTPUT_SDR_UNORM) {
+        return linearToSrgb(clamp(c, 0.0, 1.0));
+    }
+    // The sRGB swapchain applies the transfer function on write
+    return clamp(c, 0.0, 1.0);
+}
+
+#endif
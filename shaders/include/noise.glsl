@@ -0,0 +1,137 @@
+// Procedural noise: simplex, fractal (fbm) and curl noise
+//
+// Include with `#include <noise.glsl>` (see config::shader::INCLUDE_DIR).
+// Every function has a matching CPU implementation in src/noise.rs, so tools
+// like the mesh exporter see the same values; keep the two in step.
+//
+// The seed shifts the whole noise field, so any float works, but only whole
+// numbers are offered in the app. Positions are used as they are; scale them
+// by the global noise scale (scene.noise.y in sdf.frag) before calling.
+
+#ifndef NOISE_GLSL
+#define NOISE_GLSL
+
+// Simplex noise after Ian McEwan, Ashima Arts and Stefan Gustavson (MIT license),
+// in the revision with a 0.5 kernel radius, which leaves no seams between simplices
+vec3 noiseMod289(vec3 x) {
+    return x - floor(x * (1.0 / 289.0)) * 289.0;
+}
+
+vec4 noiseMod289(vec4 x) {
+    return x - floor(x * (1.0 / 289.0)) * 289.0;
+}
+
+vec4 noisePermute(vec4 x) {
+    return noiseMod289((x * 34.0 + 10.0) * x);
+}
+
+vec4 noiseTaylorInvSqrt(vec4 r) {
+    return 1.79284291400159 - 0.85373472095314 * r;
+}
+
+// 3D simplex noise, about -1 to 1
+float simplexNoise(vec3 v) {
+    const vec2 C = vec2(1.0 / 6.0, 1.0 / 3.0);
+    const vec4 D = vec4(0.0, 0.5, 1.0, 2.0);
+
+    // First corner
+    vec3 i = floor(v + dot(v, C.yyy));
+    vec3 x0 = v - i + dot(i, C.xxx);
+
+    // Other corners
+    vec3 g = step(x0.yzx, x0.xyz);
+    vec3 l = 1.0 - g;
+    vec3 i1 = min(g.xyz, l.zxy);
+    vec3 i2 = max(g.xyz, l.zxy);
+    vec3 x1 = x0 - i1 + C.xxx;
+    vec3 x2 = x0 - i2 + C.yyy;
+    vec3 x3 = x0 - D.yyy;
+
+    // Permutations
+    i = noiseMod289(i);
+    vec4 p = noisePermute(noisePermute(noisePermute(
+                 i.z + vec4(0.0, i1.z, i2.z, 1.0))
+               + i.y + vec4(0.0, i1.y, i2.y, 1.0))
+               + i.x + vec4(0.0, i1.x, i2.x, 1.0));
+
+    // Gradients: 7x7 points over a square, mapped onto an octahedron
+    vec3 ns = (1.0 / 7.0) * D.wyz - D.xzx;
+    vec4 j = p - 49.0 * floor(p * ns.z * ns.z);
+    vec4 x_ = floor(j * ns.z);
+    vec4 y_ = floor(j - 7.0 * x_);
+    vec4 x = x_ * ns.x + ns.yyyy;
+    vec4 y = y_ * ns.x + ns.yyyy;
+    vec4 h = 1.0 - abs(x) - abs(y);
+    vec4 b0 = vec4(x.xy, y.xy);
+    vec4 b1 = vec4(x.zw, y.zw);
+    vec4 s0 = floor(b0) * 2.0 + 1.0;
+    vec4 s1 = floor(b1) * 2.0 + 1.0;
+    vec4 sh = -step(h, vec4(0.0));
+    vec4 a0 = b0.xzyw + s0.xzyw * sh.xxyy;
+    vec4 a1 = b1.xzyw + s1.xzyw * sh.zzww;
+    vec3 p0 = vec3(a0.xy, h.x);
+    vec3 p1 = vec3(a0.zw, h.y);
+    vec3 p2 = vec3(a1.xy, h.z);
+    vec3 p3 = vec3(a1.zw, h.w);
+
+    // Normalise gradients
+    vec4 norm = noiseTaylorInvSqrt(vec4(dot(p0, p0), dot(p1, p1), dot(p2, p2), dot(p3, p3)));
+    p0 *= norm.x;
+    p1 *= norm.y;
+    p2 *= norm.z;
+    p3 *= norm.w;
+
+    // Mix final noise value
+    vec4 m = max(0.5 - vec4(dot(x0, x0), dot(x1, x1), dot(x2, x2), dot(x3, x3)), 0.0);
+    m = m * m;
+    return 105.0 * dot(m * m, vec4(dot(p0, x0), dot(p1, x1), dot(p2, x2), dot(p3, x3)));
+}
+
+// Shift of the noise field for a seed, kept within one period of the permutation
+vec3 noiseSeedOffset(float seed) {
+    return noiseMod289(seed * vec3(17.13, 31.71, 47.37));
+}
+
+// 3D simplex noise of a seed, about -1 to 1
+float simplexNoise(vec3 p, float seed) {
+    return simplexNoise(p + noiseSeedOffset(seed));
+}
+
+// Fractal noise: octaves of simplex noise, each twice the frequency and half
+// the amplitude of the one before, normalized to about -1 to 1
+float fbm(vec3 p, float seed, int octaves) {
+    float sum = 0.0;
+    float amplitude = 1.0;
+    float total = 0.0;
+    for (int octave = 0; octave < octaves; octave++) {
+        sum += amplitude * simplexNoise(p, seed + float(octave));
+        total += amplitude;
+        p *= 2.0;
+        amplitude *= 0.5;
+    }
+    return total > 0.0 ? sum / total : 0.0;
+}
+
+// Curl of a simplex noise vector potential: a divergence free flow field,
+// e.g. for swirling particles
+vec3 curlNoise(vec3 p, float seed) {
+    const float e = 0.01;
+    vec3 dx = vec3(e, 0.0, 0.0);
+    vec3 dy = vec3(0.0, e, 0.0);
+    vec3 dz = vec3(0.0, 0.0, e);
+
+    // Three independent potential components from shifted seeds
+    float s0 = seed;
+    float s1 = seed + 101.0;
+    float s2 = seed + 202.0;
+
+    float dz_dy = simplexNoise(p + dy, s2) - simplexNoise(p - dy, s2);
+    float dy_dz = simplexNoise(p + dz, s1) - simplexNoise(p - dz, s1);
+    float dx_dz = simplexNoise(p + dz, s0) - simplexNoise(p - dz, s0);
+    float dz_dx = simplexNoise(p + dx, s2) - simplexNoise(p - dx, s2);
+    float dy_dx = simplexNoise(p + dx, s1) - simplexNoise(p - dx, s1);
+    float dx_dy = simplexNoise(p + dy, s0) - simplexNoise(p - dy, s0);
+    return vec3(dz_dy - dy_dz, dx_dz - dz_dx, dy_dx - dx_dy) / (2.0 * e);
+}
+
+#endif
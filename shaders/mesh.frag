@@ -0,0 +1,64 @@
+#version 450
+
+// Mesh fragment shader, lit by the sun like the SDF shapes and shadowed by
+// the meshes through the shadow map
+layout(location = 0) in vec3 fragNormal;
+// Base color of the instance
+layout(location = 1) in vec3 fragColor;
+layout(location = 2) in float fragViewDepth;
+layout(location = 3) in vec4 fragShadowPosition;
+
+layout(location = 0) out vec4 outColor;
+// G-buffer, encoded like sdf.frag
+layout(location = 1) out vec4 outNormal;
+layout(location = 2) out float outLinearDepth;
+// Meshes are not outlined
+layout(location = 3) out float outSelection;
+
+// Must match MeshPushConstants in src/vulkan/mesh_pass.rs
+layout(push_constant) uniform MeshConstants {
+    vec4 cameraOrigin;
+    vec4 cameraForward;
+    vec4 cameraLens;
+    // xyz = direction towards the sun, w = intensity
+    vec4 sunDirection;
+    mat4 lightViewProjection;
+} mesh;
+
+// Depth written by the shadow pass (see src/vulkan/shadow_map.rs)
+layout(set = 0, binding = 0) uniform sampler2DShadow shadowMap;
+
+// Texels on each side of the PCF kernel
+#define SHADOW_PCF_RADIUS 1
+
+// Fraction of the sun reaching the fragment, averaged over the PCF kernel
+float sunVisibility() {
+    vec3 shadowPos = fragShadowPosition.xyz / fragShadowPosition.w;
+    vec2 uv = shadowPos.xy * 0.5 + 0.5;
+    // Beyond the far plane nothing casts a shadow
+    if (shadowPos.z >= 1.0) {
+        return 1.0;
+    }
+    vec2 texel = 1.0 / vec2(textureSize(shadowMap, 0));
+    float lit = 0.0;
+    for (int y = -SHADOW_PCF_RADIUS; y <= SHADOW_PCF_RADIUS; y++) {
+        for (int x = -SHADOW_PCF_RADIUS; x <= SHADOW_PCF_RADIUS; x++) {
+            lit += texture(shadowMap, vec3(uv + vec2(x, y) * texel, shadowPos.z));
+        }
+    }
+    float taps = float((2 * SHADOW_PCF_RADIUS + 1) * (2 * SHADOW_PCF_RADIUS + 1));
+    return lit / taps;
+}
+
+void main() {
+    vec3 normal = normalize(fragNormal);
+    vec3 sunDir = normalize(mesh.sunDirection.xyz);
+    float diffuse = max(dot(normal, sunDir), 0.0) * mesh.sunDirection.w * sunVisibility();
+    
+    // Default ambient term of calculateLighting in sdf.frag (Params.ambientColor)
+    vec3 color = fragColor * (0.1 + diffuse);
+    outColor = vec4(color, 1.0);
+    outNormal = vec4(normal * 0.5 + 0.5, 1.0);
+    outLinearDepth = fragViewDepth;
+    outSelection = 0.0;
+}
@@ -0,0 +1,63 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <camera.glsl>
+
+// Mesh vertex shader, projects triangles with the SDF camera and writes
+// depth with the same mapping as sdf.frag so meshes and raymarched shapes
+// occlude each other
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+// Per-instance inputs (must match MeshInstance in src/vulkan/mesh.rs)
+layout(location = 2) in mat4 instanceModel;
+layout(location = 6) in vec4 instanceColor;
+
+layout(location = 0) out vec3 fragNormal;
+layout(location = 1) out vec3 fragColor;
+// Distance along the camera forward axis, for the G-buffer
+layout(location = 2) out float fragViewDepth;
+// Position in shadow map clip space
+layout(location = 3) out vec4 fragShadowPosition;
+
+// Must match MeshPushConstants in src/vulkan/mesh_pass.rs
+layout(push_constant) uniform MeshConstants {
+    // xyz = camera origin of the viewport, w = orthographic half height (0 = perspective)
+    vec4 cameraOrigin;
+    // xyz = camera forward direction, w = roll about it in radians
+    vec4 cameraForward;
+    // xy = half width and height, zw = center of the view (ViewCamera::lens)
+    vec4 cameraLens;
+    // xyz = direction towards the sun, w = intensity
+    vec4 sunDirection;
+    // World to shadow map clip space
+    mat4 lightViewProjection;
+} mesh;
+
+// Depth mapping (must match sdf.frag)
+#define DEPTH_NEAR 0.01
+#define ORTHO_DEPTH_RANGE 100.0
+
+void main() {
+    // Same camera basis as the raymarcher in sdf.frag
+    vec3 forward = normalize(mesh.cameraForward.xyz);
+    vec3 right;
+    vec3 up;
+    cameraBasis(forward, mesh.cameraForward.w, right, up);
+    
+    vec3 worldPos = (instanceModel * vec4(inPosition, 1.0)).xyz;
+    vec3 q = worldPos - mesh.cameraOrigin.xyz;
+    vec3 v = vec3(dot(q, right), dot(q, up), dot(q, forward));
+    vec4 lens = mesh.cameraLens;
+    float orthoHalfHeight = mesh.cameraOrigin.w;
+    
+    if (orthoHalfHeight > 0.0) {
+        gl_Position = vec4((v.xy / orthoHalfHeight - lens.zw) / lens.xy, v.z / ORTHO_DEPTH_RANGE, 1.0);
+    } else {
+        // Depth after the perspective divide is 1 - DEPTH_NEAR / v.z
+        gl_Position = vec4((v.xy - lens.zw * v.z) / lens.xy, v.z - DEPTH_NEAR, v.z);
+    }
+    fragNormal = transpose(inverse(mat3(instanceModel))) * inNormal;
+    fragColor = instanceColor.rgb;
+    fragViewDepth = v.z;
+    fragShadowPosition = mesh.lightViewProjection * vec4(worldPos, 1.0);
+}
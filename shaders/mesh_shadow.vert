@@ -0,0 +1,17 @@
+#version 450
+
+// Mesh shadow vertex shader, draws the meshes depth-only from the sun into
+// the shadow map (see src/vulkan/shadow_map.rs)
+layout(location = 0) in vec3 inPosition;
+// Per-instance model matrix (must match MeshInstance in src/vulkan/mesh.rs)
+layout(location = 2) in mat4 instanceModel;
+
+// Must match ShadowPushConstants in src/vulkan/mesh_pass.rs
+layout(push_constant) uniform ShadowConstants {
+    // World to shadow map clip space
+    mat4 lightViewProjection;
+} shadow;
+
+void main() {
+    gl_Position = shadow.lightViewProjection * instanceModel * vec4(inPosition, 1.0);
+}
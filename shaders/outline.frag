@@ -0,0 +1,67 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <color.glsl>
+
+// Selection outline: pixels outside the selection mask with a selected pixel
+// within the thickness are painted in the outline color
+
+// Display encoding of the swapchain (OutputSpecialization):
+// 0 = SDR, 1 = scRGB, 2 = HDR10, 3 = SDR on a UNORM swapchain
+layout(constant_id = 0) const int OUTPUT_MODE = 0;
+// Brightness of outline white in HDR outputs
+layout(constant_id = 1) const float PAPER_WHITE = 200.0;
+
+// Widest outline searched (config::outline::MAX_THICKNESS)
+#define MAX_RADIUS 8
+
+layout(location = 0) in vec2 fragTexCoord;
+
+layout(location = 0) out vec4 outColor;
+
+// G-buffer selection mask of the offscreen target (rendered into its top-left corner)
+layout(set = 0, binding = 0) uniform sampler2D selectionMask;
+
+// Must match OutlinePushConstants in src/vulkan/outline.rs
+layout(push_constant) uniform OutlineConstants {
+    // Linear RGB color and opacity
+    vec4 color;
+    // Fraction of the offscreen target that contains the rendered scene
+    vec2 uvScale;
+    // Swapchain size in pixels
+    vec2 screenSize;
+    // Width in pixels
+    float thickness;
+} outline;
+
+// Whether a point of the screen (in texture coordinates) shows the selection
+bool isSelected(vec2 screenUv) {
+    if (any(lessThan(screenUv, vec2(0.0))) || any(greaterThan(screenUv, vec2(1.0)))) {
+        return false;
+    }
+    return texture(selectionMask, screenUv * outline.uvScale).r > 0.5;
+}
+
+void main() {
+    if (isSelected(fragTexCoord)) {
+        discard;
+    }
+
+    // Search a disk of the outline thickness for a selected pixel
+    float radius = min(outline.thickness, float(MAX_RADIUS));
+    int steps = int(ceil(radius));
+    vec2 pixel = 1.0 / outline.screenSize;
+    for (int y = -steps; y <= steps; y++) {
+        for (int x = -steps; x <= steps; x++) {
+            vec2 offset = vec2(x, y);
+            if (dot(offset, offset) > radius * radius) {
+                continue;
+            }
+            if (isSelected(fragTexCoord + offset * pixel)) {
+                outColor = vec4(encodeOutput(outline.color.rgb, OUTPUT_MODE, PAPER_WHITE), outline.color.a);
+                return;
+            }
+        }
+    }
+    discard;
+}
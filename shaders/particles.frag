@@ -0,0 +1,23 @@
+#version 450
+
+// Particle fragment shader, a soft round spot added to the HDR scene
+layout(location = 0) in vec3 fragColor;
+layout(location = 1) in vec2 fragCorner;
+
+layout(location = 0) out vec4 outColor;
+// Added to the G-buffer by the shared blend state, so zero keeps it unchanged
+layout(location = 1) out vec4 outNormal;
+layout(location = 2) out float outLinearDepth;
+layout(location = 3) out float outSelection;
+
+void main() {
+    float falloff = 1.0 - dot(fragCorner, fragCorner);
+    if (falloff <= 0.0) {
+        discard;
+    }
+    // Alpha is ignored by the additive blend
+    outColor = vec4(fragColor * falloff * falloff, 0.0);
+    outNormal = vec4(0.0);
+    outLinearDepth = 0.0;
+    outSelection = 0.0;
+}
@@ -0,0 +1,73 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <camera.glsl>
+
+// Particle vertex shader, draws every particle slot as a camera facing quad
+// projected with the SDF camera; dead particles collapse to a point
+layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragCorner;
+
+// Must match GpuParticle in src/vulkan/particles.rs
+struct Particle {
+    vec4 positionAge;
+    vec4 velocityLifetime;
+    vec4 startColor;
+    vec4 endColor;
+};
+
+layout(std430, set = 0, binding = 0) readonly buffer Particles {
+    Particle particles[];
+};
+
+// Must match ParticleDrawPushConstants in src/vulkan/particles.rs
+layout(push_constant) uniform ParticleConstants {
+    // xyz = camera origin of the viewport, w = orthographic half height (0 = perspective)
+    vec4 cameraOrigin;
+    // xyz = camera forward direction, w = roll about it in radians
+    vec4 cameraForward;
+    // xy = half width and height, zw = center of the view (ViewCamera::lens)
+    vec4 cameraLens;
+} camera;
+
+// Depth mapping (must match sdf.frag)
+#define DEPTH_NEAR 0.01
+#define ORTHO_DEPTH_RANGE 100.0
+
+const vec2 CORNERS[6] = vec2[](
+    vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0),
+    vec2(-1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0)
+);
+
+void main() {
+    Particle particle = particles[gl_InstanceIndex];
+    float t = particle.positionAge.w / max(particle.velocityLifetime.w, 1e-6);
+    fragCorner = CORNERS[gl_VertexIndex];
+    if (t >= 1.0) {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        fragColor = vec3(0.0);
+        return;
+    }
+
+    // Same camera basis as the raymarcher in sdf.frag
+    vec3 forward = normalize(camera.cameraForward.xyz);
+    vec3 right;
+    vec3 up;
+    cameraBasis(forward, camera.cameraForward.w, right, up);
+
+    vec3 q = particle.positionAge.xyz - camera.cameraOrigin.xyz;
+    vec3 v = vec3(dot(q, right), dot(q, up), dot(q, forward));
+    // Quads face the camera and shrink towards the end of their life
+    v.xy += fragCorner * particle.startColor.a * (1.0 - 0.5 * t);
+    vec4 lens = camera.cameraLens;
+    float orthoHalfHeight = camera.cameraOrigin.w;
+
+    if (orthoHalfHeight > 0.0) {
+        gl_Position = vec4((v.xy / orthoHalfHeight - lens.zw) / lens.xy, v.z / ORTHO_DEPTH_RANGE, 1.0);
+    } else {
+        // Depth after the perspective divide is 1 - DEPTH_NEAR / v.z
+        gl_Position = vec4((v.xy - lens.zw * v.z) / lens.xy, v.z - DEPTH_NEAR, v.z);
+    }
+    // Fade out over the lifetime
+    fragColor = mix(particle.startColor.rgb, particle.endColor.rgb, t) * (1.0 - t);
+}
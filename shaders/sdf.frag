@@ -1,9 +1,18 @@
 #version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include <noise.glsl>
+#include <camera.glsl>
 
 layout(location = 0) in vec2 fragTexCoord;
 layout(location = 1) in vec3 fragWorldPos;
 
 layout(location = 0) out vec4 outColor;
+// G-buffer: encoded world normal (alpha 1 on surfaces), linear view depth and
+// selection mask, zero on misses
+layout(location = 1) out vec4 outNormal;
+layout(location = 2) out float outLinearDepth;
+layout(location = 3) out float outSelection;
 
 // SDF Shape types
 #define SPHERE 0
@@ -12,30 +21,232 @@ layout(location = 0) out vec4 outColor;
 #define TORUS 3
 #define CYLINDER 4
 
-// SDF Shape data (will be replaced by uniform buffer from ECS)
+// Display modes (must match SDFDisplayMode::as_gpu)
+#define DISPLAY_VISIBLE 0
+#define DISPLAY_GHOST 1
+#define DISPLAY_HIDDEN 2
+
+// CSG operations (must match CsgOperation::as_gpu)
+#define CSG_UNION 0
+#define CSG_SUBTRACTION 1
+#define CSG_INTERSECTION 2
+
+// Fraction of pixels covered by a ghosted (hidden but referenced) shape
+#define GHOST_OPACITY 0.5
+
+// Optional shading features (defined by ShaderVariantKey::defines):
+//   ENABLE_SOFT_SHADOWS - penumbra soft shadows instead of hard shadow rays
+//   ENABLE_AO           - SDF ambient occlusion on the ambient term
+//   ENABLE_CONE_GI      - ambient light gathered by cones marched against the scene
+//   ENABLE_REFLECTIONS  - secondary rays reflected off and refracted through shapes
+#define AO_SAMPLES 5
+#define AO_STEP 0.03
+#define GI_CONES 6
+#define GI_CONE_STEPS 12
+#define GI_CONE_START 0.02
+// Cone radius per unit of distance (tangent of the half angle, about 30 degrees)
+#define GI_CONE_APERTURE 0.577
+// Distance secondary rays start off a surface, and reflections inside a
+// shape before a refracted ray gives up
+#define SECONDARY_RAY_OFFSET 0.01
+#define MAX_INTERNAL_REFLECTIONS 4
+
+// Rays per pixel for antialiasing (defined by ShaderVariantKey::set_samples): 1, 2, 4 or 8
+#ifndef AA_SAMPLES
+#define AA_SAMPLES 1
+#endif
+
+// Raymarch quality (specialization constants set by VulkanPipeline from RaymarchQuality)
+layout(constant_id = 0) const int MAX_MARCH_STEPS = 100;
+layout(constant_id = 1) const float MARCH_EPSILON = 0.001;
+layout(constant_id = 2) const float MAX_MARCH_DISTANCE = 10.0;
+
+// Depth mapping (must match mesh.vert): perspective depth is 1 - DEPTH_NEAR / z,
+// orthographic depth is z / ORTHO_DEPTH_RANGE, with z the distance along the view direction
+#define DEPTH_NEAR 0.01
+#define ORTHO_DEPTH_RANGE 100.0
+
+// Reference grid overlay (gridFlags bits, must match GridSettings::shader_flags)
+#define GRID_FLAG_LINES 1
+#define GRID_FLAG_AXES 2
+#define GRID_LINE_OPACITY 0.35
+#define AXIS_LINE_PIXELS 1.5
+
+// Background modes (must match BackgroundMode::as_gpu)
+#define BACKGROUND_SOLID 0
+#define BACKGROUND_GRADIENT 1
+#define BACKGROUND_SKY 2
+#define BACKGROUND_HDRI 3
+
+// Background flags (must match BACKGROUND_FLAG_* in src/vulkan/scene_buffer.rs)
+#define BACKGROUND_FLAG_MAP 1
+#define BACKGROUND_FLAG_REFLECTIONS 2
+
+// Mouse buttons (must match MOUSE_* in src/vulkan/fullscreen_pass.rs)
+#define MOUSE_LEFT 1
+#define MOUSE_RIGHT 2
+#define MOUSE_MIDDLE 4
+
+#define PI 3.14159265359
+
+// Modifier kinds (must match SDFModifier::as_gpu)
+#define MODIFIER_TWIST 0
+#define MODIFIER_BEND 1
+#define MODIFIER_REPEAT 2
+#define MODIFIER_ONION 3
+#define MODIFIER_ROUND 4
+#define MODIFIER_NOISE 5
+
+// Noise modifier fractal noise (must match config::noise::MODIFIER_OCTAVES /
+// MODIFIER_MAX_SLOPE)
+#define NOISE_OCTAVES 4
+#define NOISE_MAX_SLOPE 8.0
+
+// Scene limits (must match config::rendering::MAX_SDF_SHAPES / MAX_SDF_LIGHTS /
+// MAX_SDF_TEXTS / MAX_SDF_GLYPHS / MAX_SDF_MODIFIERS)
+#define MAX_SHAPES 32
+#define MAX_LIGHTS 8
+#define MAX_TEXTS 8
+#define MAX_GLYPHS 256
+#define MAX_MODIFIERS 64
+
+// SDF Shape data (must match GpuShape in src/vulkan/scene_buffer.rs)
 struct SDFShapeData {
-    int shapeType;
-    vec3 position;
-    float size;
-    vec4 params; // Additional parameters
-    vec3 color;
-    float metallic;
-    float roughness;
-    float emission;
-    int padding;
+    vec4 positionSize;   // xyz = position, w = size
+    vec4 params;         // Additional parameters
+    vec4 colorMetallic;  // rgb = color, a = metallic
+    vec4 material;       // x = roughness, y = emission, z = CSG blend radius, w = 1 if selected
+    ivec4 info;          // x = shape type, y = display mode, z = CSG operation, w = CSG group (0 = none)
+    ivec4 modifiers;     // x = first modifier, y = modifier count
+    vec4 optics;         // x = reflectivity, y = transparency, z = index of refraction
+    uvec4 layers;        // x = render layer mask
 };
 
-// Light data
+// Light kinds (must match LightKind::as_gpu)
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+// Light data (must match GpuLight in src/vulkan/scene_buffer.rs)
 struct LightData {
-    vec3 position;
-    vec3 color;
-    float intensity;
-    int padding;
+    vec4 position;        // xyz = position, w = radius (0 = no falloff)
+    vec4 colorIntensity;  // rgb = color, a = intensity
+    vec4 direction;       // xyz = normalized direction the light shines in
+    vec4 spot;            // x = cosine of the cone's outer half angle, y = of its inner one
+    ivec4 info;           // x = LIGHT_* kind
 };
 
-// Temporary hardcoded data for testing
-SDFShapeData shapes[3];
-LightData lights[1];
+// Background data (must match GpuBackground in src/vulkan/scene_buffer.rs)
+struct BackgroundData {
+    ivec4 mode;           // x = BACKGROUND_* mode, y = BACKGROUND_FLAG_* bits
+    vec4 gradientTop;     // rgb = color straight up
+    vec4 gradientBottom;  // rgb = color straight down
+    vec4 hdri;            // x = intensity, y = rotation in turns
+};
+
+// Mouse data (must match GpuMouse in src/vulkan/scene_buffer.rs), like
+// shadertoy's iMouse but normalized to 0..1 from the bottom left of the window
+struct MouseData {
+    vec4 position;        // xy = cursor, zw = last left press
+    ivec4 buttons;        // x = MOUSE_* bits held, y = 1 in the frame of a left press
+};
+
+// 3D text data (must match GpuText in src/vulkan/scene_buffer.rs)
+struct TextData {
+    vec4 positionSize;     // xyz = position, w = world units per em
+    vec4 extent;           // x = half depth, yz = half size of the glyph box, w = atlas distance range, in em
+    vec4 colorMetallic;    // rgb = color, a = metallic
+    vec4 material;         // x = roughness, w = 1 if selected
+    ivec4 info;            // x = first glyph, y = glyph count, z = display mode
+    vec4 inverseRotation[3];  // Rows of the rotation from world to text space
+    uvec4 layers;          // x = render layer mask
+};
+
+// Glyph quad of a text (must match GpuGlyph in src/vulkan/scene_buffer.rs)
+struct GlyphData {
+    vec4 plane;  // xy = bottom left, zw = top right, in em from the text's center
+    vec4 uv;     // Atlas coordinates of the bottom left (xy) and top right (zw) corners
+};
+
+// Step of a shape's modifier stack (must match GpuModifier in src/vulkan/scene_buffer.rs)
+struct ModifierData {
+    vec4 params;  // Twist/bend: x = rate; repeat: xyz = spacing, w = count; onion: x = thickness; round: x = radius;
+                  // noise: x = amplitude, y = frequency
+    ivec4 info;   // x = MODIFIER_* kind
+};
+
+// Scene data uploaded from the ECS world every frame
+layout(set = 0, binding = 0) uniform SceneData {
+    ivec4 counts;  // x = shape count, y = light count, z = text count, w = modifier count
+    SDFShapeData shapes[MAX_SHAPES];
+    LightData lights[MAX_LIGHTS];
+    BackgroundData background;
+    MouseData mouse;
+    TextData texts[MAX_TEXTS];
+    GlyphData glyphs[MAX_GLYPHS];
+    ModifierData modifiers[MAX_MODIFIERS];
+    vec4 noise;    // x = seed, y = scale
+} scene;
+
+// Equirectangular HDR environment map (1x1 black until one is loaded)
+layout(set = 0, binding = 1) uniform sampler2D environmentMap;
+
+// MSDF font atlas of the texts (see src/vulkan/text.rs), binding must match config::text::ATLAS_BINDING
+layout(set = 0, binding = 3) uniform sampler2D textAtlas;
+
+// Live shading parameters, edited in the Shader Parameters panel (see src/shader_params.rs).
+// The @ annotations pick each member's control, default and group (see src/vulkan/shader_annotations.rs).
+layout(set = 0, binding = 2) uniform Params {
+    // @group(Lighting)
+    vec3 ambientColor;         // @color @default(0.1) Ambient light, multiplied with the surface color
+    float specularPower;       // @slider(1, 256, step=1) @default(32) Shininess of specular highlights
+    bool specular;             // @checkbox @default(1) Draw specular highlights
+    // @group(Shadows)
+    float shadowMin;           // @slider(0, 1) @default(0.3) Light left in full shadow
+    float softShadowHardness;  // @slider(1, 32, step=0.5) @default(8) Sharpness of soft shadow edges
+    float aoStrength;          // @slider(0, 10) @default(3) Darkening of creases by ambient occlusion
+    // @group(Global illumination)
+    float giSkyStrength;       // @slider(0, 2) @default(0.5) Background light gathered by unblocked cones
+    float giBounce;            // @slider(0, 1) @default(0.5) Light bounced back by blocked cones
+    float giDistance;          // @slider(0.1, 10) @default(2) Furthest a cone looks for occluders
+    // @group(Reflections)
+    int maxBounces;            // @slider(0, 4) @default(2) Secondary rays followed after a reflective or transparent hit
+} params;
+
+// Whether ghosted shapes take part in the current march (primary rays only,
+// decided per pixel by an ordered dither so ghosts appear semi-transparent)
+bool gIncludeGhosts = false;
+
+// Push constants for window data (must match SdfPushConstants in src/vulkan/push_constants.rs)
+layout(push_constant) uniform PushConstants {
+    vec2 uResolution;
+    float uTime;
+    // Viewport camera rotation about its forward axis in radians
+    float cameraRoll;
+    // Clear color of the viewport, the background of the solid color mode
+    vec4 clearColor;
+    // Viewport render mode: 0 = shaded, 1 = normals, 2 = depth
+    int renderMode;
+    // Reference grid: GRID_FLAG_* bits and world units between grid lines
+    int gridFlags;
+    float gridSpacing;
+    // Sun (main directional light): xyz = direction towards the sun, w = intensity
+    vec4 sunDirection;
+    // Sun color (rgb), w = distance at which the grid has faded out
+    vec4 sunColor;
+    // Viewport camera: xyz = origin, w = orthographic half height (0 = perspective)
+    vec4 cameraOrigin;
+    // Viewport camera forward direction (xyz), w = mask of the render layers shown
+    vec4 cameraForward;
+    // Viewport camera extent: xy = half width and height, zw = center of the view
+    vec4 cameraLens;
+} pushConstants;
+
+#define RENDER_MODE_SHADED 0
+#define RENDER_MODE_NORMALS 1
+#define RENDER_MODE_DEPTH 2
+#define RENDER_MODE_ITERATIONS 3
+#define RENDER_MODE_DISTANCE 4
 
 // SDF distance functions
 float sdSphere(vec3 p, float r) {
@@ -62,38 +273,263 @@ float sdCylinder(vec3 p, vec2 h) {
     return min(max(d.x, d.y), 0.0) + length(max(d, 0.0));
 }
 
-// Scene SDF function
-float map(vec3 p) {
-    float minDist = 1000.0;
+// Rotate about the Y axis in proportion to the height
+vec3 opTwist(vec3 p, float rate) {
+    float c = cos(rate * p.y);
+    float s = sin(rate * p.y);
+    return vec3(c * p.x - s * p.z, p.y, s * p.x + c * p.z);
+}
+
+// Curl the X axis up around the Z axis
+vec3 opBend(vec3 p, float rate) {
+    float c = cos(rate * p.x);
+    float s = sin(rate * p.x);
+    return vec3(c * p.x - s * p.y, s * p.x + c * p.y, p.z);
+}
+
+// Fold space into the cell around the origin, axes with zero spacing are left alone
+vec3 opRepeat(vec3 p, vec3 spacing, float count) {
+    vec3 cell = round(p / max(spacing, 1e-6)) * step(1e-6, spacing);
+    if (count > 0.0) cell = clamp(cell, -count, count);
+    return p - spacing * cell;
+}
+
+// Distance to the primitive of a shape, in its local space
+float primitiveDistance(int i, vec3 localPos) {
+    float size = scene.shapes[i].positionSize.w;
+    vec4 params = scene.shapes[i].params;
     
-    for (int i = 0; i < 3; i++) {
-        vec3 localPos = p - shapes[i].position;
-        float dist = 1000.0;
-        
-        switch (shapes[i].shapeType) {
-            case SPHERE:
-                dist = sdSphere(localPos, shapes[i].size);
+    switch (scene.shapes[i].info.x) {
+        case SPHERE:
+            return sdSphere(localPos, size);
+        case BOX:
+            return sdBox(localPos, vec3(size));
+        case PLANE:
+            return sdPlane(localPos, vec4(0.0, 1.0, 0.0, 0.0));
+        case TORUS:
+            return sdTorus(localPos, vec2(size, params.x));
+        case CYLINDER:
+            return sdCylinder(localPos, vec2(size, params.x));
+    }
+    return 1000.0;
+}
+
+// Distance to a single shape, its modifier stack applied
+//
+// Domain modifiers (twist, bend, repeat) warp the point in stack order
+// before the primitive is evaluated, distance modifiers (onion, round,
+// noise) change the result afterwards. Twist and bend stretch space and
+// noise steepens the field, so the distance is scaled down to keep the
+// march from overshooting.
+float shapeDistance(int i, vec3 p) {
+    vec3 localPos = p - scene.shapes[i].positionSize.xyz;
+    float size = scene.shapes[i].positionSize.w;
+    int first = scene.shapes[i].modifiers.x;
+    int last = first + scene.shapes[i].modifiers.y;
+    
+    float stretch = 1.0;
+    for (int m = first; m < last; m++) {
+        vec4 params = scene.modifiers[m].params;
+        switch (scene.modifiers[m].info.x) {
+            case MODIFIER_TWIST:
+                localPos = opTwist(localPos, params.x);
+                stretch *= 1.0 + abs(params.x) * size;
                 break;
-            case BOX:
-                dist = sdBox(localPos, vec3(shapes[i].size));
+            case MODIFIER_BEND:
+                localPos = opBend(localPos, params.x);
+                stretch *= 1.0 + abs(params.x) * size;
                 break;
-            case PLANE:
-                dist = sdPlane(localPos, vec4(0.0, 1.0, 0.0, 0.0));
+            case MODIFIER_REPEAT:
+                localPos = opRepeat(localPos, params.xyz, params.w);
                 break;
-            case TORUS:
-                dist = sdTorus(localPos, vec2(shapes[i].size, shapes[i].params.x));
+        }
+    }
+    
+    float dist = primitiveDistance(i, localPos) / stretch;
+    float slope = 1.0;
+    for (int m = first; m < last; m++) {
+        float amount = scene.modifiers[m].params.x;
+        switch (scene.modifiers[m].info.x) {
+            case MODIFIER_ONION:
+                dist = abs(dist) - amount;
+                break;
+            case MODIFIER_ROUND:
+                dist -= amount;
                 break;
-            case CYLINDER:
-                dist = sdCylinder(localPos, vec2(shapes[i].size, shapes[i].params.x));
+            case MODIFIER_NOISE: {
+                float frequency = scene.modifiers[m].params.y * scene.noise.y;
+                dist += amount * fbm(localPos * frequency, scene.noise.x, NOISE_OCTAVES);
+                slope += abs(amount) * frequency * NOISE_MAX_SLOPE;
                 break;
+            }
         }
+    }
+    return dist / slope;
+}
+
+// Whether a shape takes part in the current march
+bool shapeActive(int i) {
+    if ((scene.shapes[i].layers.x & uint(pushConstants.cameraForward.w)) == 0u) {
+        return false;
+    }
+    int mode = scene.shapes[i].info.y;
+    return mode == DISPLAY_VISIBLE || (mode == DISPLAY_GHOST && gIncludeGhosts);
+}
+
+// Whether a text takes part in the current march
+bool textActive(int i) {
+    if ((scene.texts[i].layers.x & uint(pushConstants.cameraForward.w)) == 0u) {
+        return false;
+    }
+    int mode = scene.texts[i].info.z;
+    return mode == DISPLAY_VISIBLE || (mode == DISPLAY_GHOST && gIncludeGhosts);
+}
+
+float median3(vec3 v) {
+    return max(min(v.r, v.g), min(max(v.r, v.g), v.b));
+}
+
+// 2D distance in em to the glyphs of a text, positive outside, at most the atlas range
+float glyphsDistance(int i, vec2 p) {
+    float range = scene.texts[i].extent.w;
+    float dist = range;
+    int first = scene.texts[i].info.x;
+    for (int g = first; g < first + scene.texts[i].info.y; g++) {
+        vec4 plane = scene.glyphs[g].plane;
+        vec2 q = clamp(p, plane.xy, plane.zw);
+        float outside = length(p - q);
+        if (outside >= dist) continue;
         
-        minDist = min(minDist, dist);
+        // The field is read at the nearest point of the quad; beyond it the distance grows at least as fast
+        vec4 uv = scene.glyphs[g].uv;
+        vec2 atlasPos = mix(uv.xy, uv.zw, (q - plane.xy) / (plane.zw - plane.xy));
+        float d = (0.5 - median3(textureLod(textAtlas, atlasPos, 0.0).rgb)) * 2.0 * range;
+        dist = min(dist, outside > 0.0 ? length(vec2(outside, max(d, 0.0))) : d);
+    }
+    return dist;
+}
+
+// Distance to a text, its glyphs extruded along its local Z axis
+float textDistance(int i, vec3 p) {
+    vec3 rel = p - scene.texts[i].positionSize.xyz;
+    float size = scene.texts[i].positionSize.w;
+    vec3 local = vec3(
+        dot(scene.texts[i].inverseRotation[0].xyz, rel),
+        dot(scene.texts[i].inverseRotation[1].xyz, rel),
+        dot(scene.texts[i].inverseRotation[2].xyz, rel)
+    ) / size;
+    vec4 extent = scene.texts[i].extent;
+    
+    // Far from the glyph box the atlas is not read
+    float bound = sdBox(local, extent.yzx);
+    if (bound > extent.w) return bound * size;
+    
+    vec2 w = vec2(glyphsDistance(i, local.xy), abs(local.z) - extent.x);
+    return (min(max(w.x, w.y), 0.0) + length(max(w, 0.0))) * size;
+}
+
+// Polynomial smooth minimum, also returns the blend factor towards b
+float smoothMin(float a, float b, float k, out float h) {
+    h = clamp(0.5 + 0.5 * (a - b) / k, 0.0, 1.0);
+    return mix(a, b, h) - k * h * (1.0 - h);
+}
+
+// Combine an operand into a CSG accumulator, tracking which shape owns the surface
+float csgCombine(float acc, float d, int op, float k, inout int accId, int id) {
+    // Subtraction max(acc, -d) and intersection max(acc, d) are negated minima
+    float a = op == CSG_UNION ? acc : -acc;
+    float b = op == CSG_INTERSECTION ? -d : d;
+    float result;
+    float h;
+    if (k > 0.0) {
+        result = smoothMin(a, b, k, h);
+    } else {
+        result = min(a, b);
+        h = b < a ? 1.0 : 0.0;
+    }
+    if (h > 0.5) accId = id;
+    return op == CSG_UNION ? result : -result;
+}
+
+// Scene SDF function, also returns the index of the closest shape, or
+// MAX_SHAPES plus the index of the closest text
+//
+// Shapes sharing a CSG group are stored consecutively and folded together
+// before taking part in the scene union.
+float mapWithId(vec3 p, out int shapeId) {
+    float minDist = 1000.0;
+    shapeId = -1;
+    
+    int currentGroup = 0;
+    float groupDist = 1000.0;
+    int groupId = -1;
+    
+    for (int i = 0; i < scene.counts.x; i++) {
+        if (!shapeActive(i)) continue;
+        
+        float dist = shapeDistance(i, p);
+        int group = scene.shapes[i].info.w;
+        
+        if (group != 0 && group == currentGroup) {
+            groupDist = csgCombine(groupDist, dist, scene.shapes[i].info.z, scene.shapes[i].material.z, groupId, i);
+            continue;
+        }
+        
+        // Leaving a group: the folded composite joins the scene
+        if (groupId >= 0 && groupDist < minDist) {
+            minDist = groupDist;
+            shapeId = groupId;
+        }
+        currentGroup = group;
+        groupId = -1;
+        
+        if (group != 0) {
+            // First operand starts a new group
+            groupDist = dist;
+            groupId = i;
+        } else if (dist < minDist) {
+            minDist = dist;
+            shapeId = i;
+        }
+    }
+    
+    if (groupId >= 0 && groupDist < minDist) {
+        minDist = groupDist;
+        shapeId = groupId;
+    }
+    
+    for (int i = 0; i < scene.counts.z; i++) {
+        if (!textActive(i)) continue;
+        
+        float dist = textDistance(i, p);
+        if (dist < minDist) {
+            minDist = dist;
+            shapeId = MAX_SHAPES + i;
+        }
     }
     
     return minDist;
 }
 
+// Scene SDF function
+float map(vec3 p) {
+    int shapeId;
+    return mapWithId(p, shapeId);
+}
+
+// 4x4 Bayer matrix threshold in [0, 1) for ordered dithering
+float bayerThreshold(vec2 fragCoord) {
+    ivec2 p = ivec2(mod(fragCoord, 4.0));
+    int index = p.x + p.y * 4;
+    const float bayer[16] = float[](
+         0.0,  8.0,  2.0, 10.0,
+        12.0,  4.0, 14.0,  6.0,
+         3.0, 11.0,  1.0,  9.0,
+        15.0,  7.0, 13.0,  5.0
+    );
+    return bayer[index] / 16.0;
+}
+
 // Get normal using gradient
 vec3 getNormal(vec3 p) {
     vec2 e = vec2(0.001, 0.0);
@@ -105,32 +541,193 @@ vec3 getNormal(vec3 p) {
     return normalize(n);
 }
 
-// Ray marching
-float rayMarch(vec3 ro, vec3 rd, float maxDist) {
-    float precis = 0.001;
+// Ray marching, also counting the distance evaluations taken
+float rayMarchSteps(vec3 ro, vec3 rd, float maxDist, out int steps) {
+    float precis = MARCH_EPSILON;
     float h = precis * 2.0;
     float t = 0.0;
     
-    for (int i = 0; i < 100; i++) {
+    steps = 0;
+    for (int i = 0; i < MAX_MARCH_STEPS; i++) {
         if (abs(h) < precis || t > maxDist) break;
         h = map(ro + rd * t);
         t += h;
+        steps++;
     }
     
     return t;
 }
 
+float rayMarch(vec3 ro, vec3 rd, float maxDist) {
+    int steps;
+    return rayMarchSteps(ro, rd, maxDist, steps);
+}
+
+// Blue -> cyan -> green -> yellow -> red ramp for debug heatmaps, x in [0, 1]
+vec3 heatmap(float x) {
+    x = clamp(x, 0.0, 1.0);
+    return clamp(vec3(
+        min(4.0 * x - 1.5, -4.0 * x + 4.5),
+        min(4.0 * x - 0.5, -4.0 * x + 3.5),
+        min(4.0 * x + 0.5, -4.0 * x + 2.5)
+    ), 0.0, 1.0);
+}
+
+// Shadow factor towards a light, params.shadowMin in full shadow and 1.0 when lit
+float shadowFactor(vec3 pos, vec3 normal, vec3 lightDir, float lightDist) {
+    vec3 ro = pos + normal * 0.01;
+#ifdef ENABLE_SOFT_SHADOWS
+    // Penumbra estimate from the closest miss along the shadow ray
+    float res = 1.0;
+    float t = 0.02;
+    for (int i = 0; i < MAX_MARCH_STEPS; i++) {
+        if (t >= lightDist) break;
+        float h = map(ro + lightDir * t);
+        if (h < MARCH_EPSILON) {
+            res = 0.0;
+            break;
+        }
+        res = min(res, params.softShadowHardness * h / t);
+        t += clamp(h, 0.01, 0.5);
+    }
+    return mix(params.shadowMin, 1.0, clamp(res, 0.0, 1.0));
+#else
+    float shadowDist = rayMarch(ro, lightDir, MAX_MARCH_DISTANCE);
+    return shadowDist < lightDist ? params.shadowMin : 1.0;
+#endif
+}
+
+#ifdef ENABLE_AO
+// Ambient occlusion from a few distance samples along the normal
+float ambientOcclusion(vec3 pos, vec3 normal) {
+    float occlusion = 0.0;
+    float weight = 1.0;
+    for (int i = 1; i <= AO_SAMPLES; i++) {
+        float dist = AO_STEP * float(i);
+        occlusion += (dist - map(pos + normal * dist)) * weight;
+        weight *= 0.5;
+    }
+    return clamp(1.0 - params.aoStrength * occlusion, 0.0, 1.0);
+}
+#endif
+
+#ifdef ENABLE_CONE_GI
+// Fraction of a cone from pos along dir that is not blocked by the scene,
+// from the narrowest clearance relative to the cone radius
+float coneVisibility(vec3 pos, vec3 dir) {
+    float visibility = 1.0;
+    float t = GI_CONE_START;
+    for (int i = 0; i < GI_CONE_STEPS; i++) {
+        if (t >= params.giDistance) break;
+        float h = map(pos + dir * t);
+        visibility = min(visibility, h / (t * GI_CONE_APERTURE));
+        if (visibility <= 0.0) break;
+        // Step at least half a cone radius so the cones reach giDistance
+        t += max(h, t * GI_CONE_APERTURE * 0.5);
+    }
+    return clamp(visibility, 0.0, 1.0);
+}
+#endif
+
+// Simple sky model driven by the sun direction and color
+vec3 skyColor(vec3 rd) {
+    vec3 sunDir = normalize(pushConstants.sunDirection.xyz);
+    vec3 sunLight = pushConstants.sunColor.rgb;
+    
+    // Darken the sky as the sun goes down
+    float daylight = clamp(sunDir.y * 2.0 + 0.3, 0.0, 1.0);
+    vec3 horizon = mix(vec3(0.1, 0.1, 0.2), vec3(0.7, 0.8, 0.9), daylight);
+    vec3 zenith = mix(vec3(0.02, 0.02, 0.05), vec3(0.25, 0.45, 0.8), daylight);
+    vec3 sky = mix(horizon, zenith, clamp(rd.y, 0.0, 1.0));
+    
+    // Sun disk and glow
+    float sunAmount = max(dot(rd, sunDir), 0.0);
+    sky += sunLight * pow(sunAmount, 256.0);
+    sky += sunLight * pow(sunAmount, 8.0) * 0.25;
+    
+    return sky;
+}
+
+// Environment map radiance in direction d, rotated around Y
+vec3 environmentColor(vec3 d) {
+    float u = atan(d.x, -d.z) / (2.0 * PI) + 0.5 + scene.background.hdri.y;
+    float v = acos(clamp(d.y, -1.0, 1.0)) / PI;
+    // No mip chain: an explicit LOD avoids derivative artifacts at the u seam
+    return textureLod(environmentMap, vec2(u, v), 0.0).rgb * scene.background.hdri.x;
+}
+
+// What a ray in direction rd sees when it misses the scene
+vec3 backgroundColor(vec3 rd) {
+    int mode = scene.background.mode.x;
+    if (mode == BACKGROUND_SOLID) {
+        return pushConstants.clearColor.rgb;
+    }
+    if (mode == BACKGROUND_GRADIENT) {
+        return mix(scene.background.gradientBottom.rgb, scene.background.gradientTop.rgb, clamp(rd.y * 0.5 + 0.5, 0.0, 1.0));
+    }
+    if (mode == BACKGROUND_HDRI) {
+        return environmentColor(rd);
+    }
+    return skyColor(rd);
+}
+
+#ifdef ENABLE_CONE_GI
+// Ambient light from cones spread over the hemisphere around the normal:
+// open cones see the ambient color plus the background, blocked ones the
+// light bounced back by the occluder (guessed to be the surface's own color).
+// With every cone open and no sky strength this is the plain ambient term.
+vec3 coneTracedAmbient(vec3 pos, vec3 normal, vec3 color) {
+    vec3 helper = abs(normal.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(helper, normal));
+    vec3 bitangent = cross(normal, tangent);
+
+    vec3 irradiance = vec3(0.0);
+    float totalWeight = 0.0;
+    for (int i = 0; i < GI_CONES; i++) {
+        // One cone along the normal, the rest in a ring 60 degrees from it
+        vec3 dir = normal;
+        if (i > 0) {
+            float angle = 2.0 * PI * float(i - 1) / float(GI_CONES - 1);
+            dir = normalize(normal * 0.5 + (tangent * cos(angle) + bitangent * sin(angle)) * 0.866);
+        }
+        float weight = dot(dir, normal);
+
+        vec3 sky = params.ambientColor + backgroundColor(dir) * params.giSkyStrength;
+        vec3 bounce = sky * color * params.giBounce;
+        irradiance += mix(bounce, sky, coneVisibility(pos + normal * 0.01, dir)) * weight;
+        totalWeight += weight;
+    }
+    return color * irradiance / totalWeight;
+}
+#endif
+
 // Calculate lighting
 vec3 calculateLighting(vec3 pos, vec3 normal, vec3 viewDir, vec3 color, float metallic, float roughness) {
     vec3 finalColor = vec3(0.0);
     
-    for (int i = 0; i < 1; i++) {
-        vec3 lightDir = normalize(lights[i].position - pos);
-        vec3 lightColor = lights[i].color * lights[i].intensity;
+    for (int i = 0; i < scene.counts.y; i++) {
+        LightData light = scene.lights[i];
+        vec3 lightDir = -light.direction.xyz;
+        float lightDist = MAX_MARCH_DISTANCE;
+        float attenuation = 1.0;
+        if (light.info.x != LIGHT_DIRECTIONAL) {
+            vec3 toLight = light.position.xyz - pos;
+            lightDist = length(toLight);
+            lightDir = toLight / max(lightDist, 1e-4);
+            // Smooth window reaching zero at the radius
+            if (light.position.w > 0.0) {
+                float falloff = clamp(1.0 - pow(lightDist / light.position.w, 4.0), 0.0, 1.0);
+                attenuation = falloff * falloff;
+            }
+            if (light.info.x == LIGHT_SPOT) {
+                attenuation *= smoothstep(light.spot.x, light.spot.y, dot(-lightDir, light.direction.xyz));
+            }
+        }
+        if (attenuation <= 0.0) continue;
+        vec3 lightColor = light.colorIntensity.rgb * light.colorIntensity.a * attenuation;
         
         // Check for shadows
-        float shadowDist = rayMarch(pos + normal * 0.01, lightDir, 10.0);
-        float shadow = shadowDist < length(lights[i].position - pos) ? 0.3 : 1.0;
+        float shadow = shadowFactor(pos, normal, lightDir, lightDist);
         
         // Diffuse
         float diff = max(dot(normal, lightDir), 0.0);
@@ -138,97 +735,338 @@ vec3 calculateLighting(vec3 pos, vec3 normal, vec3 viewDir, vec3 color, float me
         
         // Specular (simplified)
         vec3 reflectDir = reflect(-lightDir, normal);
-        float spec = pow(max(dot(viewDir, reflectDir), 0.0), 32.0);
+        float spec = params.specular ? pow(max(dot(viewDir, reflectDir), 0.0), params.specularPower) : 0.0;
         vec3 specular = spec * lightColor * metallic;
         
         finalColor += (diffuse + specular) * shadow;
     }
     
+    // Sun (directional light)
+    vec3 sunDir = normalize(pushConstants.sunDirection.xyz);
+    vec3 sunLight = pushConstants.sunColor.rgb * pushConstants.sunDirection.w;
+    if (dot(sunLight, sunLight) > 0.0) {
+        float sunShadow = shadowFactor(pos, normal, sunDir, MAX_MARCH_DISTANCE);
+        
+        float sunDiff = max(dot(normal, sunDir), 0.0);
+        vec3 sunReflect = reflect(-sunDir, normal);
+        float sunSpec = params.specular ? pow(max(dot(viewDir, sunReflect), 0.0), params.specularPower) : 0.0;
+        
+        finalColor += (sunDiff * sunLight * color + sunSpec * sunLight * metallic) * sunShadow;
+    }
+    
     // Ambient
-    vec3 ambient = color * 0.1;
+#ifdef ENABLE_CONE_GI
+    vec3 ambient = coneTracedAmbient(pos, normal, color);
+#else
+    vec3 ambient = color * params.ambientColor;
+#endif
+    
+    // Mirror reflection of the background, stronger on smooth metals
+    if ((scene.background.mode.y & BACKGROUND_FLAG_REFLECTIONS) != 0) {
+        vec3 f0 = mix(vec3(0.04), color, metallic);
+        float smoothness = 1.0 - clamp(roughness, 0.0, 1.0);
+        ambient += backgroundColor(reflect(-viewDir, normal)) * f0 * smoothness * smoothness;
+    }
+#ifdef ENABLE_AO
+    ambient *= ambientOcclusion(pos, normal);
+#endif
     
     return ambient + finalColor;
 }
 
-// Push constants for window data
-layout(push_constant) uniform PushConstants {
-    vec2 uResolution;
-    float uTime;
-    float uAspectRatio;
-    // Toolbar overlay data
-    vec4 toolbarColor;
-    vec2 toolbarPosition;
-    vec2 toolbarSize;
-    int showToolbar;
-} pushConstants;
+#ifdef ENABLE_REFLECTIONS
+// Lit color of a surface hit by a secondary ray, without its own secondary rays
+vec3 shadeSecondaryHit(vec3 pos, vec3 normal, vec3 viewDir, int shapeId) {
+    if (shapeId >= MAX_SHAPES) {
+        TextData text = scene.texts[shapeId - MAX_SHAPES];
+        return calculateLighting(pos, normal, viewDir, text.colorMetallic.rgb, text.colorMetallic.a, text.material.x);
+    }
+    SDFShapeData shape = scene.shapes[shapeId];
+    return calculateLighting(pos, normal, viewDir, shape.colorMetallic.rgb, shape.colorMetallic.a, shape.material.x);
+}
 
-void main() {
-    // Check if we should render toolbar overlay
-    if (pushConstants.showToolbar == 1) {
-        // Convert fragment coordinates to screen space
-        vec2 screenPos = fragTexCoord * pushConstants.uResolution;
-        
-        // Check if fragment is within toolbar bounds
-        if (screenPos.x >= pushConstants.toolbarPosition.x &&
-            screenPos.x <= pushConstants.toolbarPosition.x + pushConstants.toolbarSize.x &&
-            screenPos.y >= pushConstants.toolbarPosition.y &&
-            screenPos.y <= pushConstants.toolbarPosition.y + pushConstants.toolbarSize.y) {
-            
-            // Render toolbar color
-            outColor = pushConstants.toolbarColor;
-            return;
+// Weights of the reflected and refracted light of a surface: its own
+// reflectivity, plus the Fresnel (Schlick) share of its transparency
+void opticsWeights(vec4 optics, float cosTheta, out float reflected, out float refracted) {
+    float f0 = (1.0 - optics.z) / (1.0 + optics.z);
+    f0 *= f0;
+    float fresnel = f0 + (1.0 - f0) * pow(1.0 - clamp(cosTheta, 0.0, 1.0), 5.0);
+    float transmitted = (1.0 - optics.x) * optics.y;
+    reflected = optics.x + transmitted * fresnel;
+    refracted = transmitted * (1.0 - fresnel);
+}
+
+// Refract a ray into the shape at pos, march it through the inside (where
+// distances are negative) and refract it out again. Rays caught by total
+// internal reflection bounce inside up to MAX_INTERNAL_REFLECTIONS times.
+// Returns false if the ray never gets out.
+bool refractThrough(inout vec3 pos, inout vec3 rd, vec3 normal, float ior) {
+    rd = refract(rd, normal, 1.0 / ior);
+    pos -= normal * SECONDARY_RAY_OFFSET;
+    for (int i = 0; i <= MAX_INTERNAL_REFLECTIONS; i++) {
+        float t = 0.0;
+        for (int marchStep = 0; marchStep < MAX_MARCH_STEPS; marchStep++) {
+            float h = -map(pos + rd * t);
+            if (h < MARCH_EPSILON || t > MAX_MARCH_DISTANCE) break;
+            t += h;
+        }
+        pos += rd * t;
+        vec3 outward = getNormal(pos);
+        vec3 exitDir = refract(rd, -outward, ior);
+        if (dot(exitDir, exitDir) > 0.0) {
+            rd = exitDir;
+            pos += outward * SECONDARY_RAY_OFFSET;
+            return true;
         }
+        rd = reflect(rd, -outward);
     }
+    return false;
+}
+
+// Follow reflected or refracted rays from a primary hit for up to
+// params.maxBounces bounces. A transparent surface follows the refracted
+// ray and takes its reflection from the background only, so each bounce
+// marches one ray.
+vec3 traceSecondaryRays(vec3 color, vec3 pos, vec3 normal, vec3 rd, int shapeId) {
+    vec3 result = vec3(0.0);
+    vec3 throughput = vec3(1.0);
+    for (int bounce = 0; bounce <= params.maxBounces; bounce++) {
+        // Texts have no optics
+        vec4 optics = shapeId < MAX_SHAPES ? scene.shapes[shapeId].optics : vec4(0.0, 0.0, 1.0, 0.0);
+        float reflected, refracted;
+        opticsWeights(optics, dot(-rd, normal), reflected, refracted);
+        if (bounce == params.maxBounces || reflected + refracted <= 0.0) {
+            return result + throughput * color;
+        }
+        result += throughput * color * (1.0 - reflected - refracted);
+
+        if (refracted > 0.0) {
+            result += throughput * reflected * backgroundColor(reflect(rd, normal));
+            // Light passing through is tinted by the shape's color
+            throughput *= refracted * scene.shapes[shapeId].colorMetallic.rgb;
+            if (!refractThrough(pos, rd, normal, optics.z)) {
+                return result;
+            }
+        } else {
+            throughput *= reflected;
+            rd = reflect(rd, normal);
+            pos += normal * SECONDARY_RAY_OFFSET;
+        }
+
+        float t = rayMarch(pos, rd, MAX_MARCH_DISTANCE);
+        if (t >= MAX_MARCH_DISTANCE) {
+            return result + throughput * backgroundColor(rd);
+        }
+        pos += rd * t;
+        normal = getNormal(pos);
+        mapWithId(pos, shapeId);
+        if (shapeId < 0) {
+            return result;
+        }
+        color = shadeSecondaryHit(pos, normal, -rd, shapeId);
+    }
+    return result;
+}
+#endif
+
+// Distance between a ray and a world axis through the origin (x), and the
+// distance along the ray to the closest point (y, negative if parallel)
+vec2 rayAxisDistance(vec3 ro, vec3 rd, vec3 axis) {
+    vec3 n = cross(rd, axis);
+    float nn = dot(n, n);
+    if (nn < 1e-8) {
+        return vec2(1e9, -1.0);
+    }
+    float b = dot(rd, axis);
+    float t = (b * dot(axis, ro) - dot(rd, ro)) / nn;
+    return vec2(abs(dot(ro, n)) / sqrt(nn), t);
+}
+
+// Blend the Y=0 reference grid and the RGB world axes over the shaded color;
+// both are hidden behind surfaces closer than sceneT
+vec3 applyGridOverlay(vec3 color, vec3 ro, vec3 rd, float sceneT, float orthoHalfHeight) {
+    // World size of one pixel at distance t along the ray
+    float pixelScale = 2.0 / pushConstants.uResolution.y;
+    float fadeDistance = max(pushConstants.sunColor.w, 0.001);
     
-    // Initialize shapes (temporary - will come from ECS)
-    shapes[0] = SDFShapeData(SPHERE, vec3(0.0, 0.0, 0.0), 0.5, vec4(0.0), vec3(1.0, 0.0, 0.0), 0.0, 0.5, 0.0, 0);
-    shapes[1] = SDFShapeData(BOX, vec3(-1.5, 0.0, 0.0), 0.3, vec4(0.0), vec3(0.0, 1.0, 0.0), 0.0, 0.5, 0.0, 0);
-    shapes[2] = SDFShapeData(SPHERE, vec3(1.5, 0.0, 0.0), 0.4, vec4(0.0), vec3(0.0, 0.0, 1.0), 0.0, 0.5, 0.0, 0);
+    if ((pushConstants.gridFlags & GRID_FLAG_LINES) != 0) {
+        float planeT = abs(rd.y) > 1e-5 ? -ro.y / rd.y : -1.0;
+        vec2 coord = (ro + rd * max(planeT, 0.0)).xz / max(pushConstants.gridSpacing, 0.001);
+        // Anti-aliased lines one pixel wide; cells smaller than a pixel fade out
+        vec2 derivative = max(fwidth(coord), vec2(1e-4));
+        vec2 lines = abs(fract(coord - 0.5) - 0.5) / derivative;
+        float alpha = 1.0 - min(min(lines.x, lines.y), 1.0);
+        alpha *= 1.0 - smoothstep(0.3, 1.0, max(derivative.x, derivative.y));
+        alpha *= 1.0 - smoothstep(fadeDistance * 0.5, fadeDistance, planeT);
+        if (planeT > 0.0 && planeT < sceneT) {
+            color = mix(color, vec3(0.8), alpha * GRID_LINE_OPACITY);
+        }
+    }
+    
+    if ((pushConstants.gridFlags & GRID_FLAG_AXES) != 0) {
+        vec3 axes[3] = vec3[3](vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0));
+        for (int i = 0; i < 3; i++) {
+            vec2 hit = rayAxisDistance(ro, rd, axes[i]);
+            if (hit.y <= 0.0 || hit.y >= sceneT) {
+                continue;
+            }
+            float pixels = hit.x / (pixelScale * (orthoHalfHeight > 0.0 ? orthoHalfHeight : hit.y));
+            float alpha = clamp(AXIS_LINE_PIXELS - pixels, 0.0, 1.0);
+            alpha *= 1.0 - smoothstep(fadeDistance * 0.5, fadeDistance, hit.y);
+            color = mix(color, axes[i], alpha);
+        }
+    }
     
-    // Initialize lights (temporary - will come from ECS)
-    lights[0] = LightData(vec3(2.0, 2.0, 2.0), vec3(1.0, 1.0, 1.0), 1.0, 0);
+    return color;
+}
+
+// Sub-pixel position of an antialiasing sample, in pixels from the pixel center
+// (the standard 2x/4x/8x MSAA patterns)
+vec2 sampleOffset(int i) {
+#if AA_SAMPLES == 2
+    const vec2 offsets[2] = vec2[](vec2(0.25, 0.25), vec2(-0.25, -0.25));
+#elif AA_SAMPLES == 4
+    const vec2 offsets[4] = vec2[](
+        vec2(-0.125, -0.375), vec2(0.375, -0.125), vec2(-0.375, 0.125), vec2(0.125, 0.375));
+#elif AA_SAMPLES == 8
+    const vec2 offsets[8] = vec2[](
+        vec2(0.0625, -0.1875), vec2(-0.0625, 0.1875), vec2(0.3125, 0.0625), vec2(-0.1875, -0.3125),
+        vec2(-0.3125, 0.3125), vec2(-0.4375, -0.0625), vec2(0.1875, 0.4375), vec2(0.4375, -0.4375));
+#else
+    const vec2 offsets[1] = vec2[](vec2(0.0));
+#endif
+    return offsets[i];
+}
+
+// G-buffer values of one sample
+struct GBufferSample {
+    vec4 normal;
+    float linearDepth;
+    float selection;
+};
+
+// Trace and shade the ray through a point of the viewport, with its depth and G-buffer values
+vec3 shadeSample(vec2 texCoord, out float depth, out GBufferSample gbuffer) {
+    // Point of the view plane; the lens widens it to the aspect ratio or a headset eye's field of view
+    vec2 uv = texCoord * 2.0 - 1.0; // Convert to [-1, 1] range
+    uv = pushConstants.cameraLens.zw + uv * pushConstants.cameraLens.xy;
     
-    // Use aspect ratio from push constants
-    float aspectRatio = pushConstants.uAspectRatio;
+    vec3 forward = length(pushConstants.cameraForward.xyz) > 0.0001
+        ? normalize(pushConstants.cameraForward.xyz) : vec3(0.0, 0.0, 1.0);
+    vec3 right;
+    vec3 up;
+    cameraBasis(forward, pushConstants.cameraRoll, right, up);
     
-    // Proper ray setup for SDF rendering with aspect ratio correction
-    vec2 uv = fragTexCoord * 2.0 - 1.0; // Convert to [-1, 1] range
-    uv.x *= aspectRatio; // Apply aspect ratio correction to prevent stretching
+    vec3 ro;
+    vec3 rd;
+    float orthoHalfHeight = pushConstants.cameraOrigin.w;
+    if (orthoHalfHeight > 0.0) {
+        // Orthographic: parallel rays from a plane through the camera origin
+        ro = pushConstants.cameraOrigin.xyz + (right * uv.x + up * uv.y) * orthoHalfHeight;
+        rd = forward;
+    } else {
+        ro = pushConstants.cameraOrigin.xyz;
+        rd = normalize(right * uv.x + up * uv.y + forward);
+    }
     
-    vec3 ro = vec3(0.0, 0.0, -2.0); // Fixed camera position
-    vec3 rd = normalize(vec3(uv, 1.0)); // Ray direction with aspect ratio correction
+    // Ghosted shapes only occupy a dithered subset of pixels
+    gIncludeGhosts = bayerThreshold(gl_FragCoord.xy) < GHOST_OPACITY;
     
     // Ray marching
-    float maxDist = 10.0;
-    float t = rayMarch(ro, rd, maxDist);
+    float maxDist = MAX_MARCH_DISTANCE;
+    int steps;
+    float t = rayMarchSteps(ro, rd, maxDist, steps);
     
-    vec3 color = vec3(0.1, 0.1, 0.2); // Background color
+    vec3 color = backgroundColor(rd);
+    depth = 1.0;
+    gbuffer = GBufferSample(vec4(0.0), 0.0, 0.0);
     
     if (t < maxDist) {
         vec3 pos = ro + rd * t;
         vec3 normal = getNormal(pos);
         vec3 viewDir = normalize(-rd);
         
+        // Depth of the hit, so meshes drawn afterwards are occluded by it
+        float viewZ = t * dot(rd, forward);
+        depth = orthoHalfHeight > 0.0
+            ? clamp(viewZ / ORTHO_DEPTH_RANGE, 0.0, 1.0)
+            : clamp(1.0 - DEPTH_NEAR / max(viewZ, DEPTH_NEAR), 0.0, 1.0);
+        gbuffer.normal = vec4(normal * 0.5 + 0.5, 1.0);
+        gbuffer.linearDepth = viewZ;
+        
         // Find which shape we hit and get its material
-        for (int i = 0; i < 3; i++) {
-            vec3 localPos = pos - shapes[i].position;
-            float dist = 1000.0;
+        int shapeId;
+        mapWithId(pos, shapeId);
+        
+        if (shapeId >= 0) {
+            // Texts come after the shapes
+            bool isText = shapeId >= MAX_SHAPES;
+            int textId = shapeId - MAX_SHAPES;
+            int displayMode = isText ? scene.texts[textId].info.z : scene.shapes[shapeId].info.y;
+            vec4 colorMetallic = isText ? scene.texts[textId].colorMetallic : scene.shapes[shapeId].colorMetallic;
+            vec4 material = isText ? scene.texts[textId].material : scene.shapes[shapeId].material;
+            bool isGhost = displayMode == DISPLAY_GHOST;
+            
+            // Ghosts do not cast shadows
+            gIncludeGhosts = false;
             
-            switch (shapes[i].shapeType) {
-                case SPHERE:
-                    dist = sdSphere(localPos, shapes[i].size);
-                    break;
-                case BOX:
-                    dist = sdBox(localPos, vec3(shapes[i].size));
-                    break;
+            vec3 baseColor = colorMetallic.rgb;
+            float metallic = colorMetallic.a;
+            float roughness = material.x;
+            gbuffer.selection = material.w;
+            if (pushConstants.renderMode == RENDER_MODE_NORMALS) {
+                color = normal * 0.5 + 0.5;
+            } else if (pushConstants.renderMode == RENDER_MODE_DEPTH) {
+                color = vec3(1.0 - t / maxDist);
+            } else if (pushConstants.renderMode == RENDER_MODE_SHADED) {
+                color = calculateLighting(pos, normal, viewDir, baseColor, metallic, roughness);
+#ifdef ENABLE_REFLECTIONS
+                color = traceSecondaryRays(color, pos, normal, rd, shapeId);
+#endif
             }
             
-            if (abs(dist) < 0.01) {
-                color = calculateLighting(pos, normal, viewDir, shapes[i].color, shapes[i].metallic, shapes[i].roughness);
-                break;
+            if (isGhost) {
+                // Wash out ghosts so they read as "not really there"
+                color = mix(color, vec3(0.8, 0.85, 1.0), 0.5);
             }
         }
     }
     
-    outColor = vec4(color, 1.0);
-}
\ No newline at end of file
+    // Heatmaps cover misses too: rays grazing a silhouette march the longest
+    if (pushConstants.renderMode == RENDER_MODE_ITERATIONS) {
+        return heatmap(float(steps) / float(MAX_MARCH_STEPS));
+    }
+    if (pushConstants.renderMode == RENDER_MODE_DISTANCE) {
+        return heatmap(min(t, maxDist) / maxDist);
+    }
+    
+    // Reference grid and world axes, occluded by the scene
+    if (pushConstants.gridFlags != 0) {
+        color = applyGridOverlay(color, ro, rd, t < maxDist ? t : 1e9, orthoHalfHeight);
+    }
+    
+    return color;
+}
+
+void main() {
+    // Average the samples; the nearest one gives the depth and the G-buffer values,
+    // so meshes never cover an antialiased edge
+    vec3 color = vec3(0.0);
+    float depth = 1.0;
+    GBufferSample gbuffer = GBufferSample(vec4(0.0), 0.0, 0.0);
+    for (int i = 0; i < AA_SAMPLES; i++) {
+        float sampleDepth;
+        GBufferSample sampleGBuffer;
+        color += shadeSample(fragTexCoord + sampleOffset(i) / pushConstants.uResolution, sampleDepth, sampleGBuffer);
+        if (sampleDepth < depth) {
+            depth = sampleDepth;
+            gbuffer = sampleGBuffer;
+        }
+    }
+    gl_FragDepth = depth;
+    
+    outColor = vec4(color / float(AA_SAMPLES), 1.0);
+    outNormal = gbuffer.normal;
+    outLinearDepth = gbuffer.linearDepth;
+    outSelection = gbuffer.selection;
+}
@@ -0,0 +1,6 @@
+// Image of paint.toml: shows Buffer A (iChannel0)
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec3 color = texture(iChannel0, fragCoord / iResolution.xy).rgb;
+    fragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,17 @@
+// Buffer A of paint.toml: a brush leaving fading trails
+//
+// iChannel0 is this buffer's previous frame. Drag with the left mouse
+// button to paint; otherwise the brush circles on its own.
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec3 previous = texture(iChannel0, fragCoord / iResolution.xy).rgb;
+
+    vec2 brush = iMouse.z > 0.0
+        ? iMouse.xy
+        : iResolution.xy * (0.5 + 0.3 * vec2(cos(iTime), sin(iTime * 1.3)));
+    float radius = 0.03 * iResolution.y;
+    float paint = smoothstep(radius, 0.0, length(fragCoord - brush));
+    vec3 color = 0.5 + 0.5 * cos(iTime + vec3(0.0, 2.0, 4.0));
+
+    fragColor = vec4(mix(previous * 0.99, color, paint), 1.0);
+}
@@ -0,0 +1,19 @@
+// Example for the shadertoy mode: cargo run -- --toy shaders/toys/plasma.frag
+//
+// No #version or main: the app adds them together with iResolution, iTime,
+// iFrame and iMouse (see src/vulkan/fullscreen_pass.rs).
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+
+    // Drag with the left mouse button to move the center
+    if (iMouse.z > 0.0) {
+        uv -= (2.0 * iMouse.xy - iResolution.xy) / iResolution.y;
+    }
+
+    float v = sin(uv.x * 3.0 + iTime)
+            + sin(uv.y * 4.0 - iTime * 1.3)
+            + sin(length(uv) * 6.0 - iTime * 2.0);
+    vec3 color = 0.5 + 0.5 * cos(v + vec3(0.0, 2.0, 4.0));
+    fragColor = vec4(color, 1.0);
+}
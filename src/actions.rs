@@ -0,0 +1,304 @@
+//! Application actions and their key bindings
+//!
+//! Every command the user can run from the keyboard is an `Action`. The
+//! `ActionRegistry` owns the list of actions with their names and key
+//! bindings: `main.rs` looks key presses up in it, and the HUD command
+//! palette (Ctrl+P) lists and fuzzy-searches the same registry, so a new
+//! action only has to be registered once to be reachable both ways.
+
+use winit::keyboard::{KeyCode, ModifiersState};
+use crate::config;
+
+/// A command that can be bound to a key and run from the command palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Open or close the command palette
+    CommandPalette,
+    /// Show or hide the toolbar
+    ToggleHud,
+    /// Create the HUD again after it failed to initialize
+    RetryHud,
+    /// Switch between windowed and windowed fullscreen
+    ToggleFullscreen,
+    /// Turn hot shader reload on or off
+    ToggleHotReload,
+    /// Recompile the SDF shaders
+    ReloadShaders,
+    /// Reload shaders, theme, scene and scripts
+    ReloadAll,
+    /// Save the current view as a PNG
+    TakeScreenshot,
+    /// Switch between the single view and the quad view
+    ToggleQuadView,
+    /// The toolbar's "Add Sphere" button
+    AddSphere,
+    /// The toolbar's "Add Box" button
+    AddBox,
+    /// Bookmark the current camera pose
+    AddCameraBookmark,
+    /// Move the camera to a bookmark, by its index in the scene's bookmarks
+    CameraBookmark(usize),
+    /// Move the camera to frame the selected entity
+    FrameSelected,
+    /// Switch the camera between perspective and orthographic projection
+    ToggleProjection,
+}
+
+/// Palette names of the bookmarks reached with the number keys
+const CAMERA_BOOKMARK_NAMES: [&str; config::camera::BOOKMARK_KEYS] = [
+    "Camera Bookmark 1",
+    "Camera Bookmark 2",
+    "Camera Bookmark 3",
+    "Camera Bookmark 4",
+    "Camera Bookmark 5",
+    "Camera Bookmark 6",
+    "Camera Bookmark 7",
+    "Camera Bookmark 8",
+    "Camera Bookmark 9",
+];
+
+/// Number keys of the camera bookmarks, 1 to 9
+const CAMERA_BOOKMARK_KEYS: [KeyCode; config::camera::BOOKMARK_KEYS] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// A key with the modifiers that must be held with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    /// Physical key, so bindings stay in place across keyboard layouts
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyChord {
+    /// A key pressed without modifiers
+    pub const fn key(key: KeyCode) -> Self {
+        Self { key, ctrl: false, shift: false, alt: false }
+    }
+
+    /// A key pressed with Ctrl
+    pub const fn ctrl(key: KeyCode) -> Self {
+        Self { key, ctrl: true, shift: false, alt: false }
+    }
+
+    /// A key pressed with Ctrl and Shift
+    pub const fn ctrl_shift(key: KeyCode) -> Self {
+        Self { key, ctrl: true, shift: true, alt: false }
+    }
+
+    /// Whether the chord holds Ctrl, Shift or Alt
+    pub fn has_modifiers(&self) -> bool {
+        self.ctrl || self.shift || self.alt
+    }
+
+    /// Whether a key press with the given modifiers triggers this chord
+    pub fn matches(&self, key: KeyCode, modifiers: ModifiersState) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.control_key()
+            && self.shift == modifiers.shift_key()
+            && self.alt == modifiers.alt_key()
+    }
+
+    /// Shortcut text shown in the UI, e.g. "Ctrl+Shift+R"
+    pub fn label(&self) -> String {
+        let key = format!("{:?}", self.key);
+        let key = key.strip_prefix("Key").or_else(|| key.strip_prefix("Digit")).unwrap_or(&key);
+        let mut label = String::new();
+        for (held, name) in [(self.ctrl, "Ctrl+"), (self.shift, "Shift+"), (self.alt, "Alt+")] {
+            if held {
+                label.push_str(name);
+            }
+        }
+        label.push_str(key);
+        label
+    }
+}
+
+/// A registered action
+#[derive(Debug, Clone)]
+pub struct ActionEntry {
+    pub action: Action,
+    /// Name shown and searched in the command palette
+    pub name: &'static str,
+    /// Key binding, None for actions only run from the palette
+    pub binding: Option<KeyChord>,
+}
+
+/// All actions with their names and key bindings
+#[derive(Debug, Clone)]
+pub struct ActionRegistry {
+    entries: Vec<ActionEntry>,
+}
+
+impl Default for ActionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionRegistry {
+    /// Create the registry with the built-in actions and their default bindings
+    pub fn new() -> Self {
+        let mut registry = Self { entries: Vec::new() };
+        registry.register(Action::CommandPalette, "Command Palette", Some(KeyChord::ctrl(KeyCode::KeyP)));
+        registry.register(Action::ToggleHud, "Toggle Toolbar", Some(KeyChord::key(KeyCode::F1)));
+        registry.register(Action::ToggleHotReload, "Toggle Hot Reload", Some(KeyChord::key(KeyCode::F2)));
+        registry.register(Action::ReloadShaders, "Reload Shaders", Some(KeyChord::key(KeyCode::F3)));
+        registry.register(Action::RetryHud, "Retry HUD", Some(KeyChord::key(KeyCode::F4)));
+        registry.register(Action::ReloadAll, "Reload Everything", Some(KeyChord::ctrl_shift(KeyCode::KeyR)));
+        registry.register(Action::ToggleFullscreen, "Toggle Fullscreen", Some(KeyChord::key(KeyCode::F11)));
+        registry.register(Action::TakeScreenshot, "Take Screenshot", Some(KeyChord::key(KeyCode::F12)));
+        registry.register(Action::ToggleQuadView, "Toggle Quad View", Some(KeyChord::ctrl(KeyCode::KeyQ)));
+        registry.register(Action::AddSphere, "Add Sphere", Some(KeyChord::ctrl(KeyCode::KeyN)));
+        registry.register(Action::AddBox, "Add Box", Some(KeyChord::ctrl(KeyCode::KeyB)));
+        registry.register(Action::AddCameraBookmark, "Add Camera Bookmark", Some(KeyChord::ctrl_shift(KeyCode::KeyB)));
+        for (index, (name, key)) in CAMERA_BOOKMARK_NAMES.into_iter().zip(CAMERA_BOOKMARK_KEYS).enumerate() {
+            registry.register(Action::CameraBookmark(index), name, Some(KeyChord::key(key)));
+        }
+        registry.register(Action::FrameSelected, "Frame Selected", Some(KeyChord::key(KeyCode::KeyF)));
+        registry.register(Action::ToggleProjection, "Toggle Orthographic", Some(KeyChord::key(KeyCode::Numpad5)));
+        registry
+    }
+
+    /// Add an action; a binding already used by another action is dropped
+    ///
+    /// # Arguments
+    /// * `action` - The action
+    /// * `name` - Name shown in the command palette
+    /// * `binding` - Key binding, if any
+    pub fn register(&mut self, action: Action, name: &'static str, binding: Option<KeyChord>) {
+        let binding = binding.filter(|chord| {
+            let taken = self.entries.iter().any(|entry| entry.binding == Some(*chord));
+            if taken {
+                log::warn!("Key binding {} of '{}' is already taken", chord.label(), name);
+            }
+            !taken
+        });
+        self.entries.push(ActionEntry { action, name, binding });
+    }
+
+    /// Registered actions in registration order
+    pub fn entries(&self) -> &[ActionEntry] {
+        &self.entries
+    }
+
+    /// Look up the action bound to a key press
+    ///
+    /// While the HUD has the keyboard, e.g. for a text field, keys without
+    /// modifiers go to the HUD and only chords with modifiers trigger actions.
+    ///
+    /// # Arguments
+    /// * `key` - Physical key pressed
+    /// * `modifiers` - Modifiers held
+    /// * `hud_wants_keyboard` - Whether the HUD captures the keyboard
+    pub fn action_for_key(&self, key: KeyCode, modifiers: ModifiersState, hud_wants_keyboard: bool) -> Option<Action> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.binding.map(|chord| (chord, entry.action)))
+            .find(|(chord, _)| chord.matches(key, modifiers))
+            .filter(|(chord, _)| !hud_wants_keyboard || chord.has_modifiers())
+            .map(|(_, action)| action)
+    }
+
+    /// Look up an action by its command palette name, ignoring case
+    pub fn find(&self, name: &str) -> Option<&ActionEntry> {
+        self.entries.iter().find(|entry| entry.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Key binding of an action, for showing it in the UI
+    pub fn binding(&self, action: Action) -> Option<KeyChord> {
+        self.entries.iter().find(|entry| entry.action == action).and_then(|entry| entry.binding)
+    }
+}
+
+/// Score how well a query fuzzily matches a text
+///
+/// The query characters must appear in the text in order, ignoring case.
+/// Matches at the start of words and runs of consecutive characters score
+/// higher, so "rs" ranks "Reload Shaders" above "Take Screenshot".
+///
+/// # Returns
+/// None if the query does not match, higher is better; an empty query
+/// matches everything with score 0
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut query = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+
+    for c in text.chars() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        let word_start = previous.is_none_or(|p| !p.is_alphanumeric() || (p.is_lowercase() && c.is_uppercase()));
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            query.next();
+            score += 1;
+            if word_start {
+                score += 8;
+            }
+            if previous_matched {
+                score += 4;
+            }
+            previous_matched = true;
+        } else {
+            previous_matched = false;
+        }
+        previous = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_lookup_requires_exact_modifiers() {
+        let registry = ActionRegistry::new();
+        assert_eq!(registry.action_for_key(KeyCode::F2, ModifiersState::empty(), false), Some(Action::ToggleHotReload));
+        assert_eq!(registry.action_for_key(KeyCode::KeyR, ModifiersState::CONTROL | ModifiersState::SHIFT, false), Some(Action::ReloadAll));
+        assert_eq!(registry.action_for_key(KeyCode::KeyR, ModifiersState::CONTROL, false), None);
+        assert_eq!(registry.action_for_key(KeyCode::KeyP, ModifiersState::empty(), false), None);
+        assert_eq!(registry.binding(Action::ReloadAll).map(|chord| chord.label()), Some("Ctrl+Shift+R".to_string()));
+        assert_eq!(KeyChord::key(KeyCode::F11).label(), "F11");
+        assert_eq!(registry.action_for_key(KeyCode::Digit3, ModifiersState::empty(), false), Some(Action::CameraBookmark(2)));
+        assert_eq!(registry.binding(Action::CameraBookmark(8)).map(|chord| chord.label()), Some("9".to_string()));
+
+        // Typing into the HUD keeps bare keys, chords with modifiers still fire
+        assert_eq!(registry.action_for_key(KeyCode::Digit3, ModifiersState::empty(), true), None);
+        assert_eq!(registry.action_for_key(KeyCode::KeyR, ModifiersState::CONTROL | ModifiersState::SHIFT, true), Some(Action::ReloadAll));
+
+        // Bindings are unique, the second action keeps only its palette entry
+        let mut registry = ActionRegistry::new();
+        registry.register(Action::ToggleHud, "Toggle Toolbar Again", Some(KeyChord::key(KeyCode::F2)));
+        assert_eq!(registry.action_for_key(KeyCode::F2, ModifiersState::empty(), false), Some(Action::ToggleHotReload));
+        assert_eq!(registry.entries().last().unwrap().binding, None);
+    }
+
+    #[test]
+    fn test_fuzzy_score_ranks_word_starts() {
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "Reload Shaders"), None);
+        assert_eq!(fuzzy_score("sl", "Reload Shaders"), None);
+
+        let reload = fuzzy_score("rs", "Reload Shaders").unwrap();
+        let screenshot = fuzzy_score("rs", "Take Screenshot").unwrap();
+        assert!(reload > screenshot);
+
+        // Case and spaces in the query do not matter, consecutive matches help
+        assert_eq!(fuzzy_score("HOT re", "Toggle Hot Reload"), fuzzy_score("hotre", "Toggle Hot Reload"));
+        assert!(fuzzy_score("scr", "Take Screenshot").unwrap() > fuzzy_score("sht", "Take Screenshot").unwrap());
+    }
+}
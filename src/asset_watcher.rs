@@ -0,0 +1,330 @@
+//! Asset watching
+//!
+//! `AssetWatcher` shares one debounced `FileWatcher` between everything
+//! that reloads from disk. Each subscription names the kind of asset, a
+//! file or directory and a handler; a change is delivered to every
+//! subscription whose path covers it. A file's directory is watched without
+//! its subdirectories, unless a directory subscription needs the whole
+//! tree. Handlers run on the watcher thread,
+//! so they usually push into a `ChangeQueue` that the main loop drains:
+//!
+//! ```ignore
+//! let config_changes = ChangeQueue::default();
+//! watcher.subscribe(AssetKind::Config, Path::new("settings.toml"), config_changes.handler())?;
+//! // each frame: if !config_changes.drain().is_empty() { config::reload() }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use log::{debug, info, warn};
+use crate::config;
+use crate::error::Result;
+use crate::file_watcher::{ChangeHandler, FileChange, FileWatcher};
+
+/// Kind of asset a subscription watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    /// GLSL shader sources
+    Shader,
+    /// Images
+    Texture,
+    /// OBJ and glTF meshes
+    Mesh,
+    /// Scene files
+    Scene,
+    /// Rhai scripts
+    Script,
+    /// Configuration files, e.g. toy manifests
+    Config,
+}
+
+impl AssetKind {
+    /// Name used in log messages
+    pub fn name(self) -> &'static str {
+        match self {
+            AssetKind::Shader => "shader",
+            AssetKind::Texture => "texture",
+            AssetKind::Mesh => "mesh",
+            AssetKind::Scene => "scene",
+            AssetKind::Script => "script",
+            AssetKind::Config => "config",
+        }
+    }
+
+    /// File extensions watched in directories subscribed with `subscribe`
+    pub fn default_extensions(self) -> Vec<String> {
+        let extensions: &[&str] = match self {
+            AssetKind::Shader => config::hot_reload::WATCH_EXTENSIONS,
+            AssetKind::Texture => &["png", "jpg", "jpeg", "hdr", "exr"],
+            AssetKind::Mesh => &["obj", "gltf", "glb"],
+            AssetKind::Scene | AssetKind::Config => &["toml"],
+            AssetKind::Script => &["rhai"],
+        };
+        extensions.iter().map(|extension| extension.to_string()).collect()
+    }
+}
+
+/// A debounced change of a watched asset
+pub type AssetChange = FileChange<AssetKind>;
+
+/// Handle returned by `subscribe`, for `unsubscribe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionId(u64);
+
+/// A file or directory watched for one asset kind
+struct Subscription {
+    id: SubscriptionId,
+    kind: AssetKind,
+    /// Directory passed to the file watcher
+    directory: PathBuf,
+    /// The watched file, None to watch the whole directory tree
+    file: Option<PathBuf>,
+    /// Extensions of interest in a directory tree, empty for all
+    extensions: Vec<String>,
+    handler: ChangeHandler<AssetKind>,
+}
+
+impl Subscription {
+    /// Whether a changed path belongs to this subscription
+    fn matches(&self, path: &Path) -> bool {
+        match &self.file {
+            Some(file) => path == file,
+            None => {
+                path.starts_with(&self.directory)
+                    && (self.extensions.is_empty()
+                        || path
+                            .extension()
+                            .and_then(|extension| extension.to_str())
+                            .is_some_and(|extension| self.extensions.iter().any(|watched| watched == extension)))
+            }
+        }
+    }
+}
+
+/// How a directory is watched for the subscriptions in it
+///
+/// # Returns
+/// None if no subscription uses the directory, Some(true) if one watches its
+/// whole tree and Some(false) if they only watch files directly in it
+fn watch_mode(subscriptions: &[Subscription], directory: &Path) -> Option<bool> {
+    subscriptions
+        .iter()
+        .filter(|subscription| subscription.directory == directory)
+        .map(|subscription| subscription.file.is_none())
+        .reduce(|recursive, other| recursive || other)
+}
+
+/// File watcher dispatching changes to typed subscriptions
+pub struct AssetWatcher {
+    /// Debounced watcher over the directories of all subscriptions
+    watcher: FileWatcher<AssetKind>,
+    /// Subscriptions, shared with the watcher thread
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+    /// Id of the next subscription
+    next_id: u64,
+}
+
+impl AssetWatcher {
+    /// Create a watcher without subscriptions
+    ///
+    /// # Arguments
+    /// * `debounce` - Quiet time after the last event before a file is reported
+    ///
+    /// # Errors
+    /// Returns an error if the platform watcher cannot be created
+    pub fn new(debounce: Duration) -> Result<Self> {
+        let subscriptions: Arc<Mutex<Vec<Subscription>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let classify_subscriptions = Arc::clone(&subscriptions);
+        let watcher = FileWatcher::new(debounce, move |path: &Path| {
+            classify_subscriptions
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|subscription| subscription.matches(path))
+                .map(|subscription| subscription.kind)
+        })?;
+
+        let dispatch_subscriptions = Arc::clone(&subscriptions);
+        watcher.set_handler(move |change: AssetChange| {
+            for subscription in dispatch_subscriptions.lock().unwrap().iter() {
+                if subscription.matches(&change.path) {
+                    debug!("{} changed: {:?}", subscription.kind.name(), change.path);
+                    (subscription.handler)(FileChange { kind: subscription.kind, path: change.path.clone() });
+                }
+            }
+        });
+
+        Ok(Self { watcher, subscriptions, next_id: 0 })
+    }
+
+    /// Create a watcher with the configured hot reload debounce
+    ///
+    /// # Errors
+    /// Returns an error if the platform watcher cannot be created
+    pub fn with_default_debounce() -> Result<Self> {
+        Self::new(Duration::from_millis(config::hot_reload::DEBOUNCE_MS))
+    }
+
+    /// Watch a file, or a directory tree for the kind's default extensions
+    ///
+    /// # Arguments
+    /// * `kind` - Kind of asset, passed back in every change
+    /// * `path` - File or directory to watch
+    /// * `handler` - Called on the watcher thread for every debounced change;
+    ///   it must not subscribe or unsubscribe
+    ///
+    /// # Errors
+    /// Returns an error if the path cannot be watched
+    pub fn subscribe(
+        &mut self,
+        kind: AssetKind,
+        path: &Path,
+        handler: impl Fn(AssetChange) + Send + Sync + 'static,
+    ) -> Result<SubscriptionId> {
+        self.subscribe_with_extensions(kind, path, kind.default_extensions(), handler)
+    }
+
+    /// Watch a file, or a directory tree for the given extensions
+    ///
+    /// # Arguments
+    /// * `kind` - Kind of asset, passed back in every change
+    /// * `path` - File or directory to watch
+    /// * `extensions` - Extensions of interest in a directory, empty for all
+    /// * `handler` - Called on the watcher thread for every debounced change
+    ///
+    /// # Errors
+    /// Returns an error if the path cannot be watched
+    pub fn subscribe_with_extensions(
+        &mut self,
+        kind: AssetKind,
+        path: &Path,
+        extensions: Vec<String>,
+        handler: impl Fn(AssetChange) + Send + Sync + 'static,
+    ) -> Result<SubscriptionId> {
+        // Event paths are absolute; compare against canonical ones
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let (directory, file) = if path.is_dir() {
+            (canonical(path), None)
+        } else {
+            // Watch the directory but not its subdirectories: editors often
+            // save by replacing the file
+            let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let directory = canonical(parent);
+            let file = path.file_name().map(|name| directory.join(name));
+            (directory, file)
+        };
+
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let before = watch_mode(&subscriptions, &directory);
+        subscriptions.push(Subscription {
+            id,
+            kind,
+            directory: directory.clone(),
+            file,
+            extensions,
+            handler: Box::new(handler),
+        });
+        let after = watch_mode(&subscriptions, &directory);
+        drop(subscriptions);
+
+        if let Err(e) = self.change_watch_mode(&directory, before, after) {
+            self.subscriptions.lock().unwrap().retain(|subscription| subscription.id != id);
+            if let Some(recursive) = before {
+                if let Err(e) = self.watcher.watch(&directory, recursive) {
+                    warn!("Failed to keep watching {:?}: {}", directory, e);
+                }
+            }
+            return Err(e);
+        }
+        info!("Watching {} assets in {:?}", kind.name(), directory);
+        Ok(id)
+    }
+
+    /// Remove a subscription, unwatching its directory if no other uses it
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let Some(index) = subscriptions.iter().position(|subscription| subscription.id == id) else {
+            return;
+        };
+        let before = watch_mode(&subscriptions, &subscriptions[index].directory);
+        let removed = subscriptions.remove(index);
+        let after = watch_mode(&subscriptions, &removed.directory);
+        drop(subscriptions);
+
+        if let Err(e) = self.change_watch_mode(&removed.directory, before, after) {
+            warn!("Failed to keep watching {:?}: {}", removed.directory, e);
+        }
+    }
+
+    /// Rewatch a directory whose subscriptions changed how it is watched
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be watched in the new mode
+    fn change_watch_mode(&mut self, directory: &Path, before: Option<bool>, after: Option<bool>) -> Result<()> {
+        if before == after {
+            return Ok(());
+        }
+        if before.is_some() {
+            self.watcher.unwatch(directory);
+        }
+        if let Some(recursive) = after {
+            self.watcher.watch(directory, recursive)?;
+        }
+        Ok(())
+    }
+
+    /// Number of files with a recorded modification time
+    pub fn file_count(&self) -> usize {
+        self.watcher.file_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(file: Option<&str>, extensions: &[&str]) -> Subscription {
+        Subscription {
+            id: SubscriptionId(0),
+            kind: AssetKind::Shader,
+            directory: PathBuf::from("/assets/shaders"),
+            file: file.map(PathBuf::from),
+            extensions: extensions.iter().map(|extension| extension.to_string()).collect(),
+            handler: Box::new(|_| {}),
+        }
+    }
+
+    #[test]
+    fn test_directory_subscription_matches_tree_by_extension() {
+        let shaders = subscription(None, &["vert", "frag"]);
+        assert!(shaders.matches(Path::new("/assets/shaders/sdf.frag")));
+        assert!(shaders.matches(Path::new("/assets/shaders/include/common.vert")));
+        assert!(!shaders.matches(Path::new("/assets/shaders/sdf.frag.spv")));
+        assert!(!shaders.matches(Path::new("/assets/textures/sdf.frag")));
+        assert!(subscription(None, &[]).matches(Path::new("/assets/shaders/notes.txt")));
+    }
+
+    #[test]
+    fn test_directories_of_files_are_not_watched_recursively() {
+        let directory = Path::new("/assets/shaders");
+        let file = subscription(Some("/assets/shaders/scene.toml"), &[]);
+        assert_eq!(watch_mode(&[], directory), None);
+        assert_eq!(watch_mode(&[file], directory), Some(false));
+
+        let tree = [subscription(Some("/assets/shaders/scene.toml"), &[]), subscription(None, &["frag"])];
+        assert_eq!(watch_mode(&tree, directory), Some(true));
+        assert_eq!(watch_mode(&tree, Path::new("/assets")), None);
+    }
+
+    #[test]
+    fn test_file_subscription_matches_only_the_file() {
+        let scene = subscription(Some("/assets/shaders/scene.toml"), &["vert"]);
+        assert!(scene.matches(Path::new("/assets/shaders/scene.toml")));
+        assert!(!scene.matches(Path::new("/assets/shaders/other.toml")));
+        assert!(!scene.matches(Path::new("/assets/shaders/sdf.vert")));
+    }
+}
@@ -0,0 +1,510 @@
+//! Asynchronous asset loading
+//!
+//! Mesh, texture and scene files are read and decoded on the job pool, so a
+//! large glTF or HDR file never stalls the render loop. `AssetManager` is an
+//! ECS resource with one `AssetStore` per kind of asset: `load` returns a
+//! `Handle` right away and starts a job for a file not seen before, and the
+//! asset can be taken from the handle once the ECS world polled the ended job
+//! at the start of an update. Until then a placeholder stands in: mesh draws
+//! use a unit cube (see `prepare_mesh_draws`) and the environment map shows
+//! a pink texture (see `vulkan::environment_map`).
+//!
+//! Handles are reference counted. An asset stays in memory while a handle
+//! to it is held, e.g. by the `MeshAsset` component of every entity drawing
+//! the mesh, so a mesh evicted from the GPU by the residency budget is
+//! uploaded again without reading its file; once the last handle is dropped
+//! the asset is released. A file that failed to load is reported once and
+//! not tried again until it changes.
+//!
+//! Loaded mesh and texture files are watched with the asset watcher. A
+//! changed file is read again while the previous version stays in use; once
+//! the job ended every handle resolves to the new version, and the change is
+//! sent to the channels returned by `AssetManager::subscribe`, so copies of
+//! the asset such as GPU buffers can be replaced.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use log::{info, warn};
+use crate::asset_watcher::{AssetChange, AssetKind, AssetWatcher, SubscriptionId};
+use crate::config;
+use crate::ecs::scene_file::SceneFile;
+use crate::error::{AppError, Result};
+use crate::file_watcher::{ChangeQueue, FileChange};
+use crate::image_io::{self, HdrImage};
+use crate::jobs::{JobHandle, JobPool};
+use crate::project;
+use crate::vulkan::mesh::{MeshData, MeshDrawList};
+
+/// Latest version of an asset, shared by the store and its handles
+struct Slot<T> {
+    path: PathBuf,
+    asset: Mutex<Option<Arc<T>>>,
+}
+
+impl<T> Slot<T> {
+    /// Lock the asset; a panic never happens while it is held
+    fn asset(&self) -> MutexGuard<'_, Option<Arc<T>>> {
+        self.asset.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Reference counted handle to an asset file, valid before the asset has loaded
+///
+/// Every clone keeps the asset in memory; it is released once all of them
+/// are dropped.
+pub struct Handle<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Handle<T> {
+    /// File the asset is loaded from, as passed to `load`
+    pub fn path(&self) -> &Path {
+        &self.slot.path
+    }
+
+    /// Latest version of the asset, None while it is loading or if it failed to load
+    pub fn get(&self) -> Option<Arc<T>> {
+        self.slot.asset().clone()
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self { slot: Arc::clone(&self.slot) }
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Handle").field(&self.slot.path).finish()
+    }
+}
+
+/// Load state of an asset file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetState {
+    /// A job is reading the file for the first time
+    Loading,
+    /// The asset is in memory; a changed file may be read again meanwhile
+    Ready,
+    /// The file could not be loaded
+    Failed,
+}
+
+/// An asset file known to a store
+struct Entry<T> {
+    slot: Arc<Slot<T>>,
+    /// Job reading the file, for the first time or after it changed
+    job: Option<JobHandle<Result<T>>>,
+    /// Whether the last read failed and no version is in memory
+    failed: bool,
+    /// Whether watching the file was attempted
+    watched: bool,
+    /// Subscription of the watched file, None if it cannot be watched
+    subscription: Option<SubscriptionId>,
+}
+
+/// Assets of one kind by file path
+pub struct AssetStore<T> {
+    kind: AssetKind,
+    /// Reads and decodes a file, on a worker thread
+    loader: fn(&Path) -> Result<T>,
+    /// Finds the file of an asset path on disk
+    locate: fn(&Path) -> PathBuf,
+    entries: HashMap<PathBuf, Entry<T>>,
+    /// Subscriptions of released files, to be removed from the watcher
+    released: Vec<SubscriptionId>,
+}
+
+impl<T: Send + Sync + 'static> AssetStore<T> {
+    /// Create an empty store
+    ///
+    /// # Arguments
+    /// * `kind` - Kind of the assets, for watching their files
+    /// * `loader` - Reads and decodes a file, called on a worker thread
+    pub fn new(kind: AssetKind, loader: fn(&Path) -> Result<T>) -> Self {
+        Self { kind, loader, locate: Path::to_path_buf, entries: HashMap::new(), released: Vec::new() }
+    }
+
+    /// Find the files of asset paths with `locate` before loading them,
+    /// e.g. in the open project
+    pub fn with_locate(mut self, locate: fn(&Path) -> PathBuf) -> Self {
+        self.locate = locate;
+        self
+    }
+
+    /// Start loading a file unless it is known already
+    ///
+    /// # Arguments
+    /// * `jobs` - Pool the file is read on
+    /// * `path` - File to load
+    ///
+    /// # Returns
+    /// A handle to the asset, which resolves once its job ended
+    pub fn load(&mut self, jobs: &JobPool, path: &Path) -> Handle<T> {
+        if let Some(entry) = self.entries.get(path) {
+            return Handle { slot: Arc::clone(&entry.slot) };
+        }
+        let slot = Arc::new(Slot { path: path.to_path_buf(), asset: Mutex::new(None) });
+        self.entries.insert(path.to_path_buf(), Entry {
+            slot: Arc::clone(&slot),
+            job: Some(self.read(jobs, path)),
+            failed: false,
+            watched: false,
+            subscription: None,
+        });
+        Handle { slot }
+    }
+
+    /// Start a job reading a file
+    fn read(&self, jobs: &JobPool, path: &Path) -> JobHandle<Result<T>> {
+        let loader = self.loader;
+        let file = (self.locate)(path);
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        jobs.spawn(name, move || loader(&file))
+    }
+
+    /// Read a file again after it changed on disk
+    ///
+    /// Handles keep resolving to the previous version until the job ended.
+    /// Does nothing for a file not in the store or already being read.
+    pub fn reload(&mut self, jobs: &JobPool, path: &Path) {
+        if self.entries.get(path).is_some_and(|entry| entry.job.is_none()) {
+            let job = self.read(jobs, path);
+            if let Some(entry) = self.entries.get_mut(path) {
+                entry.job = Some(job);
+            }
+        }
+    }
+
+    /// Load state of a file, None if it is not in the store
+    pub fn state(&self, path: &Path) -> Option<AssetState> {
+        self.entries.get(path).map(|entry| match (entry.slot.asset().is_some(), entry.failed) {
+            (true, _) => AssetState::Ready,
+            (false, true) => AssetState::Failed,
+            (false, false) => AssetState::Loading,
+        })
+    }
+
+    /// Release the files no handle is held to and take the results of ended jobs
+    ///
+    /// # Returns
+    /// The files read again after a change, and why each file that failed
+    /// since the last poll could not be loaded
+    fn poll(&mut self) -> (Vec<PathBuf>, Vec<AppError>) {
+        // Only the store's own reference is left; a running job is waited for
+        let released: Vec<PathBuf> = self.entries
+            .iter()
+            .filter(|(_, entry)| entry.job.is_none() && Arc::strong_count(&entry.slot) == 1)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in released {
+            if let Some(entry) = self.entries.remove(&path) {
+                info!("Released {} {}", self.kind.name(), path.display());
+                self.released.extend(entry.subscription);
+            }
+        }
+
+        let mut reloaded = Vec::new();
+        let mut errors = Vec::new();
+        for (path, entry) in &mut self.entries {
+            let Some(job) = entry.job.take_if(|job| job.is_finished()) else {
+                continue;
+            };
+            let mut asset = entry.slot.asset();
+            match job.finish().and_then(|result| result) {
+                Ok(loaded) => {
+                    if asset.replace(Arc::new(loaded)).is_some() || entry.failed {
+                        info!("Reloaded {} {}", self.kind.name(), path.display());
+                        reloaded.push(path.clone());
+                    } else {
+                        info!("Loaded {} {}", self.kind.name(), path.display());
+                    }
+                    entry.failed = false;
+                }
+                // A version in memory stays in use until the file is fixed
+                Err(e) => {
+                    entry.failed = asset.is_none();
+                    errors.push(e);
+                }
+            }
+        }
+        (reloaded, errors)
+    }
+
+    /// Watch the files read so far and stop watching released ones
+    ///
+    /// # Arguments
+    /// * `watcher` - Watcher to subscribe with, None if there is none; the
+    ///   files are not watched then
+    /// * `changes` - Queue the changed files are pushed to, with their path
+    ///   in the store
+    fn watch(&mut self, mut watcher: Option<&mut AssetWatcher>, changes: &ChangeQueue<AssetKind>) {
+        for subscription in self.released.drain(..) {
+            if let Some(watcher) = watcher.as_deref_mut() {
+                watcher.unsubscribe(subscription);
+            }
+        }
+        for (path, entry) in &mut self.entries {
+            if entry.watched || entry.job.is_some() {
+                continue;
+            }
+            entry.watched = true;
+            let Some(watcher) = watcher.as_deref_mut() else {
+                continue;
+            };
+            let handler = changes.handler();
+            let key = path.clone();
+            let forward = move |change: AssetChange| handler(FileChange { kind: change.kind, path: key.clone() });
+            match watcher.subscribe(self.kind, &(self.locate)(path), forward) {
+                Ok(subscription) => entry.subscription = Some(subscription),
+                Err(e) => warn!("Hot reload unavailable for {} {}: {}", self.kind.name(), path.display(), e),
+            }
+        }
+    }
+
+    /// Whether a file read so far was not watched yet
+    fn has_unwatched(&self) -> bool {
+        self.entries.values().any(|entry| !entry.watched && entry.job.is_none())
+    }
+}
+
+/// Asset stores of every kind, kept as an ECS resource
+pub struct AssetManager {
+    /// Meshes of `MeshRenderer` components, by the path the component names
+    pub meshes: AssetStore<MeshData>,
+    /// Environment maps of the background
+    pub textures: AssetStore<HdrImage>,
+    /// Scene files opened without blocking, e.g. dropped onto the window
+    pub scenes: AssetStore<SceneFile>,
+    /// Drawn in place of meshes still loading
+    placeholder_mesh: Arc<MeshData>,
+    /// Watched files that changed on disk, by their path in the store
+    changes: ChangeQueue<AssetKind>,
+    /// Channels the reloaded files are sent to
+    listeners: Vec<Sender<AssetChange>>,
+}
+
+impl Default for AssetManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetManager {
+    /// Create empty stores
+    pub fn new() -> Self {
+        Self {
+            meshes: AssetStore::new(AssetKind::Mesh, MeshData::load).with_locate(project::locate),
+            textures: AssetStore::new(AssetKind::Texture, image_io::read_hdr).with_locate(project::locate),
+            scenes: AssetStore::new(AssetKind::Scene, SceneFile::load),
+            placeholder_mesh: Arc::new(MeshData::cube()),
+            changes: ChangeQueue::default(),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Channel receiving the mesh and texture files read again after they changed
+    ///
+    /// The change is sent once every handle resolves to the new version.
+    pub fn subscribe(&mut self) -> Receiver<AssetChange> {
+        let (sender, receiver) = mpsc::channel();
+        self.listeners.push(sender);
+        receiver
+    }
+
+    /// Release unused assets and take the results of ended jobs of every store
+    ///
+    /// # Returns
+    /// Why each file that failed since the last poll could not be loaded
+    pub fn poll(&mut self) -> Vec<AppError> {
+        let (meshes, mut errors) = self.meshes.poll();
+        let (textures, texture_errors) = self.textures.poll();
+        let (_, scene_errors) = self.scenes.poll();
+        errors.extend(texture_errors);
+        errors.extend(scene_errors);
+
+        let changes: Vec<AssetChange> = meshes.into_iter().map(|path| FileChange { kind: AssetKind::Mesh, path })
+            .chain(textures.into_iter().map(|path| FileChange { kind: AssetKind::Texture, path }))
+            .collect();
+        if !changes.is_empty() {
+            // Listeners that dropped their receiver are forgotten
+            self.listeners.retain(|listener| changes.iter().all(|change| listener.send(change.clone()).is_ok()));
+        }
+        errors
+    }
+
+    /// Whether a mesh or texture file read so far was not watched yet
+    pub fn has_unwatched(&self) -> bool {
+        self.meshes.has_unwatched() || self.textures.has_unwatched()
+    }
+
+    /// Watch the mesh and texture files read so far, and read the changed ones again
+    ///
+    /// # Arguments
+    /// * `jobs` - Pool changed files are read on
+    /// * `watcher` - Shared asset watcher, None if it cannot be created;
+    ///   the files read so far are not watched then
+    pub fn watch(&mut self, jobs: &JobPool, mut watcher: Option<&mut AssetWatcher>) {
+        self.meshes.watch(watcher.as_deref_mut(), &self.changes);
+        self.textures.watch(watcher, &self.changes);
+        for change in self.changes.drain() {
+            match change.kind {
+                AssetKind::Mesh => self.meshes.reload(jobs, &change.path),
+                AssetKind::Texture => self.textures.reload(jobs, &change.path),
+                _ => {}
+            }
+        }
+    }
+
+    /// Attach the mesh data to a frame's draws, loading new mesh files
+    ///
+    /// Draws of a mesh still loading are drawn as the placeholder cube, and
+    /// draws of a mesh that failed to load are dropped.
+    ///
+    /// # Arguments
+    /// * `jobs` - Pool new mesh files are read on
+    /// * `draws` - Draw list collected from the ECS world
+    pub fn prepare_mesh_draws(&mut self, jobs: &JobPool, draws: &mut MeshDrawList) {
+        draws.resolve(|mesh| {
+            let handle = self.meshes.load(jobs, Path::new(mesh));
+            match (handle.get(), self.meshes.state(handle.path())) {
+                (Some(data), _) => Some((mesh.to_string(), data)),
+                (None, Some(AssetState::Failed)) => None,
+                (None, _) => Some((config::assets::PLACEHOLDER_MESH.to_string(), Arc::clone(&self.placeholder_mesh))),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use cgmath::{Matrix4, SquareMatrix};
+
+    /// Poll until no job is left
+    fn settle(assets: &mut AssetManager, jobs: &JobPool) -> Vec<AppError> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !jobs.active().is_empty() && Instant::now() < deadline {
+            std::thread::yield_now();
+        }
+        assets.poll()
+    }
+
+    /// Read the contents of a text file
+    fn read_text(path: &Path) -> Result<String> {
+        std::fs::read_to_string(path).map_err(|e| AppError::Generic(e.to_string()))
+    }
+
+    /// Poll a store until a handle resolves
+    fn wait_for<T: Send + Sync + 'static>(store: &mut AssetStore<T>, handle: &Handle<T>) -> Option<Arc<T>> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while store.entries.values().any(|entry| entry.job.is_some()) && Instant::now() < deadline {
+            assert!(store.poll().1.is_empty());
+        }
+        handle.get()
+    }
+
+    #[test]
+    fn test_load_returns_a_handle_before_the_asset() {
+        let jobs = JobPool::with_workers(1).unwrap();
+        let mut store = AssetStore::new(AssetKind::Config, |path| Ok(path.display().to_string()));
+        let handle = store.load(&jobs, Path::new("a.txt"));
+        assert_eq!(handle.path(), Path::new("a.txt"));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while handle.get().is_none() && Instant::now() < deadline {
+            assert!(store.poll().1.is_empty());
+        }
+        assert_eq!(handle.get().as_deref().map(String::as_str), Some("a.txt"));
+        assert_eq!(store.state(Path::new("a.txt")), Some(AssetState::Ready));
+        assert_eq!(store.state(Path::new("b.txt")), None);
+    }
+
+    #[test]
+    fn test_asset_is_released_with_its_last_handle() {
+        let jobs = JobPool::with_workers(1).unwrap();
+        let mut store = AssetStore::new(AssetKind::Config, |path| Ok(path.display().to_string()));
+        let handle = store.load(&jobs, Path::new("a.txt"));
+        let other = handle.clone();
+        assert!(wait_for(&mut store, &handle).is_some());
+
+        drop(handle);
+        store.poll();
+        assert_eq!(other.get().as_deref().map(String::as_str), Some("a.txt"));
+        drop(other);
+        store.poll();
+        assert_eq!(store.state(Path::new("a.txt")), None);
+    }
+
+    #[test]
+    fn test_reloaded_file_reaches_every_handle() {
+        let jobs = JobPool::with_workers(1).unwrap();
+        let file = std::env::temp_dir().join(format!("vulkan-app-reload-test-{}.txt", std::process::id()));
+        std::fs::write(&file, "first").unwrap();
+        let mut store = AssetStore::new(AssetKind::Config, read_text);
+        let handle = store.load(&jobs, &file);
+        let held = handle.clone();
+        assert_eq!(wait_for(&mut store, &handle).as_deref().map(String::as_str), Some("first"));
+
+        // The previous version is used until the file has been read again
+        std::fs::write(&file, "second").unwrap();
+        store.reload(&jobs, &file);
+        assert!(held.get().is_some());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut reloaded = Vec::new();
+        while reloaded.is_empty() && Instant::now() < deadline {
+            reloaded = store.poll().0;
+        }
+        assert_eq!(reloaded, [file.as_path()]);
+        assert_eq!(held.get().as_deref().map(String::as_str), Some("second"));
+
+        // A broken file keeps the last version
+        std::fs::remove_file(&file).unwrap();
+        store.reload(&jobs, &file);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut errors = Vec::new();
+        while errors.is_empty() && Instant::now() < deadline {
+            errors = store.poll().1;
+        }
+        assert_eq!(errors.len(), 1);
+        assert_eq!(handle.get().as_deref().map(String::as_str), Some("second"));
+        assert_eq!(store.state(&file), Some(AssetState::Ready));
+    }
+
+    #[test]
+    fn test_mesh_draws_use_the_placeholder_until_loaded() {
+        let jobs = JobPool::with_workers(1).unwrap();
+        let mut assets = AssetManager::new();
+        let file = std::env::temp_dir().join(format!("vulkan-app-assets-test-{}.obj", std::process::id()));
+        std::fs::write(&file, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let mesh = file.to_string_lossy().into_owned();
+        let missing = "missing/mesh.obj";
+        // Held like the `MeshAsset` components of the entities drawing them
+        let _handles = [assets.meshes.load(&jobs, &file), assets.meshes.load(&jobs, Path::new(missing))];
+
+        let frame = || {
+            let mut draws = MeshDrawList::new();
+            draws.push(&mesh, Matrix4::identity(), [1.0; 3], config::layers::DEFAULT);
+            draws.push(missing, Matrix4::identity(), [1.0; 3], config::layers::DEFAULT);
+            draws
+        };
+        let mut draws = frame();
+        assets.prepare_mesh_draws(&jobs, &mut draws);
+        assert!(draws.draws().iter().all(|draw| draw.mesh == config::assets::PLACEHOLDER_MESH));
+        assert_eq!(draws.source(config::assets::PLACEHOLDER_MESH).map(MeshData::triangle_count), Some(12));
+
+        // The missing file is reported once and its draws are dropped from then on
+        assert_eq!(settle(&mut assets, &jobs).len(), 1);
+        let mut draws = frame();
+        assets.prepare_mesh_draws(&jobs, &mut draws);
+        assert_eq!(draws.draws().len(), 1);
+        assert_eq!(draws.draws()[0].mesh, mesh);
+        assert_eq!(draws.source(&mesh).map(MeshData::triangle_count), Some(1));
+        assert!(settle(&mut assets, &jobs).is_empty());
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+//! Scene auto-save and crash recovery
+//!
+//! While the app runs, the scene as edited in the HUD is written to a folder
+//! in the system temp directory every few seconds (`AutosaveSettings`), next
+//! to a session marker saying the app is running. A clean shutdown removes
+//! both. When the next start still finds a marker, the run that wrote it
+//! crashed or was killed: its last auto-save is kept aside as the recovered
+//! scene, out of the way of the new session's auto-saves, and the HUD offers
+//! to restore or discard it. A recovered scene that is neither stays offered
+//! on later starts.
+//!
+//! Markers and auto-saves are named after the process id, and a session
+//! holds a lock on its marker until it ends. The operating system releases
+//! the lock when the process dies, so a marker that can be locked belongs to
+//! a crashed run, while those of other instances still running are left alone.
+
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use crate::config;
+use crate::error::{Result, ResultExt, Subsystem};
+use crate::settings::AutosaveSettings;
+
+/// Folder holding the auto-save and the session marker
+pub fn directory() -> PathBuf {
+    std::env::temp_dir().join(config::autosave::DIR_NAME)
+}
+
+/// Remove a file that may not exist
+fn remove_if_present(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Name of a per-process session file, e.g. `autosave-1234.toml` for `autosave.toml`
+fn session_file_name(name: &str, id: u32) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}-{}.{}", stem, id, extension),
+        None => format!("{}-{}", name, id),
+    }
+}
+
+/// Process id in the name of a session marker, None for other files
+fn marker_id(file_name: &str) -> Option<u32> {
+    let (stem, extension) = config::autosave::SESSION_FILE.rsplit_once('.')?;
+    file_name.strip_prefix(stem)?.strip_prefix('-')?.strip_suffix(extension)?.strip_suffix('.')?.parse().ok()
+}
+
+/// A running session, marked in the auto-save folder until it ends cleanly
+#[derive(Debug)]
+pub struct Session {
+    /// Auto-save folder
+    dir: PathBuf,
+    /// Id the session's files are named after, the process id
+    id: u32,
+    /// Session marker, locked while the session runs
+    marker: File,
+}
+
+impl Session {
+    /// Start a session
+    ///
+    /// # Arguments
+    /// * `dir` - Auto-save folder, created if needed
+    ///
+    /// # Returns
+    /// The session, and the scene recovered from a previous session that
+    /// did not end cleanly, if there is one
+    ///
+    /// # Errors
+    /// Returns an error if the folder or the session marker cannot be written
+    pub fn begin(dir: &Path) -> Result<(Self, Option<PathBuf>)> {
+        Self::begin_with_id(dir, std::process::id())
+    }
+
+    /// Start a session whose files are named after `id`, see `begin`
+    fn begin_with_id(dir: &Path, id: u32) -> Result<(Self, Option<PathBuf>)> {
+        fs::create_dir_all(dir).context(Subsystem::Io, format!("create auto-save folder {}", dir.display()))?;
+        let recovered = dir.join(config::autosave::RECOVERED_FILE);
+        Self::recover_crashed(dir, &recovered)?;
+
+        let path = dir.join(session_file_name(config::autosave::SESSION_FILE, id));
+        let marker = File::create(&path)
+            .and_then(|marker| marker.try_lock().map_err(io::Error::from).map(|()| marker))
+            .context(Subsystem::Io, format!("write session marker {}", path.display()))?;
+
+        let recovered = recovered.exists().then_some(recovered);
+        Ok((Self { dir: dir.to_path_buf(), id, marker }, recovered))
+    }
+
+    /// Keep the auto-save of every session whose marker is no longer locked
+    /// as the recovered scene, and remove its marker
+    ///
+    /// With several crashed sessions, the recovered scene is the last one found.
+    fn recover_crashed(dir: &Path, recovered: &Path) -> Result<()> {
+        let entries = fs::read_dir(dir).context(Subsystem::Io, format!("list auto-save folder {}", dir.display()))?;
+        for name in entries.filter_map(|entry| entry.ok()?.file_name().into_string().ok()) {
+            let Some(id) = marker_id(&name) else {
+                continue;
+            };
+            let marker = dir.join(&name);
+            let crashed = match File::open(&marker).map(|file| file.try_lock()) {
+                Ok(Ok(())) => true,
+                Ok(Err(TryLockError::WouldBlock)) => false,
+                // A marker that cannot be checked is left alone, like a running session's
+                Ok(Err(TryLockError::Error(e))) | Err(e) => {
+                    warn!("Cannot check session marker {}: {}", marker.display(), e);
+                    false
+                }
+            };
+            if !crashed {
+                debug!("Session {} is still running", id);
+                continue;
+            }
+
+            let autosave = dir.join(session_file_name(config::autosave::SCENE_FILE, id));
+            if autosave.exists() {
+                info!("Session {} did not shut down cleanly; keeping its auto-save", id);
+                fs::rename(&autosave, recovered).context(Subsystem::Io, format!("keep auto-save {}", autosave.display()))?;
+            }
+            remove_if_present(&marker).context(Subsystem::Io, format!("remove session marker {}", marker.display()))?;
+        }
+        Ok(())
+    }
+
+    /// File the session auto-saves to
+    pub fn autosave_file(&self) -> PathBuf {
+        self.dir.join(session_file_name(config::autosave::SCENE_FILE, self.id))
+    }
+
+    /// End the session cleanly, removing the marker and the auto-save
+    pub fn end(self) {
+        // The lock goes with the handle, which must be closed before the marker can be removed on Windows
+        drop(self.marker);
+        for name in [config::autosave::SCENE_FILE, config::autosave::SESSION_FILE] {
+            let path = self.dir.join(session_file_name(name, self.id));
+            if let Err(e) = remove_if_present(&path) {
+                warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+        debug!("Auto-save session ended");
+    }
+}
+
+/// Forget a recovered scene that was restored or discarded
+///
+/// # Errors
+/// Returns an error if the file exists and cannot be removed
+pub fn discard(path: &Path) -> Result<()> {
+    remove_if_present(path).context(Subsystem::Io, format!("remove recovered scene {}", path.display()))
+}
+
+/// Writes the scene to the auto-save file on a schedule
+#[derive(Debug)]
+pub struct Autosave {
+    /// Auto-save file
+    file: PathBuf,
+    settings: AutosaveSettings,
+    /// When the schedule was last checked off, the creation time before the first save
+    last_save: Instant,
+    /// Scene text written last, to skip saves of an unchanged scene
+    last_text: String,
+}
+
+impl Autosave {
+    /// Create a schedule whose first save is one interval from `now`
+    pub fn new(file: PathBuf, settings: AutosaveSettings, now: Instant) -> Self {
+        Self { file, settings, last_save: now, last_text: String::new() }
+    }
+
+    /// File the scene is written to
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Change the options; a new interval counts from the last save
+    pub fn set_settings(&mut self, settings: AutosaveSettings) {
+        self.settings = settings;
+    }
+
+    /// Whether a save is due
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.settings.enabled && now.duration_since(self.last_save) >= Duration::from_secs(u64::from(self.settings.interval_secs))
+    }
+
+    /// Write the scene, unless it is the same as the last time
+    ///
+    /// The text goes to a temporary file first and replaces the auto-save
+    /// in one step, so a crash while writing leaves the previous save.
+    ///
+    /// # Arguments
+    /// * `text` - Scene file contents
+    /// * `now` - Time the next interval counts from
+    ///
+    /// # Returns
+    /// Whether the file was written
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written; the save is retried after the next interval
+    pub fn save(&mut self, text: &str, now: Instant) -> Result<bool> {
+        self.last_save = now;
+        if text == self.last_text {
+            return Ok(false);
+        }
+        let partial = self.file.with_extension("toml.partial");
+        fs::write(&partial, text)
+            .and_then(|()| fs::rename(&partial, &self.file))
+            .context(Subsystem::Io, format!("auto-save scene to {}", self.file.display()))?;
+        self.last_text = text.to_string();
+        debug!("Auto-saved the scene to {}", self.file.display());
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty auto-save folder of its own for a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vulkan-app-autosave-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_unclean_session_leaves_a_recovered_scene() {
+        let dir = test_dir("session");
+        let (session, recovered) = Session::begin(&dir).unwrap();
+        assert_eq!(recovered, None);
+        fs::write(session.autosave_file(), "[[shapes]]").unwrap();
+
+        // The session is dropped without ending, as after a crash
+        drop(session);
+        let (session, recovered) = Session::begin(&dir).unwrap();
+        let recovered = recovered.unwrap();
+        assert_eq!(fs::read_to_string(&recovered).unwrap(), "[[shapes]]");
+        assert!(!session.autosave_file().exists());
+
+        // Offered until it is restored or discarded, also after a clean shutdown
+        session.end();
+        let (session, offered) = Session::begin(&dir).unwrap();
+        assert_eq!(offered.as_ref(), Some(&recovered));
+        discard(&recovered).unwrap();
+        session.end();
+        let (session, offered) = Session::begin(&dir).unwrap();
+        assert_eq!(offered, None);
+        session.end();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_running_sessions_keep_their_auto_saves() {
+        let dir = test_dir("instances");
+        let (first, _) = Session::begin_with_id(&dir, 1).unwrap();
+        fs::write(first.autosave_file(), "first").unwrap();
+
+        // A second instance neither recovers nor overwrites the first one's auto-save
+        let (second, recovered) = Session::begin_with_id(&dir, 2).unwrap();
+        assert_eq!(recovered, None);
+        assert_ne!(second.autosave_file(), first.autosave_file());
+        assert_eq!(fs::read_to_string(first.autosave_file()).unwrap(), "first");
+
+        // Once the first instance dies, the next start recovers its auto-save
+        drop(first);
+        let (third, recovered) = Session::begin_with_id(&dir, 3).unwrap();
+        assert_eq!(fs::read_to_string(recovered.unwrap()).unwrap(), "first");
+        assert_eq!(marker_id(&session_file_name(config::autosave::SESSION_FILE, 1)), Some(1));
+        assert_eq!(marker_id(config::autosave::RECOVERED_FILE), None);
+
+        second.end();
+        third.end();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_saves_on_schedule_and_skips_unchanged_scenes() {
+        let dir = test_dir("schedule");
+        fs::create_dir_all(&dir).unwrap();
+        let start = Instant::now();
+        let settings = AutosaveSettings { enabled: true, interval_secs: 10 };
+        let mut autosave = Autosave::new(dir.join(config::autosave::SCENE_FILE), settings, start);
+
+        assert!(!autosave.is_due(start + Duration::from_secs(9)));
+        let due = start + Duration::from_secs(10);
+        assert!(autosave.is_due(due));
+        assert!(autosave.save("a", due).unwrap());
+        assert_eq!(fs::read_to_string(autosave.file()).unwrap(), "a");
+        assert!(!autosave.is_due(due + Duration::from_secs(5)));
+
+        let later = due + Duration::from_secs(10);
+        assert!(!autosave.save("a", later).unwrap());
+        assert!(autosave.save("b", later).unwrap());
+        assert_eq!(fs::read_to_string(autosave.file()).unwrap(), "b");
+
+        autosave.set_settings(AutosaveSettings { enabled: false, ..settings });
+        assert!(!autosave.is_due(later + Duration::from_secs(60)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,367 @@
+//! Benchmark mode
+//!
+//! `--benchmark` loads `config::benchmark::SCENE_FILE`, flies the camera on a
+//! fixed orbit for a set time and records every frame: the wall-clock frame
+//! time, the CPU time spent updating and drawing, and the GPU time of the SDF
+//! pass from the renderer's timestamp queries. When the time is up it writes
+//! a per-frame CSV and a summary with min/avg/p95/p99 of each, then the app
+//! exits. Frames during a short warm-up are not recorded.
+//!
+//! The frame rate cap is lifted for the run; the present mode from the
+//! settings still applies, so use `immediate` for uncapped results.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use chrono::Local;
+use log::info;
+use crate::camera::LookCamera;
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+
+/// Length, scene and output of a benchmark run
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkSettings {
+    /// Measured time in seconds, after the warm-up
+    pub duration_secs: f32,
+    /// Scene file to load
+    pub scene: PathBuf,
+    /// Directory for the CSV and the summary
+    pub output_dir: PathBuf,
+}
+
+impl Default for BenchmarkSettings {
+    fn default() -> Self {
+        Self {
+            duration_secs: config::benchmark::DEFAULT_DURATION_SECS,
+            scene: PathBuf::from(config::benchmark::SCENE_FILE),
+            output_dir: PathBuf::from(config::benchmark::OUTPUT_DIR),
+        }
+    }
+}
+
+impl BenchmarkSettings {
+    /// Parse `--benchmark[=SECONDS]`, `--benchmark-scene PATH` and `--benchmark-dir DIR`
+    ///
+    /// # Arguments
+    /// * `args` - Command line arguments without the program name
+    ///
+    /// # Returns
+    /// `Ok(None)` if no benchmark was requested
+    ///
+    /// # Errors
+    /// Returns an error if a benchmark argument is malformed
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>> {
+        let mut settings = Self::default();
+        let mut requested = false;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = |name: &str| {
+                inline_value.clone().or_else(|| args.next())
+                    .ok_or_else(|| AppError::Generic(format!("{} needs a value", name)))
+            };
+
+            match name.as_str() {
+                // The length is inline only, so `--benchmark` can be followed by other flags
+                "--benchmark" => {
+                    if let Some(ref seconds) = inline_value {
+                        settings.duration_secs = seconds.parse::<f32>().ok()
+                            .filter(|secs| *secs > 0.0)
+                            .ok_or_else(|| AppError::Generic(format!("Invalid --benchmark length '{}', expected seconds", seconds)))?;
+                    }
+                    requested = true;
+                }
+                "--benchmark-scene" => settings.scene = PathBuf::from(value("--benchmark-scene")?),
+                "--benchmark-dir" => settings.output_dir = PathBuf::from(value("--benchmark-dir")?),
+                _ => {}
+            }
+        }
+
+        Ok(requested.then_some(settings))
+    }
+}
+
+/// Camera of the benchmark flythrough at a time into the run
+///
+/// Circles `config::benchmark::ORBIT_CENTER` once per
+/// `ORBIT_PERIOD_SECS`, looking at the center.
+pub fn camera_path(time_secs: f32) -> LookCamera {
+    let center = config::benchmark::ORBIT_CENTER;
+    let angle = time_secs / config::benchmark::ORBIT_PERIOD_SECS * std::f32::consts::TAU;
+    let origin = [
+        center[0] + config::benchmark::ORBIT_RADIUS * angle.sin(),
+        center[1] + config::benchmark::ORBIT_HEIGHT,
+        center[2] - config::benchmark::ORBIT_RADIUS * angle.cos(),
+    ];
+    let to_center = [center[0] - origin[0], center[1] - origin[1], center[2] - origin[2]];
+    let horizontal = to_center[0].hypot(to_center[2]);
+    LookCamera {
+        origin,
+        yaw: to_center[0].atan2(to_center[2]),
+        pitch: to_center[1].atan2(horizontal),
+        ortho_half_height: 0.0,
+    }
+}
+
+/// Timings of one measured frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameSample {
+    /// Time into the measured part of the run in seconds
+    pub time_secs: f32,
+    /// Wall-clock time since the previous frame in milliseconds
+    pub frame_ms: f32,
+    /// CPU time spent updating and drawing the frame in milliseconds
+    pub cpu_ms: f32,
+    /// GPU time of the SDF pass in milliseconds, None without timestamp support
+    ///
+    /// Read after the frame's fence, so it trails by the frames in flight.
+    pub gpu_ms: Option<f32>,
+}
+
+/// Distribution of one timing over a run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    /// Fastest value
+    pub min: f32,
+    /// Mean value
+    pub avg: f32,
+    /// 95th percentile
+    pub p95: f32,
+    /// 99th percentile
+    pub p99: f32,
+}
+
+impl FrameStats {
+    /// Compute the statistics of some timings
+    ///
+    /// # Returns
+    /// None if there are no timings
+    pub fn from_values(mut values: Vec<f32>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f32::total_cmp);
+        // Nearest-rank percentile
+        let percentile = |p: f32| values[((p * values.len() as f32).ceil() as usize).clamp(1, values.len()) - 1];
+        Some(Self {
+            min: values[0],
+            avg: values.iter().sum::<f32>() / values.len() as f32,
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+        })
+    }
+}
+
+/// Statistics of a finished run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkSummary {
+    /// Number of measured frames
+    pub frames: usize,
+    /// Measured time in seconds
+    pub duration_secs: f32,
+    /// Wall-clock frame times
+    pub frame: Option<FrameStats>,
+    /// CPU times
+    pub cpu: Option<FrameStats>,
+    /// GPU times, None without timestamp support
+    pub gpu: Option<FrameStats>,
+}
+
+impl std::fmt::Display for BenchmarkSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fps = if self.duration_secs > 0.0 { self.frames as f32 / self.duration_secs } else { 0.0 };
+        writeln!(f, "Benchmark: {} frames in {:.1} s ({:.1} FPS)", self.frames, self.duration_secs, fps)?;
+        writeln!(f, "{:<6} {:>9} {:>9} {:>9} {:>9}", "ms", "min", "avg", "p95", "p99")?;
+        for (name, stats) in [("frame", self.frame), ("cpu", self.cpu), ("gpu", self.gpu)] {
+            match stats {
+                Some(s) => writeln!(f, "{:<6} {:>9.3} {:>9.3} {:>9.3} {:>9.3}", name, s.min, s.avg, s.p95, s.p99)?,
+                None => writeln!(f, "{:<6} {:>9}", name, "n/a")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A benchmark in progress
+#[derive(Debug)]
+pub struct BenchmarkRun {
+    /// Length, scene and output of the run
+    settings: BenchmarkSettings,
+    /// Frames still to render before measuring
+    warmup_left: usize,
+    /// Start of the measured part, set by the first measured frame
+    started: Option<Instant>,
+    /// End of the previous frame
+    last_frame: Option<Instant>,
+    /// CPU time of the frame being rendered so far
+    frame_cpu: Duration,
+    /// Measured frames
+    samples: Vec<FrameSample>,
+}
+
+impl BenchmarkRun {
+    /// Start a run
+    pub fn new(settings: BenchmarkSettings) -> Self {
+        info!("Benchmark: {:.1} s of {} after {} warm-up frames", settings.duration_secs, settings.scene.display(), config::benchmark::WARMUP_FRAMES);
+        Self {
+            settings,
+            warmup_left: config::benchmark::WARMUP_FRAMES,
+            started: None,
+            last_frame: None,
+            frame_cpu: Duration::ZERO,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Scene file the run measures
+    pub fn scene(&self) -> &Path {
+        &self.settings.scene
+    }
+
+    /// Time into the measured part of the run in seconds, 0 during the warm-up
+    pub fn elapsed_secs(&self, now: Instant) -> f32 {
+        self.started.map_or(0.0, |started| now.duration_since(started).as_secs_f32())
+    }
+
+    /// Add CPU time spent on the current frame
+    pub fn add_cpu_time(&mut self, time: Duration) {
+        self.frame_cpu += time;
+    }
+
+    /// Record the end of a frame
+    ///
+    /// # Arguments
+    /// * `now` - When the frame was presented
+    /// * `gpu_ms` - Latest GPU time reported by the renderer
+    pub fn end_frame(&mut self, now: Instant, gpu_ms: Option<f32>) {
+        let cpu = std::mem::take(&mut self.frame_cpu);
+        let previous = self.last_frame.replace(now);
+        if self.warmup_left > 0 {
+            self.warmup_left -= 1;
+            return;
+        }
+        let Some(previous) = previous else {
+            return;
+        };
+        let started = *self.started.get_or_insert(previous);
+        self.samples.push(FrameSample {
+            time_secs: now.duration_since(started).as_secs_f32(),
+            frame_ms: now.duration_since(previous).as_secs_f32() * 1000.0,
+            cpu_ms: cpu.as_secs_f32() * 1000.0,
+            gpu_ms,
+        });
+    }
+
+    /// Whether the measured time is up
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.elapsed_secs(now) >= self.settings.duration_secs
+    }
+
+    /// Statistics of the frames recorded so far
+    pub fn summary(&self) -> BenchmarkSummary {
+        let values = |timing: fn(&FrameSample) -> Option<f32>| {
+            FrameStats::from_values(self.samples.iter().filter_map(timing).collect())
+        };
+        BenchmarkSummary {
+            frames: self.samples.len(),
+            duration_secs: self.samples.last().map_or(0.0, |sample| sample.time_secs),
+            frame: values(|sample| Some(sample.frame_ms)),
+            cpu: values(|sample| Some(sample.cpu_ms)),
+            gpu: values(|sample| sample.gpu_ms),
+        }
+    }
+
+    /// Per-frame timings as CSV
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("frame,time_s,frame_ms,cpu_ms,gpu_ms\n");
+        for (index, sample) in self.samples.iter().enumerate() {
+            let gpu = sample.gpu_ms.map(|ms| format!("{:.4}", ms)).unwrap_or_default();
+            let _ = writeln!(csv, "{},{:.4},{:.4},{:.4},{}", index, sample.time_secs, sample.frame_ms, sample.cpu_ms, gpu);
+        }
+        csv
+    }
+
+    /// Write the per-frame CSV and the summary next to it
+    ///
+    /// # Returns
+    /// Path of the CSV file
+    ///
+    /// # Errors
+    /// Returns an error if the output directory or a file cannot be written
+    pub fn write_report(&self) -> Result<PathBuf> {
+        let dir: &Path = &self.settings.output_dir;
+        fs::create_dir_all(dir).context(Subsystem::Io, format!("create benchmark directory {}", dir.display()))?;
+        let stem = Local::now().format("benchmark_%Y-%m-%d_%H-%M-%S").to_string();
+        let csv_path = dir.join(format!("{}.csv", stem));
+        fs::write(&csv_path, self.to_csv()).context(Subsystem::Io, format!("write {}", csv_path.display()))?;
+        let summary_path = dir.join(format!("{}.txt", stem));
+        let summary = format!("Scene: {}\n{}", self.settings.scene.display(), self.summary());
+        fs::write(&summary_path, summary).context(Subsystem::Io, format!("write {}", summary_path.display()))?;
+        Ok(csv_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(BenchmarkSettings::from_args(args(&["--export", "0:1:10"])).unwrap(), None);
+
+        let settings = BenchmarkSettings::from_args(args(&["--benchmark=5", "--benchmark-dir", "out"]))
+            .unwrap()
+            .expect("benchmark requested");
+        assert_eq!(settings.duration_secs, 5.0);
+        assert_eq!(settings.output_dir, PathBuf::from("out"));
+        assert_eq!(settings.scene, PathBuf::from(config::benchmark::SCENE_FILE));
+
+        assert!(BenchmarkSettings::from_args(args(&["--benchmark=-1"])).is_err());
+    }
+
+    #[test]
+    fn test_stats_and_warmup() {
+        let stats = FrameStats::from_values((1..=100).map(|ms| ms as f32).collect()).unwrap();
+        assert_eq!((stats.min, stats.avg, stats.p95, stats.p99), (1.0, 50.5, 95.0, 99.0));
+        assert_eq!(FrameStats::from_values(Vec::new()), None);
+
+        let mut run = BenchmarkRun::new(BenchmarkSettings { duration_secs: 1.0, ..BenchmarkSettings::default() });
+        let start = Instant::now();
+        let frame = Duration::from_millis(10);
+        for index in 0..config::benchmark::WARMUP_FRAMES as u32 + 3 {
+            run.add_cpu_time(Duration::from_millis(4));
+            run.end_frame(start + frame * index, None);
+        }
+        let summary = run.summary();
+        assert_eq!(summary.frames, 3);
+        assert!((summary.frame.unwrap().avg - 10.0).abs() < 1e-3);
+        assert!((summary.cpu.unwrap().avg - 4.0).abs() < 1e-3);
+        assert_eq!(summary.gpu, None);
+        assert_eq!(run.to_csv().lines().count(), 4);
+    }
+
+    #[test]
+    fn test_camera_path_looks_at_the_center() {
+        for time in [0.0, 2.5, 7.0] {
+            let camera = camera_path(time);
+            let forward = camera.forward();
+            let to_center = [
+                config::benchmark::ORBIT_CENTER[0] - camera.origin[0],
+                config::benchmark::ORBIT_CENTER[1] - camera.origin[1],
+                config::benchmark::ORBIT_CENTER[2] - camera.origin[2],
+            ];
+            let length = (to_center[0].powi(2) + to_center[1].powi(2) + to_center[2].powi(2)).sqrt();
+            assert!((forward.x - to_center[0] / length).abs() < 1e-5);
+            assert!((forward.y - to_center[1] / length).abs() < 1e-5);
+            assert!((forward.z - to_center[2] / length).abs() < 1e-5);
+        }
+    }
+}
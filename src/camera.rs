@@ -4,6 +4,9 @@
 //! and projection for 3D rendering, preventing stretching during window resize.
 
 use cgmath::{Vector3, Matrix4, Point3, Rad, Deg, perspective, InnerSpace};
+use serde::{Deserialize, Serialize};
+use crate::config;
+use crate::vulkan::viewport::ViewCamera;
 
 /// Camera structure for 3D rendering with proper aspect ratio handling
 #[derive(Debug, Clone)]
@@ -192,6 +195,239 @@ impl Camera {
     }
 }
 
+/// Perspective viewport camera turned by mouse look
+///
+/// While the right mouse button is held the window captures the pointer and
+/// raw mouse motion turns the camera around its origin. Numpad 5 switches it
+/// to an orthographic projection, whose size the mouse wheel zooms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LookCamera {
+    /// Camera position in world space
+    pub origin: [f32; 3],
+    /// Rotation around the Y axis in radians, 0 looks along +Z
+    pub yaw: f32,
+    /// Angle above the horizon in radians
+    pub pitch: f32,
+    /// Half height of the view in world units when orthographic, 0 for perspective
+    pub ortho_half_height: f32,
+}
+
+impl Default for LookCamera {
+    /// The initial perspective camera of the raymarcher
+    fn default() -> Self {
+        Self {
+            origin: config::rendering::SDF_CAMERA_ORIGIN,
+            yaw: 0.0,
+            pitch: 0.0,
+            ortho_half_height: 0.0,
+        }
+    }
+}
+
+impl LookCamera {
+    /// Turn the camera by a raw mouse motion
+    ///
+    /// # Arguments
+    /// * `dx` - Horizontal motion, positive turns right
+    /// * `dy` - Vertical motion, positive turns down
+    pub fn rotate(&mut self, dx: f32, dy: f32) {
+        let max_pitch = config::camera::MAX_PITCH_DEG.to_radians();
+        self.yaw = (self.yaw + dx * config::camera::LOOK_SENSITIVITY).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch - dy * config::camera::LOOK_SENSITIVITY).clamp(-max_pitch, max_pitch);
+    }
+
+    /// Normalized view direction
+    pub fn forward(&self) -> Vector3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vector3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch)
+    }
+
+    /// Whether the camera uses an orthographic projection
+    pub fn is_orthographic(&self) -> bool {
+        self.ortho_half_height > 0.0
+    }
+
+    /// Switch between perspective and orthographic projection
+    ///
+    /// Like Blender's Numpad 5, the orthographic view starts out the size the
+    /// perspective view has at the depth of the world origin, so things
+    /// around it keep their size on screen.
+    pub fn toggle_projection(&mut self) {
+        if self.is_orthographic() {
+            self.ortho_half_height = 0.0;
+            return;
+        }
+        let depth = -Vector3::from(self.origin).dot(self.forward());
+        let half_height = depth * config::camera::HALF_FOV_DEG.to_radians().tan();
+        self.ortho_half_height = half_height.clamp(config::camera::MIN_ORTHO_HALF_HEIGHT, config::camera::MAX_ORTHO_HALF_HEIGHT);
+    }
+
+    /// Zoom by mouse wheel lines
+    ///
+    /// The orthographic view shrinks or grows; the perspective camera moves
+    /// along its view direction instead.
+    ///
+    /// # Arguments
+    /// * `lines` - Wheel lines, positive zooms in
+    pub fn zoom(&mut self, lines: f32) {
+        if self.is_orthographic() {
+            let half_height = self.ortho_half_height / config::camera::ORTHO_ZOOM_STEP.powf(lines);
+            self.ortho_half_height = half_height.clamp(config::camera::MIN_ORTHO_HALF_HEIGHT, config::camera::MAX_ORTHO_HALF_HEIGHT);
+        } else {
+            let origin = Vector3::from(self.origin) + self.forward() * (lines * config::camera::DOLLY_STEP);
+            self.origin = origin.into();
+        }
+    }
+
+    /// Camera as passed to the shaders
+    pub fn view_camera(&self) -> ViewCamera {
+        let forward = self.forward();
+        ViewCamera {
+            origin: self.origin,
+            forward: [forward.x, forward.y, forward.z],
+            ortho_half_height: self.ortho_half_height,
+            ..ViewCamera::default()
+        }
+    }
+
+    /// Pose between this one and another
+    ///
+    /// The yaw turns the short way around. Between an orthographic and a
+    /// perspective pose the projection switches halfway.
+    ///
+    /// # Arguments
+    /// * `to` - Pose at `t` = 1
+    /// * `t` - Fraction of the way, 0 to 1
+    pub fn lerp(&self, to: &LookCamera, t: f32) -> LookCamera {
+        let mix = |from: f32, to: f32| from + (to - from) * t;
+        let turn = (to.yaw - self.yaw + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        LookCamera {
+            origin: [mix(self.origin[0], to.origin[0]), mix(self.origin[1], to.origin[1]), mix(self.origin[2], to.origin[2])],
+            yaw: (self.yaw + turn * t).rem_euclid(std::f32::consts::TAU),
+            pitch: mix(self.pitch, to.pitch),
+            ortho_half_height: match (self.is_orthographic(), to.is_orthographic()) {
+                (true, true) => mix(self.ortho_half_height, to.ortho_half_height),
+                _ if t < 0.5 => self.ortho_half_height,
+                _ => to.ortho_half_height,
+            },
+        }
+    }
+
+    /// Pose looking the same way with a sphere filling the view
+    ///
+    /// # Arguments
+    /// * `center` - Center of the sphere
+    /// * `radius` - Radius of the sphere
+    pub fn framing(&self, center: Vector3<f32>, radius: f32) -> LookCamera {
+        let half_fov = config::camera::HALF_FOV_DEG.to_radians();
+        let distance = radius.max(config::camera::FRAME_POINT_RADIUS) * config::camera::FRAME_MARGIN / half_fov.sin();
+        let ortho_half_height = match self.is_orthographic() {
+            true => radius.max(config::camera::FRAME_POINT_RADIUS) * config::camera::FRAME_MARGIN,
+            false => 0.0,
+        };
+        LookCamera {
+            origin: (center - self.forward() * distance).into(),
+            ortho_half_height,
+            ..*self
+        }
+    }
+}
+
+/// Pose part of the way between two poses, eased in and out
+///
+/// # Arguments
+/// * `from` - Pose at `t` = 0
+/// * `to` - Pose at `t` = 1
+/// * `t` - Fraction of the time, clamped to 0 to 1
+pub fn tween(from: &LookCamera, to: &LookCamera, t: f32) -> LookCamera {
+    let t = t.clamp(0.0, 1.0);
+    from.lerp(to, t * t * (3.0 - 2.0 * t))
+}
+
+/// A named camera pose saved with the scene
+///
+/// Angles are in degrees, so bookmarks can be written by hand in a scene
+/// file's `[[bookmarks]]` tables.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub origin: [f32; 3],
+    #[serde(default)]
+    pub yaw_deg: f32,
+    #[serde(default)]
+    pub pitch_deg: f32,
+    /// Half height of an orthographic view, None for perspective
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ortho_half_height: Option<f32>,
+}
+
+impl CameraBookmark {
+    /// Bookmark a camera pose
+    pub fn new(name: &str, camera: &LookCamera) -> Self {
+        Self {
+            name: name.to_string(),
+            origin: camera.origin,
+            yaw_deg: camera.yaw.to_degrees(),
+            pitch_deg: camera.pitch.to_degrees(),
+            ortho_half_height: Some(camera.ortho_half_height).filter(|_| camera.is_orthographic()),
+        }
+    }
+
+    /// The bookmarked pose
+    pub fn camera(&self) -> LookCamera {
+        let max_pitch = config::camera::MAX_PITCH_DEG;
+        LookCamera {
+            origin: self.origin,
+            yaw: self.yaw_deg.to_radians().rem_euclid(std::f32::consts::TAU),
+            pitch: self.pitch_deg.clamp(-max_pitch, max_pitch).to_radians(),
+            ortho_half_height: self.ortho_half_height.map_or(0.0, |half_height| {
+                half_height.clamp(config::camera::MIN_ORTHO_HALF_HEIGHT, config::camera::MAX_ORTHO_HALF_HEIGHT)
+            }),
+        }
+    }
+}
+
+/// Smooth move of the camera to another pose, a bookmark or the selection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraTransition {
+    from: LookCamera,
+    to: LookCamera,
+    /// Seconds since the move started
+    elapsed: f32,
+    /// Length of the move in seconds
+    duration: f32,
+}
+
+impl CameraTransition {
+    /// Start a move
+    ///
+    /// # Arguments
+    /// * `from` - Current pose
+    /// * `to` - Pose to arrive at
+    /// * `duration` - Length of the move in seconds
+    pub fn new(from: LookCamera, to: LookCamera, duration: f32) -> Self {
+        Self { from, to, elapsed: 0.0, duration }
+    }
+
+    /// Advance the move by a frame
+    ///
+    /// # Returns
+    /// The pose for this frame, eased in and out
+    pub fn advance(&mut self, delta_time: f32) -> LookCamera {
+        self.elapsed += delta_time;
+        if self.is_finished() {
+            return self.to;
+        }
+        tween(&self.from, &self.to, self.elapsed / self.duration)
+    }
+
+    /// Whether the camera arrived
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
 /// Utility functions for camera calculations
 pub mod utils {
     use super::*;
@@ -225,4 +461,81 @@ pub mod utils {
     ) -> Matrix4<f32> {
         Matrix4::look_at_rh(eye, target, up)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_look_camera_turns_and_clamps_pitch() {
+        let mut camera = LookCamera::default();
+        assert_eq!(camera.view_camera(), ViewCamera::default());
+
+        // Moving the mouse right turns towards +X, the camera's right
+        camera.rotate(std::f32::consts::FRAC_PI_2 / config::camera::LOOK_SENSITIVITY, 0.0);
+        assert!((camera.forward() - Vector3::unit_x()).magnitude() < 1e-5);
+
+        camera.rotate(0.0, -1.0e6);
+        assert_eq!(camera.pitch, config::camera::MAX_PITCH_DEG.to_radians());
+        assert!(camera.forward().y > 0.99);
+    }
+
+    #[test]
+    fn test_bookmark_transition_takes_the_short_way() {
+        let from = LookCamera { origin: [0.0; 3], yaw: 350f32.to_radians(), ..LookCamera::default() };
+        let bookmark = CameraBookmark { name: "Side".to_string(), origin: [2.0, 0.0, 0.0], yaw_deg: 10.0, pitch_deg: 120.0, ortho_half_height: None };
+        let to = bookmark.camera();
+        assert_eq!(to.pitch, config::camera::MAX_PITCH_DEG.to_radians());
+        assert!((CameraBookmark::new("Side", &to).yaw_deg - 10.0).abs() < 1e-4);
+
+        // Halfway through, the yaw crossed 0 instead of turning back through 180
+        let mut transition = CameraTransition::new(from, to, 1.0);
+        let halfway = transition.advance(0.5);
+        assert!(halfway.yaw < 1e-4 || halfway.yaw > std::f32::consts::TAU - 1e-4, "{}", halfway.yaw);
+        assert!((halfway.origin[0] - 1.0).abs() < 1e-5);
+        assert!(!transition.is_finished());
+        assert_eq!(transition.advance(0.6), to);
+        assert!(transition.is_finished());
+    }
+
+    #[test]
+    fn test_framing_keeps_the_view_direction() {
+        let camera = LookCamera { origin: [5.0, 5.0, 5.0], yaw: 0.3, pitch: -0.2, ortho_half_height: 0.0 };
+        let center = Vector3::new(1.0, 0.0, -1.0);
+        let framed = camera.framing(center, 2.0);
+        assert_eq!((framed.yaw, framed.pitch), (camera.yaw, camera.pitch));
+
+        // The sphere sits on the view axis, just inside the field of view
+        let offset = center - Vector3::from(framed.origin);
+        assert!((offset.normalize() - camera.forward()).magnitude() < 1e-5);
+        let half_fov = config::camera::HALF_FOV_DEG.to_radians();
+        assert!(2.0 / offset.magnitude() < half_fov.sin());
+
+        assert_eq!(tween(&camera, &framed, -1.0), camera);
+        assert_eq!(tween(&camera, &framed, 2.0), camera.lerp(&framed, 1.0));
+    }
+
+    #[test]
+    fn test_orthographic_toggle_and_zoom() {
+        // The default camera looks at the world origin from 2 units away
+        let mut camera = LookCamera::default();
+        camera.toggle_projection();
+        assert!(camera.is_orthographic());
+        assert!((camera.ortho_half_height - 2.0).abs() < 1e-5);
+        assert_eq!(camera.view_camera().ortho_half_height, camera.ortho_half_height);
+
+        camera.zoom(1.0);
+        assert!((camera.ortho_half_height - 2.0 / config::camera::ORTHO_ZOOM_STEP).abs() < 1e-5);
+        camera.zoom(-1.0e6);
+        assert_eq!(camera.ortho_half_height, config::camera::MAX_ORTHO_HALF_HEIGHT);
+        assert_eq!(camera.origin, LookCamera::default().origin);
+        assert_eq!(CameraBookmark::new("Ortho", &camera).camera(), camera);
+
+        camera.toggle_projection();
+        assert!(!camera.is_orthographic());
+        camera.zoom(2.0);
+        assert!((camera.origin[2] - (-2.0 + 2.0 * config::camera::DOLLY_STEP)).abs() < 1e-5);
+        assert_eq!(CameraBookmark::new("Perspective", &camera).ortho_half_height, None);
+    }
+}
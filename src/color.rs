@@ -0,0 +1,96 @@
+//! Color space convention
+//!
+//! Colors are in one of two encodings, and every conversion between them
+//! goes through the helpers in this module:
+//!
+//! - **Linear**: everything that is shaded, lit or blended by the renderer.
+//!   Material and light colors (`SDFMaterial`, `Light`, scene files),
+//!   the sun tint, viewport clear colors, debug line colors and
+//!   the HDR scene target are linear Rec.709.
+//! - **sRGB**: colors meant for the UI and for 8-bit images. ImGui style and
+//!   theme colors, label and toast colors, PNG files and frame exports are
+//!   sRGB encoded.
+//!
+//! The swapchain uses an `_SRGB` format when the surface offers one, so the
+//! hardware encodes the linear values written to it. Without one, the
+//! composite, ImGui, outline and debug line shaders apply the curve
+//! themselves with `encodeOutput` from `shaders/include/color.glsl`
+//! (`DisplayOutput::SdrUnorm`). ImGui vertex colors are decoded to linear in
+//! `shaders/imgui.frag`; a linear color shown in the UI, e.g. as a swatch,
+//! is encoded with `linear_to_srgb_rgba` first.
+
+use ash::vk;
+
+/// sRGB transfer function of one linear channel in [0, 1]
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse sRGB transfer function of one encoded channel in [0, 1]
+pub fn srgb_to_linear(encoded: f32) -> f32 {
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a linear channel as an 8-bit sRGB value, clamping to [0, 1]
+pub fn linear_to_srgb8(linear: f32) -> u8 {
+    (linear_to_srgb(linear.clamp(0.0, 1.0)) * 255.0).round() as u8
+}
+
+/// Encode a linear color for the UI; alpha is not gamma encoded
+pub fn linear_to_srgb_rgba(rgba: [f32; 4]) -> [f32; 4] {
+    [linear_to_srgb(rgba[0]), linear_to_srgb(rgba[1]), linear_to_srgb(rgba[2]), rgba[3]]
+}
+
+/// Decode a UI color to linear; alpha is not gamma encoded
+pub fn srgb_to_linear_rgba(rgba: [f32; 4]) -> [f32; 4] {
+    [srgb_to_linear(rgba[0]), srgb_to_linear(rgba[1]), srgb_to_linear(rgba[2]), rgba[3]]
+}
+
+/// Check whether the hardware applies the sRGB curve when writing or sampling a format
+pub fn is_srgb_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::R8G8B8A8_SRGB
+            | vk::Format::B8G8R8A8_SRGB
+            | vk::Format::A8B8G8R8_SRGB_PACK32
+            | vk::Format::R8G8B8_SRGB
+            | vk::Format::B8G8R8_SRGB
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_functions_round_trip() {
+        for i in 0..=255 {
+            let encoded = i as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(encoded)) - encoded).abs() < 1e-5);
+        }
+        // Mid gray in sRGB is about 21% linear
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+        assert_eq!(linear_to_srgb8(srgb_to_linear(128.0 / 255.0)), 128);
+        assert_eq!(linear_to_srgb8(2.0), 255);
+
+        // Alpha is coverage, not a color channel
+        let [r, g, _, a] = linear_to_srgb_rgba([0.214, 0.0, 0.0, 0.5]);
+        assert!((r - 0.5).abs() < 1e-3);
+        assert_eq!((g, a), (0.0, 0.5));
+    }
+
+    #[test]
+    fn test_srgb_formats() {
+        assert!(is_srgb_format(vk::Format::B8G8R8A8_SRGB));
+        assert!(!is_srgb_format(vk::Format::B8G8R8A8_UNORM));
+        assert!(!is_srgb_format(vk::Format::R16G16B16A16_SFLOAT));
+    }
+}
@@ -14,6 +14,12 @@ pub mod window {
     /// Window title
     pub const TITLE: &str = "Vulkan App - ECS";
     
+    /// Show the scene, frame rate and GPU in the window title
+    pub const SHOW_STATUS: bool = true;
+    
+    /// Milliseconds between window title status updates, the frame rate is averaged over them
+    pub const STATUS_INTERVAL_MS: u64 = 250;
+    
     /// Minimum window width
     pub const MIN_WIDTH: u32 = 600;
     
@@ -28,6 +34,10 @@ pub mod windowed_fullscreen {
     
     /// Offset from screen edges to avoid overlapping with taskbar/dock
     pub const SCREEN_EDGE_OFFSET: u32 = 0;
+    
+    /// Let winit make the window borderless fullscreen; false removes the
+    /// decorations and resizes the window over the monitor by hand
+    pub const WINIT_BORDERLESS: bool = true;
 }
 
 /// Vulkan configuration
@@ -50,6 +60,9 @@ pub mod vulkan {
     /// API version
     pub const API_VERSION: u32 = ash::vk::make_api_version(0, 1, 0, 0);
     
+    /// Newest API version requested when the loader supports it (`API_VERSION` is the minimum)
+    pub const TARGET_API_VERSION: u32 = ash::vk::make_api_version(0, 1, 3, 0);
+    
     /// Enable validation layers in debug builds
     #[cfg(debug_assertions)]
     pub const ENABLE_VALIDATION_LAYERS: bool = true;
@@ -63,14 +76,31 @@ pub mod vulkan {
     #[allow(dead_code)]
     pub const VALIDATION_LAYERS: &[&str] = &["VK_LAYER_KHRONOS_validation"];
     
+    /// Use dynamic rendering instead of render pass objects when the device supports it
+    pub const PREFER_DYNAMIC_RENDERING: bool = true;
+    
+    /// Keep textures in one descriptor indexing array when the device supports it
+    pub const PREFER_BINDLESS_TEXTURES: bool = true;
+    
+    /// Slots of the bindless texture table, lowered to the device's update-after-bind
+    /// sampled image limit (devices with descriptor indexing usually allow far more)
+    pub const MAX_BINDLESS_TEXTURES: u32 = 4096;
+    
     /// Device extensions required
     pub const DEVICE_EXTENSIONS: &[&str] = &["VK_KHR_swapchain"];
     
+    /// Device extension reporting heap usage and budgets, enabled when supported
+    pub const MEMORY_BUDGET_EXTENSION: &str = "VK_EXT_memory_budget";
+    
+    /// Physical device to use (index or name substring), overridden by `--gpu`
+    pub const GPU_OVERRIDE: Option<&str> = None;
+    
 }
 
 /// Rendering configuration
 pub mod rendering {
-    /// Clear color for the framebuffer (R, G, B, A)
+    /// Default clear color of new viewports and the clear color of the swapchain
+    /// pass (linear R, G, B, A, see the `color` module)
     pub const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
     
     /// Default line width for rasterization
@@ -85,6 +115,46 @@ pub mod rendering {
     
     /// Front face winding order
     pub const FRONT_FACE: ash::vk::FrontFace = ash::vk::FrontFace::CLOCKWISE;
+    
+    /// Maximum number of SDF shapes uploaded to the scene buffer (must match MAX_SHAPES in sdf.frag)
+    pub const MAX_SDF_SHAPES: usize = 32;
+    
+    /// Maximum number of lights uploaded to the scene buffer (must match MAX_LIGHTS in sdf.frag)
+    pub const MAX_SDF_LIGHTS: usize = 8;
+    
+    /// Maximum number of `Text3D` entities uploaded to the scene buffer (must match MAX_TEXTS in sdf.frag)
+    pub const MAX_SDF_TEXTS: usize = 8;
+    
+    /// Maximum number of glyphs of all texts together (must match MAX_GLYPHS in sdf.frag)
+    pub const MAX_SDF_GLYPHS: usize = 256;
+    
+    /// Maximum number of modifiers of all shapes together (must match MAX_MODIFIERS in sdf.frag)
+    pub const MAX_SDF_MODIFIERS: usize = 64;
+    
+    /// Ray origin of the SDF camera (must match `ro` in sdf.frag)
+    pub const SDF_CAMERA_ORIGIN: [f32; 3] = [0.0, 0.0, -2.0];
+    
+    /// Depth attachment format of the SDF and mesh render passes
+    pub const DEPTH_FORMAT: ash::vk::Format = ash::vk::Format::D32_SFLOAT;
+    
+    /// Color format of the offscreen SDF target; linear HDR, tonemapped by the composite pass
+    pub const SCENE_FORMAT: ash::vk::Format = ash::vk::Format::R16G16B16A16_SFLOAT;
+    
+    /// G-buffer normal format of the scene pass: world normal * 0.5 + 0.5, alpha 1 on surfaces
+    pub const NORMAL_FORMAT: ash::vk::Format = ash::vk::Format::A2B10G10R10_UNORM_PACK32;
+    
+    /// G-buffer linear depth format of the scene pass: view distance, 0 where nothing was hit;
+    /// half float because blending must be supported on every scene pass attachment
+    pub const LINEAR_DEPTH_FORMAT: ash::vk::Format = ash::vk::Format::R16_SFLOAT;
+    
+    /// G-buffer selection mask format of the scene pass: 1 on the selected entity's surfaces
+    pub const SELECTION_FORMAT: ash::vk::Format = ash::vk::Format::R8_UNORM;
+    
+    /// Use an HDR swapchain (scRGB or HDR10) when the surface offers one
+    pub const PREFER_HDR_OUTPUT: bool = true;
+    
+    /// Brightness of tonemapped white (and the HUD) on HDR displays, in nits
+    pub const HDR_PAPER_WHITE_NITS: f32 = 200.0;
 }
 
 /// Debug configuration
@@ -95,6 +165,21 @@ pub mod debug {
     /// Minimum log level to display
     pub const LOG_LEVEL: log::LevelFilter = log::LevelFilter::Info;
 
+    /// Also write the log to a file
+    pub const ENABLE_FILE_LOGGING: bool = true;
+
+    /// Log file path; rotated files get a numeric suffix (`.1` is the newest)
+    pub const LOG_FILE: &str = "logs/vulkan-app.log";
+
+    /// Size at which the log file is rotated (bytes)
+    pub const LOG_FILE_MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+    /// Number of rotated log files kept
+    pub const LOG_FILE_ROTATIONS: usize = 3;
+
+    /// Number of log records kept in memory for the HUD console
+    pub const CONSOLE_CAPACITY: usize = 2000;
+
     /// Enable verbose Vulkan debug messages
     #[allow(dead_code)] // For future verbose debugging
     pub const ENABLE_VERBOSE_VULKAN_DEBUG: bool = true;
@@ -132,6 +217,18 @@ pub mod debug {
     pub const ENABLE_RENDER_PASS_DEBUGGING: bool = cfg!(debug_assertions);
 }
 
+/// Crash reports written by `platform::crash`
+pub mod crash {
+    /// Directory holding one timestamped folder per crash
+    pub const CRASH_DIR: &str = "crashes";
+
+    /// Number of recent log records saved with a crash
+    pub const LOG_RECORDS: usize = 500;
+
+    /// Point the user to the crash folder in a native message box
+    pub const SHOW_MESSAGE_BOX: bool = true;
+}
+
 /// ECS configuration
 pub mod ecs {
     /// Maximum number of entities that can be created
@@ -147,6 +244,63 @@ pub mod ecs {
     pub const ENABLE_SYSTEM_PROFILING: bool = false;
 }
 
+/// Scene loading
+pub mod scene {
+    /// Scene file loaded at startup and re-loaded when it changes on disk;
+    /// the built-in scene is used when it does not exist
+    pub const SCENE_FILE: &str = "scenes/default.toml";
+}
+
+/// Scene auto-save and crash recovery
+pub mod autosave {
+    /// Whether the scene is auto-saved by default
+    pub const ENABLED: bool = true;
+
+    /// Default seconds between auto-saves
+    pub const INTERVAL_SECS: u32 = 30;
+
+    /// Range of the interval offered in the Settings window
+    pub const MIN_INTERVAL_SECS: u32 = 5;
+    pub const MAX_INTERVAL_SECS: u32 = 600;
+
+    /// Folder in the system temp directory holding the auto-save
+    pub const DIR_NAME: &str = "vulkan-app-autosave";
+
+    /// Latest auto-save of the running session, named `autosave-<pid>.toml`
+    pub const SCENE_FILE: &str = "autosave.toml";
+
+    /// Last auto-save of a session that did not shut down cleanly, until it is restored or discarded
+    pub const RECOVERED_FILE: &str = "recovered.toml";
+
+    /// Marker locked while a session runs, named `session-<pid>.lock` and removed on a clean shutdown
+    pub const SESSION_FILE: &str = "session.lock";
+}
+
+/// Projects (`project.toml`)
+pub mod project {
+    /// Project file in the project folder
+    pub const FILE_NAME: &str = "project.toml";
+
+    /// Texture directories of a project that does not list any
+    pub const TEXTURE_DIRS: &[&str] = &["textures"];
+
+    /// File remembering recently opened projects
+    pub const RECENT_FILE: &str = "recent_projects.toml";
+
+    /// Recent projects offered in the Project panel
+    pub const MAX_RECENT: usize = 8;
+}
+
+/// Rhai scripting
+pub mod scripting {
+    /// Folder whose `.rhai` scripts are loaded at startup and re-loaded when they change
+    pub const SCRIPTS_DIR: &str = "scripts";
+    
+    /// Operations a single script call may run before it is aborted,
+    /// so an endless loop cannot freeze the frame
+    pub const MAX_OPERATIONS: u64 = 1_000_000;
+}
+
 /// Shader configuration
 pub mod shader {
     /// Shader entry point name
@@ -174,6 +328,21 @@ pub mod shader {
     pub const SDF_FRAGMENT_SHADER: &str = "shaders/sdf.frag";
     pub const IMGUI_VERTEX_SHADER: &str = "shaders/imgui.vert";
     pub const IMGUI_FRAGMENT_SHADER: &str = "shaders/imgui.frag";
+    pub const COMPOSITE_VERTEX_SHADER: &str = "shaders/composite.vert";
+    pub const COMPOSITE_FRAGMENT_SHADER: &str = "shaders/composite.frag";
+    pub const DEBUG_LINE_VERTEX_SHADER: &str = "shaders/debug_line.vert";
+    pub const DEBUG_LINE_FRAGMENT_SHADER: &str = "shaders/debug_line.frag";
+    pub const MESH_VERTEX_SHADER: &str = "shaders/mesh.vert";
+    pub const MESH_FRAGMENT_SHADER: &str = "shaders/mesh.frag";
+    pub const MESH_SHADOW_VERTEX_SHADER: &str = "shaders/mesh_shadow.vert";
+    pub const PARTICLE_COMPUTE_SHADER: &str = "shaders/particles.comp";
+    pub const PARTICLE_VERTEX_SHADER: &str = "shaders/particles.vert";
+    pub const PARTICLE_FRAGMENT_SHADER: &str = "shaders/particles.frag";
+    pub const OUTLINE_FRAGMENT_SHADER: &str = "shaders/outline.frag";
+    
+    /// Directory searched for `#include <file>`, and for `#include "file"`
+    /// not found next to the including file
+    pub const INCLUDE_DIR: &str = "shaders/include";
     
     /// Preload commonly used shaders on startup
     pub const PRELOAD_SHADERS: bool = true;
@@ -187,11 +356,14 @@ pub mod hot_reload {
     /// Shader directory to watch for changes
     pub const SHADER_DIR: &str = "shaders";
     
-    /// Debounce time for file changes (milliseconds)
-    pub const DEBOUNCE_MS: u64 = 500;
+    /// Further directories watched for shader changes, e.g. shared include files
+    pub const INCLUDE_DIRS: &[&str] = &[];
+    
+    /// Quiet time after the last event of a file before it is reloaded (milliseconds)
+    pub const DEBOUNCE_MS: u64 = 200;
     
     /// File extensions to watch for shader changes
-    pub const WATCH_EXTENSIONS: &[&str] = &["vert", "frag", "geom", "comp", "tesc", "tese"];
+    pub const WATCH_EXTENSIONS: &[&str] = &["vert", "frag", "geom", "comp", "tesc", "tese", "glsl"];
     
     /// Enable hot reload for vertex shaders
     pub const RELOAD_VERTEX_SHADERS: bool = true;
@@ -208,11 +380,662 @@ pub mod hot_reload {
     /// Enable hot reload for tessellation shaders
     pub const RELOAD_TESSELLATION_SHADERS: bool = true;
     
+    /// Enable hot reload for `.glsl` include files
+    pub const RELOAD_INCLUDE_FILES: bool = true;
+    
     /// Log shader reload events
     pub const LOG_RELOAD_EVENTS: bool = true;
     
+    /// Validate reloaded pipelines with an offscreen test draw before swapping them in
+    pub const SAFE_MODE: bool = true;
+    
+    /// Size of the offscreen validation target in pixels
+    pub const VALIDATION_TARGET_SIZE: u32 = 4;
+    
+    /// Maximum time to wait for the validation draw (milliseconds)
+    pub const VALIDATION_TIMEOUT_MS: u64 = 1000;
+    
   }
 
+/// Raymarch quality tiers: (max steps, surface epsilon, max distance)
+pub mod raymarch {
+    /// Low quality tier
+    pub const LOW: (u32, f32, f32) = (48, 0.004, 8.0);
+
+    /// Medium quality tier (matches the shader defaults)
+    pub const MEDIUM: (u32, f32, f32) = (100, 0.001, 10.0);
+
+    /// High quality tier
+    pub const HIGH: (u32, f32, f32) = (200, 0.0005, 20.0);
+
+    /// Ultra quality tier
+    pub const ULTRA: (u32, f32, f32) = (400, 0.0001, 40.0);
+}
+
+/// Optional SDF shading features (shader variants)
+pub mod shader_features {
+    /// Enable soft shadows by default
+    pub const SOFT_SHADOWS: bool = true;
+
+    /// Enable ambient occlusion by default
+    pub const AMBIENT_OCCLUSION: bool = true;
+
+    /// Enable cone traced global illumination by default
+    pub const CONE_GI: bool = false;
+
+    /// Enable traced reflections and refraction by default
+    pub const REFLECTIONS: bool = false;
+
+    /// Weight of each new GPU time in the smoothed cost of a variant
+    pub const COST_SMOOTHING: f32 = 0.05;
+}
+
+/// Reflective and refractive SDF materials
+pub mod materials {
+    /// Index of refraction of new materials (about that of glass)
+    pub const DEFAULT_IOR: f32 = 1.5;
+
+    /// Range of the index of refraction in the materials panel
+    pub const IOR_RANGE: (f32, f32) = (1.0, 3.0);
+}
+
+/// Live shader parameters (the `Params` block of the SDF shader)
+pub mod shader_params {
+    /// Uniform block whose members are edited in the Shader Parameters panel
+    pub const BLOCK_NAME: &str = "Params";
+
+    /// Binding of the block in descriptor set 0
+    pub const BINDING: u32 = 2;
+
+    /// Size of the uniform buffer behind the block; larger blocks are cut off
+    pub const MAX_BLOCK_SIZE: usize = 256;
+
+    /// Appended to the shader file name for the file the values are saved in
+    pub const FILE_SUFFIX: &str = ".params.toml";
+
+    /// Slider range of a float parameter without a `@slider(min, max)` annotation
+    pub const FLOAT_RANGE: (f32, f32) = (0.0, 1.0);
+
+    /// Slider range of an int parameter without a `@slider(min, max)` annotation
+    pub const INT_RANGE: (i32, i32) = (0, 16);
+
+    /// Change per pixel dragged of a `@drag` parameter without a speed
+    pub const DRAG_SPEED: f32 = 0.01;
+}
+
+/// Dynamic resolution scaling for the SDF pass
+pub mod resolution_scaling {
+    /// Enable automatic resolution scaling
+    pub const ENABLED: bool = true;
+
+    /// Minimum render scale (fraction of swapchain size)
+    pub const MIN_SCALE: f32 = 0.5;
+
+    /// Maximum render scale (fraction of swapchain size)
+    pub const MAX_SCALE: f32 = 1.0;
+
+    /// Target GPU time for the SDF pass in milliseconds
+    pub const TARGET_FRAME_MS: f32 = 12.0;
+
+    /// Scale change applied per adjustment
+    pub const ADJUST_STEP: f32 = 0.05;
+
+    /// Relative deadband around the target before adjusting
+    pub const HYSTERESIS: f32 = 0.1;
+
+    /// Smallest size of the offscreen SDF target relative to the window
+    pub const MIN_RENDER_SCALE: f32 = 0.25;
+
+    /// Largest size of the offscreen SDF target relative to the window (supersampling)
+    pub const MAX_RENDER_SCALE: f32 = 2.0;
+
+    /// Size of the offscreen SDF target relative to the window at startup
+    pub const DEFAULT_RENDER_SCALE: f32 = 1.0;
+}
+
+/// Environment configuration (sun and sky)
+pub mod environment {
+    /// Default sun azimuth in degrees
+    pub const DEFAULT_SUN_AZIMUTH: f32 = 45.0;
+
+    /// Default sun elevation in degrees
+    pub const DEFAULT_SUN_ELEVATION: f32 = 35.0;
+
+    /// Default time of day in hours
+    pub const DEFAULT_TIME_OF_DAY: f32 = 10.0;
+
+    /// Default sun intensity
+    pub const DEFAULT_SUN_INTENSITY: f32 = 1.0;
+
+    /// Sun elevation at noon when driven by time of day (degrees)
+    pub const MAX_SUN_ELEVATION: f32 = 70.0;
+
+    /// Sun color when high in the sky
+    pub const NOON_SUN_COLOR: [f32; 3] = [1.0, 0.97, 0.92];
+
+    /// Sun color close to the horizon
+    pub const HORIZON_SUN_COLOR: [f32; 3] = [1.0, 0.5, 0.2];
+
+    /// Colors at the top and bottom of the gradient background (linear RGB)
+    pub const DEFAULT_GRADIENT_TOP: [f32; 3] = [0.18, 0.3, 0.55];
+    pub const DEFAULT_GRADIENT_BOTTOM: [f32; 3] = [0.6, 0.6, 0.65];
+}
+
+/// Debug line drawing (bounds and wireframes)
+pub mod debug_draw {
+    /// Maximum number of line vertices drawn per frame
+    pub const MAX_LINE_VERTICES: usize = 16384;
+
+    /// Number of segments used for circles
+    pub const CIRCLE_SEGMENTS: usize = 32;
+
+    /// Color of bounding boxes (R, G, B, A)
+    pub const BOUNDS_COLOR: [f32; 4] = [1.0, 0.8, 0.1, 1.0];
+
+    /// Color of wireframes (R, G, B, A)
+    pub const WIREFRAME_COLOR: [f32; 4] = [0.2, 0.9, 1.0, 1.0];
+
+    /// Half size of the square drawn for infinite planes
+    pub const PLANE_EXTENT: f32 = 2.0;
+}
+
+/// Rigid body physics
+pub mod physics {
+    /// Length of one simulation step in seconds
+    pub const TIMESTEP: f32 = 1.0 / 60.0;
+
+    /// Maximum steps per frame; slower frames drop simulation time instead
+    /// of falling further behind
+    pub const MAX_STEPS_PER_FRAME: u32 = 4;
+
+    /// Gravity when enabled (m/s²)
+    pub const GRAVITY: [f32; 3] = [0.0, -9.81, 0.0];
+
+    /// Dynamic bodies falling below this height are despawned
+    pub const DESPAWN_HEIGHT: f32 = -50.0;
+
+    /// Radius of spheres thrown from the HUD
+    pub const THROW_RADIUS: f32 = 0.2;
+
+    /// Speed of spheres thrown from the HUD (m/s)
+    pub const THROW_SPEED: f32 = 8.0;
+}
+
+/// GPU particles
+pub mod particles {
+    /// Particles in the storage buffer; the oldest are replaced when it is full
+    pub const CAPACITY: u32 = 65536;
+
+    /// Emitters simulated per frame; further ones are skipped
+    pub const MAX_EMITTERS: usize = 16;
+
+    /// Invocations per compute workgroup (must match particles.comp)
+    pub const WORKGROUP_SIZE: u32 = 64;
+
+    /// Most particles a single emitter spawns in one frame
+    pub const MAX_SPAWN_PER_FRAME: u32 = 4096;
+}
+
+/// 3D text drawn from the MSDF font atlas, see `vulkan::text`
+pub mod text {
+    /// Fonts tried in order for texts that name none, ones the platform ships
+    #[cfg(target_os = "windows")]
+    pub const DEFAULT_FONTS: &[&str] = &["C:/Windows/Fonts/arial.ttf", "C:/Windows/Fonts/segoeui.ttf"];
+    #[cfg(target_os = "macos")]
+    pub const DEFAULT_FONTS: &[&str] = &["/System/Library/Fonts/Supplemental/Arial.ttf", "/Library/Fonts/Arial.ttf"];
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    pub const DEFAULT_FONTS: &[&str] = &[
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans.ttf",
+        "/usr/share/fonts/TTF/DejaVuSans.ttf",
+        "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+        "/usr/share/fonts/liberation-sans/LiberationSans-Regular.ttf",
+    ];
+
+    /// Fonts the atlas holds at once, one page each
+    pub const MAX_FONTS: usize = 4;
+
+    /// Pixels per em of the glyphs in the atlas
+    pub const EM_PIXELS: f32 = 32.0;
+
+    /// Pixels between the outline and a saturated distance
+    pub const RANGE_PIXELS: f32 = 4.0;
+
+    /// Size of the atlas cell of one glyph in pixels
+    pub const CELL_PIXELS: usize = 48;
+
+    /// Atlas cells per row
+    pub const ATLAS_COLUMNS: usize = 16;
+
+    /// Characters in the atlas: printable ASCII and Russian
+    pub const CHARSET: &[(char, char)] = &[(' ', '~'), ('Ё', 'Ё'), ('А', 'я'), ('ё', 'ё')];
+
+    /// Binding of the atlas in the SDF shader's descriptor set (must match sdf.frag)
+    pub const ATLAS_BINDING: u32 = 3;
+}
+
+/// Procedural noise shared by the shaders and the CPU (see the `noise` module)
+pub mod noise {
+    /// Seed of a new scene's noise
+    pub const DEFAULT_SEED: u32 = 0;
+
+    /// Scale of a new scene's noise, multiplying every noise position
+    pub const DEFAULT_SCALE: f32 = 1.0;
+
+    /// Octaves of the noise modifier's fractal noise (must match NOISE_OCTAVES in sdf.frag)
+    pub const MODIFIER_OCTAVES: u32 = 4;
+
+    /// Slope of that fractal noise per unit of frequency, rarely exceeded; the
+    /// noise modifier slows the march down by it (must match NOISE_MAX_SLOPE in sdf.frag)
+    pub const MODIFIER_MAX_SLOPE: f32 = 8.0;
+}
+
+/// Mouse look of the perspective camera
+pub mod camera {
+    /// Radians the camera turns per unit of raw mouse motion
+    pub const LOOK_SENSITIVITY: f32 = 0.003;
+
+    /// Largest angle above or below the horizon in degrees
+    pub const MAX_PITCH_DEG: f32 = 89.0;
+
+    /// Seconds the camera takes to move to a bookmark or frame the selection, 0 to jump
+    pub const CAMERA_TRANSITION_SECS: f32 = 0.6;
+
+    /// Longest camera move offered in the Settings window
+    pub const MAX_CAMERA_TRANSITION_SECS: f32 = 5.0;
+
+    /// Bookmarks reached with the number keys 1 to 9
+    pub const BOOKMARK_KEYS: usize = 9;
+
+    /// Half the vertical field of view of the perspective camera in degrees,
+    /// must match the ray setup in sdf.frag
+    pub const HALF_FOV_DEG: f32 = 45.0;
+
+    /// Room left around the selection when framing it, 1 fills the view
+    pub const FRAME_MARGIN: f32 = 1.2;
+
+    /// Radius framed around lights and other entities without a size
+    pub const FRAME_POINT_RADIUS: f32 = 0.5;
+
+    /// Factor the orthographic view shrinks by per mouse wheel line
+    pub const ORTHO_ZOOM_STEP: f32 = 1.1;
+
+    /// Smallest and largest half height of the orthographic view in world units
+    pub const MIN_ORTHO_HALF_HEIGHT: f32 = 0.05;
+    pub const MAX_ORTHO_HALF_HEIGHT: f32 = 100.0;
+
+    /// World units the perspective camera moves per mouse wheel line
+    pub const DOLLY_STEP: f32 = 0.25;
+}
+
+/// Multi-viewport layout
+pub mod viewports {
+    /// Start in the 2x2 quad view instead of a single perspective view
+    pub const QUAD_VIEW_BY_DEFAULT: bool = false;
+
+    /// Distance of the orthographic axis cameras from the origin
+    pub const ORTHO_DISTANCE: f32 = 5.0;
+
+    /// Half height of the orthographic views in world units
+    pub const ORTHO_HALF_HEIGHT: f32 = 1.5;
+}
+
+/// Render layers that entities are put on and viewports show or hide
+pub mod layers {
+    /// Number of layers, at most 24 (the mask travels as a float push constant)
+    pub const COUNT: u32 = 8;
+
+    /// Mask with every layer set
+    pub const ALL: u32 = (1 << COUNT) - 1;
+
+    /// Mask of entities that name no layers: the first layer
+    pub const DEFAULT: u32 = 1;
+}
+
+/// Reference grid and world axes overlay
+pub mod grid {
+    /// Draw the grid and axes at startup
+    pub const SHOW_BY_DEFAULT: bool = true;
+
+    /// Default world units between grid lines
+    pub const DEFAULT_SPACING: f32 = 1.0;
+
+    /// Default distance from the camera at which the grid has faded out
+    pub const DEFAULT_FADE_DISTANCE: f32 = 30.0;
+
+    /// Largest fade distance offered in the viewport panel
+    pub const MAX_FADE_DISTANCE: f32 = 200.0;
+}
+
+/// HUD error notifications
+pub mod notifications {
+    /// Seconds a toast stays on screen
+    pub const TOAST_DURATION_SECS: f32 = 5.0;
+
+    /// Maximum number of toasts shown at once (oldest are dropped)
+    pub const MAX_TOASTS: usize = 5;
+}
+
+/// Frame budget guard for expensive HUD panels
+pub mod frame_budget {
+    /// Enable the guard
+    pub const ENABLED: bool = true;
+
+    /// Frame time above which a frame counts as over budget (milliseconds)
+    pub const THRESHOLD_MS: f32 = 50.0;
+
+    /// Seconds over budget before heavy panels are hidden
+    pub const TRIP_AFTER_SECS: f32 = 3.0;
+
+    /// Seconds back under budget before hidden panels are restored
+    pub const RECOVER_AFTER_SECS: f32 = 5.0;
+}
+
+/// HUD docking and layout persistence
+pub mod hud_layout {
+    /// Let HUD panels dock around the viewport
+    pub const ENABLE_DOCKING: bool = true;
+
+    /// ImGui window positions, sizes and dock layout
+    pub const IMGUI_INI_FILE: &str = "imgui.ini";
+
+    /// Open/closed state of the HUD panels
+    pub const PANELS_FILE: &str = "hud_panels.ini";
+
+    /// Toolbar groups and buttons, reloaded when saved while hot reload is on
+    pub const TOOLBAR_FILE: &str = "hud.toml";
+
+    /// Height of a toolbar along the top or bottom edge, in logical pixels
+    pub const TOOLBAR_HEIGHT: f32 = 60.0;
+
+    /// Width of a toolbar along the left or right edge, in logical pixels
+    pub const TOOLBAR_SIDE_WIDTH: f32 = 150.0;
+}
+
+/// HUD themes
+pub mod hud_theme {
+    /// Directory of custom theme files (`<name>.toml`)
+    pub const THEME_DIR: &str = "themes";
+
+    /// Theme applied at startup: a preset name ("Dark", "Light", "High Contrast")
+    /// or the file stem of a custom theme
+    pub const DEFAULT_THEME: &str = "Dark";
+}
+
+/// HUD fonts
+pub mod hud_fonts {
+    /// TTF/OTF file for the HUD text, None for ImGui's built-in font
+    pub const FONT_FILE: Option<&str> = None;
+
+    /// Size of the default HUD font in pixels
+    pub const FONT_SIZE: f32 = 16.0;
+
+    /// Additional sizes baked into the atlas (small print, headings)
+    pub const EXTRA_SIZES: &[f32] = &[13.0, 20.0, 24.0];
+
+    /// Glyph ranges loaded from the font file besides Latin, e.g. "Cyrillic", "Japanese"
+    pub const GLYPH_RANGES: &[&str] = &[];
+
+    /// Icon font merged into every size (e.g. Font Awesome), None for no icons
+    pub const ICON_FONT_FILE: Option<&str> = None;
+
+    /// First and last code point taken from the icon font (Font Awesome uses the Private Use Area)
+    pub const ICON_RANGE: [u32; 2] = [0xe000, 0xf8ff];
+}
+
+/// HUD localization
+pub mod locale {
+    /// Folder of locale files, one `<language code>.toml` per language
+    pub const DIR: &str = "locales";
+
+    /// Language of the HUD by default; English is built in
+    pub const LANGUAGE: &str = "en";
+}
+
+/// GPU Textures debug panel
+pub mod gpu_textures {
+    /// Textures the HUD can draw besides the font atlas
+    pub const MAX_USER_TEXTURES: u32 = 16;
+
+    /// Width of the thumbnails in the target list, in pixels
+    pub const THUMBNAIL_WIDTH: f32 = 160.0;
+
+    /// Zoom range of the inspector (texels to pixels)
+    pub const ZOOM_RANGE: [f32; 2] = [0.125, 16.0];
+
+    /// Near plane of the perspective depth mapping (DEPTH_NEAR in sdf.frag and mesh.vert)
+    pub const DEPTH_NEAR: f32 = 0.01;
+
+    /// View distance shown as white when depth is linearized
+    pub const DEFAULT_DEPTH_FAR: f32 = 20.0;
+}
+
+/// Offline frame export
+pub mod export {
+    /// Directory exported frames are written to
+    pub const OUTPUT_DIR: &str = "export";
+
+    /// Directory screenshots are written to
+    pub const SCREENSHOT_DIR: &str = "screenshots";
+
+    /// Default export width
+    pub const DEFAULT_WIDTH: u32 = 1920;
+
+    /// Default export height
+    pub const DEFAULT_HEIGHT: u32 = 1080;
+
+    /// Default export frame rate
+    pub const DEFAULT_FPS: u32 = 30;
+
+    /// Default length of an export started from the HUD (seconds)
+    pub const DEFAULT_DURATION_SECS: f32 = 5.0;
+
+    /// ffmpeg executable used for video export
+    pub const FFMPEG_PATH: &str = "ffmpeg";
+}
+
+/// High resolution still export, see `vulkan::still_export`
+pub mod still_export {
+    /// Largest side of the render target; bigger stills are rendered in tiles
+    /// of this size (or the device's image size limit, if smaller)
+    pub const TILE_SIZE: u32 = 4096;
+
+    /// Largest width or height of a still
+    pub const MAX_SIZE: u32 = 16384;
+
+    /// Default size as a multiple of `export::DEFAULT_WIDTH` and `DEFAULT_HEIGHT`
+    pub const DEFAULT_SIZE_MULTIPLE: u32 = 2;
+
+    /// File name of stills in the project's output directory, without extension
+    pub const FILE_STEM: &str = "still";
+}
+
+/// Shadertoy mode (`--toy`)
+pub mod toy {
+    /// Feedback buffers a toy manifest may declare (shadertoy's Buffer A to D)
+    pub const MAX_BUFFERS: usize = 4;
+
+    /// Textures a toy pass may read (`iChannel0` to `iChannel3`)
+    pub const MAX_CHANNELS: usize = 4;
+}
+
+/// World space label configuration
+pub mod labels {
+    /// Color of the drop shadow behind label text
+    pub const SHADOW_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+
+    /// Pixels between a label's anchor point and the bottom of its text
+    pub const ANCHOR_GAP: f32 = 4.0;
+
+    /// World space gap between a shape's top and its label from the scene file
+    pub const SHAPE_MARGIN: f32 = 0.1;
+}
+
+/// Scene lights and their viewport gizmos
+pub mod lights {
+    /// Half angle of the cone of new spot lights in degrees
+    pub const DEFAULT_SPOT_ANGLE_DEG: f32 = 30.0;
+
+    /// Fraction of a spot light's cone angle over which it fades out
+    pub const SPOT_SOFTNESS: f32 = 0.2;
+
+    /// Radius of a light's gizmo in pixels
+    pub const GIZMO_RADIUS: f32 = 7.0;
+
+    /// Length of the direction line of a directional or spot light gizmo in world units
+    pub const GIZMO_DIRECTION_LENGTH: f32 = 0.75;
+
+    /// Outline color of the gizmos, drawn around the light's own color
+    pub const GIZMO_OUTLINE_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+}
+
+/// Mesh rendering
+pub mod mesh {
+    /// Maximum number of mesh instances drawn per frame
+    pub const MAX_INSTANCES: usize = 4096;
+}
+
+/// Shadow map of the meshes, see `vulkan::shadow_map`
+pub mod shadows {
+    /// Width and height of the shadow map in pixels
+    pub const MAP_SIZE: u32 = 2048;
+
+    /// Smallest radius the shadow map covers, for single tiny meshes
+    pub const MIN_RADIUS: f32 = 0.01;
+
+    /// Constant depth bias of the shadow pass, against shadow acne
+    pub const DEPTH_BIAS_CONSTANT: f32 = 1.25;
+
+    /// Depth bias scaled by the slope of the triangles
+    pub const DEPTH_BIAS_SLOPE: f32 = 1.75;
+}
+
+/// Bounds of shapes and meshes and frustum culling of meshes, see `ecs::culling`
+pub mod culling {
+    /// Cull meshes outside every viewport by default
+    pub const ENABLED: bool = true;
+
+    /// Extra distance SDF shape bounds are grown by, on top of what their
+    /// modifiers add
+    pub const SHAPE_MARGIN: f32 = 0.25;
+}
+
+/// Uniform grid over the entity bounds, see `ecs::spatial`
+pub mod spatial {
+    /// Edge length of a grid cell in world units
+    pub const CELL_SIZE: f32 = 2.0;
+
+    /// Most cells an entity is listed in; larger ones are checked by every query
+    pub const MAX_CELLS_PER_ENTITY: usize = 64;
+
+    /// Farthest distance a ray is walked through the grid
+    pub const MAX_RAY_DISTANCE: f32 = 200.0;
+}
+
+/// Device memory budget of meshes and environment maps, see `vulkan::residency`
+pub mod residency {
+    /// Budget of the cached assets in MiB
+    pub const BUDGET_MB: u64 = 1024;
+
+    /// Largest share of the device local memory the cached assets may use
+    pub const HEAP_FRACTION: f64 = 0.5;
+
+    /// Frames an asset must go unused before it can be evicted; more than
+    /// the frames in flight, so none of them still draws it
+    pub const MIN_IDLE_FRAMES: u64 = 120;
+}
+
+/// Selection outline drawn around the selected entity
+pub mod outline {
+    /// Draw the outline by default
+    pub const ENABLED: bool = true;
+
+    /// Default outline color (linear RGB and opacity)
+    pub const COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+
+    /// Default outline thickness in pixels
+    pub const THICKNESS: f32 = 2.0;
+
+    /// Thickest outline accepted, bounding the pixels searched around each pixel
+    pub const MAX_THICKNESS: f32 = 8.0;
+}
+
+/// SDF to mesh extraction configuration
+pub mod mesh_export {
+    /// Default half size of the extraction bounds around the origin
+    pub const DEFAULT_HALF_EXTENT: f32 = 2.5;
+
+    /// Default number of grid cells along the longest side of the bounds
+    pub const DEFAULT_RESOLUTION: u32 = 128;
+
+    /// Largest resolution accepted from the HUD
+    pub const MAX_RESOLUTION: u32 = 512;
+
+    /// Default output file, without extension
+    pub const DEFAULT_OUTPUT: &str = "export/scene";
+}
+
+/// Uploads to device local memory, see `vulkan::upload`
+pub mod upload {
+    /// Size of the staging ring shared by uploads; larger uploads get their own staging buffer
+    pub const STAGING_RING_SIZE: u64 = 16 * 1024 * 1024;
+
+    /// Alignment of staged data, a multiple of every color format's texel size
+    pub const STAGING_ALIGNMENT: u64 = 16;
+}
+
+/// Runtime settings, see the `settings` module
+pub mod settings {
+    /// File the Settings window saves to, loaded at startup when present
+    pub const FILE: &str = "settings.toml";
+
+    /// Frame rate cap in frames per second, 0 for none
+    pub const FRAME_LIMIT: u32 = 0;
+
+    /// Highest frame rate cap the Settings window offers
+    pub const MAX_FRAME_LIMIT: u32 = 360;
+
+    /// Antialiasing rays per pixel of the SDF pass (1, 2, 4 or 8)
+    pub const MSAA_SAMPLES: u32 = 1;
+}
+
+/// Benchmark runs started with `--benchmark`
+pub mod benchmark {
+    /// Scene loaded for benchmarks, kept apart from the editable default scene
+    pub const SCENE_FILE: &str = "scenes/benchmark.toml";
+
+    /// Directory the per-frame CSV and the summary are written to
+    pub const OUTPUT_DIR: &str = "benchmarks";
+
+    /// Default length of a benchmark run (seconds)
+    pub const DEFAULT_DURATION_SECS: f32 = 20.0;
+
+    /// Frames rendered before measuring, while pipelines and caches warm up
+    pub const WARMUP_FRAMES: usize = 30;
+
+    /// Point the camera path circles around
+    pub const ORBIT_CENTER: [f32; 3] = [0.0, 0.0, 0.0];
+
+    /// Distance of the camera from the orbit center
+    pub const ORBIT_RADIUS: f32 = 3.0;
+
+    /// Height of the camera above the orbit center
+    pub const ORBIT_HEIGHT: f32 = 1.0;
+
+    /// Time for one full circle of the camera (seconds)
+    pub const ORBIT_PERIOD_SECS: f32 = 10.0;
+}
+
+/// OpenXR headset configuration (`--xr`)
+#[cfg(feature = "xr")]
+pub mod xr {
+    /// Controller profile the grip and select actions are bound for; runtimes remap it to their controllers
+    pub const INTERACTION_PROFILE: &str = "/interaction_profiles/khr/simple_controller";
+
+    /// Longest wait for an eye to finish rendering (nanoseconds)
+    pub const FRAME_TIMEOUT_NS: u64 = 1_000_000_000;
+}
+
 /// Memory configuration
 pub mod memory {
     /// Buffer alignment requirements
@@ -228,6 +1051,20 @@ pub mod memory {
     pub const ENABLE_MEMORY_DEBUGGING: bool = false;
 }
 
+/// Background job pool, see `jobs`
+pub mod jobs {
+    /// Number of worker threads; 0 uses one less than the available cores
+    pub const WORKERS: usize = 0;
+}
+
+/// Asynchronous asset loading, see `assets`
+pub mod assets {
+    /// Name of the mesh drawn while a mesh file loads
+    pub const PLACEHOLDER_MESH: &str = "<placeholder cube>";
+    /// Color of the environment map while its file loads (linear RGB)
+    pub const LOADING_TEXTURE_COLOR: [f32; 3] = [1.0, 0.0, 1.0];
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -307,4 +1144,4 @@ mod tests {
         assert_eq!(vulkan::VALIDATION_LAYERS, &["VK_LAYER_KHRONOS_validation"]);
         assert_eq!(vulkan::DEVICE_EXTENSIONS, &["VK_KHR_swapchain"]);
     }
-}
\ No newline at end of file
+}
@@ -4,10 +4,10 @@
 //! helpers to make debugging and supporting the application easier.
 
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use log::{debug, info, error};
-#[cfg(debug_assertions)]
-use log::warn;
+use log::{debug, info, warn, error};
 use crate::config;
 use crate::error::{Result, AppError, VulkanError};
 
@@ -168,14 +168,12 @@ impl VulkanDebugUtils {
         Some(total / self.frame_times.len() as u32)
     }
     
-    /// Log memory usage information
-    pub fn log_memory_usage(&self, _device: &ash::Device) {
+    /// Log the device memory the app has allocated, see `vulkan::memory_usage`
+    pub fn log_memory_usage(&self) {
         #[cfg(debug_assertions)]
         {
             if config::debug::ENABLE_MEMORY_TRACKING {
-                // This is a simplified implementation
-                // In a real application, you would query actual memory usage
-                info!("Memory usage logging (simplified implementation)");
+                crate::vulkan::memory_usage::log_usage();
                 
                 // Log object names for debugging
                 if !self.object_names.is_empty() {
@@ -313,6 +311,24 @@ impl Drop for VulkanDebugUtils {
     }
 }
 
+/// Number of validation errors reported since startup
+static VALIDATION_ERROR_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Most recent validation error message
+static LAST_VALIDATION_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Get the number of validation errors reported so far
+///
+/// Compare the value before and after a submission to detect errors it caused.
+pub fn validation_error_count() -> u64 {
+    VALIDATION_ERROR_COUNT.load(Ordering::SeqCst)
+}
+
+/// Get the most recent validation error message, if any
+pub fn last_validation_error() -> Option<String> {
+    LAST_VALIDATION_ERROR.lock().ok().and_then(|message| message.clone())
+}
+
 /// Vulkan debug callback function
 #[cfg(debug_assertions)]
 unsafe extern "system" fn vulkan_debug_callback(
@@ -329,6 +345,10 @@ unsafe extern "system" fn vulkan_debug_callback(
     match message_severity {
         ash::vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => {
             error!("Vulkan Validation Error: {}", message);
+            if let Ok(mut last) = LAST_VALIDATION_ERROR.lock() {
+                *last = Some(message.to_string());
+            }
+            VALIDATION_ERROR_COUNT.fetch_add(1, Ordering::SeqCst);
         }
         ash::vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => {
             warn!("Vulkan Validation Warning: {}", message);
@@ -348,16 +368,20 @@ unsafe extern "system" fn vulkan_debug_callback(
 }
 
 /// Initialize the logging system
+///
+/// Records go to stdout, to the rotating log file if enabled, to the
+/// in-memory buffer read by the HUD console, and to the profiler when built
+/// with a profiling feature. A log file that cannot be
+/// opened is reported as a warning instead of failing initialization.
 pub fn init_logging() -> Result<()> {
     use crate::config::debug;
+    use crate::log_sinks::{ConsoleSink, RotatingFile};
     
     if !debug::ENABLE_LOGGING {
         return Ok(());
     }
     
-    // Simple console logger setup
-    // In a real application, you might want to use a more sophisticated logging setup
-    fern::Dispatch::new()
+    let mut formatted = fern::Dispatch::new()
         .format(|out, message, record| {
             out.finish(format_args!(
                 "{} [{}][{}] {}",
@@ -367,14 +391,37 @@ pub fn init_logging() -> Result<()> {
                 message
             ))
         })
+        .chain(std::io::stdout());
+    
+    let mut file_error = None;
+    if debug::ENABLE_FILE_LOGGING {
+        match RotatingFile::open(debug::LOG_FILE, debug::LOG_FILE_MAX_BYTES, debug::LOG_FILE_ROTATIONS) {
+            Ok(file) => formatted = formatted.chain(fern::Output::writer(Box::new(file), "\n")),
+            Err(e) => file_error = Some(e),
+        }
+    }
+    
+    // The console keeps the unformatted message; it shows time and level itself
+    let mut dispatch = fern::Dispatch::new()
         .level(debug::LOG_LEVEL)
-        .chain(std::io::stdout())
+        .chain(formatted)
+        .chain(Box::new(ConsoleSink) as Box<dyn log::Log>);
+    
+    // With a profiling feature, log records also show up on the trace timeline
+    if let Some(bridge) = crate::profiling::log_bridge() {
+        dispatch = dispatch.chain(bridge);
+    }
+    
+    dispatch
         .apply()
         .map_err(|e| AppError::Generic(
             format!("Failed to initialize logging: {}", e)
         ))?;
     
-    info!("Logging system initialized");
+    match file_error {
+        Some(e) => warn!("Logging to stdout only, cannot open log file {}: {}", debug::LOG_FILE, e),
+        None => info!("Logging system initialized"),
+    }
     Ok(())
 }
 
@@ -0,0 +1,277 @@
+//! Keyframe animation
+//!
+//! An `AnimationClip` holds keyframe tracks for the transform (translation,
+//! rotation, scale) and the material parameters of an entity. The
+//! `AnimationPlayer` component plays one clip; the playback system advances
+//! every playing player by the real frame time and writes the sampled
+//! values. Children in the transform hierarchy animate their local
+//! transform. Clips are stored in the scene file under a shape's
+//! `animation` table.
+
+use legion::{system, IntoQuery, World};
+use log::debug;
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::ecs::components::{FrameTime, LocalTransform, SDFMaterial, Transform};
+use crate::hud::animation_panel::{AnimationCommand, AnimationStatus};
+
+/// Property animated by a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackTarget {
+    /// Position
+    Translation,
+    /// XYZ Euler angles in radians
+    Rotation,
+    /// Per-axis scale
+    Scale,
+    /// Material color
+    Color,
+    /// Material metallic (x of the value)
+    Metallic,
+    /// Material roughness (x of the value)
+    Roughness,
+    /// Material emission (x of the value)
+    Emission,
+}
+
+/// Keyframe values are written as a number (used for every component) or
+/// a three-component array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyValue {
+    Scalar(f32),
+    Vector([f32; 3]),
+}
+
+fn deserialize_value<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<[f32; 3], D::Error> {
+    Ok(match KeyValue::deserialize(deserializer)? {
+        KeyValue::Scalar(value) => [value; 3],
+        KeyValue::Vector(value) => value,
+    })
+}
+
+/// Value of a track at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Time in seconds from the start of the clip
+    pub time: f32,
+    #[serde(deserialize_with = "deserialize_value")]
+    pub value: [f32; 3],
+}
+
+/// Keyframes of one property, sorted by time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub target: TrackTarget,
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    /// Linearly interpolated value at a time, holding the first and last keyframes
+    ///
+    /// # Returns
+    /// None if the track has no keyframes
+    pub fn sample(&self, time: f32) -> Option<[f32; 3]> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+
+        let next = self.keyframes.iter().position(|keyframe| keyframe.time > time)?;
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = (time - a.time) / (b.time - a.time).max(f32::EPSILON);
+        Some(std::array::from_fn(|axis| a.value[axis] + (b.value[axis] - a.value[axis]) * t))
+    }
+}
+
+/// Keyframe tracks played together
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationClip {
+    /// Start over after the last keyframe
+    pub looping: bool,
+    pub tracks: Vec<Track>,
+}
+
+impl AnimationClip {
+    /// Time of the last keyframe of any track
+    pub fn duration(&self) -> f32 {
+        self.tracks
+            .iter()
+            .filter_map(|track| track.keyframes.last())
+            .map(|keyframe| keyframe.time)
+            .fold(0.0, f32::max)
+    }
+
+    /// Sort every track's keyframes by time, as `Track::sample` expects
+    pub fn sort_keyframes(&mut self) {
+        for track in &mut self.tracks {
+            track.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+    }
+
+    /// Write the values at a time into a transform and material
+    pub fn apply(&self, time: f32, transform: &mut Transform, mut material: Option<&mut SDFMaterial>) {
+        for track in &self.tracks {
+            let Some(value) = track.sample(time) else {
+                continue;
+            };
+            match (track.target, material.as_deref_mut()) {
+                (TrackTarget::Translation, _) => transform.position = value.into(),
+                (TrackTarget::Rotation, _) => transform.rotation = value.into(),
+                (TrackTarget::Scale, _) => transform.scale = value.into(),
+                (TrackTarget::Color, Some(material)) => material.color = value.into(),
+                (TrackTarget::Metallic, Some(material)) => material.metallic = value[0],
+                (TrackTarget::Roughness, Some(material)) => material.roughness = value[0],
+                (TrackTarget::Emission, Some(material)) => material.emission = value[0],
+                (_, None) => {}
+            }
+        }
+    }
+}
+
+/// Plays a clip on the entity it is attached to
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationPlayer {
+    pub clip: AnimationClip,
+    /// Playback position in seconds
+    pub time: f32,
+    /// Playback rate, 1 = real time
+    pub speed: f32,
+    pub playing: bool,
+}
+
+impl AnimationPlayer {
+    /// Player starting the clip from the beginning
+    pub fn new(mut clip: AnimationClip) -> Self {
+        clip.sort_keyframes();
+        Self { clip, time: 0.0, speed: 1.0, playing: true }
+    }
+
+    /// Advance the playback position, wrapping or stopping at the end
+    pub fn advance(&mut self, delta_seconds: f32) {
+        if !self.playing {
+            return;
+        }
+        let duration = self.clip.duration();
+        self.time += delta_seconds * self.speed;
+        if duration <= 0.0 {
+            self.time = 0.0;
+        } else if self.clip.looping {
+            self.time = self.time.rem_euclid(duration);
+        } else if self.time >= duration {
+            self.time = duration;
+            self.playing = false;
+        }
+    }
+}
+
+/// System that advances every animation player and applies its clip
+///
+/// # Arguments
+/// * `player` - The entity's animation player
+/// * `transform` - World transform, animated for root entities
+/// * `local` - Local transform, animated instead for children
+/// * `material` - Material, for color and material parameter tracks
+/// * `frame_time` - Real time since the last frame
+#[system(for_each)]
+pub fn animation_playback(
+    player: &mut AnimationPlayer,
+    transform: &mut Transform,
+    local: Option<&mut LocalTransform>,
+    material: Option<&mut SDFMaterial>,
+    #[resource] frame_time: &FrameTime,
+) {
+    player.advance(frame_time.delta_seconds);
+    let target = match local {
+        Some(local) => &mut local.0,
+        None => transform,
+    };
+    player.clip.apply(player.time, target, material);
+}
+
+/// Snapshot of the players for the animation panel
+///
+/// The transport shows the first player's position and the longest clip.
+pub fn collect_animation_status(world: &World) -> AnimationStatus {
+    let mut status = AnimationStatus::default();
+    for player in <&AnimationPlayer>::query().iter(world) {
+        if status.players == 0 {
+            status.time = player.time;
+            status.speed = player.speed;
+            status.playing = player.playing;
+        }
+        status.players += 1;
+        status.duration = status.duration.max(player.clip.duration());
+    }
+    status
+}
+
+/// Apply a transport command from the animation panel to every player
+pub fn apply_animation_command(world: &mut World, command: AnimationCommand) {
+    debug!("Animation command applied: {:?}", command);
+    for player in <&mut AnimationPlayer>::query().iter_mut(world) {
+        match command {
+            AnimationCommand::Play => {
+                // Restart clips that ran to their end
+                if !player.clip.looping && player.time >= player.clip.duration() {
+                    player.time = 0.0;
+                }
+                player.playing = true;
+            }
+            AnimationCommand::Pause => player.playing = false,
+            AnimationCommand::Seek(time) => player.time = time.clamp(0.0, player.clip.duration()),
+            AnimationCommand::SetSpeed(speed) => player.speed = speed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+
+    fn bounce() -> AnimationClip {
+        AnimationClip {
+            looping: true,
+            tracks: vec![Track {
+                target: TrackTarget::Translation,
+                keyframes: vec![
+                    Keyframe { time: 0.0, value: [0.0, 0.0, 0.0] },
+                    Keyframe { time: 1.0, value: [0.0, 2.0, 0.0] },
+                    Keyframe { time: 2.0, value: [0.0, 0.0, 0.0] },
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_track_sampling_interpolates_and_holds_ends() {
+        let track = &bounce().tracks[0];
+        assert_eq!(track.sample(-1.0), Some([0.0, 0.0, 0.0]));
+        assert_eq!(track.sample(0.5), Some([0.0, 1.0, 0.0]));
+        assert_eq!(track.sample(1.5), Some([0.0, 1.0, 0.0]));
+        assert_eq!(track.sample(5.0), Some([0.0, 0.0, 0.0]));
+        assert_eq!(Track { target: TrackTarget::Scale, keyframes: Vec::new() }.sample(0.0), None);
+    }
+
+    #[test]
+    fn test_player_loops_or_stops_and_applies() {
+        let mut player = AnimationPlayer::new(bounce());
+        player.advance(2.5);
+        assert!((player.time - 0.5).abs() < 1e-5);
+
+        let mut transform = Transform::default();
+        player.clip.apply(player.time, &mut transform, None);
+        assert_eq!(transform.position, Vector3::new(0.0, 1.0, 0.0));
+
+        player.clip.looping = false;
+        player.advance(3.0);
+        assert_eq!(player.time, 2.0);
+        assert!(!player.playing);
+    }
+}
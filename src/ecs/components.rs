@@ -1,4 +1,6 @@
-use cgmath::Vector3;
+use cgmath::{ElementWise, Euler, InnerSpace, Matrix3, Matrix4, Quaternion, Rad, Rotation, Vector3};
+use serde::{Deserialize, Serialize};
+use crate::config;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(C)]
@@ -27,6 +29,54 @@ impl Default for Transform {
     }
 }
 
+impl Transform {
+    /// Rotation as a quaternion (`rotation` holds XYZ Euler angles in radians)
+    fn rotation_quaternion(&self) -> Quaternion<f32> {
+        Quaternion::from(Euler::new(Rad(self.rotation.x), Rad(self.rotation.y), Rad(self.rotation.z)))
+    }
+
+    /// Euler angles of a rotation quaternion
+    fn euler_angles(rotation: Quaternion<f32>) -> Vector3<f32> {
+        let euler = Euler::from(rotation.normalize());
+        Vector3::new(euler.x.0, euler.y.0, euler.z.0)
+    }
+
+    /// World transform of a child with this transform as its parent
+    ///
+    /// Scale is applied per axis before the rotation; non-uniform parent
+    /// scale does not shear rotated children.
+    pub fn compose(&self, local: &Transform) -> Transform {
+        let rotation = self.rotation_quaternion();
+        Transform {
+            position: self.position + rotation.rotate_vector(self.scale.mul_element_wise(local.position)),
+            rotation: Self::euler_angles(rotation * local.rotation_quaternion()),
+            scale: self.scale.mul_element_wise(local.scale),
+        }
+    }
+
+    /// Local transform that `compose`s with `parent` back to this transform
+    pub fn relative_to(&self, parent: &Transform) -> Transform {
+        let inverse = parent.rotation_quaternion().invert();
+        Transform {
+            position: inverse.rotate_vector(self.position - parent.position).div_element_wise(parent.scale),
+            rotation: Self::euler_angles(inverse * self.rotation_quaternion()),
+            scale: self.scale.div_element_wise(parent.scale),
+        }
+    }
+
+    /// Rotation as a matrix
+    pub fn rotation_matrix(&self) -> Matrix3<f32> {
+        Matrix3::from(self.rotation_quaternion())
+    }
+
+    /// Model matrix: scale, then rotation, then translation
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position)
+            * Matrix4::from(self.rotation_quaternion())
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
@@ -51,7 +101,8 @@ pub struct Color {
 
 // SDF Components
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SDFShapeType {
     Sphere,
     Box,
@@ -70,6 +121,37 @@ pub struct SDFShape {
     pub params: [f32; 4], // Additional parameters for complex shapes
 }
 
+impl SDFShape {
+    /// Half extents of the shape's axis aligned bounding box around its position
+    ///
+    /// # Returns
+    /// None for unbounded shapes (planes)
+    pub fn half_extents(&self) -> Option<Vector3<f32>> {
+        // Must match the distance functions in sdf.frag
+        match self.shape_type {
+            SDFShapeType::Sphere | SDFShapeType::Box => Some(Vector3::new(self.size, self.size, self.size)),
+            SDFShapeType::Plane => None,
+            SDFShapeType::Torus => {
+                let outer = self.size + self.params[0];
+                Some(Vector3::new(outer, self.params[0], outer))
+            }
+            SDFShapeType::Cylinder => Some(Vector3::new(self.size, self.params[0], self.size)),
+        }
+    }
+
+    /// Radius of a sphere around the shape's position that contains it
+    ///
+    /// # Returns
+    /// None for unbounded shapes (planes)
+    pub fn bounding_radius(&self) -> Option<f32> {
+        match self.shape_type {
+            SDFShapeType::Sphere => Some(self.size),
+            SDFShapeType::Torus => Some(self.size + self.params[0]),
+            _ => self.half_extents().map(|extents| extents.magnitude()),
+        }
+    }
+}
+
 impl Default for SDFShape {
     fn default() -> Self {
         Self {
@@ -86,6 +168,13 @@ pub struct SDFMaterial {
     pub metallic: f32,
     pub roughness: f32,
     pub emission: f32,
+    /// Fraction of light mirrored off the surface, traced against the scene
+    /// by the reflections shader variant
+    pub reflectivity: f32,
+    /// Fraction of light passing through the shape, refracted by `ior`
+    pub transparency: f32,
+    /// Index of refraction of the shape's inside
+    pub ior: f32,
 }
 
 impl Default for SDFMaterial {
@@ -95,6 +184,9 @@ impl Default for SDFMaterial {
             metallic: 0.0,
             roughness: 0.5,
             emission: 0.0,
+            reflectivity: 0.0,
+            transparency: 0.0,
+            ior: config::materials::DEFAULT_IOR,
         }
     }
 }
@@ -102,23 +194,202 @@ impl Default for SDFMaterial {
 #[derive(Clone, Debug, PartialEq)]
 pub struct SDFRenderable;
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct SDFLight {
-    pub position: cgmath::Vector3<f32>,
-    pub color: cgmath::Vector3<f32>,
-    pub intensity: f32,
+/// How an SDF entity is drawn by the raymarcher
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SDFDisplayMode {
+    /// Drawn normally
+    Visible,
+    /// Drawn as a dithered ghost (hidden but selected or referenced)
+    Ghost,
+    /// Not drawn at all
+    Hidden,
 }
 
-impl Default for SDFLight {
-    fn default() -> Self {
-        Self {
-            position: cgmath::Vector3::new(2.0, 2.0, 2.0),
-            color: cgmath::Vector3::new(1.0, 1.0, 1.0),
-            intensity: 1.0,
+impl SDFDisplayMode {
+    /// Value written to the scene buffer (matches DISPLAY_* defines in sdf.frag)
+    pub fn as_gpu(self) -> i32 {
+        match self {
+            SDFDisplayMode::Visible => 0,
+            SDFDisplayMode::Ghost => 1,
+            SDFDisplayMode::Hidden => 2,
+        }
+    }
+}
+
+/// Visibility state of an SDF entity
+///
+/// Entities without this component are always visible.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SDFVisibility {
+    /// Hidden by the user
+    pub hidden: bool,
+    /// Currently selected in the editor
+    pub selected: bool,
+    /// Referenced by another entity (e.g. as a CSG operand)
+    pub referenced: bool,
+}
+
+impl SDFVisibility {
+    /// Resolve the display mode for this entity
+    ///
+    /// Hidden entities that are still selected or referenced are shown
+    /// as a dithered ghost so they can be located and manipulated.
+    pub fn display_mode(&self) -> SDFDisplayMode {
+        if !self.hidden {
+            SDFDisplayMode::Visible
+        } else if self.selected || self.referenced {
+            SDFDisplayMode::Ghost
+        } else {
+            SDFDisplayMode::Hidden
+        }
+    }
+}
+
+/// Debug overlay drawn for an entity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugDisplayMode {
+    /// No overlay
+    #[default]
+    None,
+    /// Axis aligned bounding box
+    Bounds,
+    /// Approximate wireframe of the shape
+    Wireframe,
+}
+
+impl DebugDisplayMode {
+    /// All modes, in the order shown in the editor
+    pub const ALL: [DebugDisplayMode; 3] = [
+        DebugDisplayMode::None,
+        DebugDisplayMode::Bounds,
+        DebugDisplayMode::Wireframe,
+    ];
+
+    /// Human readable name
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugDisplayMode::None => "None",
+            DebugDisplayMode::Bounds => "Bounds",
+            DebugDisplayMode::Wireframe => "Wireframe",
         }
     }
 }
 
+/// Per-entity debug overlay setting
+///
+/// Entities without this component draw no overlay unless the global
+/// override in `DebugDrawSettings` is set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SDFDebugDisplay {
+    pub mode: DebugDisplayMode,
+}
+
+/// Debug overlay settings shared by all entities (ECS resource)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DebugDrawSettings {
+    /// When set, replaces the per-entity mode for every entity
+    pub global_override: Option<DebugDisplayMode>,
+}
+
+impl DebugDrawSettings {
+    /// Resolve the mode for an entity
+    pub fn resolve(&self, entity_mode: Option<DebugDisplayMode>) -> DebugDisplayMode {
+        self.global_override.or(entity_mode).unwrap_or_default()
+    }
+}
+
+/// Real time elapsed since the previous frame (ECS resource)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTime {
+    pub delta_seconds: f32,
+}
+
+/// Boolean operation combining the operands of a CSG node
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsgOperation {
+    /// Merge all operands
+    #[default]
+    Union,
+    /// Carve every following operand out of the first one
+    Subtraction,
+    /// Keep only the volume shared by all operands
+    Intersection,
+}
+
+impl CsgOperation {
+    /// All operations, in the order shown in the editor
+    pub const ALL: [CsgOperation; 3] = [
+        CsgOperation::Union,
+        CsgOperation::Subtraction,
+        CsgOperation::Intersection,
+    ];
+
+    /// Human readable name
+    pub fn name(self) -> &'static str {
+        match self {
+            CsgOperation::Union => "Union",
+            CsgOperation::Subtraction => "Subtraction",
+            CsgOperation::Intersection => "Intersection",
+        }
+    }
+
+    /// Value written to the scene buffer (matches CSG_* defines in sdf.frag)
+    pub fn as_gpu(self) -> i32 {
+        match self {
+            CsgOperation::Union => 0,
+            CsgOperation::Subtraction => 1,
+            CsgOperation::Intersection => 2,
+        }
+    }
+}
+
+/// Composite SDF object built from other shape entities
+///
+/// Operands are combined left to right with a single operation, so their
+/// order matters for subtraction. Operands are plain shape entities; they
+/// are drawn as part of the composite rather than on their own.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CsgNode {
+    /// Operation applied between operands
+    pub operation: CsgOperation,
+    /// Smoothing radius for the operation (0 = hard edges)
+    pub blend_radius: f32,
+    /// Operand shape entities in evaluation order
+    pub operands: Vec<legion::Entity>,
+}
+
+impl CsgNode {
+    /// Move an operand to a new position, shifting the others
+    ///
+    /// # Returns
+    /// False if either index is out of range
+    pub fn move_operand(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.operands.len() || to >= self.operands.len() {
+            return false;
+        }
+        let operand = self.operands.remove(from);
+        self.operands.insert(to, operand);
+        true
+    }
+}
+
+/// Parent of an entity in the transform hierarchy
+///
+/// The entity's `Transform` is computed from its `LocalTransform` and the
+/// parent's `Transform` every frame. Use `hierarchy::set_parent` to change
+/// it so the parent's `Children` stay in sync.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Parent(pub legion::Entity);
+
+/// Children of an entity in the transform hierarchy, in creation order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Children(pub Vec<legion::Entity>);
+
+/// Transform of a child entity relative to its `Parent`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LocalTransform(pub Transform);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,6 +428,26 @@ mod tests {
         assert_eq!(transform.scale, scale);
     }
 
+    #[test]
+    fn test_transform_compose_and_relative_to() {
+        let parent = Transform {
+            position: Vector3::new(1.0, 0.0, 0.0),
+            rotation: Vector3::new(0.0, 0.0, std::f32::consts::FRAC_PI_2),
+            scale: Vector3::new(2.0, 2.0, 2.0),
+        };
+        let local = Transform { position: Vector3::new(1.0, 0.0, 0.0), ..Transform::default() };
+
+        let world = parent.compose(&local);
+        assert!((world.position - Vector3::new(1.0, 2.0, 0.0)).magnitude() < 1e-5);
+        assert!((world.rotation.z - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+        assert_eq!(world.scale, Vector3::new(2.0, 2.0, 2.0));
+
+        let back = world.relative_to(&parent);
+        assert!((back.position - local.position).magnitude() < 1e-5);
+        assert!(back.rotation.magnitude() < 1e-5);
+        assert!((back.scale - local.scale).magnitude() < 1e-5);
+    }
+
     #[test]
     fn test_mesh_new() {
         let vertices = vec![
@@ -219,6 +510,58 @@ mod tests {
         assert_ne!(transform1, transform3);
     }
 
+    #[test]
+    fn test_visibility_display_mode() {
+        assert_eq!(SDFVisibility::default().display_mode(), SDFDisplayMode::Visible);
+        
+        let hidden = SDFVisibility { hidden: true, ..Default::default() };
+        assert_eq!(hidden.display_mode(), SDFDisplayMode::Hidden);
+        
+        let selected = SDFVisibility { hidden: true, selected: true, referenced: false };
+        assert_eq!(selected.display_mode(), SDFDisplayMode::Ghost);
+        
+        let referenced = SDFVisibility { hidden: true, selected: false, referenced: true };
+        assert_eq!(referenced.display_mode(), SDFDisplayMode::Ghost);
+    }
+
+    #[test]
+    fn test_debug_draw_override_wins() {
+        let settings = DebugDrawSettings::default();
+        assert_eq!(settings.resolve(None), DebugDisplayMode::None);
+        assert_eq!(settings.resolve(Some(DebugDisplayMode::Bounds)), DebugDisplayMode::Bounds);
+
+        let settings = DebugDrawSettings { global_override: Some(DebugDisplayMode::Wireframe) };
+        assert_eq!(settings.resolve(Some(DebugDisplayMode::Bounds)), DebugDisplayMode::Wireframe);
+    }
+
+    #[test]
+    fn test_shape_half_extents() {
+        let torus = SDFShape { shape_type: SDFShapeType::Torus, size: 1.0, params: [0.25, 0.0, 0.0, 0.0] };
+        assert_eq!(torus.half_extents(), Some(Vector3::new(1.25, 0.25, 1.25)));
+        assert_eq!(torus.bounding_radius(), Some(1.25));
+
+        let cube = SDFShape { shape_type: SDFShapeType::Box, size: 1.0, ..Default::default() };
+        assert_eq!(cube.bounding_radius(), Some(3f32.sqrt()));
+
+        let plane = SDFShape { shape_type: SDFShapeType::Plane, ..Default::default() };
+        assert_eq!(plane.half_extents(), None);
+        assert_eq!(plane.bounding_radius(), None);
+    }
+
+    #[test]
+    fn test_csg_move_operand() {
+        let mut world = legion::World::default();
+        let a = world.push((Transform::default(),));
+        let b = world.push((Transform::default(),));
+        let c = world.push((Transform::default(),));
+        let mut node = CsgNode { operands: vec![a, b, c], ..Default::default() };
+
+        assert!(node.move_operand(2, 0));
+        assert_eq!(node.operands, vec![c, a, b]);
+        assert!(!node.move_operand(3, 0));
+        assert_eq!(node.operands, vec![c, a, b]);
+    }
+
     #[test]
     fn test_mesh_equality() {
         let vertices = vec![
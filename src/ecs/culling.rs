@@ -0,0 +1,450 @@
+//! Frustum culling
+//!
+//! The bounds system gives every SDF shape and mesh entity a world space
+//! `BoundingVolume`: an axis aligned box around a shape (widened by its
+//! repeat modifiers, grown by its distance modifiers and by
+//! `config::culling::SHAPE_MARGIN`) and a sphere around a mesh instance,
+//! from the mesh space bounds of the meshes the renderer uploaded (the
+//! `MeshBounds` resource). The mesh draw system then leaves out the
+//! instances the spatial index (`ecs::spatial`) finds outside the frustum of
+//! every drawn viewport (the `ViewFrustums` resource, empty when culling is
+//! off) and counts what it kept in the `CullingStats` resource shown in the
+//! stats panel. An instance whose sun shadow reaches a viewport is kept, so
+//! it stays in the shadow map.
+//!
+//! SDF shapes are never culled: the raymarcher traces shadows, reflections
+//! and ambient occlusion through the whole scene buffer, so a shape outside
+//! the view still shows up in it. Their bounds serve viewport picking.
+//! Entities without bounds yet are never culled either.
+
+use std::collections::HashMap;
+use ash::vk;
+use cgmath::{ElementWise, InnerSpace, Vector3};
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::{system, Entity, IntoQuery};
+use crate::config;
+use crate::ecs::components::{SDFShape, Transform};
+use crate::environment::SunSettings;
+use crate::ecs::mesh::MeshRenderer;
+use crate::ecs::modifiers::{SDFModifier, SDFModifiers};
+use crate::vulkan::mesh_pass::transform_sphere;
+use crate::vulkan::viewport::{rect_aspect, ViewCamera, ViewportSettings};
+
+/// World space bounds of an entity, kept up to date by `update_bounds`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoundingVolume {
+    /// Axis aligned box between two corners
+    Aabb { min: Vector3<f32>, max: Vector3<f32> },
+    /// Sphere around a center
+    Sphere { center: Vector3<f32>, radius: f32 },
+}
+
+impl BoundingVolume {
+    /// Box around an SDF shape and the copies and growth of its modifiers
+    ///
+    /// # Returns
+    /// None for unbounded shapes: planes, endless repeats, and shapes bent or
+    /// twisted, which can end up anywhere along the warped axes
+    pub fn of_shape(shape: &SDFShape, transform: &Transform, modifiers: Option<&SDFModifiers>) -> Option<Self> {
+        let mut extents = shape.half_extents()?;
+        let mut growth = config::culling::SHAPE_MARGIN;
+        // Must match the modifiers in `shapeDistance` in sdf.frag
+        for modifier in modifiers.map(|modifiers| modifiers.0.as_slice()).unwrap_or_default() {
+            match *modifier {
+                SDFModifier::Twist { .. } | SDFModifier::Bend { .. } => return None,
+                SDFModifier::Repeat { spacing, count } => {
+                    let spacing = Vector3::from(spacing).map(|spacing| if spacing < 1e-6 { 0.0 } else { spacing });
+                    if count == 0 && spacing != Vector3::new(0.0, 0.0, 0.0) {
+                        return None;
+                    }
+                    extents += spacing * count as f32;
+                }
+                SDFModifier::Onion { thickness } => growth += thickness.abs(),
+                SDFModifier::Round { radius } => growth += radius.max(0.0),
+                SDFModifier::Noise { amplitude, .. } => growth += amplitude.abs(),
+            }
+        }
+        let extents = extents.add_element_wise(growth);
+        Some(BoundingVolume::Aabb { min: transform.position - extents, max: transform.position + extents })
+    }
+
+    /// Sphere around a mesh instance from the mesh space bounds of its mesh
+    pub fn of_mesh(bounds: (Vector3<f32>, f32), transform: &Transform) -> Self {
+        let (center, radius) = transform_sphere(&transform.matrix().into(), bounds);
+        BoundingVolume::Sphere { center, radius }
+    }
+
+    /// Corners of the axis aligned box around the volume
+    pub fn aabb(&self) -> (Vector3<f32>, Vector3<f32>) {
+        match *self {
+            BoundingVolume::Aabb { min, max } => (min, max),
+            BoundingVolume::Sphere { center, radius } => {
+                let extents = Vector3::new(radius, radius, radius);
+                (center - extents, center + extents)
+            }
+        }
+    }
+
+    /// Distance along a ray to where it enters the volume
+    ///
+    /// # Arguments
+    /// * `origin` - Start of the ray
+    /// * `direction` - Normalized direction of the ray
+    ///
+    /// # Returns
+    /// 0 if the ray starts inside, None if it misses the volume
+    pub fn ray_distance(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        match *self {
+            BoundingVolume::Aabb { min, max } => {
+                let (mut near, mut far) = (0.0f32, f32::INFINITY);
+                for axis in 0..3 {
+                    if direction[axis].abs() < f32::EPSILON {
+                        if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let a = (min[axis] - origin[axis]) / direction[axis];
+                    let b = (max[axis] - origin[axis]) / direction[axis];
+                    near = near.max(a.min(b));
+                    far = far.min(a.max(b));
+                }
+                (near <= far).then_some(near)
+            }
+            BoundingVolume::Sphere { center, radius } => {
+                let to_center = center - origin;
+                if to_center.magnitude2() <= radius * radius {
+                    return Some(0.0);
+                }
+                let along = to_center.dot(direction);
+                let miss = to_center.magnitude2() - along * along;
+                if along < 0.0 || miss > radius * radius {
+                    return None;
+                }
+                Some(along - (radius * radius - miss).sqrt())
+            }
+        }
+    }
+}
+
+/// Half space `normal · p + offset >= 0` bounding a frustum
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Plane {
+    normal: Vector3<f32>,
+    offset: f32,
+}
+
+impl Plane {
+    /// Plane through a point, facing along a direction
+    fn through(point: Vector3<f32>, normal: Vector3<f32>) -> Self {
+        let normal = normal.normalize();
+        Self { normal, offset: -normal.dot(point) }
+    }
+
+    /// Signed distance of a point, positive on the inner side
+    fn distance(&self, point: Vector3<f32>) -> f32 {
+        self.normal.dot(point) + self.offset
+    }
+
+    /// Whether any part of a volume may be on the inner side
+    fn touches(&self, volume: &BoundingVolume) -> bool {
+        match *volume {
+            BoundingVolume::Aabb { min, max } => {
+                // Corner furthest along the normal
+                let corner = Vector3::new(
+                    if self.normal.x >= 0.0 { max.x } else { min.x },
+                    if self.normal.y >= 0.0 { max.y } else { min.y },
+                    if self.normal.z >= 0.0 { max.z } else { min.z },
+                );
+                self.distance(corner) >= 0.0
+            }
+            BoundingVolume::Sphere { center, radius } => self.distance(center) >= -radius,
+        }
+    }
+}
+
+/// Region a viewport's camera sees, open towards the far side
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frustum {
+    planes: [Plane; 5],
+}
+
+impl Frustum {
+    /// Frustum of a camera drawn into a viewport rectangle
+    ///
+    /// Matches the rays of the raymarcher and `ViewCamera::project`: a
+    /// perspective camera sees a 90 degree vertical field of view (or its
+    /// eye's own), an orthographic one a box of its half height, both from
+    /// their origin on.
+    pub fn new(camera: &ViewCamera, rect: &vk::Rect2D) -> Self {
+        let (right, up, forward) = camera.basis();
+        let origin = Vector3::from(camera.origin);
+        let [half_width, half_height, center_x, center_y] = camera.lens(rect_aspect(rect));
+        let (left, right_edge) = (center_x - half_width, center_x + half_width);
+        let (bottom, top) = (center_y - half_height, center_y + half_height);
+        let near = Plane::through(origin, forward);
+        let planes = if camera.ortho_half_height > 0.0 {
+            let scale = camera.ortho_half_height;
+            [
+                Plane::through(origin + right * (right_edge * scale), -right),
+                Plane::through(origin + right * (left * scale), right),
+                Plane::through(origin + up * (top * scale), -up),
+                Plane::through(origin + up * (bottom * scale), up),
+                near,
+            ]
+        } else {
+            [
+                Plane::through(origin, forward * right_edge - right),
+                Plane::through(origin, right - forward * left),
+                Plane::through(origin, forward * top - up),
+                Plane::through(origin, up - forward * bottom),
+                near,
+            ]
+        };
+        Self { planes }
+    }
+
+    /// Whether any part of a volume may be inside the frustum
+    pub fn intersects(&self, volume: &BoundingVolume) -> bool {
+        self.planes.iter().all(|plane| plane.touches(volume))
+    }
+
+    /// Whether a volume or the shadow it casts may be inside the frustum
+    ///
+    /// The shadow is the volume swept endlessly along the light's direction,
+    /// so only planes the light points away from can rule it out.
+    pub fn intersects_shadow(&self, volume: &BoundingVolume, light_direction: Vector3<f32>) -> bool {
+        self.planes.iter().all(|plane| plane.normal.dot(light_direction) > 0.0 || plane.touches(volume))
+    }
+}
+
+/// Frustums of the drawn viewports, empty to draw everything (ECS resource)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViewFrustums {
+    /// One per drawn viewport
+    pub frustums: Vec<Frustum>,
+    /// Direction the sunlight travels in, None while the sun is off
+    pub light_direction: Option<Vector3<f32>>,
+}
+
+impl ViewFrustums {
+    /// Frustums of the viewports of a layout drawn into a target
+    pub fn from_viewports(viewports: &ViewportSettings, extent: vk::Extent2D) -> Self {
+        let frustums = viewports
+            .rects(extent)
+            .iter()
+            .map(|(rect, viewport)| Frustum::new(&viewports.camera(viewport.kind), rect))
+            .collect();
+        Self { frustums, light_direction: None }
+    }
+
+    /// Also keep the volumes whose shadow from a sun reaches a frustum
+    pub fn with_sun(mut self, sun: &SunSettings) -> Self {
+        self.light_direction = (sun.intensity > 0.0).then(|| -sun.direction());
+        self
+    }
+
+    /// Whether anything inside a volume, or its shadow, is drawn; always without frustums
+    pub fn contains(&self, volume: &BoundingVolume) -> bool {
+        self.frustums.is_empty() || self.frustums.iter().any(|frustum| match self.light_direction {
+            Some(direction) => frustum.intersects_shadow(volume, direction),
+            None => frustum.intersects(volume),
+        })
+    }
+}
+
+/// Mesh space bounding spheres of the meshes uploaded so far (ECS resource)
+///
+/// Filled by the upload system from the renderer; meshes not uploaded yet
+/// have no bounds, so their entities are drawn and the mesh gets loaded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshBounds(pub HashMap<String, (Vector3<f32>, f32)>);
+
+/// Mesh instances drawn against all of them in the latest frame (ECS resource)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    /// Mesh instances drawn
+    pub meshes_visible: usize,
+    /// Mesh instances
+    pub meshes_total: usize,
+}
+
+/// System that keeps the bounding volumes of shapes and meshes up to date
+///
+/// Entities get their volume with the command buffer the first time and
+/// have it rewritten from then on; shapes that became unbounded and meshes
+/// whose bounds are unknown lose it.
+///
+/// # Arguments
+/// * `world` - Shapes, meshes and their transforms
+/// * `commands` - Adds and removes volumes
+/// * `mesh_bounds` - Mesh space bounds of the uploaded meshes
+#[system]
+#[read_component(SDFShape)]
+#[read_component(SDFModifiers)]
+#[read_component(MeshRenderer)]
+#[read_component(Transform)]
+#[write_component(BoundingVolume)]
+pub fn update_bounds(world: &mut SubWorld, commands: &mut CommandBuffer, #[resource] mesh_bounds: &MeshBounds) {
+    let mut shapes = <(Entity, &SDFShape, &Transform, Option<&SDFModifiers>, Option<&mut BoundingVolume>)>::query();
+    for (entity, shape, transform, modifiers, volume) in shapes.iter_mut(world) {
+        set_volume(commands, *entity, volume, BoundingVolume::of_shape(shape, transform, modifiers));
+    }
+
+    let mut meshes = <(Entity, &MeshRenderer, &Transform, Option<&mut BoundingVolume>)>::query();
+    for (entity, renderer, transform, volume) in meshes.iter_mut(world) {
+        let bounds = mesh_bounds.0.get(&renderer.mesh).map(|&bounds| BoundingVolume::of_mesh(bounds, transform));
+        set_volume(commands, *entity, volume, bounds);
+    }
+}
+
+/// Write, add or remove the bounding volume of an entity
+fn set_volume(commands: &mut CommandBuffer, entity: Entity, current: Option<&mut BoundingVolume>, bounds: Option<BoundingVolume>) {
+    match (current, bounds) {
+        (Some(current), Some(bounds)) => *current = bounds,
+        (None, Some(bounds)) => commands.add_component(entity, bounds),
+        (Some(_), None) => commands.remove_component::<BoundingVolume>(entity),
+        (None, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::{Resources, Schedule, World};
+    use crate::vulkan::viewport::ViewKind;
+
+    fn rect(width: u32, height: u32) -> vk::Rect2D {
+        vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: vk::Extent2D { width, height } }
+    }
+
+    fn sphere(center: [f32; 3], radius: f32) -> BoundingVolume {
+        BoundingVolume::Sphere { center: center.into(), radius }
+    }
+
+    #[test]
+    fn test_perspective_frustum_matches_projection() {
+        let camera = ViewCamera { origin: [0.0, 0.0, 0.0], ..ViewCamera::default() };
+        let frustum = Frustum::new(&camera, &rect(200, 100));
+
+        // Twice as wide as high, 90 degrees vertically
+        assert!(frustum.intersects(&sphere([1.9, 0.0, 1.0], 0.0)));
+        assert!(!frustum.intersects(&sphere([2.1, 0.0, 1.0], 0.0)));
+        assert!(!frustum.intersects(&sphere([0.0, 1.1, 1.0], 0.0)));
+        assert!(frustum.intersects(&sphere([0.0, 1.1, 1.0], 0.2)));
+        // Behind the camera
+        assert!(!frustum.intersects(&sphere([0.0, 0.0, -1.0], 0.5)));
+        assert!(frustum.intersects(&BoundingVolume::Aabb { min: Vector3::new(-1.0, -1.0, -1.0), max: Vector3::new(1.0, 1.0, 0.1) }));
+        assert!(!frustum.intersects(&BoundingVolume::Aabb { min: Vector3::new(3.0, -1.0, 1.0), max: Vector3::new(4.0, 1.0, 1.2) }));
+
+        let inside = camera.project(Vector3::new(1.9, 0.0, 1.0), &rect(200, 100));
+        assert!(inside.is_some());
+    }
+
+    #[test]
+    fn test_orthographic_frustum_is_a_box() {
+        let camera = ViewKind::Top.camera();
+        let half_height = config::viewports::ORTHO_HALF_HEIGHT;
+        let frustum = Frustum::new(&camera, &rect(100, 100));
+
+        assert!(frustum.intersects(&sphere([0.0, 0.0, 0.0], 0.1)));
+        assert!(frustum.intersects(&sphere([half_height - 0.1, -5.0, 0.0], 0.0)));
+        assert!(!frustum.intersects(&sphere([half_height + 0.1, 0.0, 0.0], 0.0)));
+        assert!(!frustum.intersects(&sphere([0.0, config::viewports::ORTHO_DISTANCE + 1.0, 0.0], 0.5)));
+    }
+
+    #[test]
+    fn test_no_frustums_draw_everything() {
+        let far_away = sphere([0.0, 0.0, -100.0], 1.0);
+        assert!(ViewFrustums::default().contains(&far_away));
+
+        let frustums = ViewFrustums { frustums: vec![Frustum::new(&ViewCamera::default(), &rect(100, 100))], light_direction: None };
+        assert!(!frustums.contains(&far_away));
+    }
+
+    #[test]
+    fn test_shadows_reaching_a_frustum_are_kept() {
+        let frustums = ViewFrustums {
+            frustums: vec![Frustum::new(&ViewCamera::default(), &rect(100, 100))],
+            light_direction: None,
+        };
+        let origin = Vector3::from(ViewCamera::default().origin);
+        // Above the view and lit from above and behind, so its shadow falls into the view
+        let above = sphere((origin + Vector3::new(0.0, 20.0, 5.0)).into(), 0.5);
+        let behind = sphere((origin - Vector3::new(0.0, 0.0, 5.0)).into(), 0.5);
+        assert!(!frustums.contains(&above));
+
+        let sun = SunSettings { azimuth_deg: 0.0, elevation_deg: 60.0, intensity: 1.0, ..SunSettings::default() };
+        let frustums = frustums.with_sun(&sun);
+        assert!(frustums.contains(&above));
+        assert!(!frustums.contains(&behind));
+        assert!(!frustums.with_sun(&SunSettings { intensity: 0.0, ..sun }).contains(&above));
+    }
+
+    #[test]
+    fn test_rays_enter_volumes_at_their_surface() {
+        let forward = Vector3::new(0.0, 0.0, 1.0);
+        let aabb = BoundingVolume::Aabb { min: Vector3::new(-1.0, -1.0, 2.0), max: Vector3::new(1.0, 1.0, 4.0) };
+        assert_eq!(aabb.ray_distance(Vector3::new(0.5, 0.0, 0.0), forward), Some(2.0));
+        assert_eq!(aabb.ray_distance(Vector3::new(0.0, 0.0, 3.0), forward), Some(0.0));
+        assert_eq!(aabb.ray_distance(Vector3::new(1.5, 0.0, 0.0), forward), None);
+        assert_eq!(aabb.ray_distance(Vector3::new(0.0, 0.0, 5.0), forward), None);
+
+        let ball = sphere([0.0, 0.0, 5.0], 1.0);
+        assert_eq!(ball.ray_distance(Vector3::new(0.0, 0.0, 0.0), forward), Some(4.0));
+        assert_eq!(ball.ray_distance(Vector3::new(0.0, 0.0, 0.0), -forward), None);
+        assert_eq!(ball.aabb(), (Vector3::new(-1.0, -1.0, 4.0), Vector3::new(1.0, 1.0, 6.0)));
+    }
+
+    #[test]
+    fn test_shape_bounds_cover_modifiers() {
+        let transform = Transform::default();
+        let bounds = |modifiers: Vec<SDFModifier>| BoundingVolume::of_shape(&SDFShape::default(), &transform, Some(&SDFModifiers(modifiers)));
+        let extent = |x: f32, y: f32, z: f32| {
+            let extents = Vector3::new(x, y, z).add_element_wise(config::culling::SHAPE_MARGIN);
+            Some(BoundingVolume::Aabb { min: -extents, max: extents })
+        };
+
+        assert_eq!(bounds(vec![]), extent(1.0, 1.0, 1.0));
+        assert_eq!(bounds(vec![SDFModifier::Round { radius: 0.5 }, SDFModifier::Noise { amplitude: 0.25, frequency: 4.0 }]), extent(1.75, 1.75, 1.75));
+        // Copies on each side of the original along the repeated axes
+        assert_eq!(bounds(vec![SDFModifier::Repeat { spacing: [2.0, 0.0, 3.0], count: 2 }]), extent(5.0, 1.0, 7.0));
+        // Endless repeats and warped shapes can be anywhere
+        assert_eq!(bounds(vec![SDFModifier::Repeat { spacing: [2.0, 0.0, 0.0], count: 0 }]), None);
+        assert_eq!(bounds(vec![SDFModifier::Repeat { spacing: [0.0, 0.0, 0.0], count: 0 }]), extent(1.0, 1.0, 1.0));
+        assert_eq!(bounds(vec![SDFModifier::Bend { rate: 0.5 }]), None);
+        assert_eq!(bounds(vec![SDFModifier::Twist { rate: 1.0 }]), None);
+    }
+
+    #[test]
+    fn test_update_bounds_follows_shapes_and_known_meshes() {
+        let mut world = World::default();
+        let transform = Transform { position: Vector3::new(1.0, 2.0, 3.0), ..Transform::default() };
+        let shape = world.push((SDFShape::default(), transform.clone()));
+        let mesh = |path: &str| MeshRenderer { mesh: path.to_string(), color: [1.0; 3] };
+        let cube = world.push((mesh("meshes/cube.obj"), transform.clone()));
+        let unknown = world.push((mesh("meshes/missing.obj"), transform));
+
+        let mut resources = Resources::default();
+        let mut bounds = MeshBounds::default();
+        bounds.0.insert("meshes/cube.obj".to_string(), (Vector3::new(0.0, 0.0, 0.0), 0.5));
+        resources.insert(bounds);
+        let mut schedule = Schedule::builder().add_system(update_bounds_system()).build();
+        schedule.execute(&mut world, &mut resources);
+
+        let volume = |world: &World, entity| world.entry_ref(entity).unwrap().get_component::<BoundingVolume>().ok().copied();
+        let extent = 1.0 + config::culling::SHAPE_MARGIN;
+        assert_eq!(volume(&world, shape), Some(BoundingVolume::Aabb {
+            min: Vector3::new(1.0 - extent, 2.0 - extent, 3.0 - extent),
+            max: Vector3::new(1.0 + extent, 2.0 + extent, 3.0 + extent),
+        }));
+        assert_eq!(volume(&world, cube), Some(sphere([1.0, 2.0, 3.0], 0.5)));
+        assert_eq!(volume(&world, unknown), None);
+
+        // Moved entities get their volume rewritten
+        world.entry(cube).unwrap().get_component_mut::<Transform>().unwrap().position = Vector3::new(0.0, 0.0, 0.0);
+        schedule.execute(&mut world, &mut resources);
+        assert_eq!(volume(&world, cube), Some(sphere([0.0, 0.0, 0.0], 0.5)));
+    }
+}
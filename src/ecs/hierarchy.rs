@@ -0,0 +1,222 @@
+//! Transform hierarchy
+//!
+//! Entities with a `Parent` are positioned by their `LocalTransform`; the
+//! propagation system walks down from every root each frame and writes the
+//! resulting world transform into `Transform`, which is what rendering
+//! reads. Moving or rotating a parent therefore moves its whole subtree.
+//! `set_parent` re-parents an entity while keeping it where it is in the
+//! world, and keeps `Parent` and `Children` consistent.
+
+use std::collections::HashSet;
+use legion::{system, Entity, EntityStore, IntoQuery, World};
+use legion::world::SubWorld;
+use log::{debug, warn};
+use crate::ecs::components::{Children, LocalTransform, Parent, SDFShape, Transform};
+use crate::error::{EcsError, Result};
+use crate::hud::hierarchy_panel::HierarchyNodeView;
+
+/// System that computes the world transforms of child entities
+///
+/// # Arguments
+/// * `world` - The hierarchy and transform components of the ECS world
+#[system]
+#[read_component(Parent)]
+#[read_component(Children)]
+#[read_component(LocalTransform)]
+#[write_component(Transform)]
+pub fn hierarchy_propagation(world: &mut SubWorld) {
+    let mut stack: Vec<(Transform, Entity)> = <(&Transform, &Children, Option<&Parent>)>::query()
+        .iter(world)
+        .filter(|(_, _, parent)| parent.is_none())
+        .flat_map(|(transform, children, _)| children.0.iter().map(move |&child| (transform.clone(), child)))
+        .collect();
+
+    // Guards against cycles created by editing components directly
+    let mut visited = HashSet::new();
+    while let Some((parent_transform, entity)) = stack.pop() {
+        if !visited.insert(entity) {
+            warn!("Transform hierarchy cycle at {:?}", entity);
+            continue;
+        }
+        let Ok(mut entry) = world.entry_mut(entity) else {
+            continue;
+        };
+        let Ok(local) = entry.get_component::<LocalTransform>() else {
+            continue;
+        };
+        let transform = parent_transform.compose(&local.0);
+        let children = entry.get_component::<Children>().map(|children| children.0.clone()).unwrap_or_default();
+        if let Ok(world_transform) = entry.get_component_mut::<Transform>() {
+            *world_transform = transform.clone();
+        }
+        stack.extend(children.into_iter().map(|child| (transform.clone(), child)));
+    }
+}
+
+/// Parent of an entity, if it has one
+fn parent_of(world: &World, entity: Entity) -> Option<Entity> {
+    world.entry_ref(entity).ok()?.get_component::<Parent>().ok().map(|parent| parent.0)
+}
+
+/// Whether `ancestor` is `entity` or one of its ancestors
+pub fn is_ancestor(world: &World, ancestor: Entity, entity: Entity) -> bool {
+    let mut current = Some(entity);
+    let mut steps = 0;
+    while let Some(node) = current {
+        if node == ancestor {
+            return true;
+        }
+        // A cycle cannot contain more links than there are entities
+        steps += 1;
+        if steps > world.len() {
+            return false;
+        }
+        current = parent_of(world, node);
+    }
+    false
+}
+
+/// World transform of an entity
+fn world_transform(world: &World, entity: Entity) -> Result<Transform> {
+    world.entry_ref(entity)
+        .map_err(|e| EcsError::ResourceAccess(format!("Entity {:?} not found: {:?}", entity, e)))?
+        .get_component::<Transform>()
+        .cloned()
+        .map_err(|_| EcsError::ResourceAccess(format!("Entity {:?} has no transform", entity)).into())
+}
+
+/// Make `child` a child of `parent` with the given local transform
+///
+/// The child must not have a parent yet.
+pub fn attach(world: &mut World, child: Entity, parent: Entity, local: Transform) -> Result<()> {
+    if world.entry_ref(child).is_err() {
+        return Err(EcsError::ResourceAccess(format!("Child {:?} not found", child)).into());
+    }
+    {
+        let mut parent_entry = world.entry(parent)
+            .ok_or_else(|| EcsError::ResourceAccess(format!("Parent {:?} not found", parent)))?;
+        match parent_entry.get_component_mut::<Children>() {
+            Ok(children) => children.0.push(child),
+            Err(_) => parent_entry.add_component(Children(vec![child])),
+        }
+    }
+
+    if let Some(mut child_entry) = world.entry(child) {
+        child_entry.add_component(Parent(parent));
+        child_entry.add_component(LocalTransform(local));
+    }
+    Ok(())
+}
+
+/// Remove an entity from its parent's children and drop its `Parent`
+fn detach(world: &mut World, child: Entity) {
+    let Some(parent) = parent_of(world, child) else {
+        return;
+    };
+    if let Some(mut parent_entry) = world.entry(parent) {
+        if let Ok(children) = parent_entry.get_component_mut::<Children>() {
+            children.0.retain(|&existing| existing != child);
+        }
+    }
+    if let Some(mut child_entry) = world.entry(child) {
+        child_entry.remove_component::<Parent>();
+        child_entry.remove_component::<LocalTransform>();
+    }
+}
+
+/// Re-parent an entity, keeping its world transform
+///
+/// # Arguments
+/// * `world` - The ECS world
+/// * `child` - Entity to move in the hierarchy
+/// * `parent` - New parent, or None to make the entity a root
+///
+/// # Errors
+/// Returns an error if an entity does not exist or has no transform, or if
+/// the new parent is the child itself or one of its descendants
+pub fn set_parent(world: &mut World, child: Entity, parent: Option<Entity>) -> Result<()> {
+    if parent_of(world, child) == parent {
+        return Ok(());
+    }
+    let child_transform = world_transform(world, child)?;
+    let local = match parent {
+        Some(parent) => {
+            if is_ancestor(world, child, parent) {
+                return Err(EcsError::EntityCreation(format!(
+                    "Cannot parent {:?} to its own descendant {:?}", child, parent
+                )).into());
+            }
+            Some(child_transform.relative_to(&world_transform(world, parent)?))
+        }
+        None => None,
+    };
+
+    detach(world, child);
+    if let (Some(parent), Some(local)) = (parent, local) {
+        attach(world, child, parent, local)?;
+    }
+    debug!("Parent of {:?} set to {:?}", child, parent);
+    Ok(())
+}
+
+/// Build a snapshot of the hierarchy for the hierarchy panel
+///
+/// Lists every entity with a transform: shapes and empty groups.
+pub fn collect_hierarchy(world: &World) -> Vec<HierarchyNodeView> {
+    <(Entity, &Transform, Option<&Parent>, Option<&Children>, Option<&SDFShape>)>::query()
+        .iter(world)
+        .map(|(&entity, transform, parent, children, shape)| {
+            HierarchyNodeView {
+                entity,
+                label: shape.map(|shape| format!("{:?}", shape.shape_type)).unwrap_or_else(|| "Group".to_string()),
+                parent: parent.map(|parent| parent.0),
+                children: children.map(|children| children.0.clone()).unwrap_or_default(),
+                position: transform.position,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+    use legion::{Resources, Schedule};
+
+    fn at(x: f32) -> Transform {
+        Transform { position: Vector3::new(x, 0.0, 0.0), ..Transform::default() }
+    }
+
+    #[test]
+    fn test_propagation_moves_the_subtree() {
+        let mut world = World::default();
+        let root = world.push((at(1.0),));
+        let child = world.push((at(0.0),));
+        let grandchild = world.push((at(0.0),));
+        attach(&mut world, child, root, at(1.0)).unwrap();
+        attach(&mut world, grandchild, child, at(1.0)).unwrap();
+
+        let mut schedule = Schedule::builder().add_system(hierarchy_propagation_system()).build();
+        schedule.execute(&mut world, &mut Resources::default());
+
+        assert_eq!(world_transform(&world, grandchild).unwrap().position, Vector3::new(3.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_set_parent_keeps_world_position_and_rejects_cycles() {
+        let mut world = World::default();
+        let a = world.push((at(2.0),));
+        let b = world.push((at(5.0),));
+
+        set_parent(&mut world, b, Some(a)).unwrap();
+        let local = world.entry_ref(b).unwrap().get_component::<LocalTransform>().unwrap().clone();
+        assert_eq!(local.0.position, Vector3::new(3.0, 0.0, 0.0));
+        assert_eq!(world.entry_ref(a).unwrap().get_component::<Children>().unwrap().0, vec![b]);
+
+        assert!(set_parent(&mut world, a, Some(b)).is_err());
+
+        set_parent(&mut world, b, None).unwrap();
+        assert_eq!(parent_of(&world, b), None);
+        assert!(world.entry_ref(a).unwrap().get_component::<Children>().unwrap().0.is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+//! World space labels
+//!
+//! Entities with a `Label` and a `Transform` show a line of text next to
+//! them in every viewport. The label system resolves the text and world
+//! position of each label every frame; the HUD label overlay projects them
+//! through the viewport cameras and draws them with ImGui.
+//!
+//! Label text may contain `{position}`, replaced with the entity's current
+//! position, to watch a moving entity.
+
+use cgmath::Vector3;
+use legion::{system, IntoQuery};
+use legion::world::SubWorld;
+use crate::ecs::components::{SDFDisplayMode, SDFVisibility, Transform};
+use crate::hud::label_overlay::WorldLabel;
+
+/// Text shown next to an entity in the viewport
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    /// Text, `{position}` is replaced with the entity position
+    pub text: String,
+    /// World space offset from the entity position
+    pub offset: Vector3<f32>,
+    /// Text color (RGBA)
+    pub color: [f32; 4],
+}
+
+impl Label {
+    /// Create a white label at the entity position
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            offset: Vector3::new(0.0, 0.0, 0.0),
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    /// Text with placeholders resolved for an entity at `position`
+    pub fn resolve(&self, position: Vector3<f32>) -> String {
+        if !self.text.contains("{position}") {
+            return self.text.clone();
+        }
+        let position = format!("({:.2}, {:.2}, {:.2})", position.x, position.y, position.z);
+        self.text.replace("{position}", &position)
+    }
+}
+
+/// Labels of the current frame (ECS resource)
+#[derive(Clone, Debug, Default)]
+pub struct LabelList(pub Vec<WorldLabel>);
+
+/// System that collects the labels of visible entities
+///
+/// # Arguments
+/// * `world` - Labels, transforms and visibility
+/// * `labels` - Replaced with this frame's labels
+#[system]
+#[read_component(Label)]
+#[read_component(Transform)]
+#[read_component(SDFVisibility)]
+pub fn build_labels(world: &SubWorld, #[resource] labels: &mut LabelList) {
+    labels.0 = <(&Label, &Transform, Option<&SDFVisibility>)>::query()
+        .iter(world)
+        .filter(|(_, _, visibility)| visibility.is_none_or(|v| v.display_mode() == SDFDisplayMode::Visible))
+        .map(|(label, transform, _)| WorldLabel {
+            position: transform.position + label.offset,
+            text: label.resolve(transform.position),
+            color: label.color,
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::{Resources, Schedule, World};
+
+    #[test]
+    fn test_build_labels_skips_hidden_entities() {
+        let mut world = World::default();
+        let transform = Transform { position: Vector3::new(1.0, 2.0, 3.0), ..Transform::default() };
+        let label = Label { offset: Vector3::new(0.0, 0.5, 0.0), ..Label::new("at {position}") };
+        world.push((label.clone(), transform.clone()));
+        world.push((label, transform, SDFVisibility { hidden: true, ..SDFVisibility::default() }));
+
+        let mut resources = Resources::default();
+        resources.insert(LabelList::default());
+        let mut schedule = Schedule::builder().add_system(build_labels_system()).build();
+        schedule.execute(&mut world, &mut resources);
+
+        let labels = resources.get::<LabelList>().unwrap();
+        assert_eq!(labels.0.len(), 1);
+        assert_eq!(labels.0[0].text, "at (1.00, 2.00, 3.00)");
+        assert_eq!(labels.0[0].position, Vector3::new(1.0, 2.5, 3.0));
+    }
+}
@@ -0,0 +1,134 @@
+//! Render layers
+//!
+//! Every SDF shape, CSG composite, mesh and text is on one or more of the
+//! `config::layers::COUNT` layers (the first one, unless it has a
+//! `RenderLayers` component), and every viewport shows a set of layers, so
+//! scaffolding geometry can be hidden while working. The scene data, mesh
+//! and text systems leave out entities on no layer that a drawn viewport
+//! shows (the `VisibleLayers` resource). The layers of the shapes and texts
+//! left are written into the scene buffer, where `shapeActive` and
+//! `textActive` in `shaders/sdf.frag` compare them with the layers of the
+//! viewport being drawn; meshes are batched by layers and each viewport
+//! draws the batches it shows (see `vulkan::mesh_pass`). A composite's
+//! layers apply to its operands. Layers are numbered from 1 in the scene
+//! file (an entry's `layers` array) and in the layers panel.
+
+use legion::{Entity, IntoQuery, World};
+use log::debug;
+use crate::config;
+use crate::ecs::components::{CsgNode, SDFShape};
+use crate::ecs::mesh::MeshRenderer;
+use crate::ecs::text::Text3D;
+use crate::hud::layers_panel::{LayerEdit, LayerEntityKind, LayerEntityView};
+
+/// Layers an entity is on, bit n for layer n + 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLayers(pub u32);
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self(config::layers::DEFAULT)
+    }
+}
+
+impl RenderLayers {
+    /// Layers from their numbers, starting at 1
+    ///
+    /// Numbers outside 1 to `config::layers::COUNT` are ignored; no numbers at all
+    /// give the default layer.
+    pub fn from_numbers(numbers: &[u32]) -> Self {
+        if numbers.is_empty() {
+            return Self::default();
+        }
+        Self(numbers
+            .iter()
+            .filter(|&&number| (1..=config::layers::COUNT).contains(&number))
+            .fold(0, |mask, number| mask | (1 << (number - 1))))
+    }
+
+    /// Numbers of the layers, starting at 1
+    pub fn numbers(self) -> Vec<u32> {
+        (1..=config::layers::COUNT).filter(|number| self.0 & (1 << (number - 1)) != 0).collect()
+    }
+
+    /// Whether any of the layers is in a mask
+    pub fn is_visible_in(self, mask: u32) -> bool {
+        self.0 & mask != 0
+    }
+}
+
+/// Layers shown by at least one drawn viewport (ECS resource)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisibleLayers(pub u32);
+
+impl Default for VisibleLayers {
+    fn default() -> Self {
+        Self(config::layers::ALL)
+    }
+}
+
+/// Snapshot of the layers of the shapes, composites, meshes and texts for the layers panel
+pub fn collect_layer_entities(world: &World) -> Vec<LayerEntityView> {
+    let view = |entity: &Entity, kind: LayerEntityKind, layers: Option<&RenderLayers>| LayerEntityView {
+        entity: *entity,
+        kind,
+        layers: layers.copied().unwrap_or_default().0,
+    };
+    let shapes = <(Entity, &SDFShape, Option<&RenderLayers>)>::query()
+        .iter(world)
+        .map(|(entity, shape, layers)| view(entity, LayerEntityKind::Shape(shape.shape_type.clone()), layers))
+        .collect::<Vec<_>>();
+    let composites = <(Entity, &CsgNode, Option<&RenderLayers>)>::query()
+        .iter(world)
+        .map(|(entity, _, layers)| view(entity, LayerEntityKind::Composite, layers))
+        .collect::<Vec<_>>();
+    let meshes = <(Entity, &MeshRenderer, Option<&RenderLayers>)>::query()
+        .iter(world)
+        .map(|(entity, _, layers)| view(entity, LayerEntityKind::Mesh, layers))
+        .collect::<Vec<_>>();
+    let texts = <(Entity, &Text3D, Option<&RenderLayers>)>::query()
+        .iter(world)
+        .map(|(entity, _, layers)| view(entity, LayerEntityKind::Text, layers))
+        .collect::<Vec<_>>();
+    shapes.into_iter().chain(composites).chain(meshes).chain(texts).collect()
+}
+
+/// Apply an edit from the layers panel
+pub fn apply_layer_edit(world: &mut World, edit: LayerEdit) {
+    debug!("Layer edit applied: {:?}", edit);
+    if let Some(mut entry) = world.entry(edit.entity) {
+        entry.add_component(RenderLayers(edit.layers));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_numbers_round_trip() {
+        assert_eq!(RenderLayers::from_numbers(&[]), RenderLayers::default());
+        assert_eq!(RenderLayers::from_numbers(&[1, 3]), RenderLayers(0b101));
+        assert_eq!(RenderLayers(0b101).numbers(), [1, 3]);
+        // Out of range numbers leave the entity on no layer
+        assert_eq!(RenderLayers::from_numbers(&[0, config::layers::COUNT + 1]), RenderLayers(0));
+        assert!(RenderLayers::default().is_visible_in(config::layers::ALL));
+        assert!(!RenderLayers(0b10).is_visible_in(0b01));
+    }
+
+    #[test]
+    fn test_edits_set_the_layers() {
+        let mut world = World::default();
+        let shape = world.push((SDFShape::default(),));
+        let composite = world.push((CsgNode { operands: vec![shape], ..CsgNode::default() },));
+        let text = world.push((Text3D::default(),));
+
+        apply_layer_edit(&mut world, LayerEdit { entity: composite, layers: 0b110 });
+        apply_layer_edit(&mut world, LayerEdit { entity: text, layers: 0b10 });
+        let views = collect_layer_entities(&world);
+        assert_eq!(views.len(), 3);
+        assert_eq!((views[0].entity, views[0].layers), (shape, config::layers::DEFAULT));
+        assert_eq!((views[1].entity, views[1].kind.clone(), views[1].layers), (composite, LayerEntityKind::Composite, 0b110));
+        assert_eq!((views[2].entity, views[2].kind.clone(), views[2].layers), (text, LayerEntityKind::Text, 0b10));
+    }
+}
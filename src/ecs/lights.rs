@@ -0,0 +1,167 @@
+//! Scene lights
+//!
+//! Entities with a `Light` light the SDF scene: directional lights shine
+//! along a direction from infinitely far away, point lights in every
+//! direction from a position and spot lights in a cone around a direction.
+//! Point and spot lights fade out towards their radius. The scene data
+//! system writes up to `config::rendering::MAX_SDF_LIGHTS` lights into the
+//! scene buffer, and `calculateLighting` in `shaders/sdf.frag` iterates
+//! them (on top of the environment's sun, which also lights the sky).
+//! Lights are stored in the scene file's `[[lights]]` tables, edited in the
+//! lights panel and shown as gizmos in the viewports.
+
+use cgmath::{InnerSpace, Vector3};
+use legion::{Entity, IntoQuery, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use crate::config;
+use crate::hud::lights_panel::{LightEdit, LightView};
+
+/// How a light shines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightKind {
+    /// Parallel rays along the light's direction, from infinitely far away
+    Directional,
+    /// Rays in every direction from the light's position
+    #[default]
+    Point,
+    /// Rays in a cone around the light's direction from its position
+    Spot,
+}
+
+impl LightKind {
+    /// All kinds, in the order shown in the editor
+    pub const ALL: [LightKind; 3] = [LightKind::Directional, LightKind::Point, LightKind::Spot];
+
+    /// Human readable name
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightKind::Directional => "Directional",
+            LightKind::Point => "Point",
+            LightKind::Spot => "Spot",
+        }
+    }
+
+    /// Value written to the scene buffer (matches LIGHT_* defines in sdf.frag)
+    pub fn as_gpu(&self) -> i32 {
+        match self {
+            LightKind::Directional => 0,
+            LightKind::Point => 1,
+            LightKind::Spot => 2,
+        }
+    }
+
+    /// Whether the light's position matters
+    pub fn has_position(&self) -> bool {
+        *self != LightKind::Directional
+    }
+
+    /// Whether the light's direction matters
+    pub fn has_direction(&self) -> bool {
+        *self != LightKind::Point
+    }
+}
+
+/// A light of the SDF scene
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Position of a point or spot light
+    pub position: Vector3<f32>,
+    /// Direction a directional or spot light shines in
+    pub direction: Vector3<f32>,
+    /// Linear RGB (see the `color` module)
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    /// Distance at which a point or spot light has faded out, 0 for no falloff
+    pub radius: f32,
+    /// Half angle of a spot light's cone in degrees
+    pub spot_angle_deg: f32,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            position: Vector3::new(2.0, 2.0, 2.0),
+            direction: Vector3::new(-1.0, -1.0, -1.0).normalize(),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+            radius: 0.0,
+            spot_angle_deg: config::lights::DEFAULT_SPOT_ANGLE_DEG,
+        }
+    }
+}
+
+impl Light {
+    /// Cosines of the spot cone's outer and inner half angles
+    ///
+    /// The light fades out between the two, over the last
+    /// `config::lights::SPOT_SOFTNESS` of the cone. The angle is kept
+    /// between 1 and 90 degrees.
+    pub fn spot_cosines(&self) -> (f32, f32) {
+        let outer = self.spot_angle_deg.clamp(1.0, 90.0).to_radians();
+        let inner = outer * (1.0 - config::lights::SPOT_SOFTNESS);
+        (outer.cos(), inner.cos())
+    }
+}
+
+/// Snapshot of the lights for the lights panel and its gizmos
+pub fn collect_lights(world: &World) -> Vec<LightView> {
+    <(Entity, &Light)>::query()
+        .iter(world)
+        .map(|(entity, light)| LightView { entity: *entity, light: light.clone() })
+        .collect()
+}
+
+/// Apply an edit from the lights panel
+pub fn apply_light_edit(world: &mut World, edit: LightEdit) {
+    debug!("Light edit applied: {:?}", edit);
+    match edit {
+        LightEdit::Add { kind, position } => {
+            world.push((Light { kind, position: position.into(), ..Light::default() },));
+        }
+        LightEdit::Set { entity, light } => {
+            if let Some(mut entry) = world.entry(entity) {
+                if let Ok(component) = entry.get_component_mut::<Light>() {
+                    *component = light;
+                }
+            }
+        }
+        LightEdit::Remove { entity } => {
+            world.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spot_cone_fades_inside_its_angle() {
+        let light = Light { kind: LightKind::Spot, spot_angle_deg: 30.0, ..Light::default() };
+        let (outer, inner) = light.spot_cosines();
+        assert!((outer - 30f32.to_radians().cos()).abs() < 1e-6);
+        assert!(inner > outer && inner < 1.0);
+        assert_eq!(Light { spot_angle_deg: 120.0, ..light }.spot_cosines().0, 90f32.to_radians().cos());
+    }
+
+    #[test]
+    fn test_edits_add_set_and_remove_lights() {
+        let mut world = World::default();
+        apply_light_edit(&mut world, LightEdit::Add { kind: LightKind::Spot, position: [0.0, 3.0, 0.0] });
+        let views = collect_lights(&world);
+        assert_eq!(views.len(), 1);
+        assert_eq!((views[0].light.kind, views[0].light.position), (LightKind::Spot, Vector3::new(0.0, 3.0, 0.0)));
+
+        let entity = views[0].entity;
+        let light = Light { intensity: 4.0, ..views[0].light.clone() };
+        apply_light_edit(&mut world, LightEdit::Set { entity, light: light.clone() });
+        assert_eq!(collect_lights(&world)[0].light, light);
+
+        apply_light_edit(&mut world, LightEdit::Remove { entity });
+        assert!(collect_lights(&world).is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+//! SDF shape materials
+//!
+//! Snapshot and edits of the `SDFMaterial` components for the materials
+//! panel. Besides color, metallic, roughness and emission a material may
+//! reflect the scene (`reflectivity`) or let light through (`transparency`,
+//! bent by `ior`); those are written into the scene buffer's `optics` and
+//! only traced by the reflections shader variant, so scenes without them
+//! pay nothing for the secondary rays.
+
+use legion::{Entity, IntoQuery, World};
+use log::debug;
+use crate::ecs::components::{SDFMaterial, SDFShape};
+use crate::hud::materials_panel::{MaterialEdit, MaterialView};
+
+/// Snapshot of the shape materials for the materials panel
+pub fn collect_materials(world: &World) -> Vec<MaterialView> {
+    <(Entity, &SDFShape, &SDFMaterial)>::query()
+        .iter(world)
+        .map(|(entity, shape, material)| MaterialView {
+            entity: *entity,
+            shape_type: shape.shape_type.clone(),
+            material: material.clone(),
+        })
+        .collect()
+}
+
+/// Apply an edit from the materials panel
+pub fn apply_material_edit(world: &mut World, edit: MaterialEdit) {
+    debug!("Material edit applied: {:?}", edit);
+    if let Some(mut entry) = world.entry(edit.entity) {
+        if let Ok(material) = entry.get_component_mut::<SDFMaterial>() {
+            *material = edit.material;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_replace_the_material() {
+        let mut world = World::default();
+        let shape = world.push((SDFShape::default(), SDFMaterial::default()));
+        world.push((SDFMaterial::default(),));
+
+        let glass = SDFMaterial { transparency: 0.9, ior: 1.33, ..SDFMaterial::default() };
+        apply_material_edit(&mut world, MaterialEdit { entity: shape, material: glass.clone() });
+        let views = collect_materials(&world);
+        assert_eq!(views.len(), 1);
+        assert_eq!((views[0].entity, &views[0].material), (shape, &glass));
+    }
+}
@@ -0,0 +1,187 @@
+//! Mesh entities
+//!
+//! Entities with a `MeshRenderer` and a `Transform` are drawn with the mesh
+//! pipeline after the SDF scene, depth tested against the raymarched
+//! surfaces. The draw list is rebuilt every frame, without the instances
+//! outside every viewport's frustum whose sun shadow misses them too or on
+//! no layer a drawn viewport shows (see `ecs::layers`), and handed to the
+//! renderer by the upload system.
+//!
+//! Each of them also gets a `MeshAsset` holding a handle to its mesh file,
+//! which keeps the mesh in memory, and reloaded after its file changed, for
+//! as long as an entity draws it.
+
+use std::path::Path;
+use legion::{system, Entity, IntoQuery, World};
+use legion::world::SubWorld;
+use log::debug;
+use crate::assets::{AssetManager, Handle};
+use crate::ecs::components::Transform;
+use crate::ecs::culling::{CullingStats, ViewFrustums};
+use crate::ecs::layers::{RenderLayers, VisibleLayers};
+use crate::ecs::spatial::SpatialIndex;
+use crate::jobs::JobPool;
+use crate::vulkan::mesh::{MeshData, MeshDrawList};
+
+/// Triangle mesh drawn at the entity's transform
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshRenderer {
+    /// Path of the `.obj`, `.gltf` or `.glb` file
+    pub mesh: String,
+    /// Base color (RGB)
+    pub color: [f32; 3],
+}
+
+/// Handle to the mesh file of the entity's `MeshRenderer`, attached by `attach_mesh_assets`
+#[derive(Clone, Debug)]
+pub struct MeshAsset(pub Handle<MeshData>);
+
+/// Give every mesh renderer a handle to its mesh, loading new mesh files
+///
+/// Runs at the start of an update, so meshes start loading as soon as their
+/// entities appear, also while they are culled. A renderer whose mesh path
+/// changed gets a new handle.
+///
+/// # Arguments
+/// * `world` - Mesh renderers to attach the handles to
+/// * `assets` - Asset manager the meshes are loaded by
+/// * `jobs` - Pool new mesh files are read on
+pub fn attach_mesh_assets(world: &mut World, assets: &mut AssetManager, jobs: &JobPool) {
+    let stale: Vec<(Entity, String)> = <(Entity, &MeshRenderer, Option<&MeshAsset>)>::query()
+        .iter(world)
+        .filter(|(_, renderer, asset)| !asset.is_some_and(|asset| asset.0.path() == Path::new(&renderer.mesh)))
+        .map(|(entity, renderer, _)| (*entity, renderer.mesh.clone()))
+        .collect();
+    for (entity, mesh) in stale {
+        let handle = assets.meshes.load(jobs, Path::new(&mesh));
+        if let Some(mut entry) = world.entry(entity) {
+            entry.add_component(MeshAsset(handle));
+        }
+    }
+}
+
+/// System that collects the mesh draws of the frame
+///
+/// # Arguments
+/// * `world` - Mesh renderers, their transforms and layers
+/// * `frustums` - Frustums of the drawn viewports
+/// * `index` - Spatial index that finds the instances outside them
+/// * `visible_layers` - Layers shown by the drawn viewports
+/// * `culling` - Mesh instances counted as visible and in total
+/// * `draws` - Draw list replaced with this frame's meshes
+#[system]
+#[read_component(MeshRenderer)]
+#[read_component(Transform)]
+#[read_component(RenderLayers)]
+pub fn build_mesh_draws(
+    world: &SubWorld,
+    #[resource] frustums: &ViewFrustums,
+    #[resource] index: &SpatialIndex,
+    #[resource] visible_layers: &VisibleLayers,
+    #[resource] culling: &mut CullingStats,
+    #[resource] draws: &mut MeshDrawList,
+) {
+    let culled = index.culled(frustums);
+    let mut list = MeshDrawList::new();
+    let mut total = 0;
+    for (entity, renderer, transform, layers) in <(Entity, &MeshRenderer, &Transform, Option<&RenderLayers>)>::query().iter(world) {
+        total += 1;
+        let layers = layers.copied().unwrap_or_default();
+        if !culled.contains(entity) && layers.is_visible_in(visible_layers.0) {
+            list.push(&renderer.mesh, transform.matrix(), renderer.color, layers.0);
+        }
+    }
+
+    if (culling.meshes_visible, culling.meshes_total) != (list.draws().len(), total) {
+        debug!("Mesh draws: {} of {}", list.draws().len(), total);
+    }
+    culling.meshes_visible = list.draws().len();
+    culling.meshes_total = total;
+    *draws = list;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+    use legion::{Resources, Schedule, World};
+    use crate::ecs::culling::BoundingVolume;
+    use crate::vulkan::viewport::ViewportSettings;
+
+    #[test]
+    fn test_build_mesh_draws_uses_entity_transform() {
+        let mut world = World::default();
+        let transform = Transform { position: Vector3::new(1.0, 2.0, 3.0), ..Transform::default() };
+        world.push((MeshRenderer { mesh: "meshes/cube.obj".to_string(), color: [1.0, 0.5, 0.0] }, transform));
+        world.push((Transform::default(),));
+        world.push((MeshRenderer { mesh: "meshes/bunny.obj".to_string(), color: [1.0; 3] }, Transform::default(), RenderLayers(0b10)));
+
+        let mut resources = Resources::default();
+        resources.insert(ViewFrustums::default());
+        resources.insert(SpatialIndex::default());
+        resources.insert(VisibleLayers(0b01));
+        resources.insert(CullingStats::default());
+        resources.insert(MeshDrawList::new());
+        let mut schedule = Schedule::builder().add_system(build_mesh_draws_system()).build();
+        schedule.execute(&mut world, &mut resources);
+
+        let draws = resources.get::<MeshDrawList>().unwrap();
+        assert_eq!(draws.draws().len(), 1);
+        assert_eq!(draws.draws()[0].mesh, "meshes/cube.obj");
+        assert_eq!(draws.draws()[0].model[3], [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(draws.draws()[0].color, [1.0, 0.5, 0.0, 1.0]);
+        assert_eq!(draws.draws()[0].layers, crate::config::layers::DEFAULT);
+    }
+
+    #[test]
+    fn test_meshes_behind_the_camera_are_culled() {
+        let mut world = World::default();
+        let renderer = MeshRenderer { mesh: "meshes/cube.obj".to_string(), color: [1.0; 3] };
+        let sphere_at = |z: f32| BoundingVolume::Sphere { center: Vector3::new(0.0, 0.0, z), radius: 0.5 };
+        let origin_z = crate::config::rendering::SDF_CAMERA_ORIGIN[2];
+        let ahead = world.push((renderer.clone(), Transform::default()));
+        let behind = world.push((renderer.clone(), Transform::default()));
+        world.push((renderer, Transform::default()));
+        let mut index = SpatialIndex::default();
+        index.insert(ahead, sphere_at(origin_z + 5.0));
+        index.insert(behind, sphere_at(origin_z - 5.0));
+
+        let mut resources = Resources::default();
+        resources.insert(index);
+        resources.insert(VisibleLayers::default());
+        let extent = ash::vk::Extent2D { width: 100, height: 100 };
+        resources.insert(ViewFrustums::from_viewports(&ViewportSettings::default(), extent));
+        resources.insert(CullingStats::default());
+        resources.insert(MeshDrawList::new());
+        let mut schedule = Schedule::builder().add_system(build_mesh_draws_system()).build();
+        schedule.execute(&mut world, &mut resources);
+
+        assert_eq!(resources.get::<MeshDrawList>().unwrap().draws().len(), 2);
+        let culling = *resources.get::<CullingStats>().unwrap();
+        assert_eq!((culling.meshes_visible, culling.meshes_total), (2, 3));
+    }
+
+    #[test]
+    fn test_mesh_assets_follow_the_renderer() {
+        let jobs = JobPool::with_workers(1).unwrap();
+        let mut assets = AssetManager::new();
+        let mut world = World::default();
+        let entity = world.push((MeshRenderer { mesh: "missing/a.obj".to_string(), color: [1.0; 3] }, Transform::default()));
+        let handle_path = |world: &World| world.entry_ref(entity).unwrap().get_component::<MeshAsset>().unwrap().0.path().to_path_buf();
+
+        attach_mesh_assets(&mut world, &mut assets, &jobs);
+        assert_eq!(handle_path(&world), Path::new("missing/a.obj"));
+
+        world.entry(entity).unwrap().get_component_mut::<MeshRenderer>().unwrap().mesh = "missing/b.obj".to_string();
+        attach_mesh_assets(&mut world, &mut assets, &jobs);
+        assert_eq!(handle_path(&world), Path::new("missing/b.obj"));
+
+        // The first mesh is released once its job ended, the second stays while the entity holds it
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while assets.meshes.state(Path::new("missing/a.obj")).is_some() && std::time::Instant::now() < deadline {
+            assets.poll();
+        }
+        assert_eq!(assets.meshes.state(Path::new("missing/a.obj")), None);
+        assert!(assets.meshes.state(Path::new("missing/b.obj")).is_some());
+    }
+}
@@ -1,5 +1,20 @@
 pub mod components;
 pub mod systems;
+pub mod hierarchy;
+pub mod animation;
+pub mod physics;
+pub mod mesh;
+pub mod particles;
+pub mod text;
+pub mod modifiers;
+pub mod layers;
+pub mod culling;
+pub mod spatial;
+pub mod lights;
+pub mod materials;
+pub mod labels;
 pub mod world;
+pub mod reload;
+pub mod scene_file;
 
 pub use world::*;
\ No newline at end of file
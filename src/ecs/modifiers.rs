@@ -0,0 +1,171 @@
+//! SDF modifier stacks
+//!
+//! Shape entities with an `SDFModifiers` component have their distance
+//! field changed by a stack of modifiers, applied in order. Domain modifiers
+//! (twist, bend, repeat) warp the point the shape is evaluated at, in the
+//! shape's local space; distance modifiers (onion, round, noise) change the
+//! distance that comes out, after all domain modifiers. The noise modifier
+//! displaces the surface by fractal noise (see the `noise` module) with the
+//! global noise seed and scale. The scene data system encodes each stack
+//! into the scene buffer next to its shape, and `shapeDistance` in
+//! `shaders/sdf.frag` evaluates it (as does the CPU copy in
+//! `vulkan::marching_cubes`). Stacks are stored in the scene file as a
+//! shape's `modifiers` array and edited in the modifiers panel.
+
+use legion::{Entity, IntoQuery, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use crate::ecs::components::SDFShape;
+use crate::hud::modifiers_panel::{ModifierEdit, ModifierStackView};
+
+/// One step of a modifier stack
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SDFModifier {
+    /// Rotate about the local Y axis in proportion to the height
+    Twist {
+        /// Radians per unit of height
+        rate: f32,
+    },
+    /// Curl the local X axis up around the Z axis
+    Bend {
+        /// Radians per unit along X
+        rate: f32,
+    },
+    /// Tile space with copies of the shape
+    Repeat {
+        /// Distance between copies along each axis, 0 leaves the axis alone
+        spacing: [f32; 3],
+        /// Copies on each side of the original, 0 for endless tiling
+        count: u32,
+    },
+    /// Hollow the shape out into a shell
+    Onion {
+        /// Half thickness of the shell
+        thickness: f32,
+    },
+    /// Grow the shape, rounding its edges
+    Round {
+        radius: f32,
+    },
+    /// Displace the surface by fractal noise
+    Noise {
+        /// Largest displacement
+        amplitude: f32,
+        /// Noise features per unit, before the global noise scale
+        frequency: f32,
+    },
+}
+
+impl SDFModifier {
+    /// One modifier of each kind with default parameters, in the order shown in the editor
+    pub const ALL: [SDFModifier; 6] = [
+        SDFModifier::Twist { rate: 1.0 },
+        SDFModifier::Bend { rate: 0.5 },
+        SDFModifier::Repeat { spacing: [2.0, 0.0, 2.0], count: 2 },
+        SDFModifier::Onion { thickness: 0.05 },
+        SDFModifier::Round { radius: 0.05 },
+        SDFModifier::Noise { amplitude: 0.05, frequency: 4.0 },
+    ];
+
+    /// Human readable name of the kind
+    pub fn name(&self) -> &'static str {
+        match self {
+            SDFModifier::Twist { .. } => "Twist",
+            SDFModifier::Bend { .. } => "Bend",
+            SDFModifier::Repeat { .. } => "Repeat",
+            SDFModifier::Onion { .. } => "Onion",
+            SDFModifier::Round { .. } => "Round",
+            SDFModifier::Noise { .. } => "Noise",
+        }
+    }
+
+    /// Value written to the scene buffer (matches MODIFIER_* defines in sdf.frag)
+    pub fn as_gpu(&self) -> i32 {
+        match self {
+            SDFModifier::Twist { .. } => 0,
+            SDFModifier::Bend { .. } => 1,
+            SDFModifier::Repeat { .. } => 2,
+            SDFModifier::Onion { .. } => 3,
+            SDFModifier::Round { .. } => 4,
+            SDFModifier::Noise { .. } => 5,
+        }
+    }
+
+    /// Parameters written to the scene buffer
+    pub fn gpu_params(&self) -> [f32; 4] {
+        match *self {
+            SDFModifier::Twist { rate } | SDFModifier::Bend { rate } => [rate, 0.0, 0.0, 0.0],
+            SDFModifier::Repeat { spacing, count } => [spacing[0].max(0.0), spacing[1].max(0.0), spacing[2].max(0.0), count as f32],
+            SDFModifier::Onion { thickness } => [thickness, 0.0, 0.0, 0.0],
+            SDFModifier::Round { radius } => [radius, 0.0, 0.0, 0.0],
+            SDFModifier::Noise { amplitude, frequency } => [amplitude, frequency.max(0.0), 0.0, 0.0],
+        }
+    }
+}
+
+/// Modifier stack of a shape, applied first to last
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SDFModifiers(pub Vec<SDFModifier>);
+
+/// Snapshot of the modifier stacks for the modifiers panel
+///
+/// Every shape is listed, with an empty stack if it has no modifiers.
+pub fn collect_modifier_stacks(world: &World) -> Vec<ModifierStackView> {
+    <(Entity, &SDFShape, Option<&SDFModifiers>)>::query()
+        .iter(world)
+        .map(|(entity, shape, modifiers)| ModifierStackView {
+            entity: *entity,
+            shape_type: shape.shape_type.clone(),
+            modifiers: modifiers.map(|modifiers| modifiers.0.clone()).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Apply an edit from the modifiers panel
+///
+/// An empty stack removes the component.
+pub fn apply_modifier_edit(world: &mut World, edit: ModifierEdit) {
+    debug!("Modifier edit applied: {:?}", edit);
+    let ModifierEdit { entity, modifiers } = edit;
+    if let Some(mut entry) = world.entry(entity) {
+        if modifiers.is_empty() {
+            entry.remove_component::<SDFModifiers>();
+        } else {
+            entry.add_component(SDFModifiers(modifiers));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modifier_stack_round_trips_through_toml() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Entry {
+            modifiers: Vec<SDFModifier>,
+        }
+
+        let entry = Entry { modifiers: SDFModifier::ALL.to_vec() };
+        let text = toml::to_string(&entry).unwrap();
+        assert_eq!(toml::from_str::<Entry>(&text).unwrap(), entry);
+
+        let parsed: Entry = toml::from_str(r#"modifiers = [{ type = "twist", rate = 2.0 }, { type = "round", radius = 0.1 }]"#).unwrap();
+        assert_eq!(parsed.modifiers, [SDFModifier::Twist { rate: 2.0 }, SDFModifier::Round { radius: 0.1 }]);
+    }
+
+    #[test]
+    fn test_empty_stack_removes_the_component() {
+        let mut world = World::default();
+        let entity = world.push((SDFShape::default(),));
+
+        apply_modifier_edit(&mut world, ModifierEdit { entity, modifiers: vec![SDFModifier::Onion { thickness: 0.1 }] });
+        assert_eq!(collect_modifier_stacks(&world)[0].modifiers, [SDFModifier::Onion { thickness: 0.1 }]);
+
+        apply_modifier_edit(&mut world, ModifierEdit { entity, modifiers: Vec::new() });
+        assert!(world.entry_ref(entity).unwrap().get_component::<SDFModifiers>().is_err());
+        assert!(collect_modifier_stacks(&world)[0].modifiers.is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+//! Particle emitters
+//!
+//! Entities with a `ParticleEmitter` and a `Transform` spawn GPU particles
+//! at their position. The emitter system turns each emitter's rate into a
+//! whole number of particles for the frame, carrying the fraction over to
+//! the next one, and collects a `ParticleEmitterList` that the upload system
+//! hands to the renderer. Spawning, motion and fading happen in the particle
+//! compute shader (see `vulkan::particles`). Emitters are stored in the scene
+//! file under a shape's `particles` table and edited in the particles panel.
+
+use cgmath::Vector3;
+use legion::world::SubWorld;
+use legion::{system, Entity, IntoQuery, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use crate::config;
+use crate::ecs::components::{FrameTime, Transform};
+use crate::hud::particles_panel::{EmitterView, ParticleEdit};
+use crate::vulkan::particles::ParticleEmitterList;
+
+/// How an emitter spawns its particles and how they look
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmitterSettings {
+    /// Whether the emitter spawns particles; live ones finish their lifetime
+    pub enabled: bool,
+    /// Particles spawned per second
+    pub rate: f32,
+    /// Seconds a particle lives
+    pub lifetime: f32,
+    /// Initial direction, normalized when collected
+    pub direction: [f32; 3],
+    /// Initial speed (units per second)
+    pub speed: f32,
+    /// Half angle of the cone particles are spawned in (radians)
+    pub spread: f32,
+    /// Downward acceleration (units per second²)
+    pub gravity: f32,
+    /// Billboard half size (units)
+    pub size: f32,
+    /// Color when spawned (linear RGB, see the `color` module)
+    pub start_color: [f32; 3],
+    /// Color at the end of the lifetime (linear RGB)
+    pub end_color: [f32; 3],
+    /// Multiplier of both colors; above 1 the particles glow in the HDR target
+    pub brightness: f32,
+}
+
+impl Default for EmitterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rate: 200.0,
+            lifetime: 2.0,
+            direction: [0.0, 1.0, 0.0],
+            speed: 2.0,
+            spread: 0.4,
+            gravity: 2.0,
+            size: 0.03,
+            start_color: [1.0, 0.5, 0.15],
+            end_color: [0.6, 0.05, 0.02],
+            brightness: 4.0,
+        }
+    }
+}
+
+/// Spawns particles at the entity's position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleEmitter {
+    pub settings: EmitterSettings,
+    /// Fraction of a particle not spawned yet, carried between frames
+    carry: f32,
+}
+
+impl ParticleEmitter {
+    /// Emitter starting with nothing carried over
+    pub fn new(settings: EmitterSettings) -> Self {
+        Self { settings, carry: 0.0 }
+    }
+
+    /// Number of particles to spawn for a frame
+    ///
+    /// The fraction left over is carried to the next frame, so low rates
+    /// still spawn on average `rate` particles per second. Disabled emitters
+    /// spawn nothing and drop the carry.
+    pub fn spawn_count(&mut self, delta_seconds: f32) -> u32 {
+        if !self.settings.enabled || self.settings.rate <= 0.0 {
+            self.carry = 0.0;
+            return 0;
+        }
+        let total = self.carry + self.settings.rate * delta_seconds.max(0.0);
+        let count = (total.floor() as u32).min(config::particles::MAX_SPAWN_PER_FRAME);
+        self.carry = (total - count as f32).min(1.0);
+        count
+    }
+}
+
+/// System that collects the emitters of the frame
+///
+/// # Arguments
+/// * `world` - Particle emitters and their transforms
+/// * `frame_time` - Real time since the last frame
+/// * `emitters` - List replaced with this frame's emitters
+#[system]
+#[write_component(ParticleEmitter)]
+#[read_component(Transform)]
+pub fn build_particle_emitters(
+    world: &mut SubWorld,
+    #[resource] frame_time: &FrameTime,
+    #[resource] emitters: &mut ParticleEmitterList,
+) {
+    let mut list = ParticleEmitterList::new(frame_time.delta_seconds);
+    for (emitter, transform) in <(&mut ParticleEmitter, &Transform)>::query().iter_mut(world) {
+        let count = emitter.spawn_count(frame_time.delta_seconds);
+        list.push(transform.position, &emitter.settings, count);
+    }
+
+    debug!("Particle emitters: {}", list.emitters().len());
+    *emitters = list;
+}
+
+/// Snapshot of the emitters for the particles panel
+pub fn collect_particle_emitters(world: &World) -> Vec<EmitterView> {
+    <(Entity, &ParticleEmitter, &Transform)>::query()
+        .iter(world)
+        .map(|(entity, emitter, transform)| EmitterView {
+            entity: *entity,
+            position: transform.position.into(),
+            settings: emitter.settings,
+        })
+        .collect()
+}
+
+/// Apply an edit from the particles panel
+pub fn apply_particle_edit(world: &mut World, edit: ParticleEdit) {
+    debug!("Particle edit applied: {:?}", edit);
+    match edit {
+        ParticleEdit::Add { position } => {
+            let transform = Transform { position: Vector3::from(position), ..Transform::default() };
+            world.push((ParticleEmitter::new(EmitterSettings::default()), transform));
+        }
+        ParticleEdit::Set { entity, settings } => {
+            if let Some(mut entry) = world.entry(entity) {
+                if let Ok(emitter) = entry.get_component_mut::<ParticleEmitter>() {
+                    emitter.settings = settings;
+                }
+            }
+        }
+        ParticleEdit::Remove { entity } => {
+            world.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::{Resources, Schedule};
+
+    #[test]
+    fn test_spawn_count_carries_fractions() {
+        let mut emitter = ParticleEmitter::new(EmitterSettings { rate: 32.0, ..EmitterSettings::default() });
+        let spawned: Vec<u32> = (0..64).map(|_| emitter.spawn_count(1.0 / 64.0)).collect();
+        assert_eq!(spawned[..4], [0, 1, 0, 1]);
+        assert_eq!(spawned.iter().sum::<u32>(), 32);
+
+        emitter.settings.enabled = false;
+        assert_eq!(emitter.spawn_count(1.0), 0);
+        emitter.settings = EmitterSettings { rate: 1.0e9, ..EmitterSettings::default() };
+        assert_eq!(emitter.spawn_count(1.0), config::particles::MAX_SPAWN_PER_FRAME);
+    }
+
+    #[test]
+    fn test_build_particle_emitters_uses_entity_position() {
+        let mut world = World::default();
+        let transform = Transform { position: Vector3::new(1.0, 2.0, 3.0), ..Transform::default() };
+        world.push((ParticleEmitter::new(EmitterSettings { rate: 10.0, ..EmitterSettings::default() }), transform));
+        world.push((Transform::default(),));
+
+        let mut resources = Resources::default();
+        resources.insert(FrameTime { delta_seconds: 0.5 });
+        resources.insert(ParticleEmitterList::default());
+        let mut schedule = Schedule::builder().add_system(build_particle_emitters_system()).build();
+        schedule.execute(&mut world, &mut resources);
+
+        let list = resources.get::<ParticleEmitterList>().unwrap();
+        assert_eq!(list.delta_seconds(), 0.5);
+        assert_eq!(list.emitters().len(), 1);
+        assert_eq!(list.emitters()[0].position, [1.0, 2.0, 3.0, 2.0]);
+        assert_eq!(list.emitters()[0].slots[1], 5);
+    }
+}
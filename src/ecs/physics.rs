@@ -0,0 +1,378 @@
+//! Rigid body physics
+//!
+//! Entities with a `Collider` take part in a rapier3d simulation stepped at
+//! `config::physics::TIMESTEP`. A `RigidBody` makes the entity dynamic or
+//! kinematic; entities with only a collider are fixed obstacles. Dynamic
+//! bodies write their position back into `Transform` after every frame's
+//! steps, while fixed and kinematic bodies follow their `Transform`, so
+//! animations and scripts can move them.
+//!
+//! Colliders approximate the SDF shapes (cylinders become capsules, tori
+//! boxes). The renderer draws shapes without rotation, so bodies have
+//! their rotations locked to keep the picture and the simulation in step.
+
+use std::collections::{HashMap, HashSet};
+use cgmath::{InnerSpace, Vector3};
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::{system, Entity, IntoQuery, World};
+use log::debug;
+use rapier3d::prelude::{
+    vector, CCDSolver, ColliderBuilder, ColliderSet, DefaultBroadPhase, ImpulseJointSet,
+    IntegrationParameters, IslandManager, MultibodyJointSet, NarrowPhase, PhysicsPipeline,
+    RigidBodyBuilder, RigidBodyHandle, RigidBodySet, RigidBodyType, Vector,
+};
+use serde::{Deserialize, Serialize};
+use crate::config;
+use crate::ecs::components::{FrameTime, SDFMaterial, SDFRenderable, SDFShape, SDFShapeType, Transform};
+
+/// How a body moves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyKind {
+    /// Moved by forces and collisions
+    #[default]
+    Dynamic,
+    /// Never moves in the simulation; follows its transform
+    Fixed,
+    /// Pushes dynamic bodies; follows its transform
+    Kinematic,
+}
+
+/// Makes a collider entity a simulated body
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RigidBody {
+    pub kind: BodyKind,
+    /// Velocity of a dynamic body when it enters the simulation
+    pub initial_velocity: Vector3<f32>,
+}
+
+/// Collision shape approximating an SDF shape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderShape {
+    Ball { radius: f32 },
+    Cuboid { half_extents: Vector3<f32> },
+    /// Vertical capsule; `half_height` is half the length of its segment
+    Capsule { half_height: f32, radius: f32 },
+    /// Everything below a horizontal plane
+    HalfSpace,
+}
+
+/// Collision shape and surface of an entity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    /// Bounciness, 0 to 1
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl Collider {
+    /// Collider approximating an SDF shape
+    pub fn from_sdf(shape: &SDFShape) -> Self {
+        let collider_shape = match shape.shape_type {
+            SDFShapeType::Sphere => ColliderShape::Ball { radius: shape.size },
+            SDFShapeType::Box => ColliderShape::Cuboid { half_extents: Vector3::new(shape.size, shape.size, shape.size) },
+            // Same height as the cylinder, with rounded ends
+            SDFShapeType::Cylinder => ColliderShape::Capsule {
+                half_height: (shape.params[0] - shape.size).max(0.0),
+                radius: shape.size,
+            },
+            SDFShapeType::Plane => ColliderShape::HalfSpace,
+            SDFShapeType::Torus => ColliderShape::Cuboid {
+                half_extents: shape.half_extents().unwrap_or(Vector3::new(shape.size, shape.size, shape.size)),
+            },
+        };
+        Self { shape: collider_shape, restitution: 0.3, friction: 0.5 }
+    }
+}
+
+/// Physics toggles, driven by the physics panel (ECS resource)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsSettings {
+    /// Step the simulation
+    pub simulate: bool,
+    /// Apply `config::physics::GRAVITY`
+    pub gravity: bool,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self { simulate: true, gravity: true }
+    }
+}
+
+/// Number of fixed steps to run for a frame, consuming them from the accumulator
+///
+/// # Arguments
+/// * `accumulator` - Simulation time not stepped yet, carried between frames
+/// * `delta_seconds` - Real time of the frame
+/// * `timestep` - Length of one step
+/// * `max_steps` - Cap; time beyond it is dropped
+fn fixed_steps(accumulator: &mut f32, delta_seconds: f32, timestep: f32, max_steps: u32) -> u32 {
+    *accumulator += delta_seconds.max(0.0);
+    let steps = ((*accumulator / timestep) as u32).min(max_steps);
+    *accumulator -= steps as f32 * timestep;
+    if steps == max_steps {
+        *accumulator = accumulator.min(timestep);
+    }
+    steps
+}
+
+/// The rapier simulation and the entity of every body (ECS resource)
+pub struct PhysicsWorld {
+    pipeline: PhysicsPipeline,
+    parameters: IntegrationParameters,
+    islands: IslandManager,
+    broad_phase: DefaultBroadPhase,
+    narrow_phase: NarrowPhase,
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    impulse_joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    ccd_solver: CCDSolver,
+    /// Body of every entity with a collider
+    handles: HashMap<Entity, RigidBodyHandle>,
+    /// Simulation time not stepped yet
+    accumulator: f32,
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhysicsWorld {
+    /// Create an empty simulation
+    pub fn new() -> Self {
+        Self {
+            pipeline: PhysicsPipeline::new(),
+            parameters: IntegrationParameters { dt: config::physics::TIMESTEP, ..IntegrationParameters::default() },
+            islands: IslandManager::new(),
+            broad_phase: DefaultBroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            bodies: RigidBodySet::new(),
+            colliders: ColliderSet::new(),
+            impulse_joints: ImpulseJointSet::new(),
+            multibody_joints: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+            handles: HashMap::new(),
+            accumulator: 0.0,
+        }
+    }
+
+    /// Number of bodies in the simulation
+    pub fn body_count(&self) -> usize {
+        self.bodies.len()
+    }
+
+    /// Add the body of an entity
+    fn insert(&mut self, entity: Entity, collider: &Collider, body: Option<&RigidBody>, position: Vector3<f32>) {
+        let translation = vector![position.x, position.y, position.z];
+        let builder = match body.map(|body| body.kind).unwrap_or(BodyKind::Fixed) {
+            BodyKind::Dynamic => {
+                let velocity = body.map(|body| body.initial_velocity).unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+                RigidBodyBuilder::dynamic().linvel(vector![velocity.x, velocity.y, velocity.z])
+            }
+            BodyKind::Fixed => RigidBodyBuilder::fixed(),
+            BodyKind::Kinematic => RigidBodyBuilder::kinematic_position_based(),
+        };
+        let handle = self.bodies.insert(builder.translation(translation).lock_rotations().build());
+
+        let shape = match collider.shape {
+            ColliderShape::Ball { radius } => ColliderBuilder::ball(radius),
+            ColliderShape::Cuboid { half_extents } => ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            ColliderShape::Capsule { half_height, radius } => ColliderBuilder::capsule_y(half_height, radius),
+            ColliderShape::HalfSpace => ColliderBuilder::halfspace(Vector::y_axis()),
+        };
+        let rapier_collider = shape.restitution(collider.restitution).friction(collider.friction).build();
+        self.colliders.insert_with_parent(rapier_collider, handle, &mut self.bodies);
+        self.handles.insert(entity, handle);
+        debug!("Added physics body for {:?}", entity);
+    }
+
+    /// Remove the bodies of entities that lost their collider or were despawned
+    fn remove_missing(&mut self, alive: &HashSet<Entity>) {
+        let removed: Vec<Entity> = self.handles.keys().filter(|entity| !alive.contains(entity)).copied().collect();
+        for entity in removed {
+            if let Some(handle) = self.handles.remove(&entity) {
+                self.bodies.remove(
+                    handle,
+                    &mut self.islands,
+                    &mut self.colliders,
+                    &mut self.impulse_joints,
+                    &mut self.multibody_joints,
+                    true,
+                );
+            }
+        }
+    }
+
+    /// Move a fixed or kinematic body to its entity's position
+    fn follow(&mut self, handle: RigidBodyHandle, position: Vector3<f32>) {
+        let Some(body) = self.bodies.get_mut(handle) else {
+            return;
+        };
+        let translation = vector![position.x, position.y, position.z];
+        match body.body_type() {
+            RigidBodyType::KinematicPositionBased => body.set_next_kinematic_translation(translation),
+            RigidBodyType::Fixed if *body.translation() != translation => body.set_translation(translation, true),
+            _ => {}
+        }
+    }
+
+    /// Run the fixed steps due for a frame
+    ///
+    /// # Returns
+    /// The number of steps run
+    fn advance(&mut self, delta_seconds: f32, gravity: bool) -> u32 {
+        let steps = fixed_steps(
+            &mut self.accumulator,
+            delta_seconds,
+            config::physics::TIMESTEP,
+            config::physics::MAX_STEPS_PER_FRAME,
+        );
+        let [x, y, z] = if gravity { config::physics::GRAVITY } else { [0.0; 3] };
+        let gravity = vector![x, y, z];
+        for _ in 0..steps {
+            self.pipeline.step(
+                &gravity,
+                &self.parameters,
+                &mut self.islands,
+                &mut self.broad_phase,
+                &mut self.narrow_phase,
+                &mut self.bodies,
+                &mut self.colliders,
+                &mut self.impulse_joints,
+                &mut self.multibody_joints,
+                &mut self.ccd_solver,
+                None,
+                &(),
+                &(),
+            );
+        }
+        steps
+    }
+
+    /// Position of an entity's dynamic body
+    fn dynamic_position(&self, entity: Entity) -> Option<Vector3<f32>> {
+        let body = self.bodies.get(*self.handles.get(&entity)?)?;
+        if !body.is_dynamic() {
+            return None;
+        }
+        let translation = body.translation();
+        Some(Vector3::new(translation.x, translation.y, translation.z))
+    }
+}
+
+/// System that steps the simulation and writes dynamic bodies back into their transforms
+///
+/// # Arguments
+/// * `world` - Colliders, bodies and transforms
+/// * `commands` - Despawns bodies that fell out of the world
+/// * `physics` - The simulation
+/// * `settings` - Simulation and gravity toggles
+/// * `frame_time` - Real time since the last frame
+#[system]
+#[read_component(Collider)]
+#[read_component(RigidBody)]
+#[write_component(Transform)]
+pub fn physics_step(
+    world: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] physics: &mut PhysicsWorld,
+    #[resource] settings: &PhysicsSettings,
+    #[resource] frame_time: &FrameTime,
+) {
+    let mut alive = HashSet::new();
+    for (entity, collider, body, transform) in <(Entity, &Collider, Option<&RigidBody>, &Transform)>::query().iter(world) {
+        alive.insert(*entity);
+        match physics.handles.get(entity) {
+            Some(&handle) => physics.follow(handle, transform.position),
+            None => physics.insert(*entity, collider, body, transform.position),
+        }
+    }
+    physics.remove_missing(&alive);
+
+    if !settings.simulate || physics.advance(frame_time.delta_seconds, settings.gravity) == 0 {
+        return;
+    }
+    for (entity, _, transform) in <(Entity, &Collider, &mut Transform)>::query().iter_mut(world) {
+        if let Some(position) = physics.dynamic_position(*entity) {
+            transform.position = position;
+            if position.y < config::physics::DESPAWN_HEIGHT {
+                commands.remove(*entity);
+            }
+        }
+    }
+}
+
+/// Spawn a dynamic sphere flying from a point
+///
+/// # Arguments
+/// * `world` - The ECS world
+/// * `origin` - Start position, usually the camera
+/// * `direction` - Direction of flight; normalized here
+pub fn throw_sphere(world: &mut World, origin: Vector3<f32>, direction: Vector3<f32>) -> Entity {
+    let shape = SDFShape {
+        shape_type: SDFShapeType::Sphere,
+        size: config::physics::THROW_RADIUS,
+        params: [0.0; 4],
+    };
+    let collider = Collider::from_sdf(&shape);
+    world.push((
+        shape,
+        SDFMaterial { color: Vector3::new(0.9, 0.9, 0.9), ..SDFMaterial::default() },
+        Transform { position: origin, ..Transform::default() },
+        SDFRenderable,
+        collider,
+        RigidBody {
+            kind: BodyKind::Dynamic,
+            initial_velocity: direction.normalize() * config::physics::THROW_SPEED,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::{EntityStore, Resources, Schedule};
+
+    #[test]
+    fn test_fixed_steps_carry_remainder_and_cap() {
+        let mut accumulator = 0.0;
+        assert_eq!(fixed_steps(&mut accumulator, 0.025, 0.01, 4), 2);
+        assert!((accumulator - 0.005).abs() < 1e-6);
+        assert_eq!(fixed_steps(&mut accumulator, 0.006, 0.01, 4), 1);
+        assert_eq!(fixed_steps(&mut accumulator, 1.0, 0.01, 4), 4);
+        assert!(accumulator <= 0.01);
+    }
+
+    #[test]
+    fn test_dynamic_body_falls_onto_fixed_box() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(PhysicsWorld::new());
+        resources.insert(PhysicsSettings::default());
+        resources.insert(FrameTime { delta_seconds: config::physics::TIMESTEP });
+
+        let floor = SDFShape { shape_type: SDFShapeType::Box, size: 1.0, params: [0.0; 4] };
+        world.push((Collider::from_sdf(&floor), Transform::default()));
+        let ball = SDFShape { shape_type: SDFShapeType::Sphere, size: 0.5, params: [0.0; 4] };
+        let falling = world.push((
+            Collider::from_sdf(&ball),
+            RigidBody::default(),
+            Transform { position: Vector3::new(0.0, 3.0, 0.0), ..Transform::default() },
+        ));
+
+        let mut schedule = Schedule::builder().add_system(physics_step_system()).build();
+        for _ in 0..240 {
+            schedule.execute(&mut world, &mut resources);
+        }
+
+        let height = world.entry_ref(falling).unwrap().get_component::<Transform>().unwrap().position.y;
+        assert!((height - 1.5).abs() < 0.05, "ball rests at {}", height);
+        assert_eq!(resources.get::<PhysicsWorld>().unwrap().body_count(), 2);
+    }
+}
@@ -0,0 +1,147 @@
+//! "Reload Everything" action
+//!
+//! `ECSWorld::reload_all` walks every reloadable asset kind in a fixed order
+//! and collects what happened to each into a `ReloadSummary`, which the HUD
+//! shows as a single toast. Kinds that are not loaded from disk yet are
+//! reported as skipped so the summary always lists every kind.
+
+use crate::error::AppError;
+use crate::hud::notifications::ToastLevel;
+
+/// Kind of asset reloaded by "Reload Everything"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadTarget {
+    /// SDF shaders: the shader cache is flushed and the active pipeline rebuilt
+    Shaders,
+    /// Application configuration
+    Config,
+    /// HUD theme
+    Theme,
+    /// Toolbar groups and buttons from the layout file
+    ToolbarLayout,
+    /// Scene entities
+    Scene,
+    /// Rhai scripts and the entities they spawned
+    Scripts,
+}
+
+impl ReloadTarget {
+    /// Every target in reload order
+    pub const ALL: [ReloadTarget; 6] = [
+        ReloadTarget::Shaders,
+        ReloadTarget::Config,
+        ReloadTarget::Theme,
+        ReloadTarget::ToolbarLayout,
+        ReloadTarget::Scene,
+        ReloadTarget::Scripts,
+    ];
+
+    /// Name shown in the summary
+    pub fn name(self) -> &'static str {
+        match self {
+            ReloadTarget::Shaders => "shaders",
+            ReloadTarget::Config => "config",
+            ReloadTarget::Theme => "theme",
+            ReloadTarget::ToolbarLayout => "toolbar layout",
+            ReloadTarget::Scene => "scene",
+            ReloadTarget::Scripts => "scripts",
+        }
+    }
+}
+
+/// What happened to one target
+#[derive(Debug)]
+pub enum ReloadOutcome {
+    /// Reloaded from its source
+    Reloaded,
+    /// Nothing to reload, with the reason
+    Skipped(&'static str),
+    /// Reloading failed; the previous state is kept
+    Failed(AppError),
+}
+
+/// Outcome of every target of one "Reload Everything"
+#[derive(Debug, Default)]
+pub struct ReloadSummary {
+    /// Outcomes in reload order
+    pub outcomes: Vec<(ReloadTarget, ReloadOutcome)>,
+}
+
+impl ReloadSummary {
+    /// Record the outcome of a target
+    pub fn record(&mut self, target: ReloadTarget, outcome: ReloadOutcome) {
+        self.outcomes.push((target, outcome));
+    }
+
+    /// Errors of the targets that failed
+    pub fn errors(&self) -> impl Iterator<Item = &AppError> {
+        self.outcomes.iter().filter_map(|(_, outcome)| match outcome {
+            ReloadOutcome::Failed(error) => Some(error),
+            _ => None,
+        })
+    }
+
+    /// Toast level: warning if anything failed
+    pub fn level(&self) -> ToastLevel {
+        if self.errors().next().is_some() {
+            ToastLevel::Warning
+        } else {
+            ToastLevel::Info
+        }
+    }
+
+    /// One-line summary for the toast
+    pub fn message(&self) -> String {
+        let names = |wanted: fn(&ReloadOutcome) -> bool| -> Vec<&str> {
+            self.outcomes
+                .iter()
+                .filter(|(_, outcome)| wanted(outcome))
+                .map(|(target, _)| target.name())
+                .collect()
+        };
+
+        let mut parts = Vec::new();
+        let reloaded = names(|outcome| matches!(outcome, ReloadOutcome::Reloaded));
+        if !reloaded.is_empty() {
+            parts.push(format!("reloaded {}", reloaded.join(", ")));
+        }
+        let failed = names(|outcome| matches!(outcome, ReloadOutcome::Failed(_)));
+        if !failed.is_empty() {
+            parts.push(format!("failed {}", failed.join(", ")));
+        }
+        let skipped = names(|outcome| matches!(outcome, ReloadOutcome::Skipped(_)));
+        if !skipped.is_empty() {
+            parts.push(format!("skipped {}", skipped.join(", ")));
+        }
+
+        if parts.is_empty() {
+            "Reload: nothing to do".to_string()
+        } else {
+            format!("Reload: {}", parts.join("; "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::VulkanError;
+
+    #[test]
+    fn test_summary_message_groups_outcomes() {
+        let mut summary = ReloadSummary::default();
+        summary.record(ReloadTarget::Shaders, ReloadOutcome::Reloaded);
+        summary.record(ReloadTarget::Config, ReloadOutcome::Skipped("compiled in"));
+        summary.record(ReloadTarget::Theme, ReloadOutcome::Reloaded);
+        summary.record(ReloadTarget::ToolbarLayout, ReloadOutcome::Skipped("no toolbar layout file"));
+        assert_eq!(summary.message(), "Reload: reloaded shaders, theme; skipped config, toolbar layout");
+        assert_eq!(summary.level(), ToastLevel::Info);
+
+        summary.record(
+            ReloadTarget::Scene,
+            ReloadOutcome::Failed(AppError::Vulkan(VulkanError::Rendering("oops".to_string()))),
+        );
+        assert_eq!(summary.message(), "Reload: reloaded shaders, theme; failed scene; skipped config, toolbar layout");
+        assert_eq!(summary.level(), ToastLevel::Warning);
+    }
+}
@@ -0,0 +1,1092 @@
+//! Scene files
+//!
+//! A scene is a TOML file listing SDF shapes, lights and CSG composites
+//! (with their operands inline). `LoadedScene` spawns it into the world and
+//! can re-load it in place when the file changes on disk: the old entities
+//! are despawned and the new ones inherit the visibility, selection and
+//! debug overlay of the entity with the same key. Keys are the `name` of an
+//! entry, or its position in the file (`shapes[1]`, `composites[0]/1`) for
+//! unnamed ones. The camera is not part of the scene and is left alone.
+//!
+//! `[[bookmarks]]` tables name camera poses to jump to (see
+//! `camera::CameraBookmark`). Saving bookmarks from the app rewrites only
+//! those tables, appended at the end of the file, and a reload that finds
+//! nothing but the bookmarks changed keeps the entities as they are.
+//!
+//! A shape with a `parent` (the name of another shape) is placed relative
+//! to it and follows it in the transform hierarchy.
+//!
+//! A shape with an `animation` table plays that keyframe clip from the
+//! moment it is spawned (see `ecs::animation`). A shape with a `physics`
+//! table gets a collider approximating it and a rigid body (see
+//! `ecs::physics`). A shape with a `label` shows that text above it in
+//! the viewport (see `ecs::labels`). A shape with a `particles` table emits
+//! GPU particles from its position (see `ecs::particles`). A shape with a
+//! `modifiers` array has its distance field changed by that stack, applied
+//! in order (see `ecs::modifiers`). Shapes, composites, meshes and texts
+//! with a `layers` array (layer numbers from 1) are on those render layers
+//! instead of the first one (see `ecs::layers`).
+//!
+//! Mesh entries (`meshes[0]` when unnamed) load a triangle mesh file and
+//! draw it depth tested against the SDF shapes (see `ecs::mesh`).
+//!
+//! Text entries (`texts[0]` when unnamed) show extruded 3D text in the SDF
+//! scene (see `ecs::text`).
+//!
+//! Shape, light, mesh and text colors are linear RGB (see the `color` module).
+//!
+//! `SceneFile::capture` goes the other way and describes the scene entities
+//! of a world as they are now, which the auto-save writes out (see the
+//! `autosave` module).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use cgmath::Vector3;
+use legion::{Entity, IntoQuery, Resources, World};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use crate::camera::CameraBookmark;
+use crate::ecs::components::{
+    CsgNode, CsgOperation, LocalTransform, Parent, SDFDebugDisplay, SDFMaterial,
+    SDFRenderable, SDFShape, SDFShapeType, SDFVisibility, Transform,
+};
+use crate::ecs::animation::{AnimationClip, AnimationPlayer};
+use crate::ecs::hierarchy;
+use crate::ecs::mesh::MeshRenderer;
+use crate::ecs::labels::Label;
+use crate::ecs::modifiers::{SDFModifier, SDFModifiers};
+use crate::ecs::layers::RenderLayers;
+use crate::ecs::lights::{Light, LightKind};
+use crate::ecs::text::Text3D;
+use crate::ecs::particles::{EmitterSettings, ParticleEmitter};
+use crate::ecs::physics::{BodyKind, Collider, RigidBody};
+use crate::config;
+use crate::error::{AppError, EcsError, Result, ResultExt, Subsystem};
+
+/// An SDF shape entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapeDesc {
+    /// Key used to match the entity across reloads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Name of the shape this one is attached to; the transform is then relative to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub shape: SDFShapeType,
+    pub size: f32,
+    pub params: [f32; 4],
+    pub color: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emission: f32,
+    /// Fraction of light mirrored off the surface
+    pub reflectivity: f32,
+    /// Fraction of light passing through the shape
+    pub transparency: f32,
+    /// Index of refraction of the shape's inside
+    pub ior: f32,
+    pub position: [f32; 3],
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+    /// Keyframe clip played on the shape
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation: Option<AnimationClip>,
+    /// Rigid body simulated for the shape
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub physics: Option<PhysicsDesc>,
+    /// Text shown above the shape in the viewport
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Particle emitter at the shape's position
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub particles: Option<EmitterSettings>,
+    /// Modifier stack, applied first to last
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<SDFModifier>,
+    /// Render layer numbers, starting at 1; empty for the first layer
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<u32>,
+}
+
+/// Rigid body of a shape entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsDesc {
+    pub body: BodyKind,
+    /// Initial velocity of a dynamic body
+    pub velocity: [f32; 3],
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl Default for PhysicsDesc {
+    fn default() -> Self {
+        let collider = Collider::from_sdf(&SDFShape::default());
+        Self {
+            body: BodyKind::default(),
+            velocity: [0.0; 3],
+            restitution: collider.restitution,
+            friction: collider.friction,
+        }
+    }
+}
+
+impl Default for ShapeDesc {
+    fn default() -> Self {
+        let shape = SDFShape::default();
+        let material = SDFMaterial::default();
+        Self {
+            name: None,
+            parent: None,
+            shape: shape.shape_type,
+            size: shape.size,
+            params: shape.params,
+            color: material.color.into(),
+            metallic: material.metallic,
+            roughness: material.roughness,
+            emission: material.emission,
+            reflectivity: material.reflectivity,
+            transparency: material.transparency,
+            ior: material.ior,
+            position: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+            animation: None,
+            physics: None,
+            label: None,
+            particles: None,
+            modifiers: Vec::new(),
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl ShapeDesc {
+    /// Describe a shape entity, None if it is not one
+    ///
+    /// The transform is the world transform; `parent` is left for the caller.
+    fn capture(world: &World, entity: Entity) -> Option<Self> {
+        let entry = world.entry_ref(entity).ok()?;
+        let shape = entry.get_component::<SDFShape>().ok()?;
+        let material = entry.get_component::<SDFMaterial>().ok()?;
+        let transform = entry.get_component::<Transform>().ok()?;
+        let physics = match (entry.get_component::<RigidBody>(), entry.get_component::<Collider>()) {
+            (Ok(body), Ok(collider)) => Some(PhysicsDesc {
+                body: body.kind,
+                velocity: body.initial_velocity.into(),
+                restitution: collider.restitution,
+                friction: collider.friction,
+            }),
+            _ => None,
+        };
+        Some(Self {
+            name: None,
+            parent: None,
+            shape: shape.shape_type.clone(),
+            size: shape.size,
+            params: shape.params,
+            color: material.color.into(),
+            metallic: material.metallic,
+            roughness: material.roughness,
+            emission: material.emission,
+            reflectivity: material.reflectivity,
+            transparency: material.transparency,
+            ior: material.ior,
+            position: transform.position.into(),
+            rotation: transform.rotation.into(),
+            scale: transform.scale.into(),
+            animation: entry.get_component::<AnimationPlayer>().ok().map(|player| player.clip.clone()),
+            physics,
+            label: entry.get_component::<Label>().ok().map(|label| label.text.clone()),
+            particles: entry.get_component::<ParticleEmitter>().ok().map(|emitter| emitter.settings),
+            modifiers: entry.get_component::<SDFModifiers>().ok().map(|modifiers| modifiers.0.clone()).unwrap_or_default(),
+            layers: capture_layers(entry.get_component::<RenderLayers>().ok().copied()),
+        })
+    }
+
+    /// Place the entry with a transform
+    fn set_transform(&mut self, transform: &Transform) {
+        self.position = transform.position.into();
+        self.rotation = transform.rotation.into();
+        self.scale = transform.scale.into();
+    }
+
+    /// Transform of the entry
+    fn transform(&self) -> Transform {
+        Transform {
+            position: self.position.into(),
+            rotation: self.rotation.into(),
+            scale: self.scale.into(),
+        }
+    }
+
+    /// Push the shape's entity
+    fn spawn(&self, world: &mut World) -> Entity {
+        let shape = SDFShape {
+            shape_type: self.shape.clone(),
+            size: self.size,
+            params: self.params,
+        };
+        let collider = Collider::from_sdf(&shape);
+        let label_height = shape.half_extents().map_or(0.0, |extents| extents.y) + config::labels::SHAPE_MARGIN;
+        let entity = world.push((
+            shape,
+            SDFMaterial {
+                color: self.color.into(),
+                metallic: self.metallic,
+                roughness: self.roughness,
+                emission: self.emission,
+                reflectivity: self.reflectivity,
+                transparency: self.transparency,
+                ior: self.ior,
+            },
+            self.transform(),
+            SDFRenderable,
+        ));
+        if let Some(mut entry) = world.entry(entity) {
+            if let Some(text) = &self.label {
+                entry.add_component(Label { offset: Vector3::new(0.0, label_height, 0.0), ..Label::new(text.as_str()) });
+            }
+            if let Some(clip) = &self.animation {
+                entry.add_component(AnimationPlayer::new(clip.clone()));
+            }
+            if let Some(physics) = &self.physics {
+                entry.add_component(Collider { restitution: physics.restitution, friction: physics.friction, ..collider });
+                entry.add_component(RigidBody { kind: physics.body, initial_velocity: physics.velocity.into() });
+            }
+            if let Some(settings) = &self.particles {
+                entry.add_component(ParticleEmitter::new(*settings));
+            }
+            if !self.modifiers.is_empty() {
+                entry.add_component(SDFModifiers(self.modifiers.clone()));
+            }
+            if !self.layers.is_empty() {
+                entry.add_component(RenderLayers::from_numbers(&self.layers));
+            }
+        }
+        entity
+    }
+}
+
+/// A light entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LightDesc {
+    pub kind: LightKind,
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance at which a point or spot light has faded out, 0 for no falloff
+    pub radius: f32,
+    /// Half angle of a spot light's cone in degrees
+    pub spot_angle_deg: f32,
+}
+
+impl Default for LightDesc {
+    fn default() -> Self {
+        Self::from_light(&Light::default())
+    }
+}
+
+impl LightDesc {
+    /// Describe a light component
+    fn from_light(light: &Light) -> Self {
+        Self {
+            kind: light.kind,
+            position: light.position.into(),
+            direction: light.direction.into(),
+            color: light.color.into(),
+            intensity: light.intensity,
+            radius: light.radius,
+            spot_angle_deg: light.spot_angle_deg,
+        }
+    }
+
+    /// Light component of the entry
+    fn light(&self) -> Light {
+        Light {
+            kind: self.kind,
+            position: self.position.into(),
+            direction: self.direction.into(),
+            color: self.color.into(),
+            intensity: self.intensity,
+            radius: self.radius,
+            spot_angle_deg: self.spot_angle_deg,
+        }
+    }
+}
+
+/// A triangle mesh entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeshDesc {
+    /// Key used to match the entity across reloads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Path of the `.obj`, `.gltf` or `.glb` file
+    pub mesh: String,
+    pub color: [f32; 3],
+    pub position: [f32; 3],
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+    /// Render layer numbers, starting at 1; empty for the first layer
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<u32>,
+}
+
+impl Default for MeshDesc {
+    fn default() -> Self {
+        Self {
+            name: None,
+            mesh: String::new(),
+            color: [0.8; 3],
+            position: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+            layers: Vec::new(),
+        }
+    }
+}
+
+/// A 3D text entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextDesc {
+    /// Key used to match the entity across reloads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Lines separated by `\n`
+    pub text: String,
+    /// Font file, empty for the default font
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub font: String,
+    pub size: f32,
+    pub depth: f32,
+    pub color: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub position: [f32; 3],
+    pub rotation: [f32; 3],
+    /// Render layer numbers, starting at 1; empty for the first layer
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<u32>,
+}
+
+impl Default for TextDesc {
+    fn default() -> Self {
+        Self::new(None, &Text3D::default(), &Transform::default())
+    }
+}
+
+impl TextDesc {
+    /// Describe a text entity
+    fn new(name: Option<String>, text: &Text3D, transform: &Transform) -> Self {
+        Self {
+            name,
+            text: text.text.clone(),
+            font: text.font.clone(),
+            size: text.size,
+            depth: text.depth,
+            color: text.color,
+            metallic: text.metallic,
+            roughness: text.roughness,
+            position: transform.position.into(),
+            rotation: transform.rotation.into(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// The text component of the entry
+    fn text(&self) -> Text3D {
+        Text3D {
+            text: self.text.clone(),
+            font: self.font.clone(),
+            size: self.size,
+            depth: self.depth,
+            color: self.color,
+            metallic: self.metallic,
+            roughness: self.roughness,
+        }
+    }
+}
+
+/// A CSG composite entry with its operand shapes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompositeDesc {
+    /// Key used to match the entity across reloads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub operation: CsgOperation,
+    pub blend_radius: f32,
+    /// Operands in evaluation order
+    pub operands: Vec<ShapeDesc>,
+    /// Render layer numbers of the composite, starting at 1; empty for the first layer
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<u32>,
+}
+
+/// Contents of a scene file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub shapes: Vec<ShapeDesc>,
+    pub lights: Vec<LightDesc>,
+    pub composites: Vec<CompositeDesc>,
+    pub meshes: Vec<MeshDesc>,
+    pub texts: Vec<TextDesc>,
+    /// Named camera poses
+    pub bookmarks: Vec<CameraBookmark>,
+}
+
+/// The `[[bookmarks]]` tables of a scene file on their own
+#[derive(Serialize)]
+struct BookmarkTables<'a> {
+    bookmarks: &'a [CameraBookmark],
+}
+
+/// Replace the `[[bookmarks]]` tables of a scene file's text
+///
+/// The rest of the text, comments included, is kept as written; the new
+/// tables go at the end. Comments right above the table after a bookmark
+/// belong to that table and are kept too.
+///
+/// # Errors
+/// Returns an error if the bookmarks cannot be serialized
+pub fn replace_bookmarks(text: &str, bookmarks: &[CameraBookmark]) -> Result<String> {
+    let mut kept = String::new();
+    let mut in_bookmark = false;
+    let mut comments = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            in_bookmark = trimmed.starts_with("[[bookmarks]]");
+            if !in_bookmark {
+                for comment in comments.drain(..) {
+                    kept.push_str(comment);
+                    kept.push('\n');
+                }
+            }
+            comments.clear();
+        }
+        if !in_bookmark {
+            kept.push_str(line);
+            kept.push('\n');
+        } else if trimmed.starts_with('#') {
+            comments.push(line);
+        } else if !trimmed.is_empty() {
+            comments.clear();
+        }
+    }
+    let mut text = kept.trim_end().to_string();
+    if !bookmarks.is_empty() {
+        let tables = toml::to_string(&BookmarkTables { bookmarks })
+            .map_err(|e| AppError::Generic(format!("Failed to serialize camera bookmarks: {}", e)))?;
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(tables.trim_end());
+    }
+    text.push('\n');
+    Ok(text)
+}
+
+/// Key of an entry: its name, or its position in the file
+fn entry_key(name: &Option<String>, position: String) -> String {
+    name.clone().unwrap_or(position)
+}
+
+/// Name of a captured entity: its key, unless the key is a position in the
+/// file it was loaded from, which would not match in the captured file
+fn capture_name(names: &[(String, Entity)], entity: Entity) -> Option<String> {
+    names
+        .iter()
+        .find(|(_, named)| *named == entity)
+        .map(|(key, _)| key.clone())
+        .filter(|key| !key.contains('['))
+}
+
+/// `layers` of a captured entity, empty on the default layer
+fn capture_layers(layers: Option<RenderLayers>) -> Vec<u32> {
+    layers.filter(|&layers| layers != RenderLayers::default()).map(RenderLayers::numbers).unwrap_or_default()
+}
+
+/// Order entities as they were loaded, the others after them
+fn sort_by_load_order(entities: &mut [Entity], names: &[(String, Entity)]) {
+    entities.sort_by_key(|entity| names.iter().position(|(_, named)| named == entity).unwrap_or(usize::MAX));
+}
+
+impl SceneFile {
+    /// Parse a scene from TOML
+    ///
+    /// # Errors
+    /// Returns an error if the text is not a valid scene
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| EcsError::SceneParse(e.to_string()).into())
+    }
+
+    /// Read and parse a scene file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context(Subsystem::Ecs, format!("read scene {}", path.display()))?;
+        Self::parse(&text).context(Subsystem::Ecs, format!("parse scene {}", path.display()))
+    }
+
+    /// Whether two scenes spawn the same entities, bookmarks aside
+    fn same_entities(&self, other: &SceneFile) -> bool {
+        self.shapes == other.shapes
+            && self.lights == other.lights
+            && self.composites == other.composites
+            && self.meshes == other.meshes
+            && self.texts == other.texts
+    }
+
+    /// Serialize the scene as TOML
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| AppError::Generic(format!("Failed to serialize scene: {}", e)))
+    }
+
+    /// Describe the scene entities of a world as they are now
+    ///
+    /// Shapes, lights, CSG composites, meshes and texts are written with their HUD
+    /// edits; the camera is not part of a scene. Entities keep the names they
+    /// were loaded with and their load order. A child is written relative to
+    /// its parent shape, which gets its position key as a name if it has none.
+    ///
+    /// # Arguments
+    /// * `world` - World to describe
+    /// * `names` - Keys of the entities loaded from a scene file
+    /// * `exclude` - Entities left out, such as the ones scripts spawned
+    pub fn capture(world: &World, names: &[(String, Entity)], exclude: &[Entity]) -> Self {
+        let nodes: Vec<(Entity, CsgNode)> = <(Entity, &CsgNode)>::query()
+            .iter(world)
+            .filter(|(entity, _)| !exclude.contains(*entity))
+            .map(|(entity, node)| (*entity, node.clone()))
+            .collect();
+        let is_operand = |entity: &Entity| nodes.iter().any(|(_, node)| node.operands.contains(entity));
+
+        let mut shape_entities: Vec<Entity> = <(Entity, &SDFShape)>::query()
+            .iter(world)
+            .map(|(entity, _)| *entity)
+            .filter(|entity| !exclude.contains(entity) && !is_operand(entity))
+            .collect();
+        sort_by_load_order(&mut shape_entities, names);
+        let (shape_entities, mut shapes): (Vec<Entity>, Vec<ShapeDesc>) = shape_entities
+            .into_iter()
+            .filter_map(|entity| {
+                let shape = ShapeDesc::capture(world, entity)?;
+                Some((entity, ShapeDesc { name: capture_name(names, entity), ..shape }))
+            })
+            .unzip();
+
+        // Children of captured shapes are written relative to them
+        for (index, entity) in shape_entities.iter().enumerate() {
+            let Ok(entry) = world.entry_ref(*entity) else {
+                continue;
+            };
+            let (Ok(parent), Ok(local)) = (entry.get_component::<Parent>(), entry.get_component::<LocalTransform>()) else {
+                continue;
+            };
+            let Some(parent_index) = shape_entities.iter().position(|shape| *shape == parent.0) else {
+                continue;
+            };
+            let parent_name = shapes[parent_index].name.get_or_insert_with(|| format!("shapes[{}]", parent_index)).clone();
+            shapes[index].parent = Some(parent_name);
+            shapes[index].set_transform(&local.0);
+        }
+
+        let mut light_entities: Vec<Entity> = <(Entity, &Light)>::query()
+            .iter(world)
+            .map(|(entity, _)| *entity)
+            .filter(|entity| !exclude.contains(entity))
+            .collect();
+        sort_by_load_order(&mut light_entities, names);
+        let lights = light_entities
+            .iter()
+            .filter_map(|entity| {
+                let entry = world.entry_ref(*entity).ok()?;
+                entry.get_component::<Light>().ok().map(LightDesc::from_light)
+            })
+            .collect();
+
+        let mut node_entities: Vec<Entity> = nodes.iter().map(|(entity, _)| *entity).filter(|entity| !is_operand(entity)).collect();
+        sort_by_load_order(&mut node_entities, names);
+        let composites = node_entities
+            .iter()
+            .filter_map(|entity| nodes.iter().find(|(node_entity, _)| node_entity == entity))
+            .map(|(entity, node)| CompositeDesc {
+                name: capture_name(names, *entity),
+                operation: node.operation,
+                blend_radius: node.blend_radius,
+                operands: node
+                    .operands
+                    .iter()
+                    .filter_map(|operand| {
+                        ShapeDesc::capture(world, *operand).map(|shape| ShapeDesc { name: capture_name(names, *operand), ..shape })
+                    })
+                    .collect(),
+                layers: capture_layers(world.entry_ref(*entity).ok().and_then(|entry| entry.get_component::<RenderLayers>().ok().copied())),
+            })
+            .collect();
+
+        let mut mesh_entities: Vec<Entity> = <(Entity, &MeshRenderer)>::query()
+            .iter(world)
+            .map(|(entity, _)| *entity)
+            .filter(|entity| !exclude.contains(entity))
+            .collect();
+        sort_by_load_order(&mut mesh_entities, names);
+        let meshes = mesh_entities
+            .iter()
+            .filter_map(|entity| {
+                let entry = world.entry_ref(*entity).ok()?;
+                let mesh = entry.get_component::<MeshRenderer>().ok()?;
+                let transform = entry.get_component::<Transform>().ok()?;
+                Some(MeshDesc {
+                    name: capture_name(names, *entity),
+                    mesh: mesh.mesh.clone(),
+                    color: mesh.color,
+                    position: transform.position.into(),
+                    rotation: transform.rotation.into(),
+                    scale: transform.scale.into(),
+                    layers: capture_layers(entry.get_component::<RenderLayers>().ok().copied()),
+                })
+            })
+            .collect();
+
+        let mut text_entities: Vec<Entity> = <(Entity, &Text3D)>::query()
+            .iter(world)
+            .map(|(entity, _)| *entity)
+            .filter(|entity| !exclude.contains(entity))
+            .collect();
+        sort_by_load_order(&mut text_entities, names);
+        let texts = text_entities
+            .iter()
+            .filter_map(|entity| {
+                let entry = world.entry_ref(*entity).ok()?;
+                let text = entry.get_component::<Text3D>().ok()?;
+                let transform = entry.get_component::<Transform>().ok()?;
+                Some(TextDesc {
+                    layers: capture_layers(entry.get_component::<RenderLayers>().ok().copied()),
+                    ..TextDesc::new(capture_name(names, *entity), text, transform)
+                })
+            })
+            .collect();
+
+        // Bookmarks belong to the camera, which the world does not hold
+        Self { shapes, lights, composites, meshes, texts, bookmarks: Vec::new() }
+    }
+
+    /// Spawn every entry into the world
+    ///
+    /// # Arguments
+    /// * `world` - World to push the entities into
+    /// * `top_level` - Receives the shapes, lights, composites, meshes and texts (not the operands)
+    ///
+    /// # Returns
+    /// Every spawned entity with its key, operands included
+    fn spawn(&self, world: &mut World, top_level: &mut Vec<Entity>) -> Vec<(String, Entity)> {
+        let mut entities = Vec::new();
+
+        for (index, shape) in self.shapes.iter().enumerate() {
+            let entity = shape.spawn(world);
+            entities.push((entry_key(&shape.name, format!("shapes[{}]", index)), entity));
+            top_level.push(entity);
+        }
+
+        // Attach children once every possible parent exists
+        for (index, shape) in self.shapes.iter().enumerate() {
+            let Some(parent_name) = &shape.parent else {
+                continue;
+            };
+            let child = entities[index].1;
+            let Some(&(_, parent)) = entities.iter().find(|(key, _)| key == parent_name) else {
+                warn!("Shape {} has unknown parent '{}'", entities[index].0, parent_name);
+                continue;
+            };
+            if hierarchy::is_ancestor(world, child, parent) {
+                warn!("Ignoring parent '{}' of {}: it would create a cycle", parent_name, entities[index].0);
+                continue;
+            }
+            if let Err(e) = hierarchy::attach(world, child, parent, shape.transform()) {
+                warn!("Failed to attach {} to '{}': {}", entities[index].0, parent_name, e);
+            }
+        }
+
+        for (index, light) in self.lights.iter().enumerate() {
+            let entity = world.push((light.light(),));
+            entities.push((format!("lights[{}]", index), entity));
+            top_level.push(entity);
+        }
+
+        for (index, composite) in self.composites.iter().enumerate() {
+            let key = entry_key(&composite.name, format!("composites[{}]", index));
+            let operands: Vec<Entity> = composite
+                .operands
+                .iter()
+                .enumerate()
+                .map(|(operand_index, operand)| {
+                    let entity = operand.spawn(world);
+                    entities.push((entry_key(&operand.name, format!("{}/{}", key, operand_index)), entity));
+                    entity
+                })
+                .collect();
+            let entity = world.push((CsgNode {
+                operation: composite.operation,
+                blend_radius: composite.blend_radius,
+                operands,
+            },));
+            if !composite.layers.is_empty() {
+                if let Some(mut entry) = world.entry(entity) {
+                    entry.add_component(RenderLayers::from_numbers(&composite.layers));
+                }
+            }
+            entities.push((key, entity));
+            top_level.push(entity);
+        }
+
+        for (index, mesh) in self.meshes.iter().enumerate() {
+            let entity = world.push((
+                MeshRenderer { mesh: mesh.mesh.clone(), color: mesh.color },
+                Transform {
+                    position: mesh.position.into(),
+                    rotation: mesh.rotation.into(),
+                    scale: mesh.scale.into(),
+                },
+            ));
+            if !mesh.layers.is_empty() {
+                if let Some(mut entry) = world.entry(entity) {
+                    entry.add_component(RenderLayers::from_numbers(&mesh.layers));
+                }
+            }
+            entities.push((entry_key(&mesh.name, format!("meshes[{}]", index)), entity));
+            top_level.push(entity);
+        }
+
+        for (index, text) in self.texts.iter().enumerate() {
+            let entity = world.push((
+                text.text(),
+                Transform {
+                    position: text.position.into(),
+                    rotation: text.rotation.into(),
+                    ..Transform::default()
+                },
+            ));
+            if !text.layers.is_empty() {
+                if let Some(mut entry) = world.entry(entity) {
+                    entry.add_component(RenderLayers::from_numbers(&text.layers));
+                }
+            }
+            entities.push((entry_key(&text.name, format!("texts[{}]", index)), entity));
+            top_level.push(entity);
+        }
+
+        entities
+    }
+}
+
+/// Editor state of an entity carried over a reload
+#[derive(Debug, Clone, Default)]
+struct EditorState {
+    visibility: Option<SDFVisibility>,
+    debug_display: Option<SDFDebugDisplay>,
+}
+
+/// A scene file spawned into the world
+#[derive(Debug)]
+pub struct LoadedScene {
+    /// File the scene was loaded from
+    pub path: PathBuf,
+    /// Camera bookmarks of the file
+    pub bookmarks: Vec<CameraBookmark>,
+    /// Spawned entities by key
+    entities: Vec<(String, Entity)>,
+    /// Contents of the file as last loaded, to tell bookmark edits from scene edits
+    loaded: SceneFile,
+}
+
+impl LoadedScene {
+    /// Load a scene file and spawn it
+    ///
+    /// # Arguments
+    /// * `path` - Scene file
+    /// * `world` - World to spawn into
+    /// * `resources` - Resources holding the SDF entity tracker
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be loaded; nothing is spawned then
+    pub fn load(path: &Path, world: &mut World, resources: &mut Resources) -> Result<Self> {
+        Self::spawn(path, SceneFile::load(path)?, world, resources)
+    }
+
+    /// Spawn a scene file read before
+    ///
+    /// # Arguments
+    /// * `path` - File the scene was read from
+    /// * `scene` - Contents of the file
+    /// * `world` - World to spawn into
+    /// * `resources` - Resources holding the SDF entity tracker
+    ///
+    /// # Errors
+    /// Returns an error if the SDF entity tracker is missing; nothing is spawned then
+    pub fn spawn(path: &Path, scene: SceneFile, world: &mut World, resources: &mut Resources) -> Result<Self> {
+        let mut top_level = resources.get_mut::<Vec<Entity>>()
+            .ok_or_else(|| EcsError::ResourceAccess("SDF entities vector not found in resources".to_string()))?;
+        let entities = scene.spawn(world, &mut top_level);
+        info!("Loaded scene {:?} with {} entities", path, entities.len());
+        Ok(Self { path: path.to_path_buf(), bookmarks: scene.bookmarks.clone(), entities, loaded: scene })
+    }
+
+    /// Re-load the scene file, replacing the spawned entities
+    ///
+    /// Entities keep the visibility, selection and debug overlay of the
+    /// previous entity with the same key. When only the bookmarks changed,
+    /// as after saving one, the entities are left alone.
+    ///
+    /// # Returns
+    /// Whether the entities were replaced
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be loaded; the current entities
+    /// are kept then
+    pub fn reload(&mut self, world: &mut World, resources: &mut Resources) -> Result<bool> {
+        let scene = SceneFile::load(&self.path)?;
+        self.bookmarks = scene.bookmarks.clone();
+        if scene.same_entities(&self.loaded) {
+            debug!("Scene {:?} changed only its bookmarks", self.path);
+            self.loaded = scene;
+            return Ok(false);
+        }
+        let mut top_level = resources.get_mut::<Vec<Entity>>()
+            .ok_or_else(|| EcsError::ResourceAccess("SDF entities vector not found in resources".to_string()))?;
+
+        let states: Vec<(String, EditorState)> = self
+            .entities
+            .iter()
+            .map(|(key, entity)| (key.clone(), Self::editor_state(world, *entity)))
+            .collect();
+        for (_, entity) in &self.entities {
+            world.remove(*entity);
+        }
+        top_level.retain(|entity| !self.entities.iter().any(|(_, old)| old == entity));
+
+        self.entities = scene.spawn(world, &mut top_level);
+        for (key, entity) in &self.entities {
+            let Some((_, state)) = states.iter().find(|(old_key, _)| old_key == key) else {
+                continue;
+            };
+            if let Some(mut entry) = world.entry(*entity) {
+                if let Some(visibility) = state.visibility.clone() {
+                    entry.add_component(visibility);
+                }
+                if let Some(debug_display) = state.debug_display.clone() {
+                    entry.add_component(debug_display);
+                }
+            }
+        }
+
+        info!("Reloaded scene {:?} with {} entities", self.path, self.entities.len());
+        debug!("Scene keys: {:?}", self.entities.iter().map(|(key, _)| key).collect::<Vec<_>>());
+        self.loaded = scene;
+        Ok(true)
+    }
+
+    /// Write new bookmarks into the scene file, see `replace_bookmarks`
+    ///
+    /// The reload the write causes leaves the entities alone, even when
+    /// they were restored from an auto-save and differ from the file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, parsed or written
+    pub fn save_bookmarks(&mut self, bookmarks: &[CameraBookmark]) -> Result<()> {
+        let text = fs::read_to_string(&self.path).context(Subsystem::Ecs, format!("read scene {}", self.path.display()))?;
+        let text = replace_bookmarks(&text, bookmarks)?;
+        let scene = SceneFile::parse(&text).context(Subsystem::Ecs, format!("parse scene {}", self.path.display()))?;
+        fs::write(&self.path, text).context(Subsystem::Io, format!("save camera bookmarks to {}", self.path.display()))?;
+        debug!("Saved {} camera bookmarks to {:?}", bookmarks.len(), self.path);
+        self.bookmarks = scene.bookmarks.clone();
+        self.loaded = scene;
+        Ok(())
+    }
+
+    /// Spawned entities by key
+    pub fn entities(&self) -> &[(String, Entity)] {
+        &self.entities
+    }
+
+    /// Despawn the scene's entities
+    pub fn unload(self, world: &mut World, resources: &mut Resources) {
+        if let Some(mut top_level) = resources.get_mut::<Vec<Entity>>() {
+            top_level.retain(|entity| !self.entities.iter().any(|(_, old)| old == entity));
+        }
+        for (_, entity) in self.entities {
+            world.remove(entity);
+        }
+        info!("Unloaded scene {:?}", self.path);
+    }
+
+    /// Editor components of an entity
+    fn editor_state(world: &World, entity: Entity) -> EditorState {
+        match world.entry_ref(entity) {
+            Ok(entry) => EditorState {
+                visibility: entry.get_component::<SDFVisibility>().ok().cloned(),
+                debug_display: entry.get_component::<SDFDebugDisplay>().ok().cloned(),
+            },
+            Err(_) => EditorState::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"
+        [[shapes]]
+        name = "ball"
+        shape = "sphere"
+        size = 0.5
+        label = "ball {position}"
+        particles = { rate = 50.0 }
+        [shapes.animation]
+        looping = true
+        [[shapes.animation.tracks]]
+        target = "roughness"
+        keyframes = [{ time = 0.0, value = 0.2 }, { time = 1.0, value = [0.8, 0.8, 0.8] }]
+
+        [[shapes]]
+        shape = "box"
+        parent = "ball"
+        transparency = 0.8
+        ior = 1.33
+        position = [-1.5, 0.0, 0.0]
+        physics = { body = "fixed" }
+        modifiers = [{ type = "twist", rate = 2.0 }, { type = "round", radius = 0.1 }]
+        layers = [1, 3]
+
+        [[lights]]
+
+        [[lights]]
+        kind = "spot"
+        direction = [0.0, -1.0, 0.0]
+        radius = 5.0
+
+        [[composites]]
+        operation = "subtraction"
+        blend_radius = 0.05
+        layers = [2]
+        [[composites.operands]]
+        shape = "box"
+        [[composites.operands]]
+        shape = "sphere"
+
+        [[meshes]]
+        mesh = "meshes/cube.obj"
+        position = [0.0, 1.0, 0.0]
+        layers = [2]
+
+        [[texts]]
+        text = "Hello"
+        rotation = [0.0, 0.5, 0.0]
+        layers = [3]
+    "#;
+
+    #[test]
+    fn test_parse_fills_defaults() {
+        let scene = SceneFile::parse(SCENE).unwrap();
+        assert_eq!(scene.shapes.len(), 2);
+        assert_eq!(scene.shapes[1].shape, SDFShapeType::Box);
+        assert_eq!(scene.shapes[1].scale, [1.0; 3]);
+        assert_eq!((scene.shapes[1].transparency, scene.shapes[1].ior), (0.8, 1.33));
+        assert_eq!(scene.shapes[0].ior, config::materials::DEFAULT_IOR);
+        let clip = scene.shapes[0].animation.as_ref().unwrap();
+        assert!(clip.looping);
+        assert_eq!(clip.tracks[0].keyframes[0].value, [0.2; 3]);
+        assert_eq!(clip.duration(), 1.0);
+        assert_eq!(scene.shapes[1].animation, None);
+        let particles = scene.shapes[0].particles.unwrap();
+        assert_eq!(particles.rate, 50.0);
+        assert_eq!(particles.lifetime, EmitterSettings::default().lifetime);
+        let physics = scene.shapes[1].physics.as_ref().unwrap();
+        assert_eq!(physics.body, BodyKind::Fixed);
+        assert_eq!(physics.restitution, PhysicsDesc::default().restitution);
+        assert_eq!(scene.shapes[1].modifiers, [SDFModifier::Twist { rate: 2.0 }, SDFModifier::Round { radius: 0.1 }]);
+        assert!(scene.shapes[0].modifiers.is_empty());
+        assert_eq!(scene.lights[0], LightDesc::default());
+        assert_eq!((scene.lights[1].kind, scene.lights[1].radius), (LightKind::Spot, 5.0));
+        assert_eq!(scene.lights[1].spot_angle_deg, config::lights::DEFAULT_SPOT_ANGLE_DEG);
+        assert_eq!(scene.composites[0].operation, CsgOperation::Subtraction);
+        assert_eq!(scene.composites[0].operands.len(), 2);
+        assert_eq!(scene.shapes[1].layers, [1, 3]);
+        assert_eq!(scene.composites[0].layers, [2]);
+        assert!(scene.shapes[0].layers.is_empty());
+        assert_eq!(scene.meshes[0].mesh, "meshes/cube.obj");
+        assert_eq!(scene.meshes[0].scale, [1.0; 3]);
+        assert_eq!((scene.meshes[0].layers.as_slice(), scene.texts[0].layers.as_slice()), (&[2][..], &[3][..]));
+        assert_eq!(scene.texts[0].size, Text3D::default().size);
+        assert!(SceneFile::parse("[[shapes]]\nshape = \"blob\"").is_err());
+    }
+
+    #[test]
+    fn test_spawn_keys_entries_and_attaches_children() {
+        let mut world = World::default();
+        let mut top_level = Vec::new();
+        let entities = SceneFile::parse(SCENE).unwrap().spawn(&mut world, &mut top_level);
+
+        let keys: Vec<&str> = entities.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["ball", "shapes[1]", "lights[0]", "lights[1]", "composites[0]/0", "composites[0]/1", "composites[0]", "meshes[0]", "texts[0]"]);
+        assert_eq!(top_level.len(), 7);
+
+        let child = world.entry_ref(entities[1].1).unwrap();
+        assert_eq!(child.get_component::<crate::ecs::components::Parent>().unwrap().0, entities[0].1);
+        assert!(world.entry_ref(entities[0].1).unwrap().get_component::<AnimationPlayer>().is_ok());
+        assert!(world.entry_ref(entities[0].1).unwrap().get_component::<ParticleEmitter>().is_ok());
+        let label = world.entry_ref(entities[0].1).unwrap().get_component::<Label>().unwrap().clone();
+        assert_eq!(label.offset.y, 0.5 + config::labels::SHAPE_MARGIN);
+    }
+
+    #[test]
+    fn test_capture_describes_the_spawned_scene() {
+        let mut world = World::default();
+        let mut top_level = Vec::new();
+        let scene = SceneFile::parse(SCENE).unwrap();
+        let entities = scene.spawn(&mut world, &mut top_level);
+
+        let captured = SceneFile::capture(&world, &entities, &[]);
+        assert_eq!(captured, scene);
+        assert_eq!(SceneFile::parse(&captured.to_toml().unwrap()).unwrap(), captured);
+
+        // Excluded entities are left out, and an unnamed parent is named by its position
+        let ball = entities[0].1;
+        let captured = SceneFile::capture(&world, &[], &[ball, entities[2].1, entities[3].1]);
+        assert!(captured.lights.is_empty());
+        assert_eq!(captured.shapes.len(), 1);
+        assert_eq!(captured.shapes[0].parent, None);
+        let captured = SceneFile::capture(&world, &[], &[]);
+        let child = captured.shapes.iter().find(|shape| shape.parent.is_some()).unwrap();
+        let parent = captured.shapes.iter().find(|shape| shape.name == child.parent).unwrap();
+        assert_eq!(parent.label.as_deref(), Some("ball {position}"));
+    }
+
+    #[test]
+    fn test_bookmarks_are_replaced_in_place() {
+        let text = "# Scene\n[[shapes]]\nname = \"ball\"\n\n[[bookmarks]]\nname = \"Old\"\norigin = [0.0, 1.0, 0.0]\n\n# Lights\n[[lights]]\n";
+        let bookmark = CameraBookmark { name: "Top".to_string(), origin: [0.0, 5.0, 0.0], yaw_deg: 0.0, pitch_deg: -89.0, ortho_half_height: None };
+        let replaced = replace_bookmarks(text, std::slice::from_ref(&bookmark)).unwrap();
+        assert!(replaced.starts_with("# Scene\n[[shapes]]\nname = \"ball\"\n\n# Lights\n[[lights]]\n\n[[bookmarks]]"), "{}", replaced);
+        let scene = SceneFile::parse(&replaced).unwrap();
+        assert_eq!(scene.bookmarks, [bookmark]);
+        assert_eq!((scene.shapes.len(), scene.lights.len()), (1, 1));
+        assert!(scene.same_entities(&SceneFile::parse(text).unwrap()));
+
+        let cleared = replace_bookmarks(&replaced, &[]).unwrap();
+        assert!(SceneFile::parse(&cleared).unwrap().bookmarks.is_empty());
+        assert!(!cleared.contains("bookmarks"));
+    }
+}
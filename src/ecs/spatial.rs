@@ -0,0 +1,338 @@
+//! Spatial index
+//!
+//! `SpatialIndex` is a uniform grid of `config::spatial::CELL_SIZE` cells
+//! over the `BoundingVolume`s of the shapes and meshes, kept by the index
+//! system after the bounds system has run. It is updated incrementally: an
+//! entity moves between cells only when the cells its bounds overlap
+//! change, and entities that lost their bounds or were despawned are
+//! dropped. Entities spanning more than `config::spatial::MAX_CELLS_PER_ENTITY`
+//! cells are kept in a list every query checks instead.
+//!
+//! Mesh culling asks it for the entities outside every viewport, testing
+//! whole cells before the entities in them, and mouse picking casts a ray
+//! through it with `raycast`, which walks only the cells along the ray.
+//! Unbounded shapes (planes) are not in the index, so they are never picked.
+
+use std::collections::{HashMap, HashSet};
+use cgmath::{InnerSpace, Vector3};
+use legion::world::SubWorld;
+use legion::{system, Entity, IntoQuery};
+use log::debug;
+use crate::config;
+use crate::ecs::culling::{BoundingVolume, ViewFrustums};
+
+/// Cells an entity's bounds overlap, as inclusive corner coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CellRange {
+    min: [i32; 3],
+    max: [i32; 3],
+}
+
+impl CellRange {
+    /// Cells overlapped by a volume
+    fn of(volume: &BoundingVolume) -> Self {
+        let (min, max) = volume.aabb();
+        Self { min: cell_of(min), max: cell_of(max) }
+    }
+
+    /// Number of cells in the range
+    fn count(&self) -> usize {
+        (0..3).map(|axis| (self.max[axis] - self.min[axis] + 1) as usize).product()
+    }
+
+    /// Coordinates of every cell in the range
+    fn cells(self) -> impl Iterator<Item = [i32; 3]> {
+        (self.min[0]..=self.max[0]).flat_map(move |x| {
+            (self.min[1]..=self.max[1]).flat_map(move |y| (self.min[2]..=self.max[2]).map(move |z| [x, y, z]))
+        })
+    }
+}
+
+/// Coordinates of the cell containing a point
+fn cell_of(point: Vector3<f32>) -> [i32; 3] {
+    let size = config::spatial::CELL_SIZE;
+    [(point.x / size).floor() as i32, (point.y / size).floor() as i32, (point.z / size).floor() as i32]
+}
+
+/// Box covered by a cell
+fn cell_volume(cell: [i32; 3]) -> BoundingVolume {
+    let size = config::spatial::CELL_SIZE;
+    let min = Vector3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32) * size;
+    BoundingVolume::Aabb { min, max: min + Vector3::new(size, size, size) }
+}
+
+/// Indexed entity
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    volume: BoundingVolume,
+    /// Cells the entity is listed in, None for entities in the large list
+    cells: Option<CellRange>,
+}
+
+/// Entity hit by a ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub entity: Entity,
+    /// Distance along the ray to the entity's bounds
+    pub distance: f32,
+}
+
+/// Uniform grid over the bounds of the shapes and meshes (ECS resource)
+#[derive(Debug, Default)]
+pub struct SpatialIndex {
+    /// Entities overlapping each occupied cell
+    cells: HashMap<[i32; 3], Vec<Entity>>,
+    /// Entities spanning too many cells to list in each
+    large: Vec<Entity>,
+    entries: HashMap<Entity, IndexEntry>,
+}
+
+impl SpatialIndex {
+    /// Number of indexed entities
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no entity is indexed
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Add an entity or update its bounds
+    ///
+    /// The entity changes cells only if its bounds moved into other ones.
+    pub fn insert(&mut self, entity: Entity, volume: BoundingVolume) {
+        let range = CellRange::of(&volume);
+        let cells = (range.count() <= config::spatial::MAX_CELLS_PER_ENTITY).then_some(range);
+        if let Some(entry) = self.entries.get_mut(&entity) {
+            entry.volume = volume;
+            if entry.cells == cells {
+                return;
+            }
+        }
+        self.unlist(entity);
+        match cells {
+            Some(range) => {
+                for cell in range.cells() {
+                    self.cells.entry(cell).or_default().push(entity);
+                }
+            }
+            None => self.large.push(entity),
+        }
+        self.entries.insert(entity, IndexEntry { volume, cells });
+    }
+
+    /// Drop an entity from the index
+    pub fn remove(&mut self, entity: Entity) {
+        self.unlist(entity);
+        self.entries.remove(&entity);
+    }
+
+    /// Drop every entity that is not kept
+    fn retain(&mut self, keep: impl Fn(Entity) -> bool) {
+        let dropped: Vec<Entity> = self.entries.keys().copied().filter(|&entity| !keep(entity)).collect();
+        for entity in dropped {
+            self.remove(entity);
+        }
+    }
+
+    /// Take an entity off the cells or the large list it is on
+    fn unlist(&mut self, entity: Entity) {
+        let Some(entry) = self.entries.get(&entity) else {
+            return;
+        };
+        match entry.cells {
+            Some(range) => {
+                for cell in range.cells() {
+                    if let Some(entities) = self.cells.get_mut(&cell) {
+                        entities.retain(|&listed| listed != entity);
+                        if entities.is_empty() {
+                            self.cells.remove(&cell);
+                        }
+                    }
+                }
+            }
+            None => self.large.retain(|&listed| listed != entity),
+        }
+    }
+
+    /// Nearest entity whose bounds a ray hits
+    ///
+    /// Walks the cells along the ray up to `config::spatial::MAX_RAY_DISTANCE`
+    /// and stops at the first cell beyond the nearest hit found so far.
+    ///
+    /// # Arguments
+    /// * `origin` - Start of the ray
+    /// * `direction` - Direction of the ray, normalized here
+    pub fn raycast(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<RayHit> {
+        if self.is_empty() || direction.magnitude2() == 0.0 {
+            return None;
+        }
+        let direction = direction.normalize();
+        let hit = |entity: &Entity| {
+            let entry = self.entries.get(entity)?;
+            entry.volume.ray_distance(origin, direction).map(|distance| RayHit { entity: *entity, distance })
+        };
+        let nearer = |best: Option<RayHit>, candidate: RayHit| match best {
+            Some(best) if best.distance <= candidate.distance => Some(best),
+            _ => Some(candidate),
+        };
+        let mut best = self.large.iter().filter_map(hit).fold(None, nearer);
+
+        // Step from cell to cell through the grid (Amanatides and Woo)
+        let size = config::spatial::CELL_SIZE;
+        let mut cell = cell_of(origin);
+        let mut step = [0; 3];
+        let mut next = [f32::INFINITY; 3];
+        let mut delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                next[axis] = ((cell[axis] + 1) as f32 * size - origin[axis]) / direction[axis];
+            } else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                next[axis] = (cell[axis] as f32 * size - origin[axis]) / direction[axis];
+            }
+            if step[axis] != 0 {
+                delta[axis] = size / direction[axis].abs();
+            }
+        }
+
+        let mut entered = 0.0;
+        while entered <= config::spatial::MAX_RAY_DISTANCE {
+            if best.is_some_and(|best| best.distance <= entered) {
+                break;
+            }
+            if let Some(entities) = self.cells.get(&cell) {
+                best = entities.iter().filter_map(hit).fold(best, nearer);
+            }
+            let axis = if next[0] <= next[1] && next[0] <= next[2] { 0 } else if next[1] <= next[2] { 1 } else { 2 };
+            entered = next[axis];
+            cell[axis] += step[axis];
+            next[axis] += delta[axis];
+        }
+        best
+    }
+
+    /// Indexed entities outside the frustum of every viewport
+    ///
+    /// Cells outside every frustum rule out all of their entities at once;
+    /// the entities of the others are tested one by one.
+    ///
+    /// # Returns
+    /// Nothing when there are no frustums, which draws everything
+    pub fn culled(&self, frustums: &ViewFrustums) -> HashSet<Entity> {
+        if frustums.frustums.is_empty() {
+            return HashSet::new();
+        }
+        let mut visible = HashSet::new();
+        let mut tested = HashSet::new();
+        let visible_cells = self.cells
+            .iter()
+            .filter(|(&cell, _)| frustums.contains(&cell_volume(cell)))
+            .flat_map(|(_, entities)| entities);
+        for &entity in visible_cells.chain(&self.large) {
+            if !tested.insert(entity) {
+                continue;
+            }
+            if self.entries.get(&entity).is_some_and(|entry| frustums.contains(&entry.volume)) {
+                visible.insert(entity);
+            }
+        }
+        self.entries.keys().copied().filter(|entity| !visible.contains(entity)).collect()
+    }
+}
+
+/// System that keeps the spatial index in step with the bounding volumes
+///
+/// # Arguments
+/// * `world` - Entities with bounds
+/// * `index` - Index updated in place
+#[system]
+#[read_component(BoundingVolume)]
+pub fn update_spatial_index(world: &SubWorld, #[resource] index: &mut SpatialIndex) {
+    let mut bounded = HashSet::new();
+    for (entity, volume) in <(Entity, &BoundingVolume)>::query().iter(world) {
+        bounded.insert(*entity);
+        index.insert(*entity, *volume);
+    }
+    index.retain(|entity| bounded.contains(&entity));
+    debug!("Spatial index: {} entities in {} cells, {} large", index.len(), index.cells.len(), index.large.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::World;
+
+    fn sphere(center: [f32; 3], radius: f32) -> BoundingVolume {
+        BoundingVolume::Sphere { center: center.into(), radius }
+    }
+
+    #[test]
+    fn test_raycast_finds_the_nearest_entity() {
+        let mut world = World::default();
+        let near = world.push((0u32,));
+        let far = world.push((1u32,));
+        let huge = world.push((2u32,));
+
+        let mut index = SpatialIndex::default();
+        index.insert(far, sphere([0.0, 0.0, 20.0], 1.0));
+        index.insert(near, sphere([0.0, 0.0, 10.0], 1.0));
+        let size = config::spatial::CELL_SIZE * config::spatial::MAX_CELLS_PER_ENTITY as f32;
+        index.insert(huge, BoundingVolume::Aabb { min: Vector3::new(-size, -size, 30.0), max: Vector3::new(size, size, 31.0) });
+        assert_eq!(index.large, vec![huge]);
+
+        let forward = Vector3::new(0.0, 0.0, 1.0);
+        let hit = index.raycast(Vector3::new(0.0, 0.5, 0.0), forward).unwrap();
+        assert_eq!(hit.entity, near);
+        assert!((hit.distance - (10.0 - 0.75f32.sqrt())).abs() < 1e-4);
+
+        // Moving the near entity out of the way reveals the far one
+        index.insert(near, sphere([5.0, 0.0, 10.0], 1.0));
+        assert_eq!(index.raycast(Vector3::new(0.0, 0.5, 0.0), forward).map(|hit| hit.entity), Some(far));
+        index.remove(far);
+        assert_eq!(index.raycast(Vector3::new(0.0, 0.5, 0.0), forward).map(|hit| hit.entity), Some(huge));
+        assert_eq!(index.raycast(Vector3::new(0.0, 0.5, 0.0), -forward), None);
+    }
+
+    #[test]
+    fn test_moving_an_entity_updates_its_cells() {
+        let mut world = World::default();
+        let entity = world.push((0u32,));
+        let mut index = SpatialIndex::default();
+
+        index.insert(entity, sphere([0.5, 0.5, 0.5], 0.1));
+        assert_eq!(index.cells.len(), 1);
+        index.insert(entity, sphere([0.6, 0.5, 0.5], 0.1));
+        assert_eq!(index.cells.get(&[0, 0, 0]), Some(&vec![entity]));
+
+        let far = config::spatial::CELL_SIZE * 3.5;
+        index.insert(entity, sphere([far, 0.5, 0.5], 0.1));
+        assert_eq!(index.cells.len(), 1);
+        assert!(index.cells.contains_key(&[3, 0, 0]));
+
+        index.retain(|_| false);
+        assert!(index.cells.is_empty() && index.is_empty());
+    }
+
+    #[test]
+    fn test_culled_entities_are_outside_every_frustum() {
+        use ash::vk;
+        use crate::ecs::culling::Frustum;
+        use crate::vulkan::viewport::ViewCamera;
+
+        let mut world = World::default();
+        let origin_z = config::rendering::SDF_CAMERA_ORIGIN[2];
+        let ahead = world.push((0u32,));
+        let behind = world.push((1u32,));
+        let mut index = SpatialIndex::default();
+        index.insert(ahead, sphere([0.0, 0.0, origin_z + 5.0], 0.5));
+        index.insert(behind, sphere([0.0, 0.0, origin_z - 5.0], 0.5));
+
+        let rect = vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: vk::Extent2D { width: 100, height: 100 } };
+        let frustums = ViewFrustums { frustums: vec![Frustum::new(&ViewCamera::default(), &rect)], light_direction: None };
+        assert_eq!(index.culled(&frustums), HashSet::from([behind]));
+        assert!(index.culled(&ViewFrustums::default()).is_empty());
+    }
+}
@@ -1,10 +1,28 @@
-use legion::{World, Resources, IntoQuery};
+use legion::{system, World, Resources, IntoQuery, Entity, EntityStore};
+use legion::world::SubWorld;
+use std::collections::HashSet;
 use crate::ecs::components::{
     Transform, Mesh, Renderable, Triangle, Color, Vertex,
-    SDFShape, SDFMaterial, SDFRenderable, SDFLight, SDFShapeType
+    SDFShape, SDFMaterial, SDFRenderable, SDFShapeType,
+    SDFVisibility, SDFDisplayMode, CsgNode, CsgOperation,
+    DebugDisplayMode, DebugDrawSettings, SDFDebugDisplay
 };
+use crate::ecs::modifiers::SDFModifiers;
+use crate::ecs::culling::MeshBounds;
+use crate::ecs::layers::{RenderLayers, VisibleLayers};
+use crate::ecs::lights::{Light, LightKind};
+use crate::hud::csg_panel::{CsgEdit, CsgOperandView, CsgTreeView};
+use crate::hud::debug_draw_panel::DebugDrawEntityView;
+use crate::vulkan::debug_draw::DebugDrawList;
+use crate::vulkan::mesh::MeshDrawList;
+use crate::vulkan::text::TextDrawList;
+use crate::vulkan::particles::ParticleEmitterList;
+use crate::vulkan::scene_buffer::GpuSceneData;
+use crate::noise::NoiseSettings;
 use crate::error::{Result, EcsError};
-use cgmath::Vector3;
+use crate::assets::AssetManager;
+use crate::jobs::JobPool;
+use cgmath::{InnerSpace, Vector3};
 use log::{debug, info, warn};
 
 /// Create a triangle mesh entity in the ECS world
@@ -106,42 +124,32 @@ pub fn render_system(world: &mut World, resources: &mut Resources) {
 
 /// System that updates entity transforms over time
 ///
-/// This system can be used to animate entities by updating their transforms
+/// This system can be used to animate entities by updating their transforms.
+/// It runs in parallel with other systems that do not touch `Transform`.
 ///
 /// # Arguments
-/// * `world` - The ECS world containing entities
-/// * `resources` - The resources container
-pub fn transform_update_system(world: &mut World, _resources: &mut Resources) {
-    let mut query = <(&mut Transform, &Color)>::query();
-    
-    let mut entity_count = 0;
-    
+/// * `transform` - Transform of the entity
+/// * `color` - Color of the entity
+#[system(for_each)]
+pub fn transform_update(transform: &mut Transform, color: &Color) {
     // This system could update transforms over time
     // For now, it's a placeholder for future animation logic
-    for (transform, color) in query.iter_mut(world) {
-        entity_count += 1;
-        
-        // Example: Rotate entities slowly based on their color
-        // This is just an application of how to access component data
-        if color.r > 0.5 {
-            // Red entities rotate faster
-            // transform.rotation.z += 0.02;
-        } else if color.g > 0.5 {
-            // Green entities rotate at medium speed
-            // transform.rotation.z += 0.01;
-        } else {
-            // Blue entities rotate slower
-            // transform.rotation.z += 0.005;
-        }
-        
-        // Log transform information for debugging
-        debug!("Transform update for entity: position={:?}, rotation={:?}, scale={:?}",
-               transform.position, transform.rotation, transform.scale);
+    // Example: Rotate entities slowly based on their color
+    // This is just an application of how to access component data
+    if color.r > 0.5 {
+        // Red entities rotate faster
+        // transform.rotation.z += 0.02;
+    } else if color.g > 0.5 {
+        // Green entities rotate at medium speed
+        // transform.rotation.z += 0.01;
+    } else {
+        // Blue entities rotate slower
+        // transform.rotation.z += 0.005;
     }
     
-    if entity_count > 0 {
-        debug!("Updated transforms for {} entities", entity_count);
-    }
+    // Log transform information for debugging
+    debug!("Transform update for entity: position={:?}, rotation={:?}, scale={:?}",
+           transform.position, transform.rotation, transform.scale);
 }
 
 /// Create SDF entities in the ECS world
@@ -169,6 +177,7 @@ pub fn create_sdf_entities(world: &mut World, resources: &mut Resources) -> Resu
             metallic: 0.0,
             roughness: 0.5,
             emission: 0.0,
+            ..SDFMaterial::default()
         },
         Transform {
             position: Vector3::new(0.0, 0.0, 0.0),
@@ -190,6 +199,7 @@ pub fn create_sdf_entities(world: &mut World, resources: &mut Resources) -> Resu
             metallic: 0.1,
             roughness: 0.7,
             emission: 0.0,
+            ..SDFMaterial::default()
         },
         Transform {
             position: Vector3::new(-1.5, 0.0, 0.0),
@@ -211,6 +221,7 @@ pub fn create_sdf_entities(world: &mut World, resources: &mut Resources) -> Resu
             metallic: 0.3,
             roughness: 0.3,
             emission: 0.0,
+            ..SDFMaterial::default()
         },
         Transform {
             position: Vector3::new(1.5, 0.0, 0.0),
@@ -222,10 +233,61 @@ pub fn create_sdf_entities(world: &mut World, resources: &mut Resources) -> Resu
     
     // Create a light
     let light_entity = world.push((
-        SDFLight {
+        Light {
+            kind: LightKind::Point,
             position: Vector3::new(2.0, 2.0, 2.0),
             color: Vector3::new(1.0, 1.0, 1.0),
             intensity: 1.0,
+            ..Light::default()
+        },
+    ));
+    
+    // Create a CSG composite above the center: a box with a sphere carved out
+    let carved_box_entity = world.push((
+        SDFShape {
+            shape_type: SDFShapeType::Box,
+            size: 0.3,
+            params: [0.0; 4],
+        },
+        SDFMaterial {
+            color: Vector3::new(1.0, 0.8, 0.2),
+            metallic: 0.2,
+            roughness: 0.4,
+            emission: 0.0,
+            ..SDFMaterial::default()
+        },
+        Transform {
+            position: Vector3::new(0.0, 1.0, 0.0),
+            rotation: Vector3::new(0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        },
+        SDFRenderable,
+    ));
+    let cutter_entity = world.push((
+        SDFShape {
+            shape_type: SDFShapeType::Sphere,
+            size: 0.38,
+            params: [0.0; 4],
+        },
+        SDFMaterial {
+            color: Vector3::new(0.9, 0.3, 0.1),
+            metallic: 0.0,
+            roughness: 0.6,
+            emission: 0.0,
+            ..SDFMaterial::default()
+        },
+        Transform {
+            position: Vector3::new(0.0, 1.0, -0.1),
+            rotation: Vector3::new(0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        },
+        SDFRenderable,
+    ));
+    let composite_entity = world.push((
+        CsgNode {
+            operation: CsgOperation::Subtraction,
+            blend_radius: 0.05,
+            operands: vec![carved_box_entity, cutter_entity],
         },
     ));
     
@@ -233,55 +295,454 @@ pub fn create_sdf_entities(world: &mut World, resources: &mut Resources) -> Resu
     sdf_entities.push(box_entity);
     sdf_entities.push(sphere2_entity);
     sdf_entities.push(light_entity);
+    sdf_entities.push(composite_entity);
     
-    info!("Created SDF entities: sphere, box, sphere, and light");
+    info!("Created SDF entities: sphere, box, sphere, CSG composite, and light");
     debug!("SDF entity IDs: sphere={:?}, box={:?}, sphere2={:?}, light={:?}",
            sphere_entity, box_entity, sphere2_entity, light_entity);
     
     Ok(())
 }
 
-/// System that handles SDF rendering
+/// System that collects SDF render data from entities
 ///
-/// This system collects SDF render data from entities and updates the Vulkan renderer
+/// Builds the scene buffer contents into the `GpuSceneData` resource;
+/// `upload_frame_data_system` hands it to the renderer afterwards. Shapes
+/// and composites on no layer that a drawn viewport shows are left out.
+/// Shapes outside the view are kept, since they still cast shadows into it
+/// and show up in its reflections.
 ///
 /// # Arguments
-/// * `world` - The ECS world containing entities
-/// * `resources` - The resources container including the Vulkan renderer
-pub fn sdf_render_system(world: &mut World, resources: &mut Resources) {
-    let _vulkan_renderer = match resources.get::<std::sync::Arc<std::sync::Mutex<crate::vulkan::renderer::VulkanRenderer>>>() {
+/// * `world` - The SDF components of the ECS world
+/// * `noise` - Global noise settings, used by noise modifiers
+/// * `visible_layers` - Render layers shown by the drawn viewports
+/// * `scene` - Scene data rebuilt for this frame
+#[system]
+#[read_component(SDFShape)]
+#[read_component(SDFMaterial)]
+#[read_component(Transform)]
+#[read_component(SDFVisibility)]
+#[read_component(CsgNode)]
+#[read_component(Light)]
+#[read_component(SDFModifiers)]
+#[read_component(RenderLayers)]
+pub fn build_scene_data(
+    world: &SubWorld,
+    #[resource] noise: &NoiseSettings,
+    #[resource] visible_layers: &VisibleLayers,
+    #[resource] scene: &mut GpuSceneData,
+) {
+    let mut sdf_query = <(Entity, &SDFShape, &SDFMaterial, &Transform, Option<&SDFVisibility>, Option<&SDFModifiers>, Option<&RenderLayers>)>::query();
+    let mut csg_query = <(&CsgNode, Option<&SDFVisibility>, Option<&RenderLayers>)>::query();
+    let mut light_query = <&Light>::query();
+    
+    let mut scene_data = GpuSceneData::default();
+    
+    let composites: Vec<(CsgNode, SDFDisplayMode, bool, RenderLayers)> = csg_query
+        .iter(world)
+        .map(|(node, visibility, layers)| {
+            let display_mode = visibility.map(|v| v.display_mode()).unwrap_or(SDFDisplayMode::Visible);
+            (node.clone(), display_mode, visibility.is_some_and(|v| v.selected), layers.copied().unwrap_or_default())
+        })
+        .collect();
+    let operands: HashSet<Entity> = composites
+        .iter()
+        .flat_map(|(node, _, _, _)| node.operands.iter().copied())
+        .collect();
+    
+    for (entity, shape, material, transform, visibility, modifiers, layers) in sdf_query.iter(world) {
+        let layers = layers.copied().unwrap_or_default();
+        if !layers.is_visible_in(visible_layers.0) {
+            continue;
+        }
+        
+        let is_operand = operands.contains(entity);
+        let display_mode = if is_operand {
+            // Operands are drawn through their composite; a hidden operand
+            // is still referenced, so it shows up on its own as a ghost
+            match visibility {
+                Some(v) if v.hidden => SDFVisibility { referenced: true, ..v.clone() }.display_mode(),
+                _ => continue,
+            }
+        } else {
+            visibility
+                .map(|v| v.display_mode())
+                .unwrap_or(SDFDisplayMode::Visible)
+        };
+        
+        debug!("SDF entity: shape={:?}, size={}, position={:?}, color={:?}, display={:?}",
+               shape.shape_type, shape.size, transform.position, material.color, display_mode);
+        
+        if !scene_data.push_shape(shape, material, transform, display_mode) {
+            warn!("Scene buffer full, dropping SDF shapes beyond {}", crate::config::rendering::MAX_SDF_SHAPES);
+            break;
+        }
+        scene_data.set_last_shape_layers(layers.0);
+        if visibility.is_some_and(|v| v.selected) {
+            scene_data.select_last_shape();
+        }
+        if let Some(modifiers) = modifiers {
+            if !scene_data.modify_last_shape(&modifiers.0) {
+                warn!("Scene buffer full, dropping modifiers beyond {}", crate::config::rendering::MAX_SDF_MODIFIERS);
+            }
+        }
+    }
+    
+    // A selected composite outlines all of its operands
+    for (index, (node, display_mode, selected, layers)) in composites.iter().enumerate() {
+        if !layers.is_visible_in(visible_layers.0) {
+            continue;
+        }
+        let group = index as i32 + 1;
+        for &operand in &node.operands {
+            let Ok(entry) = world.entry_ref(operand) else {
+                warn!("CSG operand {:?} no longer exists", operand);
+                continue;
+            };
+            let (Ok(shape), Ok(material), Ok(transform)) = (
+                entry.get_component::<SDFShape>(),
+                entry.get_component::<SDFMaterial>(),
+                entry.get_component::<Transform>(),
+            ) else {
+                warn!("CSG operand {:?} is not an SDF shape", operand);
+                continue;
+            };
+            
+            if !scene_data.push_csg_operand(shape, material, transform, *display_mode, group, node.operation, node.blend_radius) {
+                warn!("Scene buffer full, dropping CSG operands beyond {}", crate::config::rendering::MAX_SDF_SHAPES);
+                break;
+            }
+            scene_data.set_last_shape_layers(layers.0);
+            if *selected || entry.get_component::<SDFVisibility>().is_ok_and(|v| v.selected) {
+                scene_data.select_last_shape();
+            }
+            if let Ok(modifiers) = entry.get_component::<SDFModifiers>() {
+                if !scene_data.modify_last_shape(&modifiers.0) {
+                    warn!("Scene buffer full, dropping modifiers beyond {}", crate::config::rendering::MAX_SDF_MODIFIERS);
+                }
+            }
+        }
+    }
+    
+    for light in light_query.iter(world) {
+        debug!("Light: position={:?}, color={:?}, intensity={}",
+               light.position, light.color, light.intensity);
+        
+        if !scene_data.push_light(light) {
+            warn!("Scene buffer full, dropping lights beyond {}", crate::config::rendering::MAX_SDF_LIGHTS);
+            break;
+        }
+    }
+    
+    debug!("Collected {} SDF entities with {} lights", scene_data.shape_count(), scene_data.light_count());
+    scene_data.noise = noise.as_gpu();
+    *scene = scene_data;
+}
+
+/// System that collects entity bounds and wireframes for the debug line renderer
+///
+/// Every SDF entity is considered, including hidden ones, so empty or
+/// invisible entities can still be located in the viewport.
+///
+/// # Arguments
+/// * `world` - The shape components of the ECS world
+/// * `settings` - Global debug overlay settings
+/// * `lines` - Debug lines rebuilt for this frame
+#[system]
+#[read_component(SDFShape)]
+#[read_component(Transform)]
+#[read_component(SDFDebugDisplay)]
+pub fn build_debug_lines(world: &SubWorld, #[resource] settings: &DebugDrawSettings, #[resource] lines: &mut DebugDrawList) {
+    let mut query = <(&SDFShape, &Transform, Option<&SDFDebugDisplay>)>::query();
+    let mut list = DebugDrawList::new();
+    
+    for (shape, transform, display) in query.iter(world) {
+        match settings.resolve(display.map(|d| d.mode)) {
+            DebugDisplayMode::None => {}
+            DebugDisplayMode::Bounds => add_shape_bounds(&mut list, shape, transform),
+            DebugDisplayMode::Wireframe => add_shape_wireframe(&mut list, shape, transform),
+        }
+    }
+    
+    debug!("Debug draw: {} lines", list.line_count());
+    *lines = list;
+}
+
+/// System that hands the data built this frame to the renderer
+///
+/// Runs thread-local after the parallel systems, so it is the only system
+/// locking the renderer.
+///
+/// # Arguments
+/// * `_world` - The ECS world (unused)
+/// * `resources` - The resources container including the `SharedRenderer`,
+///   `GpuSceneData`, `DebugDrawList`, `MeshDrawList`, `TextDrawList` and
+///   `ParticleEmitterList`, the `AssetManager` and `JobPool` the meshes are
+///   loaded with, and the `MeshBounds` learned from the renderer
+pub fn upload_frame_data_system(_world: &mut World, resources: &mut Resources) {
+    let shared_renderer = match resources.get::<crate::renderer::SharedRenderer>() {
         Some(renderer) => renderer,
         None => {
-            warn!("VulkanRenderer resource not found in SDF render system");
+            warn!("Renderer resource not found in upload system");
             return;
         }
     };
+    let mut renderer = shared_renderer.lock().unwrap();
+    
+    if let Some(scene_data) = resources.get::<GpuSceneData>() {
+        debug!("Uploading {} SDF entities with {} lights", scene_data.shape_count(), scene_data.light_count());
+        renderer.set_scene_data(*scene_data);
+    }
+    if let Some(mut lines) = resources.get_mut::<DebugDrawList>() {
+        renderer.set_debug_lines(std::mem::take(&mut *lines));
+    }
+    if let Some(mut draws) = resources.get_mut::<MeshDrawList>() {
+        // Mesh files are read on the job pool, a placeholder is drawn meanwhile
+        if let (Some(mut assets), Some(jobs)) = (resources.get_mut::<AssetManager>(), resources.get::<JobPool>()) {
+            assets.prepare_mesh_draws(&jobs, &mut draws);
+        }
+        renderer.set_mesh_draws(std::mem::take(&mut *draws));
+    }
+    if let Some(mut bounds) = resources.get_mut::<MeshBounds>() {
+        // Evicted meshes keep their bounds, so they stay culled until seen
+        bounds.0.extend(renderer.mesh_bounds());
+    }
+    if let Some(mut texts) = resources.get_mut::<TextDrawList>() {
+        renderer.set_text_draws(std::mem::take(&mut *texts));
+    }
+    if let Some(mut emitters) = resources.get_mut::<ParticleEmitterList>() {
+        renderer.set_particle_emitters(std::mem::take(&mut *emitters));
+    }
+}
+
+/// Add the bounding box of a shape to a debug draw list
+fn add_shape_bounds(list: &mut DebugDrawList, shape: &SDFShape, transform: &Transform) {
+    let color = crate::config::debug_draw::BOUNDS_COLOR;
+    let extents = shape.half_extents().unwrap_or_else(|| {
+        // Planes are unbounded, outline a flat square instead
+        let extent = crate::config::debug_draw::PLANE_EXTENT;
+        Vector3::new(extent, 0.0, extent)
+    });
+    list.aabb(transform.position - extents, transform.position + extents, color);
+}
+
+/// Add an approximate wireframe of a shape to a debug draw list
+fn add_shape_wireframe(list: &mut DebugDrawList, shape: &SDFShape, transform: &Transform) {
+    let color = crate::config::debug_draw::WIREFRAME_COLOR;
+    let center = transform.position;
     
-    let mut sdf_query = <(&SDFShape, &SDFMaterial, &Transform)>::query();
-    let mut light_query = <&SDFLight>::query();
+    match shape.shape_type {
+        SDFShapeType::Sphere => {
+            for axis in 0..3 {
+                list.circle(center, shape.size, axis, color);
+            }
+        }
+        SDFShapeType::Box => {
+            let extents = Vector3::new(shape.size, shape.size, shape.size);
+            list.aabb(center - extents, center + extents, color);
+        }
+        SDFShapeType::Plane => {
+            // A grid patch on the plane (y = 0 in local space)
+            let extent = crate::config::debug_draw::PLANE_EXTENT;
+            let lines = 8;
+            for i in 0..=lines {
+                let t = -extent + 2.0 * extent * i as f32 / lines as f32;
+                list.line(center + Vector3::new(t, 0.0, -extent), center + Vector3::new(t, 0.0, extent), color);
+                list.line(center + Vector3::new(-extent, 0.0, t), center + Vector3::new(extent, 0.0, t), color);
+            }
+        }
+        SDFShapeType::Torus => {
+            let minor = shape.params[0];
+            list.circle(center, shape.size + minor, 1, color);
+            list.circle(center, (shape.size - minor).max(0.0), 1, color);
+            list.circle(center + Vector3::new(0.0, minor, 0.0), shape.size, 1, color);
+            list.circle(center - Vector3::new(0.0, minor, 0.0), shape.size, 1, color);
+        }
+        SDFShapeType::Cylinder => {
+            let half_height = shape.params[0];
+            let top = center + Vector3::new(0.0, half_height, 0.0);
+            let bottom = center - Vector3::new(0.0, half_height, 0.0);
+            list.circle(top, shape.size, 1, color);
+            list.circle(bottom, shape.size, 1, color);
+            for offset in [
+                Vector3::new(shape.size, 0.0, 0.0),
+                Vector3::new(-shape.size, 0.0, 0.0),
+                Vector3::new(0.0, 0.0, shape.size),
+                Vector3::new(0.0, 0.0, -shape.size),
+            ] {
+                list.line(top + offset, bottom + offset, color);
+            }
+        }
+    }
+}
+
+/// Build a snapshot of every SDF entity for the debug draw panel
+///
+/// # Arguments
+/// * `world` - The ECS world containing entities
+///
+/// # Returns
+/// One view per SDF shape entity
+pub fn collect_debug_draw_entities(world: &World) -> Vec<DebugDrawEntityView> {
+    let mut query = <(Entity, &SDFShape, Option<&SDFDebugDisplay>)>::query();
     
-    // Collect all SDF renderable entities
-    let sdf_entities: Vec<_> = sdf_query.iter(world).collect();
-    let lights: Vec<_> = light_query.iter(world).collect();
+    query
+        .iter(world)
+        .map(|(&entity, shape, display)| DebugDrawEntityView {
+            entity,
+            shape_type: shape.shape_type.clone(),
+            mode: display.map(|d| d.mode).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Set the debug overlay mode of an entity, adding the component if needed
+///
+/// # Arguments
+/// * `world` - The ECS world containing the entity
+/// * `entity` - The entity to update
+/// * `mode` - The new overlay mode
+///
+/// # Returns
+/// * Ok(()) if the mode was set
+/// * Err if the entity does not exist
+pub fn set_debug_display(world: &mut World, entity: Entity, mode: DebugDisplayMode) -> Result<()> {
+    let mut entry = world.entry(entity)
+        .ok_or_else(|| EcsError::ResourceAccess(format!("Entity {:?} not found", entity)))?;
+    
+    match entry.get_component_mut::<SDFDebugDisplay>() {
+        Ok(display) => display.mode = mode,
+        Err(_) => entry.add_component(SDFDebugDisplay { mode }),
+    }
     
-    if sdf_entities.is_empty() {
-        debug!("No SDF renderable entities found");
+    debug!("Debug display of {:?} set to {}", entity, mode.name());
+    Ok(())
+}
+
+/// Make one entity the selected one, for the selection outline
+///
+/// Clears `SDFVisibility::selected` everywhere else and adds the component to
+/// the selected entity if needed; an entity that no longer exists selects
+/// nothing.
+///
+/// # Arguments
+/// * `world` - The ECS world
+/// * `selected` - Entity to select, None to clear the selection
+pub fn set_selection(world: &mut World, selected: Option<Entity>) {
+    for (entity, visibility) in <(Entity, &mut SDFVisibility)>::query().iter_mut(world) {
+        visibility.selected = Some(*entity) == selected;
+    }
+    let Some(mut entry) = selected.and_then(|entity| world.entry(entity)) else {
         return;
+    };
+    if entry.get_component::<SDFVisibility>().is_err() {
+        entry.add_component(SDFVisibility { selected: true, ..SDFVisibility::default() });
+    }
+}
+
+/// Bounding sphere of an entity, for framing it with the camera
+///
+/// A CSG composite is bounded by its operands; a light or another entity
+/// with only a position gets a small sphere around it.
+///
+/// # Arguments
+/// * `world` - The ECS world containing the entity
+/// * `entity` - The entity
+///
+/// # Returns
+/// Center and radius, None for unbounded shapes (planes) and entities without a position
+pub fn entity_bounds(world: &World, entity: Entity) -> Option<(Vector3<f32>, f32)> {
+    let entry = world.entry_ref(entity).ok()?;
+    if let Ok(node) = entry.get_component::<CsgNode>() {
+        return node.operands
+            .iter()
+            .map(|&operand| entity_bounds(world, operand))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .reduce(merge_spheres);
+    }
+    let point_radius = crate::config::camera::FRAME_POINT_RADIUS;
+    match (entry.get_component::<SDFShape>(), entry.get_component::<Transform>()) {
+        (Ok(shape), Ok(transform)) => shape.bounding_radius().map(|radius| (transform.position, radius)),
+        (Err(_), Ok(transform)) => Some((transform.position, point_radius)),
+        (_, Err(_)) => entry.get_component::<Light>().ok().map(|light| (light.position, point_radius)),
+    }
+}
+
+/// Smallest sphere containing two spheres, given as center and radius
+fn merge_spheres(a: (Vector3<f32>, f32), b: (Vector3<f32>, f32)) -> (Vector3<f32>, f32) {
+    let offset = b.0 - a.0;
+    let distance = offset.magnitude();
+    if distance + b.1 <= a.1 {
+        return a;
+    }
+    if distance + a.1 <= b.1 {
+        return b;
     }
+    let radius = (distance + a.1 + b.1) / 2.0;
+    (a.0 + offset * ((radius - a.1) / distance), radius)
+}
+
+/// Build a snapshot of every CSG composite for the CSG tree editor
+///
+/// # Arguments
+/// * `world` - The ECS world containing CSG nodes
+///
+/// # Returns
+/// One tree view per composite entity
+pub fn collect_csg_trees(world: &World) -> Vec<CsgTreeView> {
+    let mut csg_query = <(Entity, &CsgNode)>::query();
     
-    debug!("Rendering {} SDF entities with {} lights", sdf_entities.len(), lights.len());
+    csg_query
+        .iter(world)
+        .map(|(&entity, node)| {
+            let operands = node.operands
+                .iter()
+                .map(|&operand| {
+                    let shape_type = world.entry_ref(operand)
+                        .ok()
+                        .and_then(|entry| entry.get_component::<SDFShape>().ok().map(|shape| shape.shape_type.clone()));
+                    CsgOperandView { entity: operand, shape_type }
+                })
+                .collect();
+            
+            CsgTreeView {
+                entity,
+                operation: node.operation,
+                blend_radius: node.blend_radius,
+                operands,
+            }
+        })
+        .collect()
+}
+
+/// Apply an edit from the CSG tree editor to the ECS world
+///
+/// # Arguments
+/// * `world` - The ECS world containing CSG nodes
+/// * `edit` - The edit to apply
+///
+/// # Returns
+/// * Ok(()) if the edit was applied
+/// * Err if the target composite does not exist
+pub fn apply_csg_edit(world: &mut World, edit: &CsgEdit) -> Result<()> {
+    let mut entry = world.entry_mut(edit.composite())
+        .map_err(|e| EcsError::ResourceAccess(format!("CSG composite not found: {:?}", e)))?;
+    let node = entry.get_component_mut::<CsgNode>()
+        .map_err(|e| EcsError::ResourceAccess(format!("Entity is not a CSG composite: {:?}", e)))?;
     
-    // For now, the SDF data is hardcoded in the shader
-    // In a future implementation, we would update uniform buffers with ECS data
-    for (shape, material, transform) in sdf_entities {
-        debug!("SDF entity: shape={:?}, size={}, position={:?}, color={:?}",
-               shape.shape_type, shape.size, transform.position, material.color);
+    match *edit {
+        CsgEdit::SetOperation { operation, .. } => node.operation = operation,
+        CsgEdit::SetBlendRadius { blend_radius, .. } => node.blend_radius = blend_radius.max(0.0),
+        CsgEdit::MoveOperand { from, to, .. } => {
+            if !node.move_operand(from, to) {
+                warn!("Ignoring CSG operand move {} -> {} (out of range)", from, to);
+            }
+        }
     }
     
-    for light in lights {
-        debug!("Light: position={:?}, color={:?}, intensity={}",
-               light.position, light.color, light.intensity);
-    }
+    debug!("Applied CSG edit: {:?}", edit);
+    Ok(())
 }
 
 /// System that logs statistics about the ECS world
@@ -309,4 +770,105 @@ pub fn debug_system(world: &mut World, resources: &mut Resources) {
     if let Some(triangle_entities) = resources.get::<Vec<legion::Entity>>() {
         info!("  Tracked triangle entities: {}", triangle_entities.len());
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::Schedule;
+
+    #[test]
+    fn test_parallel_systems_build_frame_data() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(GpuSceneData::default());
+        resources.insert(NoiseSettings::default());
+        resources.insert(VisibleLayers::default());
+        resources.insert(DebugDrawList::new());
+        resources.insert(DebugDrawSettings { global_override: Some(DebugDisplayMode::Bounds) });
+
+        world.push((SDFShape::default(), SDFMaterial::default(), Transform::default(), SDFRenderable));
+        world.push((Light::default(),));
+
+        let mut schedule = Schedule::builder()
+            .add_system(transform_update_system())
+            .add_system(build_scene_data_system())
+            .add_system(build_debug_lines_system())
+            .build();
+        schedule.execute(&mut world, &mut resources);
+
+        let scene = resources.get::<GpuSceneData>().unwrap();
+        assert_eq!(scene.shape_count(), 1);
+        assert_eq!(scene.light_count(), 1);
+        assert!(resources.get::<DebugDrawList>().unwrap().line_count() > 0);
+    }
+
+    #[test]
+    fn test_selection_marks_shapes_for_the_outline() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(GpuSceneData::default());
+        resources.insert(NoiseSettings::default());
+        resources.insert(VisibleLayers::default());
+        let first = world.push((SDFShape::default(), SDFMaterial::default(), Transform::default()));
+        let second = world.push((SDFShape::default(), SDFMaterial::default(), Transform::default()));
+        let mut schedule = Schedule::builder().add_system(build_scene_data_system()).build();
+
+        set_selection(&mut world, Some(first));
+        set_selection(&mut world, Some(second));
+        schedule.execute(&mut world, &mut resources);
+        let selected: Vec<bool> = {
+            let scene = resources.get::<GpuSceneData>().unwrap();
+            scene.shapes[..scene.shape_count()].iter().map(|shape| shape.material[3] > 0.0).collect()
+        };
+        assert_eq!(selected.iter().filter(|&&s| s).count(), 1);
+        assert!(world.entry(second).unwrap().get_component::<SDFVisibility>().unwrap().selected);
+        assert!(!world.entry(first).unwrap().get_component::<SDFVisibility>().unwrap().selected);
+
+        set_selection(&mut world, None);
+        schedule.execute(&mut world, &mut resources);
+        assert!(!resources.get::<GpuSceneData>().unwrap().has_selection());
+    }
+
+    #[test]
+    fn test_shapes_on_hidden_layers_are_left_out() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(GpuSceneData::default());
+        resources.insert(NoiseSettings::default());
+        resources.insert(VisibleLayers(0b001));
+        world.push((SDFShape::default(), SDFMaterial::default(), Transform::default()));
+        world.push((SDFShape::default(), SDFMaterial::default(), Transform::default(), RenderLayers(0b110)));
+        let mut schedule = Schedule::builder().add_system(build_scene_data_system()).build();
+
+        schedule.execute(&mut world, &mut resources);
+        assert_eq!(resources.get::<GpuSceneData>().unwrap().shape_count(), 1);
+
+        resources.insert(VisibleLayers(0b011));
+        schedule.execute(&mut world, &mut resources);
+        let scene = resources.get::<GpuSceneData>().unwrap();
+        let mut layers: Vec<u32> = scene.shapes[..scene.shape_count()].iter().map(|shape| shape.layers[0]).collect();
+        layers.sort();
+        assert_eq!(layers, [0b001, 0b110]);
+    }
+
+    #[test]
+    fn test_composite_bounds_contain_the_operands() {
+        let mut world = World::default();
+        let sphere = |world: &mut World, x: f32| world.push((
+            SDFShape { size: 0.5, ..SDFShape::default() },
+            Transform { position: Vector3::new(x, 1.0, 0.0), ..Transform::default() },
+        ));
+        let left = sphere(&mut world, -1.0);
+        let right = sphere(&mut world, 1.0);
+        let composite = world.push((CsgNode { operation: CsgOperation::Union, blend_radius: 0.0, operands: vec![left, right] },));
+        let light = world.push((Light::default(),));
+
+        assert_eq!(entity_bounds(&world, left), Some((Vector3::new(-1.0, 1.0, 0.0), 0.5)));
+        assert_eq!(entity_bounds(&world, composite), Some((Vector3::new(0.0, 1.0, 0.0), 1.5)));
+        assert_eq!(entity_bounds(&world, light), Some((Light::default().position, crate::config::camera::FRAME_POINT_RADIUS)));
+
+        let plane = world.push((SDFShape { shape_type: SDFShapeType::Plane, ..SDFShape::default() }, Transform::default()));
+        assert_eq!(entity_bounds(&world, plane), None);
+    }
+}
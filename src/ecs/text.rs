@@ -0,0 +1,173 @@
+//! 3D text
+//!
+//! Entities with a `Text3D` and a `Transform` show their text as glyphs
+//! extruded along their local Z axis, raymarched by the SDF shader together
+//! with the shapes. The text is centered on the entity's position and reads
+//! along local +X, seen from local +Z. The text system collects a
+//! `TextDrawList` that the upload system hands to the renderer, which lays
+//! out the glyphs from the MSDF font atlas (see `vulkan::text`). Like the
+//! shapes, texts are on render layers (see `ecs::layers`). Texts are stored
+//! in the scene file's `[[texts]]` tables and edited in the text panel.
+
+use cgmath::Vector3;
+use legion::world::SubWorld;
+use legion::{system, Entity, IntoQuery, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::config;
+use crate::ecs::components::{SDFDisplayMode, SDFVisibility, Transform};
+use crate::ecs::layers::{RenderLayers, VisibleLayers};
+use crate::hud::text_panel::{TextEdit, TextView};
+use crate::vulkan::text::TextDrawList;
+
+/// Text drawn as extruded glyphs in the scene
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Text3D {
+    /// Lines separated by `\n`
+    pub text: String,
+    /// Font file, empty for the `default_font`
+    pub font: String,
+    /// Height of an em in world units
+    pub size: f32,
+    /// Extrusion along the local Z axis in world units, 0 for flat glyphs
+    pub depth: f32,
+    /// Linear RGB (see the `color` module)
+    pub color: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl Default for Text3D {
+    fn default() -> Self {
+        Self {
+            text: "Text".to_string(),
+            font: String::new(),
+            size: 0.3,
+            depth: 0.05,
+            color: [0.9, 0.9, 0.9],
+            metallic: 0.0,
+            roughness: 0.5,
+        }
+    }
+}
+
+/// Font file of texts that name none
+///
+/// The first of `config::text::DEFAULT_FONTS` that exists, or the first one
+/// when none does, so a failed load names a font the platform should have.
+pub fn default_font() -> &'static str {
+    let fonts = config::text::DEFAULT_FONTS;
+    fonts.iter().copied().find(|font| Path::new(font).is_file()).unwrap_or(fonts[0])
+}
+
+/// System that collects the texts of the frame
+///
+/// Hidden and empty texts are left out, as are texts on no layer a drawn
+/// viewport shows.
+///
+/// # Arguments
+/// * `world` - Texts, their transforms, visibility and layers
+/// * `visible_layers` - Layers shown by the drawn viewports
+/// * `texts` - List replaced with this frame's texts
+#[system]
+#[read_component(Text3D)]
+#[read_component(Transform)]
+#[read_component(SDFVisibility)]
+#[read_component(RenderLayers)]
+pub fn build_text_draws(world: &SubWorld, #[resource] visible_layers: &VisibleLayers, #[resource] texts: &mut TextDrawList) {
+    let mut list = TextDrawList::new();
+    let mut query = <(&Text3D, &Transform, Option<&SDFVisibility>, Option<&RenderLayers>)>::query();
+    for (text, transform, visibility, layers) in query.iter(world) {
+        let display_mode = visibility.map(|v| v.display_mode()).unwrap_or(SDFDisplayMode::Visible);
+        let layers = layers.copied().unwrap_or_default();
+        if display_mode == SDFDisplayMode::Hidden || text.text.trim().is_empty() || !layers.is_visible_in(visible_layers.0) {
+            continue;
+        }
+        list.push(text, transform, display_mode, visibility.is_some_and(|v| v.selected), layers.0);
+    }
+
+    debug!("Texts: {}", list.draws().len());
+    *texts = list;
+}
+
+/// Snapshot of the texts for the text panel
+pub fn collect_texts(world: &World) -> Vec<TextView> {
+    <(Entity, &Text3D, &Transform)>::query()
+        .iter(world)
+        .map(|(entity, text, transform)| TextView {
+            entity: *entity,
+            position: transform.position.into(),
+            rotation: transform.rotation.into(),
+            text: text.clone(),
+        })
+        .collect()
+}
+
+/// Apply an edit from the text panel
+pub fn apply_text_edit(world: &mut World, edit: TextEdit) {
+    debug!("Text edit applied: {:?}", edit);
+    match edit {
+        TextEdit::Add { position } => {
+            let transform = Transform { position: Vector3::from(position), ..Transform::default() };
+            world.push((Text3D::default(), transform));
+        }
+        TextEdit::Set { entity, text, position, rotation } => {
+            if let Some(mut entry) = world.entry(entity) {
+                if let Ok(transform) = entry.get_component_mut::<Transform>() {
+                    transform.position = position.into();
+                    transform.rotation = rotation.into();
+                }
+                if let Ok(component) = entry.get_component_mut::<Text3D>() {
+                    *component = text;
+                }
+            }
+        }
+        TextEdit::Remove { entity } => {
+            world.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::{Resources, Schedule};
+
+    #[test]
+    fn test_build_text_draws_skips_hidden_and_empty_texts() {
+        let mut world = World::default();
+        let shown = world.push((Text3D::default(), Transform::default(), SDFVisibility { selected: true, ..SDFVisibility::default() }));
+        world.push((Text3D::default(), Transform::default(), SDFVisibility { hidden: true, ..SDFVisibility::default() }));
+        world.push((Text3D { text: " \n".to_string(), ..Text3D::default() }, Transform::default()));
+        world.push((Text3D::default(), Transform::default(), RenderLayers(0b10)));
+        world.push((Text3D::default(),));
+
+        let mut resources = Resources::default();
+        resources.insert(VisibleLayers(0b01));
+        resources.insert(TextDrawList::default());
+        let mut schedule = Schedule::builder().add_system(build_text_draws_system()).build();
+        schedule.execute(&mut world, &mut resources);
+
+        let list = resources.get::<TextDrawList>().unwrap();
+        assert_eq!(list.draws().len(), 1);
+        assert!(list.draws()[0].selected);
+        assert_eq!(list.draws()[0].layers, config::layers::DEFAULT);
+
+        apply_text_edit(&mut world, TextEdit::Set {
+            entity: shown,
+            text: Text3D { text: "Title".to_string(), ..Text3D::default() },
+            position: [0.0, 1.0, 0.0],
+            rotation: [0.0; 3],
+        });
+        let views = collect_texts(&world);
+        let view = views.iter().find(|view| view.entity == shown).unwrap();
+        assert_eq!((view.text.text.as_str(), view.position), ("Title", [0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn test_default_font_is_a_platform_font() {
+        assert!(config::text::DEFAULT_FONTS.contains(&default_font()));
+    }
+}
@@ -1,11 +1,75 @@
 use legion::{Resources, Schedule, World};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use chrono::{DateTime, Local};
 use std::sync::{Arc, Mutex};
-use crate::ecs::systems::{create_sdf_entities, sdf_render_system, transform_update_system};
+use std::sync::mpsc::Receiver;
+use crate::ecs::systems::{
+    apply_csg_edit, build_debug_lines_system, build_scene_data_system, collect_csg_trees,
+    collect_debug_draw_entities, create_sdf_entities, entity_bounds, set_debug_display, set_selection, transform_update_system,
+    upload_frame_data_system
+};
+use crate::ecs::components::{CsgNode, DebugDrawSettings, FrameTime, Transform};
+use crate::ecs::animation::{animation_playback_system, apply_animation_command, collect_animation_status};
+use crate::ecs::physics::{physics_step_system, throw_sphere, PhysicsSettings, PhysicsWorld};
+use crate::ecs::hierarchy::{collect_hierarchy, hierarchy_propagation_system, set_parent};
+use crate::ecs::mesh::{attach_mesh_assets, build_mesh_draws_system, MeshRenderer};
+use crate::ecs::culling::{update_bounds_system, CullingStats, MeshBounds, ViewFrustums};
+use crate::ecs::spatial::{update_spatial_index_system, SpatialIndex};
+use crate::ecs::particles::{apply_particle_edit, build_particle_emitters_system, collect_particle_emitters};
+use crate::ecs::labels::{build_labels_system, LabelList};
+use crate::ecs::text::{apply_text_edit, build_text_draws_system, collect_texts};
+use crate::ecs::modifiers::{apply_modifier_edit, collect_modifier_stacks};
+use crate::ecs::layers::{apply_layer_edit, collect_layer_entities, VisibleLayers};
+use crate::ecs::materials::{apply_material_edit, collect_materials};
+use crate::ecs::lights::{apply_light_edit, collect_lights};
+use crate::noise::NoiseSettings;
+use crate::environment::BackgroundMode;
 use crate::vulkan::renderer::VulkanRenderer;
+use crate::renderer::SharedRenderer;
+use crate::camera::{CameraBookmark, CameraTransition, LookCamera};
 use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::shader_variant::REFLECTIONS;
 use crate::vulkan::shader_watcher::{HotReloadManager, HotReloadConfig};
-use crate::error::{Result, AppError, EcsError};
+use crate::error::{Result, AppError, EcsError, ResultExt, Subsystem, VkResultExt};
+use crate::actions::{Action, ActionRegistry};
 use crate::hud::{HUD, HUDConfig, ToolbarPosition};
+use crate::hud::theme;
+use crate::hud::recovery::HudRecovery;
+use crate::hud::shader_reload_panel::ReloadFilterChange;
+use crate::hud::autosave_prompt::RecoveryChoice;
+use crate::hud::camera_bookmarks_panel::BookmarkRequest;
+use crate::settings::{AppSettings, AutosaveSettings, SettingsChanges};
+use crate::monitor::MonitorInfo;
+use crate::shader_params::ShaderParams;
+use crate::ecs::reload::{ReloadOutcome, ReloadSummary, ReloadTarget};
+use crate::ecs::scene_file::{LoadedScene, SceneFile};
+use crate::autosave::{self, Autosave};
+use crate::asset_watcher::{AssetChange, AssetKind, AssetWatcher, SubscriptionId};
+use crate::file_watcher::ChangeQueue;
+use crate::scripting::ScriptHost;
+use crate::config;
+use crate::jobs::JobPool;
+use crate::assets::{AssetManager, AssetState, Handle};
+use crate::hud::notifications::ToastLevel;
+use crate::vulkan::frame_export::{ExportJob, ExportSettings, FrameExporter};
+use crate::vulkan::fullscreen_pass::MouseState;
+use crate::vulkan::mesh_export::MeshExportJob;
+use crate::vulkan::still_export::StillExportSettings;
+use crate::vulkan::debug_draw::DebugDrawList;
+use crate::vulkan::mesh::MeshDrawList;
+use crate::vulkan::particles::ParticleEmitterList;
+use crate::vulkan::text::TextDrawList;
+use crate::vulkan::scene_buffer::GpuSceneData;
+use crate::vulkan::viewport::{self, ViewportLayout};
+use crate::vulkan::memory_usage;
+#[cfg(feature = "xr")]
+use crate::vulkan::xr::{XrInput, XrPose};
+use crate::image_io::{self, HdrFormat, HdrImage};
+use crate::frame_stats::{FrameStats, WindowStatus};
+use crate::project::{self, Project, RecentProjects};
+use crate::locale::{self, tr, tr_args};
+use cgmath::EuclideanSpace;
 use log::{info, error, debug, warn};
 use winit::window::Window;
 use ash::vk;
@@ -24,8 +88,99 @@ pub struct ECSWorld {
     /// HUD system for toolbar and UI
     pub hud: Option<HUD>,
     
+    /// Why the HUD could not be created, until a retry succeeds
+    hud_recovery: Option<HudRecovery>,
+    
     /// Hot reload manager for shader changes
     pub hot_reload_manager: Option<HotReloadManager>,
+    
+    /// Scene loaded from `config::scene::SCENE_FILE` (or the benchmark scene), None for the built-in scene
+    scene: Option<LoadedScene>,
+    
+    /// Watcher for assets reloaded by the world, created with hot reload
+    asset_watcher: Option<AssetWatcher>,
+    
+    /// Subscription to the scene file while hot reload is enabled
+    scene_subscription: Option<SubscriptionId>,
+    
+    /// Scene file changes waiting to be applied by `execute`
+    scene_changes: ChangeQueue<AssetKind>,
+    
+    /// Rhai scripts from `config::scripting::SCRIPTS_DIR`
+    scripts: ScriptHost,
+    
+    /// Subscription to the scripts folder while hot reload is enabled
+    script_subscription: Option<SubscriptionId>,
+    
+    /// Script file changes waiting to be applied by `execute`
+    script_changes: ChangeQueue<AssetKind>,
+    
+    /// Subscriptions to the toy manifest and shaders of the shadertoy mode while hot reload is enabled
+    toy_subscriptions: Vec<SubscriptionId>,
+    
+    /// Toy file changes waiting to be applied by `execute`
+    toy_changes: ChangeQueue<AssetKind>,
+    
+    /// Subscription to the toolbar layout file while hot reload is enabled
+    toolbar_subscription: Option<SubscriptionId>,
+    
+    /// Toolbar layout file changes waiting to be applied by `execute`
+    toolbar_changes: ChangeQueue<AssetKind>,
+    
+    /// Running offline frame export, advanced one frame per drawn frame
+    export_job: Option<ExportJob>,
+    
+    /// Set when an export ended, until taken with `take_export_finished`
+    export_finished: bool,
+    
+    /// Running SDF to mesh extraction on the job pool
+    mesh_export_job: Option<MeshExportJob>,
+    
+    /// Scene file read on the job pool, spawned in place of the scene once loaded
+    opening_scene: Option<Handle<SceneFile>>,
+    
+    /// Environment map of the background, held while it is chosen
+    background_map: Option<Handle<HdrImage>>,
+    
+    /// Mesh and texture files reloaded by the asset manager after they changed
+    asset_changes: Receiver<AssetChange>,
+    
+    /// Scene auto-save schedule, None until `start_autosave`
+    autosave: Option<Autosave>,
+    
+    /// Perspective camera turned by mouse look
+    look_camera: LookCamera,
+    
+    /// Camera bookmarks of the scene, kept in memory for the built-in scene
+    camera_bookmarks: Vec<CameraBookmark>,
+    
+    /// Running move of the camera to a bookmark
+    camera_transition: Option<CameraTransition>,
+    
+    /// Seconds a camera move to a bookmark or the selection takes, 0 to jump
+    camera_transition_secs: f32,
+    
+    /// Format of the linear HDR copy saved with each screenshot, None for PNG only
+    screenshot_hdr: Option<HdrFormat>,
+    
+    /// The SDF shader changed since its parameters were last reflected
+    shader_params_stale: bool,
+    
+    /// Frame rate shown in the window title
+    frame_stats: FrameStats,
+    
+    /// Window title without the status, carrying HUD failures
+    base_title: String,
+    
+    /// Title last given to the window
+    window_title: String,
+    
+    /// Whether the scene was edited in the HUD since it was loaded from its
+    /// file, which marks it unsaved
+    scene_edited: bool,
+    
+    /// Whether the cached assets were over their budget last frame, to warn once
+    over_residency_budget: bool,
 }
 
 impl ECSWorld {
@@ -33,121 +188,275 @@ impl ECSWorld {
     ///
     /// # Arguments
     /// * `vulkan_renderer` - The Vulkan renderer to use for rendering
+    /// * `scene_path` - Scene file to load, the built-in scene is used if it is missing
     ///
     /// # Returns
     /// * A new ECS world instance
     ///
     /// # Errors
     /// Returns an error if world initialization fails
-    pub fn new(vulkan_renderer: VulkanRenderer) -> Result<Self> {
-        info!("=== ECSWorld::new() STARTED ===");
+    #[tracing::instrument(name = "ecs_world_new", skip_all)]
+    pub fn new(vulkan_renderer: VulkanRenderer, scene_path: &Path) -> Result<Self> {
+        // Insert the Vulkan renderer as an Arc<Mutex> resource for shared mutable access;
+        // the systems reach the same renderer through the `SharedRenderer` resource
+        let vulkan_renderer_arc = Arc::new(Mutex::new(vulkan_renderer));
+        let mut ecs_world = Self::with_renderer(
+            Arc::clone(&vulkan_renderer_arc) as SharedRenderer,
+            scene_path,
+            Path::new(config::scripting::SCRIPTS_DIR),
+        )?;
+        debug!("Inserting Vulkan renderer as resource");
+        ecs_world.resources.insert(vulkan_renderer_arc);
+        Ok(ecs_world)
+    }
+
+    /// Create a new ECS world drawing with any renderer
+    ///
+    /// Without a `VulkanRenderer` the HUD, exports and shader hot reload are
+    /// unavailable; the systems and the frame loop run as usual, which lets
+    /// tests drive the world with a `NullRenderer`.
+    ///
+    /// # Arguments
+    /// * `renderer` - Renderer receiving each frame
+    /// * `scene_path` - Scene file to load, the built-in scene is used if it is missing
+    /// * `scripts_dir` - Folder of Rhai scripts
+    ///
+    /// # Errors
+    /// Returns an error if world initialization fails
+    pub fn with_renderer(renderer: SharedRenderer, scene_path: &Path, scripts_dir: &Path) -> Result<Self> {
         let mut world = World::default();
         let mut resources = Resources::default();
 
-        info!("Inserting Vulkan renderer as resource");
-        // Insert the Vulkan renderer as an Arc<Mutex> resource for shared mutable access
-        let vulkan_renderer_arc = Arc::new(Mutex::new(vulkan_renderer));
-        resources.insert(vulkan_renderer_arc);
+        debug!("Inserting renderer as resource");
+        resources.insert(renderer);
         
-        info!("Inserting SDF entity tracker vector");
+        debug!("Inserting SDF entity tracker vector");
         // Insert a vector to track SDF entities
         resources.insert(Vec::<legion::Entity>::new());
         
-        info!("Creating SDF entities");
-        // Create SDF entities once during initialization
-        create_sdf_entities(&mut world, &mut resources)
-            .map_err(|e| {
-                error!("Failed to create SDF entities: {}", e);
-                EcsError::EntityCreation(format!("Failed to create SDF entities: {}", e))
-            })?;
+        // Debug overlay settings, driven by the debug draw panel
+        resources.insert(DebugDrawSettings::default());
+        resources.insert(NoiseSettings::default());
+        resources.insert(VisibleLayers::default());
+        
+        // Bounds of the uploaded meshes, the grid over the entity bounds and
+        // the viewports entities are culled against
+        resources.insert(MeshBounds::default());
+        resources.insert(SpatialIndex::default());
+        resources.insert(ViewFrustums::default());
+        resources.insert(CullingStats::default());
+        
+        // Worker threads for background jobs; completions run in `update`
+        resources.insert(JobPool::new()?);
+        let mut assets = AssetManager::new();
+        let asset_changes = assets.subscribe();
+        resources.insert(assets);
+        
+        // Frame time for animation playback, updated by `execute`
+        resources.insert(FrameTime::default());
+        
+        // Rigid body simulation and its toggles from the physics panel
+        resources.insert(PhysicsWorld::new());
+        resources.insert(PhysicsSettings::default());
+        
+        // Per-frame outputs of the parallel systems, uploaded by the thread-local one
+        resources.insert(GpuSceneData::default());
+        resources.insert(DebugDrawList::new());
+        resources.insert(MeshDrawList::new());
+        resources.insert(TextDrawList::new());
+        resources.insert(ParticleEmitterList::default());
+        resources.insert(LabelList::default());
+        
+        debug!("Creating SDF entities");
+        // Load the scene file, or create the built-in scene if there is none
+        let scene = if scene_path.exists() {
+            match LoadedScene::load(scene_path, &mut world, &mut resources) {
+                Ok(scene) => Some(scene),
+                Err(e) => {
+                    warn!("{}; using the built-in scene", e);
+                    None
+                }
+            }
+        } else {
+            info!("Scene file {:?} not found; using the built-in scene", scene_path);
+            None
+        };
+        if scene.is_none() {
+            create_sdf_entities(&mut world, &mut resources)
+                .context(Subsystem::Ecs, "create SDF entities")?;
+        }
+        let camera_bookmarks = scene.as_ref().map(|scene| scene.bookmarks.clone()).unwrap_or_default();
+        
+        debug!("Loading scripts");
+        // Scripts that fail to compile are skipped; they load once fixed
+        let mut scripts = ScriptHost::new(scripts_dir);
+        for e in scripts.load_all(&mut world) {
+            warn!("{}", e);
+        }
         
-        info!("Creating ECS schedule");
-        // Create the schedule with systems that run every frame
+        debug!("Creating ECS schedule");
+        // Create the schedule with systems that run every frame. Legion runs
+        // the systems in parallel where their component and resource access
+        // allows; only the upload to the renderer is thread-local.
         let schedule = Schedule::builder()
-            .add_thread_local_fn(transform_update_system)
-            .add_thread_local_fn(sdf_render_system)
+            .add_system(transform_update_system())
+            .add_system(animation_playback_system())
+            .add_system(physics_step_system())
+            .add_system(hierarchy_propagation_system())
+            .add_system(update_bounds_system())
+            .add_system(update_spatial_index_system())
+            .add_system(build_scene_data_system())
+            .add_system(build_debug_lines_system())
+            .add_system(build_mesh_draws_system())
+            .add_system(build_text_draws_system())
+            .add_system(build_particle_emitters_system())
+            .add_system(build_labels_system())
+            .add_thread_local_fn(upload_frame_data_system)
             .build();
         
         info!("ECS world created successfully");
-        info!("=== ECSWorld::new() COMPLETED ===");
         
         Ok(Self {
             world,
             resources,
             schedule,
             hud: None,
+            hud_recovery: None,
             hot_reload_manager: None,
+            scene,
+            asset_watcher: None,
+            scene_subscription: None,
+            scene_changes: ChangeQueue::default(),
+            scripts,
+            script_subscription: None,
+            script_changes: ChangeQueue::default(),
+            toy_subscriptions: Vec::new(),
+            toy_changes: ChangeQueue::default(),
+            toolbar_subscription: None,
+            toolbar_changes: ChangeQueue::default(),
+            export_job: None,
+            export_finished: false,
+            mesh_export_job: None,
+            opening_scene: None,
+            background_map: None,
+            asset_changes,
+            autosave: None,
+            look_camera: LookCamera::default(),
+            camera_bookmarks,
+            camera_transition: None,
+            camera_transition_secs: config::camera::CAMERA_TRANSITION_SECS,
+            screenshot_hdr: None,
+            shader_params_stale: true,
+            frame_stats: FrameStats::new(),
+            base_title: config::window::TITLE.to_string(),
+            window_title: String::new(),
+            scene_edited: false,
+            over_residency_budget: false,
         })
     }
     
     /// Initialize HUD system with the given window
     ///
+    /// A failure is kept in `hud_recovery` and the call can be repeated to
+    /// retry once its cause is fixed.
+    ///
     /// # Arguments
     /// * `window` - The window to associate with the HUD
+    /// * `actions` - Actions listed in the command palette
+    /// * `settings` - Runtime settings shown in the Settings window
     ///
     /// # Returns
     /// * Ok(()) if HUD initialization succeeded
     /// * Err if HUD initialization failed
+    #[tracing::instrument(skip_all)]
     pub fn init_hud(
         &mut self,
         window: &Window,
+        actions: Arc<ActionRegistry>,
+        settings: &AppSettings,
+    ) -> Result<()> {
+        let result = self.create_hud(window, actions, settings);
+        match (&result, self.hud_recovery.as_mut()) {
+            (Ok(()), _) => {
+                // The new Shader Parameters panel needs the parameters
+                self.shader_params_stale = true;
+                if let Some(recovery) = self.hud_recovery.take() {
+                    info!("HUD recovered after {} failed attempt(s)", recovery.attempts);
+                }
+            }
+            (Err(e), Some(recovery)) => recovery.record_failure(e),
+            (Err(e), None) => self.hud_recovery = Some(HudRecovery::new(e)),
+        }
+        result
+    }
+    
+    /// Why the HUD could not be created, None while it runs or before `init_hud`
+    pub fn hud_recovery(&self) -> Option<&HudRecovery> {
+        self.hud_recovery.as_ref()
+    }
+    
+    /// Create the HUD and store it in the world, see `init_hud`
+    fn create_hud(
+        &mut self,
+        window: &Window,
+        actions: Arc<ActionRegistry>,
+        settings: &AppSettings,
     ) -> Result<()> {
-        info!("=== HUD INITIALIZATION STARTED ===");
-
-        info!("Getting VulkanRenderer from resources");
         let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
             .ok_or_else(|| {
                 error!("VulkanRenderer not found in ECS resources");
                 EcsError::ResourceAccess("VulkanRenderer not found for HUD initialization".to_string())
             })?;
 
-        info!("Creating HUD config");
-        let config = HUDConfig::default();
+        let config = HUDConfig {
+            default_toolbar_position: settings.toolbar_position,
+            theme: settings.theme.clone(),
+            ..HUDConfig::default()
+        };
 
         // Lock the renderer to access its data
         let renderer_guard = vulkan_renderer.lock().unwrap();
         let pipeline_guard = renderer_guard.pipeline.lock().unwrap();
-        let render_pass = pipeline_guard.render_pass;
+        let pass = pipeline_guard.pass;
         let device = &renderer_guard.device;
         drop(pipeline_guard); // Release pipeline lock
 
-        info!("Creating HUD instance with window, device, and render pass");
         let mut hud = HUD::new(
             window,
             device,
             &*renderer_guard,
-            render_pass,
+            pass,
             config,
-        ).map_err(|e| {
-            error!("Failed to create HUD instance: {}", e);
-            AppError::HUD(format!("Failed to initialize HUD: {}", e))
-        })?;
+            actions,
+        ).context(Subsystem::Hud, "initialize HUD")?;
         
-        info!("HUD instance created, initializing font texture");
         hud.init_font_texture()
-            .map_err(|e| {
-                error!("Failed to initialize HUD font texture: {}", e);
-                AppError::HUD(format!("Failed to initialize HUD font texture: {}", e))
-            })?;
+            .context(Subsystem::Hud, "initialize HUD font texture")?;
+        hud.settings_panel.set_app_settings(settings.clone());
         
-        info!("Font texture initialized, storing HUD in ECS world");
         // Store HUD in the world
         self.hud = Some(hud);
+        if self.is_hot_reload_enabled() {
+            if let Err(e) = self.watch_toolbar_layout() {
+                warn!("Toolbar layout hot reload unavailable: {}", e);
+            }
+        }
         
         info!("HUD system initialized successfully with font texture");
         debug!("HUD stored in ECS world at: {:p}", self.hud.as_ref().unwrap());
-        info!("=== HUD INITIALIZATION COMPLETED ===");
         Ok(())
     }
     
     /// Initialize hot reload manager
     ///
+    /// # Arguments
+    /// * `settings` - Runtime settings with the shader directory and hot reload options
+    ///
     /// # Returns
     /// * Ok(()) if hot reload initialization succeeded
     /// * Err if hot reload initialization failed
-    pub fn init_hot_reload(&mut self) -> Result<()> {
-        info!("=== HOT RELOAD INITIALIZATION STARTED ===");
-
+    #[tracing::instrument(skip_all)]
+    pub fn init_hot_reload(&mut self, settings: &AppSettings) -> Result<()> {
         // Get Vulkan renderer to access pipeline
         let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
             .ok_or_else(|| {
@@ -159,7 +468,7 @@ impl ECSWorld {
         let shader_compiler = Arc::new(Mutex::new(ShaderCompiler::new()?));
 
         // Create hot reload config
-        let config = HotReloadConfig::default();
+        let config = HotReloadConfig::from_settings(settings);
 
         // Create hot reload manager
         let mut hot_reload_manager = HotReloadManager::new(config, Arc::clone(&shader_compiler));
@@ -168,183 +477,1734 @@ impl ECSWorld {
         let renderer_guard = vulkan_renderer.lock().unwrap();
         let pipeline_arc = Arc::clone(&renderer_guard.pipeline);
         drop(renderer_guard); // Release lock before setting callback
+        pipeline_arc.lock().unwrap().safe_mode = settings.hot_reload.safe_mode;
 
         hot_reload_manager.initialize(pipeline_arc)?;
+        let enabled = hot_reload_manager.is_enabled();
 
         // Store hot reload manager
         self.hot_reload_manager = Some(hot_reload_manager);
 
+        if enabled {
+            if let Err(e) = self.watch_scene_file() {
+                warn!("Scene hot reload unavailable: {}", e);
+            }
+            if let Err(e) = self.watch_scripts() {
+                warn!("Script hot reload unavailable: {}", e);
+            }
+            if let Err(e) = self.watch_toolbar_layout() {
+                warn!("Toolbar layout hot reload unavailable: {}", e);
+            }
+        }
+
         info!("Hot reload manager initialized successfully with pipeline integration and immediate command buffer updates");
-        info!("=== HOT RELOAD INITIALIZATION COMPLETED ===");
         Ok(())
     }
     
-    /// Execute all systems in the schedule
+    /// Watch the loaded scene file for changes
+    ///
+    /// Does nothing for the built-in scene, or a restored auto-save of it
+    /// whose scene file does not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the scene file cannot be watched
+    fn watch_scene_file(&mut self) -> Result<()> {
+        let Some(path) = self.scene.as_ref().map(|scene| scene.path.clone()).filter(|path| path.exists()) else {
+            return Ok(());
+        };
+        let handler = self.scene_changes.handler();
+        let subscription = self.asset_watcher()?.subscribe(AssetKind::Scene, &path, handler)?;
+        self.scene_subscription = Some(subscription);
+        Ok(())
+    }
+    
+    /// Watch the scripts folder for added, changed and removed scripts
+    ///
+    /// Does nothing if the folder does not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the folder cannot be watched
+    fn watch_scripts(&mut self) -> Result<()> {
+        let directory = self.scripts.directory().to_path_buf();
+        if !directory.is_dir() {
+            return Ok(());
+        }
+        let handler = self.script_changes.handler();
+        let subscription = self.asset_watcher()?.subscribe(AssetKind::Script, &directory, handler)?;
+        self.script_subscription = Some(subscription);
+        Ok(())
+    }
+    
+    /// Watch the toolbar layout file for changes
+    ///
+    /// Does nothing without a HUD or a layout file, or if it is already watched.
+    ///
+    /// # Errors
+    /// Returns an error if the layout file cannot be watched
+    fn watch_toolbar_layout(&mut self) -> Result<()> {
+        let path = Path::new(config::hud_layout::TOOLBAR_FILE);
+        if self.hud.is_none() || self.toolbar_subscription.is_some() || !path.exists() {
+            return Ok(());
+        }
+        let handler = self.toolbar_changes.handler();
+        let subscription = self.asset_watcher()?.subscribe(AssetKind::Config, path, handler)?;
+        self.toolbar_subscription = Some(subscription);
+        Ok(())
+    }
+    
+    /// The asset watcher, created on first use
+    ///
+    /// # Errors
+    /// Returns an error if the platform watcher cannot be created
+    fn asset_watcher(&mut self) -> Result<&mut AssetWatcher> {
+        match self.asset_watcher {
+            Some(ref mut watcher) => Ok(watcher),
+            None => Ok(self.asset_watcher.insert(AssetWatcher::with_default_debounce()?)),
+        }
+    }
+
+    /// Execute all systems in the schedule
+    ///
+    /// # Arguments
+    /// * `window` - Current window for HUD input handling
+    /// * `delta_time` - Time since last frame
+    ///
+    /// # Returns
+    /// * Ok(()) if all systems executed successfully
+    /// * Err if any system failed to execute
+    pub fn execute(&mut self, window: &Window, delta_time: f32) -> Result<()> {
+        // Update HUD input first so its panels see this frame's clicks
+        if let Some(ref mut hud) = self.hud {
+            hud.update(window, delta_time);
+        }
+        if config::window::SHOW_STATUS && self.frame_stats.tick(Instant::now()).is_some() {
+            self.update_window_title(window);
+        }
+        self.update(delta_time)
+    }
+    
+    /// Title text the status is added to, set when the HUD is (re)initialized
+    pub fn set_base_title(&mut self, title: String) {
+        self.base_title = title;
+    }
+    
+    /// Put the frame rate, GPU and scene into the window title
+    fn update_window_title(&mut self, window: &Window) {
+        let gpu = self.resources.get::<Arc<Mutex<VulkanRenderer>>>().and_then(|renderer| {
+            let renderer = renderer.lock().unwrap();
+            renderer.device.gpus.iter().find(|gpu| gpu.selected).map(|gpu| gpu.name.clone())
+        });
+        // The built-in scene has no file to be unsaved against
+        let scene_file = self.scene.as_ref().map(|scene| scene.path.clone()).filter(|path| path.exists());
+        let unsaved = scene_file.is_some() && self.scene_edited;
+        let status = WindowStatus {
+            fps: self.frame_stats.fps(),
+            gpu,
+            scene: scene_file.map(|path| path.file_name().unwrap_or_default().to_string_lossy().into_owned()),
+            unsaved,
+        };
+        let title = status.title(&self.base_title);
+        if title != self.window_title {
+            window.set_title(&title);
+            self.window_title = title;
+        }
+    }
+
+    /// Advance the world by one frame without a window
+    ///
+    /// Applies queued reloads and HUD edits, runs the scripts and executes
+    /// the system schedule.
+    ///
+    /// # Arguments
+    /// * `delta_time` - Time since last frame
+    ///
+    /// # Errors
+    /// Returns an error if a system failed to execute
+    pub fn update(&mut self, delta_time: f32) -> Result<()> {
+        // Get hot reload state before borrowing HUD
+        let hot_reload_enabled = self.is_hot_reload_enabled();
+
+        // Hand the results of ended background jobs to their callbacks
+        let completions = self.resources.get::<JobPool>().map(|jobs| jobs.take_completions()).unwrap_or_default();
+        for completion in completions {
+            completion(self);
+        }
+        self.update_assets();
+
+        // Re-load the scene after its file changed on disk
+        if !self.scene_changes.drain().is_empty() {
+            if let Some(ref mut scene) = self.scene {
+                match scene.reload(&mut self.world, &mut self.resources) {
+                    Ok(respawned) => {
+                        self.camera_bookmarks = scene.bookmarks.clone();
+                        self.scene_edited = false;
+                        if let (true, Some(hud)) = (respawned, self.hud.as_mut()) {
+                            hud.notifications.push(ToastLevel::Info, tr_args("toasts.reloaded_scene", &[("path", &scene.path.display())]));
+                        }
+                    }
+                    // The previous scene stays loaded until the file is fixed
+                    Err(e) => Self::report_to(self.hud.as_mut(), &e),
+                }
+            }
+        }
+
+        // Rebuild the toolbar after its layout file changed on disk
+        if !self.toolbar_changes.drain().is_empty() {
+            if let Some(ref mut hud) = self.hud {
+                match hud.reload_toolbar_layout() {
+                    Ok(()) => hud.notifications.push(ToastLevel::Info, tr_args("toasts.reloaded_toolbar", &[("path", &config::hud_layout::TOOLBAR_FILE)])),
+                    // The previous layout stays up until the file is fixed
+                    Err(e) => Self::report_to(Some(hud), &e),
+                }
+            }
+        }
+
+        self.apply_recovery_choice();
+        self.update_camera_bookmarks(delta_time);
+
+        // Advance the toy and compile it again after one of its files changed;
+        // a changed manifest restarts the toy, since its buffers may have changed
+        let mut toy_active = false;
+        let mut toy_restarted = false;
+        if let Some(renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>() {
+            let mut renderer = renderer.lock().unwrap();
+            renderer.advance_toy(delta_time);
+            toy_active = renderer.toy_path().is_some();
+            let changes = self.toy_changes.drain();
+            if let (false, Some(path)) = (changes.is_empty(), renderer.toy_path().map(Path::to_path_buf)) {
+                let manifest_changed = changes.iter().any(|change| change.kind == AssetKind::Config);
+                let result = if manifest_changed {
+                    renderer.start_toy(&path).context(Subsystem::Shaders, "restart the toy")
+                } else {
+                    renderer.reload_toy().context(Subsystem::Shaders, "reload the toy shaders")
+                };
+                match result {
+                    Ok(()) => {
+                        toy_restarted = manifest_changed;
+                        if let Some(hud) = self.hud.as_mut() {
+                            hud.notifications.push(ToastLevel::Info, tr_args("toasts.reloaded_toy", &[("path", &path.display())]));
+                        }
+                    }
+                    // The previous version keeps running until the files are fixed
+                    Err(e) => Self::report_to(self.hud.as_mut(), &e),
+                }
+            }
+        }
+        if toy_restarted {
+            self.watch_toy();
+        }
+
+        if let Some(ref mut hud) = self.hud {
+            // Update hot reload button state to match current hot reload status
+            hud.toolbar.update_hot_reload_button_state(hot_reload_enabled);
+            hud.settings_panel.sync_hot_reload_enabled(hot_reload_enabled);
+            hud.settings_panel.sync_toolbar_position(hud.toolbar.position);
+            
+            // Apply shader reload filters and refresh the per-file reload state
+            if let Some(ref hot_reload) = self.hot_reload_manager {
+                for change in hud.shader_reload_panel.take_pending_changes() {
+                    match change {
+                        ReloadFilterChange::Kind(kind, enabled) => hot_reload.set_kind_enabled(kind, enabled),
+                        ReloadFilterChange::File(path, enabled) => hot_reload.set_file_enabled(&path, enabled),
+                    }
+                }
+                if hud.shader_reload_panel.is_visible {
+                    hud.shader_reload_panel.files = hot_reload.file_stats();
+                    hud.shader_reload_panel.kinds = hot_reload.kind_filters();
+                }
+            }
+            
+            // Apply CSG edits from the tree editor and refresh its snapshot
+            for edit in hud.csg_panel.take_pending_edits() {
+                self.scene_edited = true;
+                if let Err(e) = apply_csg_edit(&mut self.world, &edit) {
+                    warn!("Failed to apply CSG edit {:?}: {}", edit, e);
+                }
+            }
+            hud.csg_panel.trees = collect_csg_trees(&self.world);
+            
+            // Re-parent entities dropped onto each other in the hierarchy panel
+            for edit in hud.hierarchy_panel.take_pending_edits() {
+                self.scene_edited = true;
+                if let Err(e) = set_parent(&mut self.world, edit.child, edit.parent) {
+                    warn!("Failed to re-parent {:?}: {}", edit.child, e);
+                }
+            }
+            hud.hierarchy_panel.nodes = collect_hierarchy(&self.world);
+            set_selection(&mut self.world, hud.hierarchy_panel.selected());
+            
+            // Apply transport commands to the animation players
+            for command in hud.animation_panel.take_pending_commands() {
+                apply_animation_command(&mut self.world, command);
+            }
+            hud.animation_panel.status = collect_animation_status(&self.world);
+            
+            // Apply the physics toggles and throw a sphere from the camera on request
+            if let Some(mut settings) = self.resources.get_mut::<PhysicsSettings>() {
+                *settings = hud.physics_panel.settings;
+            }
+            if hud.physics_panel.take_throw_request() {
+                if let Some(renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>() {
+                    let renderer = renderer.lock().unwrap();
+                    let origin = renderer.camera.position.to_vec();
+                    throw_sphere(&mut self.world, origin, renderer.camera.forward());
+                }
+            }
+            hud.physics_panel.body_count = self.resources.get::<PhysicsWorld>().map_or(0, |physics| physics.body_count());
+            
+            // Apply emitter edits from the particles panel and refresh its snapshot
+            for edit in hud.particles_panel.take_pending_edits() {
+                self.scene_edited = true;
+                apply_particle_edit(&mut self.world, edit);
+            }
+            hud.particles_panel.emitters = collect_particle_emitters(&self.world);
+            
+            // Apply text edits from the text panel and refresh its snapshot
+            for edit in hud.text_panel.take_pending_edits() {
+                self.scene_edited = true;
+                apply_text_edit(&mut self.world, edit);
+            }
+            hud.text_panel.texts = collect_texts(&self.world);
+            
+            // Apply stack edits from the modifiers panel and refresh its snapshot
+            for edit in hud.modifiers_panel.take_pending_edits() {
+                self.scene_edited = true;
+                apply_modifier_edit(&mut self.world, edit);
+            }
+            hud.modifiers_panel.stacks = collect_modifier_stacks(&self.world);
+            if let Some(mut noise) = self.resources.get_mut::<NoiseSettings>() {
+                *noise = hud.modifiers_panel.noise;
+            }
+            
+            // Apply layer edits from the layers panel; the scene data keeps
+            // only what the drawn viewports show
+            for edit in hud.layers_panel.take_pending_edits() {
+                self.scene_edited = true;
+                apply_layer_edit(&mut self.world, edit);
+            }
+            hud.layers_panel.entities = collect_layer_entities(&self.world);
+            let visible_layers = hud.viewport_panel.settings.visible_layers();
+            hud.layers_panel.visible_layers = visible_layers;
+            if let Some(mut layers) = self.resources.get_mut::<VisibleLayers>() {
+                layers.0 = visible_layers;
+            }
+            
+            // Apply material edits from the materials panel and refresh its snapshot
+            for edit in hud.materials_panel.take_pending_edits() {
+                self.scene_edited = true;
+                apply_material_edit(&mut self.world, edit);
+            }
+            hud.materials_panel.materials = collect_materials(&self.world);
+            hud.materials_panel.reflections_enabled = hud.render_settings_panel.variant.has_define(REFLECTIONS);
+            
+            // Apply light edits from the lights panel and refresh its snapshot
+            for edit in hud.lights_panel.take_pending_edits() {
+                self.scene_edited = true;
+                apply_light_edit(&mut self.world, edit);
+            }
+            hud.lights_panel.lights = collect_lights(&self.world);
+            
+            // Entities kept by frustum culling, shown in the stats panel
+            if let Some(culling) = self.resources.get::<CullingStats>() {
+                hud.stats_panel.culling = *culling;
+            }
+            
+            // Queued and running background jobs, shown in the corner
+            if let Some(jobs) = self.resources.get::<JobPool>() {
+                hud.jobs_indicator.jobs = jobs.active();
+            }
+            
+            // Apply per-entity debug overlay changes and the global override
+            for (entity, mode) in hud.debug_draw_panel.take_pending_modes() {
+                if let Err(e) = set_debug_display(&mut self.world, entity, mode) {
+                    warn!("Failed to set debug display of {:?}: {}", entity, e);
+                }
+            }
+            hud.debug_draw_panel.entities = collect_debug_draw_entities(&self.world);
+            if let Some(mut settings) = self.resources.get_mut::<DebugDrawSettings>() {
+                settings.global_override = hud.debug_draw_panel.global_override;
+            }
+        }
+        
+        // Re-load scripts whose files were added, changed or removed
+        for change in self.script_changes.drain() {
+            match self.scripts.reload(&mut self.world, &change.path) {
+                Ok(()) => {
+                    if let Some(ref mut hud) = self.hud {
+                        let key = if change.path.exists() { "toasts.reloaded_script" } else { "toasts.unloaded_script" };
+                        let name = change.path.file_name().unwrap_or_default().to_string_lossy();
+                        hud.notifications.push(ToastLevel::Info, tr_args(key, &[("name", &name)]));
+                    }
+                }
+                // The previous version keeps running until the script is fixed
+                Err(e) => Self::report_to(self.hud.as_mut(), &e),
+            }
+        }
+        
+        // Call the script functions bound to pressed buttons, then every
+        // script's per-frame callback, before the systems read the world
+        let calls = self.hud.as_mut().map(|hud| hud.scripts_panel.take_pending_calls()).unwrap_or_default();
+        let mut script_errors = Vec::new();
+        for call in calls {
+            if let Err(e) = self.scripts.press(&mut self.world, &call.script, &call.function) {
+                script_errors.push(e);
+            }
+        }
+        script_errors.extend(self.scripts.update(&mut self.world, delta_time));
+        for e in &script_errors {
+            Self::report_to(self.hud.as_mut(), e);
+        }
+        if let Some(ref mut hud) = self.hud {
+            hud.scripts_panel.scripts = self.scripts.views();
+        }
+        
+        if let Some(mut frame_time) = self.resources.get_mut::<FrameTime>() {
+            frame_time.delta_seconds = delta_time;
+        }
+        self.update_view_frustums();
+        self.schedule.execute(&mut self.world, &mut self.resources);
+        if let (Some(hud), Some(mut labels)) = (self.hud.as_mut(), self.resources.get_mut::<LabelList>()) {
+            let labels = std::mem::take(&mut labels.0);
+            // Scene labels have nothing to point at while the toy shader is drawn
+            hud.label_overlay.labels = if toy_active { Vec::new() } else { labels };
+        }
+        self.update_mesh_export();
+        self.update_autosave();
+        Ok(())
+    }
+    
+    /// Start auto-saving the scene
+    ///
+    /// # Arguments
+    /// * `file` - Auto-save file of the session
+    /// * `settings` - Auto-save options
+    /// * `recovered` - Scene recovered from a session that did not shut down cleanly, offered in the HUD
+    pub fn start_autosave(&mut self, file: PathBuf, settings: AutosaveSettings, recovered: Option<&Path>) {
+        self.autosave = Some(Autosave::new(file, settings, Instant::now()));
+        if let Some(path) = recovered {
+            match self.hud.as_mut() {
+                Some(hud) => hud.autosave_prompt.offer(path),
+                None => warn!("Scene recovered from the previous session kept in {}", path.display()),
+            }
+        }
+    }
+    
+    /// Describe the scene as it is now, leaving out what scripts spawned
+    pub fn capture_scene(&self) -> SceneFile {
+        let names = self.scene.as_ref().map_or(&[][..], LoadedScene::entities);
+        SceneFile {
+            bookmarks: self.camera_bookmarks.clone(),
+            ..SceneFile::capture(&self.world, names, &self.scripts.spawned())
+        }
+    }
+    
+    /// Write the scene to the auto-save file when a save is due
+    fn update_autosave(&mut self) {
+        let now = Instant::now();
+        if !self.autosave.as_ref().is_some_and(|autosave| autosave.is_due(now)) {
+            return;
+        }
+        let text = self.capture_scene().to_toml();
+        let Some(autosave) = self.autosave.as_mut() else {
+            return;
+        };
+        // A failed save is tried again after the next interval
+        if let Err(e) = text.and_then(|text| autosave.save(&text, now)) {
+            warn!("{}", e);
+        }
+    }
+    
+    /// Restore or discard the recovered scene as answered in the HUD
+    fn apply_recovery_choice(&mut self) {
+        let Some(choice) = self.hud.as_mut().and_then(|hud| hud.autosave_prompt.take_pending_choice()) else {
+            return;
+        };
+        let result = match choice {
+            RecoveryChoice::Restore(path) => self.restore_scene(&path)
+                .and_then(|()| autosave::discard(&path))
+                .map(|()| "toasts.restored_autosave"),
+            RecoveryChoice::Discard(path) => autosave::discard(&path).map(|()| "toasts.discarded_autosave"),
+        };
+        match result {
+            Ok(key) => {
+                if let Some(ref mut hud) = self.hud {
+                    hud.notifications.push(ToastLevel::Info, tr(key));
+                }
+            }
+            Err(e) => Self::report_to(self.hud.as_mut(), &e),
+        }
+    }
+    
+    /// Take the assets loaded since the last update and report the files that failed
+    ///
+    /// New mesh renderers get their mesh handle, loaded files are watched and
+    /// changed ones read again; the GPU copy of a reloaded file is replaced.
+    /// A scene opened with `open_scene` replaces the current one once it is loaded.
+    fn update_assets(&mut self) {
+        // The watcher is only created once there is a file to watch
+        if self.resources.get::<AssetManager>().is_some_and(|assets| assets.has_unwatched()) {
+            if let Err(e) = self.asset_watcher().map(|_| ()) {
+                warn!("Asset hot reload unavailable: {}", e);
+            }
+        }
+        let errors = match (self.resources.get_mut::<AssetManager>(), self.resources.get::<JobPool>()) {
+            (Some(mut assets), Some(jobs)) => {
+                attach_mesh_assets(&mut self.world, &mut assets, &jobs);
+                let errors = assets.poll();
+                assets.watch(&jobs, self.asset_watcher.as_mut());
+                errors
+            }
+            _ => Vec::new(),
+        };
+        for e in &errors {
+            Self::report_to(self.hud.as_mut(), e);
+        }
+        
+        // Components resolve to the reloaded version through their handles,
+        // the renderer uploads it again
+        for change in self.asset_changes.try_iter() {
+            if let Some(renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>() {
+                let mut renderer = renderer.lock().unwrap();
+                match change.kind {
+                    AssetKind::Mesh => renderer.reload_mesh(&change.path.to_string_lossy()),
+                    AssetKind::Texture => renderer.reload_environment_map(&change.path),
+                    _ => {}
+                }
+            }
+            if let Some(hud) = self.hud.as_mut() {
+                let name = change.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                hud.notifications.push(ToastLevel::Info, tr_args("toasts.reloaded_asset", &[("name", &name)]));
+            }
+        }
+
+        let Some(handle) = self.opening_scene.take() else {
+            return;
+        };
+        if self.resources.get::<AssetManager>().is_some_and(|assets| assets.scenes.state(handle.path()) == Some(AssetState::Loading)) {
+            self.opening_scene = Some(handle);
+            return;
+        }
+        
+        // A file that failed to load was reported above; dropping the handle
+        // releases the file, so opening it again reads it again
+        if let Some(scene) = handle.get() {
+            let path = handle.path();
+            match self.replace_scene_with(path, (*scene).clone(), path) {
+                Ok(()) => {
+                    if let Some(hud) = self.hud.as_mut() {
+                        hud.notifications.push(ToastLevel::Info, tr_args("toasts.opened_scene", &[("path", &path.display())]));
+                    }
+                }
+                Err(e) => Self::report_to(self.hud.as_mut(), &e),
+            }
+        }
+    }
+    
+    /// Start, cancel and collect mesh exports requested in the HUD
+    ///
+    /// A new export extracts the scene as collected by this frame's systems.
+    fn update_mesh_export(&mut self) {
+        let Some(hud) = self.hud.as_mut() else {
+            return;
+        };
+        if hud.mesh_export_panel.take_pending_cancel() {
+            if let Some(job) = self.mesh_export_job.as_ref() {
+                job.cancel();
+            }
+        }
+        if let Some(settings) = hud.mesh_export_panel.take_pending_start() {
+            let scene = self.resources.get::<GpuSceneData>().map(|scene| *scene).unwrap_or_default();
+            let started = match self.resources.get::<JobPool>() {
+                Some(jobs) => MeshExportJob::start(&jobs, scene, settings),
+                None => Err(AppError::Generic("No job pool".to_string())),
+            };
+            match started.context(Subsystem::Io, "start mesh export") {
+                Ok(job) => self.mesh_export_job = Some(job),
+                Err(e) => Self::report_to(Some(&mut *hud), &e),
+            }
+        }
+
+        if let Some(job) = self.mesh_export_job.take_if(|job| job.is_finished()) {
+            match job.finish().context(Subsystem::Io, "export mesh") {
+                Ok(Some(summary)) => {
+                    info!("Mesh exported: {} ({} triangles)", summary.path.display(), summary.triangle_count);
+                    let message = tr_args("toasts.mesh_exported", &[("path", &summary.path.display()), ("triangles", &summary.triangle_count)]);
+                    hud.notifications.push(ToastLevel::Info, message);
+                }
+                Ok(None) => info!("Mesh export cancelled"),
+                Err(e) => Self::report_to(Some(&mut *hud), &e),
+            }
+        }
+        hud.mesh_export_panel.progress = self.mesh_export_job.as_ref().map(MeshExportJob::progress);
+    }
+    
+    /// Draw the headset's frame and publish the controllers as the `XrInput` resource
+    ///
+    /// # Returns
+    /// Pose of the left eye for the window to mirror, None without a headset frame
+    #[cfg(feature = "xr")]
+    fn draw_headset_frame(&mut self) -> Result<Option<XrPose>> {
+        let Some(renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>().map(|renderer| Arc::clone(&renderer)) else {
+            return Ok(None);
+        };
+        let input = renderer.lock().unwrap().draw_xr_frame(&self.look_camera)
+            .context(Subsystem::Renderer, "draw headset frame")?;
+        let Some(input) = input else {
+            return Ok(None);
+        };
+        self.resources.insert(input);
+        Ok(input.head)
+    }
+    
+    /// Whether the headset's runtime ended the session, e.g. from its system menu
+    #[cfg(feature = "xr")]
+    pub fn headset_exiting(&self) -> bool {
+        self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .is_some_and(|renderer| renderer.lock().unwrap().xr_exiting())
+    }
+    
+    /// Draw a single frame
+    ///
+    /// # Returns
+    /// * Ok(()) if the frame was drawn successfully
+    /// * Err if drawing failed
+    #[tracing::instrument(name = "frame", skip_all)]
+    pub fn draw_frame(&mut self) -> Result<()> {
+        // Check if we need to update command buffers due to hot reload from previous frame
+        // This MUST be done at the very beginning of the frame, before any rendering
+        let _needs_command_buffer_update = if let Some(ref hot_reload_manager) = self.hot_reload_manager {
+            let should_update = hot_reload_manager.check_and_clear_reloads_occurred();
+            debug!("Command buffer update needed: {}", should_update);
+            should_update
+        } else {
+            false
+        };
+
+        // Process any pending shader reloads first and check if pipeline was recreated
+        let pipeline_was_recreated = if let Some(ref mut hot_reload_manager) = self.hot_reload_manager {
+            match hot_reload_manager.process_pending_reloads().context(Subsystem::Shaders, "process pending shader reloads") {
+                Ok(was_recreated) => was_recreated,
+                Err(e) => {
+                    Self::report_to(self.hud.as_mut(), &e);
+                    false // Continue with frame rendering even if reload fails
+                }
+            }
+        } else {
+            false
+        };
+
+        // Safe-mode rejections are shown in the HUD until a reload succeeds
+        if let (Some(hot_reload_manager), Some(hud)) = (self.hot_reload_manager.as_ref(), self.hud.as_mut()) {
+            hud.shader_reload_error = hot_reload_manager.last_error();
+        }
+
+        // Without a Vulkan device there is nothing to record; the renderer just takes the frame
+        if !self.resources.contains::<Arc<Mutex<VulkanRenderer>>>() {
+            let renderer = self.resources.get::<SharedRenderer>()
+                .ok_or_else(|| EcsError::ResourceAccess("Renderer resource not found in ECS world".to_string()))?;
+            let mut renderer = renderer.lock().unwrap();
+            return renderer.draw_frame();
+        }
+
+        // The headset's frame comes first, so the window mirrors its left eye
+        let perspective = self.look_camera.view_camera();
+        #[cfg(feature = "xr")]
+        let head = self.draw_headset_frame()?;
+        #[cfg(feature = "xr")]
+        let perspective = head.as_ref().map_or(perspective, |head| head.view_camera());
+
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+
+        // IMMEDIATE command buffer update if pipeline was recreated
+        if pipeline_was_recreated {
+            info!("Pipeline was recreated during hot reload, updating command buffers immediately");
+            let mut renderer_guard = vulkan_renderer.lock().unwrap();
+            if let Err(e) = renderer_guard.update_command_buffers_after_hot_reload()
+                .context(Subsystem::Renderer, "update command buffers after hot reload")
+            {
+                Self::report_to(self.hud.as_mut(), &e);
+                // Continue with rendering even if command buffer update fails
+            } else {
+                info!("Command buffer update completed successfully after hot reload");
+            }
+        }
+
+        // Check if HUD is available and log its state
+        let mut renderer_guard = vulkan_renderer.lock().unwrap();
+        
+        // Apply render settings that require pipeline recreation before recording the frame
+        if let Some(quality) = self.hud.as_mut().and_then(|hud| hud.render_settings_panel.take_pending_quality()) {
+            if let Err(e) = renderer_guard.set_raymarch_quality(quality)
+                .context(Subsystem::Renderer, format!("switch raymarch quality to {}", quality.name()))
+            {
+                Self::report_to(self.hud.as_mut(), &e);
+            }
+        }
+        if let Some(variant) = self.hud.as_mut().and_then(|hud| hud.render_settings_panel.take_pending_variant()) {
+            let operation = format!("switch shader variant to {}", variant.name());
+            match renderer_guard.set_shader_variant(variant).context(Subsystem::Shaders, operation) {
+                Ok(()) => self.shader_params_stale = true,
+                Err(e) => Self::report_to(self.hud.as_mut(), &e),
+            }
+        }
+        if let Some(settings) = self.hud.as_mut().and_then(|hud| hud.render_settings_panel.take_pending_compile_settings()) {
+            renderer_guard.set_shader_compile_settings(settings);
+        }
+        if let Some(split_compare) = self.hud.as_mut().and_then(|hud| hud.viewport_panel.take_pending_split_compare()) {
+            let operation = format!("build split comparison pipeline ({})", split_compare.source.name());
+            if let Err(e) = renderer_guard.set_split_compare(split_compare).context(Subsystem::Shaders, operation) {
+                Self::report_to(self.hud.as_mut(), &e);
+            }
+        }
+        
+        // Shader parameters are reflected again whenever the shader changed
+        let revert = self.hud.as_mut().is_some_and(|hud| hud.shader_params_panel.take_pending_revert());
+        if std::mem::take(&mut self.shader_params_stale) | pipeline_was_recreated | revert {
+            Self::refresh_shader_params(self.hud.as_mut(), &mut renderer_guard);
+        }
+        
+        if self.hud.as_mut().is_some_and(|hud| hud.export_panel.take_pending_cancel()) && self.export_job.take().is_some() {
+            info!("Frame export cancelled");
+            self.export_finished = true;
+        }
+        if let Some(settings) = self.hud.as_mut().and_then(|hud| hud.export_panel.take_pending_start()) {
+            match Self::create_export_job(&renderer_guard, settings) {
+                Ok(job) => self.export_job = Some(job),
+                Err(e) => Self::report_to(self.hud.as_mut(), &e),
+            }
+        }
+
+        match self.hud {
+            Some(ref mut hud) => {
+                // Sun settings from the environment panel drive the main directional light
+                renderer_guard.sun = hud.sun_settings();
+                
+                // Background and environment map from the background panel, load status shown back in it;
+                // the map file is decoded on the job pool
+                let background = hud.background_settings();
+                if self.background_map.as_ref().map(Handle::path) != background.hdri_path.as_deref() {
+                    self.background_map = match (background.hdri_path.as_deref(), self.resources.get_mut::<AssetManager>(), self.resources.get::<JobPool>()) {
+                        (Some(path), Some(mut assets), Some(jobs)) => Some(assets.textures.load(&jobs, path)),
+                        _ => None,
+                    };
+                }
+                let map = self.background_map.as_ref().and_then(Handle::get);
+                let map_state = self.background_map.as_ref().and_then(|handle| {
+                    self.resources.get::<AssetManager>().and_then(|assets| assets.textures.state(handle.path()))
+                });
+                let map_decoding = map_state == Some(AssetState::Loading);
+                let map_failed = map_state == Some(AssetState::Failed);
+                if let Err(e) = renderer_guard.set_background(background, map.as_deref(), map_failed) {
+                    Self::report_to(Some(&mut *hud), &e);
+                }
+                hud.background_panel.loaded_map = renderer_guard.environment_map_path().map(|path| path.to_path_buf());
+                hud.background_panel.map_loading = map_decoding || renderer_guard.is_environment_map_loading();
+                
+                // Render scale and resolution override from the render settings panel, and stats shown back in it
+                if let Some(scale) = hud.render_settings_panel.take_pending_target_scale() {
+                    if let Err(e) = renderer_guard.set_target_scale(scale) {
+                        Self::report_to(Some(&mut *hud), &e);
+                    }
+                }
+                let target = renderer_guard.target_extent();
+                hud.render_settings_panel.target_size = [target.width, target.height];
+                if let Some(override_scale) = hud.render_settings_panel.take_pending_resolution_override() {
+                    renderer_guard.resolution_scaler.set_override(override_scale);
+                }
+                
+                let window = renderer_guard.swapchain.swapchain_extent;
+                hud.still_export_panel.window_size = [window.width, window.height];
+                
+                // Single or quad view layout and per-viewport render modes
+                if let Some(settings) = hud.viewport_panel.take_pending_settings() {
+                    renderer_guard.viewports = settings;
+                }
+                renderer_guard.viewports.perspective = perspective;
+                hud.viewport_panel.settings.perspective = renderer_guard.viewports.perspective;
+                hud.render_settings_panel.record_gpu_time(renderer_guard.gpu_frame_ms(), renderer_guard.render_scale());
+                
+                // Live shader parameters, saved once an edit is finished
+                if let Some(data) = hud.shader_params_panel.gpu_data() {
+                    renderer_guard.shader_params = data;
+                }
+                if hud.shader_params_panel.take_pending_save() {
+                    if let Some(Err(e)) = hud.shader_params_panel.params.as_ref().map(ShaderParams::save) {
+                        Self::report_to(Some(&mut *hud), &e);
+                    }
+                }
+                
+                // Exposure and tonemapping from the render settings panel
+                renderer_guard.tonemap = hud.render_settings_panel.tonemap;
+                hud.render_settings_panel.display_output = renderer_guard.display_output();
+                hud.settings_panel.active_present_mode = Some(renderer_guard.active_present_mode());
+                
+                // Device memory and heap budgets, only read while the Stats panel shows them
+                if hud.stats_panel.is_visible {
+                    hud.stats_panel.memory = memory_usage::usage();
+                    hud.stats_panel.heaps = renderer_guard.heap_budgets();
+                }
+                
+                // Warn once when the meshes and maps in use outgrow the residency budget
+                let residency = renderer_guard.residency_usage();
+                if residency.is_over_budget() && !self.over_residency_budget {
+                    hud.notifications.push(ToastLevel::Warning, tr_args("toasts.over_residency_budget", &[
+                        ("resident", &memory_usage::format_bytes(residency.resident)),
+                        ("budget", &memory_usage::format_bytes(residency.budget)),
+                    ]));
+                }
+                self.over_residency_budget = residency.is_over_budget();
+                hud.stats_panel.residency = residency;
+                
+                debug!("Drawing frame with HUD");
+                debug!("HUD address: {:p}", hud);
+                let frame = renderer_guard.frame_number();
+                renderer_guard.draw_frame_with_hud(hud)
+                    .context(Subsystem::Renderer, "draw frame with HUD")
+                    .detail("frame", frame)?;
+            }
+            None => {
+                debug!("Drawing frame without HUD");
+                renderer_guard.viewports.perspective = perspective;
+                let frame = renderer_guard.frame_number();
+                renderer_guard.draw_frame()
+                    .context(Subsystem::Renderer, "draw frame")
+                    .detail("frame", frame)?;
+            }
+        }
+        
+        // Offline export renders at its own resolution and timestep, after the realtime frame
+        if let Some(job) = self.export_job.as_mut() {
+            match Self::advance_export(&mut renderer_guard, job) {
+                Ok(true) => {}
+                Ok(false) => {
+                    let job = self.export_job.take();
+                    self.export_finished = true;
+                    match job.map(ExportJob::finish).transpose().context(Subsystem::Io, "finish frame export") {
+                        Ok(output) => {
+                            let output = output.unwrap_or_default();
+                            info!("Export finished: {}", output.display());
+                            if let Some(hud) = self.hud.as_mut() {
+                                hud.notifications.push(ToastLevel::Info, tr_args("toasts.export_finished", &[("path", &output.display())]));
+                            }
+                        }
+                        Err(e) => Self::report_to(self.hud.as_mut(), &e),
+                    }
+                }
+                Err(e) => {
+                    self.export_job = None;
+                    self.export_finished = true;
+                    Self::report_to(self.hud.as_mut(), &e);
+                }
+            }
+        }
+        if let Some(hud) = self.hud.as_mut() {
+            hud.export_panel.progress = self.export_job.as_ref().map(ExportJob::progress);
+        }
+        
+        // Stills are rendered at their own resolution, after the realtime frame too
+        if let Some(settings) = self.hud.as_mut().and_then(|hud| hud.still_export_panel.take_pending_start()) {
+            match Self::export_still(&mut renderer_guard, &settings) {
+                Ok(path) => {
+                    if let Some(hud) = self.hud.as_mut() {
+                        let message = tr_args("toasts.still_exported", &[("path", &path.display()), ("width", &settings.width), ("height", &settings.height)]);
+                        hud.notifications.push(ToastLevel::Info, message);
+                    }
+                }
+                Err(e) => Self::report_to(self.hud.as_mut(), &e),
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Start an offline frame export
+    ///
+    /// Frames are rendered one per drawn frame until the export is done;
+    /// a running export is replaced.
+    ///
+    /// # Arguments
+    /// * `settings` - Time range, frame rate, resolution and output of the export
+    ///
+    /// # Errors
+    /// Returns an error if the export target or the output cannot be created
+    pub fn start_export(&mut self, settings: ExportSettings) -> Result<()> {
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+        let job = Self::create_export_job(&vulkan_renderer.lock().unwrap(), settings)?;
+        drop(vulkan_renderer);
+        self.export_job = Some(job);
+        Ok(())
+    }
+    
+    /// Draw a toy instead of the scene (`--toy`)
+    ///
+    /// The systems keep running; the manifest and the shaders are watched
+    /// for changes while hot reload is enabled.
+    ///
+    /// # Arguments
+    /// * `path` - Shadertoy style fragment shader or toy manifest, see `vulkan::toy`
+    ///
+    /// # Errors
+    /// Returns an error if the manifest is invalid or a shader does not compile
+    pub fn start_toy(&mut self, path: &Path) -> Result<()> {
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+        vulkan_renderer.lock().unwrap().start_toy(path)
+            .context(Subsystem::Renderer, "start the shadertoy mode")?;
+        drop(vulkan_renderer);
+        info!("Shadertoy mode: drawing {}", path.display());
+
+        if self.is_hot_reload_enabled() {
+            self.watch_toy();
+        }
+        Ok(())
+    }
+
+    /// Watch the files of the running toy, replacing earlier subscriptions
+    fn watch_toy(&mut self) {
+        let files = match self.resources.get::<Arc<Mutex<VulkanRenderer>>>() {
+            Some(renderer) => renderer.lock().unwrap().toy_files(),
+            None => return,
+        };
+        let old_subscriptions = std::mem::take(&mut self.toy_subscriptions);
+        let handlers: Vec<_> = files.iter().map(|_| self.toy_changes.handler()).collect();
+        let watcher = match self.asset_watcher() {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                warn!("Toy hot reload unavailable: {}", e);
+                return;
+            }
+        };
+        for subscription in old_subscriptions {
+            watcher.unsubscribe(subscription);
+        }
+        for (file, handler) in files.into_iter().zip(handlers) {
+            let kind = if file.extension().is_some_and(|extension| extension == "toml") { AssetKind::Config } else { AssetKind::Shader };
+            match watcher.subscribe(kind, &file, handler) {
+                Ok(subscription) => self.toy_subscriptions.push(subscription),
+                Err(e) => warn!("Toy hot reload unavailable for {}: {}", file.display(), e),
+            }
+        }
+    }
+    
+    /// Update the mouse seen by the toy shader and the SDF shader
+    pub fn update_mouse(&mut self, update: impl FnOnce(&mut MouseState)) {
+        if let Some(renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>() {
+            update(&mut renderer.lock().unwrap().mouse);
+        }
+    }
+    
+    /// Stop a running export, keeping the frames written so far
+    ///
+    /// Must be called before the Vulkan renderer is destroyed, since the
+    /// export target holds GPU resources.
+    pub fn cancel_export(&mut self) {
+        if self.export_job.take().is_some() {
+            info!("Frame export cancelled");
+            self.export_finished = true;
+        }
+    }
+    
+    /// Check whether an export ended (finished, failed or cancelled) since the last call
+    pub fn take_export_finished(&mut self) -> bool {
+        std::mem::take(&mut self.export_finished)
+    }
+    
+    /// Create the GPU target and output of an export
+    fn create_export_job(renderer: &VulkanRenderer, settings: ExportSettings) -> Result<ExportJob> {
+        let exporter = renderer.create_frame_exporter(settings.width, settings.height)
+            .context(Subsystem::Renderer, "create frame export target")?;
+        ExportJob::new(settings, exporter)
+            .context(Subsystem::Io, "open frame export output")
+    }
+    
+    /// Render and write the next frame of an export
+    ///
+    /// # Returns
+    /// * Ok(true) if a frame was written
+    /// * Ok(false) if every frame has been written
+    fn advance_export(renderer: &mut VulkanRenderer, job: &mut ExportJob) -> Result<bool> {
+        let Some(time) = job.next_frame_time() else {
+            return Ok(false);
+        };
+        let pixels = renderer.export_frame(&job.exporter, time)
+            .context(Subsystem::Renderer, format!("render export frame at {:.3}s", time))?;
+        job.write_frame(&pixels)
+            .context(Subsystem::Io, "write export frame")?;
+        Ok(true)
+    }
+    
+    /// Render a still and write it to its file
+    ///
+    /// # Returns
+    /// The file that was written
+    fn export_still(renderer: &mut VulkanRenderer, settings: &StillExportSettings) -> Result<PathBuf> {
+        let pixels = renderer.export_still(settings.width, settings.height)
+            .context(Subsystem::Renderer, format!("render {}x{} still", settings.width, settings.height))?;
+        settings.save(&pixels, &renderer.tonemap)
+            .context(Subsystem::Io, "save still")
+    }
+    
+    /// Release the window surface and swapchain while the app is suspended
+    ///
+    /// The device, the scene and the HUD stay alive; frames are skipped
+    /// until `resume_rendering`.
+    ///
+    /// # Errors
+    /// Returns an error if the renderer cannot release the surface
+    pub fn suspend_rendering(&mut self) -> Result<()> {
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+        vulkan_renderer.lock().unwrap().suspend()
+            .context(Subsystem::Renderer, "suspend rendering")
+    }
+
+    /// Recreate the window surface and swapchain when the app is resumed
+    ///
+    /// # Arguments
+    /// * `window` - The window to present to again
+    ///
+    /// # Errors
+    /// Returns an error if the surface or swapchain cannot be recreated
+    pub fn resume_rendering(&mut self, window: &Window) -> Result<()> {
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+        vulkan_renderer.lock().unwrap().resume(window)
+            .context(Subsystem::Renderer, "resume rendering")?;
+
+        // The window may have changed size while suspended
+        if let Some(ref mut hud) = self.hud {
+            let size = window.inner_size();
+            hud.handle_resize(vk::Extent2D { width: size.width, height: size.height });
+        }
+        Ok(())
+    }
+
+    /// Handle window resize event
+    ///
+    /// # Arguments
+    /// * `new_width` - The new window width
+    /// * `new_height` - The new window height
+    /// * `window` - The window for HUD resizing
+    ///
+    /// # Returns
+    /// * Ok(()) if resize was handled successfully
+    /// * Err if resize handling failed
+    pub fn handle_window_resize(&mut self, new_width: u32, new_height: u32, _window: &Window) -> Result<()> {
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+
+        let mut renderer_guard = vulkan_renderer.lock().unwrap();
+        renderer_guard.handle_resize(new_width, new_height)
+            .context(Subsystem::Renderer, "handle window resize")?;
+        
+        // Update HUD if available
+        if let Some(ref mut hud) = self.hud {
+            let extent = vk::Extent2D { width: new_width, height: new_height };
+            hud.handle_resize(extent);
+            
+            // Re-initialize HUD if needed for major changes
+            if new_width > 0 && new_height > 0 {
+                // HUD will automatically handle resizing through platform interface
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Handle fullscreen toggle
+    ///
+    /// # Arguments
+    /// * `window` - The window to handle fullscreen for
+    ///
+    /// # Returns
+    /// * Ok(()) if fullscreen toggle was handled successfully
+    /// * Err if fullscreen toggle handling failed
+    pub fn handle_fullscreen_toggle(&mut self, window: &Window) -> Result<()> {
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+
+        // Get current window size
+        let physical_size = window.inner_size();
+        let new_width = physical_size.width;
+        let new_height = physical_size.height;
+
+        info!("Handling fullscreen toggle, new size: {}x{}", new_width, new_height);
+
+        // Handle the resize which will recreate the swapchain
+        let mut renderer_guard = vulkan_renderer.lock().unwrap();
+        renderer_guard.handle_resize(new_width, new_height)
+            .context(Subsystem::Renderer, "handle fullscreen toggle")?;
+        
+        // Update HUD for fullscreen
+        if let Some(ref mut hud) = self.hud {
+            let extent = vk::Extent2D { width: new_width, height: new_height };
+            hud.handle_resize(extent);
+        }
+        
+        Ok(())
+    }
+    
+    /// Log an error and show it in the HUD
+    ///
+    /// Fatal errors open the error dialog, recoverable ones become toasts.
+    ///
+    /// # Arguments
+    /// * `error` - The error to report
+    pub fn report_error(&mut self, error: &AppError) {
+        Self::report_to(self.hud.as_mut(), error);
+    }
+    
+    /// Log an error and forward it to the HUD, if there is one
+    ///
+    /// Takes the HUD instead of `self` so it can be used while other fields
+    /// (e.g. the renderer resource) are borrowed.
+    fn report_to(hud: Option<&mut HUD>, error: &AppError) {
+        if error.is_fatal() {
+            error!("{}", error.report());
+        } else {
+            warn!("{}", error.report());
+        }
+        if let Some(hud) = hud {
+            hud.report_error(error);
+        }
+    }
+    
+    /// Reflect the SDF shader's parameters and read their saved values
+    ///
+    /// The values go to the renderer right away and to the Shader Parameters
+    /// panel for editing, if there is a HUD.
+    fn refresh_shader_params(mut hud: Option<&mut HUD>, renderer: &mut VulkanRenderer) {
+        let shader = project::locate(Path::new(config::shader::SDF_FRAGMENT_SHADER));
+        let reflection = renderer.shader_reflection().context(Subsystem::Shaders, "reflect the shader parameters");
+        match reflection {
+            Ok((blocks, annotations)) => {
+                let mut params = ShaderParams::new(&shader, &annotations, blocks);
+                // A broken values file leaves the annotation defaults rather than zeros
+                if let Err(e) = params.load_values() {
+                    Self::report_to(hud.as_deref_mut(), &e);
+                }
+                debug!("Shader {} has {} live parameters", shader.display(), params.params.len());
+                renderer.shader_params = params.gpu_data();
+                if let Some(hud) = hud {
+                    hud.shader_params_panel.set_params(params);
+                }
+            }
+            Err(e) => Self::report_to(hud, &e),
+        }
+    }
+    
+    /// Check whether the user asked to quit from the HUD error dialog
+    pub fn take_quit_request(&mut self) -> bool {
+        self.hud.as_mut().is_some_and(|hud| hud.take_quit_request())
+    }
+    
+    /// Whether ImGui wants the mouse, e.g. because it hovers a window
+    pub fn hud_wants_mouse(&self) -> bool {
+        self.hud.as_ref().is_some_and(|hud| hud.wants_mouse())
+    }
+    
+    /// Whether ImGui wants the keyboard, e.g. because a text field is edited
+    pub fn hud_wants_keyboard(&self) -> bool {
+        self.hud.as_ref().is_some_and(|hud| hud.wants_keyboard())
+    }
+    
+    /// Turn the perspective camera by raw mouse motion while the pointer is captured
+    ///
+    /// # Arguments
+    /// * `dx` - Horizontal motion, positive turns right
+    /// * `dy` - Vertical motion, positive turns down
+    pub fn rotate_camera(&mut self, dx: f32, dy: f32) {
+        // Taking hold of the camera stops a move to a bookmark
+        self.camera_transition = None;
+        self.look_camera.rotate(dx, dy);
+    }
+    
+    /// Zoom the camera by mouse wheel lines over the scene
+    ///
+    /// # Arguments
+    /// * `lines` - Wheel lines, positive zooms in
+    pub fn zoom_camera(&mut self, lines: f32) {
+        self.camera_transition = None;
+        self.look_camera.zoom(lines);
+    }
+    
+    /// Switch the camera between perspective and orthographic projection
+    pub fn toggle_camera_projection(&mut self) {
+        self.camera_transition = None;
+        self.look_camera.toggle_projection();
+        debug!("Camera projection: {}", if self.look_camera.is_orthographic() { "orthographic" } else { "perspective" });
+    }
+    
+    /// Place the perspective camera, used by the benchmark flythrough
+    pub fn set_camera(&mut self, camera: LookCamera) {
+        self.camera_transition = None;
+        self.look_camera = camera;
+    }
+    
+    /// Move the camera to a bookmark, smoothly if the settings ask for it
+    ///
+    /// # Arguments
+    /// * `index` - Position of the bookmark in the scene's bookmarks
+    pub fn go_to_camera_bookmark(&mut self, index: usize) {
+        let Some(bookmark) = self.camera_bookmarks.get(index) else {
+            if let Some(ref mut hud) = self.hud {
+                hud.notifications.push(ToastLevel::Info, tr_args("toasts.no_bookmark", &[("number", &(index + 1))]));
+            }
+            return;
+        };
+        debug!("Camera to bookmark {:?}", bookmark.name);
+        self.move_camera(bookmark.camera());
+    }
+    
+    /// Select the entity under the cursor, or clear the selection over empty space
+    ///
+    /// Casts a ray from the viewport the cursor is over through the spatial
+    /// index and selects the nearest entity whose bounds it hits. The bounds
+    /// are boxes and spheres around the shapes, not their SDF surfaces, so a
+    /// click just outside a rounded or carved shape can still select it.
+    pub fn pick_at_cursor(&mut self) {
+        let ray = {
+            let Some(renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>() else {
+                return;
+            };
+            let renderer = renderer.lock().unwrap();
+            let extent = renderer.swapchain.swapchain_extent;
+            let [x, y] = renderer.mouse.position;
+            let cursor = [x * extent.width as f32, y * extent.height as f32];
+            renderer.viewports
+                .rects(extent)
+                .into_iter()
+                .find(|(rect, _)| viewport::rect_contains(rect, cursor))
+                .map(|(rect, viewport)| renderer.viewports.camera(viewport.kind).ray(cursor, &rect))
+        };
+        let Some((origin, direction)) = ray else {
+            return;
+        };
+        let hit = self.resources.get::<SpatialIndex>().and_then(|index| index.raycast(origin, direction));
+        debug!("Picked {:?}", hit);
+        if let Some(hud) = self.hud.as_mut() {
+            hud.hierarchy_panel.select(hit.map(|hit| hit.entity));
+        }
+    }
+    
+    /// Move the camera back along its view direction to frame the selected entity
+    pub fn frame_selected(&mut self) {
+        let Some(ref mut hud) = self.hud else {
+            return;
+        };
+        let Some(selected) = hud.hierarchy_panel.selected() else {
+            hud.notifications.push(ToastLevel::Info, tr("toasts.nothing_selected"));
+            return;
+        };
+        let Some((center, radius)) = entity_bounds(&self.world, selected) else {
+            hud.notifications.push(ToastLevel::Info, tr("toasts.nothing_to_frame"));
+            return;
+        };
+        debug!("Framing {:?}: center {:?}, radius {}", selected, center, radius);
+        self.move_camera(self.look_camera.framing(center, radius));
+    }
+    
+    /// Move the camera to a pose, smoothly if the settings ask for it
+    fn move_camera(&mut self, to: LookCamera) {
+        self.camera_transition = None;
+        if self.camera_transition_secs > 0.0 {
+            self.camera_transition = Some(CameraTransition::new(self.look_camera, to, self.camera_transition_secs));
+        } else {
+            self.look_camera = to;
+        }
+    }
+    
+    /// Bookmark the current camera pose and save the bookmarks
+    ///
+    /// # Arguments
+    /// * `name` - Name of the bookmark, "View N" when None
+    pub fn add_camera_bookmark(&mut self, name: Option<String>) {
+        let name = name.unwrap_or_else(|| {
+            (1..)
+                .map(|number| format!("View {}", number))
+                .find(|name| !self.camera_bookmarks.iter().any(|bookmark| bookmark.name == *name))
+                .unwrap_or_default()
+        });
+        self.camera_bookmarks.push(CameraBookmark::new(&name, &self.look_camera));
+        if let Some(ref mut hud) = self.hud {
+            hud.notifications.push(ToastLevel::Info, tr_args("toasts.bookmark_added", &[("name", &name)]));
+        }
+        self.save_camera_bookmarks();
+    }
+    
+    /// Write the bookmarks into the scene file
+    ///
+    /// The built-in scene has no file; its bookmarks last until the app closes.
+    fn save_camera_bookmarks(&mut self) {
+        let result = match self.scene.as_mut().filter(|scene| scene.path.exists()) {
+            Some(scene) => scene.save_bookmarks(&self.camera_bookmarks),
+            None => {
+                if let Some(ref mut hud) = self.hud {
+                    hud.notifications.push(ToastLevel::Warning, tr("toasts.bookmarks_not_saved"));
+                }
+                return;
+            }
+        };
+        if let Err(e) = result {
+            Self::report_to(self.hud.as_mut(), &e);
+        }
+    }
+    
+    /// Set the frustums this frame's systems cull the meshes against
+    ///
+    /// Built from the viewport layout and look camera the frame will be
+    /// drawn with, after this update moved the camera, and from the sun,
+    /// whose shadows keep off-screen meshes in the shadow map.
+    fn update_view_frustums(&mut self) {
+        let culling_enabled = self.hud.as_ref().map_or(config::culling::ENABLED, |hud| hud.stats_panel.culling_enabled);
+        // The right eye sees past the mirrored left one, so a headset draws everything
+        #[cfg(feature = "xr")]
+        let culling_enabled = culling_enabled && !self.resources.get::<XrInput>().is_some_and(|input| input.head.is_some());
+        let renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>().map(|renderer| Arc::clone(&renderer));
+        let frustums = match (culling_enabled, renderer) {
+            (true, Some(renderer)) => {
+                let renderer = renderer.lock().unwrap();
+                let mut viewports = self.hud.as_ref().map_or(renderer.viewports, |hud| hud.viewport_panel.settings);
+                viewports.perspective = self.look_camera.view_camera();
+                let sun = self.hud.as_ref().map_or(renderer.sun, |hud| hud.sun_settings());
+                ViewFrustums::from_viewports(&viewports, renderer.target_extent()).with_sun(&sun)
+            }
+            _ => ViewFrustums::default(),
+        };
+        self.resources.insert(frustums);
+    }
+    
+    /// Apply the Camera Bookmarks panel's edits and advance a running camera move
+    fn update_camera_bookmarks(&mut self, delta_time: f32) {
+        let requests = self.hud.as_mut().map(|hud| hud.camera_bookmarks_panel.take_pending_requests()).unwrap_or_default();
+        for request in requests {
+            match request {
+                BookmarkRequest::GoTo(index) => self.go_to_camera_bookmark(index),
+                BookmarkRequest::Add(name) => self.add_camera_bookmark(Some(name).filter(|name| !name.trim().is_empty())),
+                BookmarkRequest::Update(index) => {
+                    if let Some(bookmark) = self.camera_bookmarks.get_mut(index) {
+                        *bookmark = CameraBookmark::new(&bookmark.name, &self.look_camera);
+                        self.save_camera_bookmarks();
+                    }
+                }
+                BookmarkRequest::Delete(index) => {
+                    if index < self.camera_bookmarks.len() {
+                        self.camera_bookmarks.remove(index);
+                        self.save_camera_bookmarks();
+                    }
+                }
+            }
+        }
+        
+        if let Some(ref mut transition) = self.camera_transition {
+            self.look_camera = transition.advance(delta_time);
+            if transition.is_finished() {
+                self.camera_transition = None;
+            }
+        }
+        
+        if let Some(ref mut hud) = self.hud {
+            if hud.camera_bookmarks_panel.is_visible {
+                hud.camera_bookmarks_panel.bookmarks.clone_from(&self.camera_bookmarks);
+                hud.camera_bookmarks_panel.scene_file = self.scene.as_ref().map(|scene| scene.path.clone()).filter(|path| path.exists());
+            }
+        }
+    }
+    
+    /// Most recent GPU time of the SDF pass in milliseconds
+    ///
+    /// # Returns
+    /// None without a Vulkan renderer, timestamp support or a measured frame
+    pub fn gpu_frame_ms(&self) -> Option<f32> {
+        self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .and_then(|renderer| renderer.lock().unwrap().gpu_frame_ms())
+    }
+    
+    /// Toggle HUD visibility
+    pub fn toggle_hud(&mut self) {
+        if let Some(ref mut hud) = self.hud {
+            hud.toolbar.toggle_visibility();
+            info!("HUD visibility toggled");
+        }
+    }
+    
+    /// Open or close the HUD command palette
+    pub fn toggle_command_palette(&mut self) {
+        if let Some(ref mut hud) = self.hud {
+            hud.toggle_command_palette();
+        }
+    }
+    
+    /// Take the action chosen in the command palette or clicked on the toolbar since the last call
+    pub fn take_requested_action(&mut self) -> Option<Action> {
+        self.hud.as_mut().and_then(|hud| hud.take_requested_action())
+    }
+    
+    /// Take the settings applied in the Settings window since the last call
+    pub fn take_requested_settings(&mut self) -> Option<AppSettings> {
+        self.hud.as_mut().and_then(|hud| hud.settings_panel.take_pending_app_settings())
+    }
+    
+    /// Take the project file chosen in the Project panel since the last call
+    pub fn take_requested_project(&mut self) -> Option<PathBuf> {
+        self.hud.as_mut().and_then(|hud| hud.project_panel.take_pending_open())
+    }
+    
+    /// Show a project and the recent projects in the HUD
     ///
     /// # Arguments
-    /// * `window` - Current window for HUD input handling
-    /// * `delta_time` - Time since last frame
+    /// * `project` - The open project
+    /// * `recent` - Recently opened projects
+    pub fn show_project(&mut self, project: &Project, recent: &RecentProjects) {
+        if let Some(ref mut hud) = self.hud {
+            hud.project_panel.project = project.clone();
+            hud.project_panel.recent = recent.projects.clone();
+        }
+    }
+    
+    /// List the connected monitors in the Settings window
     ///
-    /// # Returns
-    /// * Ok(()) if all systems executed successfully
-    /// * Err if any system failed to execute
-    pub fn execute(&mut self, window: &Window, delta_time: f32) -> Result<()> {
-        // Get hot reload state before borrowing HUD
-        let hot_reload_enabled = self.is_hot_reload_enabled();
-
-        // Update HUD first
+    /// # Arguments
+    /// * `monitors` - Monitors windowed fullscreen can cover
+    pub fn show_monitors(&mut self, monitors: Vec<MonitorInfo>) {
         if let Some(ref mut hud) = self.hud {
-            hud.update(window, delta_time);
-            // Update hot reload button state to match current hot reload status
-            hud.toolbar.update_hot_reload_button_state(hot_reload_enabled);
+            hud.settings_panel.monitors = monitors;
         }
-        
-        self.schedule.execute(&mut self.world, &mut self.resources);
-        Ok(())
     }
     
-    /// Draw a single frame
+    /// Switch to a project that was made the current one
     ///
-    /// # Returns
-    /// * Ok(()) if the frame was drawn successfully
-    /// * Err if drawing failed
-    pub fn draw_frame(&mut self) -> Result<()> {
-        // Check if we need to update command buffers due to hot reload from previous frame
-        // This MUST be done at the very beginning of the frame, before any rendering
-        let _needs_command_buffer_update = if let Some(ref hot_reload_manager) = self.hot_reload_manager {
-            let should_update = hot_reload_manager.check_and_clear_reloads_occurred();
-            debug!("Command buffer update needed: {}", should_update);
-            should_update
-        } else {
-            false
-        };
-
-        // Process any pending shader reloads first and check if pipeline was recreated
-        let pipeline_was_recreated = if let Some(ref mut hot_reload_manager) = self.hot_reload_manager {
-            match hot_reload_manager.process_pending_reloads() {
-                Ok(was_recreated) => was_recreated,
-                Err(e) => {
-                    error!("Failed to process pending shader reloads: {}", e);
-                    false // Continue with frame rendering even if reload fails
+    /// Loads the project's scene in place of the current one, if it has one,
+    /// and points the export panel at its output directory.
+    ///
+    /// # Arguments
+    /// * `project` - The project
+    /// * `recent` - Recently opened projects, for the Project panel
+    ///
+    /// # Errors
+    /// Returns an error if the scene cannot be loaded; the current scene is kept then
+    pub fn open_project(&mut self, project: &Project, recent: &RecentProjects) -> Result<()> {
+        self.show_project(project, recent);
+        self.shader_params_stale = true;
+        if let Some(ref mut hud) = self.hud {
+            hud.export_panel.set_output_dir(project.output_dir());
+            hud.still_export_panel.set_output_dir(project.output_dir());
+            hud.notifications.push(ToastLevel::Info, tr_args("toasts.opened_project", &[("name", &project.name)]));
+        }
+        let scene_path = project.scene_file();
+        if !scene_path.exists() {
+            info!("Project {} has no scene file {:?}; keeping the current scene", project.name, scene_path);
+            return Ok(());
+        }
+        self.load_scene(&scene_path)
+    }
+    
+    /// Replace the scene, loaded or built-in, with a scene file
+    ///
+    /// # Errors
+    /// Returns an error if the scene cannot be loaded; the current scene is kept then
+    pub fn load_scene(&mut self, path: &Path) -> Result<()> {
+        self.replace_scene(path, path)
+    }
+    
+    /// Replace the scene with a scene file read on the job pool
+    ///
+    /// The current scene stays until the file is loaded, and when it cannot
+    /// be; the failure is reported in the HUD.
+    pub fn open_scene(&mut self, path: &Path) {
+        if let (Some(mut assets), Some(jobs)) = (self.resources.get_mut::<AssetManager>(), self.resources.get::<JobPool>()) {
+            self.opening_scene = Some(assets.scenes.load(&jobs, path));
+        }
+    }
+    
+    /// Open a file dropped onto the window
+    ///
+    /// Mesh files are placed at the origin, `.hdr` files become the
+    /// background's environment map and `.toml` files replace the scene.
+    /// Each is loaded on the job pool, so frames keep coming meanwhile; a
+    /// mesh is drawn as a cube until then.
+    pub fn open_dropped_file(&mut self, path: &Path) {
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match extension.as_deref() {
+            Some("obj" | "gltf" | "glb") => {
+                let entity = self.world.push((
+                    MeshRenderer { mesh: path.to_string_lossy().into_owned(), color: [0.8; 3] },
+                    Transform::default(),
+                ));
+                if let Some(mut top_level) = self.resources.get_mut::<Vec<legion::Entity>>() {
+                    top_level.push(entity);
+                }
+                info!("Added mesh {} as {:?}", path.display(), entity);
+                if let Some(hud) = self.hud.as_mut() {
+                    hud.notifications.push(ToastLevel::Info, tr_args("toasts.added_mesh", &[("name", &name)]));
                 }
             }
-        } else {
-            false
-        };
-
-        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
-            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
-
-        // IMMEDIATE command buffer update if pipeline was recreated
-        if pipeline_was_recreated {
-            info!("Pipeline was recreated during hot reload, updating command buffers immediately");
-            let mut renderer_guard = vulkan_renderer.lock().unwrap();
-            if let Err(e) = renderer_guard.update_command_buffers_after_hot_reload() {
-                error!("Failed to update command buffers after hot reload: {}", e);
-                // Continue with rendering even if command buffer update fails
-            } else {
-                info!("Command buffer update completed successfully after hot reload");
+            Some("hdr") => {
+                if let Some(hud) = self.hud.as_mut() {
+                    hud.background_panel.background.hdri_path = Some(path.to_path_buf());
+                    hud.background_panel.background.mode = BackgroundMode::Hdri;
+                }
             }
-        }
-
-        // Check if HUD is available and log its state
-        let mut renderer_guard = vulkan_renderer.lock().unwrap();
-        match self.hud {
-            Some(ref mut hud) => {
-                debug!("Drawing frame with HUD");
-                debug!("HUD address: {:p}", hud);
-                renderer_guard.draw_frame_with_hud(hud)
-                    .map_err(|e| AppError::Vulkan(crate::error::VulkanError::Rendering(
-                        format!("Failed to draw frame with HUD: {}", e)
-                    )))?;
+            Some("toml") => self.open_scene(path),
+            _ => {
+                warn!("Cannot open dropped file {}", path.display());
+                if let Some(hud) = self.hud.as_mut() {
+                    hud.notifications.push(ToastLevel::Warning, tr_args("toasts.unsupported_file", &[("name", &name)]));
+                }
             }
+        }
+    }
+    
+    /// Replace the scene with an auto-saved one
+    ///
+    /// The restored scene stands in for the current scene file (the
+    /// project's for the built-in scene): hot reload follows that file, not
+    /// the auto-save, and replaces the restored scene when the file changes.
+    ///
+    /// # Errors
+    /// Returns an error if the auto-save cannot be loaded; the current scene is kept then
+    pub fn restore_scene(&mut self, autosave: &Path) -> Result<()> {
+        let path = self.scene.as_ref().map_or_else(|| project::current().scene_file(), |scene| scene.path.clone());
+        self.replace_scene(autosave, &path)?;
+        // The restored scene is unsaved until its file is reloaded
+        self.scene_edited = true;
+        Ok(())
+    }
+    
+    /// Replace the scene with the contents of `file`, keyed to the scene file `path`
+    fn replace_scene(&mut self, file: &Path, path: &Path) -> Result<()> {
+        self.replace_scene_with(file, SceneFile::load(file)?, path)
+    }
+    
+    /// Replace the scene with `scene`, read from `file` and keyed to the scene file `path`
+    fn replace_scene_with(&mut self, file: &Path, scene: SceneFile, path: &Path) -> Result<()> {
+        // The built-in scene is its top-level entities and their CSG operands
+        let built_in: Vec<legion::Entity> = match self.scene {
+            Some(_) => Vec::new(),
             None => {
-                debug!("Drawing frame without HUD");
-                renderer_guard.draw_frame()
-                    .map_err(|e| AppError::Vulkan(crate::error::VulkanError::Rendering(
-                        format!("Failed to draw frame: {}", e)
-                    )))?;
+                let top_level = self.resources.get::<Vec<legion::Entity>>().map(|entities| entities.clone()).unwrap_or_default();
+                let operands: Vec<legion::Entity> = top_level
+                    .iter()
+                    .filter_map(|entity| self.world.entry_ref(*entity).ok())
+                    .filter_map(|entry| entry.get_component::<CsgNode>().ok().map(|node| node.operands.clone()))
+                    .flatten()
+                    .collect();
+                top_level.into_iter().chain(operands).collect()
             }
+        };
+        let mut scene = LoadedScene::spawn(file, scene, &mut self.world, &mut self.resources)?;
+        scene.path = path.to_path_buf();
+        self.camera_bookmarks = scene.bookmarks.clone();
+        self.camera_transition = None;
+        self.scene_edited = false;
+        if let Some(previous) = self.scene.replace(scene) {
+            previous.unload(&mut self.world, &mut self.resources);
+        }
+        for entity in &built_in {
+            self.world.remove(*entity);
+        }
+        if let Some(mut top_level) = self.resources.get_mut::<Vec<legion::Entity>>() {
+            top_level.retain(|entity| !built_in.contains(entity));
         }
         
+        // Follow the new file with hot reload
+        if let (Some(watcher), Some(subscription)) = (self.asset_watcher.as_mut(), self.scene_subscription.take()) {
+            watcher.unsubscribe(subscription);
+        }
+        if self.is_hot_reload_enabled() {
+            self.watch_scene_file()?;
+        }
         Ok(())
     }
     
-    /// Handle window resize event
+    /// Apply runtime settings
     ///
-    /// # Arguments
-    /// * `new_width` - The new window width
-    /// * `new_height` - The new window height
-    /// * `window` - The window for HUD resizing
+    /// Only what `changes` names is rebuilt: the swapchain for a new present
+    /// mode, the SDF pipeline for a new quality tier or sample count, the
+    /// watchers for new hot reload options. A failed step is reported and
+    /// the remaining ones still run.
     ///
-    /// # Returns
-    /// * Ok(()) if resize was handled successfully
-    /// * Err if resize handling failed
-    pub fn handle_window_resize(&mut self, new_width: u32, new_height: u32, _window: &Window) -> Result<()> {
-        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
-            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
-
-        let mut renderer_guard = vulkan_renderer.lock().unwrap();
-        renderer_guard.handle_resize(new_width, new_height)
-            .map_err(|e| AppError::Vulkan(crate::error::VulkanError::Rendering(
-                format!("Failed to handle window resize: {}", e)
-            )))?;
-        
-        // Update HUD if available
+    /// # Arguments
+    /// * `settings` - Settings to apply
+    /// * `changes` - What differs from the settings applied before
+    pub fn apply_settings(&mut self, settings: &AppSettings, changes: SettingsChanges) {
+        if changes.camera {
+            self.camera_transition_secs = settings.camera_transition_secs;
+        }
+        if changes.screenshot {
+            self.screenshot_hdr = settings.screenshot_hdr;
+        }
+        if changes.autosave {
+            if let Some(ref mut autosave) = self.autosave {
+                autosave.set_settings(settings.autosave);
+            }
+        }
+        let Some(vulkan_renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>().map(|renderer| Arc::clone(&renderer)) else {
+            warn!("VulkanRenderer not found, settings not applied");
+            return;
+        };
+        let mut errors = Vec::new();
+        {
+            let mut renderer_guard = vulkan_renderer.lock().unwrap();
+            if changes.swapchain {
+                let operation = format!("switch present mode to {}", settings.present_mode.name());
+                if let Err(e) = renderer_guard.set_present_mode(settings.present_mode).context(Subsystem::Renderer, operation) {
+                    errors.push(e);
+                }
+            }
+            if changes.quality {
+                let quality = settings.raymarch_quality;
+                match renderer_guard.set_raymarch_quality(quality)
+                    .context(Subsystem::Renderer, format!("switch raymarch quality to {}", quality.name()))
+                {
+                    Ok(()) => if let Some(ref mut hud) = self.hud {
+                        hud.render_settings_panel.quality = quality;
+                    },
+                    Err(e) => errors.push(e),
+                }
+            }
+            if changes.shader_variant {
+                let mut variant = renderer_guard.pipeline.lock().unwrap().variant.clone();
+                variant.set_samples(settings.msaa_samples);
+                let operation = format!("switch shader variant to {}", variant.name());
+                match renderer_guard.set_shader_variant(variant.clone()).context(Subsystem::Shaders, operation) {
+                    Ok(()) => {
+                        self.shader_params_stale = true;
+                        if let Some(ref mut hud) = self.hud {
+                            hud.render_settings_panel.variant = variant;
+                        }
+                    }
+                    Err(e) => errors.push(e),
+                }
+            }
+            if changes.hot_reload {
+                renderer_guard.pipeline.lock().unwrap().safe_mode = settings.hot_reload.safe_mode;
+            }
+            if changes.outline {
+                renderer_guard.outline_settings = settings.outline;
+            }
+        }
+        if changes.hot_reload {
+            if let Err(e) = self.reconfigure_hot_reload(settings) {
+                errors.push(e);
+            }
+        }
+        if changes.theme {
+            if let Some(ref mut hud) = self.hud {
+                hud.set_theme(theme::named_theme(&settings.theme));
+            }
+        }
+        if changes.toolbar {
+            self.set_hud_position(settings.toolbar_position);
+        }
+        if changes.language {
+            match locale::set_language(&settings.language).context(Subsystem::Hud, format!("switch the HUD language to {}", settings.language)) {
+                Ok(locale) => if let Some(ref mut hud) = self.hud {
+                    hud.settings_panel.require_glyph_ranges(&locale.glyph_ranges);
+                },
+                Err(e) => errors.push(e),
+            }
+        }
+        for e in errors {
+            Self::report_to(self.hud.as_mut(), &e);
+        }
+    }
+    
+    /// Restart the watchers with the shader directory and hot reload options of `settings`
+    fn reconfigure_hot_reload(&mut self, settings: &AppSettings) -> Result<()> {
+        let Some(ref mut hot_reload) = self.hot_reload_manager else {
+            return Ok(());
+        };
+        let config = HotReloadConfig::from_settings(settings);
+        if config != *hot_reload.config() {
+            hot_reload.reconfigure(config).context(Subsystem::Shaders, "restart the shader watcher")?;
+        }
+        self.set_hot_reload_enabled(settings.hot_reload.enabled)
+    }
+    
+    /// Press a toolbar button by id, as if it was clicked
+    pub fn press_toolbar_button(&mut self, id: &str) {
         if let Some(ref mut hud) = self.hud {
-            let extent = vk::Extent2D { width: new_width, height: new_height };
-            hud.handle_resize(extent);
-            
-            // Re-initialize HUD if needed for major changes
-            if new_width > 0 && new_height > 0 {
-                // HUD will automatically handle resizing through platform interface
+            if !hud.toolbar.press_button(id) {
+                debug!("Toolbar button '{}' is missing or disabled", id);
             }
         }
-        
-        Ok(())
     }
     
-    /// Handle fullscreen toggle
+    /// Switch between the single view and the quad view
+    pub fn toggle_quad_view(&mut self) {
+        match self.hud {
+            // The viewport panel owns the layout and hands it to the renderer
+            Some(ref mut hud) => hud.viewport_panel.toggle_layout(),
+            None => {
+                if let Some(vulkan_renderer) = self.resources.get::<Arc<Mutex<VulkanRenderer>>>() {
+                    let mut renderer_guard = vulkan_renderer.lock().unwrap();
+                    renderer_guard.viewports.layout = match renderer_guard.viewports.layout {
+                        ViewportLayout::Single => ViewportLayout::Quad,
+                        ViewportLayout::Quad => ViewportLayout::Single,
+                    };
+                }
+            }
+        }
+    }
+    
+    /// Save the current view, without the HUD, as a PNG in the screenshot directory
     ///
-    /// # Arguments
-    /// * `window` - The window to handle fullscreen for
+    /// The image has the size of the offscreen SDF target, so a render scale
+    /// above 100% gives a supersampled screenshot. With an HDR format chosen
+    /// in the settings, the linear frame before tonemapping is saved next to
+    /// the PNG under the same name.
     ///
     /// # Returns
-    /// * Ok(()) if fullscreen toggle was handled successfully
-    /// * Err if fullscreen toggle handling failed
-    pub fn handle_fullscreen_toggle(&mut self, window: &Window) -> Result<()> {
+    /// The PNG written
+    ///
+    /// # Errors
+    /// Returns an error if the frame cannot be rendered or the file cannot be written
+    pub fn take_screenshot(&mut self) -> Result<PathBuf> {
         let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
             .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
-
-        // Get current window size
-        let physical_size = window.inner_size();
-        let new_width = physical_size.width;
-        let new_height = physical_size.height;
-
-        info!("Handling fullscreen toggle, new size: {}x{}", new_width, new_height);
-
-        // Handle the resize which will recreate the swapchain
         let mut renderer_guard = vulkan_renderer.lock().unwrap();
-        renderer_guard.handle_resize(new_width, new_height)
-            .map_err(|e| AppError::Vulkan(crate::error::VulkanError::Rendering(
-                format!("Failed to handle fullscreen toggle: {}", e)
-            )))?;
+        let extent = renderer_guard.target_extent();
+        let exporter = renderer_guard.create_frame_exporter(extent.width, extent.height)
+            .context(Subsystem::Renderer, "create screenshot target")?;
+        let time = renderer_guard.time();
+        let halves = renderer_guard.export_frame_hdr(&exporter, time)
+            .context(Subsystem::Renderer, "render screenshot")?;
+        let pixels = FrameExporter::tonemap_pixels(&halves, &renderer_guard.tonemap);
+        drop(exporter);
+        drop(renderer_guard);
+        drop(vulkan_renderer);
         
-        // Update HUD for fullscreen
-        if let Some(ref mut hud) = self.hud {
-            let extent = vk::Extent2D { width: new_width, height: new_height };
-            hud.handle_resize(extent);
-        }
+        let dir = project::current().screenshot_dir();
+        std::fs::create_dir_all(&dir).context(Subsystem::Io, format!("create {}", dir.display()))?;
+        let path = screenshot_path(&dir, &Local::now());
+        image_io::write_png(&path, extent.width, extent.height, &pixels)?;
+        let hdr_path = match self.screenshot_hdr {
+            Some(format) => {
+                let hdr_path = path.with_extension(format.extension());
+                format.write(&hdr_path, extent.width, extent.height, &halves)?;
+                Some(hdr_path)
+            }
+            None => None,
+        };
         
-        Ok(())
-    }
-    
-    /// Toggle HUD visibility
-    pub fn toggle_hud(&mut self) {
-        if let Some(ref mut hud) = self.hud {
-            hud.toolbar.toggle_visibility();
-            info!("HUD visibility toggled");
+        let message = match hdr_path {
+            Some(ref hdr_path) => {
+                info!("Screenshot saved to {} and {}", path.display(), hdr_path.display());
+                tr_args("toasts.screenshot_saved_hdr", &[("path", &path.display()), ("hdr_path", &hdr_path.display())])
+            }
+            None => {
+                info!("Screenshot saved to {}", path.display());
+                tr_args("toasts.screenshot_saved", &[("path", &path.display())])
+            }
+        };
+        if let Some(hud) = self.hud.as_mut() {
+            hud.notifications.push(ToastLevel::Info, message);
         }
+        Ok(path)
     }
     
     /// Wait for GPU to complete all pending operations
@@ -358,10 +2218,7 @@ impl ECSWorld {
         let renderer_guard = vulkan_renderer.lock().unwrap();
         unsafe {
             renderer_guard.device.device.device_wait_idle()
-                .map_err(|e| {
-                    error!("Failed to wait for GPU idle: {:?}", e);
-                    EcsError::ResourceAccess(format!("Failed to wait for GPU idle: {:?}", e))
-                })?;
+                .vk_context(Subsystem::Renderer, "wait for GPU idle")?;
         }
         
         info!("GPU idle confirmed, safe to proceed with resource cleanup");
@@ -391,13 +2248,18 @@ impl ECSWorld {
             drop(std::mem::replace(&mut self.hot_reload_manager, None));
             info!("Hot reload manager cleaned up manually");
         }
+        self.scene_subscription = None;
+        self.script_subscription = None;
+        self.toy_subscriptions.clear();
+        self.toolbar_subscription = None;
+        self.asset_watcher = None;
     }
     
     /// Set HUD toolbar position
     #[allow(dead_code)]
     pub fn set_hud_position(&mut self, position: ToolbarPosition) {
         if let Some(ref mut hud) = self.hud {
-            hud.toolbar.set_position(crate::hud::toolbar::ToolbarPosition::Top); // Convert position type
+            hud.toolbar.set_position(position);
             info!("HUD position set to {:?}", position);
         }
     }
@@ -461,6 +2323,28 @@ impl ECSWorld {
         if let Some(ref mut hot_reload) = self.hot_reload_manager {
             hot_reload.set_enabled(enabled)?;
             info!("Hot reload {}", if enabled { "enabled" } else { "disabled" });
+            if !enabled {
+                if let Some(watcher) = self.asset_watcher.as_mut() {
+                    let subscriptions = [self.scene_subscription.take(), self.script_subscription.take(), self.toolbar_subscription.take()];
+                    for subscription in subscriptions.into_iter().flatten() {
+                        watcher.unsubscribe(subscription);
+                    }
+                    for subscription in self.toy_subscriptions.drain(..) {
+                        watcher.unsubscribe(subscription);
+                    }
+                }
+            } else {
+                if self.scene_subscription.is_none() {
+                    self.watch_scene_file()?;
+                }
+                if self.script_subscription.is_none() {
+                    self.watch_scripts()?;
+                }
+                if self.toy_subscriptions.is_empty() {
+                    self.watch_toy();
+                }
+                self.watch_toolbar_layout()?;
+            }
         } else {
             warn!("Hot reload manager not initialized");
         }
@@ -496,6 +2380,91 @@ impl ECSWorld {
         Ok(())
     }
     
+    /// Reload every asset kind and show a summary toast ("Reload Everything")
+    ///
+    /// Flushes the shader cache and rebuilds the SDF pipeline, reloads
+    /// the HUD theme, the toolbar layout, the scene file and the scripts, and reports config
+    /// (and the layout and scene when they have no file) as skipped. Failures are reported
+    /// individually; the rest still reload.
+    ///
+    /// # Returns
+    /// What happened to each asset kind
+    pub fn reload_all(&mut self) -> ReloadSummary {
+        info!("Reloading everything");
+        let mut summary = ReloadSummary::default();
+
+        for target in ReloadTarget::ALL {
+            let outcome = match target {
+                ReloadTarget::Shaders => match self.reload_all_shaders() {
+                    Ok(()) => ReloadOutcome::Reloaded,
+                    Err(e) => ReloadOutcome::Failed(e),
+                },
+                ReloadTarget::Config => ReloadOutcome::Skipped("configuration is compiled in"),
+                ReloadTarget::Theme => match self.hud {
+                    Some(ref mut hud) => match hud.reload_theme() {
+                        Ok(()) => ReloadOutcome::Reloaded,
+                        Err(e) => ReloadOutcome::Failed(e),
+                    },
+                    None => ReloadOutcome::Skipped("no HUD"),
+                },
+                ReloadTarget::ToolbarLayout => match self.hud {
+                    Some(_) if !Path::new(config::hud_layout::TOOLBAR_FILE).is_file() => ReloadOutcome::Skipped("no toolbar layout file"),
+                    Some(ref mut hud) => match hud.reload_toolbar_layout() {
+                        Ok(()) => ReloadOutcome::Reloaded,
+                        Err(e) => ReloadOutcome::Failed(e),
+                    },
+                    None => ReloadOutcome::Skipped("no HUD"),
+                },
+                ReloadTarget::Scene => match self.scene {
+                    Some(ref mut scene) => match scene.reload(&mut self.world, &mut self.resources) {
+                        Ok(_) => {
+                            self.camera_bookmarks = scene.bookmarks.clone();
+                            self.scene_edited = false;
+                            ReloadOutcome::Reloaded
+                        }
+                        Err(e) => ReloadOutcome::Failed(e),
+                    },
+                    None => ReloadOutcome::Skipped("scene is built in code"),
+                },
+                ReloadTarget::Scripts if !self.scripts.directory().is_dir() => ReloadOutcome::Skipped("no scripts folder"),
+                ReloadTarget::Scripts => match self.scripts.reload_all(&mut self.world) {
+                    Ok(()) => ReloadOutcome::Reloaded,
+                    Err(e) => ReloadOutcome::Failed(e),
+                },
+            };
+            match &outcome {
+                ReloadOutcome::Reloaded => info!("Reloaded {}", target.name()),
+                ReloadOutcome::Skipped(reason) => info!("Skipped reloading {}: {}", target.name(), reason),
+                ReloadOutcome::Failed(e) => Self::report_to(self.hud.as_mut(), e),
+            }
+            summary.record(target, outcome);
+        }
+
+        let message = summary.message();
+        info!("{}", message);
+        if let Some(ref mut hud) = self.hud {
+            hud.notifications.push(summary.level(), message);
+        }
+        summary
+    }
+
+    /// Flush the shader cache, rebuild the SDF pipeline and rerecord the command buffers
+    fn reload_all_shaders(&mut self) -> Result<()> {
+        let vulkan_renderer = self.resources.get::<Arc<Mutex<VulkanRenderer>>>()
+            .ok_or_else(|| EcsError::ResourceAccess("VulkanRenderer resource not found in ECS world".to_string()))?;
+        let mut renderer_guard = vulkan_renderer.lock().unwrap();
+
+        renderer_guard.pipeline.lock().unwrap().recompile_shaders()
+            .context(Subsystem::Shaders, "rebuild the SDF pipeline from source")?;
+        renderer_guard.update_command_buffers_after_hot_reload()
+            .context(Subsystem::Renderer, "update command buffers after reloading everything")?;
+
+        if let Some(ref hot_reload_manager) = self.hot_reload_manager {
+            hot_reload_manager.clear_last_error();
+        }
+        Ok(())
+    }
+
     /// Get hot reload statistics
     ///
     /// # Returns
@@ -523,3 +2492,125 @@ impl std::ops::DerefMut for ECSWorld {
         &mut self.world
     }
 }
+
+/// Unused file name for a screenshot taken at `time`
+fn screenshot_path(dir: &Path, time: &DateTime<Local>) -> PathBuf {
+    let name = time.format("screenshot_%Y-%m-%d_%H-%M-%S").to_string();
+    let mut path = dir.join(format!("{}.png", name));
+    // Several screenshots in the same second keep all of them
+    let mut suffix = 2;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.png", name, suffix));
+        suffix += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::NullRenderer;
+
+    #[test]
+    fn test_frames_reach_the_renderer() {
+        let (renderer, null_renderer) = NullRenderer::shared();
+        let mut world = ECSWorld::with_renderer(
+            renderer,
+            Path::new("missing/scene.toml"),
+            Path::new("missing/scripts"),
+        ).unwrap();
+
+        world.update(1.0 / 60.0).unwrap();
+        world.draw_frame().unwrap();
+
+        let null_renderer = null_renderer.lock().unwrap();
+        assert!(null_renderer.scene_data.shape_count() > 0);
+        assert_eq!(null_renderer.frames_drawn, 1);
+    }
+
+    #[test]
+    fn test_load_scene_replaces_the_built_in_scene() {
+        let (renderer, _) = NullRenderer::shared();
+        let mut world = ECSWorld::with_renderer(
+            renderer,
+            Path::new("missing/scene.toml"),
+            Path::new("missing/scripts"),
+        ).unwrap();
+        let built_in = world.resources.get::<Vec<legion::Entity>>().unwrap().clone();
+
+        world.load_scene(Path::new(config::scene::SCENE_FILE)).unwrap();
+        assert!(built_in.iter().all(|entity| world.world.entry_ref(*entity).is_err()));
+        let loaded = world.resources.get::<Vec<legion::Entity>>().unwrap().clone();
+        assert!(!loaded.is_empty());
+
+        // A scene that cannot be loaded keeps the current one
+        assert!(world.load_scene(Path::new("missing/scene.toml")).is_err());
+        assert_eq!(*world.resources.get::<Vec<legion::Entity>>().unwrap(), loaded);
+    }
+
+    #[test]
+    fn test_restored_scene_stands_in_for_the_scene_file() {
+        let (renderer, _) = NullRenderer::shared();
+        let mut world = ECSWorld::with_renderer(
+            renderer,
+            Path::new(config::scene::SCENE_FILE),
+            Path::new("missing/scripts"),
+        ).unwrap();
+        let mut edited = world.capture_scene();
+        edited.shapes[0].color = [0.1, 0.2, 0.3];
+        let file = std::env::temp_dir().join(format!("vulkan-app-restore-test-{}.toml", std::process::id()));
+        std::fs::write(&file, edited.to_toml().unwrap()).unwrap();
+
+        assert!(!world.scene_edited);
+        world.restore_scene(&file).unwrap();
+        assert_eq!(world.scene.as_ref().unwrap().path, Path::new(config::scene::SCENE_FILE));
+        assert_eq!(world.capture_scene(), edited);
+        assert!(world.scene_edited);
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_job_completions_run_on_update() {
+        let (renderer, _) = NullRenderer::shared();
+        let mut world = ECSWorld::with_renderer(
+            renderer,
+            Path::new("missing/scene.toml"),
+            Path::new("missing/scripts"),
+        ).unwrap();
+        let (done, ended) = std::sync::mpsc::channel();
+        world.resources.get::<JobPool>().unwrap().spawn_then("count", || 3, move |count, _: &mut ECSWorld| {
+            done.send((count.unwrap(), std::thread::current().id())).unwrap();
+        });
+
+        // The callback waits for an update, on the thread running it
+        let deadline = Instant::now() + std::time::Duration::from_secs(5);
+        while !world.resources.get::<JobPool>().unwrap().active().is_empty() && Instant::now() < deadline {
+            std::thread::yield_now();
+        }
+        assert!(ended.try_recv().is_err());
+        world.update(1.0 / 60.0).unwrap();
+        assert_eq!(ended.try_recv().unwrap(), (3, std::thread::current().id()));
+    }
+
+    #[test]
+    fn test_opened_scene_replaces_the_scene_once_loaded() {
+        let (renderer, _) = NullRenderer::shared();
+        let mut world = ECSWorld::with_renderer(
+            renderer,
+            Path::new("missing/scene.toml"),
+            Path::new("missing/scripts"),
+        ).unwrap();
+        let built_in = world.resources.get::<Vec<legion::Entity>>().unwrap().clone();
+
+        // The built-in scene stays while the file is read
+        world.open_scene(Path::new(config::scene::SCENE_FILE));
+        assert!(world.scene.is_none());
+        let deadline = Instant::now() + std::time::Duration::from_secs(5);
+        while world.scene.is_none() && Instant::now() < deadline {
+            world.update(1.0 / 60.0).unwrap();
+        }
+        assert_eq!(world.scene.as_ref().unwrap().path, Path::new(config::scene::SCENE_FILE));
+        assert!(built_in.iter().all(|entity| world.world.entry_ref(*entity).is_err()));
+        assert!(world.opening_scene.is_none());
+    }
+}
@@ -0,0 +1,223 @@
+//! Environment settings shared between the HUD and the SDF renderer
+//!
+//! The sun is described in polar coordinates (azimuth/elevation) and can
+//! optionally be driven by a time-of-day value which computes both the
+//! direction and a warm/cool tint for the main directional light.
+//!
+//! Rays that miss every shape show the background: a solid color, a
+//! vertical gradient, the procedural sky lit by the sun, or an HDRI
+//! environment map. The same background is reflected by shiny surfaces when
+//! environment reflections are enabled.
+
+use std::path::PathBuf;
+use cgmath::{InnerSpace, Vector3};
+use crate::config;
+
+/// Sun (main directional light) settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunSettings {
+    /// Azimuth angle in degrees, measured clockwise from +Z around the Y axis
+    pub azimuth_deg: f32,
+
+    /// Elevation angle in degrees above the horizon (-90..90)
+    pub elevation_deg: f32,
+
+    /// Whether azimuth/elevation are derived from `time_of_day`
+    pub use_time_of_day: bool,
+
+    /// Time of day in hours (0..24), only used when `use_time_of_day` is set
+    pub time_of_day: f32,
+
+    /// Light intensity multiplier
+    pub intensity: f32,
+}
+
+impl Default for SunSettings {
+    fn default() -> Self {
+        Self {
+            azimuth_deg: config::environment::DEFAULT_SUN_AZIMUTH,
+            elevation_deg: config::environment::DEFAULT_SUN_ELEVATION,
+            use_time_of_day: false,
+            time_of_day: config::environment::DEFAULT_TIME_OF_DAY,
+            intensity: config::environment::DEFAULT_SUN_INTENSITY,
+        }
+    }
+}
+
+impl SunSettings {
+    /// Recompute azimuth and elevation from the current time of day
+    ///
+    /// The sun rises in the east at 6:00, peaks at noon with
+    /// `config::environment::MAX_SUN_ELEVATION` and sets in the west at 18:00.
+    pub fn apply_time_of_day(&mut self) {
+        let hours = self.time_of_day.rem_euclid(24.0);
+        // 0.0 at sunrise, 0.5 at noon, 1.0 at sunset
+        let day_phase = (hours - 6.0) / 12.0;
+
+        self.azimuth_deg = 90.0 + day_phase * 180.0;
+        self.elevation_deg = (day_phase * std::f32::consts::PI).sin() * config::environment::MAX_SUN_ELEVATION;
+    }
+
+    /// Direction pointing from the scene towards the sun
+    ///
+    /// # Returns
+    /// Normalized direction vector in world space
+    pub fn direction(&self) -> Vector3<f32> {
+        let azimuth = self.azimuth_deg.to_radians();
+        let elevation = self.elevation_deg.to_radians();
+
+        Vector3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        ).normalize()
+    }
+
+    /// Sun color tint based on elevation
+    ///
+    /// Low sun angles shift towards orange, and the light fades out
+    /// once the sun drops below the horizon.
+    ///
+    /// # Returns
+    /// Linear RGB color (not multiplied by intensity)
+    pub fn tint(&self) -> [f32; 3] {
+        let noon = config::environment::NOON_SUN_COLOR;
+        let horizon = config::environment::HORIZON_SUN_COLOR;
+
+        // 0.0 at the horizon, 1.0 from 30 degrees upward
+        let t = (self.elevation_deg / 30.0).clamp(0.0, 1.0);
+        // Fade to black over the first 6 degrees below the horizon
+        let fade = ((self.elevation_deg + 6.0) / 6.0).clamp(0.0, 1.0);
+
+        [
+            (horizon[0] + (noon[0] - horizon[0]) * t) * fade,
+            (horizon[1] + (noon[1] - horizon[1]) * t) * fade,
+            (horizon[2] + (noon[2] - horizon[2]) * t) * fade,
+        ]
+    }
+}
+
+/// What rays that miss the scene show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackgroundMode {
+    /// The clear color of each viewport
+    SolidColor,
+    /// Vertical blend between two colors
+    Gradient,
+    /// Sky model driven by the sun
+    #[default]
+    ProceduralSky,
+    /// Equirectangular HDR environment map
+    Hdri,
+}
+
+impl BackgroundMode {
+    /// All modes, in UI order
+    pub const ALL: [BackgroundMode; 4] = [
+        BackgroundMode::SolidColor,
+        BackgroundMode::Gradient,
+        BackgroundMode::ProceduralSky,
+        BackgroundMode::Hdri,
+    ];
+
+    /// Readable name for the UI
+    pub fn name(self) -> &'static str {
+        match self {
+            BackgroundMode::SolidColor => "Solid color",
+            BackgroundMode::Gradient => "Gradient",
+            BackgroundMode::ProceduralSky => "Procedural sky",
+            BackgroundMode::Hdri => "HDRI",
+        }
+    }
+
+    /// Value of the `BACKGROUND_*` defines in sdf.frag
+    pub fn as_gpu(self) -> i32 {
+        match self {
+            BackgroundMode::SolidColor => 0,
+            BackgroundMode::Gradient => 1,
+            BackgroundMode::ProceduralSky => 2,
+            BackgroundMode::Hdri => 3,
+        }
+    }
+}
+
+/// Background and environment lighting settings
+///
+/// Colors are linear RGB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackgroundSettings {
+    /// Selected background
+    pub mode: BackgroundMode,
+
+    /// Gradient color straight up
+    pub gradient_top: [f32; 3],
+
+    /// Gradient color straight down
+    pub gradient_bottom: [f32; 3],
+
+    /// Radiance `.hdr` file of the HDRI mode, None until one is picked
+    pub hdri_path: Option<PathBuf>,
+
+    /// Brightness multiplier of the environment map
+    pub hdri_intensity: f32,
+
+    /// Rotation of the environment map around the Y axis in degrees
+    pub hdri_rotation_deg: f32,
+
+    /// Whether shiny surfaces reflect the background
+    pub reflections: bool,
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            mode: BackgroundMode::default(),
+            gradient_top: config::environment::DEFAULT_GRADIENT_TOP,
+            gradient_bottom: config::environment::DEFAULT_GRADIENT_BOTTOM,
+            hdri_path: None,
+            hdri_intensity: 1.0,
+            hdri_rotation_deg: 0.0,
+            reflections: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_is_normalized() {
+        let sun = SunSettings { azimuth_deg: 37.0, elevation_deg: 12.0, ..Default::default() };
+        assert!((sun.direction().magnitude() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_zenith_direction() {
+        let sun = SunSettings { elevation_deg: 90.0, ..Default::default() };
+        let dir = sun.direction();
+        assert!((dir.y - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_time_of_day_noon_and_sunrise() {
+        let mut sun = SunSettings { time_of_day: 12.0, ..Default::default() };
+        sun.apply_time_of_day();
+        assert!((sun.elevation_deg - config::environment::MAX_SUN_ELEVATION).abs() < 1e-4);
+        assert!((sun.azimuth_deg - 180.0).abs() < 1e-4);
+
+        sun.time_of_day = 6.0;
+        sun.apply_time_of_day();
+        assert!(sun.elevation_deg.abs() < 1e-4);
+        assert!((sun.azimuth_deg - 90.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_tint_fades_below_horizon() {
+        let sun = SunSettings { elevation_deg: -10.0, ..Default::default() };
+        assert_eq!(sun.tint(), [0.0, 0.0, 0.0]);
+
+        let sun = SunSettings { elevation_deg: 60.0, ..Default::default() };
+        assert_eq!(sun.tint(), config::environment::NOON_SUN_COLOR);
+    }
+}
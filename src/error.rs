@@ -2,160 +2,458 @@
 //! 
 //! This module defines custom error types for different components of the application,
 //! providing better error context and making debugging easier.
+//!
+//! Errors can be wrapped in a context chain with [`AppError::context`] (or
+//! [`ResultExt::context`]) recording the operation, the subsystem and the
+//! underlying `vk::Result`; [`AppError::with_detail`] attaches values such as
+//! the shader or frame involved. Wrapping keeps the inner error as the
+//! `source()` instead of formatting it into a new string. The chain yields a
+//! short user-facing summary for HUD toasts and the error dialog, and
+//! [`AppError::report`] the full multi-line report for logs and "Copy details".
+//!
+//! Every error has a stable code, shown with the summary so reports can be
+//! searched and matched across versions:
+//!
+//! | Code | Error |
+//! |---|---|
+//! | VK-001 .. VK-013 | `VulkanError` variants, in declaration order |
+//! | VK-100 | Device lost |
+//! | VK-101 | Surface lost |
+//! | WIN-001 .. WIN-003 | `WindowError` variants |
+//! | ECS-001 .. ECS-006 | `EcsError` variants |
+//! | HUD-001 | HUD errors |
+//! | IO-001 | IO errors |
+//! | APP-001 | Generic errors |
 
-use std::fmt;
+use std::fmt::{self, Write as _};
+use ash::vk;
+use thiserror::Error;
 
 /// Custom error type for the entire application
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum AppError {
     /// Vulkan-related errors
-    Vulkan(VulkanError),
+    #[error("Vulkan error: {0}")]
+    Vulkan(#[from] VulkanError),
     
     /// Window-related errors
-    Window(WindowError),
+    #[error("Window error: {0}")]
+    Window(#[from] WindowError),
     
     /// ECS-related errors
-    ECS(EcsError),
+    #[error("ECS error: {0}")]
+    ECS(#[from] EcsError),
     
     /// HUD-related errors
+    #[error("HUD error: {0}")]
     HUD(String),
     
     /// IO-related errors
-    IO(std::io::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
     
     /// Generic errors with custom messages
+    #[error("Error: {0}")]
     Generic(String),
+    
+    /// An error wrapped with the operation that failed
+    #[error(transparent)]
+    Context(Box<ErrorContext>),
+}
+
+/// Subsystem an operation belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)] // Some subsystems are for future error handling
+pub enum Subsystem {
+    /// Instance, device, swapchain and rendering
+    Renderer,
+    /// Shader compilation and hot reload
+    Shaders,
+    /// Window and event loop
+    Window,
+    /// ECS world and systems
+    Ecs,
+    /// ImGui HUD
+    Hud,
+    /// Files and other IO
+    Io,
 }
 
-impl fmt::Display for AppError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Subsystem {
+    /// Name shown to the user
+    pub fn name(self) -> &'static str {
         match self {
-            AppError::Vulkan(err) => write!(f, "Vulkan error: {}", err),
-            AppError::Window(err) => write!(f, "Window error: {}", err),
-            AppError::ECS(err) => write!(f, "ECS error: {}", err),
-            AppError::HUD(msg) => write!(f, "HUD error: {}", msg),
-            AppError::IO(err) => write!(f, "IO error: {}", err),
-            AppError::Generic(msg) => write!(f, "Error: {}", msg),
+            Subsystem::Renderer => "Renderer",
+            Subsystem::Shaders => "Shaders",
+            Subsystem::Window => "Window",
+            Subsystem::Ecs => "ECS",
+            Subsystem::Hud => "HUD",
+            Subsystem::Io => "IO",
         }
     }
 }
 
-impl std::error::Error for AppError {}
+/// One link of an error context chain
+#[derive(Debug, Error)]
+#[error("Failed to {operation}: {source}")]
+pub struct ErrorContext {
+    /// Subsystem the operation belongs to
+    pub subsystem: Subsystem,
+    
+    /// What was being done, e.g. "recreate swapchain"
+    pub operation: String,
+    
+    /// Vulkan result code, if the failure came from a Vulkan call
+    pub vk_result: Option<vk::Result>,
+    
+    /// Explicit user-facing summary, overriding the generated one
+    pub summary: Option<String>,
+    
+    /// Named values describing the failure, e.g. ("shader", "shaders/sdf.frag")
+    pub details: Vec<(&'static str, String)>,
+    
+    /// The wrapped error
+    pub source: AppError,
+}
+
+impl AppError {
+    /// Wrap this error with the operation that failed
+    ///
+    /// # Arguments
+    /// * `subsystem` - Subsystem the operation belongs to
+    /// * `operation` - What was being done, e.g. "recreate swapchain"
+    pub fn context(self, subsystem: Subsystem, operation: impl Into<String>) -> Self {
+        AppError::Context(Box::new(ErrorContext {
+            subsystem,
+            operation: operation.into(),
+            vk_result: None,
+            summary: None,
+            details: Vec::new(),
+            source: self,
+        }))
+    }
+    
+    /// Wrap a failed Vulkan call with the operation that failed
+    ///
+    /// # Arguments
+    /// * `result` - The Vulkan result code
+    /// * `subsystem` - Subsystem the operation belongs to
+    /// * `operation` - What was being done
+    pub fn from_vk(result: vk::Result, subsystem: Subsystem, operation: impl Into<String>) -> Self {
+        AppError::from(result)
+            .context(subsystem, operation)
+            .map_outer_context(|context| context.vk_result = Some(result))
+    }
+    
+    /// Set the user-facing summary of the outermost context
+    ///
+    /// Errors without context are first wrapped in one for their own
+    /// subsystem.
+    pub fn with_summary(self, summary: impl Into<String>) -> Self {
+        let summary = summary.into();
+        self.map_outer_context(|context| context.summary = Some(summary))
+    }
+    
+    /// Attach a named value to the outermost context
+    ///
+    /// Errors without context are first wrapped in one for their own
+    /// subsystem.
+    ///
+    /// # Arguments
+    /// * `key` - What the value is, e.g. "shader" or "frame"
+    /// * `value` - The value
+    pub fn with_detail(self, key: &'static str, value: impl fmt::Display) -> Self {
+        let value = value.to_string();
+        self.map_outer_context(|context| context.details.push((key, value)))
+    }
+    
+    /// Modify the outermost context, wrapping the error in one if needed
+    fn map_outer_context(self, f: impl FnOnce(&mut ErrorContext)) -> Self {
+        let mut error = match self {
+            AppError::Context(_) => self,
+            other => {
+                let subsystem = match other {
+                    AppError::Window(_) => Subsystem::Window,
+                    AppError::ECS(_) => Subsystem::Ecs,
+                    AppError::HUD(_) => Subsystem::Hud,
+                    AppError::IO(_) => Subsystem::Io,
+                    _ => Subsystem::Renderer,
+                };
+                other.context(subsystem, "continue")
+            }
+        };
+        if let AppError::Context(ref mut context) = error {
+            f(context);
+        }
+        error
+    }
+    
+    /// Contexts of the chain, outermost first
+    pub fn contexts(&self) -> impl Iterator<Item = &ErrorContext> {
+        let mut current = Some(self);
+        std::iter::from_fn(move || match current {
+            Some(AppError::Context(context)) => {
+                current = Some(&context.source);
+                Some(context.as_ref())
+            }
+            _ => None,
+        })
+    }
+    
+    /// The innermost error, below all contexts
+    pub fn root(&self) -> &AppError {
+        match self {
+            AppError::Context(context) => context.source.root(),
+            other => other,
+        }
+    }
+    
+    /// The first Vulkan result code recorded in the chain
+    pub fn vk_result(&self) -> Option<vk::Result> {
+        self.contexts().find_map(|context| context.vk_result)
+    }
+    
+    /// Subsystem of the outermost context, if any
+    pub fn subsystem(&self) -> Option<Subsystem> {
+        self.contexts().next().map(|context| context.subsystem)
+    }
+    
+    /// Stable code of the error, see the table in the module docs
+    pub fn code(&self) -> &'static str {
+        match self.vk_result() {
+            Some(vk::Result::ERROR_DEVICE_LOST) => return "VK-100",
+            Some(vk::Result::ERROR_SURFACE_LOST_KHR) => return "VK-101",
+            _ => {}
+        }
+        match self.root() {
+            AppError::Vulkan(err) => err.code(),
+            AppError::Window(err) => err.code(),
+            AppError::ECS(err) => err.code(),
+            AppError::HUD(_) => "HUD-001",
+            AppError::IO(_) => "IO-001",
+            AppError::Generic(_) | AppError::Context(_) => "APP-001",
+        }
+    }
+    
+    /// Whether the application cannot continue after this error
+    ///
+    /// Lost devices and surfaces and failed instance/device/surface creation
+    /// are fatal; everything else is shown as a recoverable notification.
+    pub fn is_fatal(&self) -> bool {
+        if matches!(self.vk_result(), Some(vk::Result::ERROR_DEVICE_LOST | vk::Result::ERROR_SURFACE_LOST_KHR)) {
+            return true;
+        }
+        matches!(
+            self.root(),
+            AppError::Vulkan(VulkanError::InstanceCreation(_))
+                | AppError::Vulkan(VulkanError::DeviceCreation(_))
+                | AppError::Vulkan(VulkanError::SurfaceCreation(_))
+        )
+    }
+    
+    /// Short message for HUD toasts and the error dialog
+    ///
+    /// Uses the outermost explicit summary if there is one, otherwise the
+    /// outermost operation and the Vulkan result code or the root cause.
+    pub fn user_summary(&self) -> String {
+        if let Some(summary) = self.contexts().find_map(|context| context.summary.clone()) {
+            return summary;
+        }
+        match self.contexts().next() {
+            Some(context) => {
+                let reason = match self.vk_result() {
+                    Some(result) => format!("{:?}", result),
+                    None => self.root().to_string(),
+                };
+                format!("{}: failed to {} ({})", context.subsystem.name(), context.operation, reason)
+            }
+            None => self.to_string(),
+        }
+    }
+    
+    /// Full report for logs and "Copy details"
+    ///
+    /// The first line is the code and the user summary, followed by one line
+    /// per context (outermost first) with its details, the root cause and
+    /// the sources below it.
+    pub fn report(&self) -> String {
+        let mut report = format!("[{}] {}", self.code(), self.user_summary());
+        for context in self.contexts() {
+            let _ = write!(report, "\n  in [{}] {}", context.subsystem.name(), context.operation);
+            if let Some(result) = context.vk_result {
+                let _ = write!(report, " ({:?})", result);
+            }
+            for (key, value) in &context.details {
+                let _ = write!(report, "\n    {}: {}", key, value);
+            }
+        }
+        
+        let root = self.root();
+        let mut previous = root.to_string();
+        let _ = write!(report, "\n  cause: {}", previous);
+        let mut source = std::error::Error::source(root);
+        while let Some(error) = source {
+            // Wrappers such as "Vulkan error: ..." already contain their source's text
+            let message = error.to_string();
+            if !previous.contains(&message) {
+                let _ = write!(report, "\n  caused by: {}", message);
+            }
+            previous = message;
+            source = error.source();
+        }
+        report
+    }
+}
 
 /// Vulkan-specific errors
-#[derive(Debug)]
+#[derive(Debug, Error)]
 #[allow(dead_code)] // Some error variants are for future error handling
 pub enum VulkanError {
     /// Instance creation failed
+    #[error("Instance creation failed: {0}")]
     InstanceCreation(String),
     
     /// Device creation failed
+    #[error("Device creation failed: {0}")]
     DeviceCreation(String),
     
     /// Surface creation failed
+    #[error("Surface creation failed: {0}")]
     SurfaceCreation(String),
     
     /// Swapchain creation failed
+    #[error("Swapchain creation failed: {0}")]
     SwapchainCreation(String),
     
     /// Pipeline creation failed
+    #[error("Pipeline creation failed: {0}")]
     PipelineCreation(String),
     
     /// Buffer creation failed
+    #[error("Buffer creation failed: {0}")]
     BufferCreation(String),
     
     /// Memory allocation failed
+    #[error("Memory allocation failed: {0}")]
     MemoryAllocation(String),
     
     /// Shader compilation failed
+    #[error("Shader compilation failed: {0}")]
     ShaderCompilation(String),
     
     /// Command buffer recording failed
+    #[error("Command buffer error: {0}")]
     CommandBuffer(String),
     
     /// Rendering failed
+    #[error("Rendering error: {0}")]
     Rendering(String),
     
+    /// Mesh file could not be parsed
+    #[error("Mesh loading failed: {0}")]
+    MeshLoad(String),
+    
     /// Validation layer error
+    #[error("Validation layer error: {0}")]
     Validation(String),
+    
+    /// Font file could not be read or parsed
+    #[error("Font loading failed: {0}")]
+    FontLoad(String),
 }
 
-impl fmt::Display for VulkanError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl VulkanError {
+    /// Stable code of the error
+    pub fn code(&self) -> &'static str {
         match self {
-            VulkanError::InstanceCreation(msg) => write!(f, "Instance creation failed: {}", msg),
-            VulkanError::DeviceCreation(msg) => write!(f, "Device creation failed: {}", msg),
-            VulkanError::SurfaceCreation(msg) => write!(f, "Surface creation failed: {}", msg),
-            VulkanError::SwapchainCreation(msg) => write!(f, "Swapchain creation failed: {}", msg),
-            VulkanError::PipelineCreation(msg) => write!(f, "Pipeline creation failed: {}", msg),
-            VulkanError::BufferCreation(msg) => write!(f, "Buffer creation failed: {}", msg),
-            VulkanError::MemoryAllocation(msg) => write!(f, "Memory allocation failed: {}", msg),
-            VulkanError::ShaderCompilation(msg) => write!(f, "Shader compilation failed: {}", msg),
-            VulkanError::CommandBuffer(msg) => write!(f, "Command buffer error: {}", msg),
-            VulkanError::Rendering(msg) => write!(f, "Rendering error: {}", msg),
-            VulkanError::Validation(msg) => write!(f, "Validation layer error: {}", msg),
+            VulkanError::InstanceCreation(_) => "VK-001",
+            VulkanError::DeviceCreation(_) => "VK-002",
+            VulkanError::SurfaceCreation(_) => "VK-003",
+            VulkanError::SwapchainCreation(_) => "VK-004",
+            VulkanError::PipelineCreation(_) => "VK-005",
+            VulkanError::BufferCreation(_) => "VK-006",
+            VulkanError::MemoryAllocation(_) => "VK-007",
+            VulkanError::ShaderCompilation(_) => "VK-008",
+            VulkanError::CommandBuffer(_) => "VK-009",
+            VulkanError::Rendering(_) => "VK-010",
+            VulkanError::MeshLoad(_) => "VK-011",
+            VulkanError::Validation(_) => "VK-012",
+            VulkanError::FontLoad(_) => "VK-013",
         }
     }
 }
 
-impl std::error::Error for VulkanError {}
-
 /// Window-related errors
-#[derive(Debug)]
+#[derive(Debug, Error)]
 #[allow(dead_code)] // Some error variants are for future error handling
 pub enum WindowError {
     /// Window creation failed
+    #[error("Window creation failed: {0}")]
     Creation(String),
     
     /// Event loop error
+    #[error("Event loop error: {0}")]
     EventLoop(String),
     
     /// Surface handle error
+    #[error("Surface handle error: {0}")]
     SurfaceHandle(String),
 }
 
-impl fmt::Display for WindowError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl WindowError {
+    /// Stable code of the error
+    pub fn code(&self) -> &'static str {
         match self {
-            WindowError::Creation(msg) => write!(f, "Window creation failed: {}", msg),
-            WindowError::EventLoop(msg) => write!(f, "Event loop error: {}", msg),
-            WindowError::SurfaceHandle(msg) => write!(f, "Surface handle error: {}", msg),
+            WindowError::Creation(_) => "WIN-001",
+            WindowError::EventLoop(_) => "WIN-002",
+            WindowError::SurfaceHandle(_) => "WIN-003",
         }
     }
 }
 
-impl std::error::Error for WindowError {}
-
 /// ECS-related errors
-#[derive(Debug)]
+#[derive(Debug, Error)]
 #[allow(dead_code)] // Some error variants are for future error handling
 pub enum EcsError {
     /// World initialization failed
+    #[error("World initialization failed: {0}")]
     WorldInitialization(String),
     
     /// System execution failed
+    #[error("System execution failed: {0}")]
     SystemExecution(String),
     
     /// Resource access failed
+    #[error("Resource access failed: {0}")]
     ResourceAccess(String),
     
     /// Entity creation failed
+    #[error("Entity creation failed: {0}")]
     EntityCreation(String),
+    
+    /// Scene file could not be parsed
+    #[error("Invalid scene file: {0}")]
+    SceneParse(String),
+    
+    /// Script failed to compile or run
+    #[error("Script error: {0}")]
+    Script(String),
 }
 
-impl fmt::Display for EcsError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl EcsError {
+    /// Stable code of the error
+    pub fn code(&self) -> &'static str {
         match self {
-            EcsError::WorldInitialization(msg) => write!(f, "World initialization failed: {}", msg),
-            EcsError::SystemExecution(msg) => write!(f, "System execution failed: {}", msg),
-            EcsError::ResourceAccess(msg) => write!(f, "Resource access failed: {}", msg),
-            EcsError::EntityCreation(msg) => write!(f, "Entity creation failed: {}", msg),
+            EcsError::WorldInitialization(_) => "ECS-001",
+            EcsError::SystemExecution(_) => "ECS-002",
+            EcsError::ResourceAccess(_) => "ECS-003",
+            EcsError::EntityCreation(_) => "ECS-004",
+            EcsError::SceneParse(_) => "ECS-005",
+            EcsError::Script(_) => "ECS-006",
         }
     }
 }
 
-impl std::error::Error for EcsError {}
-
 // Conversion from ash::vk::Result to our custom error type
 impl From<ash::vk::Result> for AppError {
     fn from(result: ash::vk::Result) -> Self {
@@ -180,10 +478,11 @@ impl From<ash::vk::Result> for AppError {
     }
 }
 
-// Conversion from std::io::Error to our custom error type
-impl From<std::io::Error> for AppError {
-    fn from(err: std::io::Error) -> Self {
-        AppError::IO(err)
+// Conversion from OpenXR results to our custom error type
+#[cfg(feature = "xr")]
+impl From<openxr::sys::Result> for AppError {
+    fn from(result: openxr::sys::Result) -> Self {
+        AppError::Generic(format!("OpenXR error: {}", result))
     }
 }
 
@@ -194,27 +493,6 @@ impl From<Box<dyn std::error::Error>> for AppError {
     }
 }
 
-// Conversion from VulkanError to AppError
-impl From<VulkanError> for AppError {
-    fn from(err: VulkanError) -> Self {
-        AppError::Vulkan(err)
-    }
-}
-
-// Conversion from EcsError to AppError
-impl From<EcsError> for AppError {
-    fn from(err: EcsError) -> Self {
-        AppError::ECS(err)
-    }
-}
-
-// Conversion from WindowError to AppError
-impl From<WindowError> for AppError {
-    fn from(err: WindowError) -> Self {
-        AppError::Window(err)
-    }
-}
-
 // Conversion from winit::error::EventLoopError to AppError
 impl From<winit::error::EventLoopError> for AppError {
     fn from(err: winit::error::EventLoopError) -> Self {
@@ -232,6 +510,49 @@ impl From<winit::error::OsError> for AppError {
 /// Result type alias for our application
 pub type Result<T> = std::result::Result<T, AppError>;
 
+/// Attach context to any result whose error converts into `AppError`
+pub trait ResultExt<T> {
+    /// Wrap the error with the operation that failed
+    ///
+    /// # Arguments
+    /// * `subsystem` - Subsystem the operation belongs to
+    /// * `operation` - What was being done, e.g. "recreate swapchain"
+    fn context(self, subsystem: Subsystem, operation: impl Into<String>) -> Result<T>;
+    
+    /// Attach a named value to the outermost context of the error
+    ///
+    /// # Arguments
+    /// * `key` - What the value is, e.g. "shader" or "frame"
+    /// * `value` - The value
+    fn detail(self, key: &'static str, value: impl fmt::Display) -> Result<T>;
+}
+
+impl<T, E: Into<AppError>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, subsystem: Subsystem, operation: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(subsystem, operation))
+    }
+    
+    fn detail(self, key: &'static str, value: impl fmt::Display) -> Result<T> {
+        self.map_err(|e| e.into().with_detail(key, value))
+    }
+}
+
+/// Attach context to raw Vulkan results, keeping the `vk::Result` code
+pub trait VkResultExt<T> {
+    /// Wrap the error with the operation that failed and its result code
+    ///
+    /// # Arguments
+    /// * `subsystem` - Subsystem the operation belongs to
+    /// * `operation` - What was being done
+    fn vk_context(self, subsystem: Subsystem, operation: impl Into<String>) -> Result<T>;
+}
+
+impl<T> VkResultExt<T> for std::result::Result<T, vk::Result> {
+    fn vk_context(self, subsystem: Subsystem, operation: impl Into<String>) -> Result<T> {
+        self.map_err(|result| AppError::from_vk(result, subsystem, operation))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,4 +652,73 @@ mod tests {
         let err_result: Result<String> = Err(AppError::Generic("error".to_string()));
         assert!(err_result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_context_chain() {
+        let result: std::result::Result<(), vk::Result> = Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY);
+        let err = result
+            .vk_context(Subsystem::Renderer, "allocate scene buffer")
+            .context(Subsystem::Renderer, "recreate swapchain")
+            .unwrap_err();
+
+        assert_eq!(err.contexts().count(), 2);
+        assert_eq!(err.vk_result(), Some(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY));
+        assert_eq!(err.subsystem(), Some(Subsystem::Renderer));
+        assert!(matches!(err.root(), AppError::Vulkan(VulkanError::MemoryAllocation(_))));
+        assert_eq!(
+            err.to_string(),
+            "Failed to recreate swapchain: Failed to allocate scene buffer: Vulkan error: Memory allocation failed: Out of device memory"
+        );
+        assert_eq!(err.user_summary(), "Renderer: failed to recreate swapchain (ERROR_OUT_OF_DEVICE_MEMORY)");
+        assert!(!err.is_fatal());
+    }
+
+    #[test]
+    fn test_summary_and_fatal_errors() {
+        let err = AppError::from_vk(vk::Result::ERROR_DEVICE_LOST, Subsystem::Renderer, "submit frame")
+            .with_summary("The GPU stopped responding");
+        assert_eq!(err.user_summary(), "The GPU stopped responding");
+        assert!(err.is_fatal());
+
+        let plain = AppError::Generic("oops".to_string());
+        assert_eq!(plain.user_summary(), "Error: oops");
+        assert!(!plain.is_fatal());
+    }
+
+    #[test]
+    fn test_codes_and_details() {
+        assert_eq!(AppError::from(VulkanError::ShaderCompilation("syntax".to_string())).code(), "VK-008");
+        assert_eq!(AppError::from(EcsError::Script("boom".to_string())).code(), "ECS-006");
+        assert_eq!(AppError::Generic("oops".to_string()).code(), "APP-001");
+        assert_eq!(AppError::from_vk(vk::Result::ERROR_DEVICE_LOST, Subsystem::Renderer, "submit frame").code(), "VK-100");
+
+        let err: std::result::Result<(), VulkanError> = Err(VulkanError::ShaderCompilation("syntax error".to_string()));
+        let err = err
+            .context(Subsystem::Shaders, "compile shader")
+            .detail("shader", "shaders/sdf.frag")
+            .context(Subsystem::Renderer, "draw frame")
+            .detail("frame", 42)
+            .unwrap_err();
+        let contexts: Vec<_> = err.contexts().collect();
+        assert_eq!(contexts[0].details, vec![("frame", "42".to_string())]);
+        assert_eq!(contexts[1].details, vec![("shader", "shaders/sdf.frag".to_string())]);
+        // Display still reads as one line, the chain stays reachable through `source()`
+        assert_eq!(
+            err.to_string(),
+            "Failed to draw frame: Failed to compile shader: Vulkan error: Shader compilation failed: syntax error"
+        );
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn test_report_lists_contexts_and_causes() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let err = AppError::from(io_err)
+            .context(Subsystem::Io, "read settings")
+            .with_detail("path", "settings.toml");
+        assert_eq!(
+            err.report(),
+            "[IO-001] IO: failed to read settings (IO error: missing)\n  in [IO] read settings\n    path: settings.toml\n  cause: IO error: missing"
+        );
+    }
+}
@@ -0,0 +1,425 @@
+//! Debounced file watching
+//!
+//! `FileWatcher<T>` wraps a notify watcher with the coalescing debounce used
+//! for shader hot reload: every event restarts a per-file timer, and a file
+//! is reported once it has been quiet for the debounce interval. Editors
+//! that save by writing twice, or by writing a temporary file and renaming
+//! it, produce a burst of events that ends up as exactly one change. A
+//! classifier maps each changed path to a `T` (e.g. a shader kind) or
+//! filters it out; changes go to a handler running on the watcher's
+//! debounce thread, typically pushing into a `ChangeQueue` drained by the
+//! main loop.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use log::{debug, error, info, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::error::{AppError, Result};
+
+/// A debounced change of a watched file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange<T> {
+    /// What the classifier made of the path
+    pub kind: T,
+    /// The changed file
+    pub path: PathBuf,
+}
+
+/// Maps a changed path to its kind, or None to ignore it
+pub type Classifier<T> = Box<dyn Fn(&Path) -> Option<T> + Send + Sync>;
+
+/// Receives debounced changes on the watcher's debounce thread
+pub type ChangeHandler<T> = Box<dyn Fn(FileChange<T>) + Send + Sync>;
+
+/// Per-file quiet timers and the last reported modification times
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    /// Quiet time after the last event before a file is reported
+    interval: Duration,
+    /// Time of the last event per file waiting to be reported
+    pending: HashMap<PathBuf, Instant>,
+    /// Modification time of the last reported change per file
+    file_times: HashMap<PathBuf, SystemTime>,
+}
+
+impl Debouncer {
+    /// Create a debouncer with the given quiet time
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, pending: HashMap::new(), file_times: HashMap::new() }
+    }
+
+    /// Remember a file's current modification time without reporting it
+    pub fn record(&mut self, path: PathBuf, modified: SystemTime) {
+        self.file_times.insert(path, modified);
+    }
+
+    /// Start or restart the quiet timer of a file
+    pub fn touch(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path, now);
+    }
+
+    /// When the next timer runs out, None if no file is waiting
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().min().map(|&last| last + self.interval)
+    }
+
+    /// Take the files that have been quiet for the interval, sorted by path
+    pub fn take_quiet(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut quiet: Vec<PathBuf> = self.pending
+            .iter()
+            .filter(|(_, &last)| now.duration_since(last) >= self.interval)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &quiet {
+            self.pending.remove(path);
+        }
+        quiet.sort();
+        quiet
+    }
+
+    /// Decide whether a quiet file changed since it was last reported, and remember it if so
+    ///
+    /// Filters out events that did not modify the contents, e.g. metadata
+    /// changes. New files are always reported.
+    pub fn accept(&mut self, path: &Path, modified: SystemTime) -> bool {
+        let accepted = self.file_times.get(path) != Some(&modified);
+        if accepted {
+            self.file_times.insert(path.to_path_buf(), modified);
+        }
+        accepted
+    }
+
+    /// Number of files with a recorded modification time
+    pub fn file_count(&self) -> usize {
+        self.file_times.len()
+    }
+}
+
+/// Files at a path: the path itself, or the files in a directory
+///
+/// # Arguments
+/// * `path` - File or directory
+/// * `recursive` - Also list the files in subdirectories
+pub fn list_files(path: &Path, recursive: bool) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+    let mut files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to read directory {:?}: {}", dir, e);
+                continue;
+            }
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let entry_path = entry.path();
+            // Symlinked directories are not followed, they may form cycles
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    if recursive {
+                        dirs.push(entry_path);
+                    }
+                }
+                Ok(_) => files.push(entry_path),
+                Err(_) => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// State shared with the notify and debounce threads
+struct WatchState<T> {
+    classify: Classifier<T>,
+    debouncer: Mutex<Debouncer>,
+    /// Wakes the debounce thread when a timer starts or the watcher stops
+    wake: Condvar,
+    /// Set when the watcher is dropped
+    stopped: AtomicBool,
+    handler: Mutex<Option<ChangeHandler<T>>>,
+}
+
+impl<T> WatchState<T> {
+    /// Start the quiet timers of the watched paths of one notify event
+    fn handle_event(&self, event: Event) {
+        debug!("File event: {:?}", event);
+        // Opening or reading a file is not a change
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        let now = Instant::now();
+        let mut debouncer = self.debouncer.lock().unwrap();
+        for path in event.paths {
+            if (self.classify)(&path).is_some() {
+                debouncer.touch(path, now);
+            }
+        }
+        drop(debouncer);
+        self.wake.notify_one();
+    }
+
+    /// Report the files whose timers ran out until the watcher stops
+    fn run_debounce(&self) {
+        let mut debouncer = self.debouncer.lock().unwrap();
+        while !self.stopped.load(Ordering::Acquire) {
+            let now = Instant::now();
+            let quiet = debouncer.take_quiet(now);
+            if !quiet.is_empty() {
+                drop(debouncer);
+                for path in quiet {
+                    self.report(path);
+                }
+                debouncer = self.debouncer.lock().unwrap();
+                continue;
+            }
+            debouncer = match debouncer.next_deadline() {
+                Some(deadline) => self.wake.wait_timeout(debouncer, deadline.saturating_duration_since(now)).unwrap().0,
+                None => self.wake.wait(debouncer).unwrap(),
+            };
+        }
+    }
+
+    /// Pass a quiet file to the handler if its contents changed
+    fn report(&self, path: PathBuf) {
+        let Some(kind) = (self.classify)(&path) else {
+            return;
+        };
+        // Deleted or renamed away; the replacement has its own timer
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            return;
+        };
+        let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+        if !self.debouncer.lock().unwrap().accept(&path, modified) {
+            return;
+        }
+        if let Some(handler) = self.handler.lock().unwrap().as_ref() {
+            handler(FileChange { kind, path });
+        }
+    }
+}
+
+/// Notify watcher with debouncing and classification
+pub struct FileWatcher<T> {
+    /// File system watcher
+    watcher: RecommendedWatcher,
+    /// Watched paths
+    roots: Vec<PathBuf>,
+    /// Classifier, debounce state and handler
+    state: Arc<WatchState<T>>,
+    /// Thread reporting files once they are quiet
+    debounce_thread: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> FileWatcher<T> {
+    /// Create a watcher that is not watching anything yet
+    ///
+    /// # Arguments
+    /// * `debounce` - Quiet time after the last event before a file is reported
+    /// * `classify` - Maps changed paths to their kind, None ignores the path
+    ///
+    /// # Errors
+    /// Returns an error if the platform watcher or the debounce thread cannot be created
+    pub fn new(debounce: Duration, classify: impl Fn(&Path) -> Option<T> + Send + Sync + 'static) -> Result<Self> {
+        let state = Arc::new(WatchState {
+            classify: Box::new(classify),
+            debouncer: Mutex::new(Debouncer::new(debounce)),
+            wake: Condvar::new(),
+            stopped: AtomicBool::new(false),
+            handler: Mutex::new(None),
+        });
+
+        let thread_state = Arc::clone(&state);
+        let watcher = RecommendedWatcher::new(
+            move |res: std::result::Result<Event, notify::Error>| match res {
+                Ok(event) => thread_state.handle_event(event),
+                Err(e) => error!("File watcher error: {:?}", e),
+            },
+            notify::Config::default(),
+        ).map_err(|e| AppError::Generic(format!("Failed to create file watcher: {}", e)))?;
+
+        let debounce_state = Arc::clone(&state);
+        let debounce_thread = std::thread::Builder::new()
+            .name("file-watcher-debounce".to_string())
+            .spawn(move || debounce_state.run_debounce())
+            .map_err(|e| AppError::Generic(format!("Failed to start file watcher thread: {}", e)))?;
+
+        Ok(Self { watcher, roots: Vec::new(), state, debounce_thread: Some(debounce_thread) })
+    }
+
+    /// Set the function receiving changes (on the debounce thread)
+    pub fn set_handler(&self, handler: impl Fn(FileChange<T>) + Send + Sync + 'static) {
+        *self.state.handler.lock().unwrap() = Some(Box::new(handler));
+    }
+
+    /// Start watching a file or directory
+    ///
+    /// Files the classifier accepts that already exist are recorded, so
+    /// only later modifications are reported. Recursive watches record the
+    /// files of the whole directory tree.
+    ///
+    /// # Arguments
+    /// * `path` - File or directory to watch
+    /// * `recursive` - Also watch subdirectories
+    ///
+    /// # Errors
+    /// Returns an error if the path cannot be watched
+    pub fn watch(&mut self, path: &Path, recursive: bool) -> Result<()> {
+        let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        self.watcher.watch(path, mode)
+            .map_err(|e| AppError::Generic(format!("Failed to watch {}: {}", path.display(), e)))?;
+        self.record_existing(path, recursive);
+        self.roots.push(path.to_path_buf());
+        info!("Started watching {:?}", path);
+        Ok(())
+    }
+
+    /// Stop watching a path passed to `watch`
+    pub fn unwatch(&mut self, path: &Path) {
+        if let Some(index) = self.roots.iter().position(|root| root == path) {
+            self.roots.remove(index);
+            if let Err(e) = self.watcher.unwatch(path) {
+                warn!("Failed to stop watching {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Number of files with a recorded modification time
+    pub fn file_count(&self) -> usize {
+        self.state.debouncer.lock().unwrap().file_count()
+    }
+
+    /// Record the modification times of the accepted files at a path
+    fn record_existing(&self, path: &Path, recursive: bool) {
+        let files = list_files(path, recursive);
+        let mut debouncer = self.state.debouncer.lock().unwrap();
+        for file in files {
+            if !file.is_file() || (self.state.classify)(&file).is_none() {
+                continue;
+            }
+            if let Ok(modified) = std::fs::metadata(&file).and_then(|metadata| metadata.modified()) {
+                debug!("Initialized file time for: {:?}", file);
+                debouncer.record(file, modified);
+            }
+        }
+    }
+}
+
+impl<T> Drop for FileWatcher<T> {
+    fn drop(&mut self) {
+        {
+            // Set under the lock so the debounce thread cannot miss the wakeup
+            let _debouncer = self.state.debouncer.lock().unwrap();
+            self.state.stopped.store(true, Ordering::Release);
+        }
+        self.state.wake.notify_all();
+        if let Some(thread) = self.debounce_thread.take() {
+            if thread.join().is_err() {
+                error!("File watcher debounce thread panicked");
+            }
+        }
+    }
+}
+
+/// Changes collected on the watcher thread for the main loop
+pub struct ChangeQueue<T> {
+    changes: Arc<Mutex<VecDeque<FileChange<T>>>>,
+}
+
+impl<T> Clone for ChangeQueue<T> {
+    fn clone(&self) -> Self {
+        Self { changes: Arc::clone(&self.changes) }
+    }
+}
+
+impl<T> Default for ChangeQueue<T> {
+    fn default() -> Self {
+        Self { changes: Arc::new(Mutex::new(VecDeque::new())) }
+    }
+}
+
+impl<T: Send + 'static> ChangeQueue<T> {
+    /// Handler for `FileWatcher::set_handler` appending to this queue
+    pub fn handler(&self) -> impl Fn(FileChange<T>) + Send + Sync + 'static {
+        let changes = Arc::clone(&self.changes);
+        move |change| changes.lock().unwrap().push_back(change)
+    }
+
+    /// Take every queued change, oldest first
+    pub fn drain(&self) -> Vec<FileChange<T>> {
+        self.changes.lock().unwrap().drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debouncer_coalesces_bursts() {
+        let interval = Duration::from_millis(200);
+        let mut debouncer = Debouncer::new(interval);
+        let path = PathBuf::from("shaders/sdf.frag");
+        let start = Instant::now();
+        assert_eq!(debouncer.next_deadline(), None);
+
+        // An editor writing twice restarts the timer
+        debouncer.touch(path.clone(), start);
+        debouncer.touch(path.clone(), start + Duration::from_millis(100));
+        assert_eq!(debouncer.next_deadline(), Some(start + Duration::from_millis(300)));
+        assert!(debouncer.take_quiet(start + Duration::from_millis(250)).is_empty());
+        assert_eq!(debouncer.take_quiet(start + Duration::from_millis(300)), [path]);
+        assert!(debouncer.take_quiet(start + Duration::from_secs(1)).is_empty());
+        assert_eq!(debouncer.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_debouncer_accepts_only_modified_files() {
+        let mut debouncer = Debouncer::new(Duration::from_millis(200));
+        let path = Path::new("scenes/default.toml");
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+
+        debouncer.record(path.to_path_buf(), start);
+        assert!(!debouncer.accept(path, start));
+        assert!(debouncer.accept(path, start + Duration::from_millis(10)));
+        assert!(!debouncer.accept(path, start + Duration::from_millis(10)));
+        assert!(debouncer.accept(Path::new("scenes/other.toml"), start));
+        assert_eq!(debouncer.file_count(), 2);
+    }
+
+    #[test]
+    fn test_list_files_walks_subdirectories() {
+        let root = std::env::temp_dir().join(format!("file_watcher_list_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("lib/nested")).unwrap();
+        for file in ["sdf.frag", "lib/noise.glsl", "lib/nested/hash.glsl"] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+
+        let recursive = list_files(&root, true);
+        let top_level = list_files(&root, false);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(recursive, [root.join("lib/nested/hash.glsl"), root.join("lib/noise.glsl"), root.join("sdf.frag")]);
+        assert_eq!(top_level, [root.join("sdf.frag")]);
+        assert_eq!(list_files(Path::new("missing.frag"), true), [PathBuf::from("missing.frag")]);
+    }
+
+    #[test]
+    fn test_change_queue_collects_handler_calls() {
+        let queue = ChangeQueue::default();
+        let handler = queue.handler();
+        handler(FileChange { kind: 1, path: PathBuf::from("a") });
+        handler(FileChange { kind: 2, path: PathBuf::from("b") });
+
+        let kinds: Vec<i32> = queue.drain().into_iter().map(|change| change.kind).collect();
+        assert_eq!(kinds, [1, 2]);
+        assert!(queue.drain().is_empty());
+    }
+}
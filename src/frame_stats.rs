@@ -0,0 +1,111 @@
+//! Frame rate and window title status
+//!
+//! `FrameStats` counts the frames the app runs and turns them into a frame
+//! rate a few times a second (`config::window::STATUS_INTERVAL_MS`). Each
+//! time it does, the ECS world writes a `WindowStatus` into the window title:
+//! the scene file with an asterisk while the scene differs from it, the frame
+//! rate and the GPU. The title is also what the taskbar shows, so the status
+//! stays visible with the HUD hidden or unavailable.
+
+use std::time::{Duration, Instant};
+use crate::config;
+
+/// Frames counted over intervals of `STATUS_INTERVAL_MS`
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    /// Frames since the interval started
+    frames: u32,
+    /// Start of the current interval, None before the first frame
+    since: Option<Instant>,
+    /// Frames per second over the last finished interval
+    fps: Option<f32>,
+}
+
+impl FrameStats {
+    /// Create stats without any frames counted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a frame
+    ///
+    /// # Returns
+    /// The frame rate when an interval just finished, None otherwise
+    pub fn tick(&mut self, now: Instant) -> Option<f32> {
+        let Some(since) = self.since else {
+            self.since = Some(now);
+            return None;
+        };
+        self.frames += 1;
+        let elapsed = now.duration_since(since);
+        if elapsed < Duration::from_millis(config::window::STATUS_INTERVAL_MS) {
+            return None;
+        }
+        let fps = self.frames as f32 / elapsed.as_secs_f32();
+        self.frames = 0;
+        self.since = Some(now);
+        self.fps = Some(fps);
+        Some(fps)
+    }
+
+    /// Frames per second over the last finished interval
+    pub fn fps(&self) -> Option<f32> {
+        self.fps
+    }
+}
+
+/// What the window title shows after the app's name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowStatus {
+    /// Frames per second
+    pub fps: Option<f32>,
+    /// Name of the GPU rendering the scene
+    pub gpu: Option<String>,
+    /// File name of the scene, None for the built-in scene
+    pub scene: Option<String>,
+    /// The scene differs from its file
+    pub unsaved: bool,
+}
+
+impl WindowStatus {
+    /// Window title with this status
+    ///
+    /// # Arguments
+    /// * `base_title` - Title of the app, which may already carry a HUD failure
+    pub fn title(&self, base_title: &str) -> String {
+        let mut title = match self.scene {
+            Some(ref scene) => format!("{}{} - {}", scene, if self.unsaved { "*" } else { "" }, base_title),
+            None => base_title.to_string(),
+        };
+        if let Some(fps) = self.fps {
+            title += &format!(" | {:.0} FPS", fps);
+        }
+        if let Some(ref gpu) = self.gpu {
+            title += &format!(" | {}", gpu);
+        }
+        title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_rate_is_reported_once_per_interval() {
+        let start = Instant::now();
+        let interval = Duration::from_millis(config::window::STATUS_INTERVAL_MS);
+        let mut stats = FrameStats::new();
+        assert_eq!(stats.tick(start), None);
+        for frame in 1..10 {
+            assert_eq!(stats.tick(start + interval * frame / 10), None);
+        }
+        let fps = stats.tick(start + interval).unwrap();
+        assert!((fps - 10.0 / interval.as_secs_f32()).abs() < 1e-3);
+        assert_eq!(stats.fps(), Some(fps));
+
+        let status = WindowStatus { fps: Some(59.6), gpu: Some("Test GPU".to_string()), scene: Some("default.toml".to_string()), unsaved: true };
+        assert_eq!(status.title("Vulkan App"), "default.toml* - Vulkan App | 60 FPS | Test GPU");
+        assert_eq!(WindowStatus::default().title("Vulkan App"), "Vulkan App");
+    }
+}
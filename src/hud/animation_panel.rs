@@ -0,0 +1,142 @@
+//! Animation panel
+//!
+//! Transport controls for the animation players in the scene: play, pause,
+//! scrubbing and playback speed. Commands apply to every player at once;
+//! the position shown is that of the first player. The ECS world refreshes
+//! the status every frame and applies the queued commands.
+
+use imgui::Ui;
+use crate::locale::{panel_title, tr, tr_args};
+
+/// Transport command for the animation players
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationCommand {
+    /// Resume playback, restarting clips that reached their end
+    Play,
+    /// Stop at the current position
+    Pause,
+    /// Jump to a time in seconds
+    Seek(f32),
+    /// Set the playback rate
+    SetSpeed(f32),
+}
+
+/// Snapshot of the animation players
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AnimationStatus {
+    /// Number of entities with an animation player
+    pub players: usize,
+    /// Playback position in seconds
+    pub time: f32,
+    /// Length of the longest clip in seconds
+    pub duration: f32,
+    /// Playback rate
+    pub speed: f32,
+    /// Whether the first player is playing
+    pub playing: bool,
+}
+
+/// Animation panel state
+pub struct AnimationPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest status of the players
+    pub status: AnimationStatus,
+
+    /// Commands waiting to be applied to the ECS world
+    pending_commands: Vec<AnimationCommand>,
+}
+
+impl Default for AnimationPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnimationPanel {
+    /// Create a new animation panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            status: AnimationStatus::default(),
+            pending_commands: Vec::new(),
+        }
+    }
+
+    /// Take the commands queued since the last call
+    pub fn take_pending_commands(&mut self) -> Vec<AnimationCommand> {
+        std::mem::take(&mut self.pending_commands)
+    }
+
+    /// Queue a command, replacing an earlier one of the same kind
+    ///
+    /// Dragging a slider produces a value every frame; only the latest matters.
+    fn queue_command(&mut self, command: AnimationCommand) {
+        self.pending_commands.retain(|queued| std::mem::discriminant(queued) != std::mem::discriminant(&command));
+        self.pending_commands.push(command);
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Animation"))
+            .position([300.0, 570.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 140.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            let status = self.status;
+            if status.players == 0 {
+                ui.text_disabled(tr("animation.empty"));
+                return;
+            }
+
+            if status.playing {
+                if ui.button(tr("animation.pause")) {
+                    self.queue_command(AnimationCommand::Pause);
+                }
+            } else if ui.button(tr("animation.play")) {
+                self.queue_command(AnimationCommand::Play);
+            }
+            ui.same_line();
+            if ui.button(tr("animation.rewind")) {
+                self.queue_command(AnimationCommand::Seek(0.0));
+            }
+            ui.same_line();
+            ui.text(tr_args("animation.players", &[("count", &status.players)]));
+
+            let mut time = status.time;
+            if ui.slider_config(tr("animation.time"), 0.0, status.duration.max(f32::EPSILON))
+                .display_format("%.2f s")
+                .build(&mut time)
+            {
+                self.queue_command(AnimationCommand::Seek(time));
+            }
+
+            let mut speed = status.speed;
+            if ui.slider_config(tr("animation.speed"), 0.0, 4.0).display_format("%.2fx").build(&mut speed) {
+                self.queue_command(AnimationCommand::SetSpeed(speed));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_command_keeps_latest_of_each_kind() {
+        let mut panel = AnimationPanel::new();
+        panel.queue_command(AnimationCommand::Seek(0.5));
+        panel.queue_command(AnimationCommand::Pause);
+        panel.queue_command(AnimationCommand::Seek(1.5));
+
+        assert_eq!(panel.take_pending_commands(), vec![AnimationCommand::Pause, AnimationCommand::Seek(1.5)]);
+        assert!(panel.take_pending_commands().is_empty());
+    }
+}
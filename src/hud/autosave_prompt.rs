@@ -0,0 +1,88 @@
+//! Auto-save restore prompt
+//!
+//! Offers the scene recovered from a session that did not shut down cleanly
+//! (see the `autosave` module). The answer is applied by the world on the
+//! next frame.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use imgui::Ui;
+use crate::locale::{title, tr, tr_args};
+
+/// Answer to the restore prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryChoice {
+    /// Replace the scene with the recovered one
+    Restore(PathBuf),
+    /// Delete the recovered scene
+    Discard(PathBuf),
+}
+
+/// A recovered scene waiting for an answer
+#[derive(Debug, Clone)]
+struct RecoveryOffer {
+    path: PathBuf,
+    /// When it was auto-saved, if the file system knows
+    saved_at: Option<DateTime<Local>>,
+}
+
+/// Restore prompt state
+#[derive(Debug, Default)]
+pub struct AutosavePrompt {
+    offer: Option<RecoveryOffer>,
+    /// Answer given in the UI, waiting to be applied
+    pending_choice: Option<RecoveryChoice>,
+}
+
+impl AutosavePrompt {
+    /// Create a prompt with nothing to offer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer a recovered scene
+    pub fn offer(&mut self, path: &Path) {
+        let saved_at = fs::metadata(path).and_then(|metadata| metadata.modified()).ok().map(DateTime::<Local>::from);
+        self.offer = Some(RecoveryOffer { path: path.to_path_buf(), saved_at });
+    }
+
+    /// Take the answer given since the last call
+    pub fn take_pending_choice(&mut self) -> Option<RecoveryChoice> {
+        self.pending_choice.take()
+    }
+
+    /// Render the prompt while a recovered scene is offered
+    pub fn render(&mut self, ui: &Ui) {
+        let Some(ref offer) = self.offer else {
+            return;
+        };
+        let display_size = ui.io().display_size;
+        let window = ui.window(title("autosave.title", "Restore Scene"))
+            .position([display_size[0] * 0.5, display_size[1] * 0.5], imgui::Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .size([420.0, 0.0], imgui::Condition::Always)
+            .collapsible(false)
+            .bg_alpha(0.95);
+        let mut choice = None;
+        if let Some(_token) = window.begin() {
+            ui.text_wrapped(tr("autosave.question"));
+            if let Some(saved_at) = offer.saved_at {
+                ui.text_disabled(tr_args("autosave.saved_at", &[("time", &saved_at.format("%Y-%m-%d %H:%M:%S"))]));
+            }
+            ui.text_disabled(offer.path.display().to_string());
+            ui.separator();
+            if ui.button(tr("autosave.restore")) {
+                choice = Some(RecoveryChoice::Restore(offer.path.clone()));
+            }
+            ui.same_line();
+            if ui.button(tr("autosave.discard")) {
+                choice = Some(RecoveryChoice::Discard(offer.path.clone()));
+            }
+        }
+        if choice.is_some() {
+            self.offer = None;
+            self.pending_choice = choice;
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Background panel for choosing what rays that miss the scene show
+//!
+//! Switches between a solid color, a vertical gradient, the procedural sky
+//! and an HDRI environment map picked from a Radiance `.hdr` file, and
+//! toggles reflections of the background on shiny surfaces.
+
+use std::path::PathBuf;
+use imgui::Ui;
+use log::debug;
+use crate::color;
+use crate::environment::{BackgroundMode, BackgroundSettings};
+use crate::platform::dialogs::{DialogOptions, DialogSlot};
+use crate::project;
+use crate::locale::{named, panel_title, tr};
+
+/// Background panel state
+pub struct BackgroundPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Current background settings edited by the panel
+    pub background: BackgroundSettings,
+
+    /// Environment map file the renderer has loaded, shown as status
+    pub loaded_map: Option<PathBuf>,
+
+    /// Whether the renderer is still uploading an environment map
+    pub map_loading: bool,
+
+    /// Environment map file dialog
+    dialog: DialogSlot<()>,
+}
+
+impl Default for BackgroundPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundPanel {
+    /// Create a new background panel with default settings
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            background: BackgroundSettings::default(),
+            loaded_map: None,
+            map_loading: false,
+            dialog: DialogSlot::default(),
+        }
+    }
+
+    /// Render the background panel
+    pub fn render(&mut self, ui: &Ui) {
+        if let Some(((), path)) = self.dialog.poll() {
+            debug!("Environment map chosen: {}", path.display());
+            self.background.hdri_path = Some(path);
+            self.background.mode = BackgroundMode::Hdri;
+        }
+
+        if !self.is_visible {
+            return;
+        }
+
+        let display_size = ui.io().display_size;
+        let window = ui.window(panel_title("Background"))
+            .position([display_size[0] - 260.0, 420.0], imgui::Condition::FirstUseEver)
+            .size([250.0, 260.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            let background = &mut self.background;
+
+            let mut mode_index = BackgroundMode::ALL.iter().position(|&m| m == background.mode).unwrap_or(0);
+            if ui.combo(tr("background.mode"), &mut mode_index, &BackgroundMode::ALL, |mode| named("background_modes", mode.name()).into()) {
+                background.mode = BackgroundMode::ALL[mode_index];
+            }
+
+            match background.mode {
+                BackgroundMode::SolidColor => {
+                    ui.text_disabled(tr("background.color_hint"));
+                }
+                BackgroundMode::Gradient => {
+                    edit_linear_color(ui, &tr("background.top"), &mut background.gradient_top);
+                    edit_linear_color(ui, &tr("background.bottom"), &mut background.gradient_bottom);
+                }
+                BackgroundMode::ProceduralSky => {
+                    ui.text_disabled(tr("background.sky_hint"));
+                }
+                BackgroundMode::Hdri => {
+                    if ui.button(tr("background.open_hdri")) {
+                        let mut options = DialogOptions::open_file(&tr("background.open_hdri_dialog")).filter(&tr("background.hdr_filter"), &["hdr"]);
+                        // Start at the current map, else in the project's textures
+                        let texture_dir = project::current().texture_dirs().into_iter().find(|dir| dir.is_dir());
+                        if let Some(path) = background.hdri_path.clone().or(texture_dir) {
+                            options = options.directory(path);
+                        }
+                        self.dialog.open((), options);
+                    }
+                    ui.same_line();
+                    if ui.button(tr("background.clear")) {
+                        background.hdri_path = None;
+                    }
+
+                    let status = match (&self.loaded_map, self.map_loading) {
+                        (_, true) => tr("background.loading").to_string(),
+                        (Some(path), false) => path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                        (None, false) => tr("background.no_map").to_string(),
+                    };
+                    ui.text_wrapped(status);
+
+                    ui.slider(tr("background.intensity"), 0.0, 8.0, &mut background.hdri_intensity);
+                    ui.slider_config(tr("background.rotation"), -180.0, 180.0)
+                        .display_format("%.0f deg")
+                        .build(&mut background.hdri_rotation_deg);
+                }
+            }
+
+            ui.separator();
+            ui.checkbox(tr("background.reflections"), &mut background.reflections);
+        }
+    }
+}
+
+/// Edit a linear color with an ImGui color picker, which works in sRGB
+///
+/// # Returns
+/// True if the color was changed
+pub(crate) fn edit_linear_color(ui: &Ui, label: &str, linear: &mut [f32; 3]) -> bool {
+    let mut srgb = linear.map(color::linear_to_srgb);
+    if ui.color_edit3(label, &mut srgb) {
+        *linear = srgb.map(color::srgb_to_linear);
+        return true;
+    }
+    false
+}
@@ -0,0 +1,135 @@
+//! Camera Bookmarks panel
+//!
+//! A dropdown of the scene's camera bookmarks that moves the camera to the
+//! chosen one, and buttons to bookmark the current view, move a bookmark to
+//! it or delete one. The edits are applied and saved to the scene file by
+//! the ECS world on the next frame.
+
+use std::path::PathBuf;
+use imgui::Ui;
+use crate::camera::CameraBookmark;
+use crate::config;
+use crate::locale::{panel_title, tr, tr_args};
+
+/// Bookmark edit asked for in the panel
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookmarkRequest {
+    /// Move the camera to a bookmark
+    GoTo(usize),
+    /// Bookmark the current view under a name, a generated one when empty
+    Add(String),
+    /// Move a bookmark to the current view
+    Update(usize),
+    /// Remove a bookmark
+    Delete(usize),
+}
+
+/// Camera Bookmarks panel state
+pub struct CameraBookmarksPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Bookmarks of the scene
+    pub bookmarks: Vec<CameraBookmark>,
+
+    /// Scene file the bookmarks are saved in, None for the built-in scene
+    pub scene_file: Option<PathBuf>,
+
+    /// Bookmark chosen in the dropdown
+    selected: Option<usize>,
+
+    /// Name typed for the next bookmark
+    name: String,
+
+    /// Edits waiting to be applied
+    pending_requests: Vec<BookmarkRequest>,
+}
+
+impl Default for CameraBookmarksPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraBookmarksPanel {
+    /// Create a new camera bookmarks panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            bookmarks: Vec::new(),
+            scene_file: None,
+            selected: None,
+            name: String::new(),
+            pending_requests: Vec::new(),
+        }
+    }
+
+    /// Take the edits asked for since the last call
+    pub fn take_pending_requests(&mut self) -> Vec<BookmarkRequest> {
+        std::mem::take(&mut self.pending_requests)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Camera Bookmarks"))
+            .position([320.0, 420.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 200.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            self.selected = self.selected.filter(|&index| index < self.bookmarks.len());
+
+            let preview = match self.selected {
+                Some(index) => bookmark_label(index, &self.bookmarks[index]),
+                None if self.bookmarks.is_empty() => tr("bookmarks.none"),
+                None => String::new(),
+            };
+            if let Some(_combo) = ui.begin_combo(tr("bookmarks.bookmark"), preview) {
+                for (index, bookmark) in self.bookmarks.iter().enumerate() {
+                    let _id = ui.push_id_usize(index);
+                    // Choosing the selected bookmark again moves back to it
+                    if ui.selectable_config(bookmark_label(index, bookmark)).selected(self.selected == Some(index)).build() {
+                        self.selected = Some(index);
+                        self.pending_requests.push(BookmarkRequest::GoTo(index));
+                    }
+                }
+            }
+            if let Some(index) = self.selected {
+                if ui.button(tr("bookmarks.update")) {
+                    self.pending_requests.push(BookmarkRequest::Update(index));
+                }
+                ui.same_line();
+                if ui.button(tr("bookmarks.delete")) {
+                    self.pending_requests.push(BookmarkRequest::Delete(index));
+                    self.selected = None;
+                }
+            }
+
+            ui.separator();
+            ui.input_text(tr("bookmarks.name"), &mut self.name).build();
+            if ui.button(tr("bookmarks.add")) {
+                self.pending_requests.push(BookmarkRequest::Add(std::mem::take(&mut self.name)));
+                self.selected = Some(self.bookmarks.len());
+            }
+
+            ui.separator();
+            match self.scene_file {
+                Some(ref path) => ui.text_disabled(tr_args("bookmarks.saved_in", &[("path", &path.display())])),
+                None => ui.text_wrapped(tr("bookmarks.not_saved")),
+            }
+            ui.text_disabled(tr("bookmarks.keys"));
+        }
+    }
+}
+
+/// Dropdown entry of a bookmark, with its number key if it has one
+fn bookmark_label(index: usize, bookmark: &CameraBookmark) -> String {
+    match index < config::camera::BOOKMARK_KEYS {
+        true => format!("{}  {}", index + 1, bookmark.name),
+        false => bookmark.name.clone(),
+    }
+}
@@ -0,0 +1,83 @@
+//! GPU capabilities panel
+//!
+//! Read-only view of the capability report of the selected device: feature
+//! support, limits and the extension list (with a filter, there are often
+//! a few hundred), the same report that is written to the log at startup.
+
+use imgui::Ui;
+use crate::vulkan::capabilities::ReportSection;
+use crate::locale::{title, tr};
+
+/// Color of supported features
+const SUPPORTED_COLOR: [f32; 4] = [0.5, 0.9, 0.5, 1.0];
+
+/// Color of missing features
+const MISSING_COLOR: [f32; 4] = [1.0, 0.5, 0.4, 1.0];
+
+/// Capabilities panel state
+pub struct CapabilitiesPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Report of the selected device
+    pub report: Vec<ReportSection>,
+
+    /// Text the listed extensions must contain
+    pub extension_filter: String,
+}
+
+impl CapabilitiesPanel {
+    /// Create a hidden capabilities panel
+    ///
+    /// # Arguments
+    /// * `report` - Capability report of the device the renderer uses
+    pub fn new(report: Vec<ReportSection>) -> Self {
+        Self {
+            is_visible: false,
+            report,
+            extension_filter: String::new(),
+        }
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(title("capabilities.title", "GPU Capabilities"))
+            .position([570.0, 240.0], imgui::Condition::FirstUseEver)
+            .size([360.0, 420.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            let needle = self.extension_filter.trim().to_lowercase();
+            for section in &self.report {
+                // Extension entries have no value, every other section is name: value
+                let is_list = section.entries.iter().all(|(_, value)| value.is_empty());
+                let flags = if is_list { imgui::TreeNodeFlags::empty() } else { imgui::TreeNodeFlags::DEFAULT_OPEN };
+                if !ui.collapsing_header(&section.title, flags) {
+                    continue;
+                }
+
+                if is_list {
+                    ui.input_text(tr("capabilities.filter"), &mut self.extension_filter).build();
+                    for (name, _) in section.entries.iter().filter(|(name, _)| name.to_lowercase().contains(&needle)) {
+                        ui.text(name);
+                    }
+                    continue;
+                }
+
+                for (name, value) in &section.entries {
+                    ui.text_disabled(name);
+                    ui.same_line_with_pos(180.0);
+                    match value.as_str() {
+                        "yes" => ui.text_colored(SUPPORTED_COLOR, value),
+                        "no" => ui.text_colored(MISSING_COLOR, value),
+                        _ => ui.text(value),
+                    }
+                }
+            }
+        }
+    }
+}
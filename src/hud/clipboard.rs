@@ -0,0 +1,31 @@
+//! System clipboard for ImGui
+//!
+//! Without a backend ImGui keeps copied text to itself; this one hands it to
+//! the system clipboard so "Copy details" and text fields work with other
+//! applications.
+
+use copypasta::{ClipboardContext, ClipboardProvider};
+use imgui::ClipboardBackend;
+use log::warn;
+
+/// ImGui clipboard backend using the system clipboard
+pub struct SystemClipboard(ClipboardContext);
+
+impl SystemClipboard {
+    /// Open the system clipboard, None if it is unavailable
+    pub fn new() -> Option<Self> {
+        ClipboardContext::new().ok().map(SystemClipboard)
+    }
+}
+
+impl ClipboardBackend for SystemClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.0.get_contents().ok()
+    }
+
+    fn set(&mut self, value: &str) {
+        if let Err(e) = self.0.set_contents(value.to_owned()) {
+            warn!("Failed to set the clipboard: {}", e);
+        }
+    }
+}
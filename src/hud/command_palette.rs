@@ -0,0 +1,203 @@
+//! Command palette (Ctrl+P)
+//!
+//! A fuzzy-searchable list of every registered action, plus showing and
+//! hiding the HUD panels. Actions are not run here: the chosen one waits in
+//! the palette until `main.rs` takes it and runs it exactly like the key
+//! binding would.
+
+use std::cmp::Reverse;
+use std::sync::Arc;
+use imgui::{Key, Ui};
+use crate::actions::{fuzzy_score, Action, ActionRegistry};
+use crate::hud::toolbar::ScreenRect;
+use crate::locale::{named, panel_name, title, tr, tr_args};
+
+/// One line of the palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PaletteItem {
+    /// A registered action, by index into the registry
+    Action(usize),
+    /// Show or hide a HUD panel, by index into the panel list
+    Panel(usize),
+}
+
+/// Command palette state
+pub struct CommandPalette {
+    /// Whether the palette is open
+    pub is_open: bool,
+
+    /// Actions listed in the palette, shared with the key bindings
+    actions: Arc<ActionRegistry>,
+
+    /// Search text
+    query: String,
+
+    /// Highlighted line of the filtered list
+    selected: usize,
+
+    /// Give the search field keyboard focus on the next frame
+    focus_query: bool,
+
+    /// Action chosen in the palette, waiting to be run
+    pending_action: Option<Action>,
+}
+
+impl CommandPalette {
+    /// Create a closed palette listing the actions of `actions`
+    pub fn new(actions: Arc<ActionRegistry>) -> Self {
+        Self {
+            is_open: false,
+            actions,
+            query: String::new(),
+            selected: 0,
+            focus_query: false,
+            pending_action: None,
+        }
+    }
+
+    /// Open the palette with an empty search, or close it
+    pub fn toggle(&mut self) {
+        self.is_open = !self.is_open;
+        if self.is_open {
+            self.query.clear();
+            self.selected = 0;
+            self.focus_query = true;
+        }
+    }
+
+    /// Take the action chosen since the last call
+    pub fn take_pending_action(&mut self) -> Option<Action> {
+        self.pending_action.take()
+    }
+
+    /// Lines matching the search, best match first, and their labels
+    fn matches(&self, panels: &[(&str, &mut bool)]) -> Vec<(PaletteItem, String)> {
+        let actions = self.actions.entries().iter().enumerate().map(|(index, entry)| {
+            (PaletteItem::Action(index), named("actions", entry.name))
+        });
+        let panels = panels.iter().enumerate().map(|(index, (name, visible))| {
+            let key = if **visible { "palette.hide_panel" } else { "palette.show_panel" };
+            (PaletteItem::Panel(index), tr_args(key, &[("name", &panel_name(name))]))
+        });
+
+        let mut scored: Vec<_> = actions
+            .chain(panels)
+            .filter_map(|(item, label)| fuzzy_score(&self.query, &label).map(|score| (score, item, label)))
+            .collect();
+        // Stable, so equal scores keep the registry order
+        scored.sort_by_key(|(score, _, _)| Reverse(*score));
+        scored.into_iter().map(|(_, item, label)| (item, label)).collect()
+    }
+
+    /// Render the palette
+    ///
+    /// # Arguments
+    /// * `ui` - ImGui frame
+    /// * `area` - Screen area not covered by the toolbar
+    /// * `panels` - Title and visibility flag of every panel
+    pub fn render(&mut self, ui: &Ui, area: ScreenRect, panels: &mut [(&str, &mut bool)]) {
+        if !self.is_open {
+            return;
+        }
+
+        let center = (area.min[0] + area.max[0]) * 0.5;
+        let window = ui.window(title("palette.title", "Command Palette"))
+            .position([center, area.min[1] + 40.0], imgui::Condition::Always)
+            .position_pivot([0.5, 0.0])
+            .size([480.0, 0.0], imgui::Condition::Always)
+            .title_bar(false)
+            .resizable(false)
+            .movable(false)
+            .collapsible(false)
+            .bg_alpha(0.97);
+
+        let mut chosen = None;
+        if let Some(_token) = window.begin() {
+            if std::mem::take(&mut self.focus_query) {
+                ui.set_keyboard_focus_here();
+            }
+            ui.set_next_item_width(-1.0);
+            if ui.input_text("##query", &mut self.query).hint(tr("palette.hint")).build() {
+                self.selected = 0;
+            }
+
+            let matches = self.matches(panels);
+            if ui.is_key_pressed(Key::DownArrow) {
+                self.selected += 1;
+            }
+            if ui.is_key_pressed(Key::UpArrow) {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            self.selected = self.selected.min(matches.len().saturating_sub(1));
+            if ui.is_key_pressed(Key::Enter) || ui.is_key_pressed(Key::KeypadEnter) {
+                chosen = matches.get(self.selected).map(|(item, _)| *item);
+            }
+            if ui.is_key_pressed(Key::Escape) {
+                self.is_open = false;
+            }
+
+            ui.child_window("##results").size([0.0, 240.0]).build(|| {
+                if matches.is_empty() {
+                    ui.text_disabled(tr("palette.no_matches"));
+                }
+                for (index, (item, label)) in matches.iter().enumerate() {
+                    let selected = index == self.selected;
+                    if ui.selectable_config(label).selected(selected).build() {
+                        chosen = Some(*item);
+                    }
+                    if selected && (ui.is_key_pressed(Key::DownArrow) || ui.is_key_pressed(Key::UpArrow)) {
+                        ui.set_scroll_here_y();
+                    }
+
+                    let shortcut = match item {
+                        PaletteItem::Action(action) => self.actions.entries()[*action].binding.map(|chord| chord.label()),
+                        PaletteItem::Panel(_) => None,
+                    };
+                    if let Some(shortcut) = shortcut {
+                        ui.same_line_with_pos(ui.content_region_max()[0] - ui.calc_text_size(&shortcut)[0]);
+                        ui.text_disabled(shortcut);
+                    }
+                }
+            });
+        }
+
+        match chosen {
+            Some(PaletteItem::Action(index)) => {
+                self.pending_action = Some(self.actions.entries()[index].action);
+                self.is_open = false;
+            }
+            Some(PaletteItem::Panel(index)) => {
+                let (_, visible) = &mut panels[index];
+                **visible = !**visible;
+                self.is_open = false;
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_rank_actions_and_panels() {
+        let mut palette = CommandPalette::new(Arc::new(ActionRegistry::new()));
+        let (mut stats, mut console) = (true, false);
+        let panels = [("Stats", &mut stats), ("Console", &mut console)];
+
+        palette.query = "stats".to_string();
+        let matches = palette.matches(&panels);
+        assert_eq!(matches[0], (PaletteItem::Panel(0), "Hide Stats Panel".to_string()));
+
+        palette.query = "rel sh".to_string();
+        let matches = palette.matches(&panels);
+        let (PaletteItem::Action(index), _) = matches[0] else {
+            panic!("expected an action first, got {:?}", matches[0]);
+        };
+        assert_eq!(palette.actions.entries()[index].action, Action::ReloadShaders);
+
+        palette.query.clear();
+        assert_eq!(palette.matches(&panels).len(), palette.actions.entries().len() + 2);
+    }
+}
@@ -0,0 +1,138 @@
+//! Log console panel
+//!
+//! Shows the records captured by the in-memory log sink, newest at the
+//! bottom, with a minimum severity filter and a case-insensitive text search
+//! over message and module, so warnings are visible without a terminal.
+
+use imgui::{ListClipper, Ui};
+use log::Level;
+use crate::log_sinks::{console_log, LogEntry};
+use crate::locale::{panel_title, tr};
+
+/// Severities selectable as the minimum shown level, most severe first
+const LEVELS: [Level; 5] = [Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace];
+
+/// Log console panel state
+pub struct ConsolePanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Least severe level shown
+    pub min_level: Level,
+
+    /// Text the shown records must contain
+    pub search: String,
+
+    /// Keep the view scrolled to the newest record
+    pub auto_scroll: bool,
+}
+
+impl Default for ConsolePanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsolePanel {
+    /// Create a new console panel showing info and more severe records
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            min_level: Level::Info,
+            search: String::new(),
+            auto_scroll: true,
+        }
+    }
+
+    /// Check whether a record passes the severity filter and the search
+    ///
+    /// # Arguments
+    /// * `entry` - The record
+    /// * `needle` - Lowercase search text
+    fn is_shown(&self, entry: &LogEntry, needle: &str) -> bool {
+        entry.level <= self.min_level && entry.matches(needle)
+    }
+
+    /// Text color of a severity
+    fn level_color(level: Level) -> [f32; 4] {
+        match level {
+            Level::Error => [1.0, 0.4, 0.4, 1.0],
+            Level::Warn => [1.0, 0.8, 0.3, 1.0],
+            Level::Info => [0.9, 0.9, 0.9, 1.0],
+            Level::Debug | Level::Trace => [0.6, 0.6, 0.6, 1.0],
+        }
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Console"))
+            .position([20.0, 420.0], imgui::Condition::FirstUseEver)
+            .size([620.0, 260.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            let mut level_index = LEVELS.iter().position(|&level| level == self.min_level).unwrap_or(2);
+            ui.set_next_item_width(90.0);
+            if ui.combo(tr("console.level"), &mut level_index, &LEVELS, |level| level.as_str().into()) {
+                self.min_level = LEVELS[level_index];
+            }
+            ui.same_line();
+            ui.set_next_item_width(200.0);
+            ui.input_text(tr("console.search"), &mut self.search).build();
+            ui.same_line();
+            ui.checkbox(tr("console.auto_scroll"), &mut self.auto_scroll);
+            ui.same_line();
+            if ui.button(tr("console.clear")) {
+                console_log().clear();
+            }
+            ui.separator();
+
+            let needle = self.search.trim().to_lowercase();
+            let entries = console_log().entries();
+            let shown: Vec<&LogEntry> = entries.iter().filter(|entry| self.is_shown(entry, &needle)).collect();
+
+            ui.child_window("console_records").horizontal_scrollbar(true).build(|| {
+                // Only the visible rows are laid out, the buffer holds thousands
+                let clipper = ListClipper::new(shown.len() as i32).begin(ui);
+                for index in clipper.iter() {
+                    let entry = shown[index as usize];
+                    ui.text_disabled(&entry.timestamp);
+                    ui.same_line();
+                    ui.text_colored(
+                        Self::level_color(entry.level),
+                        format!("{:<5} [{}] {}", entry.level, entry.target, entry.message),
+                    );
+                }
+                if self.auto_scroll && ui.scroll_y() >= ui.scroll_max_y() {
+                    ui.set_scroll_here_y_with_ratio(1.0);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_by_level_and_search() {
+        let panel = ConsolePanel { min_level: Level::Warn, ..ConsolePanel::new() };
+        let entry = |level, message: &str| LogEntry {
+            timestamp: String::new(),
+            level,
+            target: "vulkan_app::hud".to_string(),
+            message: message.to_string(),
+        };
+
+        assert!(panel.is_shown(&entry(Level::Error, "Device lost"), ""));
+        assert!(panel.is_shown(&entry(Level::Warn, "Shader reload rejected"), "shader"));
+        assert!(panel.is_shown(&entry(Level::Warn, "Frame over budget"), "hud"));
+        assert!(!panel.is_shown(&entry(Level::Warn, "Frame over budget"), "shader"));
+        assert!(!panel.is_shown(&entry(Level::Info, "Shader reloaded"), "shader"));
+    }
+}
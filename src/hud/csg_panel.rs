@@ -0,0 +1,234 @@
+//! CSG tree editor panel
+//!
+//! Shows the operation tree of every CSG composite in the scene. Operands
+//! can be dragged to reorder them, and the operation type and blend radius
+//! can be changed. The ECS world refreshes the snapshot every frame and
+//! applies the queued edits, so changes preview live in the viewport.
+
+use imgui::{DragDropFlags, TreeNodeFlags, Ui};
+use legion::Entity;
+use log::debug;
+use crate::ecs::components::{CsgOperation, SDFShapeType};
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Drag and drop payload type for operand reordering
+const OPERAND_PAYLOAD: &str = "CSG_OPERAND";
+
+/// Maximum blend radius offered by the slider
+const MAX_BLEND_RADIUS: f32 = 0.5;
+
+/// Snapshot of a single CSG operand
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsgOperandView {
+    /// Operand entity
+    pub entity: Entity,
+    /// Shape type, or None if the operand is not a shape
+    pub shape_type: Option<SDFShapeType>,
+}
+
+/// Snapshot of a CSG composite and its operands
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsgTreeView {
+    /// Composite entity holding the `CsgNode`
+    pub entity: Entity,
+    /// Operation applied between operands
+    pub operation: CsgOperation,
+    /// Smoothing radius
+    pub blend_radius: f32,
+    /// Operands in evaluation order
+    pub operands: Vec<CsgOperandView>,
+}
+
+/// Edit requested from the panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgEdit {
+    /// Change the operation of a composite
+    SetOperation { composite: Entity, operation: CsgOperation },
+    /// Change the blend radius of a composite
+    SetBlendRadius { composite: Entity, blend_radius: f32 },
+    /// Move an operand to a new position
+    MoveOperand { composite: Entity, from: usize, to: usize },
+}
+
+impl CsgEdit {
+    /// Composite entity targeted by the edit
+    pub fn composite(&self) -> Entity {
+        match *self {
+            CsgEdit::SetOperation { composite, .. }
+            | CsgEdit::SetBlendRadius { composite, .. }
+            | CsgEdit::MoveOperand { composite, .. } => composite,
+        }
+    }
+}
+
+/// Operand being dragged (composite index, operand index)
+#[derive(Debug, Clone, Copy)]
+struct OperandPayload {
+    tree: usize,
+    operand: usize,
+}
+
+/// CSG tree editor panel state
+pub struct CsgPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the scene's composites
+    pub trees: Vec<CsgTreeView>,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: Vec<CsgEdit>,
+}
+
+impl Default for CsgPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsgPanel {
+    /// Create a new CSG panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            trees: Vec::new(),
+            pending_edits: Vec::new(),
+        }
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<CsgEdit> {
+        std::mem::take(&mut self.pending_edits)
+    }
+
+    /// Queue an edit and update the local snapshot so the UI reflects it immediately
+    fn queue_edit(&mut self, tree: usize, edit: CsgEdit) {
+        debug!("CSG edit queued: {:?}", edit);
+        let view = &mut self.trees[tree];
+        match edit {
+            CsgEdit::SetOperation { operation, .. } => view.operation = operation,
+            CsgEdit::SetBlendRadius { blend_radius, .. } => view.blend_radius = blend_radius,
+            CsgEdit::MoveOperand { from, to, .. } => {
+                if from < view.operands.len() && to < view.operands.len() {
+                    let operand = view.operands.remove(from);
+                    view.operands.insert(to, operand);
+                }
+            }
+        }
+        self.pending_edits.push(edit);
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("CSG Tree"))
+            .position([10.0, 300.0], imgui::Condition::FirstUseEver)
+            .size([280.0, 260.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if self.trees.is_empty() {
+                ui.text_disabled(tr("csg.empty"));
+                return;
+            }
+
+            for tree in 0..self.trees.len() {
+                self.render_tree(ui, tree);
+            }
+        }
+    }
+
+    /// Render one composite and its operands
+    fn render_tree(&mut self, ui: &Ui, tree: usize) {
+        let composite = self.trees[tree].entity;
+        let _id = ui.push_id_usize(tree);
+
+        let label = format!("{} ({:?})", named("csg_operations", self.trees[tree].operation.name()), composite);
+        let Some(_node) = ui.tree_node_config(&label).flags(TreeNodeFlags::DEFAULT_OPEN).push() else {
+            return;
+        };
+
+        let mut index = CsgOperation::ALL
+            .iter()
+            .position(|&op| op == self.trees[tree].operation)
+            .unwrap_or(0);
+        if ui.combo(tr("csg.operation"), &mut index, &CsgOperation::ALL, |op| named("csg_operations", op.name()).into()) {
+            let operation = CsgOperation::ALL[index];
+            if operation != self.trees[tree].operation {
+                self.queue_edit(tree, CsgEdit::SetOperation { composite, operation });
+            }
+        }
+
+        let mut blend_radius = self.trees[tree].blend_radius;
+        if ui.slider(tr("csg.blend"), 0.0, MAX_BLEND_RADIUS, &mut blend_radius) {
+            self.queue_edit(tree, CsgEdit::SetBlendRadius { composite, blend_radius });
+        }
+
+        ui.text_disabled(tr("csg.drag_hint"));
+
+        let mut moved = None;
+        for (operand_index, operand) in self.trees[tree].operands.iter().enumerate() {
+            let shape_name = match &operand.shape_type {
+                Some(shape_type) => format!("{:?}", shape_type),
+                None => tr("csg.missing_shape"),
+            };
+            let role = tr(if operand_index == 0 { "csg.base" } else { "csg.operand" });
+            ui.selectable(format!("{}. {} ({}) {:?}", operand_index + 1, shape_name, role, operand.entity));
+
+            if let Some(_tooltip) = ui.drag_drop_source_config(OPERAND_PAYLOAD)
+                .begin_payload(OperandPayload { tree, operand: operand_index })
+            {
+                ui.text(tr_args("csg.move", &[("name", &shape_name)]));
+            }
+
+            if let Some(target) = ui.drag_drop_target() {
+                if let Some(Ok(payload)) = target.accept_payload::<OperandPayload, _>(OPERAND_PAYLOAD, DragDropFlags::empty()) {
+                    // Only reorder within the same composite
+                    if payload.data.tree == tree && payload.data.operand != operand_index {
+                        moved = Some((payload.data.operand, operand_index));
+                    }
+                }
+                target.pop();
+            }
+        }
+
+        if let Some((from, to)) = moved {
+            self.queue_edit(tree, CsgEdit::MoveOperand { composite, from, to });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_edit_updates_snapshot() {
+        let mut world = legion::World::default();
+        let composite = world.push((0u32,));
+        let a = world.push((1u32,));
+        let b = world.push((2u32,));
+
+        let mut panel = CsgPanel::new();
+        panel.trees.push(CsgTreeView {
+            entity: composite,
+            operation: CsgOperation::Union,
+            blend_radius: 0.0,
+            operands: vec![
+                CsgOperandView { entity: a, shape_type: Some(SDFShapeType::Box) },
+                CsgOperandView { entity: b, shape_type: Some(SDFShapeType::Sphere) },
+            ],
+        });
+
+        panel.queue_edit(0, CsgEdit::MoveOperand { composite, from: 1, to: 0 });
+        panel.queue_edit(0, CsgEdit::SetOperation { composite, operation: CsgOperation::Subtraction });
+
+        assert_eq!(panel.trees[0].operands[0].entity, b);
+        assert_eq!(panel.trees[0].operation, CsgOperation::Subtraction);
+        assert_eq!(panel.take_pending_edits().len(), 2);
+        assert!(panel.take_pending_edits().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Debug draw panel
+//!
+//! Chooses which entities show their bounding box or an approximate
+//! wireframe, plus a global override that applies one mode to every
+//! entity. The ECS world refreshes the entity list every frame and
+//! applies the queued changes.
+
+use imgui::Ui;
+use legion::Entity;
+use log::debug;
+use crate::ecs::components::{DebugDisplayMode, SDFShapeType};
+use crate::locale::{named, panel_title, tr};
+
+/// Snapshot of an SDF entity for the panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugDrawEntityView {
+    /// The entity
+    pub entity: Entity,
+    /// Shape type, used as the label
+    pub shape_type: SDFShapeType,
+    /// Per-entity overlay mode
+    pub mode: DebugDisplayMode,
+}
+
+/// Debug draw panel state
+pub struct DebugDrawPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Mode applied to every entity, overriding the per-entity setting
+    pub global_override: Option<DebugDisplayMode>,
+
+    /// Latest snapshot of the scene's SDF entities
+    pub entities: Vec<DebugDrawEntityView>,
+
+    /// Per-entity mode changes waiting to be applied to the ECS world
+    pending_modes: Vec<(Entity, DebugDisplayMode)>,
+}
+
+impl Default for DebugDrawPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugDrawPanel {
+    /// Create a new debug draw panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            global_override: None,
+            entities: Vec::new(),
+            pending_modes: Vec::new(),
+        }
+    }
+
+    /// Take the per-entity mode changes queued since the last call
+    pub fn take_pending_modes(&mut self) -> Vec<(Entity, DebugDisplayMode)> {
+        std::mem::take(&mut self.pending_modes)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Debug Draw"))
+            .position([300.0, 70.0], imgui::Condition::FirstUseEver)
+            .size([260.0, 220.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            // Index 0 means "per entity", the rest map onto DebugDisplayMode::ALL
+            let mut override_index = self.global_override
+                .and_then(|mode| DebugDisplayMode::ALL.iter().position(|&m| m == mode))
+                .map(|index| index + 1)
+                .unwrap_or(0);
+            let override_labels: Vec<String> = std::iter::once(tr("debug_draw.per_entity"))
+                .chain(DebugDisplayMode::ALL.iter().map(|mode| named("debug_modes", mode.name())))
+                .collect();
+            if ui.combo_simple_string(tr("debug_draw.global"), &mut override_index, &override_labels) {
+                self.global_override = override_index.checked_sub(1).map(|index| DebugDisplayMode::ALL[index]);
+                debug!("Debug draw global override: {:?}", self.global_override);
+            }
+
+            ui.separator();
+
+            let disabled_token = ui.begin_disabled(self.global_override.is_some());
+            for (index, view) in self.entities.iter_mut().enumerate() {
+                let _id = ui.push_id_usize(index);
+                let mut mode_index = DebugDisplayMode::ALL
+                    .iter()
+                    .position(|&m| m == view.mode)
+                    .unwrap_or(0);
+
+                let label = format!("{:?} {:?}", view.shape_type, view.entity);
+                if ui.combo(&label, &mut mode_index, &DebugDisplayMode::ALL, |mode| named("debug_modes", mode.name()).into()) {
+                    view.mode = DebugDisplayMode::ALL[mode_index];
+                    self.pending_modes.push((view.entity, view.mode));
+                }
+            }
+            disabled_token.end();
+        }
+    }
+}
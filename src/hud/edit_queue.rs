@@ -0,0 +1,56 @@
+//! Edits queued by the editing panels
+//!
+//! Dragging a slider or typing produces a new edit every frame, and only the
+//! latest edit of a component matters by the time the ECS world applies the
+//! queue. Each edit is queued under a key, usually its entity, and replaces
+//! the queued edit with the same key; edits without a key (adding or removing
+//! an entity) are all kept in order.
+
+/// Edits waiting for the world, at most one per key
+#[derive(Debug, Clone)]
+pub struct EditQueue<K, E> {
+    edits: Vec<(Option<K>, E)>,
+}
+
+impl<K, E> Default for EditQueue<K, E> {
+    fn default() -> Self {
+        Self { edits: Vec::new() }
+    }
+}
+
+impl<K: PartialEq, E> EditQueue<K, E> {
+    /// Queue an edit, replacing an earlier edit with the same key
+    ///
+    /// # Arguments
+    /// * `key` - What the edit changes, None to keep every such edit
+    /// * `edit` - The edit
+    pub fn push(&mut self, key: Option<K>, edit: E) {
+        if key.is_some() {
+            self.edits.retain(|(queued, _)| *queued != key);
+        }
+        self.edits.push((key, edit));
+    }
+
+    /// Take the edits queued since the last call, in the order they were queued
+    pub fn take(&mut self) -> Vec<E> {
+        std::mem::take(&mut self.edits).into_iter().map(|(_, edit)| edit).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_latest_edit_of_a_key_is_queued() {
+        let mut queue = EditQueue::default();
+        queue.push(Some(1), "a1");
+        queue.push(Some(2), "b");
+        queue.push(None, "add");
+        queue.push(None, "add");
+        queue.push(Some(1), "a2");
+
+        assert_eq!(queue.take(), ["b", "add", "add", "a2"]);
+        assert!(queue.take().is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Environment panel for controlling the sun and sky
+//!
+//! Provides a polar coordinate widget for picking the sun direction
+//! (azimuth around the circle, elevation towards the center) and an
+//! optional time-of-day slider that drives the sun automatically.
+
+use imgui::Ui;
+use log::debug;
+use crate::color;
+use crate::environment::SunSettings;
+use crate::locale::{panel_title, tr};
+
+/// Radius of the polar sun widget in pixels
+const POLAR_WIDGET_RADIUS: f32 = 70.0;
+
+/// Environment panel state
+pub struct EnvironmentPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Current sun settings edited by the panel
+    pub sun: SunSettings,
+
+    /// Set when the sun settings changed during the last frame
+    changed: bool,
+}
+
+impl Default for EnvironmentPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvironmentPanel {
+    /// Create a new environment panel with default sun settings
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            sun: SunSettings::default(),
+            changed: false,
+        }
+    }
+
+    /// Toggle panel visibility
+    #[allow(dead_code)]
+    pub fn toggle_visibility(&mut self) {
+        self.is_visible = !self.is_visible;
+    }
+
+    /// Check whether the sun settings changed during the last frame
+    #[allow(dead_code)]
+    pub fn was_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Render the environment panel
+    pub fn render(&mut self, ui: &Ui) {
+        self.changed = false;
+
+        if !self.is_visible {
+            return;
+        }
+
+        let display_size = ui.io().display_size;
+        let window = ui.window(panel_title("Environment"))
+            .position([display_size[0] - 260.0, 70.0], imgui::Condition::FirstUseEver)
+            .size([250.0, 340.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            ui.text(tr("environment.sun"));
+            ui.separator();
+
+            let mut changed = false;
+
+            changed |= ui.checkbox(tr("environment.time_of_day"), &mut self.sun.use_time_of_day);
+            if self.sun.use_time_of_day {
+                changed |= ui.slider_config(tr("environment.hour"), 0.0, 24.0)
+                    .display_format("%.1f h")
+                    .build(&mut self.sun.time_of_day);
+                if changed {
+                    self.sun.apply_time_of_day();
+                }
+            }
+
+            changed |= self.render_polar_widget(ui);
+
+            // Manual angle sliders are disabled while time of day drives the sun
+            let disabled_token = ui.begin_disabled(self.sun.use_time_of_day);
+            changed |= ui.slider(tr("environment.azimuth"), 0.0, 360.0, &mut self.sun.azimuth_deg);
+            changed |= ui.slider(tr("environment.elevation"), -90.0, 90.0, &mut self.sun.elevation_deg);
+            disabled_token.end();
+
+            changed |= ui.slider(tr("environment.intensity"), 0.0, 5.0, &mut self.sun.intensity);
+
+            // The tint is linear, ImGui colors are sRGB
+            let tint = self.sun.tint();
+            ui.color_button(tr("environment.sun_tint"), color::linear_to_srgb_rgba([tint[0], tint[1], tint[2], 1.0]));
+            ui.same_line();
+            ui.text(tr("environment.tint"));
+
+            if changed {
+                debug!(
+                    "Sun updated: azimuth={:.1} elevation={:.1} intensity={:.2}",
+                    self.sun.azimuth_deg, self.sun.elevation_deg, self.sun.intensity
+                );
+                self.changed = true;
+            }
+        }
+    }
+
+    /// Draw the polar coordinate widget and handle dragging
+    ///
+    /// The outer circle is the horizon and the center is the zenith.
+    ///
+    /// # Returns
+    /// True if the sun direction was changed by the user
+    fn render_polar_widget(&mut self, ui: &Ui) -> bool {
+        let origin = ui.cursor_screen_pos();
+        let size = POLAR_WIDGET_RADIUS * 2.0;
+        let center = [origin[0] + POLAR_WIDGET_RADIUS, origin[1] + POLAR_WIDGET_RADIUS];
+
+        ui.invisible_button("##SunPolar", [size, size]);
+        let mut changed = false;
+
+        if ui.is_item_active() && !self.sun.use_time_of_day {
+            let mouse = ui.io().mouse_pos;
+            let dx = mouse[0] - center[0];
+            let dy = mouse[1] - center[1];
+            let distance = (dx * dx + dy * dy).sqrt().min(POLAR_WIDGET_RADIUS);
+
+            self.sun.azimuth_deg = dx.atan2(-dy).to_degrees().rem_euclid(360.0);
+            self.sun.elevation_deg = 90.0 * (1.0 - distance / POLAR_WIDGET_RADIUS);
+            changed = true;
+        }
+
+        let draw_list = ui.get_window_draw_list();
+        draw_list.add_circle(center, POLAR_WIDGET_RADIUS, [0.5, 0.5, 0.55, 1.0]).build();
+        draw_list.add_circle(center, POLAR_WIDGET_RADIUS * 0.5, [0.35, 0.35, 0.4, 1.0]).build();
+        draw_list.add_text([center[0] - 3.0, origin[1] + 2.0], [0.7, 0.7, 0.7, 1.0], tr("environment.north"));
+
+        // Sun marker: elevation maps linearly from the rim (0 deg) to the center (90 deg)
+        let azimuth = self.sun.azimuth_deg.to_radians();
+        let radius = POLAR_WIDGET_RADIUS * (1.0 - self.sun.elevation_deg.clamp(0.0, 90.0) / 90.0);
+        let marker = [center[0] + azimuth.sin() * radius, center[1] - azimuth.cos() * radius];
+        let tint = self.sun.tint();
+
+        draw_list.add_line(center, marker, [0.6, 0.6, 0.6, 1.0]).build();
+        draw_list.add_circle(marker, 6.0, color::linear_to_srgb_rgba([tint[0].max(0.3), tint[1].max(0.3), tint[2].max(0.3), 1.0]))
+            .filled(true)
+            .build();
+
+        changed
+    }
+}
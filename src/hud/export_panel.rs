@@ -0,0 +1,142 @@
+//! Frame export panel
+//!
+//! Configures an offline export (time range, frame rate, resolution, PNG
+//! sequence or ffmpeg video, output directory) and starts or cancels it.
+//! While an export runs the ECS world feeds its progress back for the
+//! progress bar.
+
+use imgui::Ui;
+use log::debug;
+use std::path::PathBuf;
+use crate::platform::dialogs::{DialogOptions, DialogSlot};
+use crate::vulkan::frame_export::{ExportFormat, ExportSettings};
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Frame export panel state
+pub struct ExportPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Settings edited in the panel
+    pub settings: ExportSettings,
+
+    /// Output directory as edited in the text field
+    output_dir: String,
+
+    /// Frames written and total frames of the running export, if any
+    pub progress: Option<(usize, usize)>,
+
+    /// Export started in the UI, waiting to be picked up by the ECS world
+    pending_start: Option<ExportSettings>,
+
+    /// Cancel requested in the UI
+    pending_cancel: bool,
+
+    /// Output directory dialog
+    dialog: DialogSlot<()>,
+}
+
+impl Default for ExportPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportPanel {
+    /// Create a new export panel with the default settings
+    pub fn new() -> Self {
+        let settings = ExportSettings::default();
+        Self {
+            is_visible: true,
+            output_dir: settings.output_dir.display().to_string(),
+            settings,
+            progress: None,
+            pending_start: None,
+            pending_cancel: false,
+            dialog: DialogSlot::default(),
+        }
+    }
+
+    /// Replace the output directory, e.g. with the one of a newly opened project
+    pub fn set_output_dir(&mut self, dir: PathBuf) {
+        self.output_dir = dir.display().to_string();
+        self.settings.output_dir = dir;
+    }
+
+    /// Take the export started since the last call
+    pub fn take_pending_start(&mut self) -> Option<ExportSettings> {
+        self.pending_start.take()
+    }
+
+    /// Take a cancel request made since the last call
+    pub fn take_pending_cancel(&mut self) -> bool {
+        std::mem::take(&mut self.pending_cancel)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Export"))
+            .position([300.0, 300.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 240.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(((), dir)) = self.dialog.poll() {
+            self.output_dir = dir.display().to_string();
+        }
+
+        if let Some(_token) = window.begin() {
+            if let Some((written, total)) = self.progress {
+                let fraction = if total == 0 { 1.0 } else { written as f32 / total as f32 };
+                ui.progress_bar(fraction)
+                    .overlay_text(tr_args("export.progress", &[("written", &written), ("total", &total)]))
+                    .build();
+                if ui.button(tr("export.cancel")) {
+                    debug!("Export cancel requested");
+                    self.pending_cancel = true;
+                }
+                return;
+            }
+
+            let settings = &mut self.settings;
+            ui.input_float(tr("export.start"), &mut settings.start).step(0.5).build();
+            ui.input_float(tr("export.end"), &mut settings.end).step(0.5).build();
+            settings.start = settings.start.max(0.0);
+
+            let mut fps = settings.fps as i32;
+            if ui.input_int(tr("export.fps"), &mut fps).build() {
+                settings.fps = fps.clamp(1, 240) as u32;
+            }
+            let mut size = [settings.width as i32, settings.height as i32];
+            if ui.input_int2(tr("export.size"), &mut size).build() {
+                settings.width = size[0].clamp(16, 8192) as u32;
+                settings.height = size[1].clamp(16, 8192) as u32;
+            }
+
+            let mut format_index = ExportFormat::ALL.iter().position(|&f| f == settings.format).unwrap_or(0);
+            if ui.combo(tr("export.format"), &mut format_index, &ExportFormat::ALL, |format| named("export_formats", format.name()).into()) {
+                settings.format = ExportFormat::ALL[format_index];
+            }
+            ui.input_text(tr("export.output"), &mut self.output_dir).build();
+            ui.same_line();
+            if ui.button(tr("export.browse")) {
+                self.dialog.open((), DialogOptions::pick_folder(&tr("export.dir_dialog")).directory(self.output_dir.trim()));
+            }
+
+            ui.separator();
+            let valid = self.settings.end > self.settings.start;
+            ui.text(tr_args("export.frames", &[("count", &if valid { self.settings.frame_count() } else { 0 })]));
+
+            let disabled_token = ui.begin_disabled(!valid);
+            if ui.button(tr("export.export")) {
+                self.settings.output_dir = PathBuf::from(self.output_dir.trim());
+                debug!("Export requested: {:?}", self.settings);
+                self.pending_start = Some(self.settings.clone());
+            }
+            disabled_token.end();
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! HUD fonts
+//!
+//! Builds the ImGui font atlas from a user TTF (or ImGui's built-in font)
+//! at several sizes, with optional extra glyph ranges (Cyrillic, CJK, ...)
+//! and an icon font such as Font Awesome merged into every size. The atlas
+//! can be rebuilt at runtime from the settings window; the HUD then
+//! re-uploads the texture.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use imgui::{FontAtlas, FontConfig, FontGlyphRanges, FontId, FontSource};
+use log::warn;
+use crate::config;
+use crate::error::{Result, ResultExt, Subsystem};
+
+/// Code points taken from the icon font, zero-terminated as ImGui expects
+static ICON_GLYPHS: [u32; 3] = [config::hud_fonts::ICON_RANGE[0], config::hud_fonts::ICON_RANGE[1], 0];
+
+/// Extra glyph ranges loaded from the font file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphRange {
+    Cyrillic,
+    Thai,
+    Vietnamese,
+    Korean,
+    Japanese,
+    ChineseCommon,
+    ChineseFull,
+}
+
+impl GlyphRange {
+    /// All ranges in display order
+    pub const ALL: [GlyphRange; 7] = [
+        GlyphRange::Cyrillic,
+        GlyphRange::Thai,
+        GlyphRange::Vietnamese,
+        GlyphRange::Korean,
+        GlyphRange::Japanese,
+        GlyphRange::ChineseCommon,
+        GlyphRange::ChineseFull,
+    ];
+
+    /// Name used in the settings window and in `config::hud_fonts::GLYPH_RANGES`
+    pub fn name(self) -> &'static str {
+        match self {
+            GlyphRange::Cyrillic => "Cyrillic",
+            GlyphRange::Thai => "Thai",
+            GlyphRange::Vietnamese => "Vietnamese",
+            GlyphRange::Korean => "Korean",
+            GlyphRange::Japanese => "Japanese",
+            GlyphRange::ChineseCommon => "Chinese (common)",
+            GlyphRange::ChineseFull => "Chinese (full)",
+        }
+    }
+
+    /// Find a range by name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|range| range.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// ImGui's table for the range
+    fn glyph_ranges(self) -> FontGlyphRanges {
+        match self {
+            GlyphRange::Cyrillic => FontGlyphRanges::cyrillic(),
+            GlyphRange::Thai => FontGlyphRanges::thai(),
+            GlyphRange::Vietnamese => FontGlyphRanges::vietnamese(),
+            GlyphRange::Korean => FontGlyphRanges::korean(),
+            GlyphRange::Japanese => FontGlyphRanges::japanese(),
+            GlyphRange::ChineseCommon => FontGlyphRanges::chinese_simplified_common(),
+            GlyphRange::ChineseFull => FontGlyphRanges::chinese_full(),
+        }
+    }
+}
+
+/// Which fonts the atlas is built from
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSettings {
+    /// TTF/OTF file, None for ImGui's built-in font
+    pub font_file: Option<PathBuf>,
+    /// Size of the default font in pixels
+    pub size: f32,
+    /// Additional sizes baked into the atlas
+    pub extra_sizes: Vec<f32>,
+    /// Extra glyph ranges taken from the font file
+    pub glyph_ranges: Vec<GlyphRange>,
+    /// Icon font merged into every size, None for no icons
+    pub icon_font_file: Option<PathBuf>,
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        use config::hud_fonts::*;
+        Self {
+            font_file: FONT_FILE.map(PathBuf::from),
+            size: FONT_SIZE,
+            extra_sizes: EXTRA_SIZES.to_vec(),
+            glyph_ranges: GLYPH_RANGES
+                .iter()
+                .filter_map(|name| {
+                    let range = GlyphRange::from_name(name);
+                    if range.is_none() {
+                        warn!("Ignoring unknown glyph range '{}'", name);
+                    }
+                    range
+                })
+                .collect(),
+            icon_font_file: ICON_FONT_FILE.map(PathBuf::from),
+        }
+    }
+}
+
+impl FontSettings {
+    /// Sizes to bake, the default size first, without duplicates
+    pub fn sizes(&self) -> Vec<f32> {
+        let mut sizes = vec![self.size];
+        for &size in &self.extra_sizes {
+            if size > 0.0 && !sizes.iter().any(|&existing| (existing - size).abs() < 0.5) {
+                sizes.push(size);
+            }
+        }
+        sizes
+    }
+}
+
+/// Font file contents read for an atlas build
+#[derive(Debug, Default)]
+pub struct FontData {
+    /// Main font file, None for the built-in font
+    pub main: Option<Vec<u8>>,
+    /// Icon font file
+    pub icons: Option<Vec<u8>>,
+}
+
+impl FontData {
+    /// Read the font files named in the settings
+    ///
+    /// # Errors
+    /// Returns an error if a configured file cannot be read
+    pub fn load(settings: &FontSettings) -> Result<Self> {
+        let read = |path: &Path| fs::read(path).context(Subsystem::Hud, format!("read font {}", path.display()));
+        Ok(Self {
+            main: settings.font_file.as_deref().map(read).transpose()?,
+            icons: settings.icon_font_file.as_deref().map(read).transpose()?,
+        })
+    }
+}
+
+/// Fonts in the built atlas
+#[derive(Debug, Clone, Default)]
+pub struct LoadedFonts {
+    /// Pixel size and ImGui id of every baked size, the default font first
+    pub sizes: Vec<(f32, FontId)>,
+    /// Whether an icon font was merged in
+    pub has_icons: bool,
+}
+
+impl LoadedFonts {
+    /// Font closest to a pixel size, for `Ui::push_font`
+    pub fn closest(&self, size: f32) -> Option<FontId> {
+        self.sizes
+            .iter()
+            .min_by(|(a, _), (b, _)| (a - size).abs().total_cmp(&(b - size).abs()))
+            .map(|&(_, id)| id)
+    }
+}
+
+/// Clear the atlas and add every font size
+///
+/// The first size becomes ImGui's default font. Glyph ranges need a font
+/// file; with the built-in font they are skipped with a warning.
+///
+/// # Arguments
+/// * `atlas` - The context's font atlas
+/// * `settings` - Sizes and glyph ranges
+/// * `data` - Font files read with `FontData::load`
+pub fn add_fonts(atlas: &mut FontAtlas, settings: &FontSettings, data: &FontData) -> LoadedFonts {
+    atlas.clear();
+
+    if data.main.is_none() && !settings.glyph_ranges.is_empty() {
+        warn!("Extra glyph ranges need a font file; the built-in font only covers Latin");
+    }
+
+    let sizes = settings
+        .sizes()
+        .into_iter()
+        .map(|size| {
+            let base_config = FontConfig {
+                size_pixels: size,
+                oversample_h: 2,
+                oversample_v: 1,
+                pixel_snap_h: true,
+                ..FontConfig::default()
+            };
+
+            // Sources after the first are merged into the same font
+            let mut sources = Vec::new();
+            match &data.main {
+                Some(ttf) => {
+                    sources.push(FontSource::TtfData { data: ttf, size_pixels: size, config: Some(base_config.clone()) });
+                    for range in &settings.glyph_ranges {
+                        sources.push(FontSource::TtfData {
+                            data: ttf,
+                            size_pixels: size,
+                            config: Some(FontConfig { glyph_ranges: range.glyph_ranges(), ..base_config.clone() }),
+                        });
+                    }
+                }
+                None => sources.push(FontSource::DefaultFontData { config: Some(base_config.clone()) }),
+            }
+            if let Some(icons) = &data.icons {
+                sources.push(FontSource::TtfData {
+                    data: icons,
+                    size_pixels: size,
+                    config: Some(FontConfig {
+                        glyph_ranges: FontGlyphRanges::from_slice(&ICON_GLYPHS),
+                        // Icons line up with the text baseline at the text's advance
+                        glyph_min_advance_x: size,
+                        ..base_config.clone()
+                    }),
+                });
+            }
+
+            (size, atlas.add_font(&sources))
+        })
+        .collect();
+
+    LoadedFonts { sizes, has_icons: data.icons.is_some() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_put_default_first_without_duplicates() {
+        let settings = FontSettings {
+            size: 16.0,
+            extra_sizes: vec![13.0, 16.2, 0.0, 24.0, 13.0],
+            ..FontSettings::default()
+        };
+        assert_eq!(settings.sizes(), vec![16.0, 13.0, 24.0]);
+    }
+
+    #[test]
+    fn test_glyph_range_names() {
+        for range in GlyphRange::ALL {
+            assert_eq!(GlyphRange::from_name(range.name()), Some(range));
+        }
+        assert_eq!(GlyphRange::from_name(" cyrillic "), Some(GlyphRange::Cyrillic));
+        assert_eq!(GlyphRange::from_name("Klingon"), None);
+    }
+}
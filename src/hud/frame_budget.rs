@@ -0,0 +1,192 @@
+//! Frame budget guard
+//!
+//! Watches the wall-clock frame time and, when it stays above the budget for
+//! several seconds, asks the HUD to hide its expensive panels so the editor
+//! UI does not make an already slow scene unusable. The panels come back once
+//! frame times have been under budget for a while.
+
+use std::time::{Duration, Instant};
+use crate::config;
+
+/// HUD panels whose contents grow with the scene and get hidden first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeavyPanel {
+    /// CSG tree editor (one row per node)
+    Csg,
+    /// Debug draw panel (one row per entity)
+    DebugDraw,
+    /// Stats panel
+    Stats,
+    /// Log console (one row per visible record)
+    Console,
+}
+
+impl HeavyPanel {
+    /// All heavy panels
+    pub const ALL: [HeavyPanel; 4] = [HeavyPanel::Csg, HeavyPanel::DebugDraw, HeavyPanel::Stats, HeavyPanel::Console];
+
+    /// Window title of the panel
+    pub fn name(self) -> &'static str {
+        match self {
+            HeavyPanel::Csg => "CSG Tree",
+            HeavyPanel::DebugDraw => "Debug Draw",
+            HeavyPanel::Stats => "Stats",
+            HeavyPanel::Console => "Console",
+        }
+    }
+}
+
+/// Change of the guard state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameBudgetEvent {
+    /// Frames have been over budget for too long; hide heavy panels
+    Tripped {
+        /// Smoothed frame time in milliseconds
+        frame_ms: f32,
+    },
+    /// Frames are back under budget; restore hidden panels
+    Recovered,
+}
+
+/// Tracks frame times against the configured budget
+pub struct FrameBudgetMonitor {
+    /// Start of the previous frame
+    last_tick: Option<Instant>,
+
+    /// Exponential moving average of the frame time in milliseconds
+    smoothed_ms: f32,
+
+    /// When the smoothed time last crossed the threshold in the current direction
+    state_since: Option<Instant>,
+
+    /// Whether heavy panels are currently hidden
+    tripped: bool,
+
+    /// Panels hidden by the guard, restored on recovery
+    pub hidden_panels: Vec<HeavyPanel>,
+}
+
+impl Default for FrameBudgetMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameBudgetMonitor {
+    /// Weight of the newest frame in the moving average
+    const SMOOTHING: f32 = 0.1;
+
+    /// Create a monitor that is not tripped
+    pub fn new() -> Self {
+        Self {
+            last_tick: None,
+            smoothed_ms: 0.0,
+            state_since: None,
+            tripped: false,
+            hidden_panels: Vec::new(),
+        }
+    }
+
+    /// Whether heavy panels are currently hidden by the guard
+    pub fn is_tripped(&self) -> bool {
+        self.tripped
+    }
+
+    /// Smoothed frame time in milliseconds
+    #[allow(dead_code)]
+    pub fn smoothed_ms(&self) -> f32 {
+        self.smoothed_ms
+    }
+
+    /// Record the start of a new frame, measuring the previous one
+    ///
+    /// # Returns
+    /// An event if the guard tripped or recovered on this frame
+    pub fn tick(&mut self) -> Option<FrameBudgetEvent> {
+        let now = Instant::now();
+        let event = self.last_tick.and_then(|last| self.record(now.duration_since(last), now));
+        self.last_tick = Some(now);
+        event
+    }
+
+    /// Record one frame time
+    ///
+    /// # Arguments
+    /// * `frame_time` - Duration of the frame
+    /// * `now` - Time at the end of the frame
+    ///
+    /// # Returns
+    /// An event if the guard tripped or recovered on this frame
+    pub fn record(&mut self, frame_time: Duration, now: Instant) -> Option<FrameBudgetEvent> {
+        if !config::frame_budget::ENABLED {
+            return None;
+        }
+
+        let frame_ms = frame_time.as_secs_f32() * 1000.0;
+        self.smoothed_ms = if self.smoothed_ms == 0.0 {
+            frame_ms
+        } else {
+            self.smoothed_ms + (frame_ms - self.smoothed_ms) * Self::SMOOTHING
+        };
+
+        // Time is counted while the smoothed frame time is on the "other" side of the threshold
+        let over_budget = self.smoothed_ms > config::frame_budget::THRESHOLD_MS;
+        if over_budget == self.tripped {
+            self.state_since = None;
+            return None;
+        }
+
+        let since = *self.state_since.get_or_insert(now);
+        let required = if self.tripped {
+            config::frame_budget::RECOVER_AFTER_SECS
+        } else {
+            config::frame_budget::TRIP_AFTER_SECS
+        };
+        if now.duration_since(since).as_secs_f32() < required {
+            return None;
+        }
+
+        self.state_since = None;
+        self.tripped = !self.tripped;
+        Some(if self.tripped {
+            FrameBudgetEvent::Tripped { frame_ms: self.smoothed_ms }
+        } else {
+            FrameBudgetEvent::Recovered
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `seconds` worth of frames of `frame_ms` each, collecting events
+    fn run(monitor: &mut FrameBudgetMonitor, now: &mut Instant, frame_ms: u64, seconds: u64) -> Vec<FrameBudgetEvent> {
+        let frame = Duration::from_millis(frame_ms);
+        let mut events = Vec::new();
+        for _ in 0..(seconds * 1000 / frame_ms) {
+            *now += frame;
+            events.extend(monitor.record(frame, *now));
+        }
+        events
+    }
+
+    #[test]
+    fn test_trips_after_sustained_slow_frames_and_recovers() {
+        let mut monitor = FrameBudgetMonitor::new();
+        let mut now = Instant::now();
+
+        assert!(run(&mut monitor, &mut now, 16, 10).is_empty());
+        // A short hitch is not enough
+        assert!(run(&mut monitor, &mut now, 100, 1).is_empty());
+        assert!(!monitor.is_tripped());
+
+        let events = run(&mut monitor, &mut now, 100, 5);
+        assert!(matches!(events.as_slice(), [FrameBudgetEvent::Tripped { .. }]));
+        assert!(monitor.is_tripped());
+
+        let events = run(&mut monitor, &mut now, 16, 10);
+        assert_eq!(events, vec![FrameBudgetEvent::Recovered]);
+        assert!(!monitor.is_tripped());
+    }
+}
@@ -0,0 +1,300 @@
+//! GPU Textures panel
+//!
+//! Lists the renderer's offscreen images (the HDR scene color, the scene
+//! depth and the G-buffer) with live thumbnails, and shows the selected one in an inspector
+//! with zoom and channel isolation: R, G, B or A as grayscale, or depth
+//! linearized to view distance. Images are drawn through the ImGui
+//! user-texture registry of the Vulkan backend; the renderer hands over the
+//! targets every frame and `sync_textures` keeps the registrations in step
+//! when they are recreated on resize.
+
+use imgui::{TextureId, Ui};
+use log::debug;
+use ash::vk;
+use crate::config;
+use crate::error::Result;
+use crate::hud::imgui_vulkan_backend::{ImGuiVulkanBackend, TextureChannel};
+use crate::vulkan::composite::DebugTarget;
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Backend textures of one target
+#[derive(Debug, Clone, Copy)]
+struct RegisteredTarget {
+    /// View the textures sample
+    view: vk::ImageView,
+    /// Texture of the thumbnail, always the default channel
+    thumbnail: TextureId,
+    /// Texture of the inspector, the chosen channel
+    inspector: TextureId,
+}
+
+/// GPU Textures panel state
+pub struct GpuTexturesPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Offscreen images of the current frame, set by the renderer
+    pub targets: Vec<DebugTarget>,
+
+    /// Index of the target shown in the inspector
+    selected: usize,
+
+    /// Channel shown in the inspector
+    channel: TextureChannel,
+
+    /// Inspector pixels per texel
+    zoom: f32,
+
+    /// View distance shown as white in the linearized depth
+    depth_far: f32,
+
+    /// Backend textures of the targets
+    registered: Vec<RegisteredTarget>,
+}
+
+impl Default for GpuTexturesPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpuTexturesPanel {
+    /// Create a hidden panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            targets: Vec::new(),
+            selected: 0,
+            channel: TextureChannel::Rgba,
+            zoom: 1.0,
+            depth_far: config::gpu_textures::DEFAULT_DEPTH_FAR,
+            registered: Vec::new(),
+        }
+    }
+
+    /// Register the targets with the backend and apply the chosen channels
+    ///
+    /// Nothing is registered until the panel is first shown. Registrations of
+    /// images that no longer exist are released.
+    ///
+    /// # Errors
+    /// Returns an error if the backend has no texture slot left
+    pub fn sync_textures(&mut self, backend: &mut ImGuiVulkanBackend) -> Result<()> {
+        if !self.is_visible {
+            return Ok(());
+        }
+
+        let targets = &self.targets;
+        let (current, stale): (Vec<_>, Vec<_>) = std::mem::take(&mut self.registered).into_iter()
+            .partition(|registered| targets.iter().any(|target| target.view == registered.view));
+        for registered in stale {
+            backend.unregister_texture(registered.thumbnail);
+            backend.unregister_texture(registered.inspector);
+        }
+        self.registered = current;
+
+        for target in &self.targets {
+            if self.registered.iter().any(|registered| registered.view == target.view) {
+                continue;
+            }
+            let thumbnail = backend.register_texture(target.view, target.layout)?;
+            let inspector = match backend.register_texture(target.view, target.layout) {
+                Ok(id) => id,
+                Err(e) => {
+                    backend.unregister_texture(thumbnail);
+                    return Err(e);
+                }
+            };
+            debug!("GPU Textures panel registered {}", target.name);
+            self.registered.push(RegisteredTarget { view: target.view, thumbnail, inspector });
+        }
+
+        self.selected = self.selected.min(self.targets.len().saturating_sub(1));
+        let depth_range = [config::gpu_textures::DEPTH_NEAR, self.depth_far];
+        for (index, target) in self.targets.iter().enumerate() {
+            let Some(registered) = self.registration(target.view) else {
+                continue;
+            };
+            backend.set_texture_channel(registered.thumbnail, default_channel(target), depth_range);
+            let channel = if index == self.selected { self.channel } else { default_channel(target) };
+            backend.set_texture_channel(registered.inspector, channel, depth_range);
+        }
+        Ok(())
+    }
+
+    /// Backend textures of a view
+    fn registration(&self, view: vk::ImageView) -> Option<RegisteredTarget> {
+        self.registered.iter().find(|registered| registered.view == view).copied()
+    }
+
+    /// Show a target in the inspector with its default channel
+    fn select(&mut self, index: usize) {
+        if index != self.selected {
+            self.selected = index;
+            self.channel = self.targets.get(index).map(default_channel).unwrap_or_default();
+        }
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("GPU Textures"))
+            .position([880.0, 70.0], imgui::Condition::FirstUseEver)
+            .size([420.0, 560.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if self.targets.is_empty() {
+                ui.text_disabled(tr("gpu_textures.empty"));
+                return;
+            }
+
+            // Thumbnails; clicking one shows it in the inspector
+            let mut clicked = None;
+            for (index, target) in self.targets.iter().enumerate() {
+                let Some(registered) = self.registration(target.view) else {
+                    continue;
+                };
+                let border = if index == self.selected { [1.0, 0.8, 0.2, 1.0] } else { [0.0; 4] };
+                imgui::Image::new(registered.thumbnail, thumbnail_size(target.rendered_extent, config::gpu_textures::THUMBNAIL_WIDTH))
+                    .uv1(rendered_uv(target))
+                    .border_col(border)
+                    .build(ui);
+                if ui.is_item_clicked() {
+                    clicked = Some(index);
+                }
+                ui.same_line();
+                ui.group(|| {
+                    ui.text(target.name);
+                    ui.text_disabled(format!("{:?}", target.format));
+                    ui.text_disabled(format!("{}x{}", target.extent.width, target.extent.height));
+                    ui.text_disabled(tr_args("gpu_textures.rendered", &[("width", &target.rendered_extent.width), ("height", &target.rendered_extent.height)]));
+                });
+            }
+            if let Some(index) = clicked {
+                self.select(index);
+            }
+
+            ui.separator();
+            let Some(target) = self.targets.get(self.selected).copied() else {
+                return;
+            };
+            let Some(registered) = self.registration(target.view) else {
+                return;
+            };
+
+            let mut channel_index = TextureChannel::ALL.iter().position(|channel| *channel == self.channel).unwrap_or(0);
+            if ui.combo(tr("gpu_textures.channel"), &mut channel_index, &TextureChannel::ALL, |channel| named("texture_channels", channel.label()).into()) {
+                self.channel = TextureChannel::ALL[channel_index];
+            }
+            let [min_zoom, max_zoom] = config::gpu_textures::ZOOM_RANGE;
+            ui.slider_config(tr("gpu_textures.zoom"), min_zoom, max_zoom)
+                .flags(imgui::SliderFlags::LOGARITHMIC)
+                .display_format("%.3fx")
+                .build(&mut self.zoom);
+            if self.channel == TextureChannel::Depth {
+                ui.slider_config(tr("gpu_textures.white_at"), config::gpu_textures::DEPTH_NEAR, 200.0)
+                    .flags(imgui::SliderFlags::LOGARITHMIC)
+                    .display_format(tr("gpu_textures.units_format"))
+                    .build(&mut self.depth_far);
+            }
+
+            // Zoomed image, scrollable; hovering shows the texel under the cursor
+            let zoom = self.zoom;
+            ui.child_window("##gpu_texture_inspector")
+                .horizontal_scrollbar(true)
+                .build(|| {
+                    let image_min = ui.cursor_screen_pos();
+                    let size = [
+                        target.rendered_extent.width as f32 * zoom,
+                        target.rendered_extent.height as f32 * zoom,
+                    ];
+                    imgui::Image::new(registered.inspector, size)
+                        .uv1(rendered_uv(&target))
+                        .build(ui);
+                    if ui.is_item_hovered() {
+                        if let Some([x, y]) = hovered_texel(ui.io().mouse_pos, image_min, zoom, target.rendered_extent) {
+                            ui.tooltip_text(tr_args("gpu_textures.texel", &[("x", &x), ("y", &y)]));
+                        }
+                    }
+                });
+        }
+    }
+}
+
+/// Channel a target is shown with until another one is chosen
+fn default_channel(target: &DebugTarget) -> TextureChannel {
+    if target.is_depth {
+        TextureChannel::Depth
+    } else if target.format == config::rendering::LINEAR_DEPTH_FORMAT || target.format == config::rendering::SELECTION_FORMAT {
+        TextureChannel::Red
+    } else {
+        TextureChannel::Rgba
+    }
+}
+
+/// Bottom-right texture coordinate of the region rendered this frame
+fn rendered_uv(target: &DebugTarget) -> [f32; 2] {
+    [
+        target.rendered_extent.width as f32 / target.extent.width.max(1) as f32,
+        target.rendered_extent.height as f32 / target.extent.height.max(1) as f32,
+    ]
+}
+
+/// Thumbnail size of a given width keeping the aspect ratio of the rendered region
+fn thumbnail_size(rendered_extent: vk::Extent2D, width: f32) -> [f32; 2] {
+    let aspect = rendered_extent.height as f32 / rendered_extent.width.max(1) as f32;
+    [width, (width * aspect).round()]
+}
+
+/// Texel under the mouse in an image drawn at `zoom` pixels per texel
+///
+/// # Returns
+/// None if the mouse is outside the rendered region
+fn hovered_texel(mouse: [f32; 2], image_min: [f32; 2], zoom: f32, rendered_extent: vk::Extent2D) -> Option<[u32; 2]> {
+    let x = (mouse[0] - image_min[0]) / zoom;
+    let y = (mouse[1] - image_min[1]) / zoom;
+    if x < 0.0 || y < 0.0 || x >= rendered_extent.width as f32 || y >= rendered_extent.height as f32 {
+        return None;
+    }
+    Some([x as u32, y as u32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(rendered: (u32, u32), is_depth: bool) -> DebugTarget {
+        DebugTarget {
+            name: "test",
+            view: vk::ImageView::null(),
+            layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            format: config::rendering::SCENE_FORMAT,
+            extent: vk::Extent2D { width: 800, height: 600 },
+            rendered_extent: vk::Extent2D { width: rendered.0, height: rendered.1 },
+            is_depth,
+        }
+    }
+
+    #[test]
+    fn test_thumbnails_cover_the_rendered_region() {
+        let scaled = target((400, 300), false);
+        assert_eq!(rendered_uv(&scaled), [0.5, 0.5]);
+        assert_eq!(thumbnail_size(scaled.rendered_extent, 160.0), [160.0, 120.0]);
+        assert_eq!(default_channel(&scaled), TextureChannel::Rgba);
+        assert_eq!(default_channel(&target((800, 600), true)), TextureChannel::Depth);
+        let linear_depth = DebugTarget { format: config::rendering::LINEAR_DEPTH_FORMAT, ..scaled };
+        assert_eq!(default_channel(&linear_depth), TextureChannel::Red);
+    }
+
+    #[test]
+    fn test_hovered_texel_follows_zoom() {
+        let extent = vk::Extent2D { width: 400, height: 300 };
+        assert_eq!(hovered_texel([110.0, 60.0], [100.0, 50.0], 4.0, extent), Some([2, 2]));
+        assert_eq!(hovered_texel([99.0, 60.0], [100.0, 50.0], 4.0, extent), None);
+        assert_eq!(hovered_texel([100.0 + 400.0 * 4.0, 60.0], [100.0, 50.0], 4.0, extent), None);
+    }
+}
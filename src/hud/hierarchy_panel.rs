@@ -0,0 +1,220 @@
+//! Hierarchy panel
+//!
+//! Shows the transform hierarchy as a tree. Dragging an entity onto another
+//! makes it a child of that entity; dropping it on the root line detaches
+//! it. Children keep their place in the world when re-parented and then
+//! follow their parent. The ECS world refreshes the snapshot every frame
+//! and applies the queued edits.
+
+use imgui::{DragDropFlags, TreeNodeFlags, Ui};
+use legion::Entity;
+use log::debug;
+use crate::locale::{panel_title, tr, tr_args};
+
+/// Drag and drop payload type for re-parenting
+const ENTITY_PAYLOAD: &str = "HIERARCHY_ENTITY";
+
+/// Snapshot of one entity in the hierarchy
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchyNodeView {
+    /// The entity
+    pub entity: Entity,
+    /// Display name (shape type or "Group")
+    pub label: String,
+    /// Parent entity, None for roots
+    pub parent: Option<Entity>,
+    /// Child entities
+    pub children: Vec<Entity>,
+    /// World position
+    pub position: cgmath::Vector3<f32>,
+}
+
+/// Re-parenting requested from the panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HierarchyEdit {
+    /// Entity to move
+    pub child: Entity,
+    /// New parent, None to make it a root
+    pub parent: Option<Entity>,
+}
+
+/// Hierarchy panel state
+pub struct HierarchyPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the hierarchy
+    pub nodes: Vec<HierarchyNodeView>,
+
+    /// Entity whose details are shown below the tree
+    selected: Option<Entity>,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: Vec<HierarchyEdit>,
+}
+
+impl Default for HierarchyPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HierarchyPanel {
+    /// Create a new hierarchy panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            nodes: Vec::new(),
+            selected: None,
+            pending_edits: Vec::new(),
+        }
+    }
+
+    /// Entity selected in the tree, outlined in the viewports
+    pub fn selected(&self) -> Option<Entity> {
+        self.selected
+    }
+
+    /// Select an entity, e.g. one picked in a viewport, or clear the selection
+    pub fn select(&mut self, entity: Option<Entity>) {
+        self.selected = entity;
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<HierarchyEdit> {
+        std::mem::take(&mut self.pending_edits)
+    }
+
+    /// Queue a re-parenting unless it is a no-op
+    fn queue_edit(&mut self, edit: HierarchyEdit) {
+        if Some(edit.child) == edit.parent || self.node(edit.child).is_some_and(|node| node.parent == edit.parent) {
+            return;
+        }
+        debug!("Hierarchy edit queued: {:?}", edit);
+        self.pending_edits.push(edit);
+    }
+
+    /// Snapshot of an entity
+    fn node(&self, entity: Entity) -> Option<&HierarchyNodeView> {
+        self.nodes.iter().find(|node| node.entity == entity)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Hierarchy"))
+            .position([10.0, 570.0], imgui::Condition::FirstUseEver)
+            .size([280.0, 260.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if self.nodes.is_empty() {
+                ui.text_disabled(tr("hierarchy.empty"));
+                return;
+            }
+
+            // Drop target detaching an entity from its parent
+            ui.selectable(format!("{}###root", tr("hierarchy.root")));
+            if let Some(child) = Self::accept_drop(ui) {
+                self.queue_edit(HierarchyEdit { child, parent: None });
+            }
+            ui.text_disabled(tr("hierarchy.drag_hint"));
+            ui.separator();
+
+            let roots: Vec<Entity> = self.nodes.iter().filter(|node| node.parent.is_none()).map(|node| node.entity).collect();
+            for root in roots {
+                self.render_node(ui, root, 0);
+            }
+
+            if let Some(node) = self.selected.and_then(|entity| self.node(entity)) {
+                ui.separator();
+                ui.text(format!("{} {:?}", node.label, node.entity));
+                ui.text(tr_args("hierarchy.position", &[("position", &format!("{:.2} {:.2} {:.2}", node.position.x, node.position.y, node.position.z))]));
+                match node.parent {
+                    Some(parent) => ui.text(tr_args("hierarchy.parent", &[("parent", &format!("{:?}", parent))])),
+                    None => ui.text_disabled(tr("hierarchy.no_parent")),
+                }
+            }
+        }
+    }
+
+    /// Entity dropped on the last item, if any
+    fn accept_drop(ui: &Ui) -> Option<Entity> {
+        let target = ui.drag_drop_target()?;
+        let dropped = match target.accept_payload::<Entity, _>(ENTITY_PAYLOAD, DragDropFlags::empty()) {
+            Some(Ok(payload)) => Some(payload.data),
+            _ => None,
+        };
+        target.pop();
+        dropped
+    }
+
+    /// Render an entity and its subtree
+    fn render_node(&mut self, ui: &Ui, entity: Entity, depth: usize) {
+        let Some(node) = self.node(entity) else {
+            return;
+        };
+        // Cycles cannot be created from the panel; stop if one shows up anyway
+        if depth > self.nodes.len() {
+            return;
+        }
+        let label = format!("{} {:?}", node.label, entity);
+        let children = node.children.clone();
+
+        let mut flags = TreeNodeFlags::DEFAULT_OPEN | TreeNodeFlags::OPEN_ON_ARROW;
+        if children.is_empty() {
+            flags |= TreeNodeFlags::LEAF;
+        }
+        if self.selected == Some(entity) {
+            flags |= TreeNodeFlags::SELECTED;
+        }
+        let tree_node = ui.tree_node_config(&label).flags(flags).push();
+
+        if ui.is_item_clicked() {
+            self.selected = Some(entity);
+        }
+        if let Some(_tooltip) = ui.drag_drop_source_config(ENTITY_PAYLOAD).begin_payload(entity) {
+            ui.text(tr_args("hierarchy.move", &[("name", &label)]));
+        }
+        if let Some(child) = Self::accept_drop(ui) {
+            self.queue_edit(HierarchyEdit { child, parent: Some(entity) });
+        }
+
+        if let Some(_node) = tree_node {
+            for child in children {
+                self.render_node(ui, child, depth + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_edit_skips_no_ops() {
+        let mut world = legion::World::default();
+        let a = world.push((0u32,));
+        let b = world.push((1u32,));
+
+        let mut panel = HierarchyPanel::new();
+        let view = |entity, parent| HierarchyNodeView {
+            entity,
+            label: "Sphere".to_string(),
+            parent,
+            children: Vec::new(),
+            position: cgmath::Vector3::new(0.0, 0.0, 0.0),
+        };
+        panel.nodes = vec![view(a, None), view(b, Some(a))];
+
+        panel.queue_edit(HierarchyEdit { child: a, parent: Some(a) });
+        panel.queue_edit(HierarchyEdit { child: b, parent: Some(a) });
+        panel.queue_edit(HierarchyEdit { child: b, parent: None });
+
+        assert_eq!(panel.take_pending_edits(), vec![HierarchyEdit { child: b, parent: None }]);
+    }
+}
@@ -1,8 +1,17 @@
 use ash::vk;
 use ash::Device;
 use log::{debug, info, warn, error};
-use crate::error::AppError;
+use crate::config;
+use crate::error::{AppError, ResultExt, Subsystem};
 use std::mem;
+use std::sync::{Arc, Mutex};
+use crate::vulkan::buffer::GpuBuffer;
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::rendering::PassLayout;
+use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::tonemap::{DisplayOutput, OutputSpecialization};
+use crate::vulkan::upload::{UploadContext, UploadTicket};
+use crate::vulkan::deletion_queue::{DeferredResource, DeletionQueue};
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -12,11 +21,131 @@ pub struct ImguiVertex {
     pub col: [u8; 4],
 }
 
+unsafe impl bytemuck::Pod for ImguiVertex {}
+unsafe impl bytemuck::Zeroable for ImguiVertex {}
+
+/// How the fragment shader shows a texture
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureChannel {
+    /// Texture times vertex color, used for the font and regular images
+    #[default]
+    Rgba,
+    /// Red channel as grayscale
+    Red,
+    /// Green channel as grayscale
+    Green,
+    /// Blue channel as grayscale
+    Blue,
+    /// Alpha channel as grayscale
+    Alpha,
+    /// Red channel as a perspective depth, linearized from near to far
+    Depth,
+}
+
+impl TextureChannel {
+    /// Every channel, in the order the panel lists them
+    pub const ALL: [TextureChannel; 6] = [
+        TextureChannel::Rgba,
+        TextureChannel::Red,
+        TextureChannel::Green,
+        TextureChannel::Blue,
+        TextureChannel::Alpha,
+        TextureChannel::Depth,
+    ];
+
+    /// Label in the panel
+    pub fn label(self) -> &'static str {
+        match self {
+            TextureChannel::Rgba => "RGBA",
+            TextureChannel::Red => "R",
+            TextureChannel::Green => "G",
+            TextureChannel::Blue => "B",
+            TextureChannel::Alpha => "A",
+            TextureChannel::Depth => "Depth (linear)",
+        }
+    }
+
+    /// `view_mode` of `imgui.frag`
+    fn shader_mode(self) -> u32 {
+        match self {
+            TextureChannel::Rgba => 0,
+            TextureChannel::Red => 1,
+            TextureChannel::Green => 2,
+            TextureChannel::Blue => 3,
+            TextureChannel::Alpha => 4,
+            TextureChannel::Depth => 5,
+        }
+    }
+}
+
+/// Fragment push constants after the 64 byte projection matrix
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct TextureViewConstants {
+    view_mode: u32,
+    depth_near: f32,
+    depth_far: f32,
+    _padding: u32,
+}
+
+unsafe impl bytemuck::Pod for TextureViewConstants {}
+unsafe impl bytemuck::Zeroable for TextureViewConstants {}
+
+/// Byte offset of `TextureViewConstants` in the push constant block
+const TEXTURE_VIEW_OFFSET: u32 = 64;
+
+/// Texture in the ImGui user-texture registry
+#[derive(Debug, Clone, Copy)]
+struct UserTexture {
+    descriptor_set: vk::DescriptorSet,
+    channel: TextureChannel,
+    /// Near and far plane for `TextureChannel::Depth`
+    depth_range: [f32; 2],
+}
+
+impl UserTexture {
+    fn new(descriptor_set: vk::DescriptorSet) -> Self {
+        Self { descriptor_set, channel: TextureChannel::Rgba, depth_range: [0.0, 1.0] }
+    }
+
+    fn view_constants(&self) -> TextureViewConstants {
+        TextureViewConstants {
+            view_mode: self.channel.shader_mode(),
+            depth_near: self.depth_range[0],
+            depth_far: self.depth_range[1],
+            _padding: 0,
+        }
+    }
+}
+
+/// Scissor rectangle of an ImGui clip rectangle, None if nothing is visible
+///
+/// # Arguments
+/// * `clip_rect` - Clip rectangle of the draw command (min x, min y, max x, max y)
+/// * `display_pos` - Top-left corner of the ImGui display
+/// * `scale` - Framebuffer scale of the draw data
+/// * `framebuffer_size` - Size of the attachment in pixels
+fn clip_to_scissor(clip_rect: [f32; 4], display_pos: [f32; 2], scale: [f32; 2], framebuffer_size: [f32; 2]) -> Option<vk::Rect2D> {
+    let min_x = ((clip_rect[0] - display_pos[0]) * scale[0]).max(0.0);
+    let min_y = ((clip_rect[1] - display_pos[1]) * scale[1]).max(0.0);
+    let max_x = ((clip_rect[2] - display_pos[0]) * scale[0]).min(framebuffer_size[0]);
+    let max_y = ((clip_rect[3] - display_pos[1]) * scale[1]).min(framebuffer_size[1]);
+    if max_x <= min_x || max_y <= min_y {
+        return None;
+    }
+    Some(vk::Rect2D {
+        offset: vk::Offset2D { x: min_x as i32, y: min_y as i32 },
+        extent: vk::Extent2D { width: (max_x - min_x) as u32, height: (max_y - min_y) as u32 },
+    })
+}
+
 pub struct ImGuiVulkanBackend {
     device: Device,
     physical_device: Option<vk::PhysicalDevice>,
     instance: Option<ash::Instance>,
-    graphics_queue_family_index: u32,
+    upload: Arc<Mutex<UploadContext>>,
+    deletion_queue: Arc<Mutex<DeletionQueue>>,
+    font_upload: Option<UploadTicket>,
     font_texture: Option<vk::Image>,
     font_texture_view: Option<vk::ImageView>,
     font_texture_sampler: Option<vk::Sampler>,
@@ -24,12 +153,17 @@ pub struct ImGuiVulkanBackend {
     descriptor_set_layout: Option<vk::DescriptorSetLayout>,
     descriptor_pool: Option<vk::DescriptorPool>,
     descriptor_set: Option<vk::DescriptorSet>,
+    /// Font atlas and the images registered by panels, keyed by ImGui texture id
+    textures: imgui::Textures<UserTexture>,
+    font_texture_id: imgui::TextureId,
+    /// Registered textures besides the font atlas
+    user_texture_count: u32,
+    /// Nearest sampler of the registered images, so zoomed texels stay sharp
+    user_texture_sampler: Option<vk::Sampler>,
     pipeline_layout: Option<vk::PipelineLayout>,
     pipeline: Option<vk::Pipeline>,
-    vertex_buffer: Option<vk::Buffer>,
-    vertex_buffer_memory: Option<vk::DeviceMemory>,
-    index_buffer: Option<vk::Buffer>,
-    index_buffer_memory: Option<vk::DeviceMemory>,
+    vertex_buffer: Option<GpuBuffer>,
+    index_buffer: Option<GpuBuffer>,
     vertex_count: usize,
     index_count: usize,
 }
@@ -39,14 +173,18 @@ impl ImGuiVulkanBackend {
         device: &Device,
         physical_device: vk::PhysicalDevice,
         instance: &ash::Instance,
-        render_pass: vk::RenderPass,
-        graphics_queue_family_index: u32,
+        pass: &PassLayout,
+        display_output: DisplayOutput,
+        upload: Arc<Mutex<UploadContext>>,
+        deletion_queue: Arc<Mutex<DeletionQueue>>,
     ) -> Result<Self, AppError> {
         let mut backend = Self {
             device: device.clone(),
             physical_device: Some(physical_device),
             instance: Some(instance.clone()),
-            graphics_queue_family_index,
+            upload,
+            deletion_queue,
+            font_upload: None,
             font_texture: None,
             font_texture_view: None,
             font_texture_sampler: None,
@@ -54,12 +192,14 @@ impl ImGuiVulkanBackend {
             descriptor_set_layout: None,
             descriptor_pool: None,
             descriptor_set: None,
+            textures: imgui::Textures::new(),
+            font_texture_id: imgui::TextureId::new(0),
+            user_texture_count: 0,
+            user_texture_sampler: None,
             pipeline_layout: None,
             pipeline: None,
             vertex_buffer: None,
-            vertex_buffer_memory: None,
             index_buffer: None,
-            index_buffer_memory: None,
             vertex_count: 0,
             index_count: 0,
         };
@@ -68,13 +208,15 @@ impl ImGuiVulkanBackend {
         backend.create_descriptor_set_layout()?;
         
         // Create pipeline
-        backend.create_pipeline(render_pass)?;
+        backend.create_pipeline(pass, display_output)?;
         
         // Create descriptor pool
         backend.create_descriptor_pool()?;
         
         // Allocate descriptor set
         backend.allocate_descriptor_set()?;
+        backend.font_texture_id = backend.textures.insert(UserTexture::new(backend.descriptor_set.unwrap()));
+        backend.create_user_texture_sampler()?;
 
         info!("ImGui Vulkan backend created successfully");
         Ok(backend)
@@ -98,7 +240,7 @@ impl ImGuiVulkanBackend {
         Ok(())
     }
 
-    fn create_pipeline(&mut self, render_pass: vk::RenderPass) -> Result<(), AppError> {
+    fn create_pipeline(&mut self, pass: &PassLayout, display_output: DisplayOutput) -> Result<(), AppError> {
         // Create pipeline layout with push constants
         let descriptor_set_layout = self.descriptor_set_layout.unwrap();
         let descriptor_set_layout_array = [descriptor_set_layout];
@@ -107,9 +249,15 @@ impl ImGuiVulkanBackend {
         let push_constant_range = vk::PushConstantRange::default()
             .stage_flags(vk::ShaderStageFlags::VERTEX)
             .offset(0)
-            .size(64); // 4x4 matrix = 16 floats = 64 bytes
+            .size(TEXTURE_VIEW_OFFSET); // 4x4 matrix = 16 floats = 64 bytes
+        
+        // And for the fragment shader (channel and depth range of the bound texture)
+        let view_constant_range = vk::PushConstantRange::default()
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .offset(TEXTURE_VIEW_OFFSET)
+            .size(mem::size_of::<TextureViewConstants>() as u32);
         
-        let push_constant_ranges = [push_constant_range];
+        let push_constant_ranges = [push_constant_range, view_constant_range];
         
         let layout_info = vk::PipelineLayoutCreateInfo::default()
             .set_layouts(&descriptor_set_layout_array)
@@ -119,12 +267,24 @@ impl ImGuiVulkanBackend {
             Some(self.device.create_pipeline_layout(&layout_info, None)?)
         };
 
-        // Create shader modules from compiled SPIR-V
-        let vert_shader_code = include_bytes!("../../shaders/imgui.vert.spv");
-        let frag_shader_code = include_bytes!("../../shaders/imgui.frag.spv");
-
-        let vert_shader_module = Self::create_shader_module_from_spv(&self.device, vert_shader_code)?;
-        let frag_shader_module = Self::create_shader_module_from_spv(&self.device, frag_shader_code)?;
+        // Compile the shaders from source like the other passes, at runtime so
+        // a missing or broken file can be fixed and the HUD retried without rebuilding
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let mut compile = |path: &str| shader_compiler.compile_file(path, "main", &[])
+            .context(Subsystem::Hud, "compile ImGui shader")
+            .detail("shader", path);
+        let vert_shader_code = compile(config::shader::IMGUI_VERTEX_SHADER)?;
+        let frag_shader_code = compile(config::shader::IMGUI_FRAGMENT_SHADER)?;
+
+        let vert_shader_module = Self::create_shader_module(&self.device, &vert_shader_code)?;
+        let frag_shader_module = Self::create_shader_module(&self.device, &frag_shader_code)?;
+
+        // HDR swapchains need the UI colors re-encoded in the fragment shader
+        let specialization = display_output.specialization();
+        let map_entries = OutputSpecialization::map_entries();
+        let specialization_info = vk::SpecializationInfo::default()
+            .map_entries(&map_entries)
+            .data(bytemuck::bytes_of(&specialization));
 
         let shader_stages = [
             vk::PipelineShaderStageCreateInfo::default()
@@ -134,7 +294,8 @@ impl ImGuiVulkanBackend {
             vk::PipelineShaderStageCreateInfo::default()
                 .stage(vk::ShaderStageFlags::FRAGMENT)
                 .module(frag_shader_module)
-                .name(c"main"),
+                .name(c"main")
+                .specialization_info(&specialization_info),
         ];
 
         // Vertex input
@@ -225,19 +386,22 @@ impl ImGuiVulkanBackend {
         let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
             .attachments(&color_blend_attachment_array);
 
+        // The pass has a depth attachment; the HUD ignores it
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default();
+
         // Pipeline
-        let pipeline_info = vk::GraphicsPipelineCreateInfo::default()
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
             .stages(&shader_stages)
             .vertex_input_state(&vertex_input)
             .input_assembly_state(&input_assembly)
             .viewport_state(&viewport_state)
             .rasterization_state(&rasterizer)
             .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
             .color_blend_state(&color_blending)
             .dynamic_state(&dynamic_state_info)
-            .layout(self.pipeline_layout.unwrap())
-            .render_pass(render_pass)
-            .subpass(0);
+            .layout(self.pipeline_layout.unwrap()), &mut rendering);
 
         let pipeline = unsafe {
             self.device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None)
@@ -255,15 +419,9 @@ impl ImGuiVulkanBackend {
         Ok(())
     }
 
-    fn create_shader_module_from_spv(device: &Device, spv_code: &[u8]) -> Result<vk::ShaderModule, AppError> {
-        // SPIR-V is already aligned to 4 bytes, just need to cast to u32
-        let aligned_code: Vec<u32> = spv_code
-            .chunks_exact(4)
-            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
-
+    fn create_shader_module(device: &Device, code: &[u32]) -> Result<vk::ShaderModule, AppError> {
         let shader_module_create_info = vk::ShaderModuleCreateInfo::default()
-            .code(&aligned_code);
+            .code(code);
 
         unsafe {
             Ok(device.create_shader_module(&shader_module_create_info, None)?)
@@ -271,14 +429,19 @@ impl ImGuiVulkanBackend {
     }
 
     fn create_descriptor_pool(&mut self) -> Result<(), AppError> {
+        // One live set plus the sets of replaced textures that frames in flight still use,
+        // for the font and every registered texture
+        let sets_per_texture = config::vulkan::MAX_FRAMES_IN_FLIGHT as u32 + 1;
+        let max_sets = sets_per_texture * (config::gpu_textures::MAX_USER_TEXTURES + 1);
         let pool_size = vk::DescriptorPoolSize::default()
             .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-            .descriptor_count(1);
+            .descriptor_count(max_sets);
 
         let pool_sizes = [pool_size];
         let pool_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
             .pool_sizes(&pool_sizes)
-            .max_sets(1);
+            .max_sets(max_sets);
 
         self.descriptor_pool = unsafe {
             Some(self.device.create_descriptor_pool(&pool_info, None)?)
@@ -288,6 +451,11 @@ impl ImGuiVulkanBackend {
     }
 
     fn allocate_descriptor_set(&mut self) -> Result<(), AppError> {
+        self.descriptor_set = Some(self.allocate_set()?);
+        Ok(())
+    }
+
+    fn allocate_set(&self) -> Result<vk::DescriptorSet, AppError> {
         let layouts = [self.descriptor_set_layout.unwrap()];
         let alloc_info = vk::DescriptorSetAllocateInfo::default()
             .descriptor_pool(self.descriptor_pool.unwrap())
@@ -296,18 +464,141 @@ impl ImGuiVulkanBackend {
         let descriptor_sets = unsafe {
             self.device.allocate_descriptor_sets(&alloc_info)?
         };
+        Ok(descriptor_sets[0])
+    }
+
+    fn create_user_texture_sampler(&mut self) -> Result<(), AppError> {
+        // Nearest filtering also works for depth formats without linear filtering support
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+        self.user_texture_sampler = unsafe {
+            Some(self.device.create_sampler(&sampler_info, None)?)
+        };
+        Ok(())
+    }
+
+    /// Add an image to the ImGui user-texture registry
+    ///
+    /// The returned id is drawn with `imgui::Image`. The image must stay in
+    /// `layout` while the HUD is drawn and outlive the registration.
+    ///
+    /// # Arguments
+    /// * `view` - View of the image
+    /// * `layout` - Layout the image is sampled in
+    ///
+    /// # Errors
+    /// Returns an error if all `config::gpu_textures::MAX_USER_TEXTURES` are registered
+    pub fn register_texture(&mut self, view: vk::ImageView, layout: vk::ImageLayout) -> Result<imgui::TextureId, AppError> {
+        if self.user_texture_count >= config::gpu_textures::MAX_USER_TEXTURES {
+            return Err(AppError::HUD(format!("All {} HUD texture slots are in use", config::gpu_textures::MAX_USER_TEXTURES)));
+        }
+        let descriptor_set = self.allocate_set()?;
+        let image_infos = [vk::DescriptorImageInfo::default()
+            .image_layout(layout)
+            .image_view(view)
+            .sampler(self.user_texture_sampler.unwrap())];
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_infos);
+        unsafe {
+            self.device.update_descriptor_sets(&[write], &[]);
+        }
+        let id = self.textures.insert(UserTexture::new(descriptor_set));
+        self.user_texture_count += 1;
+        debug!("Registered HUD texture {:?}", id);
+        Ok(id)
+    }
 
-        self.descriptor_set = Some(descriptor_sets[0]);
+    /// Choose how a registered texture is shown
+    ///
+    /// # Arguments
+    /// * `id` - Id returned by `register_texture`
+    /// * `channel` - Channel shown
+    /// * `depth_range` - Near and far plane used by `TextureChannel::Depth`
+    pub fn set_texture_channel(&mut self, id: imgui::TextureId, channel: TextureChannel, depth_range: [f32; 2]) {
+        if id == self.font_texture_id {
+            return;
+        }
+        if let Some(texture) = self.textures.get_mut(id) {
+            texture.channel = channel;
+            texture.depth_range = depth_range;
+        }
+    }
+
+    /// Remove a texture from the registry
+    ///
+    /// Its descriptor set is freed once the frames in flight completed.
+    pub fn unregister_texture(&mut self, id: imgui::TextureId) {
+        if id == self.font_texture_id {
+            return;
+        }
+        if let (Some(texture), Some(pool)) = (self.textures.remove(id), self.descriptor_pool) {
+            self.deletion_queue.lock().unwrap().defer(DeferredResource::DescriptorSet(pool, texture.descriptor_set));
+            self.user_texture_count -= 1;
+            debug!("Unregistered HUD texture {:?}", id);
+        }
+    }
+
+    /// Texture id of the font atlas, set on the atlas before it is built
+    pub fn font_texture_id(&self) -> imgui::TextureId {
+        self.font_texture_id
+    }
+
+    /// Hand the font texture and its descriptor set to the deletion queue
+    ///
+    /// Frames in flight may still sample the old atlas, so it is destroyed
+    /// once they completed; a fresh descriptor set is allocated for the new one.
+    fn retire_font_texture(&mut self) -> Result<(), AppError> {
+        let Some(image) = self.font_texture.take() else {
+            return Ok(());
+        };
+        
+        // The upload may still be writing the old image on the transfer queue
+        if let Some(ticket) = self.font_upload.take() {
+            self.upload.lock().unwrap().wait(ticket)?;
+        }
+        
+        {
+            let mut deletion_queue = self.deletion_queue.lock().unwrap();
+            if let (Some(pool), Some(set)) = (self.descriptor_pool, self.descriptor_set.take()) {
+                deletion_queue.defer(DeferredResource::DescriptorSet(pool, set));
+            }
+            if let Some(view) = self.font_texture_view.take() {
+                deletion_queue.defer(DeferredResource::ImageView(view));
+            }
+            deletion_queue.defer(DeferredResource::Image(image));
+            if let Some(memory) = self.font_texture_memory.take() {
+                deletion_queue.defer(DeferredResource::Memory(memory));
+            }
+        }
+        
+        self.allocate_descriptor_set()?;
+        self.textures.replace(self.font_texture_id, UserTexture::new(self.descriptor_set.unwrap()));
+        debug!("Previous font texture queued for deletion");
         Ok(())
     }
 
+    /// Create the font texture, replacing the current one if there is one
     pub fn create_font_texture(&mut self, width: u32, height: u32) -> Result<(), AppError> {
         debug!("Creating font texture {}x{}", width, height);
+        
+        self.retire_font_texture()?;
 
         // Ensure minimum size for font texture
         let texture_width = std::cmp::max(width, 1);
         let texture_height = std::cmp::max(height, 1);
         
+        // Written on the upload queue and sampled on the graphics queue
+        let (sharing_mode, queue_families) = self.upload.lock().unwrap().sharing();
+        
         // Create font texture with RGBA format for proper font rendering
         // Use OPTIMAL tiling for better GPU performance and proper sampling
         let image_info = vk::ImageCreateInfo::default()
@@ -319,7 +610,8 @@ impl ImGuiVulkanBackend {
             .tiling(vk::ImageTiling::OPTIMAL) // Use OPTIMAL tiling for GPU sampling
             .initial_layout(vk::ImageLayout::UNDEFINED)
             .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .sharing_mode(sharing_mode)
+            .queue_family_indices(&queue_families)
             .samples(vk::SampleCountFlags::TYPE_1);
 
         self.font_texture = unsafe {
@@ -336,9 +628,7 @@ impl ImGuiVulkanBackend {
                 vk::MemoryPropertyFlags::DEVICE_LOCAL,
             )?);
 
-        self.font_texture_memory = unsafe {
-            Some(self.device.allocate_memory(&alloc_info, None)?)
-        };
+        self.font_texture_memory = Some(memory_usage::allocate(&self.device, &alloc_info, MemoryCategory::Textures, vk::MemoryPropertyFlags::DEVICE_LOCAL)?);
 
         unsafe {
             self.device.bind_image_memory(self.font_texture.unwrap(), self.font_texture_memory.unwrap(), 0)?;
@@ -381,9 +671,12 @@ impl ImGuiVulkanBackend {
             .min_lod(0.0)
             .max_lod(vk::LOD_CLAMP_NONE);
 
-        self.font_texture_sampler = unsafe {
-            Some(self.device.create_sampler(&sampler_info, None)?)
-        };
+        // The sampler is kept when the atlas is rebuilt
+        if self.font_texture_sampler.is_none() {
+            self.font_texture_sampler = unsafe {
+                Some(self.device.create_sampler(&sampler_info, None)?)
+            };
+        }
 
         debug!("Font texture image view and sampler created");
 
@@ -432,181 +725,15 @@ impl ImGuiVulkanBackend {
         
         info!("Font texture exists, proceeding with upload");
 
-        // Create a staging buffer for uploading the font data
-        let buffer_size = (width * height * 4) as u64; // 4 bytes per pixel for RGBA
-        
-        let staging_buffer_info = vk::BufferCreateInfo::default()
-            .size(buffer_size)
-            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE);
-
-        let staging_buffer = unsafe {
-            self.device.create_buffer(&staging_buffer_info, None)?
-        };
-
-        let staging_mem_requirements = unsafe { self.device.get_buffer_memory_requirements(staging_buffer) };
-        
-        let staging_alloc_info = vk::MemoryAllocateInfo::default()
-            .allocation_size(staging_mem_requirements.size)
-            .memory_type_index(self.find_memory_type(
-                staging_mem_requirements.memory_type_bits,
-                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-            )?);
-
-        let staging_buffer_memory = unsafe {
-            self.device.allocate_memory(&staging_alloc_info, None)?
-        };
-
-        unsafe {
-            self.device.bind_buffer_memory(staging_buffer, staging_buffer_memory, 0)?;
-        }
-
-        // Map the staging buffer and copy font data
-        let mapped_memory = unsafe {
-            self.device.map_memory(
-                staging_buffer_memory,
-                0,
-                buffer_size,
-                vk::MemoryMapFlags::empty(),
-            )?
-        };
-
-        unsafe {
-            let copy_size = std::cmp::min(pixels.len(), buffer_size as usize);
-            std::ptr::copy_nonoverlapping(pixels.as_ptr(), mapped_memory as *mut u8, copy_size);
-            self.device.unmap_memory(staging_buffer_memory);
-        }
-
-        debug!("Font data copied to staging buffer");
-
-        // Create a temporary command buffer for the texture upload
-        let command_pool_info = vk::CommandPoolCreateInfo::default()
-            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
-            .queue_family_index(self.graphics_queue_family_index);
-
-        let command_pool = unsafe {
-            self.device.create_command_pool(&command_pool_info, None)?
-        };
-
-        let alloc_info = vk::CommandBufferAllocateInfo::default()
-            .command_pool(command_pool)
-            .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(1);
-
-        let command_buffers = unsafe {
-            self.device.allocate_command_buffers(&alloc_info)?
-        };
-        let command_buffer = command_buffers[0];
-
-        // Begin command buffer
-        let begin_info = vk::CommandBufferBeginInfo::default()
-            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
-
-        unsafe {
-            self.device.begin_command_buffer(command_buffer, &begin_info)?;
-        }
-
-        // Transition image layout to TRANSFER_DST_OPTIMAL
-        let barrier = vk::ImageMemoryBarrier::default()
-            .old_layout(vk::ImageLayout::UNDEFINED)
-            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .image(self.font_texture.unwrap())
-            .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                base_mip_level: 0,
-                level_count: 1,
-                base_array_layer: 0,
-                layer_count: 1,
-            });
-
-        unsafe {
-            self.device.cmd_pipeline_barrier(
-                command_buffer,
-                vk::PipelineStageFlags::TOP_OF_PIPE,
-                vk::PipelineStageFlags::TRANSFER,
-                vk::DependencyFlags::empty(),
-                &[],
-                &[],
-                &[barrier],
-            );
-        }
-
-        // Copy buffer to image
-        let region = vk::BufferImageCopy::default()
-            .buffer_offset(0)
-            .buffer_row_length(0)
-            .buffer_image_height(0)
-            .image_subresource(vk::ImageSubresourceLayers {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                mip_level: 0,
-                base_array_layer: 0,
-                layer_count: 1,
-            })
-            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
-            .image_extent(vk::Extent3D { width, height, depth: 1 });
-
-        unsafe {
-            self.device.cmd_copy_buffer_to_image(
-                command_buffer,
-                staging_buffer,
-                self.font_texture.unwrap(),
-                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                &[region],
-            );
-        }
-
-        // Transition image layout to SHADER_READ_ONLY_OPTIMAL
-        let barrier = vk::ImageMemoryBarrier::default()
-            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .image(self.font_texture.unwrap())
-            .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                base_mip_level: 0,
-                level_count: 1,
-                base_array_layer: 0,
-                layer_count: 1,
-            });
-
-        unsafe {
-            self.device.cmd_pipeline_barrier(
-                command_buffer,
-                vk::PipelineStageFlags::TRANSFER,
-                vk::PipelineStageFlags::FRAGMENT_SHADER,
-                vk::DependencyFlags::empty(),
-                &[],
-                &[],
-                &[barrier],
-            );
-        }
-
-        // End and submit command buffer
-        unsafe {
-            self.device.end_command_buffer(command_buffer)?;
-        }
+        // Copy on the upload context (transfer queue when available) and only
+        // wait for it right before the first HUD draw
+        let byte_count = std::cmp::min(pixels.len(), (width * height * 4) as usize); // 4 bytes per pixel for RGBA
+        let extent = vk::Extent2D { width, height };
+        let ticket = self.upload.lock().unwrap()
+            .upload_to_image(&pixels[..byte_count], self.font_texture.unwrap(), extent)?;
+        self.font_upload = Some(ticket);
 
-        let command_buffers_array = [command_buffer];
-        let submit_info = vk::SubmitInfo::default()
-            .command_buffers(&command_buffers_array);
-
-        unsafe {
-            self.device.queue_submit(self.device.get_device_queue(self.graphics_queue_family_index, 0), &[submit_info], vk::Fence::null())?;
-            self.device.queue_wait_idle(self.device.get_device_queue(self.graphics_queue_family_index, 0))?;
-        }
-
-        // Cleanup
-        unsafe {
-            self.device.free_command_buffers(command_pool, &[command_buffer]);
-            self.device.destroy_command_pool(command_pool, None);
-            self.device.destroy_buffer(staging_buffer, None);
-            self.device.free_memory(staging_buffer_memory, None);
-        }
-
-        info!("Font texture upload completed successfully with proper layout transitions");
+        info!("Font texture upload submitted with proper layout transitions");
         
         // Verify the texture was uploaded correctly by checking descriptor set
         if self.descriptor_set.is_some() {
@@ -646,6 +773,12 @@ impl ImGuiVulkanBackend {
             return Err(AppError::HUD("Font texture not properly initialized".to_string()));
         }
         
+        // The font upload runs asynchronously; it has to be finished before the first draw samples it
+        if let Some(ticket) = self.font_upload.take() {
+            self.upload.lock().unwrap().wait(ticket)?;
+            debug!("Font texture upload finished");
+        }
+        
         debug!("Font texture ready, rendering ImGui");
 
         // Create vertex and index buffers
@@ -675,25 +808,13 @@ impl ImGuiVulkanBackend {
             // Bind pipeline
             self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline.unwrap());
 
-            // Bind descriptor set
-            debug!("Binding font descriptor set");
-            self.device.cmd_bind_descriptor_sets(
-                command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline_layout.unwrap(),
-                0,
-                &[self.descriptor_set.unwrap()],
-                &[],
-            );
-            debug!("Descriptor set bound");
-
             // Bind vertex and index buffers
-            if let (Some(vertex_buffer), Some(index_buffer)) = (self.vertex_buffer, self.index_buffer) {
-                self.device.cmd_bind_vertex_buffers(command_buffer, 0, &[vertex_buffer], &[0]);
-                self.device.cmd_bind_index_buffer(command_buffer, index_buffer, 0, vk::IndexType::UINT16);
+            if let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) {
+                self.device.cmd_bind_vertex_buffers(command_buffer, 0, &[vertex_buffer.handle()], &[0]);
+                self.device.cmd_bind_index_buffer(command_buffer, index_buffer.handle(), 0, vk::IndexType::UINT16);
             }
 
-            // Set viewport and scissor
+            // Set viewport; the scissor follows each command's clip rectangle
             let viewport = vk::Viewport::default()
                 .x(0.0)
                 .y(0.0)
@@ -702,34 +823,66 @@ impl ImGuiVulkanBackend {
                 .min_depth(0.0)
                 .max_depth(1.0);
 
-            let scissor = vk::Rect2D::default()
-                .offset(vk::Offset2D { x: 0, y: 0 })
-                .extent(vk::Extent2D {
-                    width: width as u32,
-                    height: height as u32,
-                });
-
             self.device.cmd_set_viewport(command_buffer, 0, &[viewport]);
-            self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
         }
 
-        // Draw each list
+        // Draw each command of each list with its texture and clip rectangle
         let mut index_offset = 0;
         let mut vertex_offset = 0;
+        let mut bound_texture = None;
 
         for (i, draw_list) in draw_data.draw_lists().enumerate() {
             debug!("Rendering draw list {} with {} vertices and {} indices",
                    i, draw_list.vtx_buffer().len(), draw_list.idx_buffer().len());
 
-            unsafe {
-                self.device.cmd_draw_indexed(
-                    command_buffer,
-                    draw_list.idx_buffer().len() as u32,
-                    1,
-                    index_offset as u32,
-                    vertex_offset as i32,
-                    0,
-                );
+            for command in draw_list.commands() {
+                match command {
+                    imgui::DrawCmd::Elements { count, cmd_params } => {
+                        let Some(scissor) = clip_to_scissor(cmd_params.clip_rect, draw_data.display_pos, draw_data.framebuffer_scale, [width, height]) else {
+                            continue;
+                        };
+                        if bound_texture != Some(cmd_params.texture_id) {
+                            let Some(texture) = self.textures.get(cmd_params.texture_id) else {
+                                debug!("Skipping ImGui draw with unregistered texture {:?}", cmd_params.texture_id);
+                                continue;
+                            };
+                            unsafe {
+                                self.device.cmd_bind_descriptor_sets(
+                                    command_buffer,
+                                    vk::PipelineBindPoint::GRAPHICS,
+                                    self.pipeline_layout.unwrap(),
+                                    0,
+                                    &[texture.descriptor_set],
+                                    &[],
+                                );
+                                self.device.cmd_push_constants(
+                                    command_buffer,
+                                    self.pipeline_layout.unwrap(),
+                                    vk::ShaderStageFlags::FRAGMENT,
+                                    TEXTURE_VIEW_OFFSET,
+                                    bytemuck::bytes_of(&texture.view_constants()),
+                                );
+                            }
+                            bound_texture = Some(cmd_params.texture_id);
+                        }
+
+                        unsafe {
+                            self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+                            self.device.cmd_draw_indexed(
+                                command_buffer,
+                                count as u32,
+                                1,
+                                (index_offset + cmd_params.idx_offset) as u32,
+                                (vertex_offset + cmd_params.vtx_offset) as i32,
+                                0,
+                            );
+                        }
+                    }
+                    // Nothing but this backend changes the state between commands
+                    imgui::DrawCmd::ResetRenderState => {}
+                    // The HUD registers no draw callbacks
+                    imgui::DrawCmd::RawCallback { .. } => {}
+                }
             }
 
             index_offset += draw_list.idx_buffer().len();
@@ -753,313 +906,69 @@ impl ImGuiVulkanBackend {
                 return;
             }
             debug!("GPU idle confirmed, safe to cleanup ImGui buffers");
-
-            if let Some(vertex_buffer) = self.vertex_buffer {
-                self.device.destroy_buffer(vertex_buffer, None);
-            }
-            if let Some(vertex_memory) = self.vertex_buffer_memory {
-                self.device.free_memory(vertex_memory, None);
-            }
-            if let Some(index_buffer) = self.index_buffer {
-                self.device.destroy_buffer(index_buffer, None);
-            }
-            if let Some(index_memory) = self.index_buffer_memory {
-                self.device.free_memory(index_memory, None);
-            }
         }
 
         self.vertex_buffer = None;
-        self.vertex_buffer_memory = None;
         self.index_buffer = None;
-        self.index_buffer_memory = None;
         self.vertex_count = 0;
         self.index_count = 0;
 
         debug!("Dynamic ImGui buffers cleaned up safely");
     }
 
-    fn create_buffers(&mut self, draw_data: &imgui::DrawData) -> Result<(), AppError> {
-        // Calculate total vertex and index counts
-        let mut total_vertices = 0;
-        let mut total_indices = 0;
-
-        for draw_list in draw_data.draw_lists() {
-            total_vertices += draw_list.vtx_buffer().len();
-            total_indices += draw_list.idx_buffer().len();
-        }
-
-        if total_vertices == 0 || total_indices == 0 {
-            return Ok(());
+    /// Hand the dynamic buffers to the deletion queue instead of waiting for the GPU
+    fn retire_dynamic_buffers(&mut self) {
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        for buffer in [self.vertex_buffer.take(), self.index_buffer.take()].into_iter().flatten() {
+            buffer.retire(&mut deletion_queue);
         }
+        self.vertex_count = 0;
+        self.index_count = 0;
+        debug!("Dynamic ImGui buffers queued for deletion");
+    }
 
-        // REUSE STRATEGY: Only recreate buffers if they're too small or don't exist
-        let vertex_size = (total_vertices * std::mem::size_of::<imgui::DrawVert>()) as u64;
-        let index_size = (total_indices * std::mem::size_of::<u16>()) as u64;
-
-        // Check if we need to recreate buffers
-        let mut need_recreate = false;
-        if self.vertex_buffer.is_none() || self.index_buffer.is_none() {
-            need_recreate = true;
-        } else {
-            // Check if current buffers are large enough
-            let current_vertex_size = if let Some(_memory) = self.vertex_buffer_memory {
-                let requirements = unsafe { self.device.get_buffer_memory_requirements(*self.vertex_buffer.as_ref().unwrap()) };
-                requirements.size
-            } else {
-                0
-            };
-
-            let current_index_size = if let Some(_memory) = self.index_buffer_memory {
-                let requirements = unsafe { self.device.get_buffer_memory_requirements(*self.index_buffer.as_ref().unwrap()) };
-                requirements.size
-            } else {
-                0
-            };
+    fn create_buffers(&mut self, draw_data: &imgui::DrawData) -> Result<(), AppError> {
+        let vertices: Vec<ImguiVertex> = draw_data.draw_lists()
+            .flat_map(|draw_list| draw_list.vtx_buffer().iter())
+            .map(|vertex| ImguiVertex { pos: vertex.pos, uv: vertex.uv, col: vertex.col })
+            .collect();
+        let indices: Vec<u16> = draw_data.draw_lists()
+            .flat_map(|draw_list| draw_list.idx_buffer().iter().copied())
+            .collect();
 
-            if vertex_size > current_vertex_size || index_size > current_index_size {
-                need_recreate = true;
-                // Clean up old buffers before creating larger ones
-                self.cleanup_dynamic_buffers();
-            }
+        if vertices.is_empty() || indices.is_empty() {
+            return Ok(());
         }
 
-        if need_recreate {
-            debug!("Creating new ImGui buffers: {} vertices, {} indices", total_vertices, total_indices);
-
-            // Create vertex buffer
-            let vertex_buffer_size = (total_vertices * mem::size_of::<ImguiVertex>()) as u64;
-            let vertex_buffer_info = vk::BufferCreateInfo::default()
-                .size(vertex_buffer_size)
-                .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
-                .sharing_mode(vk::SharingMode::EXCLUSIVE);
-
-            self.vertex_buffer = unsafe {
-                Some(self.device.create_buffer(&vertex_buffer_info, None)?)
-            };
-
-            // Create index buffer
-            let index_buffer_size = (total_indices * mem::size_of::<u16>()) as u64;
-            let index_buffer_info = vk::BufferCreateInfo::default()
-                .size(index_buffer_size)
-                .usage(vk::BufferUsageFlags::INDEX_BUFFER)
-                .sharing_mode(vk::SharingMode::EXCLUSIVE);
-
-            self.index_buffer = unsafe {
-                Some(self.device.create_buffer(&index_buffer_info, None)?)
-            };
-
-            // Allocate memory for vertex buffer
-            let vertex_mem_requirements = unsafe { self.device.get_buffer_memory_requirements(self.vertex_buffer.unwrap()) };
-            debug!("Vertex buffer memory requirements: size={}, type_bits={:032b}", vertex_mem_requirements.size, vertex_mem_requirements.memory_type_bits);
-
-            let vertex_alloc_info = vk::MemoryAllocateInfo::default()
-                .allocation_size(vertex_mem_requirements.size)
-                .memory_type_index(self.find_memory_type(
-                    vertex_mem_requirements.memory_type_bits,
-                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-                )?);
-
-            self.vertex_buffer_memory = unsafe {
-                Some(self.device.allocate_memory(&vertex_alloc_info, None)?)
-            };
-
-            unsafe {
-                self.device.bind_buffer_memory(self.vertex_buffer.unwrap(), self.vertex_buffer_memory.unwrap(), 0)?;
-            }
-
-            // Allocate memory for index buffer
-            let index_mem_requirements = unsafe { self.device.get_buffer_memory_requirements(self.index_buffer.unwrap()) };
-            debug!("Index buffer memory requirements: size={}, type_bits={:032b}", index_mem_requirements.size, index_mem_requirements.memory_type_bits);
-
-            let index_alloc_info = vk::MemoryAllocateInfo::default()
-                .allocation_size(index_mem_requirements.size)
-                .memory_type_index(self.find_memory_type(
-                    index_mem_requirements.memory_type_bits,
-                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-                )?);
+        let vertex_size = mem::size_of_val(vertices.as_slice()) as u64;
+        let index_size = mem::size_of_val(indices.as_slice()) as u64;
 
-            self.index_buffer_memory = unsafe {
-                Some(self.device.allocate_memory(&index_alloc_info, None)?)
+        // REUSE STRATEGY: Only recreate buffers if they're too small or don't exist
+        let too_small = |buffer: &Option<GpuBuffer>, size: u64| buffer.as_ref().is_none_or(|buffer| buffer.size() < size);
+        if too_small(&self.vertex_buffer, vertex_size) || too_small(&self.index_buffer, index_size) {
+            // Earlier frames may still read the old buffers
+            self.retire_dynamic_buffers();
+
+            debug!("Creating new ImGui buffers: {} vertices, {} indices", vertices.len(), indices.len());
+            let (Some(physical_device), Some(instance)) = (self.physical_device, &self.instance) else {
+                return Err(AppError::HUD("ImGui backend has no physical device for its buffers".to_string()));
             };
-
-            unsafe {
-                self.device.bind_buffer_memory(self.index_buffer.unwrap(), self.index_buffer_memory.unwrap(), 0)?;
-            }
-
-        // Upload vertex data - map the entire buffer once
-        debug!("Mapping vertex buffer memory: size={}, buffer={:?}", vertex_buffer_size, self.vertex_buffer.unwrap());
-        let vertex_mapped_memory = unsafe {
-            self.device.map_memory(
-                self.vertex_buffer_memory.unwrap(),
-                0,
-                vertex_buffer_size,
-                vk::MemoryMapFlags::empty(),
-            )?
-        };
-        debug!("Vertex buffer memory mapped successfully");
-        
-        let mut vertex_offset = 0;
-        for (list_idx, draw_list) in draw_data.draw_lists().enumerate() {
-            let vertices = draw_list.vtx_buffer();
-            let vertex_size = vertices.len() * mem::size_of::<ImguiVertex>();
-            
-            if vertex_size > 0 {
-                debug!("Processing draw list {} with {} vertices", list_idx, vertices.len());
-                
-                // Log first few vertices for debugging
-                for (i, vertex) in vertices.iter().take(3).enumerate() {
-                    debug!("Vertex {}: pos=({:.2},{:.2}), uv=({:.3},{:.3}), col=({},{},{},{})",
-                           i, vertex.pos[0], vertex.pos[1], vertex.uv[0], vertex.uv[1],
-                           vertex.col[0], vertex.col[1], vertex.col[2], vertex.col[3]);
-                }
-                
-                unsafe {
-                    let dst = vertex_mapped_memory.add(vertex_offset) as *mut ImguiVertex;
-                    // Convert DrawVert to ImguiVertex
-                    for (i, vertex) in vertices.iter().enumerate() {
-                        let imgui_vertex = ImguiVertex {
-                            pos: [vertex.pos[0], vertex.pos[1]],
-                            uv: [vertex.uv[0], vertex.uv[1]],
-                            col: [
-                                vertex.col[0],
-                                vertex.col[1],
-                                vertex.col[2],
-                                vertex.col[3],
-                            ],
-                        };
-                        dst.add(i).write(imgui_vertex);
-                    }
-                }
-            }
-            
-            vertex_offset += vertex_size;
-        }
-        
-        // Unmap vertex memory
-        unsafe {
-            self.device.unmap_memory(self.vertex_buffer_memory.unwrap());
-        }
-
-        // Upload index data - map the entire buffer once
-        debug!("Mapping index buffer memory: size={}, buffer={:?}", index_buffer_size, self.index_buffer.unwrap());
-        let index_mapped_memory = unsafe {
-            self.device.map_memory(
-                self.index_buffer_memory.unwrap(),
-                0,
-                index_buffer_size,
-                vk::MemoryMapFlags::empty(),
-            )?
-        };
-        debug!("Index buffer memory mapped successfully");
-        
-        let mut index_offset = 0;
-        for draw_list in draw_data.draw_lists() {
-            let indices = draw_list.idx_buffer();
-            let index_size = indices.len() * mem::size_of::<u16>();
-            
-            if index_size > 0 {
-                unsafe {
-                    let dst = index_mapped_memory.add(index_offset) as *mut u16;
-                    dst.copy_from_nonoverlapping(indices.as_ptr(), indices.len());
-                }
-            }
-            
-            index_offset += index_size;
-        }
-        
-        // Unmap index memory
-        unsafe {
-            self.device.unmap_memory(self.index_buffer_memory.unwrap());
-        }
-        
-          debug!("Uploaded {} vertices and {} indices to GPU buffers", total_vertices, total_indices);
+            let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+            let host_visible = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+            self.vertex_buffer = Some(GpuBuffer::new(&self.device, &memory_properties, vertex_size, vk::BufferUsageFlags::VERTEX_BUFFER, host_visible)?);
+            self.index_buffer = Some(GpuBuffer::new(&self.device, &memory_properties, index_size, vk::BufferUsageFlags::INDEX_BUFFER, host_visible)?);
         } else {
-            debug!("Reusing existing ImGui buffers: {} vertices, {} indices", total_vertices, total_indices);
-
-            // Reuse existing buffers - just upload new data
-            let vertex_buffer_size = (total_vertices * mem::size_of::<ImguiVertex>()) as u64;
-            let index_buffer_size = (total_indices * mem::size_of::<u16>()) as u64;
-
-            // Upload vertex data to existing buffer
-            debug!("Mapping existing vertex buffer memory: size={}, buffer={:?}", vertex_buffer_size, self.vertex_buffer.unwrap());
-            let vertex_mapped_memory = unsafe {
-                self.device.map_memory(
-                    self.vertex_buffer_memory.unwrap(),
-                    0,
-                    vertex_buffer_size,
-                    vk::MemoryMapFlags::empty(),
-                )?
-            };
-            debug!("Existing vertex buffer memory mapped successfully");
-
-            let mut vertex_offset = 0;
-            for (_list_idx, draw_list) in draw_data.draw_lists().enumerate() {
-                let vertices = draw_list.vtx_buffer();
-                let vertex_size = vertices.len() * mem::size_of::<ImguiVertex>();
-
-                if vertex_size > 0 {
-                    unsafe {
-                        let dst = vertex_mapped_memory.add(vertex_offset) as *mut ImguiVertex;
-                        // Convert DrawVert to ImguiVertex
-                        for (i, vertex) in vertices.iter().enumerate() {
-                            let imgui_vertex = ImguiVertex {
-                                pos: [vertex.pos[0], vertex.pos[1]],
-                                uv: [vertex.uv[0], vertex.uv[1]],
-                                col: [
-                                    vertex.col[0],
-                                    vertex.col[1],
-                                    vertex.col[2],
-                                    vertex.col[3],
-                                ],
-                            };
-                            dst.add(i).write(imgui_vertex);
-                        }
-                    }
-                }
-
-                vertex_offset += vertex_size;
-            }
-
-            unsafe {
-                self.device.unmap_memory(self.vertex_buffer_memory.unwrap());
-            }
-
-            // Upload index data to existing buffer
-            debug!("Mapping existing index buffer memory: size={}, buffer={:?}", index_buffer_size, self.index_buffer.unwrap());
-            let index_mapped_memory = unsafe {
-                self.device.map_memory(
-                    self.index_buffer_memory.unwrap(),
-                    0,
-                    index_buffer_size,
-                    vk::MemoryMapFlags::empty(),
-                )?
-            };
-            debug!("Existing index buffer memory mapped successfully");
-
-            let mut index_offset = 0;
-            for draw_list in draw_data.draw_lists() {
-                let indices = draw_list.idx_buffer();
-                let index_size = indices.len() * mem::size_of::<u16>();
-
-                if index_size > 0 {
-                    unsafe {
-                        let dst = index_mapped_memory.add(index_offset) as *mut u16;
-                        dst.copy_from_nonoverlapping(indices.as_ptr(), indices.len());
-                    }
-                }
-
-                index_offset += index_size;
-            }
-
-            unsafe {
-                self.device.unmap_memory(self.index_buffer_memory.unwrap());
-            }
+            debug!("Reusing existing ImGui buffers: {} vertices, {} indices", vertices.len(), indices.len());
+        }
 
-            debug!("Reused existing buffers with new data: {} vertices, {} indices", total_vertices, total_indices);
+        if let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) {
+            vertex_buffer.write_bytes(0, bytemuck::cast_slice(&vertices))?;
+            index_buffer.write_bytes(0, bytemuck::cast_slice(&indices))?;
         }
+        debug!("Uploaded {} vertices and {} indices to GPU buffers", vertices.len(), indices.len());
 
-        self.vertex_count = total_vertices;
-        self.index_count = total_indices;
+        self.vertex_count = vertices.len();
+        self.index_count = indices.len();
 
         Ok(())
     }
@@ -1073,9 +982,20 @@ impl ImGuiVulkanBackend {
         
         debug!("Cleaning up ImGui Vulkan backend");
         
+        // The font texture may still be the target of a pending upload
+        if let Some(ticket) = self.font_upload.take() {
+            if let Err(e) = self.upload.lock().unwrap().wait(ticket) {
+                warn!("Font upload did not finish before cleanup: {}", e);
+            }
+        }
+        
         // First clean up dynamic buffers
         self.cleanup_dynamic_buffers();
         
+        // Replaced font textures may still hold descriptor sets from our pool;
+        // the device is idle at shutdown, so drain the queue before the pool goes
+        self.deletion_queue.lock().unwrap().destroy_all(&self.device);
+        
         // Destroy resources in proper dependency order
         // With proper shutdown sequencing, no device_wait_idle() should be needed
         unsafe {
@@ -1102,6 +1022,10 @@ impl ImGuiVulkanBackend {
                 debug!("Destroying ImGui font texture sampler");
                 self.device.destroy_sampler(sampler, None);
             }
+            if let Some(sampler) = self.user_texture_sampler {
+                debug!("Destroying ImGui user texture sampler");
+                self.device.destroy_sampler(sampler, None);
+            }
             
             // 5. Destroy image view (now safe since descriptor pool is destroyed)
             if let Some(view) = self.font_texture_view {
@@ -1118,7 +1042,7 @@ impl ImGuiVulkanBackend {
             // 7. Free memory
             if let Some(memory) = self.font_texture_memory {
                 debug!("Freeing ImGui font texture memory");
-                self.device.free_memory(memory, None);
+                memory_usage::free(&self.device, memory);
             }
             
             // 8. Destroy descriptor set layout last
@@ -1132,6 +1056,9 @@ impl ImGuiVulkanBackend {
         self.font_texture = None;
         self.font_texture_view = None;
         self.font_texture_sampler = None;
+        self.user_texture_sampler = None;
+        self.textures = imgui::Textures::new();
+        self.user_texture_count = 0;
         self.font_texture_memory = None;
         self.descriptor_set_layout = None;
         self.descriptor_pool = None;
@@ -1147,4 +1074,24 @@ impl Drop for ImGuiVulkanBackend {
     fn drop(&mut self) {
         self.cleanup();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_rects_become_scissors_inside_the_framebuffer() {
+        let scissor = clip_to_scissor([10.0, 20.0, 110.0, 70.0], [0.0, 0.0], [1.0, 1.0], [800.0, 600.0]).unwrap();
+        assert_eq!(scissor.offset, vk::Offset2D { x: 10, y: 20 });
+        assert_eq!(scissor.extent, vk::Extent2D { width: 100, height: 50 });
+
+        // Clipped to the framebuffer, and nothing left when fully outside
+        let scissor = clip_to_scissor([-5.0, 590.0, 50.0, 650.0], [0.0, 0.0], [1.0, 1.0], [800.0, 600.0]).unwrap();
+        assert_eq!(scissor.offset, vk::Offset2D { x: 0, y: 590 });
+        assert_eq!(scissor.extent, vk::Extent2D { width: 50, height: 10 });
+        assert!(clip_to_scissor([900.0, 0.0, 950.0, 10.0], [0.0, 0.0], [1.0, 1.0], [800.0, 600.0]).is_none());
+
+        assert_eq!(TextureChannel::ALL.map(TextureChannel::shader_mode), [0, 1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,61 @@
+//! Background jobs indicator
+//!
+//! A small window in the bottom-left corner of the screen listing the jobs
+//! queued or running on the job pool, with how long each has been running.
+//! It is only shown while there are jobs and never takes mouse input.
+
+use imgui::Ui;
+use crate::jobs::JobStatus;
+use crate::locale::{named, tr_args};
+use crate::hud::toolbar::ScreenRect;
+
+/// Jobs indicator state
+pub struct JobsIndicator {
+    /// Queued and running jobs, refreshed by the ECS world every frame
+    pub jobs: Vec<JobStatus>,
+}
+
+impl Default for JobsIndicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobsIndicator {
+    /// Create an indicator with no jobs
+    pub fn new() -> Self {
+        Self { jobs: Vec::new() }
+    }
+
+    /// Render the indicator if there are jobs
+    ///
+    /// # Arguments
+    /// * `ui` - ImGui frame
+    /// * `area` - Screen area the indicator is anchored in
+    pub fn render(&self, ui: &Ui, area: ScreenRect) {
+        if self.jobs.is_empty() {
+            return;
+        }
+
+        let window = ui.window("##jobs")
+            .position([area.min[0] + 10.0, area.max[1] - 10.0], imgui::Condition::Always)
+            .position_pivot([0.0, 1.0])
+            .always_auto_resize(true)
+            .no_decoration()
+            .mouse_inputs(false)
+            .nav_inputs(false)
+            .nav_focus(false)
+            .focus_on_appearing(false)
+            .bg_alpha(0.6);
+        if let Some(_token) = window.begin() {
+            ui.text_disabled(tr_args("jobs.title", &[("count", &self.jobs.len())]));
+            for job in &self.jobs {
+                let name = named("jobs", &job.name);
+                match job.started {
+                    Some(_) => ui.text(tr_args("jobs.running", &[("name", &name), ("seconds", &format!("{:.1}", job.elapsed().as_secs_f32()))])),
+                    None => ui.text_disabled(tr_args("jobs.queued", &[("name", &name)])),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! World space label overlay
+//!
+//! Draws the labels collected by the ECS world on top of the scene: each
+//! label is projected through the camera of every viewport it is visible
+//! in and drawn centered above that point on the ImGui background draw
+//! list, so windows stay on top of it.
+
+use ash::vk;
+use cgmath::Vector3;
+use imgui::Ui;
+use crate::config;
+use crate::vulkan::viewport::ViewportSettings;
+
+/// A label resolved for the current frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldLabel {
+    /// World space anchor
+    pub position: Vector3<f32>,
+    /// Text to draw
+    pub text: String,
+    /// Text color (RGBA)
+    pub color: [f32; 4],
+}
+
+/// Label overlay state
+pub struct LabelOverlay {
+    /// Whether labels are drawn
+    pub is_visible: bool,
+
+    /// Latest labels from the ECS world
+    pub labels: Vec<WorldLabel>,
+}
+
+impl Default for LabelOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LabelOverlay {
+    /// Create a visible overlay without labels
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            labels: Vec::new(),
+        }
+    }
+
+    /// Draw every label in every viewport of the layout
+    ///
+    /// # Arguments
+    /// * `ui` - The ImGui frame
+    /// * `viewports` - Layout and cameras the scene is drawn with
+    pub fn render(&self, ui: &Ui, viewports: &ViewportSettings) {
+        if !self.is_visible || self.labels.is_empty() {
+            return;
+        }
+
+        let [width, height] = ui.io().display_size;
+        let extent = vk::Extent2D { width: width.max(1.0) as u32, height: height.max(1.0) as u32 };
+        let draw_list = ui.get_background_draw_list();
+        let shadow = config::labels::SHADOW_COLOR;
+
+        for (rect, viewport) in viewports.rects(extent) {
+            let camera = viewports.camera(viewport.kind);
+            let min = [rect.offset.x as f32, rect.offset.y as f32];
+            let max = [min[0] + rect.extent.width as f32, min[1] + rect.extent.height as f32];
+            // Keep labels inside their quadrant
+            draw_list.with_clip_rect_intersect(min, max, || {
+                for label in &self.labels {
+                    let Some([x, y]) = camera.project(label.position, &rect) else {
+                        continue;
+                    };
+                    let [text_width, text_height] = ui.calc_text_size(&label.text);
+                    let position = [x - text_width * 0.5, y - text_height - config::labels::ANCHOR_GAP];
+                    draw_list.add_text([position[0] + 1.0, position[1] + 1.0], shadow, &label.text);
+                    draw_list.add_text(position, label.color, &label.text);
+                }
+            });
+        }
+    }
+}
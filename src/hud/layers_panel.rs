@@ -0,0 +1,140 @@
+//! Layers panel
+//!
+//! Puts the SDF shapes, CSG composites, meshes and texts on render layers,
+//! one checkbox per layer. Which layers each viewport shows is set in the viewports
+//! panel with the same checkboxes (`layer_toggles`). The ECS world
+//! refreshes the snapshot every frame and applies the queued edits.
+
+use imgui::Ui;
+use legion::Entity;
+use crate::config;
+use crate::ecs::components::SDFShapeType;
+use crate::hud::edit_queue::EditQueue;
+use crate::locale::{panel_title, tr, tr_args};
+
+/// What an entity on layers is
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerEntityKind {
+    Shape(SDFShapeType),
+    Composite,
+    Mesh,
+    Text,
+}
+
+/// Snapshot of the layers of a shape, composite, mesh or text
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerEntityView {
+    /// The entity
+    pub entity: Entity,
+    pub kind: LayerEntityKind,
+    /// Layer mask, see `RenderLayers`
+    pub layers: u32,
+}
+
+/// Put an entity on a set of layers
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerEdit {
+    pub entity: Entity,
+    /// Layer mask, see `RenderLayers`
+    pub layers: u32,
+}
+
+/// Layers panel state
+pub struct LayersPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the shapes, composites, meshes and texts
+    pub entities: Vec<LayerEntityView>,
+
+    /// Layers shown by at least one drawn viewport
+    pub visible_layers: u32,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: EditQueue<Entity, LayerEdit>,
+}
+
+impl Default for LayersPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayersPanel {
+    /// Create a new layers panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            entities: Vec::new(),
+            visible_layers: config::layers::ALL,
+            pending_edits: EditQueue::default(),
+        }
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<LayerEdit> {
+        self.pending_edits.take()
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Layers"))
+            .position([960.0, 640.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 300.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            ui.text_disabled(tr("layers.hint"));
+            ui.separator();
+
+            if self.entities.is_empty() {
+                ui.text_disabled(tr("layers.empty"));
+                return;
+            }
+
+            let mut edits = Vec::new();
+            for (index, view) in self.entities.iter_mut().enumerate() {
+                let _id = ui.push_id_usize(index);
+                let entity = format!("{:?}", view.entity);
+                match &view.kind {
+                    LayerEntityKind::Shape(shape_type) => ui.text(format!("{:?} {}", shape_type, entity)),
+                    LayerEntityKind::Composite => ui.text(tr_args("layers.composite", &[("entity", &entity)])),
+                    LayerEntityKind::Mesh => ui.text(tr_args("layers.mesh", &[("entity", &entity)])),
+                    LayerEntityKind::Text => ui.text(tr_args("layers.text", &[("entity", &entity)])),
+                }
+                if view.layers & self.visible_layers == 0 {
+                    ui.same_line();
+                    ui.text_disabled(tr("layers.hidden"));
+                }
+                if layer_toggles(ui, &mut view.layers) {
+                    edits.push(LayerEdit { entity: view.entity, layers: view.layers });
+                }
+            }
+            for edit in edits {
+                self.pending_edits.push(Some(edit.entity), edit);
+            }
+        }
+    }
+}
+
+/// One checkbox per layer, numbered from 1, on a single line
+///
+/// # Arguments
+/// * `layers` - Layer mask to edit
+///
+/// # Returns
+/// Whether a layer was switched
+pub fn layer_toggles(ui: &Ui, layers: &mut u32) -> bool {
+    let mut changed = false;
+    for number in 1..=config::layers::COUNT {
+        if number > 1 {
+            ui.same_line();
+        }
+        changed |= ui.checkbox_flags(number.to_string(), layers, 1 << (number - 1));
+    }
+    changed
+}
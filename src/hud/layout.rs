@@ -0,0 +1,177 @@
+//! HUD layout persistence
+//!
+//! Window positions, sizes and docking come from ImGui's own ini data; which
+//! panels are open is our state and is kept next to it in a small
+//! `name=0|1` file. Both are loaded when the HUD is created and written back
+//! when ImGui asks to save, when a panel is opened or closed, and on exit.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use imgui::{Condition, StyleVar, Ui, WindowFlags};
+use crate::error::{Result, ResultExt, Subsystem};
+use crate::hud::toolbar::ScreenRect;
+use crate::locale::{panel_title, title};
+
+/// Open/closed state of the HUD panels, keyed by window title
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PanelLayout {
+    /// Visibility per panel
+    visibility: BTreeMap<String, bool>,
+}
+
+impl PanelLayout {
+    /// Capture the current visibility flags
+    pub fn capture(panels: &[(&str, &mut bool)]) -> Self {
+        Self {
+            visibility: panels.iter().map(|(name, visible)| (name.to_string(), **visible)).collect(),
+        }
+    }
+
+    /// Record the visibility of one panel
+    pub fn set(&mut self, name: &str, visible: bool) {
+        self.visibility.insert(name.to_string(), visible);
+    }
+
+    /// Set the visibility flags of the panels this layout knows about
+    ///
+    /// Panels missing from the layout (e.g. added since it was saved) keep their default.
+    pub fn apply(&self, panels: &mut [(&str, &mut bool)]) {
+        for (name, visible) in panels.iter_mut() {
+            if let Some(&saved) = self.visibility.get(*name) {
+                **visible = saved;
+            }
+        }
+    }
+
+    /// Parse `name=0|1` lines, ignoring blank lines, `#` comments and malformed lines
+    pub fn parse(text: &str) -> Self {
+        let visibility = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (name, value) = line.split_once('=')?;
+                let visible = match value.trim() {
+                    "1" | "true" => true,
+                    "0" | "false" => false,
+                    _ => return None,
+                };
+                Some((name.trim().to_string(), visible))
+            })
+            .collect();
+        Self { visibility }
+    }
+
+    /// Serialize as `name=0|1` lines
+    pub fn serialize(&self) -> String {
+        let mut text = String::from("# HUD panel visibility (1 = open)\n");
+        for (name, visible) in &self.visibility {
+            text.push_str(&format!("{}={}\n", name, *visible as u8));
+        }
+        text
+    }
+
+    /// Load a layout file
+    ///
+    /// # Returns
+    /// None if the file does not exist or cannot be read
+    pub fn load(path: &Path) -> Option<Self> {
+        fs::read_to_string(path).ok().map(|text| Self::parse(&text))
+    }
+
+    /// Write the layout file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.serialize()).context(Subsystem::Hud, format!("write {}", path.display()))
+    }
+}
+
+/// Render the dock space panels dock into, covering `area`
+///
+/// ImGui's viewport dock space always fills the whole work area, which it
+/// resets every frame, so the dock space lives in a borderless host window
+/// kept over the area next to the toolbar instead. The central node stays
+/// transparent and lets input through to the scene.
+///
+/// # Arguments
+/// * `ui` - ImGui frame
+/// * `area` - Screen area left to the panels by the toolbar
+pub fn render_dock_space(ui: &Ui, area: ScreenRect) {
+    let flags = WindowFlags::NO_DECORATION
+        | WindowFlags::NO_MOVE
+        | WindowFlags::NO_DOCKING
+        | WindowFlags::NO_BRING_TO_FRONT_ON_FOCUS
+        | WindowFlags::NO_NAV_FOCUS
+        | WindowFlags::NO_BACKGROUND
+        | WindowFlags::NO_SAVED_SETTINGS;
+    let rounding = ui.push_style_var(StyleVar::WindowRounding(0.0));
+    let border = ui.push_style_var(StyleVar::WindowBorderSize(0.0));
+    let padding = ui.push_style_var(StyleVar::WindowPadding([0.0, 0.0]));
+    let host = ui.window("##dock_space_host")
+        .position(area.min, Condition::Always)
+        .size(area.size(), Condition::Always)
+        .flags(flags)
+        .begin();
+    // The host's style must not leak into the panels docked inside it
+    padding.pop();
+    border.pop();
+    rounding.pop();
+    if host.is_some() {
+        // imgui-rs has no wrapper for a dock space inside a window
+        unsafe {
+            imgui::sys::igDockSpace(
+                imgui::sys::igGetID_Str(b"dock_space\0".as_ptr().cast()),
+                imgui::sys::ImVec2 { x: 0.0, y: 0.0 },
+                imgui::sys::ImGuiDockNodeFlags_PassthruCentralNode as i32,
+                std::ptr::null(),
+            );
+        }
+    }
+}
+
+/// Render the "Panels" window with one checkbox per panel
+///
+/// # Arguments
+/// * `ui` - ImGui frame
+/// * `area` - Screen area next to the toolbar the window first opens in
+/// * `panels` - Title and visibility flag of every panel
+pub fn render_panel_toggles(ui: &Ui, area: ScreenRect, panels: &mut [(&str, &mut bool)]) {
+    let window = ui.window(title("layout.panels", "Panels"))
+        .position([area.min[0] + 20.0, area.min[1] + 10.0], imgui::Condition::FirstUseEver)
+        .always_auto_resize(true)
+        .bg_alpha(0.9);
+
+    if let Some(_token) = window.begin() {
+        for (name, visible) in panels.iter_mut() {
+            ui.checkbox(panel_title(name), *visible);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_apply() {
+        let (mut stats, mut console) = (true, false);
+        let layout = PanelLayout::capture(&[("Stats", &mut stats), ("Console", &mut console)]);
+        let parsed = PanelLayout::parse(&layout.serialize());
+        assert_eq!(parsed, layout);
+
+        let (mut stats, mut console, mut export) = (false, true, true);
+        parsed.apply(&mut [("Stats", &mut stats), ("Console", &mut console), ("Export", &mut export)]);
+        assert_eq!((stats, console, export), (true, false, true));
+    }
+
+    #[test]
+    fn test_parse_skips_malformed_lines() {
+        let layout = PanelLayout::parse("# comment\nStats=1\nbroken\nConsole=maybe\n\nExport = 0\n");
+        let (mut stats, mut console, mut export) = (false, true, true);
+        layout.apply(&mut [("Stats", &mut stats), ("Console", &mut console), ("Export", &mut export)]);
+        assert_eq!((stats, console, export), (true, true, false));
+    }
+}
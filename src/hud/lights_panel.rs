@@ -0,0 +1,240 @@
+//! Lights panel
+//!
+//! Lists the scene's light entities and edits their kind, color, intensity,
+//! radius, position, direction and spot angle, and adds and removes lights.
+//! The ECS world refreshes the snapshot every frame and applies the queued
+//! edits. The panel also draws a gizmo for every light in the viewports: a
+//! dot in the light's color at its position and, for directional and spot
+//! lights, a line along the direction it shines in. A directional light's
+//! position only places its gizmo.
+
+use ash::vk;
+use cgmath::{InnerSpace, Vector3};
+use imgui::{TreeNodeFlags, Ui};
+use legion::Entity;
+use crate::color::linear_to_srgb_rgba;
+use crate::config;
+use crate::ecs::lights::{Light, LightKind};
+use crate::hud::background_panel::edit_linear_color;
+use crate::hud::edit_queue::EditQueue;
+use crate::locale::{named, panel_title, tr, tr_args};
+use crate::vulkan::viewport::ViewportSettings;
+
+/// Snapshot of a light
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightView {
+    /// Light entity
+    pub entity: Entity,
+    pub light: Light,
+}
+
+/// Change to the scene's lights
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightEdit {
+    /// Spawn a light of a kind at a position
+    Add { kind: LightKind, position: [f32; 3] },
+    /// Replace a light
+    Set { entity: Entity, light: Light },
+    /// Despawn a light
+    Remove { entity: Entity },
+}
+
+impl LightEdit {
+    /// Light entity the edit changes, if it already exists
+    fn entity(&self) -> Option<Entity> {
+        match self {
+            LightEdit::Add { .. } => None,
+            LightEdit::Set { entity, .. } | LightEdit::Remove { entity } => Some(*entity),
+        }
+    }
+}
+
+/// Lights panel state
+pub struct LightsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the lights
+    pub lights: Vec<LightView>,
+
+    /// Whether light gizmos are drawn in the viewports
+    pub show_gizmos: bool,
+
+    /// Kind of the light the add button spawns
+    new_kind: LightKind,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: EditQueue<Entity, LightEdit>,
+}
+
+impl Default for LightsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LightsPanel {
+    /// Create a new lights panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            lights: Vec::new(),
+            show_gizmos: true,
+            new_kind: LightKind::default(),
+            pending_edits: EditQueue::default(),
+        }
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<LightEdit> {
+        self.pending_edits.take()
+    }
+
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Lights"))
+            .position([960.0, 240.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 420.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            ui.checkbox(tr("lights.show_gizmos"), &mut self.show_gizmos);
+            let mut kind_index = LightKind::ALL.iter().position(|kind| *kind == self.new_kind).unwrap_or(0);
+            if ui.combo("##NewLightKind", &mut kind_index, &LightKind::ALL, |kind| named("light_kinds", kind.name()).into()) {
+                self.new_kind = LightKind::ALL[kind_index];
+            }
+            ui.same_line();
+            if ui.button(tr("lights.add")) {
+                let position = Light::default().position.into();
+                self.pending_edits.push(None, LightEdit::Add { kind: self.new_kind, position });
+            }
+            if self.lights.len() > config::rendering::MAX_SDF_LIGHTS {
+                ui.text_wrapped(tr_args("lights.over_limit", &[("max", &config::rendering::MAX_SDF_LIGHTS.to_string())]));
+            }
+            ui.separator();
+
+            if self.lights.is_empty() {
+                ui.text_disabled(tr("lights.empty"));
+                return;
+            }
+
+            let mut edits = Vec::new();
+            for (index, view) in self.lights.iter_mut().enumerate() {
+                let _id = ui.push_id_usize(index);
+                let label = tr_args("lights.light", &[
+                    ("kind", &named("light_kinds", view.light.kind.name())),
+                    ("entity", &format!("{:?}", view.entity)),
+                ]);
+                if !ui.collapsing_header(&label, TreeNodeFlags::empty()) {
+                    continue;
+                }
+                if edit_light(ui, &mut view.light) {
+                    edits.push(LightEdit::Set { entity: view.entity, light: view.light.clone() });
+                }
+                if ui.button(tr("lights.remove")) {
+                    edits.push(LightEdit::Remove { entity: view.entity });
+                }
+            }
+            for edit in edits {
+                self.pending_edits.push(edit.entity(), edit);
+            }
+        }
+    }
+
+    /// Draw the light gizmos in every viewport of the layout
+    ///
+    /// # Arguments
+    /// * `ui` - The ImGui frame
+    /// * `viewports` - Layout and cameras the scene is drawn with
+    pub fn render_gizmos(&self, ui: &Ui, viewports: &ViewportSettings) {
+        if !self.show_gizmos || self.lights.is_empty() {
+            return;
+        }
+
+        let [width, height] = ui.io().display_size;
+        let extent = vk::Extent2D { width: width.max(1.0) as u32, height: height.max(1.0) as u32 };
+        let draw_list = ui.get_background_draw_list();
+        let outline = config::lights::GIZMO_OUTLINE_COLOR;
+        let radius = config::lights::GIZMO_RADIUS;
+
+        for (rect, viewport) in viewports.rects(extent) {
+            let camera = viewports.camera(viewport.kind);
+            let min = [rect.offset.x as f32, rect.offset.y as f32];
+            let max = [min[0] + rect.extent.width as f32, min[1] + rect.extent.height as f32];
+            // Keep gizmos inside their quadrant
+            draw_list.with_clip_rect_intersect(min, max, || {
+                for view in &self.lights {
+                    let light = &view.light;
+                    let Some(center) = camera.project(light.position, &rect) else {
+                        continue;
+                    };
+                    let color = gizmo_color(light);
+                    if light.kind.has_direction() {
+                        let tip = light.position + direction_or_down(light.direction) * config::lights::GIZMO_DIRECTION_LENGTH;
+                        if let Some(end) = camera.project(tip, &rect) {
+                            draw_list.add_line(center, end, outline).thickness(4.0).build();
+                            draw_list.add_line(center, end, color).thickness(2.0).build();
+                        }
+                    }
+                    draw_list.add_circle(center, radius + 1.5, outline).filled(true).build();
+                    draw_list.add_circle(center, radius, color).filled(true).build();
+                }
+            });
+        }
+    }
+}
+
+/// Controls of one light
+///
+/// # Returns
+/// Whether a property changed
+fn edit_light(ui: &Ui, light: &mut Light) -> bool {
+    let mut changed = false;
+    let mut kind_index = LightKind::ALL.iter().position(|kind| *kind == light.kind).unwrap_or(0);
+    if ui.combo(tr("lights.kind"), &mut kind_index, &LightKind::ALL, |kind| named("light_kinds", kind.name()).into()) {
+        light.kind = LightKind::ALL[kind_index];
+        changed = true;
+    }
+    let mut color = light.color.into();
+    changed |= edit_linear_color(ui, &tr("lights.color"), &mut color);
+    light.color = color.into();
+    changed |= ui.slider(tr("lights.intensity"), 0.0, 10.0, &mut light.intensity);
+    if light.kind.has_position() {
+        let mut position: [f32; 3] = light.position.into();
+        if imgui::Drag::new(tr("lights.position")).speed(0.01).build_array(ui, &mut position) {
+            light.position = position.into();
+            changed = true;
+        }
+        changed |= ui.slider(tr("lights.radius"), 0.0, 50.0, &mut light.radius);
+    }
+    if light.kind.has_direction() {
+        let mut direction: [f32; 3] = light.direction.into();
+        if imgui::Drag::new(tr("lights.direction")).speed(0.01).build_array(ui, &mut direction) {
+            light.direction = direction.into();
+            changed = true;
+        }
+    }
+    if light.kind == LightKind::Spot {
+        changed |= ui.slider(tr("lights.spot_angle"), 1.0, 90.0, &mut light.spot_angle_deg);
+    }
+    changed
+}
+
+/// Normalized direction, straight down when zero (as the scene buffer does)
+fn direction_or_down(direction: Vector3<f32>) -> Vector3<f32> {
+    if direction.magnitude2() > 0.0 {
+        direction.normalize()
+    } else {
+        -Vector3::unit_y()
+    }
+}
+
+/// Display color of a light's gizmo
+fn gizmo_color(light: &Light) -> [f32; 4] {
+    linear_to_srgb_rgba([light.color.x, light.color.y, light.color.z, 1.0])
+}
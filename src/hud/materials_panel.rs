@@ -0,0 +1,130 @@
+//! Materials panel
+//!
+//! Inspector for the materials of the SDF shapes: color, metallic,
+//! roughness and emission, and the optics traced by the reflections shader
+//! variant (reflectivity, transparency and index of refraction). The ECS
+//! world refreshes the snapshot every frame and applies the queued edits,
+//! so changes show in the next frame.
+
+use imgui::{TreeNodeFlags, Ui};
+use legion::Entity;
+use crate::config;
+use crate::ecs::components::{SDFMaterial, SDFShapeType};
+use crate::hud::background_panel::edit_linear_color;
+use crate::hud::edit_queue::EditQueue;
+use crate::locale::{panel_title, tr, tr_args};
+
+/// Snapshot of a shape's material
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialView {
+    /// Shape entity
+    pub entity: Entity,
+    pub shape_type: SDFShapeType,
+    pub material: SDFMaterial,
+}
+
+/// Replace the material of a shape
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialEdit {
+    pub entity: Entity,
+    pub material: SDFMaterial,
+}
+
+/// Materials panel state
+pub struct MaterialsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the shapes' materials
+    pub materials: Vec<MaterialView>,
+
+    /// Whether the current shader variant traces reflections and refraction
+    pub reflections_enabled: bool,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: EditQueue<Entity, MaterialEdit>,
+}
+
+impl Default for MaterialsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MaterialsPanel {
+    /// Create a new materials panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            materials: Vec::new(),
+            reflections_enabled: config::shader_features::REFLECTIONS,
+            pending_edits: EditQueue::default(),
+        }
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<MaterialEdit> {
+        self.pending_edits.take()
+    }
+
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Materials"))
+            .position([960.0, 200.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 420.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if !self.reflections_enabled {
+                ui.text_wrapped(tr("materials.reflections_off"));
+                ui.separator();
+            }
+
+            if self.materials.is_empty() {
+                ui.text_disabled(tr("materials.empty"));
+                return;
+            }
+
+            let mut edits = Vec::new();
+            for (index, view) in self.materials.iter_mut().enumerate() {
+                let _id = ui.push_id_usize(index);
+                let label = tr_args("materials.shape", &[
+                    ("shape", &format!("{:?}", view.shape_type)),
+                    ("entity", &format!("{:?}", view.entity)),
+                ]);
+                if !ui.collapsing_header(&label, TreeNodeFlags::empty()) {
+                    continue;
+                }
+                if edit_material(ui, &mut view.material) {
+                    edits.push(MaterialEdit { entity: view.entity, material: view.material.clone() });
+                }
+            }
+            for edit in edits {
+                self.pending_edits.push(Some(edit.entity), edit);
+            }
+        }
+    }
+}
+
+/// Controls of one material
+///
+/// # Returns
+/// Whether a property changed
+fn edit_material(ui: &Ui, material: &mut SDFMaterial) -> bool {
+    let mut color = material.color.into();
+    let mut changed = edit_linear_color(ui, &tr("materials.color"), &mut color);
+    material.color = color.into();
+    changed |= ui.slider(tr("materials.metallic"), 0.0, 1.0, &mut material.metallic);
+    changed |= ui.slider(tr("materials.roughness"), 0.0, 1.0, &mut material.roughness);
+    changed |= ui.slider(tr("materials.emission"), 0.0, 10.0, &mut material.emission);
+    changed |= ui.slider(tr("materials.reflectivity"), 0.0, 1.0, &mut material.reflectivity);
+    changed |= ui.slider(tr("materials.transparency"), 0.0, 1.0, &mut material.transparency);
+    let (min_ior, max_ior) = config::materials::IOR_RANGE;
+    changed |= ui.slider(tr("materials.ior"), min_ior, max_ior, &mut material.ior);
+    changed
+}
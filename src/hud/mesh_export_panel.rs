@@ -0,0 +1,137 @@
+//! Mesh export panel
+//!
+//! Configures the extraction of the SDF scene into a triangle mesh (bounds,
+//! grid resolution, OBJ or STL output) and starts or cancels it. While the
+//! extraction runs on its background thread the ECS world feeds its
+//! progress back for the progress bar.
+
+use imgui::Ui;
+use log::debug;
+use std::path::PathBuf;
+use crate::config;
+use crate::platform::dialogs::{DialogOptions, DialogSlot};
+use crate::vulkan::mesh_export::{MeshExportSettings, MeshFormat};
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Mesh export panel state
+pub struct MeshExportPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Settings edited in the panel
+    pub settings: MeshExportSettings,
+
+    /// Output file as edited in the text field
+    output: String,
+
+    /// Completed fraction of the running export, if any
+    pub progress: Option<f32>,
+
+    /// Export started in the UI, waiting to be picked up by the ECS world
+    pending_start: Option<MeshExportSettings>,
+
+    /// Cancel requested in the UI
+    pending_cancel: bool,
+
+    /// Output file dialog
+    dialog: DialogSlot<()>,
+}
+
+impl Default for MeshExportPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MeshExportPanel {
+    /// Create a new mesh export panel with the default settings
+    pub fn new() -> Self {
+        let settings = MeshExportSettings::default();
+        Self {
+            is_visible: false,
+            output: settings.path.display().to_string(),
+            settings,
+            progress: None,
+            pending_start: None,
+            pending_cancel: false,
+            dialog: DialogSlot::default(),
+        }
+    }
+
+    /// Take the export started since the last call
+    pub fn take_pending_start(&mut self) -> Option<MeshExportSettings> {
+        self.pending_start.take()
+    }
+
+    /// Take a cancel request made since the last call
+    pub fn take_pending_cancel(&mut self) -> bool {
+        std::mem::take(&mut self.pending_cancel)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Mesh Export"))
+            .position([620.0, 300.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 220.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(((), path)) = self.dialog.poll() {
+            self.output = path.display().to_string();
+        }
+
+        if let Some(_token) = window.begin() {
+            if let Some(fraction) = self.progress {
+                ui.progress_bar(fraction)
+                    .overlay_text(tr_args("mesh_export.progress", &[("percent", &format!("{:.0}", fraction * 100.0))]))
+                    .build();
+                if ui.button(tr("mesh_export.cancel")) {
+                    debug!("Mesh export cancel requested");
+                    self.pending_cancel = true;
+                }
+                return;
+            }
+
+            let extract = &mut self.settings.extract;
+            ui.input_float3(tr("mesh_export.min"), &mut extract.min).build();
+            ui.input_float3(tr("mesh_export.max"), &mut extract.max).build();
+            let mut resolution = extract.resolution as i32;
+            if ui.input_int(tr("mesh_export.resolution"), &mut resolution).step(16).build() {
+                extract.resolution = resolution.clamp(8, config::mesh_export::MAX_RESOLUTION as i32) as u32;
+            }
+
+            let mut format_index = MeshFormat::ALL.iter().position(|&f| f == self.settings.format).unwrap_or(0);
+            if ui.combo(tr("mesh_export.format"), &mut format_index, &MeshFormat::ALL, |format| named("mesh_formats", format.name()).into()) {
+                self.settings.format = MeshFormat::ALL[format_index];
+            }
+            ui.input_text(tr("mesh_export.output"), &mut self.output).build();
+            ui.same_line();
+            if ui.button(tr("mesh_export.browse")) {
+                let format = self.settings.format;
+                self.dialog.open((), DialogOptions::save_file(&tr("mesh_export.file_dialog"))
+                    .filter(&named("mesh_formats", format.name()), &[format.extension()])
+                    .file_name(&format!("scene.{}", format.extension())));
+            }
+
+            ui.separator();
+            let valid = self.settings.extract.is_valid();
+            if valid {
+                let [x, y, z] = self.settings.extract.cell_counts();
+                ui.text(tr_args("mesh_export.cells", &[("x", &x), ("y", &y), ("z", &z)]));
+            } else {
+                ui.text_disabled(tr("mesh_export.invalid_bounds"));
+            }
+
+            let disabled_token = ui.begin_disabled(!valid);
+            if ui.button(tr("mesh_export.export")) {
+                self.settings.path = PathBuf::from(self.output.trim());
+                debug!("Mesh export requested: {:?}", self.settings);
+                self.pending_start = Some(self.settings.clone());
+            }
+            disabled_token.end();
+        }
+    }
+}
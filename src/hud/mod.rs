@@ -5,15 +5,102 @@
 
 pub mod toolbar;
 pub mod imgui_vulkan_backend;
+pub mod environment_panel;
+pub mod background_panel;
+pub mod render_settings_panel;
+pub mod shader_reload_panel;
+pub mod shader_params_panel;
+pub mod csg_panel;
+pub mod hierarchy_panel;
+pub mod animation_panel;
+pub mod scripts_panel;
+pub mod project_panel;
+pub mod physics_panel;
+pub mod particles_panel;
+pub mod text_panel;
+pub mod modifiers_panel;
+pub mod layers_panel;
+pub mod materials_panel;
+pub mod lights_panel;
+pub mod debug_draw_panel;
+pub mod stats_panel;
+pub mod capabilities_panel;
+pub mod gpu_textures_panel;
+pub mod notifications;
+pub mod jobs_indicator;
+pub mod autosave_prompt;
+pub mod clipboard;
+pub mod edit_queue;
+pub mod recovery;
+pub mod frame_budget;
+pub mod viewport_panel;
+pub mod camera_bookmarks_panel;
+pub mod export_panel;
+pub mod mesh_export_panel;
+pub mod still_export_panel;
+pub mod label_overlay;
+pub mod console_panel;
+pub mod layout;
+pub mod theme;
+pub mod fonts;
+pub mod settings_panel;
+pub mod command_palette;
+pub mod toolbar_layout;
 
-use crate::error::{Result, AppError};
+pub use toolbar::ToolbarPosition;
+
+use crate::actions::{Action, ActionRegistry};
+use crate::config;
+use crate::error::{Result, ResultExt, AppError, Subsystem};
+use crate::locale::{panel_name, title, tr, tr_args};
 use crate::vulkan::device::VulkanDevice;
 use crate::vulkan::renderer::VulkanRenderer;
+use crate::vulkan::rendering::PassLayout;
 use imgui::Context;
 use log::{debug, info, trace, warn};
 use winit::window::Window;
 use ash::vk;
 
+/// Title and visibility flag of every HUD panel that can be opened and closed
+///
+/// A macro rather than a method so it can borrow the panels while the ImGui
+/// frame holds the context.
+macro_rules! panel_visibility {
+    ($hud:expr) => {
+        [
+            ("Environment", &mut $hud.environment_panel.is_visible),
+            ("Background", &mut $hud.background_panel.is_visible),
+            ("Render Settings", &mut $hud.render_settings_panel.is_visible),
+            ("Shader Reload", &mut $hud.shader_reload_panel.is_visible),
+            ("Shader Parameters", &mut $hud.shader_params_panel.is_visible),
+            ("CSG Tree", &mut $hud.csg_panel.is_visible),
+            ("Hierarchy", &mut $hud.hierarchy_panel.is_visible),
+            ("Animation", &mut $hud.animation_panel.is_visible),
+            ("Scripts", &mut $hud.scripts_panel.is_visible),
+            ("Project", &mut $hud.project_panel.is_visible),
+            ("Physics", &mut $hud.physics_panel.is_visible),
+            ("Particles", &mut $hud.particles_panel.is_visible),
+            ("Text", &mut $hud.text_panel.is_visible),
+            ("Modifiers", &mut $hud.modifiers_panel.is_visible),
+            ("Layers", &mut $hud.layers_panel.is_visible),
+            ("Materials", &mut $hud.materials_panel.is_visible),
+            ("Lights", &mut $hud.lights_panel.is_visible),
+            ("Debug Draw", &mut $hud.debug_draw_panel.is_visible),
+            ("Stats", &mut $hud.stats_panel.is_visible),
+            ("Capabilities", &mut $hud.capabilities_panel.is_visible),
+            ("GPU Textures", &mut $hud.gpu_textures_panel.is_visible),
+            ("Viewports", &mut $hud.viewport_panel.is_visible),
+            ("Camera Bookmarks", &mut $hud.camera_bookmarks_panel.is_visible),
+            ("Export", &mut $hud.export_panel.is_visible),
+            ("Mesh Export", &mut $hud.mesh_export_panel.is_visible),
+            ("Still Export", &mut $hud.still_export_panel.is_visible),
+            ("Labels", &mut $hud.label_overlay.is_visible),
+            ("Console", &mut $hud.console_panel.is_visible),
+            ("Settings", &mut $hud.settings_panel.is_visible),
+        ]
+    };
+}
+
 /// Main HUD struct that manages the entire user interface
 pub struct HUD {
     /// ImGui context for UI rendering
@@ -34,6 +121,122 @@ pub struct HUD {
     /// Platform integration for winit
     pub platform: Option<imgui_winit_support::WinitPlatform>,
     
+    /// Environment panel (sun and sky controls)
+    pub environment_panel: environment_panel::EnvironmentPanel,
+    
+    /// Background panel (solid color, gradient, sky or HDRI)
+    pub background_panel: background_panel::BackgroundPanel,
+    
+    /// Render settings panel (quality and shader features)
+    pub render_settings_panel: render_settings_panel::RenderSettingsPanel,
+    
+    /// Shader reload panel (per-file reload status and filters)
+    pub shader_reload_panel: shader_reload_panel::ShaderReloadPanel,
+    
+    /// Shader parameters panel (live values of the SDF shader's Params block)
+    pub shader_params_panel: shader_params_panel::ShaderParamsPanel,
+    
+    /// CSG tree editor panel
+    pub csg_panel: csg_panel::CsgPanel,
+    
+    /// Transform hierarchy panel (grouping by drag and drop)
+    pub hierarchy_panel: hierarchy_panel::HierarchyPanel,
+    
+    /// Animation transport panel (play, pause, scrub)
+    pub animation_panel: animation_panel::AnimationPanel,
+    
+    /// Scripts panel (script errors and script-bound buttons)
+    pub scripts_panel: scripts_panel::ScriptsPanel,
+    
+    /// Project panel (open project, recent projects)
+    pub project_panel: project_panel::ProjectPanel,
+    
+    /// Physics panel (simulation toggles, throw sphere)
+    pub physics_panel: physics_panel::PhysicsPanel,
+    
+    /// Particles panel (emitter inspector)
+    pub particles_panel: particles_panel::ParticlesPanel,
+    
+    /// Text panel (3D text inspector)
+    pub text_panel: text_panel::TextPanel,
+    
+    /// Modifiers panel (modifier stacks of the shapes)
+    pub modifiers_panel: modifiers_panel::ModifiersPanel,
+    
+    /// Layers panel (render layers of the shapes and composites)
+    pub layers_panel: layers_panel::LayersPanel,
+    
+    /// Materials panel (material inspector of the shapes)
+    pub materials_panel: materials_panel::MaterialsPanel,
+
+    /// Lights panel (light entities and their viewport gizmos)
+    pub lights_panel: lights_panel::LightsPanel,
+    
+    /// Debug draw panel (bounds and wireframe overlays)
+    pub debug_draw_panel: debug_draw_panel::DebugDrawPanel,
+    
+    /// Stats panel (detected GPUs)
+    pub stats_panel: stats_panel::StatsPanel,
+    
+    /// GPU capabilities panel (features, limits, extensions)
+    pub capabilities_panel: capabilities_panel::CapabilitiesPanel,
+    
+    /// GPU Textures panel (offscreen targets with zoom and channel isolation)
+    pub gpu_textures_panel: gpu_textures_panel::GpuTexturesPanel,
+    
+    /// Viewport layout panel (single or quad view)
+    pub viewport_panel: viewport_panel::ViewportPanel,
+    
+    /// Camera bookmarks panel (jump to saved camera poses)
+    pub camera_bookmarks_panel: camera_bookmarks_panel::CameraBookmarksPanel,
+    
+    /// Offline frame export panel
+    pub export_panel: export_panel::ExportPanel,
+    
+    /// SDF to mesh export panel (bounds, resolution, OBJ/STL)
+    pub mesh_export_panel: mesh_export_panel::MeshExportPanel,
+    
+    /// High resolution still export panel (size, PNG/EXR)
+    pub still_export_panel: still_export_panel::StillExportPanel,
+    
+    /// World space labels drawn over the viewports
+    pub label_overlay: label_overlay::LabelOverlay,
+    
+    /// Log console (recent records with filtering and search)
+    pub console_panel: console_panel::ConsolePanel,
+    
+    /// Settings panel (theme selection and editing)
+    pub settings_panel: settings_panel::SettingsPanel,
+    
+    /// Fuzzy-searchable list of actions (Ctrl+P)
+    pub command_palette: command_palette::CommandPalette,
+    
+    /// Registered actions, which toolbar buttons are bound to by name
+    actions: std::sync::Arc<ActionRegistry>,
+    
+    /// Theme applied to the ImGui style
+    pub theme: theme::Theme,
+    
+    /// Fonts baked into the current atlas
+    pub fonts: fonts::LoadedFonts,
+    
+    /// Error toasts and the fatal error dialog
+    pub notifications: notifications::Notifications,
+    
+    /// Background jobs queued or running, shown in the corner
+    pub jobs_indicator: jobs_indicator::JobsIndicator,
+    
+    /// Offer to restore the scene auto-saved before a crash
+    pub autosave_prompt: autosave_prompt::AutosavePrompt,
+    
+    /// Hides heavy panels while the frame time is over budget
+    pub frame_budget: frame_budget::FrameBudgetMonitor,
+    
+    /// Error of the last rejected shader reload, shown until a reload succeeds
+    pub shader_reload_error: Option<String>,
+    
+    /// Panel visibility as last written to disk
+    saved_layout: layout::PanelLayout,
 }
 
 /// HUD configuration settings
@@ -47,8 +250,7 @@ pub struct HUDConfig {
     #[allow(dead_code)]
     pub anti_aliasing: bool,
     
-    /// Default toolbar position (top, bottom, left, right)
-    #[allow(dead_code)]
+    /// Toolbar position at startup (top, bottom, left, right)
     pub default_toolbar_position: ToolbarPosition,
     
     /// Toolbar background color
@@ -58,18 +260,9 @@ pub struct HUDConfig {
     /// Enable keyboard shortcuts
     #[allow(dead_code)]
     pub enable_shortcuts: bool,
-}
-
-/// Toolbar position options
-#[derive(Debug, Clone, Copy)]
-pub enum ToolbarPosition {
-    Top,
-    #[allow(dead_code)]
-    Bottom,
-    #[allow(dead_code)]
-    Left,
-    #[allow(dead_code)]
-    Right,
+    
+    /// Theme applied at startup, a preset name or custom theme file stem
+    pub theme: String,
 }
 
 impl Default for HUDConfig {
@@ -77,9 +270,10 @@ impl Default for HUDConfig {
         Self {
             font_size: 16.0,
             anti_aliasing: true,
-            default_toolbar_position: ToolbarPosition::Top,
+            default_toolbar_position: ToolbarPosition::default(),
             toolbar_background_color: [0.1, 0.1, 0.12, 0.8],
             enable_shortcuts: true,
+            theme: config::hud_theme::DEFAULT_THEME.to_string(),
         }
     }
 }
@@ -91,8 +285,9 @@ impl HUD {
     /// * `window` - The window reference
     /// * `device` - The Vulkan device
     /// * `renderer` - The Vulkan renderer
-    /// * `render_pass` - The render pass for ImGui
+    /// * `pass` - The present pass ImGui draws in
     /// * `config` - HUD configuration
+    /// * `actions` - Actions listed in the command palette
     ///
     /// # Returns
     /// A new HUD instance
@@ -100,14 +295,20 @@ impl HUD {
         window: &Window,
         device: &VulkanDevice,
         renderer: &VulkanRenderer,
-        render_pass: vk::RenderPass,
+        pass: PassLayout,
         config: HUDConfig,
+        actions: std::sync::Arc<ActionRegistry>,
     ) -> Result<Self> {
         info!("Initializing HUD system");
         
         // Create ImGui context
-        let mut context = Self::create_context(&config)?;
-        context.set_ini_filename(None); // Disable automatic INI saving
+        let theme = theme::named_theme(&config.theme);
+        let mut context = Self::create_context(&config, &theme)?;
+        // ImGui's ini data is saved together with the panel visibility, see `save_layout`
+        context.set_ini_filename(None);
+        if let Ok(ini) = std::fs::read_to_string(config::hud_layout::IMGUI_INI_FILE) {
+            context.load_ini_settings(&ini);
+        }
         
         // Initialize ImGui with winit platform
         let mut platform = imgui_winit_support::WinitPlatform::new(&mut context);
@@ -116,12 +317,18 @@ impl HUD {
         let window_size = window.inner_size();
         let io = context.io_mut();
         io.display_size = [window_size.width as f32, window_size.height as f32];
-        io.display_framebuffer_scale = [1.0, 1.0]; // TODO: Get actual DPI scale
+        io.display_framebuffer_scale = [1.0, 1.0]; // ImGui works in physical pixels
         
         platform.attach_window(io, window, imgui_winit_support::HiDpiMode::Default);
         
         // Create toolbar
-        let mut toolbar = toolbar::Toolbar::new(toolbar::ToolbarPosition::Top);
+        let mut toolbar = toolbar::Toolbar::new(config.default_toolbar_position);
+        
+        // Groups and buttons from the layout file, if it is valid
+        let toolbar_file = std::path::Path::new(config::hud_layout::TOOLBAR_FILE);
+        if let Err(e) = toolbar.set_layout(&toolbar_layout::ToolbarLayout::load_or_built_in(toolbar_file), &actions) {
+            warn!("Using the built-in toolbar layout: {}", e);
+        }
         
         // Set up hot reload button callback
         if let Some(_toggle_button) = toolbar.get_button("toggle_hot_reload") {
@@ -138,30 +345,80 @@ impl HUD {
             &device.device,
             device.physical_device,
             &renderer.instance.instance,
-            render_pass,
-            device.queue_families.graphics_family.unwrap(),
-        ).map_err(|e| AppError::HUD(format!("Failed to create ImGui Vulkan backend: {}", e)))?;
+            &pass,
+            renderer.swapchain.display_output,
+            std::sync::Arc::clone(&renderer.upload),
+            std::sync::Arc::clone(&renderer.deletion_queue),
+        ).context(Subsystem::Hud, "create ImGui Vulkan backend")?;
         
         info!("HUD system initialized successfully");
         
-        Ok(Self {
+        let mut hud = Self {
             context,
             toolbar,
             enabled: true,
             last_frame_time: 0.0,
             imgui_backend: Some(imgui_backend),
             platform: Some(platform),
-        })
+            environment_panel: environment_panel::EnvironmentPanel::new(),
+            background_panel: background_panel::BackgroundPanel::new(),
+            render_settings_panel: render_settings_panel::RenderSettingsPanel::new(),
+            shader_reload_panel: shader_reload_panel::ShaderReloadPanel::new(),
+            shader_params_panel: shader_params_panel::ShaderParamsPanel::new(),
+            csg_panel: csg_panel::CsgPanel::new(),
+            hierarchy_panel: hierarchy_panel::HierarchyPanel::new(),
+            animation_panel: animation_panel::AnimationPanel::new(),
+            scripts_panel: scripts_panel::ScriptsPanel::new(),
+            project_panel: project_panel::ProjectPanel::new(),
+            physics_panel: physics_panel::PhysicsPanel::new(),
+            particles_panel: particles_panel::ParticlesPanel::new(),
+            text_panel: text_panel::TextPanel::new(),
+            modifiers_panel: modifiers_panel::ModifiersPanel::new(),
+            layers_panel: layers_panel::LayersPanel::new(),
+            materials_panel: materials_panel::MaterialsPanel::new(),
+            lights_panel: lights_panel::LightsPanel::new(),
+            debug_draw_panel: debug_draw_panel::DebugDrawPanel::new(),
+            stats_panel: stats_panel::StatsPanel::new(device.gpus.clone()),
+            capabilities_panel: capabilities_panel::CapabilitiesPanel::new(device.capabilities.report()),
+            gpu_textures_panel: gpu_textures_panel::GpuTexturesPanel::new(),
+            viewport_panel: viewport_panel::ViewportPanel::new(),
+            camera_bookmarks_panel: camera_bookmarks_panel::CameraBookmarksPanel::new(),
+            export_panel: export_panel::ExportPanel::new(),
+            mesh_export_panel: mesh_export_panel::MeshExportPanel::new(),
+            still_export_panel: still_export_panel::StillExportPanel::new(),
+            label_overlay: label_overlay::LabelOverlay::new(),
+            console_panel: console_panel::ConsolePanel::new(),
+            settings_panel: settings_panel::SettingsPanel::new(theme.clone()),
+            command_palette: command_palette::CommandPalette::new(std::sync::Arc::clone(&actions)),
+            actions,
+            theme,
+            fonts: fonts::LoadedFonts::default(),
+            notifications: notifications::Notifications::new(),
+            jobs_indicator: jobs_indicator::JobsIndicator::new(),
+            autosave_prompt: autosave_prompt::AutosavePrompt::new(),
+            frame_budget: frame_budget::FrameBudgetMonitor::new(),
+            shader_reload_error: None,
+            saved_layout: layout::PanelLayout::default(),
+        };
+        
+        // Restore which panels were open last time
+        if let Some(saved) = layout::PanelLayout::load(std::path::Path::new(config::hud_layout::PANELS_FILE)) {
+            saved.apply(&mut panel_visibility!(hud));
+            hud.saved_layout = saved;
+        }
+        
+        Ok(hud)
     }
     
     /// Create ImGui context with optimized settings
     /// 
     /// # Arguments
     /// * `config` - Configuration options
+    /// * `theme` - Theme applied to the style
     /// 
     /// # Returns
     /// Configured ImGui context
-    fn create_context(_config: &HUDConfig) -> Result<Context> {
+    fn create_context(_config: &HUDConfig, theme: &theme::Theme) -> Result<Context> {
         let mut context = Context::create();
         
         // Configure ImGui settings
@@ -170,91 +427,21 @@ impl HUD {
         // Configure timing
         io.delta_time = 1.0 / 60.0;
         
-        // Set up dark theme
-        Self::setup_dark_theme(&mut context);
+        if config::hud_layout::ENABLE_DOCKING {
+            io.config_flags |= imgui::ConfigFlags::DOCKING_ENABLE;
+        }
+        
+        // Apply the configured theme
+        theme.apply(context.style_mut());
+        
+        match clipboard::SystemClipboard::new() {
+            Some(clipboard) => context.set_clipboard_backend(clipboard),
+            None => warn!("System clipboard unavailable, copied text stays inside the HUD"),
+        }
         
         Ok(context)
     }
     
-    /// Setup a dark theme similar to Blender
-    fn setup_dark_theme(context: &mut Context) {
-        let style = context.style_mut();
-        
-        // Professional dark theme with better contrast and appearance
-        style.colors[imgui::StyleColor::Text as usize] = [0.92, 0.92, 0.92, 1.00];
-        style.colors[imgui::StyleColor::TextDisabled as usize] = [0.50, 0.50, 0.50, 1.00];
-        style.colors[imgui::StyleColor::WindowBg as usize] = [0.13, 0.13, 0.15, 0.90];
-        style.colors[imgui::StyleColor::ChildBg as usize] = [0.13, 0.13, 0.15, 0.90];
-        style.colors[imgui::StyleColor::PopupBg as usize] = [0.13, 0.13, 0.15, 0.95];
-        style.colors[imgui::StyleColor::Border as usize] = [0.25, 0.25, 0.30, 0.50];
-        style.colors[imgui::StyleColor::BorderShadow as usize] = [0.00, 0.00, 0.00, 0.00];
-        style.colors[imgui::StyleColor::FrameBg as usize] = [0.16, 0.16, 0.18, 1.00];
-        style.colors[imgui::StyleColor::FrameBgHovered as usize] = [0.20, 0.20, 0.23, 1.00];
-        style.colors[imgui::StyleColor::FrameBgActive as usize] = [0.24, 0.24, 0.28, 1.00];
-        style.colors[imgui::StyleColor::TitleBg as usize] = [0.16, 0.16, 0.18, 1.00];
-        style.colors[imgui::StyleColor::TitleBgActive as usize] = [0.20, 0.20, 0.23, 1.00];
-        style.colors[imgui::StyleColor::TitleBgCollapsed as usize] = [0.16, 0.16, 0.18, 1.00];
-        style.colors[imgui::StyleColor::MenuBarBg as usize] = [0.16, 0.16, 0.18, 1.00];
-        style.colors[imgui::StyleColor::ScrollbarBg as usize] = [0.10, 0.10, 0.12, 1.00];
-        style.colors[imgui::StyleColor::ScrollbarGrab as usize] = [0.20, 0.20, 0.23, 1.00];
-        style.colors[imgui::StyleColor::ScrollbarGrabHovered as usize] = [0.24, 0.24, 0.28, 1.00];
-        style.colors[imgui::StyleColor::ScrollbarGrabActive as usize] = [0.28, 0.28, 0.32, 1.00];
-        style.colors[imgui::StyleColor::CheckMark as usize] = [0.26, 0.59, 0.98, 1.00];
-        style.colors[imgui::StyleColor::SliderGrab as usize] = [0.26, 0.59, 0.98, 1.00];
-        style.colors[imgui::StyleColor::SliderGrabActive as usize] = [0.46, 0.79, 0.98, 1.00];
-        style.colors[imgui::StyleColor::Button as usize] = [0.35, 0.35, 0.40, 1.00];
-        style.colors[imgui::StyleColor::ButtonHovered as usize] = [0.45, 0.45, 0.50, 1.00];
-        style.colors[imgui::StyleColor::ButtonActive as usize] = [0.55, 0.55, 0.60, 1.00];
-        style.colors[imgui::StyleColor::Header as usize] = [0.20, 0.20, 0.23, 1.00];
-        style.colors[imgui::StyleColor::HeaderHovered as usize] = [0.26, 0.26, 0.30, 1.00];
-        style.colors[imgui::StyleColor::HeaderActive as usize] = [0.32, 0.32, 0.36, 1.00];
-        style.colors[imgui::StyleColor::Separator as usize] = [0.20, 0.20, 0.23, 1.00];
-        style.colors[imgui::StyleColor::SeparatorHovered as usize] = [0.26, 0.26, 0.30, 1.00];
-        style.colors[imgui::StyleColor::SeparatorActive as usize] = [0.32, 0.32, 0.36, 1.00];
-        style.colors[imgui::StyleColor::ResizeGrip as usize] = [0.26, 0.59, 0.98, 0.20];
-        style.colors[imgui::StyleColor::ResizeGripHovered as usize] = [0.26, 0.59, 0.98, 0.67];
-        style.colors[imgui::StyleColor::ResizeGripActive as usize] = [0.46, 0.79, 0.98, 0.95];
-        style.colors[imgui::StyleColor::Tab as usize] = [0.20, 0.20, 0.23, 1.00];
-        style.colors[imgui::StyleColor::TabHovered as usize] = [0.26, 0.26, 0.30, 1.00];
-        style.colors[imgui::StyleColor::TabActive as usize] = [0.32, 0.32, 0.36, 1.00];
-        style.colors[imgui::StyleColor::TabUnfocused as usize] = [0.20, 0.20, 0.23, 1.00];
-        style.colors[imgui::StyleColor::TabUnfocusedActive as usize] = [0.26, 0.26, 0.30, 1.00];
-        style.colors[imgui::StyleColor::PlotLines as usize] = [0.61, 0.61, 0.61, 1.00];
-        style.colors[imgui::StyleColor::PlotLinesHovered as usize] = [1.00, 1.00, 1.00, 1.00];
-        style.colors[imgui::StyleColor::PlotHistogram as usize] = [0.90, 0.70, 0.00, 1.00];
-        style.colors[imgui::StyleColor::PlotHistogramHovered as usize] = [1.00, 0.60, 0.00, 1.00];
-        style.colors[imgui::StyleColor::TextSelectedBg as usize] = [0.26, 0.59, 0.98, 0.35];
-        style.colors[imgui::StyleColor::DragDropTarget as usize] = [0.26, 0.59, 0.98, 0.95];
-        style.colors[imgui::StyleColor::NavHighlight as usize] = [0.26, 0.59, 0.98, 0.80];
-        style.colors[imgui::StyleColor::NavWindowingHighlight as usize] = [1.00, 1.00, 1.00, 0.70];
-        style.colors[imgui::StyleColor::NavWindowingDimBg as usize] = [0.20, 0.20, 0.20, 0.20];
-        style.colors[imgui::StyleColor::ModalWindowDimBg as usize] = [0.20, 0.20, 0.20, 0.35];
-        
-        // Improve styling for better toolbar appearance
-        style.window_padding = [12.0, 8.0];
-        style.window_rounding = 0.0;
-        style.window_border_size = 0.0;
-        style.frame_padding = [8.0, 6.0];
-        style.frame_rounding = 4.0;
-        style.frame_border_size = 0.0;
-        style.item_spacing = [12.0, 8.0];
-        style.item_inner_spacing = [8.0, 6.0];
-        style.indent_spacing = 21.0;
-        style.scrollbar_size = 14.0;
-        style.scrollbar_rounding = 2.0;
-        style.grab_min_size = 10.0;
-        style.grab_rounding = 2.0;
-        style.tab_rounding = 4.0;
-        style.button_text_align = [0.5, 0.5];
-        style.display_window_padding = [8.0, 8.0];
-        style.display_safe_area_padding = [4.0, 4.0];
-        style.anti_aliased_lines = true;
-        style.anti_aliased_fill = true;
-        style.curve_tessellation_tol = 1.25;
-        
-        // Improve font rendering
-        style.window_min_size = [200.0, 50.0];
-    }
     
     /// Handle window resize
     /// 
@@ -267,14 +454,75 @@ impl HUD {
     
     /// Update the HUD state (called each frame before rendering)
 
-    /// Check if manual reload button was clicked
-    pub fn was_reload_button_clicked(&self) -> bool {
-        self.toolbar.was_button_clicked("reload_shaders")
+    /// Read the HUD theme again from disk and re-apply it
+    ///
+    /// The theme is loaded from its file, or the file saved under its name;
+    /// a preset without such a file is rebuilt.
+    ///
+    /// # Errors
+    /// Returns an error if the theme file cannot be read or parsed; the
+    /// current theme stays applied
+    pub fn reload_theme(&mut self) -> Result<()> {
+        let theme = self.theme.reloaded()?;
+        self.set_theme(theme);
+        info!("HUD theme '{}' re-applied", self.theme.name);
+        Ok(())
+    }
+
+    /// Reload the toolbar groups and buttons from the layout file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, or a button
+    /// runs an unknown action; the current toolbar stays in place
+    pub fn reload_toolbar_layout(&mut self) -> Result<()> {
+        let layout = toolbar_layout::ToolbarLayout::load(std::path::Path::new(config::hud_layout::TOOLBAR_FILE))?;
+        self.toolbar.set_layout(&layout, &self.actions)
+            .context(Subsystem::Hud, format!("apply toolbar layout {}", config::hud_layout::TOOLBAR_FILE))?;
+        info!("Toolbar layout reloaded");
+        Ok(())
+    }
+    
+    /// Apply a theme to the ImGui style and make it the edited theme
+    pub fn set_theme(&mut self, theme: theme::Theme) {
+        theme.apply(self.context.style_mut());
+        self.settings_panel.theme = theme.clone();
+        self.theme = theme;
+    }
+
+    /// Apply the theme edited in the settings panel and save it or the runtime settings when requested
+    fn apply_settings(&mut self) {
+        if let Some(theme) = self.settings_panel.take_pending_theme() {
+            theme.apply(self.context.style_mut());
+            self.theme = theme;
+        }
+        if self.settings_panel.take_pending_save() {
+            match self.theme.save() {
+                Ok(path) => {
+                    self.theme.source = Some(path.clone());
+                    self.settings_panel.theme.source = Some(path.clone());
+                    self.settings_panel.refresh_custom_themes();
+                    self.notifications.push(notifications::ToastLevel::Info, tr_args("toasts.theme_saved", &[("path", &path.display())]));
+                }
+                Err(e) => self.notifications.push(notifications::ToastLevel::Error, e.user_summary()),
+            }
+        }
+        if let Some(settings) = self.settings_panel.take_pending_settings_save() {
+            let path = std::path::Path::new(config::settings::FILE);
+            match settings.save(path) {
+                Ok(()) => self.notifications.push(notifications::ToastLevel::Info, tr_args("toasts.settings_saved", &[("path", &path.display())])),
+                Err(e) => self.notifications.push(notifications::ToastLevel::Error, e.user_summary()),
+            }
+        }
+    }
+
+    /// Get the sun settings edited in the environment panel
+    pub fn sun_settings(&self) -> crate::environment::SunSettings {
+        self.environment_panel.sun
     }
 
-    /// Check if hot reload checkbox was toggled
-    pub fn was_hot_reload_toggled(&self) -> Option<bool> {
-        self.toolbar.was_hot_reload_toggled()
+    /// Get the background settings edited in the background panel
+    pub fn background_settings(&self) -> crate::environment::BackgroundSettings {
+        self.background_panel.background.clone()
     }
 
     /// Update HUD state and animations
@@ -289,6 +537,11 @@ impl HUD {
         
         self.last_frame_time = delta_time;
         
+        // Hide or restore heavy panels based on the measured frame time
+        if let Some(event) = self.frame_budget.tick() {
+            self.apply_frame_budget_event(event);
+        }
+        
         // Update platform integration
         if let Some(platform) = &mut self.platform {
             let io = self.context.io_mut();
@@ -301,14 +554,15 @@ impl HUD {
             // Update display size
             let window_size = window.inner_size();
             io.display_size = [window_size.width as f32, window_size.height as f32];
-            io.display_framebuffer_scale = [1.0, 1.0]; // TODO: Get actual DPI scale
+            io.display_framebuffer_scale = [1.0, 1.0]; // ImGui works in physical pixels
             
             // Enable mouse input
             io.backend_flags |= imgui::BackendFlags::HAS_MOUSE_CURSORS;
             io.backend_flags |= imgui::BackendFlags::HAS_SET_MOUSE_POS;
         }
         
-        // Update toolbar
+        // Update toolbar, sized for the monitor the window is on
+        self.toolbar.scale = window.scale_factor() as f32;
         self.toolbar.update(delta_time);
         
         // Update context
@@ -333,15 +587,151 @@ impl HUD {
         // Update ImGui display size
         let io = self.context.io_mut();
         io.display_size = [extent.width as f32, extent.height as f32];
-        io.display_framebuffer_scale = [1.0, 1.0]; // TODO: Get actual DPI scale
+        io.display_framebuffer_scale = [1.0, 1.0]; // ImGui works in physical pixels
+        
+        // Font changes from the settings window rebuild the atlas between frames
+        if let Some(settings) = self.settings_panel.take_pending_fonts() {
+            self.rebuild_fonts(&settings).context(Subsystem::Hud, "rebuild the HUD font atlas")?;
+        }
+        
+        // Offscreen targets shown in the GPU Textures panel; the panel closes if they cannot be registered
+        if let Some(imgui_backend) = &mut self.imgui_backend {
+            if let Err(e) = self.gpu_textures_panel.sync_textures(imgui_backend) {
+                self.gpu_textures_panel.is_visible = false;
+                self.notifications.report(&e.context(Subsystem::Hud, "show the GPU textures"));
+            }
+        }
         
         // Create a new ImGui frame
         let ui = self.context.frame();
         
+        // Screen area left to the other windows by the toolbar
+        let safe_area = self.toolbar.safe_area(ui.io().display_size);
+        
+        // Panels dock around the scene, clear of the toolbar
+        if config::hud_layout::ENABLE_DOCKING {
+            layout::render_dock_space(&ui, safe_area);
+        }
+        
         // Render the toolbar - this creates the UI elements
         // Note: In a full implementation, you'd pass ECS world reference here
         self.toolbar.render(&ui);
         
+        // Render environment panel (sun and sky controls)
+        self.environment_panel.render(&ui);
+        
+        // Render background panel (gradient, sky, HDRI)
+        self.background_panel.render(&ui);
+        
+        // Render settings panel (raymarch quality)
+        self.render_settings_panel.render(&ui);
+        
+        // Render shader reload status and filters
+        self.shader_reload_panel.render(&ui);
+        
+        // Render live shader parameters
+        self.shader_params_panel.render(&ui);
+        
+        // Render CSG tree editor
+        self.csg_panel.render(&ui);
+        
+        // Render transform hierarchy
+        self.hierarchy_panel.render(&ui);
+        
+        // Render animation transport controls
+        self.animation_panel.render(&ui);
+        
+        // Render script buttons
+        self.scripts_panel.render(&ui);
+        
+        // Render the open project and recent projects
+        self.project_panel.render(&ui);
+        
+        // Render physics toggles
+        self.physics_panel.render(&ui);
+        
+        // Render particle emitter inspector
+        self.particles_panel.render(&ui);
+        
+        // Render 3D text inspector
+        self.text_panel.render(&ui);
+        
+        // Render modifier stack inspector
+        self.modifiers_panel.render(&ui);
+        self.layers_panel.render(&ui);
+        
+        // Render material inspector
+        self.materials_panel.render(&ui);
+        
+        // Render light editor
+        self.lights_panel.render(&ui);
+        
+        // Render debug draw panel
+        self.debug_draw_panel.render(&ui);
+        
+        // Render stats panel
+        self.stats_panel.render(&ui);
+        
+        // Render GPU capability report
+        self.capabilities_panel.render(&ui);
+        
+        // Render offscreen target viewer
+        self.gpu_textures_panel.render(&ui);
+        
+        // Render viewport layout panel and quad view labels
+        self.viewport_panel.render(&ui);
+        
+        // Render camera bookmarks
+        self.camera_bookmarks_panel.render(&ui);
+        
+        // Render world space labels in the viewports
+        self.label_overlay.render(&ui, &self.viewport_panel.settings);
+        self.lights_panel.render_gizmos(&ui, &self.viewport_panel.settings);
+        
+        // Render frame export panel
+        self.export_panel.render(&ui);
+        
+        // Render mesh export panel
+        self.mesh_export_panel.render(&ui);
+        
+        // Render still export panel
+        self.still_export_panel.render(&ui);
+        
+        // Render log console
+        self.console_panel.render(&ui);
+        
+        // Render settings (theme editor)
+        self.settings_panel.render(&ui);
+        
+        // Open and close panels
+        layout::render_panel_toggles(&ui, safe_area, &mut panel_visibility!(self));
+        
+        // Command palette, on top of the panels
+        self.command_palette.render(&ui, safe_area, &mut panel_visibility!(self));
+        
+        // Show why the last shader reload was rejected (the previous pipeline is still active)
+        if let Some(ref message) = self.shader_reload_error {
+            let center = (safe_area.min[0] + safe_area.max[0]) * 0.5;
+            let window = ui.window(title("shader_reload.failed", "Shader Reload Failed"))
+                .position([center, safe_area.max[1] - 20.0], imgui::Condition::Always)
+                .position_pivot([0.5, 1.0])
+                .size([520.0, 0.0], imgui::Condition::Always)
+                .bg_alpha(0.95);
+            if let Some(_token) = window.begin() {
+                ui.text_colored([1.0, 0.4, 0.4, 1.0], tr("shader_reload.keeping_pipeline"));
+                ui.text_wrapped(message);
+            }
+        }
+        
+        // Offer the scene auto-saved before a crash
+        self.autosave_prompt.render(&ui);
+        
+        // Background jobs in the corner opposite the toasts
+        self.jobs_indicator.render(&ui, safe_area);
+        
+        // Error toasts and the fatal error dialog
+        self.notifications.render(&ui, safe_area);
+        
         // Get the draw data and render it using Vulkan backend
         let draw_data = self.context.render();
         
@@ -359,10 +749,126 @@ impl HUD {
         
         // Note: Overlay renderer removed - we now use real ImGui rendering only
         
+        // Style changes take effect from the next frame
+        self.apply_settings();
+        
+        // Persist the layout when ImGui's window state changed or a panel was opened or closed
+        let layout_changed = self.current_layout() != self.saved_layout;
+        if self.context.io().want_save_ini_settings || layout_changed {
+            self.save_layout();
+        }
+        
         trace!("HUD rendering completed");
         Ok(())
     }
     
+    /// Write ImGui's window layout and the panel visibility to disk
+    ///
+    /// Failures are logged; the HUD keeps working with an unsaved layout.
+    pub fn save_layout(&mut self) {
+        let mut ini = String::new();
+        self.context.save_ini_settings(&mut ini);
+        self.context.io_mut().want_save_ini_settings = false;
+        if let Err(e) = std::fs::write(config::hud_layout::IMGUI_INI_FILE, ini) {
+            warn!("Failed to save ImGui layout to {}: {}", config::hud_layout::IMGUI_INI_FILE, e);
+        }
+        
+        let panels = self.current_layout();
+        match panels.save(std::path::Path::new(config::hud_layout::PANELS_FILE)) {
+            Ok(()) => debug!("HUD layout saved"),
+            Err(e) => warn!("Failed to save HUD panel layout: {}", e),
+        }
+        self.saved_layout = panels;
+    }
+    
+    /// Panel visibility to persist
+    ///
+    /// Panels hidden by the frame budget guard count as open, since the
+    /// guard restores them on its own.
+    fn current_layout(&mut self) -> layout::PanelLayout {
+        let mut current = layout::PanelLayout::capture(&panel_visibility!(self));
+        for panel in &self.frame_budget.hidden_panels {
+            current.set(panel.name(), true);
+        }
+        current
+    }
+    
+    /// Visibility flag of a heavy panel
+    fn heavy_panel_visibility(&mut self, panel: frame_budget::HeavyPanel) -> &mut bool {
+        match panel {
+            frame_budget::HeavyPanel::Csg => &mut self.csg_panel.is_visible,
+            frame_budget::HeavyPanel::DebugDraw => &mut self.debug_draw_panel.is_visible,
+            frame_budget::HeavyPanel::Stats => &mut self.stats_panel.is_visible,
+            frame_budget::HeavyPanel::Console => &mut self.console_panel.is_visible,
+        }
+    }
+    
+    /// Hide heavy panels when the frame budget guard trips and restore them on recovery
+    ///
+    /// # Arguments
+    /// * `event` - State change reported by the frame budget monitor
+    fn apply_frame_budget_event(&mut self, event: frame_budget::FrameBudgetEvent) {
+        match event {
+            frame_budget::FrameBudgetEvent::Tripped { frame_ms } => {
+                let mut hidden = Vec::new();
+                for panel in frame_budget::HeavyPanel::ALL {
+                    let visible = self.heavy_panel_visibility(panel);
+                    if *visible {
+                        *visible = false;
+                        hidden.push(panel);
+                    }
+                }
+                if hidden.is_empty() {
+                    return;
+                }
+                let names = hidden.iter().map(|panel| panel.name()).collect::<Vec<_>>().join(", ");
+                warn!("Frame time {:.0} ms over budget, hiding panels: {}", frame_ms, names);
+                let shown_names = hidden.iter().map(|panel| panel_name(panel.name())).collect::<Vec<_>>().join(", ");
+                self.notifications.push(
+                    notifications::ToastLevel::Warning,
+                    tr_args("toasts.over_budget", &[("ms", &format!("{:.0}", frame_ms)), ("panels", &shown_names)]),
+                );
+                self.frame_budget.hidden_panels = hidden;
+            }
+            frame_budget::FrameBudgetEvent::Recovered => {
+                let hidden = std::mem::take(&mut self.frame_budget.hidden_panels);
+                if hidden.is_empty() {
+                    return;
+                }
+                for &panel in &hidden {
+                    *self.heavy_panel_visibility(panel) = true;
+                }
+                info!("Frame time back under budget, restored {} panels", hidden.len());
+                self.notifications.push(notifications::ToastLevel::Info, tr("toasts.under_budget"));
+            }
+        }
+    }
+    
+    /// Show an error to the user
+    ///
+    /// Fatal errors open the error dialog, recoverable ones become toasts.
+    ///
+    /// # Arguments
+    /// * `error` - The error to report
+    pub fn report_error(&mut self, error: &AppError) {
+        self.notifications.report(error);
+    }
+    
+    /// Check whether the user asked to quit from the error dialog
+    pub fn take_quit_request(&mut self) -> bool {
+        self.notifications.take_quit_request()
+    }
+    
+    /// Open or close the command palette
+    pub fn toggle_command_palette(&mut self) {
+        self.command_palette.toggle();
+    }
+    
+    /// Take the action chosen in the command palette or clicked on the toolbar since the last call
+    pub fn take_requested_action(&mut self) -> Option<Action> {
+        self.command_palette.take_pending_action().or_else(|| self.toolbar.take_pending_action())
+    }
+    
     /// Enable or disable the HUD
     ///
     /// # Arguments
@@ -373,42 +879,47 @@ impl HUD {
         debug!("HUD {}", if enabled { "enabled" } else { "disabled" });
     }
     
-    /// Initialize font texture for ImGui
+    /// Initialize font texture for ImGui from the configured font settings
     pub fn init_font_texture(&mut self) -> Result<()> {
         debug!("Initializing font texture for ImGui");
+        let settings = self.settings_panel.fonts.clone();
+        self.rebuild_fonts(&settings)
+    }
+    
+    /// Rebuild the font atlas and upload it as a new font texture
+    ///
+    /// A font file that cannot be read is reported and the built-in font is
+    /// used instead. Must not be called while an ImGui frame is active.
+    ///
+    /// # Arguments
+    /// * `settings` - Font files, sizes and glyph ranges
+    ///
+    /// # Errors
+    /// Returns an error if the texture cannot be created or uploaded
+    pub fn rebuild_fonts(&mut self, settings: &fonts::FontSettings) -> Result<()> {
+        let data = fonts::FontData::load(settings).unwrap_or_else(|e| {
+            warn!("Falling back to the built-in font: {}", e);
+            self.notifications.push(notifications::ToastLevel::Warning, e.user_summary());
+            fonts::FontData::default()
+        });
         
-        // Build font atlas with better fonts
-        let fonts = self.context.fonts();
-        
-        // Configure font for better readability
-        let mut font_config = imgui::FontConfig::default();
-        font_config.size_pixels = 16.0; // Slightly larger for better readability
-        font_config.oversample_h = 2; // Better horizontal rendering
-        font_config.oversample_v = 1; // Better vertical rendering
-        font_config.pixel_snap_h = true; // Crisp text rendering
-        
-        // Try to add a better font - you can customize this
-        let font_sources = vec![
-            // Option 1: Try to load a system font using TtfData (we'll read the file at runtime)
-            // For now, we'll use the default font with better configuration
-            imgui::FontSource::DefaultFontData {
-                config: Some(font_config),
-            },
-        ];
-        
-        fonts.add_font(&font_sources);
-        
-        // Get font texture data
-        let font_texture = fonts.build_rgba32_texture();
+        let atlas = self.context.fonts();
+        self.fonts = fonts::add_fonts(atlas, settings, &data);
+        // Draws with the atlas name it by the backend's registry id
+        if let Some(imgui_backend) = &self.imgui_backend {
+            atlas.tex_id = imgui_backend.font_texture_id();
+        }
+        let font_texture = atlas.build_rgba32_texture();
         
-        // Upload font texture to GPU
+        // Upload font texture to GPU; the previous one is destroyed once no frame uses it
         if let Some(imgui_backend) = &mut self.imgui_backend {
             imgui_backend.create_font_texture(font_texture.width, font_texture.height)?;
-            
-            // Upload the actual font data
             imgui_backend.upload_font_data(font_texture.width, font_texture.height, font_texture.data)?;
             debug!("Font texture uploaded with size {}x{}", font_texture.width, font_texture.height);
         }
+        
+        self.toolbar.show_glyphs = self.fonts.has_icons;
+        info!("HUD font atlas built with {} size(s)", self.fonts.sizes.len());
         Ok(())
     }
     
@@ -447,6 +958,35 @@ impl HUD {
         self.context.io_mut()
     }
 
+    /// Forward a keyboard or modifier event to ImGui, for text fields and key queries
+    ///
+    /// # Arguments
+    /// * `window` - The window the event belongs to
+    /// * `window_id` - Id of that window
+    /// * `event` - The window event
+    pub fn handle_keyboard_event(&mut self, window: &Window, window_id: winit::window::WindowId, event: &winit::event::WindowEvent) {
+        if let Some(platform) = &mut self.platform {
+            let event = winit::event::Event::<()>::WindowEvent { window_id, event: event.clone() };
+            platform.handle_event(self.context.io_mut(), window, &event);
+        }
+    }
+
+    /// Check whether ImGui used the mouse in the last frame
+    ///
+    /// # Returns
+    /// True if the mouse is over a window or dragging a widget
+    pub fn wants_mouse(&self) -> bool {
+        self.context.io().want_capture_mouse
+    }
+
+    /// Check whether ImGui used the keyboard in the last frame
+    ///
+    /// # Returns
+    /// True while a text field or another widget takes key presses
+    pub fn wants_keyboard(&self) -> bool {
+        self.context.io().want_capture_keyboard
+    }
+
     /// Set up hot reload callbacks after ECS world is available
     ///
     /// # Arguments
@@ -489,6 +1029,7 @@ impl HUD {
 impl Drop for HUD {
     fn drop(&mut self) {
         info!("Destroying HUD system");
+        self.save_layout();
         
         // Explicitly clean up the ImGui Vulkan backend to ensure buffers are destroyed
         // before the Vulkan device is destroyed
@@ -0,0 +1,203 @@
+//! Modifiers panel
+//!
+//! Inspector for the modifier stacks of the SDF shapes: every shape lists
+//! its modifiers in the order they are applied, each with its parameters
+//! and buttons moving it up or down the stack or removing it, plus a
+//! combo adding a modifier at the end. The ECS world refreshes the snapshot
+//! every frame and applies the queued edits, so changes show in the next
+//! frame. The global noise seed and scale, used by the noise modifier, are
+//! edited at the top and copied into the `NoiseSettings` resource.
+
+use imgui::{TreeNodeFlags, Ui};
+use legion::Entity;
+use crate::config;
+use crate::ecs::components::SDFShapeType;
+use crate::ecs::modifiers::SDFModifier;
+use crate::hud::edit_queue::EditQueue;
+use crate::locale::{named, panel_title, tr, tr_args};
+use crate::noise::NoiseSettings;
+
+/// Snapshot of a shape's modifier stack
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifierStackView {
+    /// Shape entity
+    pub entity: Entity,
+    pub shape_type: SDFShapeType,
+    /// Modifiers in the order they are applied
+    pub modifiers: Vec<SDFModifier>,
+}
+
+/// Replace the modifier stack of a shape, an empty stack removes it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifierEdit {
+    pub entity: Entity,
+    pub modifiers: Vec<SDFModifier>,
+}
+
+/// Modifiers panel state
+pub struct ModifiersPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the shapes' stacks
+    pub stacks: Vec<ModifierStackView>,
+
+    /// Global noise settings
+    pub noise: NoiseSettings,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: EditQueue<Entity, ModifierEdit>,
+
+    /// Kind picked in the add combo, index into `SDFModifier::ALL`
+    add_kind: usize,
+}
+
+impl Default for ModifiersPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModifiersPanel {
+    /// Create a new modifiers panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            stacks: Vec::new(),
+            noise: NoiseSettings::default(),
+            pending_edits: EditQueue::default(),
+            add_kind: 0,
+        }
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<ModifierEdit> {
+        self.pending_edits.take()
+    }
+
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Modifiers"))
+            .position([960.0, 200.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 420.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if ui.collapsing_header(tr("modifiers.noise"), TreeNodeFlags::DEFAULT_OPEN) {
+                imgui::Drag::new(tr("modifiers.noise_seed")).build(ui, &mut self.noise.seed);
+                ui.slider_config(tr("modifiers.noise_scale"), 0.01, 100.0)
+                    .flags(imgui::SliderFlags::LOGARITHMIC)
+                    .build(&mut self.noise.scale);
+            }
+
+            let total: usize = self.stacks.iter().map(|stack| stack.modifiers.len()).sum();
+            if total > config::rendering::MAX_SDF_MODIFIERS {
+                ui.text_colored([1.0, 0.6, 0.2, 1.0], tr_args("modifiers.too_many", &[("count", &config::rendering::MAX_SDF_MODIFIERS)]));
+            }
+
+            if self.stacks.is_empty() {
+                ui.text_disabled(tr("modifiers.empty"));
+                return;
+            }
+
+            for index in 0..self.stacks.len() {
+                self.render_stack(ui, index);
+            }
+        }
+    }
+
+    /// Render the stack of one shape
+    fn render_stack(&mut self, ui: &Ui, index: usize) {
+        let _id = ui.push_id_usize(index);
+        let view = &self.stacks[index];
+        let label = tr_args("modifiers.shape", &[
+            ("shape", &format!("{:?}", view.shape_type)),
+            ("count", &view.modifiers.len()),
+            ("entity", &format!("{:?}", view.entity)),
+        ]);
+        if !ui.collapsing_header(&label, TreeNodeFlags::empty()) {
+            return;
+        }
+
+        let mut modifiers = view.modifiers.clone();
+        let mut changed = false;
+        let mut moved = None;
+        let mut removed = None;
+        for (position, modifier) in modifiers.iter_mut().enumerate() {
+            let _modifier_id = ui.push_id_usize(position);
+            ui.separator();
+            ui.text(format!("{}. {}", position + 1, named("modifier_kinds", modifier.name())));
+            ui.same_line();
+            if position > 0 && ui.small_button(tr("modifiers.up")) {
+                moved = Some((position, position - 1));
+            }
+            ui.same_line();
+            if position + 1 < view.modifiers.len() && ui.small_button(tr("modifiers.down")) {
+                moved = Some((position, position + 1));
+            }
+            ui.same_line();
+            if ui.small_button(tr("modifiers.remove")) {
+                removed = Some(position);
+            }
+            changed |= edit_modifier(ui, modifier);
+        }
+
+        if let Some((from, to)) = moved {
+            modifiers.swap(from, to);
+            changed = true;
+        }
+        if let Some(position) = removed {
+            modifiers.remove(position);
+            changed = true;
+        }
+
+        ui.separator();
+        ui.combo(tr("modifiers.kind"), &mut self.add_kind, &SDFModifier::ALL, |modifier| named("modifier_kinds", modifier.name()).into());
+        ui.same_line();
+        if ui.button(tr("modifiers.add")) {
+            modifiers.push(SDFModifier::ALL[self.add_kind]);
+            changed = true;
+        }
+
+        if changed {
+            let entity = self.stacks[index].entity;
+            self.stacks[index].modifiers = modifiers.clone();
+            self.pending_edits.push(Some(entity), ModifierEdit { entity, modifiers });
+        }
+    }
+}
+
+/// Parameter controls of one modifier
+///
+/// # Returns
+/// Whether a parameter changed
+fn edit_modifier(ui: &Ui, modifier: &mut SDFModifier) -> bool {
+    match modifier {
+        SDFModifier::Twist { rate } | SDFModifier::Bend { rate } => {
+            ui.slider_config(tr("modifiers.rate"), -10.0, 10.0)
+                .display_format("%.2f rad/unit")
+                .build(rate)
+        }
+        SDFModifier::Repeat { spacing, count } => {
+            let mut changed = imgui::Drag::new(tr("modifiers.spacing")).speed(0.01).range(0.0, 100.0).build_array(ui, spacing);
+            changed |= imgui::Drag::new(tr("modifiers.count")).range(0, 64).build(ui, count);
+            if *count == 0 {
+                ui.text_disabled(tr("modifiers.endless"));
+            }
+            changed
+        }
+        SDFModifier::Onion { thickness } => ui.slider(tr("modifiers.thickness"), 0.0, 0.5, thickness),
+        SDFModifier::Round { radius } => ui.slider(tr("modifiers.radius"), 0.0, 0.5, radius),
+        SDFModifier::Noise { amplitude, frequency } => {
+            let changed = ui.slider(tr("modifiers.amplitude"), 0.0, 0.5, amplitude);
+            changed | ui.slider_config(tr("modifiers.frequency"), 0.1, 32.0)
+                .flags(imgui::SliderFlags::LOGARITHMIC)
+                .build(frequency)
+        }
+    }
+}
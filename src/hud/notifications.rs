@@ -0,0 +1,286 @@
+//! Error notifications
+//!
+//! Recoverable errors are shown as toasts in the top-right corner that fade
+//! out after a few seconds; fatal errors open a dialog with the user-facing
+//! summary, the full context chain and a button to quit. Both show the
+//! error code and can copy the full error report to the clipboard.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use imgui::Ui;
+use crate::config;
+use crate::error::AppError;
+use crate::hud::toolbar::ScreenRect;
+use crate::locale::{title, tr};
+
+/// Severity of a toast
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastLevel {
+    /// Informational message
+    Info,
+    /// Something went wrong but was worked around
+    Warning,
+    /// A recoverable error
+    Error,
+}
+
+impl ToastLevel {
+    /// Text color of the level
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            ToastLevel::Info => [0.8, 0.9, 1.0, 1.0],
+            ToastLevel::Warning => [1.0, 0.8, 0.3, 1.0],
+            ToastLevel::Error => [1.0, 0.4, 0.4, 1.0],
+        }
+    }
+}
+
+/// A short-lived notification
+#[derive(Debug, Clone)]
+pub struct Toast {
+    /// Unique id, used for the ImGui window name
+    id: u64,
+    /// Severity
+    pub level: ToastLevel,
+    /// Message shown to the user
+    pub message: String,
+    /// Full error report offered by "Copy details", None for plain messages
+    pub details: Option<String>,
+    /// When the toast was shown
+    created: Instant,
+}
+
+/// Contents of the fatal error dialog
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorDialog {
+    /// Stable error code
+    pub code: &'static str,
+    /// User-facing summary
+    pub summary: String,
+    /// Context chain, outermost first, ending with the root cause
+    pub details: Vec<String>,
+    /// Full error report copied by "Copy details"
+    pub report: String,
+}
+
+impl ErrorDialog {
+    /// Build the dialog contents from an error
+    pub fn from_error(error: &AppError) -> Self {
+        let mut details: Vec<String> = error
+            .contexts()
+            .map(|context| {
+                let mut line = format!("[{}] {}", context.subsystem.name(), context.operation);
+                if let Some(result) = context.vk_result {
+                    line += &format!(" ({:?})", result);
+                }
+                for (key, value) in &context.details {
+                    line += &format!(", {} {}", key, value);
+                }
+                line
+            })
+            .collect();
+        details.push(error.root().to_string());
+
+        Self {
+            code: error.code(),
+            summary: error.user_summary(),
+            details,
+            report: error.report(),
+        }
+    }
+}
+
+/// Toast queue and error dialog state
+pub struct Notifications {
+    /// Visible toasts, oldest first
+    pub toasts: VecDeque<Toast>,
+
+    /// Fatal error dialog, if open
+    pub dialog: Option<ErrorDialog>,
+
+    /// Id of the next toast
+    next_id: u64,
+
+    /// Set when the user pressed "Quit" in the error dialog
+    quit_requested: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notifications {
+    /// Create an empty notification queue
+    pub fn new() -> Self {
+        Self {
+            toasts: VecDeque::new(),
+            dialog: None,
+            next_id: 0,
+            quit_requested: false,
+        }
+    }
+
+    /// Show a toast
+    ///
+    /// # Arguments
+    /// * `level` - Severity of the toast
+    /// * `message` - Message shown to the user
+    pub fn push(&mut self, level: ToastLevel, message: impl Into<String>) {
+        self.push_with_details(level, message.into(), None);
+    }
+
+    /// Show a toast with a report for "Copy details"
+    fn push_with_details(&mut self, level: ToastLevel, message: String, details: Option<String>) {
+        // Repeated errors (e.g. every frame) refresh the existing toast instead of stacking
+        if let Some(toast) = self.toasts.iter_mut().find(|toast| toast.level == level && toast.message == message) {
+            toast.created = Instant::now();
+            toast.details = details;
+            return;
+        }
+
+        self.toasts.push_back(Toast {
+            id: self.next_id,
+            level,
+            message,
+            details,
+            created: Instant::now(),
+        });
+        self.next_id += 1;
+        while self.toasts.len() > config::notifications::MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+    }
+
+    /// Report an error: fatal errors open the dialog, the rest become toasts
+    pub fn report(&mut self, error: &AppError) {
+        if error.is_fatal() {
+            self.dialog = Some(ErrorDialog::from_error(error));
+        } else {
+            let message = format!("[{}] {}", error.code(), error.user_summary());
+            self.push_with_details(ToastLevel::Error, message, Some(error.report()));
+        }
+    }
+
+    /// Check whether the user asked to quit from the error dialog, clearing the request
+    pub fn take_quit_request(&mut self) -> bool {
+        std::mem::take(&mut self.quit_requested)
+    }
+
+    /// Drop toasts older than the configured duration
+    fn expire(&mut self) {
+        let lifetime = Duration::from_secs_f32(config::notifications::TOAST_DURATION_SECS);
+        self.toasts.retain(|toast| toast.created.elapsed() < lifetime);
+    }
+
+    /// Render toasts and the error dialog
+    ///
+    /// # Arguments
+    /// * `ui` - ImGui frame
+    /// * `area` - Screen area toasts stack in, the corner opposite the toolbar
+    pub fn render(&mut self, ui: &Ui, area: ScreenRect) {
+        self.expire();
+        let display_size = ui.io().display_size;
+
+        let mut y = area.min[1] + 10.0;
+        for toast in &mut self.toasts {
+            // Only toasts with a report take clicks, the rest let them through to the scene
+            let window = ui.window(format!("##toast{}", toast.id))
+                .position([area.max[0] - 10.0, y], imgui::Condition::Always)
+                .position_pivot([1.0, 0.0])
+                .size([320.0, 0.0], imgui::Condition::Always)
+                .no_decoration()
+                .mouse_inputs(toast.details.is_some())
+                .nav_inputs(false)
+                .nav_focus(false)
+                .focus_on_appearing(false)
+                .bg_alpha(0.85);
+            if let Some(_token) = window.begin() {
+                ui.text_colored(toast.level.color(), &toast.message);
+                if let Some(ref details) = toast.details {
+                    if ui.small_button(tr("notifications.copy_details")) {
+                        ui.set_clipboard_text(details);
+                    }
+                    // Keep the toast while the user is reaching for it
+                    if ui.is_window_hovered() {
+                        toast.created = Instant::now();
+                    }
+                }
+                y += ui.window_size()[1] + 6.0;
+            }
+        }
+
+        let mut close_dialog = false;
+        if let Some(ref dialog) = self.dialog {
+            let window = ui.window(title("notifications.error", "Error"))
+                .position([display_size[0] * 0.5, display_size[1] * 0.5], imgui::Condition::Always)
+                .position_pivot([0.5, 0.5])
+                .size([480.0, 0.0], imgui::Condition::Always)
+                .collapsible(false)
+                .bg_alpha(0.95);
+            if let Some(_token) = window.begin() {
+                ui.text_colored(ToastLevel::Error.color(), format!("[{}] {}", dialog.code, dialog.summary));
+                if ui.collapsing_header(format!("{}###details", tr("notifications.details")), imgui::TreeNodeFlags::empty()) {
+                    for line in &dialog.details {
+                        ui.text_wrapped(line);
+                    }
+                }
+                ui.separator();
+                if ui.button(tr("notifications.copy_details")) {
+                    ui.set_clipboard_text(&dialog.report);
+                }
+                ui.same_line();
+                if ui.button(tr("notifications.dismiss")) {
+                    close_dialog = true;
+                }
+                ui.same_line();
+                if ui.button(tr("notifications.quit")) {
+                    self.quit_requested = true;
+                }
+            }
+        }
+        if close_dialog {
+            self.dialog = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Subsystem, VulkanError};
+    use ash::vk;
+
+    #[test]
+    fn test_report_routes_by_severity() {
+        let mut notifications = Notifications::new();
+
+        let recoverable = AppError::Vulkan(VulkanError::Rendering("oops".to_string()))
+            .context(Subsystem::Renderer, "draw frame");
+        notifications.report(&recoverable);
+        notifications.report(&recoverable);
+        assert_eq!(notifications.toasts.len(), 1);
+        assert!(notifications.dialog.is_none());
+        let toast = &notifications.toasts[0];
+        assert_eq!(toast.message, "[VK-010] Renderer: failed to draw frame (Vulkan error: Rendering error: oops)");
+        assert_eq!(toast.details.as_deref(), Some(recoverable.report().as_str()));
+
+        let fatal = AppError::from_vk(vk::Result::ERROR_DEVICE_LOST, Subsystem::Renderer, "submit frame");
+        notifications.report(&fatal);
+        let dialog = notifications.dialog.as_ref().expect("fatal errors open the dialog");
+        assert_eq!(dialog.details[0], "[Renderer] submit frame (ERROR_DEVICE_LOST)");
+        assert_eq!(dialog.details.len(), 2);
+        assert_eq!(dialog.code, "VK-100");
+    }
+
+    #[test]
+    fn test_toasts_are_capped() {
+        let mut notifications = Notifications::new();
+        for i in 0..config::notifications::MAX_TOASTS + 3 {
+            notifications.push(ToastLevel::Info, format!("toast {}", i));
+        }
+        assert_eq!(notifications.toasts.len(), config::notifications::MAX_TOASTS);
+        assert_eq!(notifications.toasts.back().unwrap().message, format!("toast {}", config::notifications::MAX_TOASTS + 2));
+    }
+}
@@ -0,0 +1,146 @@
+//! Particles panel
+//!
+//! Inspector for the particle emitters in the scene: spawn rate, lifetime,
+//! initial motion, size and colors of each emitter, plus buttons adding an
+//! emitter at the origin and removing one. The ECS world refreshes the
+//! snapshot every frame and applies the queued edits, so changes show in
+//! the next particles spawned.
+
+use imgui::{TreeNodeFlags, Ui};
+use legion::Entity;
+use crate::config;
+use crate::ecs::particles::EmitterSettings;
+use crate::hud::background_panel::edit_linear_color;
+use crate::hud::edit_queue::EditQueue;
+use crate::locale::{panel_title, tr, tr_args};
+
+/// Snapshot of a particle emitter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmitterView {
+    /// Emitter entity
+    pub entity: Entity,
+    /// World position particles spawn at
+    pub position: [f32; 3],
+    pub settings: EmitterSettings,
+}
+
+/// Edit requested from the panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleEdit {
+    /// Spawn an emitter with the default settings
+    Add { position: [f32; 3] },
+    /// Replace the settings of an emitter
+    Set { entity: Entity, settings: EmitterSettings },
+    /// Despawn an emitter entity
+    Remove { entity: Entity },
+}
+
+/// Particles panel state
+pub struct ParticlesPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the scene's emitters
+    pub emitters: Vec<EmitterView>,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: EditQueue<Entity, ParticleEdit>,
+}
+
+impl Default for ParticlesPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParticlesPanel {
+    /// Create a new particles panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            emitters: Vec::new(),
+            pending_edits: EditQueue::default(),
+        }
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<ParticleEdit> {
+        self.pending_edits.take()
+    }
+
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Particles"))
+            .position([960.0, 120.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 420.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if ui.button(tr("particles.add_emitter")) {
+                self.pending_edits.push(None, ParticleEdit::Add { position: [0.0, 0.5, 0.0] });
+            }
+            let spawn_rate: f32 = self.emitters.iter()
+                .filter(|view| view.settings.enabled)
+                .map(|view| view.settings.rate)
+                .sum();
+            ui.text_disabled(tr_args("particles.budget", &[("rate", &format!("{:.0}", spawn_rate)), ("slots", &config::particles::CAPACITY)]));
+            if self.emitters.len() > config::particles::MAX_EMITTERS {
+                ui.text_colored([1.0, 0.6, 0.2, 1.0], tr_args("particles.too_many", &[("count", &config::particles::MAX_EMITTERS)]));
+            }
+
+            if self.emitters.is_empty() {
+                ui.text_disabled(tr("particles.empty"));
+                return;
+            }
+
+            for index in 0..self.emitters.len() {
+                self.render_emitter(ui, index);
+            }
+        }
+    }
+
+    /// Render the controls of one emitter
+    fn render_emitter(&mut self, ui: &Ui, index: usize) {
+        let view = self.emitters[index];
+        let _id = ui.push_id_usize(index);
+        let [x, y, z] = view.position;
+        let position = format!("{:.1}, {:.1}, {:.1}", x, y, z);
+        let label = tr_args("particles.emitter", &[("position", &position), ("entity", &format!("{:?}", view.entity))]);
+        if !ui.collapsing_header(&label, TreeNodeFlags::DEFAULT_OPEN) {
+            return;
+        }
+
+        let mut settings = view.settings;
+        let mut changed = ui.checkbox(tr("particles.enabled"), &mut settings.enabled);
+        changed |= ui.slider_config(tr("particles.rate"), 0.0, 5000.0)
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .display_format("%.0f /s")
+            .build(&mut settings.rate);
+        changed |= ui.slider_config(tr("particles.lifetime"), 0.1, 10.0)
+            .display_format("%.1f s")
+            .build(&mut settings.lifetime);
+        changed |= imgui::Drag::new(tr("particles.direction")).speed(0.01).range(-1.0, 1.0).build_array(ui, &mut settings.direction);
+        changed |= ui.slider(tr("particles.speed"), 0.0, 20.0, &mut settings.speed);
+        changed |= imgui::AngleSlider::new(tr("particles.spread")).range_degrees(0.0, 180.0).build(ui, &mut settings.spread);
+        changed |= ui.slider(tr("particles.gravity"), -10.0, 10.0, &mut settings.gravity);
+        changed |= ui.slider_config(tr("particles.size"), 0.005, 0.5)
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .build(&mut settings.size);
+        changed |= edit_linear_color(ui, &tr("particles.start_color"), &mut settings.start_color);
+        changed |= edit_linear_color(ui, &tr("particles.end_color"), &mut settings.end_color);
+        changed |= ui.slider(tr("particles.brightness"), 0.0, 20.0, &mut settings.brightness);
+        if changed {
+            self.emitters[index].settings = settings;
+            self.pending_edits.push(Some(view.entity), ParticleEdit::Set { entity: view.entity, settings });
+        }
+
+        if ui.button(tr("particles.remove")) {
+            self.pending_edits.push(None, ParticleEdit::Remove { entity: view.entity });
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Physics panel
+//!
+//! Toggles for the rigid body simulation and gravity, and a "Throw sphere"
+//! debug action launching a dynamic sphere from the camera. The ECS world
+//! copies the toggles into its physics settings every frame.
+
+use imgui::Ui;
+use log::debug;
+use crate::ecs::physics::PhysicsSettings;
+use crate::locale::{panel_title, tr, tr_args};
+
+/// Physics panel state
+pub struct PhysicsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Simulation toggles applied by the ECS world
+    pub settings: PhysicsSettings,
+
+    /// Number of bodies in the simulation
+    pub body_count: usize,
+
+    /// Set when "Throw sphere" was pressed, until taken
+    throw_requested: bool,
+}
+
+impl Default for PhysicsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhysicsPanel {
+    /// Create a new physics panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            settings: PhysicsSettings::default(),
+            body_count: 0,
+            throw_requested: false,
+        }
+    }
+
+    /// Check whether a sphere should be thrown, clearing the request
+    pub fn take_throw_request(&mut self) -> bool {
+        std::mem::take(&mut self.throw_requested)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Physics"))
+            .position([960.0, 570.0], imgui::Condition::FirstUseEver)
+            .size([240.0, 140.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            ui.checkbox(tr("physics.simulate"), &mut self.settings.simulate);
+            ui.checkbox(tr("physics.gravity"), &mut self.settings.gravity);
+            if ui.button(tr("physics.throw_sphere")) {
+                debug!("Sphere throw requested");
+                self.throw_requested = true;
+            }
+            ui.text_disabled(tr_args("physics.bodies", &[("count", &self.body_count)]));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Project panel
+//!
+//! Shows the open project and its folders, opens another `project.toml`
+//! with a file dialog or from the recent projects list. The chosen file is
+//! opened by the app on the next frame.
+
+use std::path::PathBuf;
+use imgui::Ui;
+use log::debug;
+use crate::config;
+use crate::locale::{panel_title, tr, tr_args};
+use crate::platform::dialogs::{DialogOptions, DialogSlot};
+use crate::project::Project;
+
+/// Project panel state
+pub struct ProjectPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// The open project, the default one when none is open
+    pub project: Project,
+
+    /// Recently opened project files, the latest first
+    pub recent: Vec<PathBuf>,
+
+    /// Project file chosen in the UI, waiting to be opened
+    pending_open: Option<PathBuf>,
+
+    /// "Open Project..." dialog
+    dialog: DialogSlot<()>,
+}
+
+impl Default for ProjectPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProjectPanel {
+    /// Create a new project panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            project: Project::default(),
+            recent: Vec::new(),
+            pending_open: None,
+            dialog: DialogSlot::default(),
+        }
+    }
+
+    /// Take the project file chosen since the last call
+    pub fn take_pending_open(&mut self) -> Option<PathBuf> {
+        self.pending_open.take()
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if let Some(((), path)) = self.dialog.poll() {
+            debug!("Project chosen: {}", path.display());
+            self.pending_open = Some(path);
+        }
+
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Project"))
+            .position([320.0, 80.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 240.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            let project = &self.project;
+            if project.is_open() {
+                ui.text(&project.name);
+                ui.text_disabled(project.root.display().to_string());
+                ui.text(tr_args("project.shaders", &[("path", &project.shader_dir.display())]));
+                ui.text(tr_args("project.scene", &[("path", &project.scene_file.display())]));
+                let texture_dirs: Vec<String> = project.texture_dirs.iter().map(|dir| dir.display().to_string()).collect();
+                ui.text(tr_args("project.textures", &[("paths", &texture_dirs.join(", "))]));
+                ui.text(tr_args("project.export", &[("path", &project.export.output_dir.display())]));
+            } else {
+                ui.text_disabled(tr("project.none"));
+            }
+
+            if ui.button(tr("project.open")) {
+                let mut options = DialogOptions::open_file(&tr("project.open_dialog")).filter(&tr("project.filter"), &["toml"]);
+                if let Some(recent) = self.recent.first() {
+                    options = options.directory(recent);
+                }
+                self.dialog.open((), options);
+            }
+
+            ui.separator();
+            ui.text(tr("project.recent"));
+            if self.recent.is_empty() {
+                ui.text_disabled(tr_args("project.no_recent", &[("file", &config::project::FILE_NAME)]));
+            }
+            for path in &self.recent {
+                if ui.selectable(path.display().to_string()) {
+                    self.pending_open = Some(path.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Recovery from a failed HUD initialization
+//!
+//! Without a HUD the app keeps rendering the scene. `HudRecovery` keeps why
+//! the HUD could not be created and which of the files it reads at startup
+//! are missing. Since nothing can be drawn with ImGui, the failure is shown
+//! in the window title instead, with the key of the Retry HUD action that
+//! runs the initialization again once the cause (e.g. a missing or broken
+//! shader) is fixed.
+
+use std::path::Path;
+use std::time::Instant;
+use crate::config;
+use crate::error::AppError;
+
+/// Files the HUD cannot start without
+const REQUIRED_FILES: [&str; 2] = [config::shader::IMGUI_VERTEX_SHADER, config::shader::IMGUI_FRAGMENT_SHADER];
+
+/// Why the HUD is unavailable and how often it failed
+#[derive(Debug, Clone)]
+pub struct HudRecovery {
+    /// Stable code of the latest failure
+    pub code: &'static str,
+    /// User-facing summary of the latest failure
+    pub summary: String,
+    /// Full report of the latest failure
+    pub report: String,
+    /// Required files that were missing at the latest failure
+    pub missing_files: Vec<String>,
+    /// Failed attempts, the first initialization included
+    pub attempts: u32,
+    /// When the latest attempt failed
+    pub failed_at: Instant,
+}
+
+impl HudRecovery {
+    /// Keep the first failure
+    pub fn new(error: &AppError) -> Self {
+        Self {
+            code: error.code(),
+            summary: error.user_summary(),
+            report: error.report(),
+            missing_files: missing_files(&REQUIRED_FILES),
+            attempts: 1,
+            failed_at: Instant::now(),
+        }
+    }
+
+    /// Replace the failure with the one of a retry
+    pub fn record_failure(&mut self, error: &AppError) {
+        let attempts = self.attempts + 1;
+        *self = Self { attempts, ..Self::new(error) };
+    }
+
+    /// Multi-line diagnostics for the log
+    pub fn diagnostics(&self) -> String {
+        let mut text = format!("HUD unavailable after {} attempt(s)\n{}", self.attempts, self.report);
+        for file in &self.missing_files {
+            text += &format!("\n  missing file: {}", file);
+        }
+        text
+    }
+
+    /// Window title showing the failure, the fallback for the HUD itself
+    ///
+    /// # Arguments
+    /// * `base_title` - Title of the window while the HUD works
+    /// * `retry_key` - Label of the Retry HUD key binding, if bound
+    pub fn window_title(&self, base_title: &str, retry_key: Option<&str>) -> String {
+        let mut title = format!("{} - HUD unavailable [{}]", base_title, self.code);
+        if let Some(file) = self.missing_files.first() {
+            title += &format!(", missing {}", file);
+        }
+        if let Some(key) = retry_key {
+            title += &format!(", press {} to retry", key);
+        }
+        title
+    }
+}
+
+/// Files of a list that do not exist
+fn missing_files(files: &[&str]) -> Vec<String> {
+    files.iter().filter(|file| !Path::new(file).exists()).map(|file| file.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ResultExt, Subsystem};
+
+    #[test]
+    fn test_failures_are_counted_and_titled() {
+        let error = std::fs::read("no/such/imgui.vert.spv")
+            .context(Subsystem::Hud, "read ImGui shader")
+            .unwrap_err();
+        let mut recovery = HudRecovery::new(&error);
+        recovery.missing_files = vec!["no/such/imgui.vert.spv".to_string()];
+        assert_eq!(
+            recovery.window_title("Vulkan App", Some("F4")),
+            "Vulkan App - HUD unavailable [IO-001], missing no/such/imgui.vert.spv, press F4 to retry"
+        );
+        assert!(recovery.diagnostics().contains("missing file: no/such/imgui.vert.spv"));
+
+        recovery.record_failure(&AppError::HUD("still broken".to_string()));
+        assert_eq!(recovery.attempts, 2);
+        assert_eq!(recovery.code, "HUD-001");
+        assert_eq!(missing_files(&["Cargo.toml", "no/such/file"]), vec!["no/such/file".to_string()]);
+    }
+}
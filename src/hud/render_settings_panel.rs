@@ -0,0 +1,406 @@
+//! Render settings panel
+//!
+//! Lets the user switch renderer options that require pipeline
+//! recreation, such as the raymarch quality tier and the shading feature
+//! variant, size the offscreen SDF target relative to the window (the
+//! render scale, from 25% up to 200% supersampling, while the HUD stays at
+//! the window resolution), override the adaptive SDF resolution scale within
+//! that target, and change the
+//! shader compiler settings used by the next reload. Changes are queued
+//! and applied by the ECS world before the next frame is drawn. Exposure
+//! and the tonemapping operator are read by the world every frame.
+//!
+//! The GPU time of the SDF pass is smoothed per shader variant, so every
+//! shading feature shows what it costs: the time with it minus the time
+//! without it, once both variants were measured at the same render scale.
+
+use std::collections::HashMap;
+use imgui::Ui;
+use log::info;
+use crate::config;
+use crate::vulkan::quality::RaymarchQuality;
+use crate::vulkan::shader_variant::{ShaderVariantKey, SDF_FEATURES};
+use crate::vulkan::shader_compiler::{ShaderCompileSettings, ShaderTargetEnv};
+use crate::vulkan::tonemap::{DisplayOutput, TonemapOperator, TonemapSettings};
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Smoothed GPU time of the SDF pass with one shader variant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariantCost {
+    /// GPU time in milliseconds
+    pub ms: f32,
+    /// Resolution scale the time was measured at
+    pub render_scale: f32,
+}
+
+/// Render settings panel state
+pub struct RenderSettingsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Quality tier currently selected in the panel
+    pub quality: RaymarchQuality,
+
+    /// Quality change waiting to be applied by the renderer
+    pending_quality: Option<RaymarchQuality>,
+
+    /// Shader variant (feature defines) currently selected in the panel
+    pub variant: ShaderVariantKey,
+
+    /// Shader variant change waiting to be applied by the renderer
+    pending_variant: Option<ShaderVariantKey>,
+
+    /// Shader compiler settings selected in the panel
+    pub compile_settings: ShaderCompileSettings,
+
+    /// Compile settings change waiting to be applied to the shader compiler
+    pending_compile_settings: Option<ShaderCompileSettings>,
+
+    /// Size of the offscreen SDF target relative to the window
+    pub target_scale: f32,
+
+    /// Render scale change waiting to be applied, once the slider is released
+    pending_target_scale: Option<f32>,
+
+    /// Size of the offscreen SDF target in pixels (display only)
+    pub target_size: [u32; 2],
+
+    /// Whether the SDF resolution scale is chosen automatically
+    pub auto_resolution: bool,
+
+    /// Manual resolution scale used when automatic scaling is off
+    pub manual_scale: f32,
+
+    /// Resolution override waiting to be applied (`Some(None)` clears it)
+    pending_resolution_override: Option<Option<f32>>,
+
+    /// Render scale currently used by the renderer (display only)
+    pub render_scale: f32,
+
+    /// Last measured GPU time of the SDF pass (display only)
+    pub gpu_frame_ms: Option<f32>,
+
+    /// Smoothed GPU time of each shader variant measured so far
+    variant_costs: HashMap<ShaderVariantKey, VariantCost>,
+
+    /// Frames to leave out of the costs, still drawn with the previous variant
+    settle_frames: usize,
+
+    /// Exposure and tonemapping operator of the composite pass
+    pub tonemap: TonemapSettings,
+
+    /// Encoding of the swapchain (display only)
+    pub display_output: DisplayOutput,
+}
+
+impl Default for RenderSettingsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderSettingsPanel {
+    /// Create a new render settings panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            quality: RaymarchQuality::default(),
+            pending_quality: None,
+            variant: ShaderVariantKey::sdf_default(),
+            pending_variant: None,
+            compile_settings: ShaderCompileSettings::default(),
+            pending_compile_settings: None,
+            target_scale: config::resolution_scaling::DEFAULT_RENDER_SCALE,
+            pending_target_scale: None,
+            target_size: [0, 0],
+            auto_resolution: true,
+            manual_scale: config::resolution_scaling::MAX_SCALE,
+            pending_resolution_override: None,
+            render_scale: config::resolution_scaling::MAX_SCALE,
+            gpu_frame_ms: None,
+            variant_costs: HashMap::new(),
+            settle_frames: 0,
+            tonemap: TonemapSettings::default(),
+            display_output: DisplayOutput::Sdr,
+        }
+    }
+
+    /// Take the pending quality change, if any
+    pub fn take_pending_quality(&mut self) -> Option<RaymarchQuality> {
+        self.pending_quality.take()
+    }
+
+    /// Take the pending shader variant change, if any
+    pub fn take_pending_variant(&mut self) -> Option<ShaderVariantKey> {
+        self.pending_variant.take()
+    }
+
+    /// Take the pending shader compile settings change, if any
+    pub fn take_pending_compile_settings(&mut self) -> Option<ShaderCompileSettings> {
+        self.pending_compile_settings.take()
+    }
+
+    /// Take the pending render scale change, if any
+    pub fn take_pending_target_scale(&mut self) -> Option<f32> {
+        self.pending_target_scale.take()
+    }
+
+    /// Take the pending resolution override change, if any
+    ///
+    /// # Returns
+    /// `Some(Some(scale))` to force a scale, `Some(None)` to return to
+    /// automatic scaling, or `None` if nothing changed
+    pub fn take_pending_resolution_override(&mut self) -> Option<Option<f32>> {
+        self.pending_resolution_override.take()
+    }
+
+    /// Record the GPU time of the last frame
+    ///
+    /// # Arguments
+    /// * `gpu_ms` - GPU time of the SDF pass, None if not measured
+    /// * `render_scale` - Resolution scale used by the renderer
+    pub fn record_gpu_time(&mut self, gpu_ms: Option<f32>, render_scale: f32) {
+        self.gpu_frame_ms = gpu_ms;
+        self.render_scale = render_scale;
+        let Some(ms) = gpu_ms else {
+            return;
+        };
+        if self.pending_variant.is_some() || self.settle_frames > 0 {
+            self.settle_frames = self.settle_frames.saturating_sub(1);
+            return;
+        }
+
+        // A new render scale starts the average over
+        let cost = self.variant_costs.entry(self.variant.clone()).or_insert(VariantCost { ms, render_scale });
+        if cost.render_scale != render_scale {
+            *cost = VariantCost { ms, render_scale };
+        }
+        cost.ms += (ms - cost.ms) * config::shader_features::COST_SMOOTHING;
+    }
+
+    /// Smoothed GPU time of a shader variant, if it was measured
+    pub fn variant_cost(&self, variant: &ShaderVariantKey) -> Option<VariantCost> {
+        self.variant_costs.get(variant).copied()
+    }
+
+    /// GPU time a shading feature adds to the current variant
+    ///
+    /// # Returns
+    /// Milliseconds with the feature minus without it, None until both
+    /// variants were measured at the same render scale
+    pub fn feature_cost_ms(&self, define: &str) -> Option<f32> {
+        let with = self.variant_cost(&self.variant.clone().with_feature(define, true))?;
+        let without = self.variant_cost(&self.variant.clone().with_feature(define, false))?;
+        (with.render_scale == without.render_scale).then_some(with.ms - without.ms)
+    }
+
+    /// Resolution override matching the current panel state
+    fn resolution_override(&self) -> Option<f32> {
+        if self.auto_resolution {
+            None
+        } else {
+            Some(self.manual_scale)
+        }
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Render Settings"))
+            .position([10.0, 70.0], imgui::Condition::FirstUseEver)
+            .size([250.0, 400.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            ui.text(tr("render_settings.raymarch_quality"));
+
+            let mut index = RaymarchQuality::ALL
+                .iter()
+                .position(|&q| q == self.quality)
+                .unwrap_or(0);
+
+            if ui.combo("##Quality", &mut index, &RaymarchQuality::ALL, |q| named("quality", q.name()).into()) {
+                let quality = RaymarchQuality::ALL[index];
+                if quality != self.quality {
+                    info!("Raymarch quality change requested: {}", quality.name());
+                    self.quality = quality;
+                    self.pending_quality = Some(quality);
+                }
+            }
+
+            let spec = self.quality.specialization();
+            ui.text_disabled(tr_args(
+                "render_settings.quality_spec",
+                &[("steps", &spec.max_steps), ("epsilon", &spec.epsilon), ("distance", &spec.max_distance)],
+            ));
+
+            ui.separator();
+            ui.text(tr("render_settings.shading"));
+
+            let mut variant = self.variant.clone();
+            for (define, label) in SDF_FEATURES {
+                let mut enabled = variant.has_define(define);
+                if ui.checkbox(named("shading", label), &mut enabled) {
+                    variant.set_feature(define, enabled);
+                }
+                ui.same_line();
+                match self.feature_cost_ms(define) {
+                    Some(ms) => ui.text_disabled(format!("{:+.2} ms", ms)),
+                    None => ui.text_disabled(tr("render_settings.not_available")),
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(tr("render_settings.feature_cost_hint"));
+                }
+            }
+            if variant != self.variant {
+                info!("Shader variant change requested: {}", variant.name());
+                self.variant = variant.clone();
+                self.pending_variant = Some(variant);
+                self.settle_frames = config::vulkan::MAX_FRAMES_IN_FLIGHT;
+            }
+
+            ui.separator();
+            ui.text(tr("render_settings.render_scale"));
+
+            // Percent on the slider; the target is only recreated once the drag ends
+            let mut percent = self.target_scale * 100.0;
+            ui.slider_config("##RenderScale", config::resolution_scaling::MIN_RENDER_SCALE * 100.0, config::resolution_scaling::MAX_RENDER_SCALE * 100.0)
+                .display_format("%.0f%%")
+                .build(&mut percent);
+            self.target_scale = percent / 100.0;
+            if ui.is_item_deactivated_after_edit() {
+                info!("Render scale change requested: {:.0}%", percent);
+                self.pending_target_scale = Some(self.target_scale);
+            }
+            ui.text_disabled(tr_args("render_settings.target_size", &[("width", &self.target_size[0]), ("height", &self.target_size[1])]));
+
+            ui.separator();
+            ui.text(tr("render_settings.resolution_scale"));
+
+            if ui.checkbox(tr("render_settings.auto"), &mut self.auto_resolution) {
+                info!("Adaptive resolution {}", if self.auto_resolution { "enabled" } else { "disabled" });
+                self.pending_resolution_override = Some(self.resolution_override());
+            }
+
+            let disabled_token = ui.begin_disabled(self.auto_resolution);
+            if ui.slider(
+                "##Scale",
+                config::resolution_scaling::MIN_SCALE,
+                config::resolution_scaling::MAX_SCALE,
+                &mut self.manual_scale,
+            ) {
+                self.pending_resolution_override = Some(self.resolution_override());
+            }
+            disabled_token.end();
+
+            let gpu_text = match self.gpu_frame_ms {
+                Some(ms) => format!("{:.2} ms", ms),
+                None => tr("render_settings.not_available"),
+            };
+            ui.text_disabled(tr_args("render_settings.scale_gpu", &[("scale", &format!("{:.0}", self.render_scale * 100.0)), ("gpu", &gpu_text)]));
+
+            ui.separator();
+            self.render_tonemap_settings(ui);
+
+            ui.separator();
+            self.render_compiler_settings(ui);
+        }
+    }
+
+    /// Render the tonemapping section
+    fn render_tonemap_settings(&mut self, ui: &Ui) {
+        ui.text(tr("render_settings.tonemapping"));
+
+        let mut index = TonemapOperator::ALL
+            .iter()
+            .position(|&op| op == self.tonemap.operator)
+            .unwrap_or(0);
+        if ui.combo(tr("render_settings.operator"), &mut index, &TonemapOperator::ALL, |op| named("tonemap_operators", op.name()).into()) {
+            self.tonemap.operator = TonemapOperator::ALL[index];
+        }
+
+        ui.slider_config(tr("render_settings.exposure"), -5.0, 5.0)
+            .display_format("%.1f")
+            .build(&mut self.tonemap.exposure_ev);
+
+        ui.text_disabled(tr_args("render_settings.output", &[("output", &self.display_output.name())]));
+    }
+
+    /// Render the shader compiler section
+    fn render_compiler_settings(&mut self, ui: &Ui) {
+        ui.text(tr("render_settings.shader_compiler"));
+
+        let mut settings = self.compile_settings;
+
+        let levels = ShaderCompileSettings::OPTIMIZATION_LEVELS;
+        let mut level_index = levels
+            .iter()
+            .position(|(level, _)| *level == settings.optimization_level)
+            .unwrap_or(0);
+        if ui.combo(tr("render_settings.optimization"), &mut level_index, &levels, |(_, name)| named("optimization_levels", name).into()) {
+            settings.optimization_level = levels[level_index].0;
+        }
+
+        let mut env_index = ShaderTargetEnv::ALL
+            .iter()
+            .position(|&env| env == settings.target_env)
+            .unwrap_or(0);
+        if ui.combo(tr("render_settings.target"), &mut env_index, &ShaderTargetEnv::ALL, |env| env.name().into()) {
+            settings.target_env = ShaderTargetEnv::ALL[env_index];
+        }
+
+        ui.checkbox(tr("render_settings.debug_info"), &mut settings.debug_info);
+
+        if settings != self.compile_settings {
+            info!("Shader compile settings change requested: {:?}", settings);
+            self.compile_settings = settings;
+            self.pending_compile_settings = Some(settings);
+        }
+
+        ui.text_disabled(tr("render_settings.applies_on_reload"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vulkan::shader_variant::CONE_GI;
+
+    #[test]
+    fn test_resolution_override_follows_auto_toggle() {
+        let mut panel = RenderSettingsPanel::new();
+        assert_eq!(panel.resolution_override(), None);
+
+        panel.auto_resolution = false;
+        panel.manual_scale = 0.75;
+        assert_eq!(panel.resolution_override(), Some(0.75));
+        assert_eq!(panel.take_pending_resolution_override(), None);
+    }
+
+    #[test]
+    fn test_feature_cost_compares_variants_at_one_scale() {
+        let mut panel = RenderSettingsPanel::new();
+        let without = panel.variant.clone().with_feature(CONE_GI, false);
+        panel.variant = without.clone();
+        panel.record_gpu_time(Some(2.0), 1.0);
+        assert_eq!(panel.feature_cost_ms(CONE_GI), None);
+
+        // Frames still in flight with the old variant are left out
+        panel.variant = without.clone().with_feature(CONE_GI, true);
+        panel.settle_frames = config::vulkan::MAX_FRAMES_IN_FLIGHT;
+        for _ in 0..config::vulkan::MAX_FRAMES_IN_FLIGHT {
+            panel.record_gpu_time(Some(2.0), 1.0);
+        }
+        panel.record_gpu_time(Some(5.0), 1.0);
+        assert_eq!(panel.feature_cost_ms(CONE_GI), Some(3.0));
+
+        // Times at another scale replace the average instead of mixing into it
+        panel.record_gpu_time(Some(4.0), 0.5);
+        assert_eq!(panel.variant_cost(&panel.variant), Some(VariantCost { ms: 4.0, render_scale: 0.5 }));
+        assert_eq!(panel.feature_cost_ms(CONE_GI), None);
+        assert_eq!(panel.variant_cost(&without).map(|cost| cost.ms), Some(2.0));
+    }
+}
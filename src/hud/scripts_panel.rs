@@ -0,0 +1,117 @@
+//! Scripts panel
+//!
+//! Lists the loaded Rhai scripts with their errors and the buttons they
+//! bound with `button(label, function)`. Pressing a button queues a call of
+//! the bound function, made by the ECS world on its next update.
+
+use imgui::Ui;
+use log::debug;
+use crate::hud::notifications::ToastLevel;
+use crate::locale::{panel_title, tr_args};
+
+/// A HUD button bound to a script function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptButton {
+    /// Button text
+    pub label: String,
+    /// Function called without arguments when pressed
+    pub function: String,
+}
+
+/// Snapshot of one loaded script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptView {
+    /// File name
+    pub name: String,
+    /// Error that stopped the script, if any
+    pub error: Option<String>,
+    /// Buttons bound by the script
+    pub buttons: Vec<ScriptButton>,
+    /// Number of entities the script spawned
+    pub entities: usize,
+}
+
+/// Button press waiting to be handled by the ECS world
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptCall {
+    /// Script file name
+    pub script: String,
+    /// Function to call
+    pub function: String,
+}
+
+/// Scripts panel state
+pub struct ScriptsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the loaded scripts
+    pub scripts: Vec<ScriptView>,
+
+    /// Button presses waiting to be handled
+    pending_calls: Vec<ScriptCall>,
+}
+
+impl Default for ScriptsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptsPanel {
+    /// Create a new scripts panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            scripts: Vec::new(),
+            pending_calls: Vec::new(),
+        }
+    }
+
+    /// Take the button presses queued since the last call
+    pub fn take_pending_calls(&mut self) -> Vec<ScriptCall> {
+        std::mem::take(&mut self.pending_calls)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Scripts"))
+            .position([640.0, 570.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 220.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if self.scripts.is_empty() {
+                ui.text_disabled(tr_args("scripts.empty", &[("dir", &crate::config::scripting::SCRIPTS_DIR)]));
+                return;
+            }
+
+            for script in &self.scripts {
+                let _id = ui.push_id(&script.name);
+                ui.text(&script.name);
+                ui.same_line();
+                ui.text_disabled(tr_args("scripts.entities", &[("count", &script.entities)]));
+                if let Some(ref error) = script.error {
+                    ui.text_colored(ToastLevel::Error.color(), error);
+                }
+                for (index, button) in script.buttons.iter().enumerate() {
+                    if index > 0 {
+                        ui.same_line();
+                    }
+                    if ui.button(&button.label) {
+                        debug!("Script button {} pressed in {}", button.label, script.name);
+                        self.pending_calls.push(ScriptCall {
+                            script: script.name.clone(),
+                            function: button.function.clone(),
+                        });
+                    }
+                }
+                ui.separator();
+            }
+        }
+    }
+}
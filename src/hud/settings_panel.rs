@@ -0,0 +1,617 @@
+//! Settings panel
+//!
+//! Application-wide settings. The application section edits a copy of the
+//! runtime settings (present mode, fullscreen monitor, antialiasing, raymarch
+//! quality, frame limit, theme, toolbar position, HDR screenshots, shader
+//! directory and hot reload options);
+//! "Apply" hands it to the app, which rebuilds what the edit affects,
+//! "Revert" drops the edits and "Save" also writes it to `settings.toml`.
+//! The theme section selects a built-in preset or a custom theme file,
+//! edits the colors and style variables of the active theme live, and saves
+//! the result as a custom theme. The font section picks the font file,
+//! sizes, glyph ranges and icon font; applying it rebuilds the font atlas.
+//! Switching the HUD language adds the glyph ranges its locale needs the
+//! same way.
+
+use std::path::PathBuf;
+use ash::vk;
+use imgui::{StyleColor, Ui};
+use crate::color;
+use crate::config;
+use crate::hud::fonts::{FontSettings, GlyphRange};
+use crate::hud::theme::{self, Theme, ThemePreset};
+use crate::hud::ToolbarPosition;
+use crate::image_io::HdrFormat;
+use crate::locale::{self, named, panel_title, tr, tr_args};
+use crate::monitor::MonitorInfo;
+use crate::platform::dialogs::{DialogOptions, DialogSlot};
+use crate::settings::{AppSettings, MSAA_SAMPLE_COUNTS};
+use crate::vulkan::quality::RaymarchQuality;
+use crate::vulkan::swapchain::PresentMode;
+
+/// What a file chosen in the settings dialog is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DialogTarget {
+    /// Theme file to load
+    Theme,
+    /// Shader directory to watch
+    ShaderDir,
+    /// Further directory to watch for shader changes
+    IncludeDir,
+    /// Main font file
+    FontFile,
+    /// Icon font file
+    IconFont,
+}
+
+/// Settings panel state
+pub struct SettingsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Runtime settings the app is running with, restored by "Revert"
+    applied_settings: AppSettings,
+
+    /// Runtime settings as edited in the panel
+    app_settings: AppSettings,
+
+    /// Shader directory as edited in the text field
+    shader_dir: String,
+
+    /// Edited settings applied in the UI, waiting for the app
+    pending_app_settings: Option<AppSettings>,
+
+    /// Write of the applied settings to disk requested in the UI
+    pending_settings_save: bool,
+
+    /// Presentation mode the swapchain uses (display only)
+    pub active_present_mode: Option<vk::PresentModeKHR>,
+
+    /// Connected monitors offered for windowed fullscreen
+    pub monitors: Vec<MonitorInfo>,
+
+    /// Theme being edited, a copy of the active one
+    pub theme: Theme,
+
+    /// Custom theme files found in the theme directory
+    custom_themes: Vec<PathBuf>,
+
+    /// Language codes and names of the locales found in the locale directory
+    languages: Vec<(String, String)>,
+
+    /// Theme changed in the UI, waiting to be applied by the HUD
+    pending_theme: Option<Theme>,
+
+    /// Save of the edited theme requested in the UI
+    pending_save: bool,
+
+    /// Font settings of the current atlas
+    pub fonts: FontSettings,
+
+    /// Font file as edited in the text field
+    font_file: String,
+
+    /// Icon font file as edited in the text field
+    icon_font_file: String,
+
+    /// Font settings applied in the UI, waiting for the HUD to rebuild the atlas
+    pending_fonts: Option<FontSettings>,
+
+    /// Theme and font file dialog
+    dialog: DialogSlot<DialogTarget>,
+}
+
+impl SettingsPanel {
+    /// Create a new settings panel editing `theme`
+    pub fn new(theme: Theme) -> Self {
+        let fonts = FontSettings::default();
+        let app_settings = AppSettings::default();
+        Self {
+            is_visible: false,
+            shader_dir: app_settings.shader_dir.display().to_string(),
+            applied_settings: app_settings.clone(),
+            app_settings,
+            pending_app_settings: None,
+            pending_settings_save: false,
+            active_present_mode: None,
+            monitors: Vec::new(),
+            theme,
+            custom_themes: theme::custom_theme_files(),
+            languages: locale::available(),
+            pending_theme: None,
+            pending_save: false,
+            font_file: Self::path_text(&fonts.font_file),
+            icon_font_file: Self::path_text(&fonts.icon_font_file),
+            fonts,
+            pending_fonts: None,
+            dialog: DialogSlot::default(),
+        }
+    }
+
+    /// Text field contents for an optional path
+    fn path_text(path: &Option<PathBuf>) -> String {
+        path.as_ref().map(|path| path.display().to_string()).unwrap_or_default()
+    }
+
+    /// Optional path from a text field, None when blank
+    fn text_path(text: &str) -> Option<PathBuf> {
+        let text = text.trim();
+        (!text.is_empty()).then(|| PathBuf::from(text))
+    }
+
+    /// Show the settings the app is running with, dropping unapplied edits
+    pub fn set_app_settings(&mut self, settings: AppSettings) {
+        self.shader_dir = settings.shader_dir.display().to_string();
+        self.applied_settings = settings.clone();
+        self.app_settings = settings;
+    }
+
+    /// Follow hot reload being switched with the toolbar or a key binding
+    ///
+    /// Called every frame; an unapplied edit of the checkbox is only
+    /// replaced when the state actually changed.
+    pub fn sync_hot_reload_enabled(&mut self, enabled: bool) {
+        if self.applied_settings.hot_reload.enabled != enabled {
+            self.applied_settings.hot_reload.enabled = enabled;
+            self.app_settings.hot_reload.enabled = enabled;
+        }
+    }
+
+    /// Follow the toolbar being moved with its context menu
+    ///
+    /// Called every frame; an unapplied edit of the position is only
+    /// replaced when the toolbar actually moved.
+    pub fn sync_toolbar_position(&mut self, position: ToolbarPosition) {
+        if self.applied_settings.toolbar_position != position {
+            self.applied_settings.toolbar_position = position;
+            self.app_settings.toolbar_position = position;
+        }
+    }
+
+    /// Take the runtime settings applied since the last call
+    pub fn take_pending_app_settings(&mut self) -> Option<AppSettings> {
+        self.pending_app_settings.take()
+    }
+
+    /// Take the settings to write to disk, if a save was requested since the last call
+    pub fn take_pending_settings_save(&mut self) -> Option<AppSettings> {
+        std::mem::take(&mut self.pending_settings_save).then(|| self.applied_settings.clone())
+    }
+
+    /// Runtime settings with the text fields committed
+    fn edited_settings(&self) -> AppSettings {
+        AppSettings {
+            shader_dir: Self::text_path(&self.shader_dir).unwrap_or_else(|| PathBuf::from(config::hot_reload::SHADER_DIR)),
+            ..self.app_settings.clone()
+        }
+    }
+
+    /// Hand the edited settings to the app
+    fn apply_app_settings(&mut self) {
+        self.app_settings = self.edited_settings();
+        self.applied_settings = self.app_settings.clone();
+        self.pending_app_settings = Some(self.app_settings.clone());
+    }
+
+    /// Take the theme selected or edited since the last call
+    pub fn take_pending_theme(&mut self) -> Option<Theme> {
+        self.pending_theme.take()
+    }
+
+    /// Take the font settings applied since the last call
+    pub fn take_pending_fonts(&mut self) -> Option<FontSettings> {
+        self.pending_fonts.take()
+    }
+
+    /// Take a save request made since the last call
+    pub fn take_pending_save(&mut self) -> bool {
+        std::mem::take(&mut self.pending_save)
+    }
+
+    /// Add the glyph ranges a locale needs to the font settings
+    ///
+    /// The atlas is rebuilt on the next frame when a range was missing;
+    /// unknown range names are logged and skipped.
+    pub fn require_glyph_ranges(&mut self, names: &[String]) {
+        let mut added = false;
+        for name in names {
+            match GlyphRange::from_name(name) {
+                Some(range) if !self.fonts.glyph_ranges.contains(&range) => {
+                    self.fonts.glyph_ranges.push(range);
+                    added = true;
+                }
+                Some(_) => {}
+                None => log::warn!("Ignoring unknown glyph range '{}' of the locale", name),
+            }
+        }
+        if added {
+            self.pending_fonts = Some(self.fonts.clone());
+        }
+    }
+
+    /// Rescan the theme directory, e.g. after a theme was saved
+    pub fn refresh_custom_themes(&mut self) {
+        self.custom_themes = theme::custom_theme_files();
+    }
+
+    /// Select a theme, replacing the edited one
+    fn select(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.pending_theme = Some(self.theme.clone());
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        match self.dialog.poll() {
+            Some((DialogTarget::Theme, path)) => match Theme::load(&path) {
+                Ok(theme) => self.select(theme),
+                Err(e) => log::warn!("Failed to load theme: {}", e),
+            },
+            Some((DialogTarget::ShaderDir, path)) => self.shader_dir = path.display().to_string(),
+            Some((DialogTarget::IncludeDir, path)) => {
+                if !self.app_settings.include_dirs.contains(&path) {
+                    self.app_settings.include_dirs.push(path);
+                }
+            }
+            Some((DialogTarget::FontFile, path)) => self.font_file = path.display().to_string(),
+            Some((DialogTarget::IconFont, path)) => self.icon_font_file = path.display().to_string(),
+            None => {}
+        }
+
+        let window = ui.window(panel_title("Settings"))
+            .position([420.0, 80.0], imgui::Condition::FirstUseEver)
+            .size([360.0, 480.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if ui.collapsing_header(format!("{}###application", tr("settings.application")), imgui::TreeNodeFlags::DEFAULT_OPEN) {
+                self.render_app_section(ui);
+            }
+            if ui.collapsing_header(format!("{}###theme", tr("settings.theme")), imgui::TreeNodeFlags::empty()) {
+                self.render_theme_section(ui);
+            }
+            if ui.collapsing_header(format!("{}###fonts", tr("settings.fonts")), imgui::TreeNodeFlags::empty()) {
+                self.render_font_section(ui);
+            }
+        }
+    }
+
+    /// Runtime settings with apply, revert and save
+    fn render_app_section(&mut self, ui: &Ui) {
+        let settings = &mut self.app_settings;
+
+        let language_name = self.languages
+            .iter()
+            .find(|(code, _)| *code == settings.language)
+            .map_or(settings.language.as_str(), |(_, name)| name.as_str());
+        if let Some(_combo) = ui.begin_combo(format!("{}###language", tr("settings.language")), language_name) {
+            for (code, name) in &self.languages {
+                if ui.selectable_config(name).selected(*code == settings.language).build() {
+                    settings.language = code.clone();
+                }
+            }
+        }
+        // Glyph ranges need a font file, see `fonts::add_fonts`
+        if self.fonts.font_file.is_none() && !self.fonts.glyph_ranges.is_empty() {
+            ui.text_wrapped(tr("settings.language_font_hint"));
+        }
+        let mut position_index = ToolbarPosition::ALL.iter().position(|&position| position == settings.toolbar_position).unwrap_or(0);
+        if ui.combo(tr("toolbar.position"), &mut position_index, &ToolbarPosition::ALL, |position| position.name().into()) {
+            settings.toolbar_position = ToolbarPosition::ALL[position_index];
+        }
+
+        ui.text_disabled(tr("settings.display"));
+        let mut mode_index = PresentMode::ALL.iter().position(|&mode| mode == settings.present_mode).unwrap_or(0);
+        if ui.combo(tr("settings.present_mode"), &mut mode_index, &PresentMode::ALL, |mode| named("present_modes", mode.name()).into()) {
+            settings.present_mode = PresentMode::ALL[mode_index];
+        }
+        if let Some(active) = self.active_present_mode {
+            ui.text_disabled(tr_args("settings.swapchain_uses", &[("mode", &format!("{:?}", active))]));
+        }
+
+        // A monitor that is not connected right now stays selected under its name
+        let monitor_label = match settings.fullscreen_monitor {
+            Some(ref name) => self.monitors
+                .iter()
+                .find(|monitor| monitor.name == *name)
+                .map_or_else(|| name.clone(), MonitorInfo::label),
+            None => tr("settings.current_monitor"),
+        };
+        if let Some(_combo) = ui.begin_combo(tr("settings.fullscreen_monitor"), monitor_label) {
+            if ui.selectable_config(tr("settings.current_monitor")).selected(settings.fullscreen_monitor.is_none()).build() {
+                settings.fullscreen_monitor = None;
+            }
+            for monitor in &self.monitors {
+                let selected = settings.fullscreen_monitor.as_deref() == Some(monitor.name.as_str());
+                if ui.selectable_config(monitor.label()).selected(selected).build() {
+                    settings.fullscreen_monitor = Some(monitor.name.clone());
+                }
+            }
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr("settings.fullscreen_monitor_tooltip"));
+        }
+
+        let mut limit = settings.frame_limit as i32;
+        let display = if limit == 0 { tr("settings.unlimited") } else { tr("settings.fps_format") };
+        if ui.slider_config(tr("settings.frame_limit"), 0, config::settings::MAX_FRAME_LIMIT as i32)
+            .display_format(display)
+            .build(&mut limit)
+        {
+            settings.frame_limit = limit.max(0) as u32;
+        }
+
+        ui.text_disabled(tr("settings.rendering"));
+        let mut msaa_index = MSAA_SAMPLE_COUNTS.iter().position(|&count| count == settings.msaa_samples).unwrap_or(0);
+        if ui.combo(tr("settings.msaa"), &mut msaa_index, &MSAA_SAMPLE_COUNTS, |&count| {
+            if count == 1 { tr("settings.off").into() } else { format!("{}x", count).into() }
+        }) {
+            settings.msaa_samples = MSAA_SAMPLE_COUNTS[msaa_index];
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr("settings.msaa_tooltip"));
+        }
+
+        let mut quality_index = RaymarchQuality::ALL.iter().position(|&q| q == settings.raymarch_quality).unwrap_or(0);
+        if ui.combo(tr("settings.raymarch_quality"), &mut quality_index, &RaymarchQuality::ALL, |q| named("quality", q.name()).into()) {
+            settings.raymarch_quality = RaymarchQuality::ALL[quality_index];
+        }
+
+        ui.checkbox(tr("settings.selection_outline"), &mut settings.outline.enabled);
+        let mut outline_srgb = color::linear_to_srgb_rgba(settings.outline.color);
+        if ui.color_edit4(tr("settings.outline_color"), &mut outline_srgb) {
+            settings.outline.color = color::srgb_to_linear_rgba(outline_srgb);
+        }
+        ui.slider_config(tr("settings.outline_thickness"), 1.0, config::outline::MAX_THICKNESS)
+            .display_format("%.1f px")
+            .build(&mut settings.outline.thickness);
+
+        if let Some(_combo) = ui.begin_combo(format!("{}##startup", tr("settings.theme")), &settings.theme) {
+            let presets = ThemePreset::ALL.iter().map(|preset| preset.name().to_string());
+            let custom = self.custom_themes
+                .iter()
+                .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()));
+            for name in presets.chain(custom) {
+                if ui.selectable_config(&name).selected(name == settings.theme).build() {
+                    settings.theme = name;
+                }
+            }
+        }
+
+        ui.text_disabled(tr("settings.autosave"));
+        ui.checkbox(tr("settings.autosave_scene"), &mut settings.autosave.enabled);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr("settings.autosave_tooltip"));
+        }
+        let mut interval = settings.autosave.interval_secs as i32;
+        if ui.slider_config(tr("settings.autosave_interval"), config::autosave::MIN_INTERVAL_SECS as i32, config::autosave::MAX_INTERVAL_SECS as i32)
+            .display_format("%d s")
+            .build(&mut interval)
+        {
+            settings.autosave.interval_secs = interval.max(0) as u32;
+        }
+
+        ui.text_disabled(tr("settings.camera"));
+        ui.slider_config(tr("settings.camera_transition"), 0.0, config::camera::MAX_CAMERA_TRANSITION_SECS)
+            .display_format("%.1f s")
+            .build(&mut settings.camera_transition_secs);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr("settings.camera_transition_tooltip"));
+        }
+
+        ui.text_disabled(tr("settings.screenshots"));
+        let hdr_label = match settings.screenshot_hdr {
+            Some(format) => named("image_formats", format.name()),
+            None => tr("settings.off"),
+        };
+        if let Some(_combo) = ui.begin_combo(tr("settings.screenshot_hdr"), hdr_label) {
+            if ui.selectable_config(tr("settings.off")).selected(settings.screenshot_hdr.is_none()).build() {
+                settings.screenshot_hdr = None;
+            }
+            for format in HdrFormat::ALL {
+                if ui.selectable_config(named("image_formats", format.name())).selected(settings.screenshot_hdr == Some(format)).build() {
+                    settings.screenshot_hdr = Some(format);
+                }
+            }
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr("settings.screenshot_hdr_tooltip"));
+        }
+
+        ui.text_disabled(tr("settings.hot_reload"));
+        ui.checkbox(tr("settings.hot_reload_enabled"), &mut settings.hot_reload.enabled);
+        ui.checkbox(tr("settings.safe_mode"), &mut settings.hot_reload.safe_mode);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr("settings.safe_mode_tooltip"));
+        }
+        let mut debounce = settings.hot_reload.debounce_ms as i32;
+        if ui.slider_config(tr("settings.debounce"), 0, 2000).display_format("%d ms").build(&mut debounce) {
+            settings.hot_reload.debounce_ms = debounce.max(0) as u64;
+        }
+        ui.input_text(tr("settings.shader_dir"), &mut self.shader_dir).build();
+        ui.same_line();
+        if ui.button(format!("{}##shaders", tr("settings.browse"))) {
+            let options = DialogOptions::pick_folder(&tr("settings.shader_dir_dialog")).directory(&self.shader_dir);
+            self.dialog.open(DialogTarget::ShaderDir, options);
+        }
+        let mut removed = None;
+        for (index, dir) in self.app_settings.include_dirs.iter().enumerate() {
+            let _id = ui.push_id_usize(index);
+            ui.bullet_text(dir.display().to_string());
+            ui.same_line();
+            if ui.small_button(tr("settings.remove")) {
+                removed = Some(index);
+            }
+        }
+        if let Some(index) = removed {
+            self.app_settings.include_dirs.remove(index);
+        }
+        if ui.button(tr("settings.add_watched_dir")) {
+            let options = DialogOptions::pick_folder(&tr("settings.watched_dir_dialog")).directory(&self.shader_dir);
+            self.dialog.open(DialogTarget::IncludeDir, options);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr("settings.add_watched_dir_tooltip"));
+        }
+
+        ui.separator();
+        let edited = self.edited_settings() != self.applied_settings;
+        let disabled = ui.begin_disabled(!edited);
+        if ui.button(tr("settings.apply")) {
+            self.apply_app_settings();
+        }
+        ui.same_line();
+        if ui.button(tr("settings.revert")) {
+            self.set_app_settings(self.applied_settings.clone());
+        }
+        disabled.end();
+        ui.same_line();
+        if ui.button(tr("settings.save")) {
+            if edited {
+                self.apply_app_settings();
+            }
+            self.pending_settings_save = true;
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tr_args("settings.save_tooltip", &[("file", &config::settings::FILE)]));
+        }
+    }
+
+    /// Theme selector and live editor
+    fn render_theme_section(&mut self, ui: &Ui) {
+        if let Some(_combo) = ui.begin_combo(format!("{}###theme_preset", tr("settings.theme")), &self.theme.name) {
+            for preset in ThemePreset::ALL {
+                let selected = self.theme.source.is_none() && self.theme.name == preset.name();
+                if ui.selectable_config(preset.name()).selected(selected).build() {
+                    self.select(preset.theme());
+                }
+            }
+            if !self.custom_themes.is_empty() {
+                ui.separator();
+            }
+            let mut chosen = None;
+            for path in &self.custom_themes {
+                let label = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+                let selected = self.theme.source.as_ref() == Some(path);
+                if ui.selectable_config(&label).selected(selected).build() {
+                    chosen = Some(path.clone());
+                }
+            }
+            if let Some(path) = chosen {
+                match Theme::load(&path) {
+                    Ok(theme) => self.select(theme),
+                    Err(e) => log::warn!("Failed to load theme: {}", e),
+                }
+            }
+        }
+        ui.same_line();
+        if ui.button(tr("settings.rescan")) {
+            self.refresh_custom_themes();
+        }
+        ui.same_line();
+        if ui.button(tr("settings.open")) {
+            let options = DialogOptions::open_file(&tr("settings.open_theme_dialog"))
+                .filter(&tr("settings.themes_filter"), &["toml"])
+                .directory(crate::config::hud_theme::THEME_DIR);
+            self.dialog.open(DialogTarget::Theme, options);
+        }
+
+        ui.input_text(tr("settings.theme_name"), &mut self.theme.name).build();
+        if ui.button(tr("settings.save_theme")) {
+            // The name may have changed without a pending edit
+            self.pending_theme = Some(self.theme.clone());
+            self.pending_save = true;
+        }
+        ui.separator();
+
+        let mut changed = false;
+        if let Some(_node) = ui.tree_node(format!("{}###style", tr("settings.style"))) {
+            let style = &mut self.theme.style;
+            changed |= ui.slider(tr("theme_style.window_rounding"), 0.0, 12.0, &mut style.window_rounding);
+            changed |= ui.slider(tr("theme_style.window_border"), 0.0, 2.0, &mut style.window_border_size);
+            changed |= ui.slider(tr("theme_style.frame_rounding"), 0.0, 12.0, &mut style.frame_rounding);
+            changed |= ui.slider(tr("theme_style.frame_border"), 0.0, 2.0, &mut style.frame_border_size);
+            changed |= ui.slider(tr("theme_style.scrollbar_size"), 6.0, 24.0, &mut style.scrollbar_size);
+            changed |= ui.slider(tr("theme_style.scrollbar_rounding"), 0.0, 12.0, &mut style.scrollbar_rounding);
+            changed |= ui.slider(tr("theme_style.grab_size"), 4.0, 24.0, &mut style.grab_min_size);
+            changed |= ui.slider(tr("theme_style.grab_rounding"), 0.0, 12.0, &mut style.grab_rounding);
+            changed |= ui.slider(tr("theme_style.tab_rounding"), 0.0, 12.0, &mut style.tab_rounding);
+            changed |= ui.slider(tr("theme_style.indent"), 0.0, 40.0, &mut style.indent_spacing);
+            changed |= imgui::Drag::new(tr("theme_style.window_padding")).range(0.0, 24.0).build_array(ui, &mut style.window_padding);
+            changed |= imgui::Drag::new(tr("theme_style.frame_padding")).range(0.0, 24.0).build_array(ui, &mut style.frame_padding);
+            changed |= imgui::Drag::new(tr("theme_style.item_spacing")).range(0.0, 24.0).build_array(ui, &mut style.item_spacing);
+            changed |= imgui::Drag::new(tr("theme_style.item_inner_spacing")).range(0.0, 24.0).build_array(ui, &mut style.item_inner_spacing);
+        }
+        if let Some(_node) = ui.tree_node(format!("{}###colors", tr("settings.colors"))) {
+            // Colors the theme leaves out show ImGui's current value
+            let current = ui.clone_style();
+            for color in StyleColor::VARIANTS {
+                let name = theme::color_name(color);
+                let mut value = self.theme.colors.get(&name).copied().unwrap_or(current.colors[color as usize]);
+                if ui.color_edit4(&name, &mut value) {
+                    self.theme.colors.insert(name, value);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            self.pending_theme = Some(self.theme.clone());
+        }
+    }
+
+    /// Dialog choosing a font file, starting next to the current one
+    fn font_dialog(title: &str, current: &str) -> DialogOptions {
+        let options = DialogOptions::open_file(title).filter(&tr("settings.fonts_filter"), &["ttf", "otf"]);
+        match Self::text_path(current) {
+            Some(path) => options.directory(path),
+            None => options,
+        }
+    }
+
+    /// Font file, sizes, glyph ranges and icon font
+    fn render_font_section(&mut self, ui: &Ui) {
+        ui.input_text(tr("settings.font_file"), &mut self.font_file).hint(tr("settings.built_in")).build();
+        ui.same_line();
+        if ui.button(format!("{}##font", tr("settings.browse"))) {
+            self.dialog.open(DialogTarget::FontFile, Self::font_dialog(&tr("settings.open_font_dialog"), &self.font_file));
+        }
+        ui.slider(tr("settings.font_size"), 8.0, 40.0, &mut self.fonts.size);
+
+        ui.text(tr("settings.glyph_ranges"));
+        for range in GlyphRange::ALL {
+            let mut enabled = self.fonts.glyph_ranges.contains(&range);
+            if ui.checkbox(range.name(), &mut enabled) {
+                if enabled {
+                    self.fonts.glyph_ranges.push(range);
+                } else {
+                    self.fonts.glyph_ranges.retain(|&existing| existing != range);
+                }
+            }
+        }
+
+        ui.input_text(tr("settings.icon_font"), &mut self.icon_font_file).hint(tr("settings.none")).build();
+        ui.same_line();
+        if ui.button(format!("{}##icons", tr("settings.browse"))) {
+            self.dialog.open(DialogTarget::IconFont, Self::font_dialog(&tr("settings.open_icon_font_dialog"), &self.icon_font_file));
+        }
+
+        if ui.button(tr("settings.apply_fonts")) {
+            self.fonts.font_file = Self::text_path(&self.font_file);
+            self.fonts.icon_font_file = Self::text_path(&self.icon_font_file);
+            self.pending_fonts = Some(self.fonts.clone());
+        }
+        ui.same_line();
+        if ui.button(tr("settings.defaults")) {
+            let defaults = FontSettings::default();
+            self.font_file = Self::path_text(&defaults.font_file);
+            self.icon_font_file = Self::path_text(&defaults.icon_font_file);
+            self.fonts = defaults;
+            self.pending_fonts = Some(self.fonts.clone());
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Shader Parameters panel
+//!
+//! Edits the live parameters of the SDF shader (see `shader_params`) with a
+//! control per member of its `Params` block, under collapsing headers for the
+//! groups its annotations name, and lists the shader's other uniform and push
+//! constant blocks, which the app fills in. Edited values
+//! reach the GPU with the next frame and are saved by the ECS world once no
+//! control is held anymore.
+
+use imgui::{TreeNodeFlags, Ui};
+use crate::color;
+use crate::config;
+use crate::hud::background_panel::edit_linear_color;
+use crate::hud::notifications::ToastLevel;
+use crate::locale::{panel_title, tr, tr_args};
+use crate::shader_params::{ParamControl, ShaderParam, ShaderParams};
+use crate::vulkan::scene_buffer::GpuShaderParams;
+use crate::vulkan::shader_reflection::{BlockBinding, ScalarType};
+
+/// Shader Parameters panel state
+pub struct ShaderParamsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Parameters of the active SDF shader, None until it is reflected
+    pub params: Option<ShaderParams>,
+
+    /// Values were edited and are not saved yet
+    unsaved: bool,
+
+    /// Edited values waiting to be saved
+    pending_save: bool,
+
+    /// "Revert" was pressed: read the saved values again
+    pending_revert: bool,
+}
+
+impl Default for ShaderParamsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderParamsPanel {
+    /// Create a new shader parameters panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            params: None,
+            unsaved: false,
+            pending_save: false,
+            pending_revert: false,
+        }
+    }
+
+    /// Show the parameters of a freshly reflected shader
+    pub fn set_params(&mut self, params: ShaderParams) {
+        self.params = Some(params);
+        self.unsaved = false;
+    }
+
+    /// Contents of the `Params` uniform buffer, None before the shader is reflected
+    pub fn gpu_data(&self) -> Option<GpuShaderParams> {
+        self.params.as_ref().map(ShaderParams::gpu_data)
+    }
+
+    /// Whether edited values are waiting to be saved; clears the request
+    pub fn take_pending_save(&mut self) -> bool {
+        std::mem::take(&mut self.pending_save)
+    }
+
+    /// Whether the saved values should be read again; clears the request
+    pub fn take_pending_revert(&mut self) -> bool {
+        std::mem::take(&mut self.pending_revert)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Shader Parameters"))
+            .position([320.0, 340.0], imgui::Condition::FirstUseEver)
+            .size([340.0, 360.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            let Some(ref mut params) = self.params else {
+                ui.text_disabled(tr("shader_params.not_reflected"));
+                return;
+            };
+
+            let shader_name = params.shader.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            ui.text(tr_args("shader_params.shader", &[("name", &shader_name)]));
+            ui.text_disabled(tr_args("shader_params.values_file", &[("path", &params.values_file().display())]));
+            ui.separator();
+
+            if !params.has_block {
+                ui.text_wrapped(tr_args("shader_params.no_block", &[
+                    ("block", &config::shader_params::BLOCK_NAME),
+                    ("binding", &config::shader_params::BINDING),
+                ]));
+            }
+            // Ungrouped parameters first, then the groups in the order they appear
+            let mut groups: Vec<Option<String>> = Vec::new();
+            for param in &params.params {
+                if !groups.contains(&param.group) {
+                    groups.push(param.group.clone());
+                }
+            }
+            groups.sort_by_key(Option::is_some);
+            let mut changed = false;
+            for group in &groups {
+                if let Some(name) = group {
+                    if !ui.collapsing_header(name, TreeNodeFlags::DEFAULT_OPEN) {
+                        continue;
+                    }
+                }
+                for param in params.params.iter_mut().filter(|param| param.group == *group) {
+                    changed |= edit_param(ui, param);
+                }
+            }
+            if !params.unsupported.is_empty() {
+                ui.text_disabled(tr_args("shader_params.unsupported", &[("names", &params.unsupported.join(", "))]));
+            }
+            for warning in &params.warnings {
+                ui.text_colored(ToastLevel::Warning.color(), warning);
+            }
+
+            if params.has_block {
+                if ui.button(tr("shader_params.reset")) {
+                    params.reset();
+                    changed = true;
+                }
+                ui.same_line();
+                if ui.button(tr("shader_params.revert")) {
+                    self.pending_revert = true;
+                }
+            }
+
+            if ui.collapsing_header(tr("shader_params.app_blocks"), TreeNodeFlags::empty()) {
+                for block in &params.app_blocks {
+                    let binding = match block.binding {
+                        BlockBinding::Uniform { set, binding } => tr_args("shader_params.binding", &[("set", &set), ("binding", &binding)]),
+                        BlockBinding::PushConstant => tr("shader_params.push_constants"),
+                    };
+                    ui.text(format!("{} ({})", block.name, binding));
+                    for member in &block.members {
+                        let ty = member.ty.map(|ty| ty.glsl_name()).unwrap_or_else(|| "...".to_string());
+                        ui.text_disabled(format!("    {} {}", ty, member.name));
+                    }
+                }
+            }
+
+            // Save once a drag or text edit is finished, not on every frame of it
+            self.unsaved |= changed;
+            if self.unsaved && !ui.is_any_item_active() {
+                self.unsaved = false;
+                self.pending_save = true;
+            }
+        }
+    }
+}
+
+/// Draw the control of a parameter
+///
+/// # Returns
+/// True if the value was changed
+fn edit_param(ui: &Ui, param: &mut ShaderParam) -> bool {
+    let components = param.components();
+    let changed = match param.control {
+        ParamControl::Slider { min, max, .. } => {
+            let changed = ui.slider_config(&param.name, min, max).build_array(&mut param.value[..components]);
+            param.snap_to_step();
+            changed
+        }
+        ParamControl::IntSlider { min, max } => {
+            let mut values = param.value.map(|value| value.round() as i32);
+            let changed = ui.slider_config(&param.name, min, max).build_array(&mut values[..components]);
+            if changed {
+                param.value = values.map(|value| value as f32);
+            }
+            changed
+        }
+        ParamControl::Drag { speed } if param.ty.scalar == ScalarType::Float => {
+            imgui::Drag::new(&param.name).speed(speed).build_array(ui, &mut param.value[..components])
+        }
+        ParamControl::Drag { speed } => {
+            let mut values = param.value.map(|value| value.round() as i32);
+            let changed = imgui::Drag::new(&param.name).speed(speed).build_array(ui, &mut values[..components]);
+            if changed {
+                param.value = values.map(|value| value as f32);
+            }
+            changed
+        }
+        ParamControl::Color if components == 3 => {
+            let mut linear = [param.value[0], param.value[1], param.value[2]];
+            let changed = edit_linear_color(ui, &param.name, &mut linear);
+            param.value[..3].copy_from_slice(&linear);
+            changed
+        }
+        ParamControl::Color => {
+            let mut srgb = param.value;
+            for component in &mut srgb[..3] {
+                *component = color::linear_to_srgb(*component);
+            }
+            let changed = ui.color_edit4(&param.name, &mut srgb);
+            if changed {
+                for (value, component) in param.value[..3].iter_mut().zip(srgb) {
+                    *value = color::srgb_to_linear(component);
+                }
+                param.value[3] = srgb[3];
+            }
+            changed
+        }
+        ParamControl::Checkbox => {
+            let mut checked = param.value[0] != 0.0;
+            let changed = ui.checkbox(&param.name, &mut checked);
+            param.value[0] = if checked { 1.0 } else { 0.0 };
+            changed
+        }
+    };
+    if ui.is_item_hovered() {
+        let ty = param.ty.glsl_name();
+        match param.description.as_str() {
+            "" => ui.tooltip_text(ty),
+            description => ui.tooltip_text(format!("{}\n{}", description, ty)),
+        }
+    }
+    changed
+}
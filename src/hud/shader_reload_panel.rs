@@ -0,0 +1,121 @@
+//! Shader reload panel
+//!
+//! Lists the shader files watched for hot reload with the outcome, time
+//! and compile duration of their last reload. Checkboxes switch reloading
+//! per shader kind and per file; the changes are queued for the hot reload
+//! manager, which the ECS world updates on its next frame.
+
+use std::path::PathBuf;
+use imgui::Ui;
+use crate::hud::notifications::ToastLevel;
+use crate::vulkan::reload_stats::{ReloadStatus, ShaderFileStats};
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Color of files whose last reload rebuilt the pipeline
+const RELOADED_COLOR: [f32; 4] = [0.5, 0.9, 0.5, 1.0];
+
+/// Filter change waiting to be applied by the ECS world
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadFilterChange {
+    /// Enable or disable a shader kind
+    Kind(&'static str, bool),
+    /// Enable or disable one file
+    File(PathBuf, bool),
+}
+
+/// Shader reload panel state
+pub struct ShaderReloadPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the watched shader files
+    pub files: Vec<ShaderFileStats>,
+
+    /// Latest snapshot of the shader kind filters
+    pub kinds: Vec<(&'static str, bool)>,
+
+    /// Filter changes waiting to be applied
+    pending_changes: Vec<ReloadFilterChange>,
+}
+
+impl Default for ShaderReloadPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderReloadPanel {
+    /// Create a new shader reload panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            files: Vec::new(),
+            kinds: Vec::new(),
+            pending_changes: Vec::new(),
+        }
+    }
+
+    /// Take the filter changes queued since the last call
+    pub fn take_pending_changes(&mut self) -> Vec<ReloadFilterChange> {
+        std::mem::take(&mut self.pending_changes)
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Shader Reload"))
+            .position([640.0, 330.0], imgui::Condition::FirstUseEver)
+            .size([380.0, 300.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            ui.text(tr("shader_reload.reload_kinds"));
+            for (index, (kind, enabled)) in self.kinds.iter_mut().enumerate() {
+                if index % 3 != 0 {
+                    ui.same_line();
+                }
+                if ui.checkbox(*kind, enabled) {
+                    self.pending_changes.push(ReloadFilterChange::Kind(kind, *enabled));
+                }
+            }
+            ui.separator();
+
+            if self.files.is_empty() {
+                ui.text_disabled(tr("shader_reload.no_files"));
+                return;
+            }
+
+            for file in &mut self.files {
+                let label = file.path.display().to_string();
+                let _id = ui.push_id(&label);
+                if ui.checkbox(&label, &mut file.enabled) {
+                    self.pending_changes.push(ReloadFilterChange::File(file.path.clone(), file.enabled));
+                }
+                ui.same_line();
+                ui.text_disabled(file.kind);
+
+                let status_color = match file.status {
+                    ReloadStatus::Reloaded => RELOADED_COLOR,
+                    ReloadStatus::Failed(_) => ToastLevel::Error.color(),
+                    ReloadStatus::Skipped => ToastLevel::Warning.color(),
+                    _ => ui.style_color(imgui::StyleColor::TextDisabled),
+                };
+                ui.indent();
+                ui.text_colored(status_color, named("reload_status", file.status.label()));
+                if let ReloadStatus::Failed(ref error) = file.status {
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(error);
+                    }
+                }
+                if let (Some(time), Some(compile_ms)) = (file.last_reload, file.compile_ms) {
+                    ui.same_line();
+                    ui.text(tr_args("shader_reload.last_reload", &[("time", &time.format("%H:%M:%S")), ("ms", &format!("{:.1}", compile_ms)), ("count", &file.reload_count)]));
+                }
+                ui.unindent();
+            }
+        }
+    }
+}
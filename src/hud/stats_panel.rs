@@ -0,0 +1,143 @@
+//! Stats panel
+//!
+//! Read-only information about the running renderer. Lists every detected
+//! GPU with its type, Vulkan version and selection score so it is clear
+//! which device was picked and how to force another one with `--gpu`, and
+//! the device memory the app allocated per category next to the usage and
+//! budget of each memory heap, the cached meshes and environment maps
+//! against their residency budget, and how many meshes frustum culling
+//! kept, with a toggle to turn it off.
+
+use imgui::Ui;
+use crate::config;
+use crate::ecs::culling::CullingStats;
+use crate::hud::notifications::ToastLevel;
+use crate::vulkan::gpu_selection::GpuInfo;
+use crate::vulkan::memory_usage::{format_bytes, CategoryUsage, HeapBudget, MemoryCategory};
+use crate::vulkan::residency::ResidencyUsage;
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Stats panel state
+pub struct StatsPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Detected physical devices, in enumeration order
+    pub gpus: Vec<GpuInfo>,
+
+    /// Device memory allocated by the app per category, updated while visible
+    pub memory: Vec<(MemoryCategory, CategoryUsage)>,
+
+    /// Memory heaps of the selected GPU, updated while visible
+    pub heaps: Vec<HeapBudget>,
+
+    /// Cached meshes and environment maps against their budget
+    pub residency: ResidencyUsage,
+
+    /// Meshes kept by frustum culling in the latest frame
+    pub culling: CullingStats,
+
+    /// Whether meshes outside every viewport are culled
+    pub culling_enabled: bool,
+}
+
+impl StatsPanel {
+    /// Create a new stats panel
+    ///
+    /// # Arguments
+    /// * `gpus` - Physical devices detected at startup
+    pub fn new(gpus: Vec<GpuInfo>) -> Self {
+        Self {
+            is_visible: true,
+            gpus,
+            memory: Vec::new(),
+            heaps: Vec::new(),
+            residency: ResidencyUsage::default(),
+            culling: CullingStats::default(),
+            culling_enabled: config::culling::ENABLED,
+        }
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Stats"))
+            .position([570.0, 70.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 280.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            ui.text(tr_args("stats.gpus", &[("count", &self.gpus.len())]));
+            ui.separator();
+
+            for gpu in &self.gpus {
+                let marker = if gpu.selected { ">" } else { " " };
+                ui.text(format!("{} [{}] {}", marker, gpu.index, gpu.name));
+                let status = match (&gpu.score, &gpu.unsuitable_reason) {
+                    (Some(score), _) => tr_args("stats.score", &[("score", &score)]),
+                    (None, Some(reason)) => reason.clone(),
+                    (None, None) => tr("stats.unsuitable"),
+                };
+                ui.text_disabled(format!(
+                    "    {} | Vulkan {} | {}",
+                    gpu.type_name(),
+                    gpu.api_version,
+                    status
+                ));
+            }
+
+            ui.separator();
+            ui.text_disabled(tr("stats.gpu_hint"));
+
+            ui.separator();
+            ui.text(tr("stats.memory"));
+            for (category, usage) in &self.memory {
+                ui.text(tr_args("stats.category", &[
+                    ("category", &named("memory", category.name())),
+                    ("device_local", &format_bytes(usage.device_local)),
+                    ("host_visible", &format_bytes(usage.host_visible)),
+                ]));
+            }
+            for heap in &self.heaps {
+                let kind = match heap.device_local {
+                    true => tr("stats.device_local"),
+                    false => tr("stats.system"),
+                };
+                let line = match (heap.usage, heap.budget) {
+                    (Some(usage), Some(budget)) => tr_args("stats.heap_budget", &[
+                        ("index", &heap.index),
+                        ("kind", &kind),
+                        ("usage", &format_bytes(usage)),
+                        ("budget", &format_bytes(budget)),
+                        ("size", &format_bytes(heap.size)),
+                    ]),
+                    _ => tr_args("stats.heap", &[("index", &heap.index), ("kind", &kind), ("size", &format_bytes(heap.size))]),
+                };
+                ui.text_disabled(line);
+            }
+            if self.heaps.iter().any(|heap| heap.budget.is_none()) {
+                ui.text_disabled(tr("stats.no_budget"));
+            }
+
+            let residency = tr_args("stats.residency", &[
+                ("count", &self.residency.assets),
+                ("resident", &format_bytes(self.residency.resident)),
+                ("budget", &format_bytes(self.residency.budget)),
+            ]);
+            match self.residency.is_over_budget() {
+                true => ui.text_colored(ToastLevel::Warning.color(), residency),
+                false => ui.text(residency),
+            }
+
+            ui.separator();
+            ui.checkbox(tr("stats.culling"), &mut self.culling_enabled);
+            ui.text(tr_args("stats.culled_meshes", &[
+                ("visible", &self.culling.meshes_visible),
+                ("total", &self.culling.meshes_total),
+            ]));
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Still export panel
+//!
+//! Configures a high resolution still of the current frame (size, PNG or
+//! linear HDR output) and requests it. The size can be typed in or set to a
+//! multiple of the window size, which the ECS world feeds in every frame.
+
+use imgui::Ui;
+use log::debug;
+use std::path::PathBuf;
+use crate::config;
+use crate::platform::dialogs::{DialogOptions, DialogSlot};
+use crate::vulkan::still_export::{StillExportSettings, StillFormat};
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Multiples of the window size offered as buttons
+const WINDOW_MULTIPLES: [u32; 3] = [1, 2, 4];
+
+/// Still export panel state
+pub struct StillExportPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Settings edited in the panel
+    pub settings: StillExportSettings,
+
+    /// Size of the window in pixels, for the window size buttons
+    pub window_size: [u32; 2],
+
+    /// Output file as edited in the text field
+    output: String,
+
+    /// Still requested in the UI, waiting to be picked up by the ECS world
+    pending_start: Option<StillExportSettings>,
+
+    /// Output file dialog
+    dialog: DialogSlot<()>,
+}
+
+impl Default for StillExportPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StillExportPanel {
+    /// Create a new still export panel with the default settings
+    pub fn new() -> Self {
+        let settings = StillExportSettings::default();
+        Self {
+            is_visible: false,
+            output: settings.path.display().to_string(),
+            settings,
+            window_size: [config::export::DEFAULT_WIDTH, config::export::DEFAULT_HEIGHT],
+            pending_start: None,
+            dialog: DialogSlot::default(),
+        }
+    }
+
+    /// Replace the output directory, e.g. with the one of a newly opened project
+    pub fn set_output_dir(&mut self, dir: PathBuf) {
+        self.settings.path = dir.join(config::still_export::FILE_STEM);
+        self.output = self.settings.path.display().to_string();
+    }
+
+    /// Take the still requested since the last call
+    pub fn take_pending_start(&mut self) -> Option<StillExportSettings> {
+        self.pending_start.take()
+    }
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Still Export"))
+            .position([620.0, 340.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 200.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(((), path)) = self.dialog.poll() {
+            self.output = path.display().to_string();
+        }
+
+        if let Some(_token) = window.begin() {
+            let max_size = config::still_export::MAX_SIZE as i32;
+            let mut width = self.settings.width as i32;
+            if ui.input_int(tr("still_export.width"), &mut width).step(64).build() {
+                self.settings.width = width.clamp(1, max_size) as u32;
+            }
+            let mut height = self.settings.height as i32;
+            if ui.input_int(tr("still_export.height"), &mut height).step(64).build() {
+                self.settings.height = height.clamp(1, max_size) as u32;
+            }
+            ui.text(tr("still_export.window_size"));
+            for multiple in WINDOW_MULTIPLES {
+                ui.same_line();
+                if ui.button(format!("{}x", multiple)) {
+                    let [window_width, window_height] = self.window_size;
+                    self.settings.width = (window_width * multiple).clamp(1, config::still_export::MAX_SIZE);
+                    self.settings.height = (window_height * multiple).clamp(1, config::still_export::MAX_SIZE);
+                }
+            }
+
+            let mut format_index = StillFormat::ALL.iter().position(|&f| f == self.settings.format).unwrap_or(0);
+            if ui.combo(tr("still_export.format"), &mut format_index, &StillFormat::ALL, |format| named("image_formats", format.name()).into()) {
+                self.settings.format = StillFormat::ALL[format_index];
+            }
+            ui.input_text(tr("still_export.output"), &mut self.output).build();
+            ui.same_line();
+            if ui.button(tr("still_export.browse")) {
+                let format = self.settings.format;
+                self.dialog.open((), DialogOptions::save_file(&tr("still_export.file_dialog"))
+                    .filter(&named("image_formats", format.name()), &[format.extension()])
+                    .file_name(&format!("{}.{}", config::still_export::FILE_STEM, format.extension())));
+            }
+
+            ui.separator();
+            let megapixels = self.settings.width as f32 * self.settings.height as f32 / 1_000_000.0;
+            ui.text(tr_args("still_export.summary", &[
+                ("width", &self.settings.width),
+                ("height", &self.settings.height),
+                ("megapixels", &format!("{:.1}", megapixels)),
+            ]));
+            if ui.button(tr("still_export.export")) {
+                self.settings.path = PathBuf::from(self.output.trim());
+                debug!("Still export requested: {:?}", self.settings);
+                self.pending_start = Some(self.settings.clone());
+            }
+        }
+    }
+}
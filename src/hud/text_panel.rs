@@ -0,0 +1,178 @@
+//! Text panel
+//!
+//! Inspector for the 3D texts in the scene: the text itself, its font,
+//! size, extrusion depth, material and placement, plus buttons adding a
+//! text at the origin and removing one. The ECS world refreshes the
+//! snapshot every frame and applies the queued edits, so changes show in
+//! the next frame.
+
+use imgui::{TreeNodeFlags, Ui};
+use legion::Entity;
+use log::debug;
+use crate::config;
+use crate::ecs::text::{default_font, Text3D};
+use crate::hud::background_panel::edit_linear_color;
+use crate::hud::edit_queue::EditQueue;
+use crate::locale::{panel_title, tr, tr_args};
+use crate::platform::dialogs::{DialogOptions, DialogSlot};
+
+/// Snapshot of a text entity
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextView {
+    /// Text entity
+    pub entity: Entity,
+    /// World position of the text's center
+    pub position: [f32; 3],
+    /// XYZ Euler angles in radians
+    pub rotation: [f32; 3],
+    pub text: Text3D,
+}
+
+/// Edit requested from the panel
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextEdit {
+    /// Spawn a text with the default settings
+    Add { position: [f32; 3] },
+    /// Replace the text and placement of a text entity
+    Set { entity: Entity, text: Text3D, position: [f32; 3], rotation: [f32; 3] },
+    /// Despawn a text entity
+    Remove { entity: Entity },
+}
+
+/// Text panel state
+pub struct TextPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Latest snapshot of the scene's texts
+    pub texts: Vec<TextView>,
+
+    /// Edits waiting to be applied to the ECS world
+    pending_edits: EditQueue<Entity, TextEdit>,
+
+    /// Font file dialog, tagged with the text it is for
+    dialog: DialogSlot<Entity>,
+}
+
+impl Default for TextPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextPanel {
+    /// Create a new text panel
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            texts: Vec::new(),
+            pending_edits: EditQueue::default(),
+            dialog: DialogSlot::default(),
+        }
+    }
+
+    /// Take the edits queued since the last call
+    pub fn take_pending_edits(&mut self) -> Vec<TextEdit> {
+        self.pending_edits.take()
+    }
+
+
+    /// Render the panel
+    pub fn render(&mut self, ui: &Ui) {
+        if let Some((entity, path)) = self.dialog.poll() {
+            debug!("Font chosen for {:?}: {}", entity, path.display());
+            if let Some(index) = self.texts.iter().position(|view| view.entity == entity) {
+                self.texts[index].text.font = path.to_string_lossy().into_owned();
+                self.queue_set(index);
+            }
+        }
+
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Text"))
+            .position([960.0, 160.0], imgui::Condition::FirstUseEver)
+            .size([320.0, 440.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            if ui.button(tr("text.add")) {
+                self.pending_edits.push(None, TextEdit::Add { position: [0.0, 0.5, 0.0] });
+            }
+            if self.texts.len() > config::rendering::MAX_SDF_TEXTS {
+                ui.text_colored([1.0, 0.6, 0.2, 1.0], tr_args("text.too_many", &[("count", &config::rendering::MAX_SDF_TEXTS)]));
+            }
+
+            if self.texts.is_empty() {
+                ui.text_disabled(tr("text.empty"));
+                return;
+            }
+
+            for index in 0..self.texts.len() {
+                self.render_text(ui, index);
+            }
+        }
+    }
+
+    /// Queue the current state of a text from the snapshot
+    fn queue_set(&mut self, index: usize) {
+        let view = &self.texts[index];
+        self.pending_edits.push(Some(view.entity), TextEdit::Set {
+            entity: view.entity,
+            text: view.text.clone(),
+            position: view.position,
+            rotation: view.rotation,
+        });
+    }
+
+    /// Render the controls of one text
+    fn render_text(&mut self, ui: &Ui, index: usize) {
+        let _id = ui.push_id_usize(index);
+        let view = &mut self.texts[index];
+        let first_line = view.text.text.lines().next().unwrap_or_default().to_string();
+        let label = tr_args("text.entry", &[("text", &first_line), ("entity", &format!("{:?}", view.entity))]);
+        if !ui.collapsing_header(&label, TreeNodeFlags::DEFAULT_OPEN) {
+            return;
+        }
+
+        let text = &mut view.text;
+        let mut changed = ui.input_text_multiline(tr("text.text"), &mut text.text, [0.0, 60.0]).build();
+        let font = if text.font.is_empty() { tr("text.default_font").to_string() } else { text.font.clone() };
+        ui.text_wrapped(tr_args("text.font", &[("font", &font)]));
+        if ui.button(tr("text.browse_font")) {
+            let options = DialogOptions::open_file(&tr("text.open_font_dialog"))
+                .filter(&tr("text.font_filter"), &["ttf", "otf"])
+                .directory(if text.font.is_empty() { default_font() } else { text.font.as_str() });
+            self.dialog.open(view.entity, options);
+        }
+        if !text.font.is_empty() {
+            ui.same_line();
+            if ui.button(tr("text.default_font_button")) {
+                text.font.clear();
+                changed = true;
+            }
+        }
+        changed |= ui.slider_config(tr("text.size"), 0.01, 5.0)
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .build(&mut text.size);
+        changed |= ui.slider(tr("text.depth"), 0.0, 1.0, &mut text.depth);
+        changed |= edit_linear_color(ui, &tr("text.color"), &mut text.color);
+        changed |= ui.slider(tr("text.metallic"), 0.0, 1.0, &mut text.metallic);
+        changed |= ui.slider(tr("text.roughness"), 0.0, 1.0, &mut text.roughness);
+        changed |= imgui::Drag::new(tr("text.position")).speed(0.01).build_array(ui, &mut view.position);
+        let mut degrees = view.rotation.map(f32::to_degrees);
+        if imgui::Drag::new(tr("text.rotation")).speed(0.5).display_format("%.0f deg").build_array(ui, &mut degrees) {
+            view.rotation = degrees.map(f32::to_radians);
+            changed = true;
+        }
+        let entity = view.entity;
+        if changed {
+            self.queue_set(index);
+        }
+
+        if ui.button(tr("text.remove")) {
+            self.pending_edits.push(None, TextEdit::Remove { entity });
+        }
+    }
+}
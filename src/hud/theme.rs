@@ -0,0 +1,505 @@
+//! HUD themes
+//!
+//! A theme is the ImGui color table plus the style variables that shape the
+//! panels (padding, rounding, spacing). Dark, light and high-contrast
+//! presets are built in; custom themes are TOML files in the theme
+//! directory, e.g.
+//!
+//! ```toml
+//! name = "Midnight"
+//!
+//! [style]
+//! window_rounding = 6.0
+//!
+//! [colors]
+//! WindowBg = [0.05, 0.05, 0.10, 0.95]
+//! Button = [0.20, 0.25, 0.45, 1.00]
+//! ```
+//!
+//! Colors are keyed by their `imgui::StyleColor` name; colors and style
+//! variables left out of a file keep the dark preset's values. A file saved
+//! under a preset's name (e.g. `themes/dark.toml`) takes the place of that
+//! preset when the theme is loaded or reloaded.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use imgui::{Style, StyleColor};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+
+/// Style variables of a theme
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeStyle {
+    pub window_padding: [f32; 2],
+    pub window_rounding: f32,
+    pub window_border_size: f32,
+    pub frame_padding: [f32; 2],
+    pub frame_rounding: f32,
+    pub frame_border_size: f32,
+    pub item_spacing: [f32; 2],
+    pub item_inner_spacing: [f32; 2],
+    pub indent_spacing: f32,
+    pub scrollbar_size: f32,
+    pub scrollbar_rounding: f32,
+    pub grab_min_size: f32,
+    pub grab_rounding: f32,
+    pub tab_rounding: f32,
+}
+
+impl Default for ThemeStyle {
+    fn default() -> Self {
+        Self {
+            window_padding: [12.0, 8.0],
+            window_rounding: 0.0,
+            window_border_size: 0.0,
+            frame_padding: [8.0, 6.0],
+            frame_rounding: 4.0,
+            frame_border_size: 0.0,
+            item_spacing: [12.0, 8.0],
+            item_inner_spacing: [8.0, 6.0],
+            indent_spacing: 21.0,
+            scrollbar_size: 14.0,
+            scrollbar_rounding: 2.0,
+            grab_min_size: 10.0,
+            grab_rounding: 2.0,
+            tab_rounding: 4.0,
+        }
+    }
+}
+
+/// Colors and style variables of the HUD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Name shown in the theme selector
+    pub name: String,
+    /// Style variables
+    pub style: ThemeStyle,
+    /// Colors keyed by `StyleColor` name
+    pub colors: BTreeMap<String, [f32; 4]>,
+    /// File the theme was loaded from, None for presets
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// Built-in theme presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemePreset {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ThemePreset {
+    /// All presets in display order
+    pub const ALL: [ThemePreset; 3] = [ThemePreset::Dark, ThemePreset::Light, ThemePreset::HighContrast];
+
+    /// Name shown in the theme selector
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemePreset::Dark => "Dark",
+            ThemePreset::Light => "Light",
+            ThemePreset::HighContrast => "High Contrast",
+        }
+    }
+
+    /// Build the preset theme
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemePreset::Dark => Theme::dark(),
+            ThemePreset::Light => Theme::light(),
+            ThemePreset::HighContrast => Theme::high_contrast(),
+        }
+    }
+
+    /// Find a preset by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Name of a style color as used in theme files
+pub fn color_name(color: StyleColor) -> String {
+    format!("{:?}", color)
+}
+
+impl Theme {
+    /// Build a theme from a palette
+    fn from_palette(name: &str, style: ThemeStyle, palette: &[(StyleColor, [f32; 4])]) -> Self {
+        Self {
+            name: name.to_string(),
+            style,
+            colors: palette.iter().map(|&(color, value)| (color_name(color), value)).collect(),
+            source: None,
+        }
+    }
+
+    /// Dark theme similar to Blender (the default)
+    pub fn dark() -> Self {
+        use StyleColor::*;
+        Self::from_palette("Dark", ThemeStyle::default(), &[
+            (Text, [0.92, 0.92, 0.92, 1.00]),
+            (TextDisabled, [0.50, 0.50, 0.50, 1.00]),
+            (WindowBg, [0.13, 0.13, 0.15, 0.90]),
+            (ChildBg, [0.13, 0.13, 0.15, 0.90]),
+            (PopupBg, [0.13, 0.13, 0.15, 0.95]),
+            (Border, [0.25, 0.25, 0.30, 0.50]),
+            (BorderShadow, [0.00, 0.00, 0.00, 0.00]),
+            (FrameBg, [0.16, 0.16, 0.18, 1.00]),
+            (FrameBgHovered, [0.20, 0.20, 0.23, 1.00]),
+            (FrameBgActive, [0.24, 0.24, 0.28, 1.00]),
+            (TitleBg, [0.16, 0.16, 0.18, 1.00]),
+            (TitleBgActive, [0.20, 0.20, 0.23, 1.00]),
+            (TitleBgCollapsed, [0.16, 0.16, 0.18, 1.00]),
+            (MenuBarBg, [0.16, 0.16, 0.18, 1.00]),
+            (ScrollbarBg, [0.10, 0.10, 0.12, 1.00]),
+            (ScrollbarGrab, [0.20, 0.20, 0.23, 1.00]),
+            (ScrollbarGrabHovered, [0.24, 0.24, 0.28, 1.00]),
+            (ScrollbarGrabActive, [0.28, 0.28, 0.32, 1.00]),
+            (CheckMark, [0.26, 0.59, 0.98, 1.00]),
+            (SliderGrab, [0.26, 0.59, 0.98, 1.00]),
+            (SliderGrabActive, [0.46, 0.79, 0.98, 1.00]),
+            (Button, [0.35, 0.35, 0.40, 1.00]),
+            (ButtonHovered, [0.45, 0.45, 0.50, 1.00]),
+            (ButtonActive, [0.55, 0.55, 0.60, 1.00]),
+            (Header, [0.20, 0.20, 0.23, 1.00]),
+            (HeaderHovered, [0.26, 0.26, 0.30, 1.00]),
+            (HeaderActive, [0.32, 0.32, 0.36, 1.00]),
+            (Separator, [0.20, 0.20, 0.23, 1.00]),
+            (SeparatorHovered, [0.26, 0.26, 0.30, 1.00]),
+            (SeparatorActive, [0.32, 0.32, 0.36, 1.00]),
+            (ResizeGrip, [0.26, 0.59, 0.98, 0.20]),
+            (ResizeGripHovered, [0.26, 0.59, 0.98, 0.67]),
+            (ResizeGripActive, [0.46, 0.79, 0.98, 0.95]),
+            (Tab, [0.20, 0.20, 0.23, 1.00]),
+            (TabHovered, [0.26, 0.26, 0.30, 1.00]),
+            (TabActive, [0.32, 0.32, 0.36, 1.00]),
+            (TabUnfocused, [0.20, 0.20, 0.23, 1.00]),
+            (TabUnfocusedActive, [0.26, 0.26, 0.30, 1.00]),
+            (PlotLines, [0.61, 0.61, 0.61, 1.00]),
+            (PlotLinesHovered, [1.00, 1.00, 1.00, 1.00]),
+            (PlotHistogram, [0.90, 0.70, 0.00, 1.00]),
+            (PlotHistogramHovered, [1.00, 0.60, 0.00, 1.00]),
+            (TextSelectedBg, [0.26, 0.59, 0.98, 0.35]),
+            (DragDropTarget, [0.26, 0.59, 0.98, 0.95]),
+            (NavHighlight, [0.26, 0.59, 0.98, 0.80]),
+            (NavWindowingHighlight, [1.00, 1.00, 1.00, 0.70]),
+            (NavWindowingDimBg, [0.20, 0.20, 0.20, 0.20]),
+            (ModalWindowDimBg, [0.20, 0.20, 0.20, 0.35]),
+        ])
+    }
+
+    /// Light theme for bright environments
+    pub fn light() -> Self {
+        use StyleColor::*;
+        Self::from_palette("Light", ThemeStyle::default(), &[
+            (Text, [0.08, 0.08, 0.10, 1.00]),
+            (TextDisabled, [0.55, 0.55, 0.58, 1.00]),
+            (WindowBg, [0.95, 0.95, 0.96, 0.94]),
+            (ChildBg, [0.95, 0.95, 0.96, 0.94]),
+            (PopupBg, [0.98, 0.98, 0.98, 0.98]),
+            (Border, [0.70, 0.70, 0.74, 0.60]),
+            (BorderShadow, [0.00, 0.00, 0.00, 0.00]),
+            (FrameBg, [0.86, 0.86, 0.88, 1.00]),
+            (FrameBgHovered, [0.80, 0.84, 0.92, 1.00]),
+            (FrameBgActive, [0.72, 0.79, 0.92, 1.00]),
+            (TitleBg, [0.86, 0.86, 0.88, 1.00]),
+            (TitleBgActive, [0.78, 0.80, 0.86, 1.00]),
+            (TitleBgCollapsed, [0.90, 0.90, 0.92, 1.00]),
+            (MenuBarBg, [0.88, 0.88, 0.90, 1.00]),
+            (ScrollbarBg, [0.92, 0.92, 0.93, 1.00]),
+            (ScrollbarGrab, [0.70, 0.70, 0.74, 1.00]),
+            (ScrollbarGrabHovered, [0.60, 0.60, 0.66, 1.00]),
+            (ScrollbarGrabActive, [0.50, 0.50, 0.58, 1.00]),
+            (CheckMark, [0.15, 0.42, 0.85, 1.00]),
+            (SliderGrab, [0.26, 0.52, 0.90, 1.00]),
+            (SliderGrabActive, [0.15, 0.42, 0.85, 1.00]),
+            (Button, [0.80, 0.82, 0.86, 1.00]),
+            (ButtonHovered, [0.70, 0.76, 0.88, 1.00]),
+            (ButtonActive, [0.58, 0.68, 0.88, 1.00]),
+            (Header, [0.80, 0.84, 0.92, 1.00]),
+            (HeaderHovered, [0.72, 0.79, 0.92, 1.00]),
+            (HeaderActive, [0.62, 0.72, 0.92, 1.00]),
+            (Separator, [0.70, 0.70, 0.74, 1.00]),
+            (SeparatorHovered, [0.26, 0.52, 0.90, 0.78]),
+            (SeparatorActive, [0.15, 0.42, 0.85, 1.00]),
+            (ResizeGrip, [0.60, 0.60, 0.66, 0.30]),
+            (ResizeGripHovered, [0.26, 0.52, 0.90, 0.67]),
+            (ResizeGripActive, [0.15, 0.42, 0.85, 0.95]),
+            (Tab, [0.84, 0.84, 0.87, 1.00]),
+            (TabHovered, [0.72, 0.79, 0.92, 1.00]),
+            (TabActive, [0.76, 0.80, 0.90, 1.00]),
+            (TabUnfocused, [0.88, 0.88, 0.90, 1.00]),
+            (TabUnfocusedActive, [0.82, 0.84, 0.90, 1.00]),
+            (PlotLines, [0.39, 0.39, 0.39, 1.00]),
+            (PlotLinesHovered, [1.00, 0.43, 0.35, 1.00]),
+            (PlotHistogram, [0.90, 0.70, 0.00, 1.00]),
+            (PlotHistogramHovered, [1.00, 0.45, 0.00, 1.00]),
+            (TextSelectedBg, [0.26, 0.52, 0.90, 0.35]),
+            (DragDropTarget, [0.26, 0.52, 0.90, 0.95]),
+            (NavHighlight, [0.26, 0.52, 0.90, 0.80]),
+            (NavWindowingHighlight, [0.70, 0.70, 0.70, 0.70]),
+            (NavWindowingDimBg, [0.20, 0.20, 0.20, 0.20]),
+            (ModalWindowDimBg, [0.20, 0.20, 0.20, 0.35]),
+        ])
+    }
+
+    /// High-contrast theme: opaque black panels, white text, yellow accents and visible borders
+    pub fn high_contrast() -> Self {
+        use StyleColor::*;
+        let style = ThemeStyle {
+            window_border_size: 1.0,
+            frame_border_size: 1.0,
+            ..ThemeStyle::default()
+        };
+        Self::from_palette("High Contrast", style, &[
+            (Text, [1.00, 1.00, 1.00, 1.00]),
+            (TextDisabled, [0.70, 0.70, 0.70, 1.00]),
+            (WindowBg, [0.00, 0.00, 0.00, 1.00]),
+            (ChildBg, [0.00, 0.00, 0.00, 1.00]),
+            (PopupBg, [0.00, 0.00, 0.00, 1.00]),
+            (Border, [1.00, 1.00, 1.00, 1.00]),
+            (BorderShadow, [0.00, 0.00, 0.00, 0.00]),
+            (FrameBg, [0.00, 0.00, 0.00, 1.00]),
+            (FrameBgHovered, [0.25, 0.25, 0.00, 1.00]),
+            (FrameBgActive, [0.40, 0.40, 0.00, 1.00]),
+            (TitleBg, [0.00, 0.00, 0.00, 1.00]),
+            (TitleBgActive, [0.20, 0.20, 0.20, 1.00]),
+            (TitleBgCollapsed, [0.00, 0.00, 0.00, 1.00]),
+            (MenuBarBg, [0.00, 0.00, 0.00, 1.00]),
+            (ScrollbarBg, [0.00, 0.00, 0.00, 1.00]),
+            (ScrollbarGrab, [0.80, 0.80, 0.80, 1.00]),
+            (ScrollbarGrabHovered, [1.00, 1.00, 0.00, 1.00]),
+            (ScrollbarGrabActive, [1.00, 1.00, 0.00, 1.00]),
+            (CheckMark, [1.00, 1.00, 0.00, 1.00]),
+            (SliderGrab, [1.00, 1.00, 0.00, 1.00]),
+            (SliderGrabActive, [1.00, 1.00, 1.00, 1.00]),
+            (Button, [0.00, 0.00, 0.00, 1.00]),
+            (ButtonHovered, [0.30, 0.30, 0.00, 1.00]),
+            (ButtonActive, [0.50, 0.50, 0.00, 1.00]),
+            (Header, [0.20, 0.20, 0.20, 1.00]),
+            (HeaderHovered, [0.30, 0.30, 0.00, 1.00]),
+            (HeaderActive, [0.50, 0.50, 0.00, 1.00]),
+            (Separator, [1.00, 1.00, 1.00, 1.00]),
+            (SeparatorHovered, [1.00, 1.00, 0.00, 1.00]),
+            (SeparatorActive, [1.00, 1.00, 0.00, 1.00]),
+            (ResizeGrip, [1.00, 1.00, 1.00, 0.50]),
+            (ResizeGripHovered, [1.00, 1.00, 0.00, 1.00]),
+            (ResizeGripActive, [1.00, 1.00, 0.00, 1.00]),
+            (Tab, [0.00, 0.00, 0.00, 1.00]),
+            (TabHovered, [0.30, 0.30, 0.00, 1.00]),
+            (TabActive, [0.40, 0.40, 0.00, 1.00]),
+            (TabUnfocused, [0.00, 0.00, 0.00, 1.00]),
+            (TabUnfocusedActive, [0.20, 0.20, 0.20, 1.00]),
+            (PlotLines, [1.00, 1.00, 1.00, 1.00]),
+            (PlotLinesHovered, [1.00, 1.00, 0.00, 1.00]),
+            (PlotHistogram, [1.00, 1.00, 0.00, 1.00]),
+            (PlotHistogramHovered, [1.00, 1.00, 1.00, 1.00]),
+            (TextSelectedBg, [1.00, 1.00, 0.00, 0.45]),
+            (DragDropTarget, [1.00, 1.00, 0.00, 1.00]),
+            (NavHighlight, [1.00, 1.00, 0.00, 1.00]),
+            (NavWindowingHighlight, [1.00, 1.00, 1.00, 1.00]),
+            (NavWindowingDimBg, [0.00, 0.00, 0.00, 0.60]),
+            (ModalWindowDimBg, [0.00, 0.00, 0.00, 0.60]),
+        ])
+    }
+
+    /// Apply the theme to an ImGui style
+    ///
+    /// Colors the theme does not list are left unchanged.
+    pub fn apply(&self, style: &mut Style) {
+        for color in StyleColor::VARIANTS {
+            if let Some(&value) = self.colors.get(&color_name(color)) {
+                style.colors[color as usize] = value;
+            }
+        }
+
+        let vars = &self.style;
+        style.window_padding = vars.window_padding;
+        style.window_rounding = vars.window_rounding;
+        style.window_border_size = vars.window_border_size;
+        style.frame_padding = vars.frame_padding;
+        style.frame_rounding = vars.frame_rounding;
+        style.frame_border_size = vars.frame_border_size;
+        style.item_spacing = vars.item_spacing;
+        style.item_inner_spacing = vars.item_inner_spacing;
+        style.indent_spacing = vars.indent_spacing;
+        style.scrollbar_size = vars.scrollbar_size;
+        style.scrollbar_rounding = vars.scrollbar_rounding;
+        style.grab_min_size = vars.grab_min_size;
+        style.grab_rounding = vars.grab_rounding;
+        style.tab_rounding = vars.tab_rounding;
+
+        // Not themeable
+        style.button_text_align = [0.5, 0.5];
+        style.display_window_padding = [8.0, 8.0];
+        style.display_safe_area_padding = [4.0, 4.0];
+        style.anti_aliased_lines = true;
+        style.anti_aliased_fill = true;
+        style.curve_tessellation_tol = 1.25;
+        style.window_min_size = [200.0, 50.0];
+    }
+
+    /// Parse a TOML theme
+    ///
+    /// Missing colors and style variables are taken from the dark preset;
+    /// unknown color names are logged and ignored.
+    ///
+    /// # Errors
+    /// Returns an error if the text is not a valid theme
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let parsed: Theme = toml::from_str(text)
+            .map_err(|e| AppError::HUD(format!("Invalid theme: {}", e)))?;
+
+        let known: Vec<String> = StyleColor::VARIANTS.into_iter().map(color_name).collect();
+        let mut theme = Theme { colors: Theme::dark().colors, ..parsed.clone() };
+        for (name, value) in parsed.colors {
+            if known.contains(&name) {
+                theme.colors.insert(name, value);
+            } else {
+                warn!("Ignoring unknown theme color '{}'", name);
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Serialize the theme as TOML
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| AppError::HUD(format!("Failed to serialize theme: {}", e)))
+    }
+
+    /// Load a theme file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context(Subsystem::Hud, format!("read theme {}", path.display()))?;
+        let mut theme = Self::from_toml(&text).context(Subsystem::Hud, format!("parse theme {}", path.display()))?;
+        theme.source = Some(path.to_path_buf());
+        Ok(theme)
+    }
+
+    /// Write the theme to a file in the theme directory, named after the theme
+    ///
+    /// # Returns
+    /// The path written
+    ///
+    /// # Errors
+    /// Returns an error if the directory or file cannot be written
+    pub fn save(&self) -> Result<PathBuf> {
+        let dir = Path::new(config::hud_theme::THEME_DIR);
+        fs::create_dir_all(dir).context(Subsystem::Hud, format!("create {}", dir.display()))?;
+        let path = saved_theme_path(&self.name);
+        fs::write(&path, self.to_toml()?).context(Subsystem::Hud, format!("write theme {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Read the theme again from disk
+    ///
+    /// Loads the file the theme came from, or else the file saved under its
+    /// name; a preset without such a file is rebuilt.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn reloaded(&self) -> Result<Self> {
+        match self.source.clone().or_else(|| theme_file(&self.name)) {
+            Some(path) => Self::load(&path),
+            None => Ok(ThemePreset::from_name(&self.name).map(ThemePreset::theme).unwrap_or_else(|| self.clone())),
+        }
+    }
+}
+
+/// Path `Theme::save` writes a theme with this name to
+fn saved_theme_path(name: &str) -> PathBuf {
+    let file_name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    Path::new(config::hud_theme::THEME_DIR).join(format!("{}.toml", file_name))
+}
+
+/// Existing theme file for a name: a file with that stem or the one `Theme::save` writes
+fn theme_file(name: &str) -> Option<PathBuf> {
+    [Path::new(config::hud_theme::THEME_DIR).join(format!("{}.toml", name)), saved_theme_path(name)]
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+/// Custom theme files in the theme directory, sorted by path
+pub fn custom_theme_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(config::hud_theme::THEME_DIR)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Theme by name: the file stem of a theme in the theme directory or a preset
+///
+/// Falls back to the preset, or the dark preset, if no such theme can be loaded.
+pub fn named_theme(name: &str) -> Theme {
+    let preset = ThemePreset::from_name(name).map(ThemePreset::theme);
+    let Some(path) = theme_file(name) else {
+        return preset.unwrap_or_else(|| {
+            warn!("Falling back to the dark theme: no theme named '{}'", name);
+            Theme::dark()
+        });
+    };
+    Theme::load(&path).unwrap_or_else(|e| {
+        warn!("Falling back to the {} theme: {}", if preset.is_some() { "preset" } else { "dark" }, e);
+        preset.unwrap_or_else(Theme::dark)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_toml_falls_back_to_dark() {
+        let theme = Theme::from_toml(
+            "name = \"Midnight\"\n[style]\nwindow_rounding = 6.0\n[colors]\nWindowBg = [0.0, 0.0, 0.1, 1.0]\nNotAColor = [1.0, 1.0, 1.0, 1.0]\n",
+        ).unwrap();
+
+        assert_eq!(theme.name, "Midnight");
+        assert_eq!(theme.style.window_rounding, 6.0);
+        assert_eq!(theme.style.frame_rounding, ThemeStyle::default().frame_rounding);
+        assert_eq!(theme.colors["WindowBg"], [0.0, 0.0, 0.1, 1.0]);
+        assert_eq!(theme.colors["Text"], Theme::dark().colors["Text"]);
+        assert!(!theme.colors.contains_key("NotAColor"));
+    }
+
+    #[test]
+    fn test_presets_round_trip_through_toml() {
+        for preset in ThemePreset::ALL {
+            let theme = preset.theme();
+            assert_eq!(Theme::from_toml(&theme.to_toml().unwrap()).unwrap(), theme);
+            assert_eq!(ThemePreset::from_name(preset.name()), Some(preset));
+        }
+    }
+
+    #[test]
+    fn test_saved_theme_path_is_named_after_the_theme() {
+        let dir = Path::new(config::hud_theme::THEME_DIR);
+        assert_eq!(saved_theme_path("High Contrast"), dir.join("high_contrast.toml"));
+        assert_eq!(saved_theme_path("my-theme_2"), dir.join("my-theme_2.toml"));
+    }
+}
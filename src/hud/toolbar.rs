@@ -2,10 +2,26 @@
 //!
 //! This module provides a simplified toolbar system with buttons, icons,
 //! tooltips, and interactive elements for a professional UI experience.
+//! Its groups and buttons come from the layout file, see `toolbar_layout`.
 
-use imgui::{Ui, Key};
+use imgui::{Ui, MouseButton};
+use serde::{Deserialize, Serialize};
 use std::time::Instant;
 use log::{info, debug};
+use crate::actions::{Action, ActionRegistry};
+use crate::config;
+use crate::error::Result;
+use crate::hud::toolbar_layout::{GroupAlign, ToolbarLayout};
+use crate::locale::{tr, tr_args};
+
+/// Height of toolbar buttons in logical pixels
+const BUTTON_HEIGHT: f32 = 28.0;
+
+/// Gap between the toolbar edges and its buttons in logical pixels
+const EDGE_MARGIN: f32 = 8.0;
+
+/// Space between toolbar items in logical pixels
+const ITEM_SPACING: f32 = 8.0;
 
 /// Button interaction states
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -16,17 +32,31 @@ pub enum ButtonState {
     Disabled,
 }
 
+/// How a toolbar item is drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ButtonKind {
+    /// A push button running its action
+    #[default]
+    Button,
+    /// A checkbox showing whether its action's setting is on
+    Checkbox,
+}
+
 /// Toolbar button with enhanced interactivity
 pub struct ToolbarButton {
     /// Button identifier
     #[allow(dead_code)]
     pub id: String,
     
-    /// Button icon (text representation)
-    pub icon: &'static str,
+    /// Locale key or text of the button label
+    pub icon: String,
     
-    /// Button tooltip
-    pub tooltip: &'static str,
+    /// Icon font glyph shown before the label when an icon font is loaded
+    pub glyph: Option<char>,
+    
+    /// Locale key or text of the button tooltip
+    pub tooltip: String,
     
     /// Whether button is active/pressed
     pub is_active: bool,
@@ -37,6 +67,12 @@ pub struct ToolbarButton {
     /// Button action callback
     pub action: Option<Box<dyn Fn() + 'static>>,
     
+    /// Action run when the button is clicked, bound in the layout file
+    pub bound_action: Option<Action>,
+    
+    /// Key binding of the bound action, shown in the tooltip
+    pub shortcut: Option<String>,
+    
     /// Last interaction time (for animation)
     pub last_interaction: Option<Instant>,
     
@@ -51,10 +87,14 @@ pub struct ToolbarButton {
     
     /// Button color theme
     pub color_theme: ButtonColorTheme,
+    
+    /// Whether the item is a button or a checkbox
+    pub kind: ButtonKind,
 }
 
 /// Color theme for buttons
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ButtonColorTheme {
     /// Normal state color
     pub normal: [f32; 4],
@@ -83,7 +123,10 @@ impl Default for ButtonColorTheme {
 /// Toolbar group containing related buttons
 pub struct ToolbarGroup {
     /// Group name
-    pub name: &'static str,
+    pub name: String,
+    
+    /// Side of the toolbar the group sits on
+    pub align: GroupAlign,
     
     /// Group buttons
     pub buttons: Vec<ToolbarButton>,
@@ -97,22 +140,103 @@ pub struct ToolbarGroup {
     pub is_collapsed: bool,
 }
 
-/// Toolbar position and layout
-#[derive(Debug, Clone, Copy)]
+/// Edge of the screen the toolbar is anchored to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ToolbarPosition {
+    #[default]
     Top,
-    #[allow(dead_code)]
     Bottom,
-    #[allow(dead_code)]
     Left,
-    #[allow(dead_code)]
     Right,
 }
 
+impl ToolbarPosition {
+    /// All positions, in the order of the toolbar's context menu
+    pub const ALL: [ToolbarPosition; 4] = [
+        ToolbarPosition::Top,
+        ToolbarPosition::Bottom,
+        ToolbarPosition::Left,
+        ToolbarPosition::Right,
+    ];
+
+    /// Display name in the current language
+    pub fn name(self) -> String {
+        tr(match self {
+            ToolbarPosition::Top => "toolbar.top",
+            ToolbarPosition::Bottom => "toolbar.bottom",
+            ToolbarPosition::Left => "toolbar.left",
+            ToolbarPosition::Right => "toolbar.right",
+        })
+    }
+
+    /// Whether the toolbar runs along a side edge and stacks its buttons
+    pub fn is_vertical(self) -> bool {
+        matches!(self, ToolbarPosition::Left | ToolbarPosition::Right)
+    }
+
+    /// Screen rectangle of a toolbar anchored at this edge
+    ///
+    /// # Arguments
+    /// * `display_size` - Screen size in pixels
+    /// * `thickness` - Height of a horizontal or width of a vertical toolbar in pixels
+    ///
+    /// # Returns
+    /// The toolbar rectangle, never larger than the screen
+    pub fn rect(self, display_size: [f32; 2], thickness: f32) -> ScreenRect {
+        let [width, height] = display_size;
+        let across = if self.is_vertical() { width } else { height };
+        let thickness = thickness.clamp(0.0, across.max(0.0));
+        match self {
+            ToolbarPosition::Top => ScreenRect { min: [0.0, 0.0], max: [width, thickness] },
+            ToolbarPosition::Bottom => ScreenRect { min: [0.0, height - thickness], max: [width, height] },
+            ToolbarPosition::Left => ScreenRect { min: [0.0, 0.0], max: [thickness, height] },
+            ToolbarPosition::Right => ScreenRect { min: [width - thickness, 0.0], max: [width, height] },
+        }
+    }
+
+    /// Part of the screen not covered by a toolbar anchored at this edge
+    ///
+    /// # Arguments
+    /// * `display_size` - Screen size in pixels
+    /// * `thickness` - Height of a horizontal or width of a vertical toolbar in pixels
+    pub fn safe_area(self, display_size: [f32; 2], thickness: f32) -> ScreenRect {
+        let toolbar = self.rect(display_size, thickness);
+        let mut area = ScreenRect::from_size(display_size);
+        match self {
+            ToolbarPosition::Top => area.min[1] = toolbar.max[1],
+            ToolbarPosition::Bottom => area.max[1] = toolbar.min[1],
+            ToolbarPosition::Left => area.min[0] = toolbar.max[0],
+            ToolbarPosition::Right => area.max[0] = toolbar.min[0],
+        }
+        area
+    }
+}
+
+/// Axis aligned rectangle in screen pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenRect {
+    /// Top left corner
+    pub min: [f32; 2],
+    /// Bottom right corner
+    pub max: [f32; 2],
+}
+
+impl ScreenRect {
+    /// Rectangle covering a screen of the given size
+    pub fn from_size(size: [f32; 2]) -> Self {
+        Self { min: [0.0, 0.0], max: size }
+    }
+
+    /// Width and height
+    pub fn size(&self) -> [f32; 2] {
+        [self.max[0] - self.min[0], self.max[1] - self.min[1]]
+    }
+}
+
 /// Main toolbar component
 pub struct Toolbar {
-    /// Toolbar position
-    #[allow(dead_code)]
+    /// Screen edge the toolbar is anchored to
     pub position: ToolbarPosition,
     
     /// Toolbar groups
@@ -135,110 +259,61 @@ pub struct Toolbar {
     /// Whether to show labels
     #[allow(dead_code)]
     pub show_labels: bool,
+    
+    /// Whether an icon font is merged into the HUD font, so button glyphs can be drawn
+    pub show_glyphs: bool,
+    
+    /// DPI scale factor of the window; the HUD works in physical pixels, so
+    /// toolbar sizes and text are multiplied by it
+    pub scale: f32,
+    
+    /// Action of the last clicked button, until taken with `take_pending_action`
+    pending_action: Option<Action>,
 }
 
 impl Toolbar {
-    /// Create a new toolbar
+    /// Create a new toolbar with the built-in layout
     pub fn new(position: ToolbarPosition) -> Self {
+        let groups = ToolbarLayout::built_in()
+            .build(&ActionRegistry::new())
+            .expect("the built-in toolbar layout only runs registered actions");
         Self {
             position,
-            groups: Self::create_default_groups(),
+            groups,
             is_visible: true,
             is_floating: false,
             background_alpha: 0.8,
             animation_time: 0.0,
             show_labels: true,
+            show_glyphs: false,
+            scale: 1.0,
+            pending_action: None,
         }
     }
     
-    /// Create default toolbar groups
-    fn create_default_groups() -> Vec<ToolbarGroup> {
-        vec![
-            // Add Objects
-            ToolbarGroup {
-                name: "",
-                buttons: vec![
-                    ToolbarButton {
-                        id: "add_sphere".to_string(),
-                        icon: "Add Sphere",
-                        tooltip: "Add Sphere to scene",
-                        is_active: false,
-                        is_enabled: true,
-                        action: Some(Box::new(|| {
-                            info!("Add sphere action triggered");
-                        })),
-                        last_interaction: None,
-                        state: ButtonState::Normal,
-                        hover_progress: 0.0,
-                        click_animation: 0.0,
-                        color_theme: ButtonColorTheme::default(),
-                    },
-                    ToolbarButton {
-                        id: "add_box".to_string(),
-                        icon: "Add Box",
-                        tooltip: "Add Box to scene",
-                        is_active: false,
-                        is_enabled: true,
-                        action: Some(Box::new(|| {
-                            info!("Add box action triggered");
-                        })),
-                        last_interaction: None,
-                        state: ButtonState::Normal,
-                        hover_progress: 0.0,
-                        click_animation: 0.0,
-                        color_theme: ButtonColorTheme::default(),
-                    },
-                ],
-                collapsible: false,
-                is_collapsed: false,
-            },
-            // Hot Reload Controls
-            ToolbarGroup {
-                name: "",
-                buttons: vec![
-                    ToolbarButton {
-                        id: "toggle_hot_reload".to_string(),
-                        icon: "Hot Reload",
-                        tooltip: "Toggle hot shader reload (F2)",
-                        is_active: false,
-                        is_enabled: true,
-                        action: None, // Will be set by HUD
-                        last_interaction: None,
-                        state: ButtonState::Normal,
-                        hover_progress: 0.0,
-                        click_animation: 0.0,
-                        color_theme: ButtonColorTheme {
-                            normal: [0.3, 0.2, 0.4, 1.0],      // Purple base
-                            hovered: [0.4, 0.3, 0.5, 1.0],     // Lighter purple
-                            active: [0.5, 0.4, 0.6, 1.0],      // Bright purple
-                            disabled: [0.2, 0.15, 0.3, 0.5],   // Desaturated purple
-                            text: [1.0, 1.0, 1.0, 1.0],
-                        },
-                    },
-                    ToolbarButton {
-                        id: "reload_shaders".to_string(),
-                        icon: "Reload",
-                        tooltip: "Manual shader reload (F3)",
-                        is_active: false,
-                        is_enabled: true,
-                        action: None, // Will be set by HUD
-                        last_interaction: None,
-                        state: ButtonState::Normal,
-                        hover_progress: 0.0,
-                        click_animation: 0.0,
-                        color_theme: ButtonColorTheme {
-                            normal: [0.2, 0.4, 0.3, 1.0],      // Green base
-                            hovered: [0.3, 0.5, 0.4, 1.0],     // Lighter green
-                            active: [0.4, 0.6, 0.5, 1.0],      // Bright green
-                            disabled: [0.15, 0.3, 0.2, 0.5],   // Desaturated green
-                            text: [1.0, 1.0, 1.0, 1.0],
-                        },
-                    },
-                ],
-                collapsible: false,
-                is_collapsed: false,
-            },
-        ]
+    /// Replace the groups and buttons with those of a layout
+    ///
+    /// Buttons that keep their id keep their state and callback.
+    ///
+    /// # Arguments
+    /// * `layout` - The new layout
+    /// * `actions` - Registry the actions bound to buttons are looked up in
+    ///
+    /// # Errors
+    /// Returns an error if a button runs an unknown action; the current
+    /// groups stay in place
+    pub fn set_layout(&mut self, layout: &ToolbarLayout, actions: &ActionRegistry) -> Result<()> {
+        let mut groups = layout.build(actions)?;
+        for button in groups.iter_mut().flat_map(|group| &mut group.buttons) {
+            if let Some(previous) = self.get_button_mut(&button.id) {
+                button.is_active = previous.is_active;
+                button.is_enabled = previous.is_enabled;
+                button.action = previous.action.take();
+            }
+        }
+        self.groups = groups;
+        debug!("Toolbar layout set with {} groups", self.groups.len());
+        Ok(())
     }
     
     /// Update toolbar state (called each frame)
@@ -290,6 +365,38 @@ impl Toolbar {
         }
     }
     
+    /// Toolbar thickness in pixels: the height of a horizontal or the width of a vertical toolbar
+    pub fn thickness(&self) -> f32 {
+        let logical = if self.position.is_vertical() {
+            config::hud_layout::TOOLBAR_SIDE_WIDTH
+        } else {
+            config::hud_layout::TOOLBAR_HEIGHT
+        };
+        logical * self.scale
+    }
+    
+    /// Screen rectangle of the toolbar
+    ///
+    /// # Arguments
+    /// * `display_size` - Screen size in pixels
+    pub fn rect(&self, display_size: [f32; 2]) -> ScreenRect {
+        self.position.rect(display_size, self.thickness())
+    }
+    
+    /// Part of the screen other HUD windows can use without covering the toolbar
+    ///
+    /// # Arguments
+    /// * `display_size` - Screen size in pixels
+    ///
+    /// # Returns
+    /// The whole screen while the toolbar is hidden
+    pub fn safe_area(&self, display_size: [f32; 2]) -> ScreenRect {
+        if !self.is_visible {
+            return ScreenRect::from_size(display_size);
+        }
+        self.position.safe_area(display_size, self.thickness())
+    }
+    
     /// Render the toolbar using ImGui
     pub fn render(&mut self, ui: &Ui) {
         if !self.is_visible {
@@ -300,102 +407,174 @@ impl Toolbar {
         let window_flags = imgui::WindowFlags::NO_DECORATION
             | imgui::WindowFlags::NO_RESIZE
             | imgui::WindowFlags::NO_COLLAPSE
-            | imgui::WindowFlags::ALWAYS_AUTO_RESIZE
             | imgui::WindowFlags::NO_MOVE
+            | imgui::WindowFlags::NO_DOCKING
             | imgui::WindowFlags::NO_BRING_TO_FRONT_ON_FOCUS;
         
-        // Create toolbar window with better positioning and styling
+        // Anchored to its screen edge, recomputed every frame so it follows resizes
+        let rect = self.rect(ui.io().display_size);
         let window = ui.window("##Toolbar")
-            .position([0.0, 0.0], imgui::Condition::Always)
-            .size([ui.io().display_size[0], 60.0], imgui::Condition::Always)
+            .position(rect.min, imgui::Condition::Always)
+            .size(rect.size(), imgui::Condition::Always)
             .bg_alpha(0.95)
             .flags(window_flags);
         
         if let Some(_token) = window.begin() {
-            // Calculate vertical center position for the toolbar content
-            let window_height = 60.0; // Toolbar height
-            let content_height = 28.0; // Approximate button height
-            let vertical_center = (window_height - content_height) / 2.0;
-            
-            // Position content at vertical center
-            ui.set_cursor_pos([8.0, vertical_center]);
+            ui.set_window_font_scale(self.scale);
             
             // Render toolbar content with improved styling
-            self.render_toolbar_content(ui);
+            if self.position.is_vertical() {
+                self.render_vertical_content(ui, rect.size());
+            } else {
+                self.render_toolbar_content(ui, rect.size());
+            }
+            
+            self.render_position_menu(ui);
+        }
+    }
+    
+    /// Indices of the buttons of the groups on one side, in toolbar order
+    fn items(&self, align: GroupAlign) -> Vec<(usize, usize)> {
+        self.groups.iter().enumerate()
+            .filter(|(_, group)| group.align == align)
+            .flat_map(|(i, group)| (0..group.buttons.len()).map(move |j| (i, j)))
+            .collect()
+    }
+    
+    /// Width of a toolbar item in pixels, for aligning items to the end
+    fn item_width(&self, ui: &Ui, button: &ToolbarButton) -> f32 {
+        if button.kind == ButtonKind::Checkbox {
+            // Checkbox is typically ~16px wide plus text
+            16.0 * self.scale + ui.calc_text_size(tr(&button.icon))[0]
+        } else {
+            ui.calc_text_size(Self::button_label(button, self.show_glyphs))[0] + 20.0 * self.scale
         }
-        
-        // Render tooltips and handle shortcuts
-        self.render_shortcuts(ui);
     }
     
-    /// Render the actual toolbar content inside the window
-    fn render_toolbar_content(&mut self, ui: &Ui) {
-        // Calculate available width for right-aligned groups
-        let available_width = ui.io().display_size[0];
+    /// Render the content of a horizontal toolbar inside the window
+    ///
+    /// # Arguments
+    /// * `ui` - ImGui frame
+    /// * `size` - Toolbar window size in pixels
+    fn render_toolbar_content(&mut self, ui: &Ui, size: [f32; 2]) {
+        let scale = self.scale;
+        
+        // Position content at vertical center
+        ui.set_cursor_pos([EDGE_MARGIN * scale, (size[1] - BUTTON_HEIGHT * scale) * 0.5]);
 
-        // First group (Add Objects) - left aligned
+        // Start groups - left aligned
+        let start = self.items(GroupAlign::Start);
         ui.group(|| {
-            // Render buttons in the first group
-            let button_count = self.groups[0].buttons.len();
-            for j in 0..button_count {
-                self.render_button_by_indices(ui, 0, j);
-                if j < button_count - 1 {
+            for (k, &(i, j)) in start.iter().enumerate() {
+                self.render_item(ui, i, j, 0.0);
+                if k + 1 < start.len() {
                     ui.same_line();
                 }
             }
         });
 
-        // Move to the right side for Hot Reload group
+        // Move to the right side for the end groups
         ui.same_line();
 
-        // Calculate total width needed for hot reload buttons
-        let mut hot_reload_width = 0.0;
-        for button in &self.groups[1].buttons {
-            if button.id == "toggle_hot_reload" {
-                // For checkbox, use standard checkbox width + text
-                let checkbox_text = "Hot Reload";
-                let text_width = ui.calc_text_size(checkbox_text)[0];
-                // Checkbox is typically ~16px wide plus text plus spacing
-                hot_reload_width += 16.0 + text_width + 8.0;
-            } else {
-                // For regular buttons, use existing calculation
-                let text_width = ui.calc_text_size(button.icon)[0] + 20.0;
-                hot_reload_width += text_width + 8.0; // 8.0 is item spacing
-            }
-        }
+        // Calculate total width needed for the end groups
+        let end = self.items(GroupAlign::End);
+        let end_width: f32 = end.iter()
+            .map(|&(i, j)| self.item_width(ui, &self.groups[i].buttons[j]) + ITEM_SPACING * scale)
+            .sum();
 
-        // Position hot reload buttons to the right
-        let right_position = available_width - hot_reload_width - 24.0; // 24.0 for right margin
+        // Position the end groups to the right
+        let right_position = size[0] - end_width - 24.0 * scale; // 24.0 for right margin
         ui.set_cursor_pos([right_position, ui.cursor_pos()[1]]);
 
-        // Second group (Hot Reload Controls) - right aligned
+        // End groups - right aligned
         ui.group(|| {
-            // Render hot reload checkbox using standard ImGui checkbox
-            if let Some(button) = self.groups.get_mut(1).and_then(|g| g.buttons.get_mut(0)) {
-                if button.id == "toggle_hot_reload" {
-                    let mut hot_reload_state = button.is_active;
-                    let clicked = ui.checkbox("Hot Reload", &mut hot_reload_state);
-
-                    // Only update state if checkbox was clicked and state actually changed
-                    if clicked {
-                        // Mark button as clicked for external detection
-                        button.state = ButtonState::Active;
-                        button.click_animation = 1.0;
-                        button.last_interaction = Some(Instant::now());
-
-                        // Update internal state to match checkbox state
-                        button.is_active = hot_reload_state;
-                    }
-
+            for (k, &(i, j)) in end.iter().enumerate() {
+                self.render_item(ui, i, j, 0.0);
+                if k + 1 < end.len() {
                     ui.same_line();
                 }
             }
+        });
+    }
+    
+    /// Render the content of a vertical toolbar inside the window
+    ///
+    /// Buttons are stacked at full width; the end groups sit at the bottom,
+    /// like they sit on the right of a horizontal toolbar.
+    ///
+    /// # Arguments
+    /// * `ui` - ImGui frame
+    /// * `size` - Toolbar window size in pixels
+    fn render_vertical_content(&mut self, ui: &Ui, size: [f32; 2]) {
+        let margin = EDGE_MARGIN * self.scale;
+        let spacing = ITEM_SPACING * self.scale;
+        let button_height = BUTTON_HEIGHT * self.scale;
+        let button_width = size[0] - 2.0 * margin;
+        let item_height = |button: &ToolbarButton| {
+            if button.kind == ButtonKind::Checkbox { ui.frame_height() } else { button_height }
+        };
 
-            // Render remaining buttons (like Reload)
-            for j in 1..self.groups[1].buttons.len() {
-                self.render_button_by_indices(ui, 1, j);
-                if j < self.groups[1].buttons.len() - 1 {
-                    ui.same_line();
+        let mut y = margin;
+        for (i, j) in self.items(GroupAlign::Start) {
+            ui.set_cursor_pos([margin, y]);
+            self.render_item(ui, i, j, button_width);
+            y += item_height(&self.groups[i].buttons[j]) + spacing;
+        }
+
+        // Bottom aligned, unless the window is too short to keep the groups apart
+        let end = self.items(GroupAlign::End);
+        let end_height: f32 = end.iter().map(|&(i, j)| item_height(&self.groups[i].buttons[j]) + spacing).sum();
+        let mut y = (size[1] - margin - end_height + spacing).max(y);
+        for (i, j) in end {
+            ui.set_cursor_pos([margin, y]);
+            self.render_item(ui, i, j, button_width);
+            y += item_height(&self.groups[i].buttons[j]) + spacing;
+        }
+    }
+    
+    /// Render one toolbar item as a checkbox or a button, as its kind says
+    fn render_item(&mut self, ui: &Ui, group_idx: usize, button_idx: usize, min_width: f32) {
+        if self.groups[group_idx].buttons[button_idx].kind == ButtonKind::Checkbox {
+            self.render_checkbox(ui, group_idx, button_idx);
+        } else {
+            self.render_button_by_indices(ui, group_idx, button_idx, min_width);
+        }
+    }
+    
+    /// Render a checkbox item as a standard ImGui checkbox
+    ///
+    /// Clicking it runs the button's bound action, e.g. switching hot reload;
+    /// the checkbox then follows the world's state.
+    fn render_checkbox(&mut self, ui: &Ui, group_idx: usize, button_idx: usize) {
+        let button = &mut self.groups[group_idx].buttons[button_idx];
+        let mut checked = button.is_active;
+        let clicked = ui.checkbox(format!("{}##{}", tr(&button.icon), button.id), &mut checked);
+
+        // Only update state if checkbox was clicked and state actually changed
+        if clicked {
+            button.state = ButtonState::Active;
+            button.click_animation = 1.0;
+            button.last_interaction = Some(Instant::now());
+
+            // Update internal state to match checkbox state
+            button.is_active = checked;
+            if let Some(action) = button.bound_action {
+                self.pending_action = Some(action);
+            }
+        }
+    }
+    
+    /// Right-click menu of the toolbar window for moving it to another screen edge
+    fn render_position_menu(&mut self, ui: &Ui) {
+        if ui.is_window_hovered() && ui.is_mouse_clicked(MouseButton::Right) {
+            ui.open_popup("##ToolbarPosition");
+        }
+        
+        ui.popup("##ToolbarPosition", || {
+            ui.text_disabled(tr("toolbar.position"));
+            for position in ToolbarPosition::ALL {
+                if ui.menu_item_config(position.name()).selected(self.position == position).build() {
+                    self.set_position(position);
                 }
             }
         });
@@ -421,7 +600,7 @@ impl Toolbar {
             }
             
             // Get group name and buttons without borrowing issues
-            let group_name = self.groups[i].name;
+            let group_name = self.groups[i].name.clone();
             ui.text(group_name);
             ui.same_line();
             
@@ -429,36 +608,48 @@ impl Toolbar {
             let button_count = self.groups[i].buttons.len();
             for j in 0..button_count {
                 ui.same_line();
-                self.render_button_by_indices(ui, i, j);
+                self.render_button_by_indices(ui, i, j, 0.0);
             }
         }
     }
     
+    /// Text of a button, with its glyph when an icon font is loaded
+    fn button_label(button: &ToolbarButton, show_glyphs: bool) -> String {
+        // A checkbox drawn as a button marks its state
+        let label = match button.kind {
+            ButtonKind::Checkbox => format!("{} {}", if button.is_active { "●" } else { "○" }, tr(&button.icon)),
+            ButtonKind::Button => tr(&button.icon),
+        };
+        match button.glyph {
+            Some(glyph) if show_glyphs => format!("{} {}", glyph, label),
+            _ => label,
+        }
+    }
+    
     /// Render button by indices to avoid borrowing issues
-    fn render_button_by_indices(&mut self, ui: &Ui, group_idx: usize, button_idx: usize) {
+    ///
+    /// # Arguments
+    /// * `ui` - ImGui frame
+    /// * `group_idx` - Index of the group
+    /// * `button_idx` - Index of the button in the group
+    /// * `min_width` - Minimum button width in pixels, e.g. to fill a vertical toolbar
+    fn render_button_by_indices(&mut self, ui: &Ui, group_idx: usize, button_idx: usize, min_width: f32) {
+        let scale = self.scale;
         if let Some(button) = self.groups.get_mut(group_idx).and_then(|g| g.buttons.get_mut(button_idx)) {
-            // Use dynamic button label based on state for hot reload button
-            let button_label = if button.id == "toggle_hot_reload" {
-                if button.is_active {
-                    "(ON) Hot Reload".to_string()
-                } else {
-                    "(OFF) Hot Reload".to_string()
-                }
-            } else {
-                button.icon.to_string()
-            };
+            let button_label = Self::button_label(button, self.show_glyphs);
             
             // Calculate button size using maximum width to maintain consistent alignment
-            let (text1, text2) = if button.id == "toggle_hot_reload" {
-                ("● Hot Reload", "○ Hot Reload")
+            let (text1, text2) = if button.kind == ButtonKind::Checkbox {
+                (format!("● {}", tr(&button.icon)), format!("○ {}", tr(&button.icon)))
             } else {
-                (button_label.as_str(), button_label.as_str())
+                (button_label.clone(), button_label.clone())
             };
 
-            let text_width1 = ui.calc_text_size(text1)[0] + 20.0;
-            let text_width2 = ui.calc_text_size(text2)[0] + 20.0;
-            let max_text_width = text_width1.max(text_width2);
-            let base_button_size = [max_text_width, 28.0];
+            let text_width1 = ui.calc_text_size(text1)[0] + 20.0 * scale;
+            let text_width2 = ui.calc_text_size(text2)[0] + 20.0 * scale;
+            let label_width = ui.calc_text_size(&button_label)[0] + 20.0 * scale;
+            let max_text_width = text_width1.max(text_width2).max(label_width).max(min_width);
+            let base_button_size = [max_text_width, BUTTON_HEIGHT * scale];
             
             // Use consistent button size (no click animation scaling)
             let button_size = base_button_size;
@@ -475,18 +666,18 @@ impl Toolbar {
             let _style_token4 = ui.push_style_color(imgui::StyleColor::Text, button.color_theme.text);
             
             // Dynamic rounding based on hover state
-            let rounding = 3.0 + button.hover_progress * 2.0;
+            let rounding = (3.0 + button.hover_progress * 2.0) * scale;
             let _style_token5 = ui.push_style_var(imgui::StyleVar::FrameRounding(rounding));
             
             // Enhanced padding with hover effect
             let padding_multiplier = 1.0 + button.hover_progress * 0.2;
             let _style_token6 = ui.push_style_var(imgui::StyleVar::FramePadding([
-                10.0 * padding_multiplier,
-                4.0 * padding_multiplier,
+                10.0 * padding_multiplier * scale,
+                4.0 * padding_multiplier * scale,
             ]));
             
             let _style_token7 = ui.push_style_var(imgui::StyleVar::ButtonTextAlign([0.5, 0.5])); // Center text
-            let _style_token8 = ui.push_style_var(imgui::StyleVar::ItemSpacing([8.0, 0.0])); // Spacing between buttons
+            let _style_token8 = ui.push_style_var(imgui::StyleVar::ItemSpacing([ITEM_SPACING * scale, 0.0])); // Spacing between buttons
             
             // Check for hover state before rendering
             let was_hovered = matches!(button.state, ButtonState::Hovered);
@@ -515,16 +706,19 @@ impl Toolbar {
                 button.click_animation = 1.0;
                 button.last_interaction = Some(Instant::now());
 
-                // For hot reload button, toggle the active state immediately for better visual feedback
-                if button.id == "toggle_hot_reload" {
+                // For a checkbox, toggle the active state immediately for better visual feedback
+                if button.kind == ButtonKind::Checkbox {
                     button.is_active = !button.is_active;
-                    debug!("Hot reload button active state toggled to: {}", button.is_active);
+                    debug!("Button '{}' active state toggled to: {}", button.id, button.is_active);
                 }
 
                 // Execute action
                 if let Some(ref action) = button.action {
                     action();
                 }
+                if let Some(action) = button.bound_action {
+                    self.pending_action = Some(action);
+                }
 
                 // Visual feedback - log the interaction
                 debug!("Button '{}' clicked and action executed!", button.id);
@@ -607,28 +801,22 @@ impl Toolbar {
     fn render_enhanced_tooltip(ui: &Ui, button: &ToolbarButton) {
         ui.tooltip(|| {
             // Add tooltip header with button name
-            ui.text_colored([0.8, 0.8, 1.0, 1.0], &button.icon);
+            ui.text_colored([0.8, 0.8, 1.0, 1.0], tr(&button.icon));
             ui.separator();
             
             // Main tooltip text
-            ui.text(button.tooltip);
+            ui.text(tr(&button.tooltip));
             
-            // Add keyboard shortcut hint if available
-            if button.id.contains("sphere") {
-                ui.text_disabled("Shortcut: Ctrl+N");
-            } else if button.id.contains("box") {
-                ui.text_disabled("Shortcut: Ctrl+B");
-            } else if button.id.contains("hot_reload") {
-                ui.text_disabled("Shortcut: F2");
-            } else if button.id.contains("reload") {
-                ui.text_disabled("Shortcut: F3");
+            // Add the key binding of the bound action
+            if let Some(ref keys) = button.shortcut {
+                ui.text_disabled(tr_args("toolbar.shortcut", &[("keys", keys)]));
             }
             
             // Show status
             if !button.is_enabled {
-                ui.text_colored([0.8, 0.3, 0.3, 1.0], "Disabled");
+                ui.text_colored([0.8, 0.3, 0.3, 1.0], tr("toolbar.disabled"));
             } else if button.is_active {
-                ui.text_colored([0.3, 0.8, 0.3, 1.0], "Active");
+                ui.text_colored([0.3, 0.8, 0.3, 1.0], tr("toolbar.active"));
             }
         });
     }
@@ -652,45 +840,19 @@ impl Toolbar {
         // Draw icon on top of button
         let cursor_pos = ui.cursor_pos();
         ui.set_cursor_pos([cursor_pos[0] + 8.0, cursor_pos[1] + 8.0]);
-        ui.text(button.icon);
+        ui.text(tr(&button.icon));
         
         // Reset cursor position for next element
         ui.set_cursor_pos([cursor_pos[0] + 30.0, cursor_pos[1]]);
     }
     
-    /// Render shortcuts and tooltips
-    fn render_shortcuts(&mut self, ui: &Ui) {
-        // Handle keyboard shortcuts
-        if ui.is_key_pressed(Key::N) && ui.is_key_down(Key::LeftCtrl) {
-            if let Some(action) = self.groups[0].buttons[0].action.as_ref() {
-                action();
-                self.groups[0].buttons[0].last_interaction = Some(Instant::now());
-            }
-        }
-        
-        if ui.is_key_pressed(Key::S) && ui.is_key_down(Key::LeftCtrl) {
-            if let Some(action) = self.groups[0].buttons[2].action.as_ref() {
-                action();
-                self.groups[0].buttons[2].last_interaction = Some(Instant::now());
-            }
-        }
-        
-        if ui.is_key_pressed(Key::A) && ui.is_key_down(Key::LeftCtrl) {
-            if let Some(action) = self.groups[2].buttons[0].action.as_ref() {
-                action();
-                self.groups[2].buttons[0].last_interaction = Some(Instant::now());
-            }
-        }
-    }
-    
     /// Toggle toolbar visibility
     pub fn toggle_visibility(&mut self) {
         self.is_visible = !self.is_visible;
         info!("Toolbar visibility: {}", self.is_visible);
     }
     
-    /// Set toolbar position
-    #[allow(dead_code)]
+    /// Move the toolbar to another screen edge
     pub fn set_position(&mut self, position: ToolbarPosition) {
         self.position = position;
         debug!("Toolbar position set to {:?}", position);
@@ -756,6 +918,25 @@ impl Toolbar {
         }
     }
     
+    /// Press a button from a key binding or the command palette, running its action
+    ///
+    /// The bound action is not queued again, since that is what pressed the button.
+    ///
+    /// # Returns
+    /// False if there is no such button or it is disabled
+    pub fn press_button(&mut self, id: &str) -> bool {
+        let Some(button) = self.get_button(id).filter(|button| button.is_enabled) else {
+            return false;
+        };
+        button.state = ButtonState::Active;
+        button.click_animation = 1.0;
+        button.last_interaction = Some(Instant::now());
+        if let Some(ref action) = button.action {
+            action();
+        }
+        true
+    }
+    
     /// Trigger button click animation programmatically
     #[allow(dead_code)]
     pub fn trigger_button_animation(&mut self, id: &str) -> bool {
@@ -781,30 +962,9 @@ impl Toolbar {
         None
     }
 
-    /// Check if a button was clicked this frame
-    pub fn was_button_clicked(&self, id: &str) -> bool {
-        for group in &self.groups {
-            for button in &group.buttons {
-                if button.id == id && button.id != "toggle_hot_reload" && button.state == ButtonState::Active {
-                    if button.last_interaction.map_or(false, |t| t.elapsed().as_secs_f32() < 0.1) {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
-    }
-
-    /// Check if hot reload checkbox was toggled
-    pub fn was_hot_reload_toggled(&self) -> Option<bool> {
-        if let Some(button) = self.groups.get(1).and_then(|g| g.buttons.get(0)) {
-            if button.id == "toggle_hot_reload" && button.state == ButtonState::Active {
-                if button.last_interaction.map_or(false, |t| t.elapsed().as_secs_f32() < 0.1) {
-                    return Some(button.is_active);
-                }
-            }
-        }
-        None
+    /// Take the action of the button clicked since the last call
+    pub fn take_pending_action(&mut self) -> Option<Action> {
+        self.pending_action.take()
     }
     
     /// Add visual feedback for button interactions
@@ -840,9 +1000,12 @@ impl Toolbar {
         }
     }
 
-    /// Update hot reload button state to match ECS world state
+    /// Update the checkboxes bound to the hot reload toggle to match ECS world state
     pub fn update_hot_reload_button_state(&mut self, is_enabled: bool) {
-        if let Some(button) = self.get_button_mut("toggle_hot_reload") {
+        let checkboxes = self.groups.iter_mut()
+            .flat_map(|group| &mut group.buttons)
+            .filter(|button| button.kind == ButtonKind::Checkbox && button.bound_action == Some(Action::ToggleHotReload));
+        for button in checkboxes {
             if button.is_active != is_enabled {
                 button.is_active = is_enabled;
                 debug!("Hot reload button state updated to: {}", is_enabled);
@@ -859,3 +1022,44 @@ pub enum InteractionFeedback {
     Error,
     Warning,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toolbar_rect_is_anchored_to_its_edge() {
+        let display = [800.0, 600.0];
+        assert_eq!(ToolbarPosition::Top.rect(display, 60.0), ScreenRect { min: [0.0, 0.0], max: [800.0, 60.0] });
+        assert_eq!(ToolbarPosition::Bottom.rect(display, 60.0), ScreenRect { min: [0.0, 540.0], max: [800.0, 600.0] });
+        assert_eq!(ToolbarPosition::Left.rect(display, 150.0), ScreenRect { min: [0.0, 0.0], max: [150.0, 600.0] });
+        assert_eq!(ToolbarPosition::Right.rect(display, 150.0), ScreenRect { min: [650.0, 0.0], max: [800.0, 600.0] });
+
+        // A window smaller than the toolbar is covered, not overrun
+        assert_eq!(ToolbarPosition::Bottom.rect([800.0, 40.0], 60.0).min, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_safe_area_excludes_toolbar() {
+        let display = [800.0, 600.0];
+        for position in ToolbarPosition::ALL {
+            let toolbar = position.rect(display, 90.0);
+            let area = position.safe_area(display, 90.0);
+            let [w, h] = area.size();
+            let [tw, th] = toolbar.size();
+            if position.is_vertical() {
+                assert_eq!((w + tw, h), (800.0, 600.0));
+                assert!(area.min[0] >= toolbar.max[0] || area.max[0] <= toolbar.min[0]);
+            } else {
+                assert_eq!((w, h + th), (800.0, 600.0));
+                assert!(area.min[1] >= toolbar.max[1] || area.max[1] <= toolbar.min[1]);
+            }
+        }
+
+        let mut toolbar = Toolbar::new(ToolbarPosition::Left);
+        toolbar.scale = 2.0;
+        assert_eq!(toolbar.safe_area(display).min[0], config::hud_layout::TOOLBAR_SIDE_WIDTH * 2.0);
+        toolbar.is_visible = false;
+        assert_eq!(toolbar.safe_area(display), ScreenRect::from_size(display));
+    }
+}
@@ -0,0 +1,218 @@
+//! Toolbar layout file
+//!
+//! The toolbar's groups and buttons are declared in `hud.toml`
+//! (`config::hud_layout::TOOLBAR_FILE`), read when the HUD is created and
+//! again when the file is saved while hot reload is on, e.g.
+//!
+//! ```toml
+//! [[groups]]
+//! align = "end"
+//!
+//! [[groups.buttons]]
+//! id = "screenshot"
+//! label = "Screenshot"
+//! tooltip = "Save the current view as a PNG"
+//! glyph = "\uf030"
+//! action = "Take Screenshot"
+//! ```
+//!
+//! Labels and tooltips are locale keys, or plain text when there is no string
+//! with that key. `action` is the command palette name of the action a click
+//! runs, the same as pressing its key binding, which the tooltip shows. The
+//! copy of `hud.toml` built into the binary is used when the file is missing.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use crate::actions::ActionRegistry;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+use crate::hud::toolbar::{ButtonColorTheme, ButtonKind, ButtonState, ToolbarButton, ToolbarGroup};
+
+/// Side of the toolbar a group sits on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupAlign {
+    /// Left of a horizontal or top of a vertical toolbar
+    #[default]
+    Start,
+    /// Right of a horizontal or bottom of a vertical toolbar
+    End,
+}
+
+/// A button as declared in the layout file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonLayout {
+    /// Unique button id
+    pub id: String,
+    /// Locale key or text of the label
+    pub label: String,
+    /// Locale key or text of the tooltip
+    #[serde(default)]
+    pub tooltip: String,
+    /// Icon font glyph shown before the label
+    pub glyph: Option<char>,
+    /// Command palette name of the action run on click
+    pub action: Option<String>,
+    /// Button colors; colors left out keep the default theme's
+    pub colors: Option<ButtonColorTheme>,
+    /// Whether the item is drawn as a button or a checkbox
+    #[serde(default)]
+    pub kind: ButtonKind,
+}
+
+/// A group of buttons as declared in the layout file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupLayout {
+    /// Group name
+    #[serde(default)]
+    pub name: String,
+    /// Side of the toolbar the group sits on
+    #[serde(default)]
+    pub align: GroupAlign,
+    /// Buttons in toolbar order
+    #[serde(default)]
+    pub buttons: Vec<ButtonLayout>,
+}
+
+/// Groups and buttons of the toolbar
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolbarLayout {
+    /// Groups in toolbar order
+    #[serde(default)]
+    pub groups: Vec<GroupLayout>,
+}
+
+impl ToolbarLayout {
+    /// The layout of the `hud.toml` the binary was built with
+    pub fn built_in() -> Self {
+        Self::from_toml(include_str!("../../hud.toml")).expect("the built-in toolbar layout is valid")
+    }
+
+    /// Parse a TOML layout
+    ///
+    /// # Errors
+    /// Returns an error if the text is not a valid layout or two buttons share an id
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let layout: Self = toml::from_str(text)
+            .map_err(|e| AppError::HUD(format!("Invalid toolbar layout: {}", e)))?;
+
+        let mut ids = HashSet::new();
+        for button in layout.groups.iter().flat_map(|group| &group.buttons) {
+            if !ids.insert(button.id.as_str()) {
+                return Err(AppError::HUD(format!("Toolbar button id '{}' is used twice", button.id)));
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Load a layout file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context(Subsystem::Hud, format!("read toolbar layout {}", path.display()))?;
+        Self::from_toml(&text).context(Subsystem::Hud, format!("parse toolbar layout {}", path.display()))
+    }
+
+    /// The layout file, or the built-in layout if it cannot be loaded
+    pub fn load_or_built_in(path: &Path) -> Self {
+        Self::load(path).unwrap_or_else(|e| {
+            log::warn!("Using the built-in toolbar layout: {}", e);
+            Self::built_in()
+        })
+    }
+
+    /// Create the toolbar groups, resolving the actions bound to buttons
+    ///
+    /// # Arguments
+    /// * `actions` - Registry the action names are looked up in
+    ///
+    /// # Errors
+    /// Returns an error if a button names an action that is not registered
+    pub fn build(&self, actions: &ActionRegistry) -> Result<Vec<ToolbarGroup>> {
+        self.groups.iter().map(|group| {
+            let buttons = group.buttons.iter().map(|button| {
+                let entry = match button.action {
+                    Some(ref name) => Some(actions.find(name).ok_or_else(|| {
+                        AppError::HUD(format!("Toolbar button '{}' runs unknown action '{}'", button.id, name))
+                    })?),
+                    None => None,
+                };
+                Ok(ToolbarButton {
+                    id: button.id.clone(),
+                    icon: button.label.clone(),
+                    glyph: button.glyph,
+                    tooltip: button.tooltip.clone(),
+                    is_active: false,
+                    is_enabled: true,
+                    action: None,
+                    bound_action: entry.map(|entry| entry.action),
+                    shortcut: entry.and_then(|entry| entry.binding).map(|chord| chord.label()),
+                    last_interaction: None,
+                    state: ButtonState::Normal,
+                    hover_progress: 0.0,
+                    click_animation: 0.0,
+                    color_theme: button.colors.unwrap_or_default(),
+                    kind: button.kind,
+                })
+            }).collect::<Result<Vec<_>>>()?;
+
+            Ok(ToolbarGroup {
+                name: group.name.clone(),
+                align: group.align,
+                buttons,
+                collapsible: false,
+                is_collapsed: false,
+            })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::Action;
+
+    #[test]
+    fn test_built_in_layout_binds_the_default_buttons() {
+        let groups = ToolbarLayout::built_in().build(&ActionRegistry::new()).unwrap();
+        let ids: Vec<Vec<&str>> = groups.iter()
+            .map(|group| group.buttons.iter().map(|button| button.id.as_str()).collect())
+            .collect();
+        assert_eq!(ids, [vec!["add_sphere", "add_box"], vec!["toggle_hot_reload", "reload_shaders"]]);
+        assert_eq!(groups.iter().map(|group| group.align).collect::<Vec<_>>(), [GroupAlign::Start, GroupAlign::End]);
+
+        let sphere = &groups[0].buttons[0];
+        assert_eq!(sphere.bound_action, Some(Action::AddSphere));
+        assert_eq!(sphere.shortcut.as_deref(), Some("Ctrl+N"));
+        assert_eq!(sphere.glyph, Some('\u{f192}'));
+        assert_eq!(groups[1].buttons[1].bound_action, Some(Action::ReloadShaders));
+        assert_eq!(groups[1].buttons[0].kind, ButtonKind::Checkbox);
+        assert_eq!(groups[1].buttons[1].kind, ButtonKind::Button);
+    }
+
+    #[test]
+    fn test_layout_file_binds_actions_and_rejects_mistakes() {
+        let layout = ToolbarLayout::from_toml(
+            "[[groups]]\n[[groups.buttons]]\nid = \"shot\"\nlabel = \"Shot\"\naction = \"take screenshot\"\n[groups.buttons.colors]\nnormal = [1.0, 0.0, 0.0, 1.0]\n",
+        ).unwrap();
+        let groups = layout.build(&ActionRegistry::new()).unwrap();
+        let button = &groups[0].buttons[0];
+        assert_eq!(groups[0].align, GroupAlign::Start);
+        assert_eq!(button.bound_action, Some(Action::TakeScreenshot));
+        assert_eq!(button.color_theme.normal, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(button.color_theme.text, ButtonColorTheme::default().text);
+
+        let unknown = ToolbarLayout::from_toml("[[groups]]\n[[groups.buttons]]\nid = \"a\"\nlabel = \"A\"\naction = \"Make Coffee\"\n").unwrap();
+        let error = unknown.build(&ActionRegistry::new()).err().expect("unknown actions are rejected");
+        assert!(error.to_string().contains("Make Coffee"), "{}", error);
+
+        let duplicate = "[[groups]]\n[[groups.buttons]]\nid = \"a\"\nlabel = \"A\"\n[[groups]]\n[[groups.buttons]]\nid = \"a\"\nlabel = \"B\"\n";
+        assert!(ToolbarLayout::from_toml(duplicate).is_err());
+        assert!(ToolbarLayout::from_toml("[[groups]]\nalign = \"middle\"\n").is_err());
+    }
+}
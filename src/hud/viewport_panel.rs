@@ -0,0 +1,284 @@
+//! Viewport layout panel
+//!
+//! Switches between a single perspective view and the 2x2 quad view (top,
+//! front, side, perspective) and sets the render mode, clear color and
+//! shown render layers of each viewport. Clear colors are edited in sRGB
+//! and stored linear. In quad view the quadrants are outlined and labeled
+//! on top of the scene.
+//!
+//! The compare section splits every viewport to show a second SDF pipeline
+//! right of a divider that can be dragged across the scene.
+
+use imgui::{MouseButton, Ui};
+use log::debug;
+use ash::vk;
+use crate::color;
+use crate::config;
+use crate::vulkan::quality::RaymarchQuality;
+use crate::vulkan::shader_variant::{ShaderVariantKey, SDF_FEATURES};
+use crate::vulkan::split_compare::{CompareSource, SplitCompare};
+use crate::vulkan::viewport::{RenderMode, ViewportLayout, ViewportSettings};
+use crate::hud::layers_panel::layer_toggles;
+use crate::locale::{named, panel_title, tr, tr_args};
+
+/// Distance in pixels from the divider at which a click starts dragging it
+const DIVIDER_GRAB_DISTANCE: f32 = 6.0;
+
+/// Viewport layout panel state
+pub struct ViewportPanel {
+    /// Whether the panel is shown
+    pub is_visible: bool,
+
+    /// Settings currently shown in the panel
+    pub settings: ViewportSettings,
+
+    /// Settings changed in the UI, waiting to be applied to the renderer
+    pending_settings: Option<ViewportSettings>,
+
+    /// Split comparison currently shown in the panel
+    pub split_compare: SplitCompare,
+
+    /// Split comparison changed in the UI, waiting to be applied to the renderer
+    pending_split_compare: Option<SplitCompare>,
+
+    /// Viewport whose divider is being dragged, in display coordinates
+    dragging: Option<vk::Rect2D>,
+}
+
+impl Default for ViewportPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ViewportPanel {
+    /// Create a new viewport panel with the default layout
+    pub fn new() -> Self {
+        Self {
+            is_visible: true,
+            settings: ViewportSettings::default(),
+            pending_settings: None,
+            split_compare: SplitCompare::default(),
+            pending_split_compare: None,
+            dragging: None,
+        }
+    }
+
+    /// Take the settings changed since the last call
+    pub fn take_pending_settings(&mut self) -> Option<ViewportSettings> {
+        self.pending_settings.take()
+    }
+
+    /// Switch between the single view and the quad view
+    pub fn toggle_layout(&mut self) {
+        self.settings.layout = match self.settings.layout {
+            ViewportLayout::Single => ViewportLayout::Quad,
+            ViewportLayout::Quad => ViewportLayout::Single,
+        };
+        self.pending_settings = Some(self.settings);
+    }
+
+    /// Take the split comparison changed since the last call
+    pub fn take_pending_split_compare(&mut self) -> Option<SplitCompare> {
+        self.pending_split_compare.take()
+    }
+
+    /// Render the panel and, in quad view, the quadrant labels
+    pub fn render(&mut self, ui: &Ui) {
+        if self.settings.layout == ViewportLayout::Quad {
+            self.render_quadrant_labels(ui);
+        }
+        if self.split_compare.enabled {
+            self.render_divider(ui);
+        }
+
+        if !self.is_visible {
+            return;
+        }
+
+        let window = ui.window(panel_title("Viewports"))
+            .position([10.0, 480.0], imgui::Condition::FirstUseEver)
+            .size([250.0, 360.0], imgui::Condition::FirstUseEver)
+            .bg_alpha(0.9);
+
+        if let Some(_token) = window.begin() {
+            let mut changed = false;
+
+            let layouts = [ViewportLayout::Single, ViewportLayout::Quad];
+            let mut layout_index = layouts.iter().position(|&l| l == self.settings.layout).unwrap_or(0);
+            if ui.combo_simple_string(tr("viewports.layout"), &mut layout_index, &[tr("viewports.single"), tr("viewports.quad")]) {
+                self.settings.layout = layouts[layout_index];
+                changed = true;
+            }
+
+            ui.separator();
+            ui.text(tr("viewports.debug_view"));
+            let mode_labels: Vec<String> = RenderMode::ALL.iter().map(|mode| named("render_modes", mode.name())).collect();
+            // The single view shows only the perspective viewport
+            let first = if self.settings.layout == ViewportLayout::Quad { 0 } else { 3 };
+            for (i, viewport) in self.settings.viewports.iter_mut().enumerate().skip(first) {
+                let mut mode_index = RenderMode::ALL.iter().position(|&m| m == viewport.render_mode).unwrap_or(0);
+                if ui.combo_simple_string(format!("{}##mode{}", named("view_kinds", viewport.kind.name()), i), &mut mode_index, &mode_labels) {
+                    viewport.render_mode = RenderMode::ALL[mode_index];
+                    changed = true;
+                }
+                let mut srgb = color::linear_to_srgb_rgba(viewport.clear_color);
+                if ui.color_edit4(format!("{}##clear{}", tr("viewports.clear"), i), &mut srgb) {
+                    viewport.clear_color = color::srgb_to_linear_rgba(srgb);
+                    changed = true;
+                }
+                let _id = ui.push_id_usize(i);
+                ui.text_disabled(tr("viewports.layers"));
+                ui.same_line();
+                changed |= layer_toggles(ui, &mut viewport.visible_layers);
+            }
+            if self.settings.viewports.iter().skip(first).any(|viewport| viewport.render_mode.is_heatmap()) {
+                ui.text_disabled(tr("viewports.heatmap_legend"));
+            }
+
+            ui.separator();
+            let grid = &mut self.settings.grid;
+            changed |= ui.checkbox(tr("viewports.grid"), &mut grid.show_grid);
+            ui.same_line();
+            changed |= ui.checkbox(tr("viewports.world_axes"), &mut grid.show_axes);
+            let disabled_token = ui.begin_disabled(!grid.show_grid && !grid.show_axes);
+            changed |= ui.slider_config(tr("viewports.spacing"), 0.1, 10.0)
+                .display_format("%.2f")
+                .flags(imgui::SliderFlags::LOGARITHMIC | imgui::SliderFlags::ALWAYS_CLAMP)
+                .build(&mut grid.spacing);
+            changed |= ui.slider(tr("viewports.fade_distance"), 1.0, config::grid::MAX_FADE_DISTANCE, &mut grid.fade_distance);
+            disabled_token.end();
+
+            if changed {
+                debug!("Viewport settings changed: {:?}", self.settings);
+                self.pending_settings = Some(self.settings);
+            }
+
+            ui.separator();
+            self.render_compare_settings(ui);
+        }
+    }
+
+    /// Render the split comparison section
+    fn render_compare_settings(&mut self, ui: &Ui) {
+        let mut compare = self.split_compare.clone();
+        ui.checkbox(tr("viewports.split_compare"), &mut compare.enabled);
+
+        let disabled_token = ui.begin_disabled(!compare.enabled);
+        let sources = [tr("viewports.last_good"), tr("viewports.variant"), tr("viewports.quality")];
+        let mut source_index = match compare.source {
+            CompareSource::LastGood => 0,
+            CompareSource::Variant(_) => 1,
+            CompareSource::Quality(_) => 2,
+        };
+        if ui.combo_simple_string(tr("viewports.right_side"), &mut source_index, &sources) {
+            compare.source = match source_index {
+                1 => CompareSource::Variant(ShaderVariantKey::sdf_default()),
+                2 => CompareSource::Quality(RaymarchQuality::default()),
+                _ => CompareSource::LastGood,
+            };
+        }
+
+        match compare.source {
+            CompareSource::LastGood => {
+                ui.text_disabled(tr("viewports.last_good_hint"));
+            }
+            CompareSource::Variant(ref mut variant) => {
+                for (define, label) in SDF_FEATURES {
+                    let mut enabled = variant.has_define(define);
+                    if ui.checkbox(format!("{}##compare", named("shading", label)), &mut enabled) {
+                        variant.set_feature(define, enabled);
+                    }
+                }
+            }
+            CompareSource::Quality(ref mut quality) => {
+                let mut index = RaymarchQuality::ALL.iter().position(|q| q == quality).unwrap_or(0);
+                if ui.combo("##CompareQuality", &mut index, &RaymarchQuality::ALL, |q| named("quality", q.name()).into()) {
+                    *quality = RaymarchQuality::ALL[index];
+                }
+            }
+        }
+
+        ui.slider(tr("viewports.divider"), 0.0, 1.0, &mut compare.divider);
+        disabled_token.end();
+
+        if compare != self.split_compare {
+            self.set_split_compare(compare);
+        }
+    }
+
+    /// Store a changed split comparison and queue it for the renderer
+    fn set_split_compare(&mut self, compare: SplitCompare) {
+        debug!("Split comparison changed: {:?}", compare);
+        self.split_compare = compare.clone();
+        self.pending_split_compare = Some(compare);
+    }
+
+    /// Draw the divider of every viewport and let the user drag it
+    fn render_divider(&mut self, ui: &Ui) {
+        let [width, height] = ui.io().display_size;
+        let extent = vk::Extent2D { width: width.max(1.0) as u32, height: height.max(1.0) as u32 };
+        let rects: Vec<vk::Rect2D> = self.settings.rects(extent).into_iter().map(|(rect, _)| rect).collect();
+        let mouse = ui.io().mouse_pos;
+
+        // Start dragging on a click next to a divider that is not covered by a window
+        if self.dragging.is_none() && ui.is_mouse_clicked(MouseButton::Left) && !ui.io().want_capture_mouse {
+            self.dragging = rects.iter().copied().find(|rect| {
+                let divider_x = self.split_compare.split(*rect).1.offset.x as f32;
+                let top = rect.offset.y as f32;
+                (mouse[0] - divider_x).abs() <= DIVIDER_GRAB_DISTANCE
+                    && mouse[1] >= top
+                    && mouse[1] <= top + rect.extent.height as f32
+            });
+        }
+        if let Some(rect) = self.dragging {
+            if ui.is_mouse_down(MouseButton::Left) {
+                let divider = ((mouse[0] - rect.offset.x as f32) / rect.extent.width.max(1) as f32).clamp(0.0, 1.0);
+                if divider != self.split_compare.divider {
+                    let compare = SplitCompare { divider, ..self.split_compare.clone() };
+                    self.set_split_compare(compare);
+                }
+            } else {
+                self.dragging = None;
+            }
+        }
+
+        let draw_list = ui.get_background_draw_list();
+        let color = if self.dragging.is_some() { [1.0, 0.8, 0.3, 1.0] } else { [1.0, 1.0, 1.0, 0.8] };
+        for rect in &rects {
+            let x = self.split_compare.split(*rect).1.offset.x as f32;
+            let top = rect.offset.y as f32;
+            let bottom = top + rect.extent.height as f32;
+            draw_list.add_line([x, top], [x, bottom], color).thickness(2.0).build();
+            draw_list.add_text([x - 52.0, top + 40.0], [1.0, 1.0, 1.0, 0.9], tr("viewports.active"));
+            draw_list.add_text([x + 6.0, top + 40.0], [1.0, 1.0, 1.0, 0.9], compare_source_name(&self.split_compare.source));
+        }
+    }
+
+    /// Outline the quadrants and label them with their view and render mode
+    fn render_quadrant_labels(&self, ui: &Ui) {
+        let [width, height] = ui.io().display_size;
+        let extent = ash::vk::Extent2D { width: width.max(1.0) as u32, height: height.max(1.0) as u32 };
+
+        let draw_list = ui.get_background_draw_list();
+        let border = [0.0, 0.0, 0.0, 0.8];
+        draw_list.add_line([width * 0.5, 0.0], [width * 0.5, height], border).thickness(2.0).build();
+        draw_list.add_line([0.0, height * 0.5], [width, height * 0.5], border).thickness(2.0).build();
+
+        for (rect, viewport) in self.settings.rects(extent) {
+            // Bottom-left corner, clear of the toolbar along the top edge
+            let position = [rect.offset.x as f32 + 6.0, (rect.offset.y + rect.extent.height as i32) as f32 - 20.0];
+            let label = format!("{} | {}", named("view_kinds", viewport.kind.name()), named("render_modes", viewport.render_mode.name()));
+            draw_list.add_text(position, [1.0, 1.0, 1.0, 0.9], label);
+        }
+    }
+}
+
+/// Name of the right side of the split comparison in the current language
+fn compare_source_name(source: &CompareSource) -> String {
+    match source {
+        CompareSource::LastGood => tr("viewports.last_good"),
+        CompareSource::Variant(variant) => tr_args("viewports.variant_source", &[("name", &variant.name())]),
+        CompareSource::Quality(quality) => tr_args("viewports.quality_source", &[("name", &named("quality", quality.name()))]),
+    }
+}
@@ -0,0 +1,595 @@
+//! Image file input and output
+//!
+//! A small PNG encoder for frame exports. Pixel data is stored in
+//! uncompressed deflate blocks, which keeps the encoder dependency-free and
+//! fast at the cost of file size; any PNG reader (and ffmpeg) accepts it.
+//! High dynamic range stills and screenshots are written as uncompressed
+//! OpenEXR scanline files with half float RGBA channels, for the same
+//! reasons, or as flat Radiance `.hdr` files (`HdrFormat`).
+//!
+//! HDR environment maps are read from Radiance `.hdr` (RGBE) files, the
+//! format most HDRI collections offer, with flat and run-length encoded
+//! scanlines.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+use crate::vulkan::tonemap::f16_to_f32;
+
+/// PNG file signature
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// OpenEXR magic number and version 2, single part scanline file
+const EXR_SIGNATURE: [u8; 8] = [0x76, 0x2F, 0x31, 0x01, 2, 0, 0, 0];
+
+/// Half float 1.0
+const HALF_ONE: u16 = 0x3C00;
+
+/// Largest payload of a stored deflate block
+const MAX_STORED_BLOCK: usize = 65_535;
+
+/// Encode 8-bit RGBA pixels as a PNG file
+///
+/// # Arguments
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `rgba` - Rows from top to bottom, 4 bytes per pixel
+///
+/// # Returns
+/// The PNG file contents
+///
+/// # Errors
+/// Returns an error if `rgba` does not hold `width * height` pixels
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let row_bytes = width as usize * 4;
+    if rgba.len() != row_bytes * height as usize {
+        return Err(AppError::Generic(format!(
+            "PNG encoding expected {} bytes for {}x{} RGBA, got {}",
+            row_bytes * height as usize, width, height, rgba.len()
+        )));
+    }
+
+    let mut png = Vec::with_capacity(rgba.len() + rgba.len() / MAX_STORED_BLOCK * 5 + height as usize + 64);
+    png.extend_from_slice(&PNG_SIGNATURE);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    // Each scanline starts with its filter type (0 = none)
+    let mut scanlines = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks_exact(row_bytes.max(1)) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+/// Encode RGBA pixels as PNG and write them to a file
+///
+/// # Errors
+/// Returns an error if encoding or writing fails
+pub fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    let png = encode_png(width, height, rgba)?;
+    fs::write(path, png).context(Subsystem::Io, format!("write {}", path.display()))
+}
+
+/// Encode half float RGBA pixels as an OpenEXR file
+///
+/// # Arguments
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `rgba` - Rows from top to bottom, four IEEE half floats (bit patterns) per pixel
+///
+/// # Returns
+/// The EXR file contents, uncompressed with one scanline per block
+///
+/// # Errors
+/// Returns an error if `rgba` does not hold `width * height` pixels
+pub fn encode_exr(width: u32, height: u32, rgba: &[u16]) -> Result<Vec<u8>> {
+    let pixels = width as usize * height as usize;
+    if rgba.len() != pixels * 4 || width == 0 || height == 0 {
+        return Err(AppError::Generic(format!(
+            "EXR encoding expected {} halves for {}x{} RGBA, got {}",
+            pixels * 4, width, height, rgba.len()
+        )));
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&EXR_SIGNATURE);
+    // Channels are stored in alphabetical order: A, B, G, R
+    let mut channels = Vec::new();
+    for name in [b'A', b'B', b'G', b'R'] {
+        channels.extend_from_slice(&[name, 0]);
+        channels.extend_from_slice(&1i32.to_le_bytes()); // HALF
+        channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        channels.extend_from_slice(&1i32.to_le_bytes()); // xSampling
+        channels.extend_from_slice(&1i32.to_le_bytes()); // ySampling
+    }
+    channels.push(0);
+    write_attribute(&mut header, "channels", "chlist", &channels);
+    write_attribute(&mut header, "compression", "compression", &[0]);
+    let mut window = Vec::with_capacity(16);
+    for value in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(&mut header, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(&mut header, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    header.push(0);
+
+    let row_bytes = width as usize * 4 * 2;
+    let block_size = 8 + row_bytes;
+    let first_block = header.len() + height as usize * 8;
+    let mut exr = Vec::with_capacity(first_block + block_size * height as usize);
+    exr.extend_from_slice(&header);
+    for y in 0..height as usize {
+        exr.extend_from_slice(&((first_block + y * block_size) as u64).to_le_bytes());
+    }
+    for (y, row) in rgba.chunks_exact(width as usize * 4).enumerate() {
+        exr.extend_from_slice(&(y as i32).to_le_bytes());
+        exr.extend_from_slice(&(row_bytes as i32).to_le_bytes());
+        for channel in [3, 2, 1, 0] {
+            for pixel in row.chunks_exact(4) {
+                exr.extend_from_slice(&pixel[channel].to_le_bytes());
+            }
+        }
+    }
+    Ok(exr)
+}
+
+/// Encode half float RGBA pixels as OpenEXR and write them to a file
+///
+/// # Errors
+/// Returns an error if encoding or writing fails
+pub fn write_exr(path: &Path, width: u32, height: u32, rgba: &[u16]) -> Result<()> {
+    let exr = encode_exr(width, height, rgba)?;
+    fs::write(path, exr).context(Subsystem::Io, format!("write {}", path.display()))
+}
+
+/// File format of linear HDR images, written before tonemapping for grading elsewhere
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HdrFormat {
+    /// OpenEXR with half float RGBA channels
+    Exr,
+    /// Radiance RGBE (`.hdr`)
+    Radiance,
+}
+
+impl HdrFormat {
+    /// All formats in display order
+    pub const ALL: [HdrFormat; 2] = [HdrFormat::Exr, HdrFormat::Radiance];
+
+    /// Name shown in the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            HdrFormat::Exr => "OpenEXR",
+            HdrFormat::Radiance => "Radiance HDR",
+        }
+    }
+
+    /// File extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            HdrFormat::Exr => "exr",
+            HdrFormat::Radiance => "hdr",
+        }
+    }
+
+    /// Write half float RGBA pixels, as read back from the HDR scene target, as an opaque image
+    ///
+    /// The alpha of the scene target is not coverage, so EXR alpha is set to 1.
+    ///
+    /// # Arguments
+    /// * `path` - Output file
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `rgba` - Rows from top to bottom, four IEEE half floats (bit patterns) per pixel
+    ///
+    /// # Errors
+    /// Returns an error if the pixel count does not match or the file cannot be written
+    pub fn write(self, path: &Path, width: u32, height: u32, rgba: &[u16]) -> Result<()> {
+        match self {
+            HdrFormat::Exr => {
+                let opaque: Vec<u16> = rgba.chunks_exact(4)
+                    .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], HALF_ONE])
+                    .collect();
+                write_exr(path, width, height, &opaque)
+            }
+            HdrFormat::Radiance => {
+                let rgb: Vec<[f32; 3]> = rgba.chunks_exact(4)
+                    .map(|pixel| [f16_to_f32(pixel[0]), f16_to_f32(pixel[1]), f16_to_f32(pixel[2])])
+                    .collect();
+                write_hdr(path, width, height, &rgb)
+            }
+        }
+    }
+}
+
+/// Encode linear RGB pixels as a Radiance `.hdr` file with flat scanlines
+///
+/// # Arguments
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `rgb` - Rows from top to bottom; negative and non-finite values are written as 0
+///
+/// # Returns
+/// The file contents, readable by `decode_hdr`
+///
+/// # Errors
+/// Returns an error if `rgb` does not hold `width * height` pixels
+pub fn encode_hdr(width: u32, height: u32, rgb: &[[f32; 3]]) -> Result<Vec<u8>> {
+    if rgb.len() != width as usize * height as usize || width == 0 || height == 0 {
+        return Err(AppError::Generic(format!(
+            "Radiance HDR encoding expected {} pixels for {}x{}, got {}",
+            width as usize * height as usize, width, height, rgb.len()
+        )));
+    }
+    let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width);
+    let mut hdr = Vec::with_capacity(header.len() + rgb.len() * 4);
+    hdr.extend_from_slice(header.as_bytes());
+    // The largest channel's mantissa is at least 128, so no pixel reads as a run
+    for &pixel in rgb {
+        hdr.extend_from_slice(&linear_to_rgbe(pixel));
+    }
+    Ok(hdr)
+}
+
+/// Encode linear RGB pixels as a Radiance `.hdr` file and write it
+///
+/// # Errors
+/// Returns an error if encoding or writing fails
+pub fn write_hdr(path: &Path, width: u32, height: u32, rgb: &[[f32; 3]]) -> Result<()> {
+    let hdr = encode_hdr(width, height, rgb)?;
+    fs::write(path, hdr).context(Subsystem::Io, format!("write {}", path.display()))
+}
+
+/// Decoded high dynamic range image
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    /// Linear RGB, rows from top to bottom
+    pub pixels: Vec<[f32; 3]>,
+}
+
+/// Decode a Radiance `.hdr` file
+///
+/// Only the standard `-Y height +X width` orientation and its vertically
+/// flipped `+Y` variant are accepted.
+///
+/// # Arguments
+/// * `bytes` - The file contents
+///
+/// # Errors
+/// Returns an error if the header is missing or malformed, the pixel format
+/// is not RGBE or the pixel data is truncated
+pub fn decode_hdr(bytes: &[u8]) -> Result<HdrImage> {
+    let invalid = |reason: &str| AppError::Generic(format!("Invalid Radiance HDR file: {}", reason));
+
+    let mut lines = HeaderLines { bytes, position: 0 };
+    let magic = lines.next().ok_or_else(|| invalid("empty file"))?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err(invalid("missing #?RADIANCE signature"));
+    }
+    loop {
+        let line = lines.next().ok_or_else(|| invalid("header is not terminated"))?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid(&format!("unsupported pixel format {}", format)));
+            }
+        }
+    }
+
+    let resolution = lines.next().ok_or_else(|| invalid("missing resolution"))?;
+    let (flipped, height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        [y, height, "+X", width] if y == "-Y" || y == "+Y" => (
+            y == "+Y",
+            height.parse::<u32>().map_err(|_| invalid("bad height"))?,
+            width.parse::<u32>().map_err(|_| invalid("bad width"))?,
+        ),
+        _ => return Err(invalid(&format!("unsupported orientation {}", resolution))),
+    };
+    if width == 0 || height == 0 {
+        return Err(invalid("empty image"));
+    }
+
+    let mut data = &bytes[lines.position..];
+    let mut rows: Vec<Vec<[f32; 3]>> = Vec::with_capacity(height as usize);
+    for _ in 0..height {
+        let (scanline, rest) = read_rgbe_scanline(data, width as usize).ok_or_else(|| invalid("truncated pixel data"))?;
+        rows.push(scanline.iter().map(|&rgbe| rgbe_to_linear(rgbe)).collect());
+        data = rest;
+    }
+    if flipped {
+        rows.reverse();
+    }
+
+    Ok(HdrImage { width, height, pixels: rows.concat() })
+}
+
+/// Read and decode a Radiance `.hdr` file
+///
+/// # Errors
+/// Returns an error if the file cannot be read or decoded
+pub fn read_hdr(path: &Path) -> Result<HdrImage> {
+    let bytes = fs::read(path).context(Subsystem::Io, format!("read {}", path.display()))?;
+    decode_hdr(&bytes).context(Subsystem::Io, format!("decode {}", path.display()))
+}
+
+/// Newline separated header lines of a Radiance file
+struct HeaderLines<'a> {
+    bytes: &'a [u8],
+    /// Offset of the first byte after the lines read so far
+    position: usize,
+}
+
+impl<'a> Iterator for HeaderLines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = &self.bytes[self.position..];
+        let end = rest.iter().position(|&byte| byte == b'\n')?;
+        self.position += end + 1;
+        std::str::from_utf8(&rest[..end]).ok().map(str::trim_end)
+    }
+}
+
+/// Read one scanline of RGBE pixels, returning it and the remaining data
+///
+/// New-style scanlines start with `2 2 width_hi width_lo` and store each
+/// channel separately with runs; anything else is flat pixels, where
+/// `1 1 1 n` repeats the previous pixel (old-style run-length encoding).
+fn read_rgbe_scanline(data: &[u8], width: usize) -> Option<(Vec<[u8; 4]>, &[u8])> {
+    let is_rle = (8..0x8000).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && ((data[2] as usize) << 8 | data[3] as usize) == width;
+
+    if is_rle {
+        let mut scanline = vec![[0u8; 4]; width];
+        let mut position = 4;
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = *data.get(position)? as usize;
+                position += 1;
+                if count > 128 {
+                    let run = count - 128;
+                    let value = *data.get(position)?;
+                    position += 1;
+                    for pixel in scanline.get_mut(x..x + run)? {
+                        pixel[channel] = value;
+                    }
+                    x += run;
+                } else {
+                    if count == 0 {
+                        return None;
+                    }
+                    let values = data.get(position..position + count)?;
+                    for (pixel, &value) in scanline.get_mut(x..x + count)?.iter_mut().zip(values) {
+                        pixel[channel] = value;
+                    }
+                    position += count;
+                    x += count;
+                }
+            }
+        }
+        return Some((scanline, &data[position..]));
+    }
+
+    let mut scanline: Vec<[u8; 4]> = Vec::with_capacity(width);
+    let mut position = 0;
+    let mut shift = 0;
+    while scanline.len() < width {
+        let pixel: [u8; 4] = data.get(position..position + 4)?.try_into().ok()?;
+        position += 4;
+        if pixel[..3] == [1, 1, 1] {
+            let previous = *scanline.last()?;
+            let repeat = (pixel[3] as usize) << shift;
+            if scanline.len() + repeat > width {
+                return None;
+            }
+            scanline.resize(scanline.len() + repeat, previous);
+            shift += 8;
+        } else {
+            scanline.push(pixel);
+            shift = 0;
+        }
+    }
+    Some((scanline, &data[position..]))
+}
+
+/// Convert a shared-exponent RGBE pixel to linear RGB
+fn rgbe_to_linear([r, g, b, e]: [u8; 4]) -> [f32; 3] {
+    if e == 0 {
+        return [0.0; 3];
+    }
+    let scale = 2f32.powi(e as i32 - (128 + 8));
+    [r as f32 * scale, g as f32 * scale, b as f32 * scale]
+}
+
+/// Shared exponent encoding of a linear color
+fn linear_to_rgbe(rgb: [f32; 3]) -> [u8; 4] {
+    let [r, g, b] = rgb.map(|c| if c.is_finite() { c.max(0.0) } else { 0.0 });
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0; 4];
+    }
+    // max = mantissa * 2^exponent with the mantissa in [0.5, 1)
+    let exponent = (max.log2().floor() as i32 + 1).min(127);
+    let scale = 2f32.powi(8 - exponent);
+    let mantissa = |c: f32| (c * scale).min(255.0) as u8;
+    [mantissa(r), mantissa(g), mantissa(b), (exponent + 128) as u8]
+}
+
+/// Append an OpenEXR header attribute
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Append a chunk with its length and CRC
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap data in a zlib stream of stored (uncompressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_STORED_BLOCK * 5 + 11);
+    // Deflate with a 32K window, no preset dictionary, fastest level
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// CRC-32 as used by PNG chunks
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Adler-32 checksum of a zlib stream
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65_521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums_match_reference_values() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_png_layout() {
+        let png = encode_png(2, 1, &[255, 0, 0, 255, 0, 255, 0, 255]).unwrap();
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+
+        assert!(encode_png(2, 2, &[0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_exr_layout() {
+        // One row of two pixels: opaque red and half-transparent blue
+        let exr = encode_exr(2, 1, &[0x3C00, 0, 0, 0x3C00, 0, 0, 0x3C00, 0x3800]).unwrap();
+        assert_eq!(&exr[..8], &EXR_SIGNATURE);
+        assert_eq!(&exr[8..28], b"channels\0chlist\0\x49\0\0\0");
+        let header_end = exr.len() - 8 - 8 - 16;
+        assert_eq!(u64::from_le_bytes(exr[header_end..header_end + 8].try_into().unwrap()), header_end as u64 + 8);
+        // y, byte count, then A, B, G and R of both pixels
+        assert_eq!(&exr[exr.len() - 24..exr.len() - 16], &[0, 0, 0, 0, 16, 0, 0, 0]);
+        assert_eq!(&exr[exr.len() - 16..], &[0, 0x3C, 0, 0x38, 0, 0, 0, 0x3C, 0, 0, 0, 0, 0, 0x3C, 0, 0]);
+
+        assert!(encode_exr(2, 2, &[0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_encoded_hdr_decodes() {
+        let pixels = [[0.0, 0.0, 0.0], [1.0, 0.5, 0.25], [100.0, 3.0, 0.0], [-1.0, f32::NAN, 0.75]];
+        let hdr = encode_hdr(2, 2, &pixels).unwrap();
+        let image = decode_hdr(&hdr).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        for (decoded, expected) in image.pixels.iter().zip(pixels) {
+            for channel in 0..3 {
+                let expected = if expected[channel].is_finite() { expected[channel].max(0.0) } else { 0.0 };
+                // RGBE keeps 8 bits of mantissa relative to the brightest channel
+                assert!((decoded[channel] - expected).abs() <= expected.max(1.0) / 128.0, "{:?} != {:?}", decoded, expected);
+            }
+        }
+        assert!(encode_hdr(2, 2, &pixels[..3]).is_err());
+    }
+
+    #[test]
+    fn test_decode_flat_hdr() {
+        let mut file = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n-Y 2 +X 2\n".to_vec();
+        // White, a repeat of the previous pixel, half red, black
+        file.extend_from_slice(&[128, 128, 128, 129, 1, 1, 1, 1, 128, 0, 0, 128, 0, 0, 0, 0]);
+
+        let image = decode_hdr(&file).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels, vec![[1.0; 3], [1.0; 3], [0.5, 0.0, 0.0], [0.0; 3]]);
+
+        assert!(decode_hdr(&file[..file.len() - 1]).is_err());
+        assert!(decode_hdr(b"P6\n2 2\n255\n").is_err());
+    }
+
+    #[test]
+    fn test_decode_run_length_hdr_bottom_up() {
+        let mut file = b"#?RGBE\n\n+Y 2 +X 8\n".to_vec();
+        for exponent in [128, 129] {
+            file.extend_from_slice(&[2, 2, 0, 8]);
+            // Red and green as runs, blue as literals, then the exponent
+            file.extend_from_slice(&[136, 128, 136, 64]);
+            file.extend_from_slice(&[8, 0, 0, 0, 0, 0, 0, 0, 128]);
+            file.extend_from_slice(&[136, exponent]);
+        }
+
+        let image = decode_hdr(&file).unwrap();
+        assert_eq!((image.width, image.height), (8, 2));
+        // The last row in the file is the top of the image
+        assert_eq!(image.pixels[0], [1.0, 0.5, 0.0]);
+        assert_eq!(image.pixels[7], [1.0, 0.5, 1.0]);
+        assert_eq!(image.pixels[8], [0.5, 0.25, 0.0]);
+    }
+}
@@ -0,0 +1,306 @@
+//! Input recording and replay
+//!
+//! `--record FILE` captures the window and device events the app reacts to,
+//! tagged with the index of the frame they arrived before, plus the time step
+//! of every frame. `--replay FILE` plays a recording back: the recorded time
+//! steps replace the real ones and each frame's events are applied before its
+//! update, while live keyboard and mouse input is ignored. Resize races and
+//! fullscreen toggles reproduce frame by frame, which also makes recordings
+//! usable for golden-image tests.
+//!
+//! Replayed keys run their key bindings; text typed into HUD fields is not
+//! replayed, since ImGui needs the raw winit key events for it. Once the
+//! recording ends, input is live again.
+//!
+//! Recordings are TOML files.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::info;
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+
+/// Pixels per line when converting pixel wheel deltas to lines
+const PIXELS_PER_LINE: f32 = 16.0;
+
+/// Recorded form of a winit event the app reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputEvent {
+    /// Window resized to a new inner size
+    Resized { width: u32, height: u32 },
+    /// Window close button pressed
+    CloseRequested,
+    /// Window gained or lost focus
+    Focused { focused: bool },
+    /// Modifier keys changed
+    Modifiers { state: ModifiersState },
+    /// Key pressed or released
+    Key { code: KeyCode, pressed: bool, repeat: bool },
+    /// Cursor moved to a position in the window
+    CursorMoved { x: f32, y: f32 },
+    /// Mouse button pressed or released
+    MouseButton { button: MouseButton, pressed: bool },
+    /// Mouse wheel scrolled, in lines
+    MouseWheel { lines: f32 },
+    /// Raw mouse motion, used for camera look
+    MouseMotion { dx: f32, dy: f32 },
+}
+
+impl InputEvent {
+    /// Convert a window event, None for events that are not recorded
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match event {
+            WindowEvent::Resized(size) => InputEvent::Resized { width: size.width, height: size.height },
+            WindowEvent::CloseRequested => InputEvent::CloseRequested,
+            WindowEvent::Focused(focused) => InputEvent::Focused { focused: *focused },
+            WindowEvent::ModifiersChanged(modifiers) => InputEvent::Modifiers { state: modifiers.state() },
+            WindowEvent::KeyboardInput { event, .. } => {
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return None;
+                };
+                InputEvent::Key { code, pressed: event.state == ElementState::Pressed, repeat: event.repeat }
+            }
+            WindowEvent::CursorMoved { position, .. } => InputEvent::CursorMoved { x: position.x as f32, y: position.y as f32 },
+            WindowEvent::MouseInput { state, button, .. } => {
+                InputEvent::MouseButton { button: *button, pressed: *state == ElementState::Pressed }
+            }
+            WindowEvent::MouseWheel { delta, .. } => InputEvent::MouseWheel {
+                lines: match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                },
+            },
+            _ => return None,
+        })
+    }
+
+    /// Whether the event comes from the user rather than the window system
+    ///
+    /// Live user input is ignored during a replay; resizes and close
+    /// requests still go through.
+    pub fn is_user_input(&self) -> bool {
+        !matches!(self, InputEvent::Resized { .. } | InputEvent::CloseRequested)
+    }
+}
+
+/// Event with the index of the frame it arrived before
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Number of frames updated before the event arrived
+    pub frame: u64,
+    /// The event
+    #[serde(flatten)]
+    pub event: InputEvent,
+}
+
+/// Contents of a recording file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputRecording {
+    /// Window inner size when recording started
+    pub window_size: [u32; 2],
+    /// Time step of every frame in seconds
+    pub frame_deltas: Vec<f32>,
+    /// Events in arrival order
+    pub events: Vec<RecordedEvent>,
+}
+
+impl InputRecording {
+    /// Read a recording file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context(Subsystem::Io, format!("read input recording {}", path.display()))?;
+        toml::from_str(&text)
+            .map_err(|e| AppError::Generic(format!("Invalid input recording {}: {}", path.display(), e)))
+    }
+
+    /// Write the recording file
+    ///
+    /// # Errors
+    /// Returns an error if serialization or writing fails
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string(self)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize input recording: {}", e)))?;
+        fs::write(path, text).context(Subsystem::Io, format!("write input recording {}", path.display()))
+    }
+}
+
+/// Whether input is recorded or replayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputCapture {
+    /// Record to a file on exit
+    Record(PathBuf),
+    /// Replay a file
+    Replay(PathBuf),
+}
+
+impl InputCapture {
+    /// Parse `--record FILE` or `--replay FILE`
+    ///
+    /// # Returns
+    /// `Ok(None)` if neither was given
+    ///
+    /// # Errors
+    /// Returns an error if a file is missing or both were given
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>> {
+        let mut capture = None;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let make: fn(PathBuf) -> Self = match name.as_str() {
+                "--record" => InputCapture::Record,
+                "--replay" => InputCapture::Replay,
+                _ => continue,
+            };
+            let path = inline_value.or_else(|| args.next())
+                .ok_or_else(|| AppError::Generic(format!("{} needs a file", name)))?;
+            if capture.is_some() {
+                return Err(AppError::Generic("--record and --replay can not be combined".to_string()));
+            }
+            capture = Some(make(PathBuf::from(path)));
+        }
+
+        Ok(capture)
+    }
+}
+
+/// Records input while the app runs
+#[derive(Debug)]
+pub struct InputRecorder {
+    /// File written by `save`
+    path: PathBuf,
+    /// Events and frame time steps so far
+    recording: InputRecording,
+}
+
+impl InputRecorder {
+    /// Start recording
+    ///
+    /// # Arguments
+    /// * `path` - File written by `save`
+    /// * `window_size` - Current window inner size
+    pub fn new(path: PathBuf, window_size: [u32; 2]) -> Self {
+        info!("Recording input to {}", path.display());
+        Self {
+            path,
+            recording: InputRecording { window_size, ..InputRecording::default() },
+        }
+    }
+
+    /// Record an event arriving before the next frame
+    pub fn push(&mut self, event: InputEvent) {
+        let frame = self.recording.frame_deltas.len() as u64;
+        self.recording.events.push(RecordedEvent { frame, event });
+    }
+
+    /// Record the time step of a frame about to be updated
+    pub fn push_frame(&mut self, delta_time: f32) {
+        self.recording.frame_deltas.push(delta_time);
+    }
+
+    /// Write the recording to its file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save(&self) -> Result<()> {
+        self.recording.save(&self.path)?;
+        info!(
+            "Input recording saved to {} ({} frames, {} events)",
+            self.path.display(),
+            self.recording.frame_deltas.len(),
+            self.recording.events.len()
+        );
+        Ok(())
+    }
+}
+
+/// Plays a recording back frame by frame
+#[derive(Debug)]
+pub struct InputReplay {
+    /// Recording being played
+    recording: InputRecording,
+    /// Index of the next frame
+    frame: usize,
+    /// Index of the next event
+    next_event: usize,
+}
+
+impl InputReplay {
+    /// Start replaying a recording
+    pub fn new(recording: InputRecording) -> Self {
+        info!("Replaying {} frames of input", recording.frame_deltas.len());
+        Self { recording, frame: 0, next_event: 0 }
+    }
+
+    /// Window inner size when the recording started
+    pub fn window_size(&self) -> [u32; 2] {
+        self.recording.window_size
+    }
+
+    /// Advance to the next frame
+    ///
+    /// # Returns
+    /// The frame's recorded time step and the events to apply before it,
+    /// None once the recording has ended
+    pub fn next_frame(&mut self) -> Option<(f32, Vec<InputEvent>)> {
+        let delta_time = *self.recording.frame_deltas.get(self.frame)?;
+        let events = &self.recording.events[self.next_event..];
+        let count = events.iter().take_while(|recorded| recorded.frame <= self.frame as u64).count();
+        let frame_events = events[..count].iter().map(|recorded| recorded.event).collect();
+        self.next_event += count;
+        self.frame += 1;
+        Some((delta_time, frame_events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(InputCapture::from_args(args(&["--benchmark"])).unwrap(), None);
+        assert_eq!(
+            InputCapture::from_args(args(&["--replay", "bug.toml"])).unwrap(),
+            Some(InputCapture::Replay(PathBuf::from("bug.toml")))
+        );
+        assert!(InputCapture::from_args(args(&["--record"])).is_err());
+        assert!(InputCapture::from_args(args(&["--record=a.toml", "--replay=b.toml"])).is_err());
+    }
+
+    #[test]
+    fn test_recording_replays_by_frame() {
+        let mut recorder = InputRecorder::new(PathBuf::from("unused.toml"), [800, 600]);
+        recorder.push(InputEvent::Resized { width: 1024, height: 768 });
+        recorder.push_frame(0.0);
+        recorder.push_frame(0.016);
+        recorder.push(InputEvent::Key { code: KeyCode::F11, pressed: true, repeat: false });
+        recorder.push(InputEvent::Modifiers { state: ModifiersState::SHIFT });
+        recorder.push_frame(0.017);
+
+        // The file format keeps every event
+        let text = toml::to_string(&recorder.recording).unwrap();
+        let recording: InputRecording = toml::from_str(&text).unwrap();
+        assert_eq!(recording, recorder.recording);
+
+        let mut replay = InputReplay::new(recording);
+        assert_eq!(replay.window_size(), [800, 600]);
+        assert_eq!(replay.next_frame(), Some((0.0, vec![InputEvent::Resized { width: 1024, height: 768 }])));
+        assert_eq!(replay.next_frame(), Some((0.016, Vec::new())));
+        let (delta_time, events) = replay.next_frame().unwrap();
+        assert_eq!(delta_time, 0.017);
+        assert_eq!(events.len(), 2);
+        assert_eq!(replay.next_frame(), None);
+    }
+}
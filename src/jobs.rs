@@ -0,0 +1,355 @@
+//! Background jobs
+//!
+//! `JobPool` is a small pool of worker threads inserted as an ECS resource,
+//! for work too slow for a frame: mesh export, texture decoding, shader
+//! compilation and the like. A job is a closure returning a value, started
+//! with a name shown in the HUD jobs indicator while it is queued or running.
+//!
+//! There are two ways to get the value back. `spawn` returns a `JobHandle`
+//! that the owner polls; `spawn_then` instead queues a completion callback
+//! that the ECS world runs on the main thread at the start of its next
+//! update, with mutable access to the world, its resources and the HUD. A
+//! job that panics reports an error through either path instead of taking
+//! its worker down.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use log::{debug, warn};
+use crate::config;
+use crate::ecs::world::ECSWorld;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+
+/// Work sent to the workers
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Callback run on the main thread after a job ended
+pub type Completion = Box<dyn FnOnce(&mut ECSWorld) + Send>;
+
+/// A queued or running job, as shown in the HUD
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobStatus {
+    /// Unique id of the job within its pool
+    pub id: u64,
+    /// Name given when the job was started
+    pub name: String,
+    /// When a worker picked the job up, None while it is queued
+    pub started: Option<Instant>,
+}
+
+impl JobStatus {
+    /// Time the job has been running, zero while it is queued
+    pub fn elapsed(&self) -> Duration {
+        self.started.map_or(Duration::ZERO, |started| started.elapsed())
+    }
+}
+
+/// State shared between the pool and its workers
+#[derive(Default)]
+struct Shared {
+    /// Queued and running jobs in start order
+    jobs: Mutex<Vec<JobStatus>>,
+    /// Callbacks of ended `spawn_then` jobs, waiting for the main thread
+    completions: Mutex<Vec<Completion>>,
+}
+
+impl Shared {
+    /// Lock the job list; a panic never happens while it is held
+    fn jobs(&self) -> MutexGuard<'_, Vec<JobStatus>> {
+        self.jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Mark a job as picked up by a worker
+    fn start(&self, id: u64) {
+        if let Some(job) = self.jobs().iter_mut().find(|job| job.id == id) {
+            job.started = Some(Instant::now());
+        }
+    }
+
+    /// Remove an ended job from the list
+    fn end(&self, id: u64) {
+        self.jobs().retain(|job| job.id != id);
+    }
+}
+
+/// Pool of worker threads running background jobs
+///
+/// Dropping the pool closes its queue: workers finish their current job and
+/// exit, queued jobs are dropped and their handles end with an error.
+pub struct JobPool {
+    /// Queue of the workers
+    sender: Sender<Task>,
+    /// Job list and pending completions
+    shared: Arc<Shared>,
+    /// Id of the next job
+    next_id: AtomicU64,
+    /// Number of worker threads
+    workers: usize,
+}
+
+impl JobPool {
+    /// Start a pool with `config::jobs::WORKERS` threads, or one less than
+    /// the available cores if that is zero
+    ///
+    /// # Errors
+    /// Returns an error if a worker thread cannot be started
+    pub fn new() -> Result<Self> {
+        let workers = match config::jobs::WORKERS {
+            0 => std::thread::available_parallelism().map_or(1, |cores| cores.get().saturating_sub(1).max(1)),
+            workers => workers,
+        };
+        Self::with_workers(workers)
+    }
+
+    /// Start a pool with the given number of threads
+    ///
+    /// # Arguments
+    /// * `workers` - Number of worker threads, at least one
+    ///
+    /// # Errors
+    /// Returns an error if a worker thread cannot be started
+    pub fn with_workers(workers: usize) -> Result<Self> {
+        let workers = workers.max(1);
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            std::thread::Builder::new()
+                .name(format!("job-{}", index))
+                .spawn(move || Self::work(&receiver))
+                .context(Subsystem::Io, "start job worker thread")?;
+        }
+        debug!("Started {} job workers", workers);
+        Ok(Self { sender, shared: Arc::default(), next_id: AtomicU64::new(0), workers })
+    }
+
+    /// Run tasks until the pool is dropped
+    fn work(receiver: &Mutex<Receiver<Task>>) {
+        loop {
+            // The lock is held only while waiting, so other workers can run their tasks
+            let task = receiver.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).recv();
+            match task {
+                Ok(task) => task(),
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Number of worker threads
+    #[allow(dead_code)]
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Queue a job and return a handle to its result
+    ///
+    /// # Arguments
+    /// * `name` - Name shown in the HUD while the job is queued or running
+    /// * `work` - The job, run on a worker thread
+    pub fn spawn<T, F>(&self, name: impl Into<String>, work: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let name = name.into();
+        let slot = Arc::new(Slot::default());
+        let reply = Reply { slot: Arc::clone(&slot), name: name.clone() };
+        self.submit(name, move |result| reply.send(result), work);
+        JobHandle { slot }
+    }
+
+    /// Queue a job whose result is handed to a callback on the main thread
+    ///
+    /// The callback runs in `ECSWorld::update` after the job ended, also if
+    /// it panicked; it is dropped without running if the pool shuts down first.
+    ///
+    /// # Arguments
+    /// * `name` - Name shown in the HUD while the job is queued or running
+    /// * `work` - The job, run on a worker thread
+    /// * `then` - Callback receiving the job's result and the ECS world
+    pub fn spawn_then<T, F, C>(&self, name: impl Into<String>, work: F, then: C)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+        C: FnOnce(Result<T>, &mut ECSWorld) + Send + 'static,
+    {
+        let shared = Arc::clone(&self.shared);
+        self.submit(name.into(), move |result| {
+            let completion: Completion = Box::new(move |world| then(result, world));
+            shared.completions.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(completion);
+        }, work);
+    }
+
+    /// List a job and send it to the workers
+    ///
+    /// # Arguments
+    /// * `name` - Name of the job
+    /// * `deliver` - Called on the worker with the job's result
+    /// * `work` - The job
+    fn submit<T, F, D>(&self, name: String, deliver: D, work: F)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+        D: FnOnce(Result<T>) + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.shared.jobs().push(JobStatus { id, name: name.clone(), started: None });
+
+        let shared = Arc::clone(&self.shared);
+        let task: Task = Box::new(move || {
+            shared.start(id);
+            let result = panic::catch_unwind(AssertUnwindSafe(work)).map_err(|payload| {
+                let message = panic_message(payload.as_ref());
+                warn!("Job '{}' panicked: {}", name, message);
+                AppError::Generic(format!("Job '{}' panicked: {}", name, message))
+            });
+            deliver(result);
+            shared.end(id);
+        });
+        if self.sender.send(task).is_err() {
+            // Dropping the task drops its reply, which ends the handle with an error
+            self.shared.end(id);
+        }
+    }
+
+    /// Queued and running jobs in start order
+    pub fn active(&self) -> Vec<JobStatus> {
+        self.shared.jobs().clone()
+    }
+
+    /// Take the callbacks of ended `spawn_then` jobs, to be run on the main thread
+    pub fn take_completions(&self) -> Vec<Completion> {
+        std::mem::take(&mut *self.shared.completions.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+/// Text of a panic payload
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Result of a job, filled in by the worker
+struct Slot<T> {
+    result: Mutex<Option<Result<T>>>,
+    ready: Condvar,
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self { result: Mutex::new(None), ready: Condvar::new() }
+    }
+}
+
+impl<T> Slot<T> {
+    fn lock(&self) -> MutexGuard<'_, Option<Result<T>>> {
+        self.result.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Worker side of a `JobHandle`; fills the slot with an error if dropped
+/// before the job ran
+struct Reply<T> {
+    slot: Arc<Slot<T>>,
+    name: String,
+}
+
+impl<T> Reply<T> {
+    fn send(self, result: Result<T>) {
+        *self.slot.lock() = Some(result);
+        self.slot.ready.notify_all();
+    }
+}
+
+impl<T> Drop for Reply<T> {
+    fn drop(&mut self) {
+        let mut result = self.slot.lock();
+        if result.is_none() {
+            *result = Some(Err(AppError::Generic(format!("Job '{}' was dropped before it ran", self.name))));
+            self.slot.ready.notify_all();
+        }
+    }
+}
+
+/// Owner side of a job started with `JobPool::spawn`
+pub struct JobHandle<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Whether the job ended and `finish` will not block
+    pub fn is_finished(&self) -> bool {
+        self.slot.lock().is_some()
+    }
+
+    /// Wait for the job to end
+    ///
+    /// # Errors
+    /// Returns an error if the job panicked or the pool shut down before it ran
+    pub fn finish(self) -> Result<T> {
+        let mut result = self.slot.lock();
+        loop {
+            if let Some(result) = result.take() {
+                return result;
+            }
+            result = self.slot.ready.wait(result).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_returns_the_result() {
+        let pool = JobPool::with_workers(2).unwrap();
+        let handles: Vec<_> = (0..8).map(|i| pool.spawn(format!("square {}", i), move || i * i)).collect();
+        let results: Vec<i32> = handles.into_iter().map(|handle| handle.finish().unwrap()).collect();
+        assert_eq!(results, (0..8).map(|i| i * i).collect::<Vec<_>>());
+
+        // Ended jobs leave the list once their worker is done with them
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !pool.active().is_empty() && Instant::now() < deadline {
+            std::thread::yield_now();
+        }
+        assert!(pool.active().is_empty());
+    }
+
+    #[test]
+    fn test_panicking_job_reports_an_error() {
+        let pool = JobPool::with_workers(1).unwrap();
+        let failed = pool.spawn("broken", || -> u32 { panic!("out of cheese") });
+        let error = failed.finish().unwrap_err().to_string();
+        assert!(error.contains("broken") && error.contains("out of cheese"), "{}", error);
+
+        // The worker survives the panic
+        assert_eq!(pool.spawn("after", || 7).finish().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_active_lists_queued_jobs() {
+        let pool = JobPool::with_workers(1).unwrap();
+        let (release, blocked) = mpsc::channel::<()>();
+        let running = pool.spawn("running", move || blocked.recv().is_ok());
+        let queued = pool.spawn("queued", || ());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while pool.active().first().is_some_and(|job| job.started.is_none()) && Instant::now() < deadline {
+            std::thread::yield_now();
+        }
+        let active = pool.active();
+        assert_eq!(active.iter().map(|job| job.name.as_str()).collect::<Vec<_>>(), ["running", "queued"]);
+        assert!(active[0].started.is_some());
+        assert!(active[1].started.is_none());
+        assert!(!queued.is_finished());
+
+        release.send(()).unwrap();
+        assert!(running.finish().unwrap());
+        queued.finish().unwrap();
+    }
+}
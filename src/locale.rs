@@ -0,0 +1,269 @@
+//! HUD localization
+//!
+//! HUD text is looked up by key (`toolbar.add_sphere`) in the current
+//! locale. A locale is a TOML file in `config::locale::DIR` named by its
+//! language code (`ru.toml`): a display name, the font glyph ranges the
+//! language needs besides Latin (see `hud::fonts`), and nested `strings`
+//! tables whose paths make the keys. English is compiled in from
+//! `locales/en.toml` and fills in the strings a locale lacks; a key missing
+//! from both is shown as it is. Strings may contain `{name}` placeholders,
+//! filled by `tr_args`.
+//!
+//! The current locale is process-wide like the open project, so panels
+//! translate while they render and a switch shows on the next frame.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use log::{debug, warn};
+use serde::Deserialize;
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+
+/// Language code of the built-in locale
+pub const ENGLISH: &str = "en";
+
+/// Locale chosen in the settings, None for English
+static CURRENT: Mutex<Option<Locale>> = Mutex::new(None);
+
+/// Layout of a locale file
+#[derive(Debug, Deserialize)]
+struct LocaleFile {
+    /// Language name in the language itself
+    name: String,
+    #[serde(default)]
+    glyph_ranges: Vec<String>,
+    #[serde(default)]
+    strings: toml::Table,
+}
+
+/// Strings of one language
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    /// Language code, the file stem
+    pub code: String,
+    /// Language name shown in the language list
+    pub name: String,
+    /// Font glyph ranges the strings need, by `GlyphRange` name
+    pub glyph_ranges: Vec<String>,
+    /// Strings by dotted key
+    strings: HashMap<String, String>,
+}
+
+/// Add the strings of a table under a key prefix
+fn flatten(prefix: &str, table: &toml::Table, strings: &mut HashMap<String, String>) {
+    for (name, value) in table {
+        let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match value {
+            toml::Value::String(text) => {
+                strings.insert(key, text.clone());
+            }
+            toml::Value::Table(table) => flatten(&key, table, strings),
+            _ => warn!("Ignoring locale entry {}: not a string", key),
+        }
+    }
+}
+
+impl Locale {
+    /// Parse a locale file
+    ///
+    /// # Arguments
+    /// * `code` - Language code
+    /// * `text` - Contents of the file
+    ///
+    /// # Errors
+    /// Returns an error if the text is not a valid locale
+    pub fn parse(code: &str, text: &str) -> Result<Self> {
+        let file: LocaleFile = toml::from_str(text)
+            .map_err(|e| AppError::Generic(format!("Invalid locale {}: {}", code, e)))?;
+        let mut strings = HashMap::new();
+        flatten("", &file.strings, &mut strings);
+        Ok(Self { code: code.to_string(), name: file.name, glyph_ranges: file.glyph_ranges, strings })
+    }
+
+    /// Read the locale of a language from `config::locale::DIR`
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(code: &str) -> Result<Self> {
+        let path = Path::new(config::locale::DIR).join(format!("{}.toml", code));
+        let text = fs::read_to_string(&path).context(Subsystem::Io, format!("read locale {}", path.display()))?;
+        Self::parse(code, &text)
+    }
+
+    /// The built-in English locale
+    pub fn english() -> &'static Locale {
+        static ENGLISH_LOCALE: OnceLock<Locale> = OnceLock::new();
+        ENGLISH_LOCALE.get_or_init(|| {
+            Locale::parse(ENGLISH, include_str!("../locales/en.toml")).expect("the built-in English locale is valid")
+        })
+    }
+
+    /// A string of this locale
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+}
+
+/// Language codes and names of the available locales, English first
+///
+/// Files that cannot be read are logged and left out.
+pub fn available() -> Vec<(String, String)> {
+    let mut locales = vec![(ENGLISH.to_string(), Locale::english().name.clone())];
+    let Ok(entries) = fs::read_dir(config::locale::DIR) else {
+        return locales;
+    };
+    let mut others: Vec<(String, String)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .filter(|code| code != ENGLISH)
+        .filter_map(|code| match Locale::load(&code) {
+            Ok(locale) => Some((code, locale.name)),
+            Err(e) => {
+                warn!("Ignoring locale {}: {}", code, e);
+                None
+            }
+        })
+        .collect();
+    others.sort();
+    locales.extend(others);
+    locales
+}
+
+/// Switch the HUD language
+///
+/// # Returns
+/// The locale now in use
+///
+/// # Errors
+/// Returns an error if the locale cannot be loaded; the current one is kept then
+pub fn set_language(code: &str) -> Result<Locale> {
+    let locale = match code {
+        ENGLISH => Locale::english().clone(),
+        _ => Locale::load(code)?,
+    };
+    debug!("HUD language {} ({})", locale.name, locale.code);
+    *CURRENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = (code != ENGLISH).then(|| locale.clone());
+    Ok(locale)
+}
+
+/// A string of the current locale, or of English when the locale lacks it
+fn lookup(key: &str) -> Option<String> {
+    let current = CURRENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    current
+        .as_ref()
+        .and_then(|locale| locale.get(key))
+        .or_else(|| Locale::english().get(key))
+        .map(str::to_string)
+}
+
+/// Translate a key in the current locale, falling back to English and then the key
+pub fn tr(key: &str) -> String {
+    lookup(key).unwrap_or_else(|| key.to_string())
+}
+
+/// Translate a key and fill its `{name}` placeholders
+pub fn tr_args(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = tr(key);
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+/// Translated window title with a fixed ImGui id, so the window keeps its
+/// position and docking when the language changes
+pub fn title(key: &str, id: &str) -> String {
+    format!("{}###{}", tr(key), id)
+}
+
+/// Translate an English name the code knows things by
+///
+/// Panel titles, action names and option names stay English where they are
+/// keys (the saved layout, key bindings, settings files); their translation
+/// is `<section>.<name>` with the words of the name in snake case
+/// (`panels.render_settings`, `texture_channels.depth_linear`), and the name
+/// itself when there is none.
+pub fn named(section: &str, name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    lookup(&format!("{}.{}", section, words.join("_"))).unwrap_or_else(|| name.to_string())
+}
+
+/// Translated name of a panel, see `named`
+pub fn panel_name(title: &str) -> String {
+    named("panels", title)
+}
+
+/// Window title of a panel, its translated name with the English title as id
+pub fn panel_title(title: &str) -> String {
+    format!("{}###{}", panel_name(title), title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_tables_make_keys() {
+        let text = r#"
+            name = "Test"
+            glyph_ranges = ["Cyrillic"]
+            [strings]
+            greeting = "Hi {name}"
+            [strings.toolbar]
+            add_sphere = "Kugel"
+            broken = 3
+        "#;
+        let locale = Locale::parse("xx", text).unwrap();
+        assert_eq!(locale.get("toolbar.add_sphere"), Some("Kugel"));
+        assert_eq!(locale.get("greeting"), Some("Hi {name}"));
+        assert_eq!(locale.get("toolbar.broken"), None);
+        assert_eq!(locale.glyph_ranges, ["Cyrillic"]);
+        assert!(Locale::parse("xx", "strings = 1").is_err());
+    }
+
+    #[test]
+    fn test_english_is_built_in_and_the_fallback() {
+        let english = Locale::english();
+        assert_eq!(english.code, ENGLISH);
+        assert!(english.glyph_ranges.is_empty());
+        assert_eq!(tr("toolbar.add_sphere"), english.get("toolbar.add_sphere").unwrap());
+        assert_eq!(tr("no.such.key"), "no.such.key");
+        assert_eq!(tr_args("toasts.opened_project", &[("name", &"demo")]), "Opened project demo");
+        assert_eq!(title("panels.settings", "Settings"), format!("{}###Settings", tr("panels.settings")));
+        assert_eq!(panel_name("Render Settings"), tr("panels.render_settings"));
+        assert_eq!(panel_title("No Such Panel"), "No Such Panel###No Such Panel");
+        assert_eq!(available()[0].0, ENGLISH);
+    }
+
+    /// Names of the `{name}` placeholders in a string
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text.split('{').skip(1).filter_map(|rest| rest.split_once('}')).map(|(name, _)| name).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_locale_files_match_english() {
+        let english = Locale::english();
+        for (code, _) in available().into_iter().skip(1) {
+            let locale = Locale::load(&code).unwrap();
+            for range in &locale.glyph_ranges {
+                assert!(crate::hud::fonts::GlyphRange::from_name(range).is_some(), "{}: unknown glyph range {}", code, range);
+            }
+            for (key, text) in &locale.strings {
+                let Some(english_text) = english.get(key) else {
+                    panic!("{}: {} is not an English key", code, key);
+                };
+                assert_eq!(placeholders(text), placeholders(english_text), "{}: placeholders of {}", code, key);
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Extra log outputs
+//!
+//! `debug::init_logging` chains these next to stdout: a log file that is
+//! rotated once it grows past a size limit, and an in-memory ring buffer of
+//! recent records that the HUD console window reads from.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock, TryLockError};
+use log::{Level, Log, Metadata, Record};
+use crate::config;
+
+/// One captured log record
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// Local time the record was logged, `HH:MM:SS.mmm`
+    pub timestamp: String,
+    /// Severity
+    pub level: Level,
+    /// Module the record came from
+    pub target: String,
+    /// Formatted message
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Check whether the target or message contains `needle` (case-insensitive)
+    ///
+    /// `needle` must already be lowercase.
+    pub fn matches(&self, needle: &str) -> bool {
+        needle.is_empty()
+            || self.message.to_lowercase().contains(needle)
+            || self.target.to_lowercase().contains(needle)
+    }
+}
+
+/// Ring buffer of the most recent log records
+#[derive(Debug)]
+pub struct LogBuffer {
+    /// Records, oldest first
+    entries: Mutex<VecDeque<LogEntry>>,
+    /// Maximum number of records kept
+    capacity: usize,
+}
+
+impl LogBuffer {
+    /// Create an empty buffer
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Append a record, dropping the oldest when full
+    pub fn push(&self, entry: LogEntry) {
+        let mut entries = self.entries();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Lock the records for reading, oldest first
+    pub fn entries(&self) -> MutexGuard<'_, VecDeque<LogEntry>> {
+        // A panic while logging must not take the console down with it
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Copy the newest `count` records without waiting for the lock
+    ///
+    /// Used by the crash handler, which may run while the crashing thread
+    /// holds the lock; returns None instead of deadlocking.
+    pub fn try_recent(&self, count: usize) -> Option<Vec<LogEntry>> {
+        let entries = match self.entries.try_lock() {
+            Ok(entries) => entries,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        Some(entries.iter().skip(entries.len().saturating_sub(count)).cloned().collect())
+    }
+
+    /// Remove every record
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+}
+
+/// Process-wide buffer behind the HUD console
+pub fn console_log() -> &'static LogBuffer {
+    static CONSOLE_LOG: OnceLock<LogBuffer> = OnceLock::new();
+    CONSOLE_LOG.get_or_init(|| LogBuffer::new(config::debug::CONSOLE_CAPACITY))
+}
+
+/// `log` sink that appends records to the console buffer
+pub struct ConsoleSink;
+
+impl Log for ConsoleSink {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        console_log().push(LogEntry {
+            timestamp: chrono::Local::now().format("%H:%M:%S%.3f").to_string(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Log file that is rotated once it reaches a size limit
+///
+/// On rotation `name.log` becomes `name.log.1`, `name.log.1` becomes
+/// `name.log.2` and so on; the oldest file beyond `max_rotations` is deleted.
+pub struct RotatingFile {
+    /// Path of the active log file
+    path: PathBuf,
+    /// Size at which the file is rotated
+    max_bytes: u64,
+    /// Number of rotated files kept
+    max_rotations: usize,
+    /// The active file
+    file: File,
+    /// Bytes in the active file
+    written: u64,
+}
+
+impl RotatingFile {
+    /// Open (appending to) or create the log file and its directory
+    ///
+    /// # Errors
+    /// Returns an error if the directory or file cannot be created
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64, max_rotations: usize) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { path, max_bytes, max_rotations, file, written })
+    }
+
+    /// Path of the `index`-th rotated file
+    fn rotated_path(path: &Path, index: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// Shift the rotated files up by one and start a new active file
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_rotations == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = fs::remove_file(Self::rotated_path(&self.path, self.max_rotations));
+            for index in (1..self.max_rotations).rev() {
+                let from = Self::rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, Self::rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, Self::rotated_path(&self.path, 1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: String::new(),
+            level: Level::Info,
+            target: "vulkan_app".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_log_buffer_drops_oldest() {
+        let buffer = LogBuffer::new(2);
+        buffer.push(entry("one"));
+        buffer.push(entry("two"));
+        buffer.push(entry("Three"));
+
+        let messages: Vec<_> = buffer.entries().iter().map(|e| e.message.clone()).collect();
+        assert_eq!(messages, ["two", "Three"]);
+        assert!(buffer.entries()[1].matches("three"));
+        assert!(!buffer.entries()[0].matches("three"));
+    }
+
+    #[test]
+    fn test_rotating_file_keeps_limited_history() {
+        let dir = std::env::temp_dir().join(format!("vulkan-app-log-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("app.log");
+
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(fs::read_to_string(RotatingFile::rotated_path(&path, 1)).unwrap(), "cccccccc\n");
+        assert_eq!(fs::read_to_string(RotatingFile::rotated_path(&path, 2)).unwrap(), "bbbbbbbb\n");
+        assert!(!RotatingFile::rotated_path(&path, 3).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -2,35 +2,172 @@ mod vulkan;
 mod ecs;
 mod error;
 mod config;
+mod color;
+mod noise;
 mod debug;
 mod camera;
 mod hud;
+mod environment;
+mod image_io;
+mod log_sinks;
+mod profiling;
+mod platform;
+mod file_watcher;
+mod asset_watcher;
+mod scripting;
+mod actions;
+mod settings;
+mod project;
+mod locale;
+mod shader_params;
+mod autosave;
+mod renderer;
+mod benchmark;
+mod input_recording;
+mod frame_stats;
+mod monitor;
+mod jobs;
+mod assets;
 
-use winit::event::WindowEvent;
-use winit::event_loop::{EventLoop, ActiveEventLoop};
-use winit::keyboard::{Key, NamedKey};
-use winit::window::{WindowAttributes, Window};
+use winit::event::{DeviceEvent, DeviceId, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop, ActiveEventLoop};
+use winit::keyboard::ModifiersState;
+use winit::window::{CursorGrabMode, Fullscreen, WindowAttributes, Window};
 use winit::application::ApplicationHandler;
 use vulkan::VulkanRenderer;
+use vulkan::gpu_selection::GpuSelector;
+use vulkan::frame_export::ExportSettings;
+use vulkan::fullscreen_pass;
 use ecs::ECSWorld;
-use error::Result;
-use log::{info, error, debug};
+use actions::{Action, ActionRegistry};
+use settings::{AppSettings, FrameLimiter, SettingsChanges};
+use project::{Project, RecentProjects};
+use benchmark::{BenchmarkRun, BenchmarkSettings};
+use input_recording::{InputCapture, InputEvent, InputRecorder, InputRecording, InputReplay};
+use monitor::MonitorInfo;
+use error::{Result, Subsystem};
+use log::{info, error, debug, warn};
 
 
+/// Where the app is in the window lifecycle driven by `resumed` and `suspended`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lifecycle {
+    /// Nothing is created until the first `resumed`
+    Uninitialized,
+    /// Window, renderer and ECS world exist and frames are drawn
+    Running,
+    /// The surface was released by `suspended`, updates and redraws wait for `resumed`
+    Suspended,
+    /// Initialization failed and the event loop is exiting
+    Failed,
+}
+
 struct AppState {
     window: Option<Window>,
     vulkan_renderer: Option<VulkanRenderer>,
     ecs_world: Option<ECSWorld>,
     is_fullscreen: bool,
+    /// Manual fullscreen only: the next resize finishes a toggle
     fullscreen_pending: bool,
     toggle_fullscreen_flag: bool,
+    /// Manual fullscreen only: window state restored on exit
     original_window_size: winit::dpi::PhysicalSize<u32>,
     original_window_position: winit::dpi::PhysicalPosition<i32>,
     original_decorations: bool,
+    /// Connected monitors, listed in the Settings window
+    monitors: Vec<MonitorInfo>,
+    /// Monitor covered by windowed fullscreen, to notice the window moving to another one
+    fullscreen_monitor: Option<MonitorInfo>,
     is_shutting_down: bool,
+    gpu_override: Option<GpuSelector>,
+    modifiers: ModifiersState,
+    /// Export requested with `--export`, started once the world exists
+    cli_export: Option<ExportSettings>,
+    /// Quit when the `--export` run is done
+    exit_after_export: bool,
+    /// Time of the previous ECS update, for the frame delta
+    last_update: Option<std::time::Instant>,
+    /// Cursor locked and hidden for camera look while the right mouse button is held
+    mouse_captured: bool,
+    /// Key bindings, shared with the HUD command palette
+    actions: std::sync::Arc<ActionRegistry>,
+    /// Runtime settings currently applied
+    settings: AppSettings,
+    /// Paces redraws to the frame rate cap from the settings
+    frame_limiter: FrameLimiter,
+    /// Benchmark requested with `--benchmark`, the app exits when it is done
+    benchmark: Option<BenchmarkRun>,
+    /// `--record` or `--replay` from the command line, started with the window
+    input_capture: Option<InputCapture>,
+    /// Input being recorded with `--record`, saved on shutdown
+    input_recorder: Option<InputRecorder>,
+    /// Input being replayed with `--replay`, live input is ignored meanwhile
+    input_replay: Option<InputReplay>,
+    /// Fragment shader or toy manifest drawn instead of the scene with `--toy`, started once the world exists
+    toy: Option<std::path::PathBuf>,
+    /// Render to an OpenXR headset as well as the window, with `--xr`
+    xr: bool,
+    /// Projects opened recently, listed in the Project panel
+    recent_projects: RecentProjects,
+    /// Lifecycle state, so repeated `resumed` and `suspended` events are safe
+    lifecycle: Lifecycle,
+    /// Marks the session as running for crash recovery, ended on a clean shutdown
+    autosave_session: Option<autosave::Session>,
 }
 
 impl AppState {
+    /// Create the renderer, on the headset's GPU when `--xr` finds one
+    fn create_renderer(&self, window: &Window) -> Result<VulkanRenderer> {
+        if self.xr {
+            #[cfg(feature = "xr")]
+            match vulkan::xr::XrRuntime::new() {
+                Ok(runtime) => return VulkanRenderer::new_xr(window, self.settings.present_mode, runtime),
+                Err(e) => warn!("No OpenXR headset, rendering to the window only: {}", e.report()),
+            }
+            #[cfg(not(feature = "xr"))]
+            warn!("--xr needs a build with the xr feature (cargo run --features xr -- --xr), rendering to the window only");
+        }
+        VulkanRenderer::new(window, self.gpu_override.as_ref(), self.settings.present_mode)
+    }
+
+    /// Stop rendering, release GPU resources in order and exit the event loop
+    fn shutdown(&mut self, event_loop: &ActiveEventLoop) {
+        // Set shutdown flag to stop rendering
+        self.is_shutting_down = true;
+        
+        // Wait for current frame to complete before cleanup
+        if let Some(ref mut ecs_world) = self.ecs_world {
+            info!("Waiting for current frame to complete before cleanup");
+            if let Err(e) = ecs_world.wait_for_gpu_idle() {
+                error!("Failed to wait for GPU idle during shutdown: {}", e);
+            }
+            
+            info!("GPU idle confirmed, cleaning up HUD system");
+            ecs_world.cleanup_hud();
+
+            // Clean up hot reload manager to break reference cycles
+            info!("Cleaning up hot reload manager");
+            ecs_world.cleanup_hot_reload();
+            
+            // The export target holds GPU resources of the renderer
+            ecs_world.cancel_export();
+        }
+        
+        if let Some(recorder) = self.input_recorder.take() {
+            if let Err(e) = recorder.save() {
+                error!("Failed to save the input recording: {}", e);
+            }
+        }
+        
+        // The auto-save is only kept after a crash
+        if let Some(session) = self.autosave_session.take() {
+            session.end();
+        }
+          
+        info!("Graceful shutdown completed, exiting");
+        event_loop.exit();
+    }
+    
     /// Enter windowed fullscreen mode (borderless window covering the entire screen)
     fn enter_windowed_fullscreen(&mut self, window: &Window) {
         if self.is_fullscreen {
@@ -38,6 +175,19 @@ impl AppState {
             return; // Already in fullscreen
         }
         
+        if config::windowed_fullscreen::WINIT_BORDERLESS {
+            // winit restores the window on exit, and the resize it causes is an ordinary one
+            self.refresh_monitors(window);
+            let target = self.fullscreen_target(window);
+            info!("Entering borderless fullscreen on {}", target.as_ref().map_or_else(|| "the current monitor".to_string(), MonitorInfo::label));
+            self.is_fullscreen = true;
+            match target {
+                Some(monitor) => self.cover_monitor(window, monitor),
+                None => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+            }
+            return;
+        }
+        
         // Store current window state
         self.original_window_size = window.inner_size();
         self.original_window_position = window.outer_position().unwrap_or_else(|_| {
@@ -50,16 +200,9 @@ impl AppState {
                self.original_window_position.x, self.original_window_position.y,
                self.original_decorations);
         
-        // Get the current monitor's dimensions
-        let monitor = window.current_monitor();
-        let monitor_size = if let Some(ref monitor) = monitor {
-            monitor.size()
-        } else {
-            // Fallback to primary monitor
-            winit::dpi::PhysicalSize::new(1920, 1080)
-        };
-        
-        info!("Entering windowed fullscreen: {}x{}", monitor_size.width, monitor_size.height);
+        // Cover the monitor chosen in the settings, or the one the window is on
+        self.refresh_monitors(window);
+        let target = self.fullscreen_target(window);
         
         // Set the pending flag BEFORE making window changes to prevent race conditions
         self.fullscreen_pending = true;
@@ -68,21 +211,139 @@ impl AppState {
         // Remove decorations first
         window.set_decorations(false);
         
-        // Position window at top-left of monitor before resizing
-        if let Some(ref monitor) = monitor {
-            let monitor_pos = monitor.position();
-            window.set_outer_position(winit::dpi::PhysicalPosition::new(
-                monitor_pos.x + config::windowed_fullscreen::SCREEN_EDGE_OFFSET as i32,
-                monitor_pos.y + config::windowed_fullscreen::SCREEN_EDGE_OFFSET as i32
-            ));
+        match target {
+            Some(monitor) => {
+                info!("Entering windowed fullscreen on {}", monitor.label());
+                self.cover_monitor(window, monitor);
+            }
+            None => {
+                // No monitor information: assume a common resolution
+                info!("Entering windowed fullscreen without monitor information");
+                let _ = window.request_inner_size(winit::dpi::PhysicalSize::new(1920, 1080));
+            }
         }
         
-        // Resize to cover entire monitor
-        let _ = window.request_inner_size(monitor_size);
-        
         debug!("Windowed fullscreen entry initiated");
     }
     
+    /// Make a fullscreen window cover a monitor
+    fn cover_monitor(&mut self, window: &Window, monitor: MonitorInfo) {
+        if config::windowed_fullscreen::WINIT_BORDERLESS {
+            let handle = Self::monitor_handle(window, &monitor);
+            window.set_fullscreen(Some(Fullscreen::Borderless(handle)));
+            self.fullscreen_monitor = Some(monitor);
+            return;
+        }
+        
+        let offset = config::windowed_fullscreen::SCREEN_EDGE_OFFSET as i32;
+        // Position window at top-left of monitor before resizing
+        window.set_outer_position(winit::dpi::PhysicalPosition::new(
+            monitor.position[0] + offset,
+            monitor.position[1] + offset
+        ));
+        // The resize event that follows recreates the swapchain
+        let _ = window.request_inner_size(winit::dpi::PhysicalSize::new(monitor.size[0], monitor.size[1]));
+        self.fullscreen_monitor = Some(monitor);
+    }
+    
+    /// Monitor windowed fullscreen should cover
+    ///
+    /// The one chosen in the settings if it is connected, otherwise the one
+    /// the window is on.
+    fn fullscreen_target(&self, window: &Window) -> Option<MonitorInfo> {
+        let current = Self::current_monitor(window);
+        let current_name = current.as_ref().map(|monitor| monitor.name.as_str());
+        monitor::choose(&self.monitors, self.settings.fullscreen_monitor.as_deref(), current_name)
+            .map(|index| self.monitors[index].clone())
+            .or(current)
+    }
+    
+    /// The monitor the window is on, named as in the monitor list
+    fn current_monitor(window: &Window) -> Option<MonitorInfo> {
+        let current = window.current_monitor()?;
+        let index = window.available_monitors().position(|handle| handle == current).unwrap_or(0);
+        Some(MonitorInfo::new(&current, index))
+    }
+    
+    /// Handle of a listed monitor, None if it is not connected anymore
+    fn monitor_handle(window: &Window, monitor: &MonitorInfo) -> Option<winit::monitor::MonitorHandle> {
+        window.available_monitors()
+            .enumerate()
+            .find(|(index, handle)| MonitorInfo::new(handle, *index).name == monitor.name)
+            .map(|(_, handle)| handle)
+    }
+    
+    /// List the connected monitors again and show them in the Settings window
+    fn refresh_monitors(&mut self, window: &Window) {
+        self.monitors = window.available_monitors()
+            .enumerate()
+            .map(|(index, handle)| MonitorInfo::new(&handle, index))
+            .collect();
+        if let Some(ref mut ecs_world) = self.ecs_world {
+            ecs_world.show_monitors(self.monitors.clone());
+        }
+    }
+    
+    /// Cover the monitor a fullscreen window ended up on
+    ///
+    /// The window may have been moved to another monitor by the system
+    /// (e.g. Win+Shift+Arrow) or had its monitor unplugged. The size and
+    /// position restored on exit move along, scaled for the new monitor's DPI.
+    fn follow_monitor_change(&mut self, window: &Window) {
+        let (Some(current), Some(covered)) = (Self::current_monitor(window), self.fullscreen_monitor.as_ref()) else {
+            return;
+        };
+        if current.name == covered.name {
+            return;
+        }
+        info!("Fullscreen window moved from {} to {}", covered.name, current.name);
+        if config::windowed_fullscreen::WINIT_BORDERLESS {
+            // winit keeps it covering the new monitor and restores its own size on exit
+            self.fullscreen_monitor = Some(current);
+            return;
+        }
+        let position = [self.original_window_position.x, self.original_window_position.y];
+        let size = [self.original_window_size.width, self.original_window_size.height];
+        let (position, size) = monitor::carry_over(position, size, covered, &current);
+        self.original_window_position = winit::dpi::PhysicalPosition::new(position[0], position[1]);
+        self.original_window_size = winit::dpi::PhysicalSize::new(size[0], size[1]);
+        self.refresh_monitors(window);
+        self.cover_monitor(window, current);
+    }
+    
+    /// Move a fullscreen window onto the monitor chosen in the settings
+    fn retarget_fullscreen(&mut self, window: &Window) {
+        self.refresh_monitors(window);
+        let Some(target) = self.fullscreen_target(window) else {
+            return;
+        };
+        if self.fullscreen_monitor.as_ref().is_some_and(|covered| covered.name == target.name) {
+            return;
+        }
+        info!("Moving windowed fullscreen to {}", target.label());
+        self.cover_monitor(window, target);
+    }
+    
+    /// Apply settings from the Settings window
+    fn apply_settings(&mut self, settings: AppSettings) {
+        if let Some(ref ecs_world) = self.ecs_world {
+            self.settings.hot_reload.enabled = ecs_world.is_hot_reload_enabled();
+        }
+        let changes = settings.changes(&self.settings);
+        if let Some(ref mut ecs_world) = self.ecs_world {
+            ecs_world.apply_settings(&settings, changes);
+        }
+        self.frame_limiter.set_limit(settings.frame_limit);
+        self.settings = settings;
+        
+        if changes.fullscreen && self.is_fullscreen {
+            if let Some(window) = self.window.take() {
+                self.retarget_fullscreen(&window);
+                self.window = Some(window);
+            }
+        }
+    }
+    
     /// Exit windowed fullscreen mode and restore original window state
     fn exit_windowed_fullscreen(&mut self, window: &Window) {
         if !self.is_fullscreen {
@@ -90,6 +351,14 @@ impl AppState {
             return; // Not in fullscreen
         }
         
+        if config::windowed_fullscreen::WINIT_BORDERLESS {
+            info!("Exiting borderless fullscreen");
+            self.is_fullscreen = false;
+            self.fullscreen_monitor = None;
+            window.set_fullscreen(None);
+            return;
+        }
+        
         info!("Exiting windowed fullscreen: {}x{} at ({}, {})",
               self.original_window_size.width, self.original_window_size.height,
               self.original_window_position.x, self.original_window_position.y);
@@ -97,6 +366,7 @@ impl AppState {
         // Set the pending flag BEFORE making window changes to prevent race conditions
         self.fullscreen_pending = true;
         self.is_fullscreen = false;
+        self.fullscreen_monitor = None;
         
         // Restore decorations first
         window.set_decorations(self.original_decorations);
@@ -110,8 +380,41 @@ impl AppState {
         debug!("Windowed fullscreen exit initiated");
     }
     
+    /// Lock and hide the cursor for camera look, or release it
+    fn set_mouse_capture(&mut self, captured: bool) {
+        if self.mouse_captured == captured {
+            return;
+        }
+        let Some(ref window) = self.window else {
+            return;
+        };
+        
+        if captured {
+            // Not every platform can lock the cursor; confining it still delivers raw motion
+            let grab = window.set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+            if let Err(e) = grab {
+                warn!("Failed to grab the cursor for camera look: {}", e);
+            }
+            window.set_cursor_visible(false);
+            debug!("Mouse captured for camera look");
+        } else {
+            if let Err(e) = window.set_cursor_grab(CursorGrabMode::None) {
+                warn!("Failed to release the cursor: {}", e);
+            }
+            window.set_cursor_visible(true);
+            debug!("Mouse released");
+        }
+        self.mouse_captured = captured;
+    }
+    
     /// Toggle windowed fullscreen mode
     fn toggle_windowed_fullscreen(&mut self, window: &Window) {
+        // The system can end borderless fullscreen on its own
+        if config::windowed_fullscreen::WINIT_BORDERLESS && self.is_fullscreen && window.fullscreen().is_none() {
+            self.is_fullscreen = false;
+            self.fullscreen_monitor = None;
+        }
         debug!("Toggling windowed fullscreen, current state: {}", self.is_fullscreen);
         if self.is_fullscreen {
             self.exit_windowed_fullscreen(window);
@@ -119,20 +422,264 @@ impl AppState {
             self.enter_windowed_fullscreen(window);
         }
     }
-}
+    
+    /// Apply a keyboard or mouse event, live or replayed
+    fn handle_input(&mut self, input: InputEvent) {
+        // The right mouse button captures the mouse for camera look unless ImGui is using it
+        if let InputEvent::MouseButton { button: winit::event::MouseButton::Right, pressed } = input {
+            if !pressed && self.mouse_captured {
+                self.set_mouse_capture(false);
+                return;
+            }
+            if pressed && !self.ecs_world.as_ref().is_some_and(|ecs_world| ecs_world.hud_wants_mouse()) {
+                self.set_mouse_capture(true);
+                return;
+            }
+        }
+        
+        // Mouse state for ImGui, which gets none while the camera has the mouse
+        if let Some(hud) = self.ecs_world.as_mut().and_then(|ecs_world| ecs_world.hud.as_mut()) {
+            if !self.mouse_captured {
+                let io = hud.context_mut();
+                match input {
+                    InputEvent::CursorMoved { x, y } => io.mouse_pos = [x, y],
+                    InputEvent::MouseButton { button, pressed } => {
+                        let index = match button {
+                            winit::event::MouseButton::Left => Some(0),
+                            winit::event::MouseButton::Right => Some(1),
+                            winit::event::MouseButton::Middle => Some(2),
+                            _ => None,
+                        };
+                        if let Some(index) = index {
+                            io.mouse_down[index] = pressed;
+                        }
+                    }
+                    InputEvent::MouseWheel { lines } => io.mouse_wheel = lines,
+                    _ => {}
+                }
+            }
+        }
+        
+        // The toy and SDF shaders see the cursor and the buttons like shadertoy's iMouse
+        if let (Some(ecs_world), Some(window)) = (self.ecs_world.as_mut(), self.window.as_ref()) {
+            let size = window.inner_size();
+            match input {
+                InputEvent::CursorMoved { x, y } => {
+                    let position = [x / size.width.max(1) as f32, y / size.height.max(1) as f32];
+                    ecs_world.update_mouse(|mouse| mouse.move_to(position));
+                }
+                InputEvent::MouseButton { button, pressed } if !pressed || !ecs_world.hud_wants_mouse() => {
+                    let bit = match button {
+                        winit::event::MouseButton::Left => Some(fullscreen_pass::MOUSE_LEFT),
+                        winit::event::MouseButton::Right => Some(fullscreen_pass::MOUSE_RIGHT),
+                        winit::event::MouseButton::Middle => Some(fullscreen_pass::MOUSE_MIDDLE),
+                        _ => None,
+                    };
+                    if let Some(bit) = bit {
+                        ecs_world.update_mouse(|mouse| mouse.set_button(bit, pressed));
+                    }
+                    // A left click on the scene selects what is under the cursor
+                    if pressed && button == winit::event::MouseButton::Left {
+                        ecs_world.pick_at_cursor();
+                    }
+                }
+                _ => {}
+            }
+        }
+        
+        match input {
+            InputEvent::Focused { focused: false } => self.set_mouse_capture(false),
+            InputEvent::Modifiers { state } => self.modifiers = state,
+            InputEvent::Key { code, pressed: true, repeat: false } => {
+                let hud_wants_keyboard = self.ecs_world.as_ref().is_some_and(|ecs_world| ecs_world.hud_wants_keyboard());
+                if let Some(action) = self.actions.action_for_key(code, self.modifiers, hud_wants_keyboard) {
+                    debug!("{:?} pressed - {:?}", code, action);
+                    self.run_action(action);
+                }
+            }
+            // Raw motion keeps arriving while the cursor is locked and has no pointer acceleration
+            InputEvent::MouseMotion { dx, dy } if self.mouse_captured => {
+                if let Some(ref mut ecs_world) = self.ecs_world {
+                    ecs_world.rotate_camera(dx, dy);
+                }
+            }
+            InputEvent::MouseWheel { lines } => {
+                if let Some(ecs_world) = self.ecs_world.as_mut().filter(|ecs_world| !ecs_world.hud_wants_mouse()) {
+                    ecs_world.zoom_camera(lines);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Apply a replayed event
+    ///
+    /// Recorded resizes are requested from the window, whose resulting
+    /// resize event is handled as usual.
+    fn replay_input(&mut self, event_loop: &ActiveEventLoop, input: InputEvent) {
+        match input {
+            InputEvent::Resized { width, height } => {
+                let size = winit::dpi::PhysicalSize::new(width, height);
+                if let Some(window) = self.window.as_ref().filter(|window| window.inner_size() != size) {
+                    let _ = window.request_inner_size(size);
+                }
+            }
+            InputEvent::CloseRequested => {
+                info!("Replayed close request, initiating graceful shutdown");
+                self.shutdown(event_loop);
+            }
+            _ => self.handle_input(input),
+        }
+    }
+    
+    /// Create the HUD, showing a failure in the window title
+    ///
+    /// Used at startup and by the Retry HUD action; without a HUD the title
+    /// is the only place left to tell the user what went wrong.
+    fn init_hud(ecs_world: &mut ECSWorld, window: &Window, actions: &std::sync::Arc<ActionRegistry>, settings: &AppSettings, recent_projects: &RecentProjects) {
+        match tracing::info_span!("init_hud").in_scope(|| ecs_world.init_hud(window, std::sync::Arc::clone(actions), settings)) {
+            Ok(()) => {
+                info!("HUD initialized successfully!");
+                ecs_world.show_project(&project::current(), recent_projects);
+            }
+            Err(_) => {
+                if let Some(recovery) = ecs_world.hud_recovery() {
+                    error!("Failed to initialize HUD, continuing without HUD: {}", recovery.diagnostics());
+                }
+            }
+        }
+        
+        let retry_key = actions.binding(Action::RetryHud).map(|chord| chord.label());
+        let title = match ecs_world.hud_recovery() {
+            Some(recovery) => recovery.window_title(config::window::TITLE, retry_key.as_deref()),
+            None => config::window::TITLE.to_string(),
+        };
+        window.set_title(&title);
+        ecs_world.set_base_title(title);
+    }
+    
+    /// Open a project while the app runs
+    ///
+    /// Its paths take effect, its scene replaces the current one, the SDF
+    /// pipeline is rebuilt from its shaders and its shader directory is
+    /// watched. Other pipelines pick up project shaders when they are reloaded.
+    fn open_project(&mut self, path: &std::path::Path) {
+        let Some(ref mut ecs_world) = self.ecs_world else {
+            return;
+        };
+        let project = match Project::load(path) {
+            Ok(project) => project,
+            Err(e) => {
+                ecs_world.report_error(&e);
+                return;
+            }
+        };
+        info!("Opening project {} in {}", project.name, project.root.display());
+        project::set_current(project.clone());
+        self.recent_projects.push(project.file());
+        if let Err(e) = self.recent_projects.save(std::path::Path::new(config::project::RECENT_FILE)) {
+            warn!("Failed to save recent projects: {}", e);
+        }
+        if let Err(e) = ecs_world.open_project(&project, &self.recent_projects) {
+            ecs_world.report_error(&e);
+        }
+        let changes = SettingsChanges { quality: true, hot_reload: true, ..SettingsChanges::default() };
+        ecs_world.apply_settings(&self.settings, changes);
+    }
+    
+    /// Run an action from a key binding or the command palette
+    fn run_action(&mut self, action: Action) {
+        if action == Action::ToggleFullscreen {
+            if config::windowed_fullscreen::ENABLED {
+                info!("Toggling windowed fullscreen");
+                // Use a flag to avoid borrowing issues
+                self.toggle_fullscreen_flag = self.window.is_some();
+            } else {
+                debug!("Windowed fullscreen disabled in config");
+            }
+            return;
+        }
+        
+        let Some(ref mut ecs_world) = self.ecs_world else {
+            return;
+        };
+        match action {
+            Action::ToggleFullscreen => {} // Handled above, it needs no world
+            Action::CommandPalette => ecs_world.toggle_command_palette(),
+            Action::ToggleHud => {
+                info!("Toggling HUD visibility");
+                ecs_world.toggle_hud();
+            }
+            Action::RetryHud => {
+                if ecs_world.hud.is_some() {
+                    info!("HUD is running, nothing to retry");
+                } else if let Some(ref window) = self.window {
+                    info!("Retrying HUD initialization");
+                    Self::init_hud(ecs_world, window, &self.actions, &self.settings, &self.recent_projects);
+                }
+            }
+            Action::ToggleHotReload => {
+                let current_state = ecs_world.is_hot_reload_enabled();
+                match ecs_world.set_hot_reload_enabled(!current_state) {
+                    Ok(()) => {
+                        info!("Hot reload toggled to: {}", !current_state);
+                    }
+                    Err(e) => {
+                        ecs_world.report_error(&e.context(Subsystem::Shaders, "toggle hot reload"));
+                    }
+                }
+            }
+            Action::ReloadShaders => {
+                info!("Manual shader reload");
+                // Reload the main SDF shaders
+                let shaders_to_reload = [
+                    "shaders/sdf.vert",
+                    "shaders/sdf.frag",
+                ];
+                
+                for shader_path in &shaders_to_reload {
+                    match ecs_world.reload_shader(shader_path) {
+                        Ok(()) => {
+                            info!("Manual reload successful for: {}", shader_path);
+                        }
+                        Err(e) => {
+                            ecs_world.report_error(&e.context(Subsystem::Shaders, "reload shader").with_detail("shader", shader_path));
+                        }
+                    }
+                }
+            }
+            Action::ReloadAll => {
+                // Reload shaders, theme and everything else
+                info!("Reloading everything");
+                ecs_world.reload_all();
+            }
+            Action::TakeScreenshot => {
+                if let Err(e) = ecs_world.take_screenshot() {
+                    ecs_world.report_error(&e);
+                }
+            }
+            Action::ToggleQuadView => ecs_world.toggle_quad_view(),
+            Action::AddSphere => ecs_world.press_toolbar_button("add_sphere"),
+            Action::AddBox => ecs_world.press_toolbar_button("add_box"),
+            Action::AddCameraBookmark => ecs_world.add_camera_bookmark(None),
+            Action::CameraBookmark(index) => ecs_world.go_to_camera_bookmark(index),
+            Action::FrameSelected => ecs_world.frame_selected(),
+            Action::ToggleProjection => ecs_world.toggle_camera_projection(),
+        }
+    }
 
-impl ApplicationHandler for AppState {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        println!("=== APPLICATION STARTED - resumed() method called ===");
-        debug!("resumed() method called");
+    /// Create the window, renderer and ECS world on the first `resumed`
+    ///
+    /// Runs at most once: a failed start exits the event loop and is not
+    /// retried by later lifecycle events.
+    fn init_once(&mut self, event_loop: &ActiveEventLoop) {
+        self.lifecycle = Lifecycle::Failed;
         
         // Initialize logging first
         if let Err(e) = debug::init_logging() {
             eprintln!("Failed to initialize logging: {}", e);
         }
         
-        debug!("Logging initialized");
-        
         // Log debug mode configuration
         if debug::VulkanDebugUtils::is_debug_mode_enabled() {
             info!("Debug mode is enabled");
@@ -142,6 +689,15 @@ impl ApplicationHandler for AppState {
         info!("Starting Vulkan App - ECS");
         info!("This app renders SDF shapes using Vulkan with ECS architecture.");
         
+        // `--project` was opened before logging started
+        let project = project::current();
+        if project.is_open() {
+            info!("Project {} in {}", project.name, project.root.display());
+            if let Err(e) = self.recent_projects.save(std::path::Path::new(config::project::RECENT_FILE)) {
+                warn!("Failed to save recent projects: {}", e);
+            }
+        }
+        
         debug!("About to create window");
         
         let window_size = winit::dpi::PhysicalSize::new(
@@ -191,262 +747,199 @@ impl ApplicationHandler for AppState {
         debug!("Window size and position set");
         
         // Initialize Vulkan renderer
-        match VulkanRenderer::new(&window) {
+        match tracing::info_span!("init_renderer").in_scope(|| self.create_renderer(&window)) {
             Ok(renderer) => {
                 self.vulkan_renderer = Some(renderer);
                 info!("Vulkan initialized successfully!");
                 if let Some(ref renderer) = self.vulkan_renderer {
                     info!("Using device: {}", renderer.device.get_device_name(&renderer.instance.instance));
+                    platform::crash::set_gpu_info(renderer.device.describe(&renderer.instance.instance));
                 }
                 debug!("Vulkan renderer stored in AppState");
             }
             Err(e) => {
-                error!("Failed to initialize Vulkan: {}", e);
+                error!("Failed to initialize Vulkan: {}", e.report());
                 event_loop.exit();
                 return;
             }
         }
 
         // Initialize ECS world
-        info!("=== STARTING ECS WORLD INITIALIZATION ===");
-        debug!("About to initialize ECS world");
-        debug!("Checking if Vulkan renderer is available for ECS world creation");
-        debug!("Vulkan renderer is_some: {}", self.vulkan_renderer.is_some());
-        
+        let world_span = tracing::info_span!("init_ecs_world").entered();
         if let Some(renderer) = self.vulkan_renderer.take() {
-            info!("Vulkan renderer taken, proceeding with ECS world creation");
-            info!("Creating ECS world with Vulkan renderer");
-            debug!("Vulkan renderer taken successfully, creating ECS world");
-            debug!("About to call ECSWorld::new(renderer)");
-            
-            match ECSWorld::new(renderer) {
+            let scene_path = self.benchmark.as_ref()
+                .map_or_else(|| project::current().scene_file(), |run| run.scene().to_path_buf());
+            match ECSWorld::new(renderer, &scene_path) {
                 Ok(mut ecs_world) => {
-                    info!("=== ECS WORLD CREATED SUCCESSFULLY ===");
-                    info!("ECS world created successfully, initializing HUD");
-                    debug!("About to call init_hud");
-                    
                     // Initialize HUD after ECS world is created
                     // The init_hud method will handle getting the device and render pass internally
-                    info!("=== STARTING HUD INITIALIZATION ===");
-                    debug!("Calling ecs_world.init_hud(&window)...");
                     debug!("Window inner size: {}x{}", window.inner_size().width, window.inner_size().height);
-                    
-                    match ecs_world.init_hud(&window) {
-                        Ok(()) => {
-                            info!("=== HUD INITIALIZED SUCCESSFULLY ===");
-                            info!("HUD initialized successfully!");
-                            debug!("HUD is now available: {:?}", ecs_world.hud.is_some());
-                            
-                            // Set up hot reload callbacks after HUD is initialized
-                            // Note: We'll skip callback setup for now due to borrowing issues
-                            // The F2/F3 keyboard shortcuts in main.rs will handle hot reload functionality
-                            info!("Hot reload callbacks skipped due to borrowing constraints - using keyboard shortcuts instead");
-                        }
-                        Err(e) => {
-                            error!("=== HUD INITIALIZATION FAILED ===");
-                            error!("Failed to initialize HUD: {}, continuing without HUD", e);
-                            error!("HUD initialization error details: {:?}", e);
-                            debug!("HUD is still None: {:?}", ecs_world.hud.is_some());
-                        }
-                    }
-                    info!("=== HUD INITIALIZATION COMPLETED ===");
-                    debug!("Final HUD state: {:?}", ecs_world.hud.is_some());
+                    Self::init_hud(&mut ecs_world, &window, &self.actions, &self.settings, &self.recent_projects);
                     
                     // Initialize hot reload after HUD is set up
-                    info!("=== STARTING HOT RELOAD INITIALIZATION ===");
-                    debug!("About to initialize hot reload");
-                    match ecs_world.init_hot_reload() {
+                    match tracing::info_span!("init_hot_reload").in_scope(|| ecs_world.init_hot_reload(&self.settings)) {
                         Ok(()) => {
-                            info!("=== HOT RELOAD INITIALIZED SUCCESSFULLY ===");
                             info!("Hot reload initialized successfully!");
                             debug!("Hot reload is now available: {:?}", ecs_world.is_hot_reload_enabled());
                         }
                         Err(e) => {
-                            error!("=== HOT RELOAD INITIALIZATION FAILED ===");
                             error!("Failed to initialize hot reload: {}, continuing without hot reload", e);
-                            error!("Hot reload initialization error details: {:?}", e);
-                            debug!("Hot reload is still not available: {:?}", ecs_world.is_hot_reload_enabled());
+                            debug!("Hot reload initialization error details: {:?}", e);
+                        }
+                    }
+
+                    // The swapchain, HUD theme and watchers were created with the settings already
+                    let startup = SettingsChanges { quality: true, shader_variant: true, outline: true, language: true, camera: true, screenshot: true, ..SettingsChanges::default() };
+                    ecs_world.apply_settings(&self.settings, startup);
+
+                    if let Some(path) = self.toy.take() {
+                        if let Err(e) = ecs_world.start_toy(&path) {
+                            error!("Failed to start --toy: {}", e.report());
+                            self.ecs_world = Some(ecs_world);
+                            self.window = Some(window);
+                            self.shutdown(event_loop);
+                            return;
+                        }
+                    }
+
+                    if let Some(settings) = self.cli_export.take() {
+                        match ecs_world.start_export(settings) {
+                            Ok(()) => self.exit_after_export = true,
+                            Err(e) => {
+                                error!("Failed to start --export: {}", e);
+                                self.ecs_world = Some(ecs_world);
+                                self.window = Some(window);
+                                self.shutdown(event_loop);
+                                return;
+                            }
+                        }
+                    }
+
+                    // Auto-save the scene; a previous run that did not shut down cleanly
+                    // left its last auto-save to restore. Benchmark and export runs do not edit it.
+                    if self.benchmark.is_none() && !self.exit_after_export {
+                        match autosave::Session::begin(&autosave::directory()) {
+                            Ok((session, recovered)) => {
+                                ecs_world.start_autosave(session.autosave_file(), self.settings.autosave, recovered.as_deref());
+                                self.autosave_session = Some(session);
+                            }
+                            Err(e) => warn!("Scene auto-save unavailable: {}", e),
                         }
                     }
-                    info!("=== HOT RELOAD INITIALIZATION COMPLETED ===");
 
-                                        
                     self.ecs_world = Some(ecs_world);
-                    info!("=== ECS WORLD INITIALIZATION COMPLETED ===");
                     info!("ECS world initialized successfully!");
-                    debug!("ECS world stored in AppState");
                 }
                 Err(e) => {
-                    error!("=== ECS WORLD CREATION FAILED ===");
                     error!("Failed to initialize ECS world: {}", e);
-                    error!("ECS world initialization error details: {:?}", e);
+                    debug!("ECS world initialization error details: {:?}", e);
                     event_loop.exit();
                     return;
                 }
             }
         } else {
-            error!("=== VULKAN RENDERER UNAVAILABLE ===");
-            error!("Failed to take Vulkan renderer for ECS world creation");
             error!("Vulkan renderer was None when trying to create ECS world");
         }
-        info!("=== ECS WORLD INITIALIZATION FINISHED ===");
+        drop(world_span);
+        
+        // Recording starts with the window; a replay first restores the recorded window size
+        match self.input_capture.take() {
+            Some(InputCapture::Record(path)) => {
+                let size = window.inner_size();
+                self.input_recorder = Some(InputRecorder::new(path, [size.width, size.height]));
+            }
+            Some(InputCapture::Replay(path)) => match InputRecording::load(&path) {
+                Ok(recording) => {
+                    let replay = InputReplay::new(recording);
+                    let [width, height] = replay.window_size();
+                    let _ = window.request_inner_size(winit::dpi::PhysicalSize::new(width, height));
+                    self.input_replay = Some(replay);
+                }
+                Err(e) => error!("Failed to start --replay: {}", e),
+            },
+            None => {}
+        }
         
+        self.refresh_monitors(&window);
         self.window = Some(window);
+        self.lifecycle = Lifecycle::Running;
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: winit::window::WindowId, event: WindowEvent) {
-        // Handle mouse events directly for ImGui
-        if let Some(ref mut ecs_world) = self.ecs_world {
-            if let Some(ref mut hud) = ecs_world.hud {
-                match &event {
-                    WindowEvent::CursorMoved { position, .. } => {
-                        // Directly update ImGui mouse position
-                        let io = hud.context_mut();
-                        io.mouse_pos = [position.x as f32, position.y as f32];
-                    }
-                    WindowEvent::MouseInput { state, button, .. } => {
-                        // Directly update ImGui mouse button state
-                        let io = hud.context_mut();
-                        match button {
-                            winit::event::MouseButton::Left => {
-                                io.mouse_down[0] = *state == winit::event::ElementState::Pressed;
-                            }
-                            winit::event::MouseButton::Right => {
-                                io.mouse_down[1] = *state == winit::event::ElementState::Pressed;
-                            }
-                            winit::event::MouseButton::Middle => {
-                                io.mouse_down[2] = *state == winit::event::ElementState::Pressed;
-                            }
-                            _ => {}
-                        }
-                    }
-                    WindowEvent::MouseWheel { delta, .. } => {
-                        // Directly update ImGui mouse wheel
-                        let io = hud.context_mut();
-                        match delta {
-                            winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                                io.mouse_wheel = *y;
-                            },
-                            winit::event::MouseScrollDelta::PixelDelta(y) => {
-                                io.mouse_wheel = (y.y as f32) / 16.0; // Convert pixels to lines
-                            },
-                        }
-                    }
-                    _ => {}
+    /// Present again after `suspended`: only the surface is rebuilt,
+    /// the window, device and ECS world are still alive
+    fn on_resume(&mut self) {
+        let (Some(window), Some(ecs_world)) = (&self.window, &mut self.ecs_world) else {
+            error!("Resumed without a window or ECS world to present");
+            self.lifecycle = Lifecycle::Failed;
+            return;
+        };
+        info!("Resuming");
+        if let Err(e) = ecs_world.resume_rendering(window) {
+            ecs_world.report_error(&e);
+        }
+        self.lifecycle = Lifecycle::Running;
+        // The time spent suspended is not a frame delta
+        self.last_update = None;
+        window.request_redraw();
+    }
+}
+
+impl ApplicationHandler for AppState {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // Some platforms resume more than once; only the first one creates anything
+        match self.lifecycle {
+            Lifecycle::Uninitialized => self.init_once(event_loop),
+            Lifecycle::Suspended => self.on_resume(),
+            Lifecycle::Running | Lifecycle::Failed => debug!("Ignoring resumed while {:?}", self.lifecycle),
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        // The platform may destroy the window's surface (Android in the background,
+        // a laptop going to sleep): release it and everything presenting to it,
+        // keeping the device and the ECS world for `resumed`
+        if self.lifecycle != Lifecycle::Running || self.is_shutting_down {
+            return;
+        }
+        self.set_mouse_capture(false);
+        let Some(ref mut ecs_world) = self.ecs_world else {
+            return;
+        };
+        info!("Suspending");
+        if let Err(e) = ecs_world.suspend_rendering() {
+            ecs_world.report_error(&e);
+        }
+        self.lifecycle = Lifecycle::Suspended;
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, window_id: winit::window::WindowId, event: WindowEvent) {
+        // Keys and text for ImGui widgets, e.g. the command palette search field; not replayed
+        if matches!(event, WindowEvent::KeyboardInput { .. } | WindowEvent::ModifiersChanged(_))
+            && !self.mouse_captured
+            && self.input_replay.is_none()
+        {
+            if let (Some(window), Some(hud)) = (self.window.as_ref(), self.ecs_world.as_mut().and_then(|ecs_world| ecs_world.hud.as_mut())) {
+                hud.handle_keyboard_event(window, window_id, &event);
+            }
+        }
+        
+        // Keyboard and mouse input goes through `handle_input`, live or replayed
+        if let Some(input) = InputEvent::from_window_event(&event) {
+            if let Some(ref mut recorder) = self.input_recorder {
+                recorder.push(input);
+            }
+            if input.is_user_input() {
+                if self.input_replay.is_none() {
+                    self.handle_input(input);
                 }
+                return;
             }
         }
         
         match event {
             WindowEvent::CloseRequested => {
                 info!("Window close requested, initiating graceful shutdown");
-                
-                // Set shutdown flag to stop rendering
-                self.is_shutting_down = true;
-                
-                // Wait for current frame to complete before cleanup
-                if let Some(ref mut ecs_world) = self.ecs_world {
-                    info!("Waiting for current frame to complete before cleanup");
-                    if let Err(e) = ecs_world.wait_for_gpu_idle() {
-                        error!("Failed to wait for GPU idle during shutdown: {}", e);
-                    }
-                    
-                    info!("GPU idle confirmed, cleaning up HUD system");
-                    ecs_world.cleanup_hud();
-
-                    // Clean up hot reload manager to break reference cycles
-                    info!("Cleaning up hot reload manager");
-                    ecs_world.cleanup_hot_reload();
-                }
-                  
-                info!("Graceful shutdown completed, exiting");
-                event_loop.exit();
-            }
-            WindowEvent::KeyboardInput {
-                event: winit::event::KeyEvent {
-                    state: winit::event::ElementState::Pressed,
-                    logical_key: Key::Named(NamedKey::F11),
-                    ..
-                },
-                ..
-            } => {
-                // Toggle windowed fullscreen on F11 press
-                if config::windowed_fullscreen::ENABLED {
-                    info!("F11 pressed - toggling windowed fullscreen");
-                    // Use a flag to avoid borrowing issues
-                    let should_toggle = self.window.is_some();
-                    if should_toggle {
-                        // Safe to call toggle_windowed_fullscreen without borrowing window here
-                        self.toggle_fullscreen_flag = true;
-                    }
-                } else {
-                    debug!("F11 pressed - windowed fullscreen disabled in config");
-                }
-            }
-            WindowEvent::KeyboardInput {
-                event: winit::event::KeyEvent {
-                    state: winit::event::ElementState::Pressed,
-                    logical_key: Key::Named(NamedKey::F1),
-                    ..
-                },
-                ..
-            } => {
-                // Toggle HUD visibility on F1 press
-                info!("F1 pressed - toggling HUD visibility");
-                if let Some(ref mut ecs_world) = self.ecs_world {
-                    ecs_world.toggle_hud();
-                }
-            }
-            WindowEvent::KeyboardInput {
-                event: winit::event::KeyEvent {
-                    state: winit::event::ElementState::Pressed,
-                    logical_key: Key::Named(NamedKey::F2),
-                    ..
-                },
-                ..
-            } => {
-                // Toggle hot reload on F2 press
-                info!("F2 pressed - toggling hot reload");
-                if let Some(ref mut ecs_world) = self.ecs_world {
-                    let current_state = ecs_world.is_hot_reload_enabled();
-                    match ecs_world.set_hot_reload_enabled(!current_state) {
-                        Ok(()) => {
-                            info!("Hot reload toggled to: {}", !current_state);
-                        }
-                        Err(e) => {
-                            error!("Failed to toggle hot reload: {}", e);
-                        }
-                    }
-                }
-            }
-            WindowEvent::KeyboardInput {
-                event: winit::event::KeyEvent {
-                    state: winit::event::ElementState::Pressed,
-                    logical_key: Key::Named(NamedKey::F3),
-                    ..
-                },
-                ..
-            } => {
-                // Manual shader reload on F3 press
-                info!("F3 pressed - manual shader reload");
-                if let Some(ref ecs_world) = self.ecs_world {
-                    // Reload the main SDF shaders
-                    let shaders_to_reload = [
-                        "shaders/sdf.vert",
-                        "shaders/sdf.frag",
-                    ];
-                    
-                    for shader_path in &shaders_to_reload {
-                        match ecs_world.reload_shader(shader_path) {
-                            Ok(()) => {
-                                info!("Manual reload successful for: {}", shader_path);
-                            }
-                            Err(e) => {
-                                error!("Manual reload failed for {}: {}", shader_path, e);
-                            }
-                        }
-                    }
-                }
+                self.shutdown(event_loop);
             }
             WindowEvent::Resized(new_size) => {
                 info!("Window resized to: {}x{} (fullscreen_pending: {})", new_size.width, new_size.height, self.fullscreen_pending);
@@ -487,71 +980,144 @@ impl ApplicationHandler for AppState {
                     info!("Handling normal window resize");
                     if let Some(ref mut ecs_world) = self.ecs_world {
                         if let Err(e) = ecs_world.handle_window_resize(new_size.width, new_size.height, self.window.as_ref().unwrap()) {
-                            error!("Error during window resize: {}", e);
+                            ecs_world.report_error(&e);
+                        }
+                    }
+                }
+            }
+            WindowEvent::DroppedFile(path) => {
+                info!("File dropped: {}", path.display());
+                if let Some(ecs_world) = &mut self.ecs_world {
+                    ecs_world.open_dropped_file(&path);
+                }
+            }
+            WindowEvent::Moved(_) => {
+                // A fullscreen window that ends up on another monitor covers that one
+                if self.is_fullscreen && !self.fullscreen_pending {
+                    if let Some(window) = self.window.take() {
+                        self.follow_monitor_change(&window);
+                        self.window = Some(window);
+                    }
+                }
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, mut inner_size_writer } => {
+                // Dragged onto a monitor with another DPI; the resize that follows recreates the swapchain
+                info!("Window scale factor changed to {}", scale_factor);
+                if self.is_fullscreen && !config::windowed_fullscreen::WINIT_BORDERLESS {
+                    // Keep covering the monitor rather than keeping the logical size
+                    if let Some(monitor) = self.window.as_ref().and_then(Self::current_monitor) {
+                        let size = winit::dpi::PhysicalSize::new(monitor.size[0], monitor.size[1]);
+                        if let Err(e) = inner_size_writer.request_inner_size(size) {
+                            warn!("Failed to keep the fullscreen size: {}", e);
                         }
                     }
                 }
+                if let Some(window) = self.window.take() {
+                    self.refresh_monitors(&window);
+                    self.window = Some(window);
+                }
             }
             WindowEvent::RedrawRequested => {
-                // Skip rendering during shutdown
-                if self.is_shutting_down {
+                // Skip rendering during shutdown and while suspended
+                if self.is_shutting_down || self.lifecycle == Lifecycle::Suspended {
                     return;
                 }
                 
                 if let Some(ref mut ecs_world) = self.ecs_world {
                     // Draw the main 3D scene first
+                    let draw_started = std::time::Instant::now();
                     if let Err(e) = ecs_world.draw_frame() {
-                        error!("Error during draw frame: {}", e);
+                        ecs_world.report_error(&e);
                     }
-
-                    // Check for button clicks after rendering (when ImGui state is available)
-                    let (hot_reload_toggled, reload_clicked) = if let Some(ref hud) = ecs_world.hud {
-                        (hud.was_hot_reload_toggled(), hud.was_reload_button_clicked())
-                    } else {
-                        (None, false)
-                    };
-
-                    // Handle hot reload checkbox toggle
-                    if let Some(new_state) = hot_reload_toggled {
-                        info!("Hot reload checkbox toggled to: {}", new_state);
-                        match ecs_world.set_hot_reload_enabled(new_state) {
-                            Ok(()) => {
-                                info!("Hot reload state set to: {}", new_state);
-                            }
-                            Err(e) => {
-                                error!("Failed to set hot reload state: {}", e);
+                    
+                    // `--benchmark` runs write their report and exit once the time is up
+                    if let Some(ref mut run) = self.benchmark {
+                        let now = std::time::Instant::now();
+                        run.add_cpu_time(now - draw_started);
+                        run.end_frame(now, ecs_world.gpu_frame_ms());
+                        if run.is_finished(now) {
+                            let summary = run.summary();
+                            match run.write_report() {
+                                Ok(path) => info!("Benchmark report written to {}", path.display()),
+                                Err(e) => error!("Failed to write the benchmark report: {}", e),
                             }
+                            // The summary is the benchmark's report on stdout, for scripts running `--benchmark`
+                            println!("{}", summary);
+                            self.shutdown(event_loop);
+                            return;
                         }
                     }
+                    
+                    // "Quit" in the error dialog
+                    if ecs_world.take_quit_request() {
+                        info!("Quit requested from the error dialog");
+                        self.shutdown(event_loop);
+                        return;
+                    }
+                    
+                    #[cfg(feature = "xr")]
+                    if ecs_world.headset_exiting() {
+                        info!("OpenXR session ended by the runtime, exiting");
+                        self.shutdown(event_loop);
+                        return;
+                    }
 
-                    // Handle manual reload button click
-                    if reload_clicked {
-                        info!("Manual reload button clicked!");
-                        // Try to reload all shader files
-                        let shader_files = ["shaders/sdf.vert", "shaders/sdf.frag", "shaders/imgui.vert", "shaders/imgui.frag"];
-                        for shader_path in &shader_files {
-                            match ecs_world.reload_shader(shader_path) {
-                                Ok(()) => {
-                                    info!("Successfully reloaded shader: {}", shader_path);
-                                }
-                                Err(e) => {
-                                    error!("Failed to reload shader {}: {}", shader_path, e);
-                                }
-                            }
-                        }
+                    // `--export` runs exit once every frame is written
+                    if ecs_world.take_export_finished() && self.exit_after_export {
+                        info!("Export finished, exiting");
+                        self.shutdown(event_loop);
+                        return;
                     }
+
+                }
+                
+                // Settings applied in the Settings window
+                let requested = self.ecs_world.as_mut().and_then(ECSWorld::take_requested_settings);
+                if let Some(settings) = requested {
+                    self.apply_settings(settings);
+                }
+                
+                // Actions chosen in the command palette or clicked on the toolbar run like their key bindings
+                let requested = self.ecs_world.as_mut().and_then(ECSWorld::take_requested_action);
+                if let Some(action) = requested {
+                    self.run_action(action);
+                }
+                
+                // Projects chosen in the Project panel
+                let requested = self.ecs_world.as_mut().and_then(ECSWorld::take_requested_project);
+                if let Some(path) = requested {
+                    self.open_project(&path);
                 }
             }
             _ => (),
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: DeviceId, event: DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            let input = InputEvent::MouseMotion { dx: dx as f32, dy: dy as f32 };
+            // Motion only matters while the camera has the mouse
+            if let Some(recorder) = self.input_recorder.as_mut().filter(|_| self.mouse_captured) {
+                recorder.push(input);
+            }
+            if self.input_replay.is_none() {
+                self.handle_input(input);
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         // Skip ECS updates and rendering during shutdown
         if self.is_shutting_down {
             return;
         }
         
+        // Nothing to draw to until the app is resumed
+        if self.lifecycle == Lifecycle::Suspended {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+        
         // Handle fullscreen toggle flag if set
         if self.toggle_fullscreen_flag {
             self.toggle_fullscreen_flag = false;
@@ -562,10 +1128,49 @@ impl ApplicationHandler for AppState {
             }
         }
         
-        // Update ECS systems
+        // Sleep until the next frame is due when the frame rate is capped
+        if let Some(deadline) = self.frame_limiter.wait_until(std::time::Instant::now()) {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(deadline));
+            return;
+        }
+        event_loop.set_control_flow(ControlFlow::Wait);
+        
+        // Update ECS systems with the real time since the previous update
+        let now = std::time::Instant::now();
+        let mut delta_time = self.last_update.map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        self.last_update = Some(now);
+        
+        // A replay applies the frame's recorded events and time step instead
+        if let Some(replay) = self.input_replay.as_mut() {
+            match replay.next_frame() {
+                Some((recorded_delta, events)) => {
+                    delta_time = recorded_delta;
+                    for input in events {
+                        self.replay_input(event_loop, input);
+                    }
+                    if self.is_shutting_down {
+                        return;
+                    }
+                }
+                None => {
+                    info!("Input replay finished, input is live again");
+                    self.input_replay = None;
+                }
+            }
+        }
+        if let Some(ref mut recorder) = self.input_recorder {
+            recorder.push_frame(delta_time);
+        }
         if let Some(ref mut ecs_world) = self.ecs_world {
-            if let Err(e) = ecs_world.execute(self.window.as_ref().unwrap(), 0.016) {
-                error!("Error during ECS execution: {}", e);
+            // The benchmark flies the camera along its path instead of mouse look
+            if let Some(ref run) = self.benchmark {
+                ecs_world.set_camera(benchmark::camera_path(run.elapsed_secs(now)));
+            }
+            if let Err(e) = ecs_world.execute(self.window.as_ref().unwrap(), delta_time) {
+                ecs_world.report_error(&e);
+            }
+            if let Some(ref mut run) = self.benchmark {
+                run.add_cpu_time(now.elapsed());
             }
         }
         if let Some(ref window) = self.window {
@@ -575,8 +1180,23 @@ impl ApplicationHandler for AppState {
 }
 
 fn main() -> Result<()> {
-    println!("=== MAIN FUNCTION STARTED ===");
+    // Flushes the trace file on drop when built with a profiling feature
+    let _profiling = profiling::init_profiling();
+    // Crash folders with a backtrace, the recent log and GPU info
+    platform::crash::install();
+    // The project comes first, its directories are the defaults of the other options
+    let mut recent_projects = RecentProjects::load_or_default(std::path::Path::new(config::project::RECENT_FILE));
+    if let Some(path) = project::project_from_args(std::env::args().skip(1))? {
+        let project = Project::load(&path)?;
+        recent_projects.push(project.file());
+        project::set_current(project);
+    }
+    let cli_export = ExportSettings::from_args(std::env::args().skip(1))?;
+    let benchmark = BenchmarkSettings::from_args(std::env::args().skip(1))?.map(BenchmarkRun::new);
+    let input_capture = InputCapture::from_args(std::env::args().skip(1))?;
+    let toy = fullscreen_pass::toy_from_args(std::env::args().skip(1))?;
     let event_loop = EventLoop::new()?;
+    let settings = AppSettings::load_or_default(std::path::Path::new(config::settings::FILE));
     let mut app = AppState {
         window: None,
         vulkan_renderer: None,
@@ -587,7 +1207,28 @@ fn main() -> Result<()> {
         original_window_size: winit::dpi::PhysicalSize::new(800, 600),
         original_window_position: winit::dpi::PhysicalPosition::new(100, 100),
         original_decorations: true,
+        monitors: Vec::new(),
+        fullscreen_monitor: None,
         is_shutting_down: false,
+        lifecycle: Lifecycle::Uninitialized,
+        gpu_override: GpuSelector::from_args(std::env::args().skip(1)),
+        modifiers: ModifiersState::empty(),
+        cli_export,
+        exit_after_export: false,
+        last_update: None,
+        mouse_captured: false,
+        actions: std::sync::Arc::new(ActionRegistry::new()),
+        // Benchmarks measure uncapped frames
+        frame_limiter: FrameLimiter::new(if benchmark.is_some() { 0 } else { settings.frame_limit }),
+        settings,
+        benchmark,
+        input_capture,
+        input_recorder: None,
+        input_replay: None,
+        toy,
+        xr: std::env::args().skip(1).any(|arg| arg == "--xr"),
+        recent_projects,
+        autosave_session: None,
     };
     
     let _ = event_loop.run_app(&mut app);
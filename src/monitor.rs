@@ -0,0 +1,131 @@
+//! Monitors for windowed fullscreen
+//!
+//! The Settings window lists the connected monitors so windowed fullscreen
+//! can cover a chosen one; `choose` falls back to the monitor the window is
+//! on when the chosen one is not connected. When a fullscreen window ends up
+//! on another monitor, `carry_over` moves the size and position it restores
+//! to along with it, scaled for the other monitor's DPI.
+
+use winit::monitor::MonitorHandle;
+
+/// A connected monitor
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// Name reported by the system, used to find the monitor again
+    pub name: String,
+    /// Top-left corner on the desktop in physical pixels
+    pub position: [i32; 2],
+    /// Resolution in physical pixels
+    pub size: [u32; 2],
+    /// Ratio of physical to logical pixels
+    pub scale_factor: f64,
+}
+
+impl MonitorInfo {
+    /// Describe a monitor
+    ///
+    /// # Arguments
+    /// * `handle` - The monitor
+    /// * `index` - Position in the monitor list, names a monitor without one
+    pub fn new(handle: &MonitorHandle, index: usize) -> Self {
+        let position = handle.position();
+        let size = handle.size();
+        Self {
+            name: handle.name().unwrap_or_else(|| format!("Monitor {}", index + 1)),
+            position: [position.x, position.y],
+            size: [size.width, size.height],
+            scale_factor: handle.scale_factor(),
+        }
+    }
+
+    /// Entry of the monitor in the Settings window
+    pub fn label(&self) -> String {
+        format!("{} ({}x{}, {:.0}%)", self.name, self.size[0], self.size[1], self.scale_factor * 100.0)
+    }
+}
+
+/// Monitor for windowed fullscreen
+///
+/// # Arguments
+/// * `monitors` - Connected monitors
+/// * `preferred` - Name of the monitor chosen in the settings, None for the current one
+/// * `current` - Name of the monitor the window is on
+///
+/// # Returns
+/// Index of the preferred monitor if it is connected, otherwise of the
+/// current one, otherwise the first; None without monitors
+pub fn choose(monitors: &[MonitorInfo], preferred: Option<&str>, current: Option<&str>) -> Option<usize> {
+    let find = |name: &str| monitors.iter().position(|monitor| monitor.name == name);
+    preferred.and_then(find)
+        .or_else(|| current.and_then(find))
+        .or_else(|| (!monitors.is_empty()).then_some(0))
+}
+
+/// Window position and size moved from one monitor to another
+///
+/// The offset from the monitor's corner and the size are scaled by the
+/// ratio of the scale factors, so the window keeps its logical size, and
+/// the result is clamped to fit on the new monitor. A new monitor without
+/// an area, as winit reports for disconnected or virtual outputs, leaves the
+/// window where it is, and a missing scale factor counts as 1.
+///
+/// # Arguments
+/// * `position` - Outer position on the old monitor
+/// * `size` - Inner size on the old monitor
+/// * `from` - The old monitor
+/// * `to` - The new monitor
+///
+/// # Returns
+/// The position and size on the new monitor
+pub fn carry_over(position: [i32; 2], size: [u32; 2], from: &MonitorInfo, to: &MonitorInfo) -> ([i32; 2], [u32; 2]) {
+    if to.size.contains(&0) {
+        return (position, size);
+    }
+    let ratio = to.scale_factor / from.scale_factor;
+    let ratio = if ratio.is_finite() && ratio > 0.0 { ratio } else { 1.0 };
+    let mut new_position = [0; 2];
+    let mut new_size = [0; 2];
+    for axis in 0..2 {
+        new_size[axis] = ((size[axis] as f64 * ratio).round() as u32).clamp(1, to.size[axis]);
+        let offset = ((position[axis] - from.position[axis]) as f64 * ratio).round() as i32;
+        let max_offset = (to.size[axis] - new_size[axis]) as i32;
+        new_position[axis] = to.position[axis] + offset.clamp(0, max_offset);
+    }
+    (new_position, new_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, position: [i32; 2], size: [u32; 2], scale_factor: f64) -> MonitorInfo {
+        MonitorInfo { name: name.to_string(), position, size, scale_factor }
+    }
+
+    #[test]
+    fn test_choose_falls_back_to_the_current_monitor() {
+        let monitors = [monitor("A", [0, 0], [1920, 1080], 1.0), monitor("B", [1920, 0], [3840, 2160], 2.0)];
+        assert_eq!(choose(&monitors, Some("B"), Some("A")), Some(1));
+        assert_eq!(choose(&monitors, Some("Unplugged"), Some("B")), Some(1));
+        assert_eq!(choose(&monitors, None, Some("A")), Some(0));
+        assert_eq!(choose(&monitors, None, None), Some(0));
+        assert_eq!(choose(&[], Some("A"), None), None);
+    }
+
+    #[test]
+    fn test_carry_over_keeps_the_logical_size() {
+        let from = monitor("A", [0, 0], [1920, 1080], 1.0);
+        let to = monitor("B", [1920, 0], [3840, 2160], 2.0);
+        assert_eq!(carry_over([100, 50], [800, 600], &from, &to), ([2120, 100], [1600, 1200]));
+        // Past the edge of the new monitor: kept on it
+        let small = monitor("C", [1920, 0], [1280, 720], 1.0);
+        assert_eq!(carry_over([1500, 800], [1000, 600], &from, &small), ([2200, 120], [1000, 600]));
+        assert_eq!(carry_over([0, 0], [1920, 1080], &from, &small), ([1920, 0], [1280, 720]));
+
+        // A disconnected monitor reports no area or scale factor: nothing to fit on or scale by
+        let gone = monitor("D", [0, 0], [0, 0], 0.0);
+        assert_eq!(carry_over([100, 50], [800, 600], &from, &gone), ([100, 50], [800, 600]));
+        let unscaled = monitor("E", [1920, 0], [1280, 720], 0.0);
+        assert_eq!(carry_over([100, 50], [800, 600], &from, &unscaled), ([2020, 50], [800, 600]));
+    }
+}
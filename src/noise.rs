@@ -0,0 +1,177 @@
+//! Procedural noise
+//!
+//! CPU copies of the GLSL noise functions in `shaders/include/noise.glsl`
+//! (simplex, fractal and curl noise), so tools that evaluate the scene on
+//! the CPU, like the mesh exporter, see the same values as the shaders. The
+//! two are kept in step by hand; the GLSL name of each function is given in
+//! its doc comment.
+//!
+//! The global `NoiseSettings` (seed and scale) are edited in the modifiers
+//! panel and reach the shaders through the scene buffer's `noise` member.
+
+use cgmath::{InnerSpace, Vector3};
+use crate::config;
+
+/// Global noise settings (ECS resource)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseSettings {
+    /// Shifts the whole noise field
+    pub seed: u32,
+    /// Multiplies every position noise is evaluated at
+    pub scale: f32,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            seed: config::noise::DEFAULT_SEED,
+            scale: config::noise::DEFAULT_SCALE,
+        }
+    }
+}
+
+impl NoiseSettings {
+    /// Value written to the scene buffer: x = seed, y = scale, zw unused
+    pub fn as_gpu(&self) -> [f32; 4] {
+        [self.seed as f32, self.scale.max(0.0), 0.0, 0.0]
+    }
+}
+
+fn mod289(x: f32) -> f32 {
+    x - (x * (1.0 / 289.0)).floor() * 289.0
+}
+
+fn permute(x: f32) -> f32 {
+    mod289((x * 34.0 + 10.0) * x)
+}
+
+/// GLSL `step(edge, x)`
+fn step(edge: f32, x: f32) -> f32 {
+    if x < edge { 0.0 } else { 1.0 }
+}
+
+/// 3D simplex noise, about -1 to 1 (`simplexNoise(vec3)`)
+///
+/// The GLSL version evaluates the four corners as vectors; here they are
+/// evaluated one after the other, with the same arithmetic.
+fn simplex(v: Vector3<f32>) -> f32 {
+    // First corner
+    let skew = (v.x + v.y + v.z) / 3.0;
+    let i = v.map(|c| (c + skew).floor());
+    let unskew = (i.x + i.y + i.z) / 6.0;
+    let x0 = (v - i).map(|c| c + unskew);
+
+    // Other corners, by the order of x0's components
+    let g = Vector3::new(step(x0.y, x0.x), step(x0.z, x0.y), step(x0.x, x0.z));
+    let l = g.map(|c| 1.0 - c);
+    let i1 = Vector3::new(g.x.min(l.z), g.y.min(l.x), g.z.min(l.y));
+    let i2 = Vector3::new(g.x.max(l.z), g.y.max(l.x), g.z.max(l.y));
+    let corners = [Vector3::new(0.0, 0.0, 0.0), i1, i2, Vector3::new(1.0, 1.0, 1.0)];
+
+    let i = i.map(mod289);
+    let ns = Vector3::new(2.0 / 7.0, 0.5 / 7.0 - 1.0, 1.0 / 7.0);
+    let mut sum = 0.0;
+    for (k, corner) in corners.iter().enumerate() {
+        let x_k = (x0 - corner).map(|c| c + k as f32 / 6.0);
+
+        // Permutation, then a gradient from 7x7 points mapped onto an octahedron
+        let p = permute(permute(permute(i.z + corner.z) + i.y + corner.y) + i.x + corner.x);
+        let j = p - 49.0 * (p * ns.z * ns.z).floor();
+        let gx_ = (j * ns.z).floor();
+        let gy_ = (j - 7.0 * gx_).floor();
+        let x = gx_ * ns.x + ns.y;
+        let y = gy_ * ns.x + ns.y;
+        let h = 1.0 - x.abs() - y.abs();
+        let sh = -step(h, 0.0);
+        let gradient = Vector3::new(x + (x.floor() * 2.0 + 1.0) * sh, y + (y.floor() * 2.0 + 1.0) * sh, h);
+        let norm = 1.792_842_9 - 0.853_734_7 * gradient.magnitude2();
+
+        let m = (0.5 - x_k.magnitude2()).max(0.0);
+        let m = m * m;
+        sum += m * m * (gradient * norm).dot(x_k);
+    }
+    105.0 * sum
+}
+
+/// Shift of the noise field for a seed (`noiseSeedOffset`)
+fn seed_offset(seed: f32) -> Vector3<f32> {
+    Vector3::new(seed * 17.13, seed * 31.71, seed * 47.37).map(mod289)
+}
+
+/// 3D simplex noise of a seed, about -1 to 1 (`simplexNoise(vec3, float)`)
+pub fn simplex_noise(p: Vector3<f32>, seed: f32) -> f32 {
+    simplex(p + seed_offset(seed))
+}
+
+/// Fractal noise normalized to about -1 to 1 (`fbm`)
+///
+/// # Arguments
+/// * `p` - Position, already scaled
+/// * `seed` - Seed of the first octave; each further octave adds one
+/// * `octaves` - Octaves of simplex noise, each twice the frequency and
+///   half the amplitude of the one before
+pub fn fbm(mut p: Vector3<f32>, seed: f32, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    for octave in 0..octaves {
+        sum += amplitude * simplex_noise(p, seed + octave as f32);
+        total += amplitude;
+        p *= 2.0;
+        amplitude *= 0.5;
+    }
+    if total > 0.0 { sum / total } else { 0.0 }
+}
+
+/// Divergence free flow field, the curl of a simplex noise vector potential (`curlNoise`)
+#[allow(dead_code)]
+pub fn curl_noise(p: Vector3<f32>, seed: f32) -> Vector3<f32> {
+    const E: f32 = 0.01;
+    let dx = Vector3::new(E, 0.0, 0.0);
+    let dy = Vector3::new(0.0, E, 0.0);
+    let dz = Vector3::new(0.0, 0.0, E);
+
+    // Three independent potential components from shifted seeds
+    let (s0, s1, s2) = (seed, seed + 101.0, seed + 202.0);
+    let diff = |offset: Vector3<f32>, seed: f32| simplex_noise(p + offset, seed) - simplex_noise(p - offset, seed);
+
+    Vector3::new(
+        diff(dy, s2) - diff(dz, s1),
+        diff(dz, s0) - diff(dx, s2),
+        diff(dx, s1) - diff(dy, s0),
+    ) / (2.0 * E)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> impl Iterator<Item = Vector3<f32>> {
+        (0..512).map(|n| Vector3::new((n % 8) as f32 * 0.37 - 1.3, (n / 8 % 8) as f32 * 0.41 - 1.7, (n / 64) as f32 * 0.53 + 0.2))
+    }
+
+    #[test]
+    fn test_noise_is_bounded_continuous_and_seeded() {
+        for p in grid() {
+            let value = simplex_noise(p, 0.0);
+            assert!(value.abs() <= 1.1, "{:?}: {}", p, value);
+            assert!((simplex_noise(p + Vector3::new(1e-3, 0.0, 0.0), 0.0) - value).abs() < 0.01, "{:?}", p);
+            assert!(fbm(p, 3.0, config::noise::MODIFIER_OCTAVES).abs() <= 1.1);
+        }
+        assert!(grid().any(|p| simplex_noise(p, 0.0) != simplex_noise(p, 1.0)));
+        assert_eq!(fbm(Vector3::new(0.3, 0.1, 0.2), 0.0, 1), simplex_noise(Vector3::new(0.3, 0.1, 0.2), 0.0));
+    }
+
+    #[test]
+    fn test_curl_noise_is_divergence_free() {
+        // With the curl's own step the difference operators commute, so only rounding is left
+        const H: f32 = 0.01;
+        for p in grid().step_by(37) {
+            let divergence = (curl_noise(p + Vector3::new(H, 0.0, 0.0), 2.0).x - curl_noise(p - Vector3::new(H, 0.0, 0.0), 2.0).x
+                + curl_noise(p + Vector3::new(0.0, H, 0.0), 2.0).y - curl_noise(p - Vector3::new(0.0, H, 0.0), 2.0).y
+                + curl_noise(p + Vector3::new(0.0, 0.0, H), 2.0).z - curl_noise(p - Vector3::new(0.0, 0.0, H), 2.0).z)
+                / (2.0 * H);
+            assert!(divergence.abs() < 0.01, "{:?}: {}", p, divergence);
+        }
+    }
+}
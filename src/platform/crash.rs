@@ -0,0 +1,341 @@
+//! Crash reports
+//!
+//! `install` sets a panic hook and, on Windows, an unhandled exception filter
+//! for native crashes (an access violation inside a driver, for example).
+//! Every crash gets a folder named after the local time under
+//! `config::crash::CRASH_DIR` holding:
+//!
+//! - `crash.txt`: what happened, the thread, the GPU and driver, and a backtrace
+//! - `log.txt`: the most recent records of the console log buffer
+//! - `crash.dmp`: a minidump of the process (Windows native exceptions only)
+//!
+//! A native message box then points the user to the folder. Worker thread
+//! panics are often recovered (background jobs report them as an error),
+//! so those are written to disk without the message box.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chrono::{DateTime, Local};
+use crate::config;
+use crate::log_sinks::{self, LogEntry};
+
+/// GPU and driver of the renderer, set once the device exists
+static GPU_INFO: Mutex<Option<String>> = Mutex::new(None);
+
+/// Install the panic hook and the native exception filter
+///
+/// The previous panic hook still runs first, so panics are printed as before.
+pub fn install() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous_hook(info);
+        let is_main_thread = std::thread::current().name() == Some("main");
+        if let Some(dir) = write_report(&format!("Panic: {}", info)) {
+            if is_main_thread {
+                show_message(&dir);
+            }
+        }
+    }));
+
+    #[cfg(windows)]
+    seh::install();
+}
+
+/// Record the GPU and driver description included in crash reports
+///
+/// # Arguments
+/// * `info` - Description from `VulkanDevice::describe`
+pub fn set_gpu_info(info: String) {
+    *GPU_INFO.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(info);
+}
+
+/// Everything written into a crash folder
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// What happened, e.g. the panic message and location
+    pub summary: String,
+    /// Name of the crashing thread
+    pub thread: String,
+    /// Backtrace of the crashing thread
+    pub backtrace: String,
+    /// GPU and driver, if the renderer got that far
+    pub gpu: Option<String>,
+    /// Most recent log records, oldest first; None if the log was locked
+    pub log: Option<Vec<LogEntry>>,
+}
+
+impl CrashReport {
+    /// Capture the state of the current thread
+    ///
+    /// Never blocks: the crashing thread may hold the log or GPU info lock.
+    pub fn capture(summary: &str) -> Self {
+        let thread = std::thread::current();
+        Self {
+            summary: summary.to_string(),
+            thread: thread.name().map_or_else(|| format!("{:?}", thread.id()), str::to_string),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            gpu: GPU_INFO.try_lock().ok().and_then(|info| info.clone()),
+            log: log_sinks::console_log().try_recent(config::crash::LOG_RECORDS),
+        }
+    }
+
+    /// Contents of `crash.txt`
+    pub fn details(&self, time: &DateTime<Local>) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{}", self.summary);
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Time: {}", time.format("%Y-%m-%d %H:%M:%S %z"));
+        let _ = writeln!(text, "Version: {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(text, "OS: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+        let _ = writeln!(text, "Thread: {}", self.thread);
+        let _ = writeln!(text);
+        let _ = writeln!(text, "GPU:");
+        let _ = writeln!(text, "{}", self.gpu.as_deref().unwrap_or("Not initialized"));
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Backtrace:");
+        let _ = writeln!(text, "{}", self.backtrace);
+        text
+    }
+
+    /// Contents of `log.txt`
+    pub fn log_text(&self) -> String {
+        let Some(ref log) = self.log else {
+            return "Log buffer was locked by the crashing thread\n".to_string();
+        };
+        log.iter().fold(String::new(), |mut text, entry| {
+            let _ = writeln!(text, "{} [{}][{}] {}", entry.timestamp, entry.target, entry.level, entry.message);
+            text
+        })
+    }
+
+    /// Write the report into a new folder under `root`
+    ///
+    /// # Arguments
+    /// * `root` - Directory holding all crash folders
+    /// * `time` - Time of the crash, names the folder
+    ///
+    /// # Returns
+    /// The folder written
+    ///
+    /// # Errors
+    /// Returns an error if the folder or a file cannot be written
+    pub fn write(&self, root: &Path, time: &DateTime<Local>) -> io::Result<PathBuf> {
+        let name = time.format("%Y-%m-%d_%H-%M-%S").to_string();
+        let mut dir = root.join(&name);
+        // A worker panic followed by a main thread crash in the same second keeps both
+        let mut suffix = 2;
+        while dir.exists() {
+            dir = root.join(format!("{}_{}", name, suffix));
+            suffix += 1;
+        }
+
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("crash.txt"), self.details(time))?;
+        fs::write(dir.join("log.txt"), self.log_text())?;
+        Ok(dir)
+    }
+}
+
+/// Capture and write a crash report
+///
+/// Reports problems on stderr only; the logger may be what crashed.
+///
+/// # Returns
+/// The crash folder, or None if it could not be written
+fn write_report(summary: &str) -> Option<PathBuf> {
+    let report = CrashReport::capture(summary);
+    match report.write(Path::new(config::crash::CRASH_DIR), &Local::now()) {
+        Ok(dir) => {
+            eprintln!("Crash report written to {}", dir.display());
+            Some(dir)
+        }
+        Err(e) => {
+            eprintln!("Failed to write crash report: {}", e);
+            None
+        }
+    }
+}
+
+/// Tell the user where the crash report is
+fn show_message(dir: &Path) {
+    if !config::crash::SHOW_MESSAGE_BOX {
+        return;
+    }
+    let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title(format!("{} crashed", config::window::TITLE))
+        .set_description(format!(
+            "The app hit a fatal error and has to close.\n\nA crash report was saved to:\n{}",
+            dir.display()
+        ))
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+/// Unhandled native exceptions through the Win32 top-level exception filter
+#[cfg(windows)]
+mod seh {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+
+    /// Leading fields of `EXCEPTION_RECORD`
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct ExceptionRecord {
+        code: u32,
+        flags: u32,
+        record: *mut ExceptionRecord,
+        address: *mut c_void,
+    }
+
+    /// `EXCEPTION_POINTERS`
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct ExceptionPointers {
+        record: *mut ExceptionRecord,
+        context: *mut c_void,
+    }
+
+    /// `MINIDUMP_EXCEPTION_INFORMATION`, declared with 4-byte packing
+    #[allow(dead_code)]
+    #[repr(C, packed(4))]
+    struct MinidumpExceptionInformation {
+        thread_id: u32,
+        exception_pointers: *mut ExceptionPointers,
+        client_pointers: i32,
+    }
+
+    type TopLevelExceptionFilter = unsafe extern "system" fn(*mut ExceptionPointers) -> i32;
+
+    /// Let Windows Error Reporting or an attached debugger see the crash too
+    const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+    /// `MiniDumpWithIndirectlyReferencedMemory | MiniDumpWithThreadInfo`
+    const MINIDUMP_TYPE: u32 = 0x0040 | 0x1000;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetUnhandledExceptionFilter(filter: Option<TopLevelExceptionFilter>) -> Option<TopLevelExceptionFilter>;
+        fn GetCurrentProcess() -> *mut c_void;
+        fn GetCurrentProcessId() -> u32;
+        fn GetCurrentThreadId() -> u32;
+    }
+
+    #[link(name = "dbghelp")]
+    extern "system" {
+        fn MiniDumpWriteDump(
+            process: *mut c_void,
+            process_id: u32,
+            file: *mut c_void,
+            dump_type: u32,
+            exception: *const MinidumpExceptionInformation,
+            user_stream: *const c_void,
+            callback: *const c_void,
+        ) -> i32;
+    }
+
+    /// Install `filter` as the process-wide unhandled exception filter
+    pub fn install() {
+        unsafe {
+            SetUnhandledExceptionFilter(Some(filter));
+        }
+    }
+
+    /// Readable name of common fatal exception codes
+    fn exception_name(code: u32) -> &'static str {
+        match code {
+            0xC000_0005 => "access violation",
+            0xC000_001D => "illegal instruction",
+            0xC000_0094 => "integer division by zero",
+            0xC000_00FD => "stack overflow",
+            0xC000_0409 => "stack buffer overrun",
+            _ => "exception",
+        }
+    }
+
+    unsafe extern "system" fn filter(pointers: *mut ExceptionPointers) -> i32 {
+        let record = if pointers.is_null() { std::ptr::null_mut() } else { (*pointers).record };
+        let summary = if record.is_null() {
+            "Unhandled native exception".to_string()
+        } else {
+            let code = (*record).code;
+            format!("Unhandled {} (0x{:08X}) at {:p}", exception_name(code), code, (*record).address)
+        };
+
+        if let Some(dir) = super::write_report(&summary) {
+            if let Err(e) = write_minidump(&dir.join("crash.dmp"), pointers) {
+                eprintln!("Failed to write minidump: {}", e);
+            }
+            super::show_message(&dir);
+        }
+        EXCEPTION_CONTINUE_SEARCH
+    }
+
+    /// Write a minidump of the process, pointing at the crashing thread's exception
+    unsafe fn write_minidump(path: &Path, pointers: *mut ExceptionPointers) -> io::Result<()> {
+        let file = File::create(path)?;
+        let exception = MinidumpExceptionInformation {
+            thread_id: GetCurrentThreadId(),
+            exception_pointers: pointers,
+            client_pointers: 0,
+        };
+        let exception_param = if pointers.is_null() { std::ptr::null() } else { &exception as *const _ };
+        let written = MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            file.as_raw_handle(),
+            MINIDUMP_TYPE,
+            exception_param,
+            std::ptr::null(),
+            std::ptr::null(),
+        );
+        if written == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_write_creates_a_folder_per_crash() {
+        let root = std::env::temp_dir().join(format!("vulkan-app-crash-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let report = CrashReport {
+            summary: "Panic: boom".to_string(),
+            thread: "main".to_string(),
+            backtrace: "0: main".to_string(),
+            gpu: Some("Test GPU (Discrete)".to_string()),
+            log: Some(vec![LogEntry {
+                timestamp: "12:00:00.000".to_string(),
+                level: Level::Error,
+                target: "app".to_string(),
+                message: "last words".to_string(),
+            }]),
+        };
+        let time = Local::now();
+
+        let first = report.write(&root, &time).unwrap();
+        let second = report.write(&root, &time).unwrap();
+        assert_ne!(first, second);
+
+        let details = fs::read_to_string(first.join("crash.txt")).unwrap();
+        assert!(details.starts_with("Panic: boom\n"));
+        assert!(details.contains("Test GPU (Discrete)"));
+        let log = fs::read_to_string(first.join("log.txt")).unwrap();
+        assert_eq!(log, "12:00:00.000 [app][ERROR] last words\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
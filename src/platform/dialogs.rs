@@ -0,0 +1,215 @@
+//! Native file dialogs
+//!
+//! Dialogs are opened through `rfd`'s async API and polled once per frame
+//! with a no-op waker, so the render loop keeps running while a dialog is
+//! open. Panels keep a `DialogSlot` tagged with what the chosen path is for
+//! and check it every time they render.
+//!
+//! ```ignore
+//! if ui.button("Browse...") {
+//!     self.dialog.open(Target::Font, DialogOptions::open_file("Open font").filter("Fonts", &["ttf", "otf"]));
+//! }
+//! if let Some((Target::Font, path)) = self.dialog.poll() { ... }
+//! ```
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use log::debug;
+
+/// What kind of dialog to show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogKind {
+    /// Choose an existing file
+    OpenFile,
+    /// Choose where to write a file
+    SaveFile,
+    /// Choose a directory
+    PickFolder,
+}
+
+/// Title, filters and starting location of a dialog
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogOptions {
+    /// Kind of dialog
+    pub kind: DialogKind,
+    /// Window title
+    pub title: String,
+    /// File type filters: name and extensions without the dot
+    pub filters: Vec<(String, Vec<String>)>,
+    /// Directory the dialog starts in
+    pub directory: Option<PathBuf>,
+    /// Suggested file name for save dialogs
+    pub file_name: Option<String>,
+}
+
+impl DialogOptions {
+    /// Options for a dialog of the given kind
+    fn new(kind: DialogKind, title: &str) -> Self {
+        Self {
+            kind,
+            title: title.to_string(),
+            filters: Vec::new(),
+            directory: None,
+            file_name: None,
+        }
+    }
+
+    /// Dialog choosing an existing file
+    pub fn open_file(title: &str) -> Self {
+        Self::new(DialogKind::OpenFile, title)
+    }
+
+    /// Dialog choosing where to save a file
+    pub fn save_file(title: &str) -> Self {
+        Self::new(DialogKind::SaveFile, title)
+    }
+
+    /// Dialog choosing a directory
+    pub fn pick_folder(title: &str) -> Self {
+        Self::new(DialogKind::PickFolder, title)
+    }
+
+    /// Add a file type filter
+    pub fn filter(mut self, name: &str, extensions: &[&str]) -> Self {
+        self.filters.push((name.to_string(), extensions.iter().map(|ext| ext.to_string()).collect()));
+        self
+    }
+
+    /// Start in a directory; a file path starts in its parent directory
+    pub fn directory(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.directory = match path.is_file() {
+            true => path.parent().map(|parent| parent.to_path_buf()),
+            false => Some(path),
+        };
+        self
+    }
+
+    /// Suggest a file name (save dialogs)
+    pub fn file_name(mut self, name: &str) -> Self {
+        self.file_name = Some(name.to_string());
+        self
+    }
+}
+
+/// A dialog that is open, resolving to the chosen path or None if cancelled
+pub struct PendingDialog {
+    future: Pin<Box<dyn Future<Output = Option<PathBuf>>>>,
+}
+
+impl PendingDialog {
+    /// Show a native dialog
+    pub fn show(options: DialogOptions) -> Self {
+        debug!("Opening {:?} dialog '{}'", options.kind, options.title);
+        let mut dialog = rfd::AsyncFileDialog::new().set_title(&options.title);
+        for (name, extensions) in &options.filters {
+            dialog = dialog.add_filter(name, extensions.as_slice());
+        }
+        if let Some(directory) = &options.directory {
+            dialog = dialog.set_directory(directory);
+        }
+        if let Some(file_name) = &options.file_name {
+            dialog = dialog.set_file_name(file_name);
+        }
+
+        let to_path = |handle: Option<rfd::FileHandle>| handle.map(|handle| handle.path().to_path_buf());
+        match options.kind {
+            DialogKind::OpenFile => Self::from_future(async move { to_path(dialog.pick_file().await) }),
+            DialogKind::SaveFile => Self::from_future(async move { to_path(dialog.save_file().await) }),
+            DialogKind::PickFolder => Self::from_future(async move { to_path(dialog.pick_folder().await) }),
+        }
+    }
+
+    /// Wrap any future resolving to an optional path
+    pub fn from_future(future: impl Future<Output = Option<PathBuf>> + 'static) -> Self {
+        Self { future: Box::pin(future) }
+    }
+
+    /// Check whether the dialog was closed, without blocking
+    pub fn poll(&mut self) -> Poll<Option<PathBuf>> {
+        // Polled every frame anyway, so nothing needs to be woken
+        let mut context = Context::from_waker(Waker::noop());
+        self.future.as_mut().poll(&mut context)
+    }
+}
+
+/// At most one open dialog, tagged with what its result is for
+pub struct DialogSlot<T> {
+    pending: Option<(T, PendingDialog)>,
+}
+
+impl<T> Default for DialogSlot<T> {
+    fn default() -> Self {
+        Self { pending: None }
+    }
+}
+
+impl<T> DialogSlot<T> {
+    /// Check whether a dialog is open
+    #[allow(dead_code)]
+    pub fn is_open(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Show a dialog unless one is already open
+    ///
+    /// # Arguments
+    /// * `target` - Returned with the chosen path
+    /// * `options` - The dialog to show
+    pub fn open(&mut self, target: T, options: DialogOptions) {
+        self.start(target, || PendingDialog::show(options));
+    }
+
+    /// Track a dialog created by `show` unless one is already open
+    pub fn start(&mut self, target: T, show: impl FnOnce() -> PendingDialog) {
+        if self.pending.is_none() {
+            self.pending = Some((target, show()));
+        }
+    }
+
+    /// Take the result of the open dialog once it was closed
+    ///
+    /// # Returns
+    /// The target and the chosen path; None while the dialog is open, when
+    /// it was cancelled and when no dialog is open
+    pub fn poll(&mut self) -> Option<(T, PathBuf)> {
+        let (_, dialog) = self.pending.as_mut()?;
+        let Poll::Ready(path) = dialog.poll() else {
+            return None;
+        };
+        let (target, _) = self.pending.take()?;
+        path.map(|path| (target, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_returns_chosen_path_once() {
+        let mut slot = DialogSlot::default();
+        slot.start("font", || PendingDialog::from_future(std::future::ready(Some(PathBuf::from("a.ttf")))));
+        // A second dialog is ignored while the first is open
+        slot.start("icons", || PendingDialog::from_future(std::future::ready(None)));
+
+        assert_eq!(slot.poll(), Some(("font", PathBuf::from("a.ttf"))));
+        assert!(!slot.is_open());
+        assert_eq!(slot.poll(), None);
+    }
+
+    #[test]
+    fn test_slot_cancel_and_pending() {
+        let mut slot = DialogSlot::default();
+        slot.start(1, || PendingDialog::from_future(std::future::pending()));
+        assert_eq!(slot.poll(), None);
+        assert!(slot.is_open());
+
+        let mut slot = DialogSlot::default();
+        slot.start(2, || PendingDialog::from_future(std::future::ready(None)));
+        assert_eq!(slot.poll(), None);
+        assert!(!slot.is_open());
+    }
+}
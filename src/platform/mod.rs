@@ -0,0 +1,7 @@
+//! Platform integration beyond windowing
+//!
+//! Native services of the desktop the app runs on, kept behind small
+//! helpers so the HUD and ECS code stay platform-agnostic.
+
+pub mod crash;
+pub mod dialogs;
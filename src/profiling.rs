@@ -0,0 +1,98 @@
+//! Span-based profiling
+//!
+//! Frame phases (fence wait, acquire, record, HUD, submit, present) and
+//! startup steps are wrapped in `tracing` spans. Without a profiling feature
+//! no subscriber is installed, so spans cost next to nothing and `tracing`
+//! events fall through to the `log` backend. With `trace-chrome` the spans
+//! are written to a Chrome trace file (viewable in chrome://tracing or
+//! Perfetto); with `trace-tracy` they are streamed to a running Tracy.
+//!
+//! The existing `log` macros keep working either way: `debug::init_logging`
+//! chains `log_bridge()`, which re-emits log records as `tracing` events when
+//! a subscriber is installed so they appear on the profiler timeline.
+
+#[cfg(any(feature = "trace-chrome", feature = "trace-tracy"))]
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Keeps the profiling backend alive; the Chrome trace is flushed on drop
+#[derive(Default)]
+pub struct ProfilingGuard {
+    #[cfg(feature = "trace-chrome")]
+    _chrome: Option<tracing_chrome::FlushGuard>,
+}
+
+/// Install the tracing subscriber selected by the enabled profiling features
+///
+/// Call once at startup and keep the guard until exit. Does nothing when
+/// built without `trace-chrome` or `trace-tracy`.
+pub fn init_profiling() -> ProfilingGuard {
+    #[allow(unused_mut)]
+    let mut guard = ProfilingGuard::default();
+
+    #[cfg(any(feature = "trace-chrome", feature = "trace-tracy"))]
+    {
+        let registry = tracing_subscriber::registry();
+
+        #[cfg(feature = "trace-chrome")]
+        let registry = {
+            let path = format!("trace-{}.json", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+            let (layer, flush_guard) = tracing_chrome::ChromeLayerBuilder::new()
+                .file(path)
+                .include_args(true)
+                .build();
+            guard._chrome = Some(flush_guard);
+            registry.with(layer)
+        };
+
+        #[cfg(feature = "trace-tracy")]
+        let registry = registry.with(tracing_tracy::TracyLayer::default());
+
+        // Not `try_init`: it would also claim the global `log` logger that fern installs
+        if let Err(e) = tracing::subscriber::set_global_default(registry) {
+            eprintln!("Failed to install the profiling subscriber: {}", e);
+        }
+    }
+
+    guard
+}
+
+/// `log` sink forwarding records to the installed tracing subscriber
+///
+/// # Returns
+/// None when built without a profiling feature; records then only go to the
+/// regular log outputs.
+pub fn log_bridge() -> Option<Box<dyn log::Log>> {
+    #[cfg(any(feature = "trace-chrome", feature = "trace-tracy"))]
+    {
+        Some(Box::new(TracingBridge))
+    }
+    #[cfg(not(any(feature = "trace-chrome", feature = "trace-tracy")))]
+    {
+        None
+    }
+}
+
+/// Re-emits `log` records as `tracing` events
+#[cfg(any(feature = "trace-chrome", feature = "trace-tracy"))]
+struct TracingBridge;
+
+#[cfg(any(feature = "trace-chrome", feature = "trace-tracy"))]
+impl log::Log for TracingBridge {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let target = record.target();
+        let message = record.args();
+        match record.level() {
+            log::Level::Error => tracing::error!(log.target = target, "{}", message),
+            log::Level::Warn => tracing::warn!(log.target = target, "{}", message),
+            log::Level::Info => tracing::info!(log.target = target, "{}", message),
+            log::Level::Debug => tracing::debug!(log.target = target, "{}", message),
+            log::Level::Trace => tracing::trace!(log.target = target, "{}", message),
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,315 @@
+//! Projects
+//!
+//! A project is a folder with a `project.toml` naming its shader directory,
+//! scene file, texture directories and export directories, all relative to
+//! the folder. While a project is open, asset paths that used to be relative
+//! to the working directory resolve against the project instead: a built-in
+//! `shaders/...` path is looked up in the project's shader directory, other
+//! assets in the project folder and then its texture directories. Files the
+//! project does not have fall back to the built-in ones, so a project only
+//! needs to contain what it changes. Output directories always resolve
+//! against the project folder.
+//!
+//! The open project is process-wide (`current`), so the shader compiler and
+//! the mesh loader can find files without it being passed through every
+//! layer. `RecentProjects` remembers the projects opened last.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+
+/// Project opened last, None until one is opened
+static CURRENT: Mutex<Option<Project>> = Mutex::new(None);
+
+/// Where a project writes its output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectExport {
+    /// Directory exported frames are written to
+    pub output_dir: PathBuf,
+    /// Directory screenshots are written to
+    pub screenshot_dir: PathBuf,
+}
+
+impl Default for ProjectExport {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from(config::export::OUTPUT_DIR),
+            screenshot_dir: PathBuf::from(config::export::SCREENSHOT_DIR),
+        }
+    }
+}
+
+/// Contents of a `project.toml` and the folder it was read from
+///
+/// The default project has an empty root, so its paths stay relative to the
+/// working directory as they are without a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    /// Folder holding `project.toml`, empty when no project is open
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// Display name, the folder name when not given
+    pub name: String,
+    /// Directory of the project's shaders, replacing the built-in `shaders`
+    pub shader_dir: PathBuf,
+    /// Scene loaded when the project is opened
+    pub scene_file: PathBuf,
+    /// Directories searched for textures and other assets
+    pub texture_dirs: Vec<PathBuf>,
+    /// Output directories
+    pub export: ProjectExport,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::new(),
+            name: String::new(),
+            shader_dir: PathBuf::from(config::hot_reload::SHADER_DIR),
+            scene_file: PathBuf::from(config::scene::SCENE_FILE),
+            texture_dirs: config::project::TEXTURE_DIRS.iter().map(PathBuf::from).collect(),
+            export: ProjectExport::default(),
+        }
+    }
+}
+
+impl Project {
+    /// Parse a project file; missing values keep their defaults
+    ///
+    /// # Arguments
+    /// * `text` - Contents of `project.toml`
+    /// * `root` - Folder the file is in
+    ///
+    /// # Errors
+    /// Returns an error if the text is not valid project TOML
+    pub fn from_toml(text: &str, root: &Path) -> Result<Self> {
+        let mut project: Self = toml::from_str(text)
+            .map_err(|e| AppError::Generic(format!("Invalid project: {}", e)))?;
+        project.root = root.to_path_buf();
+        if project.name.is_empty() {
+            project.name = root.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| "Project".to_string());
+        }
+        Ok(project)
+    }
+
+    /// Read a project
+    ///
+    /// # Arguments
+    /// * `path` - `project.toml`, or the folder containing it
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = match path.is_dir() {
+            true => path.join(config::project::FILE_NAME),
+            false => path.to_path_buf(),
+        };
+        let text = fs::read_to_string(&file).context(Subsystem::Io, format!("read project {}", file.display()))?;
+        // A bare file name is in the working directory
+        let root = file.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        Self::from_toml(&text, root).context(Subsystem::Io, format!("parse project {}", file.display()))
+    }
+
+    /// Whether this is a project read from disk rather than the default
+    pub fn is_open(&self) -> bool {
+        !self.root.as_os_str().is_empty()
+    }
+
+    /// The project file
+    pub fn file(&self) -> PathBuf {
+        self.root.join(config::project::FILE_NAME)
+    }
+
+    /// A path relative to the project folder; absolute paths are kept
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Directory of the project's shaders
+    pub fn shader_dir(&self) -> PathBuf {
+        self.resolve(&self.shader_dir)
+    }
+
+    /// Scene loaded when the project is opened
+    pub fn scene_file(&self) -> PathBuf {
+        self.resolve(&self.scene_file)
+    }
+
+    /// Directories searched for textures
+    pub fn texture_dirs(&self) -> Vec<PathBuf> {
+        self.texture_dirs.iter().map(|dir| self.resolve(dir)).collect()
+    }
+
+    /// Directory exported frames are written to
+    pub fn output_dir(&self) -> PathBuf {
+        self.resolve(&self.export.output_dir)
+    }
+
+    /// Directory screenshots are written to
+    pub fn screenshot_dir(&self) -> PathBuf {
+        self.resolve(&self.export.screenshot_dir)
+    }
+
+    /// Find an asset given by its working directory relative path
+    ///
+    /// Built-in shader paths (`shaders/...`) are looked up in the shader
+    /// directory, other paths in the project folder and then in the texture
+    /// directories. The path is returned as it is when the project does not
+    /// have the file, is not open, or the path is absolute.
+    pub fn locate(&self, path: &Path) -> PathBuf {
+        if !self.is_open() || path.is_absolute() {
+            return path.to_path_buf();
+        }
+        let in_shader_dir = path.strip_prefix(config::hot_reload::SHADER_DIR).ok().map(|rest| self.shader_dir().join(rest));
+        in_shader_dir
+            .into_iter()
+            .chain(std::iter::once(self.resolve(path)))
+            .chain(self.texture_dirs().into_iter().map(|dir| dir.join(path)))
+            .find(|candidate| candidate.is_file())
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+}
+
+/// The open project, or the default one when none is open
+pub fn current() -> Project {
+    CURRENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone().unwrap_or_default()
+}
+
+/// Make a project the open one
+pub fn set_current(project: Project) {
+    debug!("Project {:?} at {}", project.name, project.root.display());
+    *CURRENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(project);
+}
+
+/// Find an asset in the open project, see `Project::locate`
+pub fn locate(path: &Path) -> PathBuf {
+    match CURRENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_ref() {
+        Some(project) => project.locate(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Parse `--project PATH`, a `project.toml` or the folder containing it
+///
+/// # Returns
+/// `Ok(None)` if it was not given
+///
+/// # Errors
+/// Returns an error if the path is missing
+pub fn project_from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<PathBuf>> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let path = match arg.split_once('=') {
+            Some(("--project", value)) => value.to_string(),
+            None if arg == "--project" => args.next().ok_or_else(|| AppError::Generic("--project needs a project file or folder".to_string()))?,
+            _ => continue,
+        };
+        return Ok(Some(PathBuf::from(path)));
+    }
+    Ok(None)
+}
+
+/// Project files opened recently, the latest first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentProjects {
+    /// Project files
+    pub projects: Vec<PathBuf>,
+}
+
+impl RecentProjects {
+    /// Read the list, falling back to an empty one
+    ///
+    /// A missing file is expected; a broken one is logged.
+    pub fn load_or_default(path: &Path) -> Self {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        toml::from_str(&text).unwrap_or_else(|e| {
+            warn!("Ignoring recent projects in {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// Write the list
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string_pretty(self).map_err(|e| AppError::Generic(format!("Failed to serialize recent projects: {}", e)))?;
+        fs::write(path, text).context(Subsystem::Io, format!("write recent projects {}", path.display()))
+    }
+
+    /// Move a project file to the front, dropping the oldest beyond `config::project::MAX_RECENT`
+    pub fn push(&mut self, file: PathBuf) {
+        self.projects.retain(|recent| *recent != file);
+        self.projects.insert(0, file);
+        self.projects.truncate(config::project::MAX_RECENT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_paths_resolve_against_the_root() {
+        let text = r#"
+            shader_dir = "glsl"
+            texture_dirs = ["textures", "../shared"]
+            [export]
+            output_dir = "renders"
+        "#;
+        let project = Project::from_toml(text, Path::new("work/demo")).unwrap();
+        assert!(project.is_open());
+        assert_eq!(project.name, "demo");
+        assert_eq!(project.shader_dir(), Path::new("work/demo/glsl"));
+        assert_eq!(project.scene_file(), Path::new("work/demo").join(config::scene::SCENE_FILE));
+        assert_eq!(project.texture_dirs(), [PathBuf::from("work/demo/textures"), PathBuf::from("work/demo/../shared")]);
+        assert_eq!(project.output_dir(), Path::new("work/demo/renders"));
+        assert_eq!(project.file(), Path::new("work/demo").join(config::project::FILE_NAME));
+
+        // Without a project paths stay relative to the working directory
+        let default = Project::default();
+        assert!(!default.is_open());
+        assert_eq!(default.screenshot_dir(), Path::new(config::export::SCREENSHOT_DIR));
+        assert_eq!(default.locate(Path::new("shaders/sdf.frag")), Path::new("shaders/sdf.frag"));
+    }
+
+    #[test]
+    fn test_locate_prefers_project_files_over_built_in_ones() {
+        let root = std::env::temp_dir().join(format!("vulkan-app-project-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("glsl")).unwrap();
+        fs::create_dir_all(root.join("textures")).unwrap();
+        fs::write(root.join("glsl/sdf.frag"), "").unwrap();
+        fs::write(root.join("textures/sky.hdr"), "").unwrap();
+        let project = Project::from_toml("shader_dir = \"glsl\"", &root).unwrap();
+
+        assert_eq!(project.locate(Path::new("shaders/sdf.frag")), root.join("glsl/sdf.frag"));
+        assert_eq!(project.locate(Path::new("sky.hdr")), root.join("textures/sky.hdr"));
+        // Files the project does not have are the built-in ones
+        assert_eq!(project.locate(Path::new("shaders/imgui.frag")), Path::new("shaders/imgui.frag"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_recent_projects_are_unique_and_bounded() {
+        let mut recent = RecentProjects::default();
+        for index in 0..config::project::MAX_RECENT + 2 {
+            recent.push(PathBuf::from(format!("p{}/project.toml", index)));
+        }
+        recent.push(PathBuf::from("p5/project.toml"));
+        assert_eq!(recent.projects.len(), config::project::MAX_RECENT);
+        assert_eq!(recent.projects[0], Path::new("p5/project.toml"));
+        assert_eq!(recent.projects.iter().filter(|path| path.ends_with("p5/project.toml")).count(), 1);
+
+        assert_eq!(project_from_args(["--project=demo".to_string()]).unwrap(), Some(PathBuf::from("demo")));
+        assert!(project_from_args(["--project".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Renderer abstraction
+//!
+//! The ECS world hands each frame's output to a `Renderer`: the scene data,
+//! debug lines, mesh draws, texts and particle emitters built by its systems. `VulkanRenderer` is the
+//! real implementation; `NullRenderer` keeps what it receives and draws
+//! nothing, so the systems, the frame loop and everything around them run
+//! under `cargo test` without a GPU.
+//!
+//! The world stores the renderer as a `SharedRenderer` resource. Features
+//! that need Vulkan itself (the HUD, exports, shader reload) still look up
+//! the `VulkanRenderer` and are unavailable without one.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use cgmath::Vector3;
+use crate::error::Result;
+use crate::vulkan::debug_draw::DebugDrawList;
+use crate::vulkan::mesh::MeshDrawList;
+use crate::vulkan::particles::ParticleEmitterList;
+use crate::vulkan::text::TextDrawList;
+use crate::vulkan::scene_buffer::GpuSceneData;
+
+/// Receives the per-frame output of the ECS world and presents it
+pub trait Renderer: Send {
+    /// Set the scene data uploaded with the next frame
+    fn set_scene_data(&mut self, scene_data: GpuSceneData);
+
+    /// Set the debug lines drawn with the next frame
+    fn set_debug_lines(&mut self, lines: DebugDrawList);
+
+    /// Set the meshes drawn with the next frame
+    fn set_mesh_draws(&mut self, draws: MeshDrawList);
+
+    /// Mesh space bounding spheres of the meshes uploaded so far, by file path
+    fn mesh_bounds(&self) -> HashMap<String, (Vector3<f32>, f32)>;
+
+    /// Set the texts drawn with the next frame
+    fn set_text_draws(&mut self, draws: TextDrawList);
+
+    /// Set the particle emitters simulated with the next frame
+    fn set_particle_emitters(&mut self, emitters: ParticleEmitterList);
+
+    /// Draw and present a frame
+    ///
+    /// # Errors
+    /// Returns an error if the frame cannot be drawn
+    fn draw_frame(&mut self) -> Result<()>;
+
+    /// Adapt to a new window size
+    ///
+    /// # Errors
+    /// Returns an error if the render targets cannot be recreated
+    fn handle_resize(&mut self, width: u32, height: u32) -> Result<()>;
+}
+
+/// Renderer shared between the ECS world and its systems
+pub type SharedRenderer = Arc<Mutex<dyn Renderer>>;
+
+/// Renderer without a GPU that records what it is given
+#[derive(Debug, Default)]
+#[allow(dead_code)] // Used by tests and headless tools
+pub struct NullRenderer {
+    /// Scene data of the latest frame
+    pub scene_data: GpuSceneData,
+    /// Number of debug lines of the latest frame
+    pub debug_line_count: usize,
+    /// Number of mesh draws of the latest frame
+    pub mesh_draw_count: usize,
+    /// Number of text draws of the latest frame
+    pub text_draw_count: usize,
+    /// Number of particle emitters of the latest frame
+    pub particle_emitter_count: usize,
+    /// Frames drawn so far
+    pub frames_drawn: u64,
+    /// Size passed to the latest resize
+    pub extent: Option<(u32, u32)>,
+}
+
+#[allow(dead_code)] // Used by tests and headless tools
+impl NullRenderer {
+    /// Create a null renderer wrapped for sharing, plus a handle for inspecting it
+    pub fn shared() -> (SharedRenderer, Arc<Mutex<NullRenderer>>) {
+        let renderer = Arc::new(Mutex::new(NullRenderer::default()));
+        (Arc::clone(&renderer) as SharedRenderer, renderer)
+    }
+}
+
+impl Renderer for NullRenderer {
+    fn set_scene_data(&mut self, scene_data: GpuSceneData) {
+        self.scene_data = scene_data;
+    }
+
+    fn set_debug_lines(&mut self, lines: DebugDrawList) {
+        self.debug_line_count = lines.line_count();
+    }
+
+    fn set_mesh_draws(&mut self, draws: MeshDrawList) {
+        self.mesh_draw_count = draws.draws().len();
+    }
+
+    fn mesh_bounds(&self) -> HashMap<String, (Vector3<f32>, f32)> {
+        HashMap::new()
+    }
+
+    fn set_text_draws(&mut self, draws: TextDrawList) {
+        self.text_draw_count = draws.draws().len();
+    }
+
+    fn set_particle_emitters(&mut self, emitters: ParticleEmitterList) {
+        self.particle_emitter_count = emitters.emitters().len();
+    }
+
+    fn draw_frame(&mut self) -> Result<()> {
+        self.frames_drawn += 1;
+        Ok(())
+    }
+
+    fn handle_resize(&mut self, width: u32, height: u32) -> Result<()> {
+        self.extent = Some((width, height));
+        Ok(())
+    }
+}
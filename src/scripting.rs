@@ -0,0 +1,463 @@
+//! Rhai scripting
+//!
+//! Every `.rhai` file in `config::scripting::SCRIPTS_DIR` is a script. When
+//! a script is loaded its top level runs once, then its optional functions
+//! are called with `this` bound to a map that persists between calls:
+//!
+//! ```rhai
+//! fn init() {
+//!     this.ball = spawn("sphere");
+//!     this.time = 0.0;
+//!     button("Reset", "reset");
+//! }
+//! fn update(dt) {
+//!     this.time += dt;
+//!     set_position(this.ball, this.time.sin(), 1.0, 0.0);
+//! }
+//! fn reset() { this.time = 0.0; }
+//! ```
+//!
+//! `init()` runs after loading, `update(dt)` every frame with the frame time
+//! in seconds, and functions bound with `button(label, function)` when the
+//! button is pressed in the Scripts panel. Numbers passed to the entity
+//! functions are floats (`1.0`, not `1`).
+//!
+//! Re-loading a script despawns the entities it spawned and starts it over;
+//! if the new version does not compile, the old one keeps running. A script
+//! whose call fails stops being updated until it is re-loaded.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use legion::{Entity, World};
+use log::{debug, info, warn};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use crate::config;
+use crate::ecs::components::{LocalTransform, SDFMaterial, SDFRenderable, SDFShape, SDFShapeType, Transform};
+use crate::error::{AppError, EcsError, Result, ResultExt, Subsystem};
+use crate::hud::scripts_panel::{ScriptButton, ScriptView};
+
+/// Result of the functions registered with the engine
+type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// State the registered functions work on while a script runs
+#[derive(Default)]
+struct ScriptState {
+    /// The ECS world, moved in for the duration of a script call
+    world: World,
+    /// Entities spawned by the running script
+    spawned: Vec<Entity>,
+    /// Buttons bound by the running script
+    buttons: Vec<ScriptButton>,
+}
+
+/// A loaded script
+struct Script {
+    /// Source file
+    path: PathBuf,
+    /// File name shown in the HUD
+    name: String,
+    ast: AST,
+    /// Value bound to `this` in every call
+    this: Dynamic,
+    /// Entities to despawn when the script is re-loaded or removed
+    spawned: Vec<Entity>,
+    buttons: Vec<ScriptButton>,
+    /// Last error; updates stop until the script is re-loaded
+    error: Option<String>,
+}
+
+/// Whether a script defines a function
+fn has_function(ast: &AST, name: &str, arity: usize) -> bool {
+    ast.iter_functions().any(|function| function.name == name && function.params.len() == arity)
+}
+
+/// Error for an entity the world does not contain
+fn missing_entity(entity: Entity) -> Box<EvalAltResult> {
+    format!("entity {:?} does not exist", entity).into()
+}
+
+/// Error for an entity without a component a function needs
+fn missing_component(entity: Entity, component: &str) -> Box<EvalAltResult> {
+    format!("entity {:?} has no {}", entity, component).into()
+}
+
+/// Loads, runs and re-loads the scripts of a folder
+pub struct ScriptHost {
+    engine: Engine,
+    state: Rc<RefCell<ScriptState>>,
+    scripts: Vec<Script>,
+    /// Folder the scripts are loaded from
+    directory: PathBuf,
+}
+
+impl ScriptHost {
+    /// Create a host for the scripts of a folder, without loading them
+    ///
+    /// # Arguments
+    /// * `directory` - Folder of `.rhai` files
+    pub fn new(directory: &Path) -> Self {
+        let state = Rc::new(RefCell::new(ScriptState::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(config::scripting::MAX_OPERATIONS);
+        engine.on_print(|text| info!("[script] {}", text));
+        Self::register_api(&mut engine, &state);
+
+        Self {
+            engine,
+            state,
+            scripts: Vec::new(),
+            directory: directory.to_path_buf(),
+        }
+    }
+
+    /// Folder the scripts are loaded from
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Entities spawned by the loaded scripts
+    pub fn spawned(&self) -> Vec<Entity> {
+        self.scripts.iter().flat_map(|script| script.spawned.iter().copied()).collect()
+    }
+
+    /// Register the entity and HUD functions available to scripts
+    fn register_api(engine: &mut Engine, state: &Rc<RefCell<ScriptState>>) {
+        engine.register_type_with_name::<Entity>("Entity");
+        engine.register_fn("to_string", |entity: &mut Entity| format!("{:?}", entity));
+
+        let spawn_state = Rc::clone(state);
+        engine.register_fn("spawn", move |shape: &str| -> ScriptResult<Entity> {
+            let shape_type = SDFShapeType::deserialize(shape.into_deserializer())
+                .map_err(|_: serde::de::value::Error| format!("unknown shape '{}'", shape))?;
+            let mut state = spawn_state.borrow_mut();
+            let entity = state.world.push((
+                SDFShape { shape_type, ..SDFShape::default() },
+                SDFMaterial::default(),
+                Transform::default(),
+                SDFRenderable,
+            ));
+            state.spawned.push(entity);
+            Ok(entity)
+        });
+
+        let despawn_state = Rc::clone(state);
+        engine.register_fn("despawn", move |entity: Entity| {
+            let mut state = despawn_state.borrow_mut();
+            state.world.remove(entity);
+            state.spawned.retain(|&spawned| spawned != entity);
+        });
+
+        // Transform setters write the local transform of child entities, as
+        // the world transform is computed from it every frame
+        let transform_setters: [(&str, fn(&mut Transform, [f32; 3])); 3] = [
+            ("set_position", |transform, value| transform.position = value.into()),
+            ("set_rotation", |transform, value| transform.rotation = value.into()),
+            ("set_scale", |transform, value| transform.scale = value.into()),
+        ];
+        for (name, set) in transform_setters {
+            let setter_state = Rc::clone(state);
+            engine.register_fn(name, move |entity: Entity, x: FLOAT, y: FLOAT, z: FLOAT| -> ScriptResult<()> {
+                let mut state = setter_state.borrow_mut();
+                let mut entry = state.world.entry(entity).ok_or_else(|| missing_entity(entity))?;
+                let value = [x as f32, y as f32, z as f32];
+                if let Ok(local) = entry.get_component_mut::<LocalTransform>() {
+                    set(&mut local.0, value);
+                    return Ok(());
+                }
+                let transform = entry.get_component_mut::<Transform>().map_err(|_| missing_component(entity, "transform"))?;
+                set(transform, value);
+                Ok(())
+            });
+        }
+
+        let position_state = Rc::clone(state);
+        engine.register_fn("position", move |entity: Entity| -> ScriptResult<Array> {
+            let state = position_state.borrow();
+            let entry = state.world.entry_ref(entity).map_err(|_| missing_entity(entity))?;
+            let position = entry.get_component::<Transform>().map_err(|_| missing_component(entity, "transform"))?.position;
+            Ok(vec![
+                Dynamic::from(position.x as FLOAT),
+                Dynamic::from(position.y as FLOAT),
+                Dynamic::from(position.z as FLOAT),
+            ])
+        });
+
+        let color_state = Rc::clone(state);
+        engine.register_fn("set_color", move |entity: Entity, r: FLOAT, g: FLOAT, b: FLOAT| -> ScriptResult<()> {
+            let mut state = color_state.borrow_mut();
+            let mut entry = state.world.entry(entity).ok_or_else(|| missing_entity(entity))?;
+            let material = entry.get_component_mut::<SDFMaterial>().map_err(|_| missing_component(entity, "material"))?;
+            material.color = cgmath::Vector3::new(r as f32, g as f32, b as f32);
+            Ok(())
+        });
+
+        let emission_state = Rc::clone(state);
+        engine.register_fn("set_emission", move |entity: Entity, emission: FLOAT| -> ScriptResult<()> {
+            let mut state = emission_state.borrow_mut();
+            let mut entry = state.world.entry(entity).ok_or_else(|| missing_entity(entity))?;
+            let material = entry.get_component_mut::<SDFMaterial>().map_err(|_| missing_component(entity, "material"))?;
+            material.emission = emission as f32;
+            Ok(())
+        });
+
+        let button_state = Rc::clone(state);
+        engine.register_fn("button", move |label: &str, function: &str| {
+            button_state.borrow_mut().buttons.push(ScriptButton {
+                label: label.to_string(),
+                function: function.to_string(),
+            });
+        });
+    }
+
+    /// Move the world into the script state for the duration of `run`
+    fn with_world<R>(&mut self, world: &mut World, run: impl FnOnce(&mut Self) -> R) -> R {
+        std::mem::swap(world, &mut self.state.borrow_mut().world);
+        let result = run(self);
+        std::mem::swap(world, &mut self.state.borrow_mut().world);
+        result
+    }
+
+    /// Call a script function with `this` bound, recording what it spawned and bound
+    fn call(engine: &Engine, state: &Rc<RefCell<ScriptState>>, script: &mut Script, function: &str, args: Vec<Dynamic>) -> Result<()> {
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut script.this);
+        let result = engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &script.ast, function, args);
+
+        let mut state = state.borrow_mut();
+        script.spawned.append(&mut state.spawned);
+        script.buttons.append(&mut state.buttons);
+        result.map(|_| ()).map_err(|e| {
+            let message = format!("{}: {}() failed: {}", script.name, function, e);
+            script.error = Some(message.clone());
+            EcsError::Script(message).into()
+        })
+    }
+
+    /// Compile a script and run its top level and `init()`
+    ///
+    /// # Errors
+    /// Returns an error if the source does not compile; a failing top level
+    /// or `init()` is recorded on the script instead
+    fn start(&mut self, path: &Path, source: &str) -> Result<Script> {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let ast = self.engine.compile(source)
+            .map_err(|e| AppError::from(EcsError::Script(format!("{}: {}", name, e))))?;
+
+        let mut script = Script {
+            path: path.to_path_buf(),
+            name,
+            ast,
+            this: Map::new().into(),
+            spawned: Vec::new(),
+            buttons: Vec::new(),
+            error: None,
+        };
+        let top_level = self.engine.run_ast_with_scope(&mut Scope::new(), &script.ast);
+        {
+            let mut state = self.state.borrow_mut();
+            script.spawned.append(&mut state.spawned);
+            script.buttons.append(&mut state.buttons);
+        }
+        match top_level {
+            Ok(()) if has_function(&script.ast, "init", 0) => {
+                if let Err(e) = Self::call(&self.engine, &self.state, &mut script, "init", Vec::new()) {
+                    warn!("{}", e);
+                }
+            }
+            Ok(()) => {}
+            Err(e) => {
+                warn!("Script {} failed: {}", script.name, e);
+                script.error = Some(e.to_string());
+            }
+        }
+        info!("Loaded script {:?}", path);
+        Ok(script)
+    }
+
+    /// Despawn the entities a script spawned
+    fn stop(world: &mut World, script: &Script) {
+        for &entity in &script.spawned {
+            world.remove(entity);
+        }
+    }
+
+    /// Load every script of the folder
+    ///
+    /// Scripts that fail to compile are skipped and returned as errors.
+    ///
+    /// # Returns
+    /// The errors of the scripts that could not be loaded
+    pub fn load_all(&mut self, world: &mut World) -> Vec<AppError> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension == "rhai"))
+                .collect(),
+            Err(_) => {
+                debug!("No scripts folder at {:?}", self.directory);
+                return Vec::new();
+            }
+        };
+        paths.sort();
+
+        let mut errors = Vec::new();
+        for path in paths {
+            if let Err(e) = self.reload(world, &path) {
+                errors.push(e);
+            }
+        }
+        errors
+    }
+
+    /// Unload every script and load the folder again
+    ///
+    /// # Errors
+    /// Returns the first error of a script that could not be loaded
+    pub fn reload_all(&mut self, world: &mut World) -> Result<()> {
+        for script in std::mem::take(&mut self.scripts) {
+            Self::stop(world, &script);
+        }
+        match self.load_all(world).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Load, re-load or unload a script after its file changed
+    ///
+    /// # Arguments
+    /// * `world` - The ECS world the script works on
+    /// * `path` - The changed file; a missing file unloads its script
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or does not compile; a
+    /// previously loaded version keeps running then
+    pub fn reload(&mut self, world: &mut World, path: &Path) -> Result<()> {
+        // Watcher events carry canonical paths; removed files keep theirs
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let path = canonical.as_path();
+        let index = self.scripts.iter().position(|script| script.path == path);
+        if !path.exists() {
+            if let Some(index) = index {
+                let script = self.scripts.remove(index);
+                Self::stop(world, &script);
+                info!("Unloaded script {:?}", path);
+            }
+            return Ok(());
+        }
+
+        let source = fs::read_to_string(path).context(Subsystem::Ecs, format!("read script {}", path.display()))?;
+        self.with_world(world, |host| {
+            let script = host.start(path, &source)?;
+            match index {
+                Some(index) => {
+                    let old = std::mem::replace(&mut host.scripts[index], script);
+                    Self::stop(&mut host.state.borrow_mut().world, &old);
+                }
+                None => host.scripts.push(script),
+            }
+            Ok(())
+        })
+    }
+
+    /// Call `update(dt)` of every running script
+    ///
+    /// # Returns
+    /// The errors of scripts that failed in this frame; they are not
+    /// updated again until re-loaded
+    pub fn update(&mut self, world: &mut World, delta_seconds: f32) -> Vec<AppError> {
+        if self.scripts.is_empty() {
+            return Vec::new();
+        }
+        self.with_world(world, |host| {
+            let mut errors = Vec::new();
+            for script in &mut host.scripts {
+                if script.error.is_some() || !has_function(&script.ast, "update", 1) {
+                    continue;
+                }
+                let args = vec![Dynamic::from(delta_seconds as FLOAT)];
+                if let Err(e) = Self::call(&host.engine, &host.state, script, "update", args) {
+                    errors.push(e);
+                }
+            }
+            errors
+        })
+    }
+
+    /// Call the function bound to a HUD button
+    ///
+    /// # Errors
+    /// Returns an error if the script does not exist or the call fails
+    pub fn press(&mut self, world: &mut World, script_name: &str, function: &str) -> Result<()> {
+        self.with_world(world, |host| {
+            let script = host.scripts
+                .iter_mut()
+                .find(|script| script.name == script_name)
+                .ok_or_else(|| EcsError::Script(format!("script {} is not loaded", script_name)))?;
+            Self::call(&host.engine, &host.state, script, function, Vec::new())
+        })
+    }
+
+    /// Snapshot of the scripts for the Scripts panel
+    pub fn views(&self) -> Vec<ScriptView> {
+        self.scripts
+            .iter()
+            .map(|script| ScriptView {
+                name: script.name.clone(),
+                error: script.error.clone(),
+                buttons: script.buttons.clone(),
+                entities: script.spawned.len(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::IntoQuery;
+
+    const MOVER: &str = r#"
+        fn init() {
+            this.ball = spawn("sphere");
+            button("Reset", "reset");
+        }
+        fn update(dt) { set_position(this.ball, dt, 0.0, 0.0); }
+        fn reset() { despawn(this.ball); }
+    "#;
+
+    fn positions(world: &World) -> Vec<f32> {
+        <&Transform>::query().iter(world).map(|transform| transform.position.x).collect()
+    }
+
+    #[test]
+    fn test_script_spawns_updates_and_binds_buttons() {
+        let mut world = World::default();
+        let mut host = ScriptHost::new(Path::new("scripts"));
+        let script = host.with_world(&mut world, |host| host.start(Path::new("mover.rhai"), MOVER)).unwrap();
+        host.scripts.push(script);
+
+        assert!(host.update(&mut world, 0.5).is_empty());
+        assert_eq!(positions(&world), vec![0.5]);
+        assert_eq!(host.views()[0].buttons[0].function, "reset");
+
+        host.press(&mut world, "mover.rhai", "reset").unwrap();
+        assert!(positions(&world).is_empty());
+    }
+
+    #[test]
+    fn test_failing_script_stops_updating() {
+        let mut world = World::default();
+        let mut host = ScriptHost::new(Path::new("scripts"));
+        assert!(host.with_world(&mut world, |host| host.start(Path::new("bad.rhai"), "fn update(dt) {")).is_err());
+
+        let script = host.with_world(&mut world, |host| host.start(Path::new("typo.rhai"), "fn update(dt) { spawn(\"blob\"); }")).unwrap();
+        host.scripts.push(script);
+        assert_eq!(host.update(&mut world, 0.1).len(), 1);
+        assert!(host.update(&mut world, 0.1).is_empty());
+        assert!(host.views()[0].error.is_some());
+    }
+}
@@ -0,0 +1,362 @@
+//! Runtime settings
+//!
+//! The part of the configuration that can change while the app runs. The
+//! `config` constants are the defaults, `settings.toml` overrides them at
+//! startup, and the Settings window edits a copy that is applied as a whole.
+//! `SettingsChanges` tells the caller which parts of the renderer have to be
+//! rebuilt for an edit; `FrameLimiter` paces redraws to the frame rate cap.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::config;
+use crate::hud::ToolbarPosition;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+use crate::image_io::HdrFormat;
+use crate::vulkan::outline::OutlineSettings;
+use crate::vulkan::quality::RaymarchQuality;
+use crate::vulkan::swapchain::PresentMode;
+
+/// Antialiasing sample counts offered in the Settings window
+pub const MSAA_SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+/// Hot reload options
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotReloadSettings {
+    /// Watch shaders, the scene file and scripts for changes
+    pub enabled: bool,
+    /// Quiet time after a shader change before it is reloaded, in milliseconds
+    pub debounce_ms: u64,
+    /// Validate reloaded pipelines with a test draw before swapping them in
+    pub safe_mode: bool,
+}
+
+impl Default for HotReloadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: config::hot_reload::ENABLED,
+            debounce_ms: config::hot_reload::DEBOUNCE_MS,
+            safe_mode: config::hot_reload::SAFE_MODE,
+        }
+    }
+}
+
+/// Scene auto-save options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutosaveSettings {
+    /// Save the scene to the auto-save folder periodically
+    pub enabled: bool,
+    /// Seconds between auto-saves
+    pub interval_secs: u32,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: config::autosave::ENABLED,
+            interval_secs: config::autosave::INTERVAL_SECS,
+        }
+    }
+}
+
+/// Settings that can be edited while the app runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Preferred swapchain presentation mode
+    pub present_mode: PresentMode,
+    /// Name of the monitor windowed fullscreen covers, None for the one the window is on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fullscreen_monitor: Option<String>,
+    /// Antialiasing rays per pixel of the SDF pass, one of `MSAA_SAMPLE_COUNTS`
+    pub msaa_samples: u32,
+    /// Raymarch quality tier
+    pub raymarch_quality: RaymarchQuality,
+    /// Frame rate cap in frames per second, 0 for none
+    pub frame_limit: u32,
+    /// HUD theme: a preset name or the file stem of a custom theme
+    pub theme: String,
+    /// HUD language code, the file stem of a locale (see the `locale` module)
+    pub language: String,
+    /// Screen edge the HUD toolbar is anchored to
+    pub toolbar_position: ToolbarPosition,
+    /// Directory watched for shader changes
+    pub shader_dir: PathBuf,
+    /// Further directories watched for shader changes
+    pub include_dirs: Vec<PathBuf>,
+    /// Hot reload options
+    pub hot_reload: HotReloadSettings,
+    /// Outline around the selected entity
+    pub outline: OutlineSettings,
+    /// Scene auto-save options
+    pub autosave: AutosaveSettings,
+    /// Seconds the camera takes to move to a bookmark or frame the selection, 0 to jump
+    pub camera_transition_secs: f32,
+    /// Format of a linear HDR copy saved next to each screenshot, None for PNG only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot_hdr: Option<HdrFormat>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            present_mode: PresentMode::default(),
+            fullscreen_monitor: None,
+            msaa_samples: config::settings::MSAA_SAMPLES,
+            raymarch_quality: RaymarchQuality::default(),
+            frame_limit: config::settings::FRAME_LIMIT,
+            theme: config::hud_theme::DEFAULT_THEME.to_string(),
+            language: config::locale::LANGUAGE.to_string(),
+            toolbar_position: ToolbarPosition::default(),
+            shader_dir: PathBuf::from(config::hot_reload::SHADER_DIR),
+            include_dirs: config::hot_reload::INCLUDE_DIRS.iter().map(PathBuf::from).collect(),
+            hot_reload: HotReloadSettings::default(),
+            outline: OutlineSettings::default(),
+            autosave: AutosaveSettings::default(),
+            camera_transition_secs: config::camera::CAMERA_TRANSITION_SECS,
+            screenshot_hdr: None,
+        }
+    }
+}
+
+/// What has to be rebuilt to apply edited settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SettingsChanges {
+    /// Present mode changed: recreate the swapchain
+    pub swapchain: bool,
+    /// Antialiasing changed: switch the SDF shader variant
+    pub shader_variant: bool,
+    /// Raymarch quality changed: rebuild the SDF pipeline
+    pub quality: bool,
+    /// Theme changed: apply it to the HUD
+    pub theme: bool,
+    /// Language changed: switch the HUD strings and load the glyphs they need
+    pub language: bool,
+    /// Toolbar position changed: move the toolbar
+    pub toolbar: bool,
+    /// Shader directories or hot reload options changed: restart the watchers
+    pub hot_reload: bool,
+    /// Outline options changed: hand them to the renderer
+    pub outline: bool,
+    /// Auto-save options changed: reschedule the auto-save
+    pub autosave: bool,
+    /// Camera options changed: hand them to the world
+    pub camera: bool,
+    /// Fullscreen monitor changed: move a fullscreen window onto it
+    pub fullscreen: bool,
+    /// Screenshot options changed: hand them to the world
+    pub screenshot: bool,
+}
+
+impl AppSettings {
+    /// Parse settings from TOML; missing values keep their defaults
+    ///
+    /// # Errors
+    /// Returns an error if the text is not valid settings TOML
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let settings: Self = toml::from_str(text)
+            .map_err(|e| AppError::Generic(format!("Invalid settings: {}", e)))?;
+        Ok(settings.sanitized())
+    }
+
+    /// Serialize the settings as TOML
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| AppError::Generic(format!("Failed to serialize settings: {}", e)))
+    }
+
+    /// Read a settings file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).context(Subsystem::Io, format!("read settings {}", path.display()))?;
+        Self::from_toml(&text).context(Subsystem::Io, format!("parse settings {}", path.display()))
+    }
+
+    /// Read a settings file, falling back to the defaults
+    ///
+    /// A missing file is expected; a broken one is logged.
+    pub fn load_or_default(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load(path).unwrap_or_else(|e| {
+            warn!("Using default settings: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Write the settings file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_toml()?).context(Subsystem::Io, format!("write settings {}", path.display()))
+    }
+
+    /// Snap values a hand-edited file may get wrong to supported ones
+    fn sanitized(mut self) -> Self {
+        self.msaa_samples = MSAA_SAMPLE_COUNTS
+            .into_iter()
+            .rev()
+            .find(|&count| count <= self.msaa_samples)
+            .unwrap_or(1);
+        self.frame_limit = self.frame_limit.min(config::settings::MAX_FRAME_LIMIT);
+        self.outline.thickness = self.outline.clamped_thickness();
+        self.autosave.interval_secs = self.autosave.interval_secs
+            .clamp(config::autosave::MIN_INTERVAL_SECS, config::autosave::MAX_INTERVAL_SECS);
+        self.camera_transition_secs = self.camera_transition_secs.clamp(0.0, config::camera::MAX_CAMERA_TRANSITION_SECS);
+        self
+    }
+
+    /// Compare with the settings currently applied
+    ///
+    /// # Arguments
+    /// * `applied` - Settings the app is running with
+    pub fn changes(&self, applied: &AppSettings) -> SettingsChanges {
+        SettingsChanges {
+            swapchain: self.present_mode != applied.present_mode,
+            shader_variant: self.msaa_samples != applied.msaa_samples,
+            quality: self.raymarch_quality != applied.raymarch_quality,
+            theme: self.theme != applied.theme,
+            language: self.language != applied.language,
+            toolbar: self.toolbar_position != applied.toolbar_position,
+            hot_reload: self.shader_dir != applied.shader_dir
+                || self.include_dirs != applied.include_dirs
+                || self.hot_reload != applied.hot_reload,
+            outline: self.outline != applied.outline,
+            autosave: self.autosave != applied.autosave,
+            camera: self.camera_transition_secs != applied.camera_transition_secs,
+            fullscreen: self.fullscreen_monitor != applied.fullscreen_monitor,
+            screenshot: self.screenshot_hdr != applied.screenshot_hdr,
+        }
+    }
+}
+
+/// Paces redraws to a frame rate cap
+#[derive(Debug, Default)]
+pub struct FrameLimiter {
+    /// Time between frames, None without a cap
+    interval: Option<Duration>,
+    /// When the next frame is due
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// Create a limiter for a cap in frames per second, 0 for none
+    pub fn new(frame_limit: u32) -> Self {
+        let mut limiter = Self::default();
+        limiter.set_limit(frame_limit);
+        limiter
+    }
+
+    /// Change the cap in frames per second, 0 for none
+    pub fn set_limit(&mut self, frame_limit: u32) {
+        self.interval = (frame_limit > 0).then(|| Duration::from_secs_f64(1.0 / frame_limit as f64));
+        self.next_frame = None;
+    }
+
+    /// Check whether the next frame is due
+    ///
+    /// A frame that is late by more than one interval restarts the pacing
+    /// instead of letting several frames run back to back to catch up.
+    ///
+    /// # Returns
+    /// None if a frame should be drawn now, otherwise when to check again
+    pub fn wait_until(&mut self, now: Instant) -> Option<Instant> {
+        let interval = self.interval?;
+        match self.next_frame {
+            Some(next) if now < next => Some(next),
+            Some(next) if now - next < interval => {
+                self.next_frame = Some(next + interval);
+                None
+            }
+            _ => {
+                self.next_frame = Some(now + interval);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_round_trip_and_sanitizing() {
+        let settings = AppSettings {
+            present_mode: PresentMode::Immediate,
+            msaa_samples: 4,
+            frame_limit: 60,
+            theme: "Light".to_string(),
+            toolbar_position: ToolbarPosition::Left,
+            fullscreen_monitor: Some("DISPLAY2".to_string()),
+            screenshot_hdr: Some(HdrFormat::Radiance),
+            ..AppSettings::default()
+        };
+        assert_eq!(AppSettings::from_toml(&settings.to_toml().unwrap()).unwrap(), settings);
+
+        let parsed = AppSettings::from_toml("msaa_samples = 5\nframe_limit = 100000\n[hot_reload]\ndebounce_ms = 50\n").unwrap();
+        assert_eq!(parsed.msaa_samples, 4);
+        assert_eq!(parsed.frame_limit, config::settings::MAX_FRAME_LIMIT);
+        assert_eq!(parsed.hot_reload.debounce_ms, 50);
+        assert_eq!(parsed.hot_reload.safe_mode, config::hot_reload::SAFE_MODE);
+        let parsed = AppSettings::from_toml("[outline]\nthickness = 50.0\n").unwrap();
+        assert_eq!(parsed.outline.thickness, config::outline::MAX_THICKNESS);
+        assert_eq!(parsed.outline.color, config::outline::COLOR);
+        let parsed = AppSettings::from_toml("[autosave]\ninterval_secs = 1\n").unwrap();
+        assert_eq!(parsed.autosave.interval_secs, config::autosave::MIN_INTERVAL_SECS);
+        assert!(parsed.autosave.enabled);
+        let parsed = AppSettings::from_toml("camera_transition_secs = -1.0\n").unwrap();
+        assert_eq!(parsed.camera_transition_secs, 0.0);
+        let parsed = AppSettings::from_toml("toolbar_position = \"bottom\"\n").unwrap();
+        assert_eq!(parsed.toolbar_position, ToolbarPosition::Bottom);
+        assert!(AppSettings::from_toml("present_mode = \"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn test_changes_name_the_rebuilds() {
+        let applied = AppSettings::default();
+        assert_eq!(applied.changes(&applied), SettingsChanges::default());
+
+        let mut edited = applied.clone();
+        edited.present_mode = PresentMode::Fifo;
+        edited.hot_reload.debounce_ms += 100;
+        edited.frame_limit = 30;
+        edited.include_dirs.push(PathBuf::from("shaders/lib"));
+        edited.language = "ru".to_string();
+        edited.fullscreen_monitor = Some("DISPLAY2".to_string());
+        assert_eq!(
+            edited.changes(&applied),
+            SettingsChanges { swapchain: true, hot_reload: true, language: true, fullscreen: true, ..SettingsChanges::default() }
+        );
+    }
+
+    #[test]
+    fn test_frame_limiter_paces_without_catching_up() {
+        let start = Instant::now();
+        let mut limiter = FrameLimiter::new(0);
+        assert_eq!(limiter.wait_until(start), None);
+        assert_eq!(limiter.wait_until(start), None);
+
+        let mut limiter = FrameLimiter::new(50);
+        let interval = Duration::from_millis(20);
+        assert_eq!(limiter.wait_until(start), None);
+        assert_eq!(limiter.wait_until(start + Duration::from_millis(5)), Some(start + interval));
+        assert_eq!(limiter.wait_until(start + Duration::from_millis(21)), None);
+        assert_eq!(limiter.wait_until(start + Duration::from_millis(30)), Some(start + interval * 2));
+
+        // A long stall does not cause a burst of frames
+        let late = start + Duration::from_millis(500);
+        assert_eq!(limiter.wait_until(late), None);
+        assert_eq!(limiter.wait_until(late + Duration::from_millis(1)), Some(late + interval));
+    }
+}
@@ -0,0 +1,430 @@
+//! Live shader parameters
+//!
+//! The members of the SDF fragment shader's `Params` uniform block
+//! (`config::shader_params::BLOCK_NAME`) are tuned in the Shader Parameters
+//! panel while the app runs, without recompiling. The block is found by
+//! SPIR-V reflection (see `vulkan::shader_reflection`), so a member added to
+//! the shader gets a control on the next reload: floats and float vectors get
+//! sliders, `vec3`/`vec4` members named like a color get a color picker, ints
+//! get int sliders and `bool` members, which compile to `uint`, a checkbox.
+//!
+//! `@` annotations in a member's comment (see `vulkan::shader_annotations`)
+//! choose another control, its range and step, the default value and the
+//! group the member is shown in; the rest of the comment is its tooltip:
+//!
+//! ```glsl
+//! float shadowMin;  // @slider(0, 1, step=0.05) @default(0.3) Light left in full shadow
+//! ```
+//!
+//! Values are saved per shader in a file next to it (`sdf.frag.params.toml`)
+//! and read again whenever the shader is reloaded.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::debug;
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+use crate::vulkan::scene_buffer::GpuShaderParams;
+use crate::vulkan::shader_annotations::{ShaderAnnotations, Widget};
+use crate::vulkan::shader_reflection::{BlockBinding, MemberType, ScalarType, UniformBlock};
+
+/// Control a parameter is edited with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamControl {
+    /// One float slider per component, snapping to the step if there is one
+    Slider { min: f32, max: f32, step: Option<f32> },
+    /// One int slider per component
+    IntSlider { min: i32, max: i32 },
+    /// One unbounded drag field per component, float or int
+    Drag { speed: f32 },
+    /// Color picker, for `vec3` and `vec4` members named like a color
+    Color,
+    /// Checkbox, for `bool` members
+    Checkbox,
+}
+
+/// A member of the `Params` block with its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderParam {
+    /// Member name
+    pub name: String,
+    /// Member type
+    pub ty: MemberType,
+    /// Byte offset in the block
+    offset: u32,
+    /// How the value is edited
+    pub control: ParamControl,
+    /// Value from the `@default(...)` annotation, zero without one
+    pub default: [f32; 4],
+    /// Current value, ints and bools included; only `components` are used
+    pub value: [f32; 4],
+    /// Group from the `@group(...)` annotation, None for ungrouped
+    pub group: Option<String>,
+    /// Comment of the declaration without the annotations
+    pub description: String,
+}
+
+impl ShaderParam {
+    /// Number of components in use
+    pub fn components(&self) -> usize {
+        self.ty.components as usize
+    }
+
+    /// Round the value to the step of its slider, if it has one
+    pub fn snap_to_step(&mut self) {
+        let ParamControl::Slider { min, max, step: Some(step) } = self.control else {
+            return;
+        };
+        if step > 0.0 {
+            let components = self.components();
+            for value in &mut self.value[..components] {
+                *value = (min + ((*value - min) / step).round() * step).clamp(min, max);
+            }
+        }
+    }
+
+    /// The value as a TOML value, in the type of the member
+    fn toml_value(&self) -> toml::Value {
+        let component = |value: f32| match self.ty.scalar {
+            ScalarType::Float => toml::Value::Float(f64::from(value)),
+            _ if self.control == ParamControl::Checkbox => toml::Value::Boolean(value != 0.0),
+            _ => toml::Value::Integer(value.round() as i64),
+        };
+        match self.components() {
+            1 => component(self.value[0]),
+            n => toml::Value::Array(self.value[..n].iter().map(|&value| component(value)).collect()),
+        }
+    }
+
+    /// Set the value from a TOML value, a number or bool for every component
+    ///
+    /// # Returns
+    /// Whether the value fit the member
+    fn set_toml_value(&mut self, value: &toml::Value) -> bool {
+        let number = |value: &toml::Value| match value {
+            toml::Value::Float(value) => Some(*value as f32),
+            toml::Value::Integer(value) => Some(*value as f32),
+            toml::Value::Boolean(value) => Some(if *value { 1.0 } else { 0.0 }),
+            _ => None,
+        };
+        let components: Option<Vec<f32>> = match value {
+            toml::Value::Array(values) => values.iter().map(number).collect(),
+            value => number(value).map(|value| vec![value]),
+        };
+        match components {
+            Some(components) if components.len() == self.components() => {
+                self.value[..components.len()].copy_from_slice(&components);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether a member name reads like a color
+fn is_color_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["color", "colour", "tint"].iter().any(|word| name.contains(word))
+}
+
+/// Editable parameters of a shader and the blocks around them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderParams {
+    /// Shader source file the parameters belong to
+    pub shader: PathBuf,
+    /// Members of the `Params` block that have a control
+    pub params: Vec<ShaderParam>,
+    /// Members of the `Params` block without one (matrices, arrays, structs,
+    /// or past `config::shader_params::MAX_BLOCK_SIZE`)
+    pub unsupported: Vec<String>,
+    /// Whether the shader declares the `Params` block at its binding
+    pub has_block: bool,
+    /// Malformed annotations and controls that do not fit their member
+    pub warnings: Vec<String>,
+    /// The shader's other uniform and push constant blocks, filled in by the app
+    pub app_blocks: Vec<UniformBlock>,
+}
+
+impl ShaderParams {
+    /// Build the parameters of a shader at their defaults
+    ///
+    /// # Arguments
+    /// * `shader` - Shader source file
+    /// * `annotations` - Its UI annotations, parsed by the shader compiler
+    /// * `blocks` - Blocks reflected from its SPIR-V
+    pub fn new(shader: &Path, annotations: &ShaderAnnotations, blocks: Vec<UniformBlock>) -> Self {
+        let params_binding = BlockBinding::Uniform { set: 0, binding: config::shader_params::BINDING };
+        let (params_blocks, app_blocks): (Vec<UniformBlock>, Vec<UniformBlock>) = blocks
+            .into_iter()
+            .partition(|block| block.binding == params_binding && block.name == config::shader_params::BLOCK_NAME);
+
+        let mut params = Vec::new();
+        let mut unsupported = Vec::new();
+        let mut warnings = annotations.warnings.clone();
+        for member in params_blocks.iter().flat_map(|block| &block.members) {
+            let fits = |ty: &MemberType| member.offset as usize + 4 * ty.components as usize <= config::shader_params::MAX_BLOCK_SIZE;
+            let Some(ty) = member.ty.filter(|ty| fits(ty) && ty.scalar != ScalarType::Bool) else {
+                unsupported.push(member.name.clone());
+                continue;
+            };
+            let hints = annotations.member(config::shader_params::BLOCK_NAME, &member.name).cloned().unwrap_or_default();
+            let by_type = match ty.scalar {
+                ScalarType::Float if ty.components >= 3 && is_color_name(&member.name) => ParamControl::Color,
+                ScalarType::Float => {
+                    let (min, max) = config::shader_params::FLOAT_RANGE;
+                    ParamControl::Slider { min, max, step: None }
+                }
+                ScalarType::UInt if ty.components == 1 => ParamControl::Checkbox,
+                _ => {
+                    let (min, max) = config::shader_params::INT_RANGE;
+                    ParamControl::IntSlider { min, max }
+                }
+            };
+            let control = match (hints.widget, ty.scalar) {
+                (None, _) => by_type,
+                (Some(Widget::Slider { min, max, step }), ScalarType::Float) => ParamControl::Slider { min, max, step },
+                (Some(Widget::Slider { min, max, .. }), _) => ParamControl::IntSlider { min: min as i32, max: max as i32 },
+                (Some(Widget::Drag { speed }), _) => ParamControl::Drag { speed: speed.unwrap_or(config::shader_params::DRAG_SPEED) },
+                (Some(Widget::Color), ScalarType::Float) if ty.components >= 3 => ParamControl::Color,
+                (Some(Widget::Checkbox), ScalarType::Int | ScalarType::UInt) if ty.components == 1 => ParamControl::Checkbox,
+                (Some(widget), _) => {
+                    warnings.push(format!("{} does not fit {} {}", widget.name(), ty.glsl_name(), member.name));
+                    by_type
+                }
+            };
+            let mut default = [0.0; 4];
+            match hints.default[..] {
+                [] => {}
+                [value] => default[..ty.components as usize].fill(value),
+                ref values => {
+                    for (component, value) in default.iter_mut().zip(values) {
+                        *component = *value;
+                    }
+                }
+            }
+            params.push(ShaderParam {
+                name: member.name.clone(),
+                ty,
+                offset: member.offset,
+                control,
+                default,
+                value: default,
+                group: hints.group,
+                description: hints.description,
+            });
+        }
+
+        Self {
+            shader: shader.to_path_buf(),
+            params,
+            unsupported,
+            has_block: !params_blocks.is_empty(),
+            warnings,
+            app_blocks,
+        }
+    }
+
+    /// Set the saved values from the values file, if there is one
+    ///
+    /// On error no value changes, so the parameters keep their defaults.
+    ///
+    /// # Errors
+    /// Returns an error if the values file exists and cannot be read or parsed
+    pub fn load_values(&mut self) -> Result<()> {
+        let file = self.values_file();
+        if file.exists() {
+            let text = fs::read_to_string(&file).context(Subsystem::Io, format!("read shader parameters {}", file.display()))?;
+            self.apply_toml(&text)?;
+            debug!("Shader parameters loaded from {}", file.display());
+        }
+        Ok(())
+    }
+
+    /// File the values are saved in, next to the shader
+    pub fn values_file(&self) -> PathBuf {
+        let file_name = self.shader.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        self.shader.with_file_name(format!("{}{}", file_name, config::shader_params::FILE_SUFFIX))
+    }
+
+    /// Set values from the contents of a values file
+    ///
+    /// Values of members the shader no longer has, or whose type changed, are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if the text is not a TOML table
+    pub fn apply_toml(&mut self, text: &str) -> Result<()> {
+        let table: toml::Table = toml::from_str(text)
+            .map_err(|e| AppError::Generic(format!("Invalid shader parameters {}: {}", self.values_file().display(), e)))?;
+        for param in &mut self.params {
+            if let Some(value) = table.get(&param.name) {
+                if !param.set_toml_value(value) {
+                    debug!("Ignoring saved value of {}: does not fit {}", param.name, param.ty.glsl_name());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Contents of the values file
+    pub fn to_toml(&self) -> String {
+        let table: toml::Table = self.params.iter().map(|param| (param.name.clone(), param.toml_value())).collect();
+        toml::to_string(&table).unwrap_or_default()
+    }
+
+    /// Write the values file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save(&self) -> Result<()> {
+        let file = self.values_file();
+        fs::write(&file, self.to_toml()).context(Subsystem::Io, format!("save shader parameters {}", file.display()))?;
+        debug!("Shader parameters saved to {}", file.display());
+        Ok(())
+    }
+
+    /// Set every parameter to its default
+    pub fn reset(&mut self) {
+        for param in &mut self.params {
+            param.value = param.default;
+        }
+    }
+
+    /// Contents of the `Params` uniform buffer, in the block's std140 layout
+    pub fn gpu_data(&self) -> GpuShaderParams {
+        let mut data = GpuShaderParams::default();
+        for param in &self.params {
+            let first = param.offset as usize / 4;
+            for (word, &value) in data.words[first..first + param.components()].iter_mut().zip(&param.value) {
+                *word = match param.ty.scalar {
+                    ScalarType::Float => value.to_bits(),
+                    ScalarType::Int => (value.round() as i32) as u32,
+                    ScalarType::UInt | ScalarType::Bool => value.round().max(0.0) as u32,
+                };
+            }
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vulkan::shader_reflection::BlockMember;
+
+    const SOURCE: &str = "
+        layout(set = 0, binding = 2) uniform Params {
+            vec3 ambientColor;    // @default(0.1) Ambient light
+            // @group(Shadows)
+            float shadowMin;      // @slider(0, 0.5, step=0.1) @default(0.3)   Light left in full shadow
+            bool specular;        // @default(1) @color
+            int steps;            // @drag(speed=0.5) @group()
+            mat4 transform;
+        } params;
+        uniform Other {
+            float shadowMin;      // @slider(5, 6) not in the block
+        } other;
+    ";
+
+    fn annotations() -> ShaderAnnotations {
+        ShaderAnnotations::parse(SOURCE)
+    }
+
+    fn member(name: &str, offset: u32, ty: Option<(ScalarType, u32)>) -> BlockMember {
+        let ty = ty.map(|(scalar, components)| MemberType { scalar, components });
+        BlockMember { name: name.to_string(), offset, ty }
+    }
+
+    fn blocks() -> Vec<UniformBlock> {
+        vec![
+            UniformBlock {
+                name: "Params".to_string(),
+                binding: BlockBinding::Uniform { set: 0, binding: config::shader_params::BINDING },
+                members: vec![
+                    member("ambientColor", 0, Some((ScalarType::Float, 3))),
+                    member("shadowMin", 12, Some((ScalarType::Float, 1))),
+                    member("specular", 16, Some((ScalarType::UInt, 1))),
+                    member("steps", 20, Some((ScalarType::Int, 1))),
+                    member("transform", 32, None),
+                ],
+            },
+            UniformBlock { name: "PushConstants".to_string(), binding: BlockBinding::PushConstant, members: Vec::new() },
+        ]
+    }
+
+    #[test]
+    fn test_controls_and_defaults_come_from_types_and_annotations() {
+        let params = ShaderParams::new(Path::new("shaders/sdf.frag"), &annotations(), blocks());
+        assert!(params.has_block);
+        assert_eq!(params.unsupported, ["transform"]);
+        assert_eq!(params.app_blocks.len(), 1);
+        assert_eq!(params.values_file(), Path::new("shaders/sdf.frag.params.toml"));
+
+        let controls: Vec<ParamControl> = params.params.iter().map(|param| param.control).collect();
+        assert_eq!(controls, [
+            ParamControl::Color,
+            ParamControl::Slider { min: 0.0, max: 0.5, step: Some(0.1) },
+            ParamControl::Checkbox,
+            ParamControl::Drag { speed: 0.5 },
+        ]);
+        let groups: Vec<Option<&str>> = params.params.iter().map(|param| param.group.as_deref()).collect();
+        assert_eq!(groups, [None, Some("Shadows"), Some("Shadows"), None]);
+        // A bool cannot be a color; it keeps its checkbox
+        assert_eq!(params.warnings, ["@color does not fit uint specular"]);
+        assert_eq!(params.params[0].value[..3], [0.1; 3]);
+        assert_eq!(params.params[1].default[0], 0.3);
+        assert_eq!(params.params[1].description, "Light left in full shadow");
+        assert_eq!(params.params[2].value[0], 1.0);
+        assert_eq!(params.params[3].value[0], 0.0);
+
+        let data = params.gpu_data();
+        assert_eq!(data.words[0], 0.1f32.to_bits());
+        assert_eq!(data.words[3], 0.3f32.to_bits());
+        assert_eq!(data.words[4], 1);
+
+        let mut snapped = params.params[1].clone();
+        snapped.value[0] = 0.26;
+        snapped.snap_to_step();
+        assert!((snapped.value[0] - 0.3).abs() < 1e-6);
+
+        let empty = ShaderParams::new(Path::new("a.frag"), &ShaderAnnotations::default(), blocks().split_off(1));
+        assert!(!empty.has_block && empty.params.is_empty());
+    }
+
+    #[test]
+    fn test_values_round_trip_through_toml() {
+        let mut params = ShaderParams::new(Path::new("sdf.frag"), &annotations(), blocks());
+        params.params[0].value[..3].copy_from_slice(&[1.0, 0.5, 0.25]);
+        params.params[2].value[0] = 0.0;
+        params.params[3].value[0] = 7.0;
+        let text = params.to_toml();
+        assert!(text.contains("specular = false"), "{}", text);
+        assert!(text.contains("steps = 7"), "{}", text);
+
+        let mut loaded = ShaderParams::new(Path::new("sdf.frag"), &annotations(), blocks());
+        loaded.apply_toml(&text).unwrap();
+        assert_eq!(loaded, params);
+
+        // Values that no longer fit their member are ignored
+        loaded.apply_toml("ambientColor = 1.0\nsteps = [1, 2]\nremoved = 3").unwrap();
+        assert_eq!(loaded, params);
+        assert!(loaded.apply_toml("not toml").is_err());
+        assert_eq!(loaded, params);
+
+        loaded.reset();
+        assert_eq!(loaded.params[3].value[0], 0.0);
+        assert_eq!(loaded.params[0].value[..3], [0.1; 3]);
+    }
+
+    #[test]
+    fn test_broken_values_file_keeps_the_defaults() {
+        let shader = std::env::temp_dir().join(format!("vulkan-app-params-test-{}.frag", std::process::id()));
+        let mut params = ShaderParams::new(&shader, &annotations(), blocks());
+        fs::write(params.values_file(), "ambientColor = [1.0, 1.0").unwrap();
+        let result = params.load_values();
+        let _ = fs::remove_file(params.values_file());
+
+        assert!(result.is_err());
+        assert_eq!(params, ShaderParams::new(&shader, &annotations(), blocks()));
+        assert_eq!(params.gpu_data().words[3], 0.3f32.to_bits());
+    }
+}
@@ -0,0 +1,335 @@
+//! Bindless texture table
+//!
+//! With descriptor indexing (Vulkan 1.2) every loaded texture goes into one
+//! large, partially bound combined image sampler array. A texture is then
+//! just an index (`TextureId`) that draws pass in push constants or
+//! material data, so meshes and ImGui draws no longer need a descriptor set
+//! bind each. Shaders declare the table as
+//!
+//! ```glsl
+//! #extension GL_EXT_nonuniform_qualifier : require
+//! layout(set = 1, binding = 0) uniform sampler2D textures[];
+//! // ...
+//! texture(textures[nonuniformEXT(materialTexture)], uv)
+//! ```
+//!
+//! The table only exists when `VulkanDevice::bindless_textures` is set;
+//! without it, textures keep their own descriptor sets.
+
+use std::collections::VecDeque;
+use ash::vk;
+use ash::Device;
+use log::{debug, info, warn};
+use crate::config;
+use crate::error::{AppError, Result, VulkanError};
+use crate::vulkan::capabilities::DeviceCapabilities;
+
+/// Index of a texture in the bindless table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(u32);
+
+impl TextureId {
+    /// Array index shaders use to sample the texture
+    #[allow(dead_code)]
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+/// Free list of table slots
+///
+/// Released slots are kept until every frame submitted before the release
+/// has completed, since those frames may still sample the old texture.
+#[derive(Debug)]
+struct TextureSlots {
+    /// Number of slots in the table
+    capacity: u32,
+    /// Lowest slot never handed out
+    next: u32,
+    /// Slots that can be reused
+    free: Vec<u32>,
+    /// Released slots with the number of frames submitted when released
+    retired: VecDeque<(u64, u32)>,
+    /// Frames submitted so far
+    frames_submitted: u64,
+}
+
+impl TextureSlots {
+    fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            next: 0,
+            free: Vec::new(),
+            retired: VecDeque::new(),
+            frames_submitted: 0,
+        }
+    }
+
+    /// Take a free slot, None if the table is full
+    fn allocate(&mut self) -> Option<u32> {
+        if let Some(slot) = self.free.pop() {
+            return Some(slot);
+        }
+        if self.next < self.capacity {
+            self.next += 1;
+            return Some(self.next - 1);
+        }
+        None
+    }
+
+    /// Queue a slot for reuse once the frames that may use it completed
+    fn release(&mut self, slot: u32) {
+        self.retired.push_back((self.frames_submitted, slot));
+    }
+
+    /// Make the slots no submitted frame can still use free again
+    ///
+    /// Same assumption as `DeletionQueue::collect`: the caller has waited on
+    /// the fence of the frame slot about to be reused.
+    fn reclaim(&mut self) {
+        let completed = self.frames_submitted.saturating_sub(config::vulkan::MAX_FRAMES_IN_FLIGHT as u64 - 1);
+        while self.retired.front().is_some_and(|(released_at, _)| *released_at <= completed) {
+            if let Some((_, slot)) = self.retired.pop_front() {
+                self.free.push(slot);
+            }
+        }
+    }
+
+    /// Number of slots holding a texture or waiting to be reused
+    fn in_use(&self) -> u32 {
+        self.next - self.free.len() as u32
+    }
+}
+
+/// Descriptor set holding every registered texture
+pub struct BindlessTextures {
+    layout: vk::DescriptorSetLayout,
+    pool: vk::DescriptorPool,
+    set: vk::DescriptorSet,
+    slots: TextureSlots,
+    device: Device,
+}
+
+impl BindlessTextures {
+    /// Whether the table can be used on the device, unless disabled in the config
+    pub fn is_supported(capabilities: &DeviceCapabilities) -> bool {
+        config::vulkan::PREFER_BINDLESS_TEXTURES
+            && capabilities.supports_descriptor_indexing()
+            && Self::capacity(capabilities) > 0
+    }
+
+    /// Slots of the table: `config::vulkan::MAX_BINDLESS_TEXTURES`, but no
+    /// more than the device allows in an update-after-bind set
+    fn capacity(capabilities: &DeviceCapabilities) -> u32 {
+        config::vulkan::MAX_BINDLESS_TEXTURES.min(capabilities.max_update_after_bind_sampled_images())
+    }
+
+    /// Create the table with up to `config::vulkan::MAX_BINDLESS_TEXTURES` slots
+    ///
+    /// # Arguments
+    /// * `device` - Logical device created with the descriptor indexing features
+    /// * `capabilities` - Capabilities of the device, for its descriptor limits
+    ///
+    /// # Errors
+    /// Returns an error if the layout, pool or set cannot be created
+    pub fn new(device: &Device, capabilities: &DeviceCapabilities) -> Result<Self> {
+        let capacity = Self::capacity(capabilities);
+        if capacity < config::vulkan::MAX_BINDLESS_TEXTURES {
+            warn!(
+                "Device allows {} update-after-bind sampled images, bindless texture table limited from {}",
+                capacity,
+                config::vulkan::MAX_BINDLESS_TEXTURES
+            );
+        }
+        info!("Creating bindless texture table with {} slots", capacity);
+
+        // Slots are written while frames using other slots are in flight
+        let binding_flags = [vk::DescriptorBindingFlags::PARTIALLY_BOUND
+            | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT
+            | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING];
+        let mut binding_flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfo::default()
+            .binding_flags(&binding_flags);
+        let bindings = [vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)];
+
+        let layout = unsafe {
+            device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::default()
+                .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+                .bindings(&bindings)
+                .push_next(&mut binding_flags_info), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create bindless descriptor set layout: {:?}", e)))?
+        };
+
+        let pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)];
+        let pool = unsafe {
+            device.create_descriptor_pool(&vk::DescriptorPoolCreateInfo::default()
+                .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+                .max_sets(1)
+                .pool_sizes(&pool_sizes), None)
+        };
+        let pool = match pool {
+            Ok(pool) => pool,
+            Err(e) => {
+                unsafe { device.destroy_descriptor_set_layout(layout, None) };
+                return Err(VulkanError::PipelineCreation(format!("Failed to create bindless descriptor pool: {:?}", e)).into());
+            }
+        };
+
+        let layouts = [layout];
+        let counts = [capacity];
+        let mut variable_count_info = vk::DescriptorSetVariableDescriptorCountAllocateInfo::default()
+            .descriptor_counts(&counts);
+        let sets = unsafe {
+            device.allocate_descriptor_sets(&vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(pool)
+                .set_layouts(&layouts)
+                .push_next(&mut variable_count_info))
+        };
+        let set = match sets {
+            Ok(sets) => sets[0],
+            Err(e) => {
+                unsafe {
+                    device.destroy_descriptor_pool(pool, None);
+                    device.destroy_descriptor_set_layout(layout, None);
+                }
+                return Err(VulkanError::PipelineCreation(format!("Failed to allocate bindless descriptor set: {:?}", e)).into());
+            }
+        };
+
+        Ok(Self {
+            layout,
+            pool,
+            set,
+            slots: TextureSlots::new(capacity),
+            device: device.clone(),
+        })
+    }
+
+    /// Layout to include in pipeline layouts that sample the table
+    #[allow(dead_code)]
+    pub fn layout(&self) -> vk::DescriptorSetLayout {
+        self.layout
+    }
+
+    /// Bind the table once per command buffer instead of a set per draw
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer being recorded
+    /// * `pipeline_layout` - Layout with `layout()` at `set_index`
+    /// * `set_index` - Set number the shaders declare the table at
+    #[allow(dead_code)]
+    pub fn bind(&self, command_buffer: vk::CommandBuffer, pipeline_layout: vk::PipelineLayout, set_index: u32) {
+        unsafe {
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_layout,
+                set_index,
+                &[self.set],
+                &[],
+            );
+        }
+    }
+
+    /// Put a texture into a free slot
+    ///
+    /// # Arguments
+    /// * `view` - Image view in `SHADER_READ_ONLY_OPTIMAL` layout
+    /// * `sampler` - Sampler used with the view
+    ///
+    /// # Returns
+    /// The slot shaders index with
+    ///
+    /// # Errors
+    /// Returns an error if every slot is taken
+    #[allow(dead_code)]
+    pub fn register(&mut self, view: vk::ImageView, sampler: vk::Sampler) -> Result<TextureId> {
+        let slot = self.slots.allocate().ok_or_else(|| AppError::Generic(format!(
+            "Bindless texture table is full ({} textures)", self.slots.capacity
+        )))?;
+
+        let image_info = [vk::DescriptorImageInfo::default()
+            .image_view(view)
+            .sampler(sampler)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(self.set)
+            .dst_binding(0)
+            .dst_array_element(slot)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info);
+        unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+
+        debug!("Registered bindless texture {} ({} in use)", slot, self.slots.in_use());
+        Ok(TextureId(slot))
+    }
+
+    /// Give up a slot; it is reused once the frames that may sample it completed
+    ///
+    /// The texture's view and sampler must outlive those frames too, e.g. by
+    /// going through the deletion queue.
+    #[allow(dead_code)]
+    pub fn release(&mut self, id: TextureId) {
+        self.slots.release(id.0);
+    }
+
+    /// Record that a frame was submitted
+    pub fn frame_submitted(&mut self) {
+        self.slots.frames_submitted += 1;
+    }
+
+    /// Reuse the released slots whose frames have completed
+    ///
+    /// Must be called right after waiting on the current frame's fence.
+    pub fn reclaim(&mut self) {
+        self.slots.reclaim();
+    }
+}
+
+impl Drop for BindlessTextures {
+    fn drop(&mut self) {
+        unsafe {
+            // Destroying the pool frees the set
+            self.device.destroy_descriptor_pool(self.pool, None);
+            self.device.destroy_descriptor_set_layout(self.layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_fill_up_to_capacity() {
+        let mut slots = TextureSlots::new(2);
+        assert_eq!(slots.allocate(), Some(0));
+        assert_eq!(slots.allocate(), Some(1));
+        assert_eq!(slots.allocate(), None);
+        assert_eq!(slots.in_use(), 2);
+    }
+
+    #[test]
+    fn test_released_slots_wait_for_their_frames() {
+        let mut slots = TextureSlots::new(1);
+        assert_eq!(slots.allocate(), Some(0));
+        slots.frames_submitted = 3;
+        slots.release(0);
+
+        // Frames submitted before the release may still sample the slot
+        for _ in 0..config::vulkan::MAX_FRAMES_IN_FLIGHT - 1 {
+            slots.reclaim();
+            assert_eq!(slots.allocate(), None);
+            slots.frames_submitted += 1;
+        }
+
+        slots.reclaim();
+        assert_eq!(slots.allocate(), Some(0));
+    }
+}
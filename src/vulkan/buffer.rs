@@ -0,0 +1,277 @@
+//! Buffers that own their memory
+//!
+//! `GpuBuffer` is a `vk::Buffer` together with its memory, size and usage;
+//! dropping it destroys both, and `retire` hands them to the deletion queue
+//! instead when frames in flight may still read it. `DynamicBuffer<T>` is a
+//! persistently mapped host visible buffer split into one region per frame
+//! in flight, so the CPU writes the next frame's data while the GPU reads
+//! the previous ones.
+
+use std::marker::PhantomData;
+use ash::vk;
+use ash::Device;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::deletion_queue::{DeferredResource, DeletionQueue};
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::scene_buffer::find_memory_type;
+use crate::vulkan::upload::UploadContext;
+
+/// Alignment of the per-frame regions of a `DynamicBuffer`
+///
+/// The largest `minUniformBufferOffsetAlignment` the spec allows, so regions
+/// can be bound as uniform buffers on every device.
+const REGION_ALIGNMENT: vk::DeviceSize = 256;
+
+/// A buffer and the memory bound to it
+pub struct GpuBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    /// Requested size in bytes
+    size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+    device: Device,
+}
+
+impl GpuBuffer {
+    /// Create a buffer used by the graphics queue only
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `memory_properties` - Memory properties of the physical device
+    /// * `size` - Size in bytes, larger than zero
+    /// * `usage` - Buffer usage
+    /// * `properties` - Required memory properties
+    ///
+    /// # Errors
+    /// Returns an error if the buffer cannot be created or no memory fits
+    pub fn new(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Result<Self> {
+        let buffer_info = vk::BufferCreateInfo::default()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        Self::create(device, memory_properties, &buffer_info, properties)
+    }
+
+    /// Create a device local buffer filled through the upload context
+    ///
+    /// Adds `TRANSFER_DST` to the usage and shares the buffer with the
+    /// upload queue family.
+    ///
+    /// # Errors
+    /// Returns an error if the buffer cannot be created or no memory fits
+    pub fn device_local(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        upload: &UploadContext,
+    ) -> Result<Self> {
+        let (sharing_mode, queue_families) = upload.sharing();
+        let buffer_info = vk::BufferCreateInfo::default()
+            .size(size)
+            .usage(usage | vk::BufferUsageFlags::TRANSFER_DST)
+            .sharing_mode(sharing_mode)
+            .queue_family_indices(&queue_families);
+        Self::create(device, memory_properties, &buffer_info, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+    }
+
+    fn create(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        buffer_info: &vk::BufferCreateInfo,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Result<Self> {
+        let buffer = unsafe { device.create_buffer(buffer_info, None) }
+            .map_err(|e| VulkanError::BufferCreation(format!("Failed to create {:?} buffer: {:?}", buffer_info.usage, e)))?;
+        // From here on dropping `gpu_buffer` cleans up whatever was created
+        let mut gpu_buffer = Self {
+            buffer,
+            memory: vk::DeviceMemory::null(),
+            size: buffer_info.size,
+            usage: buffer_info.usage,
+            device: device.clone(),
+        };
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let memory_type_index = find_memory_type(memory_properties, requirements.memory_type_bits, properties)
+            .ok_or_else(|| VulkanError::MemoryAllocation(format!("No {:?} memory type for {:?} buffer", properties, buffer_info.usage)))?;
+        gpu_buffer.memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index), MemoryCategory::Buffers, properties)
+            .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to allocate {:?} buffer memory: {:?}", buffer_info.usage, e)))?;
+        unsafe { device.bind_buffer_memory(buffer, gpu_buffer.memory, 0) }
+            .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to bind {:?} buffer memory: {:?}", buffer_info.usage, e)))?;
+
+        Ok(gpu_buffer)
+    }
+
+    /// The buffer handle
+    pub fn handle(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// Size in bytes as requested at creation
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
+    /// Usage the buffer was created with
+    #[allow(dead_code)]
+    pub fn usage(&self) -> vk::BufferUsageFlags {
+        self.usage
+    }
+
+    /// Copy bytes into a host visible buffer
+    ///
+    /// # Errors
+    /// Returns an error if the data does not fit or the memory cannot be mapped
+    pub fn write_bytes(&self, offset: vk::DeviceSize, bytes: &[u8]) -> Result<()> {
+        let size = bytes.len() as vk::DeviceSize;
+        if offset + size > self.size {
+            return Err(VulkanError::BufferCreation(format!("{} bytes at {} overflow a {} byte buffer", size, offset, self.size)).into());
+        }
+        unsafe {
+            let mapped = self.device.map_memory(self.memory, offset, size, vk::MemoryMapFlags::empty())
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to map {:?} buffer memory: {:?}", self.usage, e)))?;
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapped as *mut u8, bytes.len());
+            self.device.unmap_memory(self.memory);
+        }
+        Ok(())
+    }
+
+    /// Hand the buffer to the deletion queue, for buffers frames may still read
+    pub fn retire(mut self, deletion_queue: &mut DeletionQueue) {
+        deletion_queue.defer(DeferredResource::Buffer(std::mem::take(&mut self.buffer)));
+        deletion_queue.defer(DeferredResource::Memory(std::mem::take(&mut self.memory)));
+    }
+}
+
+impl Drop for GpuBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            if self.buffer != vk::Buffer::null() {
+                self.device.destroy_buffer(self.buffer, None);
+            }
+            memory_usage::free(&self.device, self.memory);
+        }
+    }
+}
+
+/// Bytes between the starts of consecutive per-frame regions
+fn region_stride(capacity: usize, element_size: usize) -> vk::DeviceSize {
+    ((capacity * element_size) as vk::DeviceSize).next_multiple_of(REGION_ALIGNMENT)
+}
+
+/// Persistently mapped buffer with one region of `capacity` elements per frame in flight
+pub struct DynamicBuffer<T> {
+    buffer: GpuBuffer,
+    mapped: *mut u8,
+    /// Elements per region
+    capacity: usize,
+    /// Bytes between region starts
+    stride: vk::DeviceSize,
+    _element: PhantomData<T>,
+}
+
+// The mapping points into memory owned by the buffer and moves with it
+unsafe impl<T: Send> Send for DynamicBuffer<T> {}
+
+impl<T: Copy> DynamicBuffer<T> {
+    /// Create and map a host visible buffer
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `memory_properties` - Memory properties of the physical device
+    /// * `usage` - Buffer usage
+    /// * `capacity` - Elements per region, larger than zero
+    /// * `frame_count` - Number of regions, usually `MAX_FRAMES_IN_FLIGHT`
+    ///
+    /// # Errors
+    /// Returns an error if the buffer cannot be created or mapped
+    pub fn new(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        usage: vk::BufferUsageFlags,
+        capacity: usize,
+        frame_count: usize,
+    ) -> Result<Self> {
+        let stride = region_stride(capacity, std::mem::size_of::<T>());
+        let buffer = GpuBuffer::new(
+            device,
+            memory_properties,
+            stride * frame_count as vk::DeviceSize,
+            usage,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        let mapped = unsafe { device.map_memory(buffer.memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty()) }
+            .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to map {:?} buffer memory: {:?}", usage, e)))?;
+
+        Ok(Self {
+            buffer,
+            mapped: mapped as *mut u8,
+            capacity,
+            stride,
+            _element: PhantomData,
+        })
+    }
+
+    /// Write elements into a frame's region
+    ///
+    /// The caller must have waited on that frame's in-flight fence.
+    ///
+    /// # Returns
+    /// The number of elements written, at most `capacity`
+    pub fn write(&mut self, frame: usize, data: &[T]) -> usize {
+        let count = data.len().min(self.capacity);
+        unsafe {
+            let dst = self.mapped.add((self.stride * frame as vk::DeviceSize) as usize) as *mut T;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), dst, count);
+        }
+        count
+    }
+
+    /// The buffer handle
+    pub fn handle(&self) -> vk::Buffer {
+        self.buffer.handle()
+    }
+
+    /// Byte offset of a frame's region
+    pub fn offset(&self, frame: usize) -> vk::DeviceSize {
+        self.stride * frame as vk::DeviceSize
+    }
+
+    /// Byte size of the elements of a region, the range to bind
+    pub fn range(&self) -> vk::DeviceSize {
+        (self.capacity * std::mem::size_of::<T>()) as vk::DeviceSize
+    }
+
+    /// Elements per region
+    #[allow(dead_code)]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<T> Drop for DynamicBuffer<T> {
+    fn drop(&mut self) {
+        unsafe { self.buffer.device.unmap_memory(self.buffer.memory) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions_are_aligned_for_uniform_binding() {
+        assert_eq!(region_stride(1, 1024), 1024);
+        assert_eq!(region_stride(1, 1040), 1280);
+        assert_eq!(region_stride(3, 28), 256);
+    }
+}
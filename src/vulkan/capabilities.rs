@@ -0,0 +1,351 @@
+//! GPU capabilities and feature gating
+//!
+//! The limits, features and extensions of the selected physical device are
+//! queried once when the device is created. Renderer code asks the typed
+//! getters instead of assuming a feature exists and falls back when it does
+//! not (the GPU timer without timestamps, for example). The full report is
+//! written to the log at startup and shown in the HUD capabilities panel.
+
+use std::collections::BTreeSet;
+use ash::vk;
+use ash::Instance;
+use log::info;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::gpu_selection;
+
+/// Limits, features and extensions of the selected physical device
+#[derive(Debug, Clone)]
+pub struct DeviceCapabilities {
+    /// Vulkan version usable on the device (the lower of instance and device version)
+    api_version: u32,
+    /// Core properties and limits
+    properties: vk::PhysicalDeviceProperties,
+    /// Core Vulkan 1.0 features
+    features: vk::PhysicalDeviceFeatures,
+    /// Supported device extensions
+    extensions: BTreeSet<String>,
+    /// Timeline semaphores (Vulkan 1.2)
+    timeline_semaphores: bool,
+    /// Buffer device address (Vulkan 1.2)
+    buffer_device_address: bool,
+    /// Runtime-sized, partially bound, variable count and update-after-bind
+    /// sampled image arrays (Vulkan 1.2)
+    descriptor_indexing: bool,
+    /// Sampled images one stage and one set may use in update-after-bind
+    /// sets, the lower of the two limits (Vulkan 1.2, 0 before)
+    update_after_bind_sampled_images: u32,
+    /// Dynamic rendering (Vulkan 1.3)
+    dynamic_rendering: bool,
+    /// Synchronization2 (Vulkan 1.3)
+    synchronization2: bool,
+    /// Valid timestamp bits of the graphics queue family
+    graphics_timestamp_bits: u32,
+}
+
+/// One titled block of the capability report
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportSection {
+    /// Section title
+    pub title: String,
+    /// Name and value pairs
+    pub entries: Vec<(String, String)>,
+}
+
+impl DeviceCapabilities {
+    /// Query the capabilities of a physical device
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - The selected physical device
+    /// * `instance_api_version` - API version the instance was created with
+    /// * `graphics_family` - Queue family the frames are recorded on
+    pub fn query(instance: &Instance, physical_device: vk::PhysicalDevice, instance_api_version: u32, graphics_family: u32) -> Self {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let api_version = instance_api_version.min(properties.api_version);
+
+        let extensions = unsafe { instance.enumerate_device_extension_properties(physical_device) }
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|extension| extension.extension_name_as_c_str().ok())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+
+        // Newer feature structs may only be chained when the device version has them
+        let mut vulkan12 = vk::PhysicalDeviceVulkan12Features::default();
+        let mut vulkan13 = vk::PhysicalDeviceVulkan13Features::default();
+        let features = if api_version >= vk::API_VERSION_1_1 {
+            let mut features2 = vk::PhysicalDeviceFeatures2::default();
+            if api_version >= vk::API_VERSION_1_2 {
+                features2 = features2.push_next(&mut vulkan12);
+            }
+            if api_version >= vk::API_VERSION_1_3 {
+                features2 = features2.push_next(&mut vulkan13);
+            }
+            unsafe { instance.get_physical_device_features2(physical_device, &mut features2) };
+            features2.features
+        } else {
+            unsafe { instance.get_physical_device_features(physical_device) }
+        };
+
+        let mut vulkan12_properties = vk::PhysicalDeviceVulkan12Properties::default();
+        if api_version >= vk::API_VERSION_1_2 {
+            let mut properties2 = vk::PhysicalDeviceProperties2::default().push_next(&mut vulkan12_properties);
+            unsafe { instance.get_physical_device_properties2(physical_device, &mut properties2) };
+        }
+
+        let queue_families = unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+        let graphics_timestamp_bits = queue_families
+            .get(graphics_family as usize)
+            .map_or(0, |family| family.timestamp_valid_bits);
+
+        Self {
+            api_version,
+            properties,
+            features,
+            extensions,
+            timeline_semaphores: vulkan12.timeline_semaphore == vk::TRUE,
+            buffer_device_address: vulkan12.buffer_device_address == vk::TRUE,
+            descriptor_indexing: vulkan12.runtime_descriptor_array == vk::TRUE
+                && vulkan12.descriptor_binding_partially_bound == vk::TRUE
+                && vulkan12.descriptor_binding_variable_descriptor_count == vk::TRUE
+                && vulkan12.shader_sampled_image_array_non_uniform_indexing == vk::TRUE
+                && vulkan12.descriptor_binding_sampled_image_update_after_bind == vk::TRUE
+                && vulkan12.descriptor_binding_update_unused_while_pending == vk::TRUE,
+            update_after_bind_sampled_images: vulkan12_properties.max_per_stage_descriptor_update_after_bind_sampled_images
+                .min(vulkan12_properties.max_descriptor_set_update_after_bind_sampled_images),
+            dynamic_rendering: vulkan13.dynamic_rendering == vk::TRUE,
+            synchronization2: vulkan13.synchronization2 == vk::TRUE,
+            graphics_timestamp_bits,
+        }
+    }
+
+    /// Check the limits the renderer cannot work without
+    ///
+    /// # Arguments
+    /// * `push_constant_size` - Largest push constant block the renderer uses
+    ///
+    /// # Errors
+    /// Returns an error naming the first missing requirement
+    pub fn check_requirements(&self, push_constant_size: u32) -> Result<()> {
+        if self.max_push_constant_size() < push_constant_size {
+            return Err(VulkanError::DeviceCreation(format!(
+                "Device allows {} bytes of push constants, the renderer needs {}",
+                self.max_push_constant_size(),
+                push_constant_size
+            )).into());
+        }
+        Ok(())
+    }
+
+    /// Vulkan version usable on the device
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// Whether a device extension is supported
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    /// Whether timeline semaphores can be used
+    pub fn supports_timeline_semaphores(&self) -> bool {
+        self.timeline_semaphores
+    }
+
+    /// Whether rendering without render pass and framebuffer objects is possible
+    pub fn supports_dynamic_rendering(&self) -> bool {
+        self.dynamic_rendering
+    }
+
+    /// Whether the second synchronization API can be used
+    pub fn supports_synchronization2(&self) -> bool {
+        self.synchronization2
+    }
+
+    /// Whether variable-count, partially bound sampled image arrays can be indexed non-uniformly and updated while bound
+    pub fn supports_descriptor_indexing(&self) -> bool {
+        self.descriptor_indexing
+    }
+
+    /// Most sampled images an update-after-bind array may hold for one stage and set
+    pub fn max_update_after_bind_sampled_images(&self) -> u32 {
+        self.update_after_bind_sampled_images
+    }
+
+    /// Whether buffers can be addressed by pointer from shaders
+    pub fn supports_buffer_device_address(&self) -> bool {
+        self.buffer_device_address
+    }
+
+    /// Whether timestamps can be written on the graphics queue
+    pub fn supports_timestamps(&self) -> bool {
+        self.properties.limits.timestamp_compute_and_graphics == vk::TRUE && self.graphics_timestamp_bits > 0
+    }
+
+    /// Nanoseconds per timestamp tick
+    pub fn timestamp_period(&self) -> f32 {
+        self.properties.limits.timestamp_period
+    }
+
+    /// Largest push constant block in bytes
+    pub fn max_push_constant_size(&self) -> u32 {
+        self.properties.limits.max_push_constants_size
+    }
+
+    /// Largest width or height of a 2D image
+    pub fn max_image_dimension_2d(&self) -> u32 {
+        self.properties.limits.max_image_dimension2_d
+    }
+
+    /// Largest viewport width and height
+    pub fn max_viewport_dimensions(&self) -> [u32; 2] {
+        self.properties.limits.max_viewport_dimensions
+    }
+
+    /// Whether lines wider than one pixel can be drawn
+    pub fn supports_wide_lines(&self) -> bool {
+        self.features.wide_lines == vk::TRUE
+    }
+
+    /// Whether pipelines can rasterize in wireframe
+    pub fn supports_fill_mode_non_solid(&self) -> bool {
+        self.features.fill_mode_non_solid == vk::TRUE
+    }
+
+    /// Largest sampler anisotropy, 1 if anisotropic filtering is unsupported
+    pub fn max_sampler_anisotropy(&self) -> f32 {
+        if self.features.sampler_anisotropy == vk::TRUE {
+            self.properties.limits.max_sampler_anisotropy
+        } else {
+            1.0
+        }
+    }
+
+    /// Everything known about the device, grouped for the log and the HUD
+    pub fn report(&self) -> Vec<ReportSection> {
+        let yes_no = |supported: bool| if supported { "yes" } else { "no" }.to_string();
+        let version = |version: u32| format!(
+            "{}.{}.{}",
+            vk::api_version_major(version),
+            vk::api_version_minor(version),
+            vk::api_version_patch(version)
+        );
+        let properties = &self.properties;
+        let limits = &properties.limits;
+        let name = properties.device_name_as_c_str().map_or_else(|_| "Unknown".into(), |name| name.to_string_lossy());
+
+        let device = vec![
+            ("Name".to_string(), name.into_owned()),
+            ("Vendor ID".to_string(), format!("0x{:04X}", properties.vendor_id)),
+            ("Device ID".to_string(), format!("0x{:04X}", properties.device_id)),
+            ("Device Vulkan".to_string(), version(properties.api_version)),
+            ("Vulkan in use".to_string(), version(self.api_version)),
+            ("Driver".to_string(), gpu_selection::format_driver_version(properties.vendor_id, properties.driver_version)),
+        ];
+        let features = vec![
+            ("Timeline semaphores".to_string(), yes_no(self.supports_timeline_semaphores())),
+            ("Dynamic rendering".to_string(), yes_no(self.supports_dynamic_rendering())),
+            ("Synchronization2".to_string(), yes_no(self.supports_synchronization2())),
+            ("Descriptor indexing".to_string(), yes_no(self.supports_descriptor_indexing())),
+            ("Buffer device address".to_string(), yes_no(self.supports_buffer_device_address())),
+            ("Graphics timestamps".to_string(), yes_no(self.supports_timestamps())),
+            ("Wide lines".to_string(), yes_no(self.supports_wide_lines())),
+            ("Wireframe fill".to_string(), yes_no(self.supports_fill_mode_non_solid())),
+            ("Geometry shaders".to_string(), yes_no(self.features.geometry_shader == vk::TRUE)),
+            ("64-bit floats in shaders".to_string(), yes_no(self.features.shader_float64 == vk::TRUE)),
+        ];
+        let limits = vec![
+            ("Push constants".to_string(), format!("{} bytes", self.max_push_constant_size())),
+            ("Image size 2D".to_string(), self.max_image_dimension_2d().to_string()),
+            ("Bound descriptor sets".to_string(), limits.max_bound_descriptor_sets.to_string()),
+            ("Sampled images per stage".to_string(), limits.max_per_stage_descriptor_sampled_images.to_string()),
+            ("Update-after-bind sampled images".to_string(), self.max_update_after_bind_sampled_images().to_string()),
+            ("Uniform buffer range".to_string(), format!("{} bytes", limits.max_uniform_buffer_range)),
+            ("Storage buffer range".to_string(), format!("{} bytes", limits.max_storage_buffer_range)),
+            ("Color attachments".to_string(), limits.max_color_attachments.to_string()),
+            ("Framebuffer size".to_string(), format!("{} x {}", limits.max_framebuffer_width, limits.max_framebuffer_height)),
+            ("Sampler anisotropy".to_string(), format!("{}", self.max_sampler_anisotropy())),
+            ("Timestamp period".to_string(), format!("{} ns", self.timestamp_period())),
+            ("Line width".to_string(), format!("{} - {}", limits.line_width_range[0], limits.line_width_range[1])),
+        ];
+
+        vec![
+            ReportSection { title: "Device".to_string(), entries: device },
+            ReportSection { title: "Features".to_string(), entries: features },
+            ReportSection { title: "Limits".to_string(), entries: limits },
+            ReportSection {
+                title: format!("Extensions ({})", self.extensions.len()),
+                entries: self.extensions.iter().map(|name| (name.clone(), String::new())).collect(),
+            },
+        ]
+    }
+
+    /// Write the report to the log
+    pub fn log_report(&self) {
+        for section in self.report() {
+            info!("{}:", section.title);
+            for (name, value) in &section.entries {
+                if value.is_empty() {
+                    info!("  {}", name);
+                } else {
+                    info!("  {}: {}", name, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities() -> DeviceCapabilities {
+        let properties = vk::PhysicalDeviceProperties {
+            api_version: vk::API_VERSION_1_3,
+            limits: vk::PhysicalDeviceLimits {
+                max_push_constants_size: 128,
+                timestamp_compute_and_graphics: vk::TRUE,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        DeviceCapabilities {
+            api_version: vk::API_VERSION_1_2,
+            properties,
+            features: vk::PhysicalDeviceFeatures::default(),
+            extensions: ["VK_KHR_swapchain".to_string()].into_iter().collect(),
+            timeline_semaphores: true,
+            buffer_device_address: false,
+            descriptor_indexing: false,
+            update_after_bind_sampled_images: 0,
+            dynamic_rendering: false,
+            synchronization2: false,
+            graphics_timestamp_bits: 0,
+        }
+    }
+
+    #[test]
+    fn test_getters_gate_on_every_requirement() {
+        let mut capabilities = capabilities();
+        assert!(capabilities.has_extension("VK_KHR_swapchain"));
+        assert!(!capabilities.has_extension("VK_KHR_dynamic_rendering"));
+        // Timestamps also need valid bits on the graphics queue
+        assert!(!capabilities.supports_timestamps());
+        capabilities.graphics_timestamp_bits = 64;
+        assert!(capabilities.supports_timestamps());
+        assert_eq!(capabilities.max_sampler_anisotropy(), 1.0);
+
+        assert!(capabilities.check_requirements(128).is_ok());
+        assert!(capabilities.check_requirements(256).is_err());
+    }
+
+    #[test]
+    fn test_report_lists_versions_and_extensions() {
+        let report = capabilities().report();
+        let device = &report[0].entries;
+        assert!(device.contains(&("Device Vulkan".to_string(), "1.3.0".to_string())));
+        assert!(device.contains(&("Vulkan in use".to_string(), "1.2.0".to_string())));
+        assert_eq!(report[3].title, "Extensions (1)");
+        assert_eq!(report[3].entries, [("VK_KHR_swapchain".to_string(), String::new())]);
+    }
+}
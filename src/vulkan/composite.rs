@@ -0,0 +1,545 @@
+//! Offscreen SDF target and composite pass
+//!
+//! The SDF scene is rendered into an offscreen color target using a pass
+//! that is compatible with the main SDF pipeline, together with the
+//! depth of the ray hits; meshes are drawn into the same pass afterwards
+//! and depth tested against the SDF surfaces. Only the top-left
+//! `scale` fraction of the target is rendered, which lets the resolution
+//! change every frame without reallocating images. The target holds linear
+//! HDR values; the composite pass upscales that region onto the swapchain
+//! image, applying exposure, tonemapping and the display encoding (see
+//! `tonemap`), before the HUD is drawn. The target itself is sized with
+//! the render scale (see `resolution_scaler::target_extent`), so it may be
+//! larger than the swapchain and downsampled instead.
+//!
+//! The scene pass also writes normals and linear depth into a G-buffer (see
+//! `gbuffer`). The scene depth is kept after the pass, so the GPU Textures
+//! panel can show every offscreen image (see `debug_targets`).
+
+use ash::vk;
+use ash::{Device, Instance};
+use std::ffi::CStr;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::depth::{DepthBuffer, DepthUsage};
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::gbuffer::{self, GBuffer};
+use crate::vulkan::rendering::{PassLayout, PassTarget};
+use crate::vulkan::scene_buffer::find_memory_type;
+use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::tonemap::{CompositePushConstants, DisplayOutput, TonemapSettings};
+use log::{debug, info};
+
+/// Offscreen color, depth and G-buffer images with their framebuffer
+struct OffscreenTarget {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    depth: DepthBuffer,
+    gbuffer: GBuffer,
+    framebuffer: vk::Framebuffer,
+    extent: vk::Extent2D,
+}
+
+/// Offscreen image the GPU Textures panel can show
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugTarget {
+    /// Name in the panel
+    pub name: &'static str,
+    /// View sampled by the HUD
+    pub view: vk::ImageView,
+    /// Layout of the image while the HUD is drawn
+    pub layout: vk::ImageLayout,
+    /// Image format
+    pub format: vk::Format,
+    /// Full size of the image
+    pub extent: vk::Extent2D,
+    /// Region rendered this frame, the top-left part of the image
+    pub rendered_extent: vk::Extent2D,
+    /// Whether the image holds depth
+    pub is_depth: bool,
+}
+
+/// Offscreen SDF target plus the pipeline that composites it to the swapchain
+pub struct CompositePass {
+    /// Offscreen SDF pass (compatible with the SDF pipeline)
+    pub scene_pass: PassLayout,
+    target: OffscreenTarget,
+    display_output: DisplayOutput,
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    device: Device,
+}
+
+impl CompositePass {
+    /// Create the offscreen target and composite pipeline
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - Physical device for memory type lookup
+    /// * `device` - The logical device
+    /// * `extent` - Full-resolution extent of the offscreen target
+    /// * `present_pass` - Pass the composite draw is recorded in
+    /// * `display_output` - Encoding the swapchain expects
+    ///
+    /// # Errors
+    /// Returns an error if any Vulkan object creation fails
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        extent: vk::Extent2D,
+        present_pass: &PassLayout,
+        display_output: DisplayOutput,
+    ) -> Result<Self> {
+        info!("Creating offscreen SDF target and composite pass ({}x{})", extent.width, extent.height);
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        // Same attachments and dependencies as the pipeline's scene pass, so the SDF pipeline
+        // is compatible; the depth is kept for the GPU Textures panel, which only changes its
+        // store op and final layout (see the depth module)
+        let scene_pass = PassLayout::with_color_formats(
+            device,
+            present_pass.mode,
+            &gbuffer::pass_formats(config::rendering::SCENE_FORMAT),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            DepthUsage::Sampled,
+            "offscreen",
+        )?;
+        let target = Self::create_target(device, &memory_properties, &scene_pass, extent)?;
+
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+        let sampler = unsafe {
+            device.create_sampler(&sampler_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create composite sampler: {:?}", e)))?
+        };
+
+        let bindings = [vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)];
+        let descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create composite descriptor set layout: {:?}", e)))?
+        };
+
+        let pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)];
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(&vk::DescriptorPoolCreateInfo::default().pool_sizes(&pool_sizes).max_sets(1), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create composite descriptor pool: {:?}", e)))?
+        };
+
+        let layouts = [descriptor_set_layout];
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(&vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&layouts))
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to allocate composite descriptor set: {:?}", e)))?[0]
+        };
+
+        let (pipeline_layout, pipeline) = Self::create_pipeline(device, present_pass, descriptor_set_layout)?;
+
+        let pass = Self {
+            scene_pass,
+            target,
+            display_output,
+            sampler,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            memory_properties,
+            device: device.clone(),
+        };
+        pass.update_descriptor_set();
+
+        info!("Composite pass created successfully");
+        Ok(pass)
+    }
+
+    /// Create the offscreen color, depth and G-buffer images and the framebuffer
+    fn create_target(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        pass: &PassLayout,
+        extent: vk::Extent2D,
+    ) -> Result<OffscreenTarget> {
+        let format = config::rendering::SCENE_FORMAT;
+        let extent = vk::Extent2D { width: extent.width.max(1), height: extent.height.max(1) };
+
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+
+        let image = unsafe {
+            device.create_image(&image_info, None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create offscreen image: {:?}", e)))?
+        };
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let memory_type_index = find_memory_type(memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            .ok_or_else(|| VulkanError::MemoryAllocation("No device local memory type for offscreen image".to_string()))?;
+
+        let memory = unsafe {
+            let memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Swapchain, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to allocate offscreen image memory: {:?}", e)))?;
+            device.bind_image_memory(image, memory, 0)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to bind offscreen image memory: {:?}", e)))?;
+            memory
+        };
+
+        let view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+        let view = unsafe {
+            device.create_image_view(&view_info, None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create offscreen image view: {:?}", e)))?
+        };
+
+        let depth = DepthBuffer::new(device, memory_properties, extent, DepthUsage::Sampled)?;
+        let gbuffer = GBuffer::new(device, memory_properties, extent)?;
+
+        let framebuffer = pass.create_framebuffer_with_extras(device, view, &gbuffer.views(), depth.view, extent)?;
+
+        debug!("Offscreen target created: {}x{}", extent.width, extent.height);
+        Ok(OffscreenTarget { image, memory, view, depth, gbuffer, framebuffer, extent })
+    }
+
+    /// Create the composite pipeline
+    fn create_pipeline(
+        device: &Device,
+        pass: &PassLayout,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let vert_code = shader_compiler.compile_file(config::shader::COMPOSITE_VERTEX_SHADER, "main", &[])?;
+        let frag_code = shader_compiler.compile_file(config::shader::COMPOSITE_FRAGMENT_SHADER, "main", &[])?;
+
+        let (vert_module, frag_module) = unsafe {
+            let vert = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&vert_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create composite vertex module: {:?}", e)))?;
+            let frag = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&frag_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create composite fragment module: {:?}", e)))?;
+            (vert, frag)
+        };
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_module)
+                .name(entry_point),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_module)
+                .name(entry_point),
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(config::rendering::LINE_WIDTH)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let color_blend_attachments = [vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false)];
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachments);
+        // The present pass has a depth attachment; the composite ignores it
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default();
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&dynamic_states);
+
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<CompositePushConstants>() as u32,
+        }];
+        let set_layouts = [descriptor_set_layout];
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&push_constant_ranges), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create composite pipeline layout: {:?}", e)))?
+        };
+
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout), &mut rendering);
+
+        let pipeline = unsafe {
+            let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None);
+            device.destroy_shader_module(vert_module, None);
+            device.destroy_shader_module(frag_module, None);
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, e)) => {
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                    return Err(VulkanError::PipelineCreation(format!("Failed to create composite pipeline: {:?}", e)).into());
+                }
+            }
+        };
+
+        Ok((pipeline_layout, pipeline))
+    }
+
+    /// Point the descriptor set at the current offscreen image
+    fn update_descriptor_set(&self) {
+        let image_infos = [vk::DescriptorImageInfo::default()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(self.target.view)
+            .sampler(self.sampler)];
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(self.descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_infos);
+
+        unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    /// Recreate the offscreen target for a new swapchain extent
+    ///
+    /// The caller must ensure the device is idle.
+    pub fn resize(&mut self, extent: vk::Extent2D) -> Result<()> {
+        let target = Self::create_target(&self.device, &self.memory_properties, &self.scene_pass, extent)?;
+        let old_target = std::mem::replace(&mut self.target, target);
+        self.destroy_target(&old_target);
+        self.update_descriptor_set();
+        Ok(())
+    }
+
+    /// Full-resolution extent of the offscreen target
+    pub fn target_extent(&self) -> vk::Extent2D {
+        self.target.extent
+    }
+
+    /// Extent actually rendered for a given scale
+    pub fn scaled_extent(&self, scale: f32) -> vk::Extent2D {
+        vk::Extent2D {
+            width: ((self.target.extent.width as f32 * scale).round() as u32).clamp(1, self.target.extent.width),
+            height: ((self.target.extent.height as f32 * scale).round() as u32).clamp(1, self.target.extent.height),
+        }
+    }
+
+    /// Offscreen images readable while the HUD is drawn
+    ///
+    /// # Arguments
+    /// * `scaled_extent` - Region rendered this frame
+    pub fn debug_targets(&self, scaled_extent: vk::Extent2D) -> Vec<DebugTarget> {
+        vec![
+            DebugTarget {
+                name: "Scene color (HDR)",
+                view: self.target.view,
+                layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                format: config::rendering::SCENE_FORMAT,
+                extent: self.target.extent,
+                rendered_extent: scaled_extent,
+                is_depth: false,
+            },
+            DebugTarget {
+                name: "Scene depth",
+                view: self.target.depth.view,
+                layout: DepthUsage::Sampled.final_layout(),
+                format: config::rendering::DEPTH_FORMAT,
+                extent: self.target.extent,
+                rendered_extent: scaled_extent,
+                is_depth: true,
+            },
+            DebugTarget {
+                name: "G-buffer normals",
+                view: self.target.gbuffer.normal_view(),
+                layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                format: config::rendering::NORMAL_FORMAT,
+                extent: self.target.extent,
+                rendered_extent: scaled_extent,
+                is_depth: false,
+            },
+            DebugTarget {
+                name: "G-buffer linear depth",
+                view: self.target.gbuffer.linear_depth_view(),
+                layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                format: config::rendering::LINEAR_DEPTH_FORMAT,
+                extent: self.target.extent,
+                rendered_extent: scaled_extent,
+                is_depth: false,
+            },
+            DebugTarget {
+                name: "G-buffer selection",
+                view: self.target.gbuffer.selection_view(),
+                layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                format: config::rendering::SELECTION_FORMAT,
+                extent: self.target.extent,
+                rendered_extent: scaled_extent,
+                is_depth: false,
+            },
+        ]
+    }
+
+    /// View of the selection mask, sampled by the outline pass; changes on resize
+    pub fn selection_view(&self) -> vk::ImageView {
+        self.target.gbuffer.selection_view()
+    }
+
+    /// Fraction of the offscreen target covered by a scaled region
+    pub fn uv_scale(&self, scaled_extent: vk::Extent2D) -> [f32; 2] {
+        [
+            scaled_extent.width as f32 / self.target.extent.width as f32,
+            scaled_extent.height as f32 / self.target.extent.height as f32,
+        ]
+    }
+
+    /// Images of the offscreen SDF pass
+    fn scene_target(&self) -> PassTarget {
+        PassTarget {
+            framebuffer: self.target.framebuffer,
+            color_image: self.target.image,
+            color_view: self.target.view,
+            depth_image: self.target.depth.image,
+            depth_view: self.target.depth.view,
+            extra_colors: self.target.gbuffer.extra_colors(),
+        }
+    }
+
+    /// Begin the offscreen SDF pass covering the scaled region
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer being recorded
+    /// * `scaled_extent` - Region rendered this frame
+    /// * `clears` - Viewport rectangles inside the region and their clear colors
+    pub fn begin_scene_pass(&self, command_buffer: vk::CommandBuffer, scaled_extent: vk::Extent2D, clears: &[(vk::Rect2D, [f32; 4])]) {
+        let render_area = vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: scaled_extent };
+        self.scene_pass.begin_with_clears(&self.device, command_buffer, &self.scene_target(), render_area, clears);
+    }
+
+    /// End the offscreen SDF pass, leaving the target ready for sampling
+    pub fn end_scene_pass(&self, command_buffer: vk::CommandBuffer) {
+        self.scene_pass.end(&self.device, command_buffer, &self.scene_target());
+    }
+
+    /// Draw the scaled offscreen region onto the current (swapchain) pass
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the present pass
+    /// * `extent` - Swapchain extent
+    /// * `scaled_extent` - Region of the offscreen target that was rendered
+    /// * `tonemap` - Exposure and tonemapping operator
+    pub fn record_composite(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        scaled_extent: vk::Extent2D,
+        tonemap: &TonemapSettings,
+    ) {
+        let push_constants = CompositePushConstants::new(self.uv_scale(scaled_extent), tonemap, self.display_output);
+
+        unsafe {
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[self.descriptor_set],
+                &[],
+            );
+            self.device.cmd_set_viewport(command_buffer, 0, &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: extent.width as f32,
+                height: extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }]);
+            self.device.cmd_set_scissor(command_buffer, 0, &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent,
+            }]);
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            self.device.cmd_draw(command_buffer, 3, 1, 0, 0); // Fullscreen triangle
+        }
+    }
+
+    /// Destroy an offscreen target
+    fn destroy_target(&self, target: &OffscreenTarget) {
+        unsafe {
+            self.device.destroy_framebuffer(target.framebuffer, None);
+            self.device.destroy_image_view(target.view, None);
+            self.device.destroy_image(target.image, None);
+            memory_usage::free(&self.device, target.memory);
+        }
+        target.depth.destroy(&self.device);
+        target.gbuffer.destroy(&self.device);
+    }
+}
+
+impl Drop for CompositePass {
+    fn drop(&mut self) {
+        debug!("Destroying composite pass");
+        self.destroy_target(&self.target);
+        unsafe {
+            self.device.destroy_pipeline(self.pipeline, None);
+            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            self.device.destroy_sampler(self.sampler, None);
+        }
+        self.scene_pass.destroy(&self.device);
+    }
+}
@@ -0,0 +1,381 @@
+//! Debug line renderer
+//!
+//! Systems fill a `DebugDrawList` with world space lines every frame; the
+//! renderer uploads them into a per-frame vertex buffer and draws them on
+//! top of the composited SDF image, before the HUD. Lines are projected
+//! with the same viewport cameras as the raymarcher so they line up with
+//! the shapes in every viewport.
+
+use ash::vk;
+use ash::{Device, Instance};
+use cgmath::Vector3;
+use std::ffi::CStr;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::rendering::PassLayout;
+use crate::vulkan::buffer::DynamicBuffer;
+use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::tonemap::{DisplayOutput, OutputSpecialization};
+use crate::vulkan::vertex_input::VertexLayout;
+use crate::vulkan::viewport::{rect_aspect, ViewCamera};
+use log::{debug, info, warn};
+
+/// Vertex of a debug line
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct DebugLineVertex {
+    pub position: [f32; 3],
+    /// Linear RGBA (see the `color` module)
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for DebugLineVertex {}
+unsafe impl bytemuck::Zeroable for DebugLineVertex {}
+
+/// Lines collected for the current frame
+#[derive(Clone, Debug, Default)]
+pub struct DebugDrawList {
+    vertices: Vec<DebugLineVertex>,
+}
+
+impl DebugDrawList {
+    /// Create an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Line vertices, two per line
+    pub fn vertices(&self) -> &[DebugLineVertex] {
+        &self.vertices
+    }
+
+    /// Number of lines in the list
+    pub fn line_count(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    /// Add a single line
+    pub fn line(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: [f32; 4]) {
+        self.vertices.push(DebugLineVertex { position: from.into(), color });
+        self.vertices.push(DebugLineVertex { position: to.into(), color });
+    }
+
+    /// Add the 12 edges of an axis aligned box
+    pub fn aabb(&mut self, min: Vector3<f32>, max: Vector3<f32>, color: [f32; 4]) {
+        let corner = |i: usize| Vector3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+
+        for i in 0..8 {
+            for axis in [1, 2, 4] {
+                // Each edge once: from the corner with the axis bit cleared
+                if i & axis == 0 {
+                    self.line(corner(i), corner(i | axis), color);
+                }
+            }
+        }
+    }
+
+    /// Add a circle around `axis` (0 = x, 1 = y, 2 = z)
+    pub fn circle(&mut self, center: Vector3<f32>, radius: f32, axis: usize, color: [f32; 4]) {
+        let segments = config::debug_draw::CIRCLE_SEGMENTS;
+        let point = |i: usize| {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            let (s, c) = angle.sin_cos();
+            let offset = match axis {
+                0 => Vector3::new(0.0, c, s),
+                1 => Vector3::new(c, 0.0, s),
+                _ => Vector3::new(c, s, 0.0),
+            };
+            center + offset * radius
+        };
+
+        for i in 0..segments {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Remove all lines
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// Push constants for the debug line shaders
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct DebugLinePushConstants {
+    /// xyz = camera origin, w = orthographic half height (0 = perspective)
+    camera_origin: [f32; 4],
+    /// xyz = camera forward direction, w = roll about it in radians
+    camera_forward: [f32; 4],
+    /// Half width and height (xy) and center (zw) of the view, see `ViewCamera::lens`
+    camera_lens: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for DebugLinePushConstants {}
+unsafe impl bytemuck::Zeroable for DebugLinePushConstants {}
+
+/// Pipeline and per-frame vertex buffers for debug lines
+pub struct DebugDrawRenderer {
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    /// One region of vertices per frame in flight
+    vertices: DynamicBuffer<DebugLineVertex>,
+    /// Number of vertices uploaded for each frame
+    vertex_counts: Vec<u32>,
+    device: Device,
+}
+
+impl DebugDrawRenderer {
+    /// Create the debug line renderer
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - Physical device for memory type lookup
+    /// * `device` - The logical device
+    /// * `pass` - Pass the lines are drawn in
+    /// * `display_output` - Encoding the swapchain expects
+    ///
+    /// # Errors
+    /// Returns an error if pipeline or buffer creation fails
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        pass: &PassLayout,
+        display_output: DisplayOutput,
+    ) -> Result<Self> {
+        info!("Creating debug line renderer");
+
+        let (pipeline_layout, pipeline) = Self::create_pipeline(device, pass, display_output)?;
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let frame_count = config::vulkan::MAX_FRAMES_IN_FLIGHT;
+        let vertices = DynamicBuffer::new(
+            device,
+            &memory_properties,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            config::debug_draw::MAX_LINE_VERTICES,
+            frame_count,
+        )?;
+
+        info!("Debug line renderer created ({} vertices per frame)", config::debug_draw::MAX_LINE_VERTICES);
+
+        Ok(Self {
+            pipeline_layout,
+            pipeline,
+            vertices,
+            vertex_counts: vec![0; frame_count],
+            device: device.clone(),
+        })
+    }
+
+    /// Create the line list pipeline
+    fn create_pipeline(device: &Device, pass: &PassLayout, display_output: DisplayOutput) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let vert_code = shader_compiler.compile_file(config::shader::DEBUG_LINE_VERTEX_SHADER, "main", &[])?;
+        let frag_code = shader_compiler.compile_file(config::shader::DEBUG_LINE_FRAGMENT_SHADER, "main", &[])?;
+
+        let (vert_module, frag_module) = unsafe {
+            let vert = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&vert_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create debug line vertex module: {:?}", e)))?;
+            let frag = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&frag_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create debug line fragment module: {:?}", e)))?;
+            (vert, frag)
+        };
+
+        // Lines are drawn straight into the swapchain, so they encode for the display themselves
+        let specialization = display_output.specialization();
+        let map_entries = OutputSpecialization::map_entries();
+        let specialization_info = vk::SpecializationInfo::default()
+            .map_entries(&map_entries)
+            .data(bytemuck::bytes_of(&specialization));
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_module)
+                .name(entry_point),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_module)
+                .name(entry_point)
+                .specialization_info(&specialization_info),
+        ];
+
+        let vertex_layout = VertexLayout::new()
+            .per_vertex::<DebugLineVertex>()
+            .attribute(vk::Format::R32G32B32_SFLOAT, std::mem::offset_of!(DebugLineVertex, position))
+            .attribute(vk::Format::R32G32B32A32_SFLOAT, std::mem::offset_of!(DebugLineVertex, color));
+        let vertex_input_info = vertex_layout.create_info();
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::LINE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(config::rendering::LINE_WIDTH)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let color_blend_attachments = [vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .alpha_blend_op(vk::BlendOp::ADD)];
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachments);
+        // Lines are drawn on top of everything, ignoring the depth attachment
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default();
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&dynamic_states);
+
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: std::mem::size_of::<DebugLinePushConstants>() as u32,
+        }];
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .push_constant_ranges(&push_constant_ranges), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create debug line pipeline layout: {:?}", e)))?
+        };
+
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout), &mut rendering);
+
+        let pipeline = unsafe {
+            let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None);
+            device.destroy_shader_module(vert_module, None);
+            device.destroy_shader_module(frag_module, None);
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, e)) => {
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                    return Err(VulkanError::PipelineCreation(format!("Failed to create debug line pipeline: {:?}", e)).into());
+                }
+            }
+        };
+
+        Ok((pipeline_layout, pipeline))
+    }
+
+    /// Upload the lines for a frame
+    ///
+    /// The caller must have waited on that frame's in-flight fence.
+    pub fn write(&mut self, frame: usize, list: &DebugDrawList) {
+        let vertices = list.vertices();
+        let count = vertices.len().min(config::debug_draw::MAX_LINE_VERTICES) & !1;
+        if count < vertices.len() {
+            warn!("Debug draw list truncated to {} vertices", count);
+        }
+
+        self.vertex_counts[frame] = self.vertices.write(frame, &vertices[..count]) as u32;
+    }
+
+    /// Record the debug lines for a frame and viewport into the current render pass
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the present render pass
+    /// * `frame` - Frame in flight whose buffer to draw
+    /// * `rect` - Viewport rectangle on the swapchain image
+    /// * `camera` - Camera of the viewport
+    pub fn record(&self, command_buffer: vk::CommandBuffer, frame: usize, rect: vk::Rect2D, camera: &ViewCamera) {
+        let vertex_count = self.vertex_counts[frame];
+        if vertex_count == 0 {
+            return;
+        }
+
+        let push_constants = DebugLinePushConstants {
+            camera_origin: camera.origin_vec4(),
+            camera_forward: camera.forward_vec4(),
+            camera_lens: camera.lens(rect_aspect(&rect)),
+        };
+
+        unsafe {
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            self.device.cmd_set_viewport(command_buffer, 0, &[vk::Viewport {
+                x: rect.offset.x as f32,
+                y: rect.offset.y as f32,
+                width: rect.extent.width as f32,
+                height: rect.extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }]);
+            self.device.cmd_set_scissor(command_buffer, 0, &[rect]);
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            self.device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.vertices.handle()], &[self.vertices.offset(frame)]);
+            self.device.cmd_draw(command_buffer, vertex_count, 1, 0, 0);
+        }
+    }
+}
+
+impl Drop for DebugDrawRenderer {
+    fn drop(&mut self) {
+        debug!("Destroying debug line renderer");
+        unsafe {
+            self.device.destroy_pipeline(self.pipeline, None);
+            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aabb_has_twelve_edges() {
+        let mut list = DebugDrawList::new();
+        list.aabb(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0), [1.0; 4]);
+        assert_eq!(list.line_count(), 12);
+
+        // Every edge is axis aligned with length 2
+        for pair in list.vertices().chunks(2) {
+            let a = Vector3::from(pair[0].position);
+            let b = Vector3::from(pair[1].position);
+            let d = b - a;
+            assert_eq!(d.x.abs() + d.y.abs() + d.z.abs(), 2.0);
+        }
+    }
+
+    #[test]
+    fn test_circle_is_closed() {
+        let mut list = DebugDrawList::new();
+        list.circle(Vector3::new(0.0, 0.0, 0.0), 1.0, 1, [1.0; 4]);
+        assert_eq!(list.line_count(), config::debug_draw::CIRCLE_SEGMENTS);
+
+        let first = list.vertices()[0].position;
+        let last = list.vertices()[list.vertices().len() - 1].position;
+        for i in 0..3 {
+            assert!((first[i] - last[i]).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Deferred destruction of GPU resources
+//!
+//! Objects replaced while frames are in flight (pipelines after a hot
+//! reload, outgrown buffers, old command buffers) may still be referenced by
+//! submitted command buffers. Instead of waiting for the device to go idle,
+//! they are queued together with the number of frames submitted so far and
+//! destroyed once the renderer has waited on the fences of all those frames.
+
+use std::collections::VecDeque;
+use ash::vk;
+use ash::Device;
+use log::debug;
+use crate::config;
+use crate::vulkan::memory_usage;
+
+/// A GPU object waiting to be destroyed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeferredResource {
+    /// Graphics pipeline
+    Pipeline(vk::Pipeline),
+    /// Pipeline layout
+    PipelineLayout(vk::PipelineLayout),
+    /// Buffer
+    Buffer(vk::Buffer),
+    /// Device memory allocation
+    Memory(vk::DeviceMemory),
+    /// Framebuffer
+    Framebuffer(vk::Framebuffer),
+    /// Image view
+    ImageView(vk::ImageView),
+    /// Image
+    Image(vk::Image),
+    /// Descriptor set allocated from a pool created with `FREE_DESCRIPTOR_SET`
+    DescriptorSet(vk::DescriptorPool, vk::DescriptorSet),
+    /// Command buffers allocated from a pool
+    CommandBuffers(vk::CommandPool, Vec<vk::CommandBuffer>),
+}
+
+impl DeferredResource {
+    /// Destroy the object
+    ///
+    /// # Safety
+    /// The GPU must have finished every command buffer that uses the object
+    unsafe fn destroy(self, device: &Device) {
+        match self {
+            DeferredResource::Pipeline(pipeline) => device.destroy_pipeline(pipeline, None),
+            DeferredResource::PipelineLayout(layout) => device.destroy_pipeline_layout(layout, None),
+            DeferredResource::Buffer(buffer) => device.destroy_buffer(buffer, None),
+            DeferredResource::Memory(memory) => memory_usage::free(device, memory),
+            DeferredResource::Framebuffer(framebuffer) => device.destroy_framebuffer(framebuffer, None),
+            DeferredResource::ImageView(view) => device.destroy_image_view(view, None),
+            DeferredResource::Image(image) => device.destroy_image(image, None),
+            DeferredResource::DescriptorSet(pool, set) => {
+                // Freeing only fails when out of host memory; the pool reclaims the set when destroyed
+                let _ = device.free_descriptor_sets(pool, &[set]);
+            }
+            DeferredResource::CommandBuffers(pool, buffers) => device.free_command_buffers(pool, &buffers),
+        }
+    }
+}
+
+/// Queue of resources destroyed once the frames that may use them completed
+#[derive(Debug, Default)]
+pub struct DeletionQueue {
+    /// Resources with the number of frames submitted when they were queued
+    pending: VecDeque<(u64, DeferredResource)>,
+    /// Frames submitted so far
+    frames_submitted: u64,
+}
+
+impl DeletionQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a resource that may still be used by submitted frames
+    pub fn defer(&mut self, resource: DeferredResource) {
+        self.pending.push_back((self.frames_submitted, resource));
+    }
+
+    /// Record that a frame was submitted
+    pub fn frame_submitted(&mut self) {
+        self.frames_submitted += 1;
+    }
+
+    /// Number of resources waiting to be destroyed
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Check whether nothing is waiting to be destroyed
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Remove the resources no submitted frame can still use
+    ///
+    /// Assumes the caller has waited on the fence of the frame slot about to
+    /// be reused, so every frame but the last `MAX_FRAMES_IN_FLIGHT - 1` is done.
+    fn drain_ready(&mut self) -> Vec<DeferredResource> {
+        let completed = self.frames_submitted.saturating_sub(config::vulkan::MAX_FRAMES_IN_FLIGHT as u64 - 1);
+        let mut ready = Vec::new();
+        while self.pending.front().is_some_and(|(queued_at, _)| *queued_at <= completed) {
+            if let Some((_, resource)) = self.pending.pop_front() {
+                ready.push(resource);
+            }
+        }
+        ready
+    }
+
+    /// Destroy the resources whose frames have completed
+    ///
+    /// Must be called right after waiting on the current frame's fence.
+    pub fn collect(&mut self, device: &Device) {
+        let ready = self.drain_ready();
+        if !ready.is_empty() {
+            debug!("Destroying {} deferred GPU resources", ready.len());
+        }
+        for resource in ready {
+            unsafe { resource.destroy(device) };
+        }
+    }
+
+    /// Destroy every queued resource
+    ///
+    /// Must only be called when the device is idle, e.g. at shutdown.
+    pub fn destroy_all(&mut self, device: &Device) {
+        for (_, resource) in self.pending.drain(..) {
+            unsafe { resource.destroy(device) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    fn test_resources_wait_for_their_frames() {
+        let mut queue = DeletionQueue::new();
+        queue.frame_submitted();
+        queue.frame_submitted();
+        queue.defer(DeferredResource::Buffer(vk::Buffer::from_raw(1)));
+
+        // The two frames that may use the buffer have not both completed yet
+        for _ in 0..config::vulkan::MAX_FRAMES_IN_FLIGHT - 1 {
+            assert!(queue.drain_ready().is_empty());
+            queue.frame_submitted();
+        }
+
+        assert_eq!(queue.drain_ready(), vec![DeferredResource::Buffer(vk::Buffer::from_raw(1))]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queued_before_any_frame_is_ready_immediately() {
+        let mut queue = DeletionQueue::new();
+        queue.defer(DeferredResource::Pipeline(vk::Pipeline::from_raw(7)));
+        assert_eq!(queue.drain_ready().len(), 1);
+    }
+}
@@ -0,0 +1,253 @@
+//! Depth attachments shared by the SDF and mesh render passes
+//!
+//! The SDF fragment shader writes the depth of its ray hits, so every render
+//! pass the SDF pipeline is used with carries a depth attachment of the same
+//! format, keeping the passes compatible. Meshes drawn after the SDF scene
+//! in the same pass are then depth tested against the raymarched surfaces.
+//!
+//! Depth is normally discarded when a pass ends. Passes created with
+//! `DepthUsage::Sampled` store it instead and leave it read-only, so debug
+//! views can sample it until the next frame clears it. The two usages differ
+//! only in the store op and the final layout, which render pass
+//! compatibility ignores; the attachment and the dependencies are otherwise
+//! identical, so a pipeline created for one pass works in the other.
+
+use ash::vk;
+use ash::Device;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::scene_buffer::find_memory_type;
+use log::debug;
+
+/// What happens to the depth contents when a pass ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthUsage {
+    /// Discarded, the image is only an attachment
+    Discard,
+    /// Stored and left in `DEPTH_STENCIL_READ_ONLY_OPTIMAL` for sampling
+    Sampled,
+}
+
+impl DepthUsage {
+    /// Image usage flags of a depth buffer used this way
+    fn image_usage(self) -> vk::ImageUsageFlags {
+        match self {
+            Self::Discard => vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            Self::Sampled => vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+        }
+    }
+
+    /// Store op of the depth attachment
+    fn store_op(self) -> vk::AttachmentStoreOp {
+        match self {
+            Self::Discard => vk::AttachmentStoreOp::DONT_CARE,
+            Self::Sampled => vk::AttachmentStoreOp::STORE,
+        }
+    }
+
+    /// Layout the depth image is left in when the pass ends
+    pub fn final_layout(self) -> vk::ImageLayout {
+        match self {
+            Self::Discard => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            Self::Sampled => vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+        }
+    }
+}
+
+/// Depth image with its memory and view
+pub struct DepthBuffer {
+    /// The depth image, transitioned by hand with dynamic rendering
+    pub image: vk::Image,
+    memory: vk::DeviceMemory,
+    /// View used as the framebuffer attachment
+    pub view: vk::ImageView,
+}
+
+impl DepthBuffer {
+    /// Create a depth image for a framebuffer
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `memory_properties` - Memory properties of the physical device
+    /// * `extent` - Size of the framebuffer
+    /// * `usage` - Whether passes sample the depth afterwards
+    ///
+    /// # Errors
+    /// Returns an error if image creation or allocation fails
+    pub fn new(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        extent: vk::Extent2D,
+        usage: DepthUsage,
+    ) -> Result<Self> {
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: extent.width.max(1), height: extent.height.max(1), depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(config::rendering::DEPTH_FORMAT)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(usage.image_usage())
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+
+        let image = unsafe {
+            device.create_image(&image_info, None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create depth image: {:?}", e)))?
+        };
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let memory_type_index = find_memory_type(memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            .ok_or_else(|| VulkanError::MemoryAllocation("No device local memory type for depth image".to_string()))?;
+
+        let memory = unsafe {
+            let memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Swapchain, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to allocate depth image memory: {:?}", e)))?;
+            device.bind_image_memory(image, memory, 0)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to bind depth image memory: {:?}", e)))?;
+            memory
+        };
+
+        let view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(config::rendering::DEPTH_FORMAT)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+        let view = unsafe {
+            device.create_image_view(&view_info, None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create depth image view: {:?}", e)))?
+        };
+
+        debug!("Depth buffer created: {}x{}", extent.width, extent.height);
+        Ok(Self { image, memory, view })
+    }
+
+    /// Destroy the image, view and memory
+    ///
+    /// The caller must ensure no framebuffer in use references the view.
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+            memory_usage::free(device, self.memory);
+        }
+    }
+}
+
+/// Depth attachment cleared at the start of the pass, discarded or stored at the end
+pub fn attachment_description(usage: DepthUsage) -> vk::AttachmentDescription {
+    vk::AttachmentDescription::default()
+        .format(config::rendering::DEPTH_FORMAT)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(usage.store_op())
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(usage.final_layout())
+}
+
+/// Reference to the depth attachment at `index` for a subpass
+pub fn attachment_reference(index: u32) -> vk::AttachmentReference {
+    vk::AttachmentReference::default()
+        .attachment(index)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+}
+
+/// Dependency ordering the depth clear after the previous pass's depth writes
+///
+/// The depth image is shared by all frames in flight; in case the depth was
+/// sampled, the clear also waits for the previous frame's shader reads. The
+/// same for both usages, see the module documentation.
+pub fn external_dependency() -> vk::SubpassDependency {
+    let stages = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+    vk::SubpassDependency::default()
+        .src_subpass(vk::SUBPASS_EXTERNAL)
+        .dst_subpass(0)
+        .src_stage_mask(stages | vk::PipelineStageFlags::FRAGMENT_SHADER)
+        .dst_stage_mask(stages)
+        .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+}
+
+/// Dependency making stored depth visible to the fragment shaders that sample it
+///
+/// Part of every pass with a depth attachment, also where the depth is discarded.
+pub fn sampled_dependency() -> vk::SubpassDependency {
+    vk::SubpassDependency::default()
+        .src_subpass(0)
+        .dst_subpass(vk::SUBPASS_EXTERNAL)
+        .src_stage_mask(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+        .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+        .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+}
+
+/// Depth attachment of a dynamic rendering pass, the counterpart of `attachment_description`
+pub fn rendering_attachment(view: vk::ImageView, usage: DepthUsage) -> vk::RenderingAttachmentInfo<'static> {
+    vk::RenderingAttachmentInfo::default()
+        .image_view(view)
+        .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(usage.store_op())
+        .clear_value(clear_value())
+}
+
+/// Barrier preparing the depth image for a dynamic rendering pass
+///
+/// Discards the old contents and orders the clear after the previous pass's
+/// depth writes, like `external_dependency` does for render pass objects.
+pub fn layout_barrier(image: vk::Image) -> vk::ImageMemoryBarrier<'static> {
+    vk::ImageMemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .old_layout(vk::ImageLayout::UNDEFINED)
+        .new_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::DEPTH,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        })
+}
+
+/// Barrier leaving stored depth read-only after a dynamic rendering pass,
+/// the counterpart of `sampled_dependency`
+pub fn sampled_barrier(image: vk::Image) -> vk::ImageMemoryBarrier<'static> {
+    vk::ImageMemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+        .old_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .new_layout(DepthUsage::Sampled.final_layout())
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::DEPTH,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        })
+}
+
+/// Clear value of the depth attachment (the far plane)
+pub fn clear_value() -> vk::ClearValue {
+    vk::ClearValue {
+        depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
+    }
+}
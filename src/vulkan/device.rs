@@ -3,6 +3,11 @@ use ash::{Device, Instance, Entry};
 use std::ffi::{CStr, CString};
 use crate::error::{Result, VulkanError};
 use crate::config;
+use crate::vulkan::bindless::BindlessTextures;
+use crate::vulkan::capabilities::DeviceCapabilities;
+use crate::vulkan::gpu_selection::{self, GpuInfo, GpuSelector};
+use crate::vulkan::push_constants::SdfPushConstants;
+use crate::vulkan::rendering::RenderingMode;
 use log::{debug, info, warn};
 
 /// Queue family indices for graphics and presentation
@@ -13,6 +18,9 @@ pub struct QueueFamilyIndices {
     
     /// Presentation queue family index
     pub present_family: Option<u32>,
+    
+    /// Dedicated transfer queue family index (no graphics), if the device has one
+    pub transfer_family: Option<u32>,
 }
 
 impl QueueFamilyIndices {
@@ -42,8 +50,26 @@ pub struct VulkanDevice {
     /// The presentation queue
     pub present_queue: vk::Queue,
     
+    /// The queue used for uploads (the graphics queue without a dedicated transfer family)
+    pub transfer_queue: vk::Queue,
+    
     /// Queue family indices
     pub queue_families: QueueFamilyIndices,
+    
+    /// Every detected physical device with its score (for the stats HUD)
+    pub gpus: Vec<GpuInfo>,
+    
+    /// Limits, features and extensions of the physical device
+    pub capabilities: DeviceCapabilities,
+    
+    /// Whether passes use dynamic rendering or render pass objects
+    pub rendering_mode: RenderingMode,
+    
+    /// Whether the descriptor indexing features of the bindless texture table are enabled
+    pub bindless_textures: bool,
+    
+    /// Whether `VK_EXT_memory_budget` is enabled, see `memory_usage::heap_budgets`
+    pub memory_budget: bool,
 }
 
 impl VulkanDevice {
@@ -52,24 +78,56 @@ impl VulkanDevice {
     /// # Arguments
     /// * `instance` - The Vulkan instance
     /// * `entry` - The Vulkan entry point
+    /// * `instance_api_version` - API version the instance was created with
     /// * `surface` - The surface to present to
+    /// * `gpu_override` - Device forced with `--gpu`, if any
+    /// * `extra_extensions` - Device extensions enabled on top of the required ones,
+    ///   e.g. those an OpenXR runtime asks for
     ///
     /// # Returns
     /// A new VulkanDevice instance
     ///
     /// # Errors
-    /// Returns an error if device creation fails
-    pub fn new(instance: &Instance, entry: &Entry, surface: vk::SurfaceKHR) -> Result<Self> {
+    /// Returns an error if device creation fails or the device misses a required limit
+    pub fn new(
+        instance: &Instance,
+        entry: &Entry,
+        instance_api_version: u32,
+        surface: vk::SurfaceKHR,
+        gpu_override: Option<&GpuSelector>,
+        extra_extensions: &[CString],
+    ) -> Result<Self> {
         info!("Creating Vulkan device");
         
         let surface_loader = ash::khr::surface::Instance::new(entry, instance);
         
-        let (physical_device, queue_families) = Self::pick_physical_device(instance, entry, &surface_loader, surface)?;
+        let (physical_device, queue_families, gpus) = Self::pick_physical_device(instance, entry, &surface_loader, surface, gpu_override)?;
         
-        let (device, graphics_queue, present_queue) = Self::create_logical_device(
+        let capabilities = DeviceCapabilities::query(
             instance,
             physical_device,
-            &queue_families
+            instance_api_version,
+            queue_families.graphics_family.unwrap_or(0),
+        );
+        capabilities.log_report();
+        capabilities.check_requirements(SdfPushConstants::SIZE)?;
+        
+        let rendering_mode = RenderingMode::select(&capabilities);
+        info!("Rendering with {}", rendering_mode.name());
+        let bindless_textures = BindlessTextures::is_supported(&capabilities);
+        // Reading the budget needs vkGetPhysicalDeviceMemoryProperties2 (Vulkan 1.1)
+        let memory_budget = capabilities.has_extension(config::vulkan::MEMORY_BUDGET_EXTENSION)
+            && capabilities.api_version() >= vk::API_VERSION_1_1;
+        info!("Memory budget {}", if memory_budget { "available" } else { "unavailable, showing allocations only" });
+        
+        let (device, graphics_queue, present_queue, transfer_queue) = Self::create_logical_device(
+            instance,
+            physical_device,
+            &queue_families,
+            rendering_mode,
+            bindless_textures,
+            memory_budget,
+            extra_extensions,
         )?;
         
         info!("Vulkan device created successfully");
@@ -79,20 +137,31 @@ impl VulkanDevice {
             physical_device,
             graphics_queue,
             present_queue,
+            transfer_queue,
             queue_families,
+            gpus,
+            capabilities,
+            rendering_mode,
+            bindless_textures,
+            memory_budget,
         })
     }
     
     /// Pick a suitable physical device
     ///
+    /// Devices missing a queue family, a required extension or surface
+    /// support are skipped; the rest are scored (discrete GPUs first) unless
+    /// `gpu_override` names a suitable device.
+    ///
     /// # Arguments
     /// * `instance` - The Vulkan instance
     /// * `entry` - The Vulkan entry point
     /// * `surface_loader` - The surface loader
     /// * `surface` - The surface to present to
+    /// * `gpu_override` - Device forced with `--gpu`, if any
     ///
     /// # Returns
-    /// A tuple of (physical_device, queue_families)
+    /// A tuple of (physical_device, queue_families, all detected GPUs)
     ///
     /// # Errors
     /// Returns an error if no suitable device is found
@@ -100,8 +169,9 @@ impl VulkanDevice {
         instance: &Instance,
         _entry: &Entry,
         surface_loader: &ash::khr::surface::Instance,
-        surface: vk::SurfaceKHR
-    ) -> Result<(vk::PhysicalDevice, QueueFamilyIndices)> {
+        surface: vk::SurfaceKHR,
+        gpu_override: Option<&GpuSelector>,
+    ) -> Result<(vk::PhysicalDevice, QueueFamilyIndices, Vec<GpuInfo>)> {
         debug!("Enumerating physical devices");
         
         let devices = unsafe {
@@ -111,17 +181,88 @@ impl VulkanDevice {
         
         debug!("Found {} physical devices", devices.len());
         
+        let mut candidates = Vec::with_capacity(devices.len());
+        let mut gpus = Vec::with_capacity(devices.len());
         for (i, &device) in devices.iter().enumerate() {
+            let properties = unsafe { instance.get_physical_device_properties(device) };
+            let device_name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+                .to_string_lossy()
+                .to_string();
             let indices = Self::find_queue_families(instance, device, surface_loader, surface);
-            if indices.is_complete() {
-                let properties = unsafe { instance.get_physical_device_properties(device) };
-                let device_name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) };
-                info!("Selected physical device {}: {}", i, device_name.to_string_lossy());
-                return Ok((device, indices));
-            }
+            
+            let unsuitable_reason = if !indices.is_complete() {
+                Some("missing graphics or present queue".to_string())
+            } else if let Some(extension) = Self::first_missing_extension(instance, device) {
+                Some(format!("missing extension {}", extension))
+            } else if !Self::has_surface_support(device, surface_loader, surface) {
+                Some("no surface formats or present modes".to_string())
+            } else {
+                None
+            };
+            
+            let gpu = GpuInfo {
+                index: i,
+                name: device_name,
+                device_type: properties.device_type,
+                api_version: format!(
+                    "{}.{}.{}",
+                    vk::api_version_major(properties.api_version),
+                    vk::api_version_minor(properties.api_version),
+                    vk::api_version_patch(properties.api_version)
+                ),
+                score: unsuitable_reason.is_none().then(|| gpu_selection::score_device(&properties)),
+                unsuitable_reason,
+                selected: false,
+            };
+            info!(
+                "GPU {}: {} ({}, Vulkan {}) - {}",
+                i,
+                gpu.name,
+                gpu.type_name(),
+                gpu.api_version,
+                match (&gpu.score, &gpu.unsuitable_reason) {
+                    (Some(score), _) => format!("score {}", score),
+                    (None, Some(reason)) => format!("unsuitable: {}", reason),
+                    (None, None) => "unsuitable".to_string(),
+                }
+            );
+            
+            candidates.push((device, indices));
+            gpus.push(gpu);
         }
         
-        Err(VulkanError::DeviceCreation("No suitable physical device found".to_string()).into())
+        let chosen = gpu_selection::choose_device(&gpus, gpu_override)
+            .ok_or_else(|| VulkanError::DeviceCreation("No suitable physical device found".to_string()))?;
+        gpus[chosen].selected = true;
+        info!("Selected physical device {}: {}", gpus[chosen].index, gpus[chosen].name);
+        
+        let (device, indices) = candidates.swap_remove(chosen);
+        Ok((device, indices, gpus))
+    }
+    
+    /// Find the first required device extension the device does not support
+    ///
+    /// # Returns
+    /// The missing extension name, or None if all are supported
+    fn first_missing_extension(instance: &Instance, device: vk::PhysicalDevice) -> Option<&'static str> {
+        let available = unsafe { instance.enumerate_device_extension_properties(device) }.unwrap_or_default();
+        config::vulkan::DEVICE_EXTENSIONS.iter().copied().find(|&required| {
+            !available.iter().any(|extension| {
+                let name = unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) };
+                name.to_str() == Ok(required)
+            })
+        })
+    }
+    
+    /// Check that the device can present to the surface at all
+    fn has_surface_support(
+        device: vk::PhysicalDevice,
+        surface_loader: &ash::khr::surface::Instance,
+        surface: vk::SurfaceKHR
+    ) -> bool {
+        let formats = unsafe { surface_loader.get_physical_device_surface_formats(device, surface) }.unwrap_or_default();
+        let present_modes = unsafe { surface_loader.get_physical_device_surface_present_modes(device, surface) }.unwrap_or_default();
+        !formats.is_empty() && !present_modes.is_empty()
     }
     
     /// Find queue families for a physical device
@@ -151,24 +292,33 @@ impl VulkanDevice {
         for (i, queue_family) in queue_families.iter().enumerate() {
             debug!("Queue family {}: flags={:?}", i, queue_family.queue_flags);
             
-            if queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS) {
+            if indices.graphics_family.is_none() && queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS) {
                 indices.graphics_family = Some(i as u32);
                 debug!("Found graphics queue family: {}", i);
             }
             
-            let present_support = unsafe {
-                surface_loader.get_physical_device_surface_support(device, i as u32, surface)
-                    .unwrap_or(false)
-            };
-            
-            if present_support {
-                indices.present_family = Some(i as u32);
-                debug!("Found present queue family: {}", i);
+            if indices.present_family.is_none() {
+                let present_support = unsafe {
+                    surface_loader.get_physical_device_surface_support(device, i as u32, surface)
+                        .unwrap_or(false)
+                };
+                
+                if present_support {
+                    indices.present_family = Some(i as u32);
+                    debug!("Found present queue family: {}", i);
+                }
             }
             
-            if indices.is_complete() {
-                debug!("All required queue families found");
-                break;
+            // Prefer a transfer-only family (DMA engine) over one that also does compute
+            let flags = queue_family.queue_flags;
+            if flags.contains(vk::QueueFlags::TRANSFER) && !flags.contains(vk::QueueFlags::GRAPHICS) {
+                let transfer_only = !flags.contains(vk::QueueFlags::COMPUTE);
+                let current_is_transfer_only = indices.transfer_family
+                    .is_some_and(|index| !queue_families[index as usize].queue_flags.contains(vk::QueueFlags::COMPUTE));
+                if indices.transfer_family.is_none() || (transfer_only && !current_is_transfer_only) {
+                    indices.transfer_family = Some(i as u32);
+                    debug!("Found dedicated transfer queue family: {}", i);
+                }
             }
         }
         
@@ -181,17 +331,25 @@ impl VulkanDevice {
     /// * `instance` - The Vulkan instance
     /// * `physical_device` - The physical device
     /// * `indices` - The queue family indices
+    /// * `rendering_mode` - Dynamic rendering is enabled as a feature if selected
+    /// * `bindless_textures` - Whether to enable the descriptor indexing features
+    /// * `memory_budget` - Whether to enable `VK_EXT_memory_budget`
+    /// * `extra_extensions` - Extensions enabled on top of the required ones
     ///
     /// # Returns
-    /// A tuple of (device, graphics_queue, present_queue)
+    /// A tuple of (device, graphics_queue, present_queue, transfer_queue)
     ///
     /// # Errors
     /// Returns an error if device creation fails
     fn create_logical_device(
         instance: &Instance,
         physical_device: vk::PhysicalDevice,
-        indices: &QueueFamilyIndices
-    ) -> Result<(Device, vk::Queue, vk::Queue)> {
+        indices: &QueueFamilyIndices,
+        rendering_mode: RenderingMode,
+        bindless_textures: bool,
+        memory_budget: bool,
+        extra_extensions: &[CString],
+    ) -> Result<(Device, vk::Queue, vk::Queue, vk::Queue)> {
         debug!("Creating logical device");
         
         let queue_priorities = [1.0];
@@ -213,12 +371,30 @@ impl VulkanDevice {
             debug!("Using same queue for graphics and presentation");
         }
         
+        if let Some(transfer_family) = indices.transfer_family {
+            let queue_create_info = vk::DeviceQueueCreateInfo::default()
+                .queue_family_index(transfer_family)
+                .queue_priorities(&queue_priorities);
+            queue_create_infos.push(queue_create_info);
+            debug!("Using dedicated transfer queue family {}", transfer_family);
+        } else {
+            debug!("No dedicated transfer queue family, uploads use the graphics queue");
+        }
+        
         // Convert extension names to CStrings for proper null-termination
-        let device_extensions_cstr: Vec<CString> = config::vulkan::DEVICE_EXTENSIONS
+        let optional_extensions = memory_budget.then_some(config::vulkan::MEMORY_BUDGET_EXTENSION);
+        let mut device_extensions_cstr: Vec<CString> = config::vulkan::DEVICE_EXTENSIONS
             .iter()
-            .map(|&ext| CString::new(ext))
+            .copied()
+            .chain(optional_extensions)
+            .map(CString::new)
             .collect::<std::result::Result<Vec<CString>, _>>()
             .map_err(|e| VulkanError::DeviceCreation(format!("Failed to create extension string: {}", e)))?;
+        for extension in extra_extensions {
+            if !device_extensions_cstr.contains(extension) {
+                device_extensions_cstr.push(extension.clone());
+            }
+        }
         
         // Convert to raw pointers
         let device_extensions: Vec<*const i8> = device_extensions_cstr
@@ -226,12 +402,27 @@ impl VulkanDevice {
             .map(|ext| ext.as_ptr())
             .collect();
         
-        debug!("Device extensions: {:?}", config::vulkan::DEVICE_EXTENSIONS);
+        debug!("Device extensions: {:?}", device_extensions_cstr);
         
-        let create_info = vk::DeviceCreateInfo::default()
+        let mut create_info = vk::DeviceCreateInfo::default()
             .queue_create_infos(&queue_create_infos)
             .enabled_extension_names(&device_extensions);
         
+        let mut vulkan13_features = vk::PhysicalDeviceVulkan13Features::default().dynamic_rendering(true);
+        if rendering_mode == RenderingMode::Dynamic {
+            create_info = create_info.push_next(&mut vulkan13_features);
+        }
+        let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::default()
+            .runtime_descriptor_array(true)
+            .descriptor_binding_partially_bound(true)
+            .descriptor_binding_variable_descriptor_count(true)
+            .shader_sampled_image_array_non_uniform_indexing(true)
+            .descriptor_binding_sampled_image_update_after_bind(true)
+            .descriptor_binding_update_unused_while_pending(true);
+        if bindless_textures {
+            create_info = create_info.push_next(&mut vulkan12_features);
+        }
+        
         let device = unsafe {
             instance.create_device(physical_device, &create_info, None)
                 .map_err(|e| VulkanError::DeviceCreation(format!("Failed to create logical device: {:?}", e)))?
@@ -239,9 +430,13 @@ impl VulkanDevice {
         
         let graphics_queue = unsafe { device.get_device_queue(indices.graphics_family.unwrap(), 0) };
         let present_queue = unsafe { device.get_device_queue(indices.present_family.unwrap(), 0) };
+        let transfer_queue = match indices.transfer_family {
+            Some(transfer_family) => unsafe { device.get_device_queue(transfer_family, 0) },
+            None => graphics_queue,
+        };
         
         debug!("Logical device created successfully");
-        Ok((device, graphics_queue, present_queue))
+        Ok((device, graphics_queue, present_queue, transfer_queue))
     }
     
     /// Get the name of the physical device
@@ -263,6 +458,30 @@ impl VulkanDevice {
         device_name.to_string_lossy().to_string()
     }
     
+    /// Describe the selected device and its driver for crash reports
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    ///
+    /// # Returns
+    /// Device name and type, vendor and device IDs, Vulkan and driver versions
+    pub fn describe(&self, instance: &Instance) -> String {
+        let properties = self.get_device_properties(instance);
+        let selected = self.gpus.iter().find(|gpu| gpu.selected);
+        let type_name = selected.map_or("Unknown", |gpu| gpu.type_name());
+        format!(
+            "{} ({})\nVendor 0x{:04X}, device 0x{:04X}\nVulkan {}.{}.{}\nDriver {}",
+            self.get_device_name(instance),
+            type_name,
+            properties.vendor_id,
+            properties.device_id,
+            vk::api_version_major(properties.api_version),
+            vk::api_version_minor(properties.api_version),
+            vk::api_version_patch(properties.api_version),
+            gpu_selection::format_driver_version(properties.vendor_id, properties.driver_version)
+        )
+    }
+    
     /// Get the properties of the physical device
     ///
     /// # Arguments
@@ -270,7 +489,6 @@ impl VulkanDevice {
     ///
     /// # Returns
     /// The physical device properties
-    #[allow(dead_code)] // For future device queries
     pub fn get_device_properties(&self, instance: &Instance) -> vk::PhysicalDeviceProperties {
         unsafe {
             instance.get_physical_device_properties(self.physical_device)
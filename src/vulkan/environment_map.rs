@@ -0,0 +1,400 @@
+//! HDRI environment map sampled by the SDF shader
+//!
+//! The map is an equirectangular RGBA16F texture bound at set 0, binding 1
+//! next to the scene uniform buffer. It is sampled for the background of
+//! rays that miss the scene and for reflections. Until a map is requested,
+//! and after it is cleared, a 1x1 black placeholder keeps the binding valid.
+//!
+//! The file is read and decoded by the asset manager on the job pool (see
+//! `assets`), then the image is uploaded on the transfer queue. From the
+//! request until the upload has completed a 1x1 pink texture is bound, so
+//! the background shows the map is on its way; a file that fails to decode
+//! or upload leaves the previous map bound, or the placeholder if there was
+//! none. Switching maps never stalls a frame.
+//!
+//! Maps that are no longer bound are cached, so switching back to one is
+//! instant, until the residency budget evicts them (see `vulkan::residency`).
+//! After a map file changed on disk `reload` drops its cached copy; a bound
+//! map stays bound until the new version has been uploaded.
+
+use std::path::{Path, PathBuf};
+use ash::vk;
+use ash::{Device, Instance};
+use log::{debug, info};
+use crate::error::{Result, VulkanError};
+use crate::config;
+use crate::image_io::HdrImage;
+use crate::vulkan::deletion_queue::DeletionQueue;
+use crate::vulkan::residency::ResidentAsset;
+use crate::vulkan::texture::Texture;
+use crate::vulkan::tonemap::f32_to_f16;
+use crate::vulkan::upload::{UploadContext, UploadTicket};
+
+/// Format of environment maps; linear filtering of 32-bit floats is optional
+const ENVIRONMENT_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// A map about to be bound
+struct PendingMap {
+    texture: Texture,
+    /// Upload to wait for, None for a map taken from the cache
+    ticket: Option<UploadTicket>,
+    path: PathBuf,
+    /// Whether the file changed since the upload started
+    stale: bool,
+}
+
+/// What the bound descriptor shows
+#[derive(Debug, Clone, PartialEq)]
+enum Shown {
+    /// No map is requested
+    Placeholder,
+    /// The requested map is not uploaded yet
+    Loading,
+    /// The requested map
+    Map(PathBuf),
+}
+
+/// Currently bound environment map and the one being uploaded
+pub struct EnvironmentMap {
+    sampler: vk::Sampler,
+    placeholder: Texture,
+    /// Shown while the requested map is loading
+    loading: Texture,
+    /// Last uploaded map, shown while it is the requested one
+    current: Option<(Texture, PathBuf)>,
+    /// Whether the current map's file changed since it was uploaded
+    stale: bool,
+    pending: Option<PendingMap>,
+    /// Maps bound before, kept until evicted
+    cached: Vec<(Texture, PathBuf)>,
+    /// Last path passed to `request`, loaded or not
+    requested: Option<PathBuf>,
+    /// Whether decoding or uploading the requested map failed
+    failed: bool,
+    /// What the descriptor returned by `poll` last shows
+    shown: Shown,
+    device: Device,
+}
+
+impl EnvironmentMap {
+    /// Create the sampler, the placeholder map and the loading texture
+    ///
+    /// Waits for their uploads so the binding is valid from the first frame.
+    ///
+    /// # Errors
+    /// Returns an error if the sampler or a texture cannot be created
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        upload: &mut UploadContext,
+    ) -> Result<Self> {
+        // Wrap around horizontally, the poles clamp
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+        let sampler = unsafe {
+            device.create_sampler(&sampler_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create environment sampler: {:?}", e)))?
+        };
+
+        let placeholder = match Self::solid_texture(instance, physical_device, device, upload, [0.0; 3]) {
+            Ok(texture) => texture,
+            Err(e) => {
+                unsafe { device.destroy_sampler(sampler, None) };
+                return Err(e);
+            }
+        };
+        let loading = match Self::solid_texture(instance, physical_device, device, upload, config::assets::LOADING_TEXTURE_COLOR) {
+            Ok(texture) => texture,
+            Err(e) => {
+                placeholder.destroy(device);
+                unsafe { device.destroy_sampler(sampler, None) };
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            sampler,
+            placeholder,
+            loading,
+            current: None,
+            stale: false,
+            pending: None,
+            cached: Vec::new(),
+            requested: None,
+            failed: false,
+            shown: Shown::Placeholder,
+            device: device.clone(),
+        })
+    }
+
+    /// Upload a 1x1 texture of one color and wait for it
+    fn solid_texture(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        upload: &mut UploadContext,
+        color: [f32; 3],
+    ) -> Result<Texture> {
+        let (texture, ticket) = Texture::upload(
+            instance,
+            physical_device,
+            device,
+            upload,
+            vk::Extent2D { width: 1, height: 1 },
+            ENVIRONMENT_FORMAT,
+            &encode_rgba16f(&[color]),
+        )?;
+        match upload.wait(ticket) {
+            Ok(()) => Ok(texture),
+            Err(e) => {
+                texture.destroy(device);
+                Err(e)
+            }
+        }
+    }
+
+    /// Ask for a map file to be shown, None to go back to the placeholder
+    ///
+    /// Called every frame. The loading texture is bound until the file's
+    /// image has been passed in and uploaded; a cached map is bound without
+    /// it. A file that failed to decode or upload is not tried again until
+    /// another path was requested in between or the file changed.
+    ///
+    /// # Arguments
+    /// * `path` - Map file, None for no map
+    /// * `image` - The file's decoded image, None while it is loading
+    /// * `decode_failed` - Whether the file could not be read or decoded
+    ///
+    /// # Errors
+    /// Returns an error if the upload cannot be submitted; the loading
+    /// texture stays bound
+    pub fn request(
+        &mut self,
+        path: Option<&Path>,
+        image: Option<&HdrImage>,
+        decode_failed: bool,
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        upload: &mut UploadContext,
+    ) -> Result<()> {
+        if self.requested.as_deref() != path {
+            self.requested = path.map(Path::to_path_buf);
+            self.failed = false;
+
+            // A newer request supersedes an upload still in flight; no frame has sampled it
+            if let Some(pending) = self.pending.take() {
+                if let Some(ticket) = pending.ticket {
+                    upload.wait(ticket)?;
+                }
+                self.cached.push((pending.texture, pending.path));
+            }
+        }
+
+        let Some(path) = path else {
+            return Ok(());
+        };
+        if decode_failed && !self.failed {
+            debug!("Environment map {} failed to decode; the previous map stays bound", path.display());
+            self.failed = true;
+        }
+        if self.failed || self.pending.is_some() || (self.loaded_path() == Some(path) && !self.stale) {
+            return Ok(());
+        }
+        if let Some(index) = self.cached.iter().position(|(_, cached)| cached == path) {
+            debug!("Environment map {} taken from the cache", path.display());
+            let (texture, path) = self.cached.swap_remove(index);
+            self.pending = Some(PendingMap { texture, ticket: None, path, stale: false });
+            return Ok(());
+        }
+        let Some(image) = image else {
+            return Ok(());
+        };
+        info!("Uploading environment map {}", path.display());
+        let uploaded = Texture::upload(
+            instance,
+            physical_device,
+            &self.device,
+            upload,
+            vk::Extent2D { width: image.width, height: image.height },
+            ENVIRONMENT_FORMAT,
+            &encode_rgba16f(&image.pixels),
+        );
+        match uploaded {
+            Ok((texture, ticket)) => {
+                self.pending = Some(PendingMap { texture, ticket: Some(ticket), path: path.to_path_buf(), stale: false });
+                Ok(())
+            }
+            Err(e) => {
+                self.failed = true;
+                Err(e)
+            }
+        }
+    }
+
+    /// Swap in a completed upload, or the placeholder once the map was cleared
+    ///
+    /// The replaced map goes to the cache, or to the deletion queue if its
+    /// file changed since.
+    ///
+    /// # Returns
+    /// The descriptor to bind from now on, if it changed
+    pub fn poll(&mut self, upload: &mut UploadContext, deletion_queue: &mut DeletionQueue) -> Option<vk::DescriptorImageInfo> {
+        let ready = self.pending.as_ref().is_some_and(|pending| match pending.ticket {
+            Some(ticket) => upload.is_complete(ticket),
+            None => true,
+        });
+        if let Some(pending) = self.pending.take_if(|_| ready) {
+            debug!("Environment map {} bound", pending.path.display());
+            let previous = self.current.replace((pending.texture, pending.path));
+            self.unbind(previous, deletion_queue);
+            self.stale = pending.stale;
+        }
+        if self.requested.is_none() {
+            let previous = self.current.take();
+            self.unbind(previous, deletion_queue);
+            self.stale = false;
+        }
+
+        let shown = self.shown();
+        if shown == self.shown {
+            return None;
+        }
+        self.shown = shown;
+        Some(self.descriptor_info())
+    }
+
+    /// Cache a map that is no longer bound, or retire it if its file changed
+    fn unbind(&mut self, previous: Option<(Texture, PathBuf)>, deletion_queue: &mut DeletionQueue) {
+        match previous {
+            Some((texture, _)) if self.stale => texture.retire(deletion_queue),
+            Some(previous) => self.cached.push(previous),
+            None => {}
+        }
+    }
+
+    /// Drop the uploaded copies of a map file that changed on disk
+    ///
+    /// A cached copy goes to the deletion queue right away; a bound or
+    /// pending one stays until the next `request` with the file's new image
+    /// has been uploaded. An upload that failed is tried again.
+    pub fn reload(&mut self, path: &Path, deletion_queue: &mut DeletionQueue) {
+        self.evict(path, deletion_queue);
+        if self.current.as_ref().is_some_and(|(_, current)| current == path) {
+            self.stale = true;
+        }
+        if let Some(pending) = self.pending.as_mut().filter(|pending| pending.path == path) {
+            pending.stale = true;
+        }
+        if self.requested.as_deref() == Some(path) {
+            self.failed = false;
+        }
+    }
+
+    /// Maps in device memory with their size and whether they are bound or about to be
+    pub fn residents(&self) -> impl Iterator<Item = (ResidentAsset, vk::DeviceSize, bool)> + '_ {
+        let bound = self.current.iter().map(|(texture, path)| (texture, path, true));
+        let pending = self.pending.iter().map(|pending| (&pending.texture, &pending.path, true));
+        let cached = self.cached.iter().map(|(texture, path)| (texture, path, false));
+        bound.chain(pending).chain(cached)
+            .map(|(texture, path, in_use)| (ResidentAsset::EnvironmentMap(path.clone()), texture.size, in_use))
+    }
+
+    /// Free a cached map; it is uploaded again when next requested
+    ///
+    /// The texture goes to the deletion queue, since frames in flight may
+    /// still sample it.
+    pub fn evict(&mut self, path: &Path, deletion_queue: &mut DeletionQueue) {
+        if let Some(index) = self.cached.iter().position(|(_, cached)| cached == path) {
+            debug!("Evicted environment map {}", path.display());
+            let (texture, _) = self.cached.swap_remove(index);
+            texture.retire(deletion_queue);
+        }
+    }
+
+    /// What the descriptor should show: the requested map, the loading
+    /// texture until it is uploaded, the previous map if it failed and the
+    /// placeholder when none is requested or there is no previous map
+    fn shown(&self) -> Shown {
+        match (&self.current, &self.requested) {
+            (_, None) => Shown::Placeholder,
+            (Some((_, path)), Some(requested)) if path == requested || self.failed => Shown::Map(path.clone()),
+            (None, Some(_)) if self.failed => Shown::Placeholder,
+            (_, Some(_)) => Shown::Loading,
+        }
+    }
+
+    /// Descriptor of the texture `shown` picks
+    pub fn descriptor_info(&self) -> vk::DescriptorImageInfo {
+        let texture = match (self.shown(), &self.current) {
+            (Shown::Map(_), Some((texture, _))) => texture,
+            (Shown::Loading, _) => &self.loading,
+            _ => &self.placeholder,
+        };
+        texture.descriptor_info(self.sampler)
+    }
+
+    /// Descriptor of the placeholder, which stays valid for the map's lifetime
+    pub fn placeholder_info(&self) -> vk::DescriptorImageInfo {
+        self.placeholder.descriptor_info(self.sampler)
+    }
+
+    /// File of the bound map, None while the placeholder or loading texture is bound
+    pub fn loaded_path(&self) -> Option<&Path> {
+        self.current
+            .as_ref()
+            .map(|(_, path)| path.as_path())
+            .filter(|path| self.requested.as_deref() == Some(*path))
+    }
+
+    /// Whether a map upload is in flight
+    pub fn is_loading(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+impl Drop for EnvironmentMap {
+    fn drop(&mut self) {
+        // The device is idle at shutdown, including pending uploads
+        if let Some(pending) = self.pending.take() {
+            pending.texture.destroy(&self.device);
+        }
+        if let Some((texture, _)) = self.current.take() {
+            texture.destroy(&self.device);
+        }
+        for (texture, _) in self.cached.drain(..) {
+            texture.destroy(&self.device);
+        }
+        self.placeholder.destroy(&self.device);
+        self.loading.destroy(&self.device);
+        unsafe { self.device.destroy_sampler(self.sampler, None) };
+    }
+}
+
+/// Pack linear RGB pixels as RGBA16F with opaque alpha
+fn encode_rgba16f(pixels: &[[f32; 3]]) -> Vec<u8> {
+    let one = f32_to_f16(1.0);
+    pixels
+        .iter()
+        .flat_map(|&[r, g, b]| [f32_to_f16(r), f32_to_f16(g), f32_to_f16(b), one])
+        .flat_map(u16::to_ne_bytes)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_rgba16f_layout() {
+        let bytes = encode_rgba16f(&[[1.0, 0.0, -2.0], [0.5, 0.5, 0.5]]);
+        let expected = [0x3c00u16, 0x0000, 0xc000, 0x3c00, 0x3800, 0x3800, 0x3800, 0x3c00];
+        assert_eq!(bytes, expected.map(u16::to_ne_bytes).concat());
+    }
+}
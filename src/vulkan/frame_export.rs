@@ -0,0 +1,707 @@
+//! Offline frame export
+//!
+//! Renders the SDF scene at a fixed timestep into an offscreen image of any
+//! resolution, independent of the swapchain, reads every frame back to the
+//! host, tonemaps it to 8-bit sRGB and hands it to a sink: a numbered PNG
+//! sequence or an ffmpeg process fed raw RGBA frames on stdin. Exports are started with
+//! `--export start:end:fps` or from the HUD export panel and advance one
+//! frame per rendered window frame, so the window stays responsive.
+//!
+//! Stills (`still_export`) use the same target, read back without tonemapping.
+
+use ash::vk;
+use ash::{Device, Instance};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem, VkResultExt, VulkanError};
+use crate::image_io;
+use crate::project;
+use crate::vulkan::depth::{DepthBuffer, DepthUsage};
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::gbuffer::GBuffer;
+use crate::vulkan::rendering::{PassLayout, PassTarget};
+use crate::vulkan::scene_buffer::find_memory_type;
+use crate::vulkan::tonemap::{f16_to_f32, TonemapSettings};
+use log::{debug, info};
+
+/// Time to wait for one exported frame before reporting an error
+const EXPORT_TIMEOUT_NS: u64 = 10_000_000_000;
+
+/// Where exported frames go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `frame_00000.png`, `frame_00001.png`, ... in the output directory
+    PngSequence,
+    /// Raw frames piped to ffmpeg, encoded to `export.mp4` in the output directory
+    Ffmpeg,
+}
+
+impl ExportFormat {
+    /// All formats in display order
+    pub const ALL: [ExportFormat; 2] = [ExportFormat::PngSequence, ExportFormat::Ffmpeg];
+
+    /// Name shown in the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::PngSequence => "PNG sequence",
+            ExportFormat::Ffmpeg => "ffmpeg (MP4)",
+        }
+    }
+}
+
+/// Time range, frame rate, resolution and output of an export
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSettings {
+    /// Scene time of the first frame in seconds
+    pub start: f32,
+    /// Scene time the export stops before, in seconds
+    pub end: f32,
+    /// Frames per second of scene time
+    pub fps: u32,
+    /// Width of the exported frames
+    pub width: u32,
+    /// Height of the exported frames
+    pub height: u32,
+    /// Output kind
+    pub format: ExportFormat,
+    /// Directory the frames or the video are written to
+    pub output_dir: PathBuf,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            start: 0.0,
+            end: config::export::DEFAULT_DURATION_SECS,
+            fps: config::export::DEFAULT_FPS,
+            width: config::export::DEFAULT_WIDTH,
+            height: config::export::DEFAULT_HEIGHT,
+            format: ExportFormat::PngSequence,
+            output_dir: project::current().output_dir(),
+        }
+    }
+}
+
+impl ExportSettings {
+    /// Parse a `start:end:fps` range, e.g. `0:4:30`
+    pub fn parse_range(spec: &str) -> Option<(f32, f32, u32)> {
+        let mut parts = spec.split(':');
+        let start = parts.next()?.trim().parse::<f32>().ok()?;
+        let end = parts.next()?.trim().parse::<f32>().ok()?;
+        let fps = parts.next()?.trim().parse::<u32>().ok()?;
+        if parts.next().is_some() || !start.is_finite() || !end.is_finite() || end <= start || fps == 0 {
+            return None;
+        }
+        Some((start, end, fps))
+    }
+
+    /// Parse a `WIDTHxHEIGHT` resolution, e.g. `1920x1080`
+    pub fn parse_size(spec: &str) -> Option<(u32, u32)> {
+        let (width, height) = spec.split_once(['x', 'X'])?;
+        let width = width.trim().parse::<u32>().ok().filter(|&w| w > 0)?;
+        let height = height.trim().parse::<u32>().ok().filter(|&h| h > 0)?;
+        Some((width, height))
+    }
+
+    /// Read an export request from command line arguments
+    ///
+    /// `--export start:end:fps` starts an export; `--export-size WxH`,
+    /// `--export-dir DIR` and `--export-ffmpeg` adjust it.
+    ///
+    /// # Arguments
+    /// * `args` - Arguments without the program name
+    ///
+    /// # Returns
+    /// `Ok(None)` if no export was requested
+    ///
+    /// # Errors
+    /// Returns an error if an export argument is malformed
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Self>> {
+        let mut settings = Self::default();
+        let mut requested = false;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = |name: &str| {
+                inline_value.clone().or_else(|| args.next())
+                    .ok_or_else(|| AppError::Generic(format!("{} needs a value", name)))
+            };
+
+            match name.as_str() {
+                "--export" => {
+                    let spec = value("--export")?;
+                    let (start, end, fps) = Self::parse_range(&spec)
+                        .ok_or_else(|| AppError::Generic(format!("Invalid --export range '{}', expected start:end:fps", spec)))?;
+                    settings.start = start;
+                    settings.end = end;
+                    settings.fps = fps;
+                    requested = true;
+                }
+                "--export-size" => {
+                    let spec = value("--export-size")?;
+                    let (width, height) = Self::parse_size(&spec)
+                        .ok_or_else(|| AppError::Generic(format!("Invalid --export-size '{}', expected WIDTHxHEIGHT", spec)))?;
+                    settings.width = width;
+                    settings.height = height;
+                }
+                "--export-dir" => settings.output_dir = PathBuf::from(value("--export-dir")?),
+                "--export-ffmpeg" => settings.format = ExportFormat::Ffmpeg,
+                _ => {}
+            }
+        }
+
+        Ok(requested.then_some(settings))
+    }
+
+    /// Number of frames in the export
+    pub fn frame_count(&self) -> usize {
+        ((self.end - self.start) * self.fps as f32).round().max(0.0) as usize
+    }
+
+    /// Scene time of a frame
+    pub fn frame_time(&self, index: usize) -> f32 {
+        self.start + index as f32 / self.fps as f32
+    }
+}
+
+/// Destination of exported frames
+pub enum FrameSink {
+    /// Numbered PNG files
+    PngSequence {
+        /// Output directory
+        dir: PathBuf,
+        /// Index of the next file
+        next_index: usize,
+    },
+    /// ffmpeg process reading raw RGBA frames from stdin
+    Ffmpeg {
+        /// The encoder process
+        child: Child,
+        /// Its stdin, closed to end the stream
+        stdin: Option<ChildStdin>,
+        /// Encoded video file
+        output: PathBuf,
+    },
+}
+
+impl FrameSink {
+    /// Create the output directory and open the sink
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created or ffmpeg cannot be started
+    pub fn open(settings: &ExportSettings) -> Result<Self> {
+        std::fs::create_dir_all(&settings.output_dir)
+            .context(Subsystem::Io, format!("create export directory {}", settings.output_dir.display()))?;
+
+        match settings.format {
+            ExportFormat::PngSequence => Ok(FrameSink::PngSequence {
+                dir: settings.output_dir.clone(),
+                next_index: 0,
+            }),
+            ExportFormat::Ffmpeg => {
+                let output = settings.output_dir.join("export.mp4");
+                let mut child = Command::new(config::export::FFMPEG_PATH)
+                    .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+                    .args(["-s", &format!("{}x{}", settings.width, settings.height)])
+                    .args(["-r", &settings.fps.to_string(), "-i", "-", "-pix_fmt", "yuv420p"])
+                    .arg(&output)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn()
+                    .context(Subsystem::Io, format!("start {}", config::export::FFMPEG_PATH))?;
+                let stdin = child.stdin.take();
+                Ok(FrameSink::Ffmpeg { child, stdin, output })
+            }
+        }
+    }
+
+    /// Write one frame
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written or ffmpeg stopped reading
+    pub fn write(&mut self, width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+        match self {
+            FrameSink::PngSequence { dir, next_index } => {
+                let path = dir.join(format!("frame_{:05}.png", next_index));
+                image_io::write_png(&path, width, height, rgba)?;
+                *next_index += 1;
+                Ok(())
+            }
+            FrameSink::Ffmpeg { stdin, .. } => {
+                let stdin = stdin.as_mut().ok_or_else(|| AppError::Generic("ffmpeg stdin is closed".to_string()))?;
+                stdin.write_all(rgba).context(Subsystem::Io, "pipe frame to ffmpeg")
+            }
+        }
+    }
+
+    /// Close the sink, waiting for ffmpeg to finish encoding
+    ///
+    /// # Returns
+    /// The directory or video file that was written
+    ///
+    /// # Errors
+    /// Returns an error if ffmpeg failed
+    pub fn finish(self) -> Result<PathBuf> {
+        match self {
+            FrameSink::PngSequence { dir, .. } => Ok(dir),
+            FrameSink::Ffmpeg { mut child, stdin, output } => {
+                drop(stdin);
+                let status = child.wait().context(Subsystem::Io, "wait for ffmpeg")?;
+                if !status.success() {
+                    return Err(AppError::Generic(format!("ffmpeg exited with {}", status)));
+                }
+                Ok(output)
+            }
+        }
+    }
+}
+
+/// Offscreen target, readback buffer and command buffer for exported frames
+pub struct FrameExporter {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    depth: DepthBuffer,
+    gbuffer: GBuffer,
+    framebuffer: vk::Framebuffer,
+    readback_buffer: vk::Buffer,
+    readback_memory: vk::DeviceMemory,
+    extent: vk::Extent2D,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    queue: vk::Queue,
+    device: Device,
+}
+
+impl FrameExporter {
+    /// Create the export target and its resources
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - The physical device used for memory type lookup
+    /// * `device` - The logical device
+    /// * `queue` - Graphics queue used for the export submits
+    /// * `queue_family_index` - Family of `queue`
+    /// * `pass` - Offscreen SDF pass the target is used with (`config::rendering::SCENE_FORMAT`)
+    /// * `extent` - Resolution of the exported frames
+    ///
+    /// # Errors
+    /// Returns an error if any Vulkan object creation fails
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        queue: vk::Queue,
+        queue_family_index: u32,
+        pass: &PassLayout,
+        extent: vk::Extent2D,
+    ) -> Result<Self> {
+        let format = config::rendering::SCENE_FORMAT;
+        info!("Creating frame exporter ({}x{}, {:?})", extent.width, extent.height, format);
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            // Sampled: the shared offscreen pass leaves its target in the shader read layout
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+
+        unsafe {
+            let image = device.create_image(&image_info, None)
+                .vk_context(Subsystem::Renderer, "create export image")?;
+            let requirements = device.get_image_memory_requirements(image);
+            let memory_type_index = find_memory_type(&memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .ok_or_else(|| VulkanError::MemoryAllocation("No device local memory type for the export image".to_string()))?;
+            let memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Textures, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .vk_context(Subsystem::Renderer, "allocate export image memory")?;
+            device.bind_image_memory(image, memory, 0)
+                .vk_context(Subsystem::Renderer, "bind export image memory")?;
+
+            let view = device.create_image_view(&vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(Self::color_range()), None)
+                .vk_context(Subsystem::Renderer, "create export image view")?;
+
+            let depth = DepthBuffer::new(device, &memory_properties, extent, DepthUsage::Discard)?;
+            let gbuffer = GBuffer::new(device, &memory_properties, extent)?;
+            let framebuffer = pass.create_framebuffer_with_extras(device, view, &gbuffer.views(), depth.view, extent)?;
+
+            let readback_size = extent.width as u64 * extent.height as u64 * Self::BYTES_PER_PIXEL as u64;
+            let readback_buffer = device.create_buffer(&vk::BufferCreateInfo::default()
+                .size(readback_size)
+                .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE), None)
+                .vk_context(Subsystem::Renderer, "create export readback buffer")?;
+            let requirements = device.get_buffer_memory_requirements(readback_buffer);
+            let memory_type_index = find_memory_type(
+                &memory_properties,
+                requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            ).ok_or_else(|| VulkanError::MemoryAllocation("No host visible memory type for the export readback".to_string()))?;
+            let readback_memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Buffers, vk::MemoryPropertyFlags::HOST_VISIBLE)
+                .vk_context(Subsystem::Renderer, "allocate export readback memory")?;
+            device.bind_buffer_memory(readback_buffer, readback_memory, 0)
+                .vk_context(Subsystem::Renderer, "bind export readback memory")?;
+
+            let command_pool = device.create_command_pool(&vk::CommandPoolCreateInfo::default()
+                .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                .queue_family_index(queue_family_index), None)
+                .vk_context(Subsystem::Renderer, "create export command pool")?;
+            let command_buffer = device.allocate_command_buffers(&vk::CommandBufferAllocateInfo::default()
+                .command_pool(command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1))
+                .vk_context(Subsystem::Renderer, "allocate export command buffer")?[0];
+            let fence = device.create_fence(&vk::FenceCreateInfo::default(), None)
+                .vk_context(Subsystem::Renderer, "create export fence")?;
+
+            Ok(Self {
+                image,
+                memory,
+                view,
+                depth,
+                gbuffer,
+                framebuffer,
+                readback_buffer,
+                readback_memory,
+                extent,
+                command_pool,
+                command_buffer,
+                fence,
+                queue,
+                device: device.clone(),
+            })
+        }
+    }
+
+    /// Resolution of the exported frames
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Size of one read back pixel (four FP16 channels)
+    const BYTES_PER_PIXEL: usize = 8;
+
+    /// Tonemap read back FP16 RGBA pixels to opaque RGBA8
+    fn encode_pixels(half_floats: &[u8], tonemap: &TonemapSettings) -> Vec<u8> {
+        Self::tonemap_pixels(&Self::to_halves(half_floats), tonemap)
+    }
+
+    /// Read back FP16 channels as half float bit patterns
+    fn to_halves(half_floats: &[u8]) -> Vec<u16> {
+        half_floats.chunks_exact(2).map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]])).collect()
+    }
+
+    /// Tonemap FP16 RGBA pixels (bit patterns) to opaque sRGB RGBA8
+    ///
+    /// # Arguments
+    /// * `halves` - Four half floats per pixel, as returned by `finish_hdr`
+    /// * `tonemap` - Exposure and operator, as in the composite pass
+    pub fn tonemap_pixels(halves: &[u16], tonemap: &TonemapSettings) -> Vec<u8> {
+        halves.chunks_exact(4)
+            .flat_map(|pixel| {
+                let [r, g, b] = tonemap.encode_srgb8([f16_to_f32(pixel[0]), f16_to_f32(pixel[1]), f16_to_f32(pixel[2])]);
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+
+    /// Subresource range of the single color mip and layer
+    fn color_range() -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    /// Images of the export target
+    fn target(&self) -> PassTarget {
+        PassTarget {
+            framebuffer: self.framebuffer,
+            color_image: self.image,
+            color_view: self.view,
+            depth_image: self.depth.image,
+            depth_view: self.depth.view,
+            extra_colors: self.gbuffer.extra_colors(),
+        }
+    }
+
+    /// Begin recording a frame: starts the offscreen SDF pass on the export target
+    ///
+    /// # Arguments
+    /// * `pass` - The pass the target was created for
+    /// * `clears` - Viewport rectangles of the export and their clear colors
+    /// * `prepare` - Records commands that go before the pass, e.g. the toy's buffers
+    ///
+    /// # Returns
+    /// The command buffer to record the scene draws into
+    ///
+    /// # Errors
+    /// Returns an error if the command buffer cannot be begun
+    pub fn begin(
+        &self,
+        pass: &PassLayout,
+        clears: &[(vk::Rect2D, [f32; 4])],
+        prepare: impl FnOnce(vk::CommandBuffer),
+    ) -> Result<vk::CommandBuffer> {
+        let render_area = vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: self.extent };
+        unsafe {
+            self.device.reset_command_buffer(self.command_buffer, vk::CommandBufferResetFlags::empty())
+                .vk_context(Subsystem::Renderer, "reset export command buffer")?;
+            self.device.begin_command_buffer(self.command_buffer, &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT))
+                .vk_context(Subsystem::Renderer, "begin export command buffer")?;
+        }
+        prepare(self.command_buffer);
+        pass.begin_with_clears(&self.device, self.command_buffer, &self.target(), render_area, clears);
+        Ok(self.command_buffer)
+    }
+
+    /// End the pass, copy the target to the host and wait for it
+    ///
+    /// # Arguments
+    /// * `pass` - The pass given to `begin`
+    /// * `tonemap` - Exposure and operator applied to the HDR target, as in the composite pass
+    ///
+    /// # Returns
+    /// Tightly packed sRGB RGBA8 pixels, top row first, with alpha forced to opaque
+    ///
+    /// # Errors
+    /// Returns an error if submission or the wait fails
+    pub fn finish(&self, pass: &PassLayout, tonemap: &TonemapSettings) -> Result<Vec<u8>> {
+        self.read_back(pass, |half_floats| Self::encode_pixels(half_floats, tonemap))
+    }
+
+    /// End the pass and read the HDR target back without tonemapping
+    ///
+    /// # Arguments
+    /// * `pass` - The pass given to `begin`
+    ///
+    /// # Returns
+    /// Four half floats (bit patterns) per pixel in linear RGBA, top row first
+    ///
+    /// # Errors
+    /// Returns an error if submission or the wait fails
+    pub fn finish_hdr(&self, pass: &PassLayout) -> Result<Vec<u16>> {
+        self.read_back(pass, Self::to_halves)
+    }
+
+    /// End the pass, copy the target to the host, wait for it and convert the mapped pixels
+    fn read_back<T>(&self, pass: &PassLayout, convert: impl FnOnce(&[u8]) -> Vec<T>) -> Result<Vec<T>> {
+        let cb = self.command_buffer;
+        pass.end(&self.device, cb, &self.target());
+        unsafe {
+            // The pass leaves the target ready for sampling; copy it out instead
+            let to_transfer = vk::ImageMemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .old_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(self.image)
+                .subresource_range(Self::color_range());
+            self.device.cmd_pipeline_barrier(
+                cb,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+
+            let region = vk::BufferImageCopy::default()
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_extent(vk::Extent3D { width: self.extent.width, height: self.extent.height, depth: 1 });
+            self.device.cmd_copy_image_to_buffer(cb, self.image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, self.readback_buffer, &[region]);
+
+            let to_host = vk::BufferMemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .buffer(self.readback_buffer)
+                .size(vk::WHOLE_SIZE);
+            self.device.cmd_pipeline_barrier(
+                cb,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[to_host],
+                &[],
+            );
+
+            self.device.end_command_buffer(cb)
+                .vk_context(Subsystem::Renderer, "end export command buffer")?;
+
+            let command_buffers = [cb];
+            let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+            self.device.reset_fences(&[self.fence])
+                .vk_context(Subsystem::Renderer, "reset export fence")?;
+            self.device.queue_submit(self.queue, &[submit_info], self.fence)
+                .vk_context(Subsystem::Renderer, "submit export frame")?;
+            self.device.wait_for_fences(&[self.fence], true, EXPORT_TIMEOUT_NS)
+                .vk_context(Subsystem::Renderer, "wait for export frame")?;
+
+            let size = self.extent.width as usize * self.extent.height as usize * Self::BYTES_PER_PIXEL;
+            let mapped = self.device.map_memory(self.readback_memory, 0, size as u64, vk::MemoryMapFlags::empty())
+                .vk_context(Subsystem::Renderer, "map export readback memory")?;
+            let pixels = convert(std::slice::from_raw_parts(mapped as *const u8, size));
+            self.device.unmap_memory(self.readback_memory);
+
+            debug!("Read back export frame ({} bytes)", size);
+            Ok(pixels)
+        }
+    }
+}
+
+impl Drop for FrameExporter {
+    fn drop(&mut self) {
+        debug!("Destroying frame exporter");
+        unsafe {
+            self.device.destroy_fence(self.fence, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.device.destroy_buffer(self.readback_buffer, None);
+            memory_usage::free(&self.device, self.readback_memory);
+            self.device.destroy_framebuffer(self.framebuffer, None);
+            self.device.destroy_image_view(self.view, None);
+            self.device.destroy_image(self.image, None);
+            memory_usage::free(&self.device, self.memory);
+        }
+        self.depth.destroy(&self.device);
+        self.gbuffer.destroy(&self.device);
+    }
+}
+
+/// A running export: settings, GPU target, sink and the next frame to render
+pub struct ExportJob {
+    /// Settings the export was started with
+    pub settings: ExportSettings,
+    /// Offscreen target of the export
+    pub exporter: FrameExporter,
+    /// Destination of the frames
+    sink: FrameSink,
+    /// Index of the next frame to render
+    next_frame: usize,
+}
+
+impl ExportJob {
+    /// Start an export
+    ///
+    /// # Errors
+    /// Returns an error if the sink cannot be opened
+    pub fn new(settings: ExportSettings, exporter: FrameExporter) -> Result<Self> {
+        let sink = FrameSink::open(&settings)?;
+        info!(
+            "Exporting {} frames ({}s to {}s at {} fps, {}x{}) as {} to {}",
+            settings.frame_count(), settings.start, settings.end, settings.fps,
+            settings.width, settings.height, settings.format.name(), settings.output_dir.display()
+        );
+        Ok(Self { settings, exporter, sink, next_frame: 0 })
+    }
+
+    /// Scene time of the next frame, or None when every frame was written
+    pub fn next_frame_time(&self) -> Option<f32> {
+        (self.next_frame < self.settings.frame_count()).then(|| self.settings.frame_time(self.next_frame))
+    }
+
+    /// Write the next frame to the sink
+    ///
+    /// # Errors
+    /// Returns an error if the sink fails
+    pub fn write_frame(&mut self, rgba: &[u8]) -> Result<()> {
+        let extent = self.exporter.extent();
+        self.sink.write(extent.width, extent.height, rgba)?;
+        self.next_frame += 1;
+        Ok(())
+    }
+
+    /// Frames written and total frames
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next_frame, self.settings.frame_count())
+    }
+
+    /// Close the sink
+    ///
+    /// # Returns
+    /// The directory or video file that was written
+    ///
+    /// # Errors
+    /// Returns an error if the sink fails to finish
+    pub fn finish(self) -> Result<PathBuf> {
+        self.sink.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vulkan::tonemap::TonemapOperator;
+
+    #[test]
+    fn test_parse_range_and_frame_times() {
+        assert_eq!(ExportSettings::parse_range("0:2:30"), Some((0.0, 2.0, 30)));
+        assert_eq!(ExportSettings::parse_range("1.5:1:30"), None);
+        assert_eq!(ExportSettings::parse_range("0:2:0"), None);
+        assert_eq!(ExportSettings::parse_range("0:2"), None);
+
+        let settings = ExportSettings { start: 1.0, end: 2.0, fps: 24, ..ExportSettings::default() };
+        assert_eq!(settings.frame_count(), 24);
+        assert_eq!(settings.frame_time(12), 1.5);
+    }
+
+    #[test]
+    fn test_from_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(ExportSettings::from_args(args(&["--gpu", "1"])).unwrap(), None);
+
+        let settings = ExportSettings::from_args(args(&["--export", "0:1:10", "--export-size=640x360", "--export-ffmpeg"]))
+            .unwrap()
+            .expect("export requested");
+        assert_eq!((settings.width, settings.height, settings.fps), (640, 360, 10));
+        assert_eq!(settings.format, ExportFormat::Ffmpeg);
+
+        assert!(ExportSettings::from_args(args(&["--export", "nope"])).is_err());
+    }
+
+    #[test]
+    fn test_encode_pixels_tonemaps_half_floats() {
+        // One pixel of (0, 1, 100, 0.5) as read back from the FP16 target
+        let pixel = [0x0000u16, 0x3c00, 0x5640, 0x3800].map(u16::to_ne_bytes).concat();
+        let none = TonemapSettings { operator: TonemapOperator::None, exposure_ev: 0.0 };
+        assert_eq!(FrameExporter::encode_pixels(&pixel, &none), vec![0, 255, 255, 255]);
+    }
+}
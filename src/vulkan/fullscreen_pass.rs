@@ -0,0 +1,519 @@
+//! Fullscreen fragment shader passes
+//!
+//! `FullscreenPass` draws one fragment shader over a rectangle of a pass,
+//! with the standard uniforms of shader playgrounds pushed as constants:
+//! the resolution, the time, the frame index and the mouse. Shaders are
+//! written like on shadertoy: the file defines
+//!
+//! ```glsl
+//! void mainImage(out vec4 fragColor, in vec2 fragCoord)
+//! ```
+//!
+//! and uses `iResolution`, `iTime`, `iFrame` and `iMouse`; the pass adds the
+//! `#version`, the uniform block and a `main` around it (see `wrap_source`).
+//! `fragCoord` has its origin at the bottom left, and the returned color is
+//! display encoded as on shadertoy and decoded to linear for the HDR scene
+//! target.
+//!
+//! Shaders may also read textures as `iChannel0`, `iChannel1`, ... and write
+//! a feedback buffer instead of the display (see `FullscreenOutput` and the
+//! `toy` module).
+//!
+//! `--toy shaders/foo.frag` runs such a shader instead of the SDF scene
+//! ("shadertoy mode"); the file is hot reloaded while the app runs.
+
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+use ash::vk;
+use ash::Device;
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem, VulkanError};
+use crate::vulkan::deletion_queue::{DeferredResource, DeletionQueue};
+use crate::vulkan::rendering::PassLayout;
+use crate::vulkan::shader_compiler::ShaderCompiler;
+use log::info;
+
+/// Declarations put in front of a fullscreen shader; the block matches `FullscreenUniforms`
+///
+/// The `iChannelN` samplers of the shader follow it (see `wrap_source`).
+const PRELUDE: &str = "#version 450
+layout(push_constant) uniform FullscreenUniforms {
+    vec2 resolution;
+    float time;
+    int frame;
+    vec4 mouse;
+} fullscreenUniforms;
+#define iResolution vec3(fullscreenUniforms.resolution, 1.0)
+#define iTime fullscreenUniforms.time
+#define iFrame fullscreenUniforms.frame
+#define iMouse fullscreenUniforms.mouse
+layout(location = 0) out vec4 fullscreenColor;
+";
+
+/// Entry point put after a shader drawn to the display (`FullscreenOutput::Image`)
+const IMAGE_EPILOGUE: &str = "
+void main() {
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, vec2(gl_FragCoord.x, iResolution.y - gl_FragCoord.y));
+    vec3 encoded = max(color.rgb, vec3(0.0));
+    vec3 linear = mix(encoded / 12.92, pow((encoded + 0.055) / 1.055, vec3(2.4)), step(0.04045, encoded));
+    fullscreenColor = vec4(linear, 1.0);
+}
+";
+
+/// Entry point put after a shader drawn into a feedback buffer (`FullscreenOutput::Buffer`)
+///
+/// `fragCoord` is not flipped: row y of the buffer image then holds
+/// `fragCoord.y == y`, so `texture(iChannel0, fragCoord / iResolution.xy)`
+/// reads back the same pixel in every pass, with the bottom-left origin.
+const BUFFER_EPILOGUE: &str = "
+void main() {
+    vec4 color = vec4(0.0);
+    mainImage(color, gl_FragCoord.xy);
+    fullscreenColor = color;
+}
+";
+
+/// Where a fullscreen shader's color goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenOutput {
+    /// The display: the color is display encoded as on shadertoy and decoded
+    /// to linear for the HDR scene target
+    Image,
+    /// A feedback buffer: the color is stored as is, negative and above one included
+    Buffer,
+}
+
+/// Output and textures of a fullscreen shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullscreenInterface {
+    /// Where the color goes
+    pub output: FullscreenOutput,
+    /// Number of `iChannelN` samplers, bindings 0.. of set 0
+    pub channels: usize,
+    /// Layout of the set holding the samplers, null without channels
+    pub set_layout: vk::DescriptorSetLayout,
+}
+
+impl FullscreenInterface {
+    /// A shader drawn to the display without textures
+    pub fn image() -> Self {
+        Self { output: FullscreenOutput::Image, channels: 0, set_layout: vk::DescriptorSetLayout::null() }
+    }
+}
+
+/// Push constants of a fullscreen shader
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct FullscreenUniforms {
+    /// Size of the drawn rectangle in pixels (`iResolution.xy`)
+    pub resolution: [f32; 2],
+    /// Seconds since the pass started (`iTime`)
+    pub time: f32,
+    /// Frames drawn before this one (`iFrame`)
+    pub frame: u32,
+    /// Mouse in pixels as on shadertoy, see `MouseState::uniform` (`iMouse`)
+    pub mouse: [f32; 4],
+}
+
+impl FullscreenUniforms {
+    /// Uniforms of a rectangle of a given size
+    pub fn new(extent: vk::Extent2D, time: f32, frame: u32, mouse: &MouseState) -> Self {
+        let resolution = [extent.width as f32, extent.height as f32];
+        Self { resolution, time, frame, mouse: mouse.uniform(resolution) }
+    }
+}
+
+unsafe impl bytemuck::Pod for FullscreenUniforms {}
+unsafe impl bytemuck::Zeroable for FullscreenUniforms {}
+
+/// `MouseState::buttons` bit of the left button
+pub const MOUSE_LEFT: u32 = 1;
+/// `MouseState::buttons` bit of the right button
+pub const MOUSE_RIGHT: u32 = 2;
+/// `MouseState::buttons` bit of the middle button
+pub const MOUSE_MIDDLE: u32 = 4;
+
+/// Mouse buttons and cursor, in window coordinates from 0 to 1 (origin top left)
+///
+/// The left button drives the shadertoy style click and drag; the toy shader
+/// and the SDF scene shader both see this state.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MouseState {
+    /// Cursor position
+    pub position: [f32; 2],
+    /// Cursor position the last time it moved with the button down
+    pub drag: [f32; 2],
+    /// Cursor position of the last press
+    pub click: [f32; 2],
+    /// Whether the left button is down
+    pub down: bool,
+    /// Whether the left button was pressed since the last `end_frame`
+    pub clicked: bool,
+    /// Buttons that are down, `MOUSE_*` bits
+    pub buttons: u32,
+}
+
+impl MouseState {
+    /// Move the cursor
+    pub fn move_to(&mut self, position: [f32; 2]) {
+        self.position = position;
+        if self.down {
+            self.drag = position;
+        }
+    }
+
+    /// Press or release a button
+    ///
+    /// # Arguments
+    /// * `button` - One of the `MOUSE_*` bits
+    /// * `pressed` - Whether the button went down
+    pub fn set_button(&mut self, button: u32, pressed: bool) {
+        if button == MOUSE_LEFT {
+            self.set_pressed(pressed);
+        }
+        if pressed {
+            self.buttons |= button;
+        } else {
+            self.buttons &= !button;
+        }
+    }
+
+    /// Press or release the left button
+    fn set_pressed(&mut self, pressed: bool) {
+        if pressed && !self.down {
+            self.click = self.position;
+            self.drag = self.position;
+            self.clicked = true;
+        }
+        self.down = pressed;
+    }
+
+    /// Forget the press once a frame has seen it
+    pub fn end_frame(&mut self) {
+        self.clicked = false;
+    }
+
+    /// Shadertoy's `iMouse` for a rectangle of a given size
+    ///
+    /// # Returns
+    /// xy: the cursor while dragging, zw: the last press, both in pixels
+    /// from the bottom left; z is negative while the button is up and w
+    /// negative except in the frame of the press
+    pub fn uniform(&self, resolution: [f32; 2]) -> [f32; 4] {
+        let pixels = |position: [f32; 2]| [position[0] * resolution[0], (1.0 - position[1]) * resolution[1]];
+        let [x, y] = pixels(self.drag);
+        let [click_x, click_y] = pixels(self.click);
+        [
+            x,
+            y,
+            if self.down { click_x } else { -click_x },
+            if self.clicked { click_y } else { -click_y },
+        ]
+    }
+}
+
+/// Put the uniform declarations and the entry point around a shadertoy style shader
+///
+/// `#line` keeps compiler messages pointing at the lines of the file.
+///
+/// # Arguments
+/// * `source` - The shader file's text
+/// * `output` - Where the color goes
+/// * `channels` - Number of `iChannelN` samplers to declare
+pub fn wrap_source(source: &str, output: FullscreenOutput, channels: usize) -> String {
+    let samplers: String = (0..channels)
+        .map(|channel| format!("layout(set = 0, binding = {0}) uniform sampler2D iChannel{0};\n", channel))
+        .collect();
+    let epilogue = match output {
+        FullscreenOutput::Image => IMAGE_EPILOGUE,
+        FullscreenOutput::Buffer => BUFFER_EPILOGUE,
+    };
+    format!("{}{}#line 1\n{}\n{}", PRELUDE, samplers, source, epilogue)
+}
+
+/// Parse `--toy FILE`, a fragment shader or a `.toml` toy manifest
+///
+/// # Returns
+/// `Ok(None)` if it was not given
+///
+/// # Errors
+/// Returns an error if the file is missing
+pub fn toy_from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<PathBuf>> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let path = match arg.split_once('=') {
+            Some(("--toy", value)) => value.to_string(),
+            None if arg == "--toy" => args.next().ok_or_else(|| AppError::Generic("--toy needs a fragment shader or a manifest".to_string()))?,
+            _ => continue,
+        };
+        return Ok(Some(PathBuf::from(path)));
+    }
+    Ok(None)
+}
+
+/// A fragment shader drawn over a rectangle of a pass
+pub struct FullscreenPass {
+    /// Shader file, read again by `reload`
+    fragment_path: PathBuf,
+    /// Output and textures the shader is compiled for
+    interface: FullscreenInterface,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    device: Device,
+}
+
+impl FullscreenPass {
+    /// Compile a shader and create its pipeline
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `pass` - Pass the shader is drawn in; its depth is neither tested nor written
+    /// * `fragment_path` - Shadertoy style fragment shader
+    /// * `interface` - Output and textures of the shader; the set layout must
+    ///   outlive the pass
+    ///
+    /// # Errors
+    /// Returns an error if the shader does not compile or the pipeline cannot be created
+    pub fn new(device: &Device, pass: &PassLayout, fragment_path: &Path, interface: FullscreenInterface) -> Result<Self> {
+        info!("Creating fullscreen pass for {}", fragment_path.display());
+        let (pipeline_layout, pipeline) = Self::create_pipeline(device, pass, fragment_path, &interface)?;
+        Ok(Self {
+            fragment_path: fragment_path.to_path_buf(),
+            interface,
+            pipeline_layout,
+            pipeline,
+            device: device.clone(),
+        })
+    }
+
+    /// The shader file
+    pub fn fragment_path(&self) -> &Path {
+        &self.fragment_path
+    }
+
+    /// Compile the shader file again and swap in the new pipeline
+    ///
+    /// The previous pipeline is handed to the deletion queue, since frames
+    /// in flight may still use it.
+    ///
+    /// # Errors
+    /// Returns an error if the shader does not compile; the previous
+    /// pipeline stays in use
+    pub fn reload(&mut self, pass: &PassLayout, deletion_queue: &mut DeletionQueue) -> Result<()> {
+        let (pipeline_layout, pipeline) = Self::create_pipeline(&self.device, pass, &self.fragment_path, &self.interface)?;
+        deletion_queue.defer(DeferredResource::Pipeline(std::mem::replace(&mut self.pipeline, pipeline)));
+        deletion_queue.defer(DeferredResource::PipelineLayout(std::mem::replace(&mut self.pipeline_layout, pipeline_layout)));
+        info!("Reloaded fullscreen shader {}", self.fragment_path.display());
+        Ok(())
+    }
+
+    /// Create the pipeline of a shader file
+    fn create_pipeline(
+        device: &Device,
+        pass: &PassLayout,
+        fragment_path: &Path,
+        interface: &FullscreenInterface,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+        let source = std::fs::read_to_string(fragment_path)
+            .context(Subsystem::Io, format!("read fullscreen shader {}", fragment_path.display()))?;
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let vert_code = shader_compiler.compile_file(config::shader::COMPOSITE_VERTEX_SHADER, "main", &[])?;
+        let frag_code = shader_compiler
+            .compile_source(&wrap_source(&source, interface.output, interface.channels), &fragment_path.to_string_lossy(), "main", shaderc::ShaderKind::Fragment, &[])
+            .context(Subsystem::Shaders, "compile fullscreen shader")
+            .detail("shader", fragment_path.display())?;
+
+        // The composite's fullscreen triangle
+        let (vert_module, frag_module) = unsafe {
+            let vert = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&vert_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create fullscreen vertex module: {:?}", e)))?;
+            let frag = match device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&frag_code), None) {
+                Ok(frag) => frag,
+                Err(e) => {
+                    device.destroy_shader_module(vert, None);
+                    return Err(VulkanError::ShaderCompilation(format!("Failed to create fullscreen fragment module: {:?}", e)).into());
+                }
+            };
+            (vert, frag)
+        };
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_module)
+                .name(entry_point),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_module)
+                .name(entry_point),
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(config::rendering::LINE_WIDTH)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        // Toy shaders only write the color; the G-buffer is left undefined in toy mode
+        let color_blend_attachments = pass.color_blend_attachments(vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false));
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachments);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default();
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&dynamic_states);
+
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<FullscreenUniforms>() as u32,
+        }];
+        let set_layouts = [interface.set_layout];
+        let set_layouts = if interface.channels > 0 { &set_layouts[..] } else { &[] };
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(set_layouts)
+                .push_constant_ranges(&push_constant_ranges), None)
+        };
+        let pipeline_layout = match pipeline_layout {
+            Ok(layout) => layout,
+            Err(e) => unsafe {
+                device.destroy_shader_module(vert_module, None);
+                device.destroy_shader_module(frag_module, None);
+                return Err(VulkanError::PipelineCreation(format!("Failed to create fullscreen pipeline layout: {:?}", e)).into());
+            },
+        };
+
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout), &mut rendering);
+
+        let pipeline = unsafe {
+            let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None);
+            device.destroy_shader_module(vert_module, None);
+            device.destroy_shader_module(frag_module, None);
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, e)) => {
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                    return Err(VulkanError::PipelineCreation(format!("Failed to create fullscreen pipeline: {:?}", e)).into());
+                }
+            }
+        };
+
+        Ok((pipeline_layout, pipeline))
+    }
+
+    /// Record the draw into the current pass
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside a pass compatible with the one given to `new`
+    /// * `rect` - Rectangle to cover; its size should match `uniforms.resolution`
+    /// * `uniforms` - Values of the standard uniforms
+    /// * `channels` - Set holding the `iChannelN` textures, if the shader has any
+    pub fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        rect: vk::Rect2D,
+        uniforms: &FullscreenUniforms,
+        channels: Option<vk::DescriptorSet>,
+    ) {
+        unsafe {
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            if let Some(descriptor_set) = channels {
+                self.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    0,
+                    &[descriptor_set],
+                    &[],
+                );
+            }
+            self.device.cmd_set_viewport(command_buffer, 0, &[vk::Viewport {
+                x: rect.offset.x as f32,
+                y: rect.offset.y as f32,
+                width: rect.extent.width as f32,
+                height: rect.extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }]);
+            self.device.cmd_set_scissor(command_buffer, 0, &[rect]);
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bytemuck::bytes_of(uniforms),
+            );
+            self.device.cmd_draw(command_buffer, 3, 1, 0, 0); // Fullscreen triangle
+        }
+    }
+}
+
+impl Drop for FullscreenPass {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_pipeline(self.pipeline, None);
+            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mouse_uniform_follows_shadertoy() {
+        let mut mouse = MouseState::default();
+        mouse.move_to([0.25, 0.25]);
+        assert_eq!(mouse.uniform([800.0, 400.0]), [0.0, 400.0, -0.0, -400.0]);
+
+        mouse.set_pressed(true);
+        mouse.move_to([0.5, 0.75]);
+        assert_eq!(mouse.uniform([800.0, 400.0]), [400.0, 100.0, 200.0, 300.0]);
+        mouse.end_frame();
+        mouse.set_pressed(false);
+        mouse.move_to([1.0, 1.0]);
+        assert_eq!(mouse.uniform([800.0, 400.0]), [400.0, 100.0, -200.0, -300.0]);
+    }
+
+    #[test]
+    fn test_toy_args_and_wrapped_source() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(toy_from_args(args(&["--gpu", "1"])).unwrap(), None);
+        assert_eq!(toy_from_args(args(&["--toy", "shaders/foo.frag"])).unwrap(), Some(PathBuf::from("shaders/foo.frag")));
+        assert_eq!(toy_from_args(args(&["--toy=a.frag"])).unwrap(), Some(PathBuf::from("a.frag")));
+        assert!(toy_from_args(args(&["--toy"])).is_err());
+
+        let source = wrap_source("void mainImage(out vec4 c, in vec2 p) { c = vec4(1.0); }", FullscreenOutput::Image, 0);
+        assert!(source.starts_with("#version 450\n"));
+        assert!(source.contains("#line 1\nvoid mainImage"));
+        assert!(!source.contains("iChannel0;"));
+
+        let source = wrap_source("void mainImage(out vec4 c, in vec2 p) { c = texture(iChannel1, p); }", FullscreenOutput::Buffer, 2);
+        assert!(source.contains("layout(set = 0, binding = 1) uniform sampler2D iChannel1;\n#line 1\n"));
+        assert!(source.contains("mainImage(color, gl_FragCoord.xy)"));
+        assert_eq!(std::mem::size_of::<FullscreenUniforms>(), 32);
+    }
+}
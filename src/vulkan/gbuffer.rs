@@ -0,0 +1,177 @@
+//! Scene G-buffer
+//!
+//! Besides the HDR color, the scene pass writes the world space normal, the
+//! linear view depth and a selection mask of each pixel's surface into three
+//! more color attachments, for post-process passes (ambient occlusion, the
+//! selection outline of `outline`) and the GPU Textures panel. Normals are
+//! stored as `normal * 0.5 + 0.5` with alpha 1 on surfaces; the linear depth
+//! is the distance along the camera's forward axis; the mask is 1 where the
+//! selected entity is visible. All are cleared to zero, so zero means no
+//! surface.
+//!
+//! Every target the scene pass draws into carries a G-buffer, keeping the
+//! framebuffers compatible with the pass. The SDF and mesh shaders write it;
+//! blended draws such as particles write zero, leaving it unchanged.
+
+use ash::vk;
+use ash::Device;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::rendering::ColorAttachment;
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::scene_buffer::find_memory_type;
+use log::debug;
+
+/// G-buffer attachment formats, in attachment order after the main color
+pub const FORMATS: [vk::Format; 3] = [
+    config::rendering::NORMAL_FORMAT,
+    config::rendering::LINEAR_DEPTH_FORMAT,
+    config::rendering::SELECTION_FORMAT,
+];
+
+/// Color formats of a pass the SDF pipeline draws in: `color`, then the G-buffer
+pub fn pass_formats(color: vk::Format) -> [vk::Format; 4] {
+    [color, FORMATS[0], FORMATS[1], FORMATS[2]]
+}
+
+/// One G-buffer image with its memory and view
+#[derive(Debug, Clone, Copy, Default)]
+struct GBufferImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+}
+
+/// Normal, linear depth and selection images of a scene target
+pub struct GBuffer {
+    images: [GBufferImage; 3],
+}
+
+impl GBuffer {
+    /// Create the G-buffer images for a framebuffer
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `memory_properties` - Memory properties of the physical device
+    /// * `extent` - Size of the framebuffer
+    ///
+    /// # Errors
+    /// Returns an error if image creation or allocation fails
+    pub fn new(device: &Device, memory_properties: &vk::PhysicalDeviceMemoryProperties, extent: vk::Extent2D) -> Result<Self> {
+        let mut gbuffer = Self { images: [GBufferImage::default(); 3] };
+        for (index, format) in FORMATS.into_iter().enumerate() {
+            match Self::create_image(device, memory_properties, format, extent) {
+                Ok(image) => gbuffer.images[index] = image,
+                Err(e) => {
+                    gbuffer.destroy(device);
+                    return Err(e);
+                }
+            }
+        }
+
+        debug!("G-buffer created: {}x{}", extent.width, extent.height);
+        Ok(gbuffer)
+    }
+
+    /// Create one sampled color attachment image
+    fn create_image(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> Result<GBufferImage> {
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: extent.width.max(1), height: extent.height.max(1), depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+
+        let mut created = GBufferImage::default();
+        let result = (|| -> Result<()> {
+            created.image = unsafe { device.create_image(&image_info, None) }
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create {:?} G-buffer image: {:?}", format, e)))?;
+
+            let requirements = unsafe { device.get_image_memory_requirements(created.image) };
+            let memory_type_index = find_memory_type(memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .ok_or_else(|| VulkanError::MemoryAllocation("No device local memory type for G-buffer image".to_string()))?;
+            created.memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Swapchain, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to allocate G-buffer image memory: {:?}", e)))?;
+            unsafe { device.bind_image_memory(created.image, created.memory, 0) }
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to bind G-buffer image memory: {:?}", e)))?;
+
+            let view_info = vk::ImageViewCreateInfo::default()
+                .image(created.image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                });
+            created.view = unsafe { device.create_image_view(&view_info, None) }
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create G-buffer image view: {:?}", e)))?;
+            Ok(())
+        })();
+
+        match result {
+            Ok(()) => Ok(created),
+            Err(e) => {
+                Self::destroy_image(device, &created);
+                Err(e)
+            }
+        }
+    }
+
+    /// Attachments for `PassTarget::extra_colors`
+    pub fn extra_colors(&self) -> [ColorAttachment; 3] {
+        self.images.map(|image| ColorAttachment { image: image.image, view: image.view })
+    }
+
+    /// Attachment views, for `PassLayout::create_framebuffer_with_extras`
+    pub fn views(&self) -> [vk::ImageView; 3] {
+        self.images.map(|image| image.view)
+    }
+
+    /// View of the encoded world space normals
+    pub fn normal_view(&self) -> vk::ImageView {
+        self.images[0].view
+    }
+
+    /// View of the linear view depth
+    pub fn linear_depth_view(&self) -> vk::ImageView {
+        self.images[1].view
+    }
+
+    /// View of the selection mask
+    pub fn selection_view(&self) -> vk::ImageView {
+        self.images[2].view
+    }
+
+    /// Destroy the images, views and memory
+    ///
+    /// The caller must ensure no framebuffer in use references the views.
+    pub fn destroy(&self, device: &Device) {
+        for image in &self.images {
+            Self::destroy_image(device, image);
+        }
+    }
+
+    /// Destroy one image; null handles are skipped by Vulkan
+    fn destroy_image(device: &Device, image: &GBufferImage) {
+        unsafe {
+            device.destroy_image_view(image.view, None);
+            device.destroy_image(image.image, None);
+            memory_usage::free(device, image.memory);
+        }
+    }
+}
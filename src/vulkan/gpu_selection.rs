@@ -0,0 +1,194 @@
+//! Physical device scoring and selection
+//!
+//! Every physical device is checked for the queue families and extensions
+//! the renderer needs, then scored so discrete GPUs win over integrated and
+//! software ones. A `--gpu <index|name>` command line argument (or
+//! `config::vulkan::GPU_OVERRIDE`) forces a specific device.
+
+use ash::vk;
+use crate::config;
+
+/// User choice of physical device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuSelector {
+    /// Device index in enumeration order
+    Index(usize),
+    /// Case-insensitive substring of the device name
+    Name(String),
+}
+
+impl GpuSelector {
+    /// Parse an index or a name
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        Some(match value.parse::<usize>() {
+            Ok(index) => GpuSelector::Index(index),
+            Err(_) => GpuSelector::Name(value.to_string()),
+        })
+    }
+
+    /// Read the override from command line arguments (`--gpu X` or `--gpu=X`)
+    ///
+    /// Falls back to `config::vulkan::GPU_OVERRIDE` when the argument is absent.
+    ///
+    /// # Arguments
+    /// * `args` - Arguments without the program name
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Option<Self> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--gpu" {
+                return args.next().as_deref().and_then(Self::parse);
+            }
+            if let Some(value) = arg.strip_prefix("--gpu=") {
+                return Self::parse(value);
+            }
+        }
+        config::vulkan::GPU_OVERRIDE.and_then(Self::parse)
+    }
+
+    /// Check whether a device matches this selector
+    pub fn matches(&self, gpu: &GpuInfo) -> bool {
+        match self {
+            GpuSelector::Index(index) => gpu.index == *index,
+            GpuSelector::Name(name) => gpu.name.to_lowercase().contains(&name.to_lowercase()),
+        }
+    }
+}
+
+/// Summary of a detected physical device
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuInfo {
+    /// Index in enumeration order
+    pub index: usize,
+    /// Device name reported by the driver
+    pub name: String,
+    /// Discrete, integrated, virtual, CPU or other
+    pub device_type: vk::PhysicalDeviceType,
+    /// Supported Vulkan version as "major.minor.patch"
+    pub api_version: String,
+    /// Selection score, `None` if the device cannot run the renderer
+    pub score: Option<u32>,
+    /// Why the device is unsuitable, if it is
+    pub unsuitable_reason: Option<String>,
+    /// Whether this device was selected
+    pub selected: bool,
+}
+
+impl GpuInfo {
+    /// Short name of the device type
+    pub fn type_name(&self) -> &'static str {
+        match self.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => "Discrete",
+            vk::PhysicalDeviceType::INTEGRATED_GPU => "Integrated",
+            vk::PhysicalDeviceType::VIRTUAL_GPU => "Virtual",
+            vk::PhysicalDeviceType::CPU => "CPU",
+            _ => "Other",
+        }
+    }
+}
+
+/// Score a suitable device from its properties
+///
+/// The device type dominates the score; larger image limits break ties
+/// between devices of the same type.
+pub fn score_device(properties: &vk::PhysicalDeviceProperties) -> u32 {
+    let type_score = match properties.device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 10_000,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 5_000,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 2_000,
+        vk::PhysicalDeviceType::CPU => 100,
+        _ => 0,
+    };
+    let timestamp_score = if properties.limits.timestamp_compute_and_graphics == vk::TRUE { 500 } else { 0 };
+    type_score + timestamp_score + properties.limits.max_image_dimension2_d / 1024
+}
+
+/// Choose a device from the scored list
+///
+/// A matching suitable override wins; otherwise the highest score is used
+/// (the first device on ties, keeping enumeration order stable).
+///
+/// # Returns
+/// Index into `gpus`, or `None` if no device is suitable
+pub fn choose_device(gpus: &[GpuInfo], selector: Option<&GpuSelector>) -> Option<usize> {
+    if let Some(selector) = selector {
+        if let Some(position) = gpus.iter().position(|gpu| gpu.score.is_some() && selector.matches(gpu)) {
+            return Some(position);
+        }
+        log::warn!("GPU override {:?} does not match a suitable device, using the best scored one", selector);
+    }
+
+    gpus.iter()
+        .enumerate()
+        .filter_map(|(position, gpu)| gpu.score.map(|score| (position, score)))
+        .fold(None, |best: Option<(usize, u32)>, (position, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((position, score)),
+        })
+        .map(|(position, _)| position)
+}
+
+/// Driver version in the vendor's own numbering
+///
+/// NVIDIA packs 10.8.8.6 bits and Intel on Windows 18.14 bits; other
+/// drivers use the Vulkan version encoding.
+pub fn format_driver_version(vendor_id: u32, version: u32) -> String {
+    match vendor_id {
+        0x10DE => format!("{}.{}.{}.{}", version >> 22, (version >> 14) & 0xFF, (version >> 6) & 0xFF, version & 0x3F),
+        0x8086 if cfg!(windows) => format!("{}.{}", version >> 14, version & 0x3FFF),
+        _ => format!(
+            "{}.{}.{}",
+            vk::api_version_major(version),
+            vk::api_version_minor(version),
+            vk::api_version_patch(version)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpu(index: usize, name: &str, score: Option<u32>) -> GpuInfo {
+        GpuInfo {
+            index,
+            name: name.to_string(),
+            device_type: vk::PhysicalDeviceType::OTHER,
+            api_version: "1.3.0".to_string(),
+            score,
+            unsuitable_reason: None,
+            selected: false,
+        }
+    }
+
+    #[test]
+    fn test_selector_parsing() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(GpuSelector::from_args(args(&["--gpu", "1"])), Some(GpuSelector::Index(1)));
+        assert_eq!(GpuSelector::from_args(args(&["--gpu=NVIDIA"])), Some(GpuSelector::Name("NVIDIA".to_string())));
+    }
+
+    #[test]
+    fn test_choose_device_prefers_score_and_override() {
+        let gpus = vec![
+            gpu(0, "Intel UHD", Some(5_000)),
+            gpu(1, "NVIDIA RTX", Some(10_000)),
+            gpu(2, "llvmpipe", None),
+        ];
+        assert_eq!(choose_device(&gpus, None), Some(1));
+        assert_eq!(choose_device(&gpus, Some(&GpuSelector::Name("intel".to_string()))), Some(0));
+        // Unsuitable devices cannot be forced
+        assert_eq!(choose_device(&gpus, Some(&GpuSelector::Index(2))), Some(1));
+        assert_eq!(choose_device(&[gpu(0, "llvmpipe", None)], None), None);
+    }
+
+    #[test]
+    fn test_driver_version_formats() {
+        let nvidia = (535 << 22) | (54 << 14) | (3 << 6);
+        assert_eq!(format_driver_version(0x10DE, nvidia), "535.54.3.0");
+        assert_eq!(format_driver_version(0x1002, vk::make_api_version(0, 2, 0, 279)), "2.0.279");
+    }
+}
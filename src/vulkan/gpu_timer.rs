@@ -0,0 +1,119 @@
+//! GPU timestamp queries for measuring render pass cost
+//!
+//! Two timestamps are written per frame in flight (start and end of the
+//! measured section). Results are read back after the frame's fence has
+//! been waited on, so reading never stalls the CPU.
+
+use ash::vk;
+use ash::Device;
+use crate::config;
+use crate::vulkan::capabilities::DeviceCapabilities;
+use crate::error::{Result, VulkanError};
+use log::{debug, warn};
+
+/// Timestamp query pool with one start/end pair per frame in flight
+pub struct GpuTimer {
+    query_pool: vk::QueryPool,
+    /// Nanoseconds per timestamp tick
+    timestamp_period: f32,
+    /// Whether a query pair was written for a frame and not read back yet
+    written: Vec<bool>,
+    device: Device,
+}
+
+impl GpuTimer {
+    /// Create a GPU timer
+    ///
+    /// # Returns
+    /// `Ok(None)` if the device does not support graphics timestamps
+    ///
+    /// # Errors
+    /// Returns an error if query pool creation fails
+    pub fn new(capabilities: &DeviceCapabilities, device: &Device) -> Result<Option<Self>> {
+        if !capabilities.supports_timestamps() {
+            warn!("Device does not support graphics timestamps, GPU timing disabled");
+            return Ok(None);
+        }
+
+        let frame_count = config::vulkan::MAX_FRAMES_IN_FLIGHT;
+        let pool_info = vk::QueryPoolCreateInfo::default()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count((frame_count * 2) as u32);
+
+        let query_pool = unsafe {
+            device.create_query_pool(&pool_info, None)
+                .map_err(|e| VulkanError::Rendering(format!("Failed to create timestamp query pool: {:?}", e)))?
+        };
+
+        debug!("GPU timer created (timestamp period {} ns)", capabilities.timestamp_period());
+
+        Ok(Some(Self {
+            query_pool,
+            timestamp_period: capabilities.timestamp_period(),
+            written: vec![false; frame_count],
+            device: device.clone(),
+        }))
+    }
+
+    /// Reset the frame's queries and write the start timestamp
+    ///
+    /// Must be recorded outside of a render pass.
+    pub fn begin(&mut self, command_buffer: vk::CommandBuffer, frame: usize) {
+        let first = (frame * 2) as u32;
+        unsafe {
+            self.device.cmd_reset_query_pool(command_buffer, self.query_pool, first, 2);
+            self.device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE, self.query_pool, first);
+        }
+    }
+
+    /// Write the end timestamp for the frame
+    pub fn end(&mut self, command_buffer: vk::CommandBuffer, frame: usize) {
+        let first = (frame * 2) as u32;
+        unsafe {
+            self.device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::BOTTOM_OF_PIPE, self.query_pool, first + 1);
+        }
+        self.written[frame] = true;
+    }
+
+    /// Read the measured time of a completed frame
+    ///
+    /// Call after waiting on the frame's in-flight fence.
+    ///
+    /// # Returns
+    /// Elapsed GPU time in milliseconds, or None if nothing was measured
+    pub fn read_ms(&mut self, frame: usize) -> Option<f32> {
+        if !self.written[frame] {
+            return None;
+        }
+        self.written[frame] = false;
+
+        let mut timestamps = [0u64; 2];
+        let result = unsafe {
+            self.device.get_query_pool_results(
+                self.query_pool,
+                (frame * 2) as u32,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        match result {
+            Ok(()) => {
+                let ticks = timestamps[1].saturating_sub(timestamps[0]);
+                Some(ticks as f32 * self.timestamp_period / 1_000_000.0)
+            }
+            Err(e) => {
+                debug!("Timestamp results not available for frame {}: {:?}", frame, e);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_query_pool(self.query_pool, None);
+        }
+    }
+}
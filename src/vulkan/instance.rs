@@ -18,6 +18,9 @@ pub struct VulkanInstance {
     /// The Vulkan instance
     pub instance: Instance,
     
+    /// API version the instance was created with
+    pub api_version: u32,
+    
     /// Debug utilities for validation and logging
     #[cfg(debug_assertions)]
     debug_utils: Option<crate::debug::VulkanDebugUtils>,
@@ -32,6 +35,18 @@ impl VulkanInstance {
     /// # Errors
     /// Returns an error if instance creation fails
     pub fn new() -> Result<Self> {
+        Self::with_extensions(&[])
+    }
+    
+    /// Create a new Vulkan instance with extensions another API needs
+    ///
+    /// # Arguments
+    /// * `extra_extensions` - Instance extensions enabled on top of the required ones,
+    ///   e.g. those an OpenXR runtime asks for
+    ///
+    /// # Errors
+    /// Returns an error if instance creation fails
+    pub fn with_extensions(extra_extensions: &[CString]) -> Result<Self> {
         info!("Creating Vulkan instance");
         
         let entry = unsafe { Entry::load() }
@@ -39,8 +54,19 @@ impl VulkanInstance {
         
         debug!("Vulkan entry loaded successfully");
         
-        let instance = Self::create_instance(&entry)?;
-        debug!("Vulkan instance created successfully");
+        // A Vulkan 1.0 loader has no version query and rejects newer versions
+        let api_version = unsafe { entry.try_enumerate_instance_version() }
+            .ok()
+            .flatten()
+            .unwrap_or(config::vulkan::API_VERSION)
+            .min(config::vulkan::TARGET_API_VERSION);
+        
+        let instance = Self::create_instance(&entry, api_version, extra_extensions)?;
+        debug!(
+            "Vulkan instance created successfully (API {}.{})",
+            vk::api_version_major(api_version),
+            vk::api_version_minor(api_version)
+        );
         
         #[cfg(debug_assertions)]
         let mut debug_utils = crate::debug::VulkanDebugUtils::new();
@@ -54,6 +80,7 @@ impl VulkanInstance {
         Ok(Self {
             entry,
             instance,
+            api_version,
             #[cfg(debug_assertions)]
             debug_utils: Some(debug_utils),
         })
@@ -63,13 +90,15 @@ impl VulkanInstance {
     ///
     /// # Arguments
     /// * `entry` - The Vulkan entry point
+    /// * `api_version` - Highest API version the application uses
+    /// * `extra_extensions` - Extensions enabled on top of the required ones
     ///
     /// # Returns
     /// The created Vulkan instance
     ///
     /// # Errors
     /// Returns an error if instance creation fails
-    fn create_instance(entry: &Entry) -> Result<Instance> {
+    fn create_instance(entry: &Entry, api_version: u32, extra_extensions: &[CString]) -> Result<Instance> {
         let app_name = CString::new(config::vulkan::APP_NAME)
             .map_err(|e| VulkanError::InstanceCreation(format!("Failed to create app name string: {}", e)))?;
         let engine_name = CString::new(config::vulkan::ENGINE_NAME)
@@ -80,10 +109,16 @@ impl VulkanInstance {
             .application_version(config::vulkan::APP_VERSION)
             .engine_name(&engine_name)
             .engine_version(config::vulkan::ENGINE_VERSION)
-            .api_version(config::vulkan::API_VERSION);
+            .api_version(api_version);
 
         // Get required extensions
-        let (extensions, _extension_strings) = Self::get_required_extensions(entry)?;
+        let (mut extensions, _extension_strings) = Self::get_required_extensions(entry)?;
+        for extension in extra_extensions {
+            if !extensions.iter().any(|&name| unsafe { std::ffi::CStr::from_ptr(name) } == extension.as_c_str()) {
+                extensions.push(extension.as_ptr());
+            }
+        }
+        debug!("Extra extensions: {:?}", extra_extensions);
         
         // Check for validation layer support in debug builds
         #[cfg(debug_assertions)]
@@ -165,6 +200,18 @@ impl VulkanInstance {
             }
         }
         
+        // HDR surface color spaces are only reported with the swapchain colorspace extension
+        if config::rendering::PREFER_HDR_OUTPUT {
+            let available = unsafe { _entry.enumerate_instance_extension_properties(None) }
+                .map_err(|e| VulkanError::InstanceCreation(format!("Failed to enumerate instance extensions: {:?}", e)))?;
+            if available.iter().any(|ext| ext.extension_name_as_c_str() == Ok(vk::EXT_SWAPCHAIN_COLORSPACE_NAME)) {
+                extensions.push(vk::EXT_SWAPCHAIN_COLORSPACE_NAME.as_ptr());
+                debug!("Added swapchain colorspace extension");
+            } else {
+                debug!("Swapchain colorspace extension not available, HDR output is disabled");
+            }
+        }
+
         // Convert to strings for debugging
         let extension_names: Vec<String> = extensions.iter()
             .map(|&ptr| unsafe { std::ffi::CStr::from_ptr(ptr) }.to_string_lossy().to_string())
@@ -0,0 +1,244 @@
+//! GPU memory usage
+//!
+//! Device memory is allocated in many places (buffers, textures, render
+//! targets), all through `allocate` and `free`, which keep a tally of the
+//! live allocations by category and by kind of memory. With
+//! `VK_EXT_memory_budget` the driver also reports how much of each heap the
+//! process uses and how much it may use. The Stats panel shows both, and a
+//! failed allocation logs them.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use ash::prelude::VkResult;
+use ash::{vk, Device, Instance};
+use log::{error, info};
+
+/// What device memory is allocated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoryCategory {
+    /// Vertex, index, uniform, storage, staging and readback buffers
+    Buffers,
+    /// Images that keep their size: textures, fonts, export targets
+    Textures,
+    /// Images sized with the swapchain: depth, offscreen and G-buffer targets
+    Swapchain,
+}
+
+impl MemoryCategory {
+    /// Every category, in the order the Stats panel lists them
+    pub const ALL: [MemoryCategory; 3] = [MemoryCategory::Buffers, MemoryCategory::Textures, MemoryCategory::Swapchain];
+
+    /// Name of the category, translated with `locale::named("memory", ...)`
+    pub fn name(self) -> &'static str {
+        match self {
+            MemoryCategory::Buffers => "Buffers",
+            MemoryCategory::Textures => "Textures",
+            MemoryCategory::Swapchain => "Swapchain",
+        }
+    }
+}
+
+/// Live allocations of a category
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CategoryUsage {
+    /// Bytes in memory only the GPU can access
+    pub device_local: vk::DeviceSize,
+    /// Bytes in memory the CPU can map
+    pub host_visible: vk::DeviceSize,
+    /// Number of allocations
+    pub allocations: u32,
+}
+
+/// A live allocation
+#[derive(Debug, Clone, Copy)]
+struct Allocation {
+    category: MemoryCategory,
+    size: vk::DeviceSize,
+    host_visible: bool,
+}
+
+/// Live allocations by handle
+#[derive(Debug, Default)]
+struct Tally {
+    allocations: BTreeMap<vk::DeviceMemory, Allocation>,
+}
+
+impl Tally {
+    const fn new() -> Self {
+        Self { allocations: BTreeMap::new() }
+    }
+
+    fn add(&mut self, memory: vk::DeviceMemory, allocation: Allocation) {
+        self.allocations.insert(memory, allocation);
+    }
+
+    fn remove(&mut self, memory: vk::DeviceMemory) {
+        self.allocations.remove(&memory);
+    }
+
+    fn usage(&self) -> Vec<(MemoryCategory, CategoryUsage)> {
+        MemoryCategory::ALL.map(|category| {
+            let mut usage = CategoryUsage::default();
+            for allocation in self.allocations.values().filter(|allocation| allocation.category == category) {
+                match allocation.host_visible {
+                    true => usage.host_visible += allocation.size,
+                    false => usage.device_local += allocation.size,
+                }
+                usage.allocations += 1;
+            }
+            (category, usage)
+        }).to_vec()
+    }
+}
+
+static TALLY: Mutex<Tally> = Mutex::new(Tally::new());
+
+/// Allocate device memory and count it
+///
+/// # Arguments
+/// * `device` - The logical device
+/// * `info` - Size and memory type of the allocation
+/// * `category` - What the memory is for
+/// * `properties` - Property flags the memory type was chosen for
+///
+/// # Errors
+/// Returns the Vulkan error of a failed allocation, after logging the memory in use
+pub fn allocate(
+    device: &Device,
+    info: &vk::MemoryAllocateInfo,
+    category: MemoryCategory,
+    properties: vk::MemoryPropertyFlags,
+) -> VkResult<vk::DeviceMemory> {
+    let host_visible = properties.contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+    match unsafe { device.allocate_memory(info, None) } {
+        Ok(memory) => {
+            let allocation = Allocation { category, size: info.allocation_size, host_visible };
+            TALLY.lock().unwrap().add(memory, allocation);
+            Ok(memory)
+        }
+        Err(e) => {
+            let kind = if host_visible { "host visible" } else { "device local" };
+            error!("Failed to allocate {} of {} memory for {}: {:?}", format_bytes(info.allocation_size), kind, category.name(), e);
+            log_usage();
+            Err(e)
+        }
+    }
+}
+
+/// Free device memory allocated with `allocate`; null handles are ignored
+///
+/// # Arguments
+/// * `device` - The logical device
+/// * `memory` - The memory to free, which no resource may use anymore
+pub fn free(device: &Device, memory: vk::DeviceMemory) {
+    if memory == vk::DeviceMemory::null() {
+        return;
+    }
+    TALLY.lock().unwrap().remove(memory);
+    unsafe { device.free_memory(memory, None) };
+}
+
+/// Live allocations of the app by category
+pub fn usage() -> Vec<(MemoryCategory, CategoryUsage)> {
+    TALLY.lock().unwrap().usage()
+}
+
+/// Log the live allocations by category
+pub fn log_usage() {
+    for (category, usage) in usage() {
+        info!(
+            "{}: {} device local, {} host visible in {} allocations",
+            category.name(),
+            format_bytes(usage.device_local),
+            format_bytes(usage.host_visible),
+            usage.allocations
+        );
+    }
+}
+
+/// Size, usage and budget of a memory heap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapBudget {
+    /// Heap index
+    pub index: u32,
+    /// Size of the heap
+    pub size: vk::DeviceSize,
+    /// Whether the heap is device local (video memory)
+    pub device_local: bool,
+    /// Bytes of the heap the process uses, None without `VK_EXT_memory_budget`
+    pub usage: Option<vk::DeviceSize>,
+    /// Bytes of the heap the process may use, None without `VK_EXT_memory_budget`
+    pub budget: Option<vk::DeviceSize>,
+}
+
+/// Read the memory heaps of a physical device
+///
+/// # Arguments
+/// * `instance` - The Vulkan instance
+/// * `physical_device` - The physical device
+/// * `memory_budget` - Whether `VK_EXT_memory_budget` is enabled on the device
+pub fn heap_budgets(instance: &Instance, physical_device: vk::PhysicalDevice, memory_budget: bool) -> Vec<HeapBudget> {
+    let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    // The budget is chained to the Vulkan 1.1 query, which a 1.0 instance lacks
+    let memory_properties = match memory_budget {
+        true => {
+            let mut properties = vk::PhysicalDeviceMemoryProperties2::default().push_next(&mut budget_properties);
+            unsafe { instance.get_physical_device_memory_properties2(physical_device, &mut properties) };
+            properties.memory_properties
+        }
+        false => unsafe { instance.get_physical_device_memory_properties(physical_device) },
+    };
+    (0..memory_properties.memory_heap_count as usize)
+        .map(|index| {
+            let heap = memory_properties.memory_heaps[index];
+            HeapBudget {
+                index: index as u32,
+                size: heap.size,
+                device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                usage: memory_budget.then(|| budget_properties.heap_usage[index]),
+                budget: memory_budget.then(|| budget_properties.heap_budget[index]),
+            }
+        })
+        .collect()
+}
+
+/// Byte count for people, in binary units
+pub fn format_bytes(bytes: vk::DeviceSize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    fn test_tally_counts_live_allocations_by_category() {
+        let mut tally = Tally::new();
+        let buffer = Allocation { category: MemoryCategory::Buffers, size: 256, host_visible: true };
+        let depth = Allocation { category: MemoryCategory::Swapchain, size: 4096, host_visible: false };
+        tally.add(vk::DeviceMemory::from_raw(1), buffer);
+        tally.add(vk::DeviceMemory::from_raw(2), buffer);
+        tally.add(vk::DeviceMemory::from_raw(3), depth);
+        tally.remove(vk::DeviceMemory::from_raw(2));
+
+        let usage = tally.usage();
+        assert_eq!(usage[0], (MemoryCategory::Buffers, CategoryUsage { device_local: 0, host_visible: 256, allocations: 1 }));
+        assert_eq!(usage[1], (MemoryCategory::Textures, CategoryUsage::default()));
+        assert_eq!(usage[2], (MemoryCategory::Swapchain, CategoryUsage { device_local: 4096, host_visible: 0, allocations: 1 }));
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024 / 2), "1.5 MiB");
+        assert_eq!(format_bytes(8 * 1024 * 1024 * 1024), "8.0 GiB");
+    }
+}
@@ -0,0 +1,465 @@
+//! Triangle mesh data
+//!
+//! Meshes are loaded from Wavefront OBJ or glTF files into indexed vertex
+//! lists with positions and normals. Faces without normals get smooth
+//! normals averaged from the triangles around each vertex. The ECS world
+//! collects a `MeshDrawList` every frame and attaches the mesh data loaded
+//! by the asset manager; `MeshPass` uploads each mesh the first time it is
+//! drawn and draws all instances of a mesh in one instanced draw call.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use cgmath::{InnerSpace, Matrix4, Vector3};
+use crate::error::{Result, ResultExt, Subsystem, VulkanError};
+use log::{debug, warn};
+
+/// Vertex of a mesh
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+unsafe impl bytemuck::Pod for MeshVertex {}
+unsafe impl bytemuck::Zeroable for MeshVertex {}
+
+/// Indexed triangle list
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    /// Three indices per triangle
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// Load a mesh file, picking the format from the extension
+    ///
+    /// # Arguments
+    /// * `path` - `.obj`, `.gltf` or `.glb` file
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, its format is unknown or
+    /// it holds no triangles
+    pub fn load(path: &Path) -> Result<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let mesh = match extension.as_deref() {
+            Some("obj") => {
+                let text = fs::read_to_string(path).context(Subsystem::Io, format!("read mesh {}", path.display()))?;
+                Self::parse_obj(&text)
+            }
+            Some("gltf") | Some("glb") => Self::load_gltf(path),
+            _ => Err(VulkanError::MeshLoad(format!("Unsupported mesh format: {}", path.display())).into()),
+        };
+        let mesh = mesh.context(Subsystem::Renderer, format!("load mesh {}", path.display()))?;
+        debug!("Loaded mesh {:?}: {} vertices, {} triangles", path, mesh.vertices.len(), mesh.triangle_count());
+        Ok(mesh)
+    }
+
+    /// Parse a Wavefront OBJ mesh
+    ///
+    /// Reads `v`, `vn` and `f` statements; polygons are triangulated as fans
+    /// and negative (relative) indices are supported. Texture coordinates,
+    /// groups and materials are ignored.
+    ///
+    /// # Errors
+    /// Returns an error on malformed statements, out of range indices or a
+    /// mesh without faces
+    pub fn parse_obj(text: &str) -> Result<Self> {
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+        let mut corners: HashMap<(usize, Option<usize>), u32> = HashMap::new();
+        let mut mesh = MeshData::default();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("v") => positions.push(parse_vec3(words, line_number)?),
+                Some("vn") => normals.push(parse_vec3(words, line_number)?),
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for corner in words {
+                        let mut parts = corner.split('/');
+                        let position = resolve_index(parts.next().unwrap_or(""), positions.len(), line_number)?;
+                        let normal = match parts.nth(1) {
+                            Some(index) if !index.is_empty() => Some(resolve_index(index, normals.len(), line_number)?),
+                            _ => None,
+                        };
+                        let index = *corners.entry((position, normal)).or_insert_with(|| {
+                            mesh.vertices.push(MeshVertex {
+                                position: positions[position],
+                                normal: normal.map_or([0.0; 3], |n| normals[n]),
+                            });
+                            mesh.vertices.len() as u32 - 1
+                        });
+                        face.push(index);
+                    }
+                    if face.len() < 3 {
+                        return Err(VulkanError::MeshLoad(format!("Line {}: face with fewer than 3 corners", line_number)).into());
+                    }
+                    for i in 1..face.len() - 1 {
+                        mesh.indices.extend([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        mesh.fill_missing_normals();
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    /// Load the triangle primitives of every mesh in a glTF file
+    ///
+    /// Primitives are merged into one mesh in their own space; node
+    /// transforms are ignored.
+    fn load_gltf(path: &Path) -> Result<Self> {
+        let (document, buffers, _images) = gltf::import(path)
+            .map_err(|e| VulkanError::MeshLoad(e.to_string()))?;
+        let mut mesh = MeshData::default();
+
+        for primitive in document.meshes().flat_map(|m| m.primitives()) {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                warn!("Skipping {:?} primitive in {:?}", primitive.mode(), path);
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let normals: Vec<[f32; 3]> = reader.read_normals().map(|n| n.collect()).unwrap_or_default();
+
+            let base = mesh.vertices.len() as u32;
+            for (i, position) in positions.enumerate() {
+                let normal = normals.get(i).copied().unwrap_or([0.0; 3]);
+                mesh.vertices.push(MeshVertex { position, normal });
+            }
+            match reader.read_indices() {
+                Some(indices) => mesh.indices.extend(indices.into_u32().map(|i| base + i)),
+                None => mesh.indices.extend(base..mesh.vertices.len() as u32),
+            }
+        }
+
+        mesh.fill_missing_normals();
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    /// Unit cube centered on the origin with flat faces, drawn while a mesh loads
+    pub fn cube() -> Self {
+        let mut mesh = MeshData::default();
+        for axis in 0..3 {
+            for sign in [1.0f32, -1.0] {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                let mut normal = [0.0; 3];
+                normal[axis] = sign;
+                let first = mesh.vertices.len() as u32;
+                for (a, b) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+                    let mut position = [0.0; 3];
+                    position[axis] = 0.5 * sign;
+                    position[u] = a;
+                    position[v] = b;
+                    mesh.vertices.push(MeshVertex { position, normal });
+                }
+                // Counter-clockwise seen from outside
+                let corners = if sign > 0.0 { [0, 1, 2, 0, 2, 3] } else { [0, 2, 1, 0, 3, 2] };
+                mesh.indices.extend(corners.map(|corner| first + corner));
+            }
+        }
+        mesh
+    }
+
+    /// Number of triangles
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Center of the bounding box and the distance to the farthest vertex
+    pub fn bounding_sphere(&self) -> (Vector3<f32>, f32) {
+        let Some(first) = self.vertices.first().map(|v| Vector3::from(v.position)) else {
+            return (Vector3::new(0.0, 0.0, 0.0), 0.0);
+        };
+        let (min, max) = self.vertices.iter().fold((first, first), |(min, max), v| {
+            let [x, y, z] = v.position;
+            (Vector3::new(min.x.min(x), min.y.min(y), min.z.min(z)), Vector3::new(max.x.max(x), max.y.max(y), max.z.max(z)))
+        });
+        let center = (min + max) * 0.5;
+        let radius = self.vertices.iter().fold(0.0f32, |radius, v| radius.max((Vector3::from(v.position) - center).magnitude()));
+        (center, radius)
+    }
+
+    /// Give vertices without a normal the average normal of their triangles
+    fn fill_missing_normals(&mut self) {
+        let missing: Vec<bool> = self.vertices.iter().map(|v| v.normal == [0.0; 3]).collect();
+        if !missing.contains(&true) {
+            return;
+        }
+
+        let mut sums = vec![Vector3::new(0.0f32, 0.0, 0.0); self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let corner = |i: usize| self.vertices.get(triangle[i] as usize).map(|v| Vector3::from(v.position));
+            let (Some(a), Some(b), Some(c)) = (corner(0), corner(1), corner(2)) else {
+                continue;
+            };
+            // Area weighted: the cross product is not normalized
+            let normal = (b - a).cross(c - a);
+            for &index in triangle {
+                if missing[index as usize] {
+                    sums[index as usize] += normal;
+                }
+            }
+        }
+
+        for (vertex, (sum, missing)) in self.vertices.iter_mut().zip(sums.into_iter().zip(missing)) {
+            if missing {
+                let normal = if sum.magnitude2() > 0.0 { sum.normalize() } else { Vector3::unit_y() };
+                vertex.normal = normal.into();
+            }
+        }
+    }
+
+    /// Check the mesh has triangles and every index is in range
+    fn validate(&self) -> Result<()> {
+        if self.indices.is_empty() || !self.indices.len().is_multiple_of(3) {
+            return Err(VulkanError::MeshLoad("Mesh has no triangles".to_string()).into());
+        }
+        if let Some(&index) = self.indices.iter().find(|&&i| i as usize >= self.vertices.len()) {
+            return Err(VulkanError::MeshLoad(format!("Index {} out of range ({} vertices)", index, self.vertices.len())).into());
+        }
+        Ok(())
+    }
+}
+
+/// Parse the three numbers of a `v` or `vn` statement
+fn parse_vec3<'a>(mut words: impl Iterator<Item = &'a str>, line_number: usize) -> Result<[f32; 3]> {
+    let mut value = [0.0; 3];
+    for component in &mut value {
+        *component = words
+            .next()
+            .and_then(|word| word.parse().ok())
+            .ok_or_else(|| VulkanError::MeshLoad(format!("Line {}: expected three numbers", line_number)))?;
+    }
+    Ok(value)
+}
+
+/// Resolve a 1-based or negative OBJ index against the number of elements so far
+fn resolve_index(word: &str, count: usize, line_number: usize) -> Result<usize> {
+    let index: i64 = word
+        .parse()
+        .map_err(|_| VulkanError::MeshLoad(format!("Line {}: invalid index '{}'", line_number, word)))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(VulkanError::MeshLoad(format!("Line {}: index {} out of range", line_number, index)).into());
+    }
+    Ok(resolved as usize)
+}
+
+/// One mesh instance to draw
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshDraw {
+    /// Path of the mesh file
+    pub mesh: String,
+    /// Model matrix (column major)
+    pub model: [[f32; 4]; 4],
+    /// Base color (RGB, A unused)
+    pub color: [f32; 4],
+    /// Layer mask, see `RenderLayers`
+    pub layers: u32,
+}
+
+/// Per-instance vertex data of a mesh draw (must match the instance inputs of mesh.vert)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct MeshInstance {
+    /// Model matrix (column major)
+    pub model: [[f32; 4]; 4],
+    /// Base color (RGB, A unused)
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for MeshInstance {}
+unsafe impl bytemuck::Zeroable for MeshInstance {}
+
+/// Consecutive instances of one mesh on the same layers, drawn with one instanced draw call
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshBatch {
+    /// Path of the mesh file
+    pub mesh: String,
+    /// Layer mask of the instances, see `RenderLayers`
+    pub layers: u32,
+    /// Index of the batch's first instance in the instance buffer
+    pub first_instance: u32,
+    pub instance_count: u32,
+}
+
+/// Mesh draws collected for the current frame
+#[derive(Clone, Debug, Default)]
+pub struct MeshDrawList {
+    draws: Vec<MeshDraw>,
+    /// Data of the meshes the draws name, attached by `resolve`
+    sources: HashMap<String, Arc<MeshData>>,
+}
+
+impl MeshDrawList {
+    /// Create an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws in the list
+    pub fn draws(&self) -> &[MeshDraw] {
+        &self.draws
+    }
+
+    /// Add a mesh instance
+    ///
+    /// # Arguments
+    /// * `layers` - Layer mask, see `RenderLayers`
+    pub fn push(&mut self, mesh: &str, model: Matrix4<f32>, color: [f32; 3], layers: u32) {
+        self.draws.push(MeshDraw {
+            mesh: mesh.to_string(),
+            model: model.into(),
+            color: [color[0], color[1], color[2], 1.0],
+            layers,
+        });
+    }
+
+    /// Point the draws at the mesh data they are drawn with
+    ///
+    /// # Arguments
+    /// * `resolve` - Called once per mesh file with its path; returns the
+    ///   mesh name and data to draw it with, or None to drop its draws
+    pub fn resolve(&mut self, mut resolve: impl FnMut(&str) -> Option<(String, Arc<MeshData>)>) {
+        let mut resolved: HashMap<String, Option<String>> = HashMap::new();
+        let sources = &mut self.sources;
+        self.draws.retain_mut(|draw| {
+            let name = resolved.entry(draw.mesh.clone()).or_insert_with(|| {
+                resolve(&draw.mesh).map(|(name, data)| {
+                    sources.insert(name.clone(), data);
+                    name
+                })
+            });
+            match name {
+                Some(name) => {
+                    draw.mesh.clone_from(name);
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    /// Data of a mesh the draws name, None if `resolve` did not attach it
+    pub fn source(&self, mesh: &str) -> Option<&MeshData> {
+        self.sources.get(mesh).map(Arc::as_ref)
+    }
+
+    /// Group the draws into one batch per mesh and layer mask
+    ///
+    /// Batches are ordered by their first draw and the draws of a batch keep
+    /// their order. Viewports skip the batches on no layer they show.
+    ///
+    /// # Returns
+    /// The batches and the instance data they index, batch after batch
+    pub fn batch(&self) -> (Vec<MeshBatch>, Vec<MeshInstance>) {
+        let mut grouped: Vec<(&str, u32, Vec<MeshInstance>)> = Vec::new();
+        for draw in &self.draws {
+            let instance = MeshInstance { model: draw.model, color: draw.color };
+            match grouped.iter_mut().find(|(mesh, layers, _)| *mesh == draw.mesh && *layers == draw.layers) {
+                Some((_, _, instances)) => instances.push(instance),
+                None => grouped.push((&draw.mesh, draw.layers, vec![instance])),
+            }
+        }
+
+        let mut batches = Vec::with_capacity(grouped.len());
+        let mut instances = Vec::with_capacity(self.draws.len());
+        for (mesh, layers, group) in grouped {
+            batches.push(MeshBatch {
+                mesh: mesh.to_string(),
+                layers,
+                first_instance: instances.len() as u32,
+                instance_count: group.len() as u32,
+            });
+            instances.extend(group);
+        }
+        (batches, instances)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_obj_triangulates_and_shares_corners() {
+        let text = "\
+            # unit quad\n\
+            v 0 0 0\n\
+            v 1 0 0\n\
+            v 1 1 0\n\
+            v 0 1 0\n\
+            vn 0 0 1\n\
+            f 1//1 2//1 3//1 4//1\n\
+            f -4//-1 -2//-1 -1//-1\n";
+        let mesh = MeshData::parse_obj(text).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3, 0, 2, 3]);
+        assert!(mesh.vertices.iter().all(|v| v.normal == [0.0, 0.0, 1.0]));
+
+        assert!(MeshData::parse_obj("v 0 0 0\nf 1 2 3\n").is_err());
+        assert!(MeshData::parse_obj("v 0 0\n").is_err());
+        assert!(MeshData::parse_obj("v 0 0 0\n").is_err());
+
+        let (center, radius) = mesh.bounding_sphere();
+        assert_eq!(center, Vector3::new(0.5, 0.5, 0.0));
+        assert!((radius - 0.5f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_batch_groups_instances_by_mesh_and_layers() {
+        let mut list = MeshDrawList::new();
+        list.push("meshes/cube.obj", Matrix4::from_translation(Vector3::new(1.0, 0.0, 0.0)), [1.0, 0.0, 0.0], 1);
+        list.push("meshes/bunny.obj", Matrix4::from_scale(2.0), [0.0, 1.0, 0.0], 1);
+        list.push("meshes/cube.obj", Matrix4::from_translation(Vector3::new(3.0, 0.0, 0.0)), [0.0, 0.0, 1.0], 1);
+        list.push("meshes/cube.obj", Matrix4::from_scale(1.0), [1.0; 3], 0b10);
+
+        let (batches, instances) = list.batch();
+        assert_eq!(batches, vec![
+            MeshBatch { mesh: "meshes/cube.obj".to_string(), layers: 1, first_instance: 0, instance_count: 2 },
+            MeshBatch { mesh: "meshes/bunny.obj".to_string(), layers: 1, first_instance: 2, instance_count: 1 },
+            MeshBatch { mesh: "meshes/cube.obj".to_string(), layers: 0b10, first_instance: 3, instance_count: 1 },
+        ]);
+        assert_eq!(instances[0].model[3], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(instances[1].model[3], [3.0, 0.0, 0.0, 1.0]);
+        assert_eq!(instances[1].color, [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(instances[2].model[0], [2.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_cube_faces_point_outwards() {
+        let cube = MeshData::cube();
+        cube.validate().unwrap();
+        assert_eq!(cube.triangle_count(), 12);
+        for triangle in cube.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(cube.vertices[triangle[i] as usize].position));
+            let normal = Vector3::from(cube.vertices[triangle[0] as usize].normal);
+            assert!((b - a).cross(c - a).dot(normal) > 0.0);
+            assert!(a.dot(normal) > 0.0);
+        }
+        let (center, radius) = cube.bounding_sphere();
+        assert_eq!(center, Vector3::new(0.0, 0.0, 0.0));
+        assert!((radius - 0.75f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_obj_computes_missing_normals() {
+        let mesh = MeshData::parse_obj("v 0 0 0\nv 1 0 0\nv 0 0 -1\nf 1/1 2/2 3/3\n").unwrap();
+        for vertex in &mesh.vertices {
+            assert_eq!(vertex.normal, [0.0, 1.0, 0.0]);
+        }
+    }
+}
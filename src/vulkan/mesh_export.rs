@@ -0,0 +1,250 @@
+//! SDF to mesh export
+//!
+//! Extracts the surface of the current scene with marching cubes as a
+//! background job and writes it as Wavefront OBJ or binary STL, for 3D
+//! printing or import into other tools. The ECS world starts a
+//! `MeshExportJob` from the HUD mesh export panel and polls it every frame
+//! for the progress bar.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use cgmath::{InnerSpace, Vector3};
+use crate::error::{AppError, Result, ResultExt, Subsystem};
+use crate::jobs::{JobHandle, JobPool};
+use crate::vulkan::marching_cubes::{self, ExtractProgress, ExtractSettings, SceneSdf};
+use crate::vulkan::mesh::MeshData;
+use crate::vulkan::scene_buffer::GpuSceneData;
+use log::info;
+
+/// File format of an exported mesh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    /// Wavefront OBJ with per-vertex normals
+    Obj,
+    /// Binary STL
+    Stl,
+}
+
+impl MeshFormat {
+    /// All formats in display order
+    pub const ALL: [MeshFormat; 2] = [MeshFormat::Obj, MeshFormat::Stl];
+
+    /// Name shown in the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "OBJ",
+            MeshFormat::Stl => "STL (binary)",
+        }
+    }
+
+    /// File extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "obj",
+            MeshFormat::Stl => "stl",
+        }
+    }
+}
+
+/// Grid, format and output file of a mesh export
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshExportSettings {
+    /// Bounds and resolution of the sampling grid
+    pub extract: ExtractSettings,
+    /// Output format
+    pub format: MeshFormat,
+    /// Output file; the format's extension is used if it has none
+    pub path: PathBuf,
+}
+
+impl Default for MeshExportSettings {
+    fn default() -> Self {
+        Self {
+            extract: ExtractSettings::default(),
+            format: MeshFormat::Obj,
+            path: PathBuf::from(crate::config::mesh_export::DEFAULT_OUTPUT),
+        }
+    }
+}
+
+impl MeshExportSettings {
+    /// Output file with the format's extension added if missing
+    pub fn output_path(&self) -> PathBuf {
+        if self.path.extension().is_some() {
+            self.path.clone()
+        } else {
+            self.path.with_extension(self.format.extension())
+        }
+    }
+}
+
+/// Result of a finished export
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshExportSummary {
+    /// File written
+    pub path: PathBuf,
+    /// Number of triangles written
+    pub triangle_count: usize,
+}
+
+/// Mesh export running on the job pool
+pub struct MeshExportJob {
+    progress: Arc<ExtractProgress>,
+    handle: JobHandle<Result<Option<MeshExportSummary>>>,
+}
+
+impl MeshExportJob {
+    /// Start extracting and writing the scene
+    ///
+    /// # Arguments
+    /// * `jobs` - Pool the export runs on
+    /// * `scene` - Scene collected from the ECS world this frame
+    /// * `settings` - Grid, format and output of the export
+    ///
+    /// # Errors
+    /// Returns an error if the settings are invalid
+    pub fn start(jobs: &JobPool, scene: GpuSceneData, settings: MeshExportSettings) -> Result<Self> {
+        if !settings.extract.is_valid() {
+            return Err(AppError::Generic("Mesh export bounds are empty".to_string()));
+        }
+
+        let progress = Arc::new(ExtractProgress::default());
+        let job_progress = Arc::clone(&progress);
+        let handle = jobs.spawn("Mesh export", move || Self::run(SceneSdf::new(scene), &settings, &job_progress));
+
+        Ok(Self { progress, handle })
+    }
+
+    /// Extract and write the mesh; None if cancelled
+    fn run(sdf: SceneSdf, settings: &MeshExportSettings, progress: &ExtractProgress) -> Result<Option<MeshExportSummary>> {
+        let [nx, ny, nz] = settings.extract.cell_counts();
+        info!("Extracting scene mesh on a {}x{}x{} grid", nx, ny, nz);
+        let Some(mesh) = marching_cubes::extract(&sdf, &settings.extract, progress) else {
+            return Ok(None);
+        };
+        if mesh.indices.is_empty() {
+            return Err(AppError::Generic("No surface inside the export bounds".to_string()));
+        }
+
+        let path = settings.output_path();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).context(Subsystem::Io, format!("create directory {}", dir.display()))?;
+        }
+        let file = File::create(&path).context(Subsystem::Io, format!("create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        match settings.format {
+            MeshFormat::Obj => write_obj(&mesh, &mut writer),
+            MeshFormat::Stl => write_stl(&mesh, &mut writer),
+        }
+        .and_then(|()| writer.flush())
+        .context(Subsystem::Io, format!("write {}", path.display()))?;
+
+        info!("Exported {} triangles to {}", mesh.triangle_count(), path.display());
+        Ok(Some(MeshExportSummary { path, triangle_count: mesh.triangle_count() }))
+    }
+
+    /// Completed fraction in [0, 1]
+    pub fn progress(&self) -> f32 {
+        self.progress.fraction()
+    }
+
+    /// Ask the export to stop; it ends without writing a file
+    pub fn cancel(&self) {
+        self.progress.cancel();
+    }
+
+    /// Whether the job is done and `finish` will not block
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Wait for the export to end
+    ///
+    /// # Returns
+    /// The written file, or None if the export was cancelled
+    ///
+    /// # Errors
+    /// Returns an error if extraction or writing failed, or the job panicked
+    pub fn finish(self) -> Result<Option<MeshExportSummary>> {
+        self.handle.finish()?
+    }
+}
+
+/// Write a mesh as Wavefront OBJ with per-vertex normals
+///
+/// # Errors
+/// Returns an error if writing fails
+pub fn write_obj(mesh: &MeshData, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "# SDF scene, {} triangles", mesh.triangle_count())?;
+    for vertex in &mesh.vertices {
+        let [x, y, z] = vertex.position;
+        writeln!(writer, "v {} {} {}", x, y, z)?;
+    }
+    for vertex in &mesh.vertices {
+        let [x, y, z] = vertex.normal;
+        writeln!(writer, "vn {} {} {}", x, y, z)?;
+    }
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+        writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+    }
+    Ok(())
+}
+
+/// Write a mesh as binary STL with one facet normal per triangle
+///
+/// # Errors
+/// Returns an error if writing fails
+pub fn write_stl(mesh: &MeshData, writer: &mut impl Write) -> io::Result<()> {
+    let mut header = [0u8; 80];
+    let title = b"SDF scene exported by vulkan-app";
+    header[..title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.triangle_count() as u32).to_le_bytes())?;
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(mesh.vertices[triangle[i] as usize].position));
+        let normal = (b - a).cross(c - a);
+        let normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { normal };
+        for v in [normal, a, b, c] {
+            for component in [v.x, v.y, v.z] {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        // Attribute byte count
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> MeshData {
+        let mut mesh = MeshData::parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        mesh.vertices[0].normal = [0.0, 0.0, 1.0];
+        mesh
+    }
+
+    #[test]
+    fn test_obj_round_trips_through_the_loader() {
+        let mesh = triangle();
+        let mut bytes = Vec::new();
+        write_obj(&mesh, &mut bytes).unwrap();
+        assert_eq!(MeshData::parse_obj(&String::from_utf8(bytes).unwrap()).unwrap(), mesh);
+    }
+
+    #[test]
+    fn test_stl_layout() {
+        let mut bytes = Vec::new();
+        write_stl(&triangle(), &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 80 + 4 + 50);
+        assert_eq!(u32::from_le_bytes(bytes[80..84].try_into().unwrap()), 1);
+        // Facet normal of a counter-clockwise triangle in the xy plane
+        let normal_z = f32::from_le_bytes(bytes[92..96].try_into().unwrap());
+        assert_eq!(normal_z, 1.0);
+    }
+}
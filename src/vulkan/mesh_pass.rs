@@ -0,0 +1,709 @@
+//! Mesh pipeline
+//!
+//! Draws triangle meshes into the offscreen SDF target right after the
+//! raymarched scene, in the same render pass. The SDF pipeline writes the
+//! depth of its ray hits and this pipeline depth tests against it, so meshes
+//! and SDF shapes occlude each other. Meshes are projected with the same
+//! viewport cameras as the raymarcher (see `shaders/mesh.vert`).
+//!
+//! Mesh files are loaded by the asset manager, which attaches their data to
+//! the draw list (see `assets`). Each mesh is copied to device local buffers
+//! the first time a draw references it and kept until the residency budget
+//! evicts it (see `vulkan::residency`) or its file changes; the meshes new
+//! in a frame share one upload submission, which runs in the background,
+//! and are drawn from the first frame after it completed. Meshes whose
+//! buffers fail to upload are reported once and skipped afterwards.
+//!
+//! Draws of the same mesh are batched: their model matrices and colors are
+//! written to a per-frame instance buffer read through a per-instance vertex
+//! binding, so every mesh costs one draw call however often it is placed.
+//! Instances on different render layers go in different batches, and each
+//! viewport draws only the batches on a layer it shows.
+//!
+//! Meshes cast shadows from the sun onto each other: `record_shadows` draws
+//! the same batches depth-only into the shadow map (see `shadow_map`)
+//! before the scene pass, and the mesh fragment shader samples it.
+
+use ash::vk;
+use ash::{Device, Instance};
+use cgmath::{InnerSpace, Matrix4, Vector3};
+use std::collections::{HashMap, HashSet};
+use std::ffi::CStr;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::buffer::{DynamicBuffer, GpuBuffer};
+use crate::vulkan::deletion_queue::DeletionQueue;
+use crate::vulkan::mesh::{MeshBatch, MeshData, MeshDrawList, MeshInstance, MeshVertex};
+use crate::vulkan::rendering::PassLayout;
+use crate::vulkan::residency::ResidentAsset;
+use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::shadow_map::{enclosing_sphere, light_view_projection, ShadowMap};
+use crate::vulkan::upload::{UploadContext, UploadTicket};
+use crate::vulkan::vertex_input::VertexLayout;
+use crate::vulkan::viewport::{rect_aspect, Tile, ViewCamera};
+use log::{debug, error, info, warn};
+
+/// Push constants for the mesh shaders
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct MeshPushConstants {
+    /// xyz = camera origin, w = orthographic half height (0 = perspective)
+    camera_origin: [f32; 4],
+    /// xyz = camera forward direction, w = roll about it in radians
+    camera_forward: [f32; 4],
+    /// Half width and height (xy) and center (zw) of the view, see `ViewCamera::lens`
+    camera_lens: [f32; 4],
+    /// xyz = direction towards the sun, w = intensity
+    sun_direction: [f32; 4],
+    /// World to shadow map clip space (column major)
+    light_view_projection: [[f32; 4]; 4],
+}
+
+unsafe impl bytemuck::Pod for MeshPushConstants {}
+unsafe impl bytemuck::Zeroable for MeshPushConstants {}
+
+/// Push constants for the shadow pass (must match mesh_shadow.vert)
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct ShadowPushConstants {
+    /// World to shadow map clip space (column major)
+    light_view_projection: [[f32; 4]; 4],
+}
+
+unsafe impl bytemuck::Pod for ShadowPushConstants {}
+unsafe impl bytemuck::Zeroable for ShadowPushConstants {}
+
+/// Vertex and index buffers of an uploaded mesh
+struct GpuMesh {
+    vertex_buffer: GpuBuffer,
+    index_buffer: GpuBuffer,
+    index_count: u32,
+    /// Bounding sphere in mesh space
+    bounds: (Vector3<f32>, f32),
+}
+
+/// A mesh whose buffers are being uploaded
+struct PendingMesh {
+    path: String,
+    mesh: GpuMesh,
+    ticket: UploadTicket,
+    /// Whether the file changed since the upload started
+    stale: bool,
+}
+
+/// Mesh pipeline, uploaded meshes and the draws of the current frame
+pub struct MeshPass {
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    shadow_pipeline_layout: vk::PipelineLayout,
+    shadow_pipeline: vk::Pipeline,
+    shadow_map: ShadowMap,
+    /// Bounding sphere of the drawn instances, which the shadow map covers
+    shadow_bounds: Option<(Vector3<f32>, f32)>,
+    /// Uploaded meshes by file path
+    meshes: HashMap<String, GpuMesh>,
+    /// Meshes whose upload is in flight, drawn once it completed
+    uploading: Vec<PendingMesh>,
+    /// Mesh files that failed to load
+    failed: HashSet<String>,
+    /// Instanced draws recorded with the next frames
+    batches: Vec<MeshBatch>,
+    /// Instance data of the batches, written to the frame's region before recording
+    instances: Vec<MeshInstance>,
+    /// One region of instances per frame in flight
+    instance_buffer: DynamicBuffer<MeshInstance>,
+    /// Number of instances written for each frame
+    instance_counts: Vec<u32>,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    device: Device,
+}
+
+impl MeshPass {
+    /// Create the mesh pipeline
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - Physical device for memory type lookup
+    /// * `device` - The logical device
+    /// * `pass` - Offscreen SDF pass the meshes are drawn in
+    ///
+    /// # Errors
+    /// Returns an error if shader compilation or pipeline creation fails
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        pass: &PassLayout,
+    ) -> Result<Self> {
+        info!("Creating mesh pipeline");
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let shadow_map = ShadowMap::new(instance, physical_device, device, pass.mode)?;
+        let (pipeline_layout, pipeline) = Self::create_pipeline(device, pass, shadow_map.descriptor_set_layout())?;
+        let (shadow_pipeline_layout, shadow_pipeline) = match Self::create_shadow_pipeline(device, &shadow_map) {
+            Ok(shadow) => shadow,
+            Err(e) => {
+                unsafe {
+                    device.destroy_pipeline(pipeline, None);
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                }
+                return Err(e);
+            }
+        };
+        let frame_count = config::vulkan::MAX_FRAMES_IN_FLIGHT;
+        let instance_buffer = match DynamicBuffer::new(
+            device,
+            &memory_properties,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            config::mesh::MAX_INSTANCES,
+            frame_count,
+        ) {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                unsafe {
+                    device.destroy_pipeline(shadow_pipeline, None);
+                    device.destroy_pipeline_layout(shadow_pipeline_layout, None);
+                    device.destroy_pipeline(pipeline, None);
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                }
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            pipeline_layout,
+            pipeline,
+            shadow_pipeline_layout,
+            shadow_pipeline,
+            shadow_map,
+            shadow_bounds: None,
+            meshes: HashMap::new(),
+            uploading: Vec::new(),
+            failed: HashSet::new(),
+            batches: Vec::new(),
+            instances: Vec::new(),
+            instance_buffer,
+            instance_counts: vec![0; frame_count],
+            memory_properties,
+            device: device.clone(),
+        })
+    }
+
+    /// Create the triangle list pipeline with depth testing
+    ///
+    /// Binding 0 holds the mesh vertices, binding 1 the per-instance model
+    /// matrix and color; set 0 samples the shadow map.
+    fn create_pipeline(device: &Device, pass: &PassLayout, shadow_set_layout: vk::DescriptorSetLayout) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let vert_code = shader_compiler.compile_file(config::shader::MESH_VERTEX_SHADER, "main", &[])?;
+        let frag_code = shader_compiler.compile_file(config::shader::MESH_FRAGMENT_SHADER, "main", &[])?;
+
+        let (vert_module, frag_module) = unsafe {
+            let vert = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&vert_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create mesh vertex module: {:?}", e)))?;
+            let frag = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&frag_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create mesh fragment module: {:?}", e)))?;
+            (vert, frag)
+        };
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_module)
+                .name(entry_point),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_module)
+                .name(entry_point),
+        ];
+
+        let vertex_layout = Self::vertex_layout();
+        let vertex_input_info = vertex_layout.create_info();
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        // Not culled: the raymarcher's camera basis mirrors the winding of some views
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(config::rendering::LINE_WIDTH)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(vk::CompareOp::LESS);
+        let color_blend_attachments = pass.color_blend_attachments(vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false));
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachments);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&dynamic_states);
+
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<MeshPushConstants>() as u32,
+        }];
+        let set_layouts = [shadow_set_layout];
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&push_constant_ranges), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create mesh pipeline layout: {:?}", e)))?
+        };
+
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout), &mut rendering);
+
+        let pipeline = unsafe {
+            let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None);
+            device.destroy_shader_module(vert_module, None);
+            device.destroy_shader_module(frag_module, None);
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, e)) => {
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                    return Err(VulkanError::PipelineCreation(format!("Failed to create mesh pipeline: {:?}", e)).into());
+                }
+            }
+        };
+
+        Ok((pipeline_layout, pipeline))
+    }
+
+    /// Mesh vertices in binding 0, per-instance model matrix and color in binding 1
+    fn vertex_layout() -> VertexLayout {
+        VertexLayout::new()
+            .per_vertex::<MeshVertex>()
+            .attribute(vk::Format::R32G32B32_SFLOAT, std::mem::offset_of!(MeshVertex, position))
+            .attribute(vk::Format::R32G32B32_SFLOAT, std::mem::offset_of!(MeshVertex, normal))
+            .per_instance::<MeshInstance>()
+            .matrix4(std::mem::offset_of!(MeshInstance, model))
+            .attribute(vk::Format::R32G32B32A32_SFLOAT, std::mem::offset_of!(MeshInstance, color))
+    }
+
+    /// Create the depth-only pipeline drawing the meshes into the shadow map
+    ///
+    /// Same vertex inputs as the mesh pipeline, no fragment shader, and a
+    /// depth bias against shadow acne.
+    fn create_shadow_pipeline(device: &Device, shadow_map: &ShadowMap) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let vert_code = shader_compiler.compile_file(config::shader::MESH_SHADOW_VERTEX_SHADER, "main", &[])?;
+        let vert_module = unsafe {
+            device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&vert_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create mesh shadow vertex module: {:?}", e)))?
+        };
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let shader_stages = [vk::PipelineShaderStageCreateInfo::default()
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(vert_module)
+            .name(entry_point)];
+
+        let vertex_layout = Self::vertex_layout();
+        let vertex_input_info = vertex_layout.create_info();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        // Not culled, so open meshes cast shadows from both sides
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(config::rendering::LINE_WIDTH)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .depth_bias_enable(true)
+            .depth_bias_constant_factor(config::shadows::DEPTH_BIAS_CONSTANT)
+            .depth_bias_slope_factor(config::shadows::DEPTH_BIAS_SLOPE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(vk::CompareOp::LESS);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&dynamic_states);
+
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: std::mem::size_of::<ShadowPushConstants>() as u32,
+        }];
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .push_constant_ranges(&push_constant_ranges), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create mesh shadow pipeline layout: {:?}", e)))?
+        };
+
+        let mut rendering = ShadowMap::rendering_info();
+        let pipeline_info = shadow_map.attach(vk::GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout), &mut rendering);
+
+        let pipeline = unsafe {
+            let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None);
+            device.destroy_shader_module(vert_module, None);
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, e)) => {
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                    return Err(VulkanError::PipelineCreation(format!("Failed to create mesh shadow pipeline: {:?}", e)).into());
+                }
+            }
+        };
+
+        Ok((pipeline_layout, pipeline))
+    }
+
+    /// Set the draws recorded with the next frames, uploading new meshes
+    ///
+    /// Called every frame. New meshes are uploaded in one submission that is
+    /// not waited on; each is drawn from the first call after its upload
+    /// completed. Meshes without data in the list are not drawn.
+    ///
+    /// # Arguments
+    /// * `list` - Mesh instances collected from the ECS world, with their data
+    /// * `upload` - Upload context the mesh data is copied with
+    pub fn set_draws(&mut self, list: MeshDrawList, upload: &mut UploadContext) {
+        self.poll_uploads(upload);
+        let (batches, instances) = list.batch();
+
+        // Buffers are created before the batch borrows the context
+        let mut loaded: Vec<(String, &MeshData, GpuMesh)> = Vec::new();
+        for batch in &batches {
+            let uploading = self.uploading.iter().any(|pending| pending.path == batch.mesh);
+            if uploading || self.meshes.contains_key(&batch.mesh) || self.failed.contains(&batch.mesh) {
+                continue;
+            }
+            let Some(data) = list.source(&batch.mesh) else {
+                continue;
+            };
+            match self.create_mesh(data, upload) {
+                Ok(mesh) => loaded.push((batch.mesh.clone(), data, mesh)),
+                Err(e) => {
+                    error!("Failed to create the buffers of mesh {}: {}", batch.mesh, e);
+                    self.failed.insert(batch.mesh.clone());
+                }
+            }
+        }
+
+        if !loaded.is_empty() {
+            let mut batch = upload.batch();
+            let queued = loaded.iter().try_for_each(|(_, data, mesh)| {
+                batch.upload_to_buffer(bytemuck::cast_slice(&data.vertices), mesh.vertex_buffer.handle(), 0)?;
+                batch.upload_to_buffer(bytemuck::cast_slice(&data.indices), mesh.index_buffer.handle(), 0)
+            });
+            match queued.and_then(|_| batch.submit()) {
+                Ok(ticket) => {
+                    for (path, _, mesh) in loaded {
+                        debug!("Uploading mesh {}", path);
+                        self.uploading.push(PendingMesh { path, mesh, ticket, stale: false });
+                    }
+                }
+                Err(e) => {
+                    for (path, _, _) in loaded {
+                        error!("Failed to upload mesh {}: {}", path, e);
+                        self.failed.insert(path);
+                    }
+                }
+            }
+        }
+        self.batches = batches;
+        self.instances = instances;
+        self.shadow_bounds = self.instance_bounds();
+    }
+
+    /// Move the meshes whose upload completed to the drawn ones
+    ///
+    /// A mesh whose file changed meanwhile is dropped instead, as no frame
+    /// has drawn it; the next draws upload its new version.
+    fn poll_uploads(&mut self, upload: &mut UploadContext) {
+        let (done, uploading): (Vec<_>, Vec<_>) = std::mem::take(&mut self.uploading)
+            .into_iter()
+            .partition(|pending| upload.is_complete(pending.ticket));
+        self.uploading = uploading;
+        for pending in done {
+            if pending.stale {
+                debug!("Mesh {} changed during its upload", pending.path);
+                continue;
+            }
+            info!("Uploaded mesh {} ({} triangles)", pending.path, pending.mesh.index_count / 3);
+            self.meshes.insert(pending.path, pending.mesh);
+        }
+    }
+
+    /// Bounding sphere of the instances of the uploaded meshes
+    fn instance_bounds(&self) -> Option<(Vector3<f32>, f32)> {
+        enclosing_sphere(self.batches.iter()
+            .filter_map(|batch| Some((batch, self.meshes.get(&batch.mesh)?.bounds)))
+            .flat_map(|(batch, bounds)| {
+                let first = batch.first_instance as usize;
+                self.instances[first..first + batch.instance_count as usize]
+                    .iter()
+                    .map(move |instance| transform_sphere(&instance.model, bounds))
+            }))
+    }
+
+    /// Write the instances of the current draws into a frame's instance buffer
+    ///
+    /// The caller must have waited on that frame's in-flight fence.
+    pub fn write_instances(&mut self, frame: usize) {
+        let count = self.instance_buffer.write(frame, &self.instances);
+        if count < self.instances.len() {
+            warn!("Mesh instances truncated to {}", count);
+        }
+        self.instance_counts[frame] = count as u32;
+    }
+
+    /// Number of meshes uploaded so far
+    #[allow(dead_code)]
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+
+    /// Mesh space bounding spheres of the uploaded meshes by file path
+    pub fn mesh_bounds(&self) -> HashMap<String, (Vector3<f32>, f32)> {
+        self.meshes.iter().map(|(path, mesh)| (path.clone(), mesh.bounds)).collect()
+    }
+
+    /// Uploaded meshes with their size and whether the current draws use
+    /// them; meshes still uploading count as used
+    pub fn residents(&self) -> impl Iterator<Item = (ResidentAsset, vk::DeviceSize, bool)> + '_ {
+        let uploaded = self.meshes.iter().map(|(path, mesh)| (path, mesh, self.batches.iter().any(|batch| batch.mesh == *path)));
+        let uploading = self.uploading.iter().map(|pending| (&pending.path, &pending.mesh, true));
+        uploaded.chain(uploading).map(|(path, mesh, in_use)| {
+            (ResidentAsset::Mesh(path.clone()), mesh.vertex_buffer.size() + mesh.index_buffer.size(), in_use)
+        })
+    }
+
+    /// Free an uploaded mesh; it is uploaded again when next drawn
+    ///
+    /// The buffers are destroyed right away, so the mesh must not have been
+    /// drawn by a frame still in flight, which `Residency` ensures.
+    pub fn evict(&mut self, path: &str) {
+        if self.meshes.remove(path).is_some() {
+            debug!("Evicted mesh {}", path);
+            self.shadow_bounds = self.instance_bounds();
+        }
+    }
+
+    /// Drop an uploaded mesh whose file changed on disk; it is uploaded
+    /// again from the data of the next draws
+    ///
+    /// The buffers go to the deletion queue, since frames in flight may
+    /// still draw them. A mesh that failed to upload is tried again, and one
+    /// still uploading is dropped once its upload completed.
+    pub fn reload(&mut self, path: &str, deletion_queue: &mut DeletionQueue) {
+        self.failed.remove(path);
+        for pending in self.uploading.iter_mut().filter(|pending| pending.path == path) {
+            pending.stale = true;
+        }
+        if let Some(mesh) = self.meshes.remove(path) {
+            debug!("Mesh {} will be uploaded again", path);
+            mesh.vertex_buffer.retire(deletion_queue);
+            mesh.index_buffer.retire(deletion_queue);
+            self.shadow_bounds = self.instance_bounds();
+        }
+    }
+
+    /// Create the device local vertex and index buffers of a mesh
+    fn create_mesh(&self, data: &MeshData, upload: &UploadContext) -> Result<GpuMesh> {
+        let buffer = |size: usize, usage| {
+            GpuBuffer::device_local(&self.device, &self.memory_properties, size as vk::DeviceSize, usage, upload)
+        };
+        Ok(GpuMesh {
+            vertex_buffer: buffer(std::mem::size_of_val(data.vertices.as_slice()), vk::BufferUsageFlags::VERTEX_BUFFER)?,
+            index_buffer: buffer(std::mem::size_of_val(data.indices.as_slice()), vk::BufferUsageFlags::INDEX_BUFFER)?,
+            index_count: data.indices.len() as u32,
+            bounds: data.bounding_sphere(),
+        })
+    }
+
+    /// World to shadow map clip space for a sun direction
+    fn light_view_projection(&self, sun_direction: [f32; 4]) -> [[f32; 4]; 4] {
+        let towards_sun = Vector3::new(sun_direction[0], sun_direction[1], sun_direction[2]);
+        let bounds = self.shadow_bounds.unwrap_or((Vector3::new(0.0, 0.0, 0.0), 1.0));
+        light_view_projection(towards_sun, bounds).into()
+    }
+
+    /// Record the shadow pass, before the scene pass of a frame
+    ///
+    /// Draws the frame's instances depth-only into the shadow map. Recorded
+    /// whenever `record` draws something, so the map is always written
+    /// before the mesh shader samples it.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer outside of any pass
+    /// * `frame` - Frame in flight whose instance buffer to draw
+    /// * `sun_direction` - Direction towards the sun (xyz) and intensity (w)
+    pub fn record_shadows(&self, command_buffer: vk::CommandBuffer, frame: usize, sun_direction: [f32; 4]) {
+        let instance_count = self.instance_counts[frame];
+        if instance_count == 0 {
+            return;
+        }
+
+        let push_constants = ShadowPushConstants {
+            light_view_projection: self.light_view_projection(sun_direction),
+        };
+        self.shadow_map.begin(command_buffer);
+        unsafe {
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.shadow_pipeline);
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.shadow_pipeline_layout,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            self.device.cmd_bind_vertex_buffers(command_buffer, 1, &[self.instance_buffer.handle()], &[self.instance_buffer.offset(frame)]);
+        }
+        self.draw_batches(command_buffer, instance_count, config::layers::ALL);
+        self.shadow_map.end(command_buffer);
+    }
+
+    /// Record the mesh draws for a viewport into the current scene pass
+    ///
+    /// Issues one instanced draw per mesh, reading the instances written for
+    /// the frame by `write_instances`, and samples the shadow map written by
+    /// `record_shadows` earlier in the command buffer.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the offscreen SDF render pass
+    /// * `frame` - Frame in flight whose instance buffer to draw
+    /// * `rect` - Viewport rectangle in the rendered image
+    /// * `tile` - Area of the image the target covers
+    /// * `camera` - Camera of the viewport
+    /// * `layers` - Mask of the render layers the viewport shows
+    /// * `sun_direction` - Direction towards the sun (xyz) and intensity (w)
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: usize,
+        rect: vk::Rect2D,
+        tile: &Tile,
+        camera: &ViewCamera,
+        layers: u32,
+        sun_direction: [f32; 4],
+    ) {
+        let instance_count = self.instance_counts[frame];
+        let Some(scissor) = tile.scissor(rect).filter(|_| instance_count > 0) else {
+            return;
+        };
+
+        let push_constants = MeshPushConstants {
+            camera_origin: camera.origin_vec4(),
+            camera_forward: camera.forward_vec4(),
+            camera_lens: camera.lens(rect_aspect(&rect)),
+            sun_direction,
+            light_view_projection: self.light_view_projection(sun_direction),
+        };
+
+        unsafe {
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[self.shadow_map.descriptor_set()],
+                &[],
+            );
+            self.device.cmd_set_viewport(command_buffer, 0, &[tile.viewport(&rect)]);
+            self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            self.device.cmd_bind_vertex_buffers(command_buffer, 1, &[self.instance_buffer.handle()], &[self.instance_buffer.offset(frame)]);
+        }
+        self.draw_batches(command_buffer, instance_count, layers);
+    }
+
+    /// Draw every batch of uploaded meshes on one of the layers with the bound pipeline and instances
+    fn draw_batches(&self, command_buffer: vk::CommandBuffer, instance_count: u32, layers: u32) {
+        unsafe {
+            for batch in self.batches.iter().filter(|batch| batch.layers & layers != 0) {
+                let Some(mesh) = self.meshes.get(&batch.mesh) else {
+                    continue;
+                };
+                // Batches past a truncated instance buffer are dropped
+                let count = batch.instance_count.min(instance_count.saturating_sub(batch.first_instance));
+                if count == 0 {
+                    continue;
+                }
+                self.device.cmd_bind_vertex_buffers(command_buffer, 0, &[mesh.vertex_buffer.handle()], &[0]);
+                self.device.cmd_bind_index_buffer(command_buffer, mesh.index_buffer.handle(), 0, vk::IndexType::UINT32);
+                self.device.cmd_draw_indexed(command_buffer, mesh.index_count, count, 0, 0, batch.first_instance);
+            }
+        }
+    }
+}
+
+impl Drop for MeshPass {
+    fn drop(&mut self) {
+        debug!("Destroying mesh pipeline and {} meshes", self.meshes.len());
+        // The device is idle at shutdown, including pending uploads
+        self.uploading.clear();
+        self.meshes.clear();
+        unsafe {
+            self.device.destroy_pipeline(self.shadow_pipeline, None);
+            self.device.destroy_pipeline_layout(self.shadow_pipeline_layout, None);
+            self.device.destroy_pipeline(self.pipeline, None);
+            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
+    }
+}
+
+/// Bounding sphere of a mesh placed by a model matrix
+///
+/// The radius grows with the largest scale of the matrix's axes.
+pub fn transform_sphere(model: &[[f32; 4]; 4], (center, radius): (Vector3<f32>, f32)) -> (Vector3<f32>, f32) {
+    let model = Matrix4::from(*model);
+    let scale = model.x.truncate().magnitude()
+        .max(model.y.truncate().magnitude())
+        .max(model.z.truncate().magnitude());
+    ((model * center.extend(1.0)).truncate(), radius * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_sphere_moves_and_scales_the_bounds() {
+        let model = Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))
+            * Matrix4::from_nonuniform_scale(1.0, 3.0, 2.0);
+        let (center, radius) = transform_sphere(&model.into(), (Vector3::new(1.0, 1.0, 0.0), 0.5));
+        assert_eq!(center, Vector3::new(2.0, 5.0, 3.0));
+        assert_eq!(radius, 1.5);
+    }
+}
@@ -3,8 +3,52 @@ pub mod device;
 pub mod swapchain;
 pub mod pipeline;
 pub mod renderer;
+pub mod shader_annotations;
 pub mod shader_compiler;
+pub mod shader_reflection;
 pub mod shader_watcher;
+pub mod reload_stats;
+pub mod push_constants;
+pub mod scene_buffer;
+pub mod quality;
+pub mod gpu_timer;
+pub mod resolution_scaler;
+pub mod composite;
+pub mod debug_draw;
+pub mod depth;
+pub mod gbuffer;
+pub mod mesh;
+pub mod mesh_pass;
+pub mod shadow_map;
+pub mod outline;
+pub mod vertex_input;
+pub mod particles;
+pub mod marching_cubes;
+pub mod mesh_export;
+pub mod shader_variant;
+pub mod pipeline_validator;
+pub mod gpu_selection;
+pub mod capabilities;
+pub mod rendering;
+pub mod bindless;
+pub mod tonemap;
+pub mod texture;
+pub mod environment_map;
+pub mod msdf;
+pub mod text;
+pub mod upload;
+pub mod buffer;
+pub mod memory_usage;
+pub mod residency;
+pub mod viewport;
+pub mod split_compare;
+pub mod deletion_queue;
+pub mod frame_export;
+pub mod still_export;
+pub mod fullscreen_pass;
+pub mod toy;
+#[cfg(feature = "xr")]
+pub mod xr;
 
 pub use instance::VulkanInstance;
 pub use device::VulkanDevice;
@@ -0,0 +1,381 @@
+//! Multi-channel signed distance fields of glyph outlines
+//!
+//! An MSDF stores three distance fields, one per color channel, whose
+//! median is the distance to the outline. Every edge of a contour belongs
+//! to two of the three channels, and edges meeting at a corner differ in
+//! one of them, so the median of filtered texels keeps corners sharp where
+//! a single distance field rounds them off (Chlumský, "Shape Decomposition
+//! for Multi-channel Distance Fields").
+//!
+//! Curves are flattened to polylines before measuring. Each channel holds
+//! the signed pseudo-distance to its nearest edge: past the end of an edge,
+//! the distance to the edge's extended tangent. Texels whose median
+//! disagrees in sign with the true distance get the true distance in all
+//! channels, which removes the artifacts the simple edge coloring leaves.
+
+/// Channel bits of an edge color
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+/// Sine of the smallest turn between two segments that makes a corner
+const CORNER_SINE: f32 = 0.14;
+
+/// Line segments a quadratic curve is flattened to
+const QUAD_STEPS: usize = 8;
+
+/// Line segments a cubic curve is flattened to
+const CUBIC_STEPS: usize = 12;
+
+/// Distances closer than this (in pixels) are ties
+const TIE_EPSILON: f32 = 1e-4;
+
+type Point = [f32; 2];
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn dot(a: Point, b: Point) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: Point, b: Point) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn lerp(a: Point, b: Point, t: f32) -> Point {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+}
+
+/// Segment of a glyph outline, y up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    Line(Point, Point),
+    /// Quadratic Bézier curve through its first and last point
+    Quad(Point, Point, Point),
+    /// Cubic Bézier curve through its first and last point
+    Cubic(Point, Point, Point, Point),
+}
+
+impl Segment {
+    /// Control points in order
+    fn points(&self) -> Vec<Point> {
+        match *self {
+            Segment::Line(a, b) => vec![a, b],
+            Segment::Quad(a, b, c) => vec![a, b, c],
+            Segment::Cubic(a, b, c, d) => vec![a, b, c, d],
+        }
+    }
+
+    fn start(&self) -> Point {
+        self.points()[0]
+    }
+
+    fn end(&self) -> Point {
+        *self.points().last().unwrap()
+    }
+
+    /// Point at parameter `t` in 0..=1
+    fn point(&self, t: f32) -> Point {
+        match *self {
+            Segment::Line(a, b) => lerp(a, b, t),
+            Segment::Quad(a, b, c) => lerp(lerp(a, b, t), lerp(b, c, t), t),
+            Segment::Cubic(a, b, c, d) => {
+                let (ab, bc, cd) = (lerp(a, b, t), lerp(b, c, t), lerp(c, d, t));
+                lerp(lerp(ab, bc, t), lerp(bc, cd, t), t)
+            }
+        }
+    }
+
+    /// Tangent at the start, from the first control point that differs from it
+    fn start_direction(&self) -> Point {
+        let points = self.points();
+        points[1..].iter()
+            .map(|&point| sub(point, points[0]))
+            .find(|&direction| dot(direction, direction) > 0.0)
+            .unwrap_or([0.0; 2])
+    }
+
+    /// Tangent at the end, from the last control point that differs from it
+    fn end_direction(&self) -> Point {
+        let points = self.points();
+        let end = points[points.len() - 1];
+        points[..points.len() - 1].iter().rev()
+            .map(|&point| sub(end, point))
+            .find(|&direction| dot(direction, direction) > 0.0)
+            .unwrap_or([0.0; 2])
+    }
+
+    /// Whether all control points coincide
+    fn is_degenerate(&self) -> bool {
+        self.start_direction() == [0.0; 2]
+    }
+
+    /// Line segments the segment is flattened to
+    fn steps(&self) -> usize {
+        match self {
+            Segment::Line(..) => 1,
+            Segment::Quad(..) => QUAD_STEPS,
+            Segment::Cubic(..) => CUBIC_STEPS,
+        }
+    }
+
+    /// The segment scaled about the origin, then moved by `offset`
+    pub fn transformed(&self, scale: f32, offset: Point) -> Self {
+        let map = |[x, y]: Point| [x * scale + offset[0], y * scale + offset[1]];
+        match *self {
+            Segment::Line(a, b) => Segment::Line(map(a), map(b)),
+            Segment::Quad(a, b, c) => Segment::Quad(map(a), map(b), map(c)),
+            Segment::Cubic(a, b, c, d) => Segment::Cubic(map(a), map(b), map(c), map(d)),
+        }
+    }
+}
+
+/// Split an outline into closed contours
+///
+/// A contour ends where it is back at its start or the next segment does
+/// not continue from the previous one. Degenerate segments are dropped.
+pub fn contours(segments: &[Segment]) -> Vec<Vec<Segment>> {
+    let mut contours: Vec<Vec<Segment>> = Vec::new();
+    for segment in segments.iter().filter(|segment| !segment.is_degenerate()) {
+        match contours.last_mut() {
+            Some(contour) if contour[contour.len() - 1].end() == segment.start() && contour[0].start() != segment.start() => {
+                contour.push(*segment);
+            }
+            _ => contours.push(vec![*segment]),
+        }
+    }
+    contours
+}
+
+/// Whether two tangents meet at a corner
+fn is_corner(a: Point, b: Point) -> bool {
+    dot(a, b) <= 0.0 || cross(a, b).abs() > CORNER_SINE * (dot(a, a) * dot(b, b)).sqrt()
+}
+
+/// Channels of each segment of a contour
+///
+/// The contour is split at its corners into splines colored cyan, magenta
+/// and yellow in turn, so neighbors always differ. A smooth contour is white
+/// and a contour with a single corner is split in three.
+fn color_contour(contour: &[Segment]) -> Vec<u8> {
+    let count = contour.len();
+    let corners: Vec<usize> = (0..count)
+        .filter(|&i| is_corner(contour[(i + count - 1) % count].end_direction(), contour[i].start_direction()))
+        .collect();
+    let mut colors = vec![WHITE; count];
+    match corners.len() {
+        0 => {}
+        1 if count < 3 => {}
+        1 => {
+            for j in 0..count {
+                colors[(corners[0] + j) % count] = [MAGENTA, WHITE, YELLOW][3 * j / count];
+            }
+        }
+        splines => {
+            let mut spline = 0;
+            for j in 0..count {
+                let index = (corners[0] + j) % count;
+                if j > 0 && corners.contains(&index) {
+                    spline += 1;
+                }
+                // The last spline also meets the first, which is cyan
+                colors[index] = match [CYAN, MAGENTA, YELLOW][spline % 3] {
+                    CYAN if spline == splines - 1 => MAGENTA,
+                    color => color,
+                };
+            }
+        }
+    }
+    colors
+}
+
+/// Flattened edge of a contour and the channels it belongs to
+struct Edge {
+    points: Vec<Point>,
+    color: u8,
+}
+
+/// Closest line segment found so far
+#[derive(Clone, Copy)]
+struct Nearest {
+    /// Distance to the closest point of the segment
+    distance: f32,
+    /// How perpendicular the way to the closest point is, to break ties at shared ends
+    orthogonality: f32,
+    /// Distance, positive left of the segment
+    signed: f32,
+    /// Signed distance to the extended segment past the ends of an edge, `signed` elsewhere
+    pseudo: f32,
+}
+
+impl Default for Nearest {
+    fn default() -> Self {
+        Self { distance: f32::INFINITY, orthogonality: 0.0, signed: f32::INFINITY, pseudo: f32::INFINITY }
+    }
+}
+
+impl Nearest {
+    /// Measure the line segment from `a` to `b` from `p`
+    ///
+    /// `first` and `last` tell whether the segment starts or ends its edge.
+    fn measure(a: Point, b: Point, p: Point, first: bool, last: bool) -> Option<Self> {
+        let ab = sub(b, a);
+        let length_squared = dot(ab, ab);
+        if length_squared <= 0.0 {
+            return None;
+        }
+        let length = length_squared.sqrt();
+        let t = dot(sub(p, a), ab) / length_squared;
+        let offset = sub(p, lerp(a, b, t.clamp(0.0, 1.0)));
+        let distance = dot(offset, offset).sqrt();
+        let line = cross(ab, sub(p, a)) / length;
+        let signed = if line < 0.0 { -distance } else { distance };
+        let orthogonality = if (0.0..=1.0).contains(&t) && t != 0.0 && t != 1.0 || distance == 0.0 {
+            1.0
+        } else {
+            (cross(ab, offset) / (length * distance)).abs()
+        };
+        let past_end = (t < 0.0 && first) || (t > 1.0 && last);
+        Some(Self { distance, orthogonality, signed, pseudo: if past_end { line } else { signed } })
+    }
+
+    /// Keep the closer of the two, the more orthogonal one on a tie
+    fn take(&mut self, other: Self) {
+        if other.distance < self.distance - TIE_EPSILON
+            || ((other.distance - self.distance).abs() <= TIE_EPSILON && other.orthogonality > self.orthogonality)
+        {
+            *self = other;
+        }
+    }
+}
+
+/// Middle one of three values
+pub fn median([a, b, c]: [f32; 3]) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// Generate the MSDF of an outline
+///
+/// The field is positive inside the outline whichever way its outer
+/// contours turn, and stored as `0.5 + distance / (2 * range)`, so 0.5 is
+/// on the outline and distances beyond `range` saturate.
+///
+/// # Arguments
+/// * `segments` - Outline in pixels, y up from the bottom of the image
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `range` - Distance in pixels between the outline and a saturated texel
+///
+/// # Returns
+/// RGBA8 pixels, rows from top to bottom, alpha opaque
+pub fn generate(segments: &[Segment], width: usize, height: usize, range: f32) -> Vec<u8> {
+    let mut edges = Vec::new();
+    let mut area = 0.0;
+    for contour in contours(segments) {
+        for (segment, color) in contour.iter().zip(color_contour(&contour)) {
+            let steps = segment.steps();
+            let points: Vec<Point> = (0..=steps).map(|i| segment.point(i as f32 / steps as f32)).collect();
+            area += points.windows(2).map(|pair| cross(pair[0], pair[1])).sum::<f32>();
+            edges.push(Edge { points, color });
+        }
+    }
+    // Outer contours of TrueType fonts turn clockwise, of PostScript fonts counterclockwise
+    let orientation = if area < 0.0 { -1.0 } else { 1.0 };
+
+    let mut pixels = vec![0; width * height * 4];
+    for row in 0..height {
+        for column in 0..width {
+            let p = [column as f32 + 0.5, (height - row) as f32 - 0.5];
+            let mut nearest = Nearest::default();
+            let mut channels = [Nearest::default(); 3];
+            for edge in &edges {
+                let last = edge.points.len() - 2;
+                for (index, pair) in edge.points.windows(2).enumerate() {
+                    let Some(candidate) = Nearest::measure(pair[0], pair[1], p, index == 0, index == last) else {
+                        continue;
+                    };
+                    nearest.take(candidate);
+                    for (channel, best) in channels.iter_mut().enumerate() {
+                        if edge.color & (1 << channel) != 0 {
+                            best.take(candidate);
+                        }
+                    }
+                }
+            }
+
+            let distance = nearest.signed * orientation;
+            let mut distances = channels.map(|channel| channel.pseudo * orientation);
+            if (median(distances) < 0.0) != (distance < 0.0) {
+                distances = [distance; 3];
+            }
+            let texel = (row * width + column) * 4;
+            for (channel, distance) in distances.into_iter().enumerate() {
+                pixels[texel + channel] = ((0.5 + distance / (2.0 * range)).clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+            pixels[texel + 3] = 255;
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Square outline from `min` to `max`, counterclockwise unless `clockwise`
+    fn square(min: f32, max: f32, clockwise: bool) -> Vec<Segment> {
+        let mut corners = vec![[min, min], [max, min], [max, max], [min, max]];
+        if clockwise {
+            corners.reverse();
+        }
+        (0..4).map(|i| Segment::Line(corners[i], corners[(i + 1) % 4])).collect()
+    }
+
+    /// Median of a texel, back in pixels
+    fn distance_at(pixels: &[u8], width: usize, column: usize, row: usize, range: f32) -> f32 {
+        let texel = (row * width + column) * 4;
+        let channels = [0, 1, 2].map(|channel| pixels[texel + channel] as f32 / 255.0);
+        (median(channels) - 0.5) * 2.0 * range
+    }
+
+    #[test]
+    fn test_square_field_is_inside_positive_either_way_round() {
+        let range = 4.0;
+        for clockwise in [false, true] {
+            let field = generate(&square(4.0, 12.0, clockwise), 16, 16, range);
+            // Rows count down from the top, so row 8 is just below y = 8
+            assert!((distance_at(&field, 16, 8, 8, range) - 3.5).abs() < 0.1);
+            assert!((distance_at(&field, 16, 5, 8, range) - 1.5).abs() < 0.1);
+            assert!((distance_at(&field, 16, 2, 8, range) + 1.5).abs() < 0.1);
+            assert!(distance_at(&field, 16, 0, 0, range) < -3.0);
+        }
+    }
+
+    #[test]
+    fn test_corners_split_edge_colors() {
+        let colors = color_contour(&square(0.0, 1.0, false));
+        assert_eq!(colors, vec![CYAN, MAGENTA, YELLOW, MAGENTA]);
+        for i in 0..4 {
+            let (a, b) = (colors[i], colors[(i + 1) % 4]);
+            assert_ne!(a, b);
+            assert_eq!((a & b).count_ones(), 1);
+        }
+
+        // A circle made of quadratic arcs has no corners
+        let arc = |a: Point, b: Point, c: Point| Segment::Quad(a, b, c);
+        let circle = [
+            arc([1.0, 0.0], [1.0, 1.0], [0.0, 1.0]),
+            arc([0.0, 1.0], [-1.0, 1.0], [-1.0, 0.0]),
+            arc([-1.0, 0.0], [-1.0, -1.0], [0.0, -1.0]),
+            arc([0.0, -1.0], [1.0, -1.0], [1.0, 0.0]),
+        ];
+        assert_eq!(color_contour(&circle), vec![WHITE; 4]);
+        assert_eq!(contours(&[square(0.0, 1.0, false), square(2.0, 3.0, false)].concat()).len(), 2);
+    }
+}
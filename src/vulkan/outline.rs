@@ -0,0 +1,364 @@
+//! Selection outline
+//!
+//! A post-process draw in the present pass, after the composite: every
+//! pixel outside the selection mask of the G-buffer (see `gbuffer`) that has
+//! a selected pixel within the outline thickness is painted in the outline
+//! color. The mask only covers the visible surfaces of the selected entity,
+//! so the outline follows its silhouette and the outlines of surfaces in
+//! front of it. Thickness is measured in swapchain pixels, independent of
+//! the render scale. Like the debug lines, the color is encoded for the
+//! display in the shader (`OutputSpecialization`).
+
+use ash::vk;
+use ash::Device;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::rendering::PassLayout;
+use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::tonemap::{DisplayOutput, OutputSpecialization};
+use log::{debug, info};
+
+/// Outline options of the Settings window
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutlineSettings {
+    /// Draw the outline around the selected entity
+    pub enabled: bool,
+    /// Linear RGB color and opacity
+    pub color: [f32; 4],
+    /// Width in pixels, up to `config::outline::MAX_THICKNESS`
+    pub thickness: f32,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: config::outline::ENABLED,
+            color: config::outline::COLOR,
+            thickness: config::outline::THICKNESS,
+        }
+    }
+}
+
+impl OutlineSettings {
+    /// Thickness clamped to the supported range
+    pub fn clamped_thickness(&self) -> f32 {
+        self.thickness.clamp(1.0, config::outline::MAX_THICKNESS)
+    }
+}
+
+/// Push constants of `shaders/outline.frag`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct OutlinePushConstants {
+    /// Linear RGB color and opacity
+    pub color: [f32; 4],
+    /// Fraction of the offscreen target that contains the rendered scene
+    pub uv_scale: [f32; 2],
+    /// Swapchain size in pixels
+    pub screen_size: [f32; 2],
+    /// Width in pixels
+    pub thickness: f32,
+    _padding: [f32; 3],
+}
+
+unsafe impl bytemuck::Pod for OutlinePushConstants {}
+unsafe impl bytemuck::Zeroable for OutlinePushConstants {}
+
+impl OutlinePushConstants {
+    /// Push constants for one outline draw
+    pub fn new(settings: &OutlineSettings, uv_scale: [f32; 2], extent: vk::Extent2D) -> Self {
+        Self {
+            color: settings.color,
+            uv_scale,
+            screen_size: [extent.width as f32, extent.height as f32],
+            thickness: settings.clamped_thickness(),
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Pipeline drawing the selection outline onto the swapchain
+pub struct OutlinePass {
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    device: Device,
+}
+
+impl OutlinePass {
+    /// Create the outline pipeline
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `present_pass` - Pass the outline is drawn in
+    /// * `display_output` - Encoding the swapchain expects
+    /// * `selection_view` - Selection mask of the offscreen target
+    ///
+    /// # Errors
+    /// Returns an error if any Vulkan object creation fails
+    pub fn new(device: &Device, present_pass: &PassLayout, display_output: DisplayOutput, selection_view: vk::ImageView) -> Result<Self> {
+        info!("Creating selection outline pass");
+
+        // Nearest filtering keeps the mask binary at any render scale
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+        let sampler = unsafe {
+            device.create_sampler(&sampler_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create outline sampler: {:?}", e)))?
+        };
+
+        let bindings = [vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)];
+        let descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create outline descriptor set layout: {:?}", e)))?
+        };
+
+        let pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)];
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(&vk::DescriptorPoolCreateInfo::default().pool_sizes(&pool_sizes).max_sets(1), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create outline descriptor pool: {:?}", e)))?
+        };
+
+        let layouts = [descriptor_set_layout];
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(&vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&layouts))
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to allocate outline descriptor set: {:?}", e)))?[0]
+        };
+
+        let (pipeline_layout, pipeline) = Self::create_pipeline(device, present_pass, display_output, descriptor_set_layout)?;
+
+        let pass = Self {
+            sampler,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            device: device.clone(),
+        };
+        pass.set_selection_view(selection_view);
+
+        info!("Selection outline pass created");
+        Ok(pass)
+    }
+
+    /// Create the outline pipeline, alpha blended over the composited scene
+    fn create_pipeline(
+        device: &Device,
+        pass: &PassLayout,
+        display_output: DisplayOutput,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let vert_code = shader_compiler.compile_file(config::shader::COMPOSITE_VERTEX_SHADER, "main", &[])?;
+        let frag_code = shader_compiler.compile_file(config::shader::OUTLINE_FRAGMENT_SHADER, "main", &[])?;
+
+        let (vert_module, frag_module) = unsafe {
+            let vert = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&vert_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create outline vertex module: {:?}", e)))?;
+            let frag = device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&frag_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create outline fragment module: {:?}", e)))?;
+            (vert, frag)
+        };
+
+        let specialization = display_output.specialization();
+        let map_entries = OutputSpecialization::map_entries();
+        let specialization_info = vk::SpecializationInfo::default()
+            .map_entries(&map_entries)
+            .data(bytemuck::bytes_of(&specialization));
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_module)
+                .name(entry_point),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_module)
+                .name(entry_point)
+                .specialization_info(&specialization_info),
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(config::rendering::LINE_WIDTH)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let color_blend_attachments = [vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .alpha_blend_op(vk::BlendOp::ADD)];
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachments);
+        // Drawn over the composited image, ignoring the depth attachment
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default();
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&dynamic_states);
+
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<OutlinePushConstants>() as u32,
+        }];
+        let set_layouts = [descriptor_set_layout];
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&push_constant_ranges), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create outline pipeline layout: {:?}", e)))?
+        };
+
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout), &mut rendering);
+
+        let pipeline = unsafe {
+            let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None);
+            device.destroy_shader_module(vert_module, None);
+            device.destroy_shader_module(frag_module, None);
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, e)) => {
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                    return Err(VulkanError::PipelineCreation(format!("Failed to create outline pipeline: {:?}", e)).into());
+                }
+            }
+        };
+
+        Ok((pipeline_layout, pipeline))
+    }
+
+    /// Point the descriptor set at the selection mask, after the offscreen target was recreated
+    ///
+    /// The caller must ensure the device is idle.
+    pub fn set_selection_view(&self, selection_view: vk::ImageView) {
+        let image_infos = [vk::DescriptorImageInfo::default()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(selection_view)
+            .sampler(self.sampler)];
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(self.descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_infos);
+
+        unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+        debug!("Outline reads selection mask {:?}", selection_view);
+    }
+
+    /// Draw the outline onto the current (swapchain) pass
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the present pass
+    /// * `extent` - Swapchain extent
+    /// * `uv_scale` - Fraction of the offscreen target that was rendered
+    /// * `settings` - Color and thickness
+    pub fn record(&self, command_buffer: vk::CommandBuffer, extent: vk::Extent2D, uv_scale: [f32; 2], settings: &OutlineSettings) {
+        let push_constants = OutlinePushConstants::new(settings, uv_scale, extent);
+
+        unsafe {
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[self.descriptor_set],
+                &[],
+            );
+            self.device.cmd_set_viewport(command_buffer, 0, &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: extent.width as f32,
+                height: extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }]);
+            self.device.cmd_set_scissor(command_buffer, 0, &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent,
+            }]);
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            self.device.cmd_draw(command_buffer, 3, 1, 0, 0); // Fullscreen triangle
+        }
+    }
+}
+
+impl Drop for OutlinePass {
+    fn drop(&mut self) {
+        debug!("Destroying selection outline pass");
+        unsafe {
+            self.device.destroy_pipeline(self.pipeline, None);
+            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            self.device.destroy_sampler(self.sampler, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_constants_clamp_thickness() {
+        let extent = vk::Extent2D { width: 800, height: 600 };
+        let thick = OutlineSettings { thickness: 100.0, ..OutlineSettings::default() };
+        let push_constants = OutlinePushConstants::new(&thick, [0.5, 0.5], extent);
+        assert_eq!(push_constants.thickness, config::outline::MAX_THICKNESS);
+        assert_eq!(push_constants.screen_size, [800.0, 600.0]);
+        assert_eq!(std::mem::size_of::<OutlinePushConstants>(), 48);
+
+        let thin = OutlineSettings { thickness: 0.0, ..OutlineSettings::default() };
+        assert_eq!(thin.clamped_thickness(), 1.0);
+    }
+}
@@ -0,0 +1,655 @@
+//! GPU particles
+//!
+//! Particles live in a device local storage buffer of
+//! `config::particles::CAPACITY` slots that is only touched by shaders. Each
+//! frame, before the scene pass, a compute dispatch (`shaders/particles.comp`)
+//! spawns the particles the emitters asked for and moves, ages and kills
+//! the rest; inside the scene pass every slot is drawn as a camera facing
+//! quad (`shaders/particles.vert`), depth tested against the SDF surfaces
+//! and meshes and blended additively into the HDR target.
+//!
+//! Slots are handed out as a ring: every emitter gets the next `count`
+//! slots for its new particles, overwriting the oldest ones once the buffer
+//! is full, so the CPU never reads back which particles died. The
+//! simulation is skipped once every particle has outlived its lifetime.
+
+use ash::vk;
+use ash::{Device, Instance};
+use cgmath::{InnerSpace, Vector3};
+use std::ffi::CStr;
+use crate::config;
+use crate::ecs::particles::EmitterSettings;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::buffer::{DynamicBuffer, GpuBuffer};
+use crate::vulkan::rendering::PassLayout;
+use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::viewport::{rect_aspect, Tile, ViewCamera};
+use log::{debug, info};
+
+/// Particle in the storage buffer (must match particles.comp and particles.vert)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+#[allow(dead_code)] // Only written by the compute shader
+struct GpuParticle {
+    /// xyz = position, w = age in seconds
+    position_age: [f32; 4],
+    /// xyz = velocity, w = lifetime in seconds (dead once the age reaches it)
+    velocity_lifetime: [f32; 4],
+    /// rgb = color when spawned, a = half size
+    start_color: [f32; 4],
+    /// rgb = color at the end of the lifetime, a = downward acceleration
+    end_color: [f32; 4],
+}
+
+/// Emitter as read by the compute shader (must match particles.comp)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct GpuEmitter {
+    /// xyz = spawn position, w = lifetime in seconds
+    pub position: [f32; 4],
+    /// xyz = direction times speed, w = spread half angle in radians
+    pub velocity: [f32; 4],
+    /// rgb = color when spawned, a = half size
+    pub start_color: [f32; 4],
+    /// rgb = color at the end of the lifetime, a = downward acceleration
+    pub end_color: [f32; 4],
+    /// x = first slot, y = number of particles to spawn this frame
+    pub slots: [u32; 4],
+}
+
+unsafe impl bytemuck::Pod for GpuEmitter {}
+unsafe impl bytemuck::Zeroable for GpuEmitter {}
+
+/// Push constants of the particle compute shader
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct SimulationPushConstants {
+    delta_seconds: f32,
+    emitter_count: u32,
+    capacity: u32,
+    /// Changes every dispatch so spawns get new random directions
+    seed: u32,
+}
+
+unsafe impl bytemuck::Pod for SimulationPushConstants {}
+unsafe impl bytemuck::Zeroable for SimulationPushConstants {}
+
+/// Push constants of the particle vertex shader
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct ParticleDrawPushConstants {
+    /// xyz = camera origin, w = orthographic half height (0 = perspective)
+    camera_origin: [f32; 4],
+    /// xyz = camera forward direction, w = roll about it in radians
+    camera_forward: [f32; 4],
+    /// Half width and height (xy) and center (zw) of the view, see `ViewCamera::lens`
+    camera_lens: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for ParticleDrawPushConstants {}
+unsafe impl bytemuck::Zeroable for ParticleDrawPushConstants {}
+
+/// Emitters collected for the current frame
+#[derive(Clone, Debug, Default)]
+pub struct ParticleEmitterList {
+    emitters: Vec<GpuEmitter>,
+    /// Time the particles are advanced by
+    delta_seconds: f32,
+}
+
+impl ParticleEmitterList {
+    /// Create an empty list advancing the particles by `delta_seconds`
+    pub fn new(delta_seconds: f32) -> Self {
+        Self { emitters: Vec::new(), delta_seconds }
+    }
+
+    /// Emitters in the list
+    pub fn emitters(&self) -> &[GpuEmitter] {
+        &self.emitters
+    }
+
+    /// Time the particles are advanced by
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta_seconds
+    }
+
+    /// Add an emitter spawning `count` particles this frame
+    ///
+    /// Emitters beyond `config::particles::MAX_EMITTERS` are dropped.
+    pub fn push(&mut self, position: Vector3<f32>, settings: &EmitterSettings, count: u32) {
+        if self.emitters.len() >= config::particles::MAX_EMITTERS {
+            return;
+        }
+        // Straight up for a zero direction
+        let direction = Vector3::from(settings.direction);
+        let [x, y, z]: [f32; 3] = if direction.magnitude2() > 0.0 { direction.normalize() } else { Vector3::unit_y() }.into();
+        let [r, g, b] = settings.start_color.map(|c| c * settings.brightness);
+        let [end_r, end_g, end_b] = settings.end_color.map(|c| c * settings.brightness);
+        self.emitters.push(GpuEmitter {
+            position: [position.x, position.y, position.z, settings.lifetime],
+            velocity: [x * settings.speed, y * settings.speed, z * settings.speed, settings.spread],
+            start_color: [r, g, b, settings.size],
+            end_color: [end_r, end_g, end_b, settings.gravity],
+            slots: [0, count, 0, 0],
+        });
+    }
+}
+
+/// Hands out particle slots as a ring and tracks how long particles may live
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SpawnRing {
+    capacity: u32,
+    /// Slot the next spawn starts at
+    cursor: u32,
+    /// Seconds until every spawned particle is dead
+    alive_seconds: f32,
+}
+
+impl SpawnRing {
+    fn new(capacity: u32) -> Self {
+        Self { capacity, cursor: 0, alive_seconds: 0.0 }
+    }
+
+    /// Reserve the slots of `count` new particles
+    ///
+    /// # Returns
+    /// The first slot; the range wraps around the end of the buffer
+    fn allocate(&mut self, count: u32, lifetime: f32) -> u32 {
+        let first = self.cursor;
+        let count = count.min(self.capacity);
+        if count > 0 {
+            self.cursor = (self.cursor + count) % self.capacity;
+            self.alive_seconds = self.alive_seconds.max(lifetime);
+        }
+        first
+    }
+
+    /// Let time pass for the live particles
+    fn advance(&mut self, delta_seconds: f32) {
+        self.alive_seconds = (self.alive_seconds - delta_seconds.max(0.0)).max(0.0);
+    }
+
+    /// Whether any particle may still be alive
+    fn is_active(&self) -> bool {
+        self.alive_seconds > 0.0
+    }
+}
+
+/// Particle storage buffer, the simulation and draw pipelines, and the emitters of the frame
+pub struct ParticlePass {
+    set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    compute_layout: vk::PipelineLayout,
+    compute_pipeline: vk::Pipeline,
+    draw_layout: vk::PipelineLayout,
+    draw_pipeline: vk::Pipeline,
+    /// One slot per particle, only accessed by shaders
+    particles: GpuBuffer,
+    /// One region of emitters per frame in flight
+    emitters: DynamicBuffer<GpuEmitter>,
+    /// Emitters simulated with the next frame
+    pending: ParticleEmitterList,
+    ring: SpawnRing,
+    /// Set until the storage buffer has been zeroed (all particles dead)
+    needs_clear: bool,
+    /// Dispatches recorded so far, the random seed
+    dispatches: u32,
+    device: Device,
+}
+
+impl ParticlePass {
+    /// Create the particle buffers and pipelines
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - Physical device for memory type lookup
+    /// * `device` - The logical device
+    /// * `pass` - Offscreen SDF pass the particles are drawn in
+    ///
+    /// # Errors
+    /// Returns an error if buffer creation, shader compilation or pipeline creation fails
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        pass: &PassLayout,
+    ) -> Result<Self> {
+        info!("Creating particle system ({} particles)", config::particles::CAPACITY);
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let particles = GpuBuffer::new(
+            device,
+            &memory_properties,
+            (config::particles::CAPACITY as usize * std::mem::size_of::<GpuParticle>()) as vk::DeviceSize,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let emitters = DynamicBuffer::new(
+            device,
+            &memory_properties,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            config::particles::MAX_EMITTERS,
+            config::vulkan::MAX_FRAMES_IN_FLIGHT,
+        )?;
+
+        // From here on dropping `particle_pass` destroys whatever was created
+        let mut particle_pass = Self {
+            set_layout: vk::DescriptorSetLayout::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_set: vk::DescriptorSet::null(),
+            compute_layout: vk::PipelineLayout::null(),
+            compute_pipeline: vk::Pipeline::null(),
+            draw_layout: vk::PipelineLayout::null(),
+            draw_pipeline: vk::Pipeline::null(),
+            particles,
+            emitters,
+            pending: ParticleEmitterList::default(),
+            ring: SpawnRing::new(config::particles::CAPACITY),
+            needs_clear: true,
+            dispatches: 0,
+            device: device.clone(),
+        };
+        particle_pass.create_descriptor_set()?;
+        particle_pass.create_compute_pipeline()?;
+        particle_pass.create_draw_pipeline(pass)?;
+        Ok(particle_pass)
+    }
+
+    /// Create the set binding the particles and this frame's emitters
+    fn create_descriptor_set(&mut self) -> Result<()> {
+        let bindings = [
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE | vk::ShaderStageFlags::VERTEX),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(1)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER_DYNAMIC)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE),
+        ];
+        self.set_layout = unsafe {
+            self.device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings), None)
+        }
+        .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create particle set layout: {:?}", e)))?;
+
+        let pool_sizes = [
+            vk::DescriptorPoolSize::default().ty(vk::DescriptorType::STORAGE_BUFFER).descriptor_count(1),
+            vk::DescriptorPoolSize::default().ty(vk::DescriptorType::STORAGE_BUFFER_DYNAMIC).descriptor_count(1),
+        ];
+        self.descriptor_pool = unsafe {
+            self.device.create_descriptor_pool(&vk::DescriptorPoolCreateInfo::default()
+                .max_sets(1)
+                .pool_sizes(&pool_sizes), None)
+        }
+        .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create particle descriptor pool: {:?}", e)))?;
+
+        let set_layouts = [self.set_layout];
+        self.descriptor_set = unsafe {
+            self.device.allocate_descriptor_sets(&vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(self.descriptor_pool)
+                .set_layouts(&set_layouts))
+        }
+        .map_err(|e| VulkanError::PipelineCreation(format!("Failed to allocate particle descriptor set: {:?}", e)))?[0];
+
+        let particle_info = [vk::DescriptorBufferInfo::default()
+            .buffer(self.particles.handle())
+            .offset(0)
+            .range(vk::WHOLE_SIZE)];
+        let emitter_info = [vk::DescriptorBufferInfo::default()
+            .buffer(self.emitters.handle())
+            .offset(0)
+            .range(self.emitters.range())];
+        let writes = [
+            vk::WriteDescriptorSet::default()
+                .dst_set(self.descriptor_set)
+                .dst_binding(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&particle_info),
+            vk::WriteDescriptorSet::default()
+                .dst_set(self.descriptor_set)
+                .dst_binding(1)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER_DYNAMIC)
+                .buffer_info(&emitter_info),
+        ];
+        unsafe { self.device.update_descriptor_sets(&writes, &[]) };
+        Ok(())
+    }
+
+    /// Create the simulation pipeline
+    fn create_compute_pipeline(&mut self) -> Result<()> {
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let code = shader_compiler.compile_file(config::shader::PARTICLE_COMPUTE_SHADER, "main", &[])?;
+        let module = unsafe { self.device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&code), None) }
+            .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create particle compute module: {:?}", e)))?;
+
+        let set_layouts = [self.set_layout];
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: std::mem::size_of::<SimulationPushConstants>() as u32,
+        }];
+        let layout = unsafe {
+            self.device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&push_constant_ranges), None)
+        };
+        let layout = match layout {
+            Ok(layout) => layout,
+            Err(e) => {
+                unsafe { self.device.destroy_shader_module(module, None) };
+                return Err(VulkanError::PipelineCreation(format!("Failed to create particle compute layout: {:?}", e)).into());
+            }
+        };
+        self.compute_layout = layout;
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let pipeline_info = vk::ComputePipelineCreateInfo::default()
+            .stage(vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::COMPUTE)
+                .module(module)
+                .name(entry_point))
+            .layout(layout);
+        let result = unsafe { self.device.create_compute_pipelines(vk::PipelineCache::null(), &[pipeline_info], None) };
+        unsafe { self.device.destroy_shader_module(module, None) };
+        self.compute_pipeline = result
+            .map_err(|(_, e)| VulkanError::PipelineCreation(format!("Failed to create particle compute pipeline: {:?}", e)))?[0];
+        Ok(())
+    }
+
+    /// Create the billboard pipeline with depth testing and additive blending
+    fn create_draw_pipeline(&mut self, pass: &PassLayout) -> Result<()> {
+        let mut shader_compiler = ShaderCompiler::new()?;
+        let vert_code = shader_compiler.compile_file(config::shader::PARTICLE_VERTEX_SHADER, "main", &[])?;
+        let frag_code = shader_compiler.compile_file(config::shader::PARTICLE_FRAGMENT_SHADER, "main", &[])?;
+
+        let (vert_module, frag_module) = unsafe {
+            let vert = self.device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&vert_code), None)
+                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create particle vertex module: {:?}", e)))?;
+            let frag = match self.device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&frag_code), None) {
+                Ok(frag) => frag,
+                Err(e) => {
+                    self.device.destroy_shader_module(vert, None);
+                    return Err(VulkanError::ShaderCompilation(format!("Failed to create particle fragment module: {:?}", e)).into());
+                }
+            };
+            (vert, frag)
+        };
+
+        let entry_point = unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) };
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_module)
+                .name(entry_point),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_module)
+                .name(entry_point),
+        ];
+
+        // Quads are built from the vertex and instance index, without vertex buffers
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(config::rendering::LINE_WIDTH)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        // Hidden behind surfaces, but particles do not occlude each other
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(true)
+            .depth_write_enable(false)
+            .depth_compare_op(vk::CompareOp::LESS);
+        // Additive in color, leaving the alpha of the scene untouched; the shader adds
+        // zero to the G-buffer attachments
+        let color_blend_attachments = pass.color_blend_attachments(vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::ONE)
+            .dst_color_blend_factor(vk::BlendFactor::ONE)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+            .alpha_blend_op(vk::BlendOp::ADD));
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachments);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::default()
+            .dynamic_states(&dynamic_states);
+
+        let set_layouts = [self.set_layout];
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: std::mem::size_of::<ParticleDrawPushConstants>() as u32,
+        }];
+        let layout = unsafe {
+            self.device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&push_constant_ranges), None)
+        };
+        let layout = match layout {
+            Ok(layout) => layout,
+            Err(e) => {
+                unsafe {
+                    self.device.destroy_shader_module(vert_module, None);
+                    self.device.destroy_shader_module(frag_module, None);
+                }
+                return Err(VulkanError::PipelineCreation(format!("Failed to create particle pipeline layout: {:?}", e)).into());
+            }
+        };
+        self.draw_layout = layout;
+
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(layout), &mut rendering);
+
+        let result = unsafe { self.device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None) };
+        unsafe {
+            self.device.destroy_shader_module(vert_module, None);
+            self.device.destroy_shader_module(frag_module, None);
+        }
+        self.draw_pipeline = result
+            .map_err(|(_, e)| VulkanError::PipelineCreation(format!("Failed to create particle pipeline: {:?}", e)))?[0];
+        Ok(())
+    }
+
+    /// Set the emitters simulated with the next frame
+    ///
+    /// # Arguments
+    /// * `emitters` - Emitters collected from the ECS world
+    pub fn set_emitters(&mut self, emitters: ParticleEmitterList) {
+        self.pending = emitters;
+    }
+
+    /// Whether any particle may be alive, i.e. whether there is anything to draw
+    pub fn is_active(&self) -> bool {
+        self.ring.is_active()
+    }
+
+    /// Record the simulation step, outside of any render pass
+    ///
+    /// Takes the pending emitters, so a frame recorded twice does not spawn
+    /// its particles twice. Must be called after waiting on the frame's
+    /// in-flight fence, since the frame's emitter region is written here.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer outside of any render pass
+    /// * `frame` - Frame in flight, selecting the emitter region
+    pub fn record_simulation(&mut self, command_buffer: vk::CommandBuffer, frame: usize) {
+        let list = std::mem::take(&mut self.pending);
+        let mut emitters = list.emitters;
+        for emitter in &mut emitters {
+            emitter.slots[0] = self.ring.allocate(emitter.slots[1], emitter.position[3]);
+        }
+        let emitter_count = self.emitters.write(frame, &emitters) as u32;
+
+        if !self.ring.is_active() {
+            return;
+        }
+        self.ring.advance(list.delta_seconds);
+
+        unsafe {
+            if self.needs_clear {
+                self.device.cmd_fill_buffer(command_buffer, self.particles.handle(), 0, vk::WHOLE_SIZE, 0);
+                self.needs_clear = false;
+            }
+            // Last frame's draws read the slots about to be written; a fill writes all of them
+            let barrier = vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER | vk::PipelineStageFlags::VERTEX_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[barrier],
+                &[],
+                &[],
+            );
+
+            let push_constants = SimulationPushConstants {
+                delta_seconds: list.delta_seconds.max(0.0),
+                emitter_count,
+                capacity: config::particles::CAPACITY,
+                seed: self.dispatches,
+            };
+            self.dispatches = self.dispatches.wrapping_add(1);
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, self.compute_pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.compute_layout,
+                0,
+                &[self.descriptor_set],
+                &[self.emitters.offset(frame) as u32],
+            );
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.compute_layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            self.device.cmd_dispatch(command_buffer, config::particles::CAPACITY.div_ceil(config::particles::WORKGROUP_SIZE), 1, 1);
+
+            let barrier = vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ);
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::VERTEX_SHADER,
+                vk::DependencyFlags::empty(),
+                &[barrier],
+                &[],
+                &[],
+            );
+        }
+        debug!("Particle simulation: {} emitters", emitter_count);
+    }
+
+    /// Record the particle draw for a viewport into the current scene pass
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the offscreen SDF render pass
+    /// * `frame` - Frame in flight the simulation was recorded for
+    /// * `rect` - Viewport rectangle in the rendered image
+    /// * `tile` - Area of the image the target covers
+    /// * `camera` - Camera of the viewport
+    pub fn record(&self, command_buffer: vk::CommandBuffer, frame: usize, rect: vk::Rect2D, tile: &Tile, camera: &ViewCamera) {
+        let Some(scissor) = tile.scissor(rect).filter(|_| self.ring.is_active()) else {
+            return;
+        };
+
+        let push_constants = ParticleDrawPushConstants {
+            camera_origin: camera.origin_vec4(),
+            camera_forward: camera.forward_vec4(),
+            camera_lens: camera.lens(rect_aspect(&rect)),
+        };
+
+        unsafe {
+            self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.draw_pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.draw_layout,
+                0,
+                &[self.descriptor_set],
+                &[self.emitters.offset(frame) as u32],
+            );
+            self.device.cmd_set_viewport(command_buffer, 0, &[tile.viewport(&rect)]);
+            self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.draw_layout,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            // Six vertices per quad; dead particles collapse to a point
+            self.device.cmd_draw(command_buffer, 6, config::particles::CAPACITY, 0, 0);
+        }
+    }
+}
+
+impl Drop for ParticlePass {
+    fn drop(&mut self) {
+        debug!("Destroying particle system");
+        unsafe {
+            self.device.destroy_pipeline(self.draw_pipeline, None);
+            self.device.destroy_pipeline_layout(self.draw_layout, None);
+            self.device.destroy_pipeline(self.compute_pipeline, None);
+            self.device.destroy_pipeline_layout(self.compute_layout, None);
+            self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device.destroy_descriptor_set_layout(self.set_layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emitter_layout_matches_shader() {
+        assert_eq!(std::mem::size_of::<GpuParticle>(), 64);
+        assert_eq!(std::mem::size_of::<GpuEmitter>(), 80);
+        assert_eq!(std::mem::size_of::<SimulationPushConstants>(), 16);
+    }
+
+    #[test]
+    fn test_spawn_ring_wraps_and_expires() {
+        let mut ring = SpawnRing::new(8);
+        assert!(!ring.is_active());
+        assert_eq!(ring.allocate(0, 5.0), 0);
+        assert!(!ring.is_active());
+
+        assert_eq!(ring.allocate(6, 1.0), 0);
+        assert_eq!(ring.allocate(4, 2.0), 6);
+        assert_eq!(ring.allocate(20, 0.5), 2);
+        assert_eq!(ring.cursor, 2);
+
+        ring.advance(1.5);
+        assert!(ring.is_active());
+        ring.advance(0.5);
+        assert!(!ring.is_active());
+    }
+}
@@ -1,30 +1,72 @@
 use ash::vk;
 use ash::Device;
+use std::collections::HashMap;
 use std::ffi::CStr;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use crate::error::{Result, VulkanError};
 use crate::config;
-use crate::vulkan::shader_compiler::ShaderCompiler;
+use crate::vulkan::shader_compiler::{ShaderCompileSettings, ShaderCompiler};
+use crate::vulkan::shader_annotations::ShaderAnnotations;
+use crate::vulkan::shader_reflection::{self, UniformBlock};
+use crate::vulkan::push_constants::SdfPushConstants;
+use crate::vulkan::quality::{RaymarchQuality, RaymarchSpecialization};
+use crate::vulkan::shader_variant::ShaderVariantKey;
+use crate::vulkan::pipeline_validator::PipelineValidator;
+use crate::vulkan::split_compare::CompareSource;
+use crate::vulkan::depth::DepthUsage;
+use crate::vulkan::gbuffer;
+use crate::vulkan::rendering::{PassLayout, RenderingMode};
 use log::{debug, info, warn};
 
 /// Vulkan pipeline wrapper with proper resource management
 ///
-/// This struct manages Vulkan render pass, pipeline layout, and graphics pipeline,
+/// This struct manages the scene and present passes, pipeline layout, and graphics pipeline,
 /// ensuring proper cleanup and providing debugging capabilities.
 #[derive(Clone)]
 pub struct VulkanPipeline {
-    /// The render pass
-    pub render_pass: vk::RenderPass,
+    /// The present pass (swapchain color plus depth) for the composite, HUD and debug lines
+    pub pass: PassLayout,
+    
+    /// The linear HDR scene pass the SDF pipelines are built against
+    pub scene_pass: PassLayout,
     
     /// The pipeline layout
     pub pipeline_layout: vk::PipelineLayout,
     
+    /// Descriptor set layout for the scene uniform buffer (set 0)
+    pub descriptor_set_layout: vk::DescriptorSetLayout,
+    
     /// The graphics pipeline
     pub graphics_pipeline: vk::Pipeline,
     
     /// The device reference for cleanup
     pub device: Device,
     
+    /// Raymarch quality baked into the pipeline via specialization constants
+    pub quality: RaymarchQuality,
+    
+    /// Shader variant (define set) of the active pipeline
+    pub variant: ShaderVariantKey,
+    
+    /// Pipelines built for the current quality tier, one per variant (includes the active one)
+    variants: HashMap<ShaderVariantKey, (vk::PipelineLayout, vk::Pipeline)>,
+    
+    /// Active pipeline from before the last successful hot reload, for split comparison
+    last_good: Option<(vk::PipelineLayout, vk::Pipeline)>,
+    
+    /// Pipeline of the active variant at another quality tier, for split comparison
+    quality_comparison: Option<(RaymarchQuality, ShaderVariantKey, vk::PipelineLayout, vk::Pipeline)>,
+    
+    /// Replaced pipelines that frames in flight may still use, handed to the renderer's deletion queue
+    retired: Vec<(vk::PipelineLayout, vk::Pipeline)>,
+    
+    /// Safe-mode validator for reloaded pipelines, installed by the renderer
+    validator: Option<Arc<PipelineValidator>>,
+    
+    /// Validate reloaded pipelines before swapping them in
+    pub safe_mode: bool,
+    
     /// Shader compiler for runtime compilation
     #[allow(dead_code)]
     shader_compiler: Arc<Mutex<ShaderCompiler>>,
@@ -36,13 +78,14 @@ impl VulkanPipeline {
     /// # Arguments
     /// * `device` - The Vulkan device
     /// * `swapchain_format` - The swapchain image format
+    /// * `rendering_mode` - Render pass objects or dynamic rendering
     ///
     /// # Returns
     /// A new VulkanPipeline instance
     ///
     /// # Errors
     /// Returns an error if pipeline creation fails
-    pub fn new(device: &Device, swapchain_format: vk::Format) -> Result<Self> {
+    pub fn new(device: &Device, swapchain_format: vk::Format, rendering_mode: RenderingMode) -> Result<Self> {
         info!("Creating Vulkan pipeline");
         
         // Initialize shader compiler
@@ -72,76 +115,100 @@ impl VulkanPipeline {
             }
         }
         
-        let render_pass = Self::create_render_pass(device, swapchain_format)?;
-        debug!("Render pass created successfully");
+        let pass = PassLayout::new(device, rendering_mode, swapchain_format, vk::ImageLayout::PRESENT_SRC_KHR, DepthUsage::Discard, "present")?;
+        debug!("Present pass created with {}", rendering_mode.name());
+        
+        // The SDF scene is drawn into offscreen HDR targets that the composite pass samples
+        let scene_pass = PassLayout::with_color_formats(
+            device,
+            rendering_mode,
+            &gbuffer::pass_formats(config::rendering::SCENE_FORMAT),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            DepthUsage::Discard,
+            "offscreen",
+        )?;
+        
+        let descriptor_set_layout = Self::create_descriptor_set_layout(device)?;
+        debug!("Descriptor set layout created successfully");
         
-        let (pipeline_layout, graphics_pipeline) = Self::create_graphics_pipeline(device, render_pass, &mut shader_compiler)?;
+        let quality = RaymarchQuality::default();
+        let variant = ShaderVariantKey::sdf_default();
+        let (pipeline_layout, graphics_pipeline) = Self::create_graphics_pipeline(device, &scene_pass, descriptor_set_layout, quality, &variant, &mut shader_compiler)?;
         debug!("Graphics pipeline created successfully");
         
         info!("Vulkan pipeline created successfully with runtime shader compilation");
         
         Ok(Self {
-            render_pass,
+            pass,
+            scene_pass,
             pipeline_layout,
+            descriptor_set_layout,
             graphics_pipeline,
             device: device.clone(), // Clone device for cleanup
+            quality,
+            variants: HashMap::from([(variant.clone(), (pipeline_layout, graphics_pipeline))]),
+            variant,
+            last_good: None,
+            quality_comparison: None,
+            retired: Vec::new(),
+            validator: None,
+            safe_mode: config::hot_reload::SAFE_MODE,
             shader_compiler: Arc::new(Mutex::new(shader_compiler)),
         })
     }
     
-    /// Create a render pass
+    /// Create the descriptor set layout for the scene uniform buffer
     ///
     /// # Arguments
     /// * `device` - The Vulkan device
-    /// * `format` - The swapchain image format
     ///
     /// # Returns
-    /// The created render pass
+    /// The created descriptor set layout
     ///
     /// # Errors
-    /// Returns an error if render pass creation fails
-    fn create_render_pass(device: &Device, format: vk::Format) -> Result<vk::RenderPass> {
-        debug!("Creating render pass with format: {:?}", format);
-        
-        let color_attachment = vk::AttachmentDescription::default()
-            .format(format)
-            .samples(vk::SampleCountFlags::TYPE_1)
-            .load_op(vk::AttachmentLoadOp::CLEAR)
-            .store_op(vk::AttachmentStoreOp::STORE)
-            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-            .initial_layout(vk::ImageLayout::UNDEFINED)
-            .final_layout(vk::ImageLayout::PRESENT_SRC_KHR);
-        
-        let color_attachment_ref = vk::AttachmentReference::default()
-            .attachment(0)
-            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
-        
-        let color_attachment_refs = [color_attachment_ref];
-        let subpass = vk::SubpassDescription::default()
-            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-            .color_attachments(&color_attachment_refs);
-        
-        let attachments = [color_attachment];
-        let subpasses = [subpass];
-        let render_pass_info = vk::RenderPassCreateInfo::default()
-            .attachments(&attachments)
-            .subpasses(&subpasses);
-        
-        let render_pass = unsafe {
-            device.create_render_pass(&render_pass_info, None)
-                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create render pass: {:?}", e)))?
+    /// Returns an error if descriptor set layout creation fails
+    fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout> {
+        let scene_binding = vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+        let environment_binding = vk::DescriptorSetLayoutBinding::default()
+            .binding(1)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+        let params_binding = vk::DescriptorSetLayoutBinding::default()
+            .binding(config::shader_params::BINDING)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+        let text_atlas_binding = vk::DescriptorSetLayoutBinding::default()
+            .binding(config::text::ATLAS_BINDING)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+        
+        let bindings = [scene_binding, environment_binding, params_binding, text_atlas_binding];
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::default()
+            .bindings(&bindings);
+        
+        let descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(&layout_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create descriptor set layout: {:?}", e)))?
         };
         
-        debug!("Render pass created successfully");
-        Ok(render_pass)
+        Ok(descriptor_set_layout)
     }
     
     /// Create a graphics pipeline
     ///
     /// # Arguments
     /// * `device` - The Vulkan device
-    /// * `render_pass` - The render pass
+    /// * `pass` - Pass the pipeline is drawn in
+    /// * `descriptor_set_layout` - Layout of the scene descriptor set
+    /// * `quality` - Raymarch quality tier for the specialization constants
+    /// * `variant` - Defines the fragment shader is compiled with
     ///
     /// # Returns
     /// A tuple of (pipeline_layout, graphics_pipeline)
@@ -150,7 +217,10 @@ impl VulkanPipeline {
     /// Returns an error if pipeline creation fails
     fn create_graphics_pipeline(
         device: &Device,
-        render_pass: vk::RenderPass,
+        pass: &PassLayout,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        quality: RaymarchQuality,
+        variant: &ShaderVariantKey,
         shader_compiler: &mut ShaderCompiler
     ) -> Result<(vk::PipelineLayout, vk::Pipeline)> {
         debug!("Creating graphics pipeline with runtime shader compilation");
@@ -158,13 +228,17 @@ impl VulkanPipeline {
         // Compile shaders at runtime
         let vert_shader_code = shader_compiler.compile_file(
             config::shader::SDF_VERTEX_SHADER,
-            "main"
+            "main",
+            &[]
         )?;
         
+        // Shading features are selected with preprocessor defines
         let frag_shader_code = shader_compiler.compile_file(
             config::shader::SDF_FRAGMENT_SHADER,
-            "main"
+            "main",
+            &variant.defines()
         )?;
+        debug!("Using shader variant: {}", variant.name());
         
         debug!("Compiled vertex shader ({} words)", vert_shader_code.len());
         debug!("Compiled fragment shader ({} words)", frag_shader_code.len());
@@ -181,10 +255,19 @@ impl VulkanPipeline {
             .module(vert_shader_module)
             .name(unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) });
         
+        // Raymarch quality is provided through specialization constants
+        let specialization = quality.specialization();
+        let specialization_entries = RaymarchSpecialization::map_entries();
+        let specialization_info = vk::SpecializationInfo::default()
+            .map_entries(&specialization_entries)
+            .data(bytemuck::bytes_of(&specialization));
+        debug!("Using {} raymarch quality: {:?}", quality.name(), specialization);
+        
         let frag_stage = vk::PipelineShaderStageCreateInfo::default()
             .stage(vk::ShaderStageFlags::FRAGMENT)
             .module(frag_shader_module)
-            .name(unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) });
+            .name(unsafe { CStr::from_bytes_with_nul_unchecked(config::shader::ENTRY_POINT) })
+            .specialization_info(&specialization_info);
         
         let shader_stages = [vert_stage, frag_stage];
         
@@ -216,26 +299,34 @@ impl VulkanPipeline {
             .sample_shading_enable(false)
             .rasterization_samples(vk::SampleCountFlags::TYPE_1);
         
-        // Color blending
+        // Depth: every fragment writes the depth of its ray hit (gl_FragDepth)
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(vk::CompareOp::ALWAYS);
+        
+        // Color blending, the same for the color and the G-buffer attachments
         let color_blend_attachment_state = vk::PipelineColorBlendAttachmentState::default()
             .color_write_mask(vk::ColorComponentFlags::R | vk::ColorComponentFlags::G | vk::ColorComponentFlags::B | vk::ColorComponentFlags::A)
             .blend_enable(false);
         
-        let color_blend_attachments = [color_blend_attachment_state];
+        let color_blend_attachments = pass.color_blend_attachments(color_blend_attachment_state);
         let color_blending = vk::PipelineColorBlendStateCreateInfo::default()
             .attachments(&color_blend_attachments);
         
         // Push constant range for window data (both vertex and fragment shaders)
-        // Updated to match the actual push constant block size in the fragment shader (52 bytes)
+        // Sized from SdfPushConstants, which mirrors the fragment shader push constant block
         let push_constant_range = vk::PushConstantRange {
             stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
             offset: 0,
-            size: 52, // Updated to match fragment shader push constant block size
+            size: SdfPushConstants::SIZE,
         };
         let push_constant_ranges = [push_constant_range];
         
-        // Pipeline layout with push constants
+        // Pipeline layout with the scene descriptor set and push constants
+        let set_layouts = [descriptor_set_layout];
         let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default()
+            .set_layouts(&set_layouts)
             .push_constant_ranges(&push_constant_ranges);
         let pipeline_layout = unsafe {
             device.create_pipeline_layout(&pipeline_layout_info, None)
@@ -248,18 +339,18 @@ impl VulkanPipeline {
             .dynamic_states(&dynamic_states);
         
         // Graphics pipeline
-        let pipeline_info = vk::GraphicsPipelineCreateInfo::default()
+        let mut rendering = pass.rendering_info();
+        let pipeline_info = pass.attach(vk::GraphicsPipelineCreateInfo::default()
             .stages(&shader_stages)
             .vertex_input_state(&vertex_input_info)
             .input_assembly_state(&input_assembly)
             .viewport_state(&viewport_state)
             .rasterization_state(&rasterizer)
             .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
             .color_blend_state(&color_blending)
             .dynamic_state(&dynamic_state)
-            .layout(pipeline_layout)
-            .render_pass(render_pass)
-            .subpass(0);
+            .layout(pipeline_layout), &mut rendering);
         
         let graphics_pipeline = unsafe {
             let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None);
@@ -311,6 +402,40 @@ impl VulkanPipeline {
         Ok(shader_module)
     }
     
+    /// Install the validator used to test reloaded pipelines before swapping
+    ///
+    /// # Arguments
+    /// * `validator` - Validator sharing this pipeline's descriptor set layout
+    pub fn set_validator(&mut self, validator: PipelineValidator) {
+        self.validator = Some(Arc::new(validator));
+    }
+    
+    /// Test a freshly built pipeline with a validation draw (safe mode)
+    ///
+    /// On failure the candidate is destroyed, so the caller can keep the
+    /// active pipeline. The validator waits for its own submission, so
+    /// frames in flight are not affected.
+    ///
+    /// # Errors
+    /// Returns the validation error if the test draw failed
+    fn validate_candidate(&self, pipeline_layout: vk::PipelineLayout, graphics_pipeline: vk::Pipeline) -> Result<()> {
+        let Some(validator) = self.validator.as_ref().filter(|_| self.safe_mode) else {
+            return Ok(());
+        };
+        
+        if let Err(e) = validator.validate(pipeline_layout, graphics_pipeline) {
+            warn!("Reloaded pipeline rejected, keeping the previous one: {}", e);
+            unsafe {
+                self.device.destroy_pipeline(graphics_pipeline, None);
+                self.device.destroy_pipeline_layout(pipeline_layout, None);
+            }
+            return Err(e);
+        }
+        
+        info!("Reloaded pipeline passed the validation draw");
+        Ok(())
+    }
+    
     /// Recompile shaders and recreate the pipeline
     ///
     /// This method allows for hot-reloading of shaders during development
@@ -321,29 +446,13 @@ impl VulkanPipeline {
     ///
     /// # Errors
     /// Returns an error if shader compilation or pipeline recreation fails
-    #[allow(dead_code)]
     pub fn recompile_shaders(&mut self) -> Result<()> {
         info!("Recompiling shaders and recreating pipeline");
         
         // Clear shader cache to force recompilation
         self.shader_compiler.lock().unwrap().clear_cache();
         
-        // Recreate the graphics pipeline with fresh shaders
-        let (pipeline_layout, graphics_pipeline) = Self::create_graphics_pipeline(
-            &self.device,
-            self.render_pass,
-            &mut self.shader_compiler.lock().unwrap()
-        )?;
-        
-        // Clean up old pipeline and layout
-        unsafe {
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
-        }
-        
-        // Update with new pipeline
-        self.pipeline_layout = pipeline_layout;
-        self.graphics_pipeline = graphics_pipeline;
+        self.rebuild_active_variant()?;
         
         info!("Shader recompilation completed successfully");
         Ok(())
@@ -352,68 +461,317 @@ impl VulkanPipeline {
     /// Recompile a specific shader and recreate the pipeline
     ///
     /// This method allows for hot-reloading of a specific shader during development
-    /// Note: This method should only be called when no rendering is in progress
+    /// Note: This method must be called between frames, not while recording;
+    /// replaced pipelines are retired, see `take_retired`
     /// CRITICAL: Command buffers must be recreated after calling this method
     ///
+    /// Every variant built from the changed file is invalidated. The active
+    /// variant is rebuilt immediately, the others on their next use.
+    ///
     /// # Arguments
     /// * `shader_path` - Path to the shader file that was changed
     ///
     /// # Returns
-    /// Ok(()) if recompilation succeeded
-    /// Err if recompilation failed
+    /// Ok(true) if the pipeline was recreated, Ok(false) if the file is not
+    /// used by this pipeline
     ///
     /// # Errors
-    /// Returns an error if shader compilation or pipeline recreation fails
-    pub fn recompile_shader(&mut self, shader_path: &str) -> Result<()> {
+    /// Returns an error if shader compilation or pipeline recreation fails, or
+    /// if the new pipeline fails its safe-mode validation draw; in every case
+    /// the previous pipeline stays active
+    pub fn recompile_shader(&mut self, shader_path: &str) -> Result<bool> {
         info!("Recompiling shader {} and recreating pipeline", shader_path);
 
-        // Clear specific shader from cache to force recompilation
-        // For now, we'll clear the entire cache and recreate the pipeline
-        self.shader_compiler.lock().unwrap().clear_cache();
+        // Drop every compiled define set of this file; an include file may
+        // be part of any shader, so then everything is dropped
+        if ShaderCompiler::is_include_file(Path::new(shader_path)) {
+            self.shader_compiler.lock().unwrap().clear_cache();
+        } else {
+            self.shader_compiler.lock().unwrap().invalidate_file(shader_path);
+        }
 
-        // CRITICAL: Wait for GPU to complete ALL work before pipeline destruction
-        // This is the most important synchronization point
-        info!("Waiting for GPU to complete all work before pipeline recreation");
-        unsafe {
-            self.device.device_wait_idle()
-                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to wait for device idle: {:?}", e)))?;
+        if !Self::uses_shader(shader_path) {
+            info!("Shader {} is not used by the SDF pipeline, nothing to recreate", shader_path);
+            return Ok(false);
         }
-        info!("GPU idle confirmed, safe to proceed with pipeline recreation");
 
+        self.rebuild_active_variant()?;
+
+        info!("Shader recompilation completed successfully for: {}", shader_path);
+        info!("IMPORTANT: All command buffers must be recreated immediately");
+        Ok(true)
+    }
+    
+    /// Check whether a shader file is one of the SDF pipeline's stages, or an
+    /// include file they may use
+    fn uses_shader(shader_path: &str) -> bool {
+        if ShaderCompiler::is_include_file(Path::new(shader_path)) {
+            return true;
+        }
+        let file_name = Path::new(shader_path).file_name();
+        [config::shader::SDF_VERTEX_SHADER, config::shader::SDF_FRAGMENT_SHADER]
+            .iter()
+            .any(|path| Path::new(path).file_name() == file_name)
+    }
+    
+    /// Rebuild the active variant from source and drop all other variants
+    ///
+    /// # Errors
+    /// Returns an error if pipeline creation or the safe-mode validation draw
+    /// fails; the previous pipelines are left untouched in that case
+    fn rebuild_active_variant(&mut self) -> Result<()> {
         // Recreate the graphics pipeline with fresh shaders
         let (pipeline_layout, graphics_pipeline) = Self::create_graphics_pipeline(
             &self.device,
-            self.render_pass,
+            &self.scene_pass,
+            self.descriptor_set_layout,
+            self.quality,
+            &self.variant,
             &mut self.shader_compiler.lock().unwrap()
         )?;
 
-        // Store old handles for cleanup after pipeline creation succeeds
-        let old_pipeline_layout = self.pipeline_layout;
-        let old_graphics_pipeline = self.graphics_pipeline;
+        // Safe mode: test the new pipeline before it replaces the old one
+        self.validate_candidate(pipeline_layout, graphics_pipeline)?;
 
-        // Update with new pipeline first (this is the critical change)
-        self.pipeline_layout = pipeline_layout;
-        self.graphics_pipeline = graphics_pipeline;
-
-        // Wait one more time to ensure no command buffers are still submitting to old pipeline
-        info!("Final GPU sync before destroying old pipeline");
-        unsafe {
-            self.device.device_wait_idle()
-                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to wait for device idle before cleanup: {:?}", e)))?;
-        }
-
-        // Clean up old pipeline and layout ONLY after double-sync
-        // This ensures absolutely no command buffers are using the old pipeline
-        unsafe {
-            self.device.destroy_pipeline(old_graphics_pipeline, None);
-            self.device.destroy_pipeline_layout(old_pipeline_layout, None);
+        // Keep the old active pipeline for split comparison; all other variants
+        // were built from the old source and are rebuilt on demand. Frames in
+        // flight may still use them, so they are retired instead of destroyed.
+        let previous = self.variants.remove(&self.variant);
+        self.retire_variants();
+        self.retire_quality_comparison();
+        if let Some(replaced) = std::mem::replace(&mut self.last_good, previous) {
+            self.retired.push(replaced);
         }
-
-        info!("Shader recompilation completed successfully for: {}", shader_path);
-        info!("IMPORTANT: All command buffers must be recreated immediately");
+        self.activate_variant(self.variant.clone(), pipeline_layout, graphics_pipeline);
         Ok(())
     }
     
+    /// Switch the raymarch quality tier and recreate the pipeline
+    ///
+    /// Note: This method must be called between frames, not while recording;
+    /// replaced pipelines are retired, see `take_retired`
+    /// CRITICAL: Command buffers must be recreated after calling this method
+    ///
+    /// # Arguments
+    /// * `quality` - The new quality tier
+    ///
+    /// # Returns
+    /// Ok(true) if the pipeline was recreated, Ok(false) if the tier was unchanged
+    ///
+    /// # Errors
+    /// Returns an error if pipeline recreation fails; the old pipeline stays active
+    pub fn set_quality(&mut self, quality: RaymarchQuality) -> Result<bool> {
+        if quality == self.quality {
+            return Ok(false);
+        }
+        
+        info!("Switching raymarch quality from {} to {}", self.quality.name(), quality.name());
+        
+        let (pipeline_layout, graphics_pipeline) = Self::create_graphics_pipeline(
+            &self.device,
+            &self.scene_pass,
+            self.descriptor_set_layout,
+            quality,
+            &self.variant,
+            &mut self.shader_compiler.lock().unwrap()
+        )?;
+        
+        // Every variant was specialized for the old tier
+        self.retire_variants();
+        self.activate_variant(self.variant.clone(), pipeline_layout, graphics_pipeline);
+        self.quality = quality;
+        
+        info!("Raymarch quality set to {}", quality.name());
+        Ok(true)
+    }
+    
+    /// Switch the shader variant
+    ///
+    /// Variants built earlier for the current quality tier are reused from the
+    /// variant map; new ones are compiled and kept for later switches.
+    /// CRITICAL: Command buffers must be recreated after calling this method
+    ///
+    /// # Arguments
+    /// * `variant` - Defines of the new variant
+    ///
+    /// # Returns
+    /// Ok(true) if the active pipeline changed, Ok(false) if the variant was unchanged
+    ///
+    /// # Errors
+    /// Returns an error if pipeline creation fails; the old pipeline stays active
+    pub fn set_variant(&mut self, variant: ShaderVariantKey) -> Result<bool> {
+        if variant == self.variant {
+            return Ok(false);
+        }
+        
+        let (pipeline_layout, graphics_pipeline) = match self.variants.get(&variant) {
+            Some(&built) => {
+                info!("Switching to cached shader variant: {}", variant.name());
+                built
+            }
+            None => {
+                info!("Building shader variant: {}", variant.name());
+                Self::create_graphics_pipeline(
+                    &self.device,
+                    &self.scene_pass,
+                    self.descriptor_set_layout,
+                    self.quality,
+                    &variant,
+                    &mut self.shader_compiler.lock().unwrap()
+                )?
+            }
+        };
+        
+        // The previous variant stays in the map; command buffers are rerecorded every frame
+        self.activate_variant(variant, pipeline_layout, graphics_pipeline);
+        Ok(true)
+    }
+    
+    /// Reflect the uniform and push constant blocks of the active fragment shader
+    ///
+    /// Compiles the shader with the active variant's defines, which the
+    /// shader cache usually answers without compiling again.
+    ///
+    /// # Returns
+    /// The blocks and the UI annotations of their members
+    ///
+    /// # Errors
+    /// Returns an error if the shader cannot be compiled or its SPIR-V cannot be read
+    pub fn reflect_fragment_shader(&self) -> Result<(Vec<UniformBlock>, ShaderAnnotations)> {
+        let mut compiler = self.shader_compiler.lock().unwrap();
+        let spirv = compiler.compile_file(config::shader::SDF_FRAGMENT_SHADER, "main", &self.variant.defines())?;
+        let annotations = compiler.annotations(config::shader::SDF_FRAGMENT_SHADER).cloned().unwrap_or_default();
+        Ok((shader_reflection::uniform_blocks(&spirv)?, annotations))
+    }
+    
+    /// Number of pipelines currently built, one per variant
+    #[allow(dead_code)]
+    pub fn variant_count(&self) -> usize {
+        self.variants.len()
+    }
+    
+    /// Build the pipeline a split comparison needs, if it does not exist yet
+    ///
+    /// A replaced quality comparison pipeline is retired, see `take_retired`.
+    ///
+    /// # Arguments
+    /// * `source` - Pipeline to draw right of the divider
+    ///
+    /// # Errors
+    /// Returns an error if pipeline creation fails
+    pub fn prepare_comparison(&mut self, source: &CompareSource) -> Result<()> {
+        match source {
+            CompareSource::LastGood => Ok(()),
+            CompareSource::Variant(variant) => {
+                if !self.variants.contains_key(variant) {
+                    info!("Building comparison shader variant: {}", variant.name());
+                    let built = Self::create_graphics_pipeline(
+                        &self.device,
+                        &self.scene_pass,
+                        self.descriptor_set_layout,
+                        self.quality,
+                        variant,
+                        &mut self.shader_compiler.lock().unwrap()
+                    )?;
+                    self.variants.insert(variant.clone(), built);
+                }
+                Ok(())
+            }
+            CompareSource::Quality(quality) => {
+                if *quality == self.quality {
+                    return Ok(());
+                }
+                if let Some((built_quality, ref built_variant, _, _)) = self.quality_comparison {
+                    if built_quality == *quality && *built_variant == self.variant {
+                        return Ok(());
+                    }
+                }
+                
+                info!("Building comparison pipeline at {} quality", quality.name());
+                let (pipeline_layout, graphics_pipeline) = Self::create_graphics_pipeline(
+                    &self.device,
+                    &self.scene_pass,
+                    self.descriptor_set_layout,
+                    *quality,
+                    &self.variant,
+                    &mut self.shader_compiler.lock().unwrap()
+                )?;
+                
+                // The old comparison pipeline may still be used by frames in flight
+                self.retire_quality_comparison();
+                self.quality_comparison = Some((*quality, self.variant.clone(), pipeline_layout, graphics_pipeline));
+                Ok(())
+            }
+        }
+    }
+    
+    /// Pipeline to draw right of the divider in a split comparison
+    ///
+    /// # Arguments
+    /// * `source` - Pipeline to draw right of the divider
+    ///
+    /// # Returns
+    /// The pipeline layout and pipeline, or None if the source has not been
+    /// built (see `prepare_comparison`) or, for `LastGood`, no reload happened yet
+    pub fn comparison(&self, source: &CompareSource) -> Option<(vk::PipelineLayout, vk::Pipeline)> {
+        match source {
+            CompareSource::LastGood => self.last_good,
+            CompareSource::Variant(variant) => self.variants.get(variant).copied(),
+            CompareSource::Quality(quality) if *quality == self.quality => Some((self.pipeline_layout, self.graphics_pipeline)),
+            CompareSource::Quality(quality) => self.quality_comparison
+                .as_ref()
+                .filter(|(built_quality, built_variant, _, _)| built_quality == quality && *built_variant == self.variant)
+                .map(|&(_, _, pipeline_layout, graphics_pipeline)| (pipeline_layout, graphics_pipeline)),
+        }
+    }
+    
+    /// Retire the quality comparison pipeline, if any
+    fn retire_quality_comparison(&mut self) {
+        if let Some((_, _, pipeline_layout, graphics_pipeline)) = self.quality_comparison.take() {
+            self.retired.push((pipeline_layout, graphics_pipeline));
+        }
+    }
+    
+    /// Take the pipelines replaced since the last call
+    ///
+    /// The renderer queues them for deletion once every frame submitted so
+    /// far has completed, instead of waiting for the device to go idle.
+    ///
+    /// # Returns
+    /// The retired (layout, pipeline) pairs
+    pub fn take_retired(&mut self) -> Vec<(vk::PipelineLayout, vk::Pipeline)> {
+        std::mem::take(&mut self.retired)
+    }
+    
+    /// Make a built pipeline the active one and record it in the variant map
+    fn activate_variant(&mut self, variant: ShaderVariantKey, pipeline_layout: vk::PipelineLayout, graphics_pipeline: vk::Pipeline) {
+        self.pipeline_layout = pipeline_layout;
+        self.graphics_pipeline = graphics_pipeline;
+        self.variants.insert(variant.clone(), (pipeline_layout, graphics_pipeline));
+        self.variant = variant;
+    }
+    
+    /// Retire every built variant, including the active one
+    ///
+    /// Must be followed by `activate_variant` unless the pipeline is being dropped.
+    fn retire_variants(&mut self) {
+        self.retired.extend(self.variants.drain().map(|(_, built)| built));
+    }
+    
+    /// Change the shader compiler's code generation settings
+    ///
+    /// The active pipeline is not rebuilt; the settings take effect on the
+    /// next shader reload or newly built variant.
+    ///
+    /// # Arguments
+    /// * `settings` - The new compile settings
+    ///
+    /// # Returns
+    /// `true` if the settings changed
+    pub fn set_compile_settings(&mut self, settings: ShaderCompileSettings) -> bool {
+        self.shader_compiler.lock().unwrap().set_settings(settings)
+    }
+    
     /// Get shader compiler statistics
     ///
     /// # Returns
@@ -427,11 +785,24 @@ impl VulkanPipeline {
 impl Drop for VulkanPipeline {
     fn drop(&mut self) {
         debug!("Destroying Vulkan pipeline");
+        // The validator's descriptor sets use our layout, release them first
+        self.validator = None;
+        // The active pipeline and layout are part of the variant map; the
+        // device is idle at shutdown, so everything is destroyed right away
+        self.retire_variants();
+        self.retire_quality_comparison();
+        self.retired.extend(self.last_good.take());
+        for (pipeline_layout, graphics_pipeline) in self.retired.drain(..) {
+            unsafe {
+                self.device.destroy_pipeline(graphics_pipeline, None);
+                self.device.destroy_pipeline_layout(pipeline_layout, None);
+            }
+        }
         unsafe {
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.destroy_render_pass(self.render_pass, None);
+            self.device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
         }
+        self.pass.destroy(&self.device);
+        self.scene_pass.destroy(&self.device);
         debug!("Vulkan pipeline destroyed");
     }
 }
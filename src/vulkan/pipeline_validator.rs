@@ -0,0 +1,313 @@
+//! Safe-mode validation for hot-reloaded pipelines
+//!
+//! Before a reloaded SDF pipeline replaces the active one, it is used for a
+//! single draw into a tiny offscreen target. The draw is submitted and waited
+//! on; a device error, a timeout or a new validation layer error rejects the
+//! pipeline so the previous one stays active.
+
+use ash::vk;
+use ash::{Device, Instance};
+use std::time::Duration;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::depth::{DepthBuffer, DepthUsage};
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::gbuffer::{self, GBuffer};
+use crate::vulkan::rendering::{PassLayout, PassTarget, RenderingMode};
+use crate::vulkan::push_constants::SdfPushConstants;
+use crate::vulkan::scene_buffer::{find_memory_type, GpuSceneData, SceneBuffer};
+use log::{debug, info};
+
+/// Offscreen target, command buffer and scene data for validation draws
+pub struct PipelineValidator {
+    pass: PassLayout,
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    depth: DepthBuffer,
+    gbuffer: GBuffer,
+    framebuffer: vk::Framebuffer,
+    extent: vk::Extent2D,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    scene_buffer: SceneBuffer,
+    queue: vk::Queue,
+    device: Device,
+}
+
+impl PipelineValidator {
+    /// Create the validation target and its resources
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - The physical device used for memory type lookup
+    /// * `device` - The logical device
+    /// * `queue` - Graphics queue used for the validation submit
+    /// * `queue_family_index` - Family of `queue`
+    /// * `format` - Color format of the SDF pipeline's pass
+    /// * `rendering_mode` - Render pass objects or dynamic rendering, as for the SDF pipeline
+    /// * `descriptor_set_layout` - Scene descriptor set layout from `VulkanPipeline`
+    /// * `environment` - Environment map that stays valid while the validator exists
+    /// * `text_atlas` - Text atlas that stays valid while the validator exists
+    ///
+    /// # Errors
+    /// Returns an error if any Vulkan object creation fails
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        queue: vk::Queue,
+        queue_family_index: u32,
+        format: vk::Format,
+        rendering_mode: RenderingMode,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        environment: vk::DescriptorImageInfo,
+        text_atlas: vk::DescriptorImageInfo,
+    ) -> Result<Self> {
+        let size = config::hot_reload::VALIDATION_TARGET_SIZE;
+        let extent = vk::Extent2D { width: size, height: size };
+        debug!("Creating pipeline validator ({}x{} target)", size, size);
+
+        // Compatible with the SDF pipeline's pass; the image is never read
+        let pass = PassLayout::with_color_formats(
+            device,
+            rendering_mode,
+            &gbuffer::pass_formats(format),
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            DepthUsage::Discard,
+            "validation",
+        )?;
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let (image, memory, view) = Self::create_image(device, &memory_properties, format, extent)?;
+        let depth = DepthBuffer::new(device, &memory_properties, extent, DepthUsage::Discard)?;
+        let gbuffer = GBuffer::new(device, &memory_properties, extent)?;
+
+        let framebuffer = pass.create_framebuffer_with_extras(device, view, &gbuffer.views(), depth.view, extent)?;
+
+        let command_pool = unsafe {
+            device.create_command_pool(&vk::CommandPoolCreateInfo::default()
+                .queue_family_index(queue_family_index)
+                .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER), None)
+                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to create validation command pool: {:?}", e)))?
+        };
+        let command_buffer = unsafe {
+            device.allocate_command_buffers(&vk::CommandBufferAllocateInfo::default()
+                .command_pool(command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1))
+                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to allocate validation command buffer: {:?}", e)))?[0]
+        };
+        let fence = unsafe {
+            device.create_fence(&vk::FenceCreateInfo::default(), None)
+                .map_err(|e| VulkanError::Rendering(format!("Failed to create validation fence: {:?}", e)))?
+        };
+
+        // An empty scene: the validation draw only has to exercise the shader
+        let mut scene_buffer = SceneBuffer::new(instance, physical_device, device, descriptor_set_layout, environment, text_atlas)?;
+        scene_buffer.write(0, &GpuSceneData::default());
+
+        info!("Pipeline validator created");
+        Ok(Self {
+            pass,
+            image,
+            memory,
+            view,
+            depth,
+            gbuffer,
+            framebuffer,
+            extent,
+            command_pool,
+            command_buffer,
+            fence,
+            scene_buffer,
+            queue,
+            device: device.clone(),
+        })
+    }
+
+    /// Create the validation color image and its view
+    fn create_image(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> Result<(vk::Image, vk::DeviceMemory, vk::ImageView)> {
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+
+        let image = unsafe {
+            device.create_image(&image_info, None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create validation image: {:?}", e)))?
+        };
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let memory_type_index = find_memory_type(memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            .ok_or_else(|| VulkanError::MemoryAllocation("No device local memory type for validation image".to_string()))?;
+
+        let memory = unsafe {
+            let memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Textures, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to allocate validation image memory: {:?}", e)))?;
+            device.bind_image_memory(image, memory, 0)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to bind validation image memory: {:?}", e)))?;
+            memory
+        };
+
+        let view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+        let view = unsafe {
+            device.create_image_view(&view_info, None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create validation image view: {:?}", e)))?
+        };
+
+        Ok((image, memory, view))
+    }
+
+    /// Draw once with the given pipeline and wait for the result
+    ///
+    /// Must be called between frames on the render thread; the validation
+    /// submit shares the graphics queue with the renderer and waits only for
+    /// its own fence, so frames in flight keep running.
+    ///
+    /// # Arguments
+    /// * `pipeline_layout` - Layout of the candidate pipeline
+    /// * `pipeline` - The candidate SDF pipeline
+    ///
+    /// # Errors
+    /// Returns `VulkanError::Validation` if recording, submission or the draw
+    /// itself fails, times out, or triggers a validation layer error
+    pub fn validate(&self, pipeline_layout: vk::PipelineLayout, pipeline: vk::Pipeline) -> Result<()> {
+        let errors_before = crate::debug::validation_error_count();
+
+        self.record(pipeline_layout, pipeline)?;
+
+        let command_buffers = [self.command_buffer];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+        let timeout = Duration::from_millis(config::hot_reload::VALIDATION_TIMEOUT_MS).as_nanos() as u64;
+
+        unsafe {
+            self.device.reset_fences(&[self.fence])
+                .map_err(|e| VulkanError::Validation(format!("Failed to reset validation fence: {:?}", e)))?;
+            self.device.queue_submit(self.queue, &[submit_info], self.fence)
+                .map_err(|e| VulkanError::Validation(format!("Validation draw submit failed: {:?}", e)))?;
+            match self.device.wait_for_fences(&[self.fence], true, timeout) {
+                Ok(()) => {}
+                Err(vk::Result::TIMEOUT) => {
+                    // Do not leave the queue busy with a runaway shader
+                    let _ = self.device.queue_wait_idle(self.queue);
+                    return Err(VulkanError::Validation(format!(
+                        "Validation draw did not finish within {} ms",
+                        config::hot_reload::VALIDATION_TIMEOUT_MS
+                    )).into());
+                }
+                Err(e) => return Err(VulkanError::Validation(format!("Validation draw failed: {:?}", e)).into()),
+            }
+        }
+
+        if crate::debug::validation_error_count() > errors_before {
+            let message = crate::debug::last_validation_error().unwrap_or_default();
+            return Err(VulkanError::Validation(format!("Validation draw reported errors: {}", message)).into());
+        }
+
+        debug!("Validation draw succeeded");
+        Ok(())
+    }
+
+    /// Record the validation draw
+    fn record(&self, pipeline_layout: vk::PipelineLayout, pipeline: vk::Pipeline) -> Result<()> {
+        let target = PassTarget {
+            framebuffer: self.framebuffer,
+            color_image: self.image,
+            color_view: self.view,
+            depth_image: self.depth.image,
+            depth_view: self.depth.view,
+            extra_colors: self.gbuffer.extra_colors(),
+        };
+        let render_area = vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: self.extent };
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: self.extent.width as f32,
+            height: self.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+
+        let mut push_constants: SdfPushConstants = bytemuck::Zeroable::zeroed();
+        push_constants.resolution = [self.extent.width as f32, self.extent.height as f32];
+
+        unsafe {
+            self.device.reset_command_buffer(self.command_buffer, vk::CommandBufferResetFlags::empty())
+                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to reset validation command buffer: {:?}", e)))?;
+            self.device.begin_command_buffer(self.command_buffer, &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT))
+                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to begin validation command buffer: {:?}", e)))?;
+
+            self.pass.begin(&self.device, self.command_buffer, &target, render_area, [0.0, 0.0, 0.0, 1.0]);
+            self.device.cmd_bind_pipeline(self.command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                self.command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_layout,
+                0,
+                &[self.scene_buffer.descriptor_set(0)],
+                &[],
+            );
+            self.device.cmd_set_viewport(self.command_buffer, 0, &[viewport]);
+            self.device.cmd_set_scissor(self.command_buffer, 0, &[render_area]);
+            self.device.cmd_push_constants(
+                self.command_buffer,
+                pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&push_constants),
+            );
+            self.device.cmd_draw(self.command_buffer, 6, 1, 0, 0);
+            self.pass.end(&self.device, self.command_buffer, &target);
+
+            self.device.end_command_buffer(self.command_buffer)
+                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to end validation command buffer: {:?}", e)))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PipelineValidator {
+    fn drop(&mut self) {
+        debug!("Destroying pipeline validator");
+        unsafe {
+            self.device.destroy_fence(self.fence, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.device.destroy_framebuffer(self.framebuffer, None);
+            self.device.destroy_image_view(self.view, None);
+            self.device.destroy_image(self.image, None);
+            memory_usage::free(&self.device, self.memory);
+        }
+        self.pass.destroy(&self.device);
+        self.depth.destroy(&self.device);
+        self.gbuffer.destroy(&self.device);
+    }
+}
@@ -0,0 +1,159 @@
+//! Push constant layout shared by the SDF vertex and fragment shaders
+//!
+//! The field order and padding must match the `PushConstants` block in
+//! `shaders/sdf.frag` (std430 layout).
+
+use crate::config;
+use crate::environment::SunSettings;
+use crate::vulkan::viewport::{GridSettings, RenderMode, ViewCamera};
+
+/// Push constants for the SDF fullscreen pass
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct SdfPushConstants {
+    /// Framebuffer resolution in pixels
+    pub resolution: [f32; 2],
+    /// Elapsed time in seconds
+    pub time: f32,
+    /// Camera rotation about its forward axis in radians (`ViewCamera::roll`)
+    pub camera_roll: f32,
+    /// Clear color of the viewport (linear RGBA), the background of the
+    /// solid color mode
+    pub clear_color: [f32; 4],
+    /// Viewport render mode (`RenderMode::shader_value`)
+    pub render_mode: i32,
+    /// Reference grid and axes (`GridSettings::shader_flags`)
+    pub grid_flags: i32,
+    /// World units between grid lines
+    pub grid_spacing: f32,
+    _padding: f32,
+    /// Direction towards the sun (xyz) and intensity (w)
+    pub sun_direction: [f32; 4],
+    /// Sun color (rgb), w = grid fade distance
+    pub sun_color: [f32; 4],
+    /// Camera origin (xyz), w = orthographic half height or 0 for perspective
+    pub camera_origin: [f32; 4],
+    /// Camera forward direction (xyz), w = mask of the render layers shown
+    /// (exact as a float up to `config::layers::COUNT` = 24)
+    pub camera_forward: [f32; 4],
+    /// Half width and height (xy) and center (zw) of the view, see `ViewCamera::lens`
+    pub camera_lens: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for SdfPushConstants {}
+unsafe impl bytemuck::Zeroable for SdfPushConstants {}
+
+// Push constants must fit the 128 bytes every device guarantees
+const _: () = assert!(SdfPushConstants::SIZE <= 128);
+
+impl SdfPushConstants {
+    /// Size of the push constant block in bytes
+    pub const SIZE: u32 = std::mem::size_of::<SdfPushConstants>() as u32;
+
+    /// Build push constants for the given frame state
+    ///
+    /// Uses the default perspective camera; see [`Self::with_view`].
+    ///
+    /// # Arguments
+    /// * `width` - Framebuffer width
+    /// * `height` - Framebuffer height
+    /// * `time` - Elapsed time
+    /// * `aspect_ratio` - Viewport aspect ratio
+    /// * `sun` - Current sun settings
+    pub fn new(width: u32, height: u32, time: f32, aspect_ratio: f32, sun: &SunSettings) -> Self {
+        let direction = sun.direction();
+        let tint = sun.tint();
+
+        Self {
+            resolution: [width as f32, height as f32],
+            time,
+            camera_roll: 0.0,
+            clear_color: config::rendering::CLEAR_COLOR,
+            render_mode: RenderMode::Shaded.shader_value(),
+            grid_flags: 0,
+            grid_spacing: 1.0,
+            _padding: 0.0,
+            sun_direction: [direction.x, direction.y, direction.z, sun.intensity],
+            sun_color: [tint[0], tint[1], tint[2], 0.0],
+            camera_origin: ViewCamera::default().origin_vec4(),
+            camera_forward: ViewCamera::default().forward_vec4(),
+            camera_lens: ViewCamera::default().lens(aspect_ratio),
+        }
+        .with_layers(config::layers::ALL)
+    }
+
+    /// Set the viewport camera, render mode and size
+    ///
+    /// # Arguments
+    /// * `camera` - Camera of the viewport
+    /// * `render_mode` - Shading of the viewport
+    /// * `width` - Viewport width in pixels
+    /// * `height` - Viewport height in pixels
+    pub fn with_view(mut self, camera: &ViewCamera, render_mode: RenderMode, width: u32, height: u32) -> Self {
+        let layers = self.camera_forward[3];
+        self.resolution = [width as f32, height as f32];
+        self.render_mode = render_mode.shader_value();
+        self.camera_roll = camera.roll;
+        self.camera_origin = camera.origin_vec4();
+        self.camera_forward = camera.forward_vec4();
+        self.camera_forward[3] = layers;
+        self.camera_lens = camera.lens(width as f32 / height.max(1) as f32);
+        self
+    }
+
+    /// Set the clear color of the viewport
+    ///
+    /// # Arguments
+    /// * `clear_color` - Linear RGBA color, see `Viewport::clear_color`
+    pub fn with_clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// Set the render layers the viewport shows
+    ///
+    /// # Arguments
+    /// * `layers` - Layer mask, see `RenderLayers`
+    pub fn with_layers(mut self, layers: u32) -> Self {
+        self.camera_forward[3] = layers as f32;
+        self
+    }
+
+    /// Set the reference grid and axes drawn behind the scene
+    ///
+    /// # Arguments
+    /// * `grid` - Grid settings of the viewport layout
+    pub fn with_grid(mut self, grid: &GridSettings) -> Self {
+        self.grid_flags = grid.shader_flags();
+        self.grid_spacing = grid.spacing;
+        self.sun_color[3] = grid.fade_distance;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_matches_shader_block() {
+        assert_eq!(SdfPushConstants::SIZE, 128);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, camera_roll), 12);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, clear_color), 16);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, render_mode), 32);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, grid_flags), 36);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, grid_spacing), 40);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, sun_direction), 48);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, camera_origin), 80);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, camera_forward), 96);
+        assert_eq!(std::mem::offset_of!(SdfPushConstants, camera_lens), 112);
+    }
+
+    #[test]
+    fn test_layers_survive_the_view() {
+        let base = SdfPushConstants::new(800, 600, 0.0, 1.0, &SunSettings::default());
+        assert_eq!(base.camera_forward[3], config::layers::ALL as f32);
+        let view = base.with_layers(0b101).with_view(&ViewCamera::default(), RenderMode::Depth, 400, 300);
+        assert_eq!(view.camera_forward, [0.0, 0.0, 1.0, 5.0]);
+    }
+}
@@ -0,0 +1,109 @@
+//! Raymarch quality tiers
+//!
+//! Each tier maps to a set of specialization constants (max march steps,
+//! surface epsilon and max distance) baked into the SDF fragment shader
+//! when `VulkanPipeline` creates the graphics pipeline.
+
+use ash::vk;
+use serde::{Deserialize, Serialize};
+use crate::config;
+
+/// Raymarch quality tier selectable from the HUD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RaymarchQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Ultra,
+}
+
+impl RaymarchQuality {
+    /// All tiers in ascending order
+    pub const ALL: [RaymarchQuality; 4] = [
+        RaymarchQuality::Low,
+        RaymarchQuality::Medium,
+        RaymarchQuality::High,
+        RaymarchQuality::Ultra,
+    ];
+
+    /// Display name for the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            RaymarchQuality::Low => "Low",
+            RaymarchQuality::Medium => "Medium",
+            RaymarchQuality::High => "High",
+            RaymarchQuality::Ultra => "Ultra",
+        }
+    }
+
+    /// Specialization constant values for this tier
+    pub fn specialization(self) -> RaymarchSpecialization {
+        let (max_steps, epsilon, max_distance) = match self {
+            RaymarchQuality::Low => config::raymarch::LOW,
+            RaymarchQuality::Medium => config::raymarch::MEDIUM,
+            RaymarchQuality::High => config::raymarch::HIGH,
+            RaymarchQuality::Ultra => config::raymarch::ULTRA,
+        };
+
+        RaymarchSpecialization {
+            max_steps: max_steps as i32,
+            epsilon,
+            max_distance,
+        }
+    }
+}
+
+/// Specialization constant data for the SDF fragment shader
+///
+/// Field order matches `constant_id` 0..2 in `shaders/sdf.frag`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct RaymarchSpecialization {
+    pub max_steps: i32,
+    pub epsilon: f32,
+    pub max_distance: f32,
+}
+
+unsafe impl bytemuck::Pod for RaymarchSpecialization {}
+unsafe impl bytemuck::Zeroable for RaymarchSpecialization {}
+
+impl RaymarchSpecialization {
+    /// Map entries describing where each constant lives in the data blob
+    pub fn map_entries() -> [vk::SpecializationMapEntry; 3] {
+        [
+            vk::SpecializationMapEntry::default()
+                .constant_id(0)
+                .offset(std::mem::offset_of!(RaymarchSpecialization, max_steps) as u32)
+                .size(std::mem::size_of::<i32>()),
+            vk::SpecializationMapEntry::default()
+                .constant_id(1)
+                .offset(std::mem::offset_of!(RaymarchSpecialization, epsilon) as u32)
+                .size(std::mem::size_of::<f32>()),
+            vk::SpecializationMapEntry::default()
+                .constant_id(2)
+                .offset(std::mem::offset_of!(RaymarchSpecialization, max_distance) as u32)
+                .size(std::mem::size_of::<f32>()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_medium_matches_shader_defaults() {
+        let spec = RaymarchQuality::Medium.specialization();
+        assert_eq!(spec.max_steps, 100);
+        assert_eq!(spec.epsilon, 0.001);
+        assert_eq!(spec.max_distance, 10.0);
+    }
+
+    #[test]
+    fn test_tiers_increase_in_cost() {
+        let steps: Vec<i32> = RaymarchQuality::ALL.iter().map(|q| q.specialization().max_steps).collect();
+        assert!(steps.windows(2).all(|w| w[0] < w[1]));
+    }
+}
@@ -0,0 +1,239 @@
+//! Per-shader hot reload state
+//!
+//! `ShaderReloadStats` knows every shader file under the watched directories
+//! and what happened the last time it was reloaded: when, how long the
+//! recompile took and whether it succeeded. It also holds the reload
+//! filters: one toggle per shader kind, starting from the
+//! `config::hot_reload::RELOAD_*` constants, and one per file. The hot
+//! reload manager shares it with the watcher thread, which asks
+//! `should_reload` before queueing a change; the Shader Reload panel shows
+//! a snapshot and flips the toggles.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use log::debug;
+use crate::config;
+use crate::file_watcher::list_files;
+use crate::vulkan::shader_watcher::shader_kind;
+
+/// Outcome of the last reload of a shader file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadStatus {
+    /// Watched, not reloaded yet
+    Watched,
+    /// Recompiled and the pipeline rebuilt
+    Reloaded,
+    /// Recompiled, but no pipeline uses the file
+    Unused,
+    /// Change ignored by a kind or file filter
+    Skipped,
+    /// Recompile or pipeline rebuild failed, the previous pipeline stays
+    Failed(String),
+}
+
+impl ReloadStatus {
+    /// Short label shown in the HUD
+    pub fn label(&self) -> &str {
+        match self {
+            ReloadStatus::Watched => "watched",
+            ReloadStatus::Reloaded => "reloaded",
+            ReloadStatus::Unused => "not used",
+            ReloadStatus::Skipped => "skipped",
+            ReloadStatus::Failed(_) => "failed",
+        }
+    }
+}
+
+/// Reload state of one shader file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderFileStats {
+    /// File path, relative to the working directory where possible
+    pub path: PathBuf,
+    /// Shader kind from the extension ("vertex", "fragment", ...)
+    pub kind: &'static str,
+    /// Whether changes of this file are reloaded
+    pub enabled: bool,
+    /// Outcome of the last reload
+    pub status: ReloadStatus,
+    /// When the file was last reloaded
+    pub last_reload: Option<DateTime<Local>>,
+    /// Duration of the last recompile in milliseconds
+    pub compile_ms: Option<f64>,
+    /// Number of reloads since startup
+    pub reload_count: u32,
+}
+
+/// Reload filters and per-file reload state
+#[derive(Debug, Clone)]
+pub struct ShaderReloadStats {
+    /// Files by canonical path
+    files: BTreeMap<PathBuf, ShaderFileStats>,
+    /// Shader kinds and whether they are reloaded
+    kinds: Vec<(&'static str, bool)>,
+}
+
+impl Default for ShaderReloadStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderReloadStats {
+    /// Create an empty tracker with the configured kind filters
+    pub fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+            kinds: vec![
+                ("vertex", config::hot_reload::RELOAD_VERTEX_SHADERS),
+                ("fragment", config::hot_reload::RELOAD_FRAGMENT_SHADERS),
+                ("geometry", config::hot_reload::RELOAD_GEOMETRY_SHADERS),
+                ("compute", config::hot_reload::RELOAD_COMPUTE_SHADERS),
+                ("tess_control", config::hot_reload::RELOAD_TESSELLATION_SHADERS),
+                ("tess_evaluation", config::hot_reload::RELOAD_TESSELLATION_SHADERS),
+                ("include", config::hot_reload::RELOAD_INCLUDE_FILES),
+            ],
+        }
+    }
+
+    /// Key and display path of a shader file
+    ///
+    /// Watcher events carry absolute paths while manual reloads use paths
+    /// relative to the working directory; both map to the same entry.
+    fn normalize(path: &Path) -> (PathBuf, PathBuf) {
+        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let display = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| key.strip_prefix(cwd).ok().map(Path::to_path_buf))
+            .unwrap_or_else(|| path.to_path_buf());
+        (key, display)
+    }
+
+    /// Entry of a shader file, created on first use
+    fn track(&mut self, path: &Path) -> &mut ShaderFileStats {
+        let (key, display) = Self::normalize(path);
+        self.files.entry(key).or_insert_with(|| ShaderFileStats {
+            kind: shader_kind(path.extension().and_then(|ext| ext.to_str()).unwrap_or_default()),
+            path: display,
+            enabled: true,
+            status: ReloadStatus::Watched,
+            last_reload: None,
+            compile_ms: None,
+            reload_count: 0,
+        })
+    }
+
+    /// List the shader files in directory trees
+    ///
+    /// # Arguments
+    /// * `roots` - Watched shader directories
+    /// * `extensions` - Shader file extensions
+    pub fn scan(&mut self, roots: &[PathBuf], extensions: &[String]) {
+        for path in roots.iter().flat_map(|root| list_files(root, true)) {
+            let watched = path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.iter().any(|watched| watched == ext));
+            if path.is_file() && watched {
+                self.track(&path);
+            }
+        }
+    }
+
+    /// Whether a shader kind is reloaded
+    pub fn is_kind_enabled(&self, kind: &str) -> bool {
+        self.kinds.iter().any(|&(name, enabled)| name == kind && enabled)
+    }
+
+    /// Decide whether a change of a file is reloaded
+    ///
+    /// A file that is filtered out is marked as skipped.
+    pub fn should_reload(&mut self, path: &Path) -> bool {
+        let kind = self.track(path).kind;
+        let kind_enabled = self.is_kind_enabled(kind);
+        let file = self.track(path);
+        if kind_enabled && file.enabled {
+            return true;
+        }
+        debug!("Skipping reload of filtered shader {:?} ({})", file.path, file.kind);
+        file.status = ReloadStatus::Skipped;
+        false
+    }
+
+    /// Record the outcome of a reload
+    ///
+    /// # Arguments
+    /// * `path` - Reloaded file
+    /// * `compile_time` - Time the recompile took
+    /// * `result` - Whether a pipeline was rebuilt, or the error
+    pub fn record(&mut self, path: &Path, compile_time: Duration, result: std::result::Result<bool, String>) {
+        let file = self.track(path);
+        file.status = match result {
+            Ok(true) => ReloadStatus::Reloaded,
+            Ok(false) => ReloadStatus::Unused,
+            Err(e) => ReloadStatus::Failed(e),
+        };
+        file.last_reload = Some(Local::now());
+        file.compile_ms = Some(compile_time.as_secs_f64() * 1000.0);
+        file.reload_count += 1;
+    }
+
+    /// Enable or disable reloading of one file
+    pub fn set_file_enabled(&mut self, path: &Path, enabled: bool) {
+        self.track(path).enabled = enabled;
+    }
+
+    /// Enable or disable reloading of a shader kind
+    pub fn set_kind_enabled(&mut self, kind: &str, enabled: bool) {
+        if let Some(entry) = self.kinds.iter_mut().find(|(name, _)| *name == kind) {
+            entry.1 = enabled;
+        }
+    }
+
+    /// Known shader files, sorted by path
+    pub fn files(&self) -> Vec<ShaderFileStats> {
+        self.files.values().cloned().collect()
+    }
+
+    /// Shader kinds and whether they are reloaded
+    pub fn kinds(&self) -> &[(&'static str, bool)] {
+        &self.kinds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_skip_changes() {
+        let mut stats = ShaderReloadStats::new();
+        let frag = Path::new("missing/sdf.frag");
+        let vert = Path::new("missing/sdf.vert");
+        assert!(stats.should_reload(frag));
+        assert!(stats.should_reload(vert));
+
+        stats.set_file_enabled(frag, false);
+        stats.set_kind_enabled("vertex", false);
+        assert!(!stats.should_reload(frag));
+        assert!(!stats.should_reload(vert));
+        assert!(!stats.is_kind_enabled("vertex"));
+        assert!(stats.files().iter().all(|file| file.status == ReloadStatus::Skipped));
+    }
+
+    #[test]
+    fn test_record_keeps_last_outcome() {
+        let mut stats = ShaderReloadStats::new();
+        let path = Path::new("missing/sdf.frag");
+        stats.record(path, Duration::from_millis(12), Ok(true));
+        stats.record(path, Duration::from_millis(30), Err("syntax error".to_string()));
+
+        let files = stats.files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].kind, "fragment");
+        assert_eq!(files[0].reload_count, 2);
+        assert_eq!(files[0].status, ReloadStatus::Failed("syntax error".to_string()));
+        assert!((files[0].compile_ms.unwrap() - 30.0).abs() < 1e-9);
+        assert!(files[0].last_reload.is_some());
+    }
+}
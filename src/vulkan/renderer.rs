@@ -1,12 +1,58 @@
 use ash::vk;
 use ash::{Device, Instance};
+use std::collections::HashMap;
+use std::ffi::CString;
 use std::sync::{Arc, Mutex};
+use cgmath::Vector3;
 use crate::vulkan::{VulkanInstance, VulkanDevice, VulkanSwapchain, VulkanPipeline};
-use crate::error::{Result, VulkanError};
+use crate::error::{AppError, Result, ResultExt, Subsystem, VkResultExt, VulkanError};
 use crate::config;
 use crate::camera::Camera;
+use crate::renderer::Renderer;
+use crate::environment::{BackgroundSettings, SunSettings};
+use crate::image_io::HdrImage;
+use crate::vulkan::memory_usage;
+use crate::vulkan::residency::{Residency, ResidencyUsage, ResidentAsset};
+use crate::vulkan::push_constants::SdfPushConstants;
+use crate::vulkan::scene_buffer::{GpuBackground, GpuMouse, GpuSceneData, GpuShaderParams, SceneBuffer};
+use crate::vulkan::shader_annotations::ShaderAnnotations;
+use crate::vulkan::shader_reflection::UniformBlock;
+use crate::vulkan::environment_map::EnvironmentMap;
+use crate::vulkan::text::{TextDrawList, TextPass};
+use crate::vulkan::quality::RaymarchQuality;
+use crate::vulkan::shader_variant::ShaderVariantKey;
+use crate::vulkan::shader_compiler::ShaderCompileSettings;
+use crate::vulkan::bindless::BindlessTextures;
+use crate::vulkan::composite::CompositePass;
+use crate::vulkan::debug_draw::{DebugDrawList, DebugDrawRenderer};
+use crate::vulkan::depth::{DepthBuffer, DepthUsage};
+use crate::vulkan::mesh::MeshDrawList;
+use crate::vulkan::mesh_pass::MeshPass;
+use crate::vulkan::outline::{OutlinePass, OutlineSettings};
+use crate::vulkan::particles::{ParticleEmitterList, ParticlePass};
+use crate::vulkan::pipeline_validator::PipelineValidator;
+use crate::vulkan::rendering::{PassLayout, PassTarget};
+use crate::vulkan::gpu_selection::GpuSelector;
+use crate::vulkan::upload::UploadContext;
+use crate::vulkan::deletion_queue::{DeferredResource, DeletionQueue};
+use crate::vulkan::viewport::{self, Tile, ViewportSettings};
+use crate::vulkan::split_compare::SplitCompare;
+use crate::vulkan::frame_export::FrameExporter;
+use crate::vulkan::still_export;
+use crate::vulkan::fullscreen_pass::MouseState;
+use crate::vulkan::toy::Toy;
+use crate::vulkan::gpu_timer::GpuTimer;
+use crate::vulkan::resolution_scaler::{self, ResolutionScaler};
+use crate::vulkan::tonemap::{DisplayOutput, TonemapSettings};
+use crate::vulkan::swapchain::PresentMode;
+#[cfg(feature = "xr")]
+use crate::camera::LookCamera;
+#[cfg(feature = "xr")]
+use crate::vulkan::viewport::{ViewKind, ViewportLayout};
+#[cfg(feature = "xr")]
+use crate::vulkan::xr::{self, XrInput, XrRuntime, XrSession};
 use winit::window::Window;
-use log::{debug, info, error};
+use log::{debug, info, warn, error};
 
 // Wrapper for surface to handle proper cleanup
 struct SurfaceWrapper {
@@ -26,11 +72,6 @@ pub struct VulkanRenderer {
     // Order matters for cleanup! Rust drops in reverse order of declaration.
     // Things that depend on others must be declared first.
     
-    _vertex_buffer: vk::Buffer,
-    _vertex_buffer_memory: vk::DeviceMemory,
-    _index_buffer: vk::Buffer,
-    _index_buffer_memory: vk::DeviceMemory,
-    
     // Sync objects (cleaned up before device)
     image_available_semaphores: Vec<vk::Semaphore>,
     render_finished_semaphores: Vec<vk::Semaphore>,
@@ -43,6 +84,79 @@ pub struct VulkanRenderer {
     // Framebuffers (cleaned up before pipeline and swapchain)
     framebuffers: Vec<vk::Framebuffer>,
     
+    // Depth attachment shared by the swapchain framebuffers (cleaned up after them)
+    depth_buffer: DepthBuffer,
+    
+    // Scene uniform buffers (cleaned up before pipeline, uses its descriptor set layout)
+    scene_buffer: SceneBuffer,
+    
+    // HDRI sampled by the SDF shader for the background and reflections (cleaned up before device)
+    environment_map: EnvironmentMap,
+    
+    // MSDF font atlas and laid out texts of the scene (cleaned up before device)
+    text: TextPass,
+    
+    // Offscreen SDF target and composite pipeline (cleaned up before device)
+    composite: CompositePass,
+    
+    // Shaders drawn into the offscreen target instead of the scene with `--toy` (cleaned up before device)
+    toy: Option<Toy>,
+    
+    // Headset session drawing through the offscreen target with `--xr` (cleaned up before device)
+    #[cfg(feature = "xr")]
+    xr: Option<XrSession>,
+    
+    // Mouse as seen by the toy shader and the SDF shader
+    pub mouse: MouseState,
+    
+    // Debug line pipeline and vertex buffers (cleaned up before device)
+    debug_draw: DebugDrawRenderer,
+    
+    // Latest debug lines collected from the ECS world
+    debug_lines: DebugDrawList,
+    
+    // Selection outline drawn over the composited scene (cleaned up before device)
+    outline: OutlinePass,
+    
+    // Mesh pipeline and uploaded meshes, drawn after the SDF (cleaned up before device)
+    mesh_pass: MeshPass,
+    
+    // Budget of the cached meshes and environment maps
+    residency: Residency,
+    
+    // GPU particle simulation and billboards, drawn after the meshes (cleaned up before device)
+    particles: ParticlePass,
+    
+    // GPU timestamp queries for the SDF pass, None if unsupported
+    gpu_timer: Option<GpuTimer>,
+    
+    // Descriptor indexing table of all textures, None without descriptor indexing (cleaned up before device)
+    pub bindless: Option<BindlessTextures>,
+    
+    // Adaptive resolution controller for the SDF pass
+    pub resolution_scaler: ResolutionScaler,
+    
+    // Size of the offscreen SDF target relative to the swapchain, independent of the HUD
+    target_scale: f32,
+    
+    // Most recent measured GPU time of the SDF pass in milliseconds
+    last_gpu_ms: Option<f32>,
+    
+    // Latest scene data collected from the ECS world
+    scene_data: GpuSceneData,
+    
+    // Single or quad viewport layout of the SDF pass
+    pub viewports: ViewportSettings,
+    
+    // Split-screen comparison against a second SDF pipeline
+    split_compare: SplitCompare,
+    
+    // Resources destroyed once in-flight frames are done (flushed in drop) - shared with the HUD backend
+    pub deletion_queue: Arc<Mutex<DeletionQueue>>,
+    
+    // Transfer queue uploads (cleaned up before device) - shared with the HUD backend
+    pub upload: Arc<Mutex<UploadContext>>,
+    
     // Pipeline (cleaned up before device) - shared with hot reload manager
     pub pipeline: Arc<Mutex<VulkanPipeline>>,
     
@@ -61,9 +175,27 @@ pub struct VulkanRenderer {
     // Camera for proper projection handling
     pub camera: Camera,
     
+    // Sun settings driving the main directional light and sky
+    pub sun: SunSettings,
+    
+    // Exposure and tonemapping applied when compositing the HDR scene
+    pub tonemap: TonemapSettings,
+    
+    // Color and thickness of the selection outline, from the Settings window
+    pub outline_settings: OutlineSettings,
+    
+    // Values of the SDF shader's Params block, from the Shader Parameters panel
+    pub shader_params: GpuShaderParams,
+    
+    // Background shown where rays miss the scene, set through `set_background`
+    background: BackgroundSettings,
+    
     // Runtime state
     current_frame: usize,
     
+    // Frames submitted so far, attached to frame errors
+    frame_number: u64,
+    
     // For dynamic push constant updates
     time: f32,
     
@@ -76,60 +208,184 @@ impl VulkanRenderer {
     ///
     /// # Arguments
     /// * `window` - The window to render to
+    /// * `gpu_override` - Physical device forced with `--gpu`, if any
+    /// * `present_mode` - Preferred presentation mode of the swapchain
     ///
     /// # Returns
     /// A new VulkanRenderer instance
     ///
     /// # Errors
     /// Returns an error if renderer initialization fails
-    pub fn new(window: &Window) -> Result<Self> {
+    pub fn new(window: &Window, gpu_override: Option<&GpuSelector>, present_mode: PresentMode) -> Result<Self> {
         info!("Initializing Vulkan renderer");
         
         let instance = VulkanInstance::new()
-            .map_err(|e| VulkanError::InstanceCreation(format!("Failed to create Vulkan instance: {}", e)))?;
+            .context(Subsystem::Renderer, "create Vulkan instance")?;
+        Self::with_instance(window, instance, gpu_override, &[], present_mode)
+    }
+    
+    /// Create a renderer that also draws into an OpenXR headset
+    ///
+    /// The instance and device get the extensions the runtime asks for, and
+    /// the device is created on the GPU the headset is connected to.
+    ///
+    /// # Arguments
+    /// * `window` - The window to render to, which mirrors the left eye
+    /// * `present_mode` - Preferred presentation mode of the swapchain
+    /// * `runtime` - The OpenXR runtime with a headset
+    ///
+    /// # Errors
+    /// Returns an error if renderer initialization or the XR session fails
+    #[cfg(feature = "xr")]
+    pub fn new_xr(window: &Window, present_mode: PresentMode, runtime: XrRuntime) -> Result<Self> {
+        info!("Initializing Vulkan renderer for OpenXR");
+        
+        let instance = VulkanInstance::with_extensions(&runtime.instance_extensions()?)
+            .context(Subsystem::Renderer, "create Vulkan instance")?;
+        runtime.check_api_version(instance.api_version)?;
+        let gpu = runtime.gpu_selector(&instance.instance)?;
+        let mut renderer = Self::with_instance(window, instance, Some(&gpu), &runtime.device_extensions()?, present_mode)?;
+        if renderer.display_output() != DisplayOutput::Sdr {
+            return Err(AppError::Generic("OpenXR rendering needs an sRGB window swapchain".to_string()));
+        }
         
+        let pipeline = renderer.pipeline.lock().unwrap();
+        let session = XrSession::new(
+            runtime,
+            &renderer.instance.instance,
+            &renderer.device,
+            &pipeline.pass,
+            renderer.swapchain.swapchain_image_format,
+        )?;
+        drop(pipeline);
+        renderer.xr = Some(session);
+        Ok(renderer)
+    }
+    
+    /// Create the surface, device and everything drawn with them on an instance
+    fn with_instance(
+        window: &Window,
+        instance: VulkanInstance,
+        gpu_override: Option<&GpuSelector>,
+        device_extensions: &[CString],
+        present_mode: PresentMode,
+    ) -> Result<Self> {
         let surface = Self::create_surface(&instance.entry, &instance.instance, window)?;
         let surface_loader = ash::khr::surface::Instance::new(&instance.entry, &instance.instance);
         
-        let device = VulkanDevice::new(&instance.instance, &instance.entry, surface)
-            .map_err(|e| VulkanError::DeviceCreation(format!("Failed to create Vulkan device: {}", e)))?;
+        let device = VulkanDevice::new(&instance.instance, &instance.entry, instance.api_version, surface, gpu_override, device_extensions)
+            .context(Subsystem::Renderer, "create Vulkan device")?;
+        
+        let swapchain = VulkanSwapchain::new(&instance.instance, &instance.entry, &device, surface, window, present_mode)
+            .context(Subsystem::Renderer, "create swapchain")?;
         
-        let swapchain = VulkanSwapchain::new(&instance.instance, &instance.entry, &device, surface, window)
-            .map_err(|e| VulkanError::SwapchainCreation(format!("Failed to create swapchain: {}", e)))?;
+        let upload = Arc::new(Mutex::new(UploadContext::new(&instance.instance, &device)?));
+        let device_local_bytes = memory_usage::heap_budgets(&instance.instance, device.physical_device, device.memory_budget)
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(|heap| heap.budget.unwrap_or(heap.size))
+            .reduce(|total, bytes| total + bytes);
+        let residency = Residency::new(Residency::budget_for(device_local_bytes));
+        info!("Asset residency budget {}", memory_usage::format_bytes(residency.usage().budget));
+        let environment_map = EnvironmentMap::new(
+            &instance.instance,
+            device.physical_device,
+            &device.device,
+            &mut upload.lock().unwrap(),
+        )?;
+        let text = TextPass::new(
+            &instance.instance,
+            device.physical_device,
+            &device.device,
+            &mut upload.lock().unwrap(),
+        )?;
         
-        let pipeline = Arc::new(Mutex::new(VulkanPipeline::new(&device.device, swapchain.swapchain_image_format)
-            .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create pipeline: {}", e)))?));
+        let pipeline = Arc::new(Mutex::new(VulkanPipeline::new(&device.device, swapchain.swapchain_image_format, device.rendering_mode)
+            .context(Subsystem::Renderer, "create pipeline")?));
         
-        let pipeline_guard = pipeline.lock().unwrap();
+        let mut pipeline_guard = pipeline.lock().unwrap();
+        let memory_properties = unsafe { instance.instance.get_physical_device_memory_properties(device.physical_device) };
+        let depth_buffer = DepthBuffer::new(&device.device, &memory_properties, swapchain.swapchain_extent, DepthUsage::Discard)?;
         let framebuffers = Self::create_framebuffers(
             &device.device,
-            pipeline_guard.render_pass,
+            &pipeline_guard.pass,
             &swapchain.swapchain_image_views,
+            depth_buffer.view,
             swapchain.swapchain_extent
         )?;
 
-        let command_pool = Self::create_command_pool(&device.device, &device.queue_families)?;
-        let command_buffers = Self::create_command_buffers(
+        let scene_buffer = SceneBuffer::new(
+            &instance.instance,
+            device.physical_device,
             &device.device,
-            command_pool,
-            pipeline_guard.graphics_pipeline,
-            pipeline_guard.pipeline_layout,
-            pipeline_guard.render_pass,
-            &framebuffers,
-            swapchain.swapchain_extent
+            pipeline_guard.descriptor_set_layout,
+            environment_map.descriptor_info(),
+            text.descriptor_info(),
+        )?;
+
+        let composite = CompositePass::new(
+            &instance.instance,
+            device.physical_device,
+            &device.device,
+            resolution_scaler::target_extent(
+                swapchain.swapchain_extent,
+                config::resolution_scaling::DEFAULT_RENDER_SCALE,
+                device.capabilities.max_image_dimension_2d(),
+            ),
+            &pipeline_guard.pass,
+            swapchain.display_output,
+        )?;
+        let debug_draw = DebugDrawRenderer::new(
+            &instance.instance,
+            device.physical_device,
+            &device.device,
+            &pipeline_guard.pass,
+            swapchain.display_output,
+        )?;
+        let outline = OutlinePass::new(&device.device, &pipeline_guard.pass, swapchain.display_output, composite.selection_view())?;
+        let mesh_pass = MeshPass::new(
+            &instance.instance,
+            device.physical_device,
+            &device.device,
+            &composite.scene_pass,
+        )?;
+        let particles = ParticlePass::new(
+            &instance.instance,
+            device.physical_device,
+            &device.device,
+            &composite.scene_pass,
+        )?;
+        let gpu_timer = GpuTimer::new(&device.capabilities, &device.device)?;
+        let bindless = if device.bindless_textures {
+            Some(BindlessTextures::new(&device.device, &device.capabilities)?)
+        } else {
+            info!("Descriptor indexing unavailable, textures use their own descriptor sets");
+            None
+        };
+
+        // Safe mode for hot reload: reloaded pipelines are test-drawn before being swapped in
+        let validator = PipelineValidator::new(
+            &instance.instance,
+            device.physical_device,
+            &device.device,
+            device.graphics_queue,
+            device.queue_families.graphics_family.unwrap(),
+            config::rendering::SCENE_FORMAT,
+            device.rendering_mode,
+            pipeline_guard.descriptor_set_layout,
+            environment_map.placeholder_info(),
+            text.placeholder_info(),
         )?;
+        pipeline_guard.set_validator(validator);
+
+        let command_pool = Self::create_command_pool(&device.device, &device.queue_families)?;
+        let command_buffers = Self::create_command_buffers(&device.device, command_pool, framebuffers.len())?;
         drop(pipeline_guard); // Release the lock
 
         let (image_available_semaphores, render_finished_semaphores, in_flight_fences) =
             Self::create_sync_objects(&device.device)?;
         
         
-        // Temporarily disable vertex buffer creation to focus on ECS integration
-        let vertex_buffer = vk::Buffer::null();
-        let vertex_buffer_memory = vk::DeviceMemory::null();
-        let index_buffer = vk::Buffer::null();
-        let index_buffer_memory = vk::DeviceMemory::null();
-        
         // Create camera with proper aspect ratio
         let aspect_ratio = swapchain.swapchain_extent.width as f32 / swapchain.swapchain_extent.height as f32;
         let camera = Camera::with_params(
@@ -145,23 +401,50 @@ impl VulkanRenderer {
         info!("Vulkan renderer initialized successfully");
         
         Ok(Self {
-            _vertex_buffer: vertex_buffer,
-            _vertex_buffer_memory: vertex_buffer_memory,
-            _index_buffer: index_buffer,
-            _index_buffer_memory: index_buffer_memory,
             image_available_semaphores,
             render_finished_semaphores,
             in_flight_fences,
             command_pool,
             command_buffers,
             framebuffers,
+            depth_buffer,
+            scene_buffer,
+            environment_map,
+            text,
+            composite,
+            toy: None,
+            #[cfg(feature = "xr")]
+            xr: None,
+            mouse: MouseState::default(),
+            debug_draw,
+            debug_lines: DebugDrawList::new(),
+            outline,
+            mesh_pass,
+            residency,
+            particles,
+            gpu_timer,
+            bindless,
+            resolution_scaler: ResolutionScaler::default(),
+            target_scale: config::resolution_scaling::DEFAULT_RENDER_SCALE,
+            last_gpu_ms: None,
+            scene_data: GpuSceneData::default(),
+            viewports: ViewportSettings::default(),
+            split_compare: SplitCompare::default(),
+            deletion_queue: Arc::new(Mutex::new(DeletionQueue::new())),
+            upload,
             pipeline,
             swapchain,
             surface: SurfaceWrapper { surface, surface_loader },
             device,
             instance,
             camera,
+            sun: SunSettings::default(),
+            tonemap: TonemapSettings::default(),
+            outline_settings: OutlineSettings::default(),
+            shader_params: GpuShaderParams::default(),
+            background: BackgroundSettings::default(),
             current_frame: 0,
+            frame_number: 0,
             time: 0.0,
             hud_reference: None,
         })
@@ -214,46 +497,47 @@ impl VulkanRenderer {
     ///
     /// # Arguments
     /// * `device` - The Vulkan device
-    /// * `render_pass` - The render pass
+    /// * `pass` - The present pass
     /// * `image_views` - The swapchain image views
+    /// * `depth_view` - The depth attachment shared by all framebuffers
     /// * `extent` - The extent of the framebuffers
     ///
     /// # Returns
-    /// A vector of created framebuffers
+    /// A vector of created framebuffers, null handles with dynamic rendering
     ///
     /// # Errors
     /// Returns an error if framebuffer creation fails
     fn create_framebuffers(
         device: &Device,
-        render_pass: vk::RenderPass,
+        pass: &PassLayout,
         image_views: &[vk::ImageView],
+        depth_view: vk::ImageView,
         extent: vk::Extent2D
     ) -> Result<Vec<vk::Framebuffer>> {
         debug!("Creating {} framebuffers", image_views.len());
         
         let mut framebuffers = vec![];
         
-        for (i, &image_view) in image_views.iter().enumerate() {
-            let attachments = [image_view];
-            
-            let framebuffer_info = vk::FramebufferCreateInfo::default()
-                .render_pass(render_pass)
-                .attachments(&attachments)
-                .width(extent.width)
-                .height(extent.height)
-                .layers(1);
-            
-            let framebuffer = unsafe {
-                device.create_framebuffer(&framebuffer_info, None)
-                    .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create framebuffer {}: {:?}", i, e)))?
-            };
-            framebuffers.push(framebuffer);
+        for &image_view in image_views {
+            framebuffers.push(pass.create_framebuffer(device, image_view, depth_view, extent)?);
         }
         
         debug!("Created {} framebuffers successfully", framebuffers.len());
         Ok(framebuffers)
     }
     
+    /// Swapchain image the present pass draws into this frame
+    fn present_target(&self, image_index: usize) -> PassTarget {
+        PassTarget {
+            framebuffer: self.framebuffers[image_index],
+            color_image: self.swapchain.swapchain_images[image_index],
+            color_view: self.swapchain.swapchain_image_views[image_index],
+            depth_image: self.depth_buffer.image,
+            depth_view: self.depth_buffer.view,
+            extra_colors: Default::default(),
+        }
+    }
+    
     /// Create a command pool for command buffer allocation
     ///
     /// # Arguments
@@ -287,108 +571,37 @@ impl VulkanRenderer {
     
     /// Create command buffers for rendering
     ///
+    /// Every frame is recorded right before submission, so the buffers are
+    /// only allocated here, one per swapchain image.
+    ///
     /// # Arguments
     /// * `device` - The Vulkan device
     /// * `command_pool` - The command pool to allocate from
-    /// * `graphics_pipeline` - The graphics pipeline to bind
-    /// * `render_pass` - The render pass to use
-    /// * `framebuffers` - The framebuffers to render to
-    /// * `extent` - The render extent
+    /// * `count` - Number of swapchain images
     ///
     /// # Returns
     /// A vector of created command buffers
     ///
     /// # Errors
-    /// Returns an error if command buffer creation or recording fails
+    /// Returns an error if command buffer allocation fails
     fn create_command_buffers(
         device: &Device,
         command_pool: vk::CommandPool,
-        graphics_pipeline: vk::Pipeline,
-        pipeline_layout: vk::PipelineLayout,
-        render_pass: vk::RenderPass,
-        framebuffers: &[vk::Framebuffer],
-        extent: vk::Extent2D,
+        count: usize,
     ) -> Result<Vec<vk::CommandBuffer>> {
-        debug!("Creating {} command buffers", framebuffers.len());
+        debug!("Creating {} command buffers", count);
         
         let alloc_info = vk::CommandBufferAllocateInfo::default()
             .command_pool(command_pool)
             .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(framebuffers.len() as u32);
+            .command_buffer_count(count as u32);
         
         let command_buffers = unsafe {
             device.allocate_command_buffers(&alloc_info)
                 .map_err(|e| VulkanError::CommandBuffer(format!("Failed to allocate command buffers: {:?}", e)))?
         };
         
-        for (i, &command_buffer) in command_buffers.iter().enumerate() {
-            debug!("Recording command buffer {}", i);
-            
-            let begin_info = vk::CommandBufferBeginInfo::default();
-            unsafe {
-                device.begin_command_buffer(command_buffer, &begin_info)
-                    .map_err(|e| VulkanError::CommandBuffer(format!("Failed to begin command buffer {}: {:?}", i, e)))?;
-            }
-            
-            let render_pass_begin_info = vk::RenderPassBeginInfo::default()
-                .render_pass(render_pass)
-                .framebuffer(framebuffers[i])
-                .render_area(vk::Rect2D {
-                    offset: vk::Offset2D { x: 0, y: 0 },
-                    extent,
-                })
-                .clear_values(&[vk::ClearValue {
-                    color: vk::ClearColorValue {
-                        float32: config::rendering::CLEAR_COLOR,
-                    },
-                }]);
-            
-            unsafe {
-                device.cmd_begin_render_pass(command_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
-                debug!("Binding pipeline {:?} in command buffer {}", graphics_pipeline, i);
-                device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, graphics_pipeline);
-                
-                // Set dynamic viewport and scissor
-                let viewport = vk::Viewport {
-                    x: 0.0,
-                    y: 0.0,
-                    width: extent.width as f32,
-                    height: extent.height as f32,
-                    min_depth: 0.0,
-                    max_depth: 1.0,
-                };
-                device.cmd_set_viewport(command_buffer, 0, &[viewport]);
-                
-                let scissor = vk::Rect2D {
-                    offset: vk::Offset2D { x: 0, y: 0 },
-                    extent,
-                };
-                device.cmd_set_scissor(command_buffer, 0, &[scissor]);
-                
-                // Push window data as push constants
-                let aspect_ratio = extent.width as f32 / extent.height as f32;
-                let push_constants = [
-                    extent.width as f32,      // uResolution.x
-                    extent.height as f32,     // uResolution.y
-                    0.0 as f32,               // uTime (placeholder)
-                    aspect_ratio,             // uAspectRatio
-                ];
-                device.cmd_push_constants(
-                    command_buffer,
-                    pipeline_layout,
-                    vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
-                    0,
-                    bytemuck::bytes_of(&push_constants)
-                );
-                
-                device.cmd_draw(command_buffer, 6, 1, 0, 0); // Draw 6 vertices for fullscreen quad
-                device.cmd_end_render_pass(command_buffer);
-                device.end_command_buffer(command_buffer)
-                    .map_err(|e| VulkanError::CommandBuffer(format!("Failed to end command buffer {}: {:?}", i, e)))?;
-            }
-        }
-        
-        debug!("Command buffers created and recorded successfully");
+        debug!("Command buffers created successfully");
         Ok(command_buffers)
     }
     
@@ -448,6 +661,9 @@ impl VulkanRenderer {
     /// # Errors
     /// Returns an error if any part of the drawing process fails
     pub fn draw_frame_with_hud(&mut self, hud: &mut crate::hud::HUD) -> Result<()> {
+        if self.is_suspended() {
+            return Ok(());
+        }
         debug!("Drawing frame {} with HUD", self.current_frame);
         
         // Update time for animation
@@ -455,6 +671,7 @@ impl VulkanRenderer {
         
         unsafe {
             // Wait for the previous frame to finish with timeout to prevent hanging
+            let phase = tracing::debug_span!("wait_fence").entered();
             const FENCE_TIMEOUT_NS: u64 = 1_000_000_000; // 1 second timeout
             
             match self.device.device.wait_for_fences(&[self.in_flight_fences[self.current_frame]], true, FENCE_TIMEOUT_NS) {
@@ -463,82 +680,99 @@ impl VulkanRenderer {
                 }
                 Err(e) => {
                     error!("Fence wait timed out or failed: {:?}. This may indicate a GPU hang.", e);
-                    return Err(VulkanError::Rendering(format!("Fence wait failed: {:?}", e)).into());
+                    return Err(AppError::from_vk(e, Subsystem::Renderer, "wait for in-flight fence")
+                        .with_summary("The GPU stopped responding while waiting for the previous frame"));
                 }
             }
             
+            // The fence guarantees the GPU is done with this frame's scene buffer and queries
+            self.collect_deferred_deletions();
+            self.write_scene_buffer();
+            self.update_residency();
+            self.debug_draw.write(self.current_frame, &self.debug_lines);
+            self.mesh_pass.write_instances(self.current_frame);
+            self.update_resolution_scale();
+            
             // Acquire an image from the swapchain
+            drop(phase);
+            let phase = tracing::debug_span!("acquire").entered();
             let (image_index, _) = self.swapchain.swapchain_loader.acquire_next_image(
                 self.swapchain.swapchain,
                 u64::MAX,
                 self.image_available_semaphores[self.current_frame],
                 vk::Fence::null()
-            ).map_err(|e| VulkanError::Rendering(format!("Failed to acquire next image: {:?}", e)))?;
+            ).vk_context(Subsystem::Renderer, "acquire next image")?;
             
-            // Update push constants with camera matrices
             let extent = self.swapchain.swapchain_extent;
-            let push_constants = [
-                extent.width as f32,      // uResolution.x
-                extent.height as f32,     // uResolution.y
-                self.time,                // uTime
-                self.camera.aspect_ratio,    // uAspectRatio (from camera)
-            ];
+            let scaled_extent = self.composite.scaled_extent(self.resolution_scaler.scale());
             
             // Record command buffer with updated push constants
+            drop(phase);
+            let phase = tracing::debug_span!("record").entered();
             let command_buffer = self.command_buffers[image_index as usize];
             
             // Reset and rerecord command buffer
             self.device.device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())
-                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to reset command buffer: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "reset command buffer")?;
             
             let begin_info = vk::CommandBufferBeginInfo::default();
             self.device.device.begin_command_buffer(command_buffer, &begin_info)
-                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to begin command buffer: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "begin command buffer")?;
 
+            if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+                gpu_timer.begin(command_buffer, self.current_frame);
+            }
+            
+            // Feedback buffers of the toy, or the particles and the shadow map, run before the scene pass
+            let toy_time = self.toy.as_ref().map_or(self.time, Toy::time);
+            self.record_scene_inputs(command_buffer, true, toy_time);
             let pipeline_guard = self.pipeline.lock().unwrap();
-            let render_pass_begin_info = vk::RenderPassBeginInfo::default()
-                .render_pass(pipeline_guard.render_pass)
-                .framebuffer(self.framebuffers[image_index as usize])
-                .render_area(vk::Rect2D {
-                    offset: vk::Offset2D { x: 0, y: 0 },
-                    extent,
-                })
-                .clear_values(&[vk::ClearValue {
-                    color: vk::ClearColorValue {
-                        float32: config::rendering::CLEAR_COLOR,
-                    },
-                }]);
-            
-            self.device.device.cmd_begin_render_pass(command_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
-            self.device.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline_guard.graphics_pipeline);
             
-            // Set dynamic viewport and scissor
-            let viewport = vk::Viewport {
-                x: 0.0,
-                y: 0.0,
-                width: extent.width as f32,
-                height: extent.height as f32,
-                min_depth: 0.0,
-                max_depth: 1.0,
-            };
-            self.device.device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+            // SDF pass into the offscreen target at the scaled resolution
+            self.composite.begin_scene_pass(command_buffer, scaled_extent, &self.viewports.clears(scaled_extent));
+            if let Some(toy) = self.toy.as_mut() {
+                // Shadertoy mode draws the toy image instead of the scene
+                toy.record_image(command_buffer, full_rect(scaled_extent), &self.mouse, toy.time());
+                toy.end_frame();
+            } else {
+                self.device.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline_guard.graphics_pipeline);
+                self.device.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline_guard.pipeline_layout,
+                    0,
+                    &[self.scene_buffer.descriptor_set(self.current_frame)],
+                    &[],
+                );
+                
+                // One fullscreen draw per viewport, each into its own rectangle
+                let tile = Tile::full(scaled_extent);
+                self.record_scene_viewports(command_buffer, &pipeline_guard, scaled_extent, &tile);
+                self.record_mesh_viewports(command_buffer, scaled_extent, &tile);
+                self.record_particle_viewports(command_buffer, scaled_extent, &tile);
+            }
+            self.composite.end_scene_pass(command_buffer);
+            // This frame's shader has seen the press
+            self.mouse.end_frame();
+            
+            if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+                gpu_timer.end(command_buffer, self.current_frame);
+            }
             
-            let scissor = vk::Rect2D {
+            // Composite pass: upscale the SDF target onto the swapchain image
+            let present_target = self.present_target(image_index as usize);
+            let render_area = vk::Rect2D {
                 offset: vk::Offset2D { x: 0, y: 0 },
                 extent,
             };
-            self.device.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
-            
-            // Push updated constants to both vertex and fragment shaders
-            self.device.device.cmd_push_constants(
-                command_buffer,
-                pipeline_guard.pipeline_layout,
-                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
-                0,
-                bytemuck::bytes_of(&push_constants)
-            );
-            
-            self.device.device.cmd_draw(command_buffer, 6, 1, 0, 0); // Draw 6 vertices for fullscreen quad
+            pipeline_guard.pass.begin(&self.device.device, command_buffer, &present_target, render_area, config::rendering::CLEAR_COLOR);
+            self.composite.record_composite(command_buffer, extent, scaled_extent, &self.tonemap);
+            if self.toy.is_none() {
+                self.record_outline(command_buffer, extent, scaled_extent);
+                for (rect, viewport) in self.viewports.rects(extent) {
+                    self.debug_draw.record(command_buffer, self.current_frame, rect, &self.viewports.camera(viewport.kind));
+                }
+            }
             
             // Render HUD
             debug!("Rendering HUD directly");
@@ -548,19 +782,22 @@ impl VulkanRenderer {
             };
             
             // Render ImGui HUD
-            if let Err(e) = hud.render(command_buffer, hud_extent) {
+            hud.gpu_textures_panel.targets = self.composite.debug_targets(scaled_extent);
+            if let Err(e) = tracing::debug_span!("hud").in_scope(|| hud.render(command_buffer, hud_extent)) {
                 error!("Failed to render HUD: {}", e);
             } else {
                 debug!("HUD rendered successfully");
             }
             
-            self.device.device.cmd_end_render_pass(command_buffer);
+            pipeline_guard.pass.end(&self.device.device, command_buffer, &present_target);
             self.device.device.end_command_buffer(command_buffer)
-                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to end command buffer: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "end command buffer")?;
             
             // Reset the fence for this frame
+            drop(phase);
+            let phase = tracing::debug_span!("submit").entered();
             self.device.device.reset_fences(&[self.in_flight_fences[self.current_frame]])
-                .map_err(|e| VulkanError::Rendering(format!("Failed to reset fences: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "reset fences")?;
             
             // Set up the submission info
             let wait_semaphores = [self.image_available_semaphores[self.current_frame]];
@@ -579,9 +816,11 @@ impl VulkanRenderer {
                 self.device.graphics_queue,
                 &[submit_info],
                 self.in_flight_fences[self.current_frame]
-            ).map_err(|e| VulkanError::Rendering(format!("Failed to submit command buffer: {:?}", e)))?;
+            ).vk_context(Subsystem::Renderer, "submit command buffer")?;
             
             // Present the image
+            drop(phase);
+            let _phase = tracing::debug_span!("present").entered();
             let swapchains = [self.swapchain.swapchain];
             let image_indices = [image_index];
             
@@ -591,10 +830,15 @@ impl VulkanRenderer {
                 .image_indices(&image_indices);
             
             self.swapchain.swapchain_loader.queue_present(self.device.present_queue, &present_info)
-                .map_err(|e| VulkanError::Rendering(format!("Failed to present image: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "present image")?;
             
             // Advance to the next frame
+            self.deletion_queue.lock().unwrap().frame_submitted();
+            if let Some(bindless) = self.bindless.as_mut() {
+                bindless.frame_submitted();
+            }
             self.current_frame = (self.current_frame + 1) % config::vulkan::MAX_FRAMES_IN_FLIGHT;
+            self.frame_number += 1;
         }
         
         debug!("Frame {} with HUD completed successfully", self.current_frame);
@@ -610,6 +854,9 @@ impl VulkanRenderer {
     /// # Errors
     /// Returns an error if any part of the drawing process fails
     pub fn draw_frame(&mut self) -> Result<()> {
+        if self.is_suspended() {
+            return Ok(());
+        }
         debug!("Drawing frame {}", self.current_frame);
         
         // Update time for animation
@@ -617,6 +864,7 @@ impl VulkanRenderer {
         
         unsafe {
             // Wait for the previous frame to finish with timeout to prevent hanging
+            let phase = tracing::debug_span!("wait_fence").entered();
             const FENCE_TIMEOUT_NS: u64 = 1_000_000_000; // 1 second timeout
             
             match self.device.device.wait_for_fences(&[self.in_flight_fences[self.current_frame]], true, FENCE_TIMEOUT_NS) {
@@ -625,82 +873,99 @@ impl VulkanRenderer {
                 }
                 Err(e) => {
                     error!("Fence wait timed out or failed: {:?}. This may indicate a GPU hang.", e);
-                    return Err(VulkanError::Rendering(format!("Fence wait failed: {:?}", e)).into());
+                    return Err(AppError::from_vk(e, Subsystem::Renderer, "wait for in-flight fence")
+                        .with_summary("The GPU stopped responding while waiting for the previous frame"));
                 }
             }
             
+            // The fence guarantees the GPU is done with this frame's scene buffer and queries
+            self.collect_deferred_deletions();
+            self.write_scene_buffer();
+            self.update_residency();
+            self.debug_draw.write(self.current_frame, &self.debug_lines);
+            self.mesh_pass.write_instances(self.current_frame);
+            self.update_resolution_scale();
+            
             // Acquire an image from the swapchain
+            drop(phase);
+            let phase = tracing::debug_span!("acquire").entered();
             let (image_index, _) = self.swapchain.swapchain_loader.acquire_next_image(
                 self.swapchain.swapchain,
                 u64::MAX,
                 self.image_available_semaphores[self.current_frame],
                 vk::Fence::null()
-            ).map_err(|e| VulkanError::Rendering(format!("Failed to acquire next image: {:?}", e)))?;
+            ).vk_context(Subsystem::Renderer, "acquire next image")?;
             
-            // Update push constants with camera matrices
             let extent = self.swapchain.swapchain_extent;
-            let push_constants = [
-                extent.width as f32,      // uResolution.x
-                extent.height as f32,     // uResolution.y
-                self.time,                // uTime
-                self.camera.aspect_ratio,    // uAspectRatio (from camera)
-            ];
+            let scaled_extent = self.composite.scaled_extent(self.resolution_scaler.scale());
             
             // Record command buffer with updated push constants
+            drop(phase);
+            let phase = tracing::debug_span!("record").entered();
             let command_buffer = self.command_buffers[image_index as usize];
             
             // Reset and rerecord command buffer
             self.device.device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())
-                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to reset command buffer: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "reset command buffer")?;
             
             let begin_info = vk::CommandBufferBeginInfo::default();
             self.device.device.begin_command_buffer(command_buffer, &begin_info)
-                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to begin command buffer: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "begin command buffer")?;
 
+            if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+                gpu_timer.begin(command_buffer, self.current_frame);
+            }
+            
+            // Feedback buffers of the toy, or the particles and the shadow map, run before the scene pass
+            let toy_time = self.toy.as_ref().map_or(self.time, Toy::time);
+            self.record_scene_inputs(command_buffer, true, toy_time);
             let pipeline_guard = self.pipeline.lock().unwrap();
-            let render_pass_begin_info = vk::RenderPassBeginInfo::default()
-                .render_pass(pipeline_guard.render_pass)
-                .framebuffer(self.framebuffers[image_index as usize])
-                .render_area(vk::Rect2D {
-                    offset: vk::Offset2D { x: 0, y: 0 },
-                    extent,
-                })
-                .clear_values(&[vk::ClearValue {
-                    color: vk::ClearColorValue {
-                        float32: config::rendering::CLEAR_COLOR,
-                    },
-                }]);
-            
-            self.device.device.cmd_begin_render_pass(command_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
-            self.device.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline_guard.graphics_pipeline);
             
-            // Set dynamic viewport and scissor
-            let viewport = vk::Viewport {
-                x: 0.0,
-                y: 0.0,
-                width: extent.width as f32,
-                height: extent.height as f32,
-                min_depth: 0.0,
-                max_depth: 1.0,
-            };
-            self.device.device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+            // SDF pass into the offscreen target at the scaled resolution
+            self.composite.begin_scene_pass(command_buffer, scaled_extent, &self.viewports.clears(scaled_extent));
+            if let Some(toy) = self.toy.as_mut() {
+                // Shadertoy mode draws the toy image instead of the scene
+                toy.record_image(command_buffer, full_rect(scaled_extent), &self.mouse, toy.time());
+                toy.end_frame();
+            } else {
+                self.device.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline_guard.graphics_pipeline);
+                self.device.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline_guard.pipeline_layout,
+                    0,
+                    &[self.scene_buffer.descriptor_set(self.current_frame)],
+                    &[],
+                );
+                
+                // One fullscreen draw per viewport, each into its own rectangle
+                let tile = Tile::full(scaled_extent);
+                self.record_scene_viewports(command_buffer, &pipeline_guard, scaled_extent, &tile);
+                self.record_mesh_viewports(command_buffer, scaled_extent, &tile);
+                self.record_particle_viewports(command_buffer, scaled_extent, &tile);
+            }
+            self.composite.end_scene_pass(command_buffer);
+            // This frame's shader has seen the press
+            self.mouse.end_frame();
             
-            let scissor = vk::Rect2D {
+            if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+                gpu_timer.end(command_buffer, self.current_frame);
+            }
+            
+            // Composite pass: upscale the SDF target onto the swapchain image
+            let present_target = self.present_target(image_index as usize);
+            let render_area = vk::Rect2D {
                 offset: vk::Offset2D { x: 0, y: 0 },
                 extent,
             };
-            self.device.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
-            
-            // Push updated constants to both vertex and fragment shaders
-            self.device.device.cmd_push_constants(
-                command_buffer,
-                pipeline_guard.pipeline_layout,
-                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
-                0,
-                bytemuck::bytes_of(&push_constants)
-            );
-            
-            self.device.device.cmd_draw(command_buffer, 6, 1, 0, 0); // Draw 6 vertices for fullscreen quad
+            pipeline_guard.pass.begin(&self.device.device, command_buffer, &present_target, render_area, config::rendering::CLEAR_COLOR);
+            self.composite.record_composite(command_buffer, extent, scaled_extent, &self.tonemap);
+            if self.toy.is_none() {
+                self.record_outline(command_buffer, extent, scaled_extent);
+                for (rect, viewport) in self.viewports.rects(extent) {
+                    self.debug_draw.record(command_buffer, self.current_frame, rect, &self.viewports.camera(viewport.kind));
+                }
+            }
             
             // Render HUD if available
             if let Some(hud) = self.get_hud_for_rendering() {
@@ -711,7 +976,8 @@ impl VulkanRenderer {
                 };
                 
                 // Render ImGui HUD
-                if let Err(e) = hud.render(command_buffer, hud_extent) {
+                hud.gpu_textures_panel.targets = self.composite.debug_targets(scaled_extent);
+                if let Err(e) = tracing::debug_span!("hud").in_scope(|| hud.render(command_buffer, hud_extent)) {
                     error!("Failed to render HUD: {}", e);
                 } else {
                     debug!("HUD rendered successfully");
@@ -720,13 +986,15 @@ impl VulkanRenderer {
                 debug!("No HUD available for rendering");
             }
             
-            self.device.device.cmd_end_render_pass(command_buffer);
+            pipeline_guard.pass.end(&self.device.device, command_buffer, &present_target);
             self.device.device.end_command_buffer(command_buffer)
-                .map_err(|e| VulkanError::CommandBuffer(format!("Failed to end command buffer: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "end command buffer")?;
             
             // Reset the fence for this frame
+            drop(phase);
+            let phase = tracing::debug_span!("submit").entered();
             self.device.device.reset_fences(&[self.in_flight_fences[self.current_frame]])
-                .map_err(|e| VulkanError::Rendering(format!("Failed to reset fences: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "reset fences")?;
             
             // Set up the submission info
             let wait_semaphores = [self.image_available_semaphores[self.current_frame]];
@@ -745,9 +1013,11 @@ impl VulkanRenderer {
                 self.device.graphics_queue,
                 &[submit_info],
                 self.in_flight_fences[self.current_frame]
-            ).map_err(|e| VulkanError::Rendering(format!("Failed to submit command buffer: {:?}", e)))?;
+            ).vk_context(Subsystem::Renderer, "submit command buffer")?;
             
             // Present the image
+            drop(phase);
+            let _phase = tracing::debug_span!("present").entered();
             let swapchains = [self.swapchain.swapchain];
             let image_indices = [image_index];
             
@@ -757,16 +1027,90 @@ impl VulkanRenderer {
                 .image_indices(&image_indices);
             
             self.swapchain.swapchain_loader.queue_present(self.device.present_queue, &present_info)
-                .map_err(|e| VulkanError::Rendering(format!("Failed to present image: {:?}", e)))?;
+                .vk_context(Subsystem::Renderer, "present image")?;
             
             // Advance to the next frame
+            self.deletion_queue.lock().unwrap().frame_submitted();
+            if let Some(bindless) = self.bindless.as_mut() {
+                bindless.frame_submitted();
+            }
             self.current_frame = (self.current_frame + 1) % config::vulkan::MAX_FRAMES_IN_FLIGHT;
+            self.frame_number += 1;
         }
         
         debug!("Frame {} completed successfully", self.current_frame);
         Ok(())
     }
     
+    /// Whether the surface is gone, between `suspend` and `resume`
+    pub fn is_suspended(&self) -> bool {
+        self.surface.surface == vk::SurfaceKHR::null()
+    }
+
+    /// Release the surface and everything that depends on it
+    ///
+    /// Called when the app is suspended (Android moving to the background,
+    /// a laptop going to sleep): the window's surface may be destroyed by the
+    /// platform, so the swapchain, its framebuffers and the surface are
+    /// destroyed. The device, pipelines, scene buffers and the offscreen
+    /// targets are kept, and frames are skipped until `resume`.
+    ///
+    /// # Errors
+    /// Returns an error if the device cannot be waited on
+    pub fn suspend(&mut self) -> Result<()> {
+        if self.is_suspended() {
+            return Ok(());
+        }
+        info!("Suspending renderer: releasing the surface and swapchain");
+        self.device.safe_device_wait_idle()
+            .context(Subsystem::Renderer, "wait for device idle before suspending")?;
+        unsafe {
+            for &framebuffer in &self.framebuffers {
+                self.device.device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+        self.framebuffers.clear();
+        self.swapchain.release(&self.device);
+        unsafe {
+            self.surface.surface_loader.destroy_surface(self.surface.surface, None);
+        }
+        self.surface.surface = vk::SurfaceKHR::null();
+        Ok(())
+    }
+
+    /// Recreate the surface and the swapchain after `suspend`
+    ///
+    /// Only the surface-dependent resources are rebuilt, the same way a
+    /// resize rebuilds them; the rest of the renderer is reused as is.
+    ///
+    /// # Arguments
+    /// * `window` - The window to present to again
+    ///
+    /// # Errors
+    /// Returns an error if the surface cannot be created or presented to,
+    /// or if the swapchain cannot be recreated
+    pub fn resume(&mut self, window: &Window) -> Result<()> {
+        if !self.is_suspended() {
+            return Ok(());
+        }
+        info!("Resuming renderer: recreating the surface and swapchain");
+        let surface = Self::create_surface(&self.instance.entry, &self.instance.instance, window)?;
+        let present_family = self.device.queue_families.present_family
+            .ok_or_else(|| VulkanError::SurfaceCreation("No present queue family".to_string()))?;
+        let supported = unsafe {
+            self.surface.surface_loader.get_physical_device_surface_support(self.device.physical_device, present_family, surface)
+        }.unwrap_or(false);
+        if !supported {
+            unsafe {
+                self.surface.surface_loader.destroy_surface(surface, None);
+            }
+            return Err(VulkanError::SurfaceCreation("The new surface cannot be presented to by the device".to_string()).into());
+        }
+        self.surface.surface = surface;
+
+        let size = window.inner_size();
+        self.handle_resize(size.width, size.height)
+    }
     
     /// Handle window resize
     ///
@@ -778,8 +1122,14 @@ impl VulkanRenderer {
     /// * Ok(()) if resize was handled successfully
     /// * Err if resize handling failed
     pub fn handle_resize(&mut self, new_width: u32, new_height: u32) -> Result<()> {
+        if self.is_suspended() {
+            debug!("Ignoring resize to {}x{} while suspended", new_width, new_height);
+            return Ok(());
+        }
         info!("Handling window resize to {}x{}", new_width, new_height);
         
+        // Resize still waits for idle: the swapchain images and the composite
+        // descriptor set are replaced in place rather than through the deletion queue.
         // Use safe device wait to prevent hanging
         match self.device.safe_device_wait_idle() {
             Ok(_) => {
@@ -798,9 +1148,8 @@ impl VulkanRenderer {
                 debug!("Swapchain recreated successfully");
             }
             Err(e) => {
-                error!("Failed to recreate swapchain: {}. Attempting partial recovery.", e);
-                // Try to continue with existing swapchain if recreation fails
-                return Err(VulkanError::SwapchainCreation(format!("Failed to recreate swapchain: {}", e)).into());
+                // The caller reports it and keeps the existing swapchain
+                return Err(e.context(Subsystem::Renderer, "recreate swapchain"));
             }
         }
         
@@ -814,6 +1163,9 @@ impl VulkanRenderer {
             return Err(e);
         }
         
+        // Resize the offscreen SDF target to match the new swapchain
+        self.resize_offscreen()?;
+        
         // Recreate command buffers with error handling
         if let Err(e) = self.recreate_command_buffers() {
             error!("Failed to recreate command buffers: {}. Vulkan state may be inconsistent.", e);
@@ -824,6 +1176,63 @@ impl VulkanRenderer {
         Ok(())
     }
     
+    /// Recreate the offscreen SDF target and toy buffers for the swapchain and render scale
+    ///
+    /// The caller must ensure the device is idle.
+    fn resize_offscreen(&mut self) -> Result<()> {
+        let extent = resolution_scaler::target_extent(
+            self.swapchain.swapchain_extent,
+            self.target_scale,
+            self.device.capabilities.max_image_dimension_2d(),
+        );
+        if let Err(e) = self.composite.resize(extent) {
+            error!("Failed to resize offscreen SDF target: {}. Vulkan state may be inconsistent.", e);
+            return Err(e);
+        }
+        self.outline.set_selection_view(self.composite.selection_view());
+        if let Some(toy) = self.toy.as_mut() {
+            if let Err(e) = toy.resize(&self.composite.scene_pass, extent) {
+                error!("Failed to resize toy buffers: {}. Vulkan state may be inconsistent.", e);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Size the offscreen SDF target relative to the window
+    ///
+    /// The HUD keeps drawing at the swapchain resolution; the composite
+    /// pass upscales or downsamples the scene onto it.
+    ///
+    /// # Arguments
+    /// * `scale` - Target size relative to the swapchain, clamped to `MIN_RENDER_SCALE..=MAX_RENDER_SCALE`
+    ///
+    /// # Errors
+    /// Returns an error if the target cannot be recreated
+    pub fn set_target_scale(&mut self, scale: f32) -> Result<()> {
+        let scale = resolution_scaler::clamp_render_scale(scale);
+        if scale == self.target_scale {
+            return Ok(());
+        }
+        self.target_scale = scale;
+        if self.is_suspended() {
+            // Applied with the next resize on resume
+            return Ok(());
+        }
+        if let Err(e) = self.device.safe_device_wait_idle() {
+            error!("Failed to wait for device idle before changing the render scale: {}", e);
+        }
+        self.resize_offscreen()?;
+        let extent = self.composite.target_extent();
+        info!("Render scale {:.0}%, SDF target {}x{}", scale * 100.0, extent.width, extent.height);
+        Ok(())
+    }
+    
+    /// Size of the offscreen SDF target, the window size times the render scale
+    pub fn target_extent(&self) -> vk::Extent2D {
+        self.composite.target_extent()
+    }
+    
     /// Recreate framebuffers after resize
     ///
     /// # Returns
@@ -837,12 +1246,20 @@ impl VulkanRenderer {
             }
         }
 
+        // Replace the depth buffer with one of the new size
+        let memory_properties = unsafe {
+            self.instance.instance.get_physical_device_memory_properties(self.device.physical_device)
+        };
+        let depth_buffer = DepthBuffer::new(&self.device.device, &memory_properties, self.swapchain.swapchain_extent, DepthUsage::Discard)?;
+        std::mem::replace(&mut self.depth_buffer, depth_buffer).destroy(&self.device.device);
+
         // Create new framebuffers
         let pipeline_guard = self.pipeline.lock().unwrap();
         self.framebuffers = Self::create_framebuffers(
             &self.device.device,
-            pipeline_guard.render_pass,
+            &pipeline_guard.pass,
             &self.swapchain.swapchain_image_views,
+            self.depth_buffer.view,
             self.swapchain.swapchain_extent
         )?;
         
@@ -854,77 +1271,911 @@ impl VulkanRenderer {
     /// # Returns
     /// * Ok(()) if command buffers were recreated successfully
     /// * Err if command buffer recreation failed
+    #[tracing::instrument(skip_all)]
     fn recreate_command_buffers(&mut self) -> Result<()> {
-        info!("=== RECREATING COMMAND BUFFERS AFTER HOT RELOAD ===");
+        // Old command buffers may still be in flight, free them once their frames complete
+        let old_command_buffers = std::mem::take(&mut self.command_buffers);
+        self.deletion_queue.lock().unwrap().defer(DeferredResource::CommandBuffers(self.command_pool, old_command_buffers));
+        info!("Old command buffers queued for deletion");
 
-        // CRITICAL: Wait for GPU to complete ALL work before destroying command buffers
-        // Command buffers might still be in flight when hot reload occurs
-        info!("Waiting for GPU to complete all work before command buffer recreation");
-        unsafe {
-            self.device.device.device_wait_idle()
-                .map_err(|e| VulkanError::Rendering(format!("Failed to wait for device idle before command buffer recreation: {:?}", e)))?;
-        }
-        info!("GPU idle confirmed, safe to recreate command buffers");
+        self.command_buffers = Self::create_command_buffers(&self.device.device, self.command_pool, self.framebuffers.len())?;
+        info!("New command buffers created");
+        Ok(())
+    }
 
-        // Free old command buffers
-        unsafe {
-            self.device.device.free_command_buffers(self.command_pool, &self.command_buffers);
+    /// Change the preferred presentation mode
+    ///
+    /// Recreates the swapchain at its current size if the preference changed.
+    ///
+    /// # Arguments
+    /// * `present_mode` - The new preference; FIFO is used when the surface lacks it
+    ///
+    /// # Errors
+    /// Returns an error if the swapchain cannot be recreated
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> Result<()> {
+        if present_mode == self.swapchain.present_mode {
+            return Ok(());
         }
-        info!("Old command buffers freed");
+        info!("Switching present mode to {}", present_mode.name());
+        self.swapchain.present_mode = present_mode;
+        let extent = self.swapchain.swapchain_extent;
+        self.handle_resize(extent.width, extent.height)
+    }
 
-    // Create new command buffers with the new pipeline
-        let pipeline_guard = self.pipeline.lock().unwrap();
-        debug!("Using pipeline handle for command buffer recreation");
-        self.command_buffers = Self::create_command_buffers(
-            &self.device.device,
-            self.command_pool,
-            pipeline_guard.graphics_pipeline,
-            pipeline_guard.pipeline_layout,
-            pipeline_guard.render_pass,
-            &self.framebuffers,
-            self.swapchain.swapchain_extent
-        )?;
-        info!("New command buffers created and recorded with updated pipeline");
+    /// Presentation mode the swapchain is using
+    pub fn active_present_mode(&self) -> vk::PresentModeKHR {
+        self.swapchain.active_present_mode
+    }
 
-        info!("=== COMMAND BUFFER RECREATION COMPLETED SUCCESSFULLY ===");
+    /// Change the raymarch quality tier
+    ///
+    /// Recreates the graphics pipeline with new specialization constants and
+    /// rerecords the command buffers if the tier actually changed.
+    ///
+    /// # Arguments
+    /// * `quality` - The new quality tier
+    pub fn set_raymarch_quality(&mut self, quality: RaymarchQuality) -> Result<()> {
+        let recreated = self.pipeline.lock().unwrap().set_quality(quality)?;
+        if recreated {
+            self.prepare_split_compare()?;
+            self.recreate_command_buffers()?;
+        }
         Ok(())
     }
 
-    /// Update command buffers after pipeline recreation (for hot reload)
+    /// Change the shading feature variant
     ///
-    /// # Returns
-    /// * Ok(()) if command buffers were updated successfully
-    /// * Err if command buffer update failed
-    pub fn update_command_buffers_after_hot_reload(&mut self) -> Result<()> {
-        info!("Updating command buffers after hot reload");
-        self.recreate_command_buffers()
+    /// Switches to the cached pipeline for the variant, building it on first
+    /// use, and rerecords the command buffers if the variant changed.
+    ///
+    /// # Arguments
+    /// * `variant` - The new shader variant
+    pub fn set_shader_variant(&mut self, variant: ShaderVariantKey) -> Result<()> {
+        let changed = self.pipeline.lock().unwrap().set_variant(variant)?;
+        if changed {
+            self.prepare_split_compare()?;
+            self.recreate_command_buffers()?;
+        }
+        Ok(())
     }
 
+    /// Change the split-screen comparison
+    ///
+    /// Builds the comparison pipeline if needed. Viewports are drawn unsplit
+    /// while it is unavailable, e.g. `LastGood` before the first hot reload.
+    ///
+    /// # Arguments
+    /// * `split_compare` - Divider position and the pipeline right of it
+    ///
+    /// # Errors
+    /// Returns an error if the comparison pipeline cannot be built; the
+    /// settings are kept and the viewports stay unsplit
+    pub fn set_split_compare(&mut self, split_compare: SplitCompare) -> Result<()> {
+        self.split_compare = split_compare;
+        self.prepare_split_compare()
+    }
 
-    /// Get HUD reference for rendering (unsafe - used during render pass)
-    fn get_hud_for_rendering(&self) -> Option<&mut crate::hud::HUD> {
-        debug!("Getting HUD reference for rendering, current reference: {:?}", self.hud_reference);
-        unsafe {
-            self.hud_reference.map(|ptr| {
-                debug!("Dereferencing HUD pointer: {:?}", ptr);
-                &mut *ptr
-            })
+    /// Build the comparison pipeline of the current split settings
+    ///
+    /// Pipeline switches and hot reloads drop comparison pipelines built
+    /// for the old state, so this runs again after each of them.
+    fn prepare_split_compare(&mut self) -> Result<()> {
+        if !self.split_compare.enabled {
+            return Ok(());
         }
+        self.pipeline.lock().unwrap().prepare_comparison(&self.split_compare.source)
     }
-}
 
-impl Drop for VulkanRenderer {
-    fn drop(&mut self) {
-        debug!("Starting VulkanRenderer cleanup");
-        
-        unsafe {
-            // Clean up resources in proper dependency order
-            // With proper shutdown sequencing, no device_wait_idle() should be needed
-            
-            // 1. Clean up sync objects first (they don't depend on other resources)
-            debug!("Cleaning up sync objects");
-            for &fence in &self.in_flight_fences {
-                self.device.device.destroy_fence(fence, None);
+    /// Change the shader compiler settings used for the next reload
+    ///
+    /// # Arguments
+    /// * `settings` - Optimization level, target environment and debug info
+    pub fn set_shader_compile_settings(&mut self, settings: ShaderCompileSettings) {
+        if self.pipeline.lock().unwrap().set_compile_settings(settings) {
+            info!("Shader compile settings will apply on the next shader reload");
+        }
+    }
+
+    /// Set the scene data uploaded with the next frame
+    ///
+    /// # Arguments
+    /// * `scene_data` - Shapes and lights collected from the ECS world
+    pub fn set_scene_data(&mut self, scene_data: GpuSceneData) {
+        self.scene_data = scene_data;
+    }
+
+    /// Set the background and upload its environment map once it is decoded
+    ///
+    /// The map is uploaded in the background; until it is ready the pink
+    /// loading texture is shown. A map that fails to load leaves the
+    /// previous one shown.
+    ///
+    /// # Arguments
+    /// * `background` - Background settings from the HUD
+    /// * `map` - Decoded image of the environment map file, None while it is loading
+    /// * `map_failed` - Whether the environment map file could not be read or decoded
+    ///
+    /// # Errors
+    /// Returns an error if the environment map cannot be uploaded; the
+    /// settings are applied regardless
+    pub fn set_background(&mut self, background: BackgroundSettings, map: Option<&HdrImage>, map_failed: bool) -> Result<()> {
+        let result = self.environment_map.request(
+            background.hdri_path.as_deref(),
+            map,
+            map_failed,
+            &self.instance.instance,
+            self.device.physical_device,
+            &mut self.upload.lock().unwrap(),
+        );
+        self.background = background;
+        result
+    }
+
+    /// Uniform and push constant blocks of the active SDF fragment shader,
+    /// with the UI annotations of their members
+    ///
+    /// # Errors
+    /// Returns an error if the shader cannot be compiled or reflected
+    pub fn shader_reflection(&self) -> Result<(Vec<UniformBlock>, ShaderAnnotations)> {
+        self.pipeline.lock().unwrap().reflect_fragment_shader()
+    }
+
+    /// File of the environment map in use, None if none is loaded
+    pub fn environment_map_path(&self) -> Option<&std::path::Path> {
+        self.environment_map.loaded_path()
+    }
+
+    /// Whether an environment map is still being uploaded
+    pub fn is_environment_map_loading(&self) -> bool {
+        self.environment_map.is_loading()
+    }
+
+    /// Upload an environment map again after its file changed on disk
+    ///
+    /// The previous version stays bound until the new image, passed with
+    /// `set_background`, has been uploaded.
+    pub fn reload_environment_map(&mut self, path: &std::path::Path) {
+        self.environment_map.reload(path, &mut self.deletion_queue.lock().unwrap());
+    }
+
+    /// Set the debug lines drawn with the next frame
+    ///
+    /// # Arguments
+    /// * `lines` - Bounds and wireframes collected from the ECS world
+    pub fn set_debug_lines(&mut self, lines: DebugDrawList) {
+        self.debug_lines = lines;
+    }
+
+    /// Set the meshes drawn with the next frame
+    ///
+    /// Meshes not drawn before start uploading here and are drawn once uploaded.
+    ///
+    /// # Arguments
+    /// * `draws` - Mesh instances collected from the ECS world
+    pub fn set_mesh_draws(&mut self, draws: MeshDrawList) {
+        self.mesh_pass.set_draws(draws, &mut self.upload.lock().unwrap());
+    }
+
+    /// Mesh space bounding spheres of the uploaded meshes, for culling
+    pub fn mesh_bounds(&self) -> HashMap<String, (Vector3<f32>, f32)> {
+        self.mesh_pass.mesh_bounds()
+    }
+
+    /// Upload a mesh again after its file changed on disk
+    ///
+    /// The new version is uploaded from the data of the next mesh draws.
+    pub fn reload_mesh(&mut self, path: &str) {
+        self.mesh_pass.reload(path, &mut self.deletion_queue.lock().unwrap());
+    }
+
+    /// Set the texts drawn from the next frame on
+    ///
+    /// # Arguments
+    /// * `draws` - Texts collected from the ECS world
+    pub fn set_text_draws(&mut self, draws: TextDrawList) {
+        self.text.set_draws(draws, &self.instance.instance, self.device.physical_device, &mut self.upload.lock().unwrap());
+    }
+
+    /// Set the particle emitters simulated with the next frame
+    ///
+    /// # Arguments
+    /// * `emitters` - Emitters collected from the ECS world
+    pub fn set_particle_emitters(&mut self, emitters: ParticleEmitterList) {
+        self.particles.set_emitters(emitters);
+    }
+
+    /// Upload the scene data and background for the current frame
+    ///
+    /// Binds a newly uploaded environment map and text atlas first. Must be
+    /// called after waiting on the current frame's fence.
+    fn write_scene_buffer(&mut self) {
+        let environment = self.environment_map.poll(&mut self.upload.lock().unwrap(), &mut self.deletion_queue.lock().unwrap());
+        if let Some(environment) = environment {
+            self.scene_buffer.set_environment(environment);
+        }
+        let text_atlas = self.text.poll(&mut self.upload.lock().unwrap(), &mut self.deletion_queue.lock().unwrap());
+        if let Some(text_atlas) = text_atlas {
+            self.scene_buffer.set_text_atlas(text_atlas);
+        }
+
+        let has_map = self.environment_map.loaded_path().is_some();
+        self.scene_data.background = GpuBackground::new(&self.background, has_map);
+        self.scene_data.mouse = GpuMouse::new(&self.mouse);
+        self.text.write(&mut self.scene_data);
+        self.scene_buffer.write(self.current_frame, &self.scene_data);
+        self.scene_buffer.write_params(self.current_frame, &self.shader_params);
+    }
+
+    /// Destroy resources that no frame in flight can use anymore
+    ///
+    /// Pipelines replaced by hot reload or render setting changes are queued
+    /// first. Released bindless texture slots become reusable as well. Must be
+    /// called after waiting on the current frame's fence.
+    fn collect_deferred_deletions(&mut self) {
+        let retired = self.pipeline.lock().unwrap().take_retired();
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        for (pipeline_layout, graphics_pipeline) in retired {
+            deletion_queue.defer(DeferredResource::Pipeline(graphics_pipeline));
+            deletion_queue.defer(DeferredResource::PipelineLayout(pipeline_layout));
+        }
+        deletion_queue.collect(&self.device.device);
+        if let Some(bindless) = self.bindless.as_mut() {
+            bindless.reclaim();
+        }
+    }
+
+    /// Read the GPU time of the frame that just completed and adjust the render scale
+    ///
+    /// Must be called after waiting on the current frame's fence.
+    fn update_resolution_scale(&mut self) {
+        let Some(gpu_ms) = self.gpu_timer.as_mut().and_then(|timer| timer.read_ms(self.current_frame)) else {
+            return;
+        };
+
+        self.last_gpu_ms = Some(gpu_ms);
+        if self.resolution_scaler.record(gpu_ms) {
+            debug!("SDF render scale changed to {:.2} (GPU {:.2} ms)", self.resolution_scaler.scale(), gpu_ms);
+        }
+    }
+
+    /// Most recent GPU time of the SDF pass in milliseconds
+    ///
+    /// # Returns
+    /// None if timestamps are unsupported or nothing was measured yet
+    pub fn gpu_frame_ms(&self) -> Option<f32> {
+        self.last_gpu_ms
+    }
+
+    /// Cached meshes and environment maps against their budget
+    pub fn residency_usage(&self) -> ResidencyUsage {
+        self.residency.usage()
+    }
+
+    /// Evict the cached meshes and environment maps unused for longest while over budget
+    ///
+    /// Must be called after waiting on the current frame's fence.
+    fn update_residency(&mut self) {
+        self.residency.update(self.mesh_pass.residents().chain(self.environment_map.residents()));
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        for asset in self.residency.evict() {
+            match asset {
+                ResidentAsset::Mesh(path) => self.mesh_pass.evict(&path),
+                ResidentAsset::EnvironmentMap(path) => self.environment_map.evict(&path, &mut deletion_queue),
+            }
+        }
+    }
+
+    /// Size, usage and budget of the memory heaps of the GPU
+    ///
+    /// # Returns
+    /// The heaps, without usage and budget if `VK_EXT_memory_budget` is not enabled
+    pub fn heap_budgets(&self) -> Vec<memory_usage::HeapBudget> {
+        memory_usage::heap_budgets(&self.instance.instance, self.device.physical_device, self.device.memory_budget)
+    }
+
+    /// Record the SDF draw of every viewport into the current scene pass
+    ///
+    /// With split comparison on, the viewports are drawn twice: left of the
+    /// divider with the active pipeline, then right of it with the
+    /// comparison pipeline, using the same cameras.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the scene render pass, with the SDF pipeline bound
+    /// * `pipeline` - The bound SDF pipeline
+    /// * `scaled_extent` - Size of the image the viewports cover
+    /// * `tile` - Area of the image the target covers
+    fn record_scene_viewports(&self, command_buffer: vk::CommandBuffer, pipeline: &VulkanPipeline, scaled_extent: vk::Extent2D, tile: &Tile) {
+        let comparison = Some(&self.split_compare)
+            .filter(|split| split.enabled)
+            .and_then(|split| pipeline.comparison(&split.source));
+        
+        let Some((compare_layout, compare_pipeline)) = comparison else {
+            self.record_viewport_draws(command_buffer, pipeline.pipeline_layout, scaled_extent, tile, |rect| rect);
+            return;
+        };
+        
+        self.record_viewport_draws(command_buffer, pipeline.pipeline_layout, scaled_extent, tile, |rect| self.split_compare.split(rect).0);
+        unsafe {
+            self.device.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, compare_pipeline);
+            self.device.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                compare_layout,
+                0,
+                &[self.scene_buffer.descriptor_set(self.current_frame)],
+                &[],
+            );
+        }
+        self.record_viewport_draws(command_buffer, compare_layout, scaled_extent, tile, |rect| self.split_compare.split(rect).1);
+    }
+    
+    /// Direction towards the sun (xyz) and its intensity (w), as the mesh shaders take it
+    fn sun_direction(&self) -> [f32; 4] {
+        let sun = self.sun.direction();
+        [sun.x, sun.y, sun.z, self.sun.intensity]
+    }
+
+    /// Record the passes the scene pass reads from
+    ///
+    /// These are the toy's feedback buffers in Shadertoy mode, otherwise the
+    /// particle simulation and the mesh shadow map with its barrier. Every
+    /// command buffer that begins a scene pass records them through here first.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer outside of any pass
+    /// * `simulate` - Whether to step the toy buffers or particles; the shadow map is always drawn
+    /// * `toy_time` - Time the toy buffers are stepped at
+    fn record_scene_inputs(&mut self, command_buffer: vk::CommandBuffer, simulate: bool, toy_time: f32) {
+        if let Some(toy) = self.toy.as_mut() {
+            if simulate {
+                toy.record_buffers(command_buffer, &self.composite.scene_pass, &self.mouse, toy_time);
+            }
+            return;
+        }
+        if simulate {
+            self.particles.record_simulation(command_buffer, self.current_frame);
+        }
+        self.mesh_pass.record_shadows(command_buffer, self.current_frame, self.sun_direction());
+    }
+
+    /// Record the mesh draws of every viewport into the current scene pass
+    ///
+    /// Must follow the SDF draws so meshes are depth tested against them.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the scene render pass
+    /// * `scaled_extent` - Size of the image the viewports cover
+    /// * `tile` - Area of the image the target covers
+    fn record_mesh_viewports(&self, command_buffer: vk::CommandBuffer, scaled_extent: vk::Extent2D, tile: &Tile) {
+        let sun_direction = self.sun_direction();
+        for (rect, viewport) in self.viewports.rects(scaled_extent) {
+            let camera = self.viewports.camera(viewport.kind);
+            self.mesh_pass.record(command_buffer, self.current_frame, rect, tile, &camera, viewport.visible_layers, sun_direction);
+        }
+    }
+    
+    /// Record the particle draws of every viewport into the current scene pass
+    ///
+    /// Must follow the SDF and mesh draws so particles are depth tested against them.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the scene render pass
+    /// * `scaled_extent` - Size of the image the viewports cover
+    /// * `tile` - Area of the image the target covers
+    fn record_particle_viewports(&self, command_buffer: vk::CommandBuffer, scaled_extent: vk::Extent2D, tile: &Tile) {
+        for (rect, viewport) in self.viewports.rects(scaled_extent) {
+            self.particles.record(command_buffer, self.current_frame, rect, tile, &self.viewports.camera(viewport.kind));
+        }
+    }
+    
+    /// Record the selection outline over the composited scene
+    ///
+    /// Skipped when disabled or when nothing is selected, sparing the search
+    /// around every pixel.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the present pass
+    /// * `extent` - Swapchain extent
+    /// * `scaled_extent` - Region of the offscreen target that was rendered
+    fn record_outline(&self, command_buffer: vk::CommandBuffer, extent: vk::Extent2D, scaled_extent: vk::Extent2D) {
+        if self.outline_settings.enabled && self.scene_data.has_selection() {
+            self.outline.record(command_buffer, extent, self.composite.uv_scale(scaled_extent), &self.outline_settings);
+        }
+    }
+    
+    /// Record one fullscreen draw per viewport with the bound pipeline
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the scene render pass
+    /// * `pipeline_layout` - Layout of the bound SDF pipeline
+    /// * `scaled_extent` - Size of the image the viewports cover
+    /// * `tile` - Area of the image the target covers
+    /// * `scissor` - Maps a viewport rectangle to the part of it to draw
+    fn record_viewport_draws<F>(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_layout: vk::PipelineLayout,
+        scaled_extent: vk::Extent2D,
+        tile: &Tile,
+        scissor: F,
+    )
+    where
+        F: Fn(vk::Rect2D) -> vk::Rect2D,
+    {
+        let base = SdfPushConstants::new(
+            scaled_extent.width,
+            scaled_extent.height,
+            self.time,
+            self.camera.aspect_ratio,
+            &self.sun,
+        )
+        .with_grid(&self.viewports.grid);
+        
+        for (rect, viewport) in self.viewports.rects(scaled_extent) {
+            let Some(scissor_rect) = tile.scissor(scissor(rect)) else {
+                continue;
+            };
+            
+            let push_constants = base
+                .with_view(
+                    &self.viewports.camera(viewport.kind),
+                    viewport.render_mode,
+                    rect.extent.width,
+                    rect.extent.height,
+                )
+                .with_clear_color(viewport.clear_color)
+                .with_layers(viewport.visible_layers);
+            unsafe {
+                self.device.device.cmd_set_viewport(command_buffer, 0, &[tile.viewport(&rect)]);
+                self.device.device.cmd_set_scissor(command_buffer, 0, &[scissor_rect]);
+                
+                // Push updated constants to both vertex and fragment shaders
+                self.device.device.cmd_push_constants(
+                    command_buffer,
+                    pipeline_layout,
+                    vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                    0,
+                    bytemuck::bytes_of(&push_constants)
+                );
+                
+                self.device.device.cmd_draw(command_buffer, 6, 1, 0, 0); // Draw 6 vertices for fullscreen quad
+            }
+        }
+    }
+    
+    /// Scene time of the realtime frames in seconds
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+    
+    /// Number of frames submitted so far
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+    
+    /// Current adaptive SDF render scale (fraction of the offscreen target)
+    pub fn render_scale(&self) -> f32 {
+        self.resolution_scaler.scale()
+    }
+    
+    /// How the swapchain expects colors to be encoded (SDR or an HDR output)
+    pub fn display_output(&self) -> DisplayOutput {
+        self.swapchain.display_output
+    }
+
+    /// Create an offscreen target for frame export
+    ///
+    /// # Arguments
+    /// * `width` - Width of the exported frames
+    /// * `height` - Height of the exported frames
+    ///
+    /// # Errors
+    /// Returns an error if creation fails
+    pub fn create_frame_exporter(&self, width: u32, height: u32) -> Result<FrameExporter> {
+        FrameExporter::new(
+            &self.instance.instance,
+            self.device.physical_device,
+            &self.device.device,
+            self.device.graphics_queue,
+            self.device.queue_families.graphics_family.unwrap(),
+            &self.composite.scene_pass,
+            vk::Extent2D { width, height },
+        )
+    }
+
+    /// Render the scene at a given time into an export target and read it back
+    ///
+    /// Uses the current viewports, camera, sun and split comparison, at the
+    /// export resolution instead of the swapchain's; in shadertoy mode the
+    /// toy shader is rendered at the given time instead. Waits for the device to
+    /// go idle first, since the scene buffer of the current frame is reused.
+    ///
+    /// # Arguments
+    /// * `exporter` - The export target
+    /// * `time` - Scene time of the frame in seconds
+    ///
+    /// # Returns
+    /// The frame as tightly packed RGBA8 pixels
+    ///
+    /// # Errors
+    /// Returns an error if recording, submission or readback fails
+    pub fn export_frame(&mut self, exporter: &FrameExporter, time: f32) -> Result<Vec<u8>> {
+        let tonemap = self.tonemap;
+        self.render_frame_export(exporter, time, |pass| exporter.finish(pass, &tonemap))
+    }
+
+    /// Render the current frame into an export target and read it back in HDR
+    ///
+    /// Like `export_frame`, without tonemapping, so the linear frame can be
+    /// saved for grading in other tools.
+    ///
+    /// # Arguments
+    /// * `exporter` - The export target
+    /// * `time` - Scene time of the frame in seconds
+    ///
+    /// # Returns
+    /// Four half floats (bit patterns) per pixel in linear RGBA, top row first
+    ///
+    /// # Errors
+    /// Returns an error if recording, submission or readback fails
+    pub fn export_frame_hdr(&mut self, exporter: &FrameExporter, time: f32) -> Result<Vec<u16>> {
+        self.render_frame_export(exporter, time, |pass| exporter.finish_hdr(pass))
+    }
+
+    /// Record the whole frame into an export target at a scene time and read it back
+    fn render_frame_export<T>(
+        &mut self,
+        exporter: &FrameExporter,
+        time: f32,
+        read_back: impl FnOnce(&PassLayout) -> Result<T>,
+    ) -> Result<T> {
+        unsafe {
+            self.device.device.device_wait_idle()
+                .vk_context(Subsystem::Renderer, "wait for device idle before frame export")?;
+        }
+        self.write_scene_buffer();
+        self.mesh_pass.write_instances(self.current_frame);
+
+        let realtime = std::mem::replace(&mut self.time, time);
+        let extent = exporter.extent();
+        let result = self.record_export(exporter, extent, &Tile::full(extent), true)
+            .and_then(|()| read_back(&self.composite.scene_pass));
+        self.time = realtime;
+        result
+    }
+
+    /// Render the current frame at any resolution and read it back in HDR
+    ///
+    /// The swapchain and the offscreen target are left alone. Stills larger
+    /// than `config::still_export::TILE_SIZE` (or the device's image size
+    /// limit) are rendered tile by tile into one export target, each tile
+    /// with the viewports and cameras of the whole still. Waits for the
+    /// device to go idle first, like `export_frame`.
+    ///
+    /// # Arguments
+    /// * `width` - Width of the still, at most `MAX_SIZE` and the device's viewport limit
+    /// * `height` - Height of the still, likewise
+    ///
+    /// # Returns
+    /// Four half floats (bit patterns) per pixel in linear RGBA, top row first
+    ///
+    /// # Errors
+    /// Returns an error if the size is out of range, if a toy would need
+    /// tiles, or if rendering or readback fails
+    pub fn export_still(&mut self, width: u32, height: u32) -> Result<Vec<u16>> {
+        let [max_width, max_height] = self.device.capabilities.max_viewport_dimensions()
+            .map(|max| max.min(config::still_export::MAX_SIZE));
+        if width == 0 || height == 0 || width > max_width || height > max_height {
+            return Err(AppError::Generic(format!(
+                "Still size {}x{} is outside the supported range of 1x1 to {}x{}",
+                width, height, max_width, max_height
+            )));
+        }
+        let extent = vk::Extent2D { width, height };
+        let tile_size = config::still_export::TILE_SIZE.min(self.device.capabilities.max_image_dimension_2d());
+        let tiles = viewport::tiles(extent, tile_size);
+        if self.toy.is_some() && tiles.len() > 1 {
+            // Toy buffers cover the whole target, so tiles would not line up
+            return Err(AppError::Generic(format!(
+                "Toys cannot be rendered in tiles; stills of a toy are limited to {}x{}",
+                tile_size, tile_size
+            )));
+        }
+
+        let target = vk::Extent2D { width: width.min(tile_size), height: height.min(tile_size) };
+        let exporter = self.create_frame_exporter(target.width, target.height)?;
+        unsafe {
+            self.device.device.device_wait_idle()
+                .vk_context(Subsystem::Renderer, "wait for device idle before still export")?;
+        }
+        self.write_scene_buffer();
+        self.mesh_pass.write_instances(self.current_frame);
+
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        for (index, tile) in tiles.iter().enumerate() {
+            // Particles step once, so every tile draws the same particles
+            self.record_export(&exporter, extent, tile, index == 0)?;
+            let tile_pixels = exporter.finish_hdr(&self.composite.scene_pass)?;
+            still_export::copy_tile(&mut pixels, width, &tile_pixels, target.width, tile);
+        }
+        info!("Rendered {}x{} still in {} tile(s) of up to {}x{}", width, height, tiles.len(), target.width, target.height);
+        Ok(pixels)
+    }
+
+    /// Record the scene into an export target, up to the readback
+    ///
+    /// # Arguments
+    /// * `exporter` - The export target
+    /// * `extent` - Size of the exported image
+    /// * `tile` - Area of the image the target covers
+    /// * `simulate` - Whether to step the toy buffers or particles before the pass
+    ///
+    /// # Errors
+    /// Returns an error if the command buffer cannot be begun
+    fn record_export(&mut self, exporter: &FrameExporter, extent: vk::Extent2D, tile: &Tile, simulate: bool) -> Result<()> {
+        let time = self.time;
+        let scene_pass = self.composite.scene_pass;
+        let clears: Vec<_> = self.viewports.clears(extent).into_iter()
+            .filter_map(|(rect, color)| Some((tile.scissor(rect)?, color)))
+            .collect();
+        let command_buffer = exporter.begin(&scene_pass, &clears, |command_buffer| {
+            self.record_scene_inputs(command_buffer, simulate, time);
+        })?;
+
+        let pipeline_guard = self.pipeline.lock().unwrap();
+        unsafe {
+            self.device.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline_guard.graphics_pipeline);
+            self.device.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_guard.pipeline_layout,
+                0,
+                &[self.scene_buffer.descriptor_set(self.current_frame)],
+                &[],
+            );
+        }
+        if let Some(toy) = self.toy.as_ref() {
+            toy.record_image(command_buffer, full_rect(extent), &self.mouse, time);
+            return Ok(());
+        }
+        self.record_scene_viewports(command_buffer, &pipeline_guard, extent, tile);
+        drop(pipeline_guard);
+        self.record_mesh_viewports(command_buffer, extent, tile);
+        self.record_particle_viewports(command_buffer, extent, tile);
+        Ok(())
+    }
+
+    /// Whether the headset's runtime is closing the session and the app should quit
+    #[cfg(feature = "xr")]
+    pub fn xr_exiting(&self) -> bool {
+        self.xr.as_ref().is_some_and(XrSession::is_exiting)
+    }
+
+    /// Draw the headset's eyes if an OpenXR session is running
+    ///
+    /// Each eye is drawn through the offscreen SDF target and the composite
+    /// pass into the runtime's swapchain for that eye, with the single
+    /// perspective view at the eye's pose; toys, debug lines and the
+    /// outline stay on the window. Blocks until the headset wants a frame.
+    /// The eyes reuse the offscreen target, which the shared graphics queue
+    /// orders after the window's frames, and the scene buffer of the current
+    /// frame, whose fence is waited for first.
+    ///
+    /// # Arguments
+    /// * `anchor` - Camera the tracking space is placed at
+    ///
+    /// # Returns
+    /// Headset and controllers of the frame; None without a session or
+    /// while the runtime does not want frames
+    ///
+    /// # Errors
+    /// Returns an error if the runtime or recording and submission fail
+    #[cfg(feature = "xr")]
+    pub fn draw_xr_frame(&mut self, anchor: &LookCamera) -> Result<Option<XrInput>> {
+        let Some(mut session) = self.xr.take() else {
+            return Ok(None);
+        };
+        let result = self.draw_xr_eyes(&mut session, anchor);
+        self.xr = Some(session);
+        result
+    }
+    
+    /// Record, submit and show one frame of every eye
+    ///
+    /// An eye that fails still hands its image back, and the frame is then
+    /// ended without layers, so the runtime's frame loop keeps going.
+    #[cfg(feature = "xr")]
+    fn draw_xr_eyes(&mut self, session: &mut XrSession, anchor: &LookCamera) -> Result<Option<XrInput>> {
+        // The eyes write the buffers of the window's current frame, like its next draw
+        unsafe {
+            self.device.device.wait_for_fences(&[self.in_flight_fences[self.current_frame]], true, config::xr::FRAME_TIMEOUT_NS)
+                .vk_context(Subsystem::Renderer, "wait for in-flight fence before headset frame")?;
+        }
+        let Some(frame) = session.begin_frame(anchor)? else {
+            return Ok(None);
+        };
+        self.write_scene_buffer();
+        self.mesh_pass.write_instances(self.current_frame);
+        
+        let extent = session.eye_extent();
+        let scaled_extent = xr::fit_extent(extent, self.composite.target_extent());
+        let window_viewports = self.viewports;
+        self.viewports.layout = ViewportLayout::Single;
+        self.viewports.viewports[3].kind = ViewKind::Perspective;
+        let result = (0..xr::EYES).try_for_each(|eye| {
+            self.viewports.perspective = frame.eyes[eye];
+            let image_index = session.acquire(eye)?;
+            let drawn = self.draw_xr_eye(session, eye, image_index, extent, scaled_extent);
+            session.release(eye)?;
+            drawn
+        });
+        self.viewports = window_viewports;
+        session.end_frame(&frame, result.is_ok())?;
+        result?;
+        Ok(Some(frame.input))
+    }
+    
+    /// Record and submit one eye into an acquired image of its swapchain
+    #[cfg(feature = "xr")]
+    fn draw_xr_eye(&mut self, session: &mut XrSession, eye: usize, image_index: usize, extent: vk::Extent2D, scaled_extent: vk::Extent2D) -> Result<()> {
+        let command_buffer = session.begin_commands()?;
+        
+        self.record_scene_inputs(command_buffer, false, self.time);
+        let pipeline_guard = self.pipeline.lock().unwrap();
+        self.composite.begin_scene_pass(command_buffer, scaled_extent, &self.viewports.clears(scaled_extent));
+        unsafe {
+            self.device.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline_guard.graphics_pipeline);
+            self.device.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_guard.pipeline_layout,
+                0,
+                &[self.scene_buffer.descriptor_set(self.current_frame)],
+                &[],
+            );
+        }
+        let tile = Tile::full(scaled_extent);
+        self.record_scene_viewports(command_buffer, &pipeline_guard, scaled_extent, &tile);
+        self.record_mesh_viewports(command_buffer, scaled_extent, &tile);
+        self.record_particle_viewports(command_buffer, scaled_extent, &tile);
+        self.composite.end_scene_pass(command_buffer);
+        
+        let target = session.eye_target(eye, image_index);
+        pipeline_guard.pass.begin(&self.device.device, command_buffer, &target, full_rect(extent), config::rendering::CLEAR_COLOR);
+        self.composite.record_composite(command_buffer, extent, scaled_extent, &self.tonemap);
+        pipeline_guard.pass.end(&self.device.device, command_buffer, &target);
+        drop(pipeline_guard);
+        session.submit(eye, image_index)
+    }
+    
+    /// Draw a toy instead of the scene ("shadertoy mode")
+    ///
+    /// A running toy is replaced once the new one is ready, e.g. after its
+    /// manifest changed; it keeps running if the new one fails.
+    ///
+    /// # Arguments
+    /// * `path` - Shadertoy style fragment shader or toy manifest, see `toy`
+    ///
+    /// # Errors
+    /// Returns an error if the manifest is invalid or a shader does not compile
+    pub fn start_toy(&mut self, path: &std::path::Path) -> Result<()> {
+        let memory_properties = unsafe {
+            self.instance.instance.get_physical_device_memory_properties(self.device.physical_device)
+        };
+        let toy = Toy::new(&self.device.device, &memory_properties, &self.composite.scene_pass, path, self.composite.target_extent())?;
+        if self.toy.is_some() {
+            // Frames in flight may still draw the buffers of the running toy
+            self.device.safe_device_wait_idle()?;
+        }
+        self.toy = Some(toy);
+        Ok(())
+    }
+
+    /// Manifest or shader file of the shadertoy mode, None while the scene is drawn
+    pub fn toy_path(&self) -> Option<&std::path::Path> {
+        self.toy.as_ref().map(Toy::path)
+    }
+
+    /// Files of the running toy to watch for changes, the manifest first
+    pub fn toy_files(&self) -> Vec<std::path::PathBuf> {
+        self.toy.as_ref().map(Toy::watched_files).unwrap_or_default()
+    }
+
+    /// Compile the toy shaders again after a file changed
+    ///
+    /// # Errors
+    /// Returns an error if the shader does not compile; the previous version keeps running
+    pub fn reload_toy(&mut self) -> Result<()> {
+        let Some(toy) = self.toy.as_mut() else {
+            return Ok(());
+        };
+        toy.reload(&self.composite.scene_pass, &mut self.deletion_queue.lock().unwrap())
+    }
+
+    /// Advance the clock of the toy
+    pub fn advance_toy(&mut self, delta_time: f32) {
+        if let Some(toy) = self.toy.as_mut() {
+            toy.advance(delta_time);
+        }
+    }
+
+    /// Update command buffers after pipeline recreation (for hot reload)
+    ///
+    /// # Returns
+    /// * Ok(()) if command buffers were updated successfully
+    /// * Err if command buffer update failed
+    pub fn update_command_buffers_after_hot_reload(&mut self) -> Result<()> {
+        info!("Updating command buffers after hot reload");
+        if let Err(e) = self.prepare_split_compare() {
+            warn!("Split comparison unavailable after hot reload: {}", e);
+        }
+        self.recreate_command_buffers()
+    }
+
+
+    /// Get HUD reference for rendering (unsafe - used during render pass)
+    fn get_hud_for_rendering(&self) -> Option<&mut crate::hud::HUD> {
+        debug!("Getting HUD reference for rendering, current reference: {:?}", self.hud_reference);
+        unsafe {
+            self.hud_reference.map(|ptr| {
+                debug!("Dereferencing HUD pointer: {:?}", ptr);
+                &mut *ptr
+            })
+        }
+    }
+}
+
+/// Rectangle covering an extent from the top-left corner
+fn full_rect(extent: vk::Extent2D) -> vk::Rect2D {
+    vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent }
+}
+
+impl Renderer for VulkanRenderer {
+    fn set_scene_data(&mut self, scene_data: GpuSceneData) {
+        VulkanRenderer::set_scene_data(self, scene_data);
+    }
+
+    fn set_debug_lines(&mut self, lines: DebugDrawList) {
+        VulkanRenderer::set_debug_lines(self, lines);
+    }
+
+    fn set_mesh_draws(&mut self, draws: MeshDrawList) {
+        VulkanRenderer::set_mesh_draws(self, draws);
+    }
+
+    fn mesh_bounds(&self) -> HashMap<String, (Vector3<f32>, f32)> {
+        VulkanRenderer::mesh_bounds(self)
+    }
+
+    fn set_text_draws(&mut self, draws: TextDrawList) {
+        VulkanRenderer::set_text_draws(self, draws);
+    }
+
+    fn set_particle_emitters(&mut self, emitters: ParticleEmitterList) {
+        VulkanRenderer::set_particle_emitters(self, emitters);
+    }
+
+    fn draw_frame(&mut self) -> Result<()> {
+        VulkanRenderer::draw_frame(self)
+    }
+
+    fn handle_resize(&mut self, width: u32, height: u32) -> Result<()> {
+        VulkanRenderer::handle_resize(self, width, height)
+    }
+}
+
+impl Drop for VulkanRenderer {
+    fn drop(&mut self) {
+        debug!("Starting VulkanRenderer cleanup");
+        
+        unsafe {
+            // Clean up resources in proper dependency order
+            // With proper shutdown sequencing, no device_wait_idle() should be needed
+            
+            // 1. Clean up sync objects first (they don't depend on other resources)
+            debug!("Cleaning up sync objects");
+            for &fence in &self.in_flight_fences {
+                self.device.device.destroy_fence(fence, None);
             }
             for &semaphore in &self.render_finished_semaphores {
                 self.device.device.destroy_semaphore(semaphore, None);
@@ -933,38 +2184,28 @@ impl Drop for VulkanRenderer {
                 self.device.device.destroy_semaphore(semaphore, None);
             }
             
-            // 2. Clean up command pool (this will clean up command buffers)
+            // 2. Destroy deferred resources; command buffers among them belong to the pool
+            debug!("Cleaning up deferred deletions");
+            self.deletion_queue.lock().unwrap().destroy_all(&self.device.device);
+            
+            // 3. Clean up command pool (this will clean up command buffers)
             debug!("Cleaning up command pool");
             self.device.device.destroy_command_pool(self.command_pool, None);
             
-            // 3. Clean up framebuffers (depend on render pass and image views)
+            // 4. Clean up framebuffers (depend on render pass and image views)
             debug!("Cleaning up framebuffers");
             for &framebuffer in &self.framebuffers {
                 self.device.device.destroy_framebuffer(framebuffer, None);
             }
+            self.depth_buffer.destroy(&self.device.device);
 
-            // 4. Explicitly clean up pipeline to break reference cycle
+            // 5. Explicitly clean up pipeline to break reference cycle
             debug!("Explicitly dropping pipeline reference");
             // Take the pipeline out of the Arc to drop our reference
             if let Ok(_pipeline_guard) = self.pipeline.lock() {
                 debug!("Dropping our pipeline reference to break reference cycle");
                 // The pipeline resources will be cleaned up when the last Arc reference is dropped
             }
-
-            // 5. Clean up vertex and index buffers if they exist
-            debug!("Cleaning up vertex and index buffers");
-            if self._vertex_buffer != vk::Buffer::null() {
-                self.device.device.destroy_buffer(self._vertex_buffer, None);
-            }
-            if self._vertex_buffer_memory != vk::DeviceMemory::null() {
-                self.device.device.free_memory(self._vertex_buffer_memory, None);
-            }
-            if self._index_buffer != vk::Buffer::null() {
-                self.device.device.destroy_buffer(self._index_buffer, None);
-            }
-            if self._index_buffer_memory != vk::DeviceMemory::null() {
-                self.device.device.free_memory(self._index_buffer_memory, None);
-            }
         }
         
         debug!("VulkanRenderer cleanup completed");
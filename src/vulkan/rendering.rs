@@ -0,0 +1,542 @@
+//! Render pass objects or dynamic rendering
+//!
+//! Every pass of the renderer draws into one color attachment plus a depth
+//! attachment (see `depth`); the scene pass has extra color attachments for
+//! its G-buffer (see `gbuffer`). With dynamic rendering (Vulkan
+//! 1.3) a pass is begun directly on image views and pipelines name their
+//! attachment formats, so there are no render pass or framebuffer objects to
+//! keep compatible or to recreate on resize. Drivers without it keep the
+//! render pass path. `PassLayout` hides the difference: pipelines are built
+//! through `attach` and passes are recorded with `begin` and `end`.
+
+use ash::vk;
+use ash::Device;
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::capabilities::DeviceCapabilities;
+use crate::vulkan::depth::{self, DepthUsage};
+
+/// How passes are begun and what pipelines are made compatible with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderingMode {
+    /// Render pass and framebuffer objects
+    RenderPass,
+    /// `vkCmdBeginRendering` on image views
+    Dynamic,
+}
+
+impl RenderingMode {
+    /// Dynamic rendering when the device supports it, unless disabled in the config
+    pub fn select(capabilities: &DeviceCapabilities) -> Self {
+        if config::vulkan::PREFER_DYNAMIC_RENDERING && capabilities.supports_dynamic_rendering() {
+            Self::Dynamic
+        } else {
+            Self::RenderPass
+        }
+    }
+
+    /// Readable name for the log
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RenderPass => "render pass objects",
+            Self::Dynamic => "dynamic rendering",
+        }
+    }
+}
+
+/// Most color attachments of a pass: the main color plus the scene G-buffer
+pub const MAX_COLOR_ATTACHMENTS: usize = 4;
+
+/// Image and view of a color attachment after the first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorAttachment {
+    /// Image, transitioned by hand with dynamic rendering
+    pub image: vk::Image,
+    /// Attachment view
+    pub view: vk::ImageView,
+}
+
+/// Images a pass draws into
+#[derive(Debug, Clone, Copy)]
+pub struct PassTarget {
+    /// Framebuffer of the images, null with dynamic rendering
+    pub framebuffer: vk::Framebuffer,
+    /// Color image, transitioned by hand with dynamic rendering
+    pub color_image: vk::Image,
+    /// Color attachment view
+    pub color_view: vk::ImageView,
+    /// Depth image, transitioned by hand with dynamic rendering
+    pub depth_image: vk::Image,
+    /// Depth attachment view
+    pub depth_view: vk::ImageView,
+    /// Color attachments after the first, only as many as the pass has
+    pub extra_colors: [ColorAttachment; MAX_COLOR_ATTACHMENTS - 1],
+}
+
+/// Attachment formats and final layout of a pass
+///
+/// Copies share the render pass object; the owner destroys it once.
+#[derive(Debug, Clone, Copy)]
+pub struct PassLayout {
+    /// Whether the pass uses a render pass object
+    pub mode: RenderingMode,
+    /// Render pass object, null with dynamic rendering
+    pub render_pass: vk::RenderPass,
+    /// Formats of the color attachments (an array so pipelines can point at it)
+    color_formats: [vk::Format; MAX_COLOR_ATTACHMENTS],
+    /// Number of color attachments in `color_formats`
+    color_count: usize,
+    /// Layout the color image is left in when the pass ends
+    final_layout: vk::ImageLayout,
+    /// Whether the depth is discarded or kept for sampling
+    depth_usage: DepthUsage,
+}
+
+impl PassLayout {
+    /// Describe a pass, creating its render pass object unless `mode` is dynamic
+    ///
+    /// The color attachment is cleared and stored; the depth attachment is
+    /// cleared and, depending on `depth_usage`, discarded or stored.
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `mode` - Rendering mode chosen for the device
+    /// * `format` - Color attachment format
+    /// * `final_layout` - Layout the color image is used in after the pass
+    /// * `depth_usage` - Whether the depth is sampled after the pass
+    /// * `name` - Name of the pass for error messages
+    ///
+    /// # Errors
+    /// Returns an error if render pass creation fails
+    pub fn new(
+        device: &Device,
+        mode: RenderingMode,
+        format: vk::Format,
+        final_layout: vk::ImageLayout,
+        depth_usage: DepthUsage,
+        name: &str,
+    ) -> Result<Self> {
+        Self::with_color_formats(device, mode, &[format], final_layout, depth_usage, name)
+    }
+
+    /// Describe a pass with several color attachments
+    ///
+    /// Like `new`; every color attachment is cleared (the extra ones to zero),
+    /// stored and left in `final_layout`.
+    ///
+    /// # Arguments
+    /// * `formats` - Color attachment formats, the main one first, at most `MAX_COLOR_ATTACHMENTS`
+    ///
+    /// # Errors
+    /// Returns an error if render pass creation fails
+    pub fn with_color_formats(
+        device: &Device,
+        mode: RenderingMode,
+        formats: &[vk::Format],
+        final_layout: vk::ImageLayout,
+        depth_usage: DepthUsage,
+        name: &str,
+    ) -> Result<Self> {
+        assert!((1..=MAX_COLOR_ATTACHMENTS).contains(&formats.len()), "{} pass with {} color attachments", name, formats.len());
+        let render_pass = match mode {
+            RenderingMode::RenderPass => Self::create_render_pass(device, formats, final_layout, depth_usage, name)?,
+            RenderingMode::Dynamic => vk::RenderPass::null(),
+        };
+        let mut color_formats = [vk::Format::UNDEFINED; MAX_COLOR_ATTACHMENTS];
+        color_formats[..formats.len()].copy_from_slice(formats);
+        Ok(Self { mode, render_pass, color_formats, color_count: formats.len(), final_layout, depth_usage })
+    }
+
+    /// Create the render pass object
+    fn create_render_pass(
+        device: &Device,
+        formats: &[vk::Format],
+        final_layout: vk::ImageLayout,
+        depth_usage: DepthUsage,
+        name: &str,
+    ) -> Result<vk::RenderPass> {
+        let color_attachment = |format| vk::AttachmentDescription::default()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(final_layout);
+
+        let color_attachment_refs: Vec<vk::AttachmentReference> = attachment_indices(formats.len())
+            .into_iter()
+            .map(|index| vk::AttachmentReference::default()
+                .attachment(index)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL))
+            .collect();
+        let depth_attachment_ref = depth::attachment_reference(1);
+        let subpasses = [vk::SubpassDescription::default()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_refs)
+            .depth_stencil_attachment(&depth_attachment_ref)];
+
+        // The depth dependencies do not depend on its usage, keeping the passes compatible
+        let mut dependencies = vec![depth::external_dependency(), depth::sampled_dependency()];
+        // Make the color writes visible to whoever reads the image next
+        if let Some((dst_stage, dst_access)) = Self::reader_of(final_layout) {
+            dependencies.push(vk::SubpassDependency::default()
+                .src_subpass(0)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(dst_stage)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(dst_access));
+        }
+
+        // Main color, depth, then the extra colors (see `attachment_indices`)
+        let mut attachments = vec![color_attachment(formats[0]), depth::attachment_description(depth_usage)];
+        attachments.extend(formats[1..].iter().map(|&format| color_attachment(format)));
+        let render_pass_info = vk::RenderPassCreateInfo::default()
+            .attachments(&attachments)
+            .subpasses(&subpasses)
+            .dependencies(&dependencies);
+
+        unsafe {
+            Ok(device.create_render_pass(&render_pass_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create {} render pass: {:?}", name, e)))?)
+        }
+    }
+
+    /// Stage and access of the reads that follow a pass ending in `final_layout`
+    ///
+    /// # Returns
+    /// None if nothing in the command buffer reads the image (presentation
+    /// waits on a semaphore, validation targets are never read)
+    fn reader_of(final_layout: vk::ImageLayout) -> Option<(vk::PipelineStageFlags, vk::AccessFlags)> {
+        match final_layout {
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => Some((vk::PipelineStageFlags::FRAGMENT_SHADER, vk::AccessFlags::SHADER_READ)),
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL => Some((vk::PipelineStageFlags::TRANSFER, vk::AccessFlags::TRANSFER_READ)),
+            _ => None,
+        }
+    }
+
+    /// Create a framebuffer for the pass, or a null handle with dynamic rendering
+    ///
+    /// # Errors
+    /// Returns an error if framebuffer creation fails
+    pub fn create_framebuffer(
+        &self,
+        device: &Device,
+        color_view: vk::ImageView,
+        depth_view: vk::ImageView,
+        extent: vk::Extent2D,
+    ) -> Result<vk::Framebuffer> {
+        self.create_framebuffer_with_extras(device, color_view, &[], depth_view, extent)
+    }
+
+    /// Create a framebuffer for a pass with several color attachments
+    ///
+    /// # Arguments
+    /// * `extra_views` - Views of the color attachments after the first, in format order
+    ///
+    /// # Errors
+    /// Returns an error if framebuffer creation fails
+    pub fn create_framebuffer_with_extras(
+        &self,
+        device: &Device,
+        color_view: vk::ImageView,
+        extra_views: &[vk::ImageView],
+        depth_view: vk::ImageView,
+        extent: vk::Extent2D,
+    ) -> Result<vk::Framebuffer> {
+        debug_assert_eq!(extra_views.len() + 1, self.color_count);
+        if self.mode == RenderingMode::Dynamic {
+            return Ok(vk::Framebuffer::null());
+        }
+        let mut attachments = vec![color_view, depth_view];
+        attachments.extend_from_slice(extra_views);
+        unsafe {
+            Ok(device.create_framebuffer(&vk::FramebufferCreateInfo::default()
+                .render_pass(self.render_pass)
+                .attachments(&attachments)
+                .width(extent.width)
+                .height(extent.height)
+                .layers(1), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create framebuffer: {:?}", e)))?)
+        }
+    }
+
+    /// Attachment formats chained into pipelines built for dynamic rendering
+    pub fn rendering_info(&self) -> vk::PipelineRenderingCreateInfo<'_> {
+        vk::PipelineRenderingCreateInfo::default()
+            .color_attachment_formats(&self.color_formats[..self.color_count])
+            .depth_attachment_format(config::rendering::DEPTH_FORMAT)
+    }
+
+    /// Number of color attachments
+    pub fn color_count(&self) -> usize {
+        self.color_count
+    }
+
+    /// Blend state of every color attachment for a pipeline drawn in the pass
+    ///
+    /// All attachments get the same state, as `independentBlend` is not
+    /// enabled; fragment shaders write every location, zero where a blended
+    /// draw adds nothing to the G-buffer.
+    pub fn color_blend_attachments(&self, state: vk::PipelineColorBlendAttachmentState) -> Vec<vk::PipelineColorBlendAttachmentState> {
+        vec![state; self.color_count]
+    }
+
+    /// Make a pipeline compatible with the pass
+    ///
+    /// # Arguments
+    /// * `pipeline_info` - The otherwise complete pipeline description
+    /// * `rendering` - Storage for the chained formats, from `rendering_info`
+    pub fn attach<'a>(
+        &self,
+        pipeline_info: vk::GraphicsPipelineCreateInfo<'a>,
+        rendering: &'a mut vk::PipelineRenderingCreateInfo<'_>,
+    ) -> vk::GraphicsPipelineCreateInfo<'a> {
+        match self.mode {
+            RenderingMode::RenderPass => pipeline_info.render_pass(self.render_pass).subpass(0),
+            RenderingMode::Dynamic => pipeline_info.push_next(rendering),
+        }
+    }
+
+    /// Begin the pass, clearing both attachments inside `render_area`
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `command_buffer` - Command buffer being recorded
+    /// * `target` - Images to draw into
+    /// * `render_area` - Region that is cleared and drawn
+    /// * `clear_color` - Clear color of the color attachment
+    pub fn begin(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        target: &PassTarget,
+        render_area: vk::Rect2D,
+        clear_color: [f32; 4],
+    ) {
+        let clear_color = vk::ClearValue { color: vk::ClearColorValue { float32: clear_color } };
+        let extra_clear = vk::ClearValue { color: vk::ClearColorValue { float32: [0.0; 4] } };
+        let extra_colors = &target.extra_colors[..self.color_count - 1];
+        match self.mode {
+            RenderingMode::RenderPass => {
+                let mut clear_values = vec![clear_color, depth::clear_value()];
+                clear_values.extend(extra_colors.iter().map(|_| extra_clear));
+                let begin_info = vk::RenderPassBeginInfo::default()
+                    .render_pass(self.render_pass)
+                    .framebuffer(target.framebuffer)
+                    .render_area(render_area)
+                    .clear_values(&clear_values);
+                unsafe {
+                    device.cmd_begin_render_pass(command_buffer, &begin_info, vk::SubpassContents::INLINE);
+                }
+            }
+            RenderingMode::Dynamic => {
+                // The old contents are cleared, only earlier reads and writes must be done
+                let color_barrier = |image| vk::ImageMemoryBarrier::default()
+                    .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(image)
+                    .subresource_range(Self::color_range());
+                let depth_stages = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+                let color_attachment = |view, clear_value| vk::RenderingAttachmentInfo::default()
+                    .image_view(view)
+                    .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .clear_value(clear_value);
+                let mut color_attachments = vec![color_attachment(target.color_view, clear_color)];
+                color_attachments.extend(extra_colors.iter().map(|extra| color_attachment(extra.view, extra_clear)));
+                let mut barriers = vec![color_barrier(target.color_image), depth::layout_barrier(target.depth_image)];
+                barriers.extend(extra_colors.iter().map(|extra| color_barrier(extra.image)));
+                let depth_attachment = depth::rendering_attachment(target.depth_view, self.depth_usage);
+                let rendering_info = vk::RenderingInfo::default()
+                    .render_area(render_area)
+                    .layer_count(1)
+                    .color_attachments(&color_attachments)
+                    .depth_attachment(&depth_attachment);
+                unsafe {
+                    device.cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::FRAGMENT_SHADER | depth_stages,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | depth_stages,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &[],
+                        &barriers,
+                    );
+                    device.cmd_begin_rendering(command_buffer, &rendering_info);
+                }
+            }
+        }
+    }
+
+    /// Begin the pass with a clear color per rectangle
+    ///
+    /// The pass is begun with the first rectangle's color; rectangles with
+    /// another color are cleared again inside the pass. Used for viewports
+    /// with their own clear colors.
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `command_buffer` - Command buffer being recorded
+    /// * `target` - Images to draw into
+    /// * `render_area` - Region that is cleared and drawn
+    /// * `clears` - Rectangles inside `render_area` and their clear colors
+    pub fn begin_with_clears(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        target: &PassTarget,
+        render_area: vk::Rect2D,
+        clears: &[(vk::Rect2D, [f32; 4])],
+    ) {
+        let (clear_color, recleared) = split_clears(clears);
+        self.begin(device, command_buffer, target, render_area, clear_color);
+        for (rect, color) in recleared {
+            let attachments = [vk::ClearAttachment {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                color_attachment: 0,
+                clear_value: vk::ClearValue { color: vk::ClearColorValue { float32: color } },
+            }];
+            let rects = [vk::ClearRect { rect, base_array_layer: 0, layer_count: 1 }];
+            unsafe {
+                device.cmd_clear_attachments(command_buffer, &attachments, &rects);
+            }
+        }
+    }
+
+    /// End the pass, leaving the color image in the final layout and sampled
+    /// depth read-only
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `command_buffer` - Command buffer being recorded
+    /// * `target` - Images the pass was begun with
+    pub fn end(&self, device: &Device, command_buffer: vk::CommandBuffer, target: &PassTarget) {
+        match self.mode {
+            RenderingMode::RenderPass => unsafe {
+                device.cmd_end_render_pass(command_buffer);
+            },
+            RenderingMode::Dynamic => {
+                let (dst_stage, dst_access) = Self::reader_of(self.final_layout)
+                    .unwrap_or((vk::PipelineStageFlags::BOTTOM_OF_PIPE, vk::AccessFlags::empty()));
+                let barrier = |image| vk::ImageMemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(dst_access)
+                    .old_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .new_layout(self.final_layout)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(image)
+                    .subresource_range(Self::color_range());
+                let mut barriers = vec![barrier(target.color_image)];
+                barriers.extend(target.extra_colors[..self.color_count - 1].iter().map(|extra| barrier(extra.image)));
+                unsafe {
+                    device.cmd_end_rendering(command_buffer);
+                    device.cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                        dst_stage,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &[],
+                        &barriers,
+                    );
+                    if self.depth_usage == DepthUsage::Sampled {
+                        device.cmd_pipeline_barrier(
+                            command_buffer,
+                            vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                            vk::PipelineStageFlags::FRAGMENT_SHADER,
+                            vk::DependencyFlags::empty(),
+                            &[],
+                            &[],
+                            &[depth::sampled_barrier(target.depth_image)],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Subresource range of the single color mip and layer
+    fn color_range() -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    /// Destroy the render pass object, if any
+    ///
+    /// Called by the owner of the pass once no pipeline or framebuffer uses it.
+    pub fn destroy(&self, device: &Device) {
+        if self.render_pass != vk::RenderPass::null() {
+            unsafe {
+                device.destroy_render_pass(self.render_pass, None);
+            }
+        }
+    }
+}
+
+/// Render pass attachment index of each color attachment
+///
+/// The main color is attachment 0 and depth attachment 1, as in passes
+/// without extra colors; the extra colors follow.
+fn attachment_indices(color_count: usize) -> Vec<u32> {
+    (0..color_count as u32).map(|i| if i == 0 { 0 } else { i + 1 }).collect()
+}
+
+/// Clear color to begin a pass with and the rectangles that need another one
+///
+/// Falls back to `config::rendering::CLEAR_COLOR` without rectangles.
+fn split_clears(clears: &[(vk::Rect2D, [f32; 4])]) -> ([f32; 4], Vec<(vk::Rect2D, [f32; 4])>) {
+    let clear_color = clears.first().map_or(config::rendering::CLEAR_COLOR, |&(_, color)| color);
+    let recleared = clears.iter().filter(|&&(_, color)| color != clear_color).copied().collect();
+    (clear_color, recleared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_clears_reclears_only_other_colors() {
+        let rect = |x| vk::Rect2D { offset: vk::Offset2D { x, y: 0 }, extent: vk::Extent2D { width: 10, height: 10 } };
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+
+        let (color, recleared) = split_clears(&[(rect(0), red), (rect(10), blue), (rect(20), red)]);
+        assert_eq!(color, red);
+        assert_eq!(recleared, vec![(rect(10), blue)]);
+        assert_eq!(split_clears(&[]), (config::rendering::CLEAR_COLOR, Vec::new()));
+    }
+
+    #[test]
+    fn test_extra_colors_follow_the_depth_attachment() {
+        assert_eq!(attachment_indices(1), vec![0]);
+        assert_eq!(attachment_indices(3), vec![0, 2, 3]);
+        assert_eq!(attachment_indices(MAX_COLOR_ATTACHMENTS), vec![0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_reader_of_final_layouts() {
+        assert_eq!(
+            PassLayout::reader_of(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+            Some((vk::PipelineStageFlags::FRAGMENT_SHADER, vk::AccessFlags::SHADER_READ)),
+        );
+        assert_eq!(
+            PassLayout::reader_of(vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+            Some((vk::PipelineStageFlags::TRANSFER, vk::AccessFlags::TRANSFER_READ)),
+        );
+        assert_eq!(PassLayout::reader_of(vk::ImageLayout::PRESENT_SRC_KHR), None);
+        assert_eq!(PassLayout::reader_of(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Residency of assets loaded from disk
+//!
+//! Meshes and environment maps stay in device memory after they were last
+//! used, so switching back to them is instant. `Residency` keeps them under
+//! a VRAM budget (`config::residency`): once the cached assets exceed it,
+//! the ones unused for longest are evicted. Evicted assets are loaded from
+//! their file again when they are next used.
+//!
+//! Assets drawn within the last `MIN_IDLE_FRAMES` frames are never evicted,
+//! which also keeps frames in flight from using freed memory. When those
+//! alone exceed the budget the residency is over budget and the HUD warns.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use ash::vk;
+use crate::config;
+
+/// An asset kept in device memory
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResidentAsset {
+    /// Vertex and index buffers of a mesh file, see `MeshPass`
+    Mesh(String),
+    /// Environment map texture of an HDRI file, see `EnvironmentMap`
+    EnvironmentMap(PathBuf),
+}
+
+/// Size and last use of a resident asset
+#[derive(Debug, Clone, Copy)]
+struct Resident {
+    size: vk::DeviceSize,
+    last_used: u64,
+}
+
+/// Resident bytes against the budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResidencyUsage {
+    /// Bytes of the resident assets
+    pub resident: vk::DeviceSize,
+    /// Bytes the assets may use
+    pub budget: vk::DeviceSize,
+    /// Number of resident assets
+    pub assets: usize,
+}
+
+impl ResidencyUsage {
+    /// Whether the resident assets exceed the budget; after `evict`, only
+    /// because the ones used recently do
+    pub fn is_over_budget(&self) -> bool {
+        self.resident > self.budget
+    }
+}
+
+/// Least recently used eviction of assets under a budget
+#[derive(Debug)]
+pub struct Residency {
+    residents: BTreeMap<ResidentAsset, Resident>,
+    budget: vk::DeviceSize,
+    frame: u64,
+}
+
+impl Residency {
+    /// Create an empty residency
+    ///
+    /// # Arguments
+    /// * `budget` - Bytes the assets may use
+    pub fn new(budget: vk::DeviceSize) -> Self {
+        Self { residents: BTreeMap::new(), budget, frame: 0 }
+    }
+
+    /// Budget for a GPU: `BUDGET_MB`, at most `HEAP_FRACTION` of the device local memory
+    ///
+    /// # Arguments
+    /// * `device_local_bytes` - Budget (or size) of the device local heaps, None if unknown
+    pub fn budget_for(device_local_bytes: Option<vk::DeviceSize>) -> vk::DeviceSize {
+        let configured = config::residency::BUDGET_MB * 1024 * 1024;
+        match device_local_bytes {
+            Some(bytes) => configured.min((bytes as f64 * config::residency::HEAP_FRACTION) as vk::DeviceSize),
+            None => configured,
+        }
+    }
+
+    /// Start a frame with the assets currently in device memory
+    ///
+    /// Assets missing from the list were freed and are forgotten, new ones
+    /// are added as used in this frame.
+    ///
+    /// # Arguments
+    /// * `assets` - Every resident asset with its size and whether this frame uses it
+    pub fn update(&mut self, assets: impl IntoIterator<Item = (ResidentAsset, vk::DeviceSize, bool)>) {
+        self.frame += 1;
+        let frame = self.frame;
+        let mut residents = BTreeMap::new();
+        for (asset, size, in_use) in assets {
+            let last_used = match self.residents.get(&asset) {
+                Some(resident) if !in_use => resident.last_used,
+                _ => frame,
+            };
+            residents.insert(asset, Resident { size, last_used });
+        }
+        self.residents = residents;
+    }
+
+    /// Forget the assets to free to get under the budget
+    ///
+    /// Assets used within `MIN_IDLE_FRAMES` frames are kept even if the
+    /// budget is still exceeded without them.
+    ///
+    /// # Returns
+    /// The assets the caller must free, least recently used first
+    pub fn evict(&mut self) -> Vec<ResidentAsset> {
+        let mut resident = self.resident_bytes();
+        if resident <= self.budget {
+            return Vec::new();
+        }
+        let mut idle: Vec<(&ResidentAsset, &Resident)> = self.residents.iter()
+            .filter(|(_, entry)| self.frame - entry.last_used >= config::residency::MIN_IDLE_FRAMES)
+            .collect();
+        idle.sort_by_key(|(_, entry)| entry.last_used);
+
+        let mut evicted = Vec::new();
+        for (asset, entry) in idle {
+            if resident <= self.budget {
+                break;
+            }
+            resident -= entry.size;
+            evicted.push(asset.clone());
+        }
+        for asset in &evicted {
+            self.residents.remove(asset);
+        }
+        evicted
+    }
+
+    /// Resident bytes against the budget
+    pub fn usage(&self) -> ResidencyUsage {
+        ResidencyUsage { resident: self.resident_bytes(), budget: self.budget, assets: self.residents.len() }
+    }
+
+    fn resident_bytes(&self) -> vk::DeviceSize {
+        self.residents.values().map(|entry| entry.size).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh(name: &str) -> ResidentAsset {
+        ResidentAsset::Mesh(name.to_string())
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_idle_assets() {
+        let mut residency = Residency::new(250);
+        residency.update([(mesh("a"), 100, true), (mesh("b"), 100, true)]);
+        for _ in 0..config::residency::MIN_IDLE_FRAMES {
+            residency.update([(mesh("a"), 100, false), (mesh("b"), 100, true)]);
+        }
+        residency.update([(mesh("a"), 100, false), (mesh("b"), 100, false), (mesh("c"), 100, true)]);
+        // "a" has been idle longest; "c" is in use and "b" was used too recently
+        assert_eq!(residency.evict(), vec![mesh("a")]);
+        assert_eq!(residency.usage(), ResidencyUsage { resident: 200, budget: 250, assets: 2 });
+        assert_eq!(residency.evict(), Vec::new());
+    }
+
+    #[test]
+    fn test_assets_in_use_are_kept_over_budget() {
+        let mut residency = Residency::new(100);
+        for _ in 0..=config::residency::MIN_IDLE_FRAMES {
+            residency.update([(mesh("a"), 150, true)]);
+        }
+        assert_eq!(residency.evict(), Vec::new());
+        assert!(residency.usage().is_over_budget());
+
+        assert_eq!(Residency::budget_for(Some(1024 * 1024)), (1024.0 * 1024.0 * config::residency::HEAP_FRACTION) as vk::DeviceSize);
+    }
+}
@@ -0,0 +1,176 @@
+//! Dynamic resolution scaling for the SDF pass
+//!
+//! Tracks recent GPU frame times and nudges the render scale down when the
+//! SDF pass exceeds the frame budget, and back up when there is headroom.
+//!
+//! The scale is a fraction of the offscreen target, whose own size is the
+//! window's times the render scale chosen in the Render Settings panel (see
+//! `target_extent`), from a quarter of the window up to supersampling.
+
+use ash::vk;
+use crate::config;
+
+/// Number of GPU frame samples averaged before adjusting the scale
+const SAMPLE_WINDOW: usize = 16;
+
+/// Controller for the SDF render scale
+#[derive(Debug, Clone)]
+pub struct ResolutionScaler {
+    /// Current scale applied to the SDF target (fraction of swapchain size)
+    scale: f32,
+
+    /// Manual override; when set, automatic scaling is disabled
+    override_scale: Option<f32>,
+
+    /// Recent GPU frame times in milliseconds
+    samples: Vec<f32>,
+
+    /// Target GPU time for the SDF pass in milliseconds
+    target_ms: f32,
+}
+
+impl Default for ResolutionScaler {
+    fn default() -> Self {
+        Self::new(config::resolution_scaling::TARGET_FRAME_MS)
+    }
+}
+
+impl ResolutionScaler {
+    /// Create a scaler for the given GPU time budget
+    pub fn new(target_ms: f32) -> Self {
+        Self {
+            scale: config::resolution_scaling::MAX_SCALE,
+            override_scale: None,
+            samples: Vec::with_capacity(SAMPLE_WINDOW),
+            target_ms,
+        }
+    }
+
+    /// Current render scale
+    pub fn scale(&self) -> f32 {
+        self.override_scale.unwrap_or(self.scale)
+    }
+
+    /// Manual override, if any
+    pub fn override_scale(&self) -> Option<f32> {
+        self.override_scale
+    }
+
+    /// Set or clear the manual override
+    pub fn set_override(&mut self, scale: Option<f32>) {
+        self.override_scale = scale.map(clamp_scale);
+        self.samples.clear();
+    }
+
+    /// Average of the recent GPU frame times
+    pub fn average_ms(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            None
+        } else {
+            Some(self.samples.iter().sum::<f32>() / self.samples.len() as f32)
+        }
+    }
+
+    /// Record a GPU frame time and adjust the scale once a full window is collected
+    ///
+    /// # Returns
+    /// True if the scale changed
+    pub fn record(&mut self, gpu_ms: f32) -> bool {
+        if !config::resolution_scaling::ENABLED || self.override_scale.is_some() {
+            return false;
+        }
+
+        self.samples.push(gpu_ms);
+        if self.samples.len() < SAMPLE_WINDOW {
+            return false;
+        }
+
+        let average = self.average_ms().unwrap_or(self.target_ms);
+        self.samples.clear();
+
+        let hysteresis = config::resolution_scaling::HYSTERESIS;
+        let step = config::resolution_scaling::ADJUST_STEP;
+        let old_scale = self.scale;
+
+        if average > self.target_ms * (1.0 + hysteresis) {
+            self.scale = clamp_scale(self.scale - step);
+        } else if average < self.target_ms * (1.0 - hysteresis) {
+            self.scale = clamp_scale(self.scale + step);
+        }
+
+        (self.scale - old_scale).abs() > f32::EPSILON
+    }
+}
+
+/// Clamp a scale into the configured range
+fn clamp_scale(scale: f32) -> f32 {
+    scale.clamp(config::resolution_scaling::MIN_SCALE, config::resolution_scaling::MAX_SCALE)
+}
+
+/// Clamp a render scale into `MIN_RENDER_SCALE..=MAX_RENDER_SCALE`
+pub fn clamp_render_scale(render_scale: f32) -> f32 {
+    render_scale.clamp(config::resolution_scaling::MIN_RENDER_SCALE, config::resolution_scaling::MAX_RENDER_SCALE)
+}
+
+/// Size of the offscreen SDF target for a render scale
+///
+/// # Arguments
+/// * `extent` - Swapchain extent
+/// * `render_scale` - Target size relative to the swapchain, clamped to the configured range
+/// * `max_dimension` - Largest image side the device supports; a larger
+///   target is scaled down as a whole, keeping the aspect ratio
+pub fn target_extent(extent: vk::Extent2D, render_scale: f32, max_dimension: u32) -> vk::Extent2D {
+    let longest = extent.width.max(extent.height).max(1) as f32;
+    let render_scale = clamp_render_scale(render_scale).min(max_dimension as f32 / longest);
+    let side = |size: u32| ((size as f32 * render_scale).round() as u32).clamp(1, max_dimension.max(1));
+    vk::Extent2D { width: side(extent.width), height: side(extent.height) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_drops_when_over_budget() {
+        let mut scaler = ResolutionScaler::new(10.0);
+        for _ in 0..SAMPLE_WINDOW {
+            scaler.record(20.0);
+        }
+        assert!(scaler.scale() < config::resolution_scaling::MAX_SCALE);
+    }
+
+    #[test]
+    fn test_scale_never_leaves_range() {
+        let mut scaler = ResolutionScaler::new(10.0);
+        for _ in 0..SAMPLE_WINDOW * 100 {
+            scaler.record(100.0);
+        }
+        assert_eq!(scaler.scale(), config::resolution_scaling::MIN_SCALE);
+
+        for _ in 0..SAMPLE_WINDOW * 100 {
+            scaler.record(0.1);
+        }
+        assert_eq!(scaler.scale(), config::resolution_scaling::MAX_SCALE);
+    }
+
+    #[test]
+    fn test_override_wins_and_is_clamped() {
+        let mut scaler = ResolutionScaler::new(10.0);
+        scaler.set_override(Some(0.1));
+        assert_eq!(scaler.scale(), config::resolution_scaling::MIN_SCALE);
+        assert!(!scaler.record(100.0));
+
+        scaler.set_override(None);
+        assert_eq!(scaler.scale(), config::resolution_scaling::MAX_SCALE);
+    }
+
+    #[test]
+    fn test_target_extent_follows_render_scale() {
+        let window = vk::Extent2D { width: 1920, height: 1080 };
+        assert_eq!(target_extent(window, 1.0, 16384), window);
+        assert_eq!(target_extent(window, 2.0, 16384), vk::Extent2D { width: 3840, height: 2160 });
+        assert_eq!(target_extent(window, 0.1, 16384), vk::Extent2D { width: 480, height: 270 });
+        // Limited by the device
+        assert_eq!(target_extent(window, 2.0, 2048), vk::Extent2D { width: 2048, height: 1152 });
+    }
+}
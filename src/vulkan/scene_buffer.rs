@@ -0,0 +1,850 @@
+//! Scene uniform buffer for the SDF fragment shader
+//!
+//! Shapes and lights collected from the ECS world are packed into a std140
+//! uniform block (`SceneData` in `shaders/sdf.frag`). A `DynamicBuffer`
+//! region is kept per frame in flight so the CPU never writes data the GPU
+//! is still reading.
+//! The same descriptor sets hold the environment map at binding 1, the
+//! shader's live parameters (`Params`, see `shader_params`) at binding 2 and
+//! the font atlas of the texts (see `text`) at binding 3.
+
+use ash::vk;
+use ash::{Device, Instance};
+use cgmath::InnerSpace;
+use crate::config;
+use crate::environment::{BackgroundMode, BackgroundSettings};
+use crate::ecs::components::{CsgOperation, SDFDisplayMode, SDFMaterial, SDFShape, SDFShapeType, Transform};
+use crate::ecs::lights::Light;
+use crate::ecs::modifiers::SDFModifier;
+use crate::ecs::text::Text3D;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::buffer::DynamicBuffer;
+use crate::vulkan::fullscreen_pass::MouseState;
+use log::{debug, info};
+
+/// GPU representation of a single SDF shape (std140, 128 bytes)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct GpuShape {
+    /// xyz = position, w = size
+    pub position_size: [f32; 4],
+    /// Shape specific parameters
+    pub params: [f32; 4],
+    /// rgb = color, a = metallic
+    pub color_metallic: [f32; 4],
+    /// x = roughness, y = emission, z = CSG blend radius, w = 1 if selected
+    pub material: [f32; 4],
+    /// x = shape type, y = display mode, z = CSG operation, w = CSG group (0 = none)
+    pub info: [i32; 4],
+    /// x = first modifier, y = modifier count, zw unused
+    pub modifiers: [i32; 4],
+    /// x = reflectivity, y = transparency, z = index of refraction, w unused
+    pub optics: [f32; 4],
+    /// x = render layer mask (see `RenderLayers`), yzw unused
+    pub layers: [u32; 4],
+}
+
+/// GPU representation of one step of a modifier stack (std140, 32 bytes)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct GpuModifier {
+    /// Parameters, see `SDFModifier::gpu_params`
+    pub params: [f32; 4],
+    /// x = modifier kind, yzw unused
+    pub info: [i32; 4],
+}
+
+/// GPU representation of a light (std140, 80 bytes)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct GpuLight {
+    /// xyz = position, w = radius (0 = no falloff)
+    pub position: [f32; 4],
+    /// rgb = color, a = intensity
+    pub color_intensity: [f32; 4],
+    /// xyz = normalized direction the light shines in, w unused
+    pub direction: [f32; 4],
+    /// x = cosine of the spot cone's outer half angle, y = of its inner one, zw unused
+    pub spot: [f32; 4],
+    /// x = light kind, yzw unused
+    pub info: [i32; 4],
+}
+
+/// GPU representation of the background settings (std140, 80 bytes)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct GpuBackground {
+    /// x = background mode, y = `BACKGROUND_FLAG_*` bits, zw unused
+    pub mode: [i32; 4],
+    /// rgb = gradient color straight up
+    pub gradient_top: [f32; 4],
+    /// rgb = gradient color straight down
+    pub gradient_bottom: [f32; 4],
+    /// x = environment map intensity, y = rotation in turns
+    pub hdri: [f32; 4],
+}
+
+/// Flag set when an environment map is bound (must match sdf.frag)
+const BACKGROUND_FLAG_MAP: i32 = 1;
+/// Flag set when surfaces reflect the background (must match sdf.frag)
+const BACKGROUND_FLAG_REFLECTIONS: i32 = 2;
+
+impl GpuBackground {
+    /// Pack the background settings
+    ///
+    /// # Arguments
+    /// * `settings` - Background settings from the HUD
+    /// * `has_map` - Whether an environment map is bound; the HDRI mode
+    ///   shows the procedural sky without one
+    pub fn new(settings: &BackgroundSettings, has_map: bool) -> Self {
+        let mode = match settings.mode {
+            BackgroundMode::Hdri if !has_map => BackgroundMode::ProceduralSky,
+            mode => mode,
+        };
+        let mut flags = 0;
+        if has_map {
+            flags |= BACKGROUND_FLAG_MAP;
+        }
+        if settings.reflections {
+            flags |= BACKGROUND_FLAG_REFLECTIONS;
+        }
+        let rgb = |[r, g, b]: [f32; 3]| [r, g, b, 0.0];
+
+        Self {
+            mode: [mode.as_gpu(), flags, 0, 0],
+            gradient_top: rgb(settings.gradient_top),
+            gradient_bottom: rgb(settings.gradient_bottom),
+            hdri: [settings.hdri_intensity.max(0.0), settings.hdri_rotation_deg / 360.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// GPU representation of the mouse, like shadertoy's `iMouse` but normalized (std140, 32 bytes)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct GpuMouse {
+    /// xy = cursor, zw = last left press, from 0 to 1 with the origin at the
+    /// bottom left of the window
+    pub position: [f32; 4],
+    /// x = `MOUSE_*` bits of the buttons that are down, y = 1 in the frame of
+    /// a left press, zw unused
+    pub buttons: [i32; 4],
+}
+
+impl GpuMouse {
+    /// Pack the mouse state, flipping the window's y axis
+    pub fn new(mouse: &MouseState) -> Self {
+        Self {
+            position: [mouse.position[0], 1.0 - mouse.position[1], mouse.click[0], 1.0 - mouse.click[1]],
+            buttons: [mouse.buttons as i32, mouse.clicked as i32, 0, 0],
+        }
+    }
+}
+
+/// GPU representation of a 3D text (std140, 144 bytes)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct GpuText {
+    /// xyz = position, w = world units per em
+    pub position_size: [f32; 4],
+    /// x = half depth, yz = half size of the box around the glyphs,
+    /// w = distance range of the atlas, all in em
+    pub extent: [f32; 4],
+    /// rgb = color, a = metallic
+    pub color_metallic: [f32; 4],
+    /// x = roughness, yz unused, w = 1 if selected
+    pub material: [f32; 4],
+    /// x = first glyph, y = glyph count, z = display mode, w unused
+    pub info: [i32; 4],
+    /// Rows of the rotation from world space to text space
+    pub inverse_rotation: [[f32; 4]; 3],
+    /// x = render layer mask (see `RenderLayers`), yzw unused
+    pub layers: [u32; 4],
+}
+
+/// GPU representation of a glyph quad of a text (std140, 32 bytes)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct GpuGlyph {
+    /// xy = bottom left, zw = top right, in em from the center of the text
+    pub plane: [f32; 4],
+    /// Atlas coordinates of the bottom left (xy) and top right (zw) corners
+    pub uv: [f32; 4],
+}
+
+/// Complete scene uniform block
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct GpuSceneData {
+    /// x = shape count, y = light count, z = text count, w = modifier count
+    pub counts: [i32; 4],
+    pub shapes: [GpuShape; config::rendering::MAX_SDF_SHAPES],
+    pub lights: [GpuLight; config::rendering::MAX_SDF_LIGHTS],
+    /// Filled in by the renderer, the ECS leaves it zeroed
+    pub background: GpuBackground,
+    /// Filled in by the renderer, the ECS leaves it zeroed
+    pub mouse: GpuMouse,
+    /// Filled in by the renderer, the ECS leaves them zeroed
+    pub texts: [GpuText; config::rendering::MAX_SDF_TEXTS],
+    /// Quads of all texts, each text's consecutively
+    pub glyphs: [GpuGlyph; config::rendering::MAX_SDF_GLYPHS],
+    /// Modifier stacks of all shapes, each shape's consecutively
+    pub modifiers: [GpuModifier; config::rendering::MAX_SDF_MODIFIERS],
+    /// Global noise settings: x = seed, y = scale, zw unused (see `NoiseSettings::as_gpu`)
+    pub noise: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for GpuSceneData {}
+unsafe impl bytemuck::Zeroable for GpuSceneData {}
+
+impl Default for GpuSceneData {
+    fn default() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+}
+
+/// Contents of the `Params` uniform block, laid out by `ShaderParams::gpu_data`
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct GpuShaderParams {
+    /// Block words at their std140 offsets
+    pub words: [u32; config::shader_params::MAX_BLOCK_SIZE / 4],
+}
+
+unsafe impl bytemuck::Pod for GpuShaderParams {}
+unsafe impl bytemuck::Zeroable for GpuShaderParams {}
+
+impl Default for GpuShaderParams {
+    fn default() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+}
+
+impl GpuSceneData {
+    /// Number of shapes currently stored
+    pub fn shape_count(&self) -> usize {
+        self.counts[0] as usize
+    }
+
+    /// Number of lights currently stored
+    pub fn light_count(&self) -> usize {
+        self.counts[1] as usize
+    }
+
+    /// Append a shape to the scene
+    ///
+    /// # Returns
+    /// False if the scene is already at `config::rendering::MAX_SDF_SHAPES`
+    pub fn push_shape(
+        &mut self,
+        shape: &SDFShape,
+        material: &SDFMaterial,
+        transform: &Transform,
+        display_mode: SDFDisplayMode,
+    ) -> bool {
+        let index = self.shape_count();
+        if index >= config::rendering::MAX_SDF_SHAPES {
+            return false;
+        }
+
+        self.shapes[index] = GpuShape {
+            position_size: [transform.position.x, transform.position.y, transform.position.z, shape.size],
+            params: shape.params,
+            color_metallic: [material.color.x, material.color.y, material.color.z, material.metallic],
+            material: [material.roughness, material.emission, 0.0, 0.0],
+            info: [shape_type_to_gpu(&shape.shape_type), display_mode.as_gpu(), 0, 0],
+            modifiers: [0; 4],
+            optics: [
+                material.reflectivity.clamp(0.0, 1.0),
+                material.transparency.clamp(0.0, 1.0),
+                material.ior.max(1.0),
+                0.0,
+            ],
+            layers: [config::layers::DEFAULT, 0, 0, 0],
+        };
+        self.counts[0] += 1;
+        true
+    }
+
+    /// Append a shape as an operand of a CSG composite
+    ///
+    /// Operands of one composite must be pushed consecutively with the same
+    /// group, in evaluation order. The operation of the first operand is ignored.
+    ///
+    /// # Arguments
+    /// * `group` - Composite index, starting at 1
+    /// * `operation` - Operation combining this operand with the previous ones
+    /// * `blend_radius` - Smoothing radius (0 = hard edges)
+    ///
+    /// # Returns
+    /// False if the scene is already at `config::rendering::MAX_SDF_SHAPES`
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_csg_operand(
+        &mut self,
+        shape: &SDFShape,
+        material: &SDFMaterial,
+        transform: &Transform,
+        display_mode: SDFDisplayMode,
+        group: i32,
+        operation: CsgOperation,
+        blend_radius: f32,
+    ) -> bool {
+        if !self.push_shape(shape, material, transform, display_mode) {
+            return false;
+        }
+
+        let gpu_shape = &mut self.shapes[self.shape_count() - 1];
+        gpu_shape.material[2] = blend_radius.max(0.0);
+        gpu_shape.info[2] = operation.as_gpu();
+        gpu_shape.info[3] = group;
+        true
+    }
+
+    /// Mark the shape pushed last as selected, for the selection outline
+    pub fn select_last_shape(&mut self) {
+        if let Some(index) = self.shape_count().checked_sub(1) {
+            self.shapes[index].material[3] = 1.0;
+        }
+    }
+
+    /// Number of modifiers of all shapes currently stored
+    pub fn modifier_count(&self) -> usize {
+        self.counts[3] as usize
+    }
+
+    /// Put the shape pushed last on render layers
+    ///
+    /// Shapes are on `config::layers::DEFAULT` until this is called.
+    ///
+    /// # Arguments
+    /// * `layers` - Layer mask, see `RenderLayers`
+    pub fn set_last_shape_layers(&mut self, layers: u32) {
+        if let Some(index) = self.shape_count().checked_sub(1) {
+            self.shapes[index].layers[0] = layers;
+        }
+    }
+
+    /// Give the shape pushed last a modifier stack
+    ///
+    /// # Returns
+    /// False if the stack does not fit in `config::rendering::MAX_SDF_MODIFIERS`;
+    /// the shape is then left unmodified
+    pub fn modify_last_shape(&mut self, modifiers: &[SDFModifier]) -> bool {
+        let Some(index) = self.shape_count().checked_sub(1) else {
+            return false;
+        };
+        let first = self.modifier_count();
+        if first + modifiers.len() > config::rendering::MAX_SDF_MODIFIERS {
+            return false;
+        }
+
+        for (slot, modifier) in self.modifiers[first..].iter_mut().zip(modifiers) {
+            *slot = GpuModifier { params: modifier.gpu_params(), info: [modifier.as_gpu(), 0, 0, 0] };
+        }
+        self.shapes[index].modifiers[0] = first as i32;
+        self.shapes[index].modifiers[1] = modifiers.len() as i32;
+        self.counts[3] += modifiers.len() as i32;
+        true
+    }
+
+    /// Whether any shape or text is selected
+    pub fn has_selection(&self) -> bool {
+        self.shapes[..self.shape_count()].iter().any(|shape| shape.material[3] > 0.0)
+            || self.texts[..self.text_count()].iter().any(|text| text.material[3] > 0.0)
+    }
+
+    /// Append a light to the scene
+    ///
+    /// A zero direction is replaced by straight down.
+    ///
+    /// # Returns
+    /// False if the scene is already at `config::rendering::MAX_SDF_LIGHTS`
+    pub fn push_light(&mut self, light: &Light) -> bool {
+        let index = self.light_count();
+        if index >= config::rendering::MAX_SDF_LIGHTS {
+            return false;
+        }
+
+        let direction = if light.direction.magnitude2() > 0.0 { light.direction.normalize() } else { -cgmath::Vector3::unit_y() };
+        let (cos_outer, cos_inner) = light.spot_cosines();
+        self.lights[index] = GpuLight {
+            position: [light.position.x, light.position.y, light.position.z, light.radius.max(0.0)],
+            color_intensity: [light.color.x, light.color.y, light.color.z, light.intensity],
+            direction: [direction.x, direction.y, direction.z, 0.0],
+            spot: [cos_outer, cos_inner, 0.0, 0.0],
+            info: [light.kind.as_gpu(), 0, 0, 0],
+        };
+        self.counts[1] += 1;
+        true
+    }
+
+    /// Number of texts currently stored
+    pub fn text_count(&self) -> usize {
+        self.counts[2] as usize
+    }
+
+    /// Remove all texts
+    pub fn clear_texts(&mut self) {
+        self.counts[2] = 0;
+    }
+
+    /// Append a text laid out into glyph quads
+    ///
+    /// The scale of the transform is ignored, like for shapes.
+    ///
+    /// # Arguments
+    /// * `text` - Size, depth and material of the text
+    /// * `transform` - Center and orientation; the text faces local +Z
+    /// * `display_mode` - How the text is drawn
+    /// * `glyphs` - Quads in em, centered on the origin
+    /// * `half_size` - Half size of the box around the quads in em
+    ///
+    /// # Returns
+    /// False if the scene is already at `config::rendering::MAX_SDF_TEXTS`
+    /// texts or the glyphs do not fit in `config::rendering::MAX_SDF_GLYPHS`
+    pub fn push_text(
+        &mut self,
+        text: &Text3D,
+        transform: &Transform,
+        display_mode: SDFDisplayMode,
+        glyphs: &[GpuGlyph],
+        half_size: [f32; 2],
+    ) -> bool {
+        let index = self.text_count();
+        let first_glyph = self.texts[..index].iter().map(|text| (text.info[0] + text.info[1]) as usize).max().unwrap_or(0);
+        if index >= config::rendering::MAX_SDF_TEXTS || first_glyph + glyphs.len() > config::rendering::MAX_SDF_GLYPHS {
+            return false;
+        }
+
+        self.glyphs[first_glyph..first_glyph + glyphs.len()].copy_from_slice(glyphs);
+        let size = text.size.max(1e-3);
+        let rotation = transform.rotation_matrix();
+        let row = |column: cgmath::Vector3<f32>| [column.x, column.y, column.z, 0.0];
+        self.texts[index] = GpuText {
+            position_size: [transform.position.x, transform.position.y, transform.position.z, size],
+            extent: [
+                text.depth.max(0.0) * 0.5 / size,
+                half_size[0],
+                half_size[1],
+                config::text::RANGE_PIXELS / config::text::EM_PIXELS,
+            ],
+            color_metallic: [text.color[0], text.color[1], text.color[2], text.metallic],
+            material: [text.roughness, 0.0, 0.0, 0.0],
+            info: [first_glyph as i32, glyphs.len() as i32, display_mode.as_gpu(), 0],
+            // The inverse of a rotation is its transpose: rows of the one are columns of the other
+            inverse_rotation: [row(rotation.x), row(rotation.y), row(rotation.z)],
+            layers: [config::layers::DEFAULT, 0, 0, 0],
+        };
+        self.counts[2] += 1;
+        true
+    }
+
+    /// Mark the text pushed last as selected, for the selection outline
+    pub fn select_last_text(&mut self) {
+        if let Some(index) = self.text_count().checked_sub(1) {
+            self.texts[index].material[3] = 1.0;
+        }
+    }
+
+    /// Put the text pushed last on render layers
+    ///
+    /// Texts are on `config::layers::DEFAULT` until this is called.
+    ///
+    /// # Arguments
+    /// * `layers` - Layer mask, see `RenderLayers`
+    pub fn set_last_text_layers(&mut self, layers: u32) {
+        if let Some(index) = self.text_count().checked_sub(1) {
+            self.texts[index].layers[0] = layers;
+        }
+    }
+}
+
+/// Convert a shape type to the matching `#define` in sdf.frag
+fn shape_type_to_gpu(shape_type: &SDFShapeType) -> i32 {
+    match shape_type {
+        SDFShapeType::Sphere => 0,
+        SDFShapeType::Box => 1,
+        SDFShapeType::Plane => 2,
+        SDFShapeType::Torus => 3,
+        SDFShapeType::Cylinder => 4,
+    }
+}
+
+/// Per-frame uniform buffers and descriptor sets holding `GpuSceneData`
+pub struct SceneBuffer {
+    /// One region per frame in flight
+    buffer: DynamicBuffer<GpuSceneData>,
+    /// Shader parameters, one region per frame in flight
+    params_buffer: DynamicBuffer<GpuShaderParams>,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    /// Environment map to write into each frame's set before its next use
+    pending_environment: Vec<Option<vk::DescriptorImageInfo>>,
+    /// Font atlas to write into each frame's set before its next use
+    pending_text_atlas: Vec<Option<vk::DescriptorImageInfo>>,
+    device: Device,
+}
+
+impl SceneBuffer {
+    /// Create scene buffers for every frame in flight
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - The physical device used for memory type lookup
+    /// * `device` - The logical device
+    /// * `descriptor_set_layout` - Layout from `VulkanPipeline`
+    /// * `environment` - Environment map sampled at binding 1
+    /// * `text_atlas` - Font atlas sampled at `config::text::ATLAS_BINDING`
+    ///
+    /// # Errors
+    /// Returns an error if buffer, memory or descriptor creation fails
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        environment: vk::DescriptorImageInfo,
+        text_atlas: vk::DescriptorImageInfo,
+    ) -> Result<Self> {
+        let frame_count = config::vulkan::MAX_FRAMES_IN_FLIGHT;
+        let size = std::mem::size_of::<GpuSceneData>() as vk::DeviceSize;
+        debug!("Creating scene uniform buffer ({} frames of {} bytes)", frame_count, size);
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let mut buffer = DynamicBuffer::new(device, &memory_properties, vk::BufferUsageFlags::UNIFORM_BUFFER, 1, frame_count)?;
+        let mut params_buffer = DynamicBuffer::new(device, &memory_properties, vk::BufferUsageFlags::UNIFORM_BUFFER, 1, frame_count)?;
+        for frame in 0..frame_count {
+            buffer.write(frame, &[GpuSceneData::default()]);
+            params_buffer.write(frame, &[GpuShaderParams::default()]);
+        }
+
+        let pool_sizes = [
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(2 * frame_count as u32),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(2 * frame_count as u32),
+        ];
+        let pool_info = vk::DescriptorPoolCreateInfo::default()
+            .pool_sizes(&pool_sizes)
+            .max_sets(frame_count as u32);
+
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(&pool_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create scene descriptor pool: {:?}", e)))?
+        };
+
+        let layouts = vec![descriptor_set_layout; frame_count];
+        let alloc_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+
+        let descriptor_sets = unsafe {
+            device.allocate_descriptor_sets(&alloc_info)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to allocate scene descriptor sets: {:?}", e)))?
+        };
+
+        for (frame, &set) in descriptor_sets.iter().enumerate() {
+            let buffer_infos = [vk::DescriptorBufferInfo::default()
+                .buffer(buffer.handle())
+                .offset(buffer.offset(frame))
+                .range(buffer.range())];
+            let params_infos = [vk::DescriptorBufferInfo::default()
+                .buffer(params_buffer.handle())
+                .offset(params_buffer.offset(frame))
+                .range(params_buffer.range())];
+            let image_infos = [environment];
+            let atlas_infos = [text_atlas];
+            let writes = [
+                vk::WriteDescriptorSet::default()
+                    .dst_set(set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(&buffer_infos),
+                vk::WriteDescriptorSet::default()
+                    .dst_set(set)
+                    .dst_binding(1)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&image_infos),
+                vk::WriteDescriptorSet::default()
+                    .dst_set(set)
+                    .dst_binding(config::shader_params::BINDING)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(&params_infos),
+                vk::WriteDescriptorSet::default()
+                    .dst_set(set)
+                    .dst_binding(config::text::ATLAS_BINDING)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&atlas_infos),
+            ];
+
+            unsafe { device.update_descriptor_sets(&writes, &[]) };
+        }
+
+        info!("Scene uniform buffers created");
+
+        Ok(Self {
+            buffer,
+            params_buffer,
+            descriptor_pool,
+            descriptor_sets,
+            pending_environment: vec![None; frame_count],
+            pending_text_atlas: vec![None; frame_count],
+            device: device.clone(),
+        })
+    }
+
+    /// Write scene data into the buffer for the given frame
+    ///
+    /// Also applies environment map and font atlas changes to that frame's descriptor set.
+    /// The caller must have waited on that frame's in-flight fence.
+    pub fn write(&mut self, frame: usize, data: &GpuSceneData) {
+        self.buffer.write(frame, std::slice::from_ref(data));
+
+        if let Some(environment) = self.pending_environment[frame].take() {
+            let image_infos = [environment];
+            let write = vk::WriteDescriptorSet::default()
+                .dst_set(self.descriptor_sets[frame])
+                .dst_binding(1)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(&image_infos);
+            unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+        }
+        if let Some(text_atlas) = self.pending_text_atlas[frame].take() {
+            let image_infos = [text_atlas];
+            let write = vk::WriteDescriptorSet::default()
+                .dst_set(self.descriptor_sets[frame])
+                .dst_binding(config::text::ATLAS_BINDING)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(&image_infos);
+            unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+        }
+    }
+
+    /// Write the shader parameters into the buffer for the given frame
+    ///
+    /// The caller must have waited on that frame's in-flight fence.
+    pub fn write_params(&mut self, frame: usize, params: &GpuShaderParams) {
+        self.params_buffer.write(frame, std::slice::from_ref(params));
+    }
+
+    /// Bind another environment map
+    ///
+    /// Sets of frames in flight cannot be updated, so every frame's set
+    /// switches in its next `write`.
+    pub fn set_environment(&mut self, environment: vk::DescriptorImageInfo) {
+        self.pending_environment.fill(Some(environment));
+    }
+
+    /// Bind another font atlas, switching every frame's set in its next `write`
+    pub fn set_text_atlas(&mut self, text_atlas: vk::DescriptorImageInfo) {
+        self.pending_text_atlas.fill(Some(text_atlas));
+    }
+
+    /// Descriptor set bound at set 0 for the given frame
+    pub fn descriptor_set(&self, frame: usize) -> vk::DescriptorSet {
+        self.descriptor_sets[frame]
+    }
+}
+
+impl Drop for SceneBuffer {
+    fn drop(&mut self) {
+        debug!("Destroying scene uniform buffers");
+        unsafe {
+            self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+        }
+    }
+}
+
+/// Find a memory type matching the filter and property flags
+pub(crate) fn find_memory_type(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    type_filter: u32,
+    properties: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    (0..memory_properties.memory_type_count).find(|&i| {
+        (type_filter & (1 << i)) != 0
+            && memory_properties.memory_types[i as usize].property_flags.contains(properties)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_struct_sizes_match_std140() {
+        assert_eq!(std::mem::size_of::<GpuShape>(), 128);
+        assert_eq!(std::mem::size_of::<GpuModifier>(), 32);
+        assert_eq!(std::mem::size_of::<GpuLight>(), 80);
+        assert_eq!(std::mem::size_of::<GpuText>(), 144);
+        assert_eq!(std::mem::size_of::<GpuGlyph>(), 32);
+        assert_eq!(
+            std::mem::size_of::<GpuSceneData>(),
+            16 + 128 * config::rendering::MAX_SDF_SHAPES + 80 * config::rendering::MAX_SDF_LIGHTS + 80 + 32
+                + 144 * config::rendering::MAX_SDF_TEXTS + 32 * config::rendering::MAX_SDF_GLYPHS
+                + 32 * config::rendering::MAX_SDF_MODIFIERS + 16
+        );
+    }
+
+    #[test]
+    fn test_mouse_is_normalized_from_bottom_left() {
+        use crate::vulkan::fullscreen_pass::{MOUSE_LEFT, MOUSE_RIGHT};
+
+        let mut mouse = MouseState::default();
+        mouse.move_to([0.25, 0.75]);
+        mouse.set_button(MOUSE_LEFT, true);
+        mouse.set_button(MOUSE_RIGHT, true);
+        let gpu = GpuMouse::new(&mouse);
+        assert_eq!(gpu.position, [0.25, 0.25, 0.25, 0.25]);
+        assert_eq!(gpu.buttons, [(MOUSE_LEFT | MOUSE_RIGHT) as i32, 1, 0, 0]);
+
+        mouse.end_frame();
+        mouse.set_button(MOUSE_LEFT, false);
+        assert_eq!(GpuMouse::new(&mouse).buttons, [MOUSE_RIGHT as i32, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_background_falls_back_to_sky_without_map() {
+        let settings = BackgroundSettings { mode: BackgroundMode::Hdri, reflections: true, ..Default::default() };
+        let background = GpuBackground::new(&settings, false);
+        assert_eq!(background.mode[0], BackgroundMode::ProceduralSky.as_gpu());
+        assert_eq!(background.mode[1], BACKGROUND_FLAG_REFLECTIONS);
+
+        let background = GpuBackground::new(&settings, true);
+        assert_eq!(background.mode[0], BackgroundMode::Hdri.as_gpu());
+        assert_eq!(background.mode[1], BACKGROUND_FLAG_MAP | BACKGROUND_FLAG_REFLECTIONS);
+    }
+
+    #[test]
+    fn test_push_shape_respects_capacity() {
+        let mut data = GpuSceneData::default();
+        let shape = SDFShape::default();
+        let material = SDFMaterial::default();
+        let transform = Transform::default();
+
+        for _ in 0..config::rendering::MAX_SDF_SHAPES {
+            assert!(data.push_shape(&shape, &material, &transform, SDFDisplayMode::Visible));
+        }
+        assert!(!data.push_shape(&shape, &material, &transform, SDFDisplayMode::Visible));
+        assert_eq!(data.shape_count(), config::rendering::MAX_SDF_SHAPES);
+    }
+
+    #[test]
+    fn test_push_shape_encodes_display_mode() {
+        let mut data = GpuSceneData::default();
+        let shape = SDFShape { shape_type: SDFShapeType::Box, ..Default::default() };
+        data.push_shape(&shape, &SDFMaterial::default(), &Transform::default(), SDFDisplayMode::Ghost);
+
+        assert_eq!(data.shapes[0].info[0], 1);
+        assert_eq!(data.shapes[0].info[1], SDFDisplayMode::Ghost.as_gpu());
+    }
+
+    #[test]
+    fn test_push_light_encodes_kind_and_direction() {
+        use crate::ecs::lights::LightKind;
+
+        let mut data = GpuSceneData::default();
+        let spot = Light { kind: LightKind::Spot, direction: cgmath::Vector3::new(0.0, -2.0, 0.0), radius: 4.0, ..Light::default() };
+        assert!(data.push_light(&spot));
+        assert!(data.push_light(&Light { direction: cgmath::Vector3::new(0.0, 0.0, 0.0), ..Light::default() }));
+        assert_eq!(data.lights[0].info[0], LightKind::Spot.as_gpu());
+        assert_eq!(data.lights[0].direction, [0.0, -1.0, 0.0, 0.0]);
+        assert_eq!(data.lights[0].position[3], 4.0);
+        assert_eq!(data.lights[1].direction, [0.0, -1.0, 0.0, 0.0]);
+        assert_eq!(data.light_count(), 2);
+    }
+
+    #[test]
+    fn test_push_shape_clamps_optics() {
+        let mut data = GpuSceneData::default();
+        let glass = SDFMaterial { reflectivity: 0.1, transparency: 1.5, ior: 0.5, ..Default::default() };
+        data.push_shape(&SDFShape::default(), &glass, &Transform::default(), SDFDisplayMode::Visible);
+        assert_eq!(data.shapes[0].optics, [0.1, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_modify_last_shape_appends_stacks() {
+        let mut data = GpuSceneData::default();
+        let twist = [SDFModifier::Twist { rate: 2.0 }];
+        assert!(!data.modify_last_shape(&twist));
+
+        data.push_shape(&SDFShape::default(), &SDFMaterial::default(), &Transform::default(), SDFDisplayMode::Visible);
+        data.push_shape(&SDFShape::default(), &SDFMaterial::default(), &Transform::default(), SDFDisplayMode::Visible);
+        assert!(data.modify_last_shape(&twist));
+        assert_eq!(data.shapes[1].modifiers, [0, 1, config::layers::DEFAULT as i32, 0]);
+        assert_eq!(data.modifiers[0], GpuModifier { params: [2.0, 0.0, 0.0, 0.0], info: [0; 4] });
+
+        // A stack that does not fit leaves the shape unmodified
+        let full = vec![SDFModifier::Round { radius: 0.1 }; config::rendering::MAX_SDF_MODIFIERS];
+        data.push_shape(&SDFShape::default(), &SDFMaterial::default(), &Transform::default(), SDFDisplayMode::Visible);
+        assert!(!data.modify_last_shape(&full));
+        assert_eq!((data.shapes[2].modifiers, data.modifier_count()), ([0, 0, config::layers::DEFAULT as i32, 0], 1));
+
+        // The stack keeps the layers
+        data.set_last_shape_layers(0b101);
+        assert!(data.modify_last_shape(&twist));
+        assert_eq!(data.shapes[2].modifiers, [1, 1, 0b101, 0]);
+    }
+
+    #[test]
+    fn test_push_csg_operand_encodes_group() {
+        let mut data = GpuSceneData::default();
+        data.push_csg_operand(
+            &SDFShape::default(),
+            &SDFMaterial::default(),
+            &Transform::default(),
+            SDFDisplayMode::Visible,
+            2,
+            CsgOperation::Subtraction,
+            0.1,
+        );
+
+        assert_eq!(data.shapes[0].info[2], CsgOperation::Subtraction.as_gpu());
+        assert_eq!(data.shapes[0].info[3], 2);
+        assert_eq!(data.shapes[0].material[2], 0.1);
+    }
+
+    #[test]
+    fn test_select_last_shape() {
+        let mut data = GpuSceneData::default();
+        data.select_last_shape();
+        assert!(!data.has_selection());
+
+        for _ in 0..2 {
+            data.push_shape(&SDFShape::default(), &SDFMaterial::default(), &Transform::default(), SDFDisplayMode::Visible);
+        }
+        data.select_last_shape();
+        assert_eq!(data.shapes[0].material[3], 0.0);
+        assert_eq!(data.shapes[1].material[3], 1.0);
+        assert!(data.has_selection());
+    }
+
+    #[test]
+    fn test_push_text_packs_glyphs_after_the_previous_text() {
+        use cgmath::Vector3;
+
+        let mut data = GpuSceneData::default();
+        let text = Text3D { size: 0.5, depth: 0.2, ..Text3D::default() };
+        let transform = Transform { rotation: Vector3::new(0.0, std::f32::consts::FRAC_PI_2, 0.0), ..Transform::default() };
+        let glyphs = [GpuGlyph::default(); 3];
+        assert!(data.push_text(&text, &transform, SDFDisplayMode::Visible, &glyphs, [1.5, 0.5]));
+        assert!(data.push_text(&text, &Transform::default(), SDFDisplayMode::Ghost, &glyphs[..2], [1.0, 0.5]));
+
+        assert_eq!(data.text_count(), 2);
+        assert_eq!(data.texts[1].info, [3, 2, SDFDisplayMode::Ghost.as_gpu(), 0]);
+        assert!((data.texts[0].extent[0] - 0.2).abs() < 1e-6);
+        // A quarter turn around Y takes world +X to text +Z
+        let row = data.texts[0].inverse_rotation[2];
+        assert!((row[0] - 1.0).abs() < 1e-6 && row[1].abs() < 1e-6 && row[2].abs() < 1e-6);
+
+        let too_many = vec![GpuGlyph::default(); config::rendering::MAX_SDF_GLYPHS];
+        assert!(!data.push_text(&text, &transform, SDFDisplayMode::Visible, &too_many, [1.0, 1.0]));
+        data.select_last_text();
+        assert!(data.has_selection());
+        assert_eq!(data.texts[1].layers[0], config::layers::DEFAULT);
+        data.set_last_text_layers(0b10);
+        assert_eq!(data.texts[1].layers[0], 0b10);
+    }
+}
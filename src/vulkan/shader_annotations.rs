@@ -0,0 +1,312 @@
+//! Shader UI annotations
+//!
+//! The members of a shader's uniform blocks may say how the Shader Parameters
+//! panel edits them with `@` annotations in a trailing comment:
+//!
+//! ```glsl
+//! layout(set = 0, binding = 2) uniform Params {
+//!     // @group(Shadows)
+//!     float shadowMin;   // @slider(0, 1, step=0.05) @default(0.3) Light left in full shadow
+//!     int steps;         // @drag(speed=0.2)
+//!     #pragma ui @group(Lighting)
+//!     vec3 fogTint;      // @color
+//!     bool specular;     // @checkbox @default(1)
+//! } params;
+//! ```
+//!
+//! `@slider(min, max, step=s)`, `@drag(speed=s)`, `@color` and `@checkbox`
+//! pick the control and `@default(...)` the value Reset goes back to, one
+//! number for all components or one per component. `@group(Name)` on a line of
+//! its own, in a comment or a `#pragma ui` line, puts the members below it
+//! under a collapsing header until the next group (`@group()` ends it); on a
+//! member's line it groups just that member. The rest of the comment is the
+//! member's tooltip.
+//!
+//! `ShaderCompiler` parses the annotations whenever it reads a shader.
+//! Malformed annotations are collected as warnings instead of failing the
+//! compile, since the shader itself is fine.
+
+use std::collections::HashMap;
+
+/// Pragma whose lines carry annotations, `#pragma ui @group(Name)`
+const PRAGMA: &str = "ui";
+
+/// Control an annotation asks for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Widget {
+    /// `@slider(min, max, step=s)`, the step is optional
+    Slider { min: f32, max: f32, step: Option<f32> },
+    /// `@drag(speed=s)`, unbounded, the speed is optional
+    Drag { speed: Option<f32> },
+    /// `@color`
+    Color,
+    /// `@checkbox`
+    Checkbox,
+}
+
+impl Widget {
+    /// The annotation's name, for messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Widget::Slider { .. } => "@slider",
+            Widget::Drag { .. } => "@drag",
+            Widget::Color => "@color",
+            Widget::Checkbox => "@checkbox",
+        }
+    }
+}
+
+/// Annotations of one block member
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemberAnnotations {
+    /// Control asked for, None to pick one by the member's type
+    pub widget: Option<Widget>,
+    /// Numbers of `@default(...)`, empty without one
+    pub default: Vec<f32>,
+    /// Group the member is shown in, None for ungrouped
+    pub group: Option<String>,
+    /// Comment text around the annotations
+    pub description: String,
+}
+
+/// Annotations of the uniform block members of a shader source
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderAnnotations {
+    /// Annotations by block name and member name
+    members: HashMap<(String, String), MemberAnnotations>,
+    /// Malformed annotations, with their line numbers
+    pub warnings: Vec<String>,
+}
+
+/// `@name(args)` annotations in a comment and the text around them
+///
+/// Arguments are None for an annotation without parentheses.
+fn split_annotations<'a>(comment: &'a str, warnings: &mut Vec<String>) -> (Vec<(&'a str, Option<&'a str>)>, String) {
+    let mut annotations = Vec::new();
+    let mut description = String::new();
+    let mut rest = comment;
+    while let Some(at) = rest.find('@') {
+        description.push_str(&rest[..at]);
+        let after = &rest[at + 1..];
+        let name_len = after.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(after.len());
+        // An `@` inside a word, like in an address, is text
+        if name_len == 0 || rest[..at].ends_with(|c: char| !c.is_whitespace()) {
+            description.push('@');
+            rest = after;
+            continue;
+        }
+        let (name, mut tail) = after.split_at(name_len);
+        let mut args = None;
+        if let Some(open) = tail.strip_prefix('(') {
+            match open.find(')') {
+                Some(close) => {
+                    args = Some(&open[..close]);
+                    tail = &open[close + 1..];
+                }
+                None => {
+                    warnings.push(format!("@{} is missing a closing parenthesis", name));
+                    tail = "";
+                }
+            }
+        }
+        annotations.push((name, args));
+        rest = tail;
+    }
+    description.push_str(rest);
+    (annotations, description.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Apply one annotation to a member's annotations
+///
+/// # Errors
+/// Returns a message if the annotation is unknown or its arguments do not fit it
+fn apply_annotation(member: &mut MemberAnnotations, name: &str, args: Option<&str>) -> std::result::Result<(), String> {
+    let args = args.unwrap_or("");
+    if name == "group" {
+        member.group = Some(args.trim().trim_matches('"').to_string());
+        return Ok(());
+    }
+
+    let mut positional = Vec::new();
+    let mut named = Vec::new();
+    for arg in args.split(',').map(str::trim).filter(|arg| !arg.is_empty()) {
+        match arg.split_once('=') {
+            Some((key, value)) => named.push((key.trim(), value.trim())),
+            None => positional.push(arg),
+        }
+    }
+    let number = |text: &str| text.parse::<f32>().map_err(|_| format!("@{}: '{}' is not a number", name, text));
+    let numbers = positional.iter().map(|text| number(text)).collect::<std::result::Result<Vec<f32>, String>>()?;
+    let option = |key: &str| named.iter().find(|(name, _)| *name == key).map(|(_, value)| number(value)).transpose();
+    let allowed: &[&str] = match name {
+        "slider" => &["step"],
+        "drag" => &["speed"],
+        _ => &[],
+    };
+    if let Some((key, _)) = named.iter().find(|(key, _)| !allowed.contains(key)) {
+        return Err(format!("@{} has no argument '{}'", name, key));
+    }
+
+    match (name, &numbers[..]) {
+        ("slider", &[min, max]) => member.widget = Some(Widget::Slider { min, max, step: option("step")? }),
+        ("slider", _) => return Err("@slider needs (min, max)".to_string()),
+        ("drag", []) => member.widget = Some(Widget::Drag { speed: option("speed")? }),
+        ("color", []) => member.widget = Some(Widget::Color),
+        ("checkbox", []) => member.widget = Some(Widget::Checkbox),
+        ("default", []) => return Err("@default needs a value".to_string()),
+        ("default", values) => member.default = values.to_vec(),
+        ("drag" | "color" | "checkbox", _) => return Err(format!("@{} takes no values", name)),
+        _ => return Err(format!("unknown annotation @{}", name)),
+    }
+    Ok(())
+}
+
+impl ShaderAnnotations {
+    /// Parse the annotations of a shader source
+    ///
+    /// # Arguments
+    /// * `source` - GLSL source code
+    pub fn parse(source: &str) -> Self {
+        let mut annotations = Self::default();
+        let mut block: Option<String> = None;
+        let mut group: Option<String> = None;
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            let (code, comment) = match line.strip_prefix("#pragma") {
+                Some(pragma) => match pragma.split_whitespace().next() {
+                    Some(PRAGMA) => ("", pragma.trim_start()[PRAGMA.len()..].trim_start()),
+                    _ => continue,
+                },
+                None => line.split_once("//").unwrap_or((line, "")),
+            };
+
+            let Some(block_name) = &block else {
+                let tokens: Vec<&str> = code.split(|c: char| c.is_whitespace() || c == '{').filter(|token| !token.is_empty()).collect();
+                let name = tokens.iter().position(|token| *token == "uniform").and_then(|at| tokens.get(at + 1));
+                if let Some(name) = name.filter(|_| !code.trim_end().ends_with(';')) {
+                    block = Some(name.to_string());
+                    group = None;
+                }
+                continue;
+            };
+            if code.trim_start().starts_with('}') {
+                block = None;
+                continue;
+            }
+
+            let mut warnings = Vec::new();
+            let mut member = MemberAnnotations::default();
+            let (found, description) = split_annotations(comment, &mut warnings);
+            for (name, args) in found {
+                if let Err(message) = apply_annotation(&mut member, name, args) {
+                    warnings.push(message);
+                }
+            }
+
+            let code = code.trim();
+            if code.is_empty() {
+                if let Some(name) = member.group.take() {
+                    group = Some(name).filter(|name| !name.is_empty());
+                }
+                if member.widget.is_some() || !member.default.is_empty() {
+                    warnings.push("annotations are not on a member's line".to_string());
+                }
+            } else if let Some(name) = code.strip_suffix(';').and_then(|code| code.split_whitespace().last()) {
+                let name = name.split('[').next().unwrap_or(name);
+                member.group = member.group.or_else(|| group.clone()).filter(|name| !name.is_empty());
+                member.description = description;
+                if member != MemberAnnotations::default() {
+                    annotations.members.insert((block_name.clone(), name.to_string()), member);
+                }
+            }
+            annotations.warnings.extend(warnings.into_iter().map(|message| format!("line {}: {}", index + 1, message)));
+        }
+        annotations
+    }
+
+    /// Annotations of a block member, None if it has none
+    ///
+    /// # Arguments
+    /// * `block` - Block type name, `Params` for `uniform Params { ... } params;`
+    /// * `member` - Member name
+    pub fn member(&self, block: &str, member: &str) -> Option<&MemberAnnotations> {
+        self.members.get(&(block.to_string(), member.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_widgets_groups_and_descriptions() {
+        let source = "
+            uniform sampler2D environmentMap;  // @color not a block
+            layout(set = 0, binding = 2) uniform Params {
+                vec3 ambientColor;   // @default(0.1) Ambient light
+                // @group(Shadows)
+                float shadowMin;     // @slider(0, 1, step=0.05) @default(0.3) Light left in full shadow
+                int steps[2];        // @drag(speed=0.2) @group(Quality)
+                #pragma ui @group(\"Shading\")
+                vec4 tint;           // @color mail@example.com
+                bool specular;       // @checkbox
+                // @group()
+                float plain;
+            } params;
+            layout(push_constant) uniform PushConstants {
+                float uTime;         // @drag
+            } pc;
+        ";
+        let annotations = ShaderAnnotations::parse(source);
+        assert!(annotations.warnings.is_empty(), "{:?}", annotations.warnings);
+
+        let ambient = annotations.member("Params", "ambientColor").unwrap();
+        assert_eq!((ambient.widget, &ambient.default[..], ambient.group.as_deref()), (None, &[0.1][..], None));
+        assert_eq!(ambient.description, "Ambient light");
+
+        let shadow = annotations.member("Params", "shadowMin").unwrap();
+        assert_eq!(shadow.widget, Some(Widget::Slider { min: 0.0, max: 1.0, step: Some(0.05) }));
+        assert_eq!(shadow.default, [0.3]);
+        assert_eq!(shadow.group.as_deref(), Some("Shadows"));
+        assert_eq!(shadow.description, "Light left in full shadow");
+
+        let steps = annotations.member("Params", "steps").unwrap();
+        assert_eq!(steps.widget, Some(Widget::Drag { speed: Some(0.2) }));
+        assert_eq!(steps.group.as_deref(), Some("Quality"));
+
+        let tint = annotations.member("Params", "tint").unwrap();
+        assert_eq!((tint.widget, tint.group.as_deref()), (Some(Widget::Color), Some("Shading")));
+        assert_eq!(tint.description, "mail@example.com");
+        assert_eq!(annotations.member("Params", "specular").unwrap().group.as_deref(), Some("Shading"));
+        assert_eq!(annotations.member("Params", "plain"), None);
+
+        assert_eq!(annotations.member("PushConstants", "uTime").unwrap().widget, Some(Widget::Drag { speed: None }));
+        assert_eq!(annotations.member("Params", "environmentMap"), None);
+    }
+
+    #[test]
+    fn test_malformed_annotations_become_warnings() {
+        let source = "
+            uniform Params {
+                float a;    // @slider(0) Half a range
+                float b;    // @slider(0, x)
+                float c;    // @drag(step=1)
+                float d;    // @shiny
+                float e;    // @default(1
+                // @color
+            } params;
+        ";
+        let annotations = ShaderAnnotations::parse(source);
+        assert_eq!(annotations.warnings, [
+            "line 3: @slider needs (min, max)",
+            "line 4: @slider: 'x' is not a number",
+            "line 5: @drag has no argument 'step'",
+            "line 6: unknown annotation @shiny",
+            "line 7: @default is missing a closing parenthesis",
+            "line 7: @default needs a value",
+            "line 8: annotations are not on a member's line",
+        ]);
+        // The member still gets the rest of its comment
+        assert_eq!(annotations.member("Params", "a").unwrap().description, "Half a range");
+    }
+}
@@ -5,12 +5,15 @@
 //! without depending on external tools.
 
 use shaderc::Compiler;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use crate::error::{Result, VulkanError};
-use log::{debug, info, error};
+use crate::config;
+use crate::project;
+use crate::error::{Result, ResultExt, Subsystem, VulkanError};
+use crate::vulkan::shader_annotations::ShaderAnnotations;
+use log::{debug, info, warn, error};
 
 /// Shader cache entry containing compiled SPIR-V bytecode
 #[derive(Debug, Clone)]
@@ -25,6 +28,83 @@ struct CacheEntry {
     compiled_at: std::time::SystemTime,
 }
 
+/// Vulkan environment version the SPIR-V is generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShaderTargetEnv {
+    /// Vulkan 1.0 (SPIR-V 1.0)
+    #[default]
+    Vulkan1_0,
+    /// Vulkan 1.1 (SPIR-V 1.3)
+    Vulkan1_1,
+    /// Vulkan 1.2 (SPIR-V 1.5)
+    Vulkan1_2,
+    /// Vulkan 1.3 (SPIR-V 1.6)
+    Vulkan1_3,
+}
+
+impl ShaderTargetEnv {
+    /// All target environments, oldest first
+    pub const ALL: [ShaderTargetEnv; 4] = [
+        ShaderTargetEnv::Vulkan1_0,
+        ShaderTargetEnv::Vulkan1_1,
+        ShaderTargetEnv::Vulkan1_2,
+        ShaderTargetEnv::Vulkan1_3,
+    ];
+
+    /// Display name
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShaderTargetEnv::Vulkan1_0 => "Vulkan 1.0",
+            ShaderTargetEnv::Vulkan1_1 => "Vulkan 1.1",
+            ShaderTargetEnv::Vulkan1_2 => "Vulkan 1.2",
+            ShaderTargetEnv::Vulkan1_3 => "Vulkan 1.3",
+        }
+    }
+
+    /// Matching shaderc environment version
+    fn env_version(&self) -> shaderc::EnvVersion {
+        match self {
+            ShaderTargetEnv::Vulkan1_0 => shaderc::EnvVersion::Vulkan1_0,
+            ShaderTargetEnv::Vulkan1_1 => shaderc::EnvVersion::Vulkan1_1,
+            ShaderTargetEnv::Vulkan1_2 => shaderc::EnvVersion::Vulkan1_2,
+            ShaderTargetEnv::Vulkan1_3 => shaderc::EnvVersion::Vulkan1_3,
+        }
+    }
+}
+
+/// Code generation settings that can be changed at runtime
+///
+/// Defaults follow the build profile (see `config::shader`), but any
+/// build can switch them, e.g. to get debuggable shaders from a release build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShaderCompileSettings {
+    /// shaderc optimization level
+    pub optimization_level: shaderc::OptimizationLevel,
+    /// Vulkan environment the SPIR-V targets
+    pub target_env: ShaderTargetEnv,
+    /// Emit debug information (names, source lines)
+    pub debug_info: bool,
+}
+
+impl Default for ShaderCompileSettings {
+    fn default() -> Self {
+        Self {
+            optimization_level: config::shader::OPTIMIZATION_LEVEL,
+            target_env: ShaderTargetEnv::default(),
+            debug_info: config::shader::ENABLE_SHADER_DEBUG,
+        }
+    }
+}
+
+impl ShaderCompileSettings {
+    /// Optimization levels offered in the UI, with display names
+    pub const OPTIMIZATION_LEVELS: [(shaderc::OptimizationLevel, &'static str); 3] = [
+        (shaderc::OptimizationLevel::Zero, "None"),
+        (shaderc::OptimizationLevel::Size, "Size"),
+        (shaderc::OptimizationLevel::Performance, "Performance"),
+    ];
+}
+
 /// Runtime shader compiler with caching capabilities
 pub struct ShaderCompiler {
     /// Shaderc compiler instance
@@ -37,6 +117,10 @@ pub struct ShaderCompiler {
     enable_debug: bool,
     /// Optimization level for compilation
     optimization_level: shaderc::OptimizationLevel,
+    /// Vulkan environment the SPIR-V is generated for
+    target_env: ShaderTargetEnv,
+    /// UI annotations of each shader file, as of its last read
+    annotations: HashMap<PathBuf, ShaderAnnotations>,
 }
 
 impl ShaderCompiler {
@@ -66,6 +150,8 @@ impl ShaderCompiler {
             } else {
                 shaderc::OptimizationLevel::Performance
             },
+            target_env: ShaderTargetEnv::default(),
+            annotations: HashMap::new(),
         })
     }
     
@@ -84,19 +170,66 @@ impl ShaderCompiler {
                enable_cache, enable_debug, optimization_level);
     }
     
+    /// Get the current code generation settings
+    pub fn settings(&self) -> ShaderCompileSettings {
+        ShaderCompileSettings {
+            optimization_level: self.optimization_level,
+            target_env: self.target_env,
+            debug_info: self.enable_debug,
+        }
+    }
+    
+    /// Change the code generation settings
+    ///
+    /// Cached SPIR-V was generated with the old settings and is dropped, so
+    /// the new settings take effect the next time shaders are (re)compiled.
+    ///
+    /// # Arguments
+    /// * `settings` - The new settings
+    ///
+    /// # Returns
+    /// `true` if the settings changed
+    pub fn set_settings(&mut self, settings: ShaderCompileSettings) -> bool {
+        if settings == self.settings() {
+            return false;
+        }
+        
+        self.optimization_level = settings.optimization_level;
+        self.target_env = settings.target_env;
+        self.enable_debug = settings.debug_info;
+        self.clear_cache();
+        
+        info!("Shader compiler settings changed: {:?}", settings);
+        true
+    }
+    
     /// Compile a GLSL shader file to SPIR-V
     /// 
     /// # Arguments
-    /// * `shader_path` - Path to the GLSL shader file
+    /// * `shader_path` - Path to the GLSL shader file; the open project's copy is used if it has one
     /// * `entry_point` - Entry point function name (usually "main")
+    /// * `defines` - Preprocessor macro definitions as (name, value) pairs
     /// 
     /// # Returns
     /// Compiled SPIR-V bytecode as Vec<u32>
     /// 
     /// # Errors
-    /// Returns an error if compilation fails
-    pub fn compile_file(&mut self, shader_path: &str, entry_point: &str) -> Result<Vec<u32>> {
-        let shader_path = Path::new(shader_path);
+    /// Returns an error if compilation fails, with the shader and its
+    /// defines attached as details
+    pub fn compile_file(&mut self, shader_path: &str, entry_point: &str, defines: &[(&str, &str)]) -> Result<Vec<u32>> {
+        let result = self.compile_path(Path::new(shader_path), entry_point, defines)
+            .context(Subsystem::Shaders, "compile shader")
+            .detail("shader", shader_path);
+        if defines.is_empty() {
+            return result;
+        }
+        let defines: Vec<String> = defines.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        result.detail("defines", defines.join(" "))
+    }
+    
+    /// Read and compile a shader file, see `compile_file`
+    fn compile_path(&mut self, shader_path: &Path, entry_point: &str, defines: &[(&str, &str)]) -> Result<Vec<u32>> {
+        let shader_path = &project::locate(shader_path);
         
         // Determine shader kind from file extension
         let shader_kind = self.determine_shader_kind(shader_path)?;
@@ -105,8 +238,15 @@ impl ShaderCompiler {
         let source = fs::read_to_string(shader_path)
             .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to read shader file '{}': {}", shader_path.display(), e)))?;
         
+        // Annotations are read on every compile, cache hits included, so they follow the source
+        let annotations = ShaderAnnotations::parse(&source);
+        for warning in &annotations.warnings {
+            warn!("Shader annotation in {}, {}", shader_path.display(), warning);
+        }
+        self.annotations.insert(shader_path.clone(), annotations);
+        
         // Compile the shader
-        self.compile_source(&source, shader_path.to_str().unwrap(), entry_point, shader_kind)
+        self.compile_source(&source, shader_path.to_str().unwrap(), entry_point, shader_kind, defines)
     }
     
     /// Compile GLSL source code to SPIR-V
@@ -116,18 +256,29 @@ impl ShaderCompiler {
     /// * `file_name` - File name for error reporting
     /// * `entry_point` - Entry point function name
     /// * `kind` - Shader type (vertex, fragment, etc.)
+    /// * `defines` - Preprocessor macro definitions as (name, value) pairs
     /// 
     /// # Returns
     /// Compiled SPIR-V bytecode as Vec<u32>
     /// 
     /// # Errors
     /// Returns an error if compilation fails
-    pub fn compile_source(&mut self, source: &str, file_name: &str, entry_point: &str, kind: shaderc::ShaderKind) -> Result<Vec<u32>> {
-        debug!("Compiling shader '{}' with entry point '{}'", file_name, entry_point);
+    pub fn compile_source(
+        &mut self,
+        source: &str,
+        file_name: &str,
+        entry_point: &str,
+        kind: shaderc::ShaderKind,
+        defines: &[(&str, &str)],
+    ) -> Result<Vec<u32>> {
+        debug!("Compiling shader '{}' with entry point '{}' and defines {:?}", file_name, entry_point, defines);
+        
+        // Each define set is cached separately
+        let cache_key = Self::cache_key(file_name, defines);
         
         // Check cache first if enabled
         if self.enable_cache {
-            if let Some(cached_spirv) = self.check_cache(source, file_name) {
+            if let Some(cached_spirv) = self.check_cache(source, &cache_key) {
                 info!("Using cached compiled shader: {}", file_name);
                 return Ok(cached_spirv);
             }
@@ -141,14 +292,24 @@ impl ShaderCompiler {
         // Set optimization level
         compile_options.set_optimization_level(self.optimization_level);
         
-        // Enable debug info in debug builds
+        // Preprocessor defines for shader variants
+        for (name, value) in defines {
+            compile_options.add_macro_definition(name, Some(value));
+        }
+        
+        // `#include` files are read as the source is compiled
+        compile_options.set_include_callback(|requested, include_type, requesting, _depth| {
+            Self::resolve_include(requested, include_type, requesting)
+        });
+        
+        // Enable debug info if requested (defaults to debug builds)
         if self.enable_debug {
             compile_options.set_generate_debug_info();
             debug!("Debug info enabled for shader compilation");
         }
         
-        // Set target environment to Vulkan 1.0
-        compile_options.set_target_env(shaderc::TargetEnv::Vulkan, shaderc::EnvVersion::Vulkan1_0 as u32);
+        // Set target environment
+        compile_options.set_target_env(shaderc::TargetEnv::Vulkan, self.target_env.env_version() as u32);
         
         // Compile the shader
         let artifact = self.compiler
@@ -172,12 +333,60 @@ impl ShaderCompiler {
         
         // Cache the result if enabled
         if self.enable_cache {
-            self.cache_result(file_name, &spirv);
+            self.cache_result(&cache_key, &spirv);
         }
         
         Ok(spirv)
     }
     
+    /// Find and read an `#include`d file
+    ///
+    /// `#include "file"` is looked up next to the including file first, then
+    /// like `#include <file>` in `config::shader::INCLUDE_DIR`. The open
+    /// project's copy of a file is used if it has one, as for shader files.
+    ///
+    /// # Arguments
+    /// * `requested` - File named by the directive
+    /// * `include_type` - Quoted (relative) or angle-bracket (standard) include
+    /// * `requesting` - Path of the including file
+    ///
+    /// # Returns
+    /// The file's path and content, or a message for the compile error
+    fn resolve_include(
+        requested: &str,
+        include_type: shaderc::IncludeType,
+        requesting: &str,
+    ) -> std::result::Result<shaderc::ResolvedInclude, String> {
+        let next_to_requesting = matches!(include_type, shaderc::IncludeType::Relative)
+            .then(|| Path::new(requesting).parent().unwrap_or(Path::new("")).join(requested));
+        let in_include_dir = Path::new(config::shader::INCLUDE_DIR).join(requested);
+        
+        for candidate in next_to_requesting.into_iter().chain(std::iter::once(in_include_dir)) {
+            let path = project::locate(&candidate);
+            if path.is_file() {
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read include file '{}': {}", path.display(), e))?;
+                debug!("Including '{}' in '{}'", path.display(), requesting);
+                return Ok(shaderc::ResolvedInclude { resolved_name: path.to_string_lossy().into_owned(), content });
+            }
+        }
+        Err(format!("Include file '{}' not found for '{}'", requested, requesting))
+    }
+    
+    /// Whether a file is an include file rather than a shader stage
+    pub fn is_include_file(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("glsl"))
+    }
+    
+    /// Build the cache key for a file compiled with a set of defines
+    fn cache_key(file_name: &str, defines: &[(&str, &str)]) -> String {
+        let mut key = file_name.to_string();
+        for (name, value) in defines {
+            key.push_str(&format!("|{}={}", name, value));
+        }
+        key
+    }
+    
     /// Determine shader kind from file extension
     /// 
     /// # Arguments
@@ -275,6 +484,17 @@ impl ShaderCompiler {
         hasher.finish()
     }
     
+    /// UI annotations of a shader file, as of the last time it was compiled
+    ///
+    /// # Arguments
+    /// * `shader_path` - Path the shader was compiled by; the open project's copy is used if it has one
+    ///
+    /// # Returns
+    /// The annotations, None if the file has not been compiled yet
+    pub fn annotations(&self, shader_path: &str) -> Option<&ShaderAnnotations> {
+        self.annotations.get(&project::locate(Path::new(shader_path)))
+    }
+    
     /// Clear the shader cache
     #[allow(dead_code)]
     pub fn clear_cache(&self) {
@@ -283,6 +503,29 @@ impl ShaderCompiler {
         info!("Shader cache cleared");
     }
     
+    /// Drop every cached variant of a shader file
+    ///
+    /// Entries are matched by file name, so the path reported by the file
+    /// watcher does not need to match the path used for compilation.
+    ///
+    /// # Arguments
+    /// * `shader_path` - Path of the changed shader file
+    ///
+    /// # Returns
+    /// Number of removed cache entries
+    pub fn invalidate_file(&self, shader_path: &str) -> usize {
+        let file_name = Path::new(shader_path).file_name();
+        let mut cache = self.cache.lock().unwrap();
+        let before = cache.len();
+        cache.retain(|key, _| {
+            let key_path = key.split('|').next().unwrap_or(key);
+            Path::new(key_path).file_name() != file_name
+        });
+        let removed = before - cache.len();
+        debug!("Invalidated {} cached variants of {}", removed, shader_path);
+        removed
+    }
+    
     /// Get cache statistics
     /// 
     /// # Returns
@@ -309,7 +552,7 @@ impl ShaderCompiler {
         
         for &shader_path in shader_paths {
             debug!("Preloading shader: {}", shader_path);
-            self.compile_file(shader_path, "main")?;
+            self.compile_file(shader_path, "main", &[])?;
         }
         
         info!("Shader preloading completed successfully");
@@ -375,6 +618,54 @@ mod tests {
         assert_eq!(size, 0);
     }
     
+    #[test]
+    fn test_cache_key_includes_defines() {
+        assert_eq!(ShaderCompiler::cache_key("a.frag", &[]), "a.frag");
+        assert_ne!(
+            ShaderCompiler::cache_key("a.frag", &[("ENABLE_AO", "1")]),
+            ShaderCompiler::cache_key("a.frag", &[])
+        );
+    }
+    
+    #[test]
+    fn test_invalidate_file_removes_all_variants() {
+        let compiler = ShaderCompiler::new().unwrap();
+        compiler.cache_result(&ShaderCompiler::cache_key("shaders/a.frag", &[]), &[1]);
+        compiler.cache_result(&ShaderCompiler::cache_key("shaders/a.frag", &[("ENABLE_AO", "1")]), &[2]);
+        compiler.cache_result(&ShaderCompiler::cache_key("shaders/b.frag", &[]), &[3]);
+        
+        assert_eq!(compiler.invalidate_file("/project/shaders/a.frag"), 2);
+        assert_eq!(compiler.get_cache_stats().0, 1);
+    }
+    
+    #[test]
+    fn test_set_settings_clears_cache() {
+        let mut compiler = ShaderCompiler::new().unwrap();
+        compiler.cache_result("shaders/a.frag", &[1]);
+        
+        let unchanged = compiler.settings();
+        assert!(!compiler.set_settings(unchanged));
+        assert_eq!(compiler.get_cache_stats().0, 1);
+        
+        let settings = ShaderCompileSettings { target_env: ShaderTargetEnv::Vulkan1_2, ..unchanged };
+        assert!(compiler.set_settings(settings));
+        assert_eq!(compiler.settings().target_env, ShaderTargetEnv::Vulkan1_2);
+        assert_eq!(compiler.get_cache_stats().0, 0);
+    }
+    
+    #[test]
+    fn test_includes_resolve_next_to_the_file_then_in_the_include_dir() {
+        let relative = ShaderCompiler::resolve_include("include/noise.glsl", shaderc::IncludeType::Relative, "shaders/sdf.frag").unwrap();
+        assert!(relative.content.contains("float simplexNoise("));
+        let standard = ShaderCompiler::resolve_include("noise.glsl", shaderc::IncludeType::Standard, "shaders/sdf.frag").unwrap();
+        assert_eq!(standard.content, relative.content);
+        
+        // Standard includes are not looked up next to the file
+        assert!(ShaderCompiler::resolve_include("include/noise.glsl", shaderc::IncludeType::Standard, "shaders/sdf.frag").is_err());
+        assert!(ShaderCompiler::is_include_file(Path::new("shaders/include/noise.glsl")));
+        assert!(!ShaderCompiler::is_include_file(Path::new("shaders/sdf.frag")));
+    }
+    
     #[test]
     fn test_source_hashing() {
         let compiler = ShaderCompiler::new().unwrap();
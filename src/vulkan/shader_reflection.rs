@@ -0,0 +1,353 @@
+//! SPIR-V reflection of uniform and push constant blocks
+//!
+//! Reads the names, bindings and member layout of the `uniform` blocks and
+//! the push constant block straight from the instruction stream of a
+//! compiled shader, so the HUD can build editors for them (see
+//! `shader_params`). Only what those editors need is reflected: scalar and
+//! vector members get a type, matrices, arrays and nested structs are listed
+//! without one.
+
+use std::collections::HashMap;
+use crate::error::{Result, VulkanError};
+
+/// First word of every SPIR-V module
+const MAGIC: u32 = 0x0723_0203;
+/// Words in the module header before the first instruction
+const HEADER_WORDS: usize = 5;
+
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+
+/// Component type of a block member
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarType {
+    /// 32 bit float
+    Float,
+    /// 32 bit signed integer
+    Int,
+    /// 32 bit unsigned integer; GLSL `bool` members of a block compile to this
+    UInt,
+    /// Boolean, only seen outside of blocks
+    Bool,
+}
+
+/// Type of a scalar or vector block member
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemberType {
+    /// Component type
+    pub scalar: ScalarType,
+    /// Number of components, 1 for a scalar
+    pub components: u32,
+}
+
+impl MemberType {
+    /// GLSL name of the type (`float`, `vec3`, `uint`, ...)
+    pub fn glsl_name(&self) -> String {
+        let (scalar, prefix) = match self.scalar {
+            ScalarType::Float => ("float", ""),
+            ScalarType::Int => ("int", "i"),
+            ScalarType::UInt => ("uint", "u"),
+            ScalarType::Bool => ("bool", "b"),
+        };
+        match self.components {
+            1 => scalar.to_string(),
+            n => format!("{}vec{}", prefix, n),
+        }
+    }
+}
+
+/// Member of a reflected block
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMember {
+    /// Member name, `member<index>` when the shader was compiled without names
+    pub name: String,
+    /// Byte offset in the block
+    pub offset: u32,
+    /// Type of a scalar or vector member, None for any other
+    pub ty: Option<MemberType>,
+}
+
+/// Where a block is bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockBinding {
+    /// Uniform buffer at a descriptor set and binding
+    Uniform { set: u32, binding: u32 },
+    /// The push constant block
+    PushConstant,
+}
+
+/// A `uniform` or push constant block of a shader
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformBlock {
+    /// Block (type) name, e.g. `SceneData`
+    pub name: String,
+    /// Where the block is bound
+    pub binding: BlockBinding,
+    /// Members in declaration order
+    pub members: Vec<BlockMember>,
+}
+
+/// What the instruction stream says about one id
+#[derive(Debug, Default)]
+struct IdInfo {
+    name: Option<String>,
+    member_names: HashMap<u32, String>,
+    member_offsets: HashMap<u32, u32>,
+    is_block: bool,
+    set: Option<u32>,
+    binding: Option<u32>,
+}
+
+/// A type declaration the reflection cares about
+#[derive(Debug, Clone)]
+enum TypeDecl {
+    Scalar(ScalarType),
+    Vector(u32, u32),
+    Struct(Vec<u32>),
+    Pointer(u32, u32),
+}
+
+/// Decode a literal string starting at the first word of `words`
+fn literal_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take_while(|&byte| byte != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Reflect the uniform and push constant blocks of a SPIR-V module
+///
+/// # Arguments
+/// * `spirv` - Module words, as returned by the shader compiler
+///
+/// # Returns
+/// Uniform blocks ordered by set and binding, then the push constant block
+///
+/// # Errors
+/// Returns an error if the words are not a well-formed SPIR-V module
+pub fn uniform_blocks(spirv: &[u32]) -> Result<Vec<UniformBlock>> {
+    if spirv.len() < HEADER_WORDS || spirv[0] != MAGIC {
+        return Err(VulkanError::ShaderCompilation("Not a SPIR-V module".to_string()).into());
+    }
+
+    let mut ids: HashMap<u32, IdInfo> = HashMap::new();
+    let mut types: HashMap<u32, TypeDecl> = HashMap::new();
+    let mut variables: Vec<(u32, u32)> = Vec::new();
+
+    let mut position = HEADER_WORDS;
+    while position < spirv.len() {
+        let word_count = (spirv[position] >> 16) as usize;
+        let opcode = spirv[position] & 0xffff;
+        if word_count == 0 || position + word_count > spirv.len() {
+            return Err(VulkanError::ShaderCompilation(format!("Malformed SPIR-V instruction at word {}", position)).into());
+        }
+        let operands = &spirv[position + 1..position + word_count];
+        position += word_count;
+
+        match (opcode, operands) {
+            (OP_NAME, [target, name @ ..]) => {
+                ids.entry(*target).or_default().name = Some(literal_string(name));
+            }
+            (OP_MEMBER_NAME, [target, member, name @ ..]) => {
+                ids.entry(*target).or_default().member_names.insert(*member, literal_string(name));
+            }
+            (OP_TYPE_BOOL, [result]) => {
+                types.insert(*result, TypeDecl::Scalar(ScalarType::Bool));
+            }
+            (OP_TYPE_INT, [result, 32, signedness]) => {
+                let scalar = if *signedness == 0 { ScalarType::UInt } else { ScalarType::Int };
+                types.insert(*result, TypeDecl::Scalar(scalar));
+            }
+            (OP_TYPE_FLOAT, [result, 32]) => {
+                types.insert(*result, TypeDecl::Scalar(ScalarType::Float));
+            }
+            (OP_TYPE_VECTOR, [result, component, count]) => {
+                types.insert(*result, TypeDecl::Vector(*component, *count));
+            }
+            (OP_TYPE_STRUCT, [result, members @ ..]) => {
+                types.insert(*result, TypeDecl::Struct(members.to_vec()));
+            }
+            (OP_TYPE_POINTER, [result, storage_class, pointee]) => {
+                types.insert(*result, TypeDecl::Pointer(*storage_class, *pointee));
+            }
+            (OP_VARIABLE, [result_type, result, _storage_class, ..]) => {
+                variables.push((*result_type, *result));
+            }
+            (OP_DECORATE, [target, decoration, literals @ ..]) => {
+                let info = ids.entry(*target).or_default();
+                match (*decoration, literals) {
+                    (DECORATION_BLOCK, _) => info.is_block = true,
+                    (DECORATION_BINDING, [binding, ..]) => info.binding = Some(*binding),
+                    (DECORATION_DESCRIPTOR_SET, [set, ..]) => info.set = Some(*set),
+                    _ => {}
+                }
+            }
+            (OP_MEMBER_DECORATE, [target, member, DECORATION_OFFSET, offset, ..]) => {
+                ids.entry(*target).or_default().member_offsets.insert(*member, *offset);
+            }
+            _ => {}
+        }
+    }
+
+    let member_type = |id: u32| match types.get(&id) {
+        Some(TypeDecl::Scalar(scalar)) => Some(MemberType { scalar: *scalar, components: 1 }),
+        Some(TypeDecl::Vector(component, count)) => match types.get(component) {
+            Some(TypeDecl::Scalar(scalar)) => Some(MemberType { scalar: *scalar, components: *count }),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let no_info = IdInfo::default();
+    let mut blocks = Vec::new();
+    for (pointer_type, variable) in variables {
+        let Some(&TypeDecl::Pointer(storage_class, struct_id)) = types.get(&pointer_type) else {
+            continue;
+        };
+        let Some(TypeDecl::Struct(member_types)) = types.get(&struct_id) else {
+            continue;
+        };
+        let struct_info = ids.get(&struct_id).unwrap_or(&no_info);
+        let variable_info = ids.get(&variable).unwrap_or(&no_info);
+        let binding = match storage_class {
+            STORAGE_CLASS_PUSH_CONSTANT => BlockBinding::PushConstant,
+            STORAGE_CLASS_UNIFORM if struct_info.is_block => BlockBinding::Uniform {
+                set: variable_info.set.unwrap_or(0),
+                binding: variable_info.binding.unwrap_or(0),
+            },
+            _ => continue,
+        };
+        let members = member_types
+            .iter()
+            .enumerate()
+            .map(|(index, &ty)| {
+                let index = index as u32;
+                BlockMember {
+                    name: struct_info.member_names.get(&index).cloned().unwrap_or_else(|| format!("member{}", index)),
+                    offset: struct_info.member_offsets.get(&index).copied().unwrap_or(0),
+                    ty: member_type(ty),
+                }
+            })
+            .collect();
+        blocks.push(UniformBlock {
+            name: struct_info.name.clone().unwrap_or_default(),
+            binding,
+            members,
+        });
+    }
+
+    blocks.sort_by_key(|block| match block.binding {
+        BlockBinding::Uniform { set, binding } => (0, set, binding),
+        BlockBinding::PushConstant => (1, 0, 0),
+    });
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode an instruction
+    fn op(opcode: u32, operands: &[u32]) -> Vec<u32> {
+        let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode];
+        words.extend_from_slice(operands);
+        words
+    }
+
+    /// Encode a literal string, nul terminated and padded to whole words
+    fn string(text: &str) -> Vec<u32> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        bytes.resize(bytes.len().div_ceil(4) * 4, 0);
+        bytes.chunks(4).map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])).collect()
+    }
+
+    /// A module with `uniform Params { vec3 tint; float gain; bool on; mat4 m; }`
+    /// at set 0 binding 2 and a push constant block `{ int mode; }`
+    fn module() -> Vec<u32> {
+        let (float, vec3, uint, int, mat4) = (1, 2, 3, 4, 5);
+        let (params, params_ptr, params_var) = (10, 11, 12);
+        let (push, push_ptr, push_var) = (20, 21, 22);
+        let mut words = vec![MAGIC, 0x0001_0000, 0, 30, 0];
+        words.extend(op(OP_NAME, &[&[params][..], &string("Params")].concat()));
+        words.extend(op(OP_MEMBER_NAME, &[&[params, 0][..], &string("tint")].concat()));
+        words.extend(op(OP_MEMBER_NAME, &[&[params, 1][..], &string("gain")].concat()));
+        words.extend(op(OP_MEMBER_NAME, &[&[params, 2][..], &string("on")].concat()));
+        words.extend(op(OP_MEMBER_NAME, &[&[params, 3][..], &string("m")].concat()));
+        words.extend(op(OP_NAME, &[&[push][..], &string("PushConstants")].concat()));
+        words.extend(op(OP_MEMBER_NAME, &[&[push, 0][..], &string("mode")].concat()));
+        words.extend(op(OP_DECORATE, &[params, DECORATION_BLOCK]));
+        words.extend(op(OP_DECORATE, &[params_var, DECORATION_DESCRIPTOR_SET, 0]));
+        words.extend(op(OP_DECORATE, &[params_var, DECORATION_BINDING, 2]));
+        for (member, offset) in [(0, 0), (1, 12), (2, 16), (3, 32)] {
+            words.extend(op(OP_MEMBER_DECORATE, &[params, member, DECORATION_OFFSET, offset]));
+        }
+        words.extend(op(OP_DECORATE, &[push, DECORATION_BLOCK]));
+        words.extend(op(OP_MEMBER_DECORATE, &[push, 0, DECORATION_OFFSET, 0]));
+        words.extend(op(OP_TYPE_FLOAT, &[float, 32]));
+        words.extend(op(OP_TYPE_VECTOR, &[vec3, float, 3]));
+        words.extend(op(OP_TYPE_INT, &[uint, 32, 0]));
+        words.extend(op(OP_TYPE_INT, &[int, 32, 1]));
+        // Stands in for a matrix, which is not reflected
+        words.extend(op(24, &[mat4, vec3, 4]));
+        words.extend(op(OP_TYPE_STRUCT, &[params, vec3, float, uint, mat4]));
+        words.extend(op(OP_TYPE_POINTER, &[params_ptr, STORAGE_CLASS_UNIFORM, params]));
+        words.extend(op(OP_TYPE_STRUCT, &[push, int]));
+        words.extend(op(OP_TYPE_POINTER, &[push_ptr, STORAGE_CLASS_PUSH_CONSTANT, push]));
+        words.extend(op(OP_VARIABLE, &[push_ptr, push_var, STORAGE_CLASS_PUSH_CONSTANT]));
+        words.extend(op(OP_VARIABLE, &[params_ptr, params_var, STORAGE_CLASS_UNIFORM]));
+        words
+    }
+
+    #[test]
+    fn test_reflects_uniform_and_push_constant_blocks() {
+        let blocks = uniform_blocks(&module()).unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        let params = &blocks[0];
+        assert_eq!(params.name, "Params");
+        assert_eq!(params.binding, BlockBinding::Uniform { set: 0, binding: 2 });
+        let members: Vec<(&str, u32, Option<String>)> = params.members
+            .iter()
+            .map(|member| (member.name.as_str(), member.offset, member.ty.map(|ty| ty.glsl_name())))
+            .collect();
+        assert_eq!(members, [
+            ("tint", 0, Some("vec3".to_string())),
+            ("gain", 12, Some("float".to_string())),
+            ("on", 16, Some("uint".to_string())),
+            ("m", 32, None),
+        ]);
+
+        assert_eq!(blocks[1].name, "PushConstants");
+        assert_eq!(blocks[1].binding, BlockBinding::PushConstant);
+        assert_eq!(blocks[1].members[0].ty, Some(MemberType { scalar: ScalarType::Int, components: 1 }));
+    }
+
+    #[test]
+    fn test_rejects_malformed_modules() {
+        assert!(uniform_blocks(&[]).is_err());
+        assert!(uniform_blocks(&[0xdead_beef, 0, 0, 0, 0]).is_err());
+        let mut truncated = module();
+        truncated.push((5 << 16) | OP_NAME);
+        assert!(uniform_blocks(&truncated).is_err());
+    }
+}
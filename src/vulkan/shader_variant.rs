@@ -0,0 +1,155 @@
+//! Shader variants selected by preprocessor defines
+//!
+//! A `ShaderVariantKey` is a set of preprocessor defines passed to
+//! `ShaderCompiler::compile_file`. Optional features live in one shader file
+//! behind `#ifdef`s, and `VulkanPipeline` keeps one graphics pipeline per key
+//! so switching features at runtime does not recompile anything that was
+//! built before.
+
+use std::collections::BTreeMap;
+use crate::config;
+
+/// Define enabling penumbra soft shadows in the SDF fragment shader
+pub const SOFT_SHADOWS: &str = "ENABLE_SOFT_SHADOWS";
+
+/// Define enabling SDF ambient occlusion in the SDF fragment shader
+pub const AMBIENT_OCCLUSION: &str = "ENABLE_AO";
+
+/// Define enabling cone traced global illumination in the SDF fragment shader
+pub const CONE_GI: &str = "ENABLE_CONE_GI";
+
+/// Define enabling traced reflections and refraction in the SDF fragment shader
+pub const REFLECTIONS: &str = "ENABLE_REFLECTIONS";
+
+/// Define setting the rays traced per pixel for antialiasing in the SDF fragment shader
+pub const AA_SAMPLES: &str = "AA_SAMPLES";
+
+/// Toggleable SDF shader features as (define, label) pairs, in display order
+pub const SDF_FEATURES: [(&str, &str); 4] = [
+    (SOFT_SHADOWS, "Soft shadows"),
+    (AMBIENT_OCCLUSION, "Ambient occlusion"),
+    (CONE_GI, "Cone traced GI"),
+    (REFLECTIONS, "Reflections and refraction"),
+];
+
+/// Set of preprocessor defines identifying one shader variant
+///
+/// Defines are kept sorted so equal sets always produce the same key and the
+/// same define order for the compiler.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ShaderVariantKey {
+    defines: BTreeMap<String, String>,
+}
+
+impl ShaderVariantKey {
+    /// Create a variant without any defines
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default variant of the SDF fragment shader
+    pub fn sdf_default() -> Self {
+        Self::new()
+            .with_feature(SOFT_SHADOWS, config::shader_features::SOFT_SHADOWS)
+            .with_feature(AMBIENT_OCCLUSION, config::shader_features::AMBIENT_OCCLUSION)
+            .with_feature(CONE_GI, config::shader_features::CONE_GI)
+            .with_feature(REFLECTIONS, config::shader_features::REFLECTIONS)
+    }
+
+    /// Add a define with a value
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Add a feature define (`NAME=1`) if `enabled`
+    pub fn with_feature(mut self, name: &str, enabled: bool) -> Self {
+        self.set_feature(name, enabled);
+        self
+    }
+
+    /// Enable or disable a feature define (`NAME=1`)
+    pub fn set_feature(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.defines.insert(name.to_string(), "1".to_string());
+        } else {
+            self.defines.remove(name);
+        }
+    }
+
+    /// Set the antialiasing rays per pixel; one ray needs no define
+    pub fn set_samples(&mut self, samples: u32) {
+        if samples > 1 {
+            self.defines.insert(AA_SAMPLES.to_string(), samples.to_string());
+        } else {
+            self.defines.remove(AA_SAMPLES);
+        }
+    }
+
+    /// Antialiasing rays per pixel of this variant
+    pub fn samples(&self) -> u32 {
+        self.defines.get(AA_SAMPLES).and_then(|value| value.parse().ok()).unwrap_or(1)
+    }
+
+    /// Check whether a define is present
+    pub fn has_define(&self, name: &str) -> bool {
+        self.defines.contains_key(name)
+    }
+
+    /// Preprocessor defines of this variant as (name, value) pairs
+    pub fn defines(&self) -> Vec<(&str, &str)> {
+        self.defines
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// Short description for logs and the HUD
+    pub fn name(&self) -> String {
+        if self.defines.is_empty() {
+            return "base".to_string();
+        }
+
+        self.defines
+            .iter()
+            .map(|(name, value)| if value == "1" { name.clone() } else { format!("{}={}", name, value) })
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defines_are_order_independent() {
+        let a = ShaderVariantKey::new().with_feature(SOFT_SHADOWS, true).with_feature(AMBIENT_OCCLUSION, true);
+        let b = ShaderVariantKey::new().with_feature(AMBIENT_OCCLUSION, true).with_feature(SOFT_SHADOWS, true);
+        assert_eq!(a, b);
+        assert_eq!(a.defines(), vec![(AMBIENT_OCCLUSION, "1"), (SOFT_SHADOWS, "1")]);
+    }
+
+    #[test]
+    fn test_set_feature_toggles_define() {
+        let mut key = ShaderVariantKey::new().with_define("SAMPLES", "4");
+        key.set_feature(SOFT_SHADOWS, true);
+        assert!(key.has_define(SOFT_SHADOWS));
+        assert_eq!(key.name(), "ENABLE_SOFT_SHADOWS + SAMPLES=4");
+
+        key.set_feature(SOFT_SHADOWS, false);
+        assert!(!key.has_define(SOFT_SHADOWS));
+        assert_eq!(key.defines(), vec![("SAMPLES", "4")]);
+    }
+
+    #[test]
+    fn test_samples_define() {
+        let mut key = ShaderVariantKey::sdf_default();
+        assert_eq!(key.samples(), 1);
+        key.set_samples(4);
+        assert_eq!(key.samples(), 4);
+        assert!(key.name().contains("AA_SAMPLES=4"));
+        key.set_samples(1);
+        assert_eq!(key, ShaderVariantKey::sdf_default());
+    }
+}
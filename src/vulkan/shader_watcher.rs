@@ -5,26 +5,29 @@
 
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
-use std::collections::{HashMap, VecDeque};
-use notify::{Watcher, RecursiveMode, Event, RecommendedWatcher};
+use std::time::{Duration, Instant};
+use std::collections::VecDeque;
 use log::{info, error, debug, warn};
 use crate::error::{Result, VulkanError};
+use crate::asset_watcher::{AssetChange, AssetKind, AssetWatcher, SubscriptionId};
 use crate::vulkan::shader_compiler::ShaderCompiler;
 use crate::vulkan::pipeline::VulkanPipeline;
+use crate::vulkan::reload_stats::{ShaderFileStats, ShaderReloadStats};
+use crate::settings::AppSettings;
 use crate::config;
+use crate::project;
 
 /// Shader change event callback type
 pub type ShaderChangeCallback = Box<dyn Fn(&str, &str) -> Result<()> + Send + Sync>;
 
 /// Hot reload configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HotReloadConfig {
     /// Enable/disable hot reload
     pub enabled: bool,
-    /// Shader directory to watch
-    pub shader_dir: PathBuf,
-    /// Debounce time for file changes (milliseconds)
+    /// Directory trees to watch: the shader directory, then include directories
+    pub watch_roots: Vec<PathBuf>,
+    /// Quiet time after the last event of a file before it is reloaded (milliseconds)
     pub debounce_ms: u64,
     /// File extensions to watch
     pub watch_extensions: Vec<String>,
@@ -34,7 +37,10 @@ impl Default for HotReloadConfig {
     fn default() -> Self {
         Self {
             enabled: config::hot_reload::ENABLED,
-            shader_dir: PathBuf::from(config::hot_reload::SHADER_DIR),
+            watch_roots: std::iter::once(config::hot_reload::SHADER_DIR)
+                .chain(config::hot_reload::INCLUDE_DIRS.iter().copied())
+                .map(PathBuf::from)
+                .collect(),
             debounce_ms: config::hot_reload::DEBOUNCE_MS,
             watch_extensions: config::hot_reload::WATCH_EXTENSIONS.iter()
                 .map(|s| s.to_string())
@@ -43,25 +49,72 @@ impl Default for HotReloadConfig {
     }
 }
 
+impl HotReloadConfig {
+    /// Configuration for the runtime settings
+    ///
+    /// The shader directory of an open project is watched as well.
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        let project = project::current();
+        let project_shader_dir = project.is_open().then(|| project.shader_dir());
+        Self {
+            enabled: settings.hot_reload.enabled,
+            watch_roots: std::iter::once(settings.shader_dir.clone())
+                .chain(project_shader_dir)
+                .chain(settings.include_dirs.iter().cloned())
+                .collect(),
+            debounce_ms: settings.hot_reload.debounce_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Watch roots without duplicates and without directories inside another root
+    ///
+    /// Watching `shaders/lib` next to `shaders` would report every change
+    /// in it twice.
+    pub fn distinct_roots(&self) -> Vec<PathBuf> {
+        let canonical = |path: &PathBuf| path.canonicalize().unwrap_or_else(|_| path.clone());
+        let roots: Vec<(PathBuf, PathBuf)> = self.watch_roots.iter().map(|root| (root.clone(), canonical(root))).collect();
+        roots
+            .iter()
+            .enumerate()
+            .filter(|(index, (_, root))| {
+                !roots.iter().enumerate().any(|(other_index, (_, other))| {
+                    // Nested in another root, or a duplicate of an earlier one
+                    (root.starts_with(other) && root != other) || (root == other && other_index < *index)
+                })
+            })
+            .map(|(_, (root, _))| root.clone())
+            .collect()
+    }
+}
+
+/// Shader kind for a shader file extension
+pub(crate) fn shader_kind(extension: &str) -> &'static str {
+    match extension {
+        "vert" => "vertex",
+        "frag" => "fragment",
+        "geom" => "geometry",
+        "comp" => "compute",
+        "tesc" => "tess_control",
+        "tese" => "tess_evaluation",
+        "glsl" => "include",
+        _ => "unknown",
+    }
+}
+
 /// Shader file watcher for hot reload
 pub struct ShaderWatcher {
-    /// File system watcher
-    _watcher: RecommendedWatcher,
+    /// Asset watcher holding the shader directory subscription
+    watcher: AssetWatcher,
+    /// Subscriptions to the watch roots while hot reload is enabled
+    subscriptions: Vec<SubscriptionId>,
     /// Hot reload configuration
     config: HotReloadConfig,
-    /// Map of file paths to last modification times
-    file_times: Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
     /// Shader compiler reference
     #[allow(dead_code)]
     shader_compiler: Arc<Mutex<ShaderCompiler>>,
-    /// Callback for shader changes
-    #[allow(dead_code)]
-    change_callback: Option<ShaderChangeCallback>,
-    /// Whether the watcher is running
-    #[allow(dead_code)]
-    is_running: Arc<Mutex<bool>>,
-    /// Arc reference to callback for thread-safe access
-    _change_callback_arc: Arc<Mutex<Option<ShaderChangeCallback>>>,
+    /// Callback for shader changes, shared with the watcher thread
+    change_callback: Arc<Mutex<Option<ShaderChangeCallback>>>,
 }
 
 impl ShaderWatcher {
@@ -79,52 +132,22 @@ impl ShaderWatcher {
     pub fn new(config: HotReloadConfig, shader_compiler: Arc<Mutex<ShaderCompiler>>) -> Result<Self> {
         info!("Creating shader watcher with config: {:?}", config);
         
-        let file_times = Arc::new(Mutex::new(HashMap::new()));
-        let is_running = Arc::new(Mutex::new(false));
         let change_callback = Arc::new(Mutex::new(None::<ShaderChangeCallback>));
         
-        // Clone the Arcs for the watcher thread
-        let file_times_clone = Arc::clone(&file_times);
-        let config_clone = config.clone();
-        let is_running_clone = Arc::clone(&is_running);
-        let change_callback_clone = Arc::clone(&change_callback);
-        
-        // Create the file system watcher
-        let mut watcher = RecommendedWatcher::new(
-            move |res: std::result::Result<Event, notify::Error>| {
-                match res {
-                    Ok(event) => {
-                        let callback_guard = change_callback_clone.lock().unwrap();
-                        if let Err(e) = Self::handle_file_event(event, &config_clone, &file_times_clone, &is_running_clone, &*callback_guard) {
-                            error!("Error handling file event: {}", e);
-                        }
-                    }
-                    Err(e) => error!("File watcher error: {:?}", e),
-                }
-            },
-            notify::Config::default(),
-        ).map_err(|e| VulkanError::ShaderCompilation(format!("Failed to create file watcher: {}", e)))?;
+        let mut watcher = Self {
+            watcher: AssetWatcher::new(Duration::from_millis(config.debounce_ms))?,
+            subscriptions: Vec::new(),
+            config,
+            shader_compiler,
+            change_callback,
+        };
         
-        // Start watching the shader directory
-        if config.enabled {
-            watcher.watch(&config.shader_dir, RecursiveMode::Recursive)
-                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to watch shader directory: {}", e)))?;
-            
-            info!("Started watching shader directory: {:?}", config.shader_dir);
-            
-            // Initialize file times for existing shader files
-            Self::initialize_file_times(&config.shader_dir, &file_times, &config.watch_extensions)?;
+        // Start watching the shader directories
+        if watcher.config.enabled {
+            watcher.watch_roots()?;
         }
         
-        Ok(Self {
-            _watcher: watcher,
-            config,
-            file_times,
-            shader_compiler,
-            change_callback: None,
-            is_running,
-            _change_callback_arc: change_callback,
-        })
+        Ok(watcher)
     }
     
     /// Set the callback for shader changes
@@ -132,11 +155,7 @@ impl ShaderWatcher {
     /// # Arguments
     /// * `callback` - Callback function to call when shaders change
     pub fn set_change_callback(&mut self, callback: ShaderChangeCallback) {
-        // Update the Arc for thread-safe access first
-        if let Ok(mut callback_arc) = self._change_callback_arc.lock() {
-            *callback_arc = Some(callback);
-        }
-        // Don't store in self.change_callback since we moved it to the Arc
+        *self.change_callback.lock().unwrap() = Some(callback);
     }
     
     /// Enable or disable hot reload
@@ -152,16 +171,12 @@ impl ShaderWatcher {
         
         if enabled {
             info!("Enabling hot shader reload");
-            // Start watching
-            self._watcher.watch(&self.config.shader_dir, RecursiveMode::Recursive)
-                .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to watch shader directory: {}", e)))?;
-            
-            // Initialize file times
-            Self::initialize_file_times(&self.config.shader_dir, &self.file_times, &self.config.watch_extensions)?;
+            self.watch_roots()?;
         } else {
             info!("Disabling hot shader reload");
-            // Stop watching
-            let _ = self._watcher.unwatch(&self.config.shader_dir);
+            for subscription in self.subscriptions.drain(..) {
+                self.watcher.unsubscribe(subscription);
+            }
         }
         
         Ok(())
@@ -173,169 +188,57 @@ impl ShaderWatcher {
         self.config.enabled
     }
 
-    /// Handle file system events
-    fn handle_file_event(
-        event: Event,
-        config: &HotReloadConfig,
-        file_times: &Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
-        is_running: &Arc<Mutex<bool>>,
-        callback: &Option<ShaderChangeCallback>,
-    ) -> Result<()> {
-        if !config.enabled {
-            return Ok(());
-        }
-        
-        // Prevent concurrent processing
-        {
-            let mut running = is_running.lock().unwrap();
-            if *running {
-                debug!("File event processing already in progress, skipping");
-                return Ok(());
+    /// Subscribe to every watch root
+    ///
+    /// A root that cannot be watched is logged and skipped.
+    ///
+    /// # Errors
+    /// Returns an error if none of the roots can be watched
+    fn watch_roots(&mut self) -> Result<()> {
+        let roots = self.config.distinct_roots();
+        let mut last_error = None;
+        for root in &roots {
+            if !root.exists() {
+                warn!("Shader directory does not exist: {:?}", root);
             }
-            *running = true;
-        }
-        
-        let result = Self::process_file_event(event, config, file_times, callback);
-        
-        // Clear the running flag
-        {
-            let mut running = is_running.lock().unwrap();
-            *running = false;
-        }
-        
-        result
-    }
-    
-    /// Process a single file event
-    fn process_file_event(
-        event: Event,
-        config: &HotReloadConfig,
-        file_times: &Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
-        callback: &Option<ShaderChangeCallback>,
-    ) -> Result<()> {
-        debug!("File event: {:?}", event);
-        
-        for path in event.paths {
-            // Check if the file has a shader extension we care about
-            if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
-                if !config.watch_extensions.contains(&extension.to_string()) {
-                    continue;
-                }
-                
-                // Get the current modification time
-                let metadata = std::fs::metadata(&path);
-                if let Ok(metadata) = metadata {
-                    let current_time = metadata.modified()
-                        .unwrap_or_else(|_| SystemTime::now());
-                    
-                    // Check if we should process this file
-                    let should_process = {
-                        let times = file_times.lock().unwrap();
-                        if let Some(last_time) = times.get(&path) {
-                            // Only process if the file is newer than our last record
-                            current_time.duration_since(*last_time).unwrap_or(Duration::ZERO) >= Duration::from_millis(config.debounce_ms)
-                        } else {
-                            // New file, always process
-                            true
-                        }
-                    };
-                    
-                    if should_process {
-                        if config::hot_reload::LOG_RELOAD_EVENTS {
-                            info!("Shader file changed: {:?}", path);
-                        }
-                        
-                        // Update the last modification time
-                        {
-                            let mut times = file_times.lock().unwrap();
-                            times.insert(path.clone(), current_time);
-                        }
-                        
-                        // Trigger shader reload
-                        if let Some(shader_path) = path.to_str() {
-                            // Determine shader kind from extension
-                            let shader_kind = match extension {
-                                "vert" => "vertex",
-                                "frag" => "fragment",
-                                "geom" => "geometry",
-                                "comp" => "compute",
-                                "tesc" => "tess_control",
-                                "tese" => "tess_evaluation",
-                                _ => "unknown",
-                            };
-                            
-                            // Check if this shader type should be reloaded
-                            let should_reload = match shader_kind {
-                                "vertex" => config::hot_reload::RELOAD_VERTEX_SHADERS,
-                                "fragment" => config::hot_reload::RELOAD_FRAGMENT_SHADERS,
-                                "geometry" => config::hot_reload::RELOAD_GEOMETRY_SHADERS,
-                                "compute" => config::hot_reload::RELOAD_COMPUTE_SHADERS,
-                                "tess_control" | "tess_evaluation" => config::hot_reload::RELOAD_TESSELLATION_SHADERS,
-                                _ => false,
-                            };
-                            
-                            if should_reload {
-                                if config::hot_reload::LOG_RELOAD_EVENTS {
-                                    info!("Triggering hot reload for {} shader: {}", shader_kind, shader_path);
-                                }
-                                debug!("Hot reload triggered for: {} ({})", shader_path, shader_kind);
-                                
-                                // Actually trigger the callback to handle the shader change
-                                if let Some(ref callback) = callback {
-                                    if let Err(e) = callback(shader_path, shader_kind) {
-                                        error!("Failed to handle shader change: {}", e);
-                                    }
-                                }
-                            } else {
-                                debug!("Skipping reload for disabled shader type: {} ({})", shader_path, shader_kind);
-                            }
-                        }
-                    }
+            let callback = Arc::clone(&self.change_callback);
+            match self.watcher.subscribe_with_extensions(
+                AssetKind::Shader,
+                root,
+                self.config.watch_extensions.clone(),
+                move |change| Self::handle_change(&change, &callback.lock().unwrap()),
+            ) {
+                Ok(subscription) => self.subscriptions.push(subscription),
+                Err(e) => {
+                    warn!("Failed to watch shader directory {:?}: {}", root, e);
+                    last_error = Some(e);
                 }
             }
         }
-        
+        if let Some(e) = last_error.filter(|_| self.subscriptions.is_empty()) {
+            return Err(VulkanError::ShaderCompilation(format!("Failed to watch shader directories: {}", e)).into());
+        }
+        info!("Initialized file times for {} shader files in {} directories", self.watcher.file_count(), self.subscriptions.len());
         Ok(())
     }
-    
-    /// Initialize file modification times for existing shader files
-    fn initialize_file_times(
-        shader_dir: &Path,
-        file_times: &Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
-        watch_extensions: &[String],
-    ) -> Result<()> {
-        debug!("Initializing file times for shader directory: {:?}", shader_dir);
+
+    /// Pass a debounced shader change to the callback
+    fn handle_change(change: &AssetChange, callback: &Option<ShaderChangeCallback>) {
+        let Some(shader_path) = change.path.to_str() else {
+            return;
+        };
+        let shader_kind = shader_kind(change.path.extension().and_then(|ext| ext.to_str()).unwrap_or_default());
         
-        if !shader_dir.exists() {
-            warn!("Shader directory does not exist: {:?}", shader_dir);
-            return Ok(());
+        if config::hot_reload::LOG_RELOAD_EVENTS {
+            info!("Shader file changed: {:?}", change.path);
         }
+        debug!("Hot reload triggered for: {} ({})", shader_path, shader_kind);
         
-        let mut times = file_times.lock().unwrap();
-        
-        for entry in std::fs::read_dir(shader_dir)
-            .map_err(|e| VulkanError::ShaderCompilation(format!("Failed to read shader directory: {}", e)))? 
-        {
-            let entry = entry.map_err(|e| VulkanError::ShaderCompilation(format!("Failed to read directory entry: {}", e)))?;
-            let path = entry.path();
-            
-            // Check if it's a file with a shader extension
-            if path.is_file() {
-                if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
-                    if watch_extensions.contains(&extension.to_string()) {
-                        if let Ok(metadata) = std::fs::metadata(&path) {
-                            if let Ok(modified_time) = metadata.modified() {
-                                times.insert(path.clone(), modified_time);
-                                debug!("Initialized file time for: {:?}", path);
-                            }
-                        }
-                    }
-                }
+        if let Some(ref callback) = callback {
+            if let Err(e) = callback(shader_path, shader_kind) {
+                error!("Failed to handle shader change: {}", e);
             }
         }
-        
-        info!("Initialized file times for {} shader files", times.len());
-        Ok(())
     }
 
     /// Manually trigger a reload for a specific shader file
@@ -346,7 +249,8 @@ impl ShaderWatcher {
     pub fn reload_shader(&self, shader_path: &str) -> Result<()> {
         info!("Manual reload requested for shader: {}", shader_path);
         
-        if let Some(callback) = &self.change_callback {
+        let callback_guard = self.change_callback.lock().unwrap();
+        if let Some(callback) = callback_guard.as_ref() {
             // Determine shader kind from file extension
             let path = Path::new(shader_path);
             let extension = path.extension()
@@ -355,32 +259,8 @@ impl ShaderWatcher {
                     format!("No file extension found for shader: {}", shader_path)
                 ))?;
             
-            let shader_kind = match extension {
-                "vert" => "vertex",
-                "frag" => "fragment",
-                "geom" => "geometry",
-                "comp" => "compute",
-                "tesc" => "tess_control",
-                "tese" => "tess_evaluation",
-                _ => "unknown",
-            };
-            
-            // Check if this shader type should be reloaded
-            let should_reload = match shader_kind {
-                "vertex" => config::hot_reload::RELOAD_VERTEX_SHADERS,
-                "fragment" => config::hot_reload::RELOAD_FRAGMENT_SHADERS,
-                "geometry" => config::hot_reload::RELOAD_GEOMETRY_SHADERS,
-                "compute" => config::hot_reload::RELOAD_COMPUTE_SHADERS,
-                "tess_control" | "tess_evaluation" => config::hot_reload::RELOAD_TESSELLATION_SHADERS,
-                _ => false,
-            };
-            
-            if should_reload {
-                callback(shader_path, shader_kind)?;
-                info!("Manual reload completed for: {}", shader_path);
-            } else {
-                warn!("Manual reload skipped for disabled shader type: {} ({})", shader_path, shader_kind);
-            }
+            callback(shader_path, shader_kind(extension))?;
+            info!("Manual reload requested for: {}", shader_path);
         } else {
             warn!("No change callback set for shader reload");
         }
@@ -391,9 +271,7 @@ impl ShaderWatcher {
     /// Get statistics about the watcher
     #[allow(dead_code)]
     pub fn get_stats(&self) -> (usize, bool) {
-        let file_count = self.file_times.lock().unwrap().len();
-        let is_enabled = self.config.enabled;
-        (file_count, is_enabled)
+        (self.watcher.file_count(), self.config.enabled)
     }
 }
 
@@ -426,6 +304,10 @@ pub struct HotReloadManager {
     pending_reloads: Arc<Mutex<VecDeque<ShaderReloadRequest>>>,
     /// Flag to track if reloads occurred in the current frame
     reloads_occurred: Arc<Mutex<bool>>,
+    /// Error of the last failed reload, cleared by the next successful one
+    last_error: Mutex<Option<String>>,
+    /// Reload filters and per-file reload state, shared with the watcher thread
+    stats: Arc<Mutex<ShaderReloadStats>>,
 }
 
 impl HotReloadManager {
@@ -445,6 +327,8 @@ impl HotReloadManager {
             pipeline: None,
             pending_reloads: Arc::new(Mutex::new(VecDeque::new())),
             reloads_occurred: Arc::new(Mutex::new(false)),
+            last_error: Mutex::new(None),
+            stats: Arc::new(Mutex::new(ShaderReloadStats::new())),
         }
     }
     
@@ -462,22 +346,10 @@ impl HotReloadManager {
         info!("Initializing hot reload manager");
 
         self.pipeline = Some(pipeline);
+        self.stats.lock().unwrap().scan(&self.config.distinct_roots(), &self.config.watch_extensions);
 
         if self.config.enabled {
-            let shader_compiler = Arc::clone(&self.shader_compiler);
-            let _pipeline_ref = self.pipeline.clone().unwrap();
-
-            // Create the watcher with a callback
-            let mut watcher = ShaderWatcher::new(self.config.clone(), shader_compiler)?;
-
-            // Set up the change callback to queue reload requests instead of immediate processing
-            let pending_reloads_clone = Arc::clone(&self.pending_reloads);
-
-            watcher.set_change_callback(Box::new(move |shader_path: &str, shader_kind: &str| {
-                Self::queue_shader_reload(shader_path, shader_kind, &pending_reloads_clone)
-            }));
-
-            self.watcher = Some(watcher);
+            self.start_watcher()?;
             info!("Hot reload manager initialized successfully");
         } else {
             info!("Hot reload disabled in configuration");
@@ -486,6 +358,54 @@ impl HotReloadManager {
         Ok(())
     }
 
+    /// Create the shader watcher for the current configuration
+    fn start_watcher(&mut self) -> Result<()> {
+        let shader_compiler = Arc::clone(&self.shader_compiler);
+
+        // Create the watcher with a callback
+        let mut watcher = ShaderWatcher::new(self.config.clone(), shader_compiler)?;
+
+        // Set up the change callback to queue reload requests instead of immediate processing
+        let pending_reloads_clone = Arc::clone(&self.pending_reloads);
+        let stats = Arc::clone(&self.stats);
+
+        watcher.set_change_callback(Box::new(move |shader_path: &str, shader_kind: &str| {
+            if !stats.lock().unwrap().should_reload(Path::new(shader_path)) {
+                return Ok(());
+            }
+            Self::queue_shader_reload(shader_path, shader_kind, &pending_reloads_clone)
+        }));
+
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Apply a new configuration
+    ///
+    /// The shader watcher is recreated, so a new shader directory or
+    /// debounce time takes effect right away.
+    ///
+    /// # Arguments
+    /// * `config` - The new hot reload configuration
+    ///
+    /// # Errors
+    /// Returns an error if the shader directory cannot be watched
+    pub fn reconfigure(&mut self, config: HotReloadConfig) -> Result<()> {
+        info!("Reconfiguring hot reload: {:?}", config);
+        self.watcher = None;
+        self.config = config;
+        self.stats.lock().unwrap().scan(&self.config.distinct_roots(), &self.config.watch_extensions);
+        if self.config.enabled && self.pipeline.is_some() {
+            self.start_watcher()?;
+        }
+        Ok(())
+    }
+
+    /// Current configuration
+    pub fn config(&self) -> &HotReloadConfig {
+        &self.config
+    }
+
     /// Queue a shader reload request for later processing
     fn queue_shader_reload(
         shader_path: &str,
@@ -544,12 +464,24 @@ impl HotReloadManager {
                 // This will involve proper GPU synchronization
                 {
                     let mut pipeline_guard = pipeline.lock().unwrap();
-                    if let Err(e) = pipeline_guard.recompile_shader(&request.shader_path) {
-                        error!("FAILED to recreate pipeline for {}: {}", request.shader_path, e);
-                        // Continue processing other reloads even if one fails
-                    } else {
-                        info!("SUCCESS: Pipeline recreated for: {}", request.shader_path);
-                        pipeline_recreated = true;
+                    let started = Instant::now();
+                    let result = pipeline_guard.recompile_shader(&request.shader_path);
+                    self.stats.lock().unwrap().record(
+                        Path::new(&request.shader_path),
+                        started.elapsed(),
+                        result.as_ref().map(|&recreated| recreated).map_err(|e| e.to_string()),
+                    );
+                    match result {
+                        Ok(recreated) => {
+                            info!("SUCCESS: Reload processed for: {} (pipeline recreated: {})", request.shader_path, recreated);
+                            *self.last_error.lock().unwrap() = None;
+                            pipeline_recreated |= recreated;
+                        }
+                        Err(e) => {
+                            error!("FAILED to recreate pipeline for {}: {}", request.shader_path, e);
+                            *self.last_error.lock().unwrap() = Some(format!("{}: {}", request.shader_path, e));
+                            // Continue processing other reloads even if one fails
+                        }
                     }
                 }
             }
@@ -582,6 +514,38 @@ impl HotReloadManager {
         occurred
     }
 
+    /// Get the error of the last failed reload, if the shaders have not been fixed since
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    /// Forget the last reload error after the shaders were rebuilt by other means
+    pub fn clear_last_error(&self) {
+        *self.last_error.lock().unwrap() = None;
+    }
+
+    /// Snapshot of the per-file reload state, sorted by path
+    pub fn file_stats(&self) -> Vec<ShaderFileStats> {
+        self.stats.lock().unwrap().files()
+    }
+
+    /// Shader kinds and whether they are reloaded
+    pub fn kind_filters(&self) -> Vec<(&'static str, bool)> {
+        self.stats.lock().unwrap().kinds().to_vec()
+    }
+
+    /// Enable or disable reloading of one shader file
+    pub fn set_file_enabled(&self, path: &Path, enabled: bool) {
+        info!("Hot reload of {:?} {}", path, if enabled { "enabled" } else { "disabled" });
+        self.stats.lock().unwrap().set_file_enabled(path, enabled);
+    }
+
+    /// Enable or disable reloading of a shader kind
+    pub fn set_kind_enabled(&self, kind: &str, enabled: bool) {
+        info!("Hot reload of {} shaders {}", kind, if enabled { "enabled" } else { "disabled" });
+        self.stats.lock().unwrap().set_kind_enabled(kind, enabled);
+    }
+
     /// Get the number of pending reload requests
     #[allow(dead_code)]
     pub fn pending_reload_count(&self) -> usize {
@@ -594,6 +558,9 @@ impl HotReloadManager {
         
         if let Some(ref mut watcher) = self.watcher {
             watcher.set_enabled(enabled)?;
+        } else if enabled && self.pipeline.is_some() {
+            // Started with hot reload disabled, so there is no watcher yet
+            self.start_watcher()?;
         }
         
         info!("Hot reload {}", if enabled { "enabled" } else { "disabled" });
@@ -618,15 +585,7 @@ impl HotReloadManager {
     /// Manually trigger a shader reload (queues it for safe processing)
     pub fn reload_shader(&self, shader_path: &str) -> Result<()> {
         if let Some(extension) = Path::new(shader_path).extension().and_then(|ext| ext.to_str()) {
-            let shader_kind = match extension {
-                "vert" => "vertex",
-                "frag" => "fragment",
-                "geom" => "geometry",
-                "comp" => "compute",
-                "tesc" => "tess_control",
-                "tese" => "tess_evaluation",
-                _ => "unknown",
-            };
+            let shader_kind = shader_kind(extension);
 
             // Queue the reload request instead of processing immediately
             Self::queue_shader_reload(shader_path, shader_kind, &self.pending_reloads)
@@ -652,3 +611,33 @@ impl Drop for HotReloadManager {
         debug!("Shader watcher will be cleaned up automatically");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_roots_drops_nested_and_duplicate_roots() {
+        let config = HotReloadConfig {
+            watch_roots: ["missing/shaders", "missing/shaders/lib", "missing/include", "missing/shaders"]
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            ..HotReloadConfig::default()
+        };
+        assert_eq!(config.distinct_roots(), [PathBuf::from("missing/shaders"), PathBuf::from("missing/include")]);
+    }
+
+    #[test]
+    fn test_manual_reloads_queue_until_processed() {
+        let compiler = Arc::new(Mutex::new(ShaderCompiler::new().unwrap()));
+        let manager = HotReloadManager::new(HotReloadConfig::default(), compiler);
+        manager.reload_shader("shaders/sdf.frag").unwrap();
+        manager.reload_shader("shaders/README").unwrap();
+        assert_eq!(manager.pending_reload_count(), 1);
+
+        // Without a pipeline the queue is drained and nothing is rebuilt
+        assert!(!manager.process_pending_reloads().unwrap());
+        assert_eq!(manager.pending_reload_count(), 0);
+    }
+}
@@ -0,0 +1,384 @@
+//! Directional shadow map of the meshes
+//!
+//! Before the scene pass, `MeshPass` draws every mesh instance depth-only
+//! from the sun into a square depth image, with an orthographic projection
+//! fitted around a bounding sphere of the instances. The mesh fragment
+//! shader then compares its position against the map with a comparison
+//! sampler and filters the result over a small kernel (PCF). There is one
+//! map for the whole scene rather than cascades: the meshes' bounds are
+//! known, so the map covers exactly them.
+//!
+//! The pass carries its own dependencies like `PassLayout` does: the depth
+//! clear waits for the previous frame's shader reads and the stored depth is
+//! made visible to the fragment shaders of the scene pass that follows. The
+//! renderer records it with the other inputs of the scene pass, ahead of
+//! every scene pass.
+
+use ash::vk;
+use ash::{Device, Instance};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3};
+use crate::config;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::depth::{self, DepthBuffer, DepthUsage};
+use crate::vulkan::rendering::RenderingMode;
+use log::{debug, info, warn};
+
+/// Depth image the meshes cast shadows into, with the pass drawing it and
+/// the descriptor set sampling it
+pub struct ShadowMap {
+    depth: DepthBuffer,
+    mode: RenderingMode,
+    /// Depth-only render pass object, null with dynamic rendering
+    render_pass: vk::RenderPass,
+    /// Framebuffer of the depth image, null with dynamic rendering
+    framebuffer: vk::Framebuffer,
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    device: Device,
+}
+
+impl ShadowMap {
+    /// Create the shadow map of `config::shadows::MAP_SIZE` pixels
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - Physical device for memory type and format lookup
+    /// * `device` - The logical device
+    /// * `mode` - Rendering mode chosen for the device
+    ///
+    /// # Errors
+    /// Returns an error if any Vulkan object creation fails
+    pub fn new(instance: &Instance, physical_device: vk::PhysicalDevice, device: &Device, mode: RenderingMode) -> Result<Self> {
+        info!("Creating {0}x{0} shadow map", config::shadows::MAP_SIZE);
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let format_properties = unsafe {
+            instance.get_physical_device_format_properties(physical_device, config::rendering::DEPTH_FORMAT)
+        };
+        let filter = compare_filter(format_properties.optimal_tiling_features);
+        if filter != vk::Filter::LINEAR {
+            warn!("Depth format {:?} cannot be filtered linearly; shadow edges use the PCF kernel alone", config::rendering::DEPTH_FORMAT);
+        }
+
+        let depth = DepthBuffer::new(device, &memory_properties, Self::extent(), DepthUsage::Sampled)?;
+        let (render_pass, framebuffer) = match mode {
+            RenderingMode::RenderPass => {
+                let render_pass = Self::create_render_pass(device)?;
+                let framebuffer = unsafe {
+                    device.create_framebuffer(&vk::FramebufferCreateInfo::default()
+                        .render_pass(render_pass)
+                        .attachments(&[depth.view])
+                        .width(config::shadows::MAP_SIZE)
+                        .height(config::shadows::MAP_SIZE)
+                        .layers(1), None)
+                        .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create shadow map framebuffer: {:?}", e)))?
+                };
+                (render_pass, framebuffer)
+            }
+            RenderingMode::Dynamic => (vk::RenderPass::null(), vk::Framebuffer::null()),
+        };
+
+        // Depth comparison, bilinear where supported; outside the map is lit
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(filter)
+            .min_filter(filter)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
+            .compare_enable(true)
+            .compare_op(vk::CompareOp::LESS_OR_EQUAL)
+            .max_lod(0.0);
+        let sampler = unsafe {
+            device.create_sampler(&sampler_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create shadow map sampler: {:?}", e)))?
+        };
+
+        let bindings = [vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)];
+        let descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create shadow map descriptor set layout: {:?}", e)))?
+        };
+
+        let pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)];
+        let descriptor_pool = unsafe {
+            device.create_descriptor_pool(&vk::DescriptorPoolCreateInfo::default().pool_sizes(&pool_sizes).max_sets(1), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create shadow map descriptor pool: {:?}", e)))?
+        };
+
+        let layouts = [descriptor_set_layout];
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(&vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&layouts))
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to allocate shadow map descriptor set: {:?}", e)))?[0]
+        };
+
+        // The map never changes size, so the set is written once
+        let image_info = [vk::DescriptorImageInfo::default()
+            .sampler(sampler)
+            .image_view(depth.view)
+            .image_layout(DepthUsage::Sampled.final_layout())];
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info);
+        unsafe {
+            device.update_descriptor_sets(&[write], &[]);
+        }
+
+        Ok(Self {
+            depth,
+            mode,
+            render_pass,
+            framebuffer,
+            sampler,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            device: device.clone(),
+        })
+    }
+
+    /// Create the render pass with the depth attachment only
+    fn create_render_pass(device: &Device) -> Result<vk::RenderPass> {
+        let attachments = [depth::attachment_description(DepthUsage::Sampled)];
+        let depth_attachment_ref = depth::attachment_reference(0);
+        let subpasses = [vk::SubpassDescription::default()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .depth_stencil_attachment(&depth_attachment_ref)];
+        let dependencies = [depth::external_dependency(), depth::sampled_dependency()];
+        unsafe {
+            Ok(device.create_render_pass(&vk::RenderPassCreateInfo::default()
+                .attachments(&attachments)
+                .subpasses(&subpasses)
+                .dependencies(&dependencies), None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create shadow map render pass: {:?}", e)))?)
+        }
+    }
+
+    /// Size of the map
+    pub fn extent() -> vk::Extent2D {
+        vk::Extent2D { width: config::shadows::MAP_SIZE, height: config::shadows::MAP_SIZE }
+    }
+
+    /// Layout of the set the mesh pipeline samples the map through
+    pub fn descriptor_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.descriptor_set_layout
+    }
+
+    /// Set sampling the map
+    pub fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.descriptor_set
+    }
+
+    /// Depth format chained into the shadow pipeline for dynamic rendering
+    pub fn rendering_info() -> vk::PipelineRenderingCreateInfo<'static> {
+        vk::PipelineRenderingCreateInfo::default()
+            .depth_attachment_format(config::rendering::DEPTH_FORMAT)
+    }
+
+    /// Make the shadow pipeline compatible with the pass, like `PassLayout::attach`
+    pub fn attach<'a>(
+        &self,
+        pipeline_info: vk::GraphicsPipelineCreateInfo<'a>,
+        rendering: &'a mut vk::PipelineRenderingCreateInfo<'_>,
+    ) -> vk::GraphicsPipelineCreateInfo<'a> {
+        match self.mode {
+            RenderingMode::RenderPass => pipeline_info.render_pass(self.render_pass).subpass(0),
+            RenderingMode::Dynamic => pipeline_info.push_next(rendering),
+        }
+    }
+
+    /// Begin the pass, clearing the map
+    ///
+    /// Viewport and scissor are set to the whole map.
+    pub fn begin(&self, command_buffer: vk::CommandBuffer) {
+        let render_area = vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: Self::extent() };
+        let size = config::shadows::MAP_SIZE as f32;
+        let viewport = vk::Viewport { x: 0.0, y: 0.0, width: size, height: size, min_depth: 0.0, max_depth: 1.0 };
+        unsafe {
+            match self.mode {
+                RenderingMode::RenderPass => {
+                    let clear_values = [depth::clear_value()];
+                    let begin_info = vk::RenderPassBeginInfo::default()
+                        .render_pass(self.render_pass)
+                        .framebuffer(self.framebuffer)
+                        .render_area(render_area)
+                        .clear_values(&clear_values);
+                    self.device.cmd_begin_render_pass(command_buffer, &begin_info, vk::SubpassContents::INLINE);
+                }
+                RenderingMode::Dynamic => {
+                    // The previous frame's mesh shaders may still sample the map
+                    let depth_stages = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+                    self.device.cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::FRAGMENT_SHADER | depth_stages,
+                        depth_stages,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &[],
+                        &[depth::layout_barrier(self.depth.image)],
+                    );
+                    let depth_attachment = depth::rendering_attachment(self.depth.view, DepthUsage::Sampled);
+                    let rendering_info = vk::RenderingInfo::default()
+                        .render_area(render_area)
+                        .layer_count(1)
+                        .depth_attachment(&depth_attachment);
+                    self.device.cmd_begin_rendering(command_buffer, &rendering_info);
+                }
+            }
+            self.device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+            self.device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+        }
+    }
+
+    /// End the pass, leaving the map read-only for the scene pass's fragment shaders
+    pub fn end(&self, command_buffer: vk::CommandBuffer) {
+        unsafe {
+            match self.mode {
+                RenderingMode::RenderPass => self.device.cmd_end_render_pass(command_buffer),
+                RenderingMode::Dynamic => {
+                    self.device.cmd_end_rendering(command_buffer);
+                    self.device.cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                        vk::PipelineStageFlags::FRAGMENT_SHADER,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &[],
+                        &[depth::sampled_barrier(self.depth.image)],
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ShadowMap {
+    fn drop(&mut self) {
+        debug!("Destroying shadow map");
+        unsafe {
+            self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            self.device.destroy_sampler(self.sampler, None);
+            if self.framebuffer != vk::Framebuffer::null() {
+                self.device.destroy_framebuffer(self.framebuffer, None);
+            }
+            if self.render_pass != vk::RenderPass::null() {
+                self.device.destroy_render_pass(self.render_pass, None);
+            }
+        }
+        self.depth.destroy(&self.device);
+    }
+}
+
+/// Filter of the comparison sampler for a depth format with these features
+///
+/// Linear filtering of depth formats is optional in Vulkan, and sampling
+/// with a filter the format lacks is undefined.
+fn compare_filter(features: vk::FormatFeatureFlags) -> vk::Filter {
+    if features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+        vk::Filter::LINEAR
+    } else {
+        vk::Filter::NEAREST
+    }
+}
+
+/// Smallest sphere around the centers' bounding box that holds every sphere
+///
+/// # Returns
+/// None without spheres
+pub fn enclosing_sphere(spheres: impl IntoIterator<Item = (Vector3<f32>, f32)>) -> Option<(Vector3<f32>, f32)> {
+    let spheres: Vec<_> = spheres.into_iter().collect();
+    let (first, _) = *spheres.first()?;
+    let (min, max) = spheres.iter().fold((first, first), |(min, max), (center, _)| {
+        (
+            Vector3::new(min.x.min(center.x), min.y.min(center.y), min.z.min(center.z)),
+            Vector3::new(max.x.max(center.x), max.y.max(center.y), max.z.max(center.z)),
+        )
+    });
+    let center = (min + max) * 0.5;
+    let radius = spheres.iter().fold(0.0f32, |radius, (c, r)| radius.max((c - center).magnitude() + r));
+    Some((center, radius))
+}
+
+/// View projection of the shadow map for a sun shining on a bounding sphere
+///
+/// An orthographic projection looking against `towards_sun` that maps the
+/// sphere into the map, with Vulkan's 0 to 1 depth range (0 nearest the sun).
+///
+/// # Arguments
+/// * `towards_sun` - Direction towards the sun, straight up when zero
+/// * `bounds` - Center and radius of the sphere that casts and receives shadows
+pub fn light_view_projection(towards_sun: Vector3<f32>, (center, radius): (Vector3<f32>, f32)) -> Matrix4<f32> {
+    let direction = if towards_sun.magnitude2() > 0.0 { towards_sun.normalize() } else { Vector3::unit_y() };
+    let radius = radius.max(config::shadows::MIN_RADIUS);
+    let up = if direction.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+    let eye = center + direction * radius * 2.0;
+    let view = Matrix4::look_at_rh(Point3::from_vec(eye), Point3::from_vec(center), up);
+    let projection = cgmath::ortho(-radius, radius, -radius, radius, radius, radius * 3.0);
+    // OpenGL's -1 to 1 depth to Vulkan's 0 to 1
+    let depth_range = Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.5, 0.0,
+        0.0, 0.0, 0.5, 1.0,
+    );
+    depth_range * projection * view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+
+    #[test]
+    fn test_compare_filter_needs_linear_support() {
+        let sampled = vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT;
+        assert_eq!(compare_filter(sampled), vk::Filter::NEAREST);
+        assert_eq!(compare_filter(sampled | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR), vk::Filter::LINEAR);
+    }
+
+    #[test]
+    fn test_enclosing_sphere_holds_every_sphere() {
+        assert_eq!(enclosing_sphere([]), None);
+        let spheres = [(Vector3::new(-2.0, 0.0, 0.0), 1.0), (Vector3::new(3.0, 1.0, 0.0), 0.5), (Vector3::new(0.0, 0.0, 0.0), 4.0)];
+        let (center, radius) = enclosing_sphere(spheres).unwrap();
+        assert_eq!(center, Vector3::new(0.5, 0.5, 0.0));
+        for (c, r) in spheres {
+            assert!((c - center).magnitude() + r <= radius + 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_light_view_projection_fits_the_bounds() {
+        let bounds = (Vector3::new(1.0, 2.0, -3.0), 2.0);
+        let towards_sun = Vector3::new(0.3, 1.0, 0.2);
+        let matrix = light_view_projection(towards_sun, bounds);
+        let project = |point: Vector3<f32>| {
+            let clip = matrix * Vector4::new(point.x, point.y, point.z, 1.0);
+            clip.truncate() / clip.w
+        };
+
+        let sun = towards_sun.normalize();
+        let nearest = project(bounds.0 + sun * bounds.1);
+        let farthest = project(bounds.0 - sun * bounds.1);
+        let center = project(bounds.0);
+        assert!(center.x.abs() < 1e-5 && center.y.abs() < 1e-5);
+        assert!(nearest.z.abs() < 1e-5 && (farthest.z - 1.0).abs() < 1e-5);
+
+        // Straight down the sun stays well defined
+        let overhead = light_view_projection(Vector3::unit_y(), bounds) * Vector4::new(1.0, 2.0, -3.0, 1.0);
+        assert!(overhead.x.abs() < 1e-5 && (overhead.z - 0.5).abs() < 1e-5);
+    }
+}
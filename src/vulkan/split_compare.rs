@@ -0,0 +1,99 @@
+//! Split-screen shader comparison
+//!
+//! Every viewport can be split by a vertical divider: the left side is drawn
+//! with the active SDF pipeline, the right side with a comparison pipeline
+//! from `VulkanPipeline` (the pipeline from before the last hot reload,
+//! another feature variant or another quality tier). Both sides use the same
+//! camera, so the divider can be dragged across the image to compare a
+//! shader edit against the previous version in real time.
+
+use ash::vk;
+use crate::vulkan::quality::RaymarchQuality;
+use crate::vulkan::shader_variant::ShaderVariantKey;
+
+/// Pipeline drawn on the right side of the divider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompareSource {
+    /// The pipeline that was active before the last successful hot reload
+    LastGood,
+    /// Another shader variant at the active quality tier
+    Variant(ShaderVariantKey),
+    /// The active shader variant at another quality tier
+    Quality(RaymarchQuality),
+}
+
+impl CompareSource {
+    /// Short description for the HUD
+    pub fn name(&self) -> String {
+        match self {
+            CompareSource::LastGood => "Last good".to_string(),
+            CompareSource::Variant(variant) => format!("Variant: {}", variant.name()),
+            CompareSource::Quality(quality) => format!("Quality: {}", quality.name()),
+        }
+    }
+}
+
+/// Split-screen comparison settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitCompare {
+    /// Whether viewports are split
+    pub enabled: bool,
+    /// Pipeline drawn right of the divider
+    pub source: CompareSource,
+    /// Divider position as a fraction of each viewport's width
+    pub divider: f32,
+}
+
+impl Default for SplitCompare {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: CompareSource::LastGood,
+            divider: 0.5,
+        }
+    }
+}
+
+impl SplitCompare {
+    /// Split a viewport rectangle at the divider
+    ///
+    /// # Arguments
+    /// * `rect` - Rectangle of the viewport
+    ///
+    /// # Returns
+    /// The (left, right) scissor rectangles; either may be empty when the
+    /// divider sits at an edge
+    pub fn split(&self, rect: vk::Rect2D) -> (vk::Rect2D, vk::Rect2D) {
+        let left_width = (rect.extent.width as f32 * self.divider.clamp(0.0, 1.0)).round() as u32;
+        let left = vk::Rect2D {
+            offset: rect.offset,
+            extent: vk::Extent2D { width: left_width, height: rect.extent.height },
+        };
+        let right = vk::Rect2D {
+            offset: vk::Offset2D { x: rect.offset.x + left_width as i32, y: rect.offset.y },
+            extent: vk::Extent2D { width: rect.extent.width - left_width, height: rect.extent.height },
+        };
+        (left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_covers_rect() {
+        let compare = SplitCompare { divider: 0.25, ..SplitCompare::default() };
+        let rect = vk::Rect2D {
+            offset: vk::Offset2D { x: 400, y: 300 },
+            extent: vk::Extent2D { width: 401, height: 301 },
+        };
+        let (left, right) = compare.split(rect);
+        assert_eq!(left.offset, rect.offset);
+        assert_eq!(left.extent.width + right.extent.width, 401);
+        assert_eq!(right.offset.x, 400 + left.extent.width as i32);
+
+        let edge = SplitCompare { divider: 1.5, ..SplitCompare::default() };
+        assert_eq!(edge.split(rect).1.extent.width, 0);
+    }
+}
@@ -0,0 +1,163 @@
+//! High resolution still export
+//!
+//! Renders one frame of the scene at any resolution, e.g. four times the
+//! window size, without resizing the swapchain or the window. Stills larger
+//! than the render target are rendered in tiles (`Renderer::export_still`),
+//! which `copy_tile` assembles into one image on the host. PNG stills are
+//! tonemapped like the window; EXR and Radiance HDR stills keep the linear
+//! values of the HDR target for grading in other tools.
+
+use std::fs;
+use std::path::PathBuf;
+use crate::config;
+use crate::error::{Result, ResultExt, Subsystem};
+use crate::image_io::{self, HdrFormat};
+use crate::project;
+use crate::vulkan::frame_export::FrameExporter;
+use crate::vulkan::tonemap::TonemapSettings;
+use crate::vulkan::viewport::Tile;
+use log::info;
+
+/// File format of an exported still
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StillFormat {
+    /// 8-bit sRGB PNG, tonemapped like the window
+    Png,
+    /// Linear scene colors before tonemapping
+    Hdr(HdrFormat),
+}
+
+impl StillFormat {
+    /// All formats in display order
+    pub const ALL: [StillFormat; 3] = [StillFormat::Png, StillFormat::Hdr(HdrFormat::Exr), StillFormat::Hdr(HdrFormat::Radiance)];
+
+    /// Name shown in the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            StillFormat::Png => "PNG",
+            StillFormat::Hdr(format) => format.name(),
+        }
+    }
+
+    /// File extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            StillFormat::Png => "png",
+            StillFormat::Hdr(format) => format.extension(),
+        }
+    }
+}
+
+/// Resolution, format and output file of a still
+#[derive(Debug, Clone, PartialEq)]
+pub struct StillExportSettings {
+    /// Width of the still
+    pub width: u32,
+    /// Height of the still
+    pub height: u32,
+    /// Output format
+    pub format: StillFormat,
+    /// Output file; the format's extension is used if it has none
+    pub path: PathBuf,
+}
+
+impl Default for StillExportSettings {
+    fn default() -> Self {
+        Self {
+            width: config::export::DEFAULT_WIDTH * config::still_export::DEFAULT_SIZE_MULTIPLE,
+            height: config::export::DEFAULT_HEIGHT * config::still_export::DEFAULT_SIZE_MULTIPLE,
+            format: StillFormat::Png,
+            path: project::current().output_dir().join(config::still_export::FILE_STEM),
+        }
+    }
+}
+
+impl StillExportSettings {
+    /// Output file with the format's extension added if missing
+    pub fn output_path(&self) -> PathBuf {
+        if self.path.extension().is_some() {
+            self.path.clone()
+        } else {
+            self.path.with_extension(self.format.extension())
+        }
+    }
+
+    /// Write a rendered still
+    ///
+    /// # Arguments
+    /// * `pixels` - The still from `Renderer::export_still`, FP16 RGBA
+    /// * `tonemap` - Exposure and operator applied to PNG stills
+    ///
+    /// # Returns
+    /// The file that was written
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created or the file cannot be written
+    pub fn save(&self, pixels: &[u16], tonemap: &TonemapSettings) -> Result<PathBuf> {
+        let path = self.output_path();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).context(Subsystem::Io, format!("create directory {}", dir.display()))?;
+        }
+        match self.format {
+            StillFormat::Png => {
+                let rgba = FrameExporter::tonemap_pixels(pixels, tonemap);
+                image_io::write_png(&path, self.width, self.height, &rgba)?;
+            }
+            StillFormat::Hdr(format) => format.write(&path, self.width, self.height, pixels)?,
+        }
+        info!("Saved {}x{} still to {}", self.width, self.height, path.display());
+        Ok(path)
+    }
+}
+
+/// Copy a rendered tile into the still
+///
+/// # Arguments
+/// * `image` - The still, four channels per pixel
+/// * `image_width` - Width of the still
+/// * `tile_pixels` - The read back render target, four channels per pixel
+/// * `target_width` - Width of the render target, which may be wider than the tile
+/// * `tile` - Area of the still the tile covers
+pub fn copy_tile(image: &mut [u16], image_width: u32, tile_pixels: &[u16], target_width: u32, tile: &Tile) {
+    let row_len = tile.extent.width as usize * 4;
+    for row in 0..tile.extent.height as usize {
+        let src = row * target_width as usize * 4;
+        let dst = ((tile.offset.y as usize + row) * image_width as usize + tile.offset.x as usize) * 4;
+        image[dst..dst + row_len].copy_from_slice(&tile_pixels[src..src + row_len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk;
+    use crate::vulkan::viewport;
+
+    #[test]
+    fn test_tiles_assemble_the_still() {
+        // Each pixel's channels hold its index in the still
+        let extent = vk::Extent2D { width: 5, height: 3 };
+        let mut image = vec![0; 5 * 3 * 4];
+        for tile in viewport::tiles(extent, 2) {
+            let mut target = vec![u16::MAX; 2 * 2 * 4];
+            for y in 0..tile.extent.height {
+                for x in 0..tile.extent.width {
+                    let index = (tile.offset.y as u32 + y) * 5 + tile.offset.x as u32 + x;
+                    let texel = (y * 2 + x) as usize * 4;
+                    target[texel..texel + 4].fill(index as u16);
+                }
+            }
+            copy_tile(&mut image, 5, &target, 2, &tile);
+        }
+        let expected: Vec<u16> = (0..15).flat_map(|index| [index; 4]).collect();
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    fn test_output_path_uses_format_extension() {
+        let settings = StillExportSettings { format: StillFormat::Hdr(HdrFormat::Exr), path: PathBuf::from("out/still"), ..StillExportSettings::default() };
+        assert_eq!(settings.output_path(), PathBuf::from("out/still.exr"));
+        let settings = StillExportSettings { path: PathBuf::from("shot.png"), ..settings };
+        assert_eq!(settings.output_path(), PathBuf::from("shot.png"));
+    }
+}
@@ -1,9 +1,66 @@
 use ash::vk;
 use ash::{Device, Instance, Entry};
 use crate::vulkan::device::{VulkanDevice, QueueFamilyIndices};
+use crate::config;
 use crate::error::{Result, VulkanError};
+use crate::vulkan::tonemap::DisplayOutput;
 use winit::window::Window;
-use log::{debug, info, error};
+use log::{debug, info, error, warn};
+use serde::{Deserialize, Serialize};
+
+/// Presentation mode preferred for the swapchain
+///
+/// FIFO is the only mode every surface supports, so the others fall back to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresentMode {
+    /// Wait for vertical blank, no tearing
+    Fifo,
+    /// Replace the queued image, no tearing and no frame rate cap
+    #[default]
+    Mailbox,
+    /// Present right away, may tear
+    Immediate,
+}
+
+impl PresentMode {
+    /// All modes in display order
+    pub const ALL: [PresentMode; 3] = [PresentMode::Fifo, PresentMode::Mailbox, PresentMode::Immediate];
+
+    /// Display name for the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            PresentMode::Fifo => "VSync (FIFO)",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Immediate => "Immediate",
+        }
+    }
+
+    /// Vulkan mode of this preference
+    fn vk(self) -> vk::PresentModeKHR {
+        match self {
+            PresentMode::Fifo => vk::PresentModeKHR::FIFO,
+            PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+            PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+        }
+    }
+
+    /// Pick the preferred mode if the surface offers it, FIFO otherwise
+    ///
+    /// # Arguments
+    /// * `available` - Present modes reported for the surface
+    pub fn choose(self, available: &[vk::PresentModeKHR]) -> vk::PresentModeKHR {
+        let preferred = self.vk();
+        if available.contains(&preferred) {
+            preferred
+        } else {
+            if preferred != vk::PresentModeKHR::FIFO {
+                warn!("Present mode {:?} not supported by the surface, using FIFO", preferred);
+            }
+            vk::PresentModeKHR::FIFO
+        }
+    }
+}
 
 /// Vulkan swapchain wrapper with proper resource management
 ///
@@ -14,14 +71,26 @@ pub struct VulkanSwapchain {
     pub swapchain: vk::SwapchainKHR,
     
     /// The swapchain images (owned by the swapchain)
-    pub _swapchain_images: Vec<vk::Image>,
+    pub swapchain_images: Vec<vk::Image>,
     
     /// The swapchain image format
     pub swapchain_image_format: vk::Format,
     
+    /// Color space of the swapchain images, kept when recreating
+    pub color_space: vk::ColorSpaceKHR,
+    
+    /// How colors written to the swapchain are encoded (SDR or HDR)
+    pub display_output: DisplayOutput,
+    
     /// The swapchain extent
     pub swapchain_extent: vk::Extent2D,
     
+    /// Preferred presentation mode, used again when recreating
+    pub present_mode: PresentMode,
+    
+    /// Presentation mode the swapchain was created with
+    pub active_present_mode: vk::PresentModeKHR,
+    
     /// The swapchain image views
     pub swapchain_image_views: Vec<vk::ImageView>,
     
@@ -41,6 +110,7 @@ impl VulkanSwapchain {
     /// * `device` - The Vulkan device
     /// * `surface` - The surface to present to
     /// * `window` - The window
+    /// * `present_mode` - Preferred presentation mode
     ///
     /// # Returns
     /// A new VulkanSwapchain instance
@@ -53,12 +123,13 @@ impl VulkanSwapchain {
         device: &VulkanDevice,
         surface: vk::SurfaceKHR,
         window: &Window,
+        present_mode: PresentMode,
     ) -> Result<Self> {
         info!("Creating Vulkan swapchain");
         
         let swapchain_loader = ash::khr::swapchain::Device::new(instance, &device.device);
         
-        let (swapchain, swapchain_images, swapchain_image_format, swapchain_extent) =
+        let (swapchain, swapchain_images, surface_format, display_output, swapchain_extent, active_present_mode) =
             Self::create_swapchain(
                 instance,
                 entry,
@@ -67,10 +138,13 @@ impl VulkanSwapchain {
                 surface,
                 &swapchain_loader,
                 window,
-                &device.queue_families
+                &device.queue_families,
+                present_mode,
             )?;
         
         debug!("Swapchain created with {} images", swapchain_images.len());
+        info!("Display output: {} ({:?}, {:?})", display_output.name(), surface_format.format, surface_format.color_space);
+        let swapchain_image_format = surface_format.format;
         
         let swapchain_image_views = Self::create_swapchain_image_views(
             &device.device,
@@ -84,9 +158,13 @@ impl VulkanSwapchain {
         
         Ok(Self {
             swapchain,
-            _swapchain_images: swapchain_images,
+            swapchain_images,
             swapchain_image_format,
+            color_space: surface_format.color_space,
+            display_output,
             swapchain_extent,
+            present_mode,
+            active_present_mode,
             swapchain_image_views,
             swapchain_loader,
             _device: device.device.clone(),
@@ -104,9 +182,10 @@ impl VulkanSwapchain {
     /// * `swapchain_loader` - The swapchain loader
     /// * `window` - The window
     /// * `queue_families` - The queue family indices
+    /// * `present_mode` - Preferred presentation mode
     ///
     /// # Returns
-    /// A tuple of (swapchain, swapchain_images, swapchain_image_format, swapchain_extent)
+    /// A tuple of (swapchain, swapchain_images, surface_format, display_output, swapchain_extent, present_mode)
     ///
     /// # Errors
     /// Returns an error if swapchain creation fails
@@ -119,7 +198,8 @@ impl VulkanSwapchain {
         swapchain_loader: &ash::khr::swapchain::Device,
         window: &Window,
         queue_families: &QueueFamilyIndices,
-    ) -> Result<(vk::SwapchainKHR, Vec<vk::Image>, vk::Format, vk::Extent2D)> {
+        present_mode: PresentMode,
+    ) -> Result<(vk::SwapchainKHR, Vec<vk::Image>, vk::SurfaceFormatKHR, DisplayOutput, vk::Extent2D, vk::PresentModeKHR)> {
         debug!("Creating swapchain");
         
         let surface_loader_temp = ash::khr::surface::Instance::new(entry, instance);
@@ -140,15 +220,14 @@ impl VulkanSwapchain {
         
         debug!("Found {} surface formats and {} present modes", surface_formats.len(), present_modes.len());
         
-        let surface_format = surface_formats.iter()
-            .find(|format| format.format == vk::Format::B8G8R8A8_SRGB && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
-            .unwrap_or(&surface_formats[0]);
+        if surface_formats.is_empty() {
+            return Err(VulkanError::SwapchainCreation("Surface reports no formats".to_string()).into());
+        }
+        let (surface_format, display_output) = DisplayOutput::choose(&surface_formats, config::rendering::PREFER_HDR_OUTPUT);
         
-        debug!("Selected surface format: {:?}", surface_format.format);
+        debug!("Selected surface format: {:?} ({:?})", surface_format.format, surface_format.color_space);
         
-        let present_mode = present_modes.iter()
-            .find(|mode| **mode == vk::PresentModeKHR::MAILBOX)
-            .unwrap_or(&vk::PresentModeKHR::FIFO);
+        let present_mode = present_mode.choose(&present_modes);
         
         debug!("Selected present mode: {:?}", present_mode);
         
@@ -198,7 +277,7 @@ impl VulkanSwapchain {
             .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
             .pre_transform(surface_capabilities.current_transform)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-            .present_mode(*present_mode)
+            .present_mode(present_mode)
             .clipped(true)
             .image_sharing_mode(sharing_mode)
             .queue_family_indices(&queue_family_indices);
@@ -213,7 +292,7 @@ impl VulkanSwapchain {
         };
         
         debug!("Swapchain created successfully");
-        Ok((swapchain, swapchain_images, surface_format.format, extent))
+        Ok((swapchain, swapchain_images, surface_format, display_output, extent, present_mode))
     }
     
     /// Create image views for the swapchain images
@@ -299,7 +378,11 @@ impl VulkanSwapchain {
         self.swapchain_image_views.clear();
         
         // Create new swapchain with old swapchain as reference
-        let (new_swapchain, new_swapchain_images, new_swapchain_image_format, new_swapchain_extent) =
+        let surface_format = vk::SurfaceFormatKHR {
+            format: self.swapchain_image_format,
+            color_space: self.color_space,
+        };
+        let (new_swapchain, new_swapchain_images, new_swapchain_extent, new_present_mode) =
             match Self::create_swapchain_with_old(
                 instance,
                 entry,
@@ -308,6 +391,8 @@ impl VulkanSwapchain {
                 surface,
                 self.swapchain,
                 &self.swapchain_loader,
+                surface_format,
+                self.present_mode,
                 new_width,
                 new_height
             ) {
@@ -322,14 +407,14 @@ impl VulkanSwapchain {
         // Update swapchain data only after successful creation
         let old_swapchain = self.swapchain;
         self.swapchain = new_swapchain;
-        self._swapchain_images = new_swapchain_images;
-        self.swapchain_image_format = new_swapchain_image_format;
+        self.swapchain_images = new_swapchain_images;
         self.swapchain_extent = new_swapchain_extent;
+        self.active_present_mode = new_present_mode;
         
         // Create new image views with error handling
         match Self::create_swapchain_image_views(
             &device.device,
-            &self._swapchain_images,
+            &self.swapchain_images,
             self.swapchain_image_format
         ) {
             Ok(image_views) => {
@@ -356,6 +441,26 @@ impl VulkanSwapchain {
         Ok(())
     }
     
+    /// Destroy the swapchain and its image views while the surface goes away
+    ///
+    /// The swapchain handle is left null, so a later `recreate` builds a fresh
+    /// swapchain for the new surface with the same format and present mode.
+    ///
+    /// # Arguments
+    /// * `device` - The Vulkan device, which must be idle
+    pub fn release(&mut self, device: &VulkanDevice) {
+        info!("Releasing swapchain");
+        unsafe {
+            for &image_view in &self.swapchain_image_views {
+                device.device.destroy_image_view(image_view, None);
+            }
+            self.swapchain_loader.destroy_swapchain(self.swapchain, None);
+        }
+        self.swapchain_image_views.clear();
+        self.swapchain_images.clear();
+        self.swapchain = vk::SwapchainKHR::null();
+    }
+    
     /// Create a new swapchain using an old swapchain as reference
     ///
     /// # Arguments
@@ -364,11 +469,13 @@ impl VulkanSwapchain {
     /// * `surface` - The Vulkan surface
     /// * `old_swapchain` - The old swapchain to use as reference
     /// * `swapchain_loader` - The swapchain loader
+    /// * `surface_format` - Format and color space of the old swapchain, so pipelines stay compatible
+    /// * `present_mode` - Preferred presentation mode
     /// * `new_width` - The new window width
     /// * `new_height` - The new window height
     ///
     /// # Returns
-    /// A tuple of (swapchain, swapchain_images, swapchain_image_format, swapchain_extent)
+    /// A tuple of (swapchain, swapchain_images, swapchain_extent, present_mode)
     ///
     /// # Errors
     /// Returns an error if swapchain creation fails
@@ -380,9 +487,11 @@ impl VulkanSwapchain {
         surface: vk::SurfaceKHR,
         old_swapchain: vk::SwapchainKHR,
         swapchain_loader: &ash::khr::swapchain::Device,
+        surface_format: vk::SurfaceFormatKHR,
+        present_mode: PresentMode,
         new_width: u32,
         new_height: u32,
-    ) -> Result<(vk::SwapchainKHR, Vec<vk::Image>, vk::Format, vk::Extent2D)> {
+    ) -> Result<(vk::SwapchainKHR, Vec<vk::Image>, vk::Extent2D, vk::PresentModeKHR)> {
         debug!("Creating new swapchain with old swapchain reference");
         
         // Get surface capabilities to determine proper extent
@@ -409,7 +518,12 @@ impl VulkanSwapchain {
             }
         };
         
-        let format = vk::Format::B8G8R8A8_SRGB;
+        let present_modes = unsafe {
+            surface_loader_temp.get_physical_device_surface_present_modes(physical_device, surface)
+                .map_err(|e| VulkanError::SwapchainCreation(format!("Failed to get present modes during recreate: {:?}", e)))?
+        };
+        let present_mode = present_mode.choose(&present_modes);
+        
         let image_count = surface_capabilities.min_image_count + 1;
         let image_count = if surface_capabilities.max_image_count > 0 {
             image_count.min(surface_capabilities.max_image_count)
@@ -421,14 +535,14 @@ impl VulkanSwapchain {
             .surface(surface)
             .old_swapchain(old_swapchain)
             .min_image_count(image_count)
-            .image_format(format)
-            .image_color_space(vk::ColorSpaceKHR::SRGB_NONLINEAR)
+            .image_format(surface_format.format)
+            .image_color_space(surface_format.color_space)
             .image_extent(extent)
             .image_array_layers(1)
             .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
             .pre_transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-            .present_mode(vk::PresentModeKHR::FIFO)
+            .present_mode(present_mode)
             .clipped(true)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE);
         
@@ -443,7 +557,7 @@ impl VulkanSwapchain {
         };
         
         debug!("New swapchain created successfully");
-        Ok((swapchain, swapchain_images, format, extent))
+        Ok((swapchain, swapchain_images, extent, present_mode))
     }
 }
 
@@ -460,4 +574,17 @@ impl Drop for VulkanSwapchain {
         }
         debug!("Vulkan swapchain destroyed");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_present_mode_falls_back_to_fifo() {
+        let available = [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::MAILBOX];
+        assert_eq!(PresentMode::Mailbox.choose(&available), vk::PresentModeKHR::MAILBOX);
+        assert_eq!(PresentMode::Immediate.choose(&available), vk::PresentModeKHR::FIFO);
+        assert_eq!(PresentMode::Fifo.choose(&available), vk::PresentModeKHR::FIFO);
+    }
+}
@@ -0,0 +1,585 @@
+//! MSDF font atlas and layout of 3D text
+//!
+//! Every font in use gets one page of a single RGBA8 atlas bound at set 0,
+//! binding 3: the MSDF (see `vulkan::msdf`) of each glyph of
+//! `config::text::CHARSET` in a grid of cells, pages stacked vertically. A
+//! font is read and rasterized the first time a text uses it, then the whole
+//! atlas is uploaded again; like environment maps, the previous atlas stays
+//! bound until the upload has completed. Until the first font is loaded a
+//! 1x1 transparent placeholder keeps the binding valid.
+//!
+//! Texts are laid out into glyph quads on the CPU every time the draws are
+//! set, and the quads are written to the scene uniform buffer with the
+//! texts. Fonts that fail to load are reported once, and their texts fall
+//! back to the default font.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use ab_glyph::{Font, FontVec, OutlineCurve};
+use ash::vk;
+use ash::{Device, Instance};
+use log::{debug, error, info};
+use crate::config;
+use crate::ecs::components::{SDFDisplayMode, Transform};
+use crate::ecs::text::{default_font, Text3D};
+use crate::error::{Result, VulkanError};
+use crate::project;
+use crate::vulkan::deletion_queue::DeletionQueue;
+use crate::vulkan::msdf::{self, Segment};
+use crate::vulkan::scene_buffer::{GpuGlyph, GpuSceneData};
+use crate::vulkan::texture::Texture;
+use crate::vulkan::upload::{UploadContext, UploadTicket};
+
+/// Format of the atlas; the distances are stored linearly
+const ATLAS_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+/// Number of glyphs in `config::text::CHARSET`
+fn glyph_count() -> usize {
+    config::text::CHARSET.iter().map(|&(first, last)| (first..=last).count()).sum()
+}
+
+/// Atlas width in pixels
+fn atlas_width() -> usize {
+    config::text::ATLAS_COLUMNS * config::text::CELL_PIXELS
+}
+
+/// Height of one font's page in pixels
+fn page_height() -> usize {
+    glyph_count().div_ceil(config::text::ATLAS_COLUMNS) * config::text::CELL_PIXELS
+}
+
+/// Atlas height in pixels, with room for `config::text::MAX_FONTS` pages
+fn atlas_height() -> usize {
+    page_height() * config::text::MAX_FONTS
+}
+
+/// Top left pixel of a glyph's cell
+///
+/// # Arguments
+/// * `page` - Index of the font
+/// * `glyph` - Index of the glyph in `config::text::CHARSET`
+fn cell_origin(page: usize, glyph: usize) -> (usize, usize) {
+    let column = glyph % config::text::ATLAS_COLUMNS;
+    let row = glyph / config::text::ATLAS_COLUMNS;
+    (column * config::text::CELL_PIXELS, page * page_height() + row * config::text::CELL_PIXELS)
+}
+
+/// One text to draw
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextDraw {
+    pub text: Text3D,
+    pub transform: Transform,
+    pub display_mode: SDFDisplayMode,
+    /// Currently selected in the editor
+    pub selected: bool,
+    /// Layer mask, see `RenderLayers`
+    pub layers: u32,
+}
+
+/// Text draws collected for the current frame
+#[derive(Clone, Debug, Default)]
+pub struct TextDrawList {
+    draws: Vec<TextDraw>,
+}
+
+impl TextDrawList {
+    /// Create an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws in the list
+    pub fn draws(&self) -> &[TextDraw] {
+        &self.draws
+    }
+
+    /// Add a text
+    ///
+    /// # Arguments
+    /// * `layers` - Layer mask, see `RenderLayers`
+    pub fn push(&mut self, text: &Text3D, transform: &Transform, display_mode: SDFDisplayMode, selected: bool, layers: u32) {
+        self.draws.push(TextDraw {
+            text: text.clone(),
+            transform: transform.clone(),
+            display_mode,
+            selected,
+            layers,
+        });
+    }
+}
+
+/// Glyph of a font in the atlas
+#[derive(Clone, Copy, Debug)]
+struct AtlasGlyph {
+    /// Quad in em relative to the pen position on the baseline, as in `GpuGlyph`
+    plane: [f32; 4],
+    /// As in `GpuGlyph`
+    uv: [f32; 4],
+}
+
+/// Font with its glyphs in the atlas
+struct AtlasFont {
+    /// Path as given by the texts, empty for the default font
+    name: String,
+    font: FontVec,
+    /// Glyphs with an outline; characters without one only advance the pen
+    glyphs: HashMap<char, AtlasGlyph>,
+}
+
+impl AtlasFont {
+    /// Font units per em
+    fn units_per_em(&self) -> f32 {
+        self.font.units_per_em().unwrap_or(1000.0)
+    }
+}
+
+/// CPU copy of the atlas and the fonts in it
+pub struct FontAtlas {
+    fonts: Vec<AtlasFont>,
+    /// RGBA8 pixels, rows from top to bottom
+    pixels: Vec<u8>,
+}
+
+impl Default for FontAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontAtlas {
+    /// Create an empty atlas
+    pub fn new() -> Self {
+        Self {
+            fonts: Vec::new(),
+            pixels: vec![0; atlas_width() * atlas_height() * 4],
+        }
+    }
+
+    /// Size of the atlas image
+    pub fn extent(&self) -> vk::Extent2D {
+        vk::Extent2D { width: atlas_width() as u32, height: atlas_height() as u32 }
+    }
+
+    /// Pixels of the atlas, rows from top to bottom
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Index of a font already in the atlas
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.fonts.iter().position(|font| font.name == name)
+    }
+
+    /// Parse a font and rasterize its glyphs into the next free page
+    ///
+    /// # Arguments
+    /// * `name` - Name the texts refer to the font by
+    /// * `data` - Contents of a TrueType or OpenType file
+    ///
+    /// # Returns
+    /// Index of the font
+    ///
+    /// # Errors
+    /// Returns an error if the data is not a font or all pages are taken
+    pub fn add(&mut self, name: &str, data: Vec<u8>) -> Result<usize> {
+        if self.fonts.len() >= config::text::MAX_FONTS {
+            return Err(VulkanError::FontLoad(format!("Text atlas is full ({} fonts)", config::text::MAX_FONTS)).into());
+        }
+        let font = FontVec::try_from_vec(data)
+            .map_err(|_| VulkanError::FontLoad("Not a TrueType or OpenType font".to_string()))?;
+
+        let page = self.fonts.len();
+        let mut atlas_font = AtlasFont { name: name.to_string(), font, glyphs: HashMap::new() };
+        let scale = config::text::EM_PIXELS / atlas_font.units_per_em();
+        let range = config::text::RANGE_PIXELS;
+        let extent = self.extent();
+        let chars = config::text::CHARSET.iter().flat_map(|&(first, last)| first..=last);
+        for (index, c) in chars.enumerate() {
+            let Some(outline) = atlas_font.font.outline(atlas_font.font.glyph_id(c)) else {
+                continue;
+            };
+            let segments: Vec<Segment> = outline.curves.iter().map(segment).collect();
+            // Bounds of the control points, which contain the curves
+            let Some((min, max)) = bounds(&segments) else {
+                continue;
+            };
+
+            let cell = config::text::CELL_PIXELS;
+            let width = (((max[0] - min[0]) * scale + 2.0 * range).ceil() as usize).min(cell);
+            let height = (((max[1] - min[1]) * scale + 2.0 * range).ceil() as usize).min(cell);
+            let offset = [range - min[0] * scale, range - min[1] * scale];
+            let pixels = msdf::generate(
+                &segments.iter().map(|segment| segment.transformed(scale, offset)).collect::<Vec<_>>(),
+                width,
+                height,
+                range,
+            );
+
+            let (x, y) = cell_origin(page, index);
+            for row in 0..height {
+                let start = ((y + row) * atlas_width() + x) * 4;
+                self.pixels[start..start + width * 4].copy_from_slice(&pixels[row * width * 4..(row + 1) * width * 4]);
+            }
+
+            let (left, bottom) = ((min[0] * scale - range) / config::text::EM_PIXELS, (min[1] * scale - range) / config::text::EM_PIXELS);
+            atlas_font.glyphs.insert(c, AtlasGlyph {
+                plane: [
+                    left,
+                    bottom,
+                    left + width as f32 / config::text::EM_PIXELS,
+                    bottom + height as f32 / config::text::EM_PIXELS,
+                ],
+                // The bitmap's top row is the cell's top row
+                uv: [
+                    x as f32 / extent.width as f32,
+                    (y + height) as f32 / extent.height as f32,
+                    (x + width) as f32 / extent.width as f32,
+                    y as f32 / extent.height as f32,
+                ],
+            });
+        }
+
+        debug!("Font {:?} rasterized into page {} ({} glyphs)", name, page, atlas_font.glyphs.len());
+        self.fonts.push(atlas_font);
+        Ok(page)
+    }
+
+    /// Lay out a text into glyph quads
+    ///
+    /// Lines are separated by `\n` and centered horizontally, and the block
+    /// of lines is centered vertically on the origin. Characters outside
+    /// `config::text::CHARSET` advance the pen but are not drawn.
+    ///
+    /// # Arguments
+    /// * `font` - Index of the font
+    /// * `text` - Text to lay out
+    ///
+    /// # Returns
+    /// The quads in em, and the half size of the box around them
+    pub fn layout(&self, font: usize, text: &str) -> (Vec<GpuGlyph>, [f32; 2]) {
+        let atlas_font = &self.fonts[font];
+        let units = atlas_font.units_per_em();
+        let ascent = atlas_font.font.ascent_unscaled() / units;
+        let descent = atlas_font.font.descent_unscaled() / units;
+        let line_height = ascent - descent + atlas_font.font.line_gap_unscaled() / units;
+
+        let mut glyphs = Vec::new();
+        let lines: Vec<&str> = text.split('\n').map(|line| line.trim_end_matches('\r')).collect();
+        for (index, line) in lines.iter().enumerate() {
+            let line_start = glyphs.len();
+            let baseline = -(index as f32) * line_height;
+            let mut pen = 0.0;
+            let mut previous = None;
+            for c in line.chars() {
+                let id = atlas_font.font.glyph_id(c);
+                if let Some(previous) = previous {
+                    pen += atlas_font.font.kern_unscaled(previous, id) / units;
+                }
+                if let Some(glyph) = atlas_font.glyphs.get(&c) {
+                    let [left, bottom, right, top] = glyph.plane;
+                    glyphs.push(GpuGlyph {
+                        plane: [left + pen, bottom + baseline, right + pen, top + baseline],
+                        uv: glyph.uv,
+                    });
+                }
+                pen += atlas_font.font.h_advance_unscaled(id) / units;
+                previous = Some(id);
+            }
+            for glyph in &mut glyphs[line_start..] {
+                glyph.plane[0] -= pen * 0.5;
+                glyph.plane[2] -= pen * 0.5;
+            }
+        }
+
+        let top = ascent;
+        let bottom = descent - (lines.len() - 1) as f32 * line_height;
+        let center = (top + bottom) * 0.5;
+        let mut half_size = [0.0f32; 2];
+        for glyph in &mut glyphs {
+            glyph.plane[1] -= center;
+            glyph.plane[3] -= center;
+            half_size[0] = half_size[0].max(glyph.plane[0].abs()).max(glyph.plane[2].abs());
+            half_size[1] = half_size[1].max(glyph.plane[1].abs()).max(glyph.plane[3].abs());
+        }
+        (glyphs, half_size)
+    }
+}
+
+/// Convert an outline curve in font units
+fn segment(curve: &OutlineCurve) -> Segment {
+    let point = |p: ab_glyph::Point| [p.x, p.y];
+    match *curve {
+        OutlineCurve::Line(a, b) => Segment::Line(point(a), point(b)),
+        OutlineCurve::Quad(a, b, c) => Segment::Quad(point(a), point(b), point(c)),
+        OutlineCurve::Cubic(a, b, c, d) => Segment::Cubic(point(a), point(b), point(c), point(d)),
+    }
+}
+
+/// Bottom left and top right corner around the control points, None without any
+fn bounds(segments: &[Segment]) -> Option<([f32; 2], [f32; 2])> {
+    let points = segments.iter().flat_map(|segment| match *segment {
+        Segment::Line(a, b) => vec![a, b],
+        Segment::Quad(a, b, c) => vec![a, b, c],
+        Segment::Cubic(a, b, c, d) => vec![a, b, c, d],
+    });
+    points.fold(None, |bounds, [x, y]| match bounds {
+        None => Some(([x, y], [x, y])),
+        Some((min, max)) => Some(([min[0].min(x), min[1].min(y)], [max[0].max(x), max[1].max(y)])),
+    })
+}
+
+/// A text laid out for the scene buffer
+struct LaidOutText {
+    draw: TextDraw,
+    glyphs: Vec<GpuGlyph>,
+    half_size: [f32; 2],
+}
+
+/// Font atlas texture and the texts of the current frame
+pub struct TextPass {
+    atlas: FontAtlas,
+    /// Fonts that failed to load, not retried
+    failed: HashSet<String>,
+    sampler: vk::Sampler,
+    placeholder: Texture,
+    current: Option<Texture>,
+    pending: Option<(Texture, UploadTicket)>,
+    texts: Vec<LaidOutText>,
+    device: Device,
+}
+
+impl TextPass {
+    /// Create the sampler and the placeholder atlas
+    ///
+    /// Waits for the placeholder upload so the binding is valid from the first frame.
+    ///
+    /// # Errors
+    /// Returns an error if the sampler or placeholder cannot be created
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        upload: &mut UploadContext,
+    ) -> Result<Self> {
+        // Glyphs are sampled inside their quads, which stay within their cells
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+        let sampler = unsafe {
+            device.create_sampler(&sampler_info, None)
+                .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create text atlas sampler: {:?}", e)))?
+        };
+
+        let placeholder = Texture::upload(
+            instance,
+            physical_device,
+            device,
+            upload,
+            vk::Extent2D { width: 1, height: 1 },
+            ATLAS_FORMAT,
+            &[0; 4],
+        ).and_then(|(texture, ticket)| match upload.wait(ticket) {
+            Ok(()) => Ok(texture),
+            Err(e) => {
+                texture.destroy(device);
+                Err(e)
+            }
+        });
+        let placeholder = match placeholder {
+            Ok(texture) => texture,
+            Err(e) => {
+                unsafe { device.destroy_sampler(sampler, None) };
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            atlas: FontAtlas::new(),
+            failed: HashSet::new(),
+            sampler,
+            placeholder,
+            current: None,
+            pending: None,
+            texts: Vec::new(),
+            device: device.clone(),
+        })
+    }
+
+    /// Replace the texts drawn from the next frame on
+    ///
+    /// Fonts used for the first time are added to the atlas, which is then
+    /// uploaded again; the texts are laid out right away.
+    ///
+    /// # Arguments
+    /// * `list` - Texts collected from the ECS world
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - The physical device used for memory type lookup
+    /// * `upload` - Upload context the atlas is copied with
+    pub fn set_draws(
+        &mut self,
+        list: TextDrawList,
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        upload: &mut UploadContext,
+    ) {
+        let mut added = false;
+        self.texts.clear();
+        for draw in list.draws {
+            let font = self.font(&draw.text.font, &mut added).or_else(|| self.font("", &mut added));
+            let Some(font) = font else {
+                continue;
+            };
+            let (glyphs, half_size) = self.atlas.layout(font, &draw.text.text);
+            self.texts.push(LaidOutText { draw, glyphs, half_size });
+        }
+
+        if added {
+            if let Err(e) = self.upload_atlas(instance, physical_device, upload) {
+                error!("Failed to upload the text atlas: {}", e);
+            }
+        }
+    }
+
+    /// Index of a font in the atlas, adding it if it is new
+    ///
+    /// # Arguments
+    /// * `name` - Font file, empty for the `default_font`
+    /// * `added` - Set when the font was added
+    fn font(&mut self, name: &str, added: &mut bool) -> Option<usize> {
+        if let Some(index) = self.atlas.find(name) {
+            return Some(index);
+        }
+        if self.failed.contains(name) {
+            return None;
+        }
+
+        let path = project::locate(Path::new(if name.is_empty() { default_font() } else { name }));
+        info!("Loading font {}", path.display());
+        let loaded = match std::fs::read(&path) {
+            Ok(data) => self.atlas.add(name, data),
+            Err(e) => Err(VulkanError::FontLoad(e.to_string()).into()),
+        };
+        match loaded {
+            Ok(index) => {
+                *added = true;
+                Some(index)
+            }
+            Err(e) => {
+                error!("Failed to load font {}: {}", path.display(), e);
+                self.failed.insert(name.to_string());
+                None
+            }
+        }
+    }
+
+    /// Submit the upload of the whole atlas
+    ///
+    /// # Errors
+    /// Returns an error if the texture cannot be created or the upload cannot be submitted
+    fn upload_atlas(&mut self, instance: &Instance, physical_device: vk::PhysicalDevice, upload: &mut UploadContext) -> Result<()> {
+        // A newer atlas supersedes an upload still in flight; no frame has sampled it
+        if let Some((texture, ticket)) = self.pending.take() {
+            let waited = upload.wait(ticket);
+            texture.destroy(&self.device);
+            waited?;
+        }
+        let pending = Texture::upload(
+            instance,
+            physical_device,
+            &self.device,
+            upload,
+            self.atlas.extent(),
+            ATLAS_FORMAT,
+            self.atlas.pixels(),
+        )?;
+        self.pending = Some(pending);
+        Ok(())
+    }
+
+    /// Swap in a completed atlas upload
+    ///
+    /// The replaced atlas goes to the deletion queue, since frames in flight
+    /// may still sample it.
+    ///
+    /// # Returns
+    /// The descriptor to bind from now on, if it changed
+    pub fn poll(&mut self, upload: &mut UploadContext, deletion_queue: &mut DeletionQueue) -> Option<vk::DescriptorImageInfo> {
+        if !self.pending.as_ref().is_some_and(|(_, ticket)| upload.is_complete(*ticket)) {
+            return None;
+        }
+        let (texture, _) = self.pending.take()?;
+        debug!("Text atlas bound");
+        if let Some(previous) = self.current.replace(texture) {
+            previous.retire(deletion_queue);
+        }
+        Some(self.descriptor_info())
+    }
+
+    /// Write the laid out texts into the scene data
+    ///
+    /// Texts past `config::rendering::MAX_SDF_TEXTS`, or whose glyphs no
+    /// longer fit, are left out.
+    pub fn write(&self, data: &mut GpuSceneData) {
+        data.clear_texts();
+        for text in &self.texts {
+            let draw = &text.draw;
+            if !data.push_text(&draw.text, &draw.transform, draw.display_mode, &text.glyphs, text.half_size) {
+                debug!("Texts truncated to {}", data.text_count());
+                break;
+            }
+            data.set_last_text_layers(draw.layers);
+            if draw.selected {
+                data.select_last_text();
+            }
+        }
+    }
+
+    /// Descriptor of the bound atlas, the placeholder before the first font is loaded
+    pub fn descriptor_info(&self) -> vk::DescriptorImageInfo {
+        match &self.current {
+            Some(texture) => texture.descriptor_info(self.sampler),
+            None => self.placeholder.descriptor_info(self.sampler),
+        }
+    }
+
+    /// Descriptor of the placeholder, which stays valid for the pass's lifetime
+    pub fn placeholder_info(&self) -> vk::DescriptorImageInfo {
+        self.placeholder.descriptor_info(self.sampler)
+    }
+}
+
+impl Drop for TextPass {
+    fn drop(&mut self) {
+        // The device is idle at shutdown, including pending uploads
+        if let Some((texture, _)) = self.pending.take() {
+            texture.destroy(&self.device);
+        }
+        if let Some(texture) = self.current.take() {
+            texture.destroy(&self.device);
+        }
+        self.placeholder.destroy(&self.device);
+        unsafe { self.device.destroy_sampler(self.sampler, None) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_page_fits_the_charset_inside_the_atlas() {
+        let last = glyph_count() - 1;
+        for page in 0..config::text::MAX_FONTS {
+            let (x, y) = cell_origin(page, last);
+            assert!(x + config::text::CELL_PIXELS <= atlas_width());
+            assert!(y + config::text::CELL_PIXELS <= (page + 1) * page_height());
+        }
+        assert_eq!(cell_origin(1, 0), (0, page_height()));
+        assert_eq!(atlas_height(), page_height() * config::text::MAX_FONTS);
+    }
+}
@@ -0,0 +1,158 @@
+//! Sampled 2D textures uploaded through the transfer queue
+//!
+//! A `Texture` is a device local image with one mip level and a view,
+//! filled by `UploadContext::upload_to_image`. The upload is not waited
+//! on: callers keep the returned ticket and only sample the texture once it
+//! has completed.
+
+use ash::vk;
+use ash::{Device, Instance};
+use log::debug;
+use crate::error::{Result, VulkanError};
+use crate::vulkan::deletion_queue::{DeferredResource, DeletionQueue};
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::scene_buffer::find_memory_type;
+use crate::vulkan::upload::{UploadContext, UploadTicket};
+
+/// Image, memory and view of a texture in `SHADER_READ_ONLY_OPTIMAL` layout
+pub struct Texture {
+    pub image: vk::Image,
+    pub memory: vk::DeviceMemory,
+    pub view: vk::ImageView,
+    pub extent: vk::Extent2D,
+    pub format: vk::Format,
+    /// Bytes of device memory bound to the image
+    pub size: vk::DeviceSize,
+}
+
+impl Texture {
+    /// Create a texture and submit the upload of its pixels
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance
+    /// * `physical_device` - The physical device used for memory type lookup
+    /// * `device` - The logical device
+    /// * `upload` - Upload context the copy is submitted to
+    /// * `extent` - Size in pixels
+    /// * `format` - Image format, `pixels` must be tightly packed in it
+    /// * `pixels` - Rows from top to bottom
+    ///
+    /// # Returns
+    /// The texture and the ticket of its upload
+    ///
+    /// # Errors
+    /// Returns an error if a Vulkan object cannot be created or the upload
+    /// cannot be submitted; nothing is leaked in that case
+    #[allow(clippy::too_many_arguments)]
+    pub fn upload(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        upload: &mut UploadContext,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        pixels: &[u8],
+    ) -> Result<(Self, UploadTicket)> {
+        debug!("Uploading {}x{} {:?} texture ({} bytes)", extent.width, extent.height, format, pixels.len());
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+
+        // Written on the upload queue and sampled on the graphics queue
+        let (sharing_mode, queue_families) = upload.sharing();
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+            .sharing_mode(sharing_mode)
+            .queue_family_indices(&queue_families)
+            .samples(vk::SampleCountFlags::TYPE_1);
+
+        let mut texture = Self {
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            extent,
+            format,
+            size: 0,
+        };
+        if let Err(e) = texture.create_resources(device, &memory_properties, &image_info) {
+            texture.destroy(device);
+            return Err(e);
+        }
+
+        match upload.upload_to_image(pixels, texture.image, extent) {
+            Ok(ticket) => Ok((texture, ticket)),
+            Err(e) => {
+                texture.destroy(device);
+                Err(e)
+            }
+        }
+    }
+
+    /// Create the image, its memory and view
+    fn create_resources(
+        &mut self,
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        image_info: &vk::ImageCreateInfo,
+    ) -> Result<()> {
+        unsafe {
+            self.image = device.create_image(image_info, None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create texture image: {:?}", e)))?;
+
+            let requirements = device.get_image_memory_requirements(self.image);
+            self.size = requirements.size;
+            let memory_type_index = find_memory_type(memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .ok_or_else(|| VulkanError::MemoryAllocation("No device local memory type for texture".to_string()))?;
+            self.memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Textures, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to allocate texture memory: {:?}", e)))?;
+            device.bind_image_memory(self.image, self.memory, 0)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to bind texture memory: {:?}", e)))?;
+
+            self.view = device.create_image_view(&vk::ImageViewCreateInfo::default()
+                .image(self.image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(self.format)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }), None)
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create texture view: {:?}", e)))?;
+
+            Ok(())
+        }
+    }
+
+    /// Descriptor for sampling the texture with the given sampler
+    pub fn descriptor_info(&self, sampler: vk::Sampler) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo::default()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(self.view)
+            .sampler(sampler)
+    }
+
+    /// Hand the texture to the deletion queue, for textures frames may still sample
+    pub fn retire(self, deletion_queue: &mut DeletionQueue) {
+        deletion_queue.defer(DeferredResource::ImageView(self.view));
+        deletion_queue.defer(DeferredResource::Image(self.image));
+        deletion_queue.defer(DeferredResource::Memory(self.memory));
+    }
+
+    /// Destroy the texture right away; the GPU must no longer use it
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+            memory_usage::free(device, self.memory);
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Tonemapping and HDR display output
+//!
+//! The SDF scene is rendered in linear HDR (`config::rendering::SCENE_FORMAT`).
+//! The composite pass scales it by the exposure, compresses it with the
+//! selected operator and encodes it for the swapchain's color space:
+//!
+//! - `Sdr`: 8-bit sRGB swapchain, values are clamped to [0, 1]
+//! - `SdrUnorm`: like `Sdr`, on a surface without `_SRGB` formats; the
+//!   shaders apply the sRGB curve
+//! - `ScRgb`: FP16 extended linear sRGB, 1.0 is 80 nits
+//! - `Hdr10`: 10-bit Rec.2020 with the ST 2084 (PQ) curve
+//!
+//! HDR surfaces are only offered by some drivers and displays; without one
+//! the swapchain stays SDR. In HDR outputs, 1.0 after tonemapping is shown
+//! at `config::rendering::HDR_PAPER_WHITE_NITS`, and the HUD and debug lines
+//! are encoded the same way through `OutputSpecialization`. Frame export
+//! always writes SDR, tonemapped on the CPU with the same curves.
+
+use ash::vk;
+use crate::color;
+use crate::config;
+
+/// Curve that compresses scene values into the displayable range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TonemapOperator {
+    /// Exposure only, values above 1.0 clip on SDR displays
+    None,
+    /// `x / (1 + x)`
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    #[default]
+    Aces,
+}
+
+impl TonemapOperator {
+    /// All operators, in UI order
+    pub const ALL: [TonemapOperator; 3] = [TonemapOperator::None, TonemapOperator::Reinhard, TonemapOperator::Aces];
+
+    /// Readable name for the UI
+    pub fn name(self) -> &'static str {
+        match self {
+            TonemapOperator::None => "None",
+            TonemapOperator::Reinhard => "Reinhard",
+            TonemapOperator::Aces => "ACES",
+        }
+    }
+
+    /// Value of `operator` in `shaders/composite.frag`
+    fn shader_index(self) -> u32 {
+        match self {
+            TonemapOperator::None => 0,
+            TonemapOperator::Reinhard => 1,
+            TonemapOperator::Aces => 2,
+        }
+    }
+
+    /// Apply the curve to one linear channel
+    fn apply(self, x: f32) -> f32 {
+        let x = x.max(0.0);
+        match self {
+            TonemapOperator::None => x,
+            TonemapOperator::Reinhard => x / (1.0 + x),
+            TonemapOperator::Aces => ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Exposure and tonemapping applied by the composite pass
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonemapSettings {
+    /// Curve applied after the exposure
+    pub operator: TonemapOperator,
+    /// Exposure in stops; each stop doubles the brightness
+    pub exposure_ev: f32,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            operator: TonemapOperator::default(),
+            exposure_ev: 0.0,
+        }
+    }
+}
+
+impl TonemapSettings {
+    /// Tonemap a linear scene color, before any display encoding
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let scale = self.exposure_ev.exp2();
+        rgb.map(|channel| self.operator.apply(channel * scale))
+    }
+
+    /// Tonemap a linear scene color into an 8-bit sRGB pixel, as shown on an SDR display
+    pub fn encode_srgb8(&self, rgb: [f32; 3]) -> [u8; 3] {
+        self.apply(rgb).map(color::linear_to_srgb8)
+    }
+}
+
+/// How the swapchain expects colors to be encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayOutput {
+    /// sRGB, [0, 1], encoded by the swapchain format
+    Sdr,
+    /// sRGB, [0, 1], encoded by the shaders for a UNORM swapchain
+    SdrUnorm,
+    /// Extended linear sRGB (FP16), 1.0 = 80 nits
+    ScRgb,
+    /// Rec.2020 primaries with the PQ curve
+    Hdr10,
+}
+
+impl DisplayOutput {
+    /// Readable name for the log and the UI
+    pub fn name(self) -> &'static str {
+        match self {
+            DisplayOutput::Sdr => "SDR",
+            DisplayOutput::SdrUnorm => "SDR (shader encoded)",
+            DisplayOutput::ScRgb => "HDR (scRGB)",
+            DisplayOutput::Hdr10 => "HDR10 (PQ)",
+        }
+    }
+
+    /// Check whether the output shows values above SDR white
+    #[allow(dead_code)]
+    pub fn is_hdr(self) -> bool {
+        matches!(self, DisplayOutput::ScRgb | DisplayOutput::Hdr10)
+    }
+
+    /// Pick the swapchain format and the matching output
+    ///
+    /// HDR formats are preferred when `prefer_hdr` is set (scRGB first, it
+    /// keeps blending linear); otherwise 8-bit sRGB, then any `_SRGB` format,
+    /// then whatever comes first with the shaders doing the encoding.
+    ///
+    /// # Arguments
+    /// * `formats` - Formats the surface supports, must not be empty
+    /// * `prefer_hdr` - Whether HDR formats may be used
+    pub fn choose(formats: &[vk::SurfaceFormatKHR], prefer_hdr: bool) -> (vk::SurfaceFormatKHR, Self) {
+        let find = |format: vk::Format, color_space: vk::ColorSpaceKHR| {
+            formats.iter().copied().find(|f| f.format == format && f.color_space == color_space)
+        };
+
+        if prefer_hdr {
+            if let Some(format) = find(vk::Format::R16G16B16A16_SFLOAT, vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT) {
+                return (format, DisplayOutput::ScRgb);
+            }
+            if let Some(format) = find(vk::Format::A2B10G10R10_UNORM_PACK32, vk::ColorSpaceKHR::HDR10_ST2084_EXT) {
+                return (format, DisplayOutput::Hdr10);
+            }
+        }
+
+        let srgb = find(vk::Format::B8G8R8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR)
+            .or_else(|| find(vk::Format::R8G8B8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR))
+            .or_else(|| formats.iter().copied().find(|f| {
+                color::is_srgb_format(f.format) && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+            }));
+        match srgb {
+            Some(format) => (format, DisplayOutput::Sdr),
+            None => (formats[0], DisplayOutput::SdrUnorm),
+        }
+    }
+
+    /// Value of `outputMode` in the shaders
+    fn shader_index(self) -> u32 {
+        match self {
+            DisplayOutput::Sdr => 0,
+            DisplayOutput::ScRgb => 1,
+            DisplayOutput::Hdr10 => 2,
+            DisplayOutput::SdrUnorm => 3,
+        }
+    }
+
+    /// Specialization constants of the pipelines drawing into the swapchain
+    pub fn specialization(self) -> OutputSpecialization {
+        OutputSpecialization {
+            output_mode: self.shader_index(),
+            paper_white_nits: config::rendering::HDR_PAPER_WHITE_NITS,
+        }
+    }
+}
+
+/// Push constants of `shaders/composite.frag`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct CompositePushConstants {
+    /// Fraction of the offscreen target that contains the rendered scene
+    pub uv_scale: [f32; 2],
+    /// Linear exposure multiplier
+    pub exposure: f32,
+    /// `TonemapOperator` index
+    pub operator: u32,
+    /// `DisplayOutput` index
+    pub output_mode: u32,
+    /// Brightness of 1.0 in HDR outputs
+    pub paper_white_nits: f32,
+}
+
+unsafe impl bytemuck::Pod for CompositePushConstants {}
+unsafe impl bytemuck::Zeroable for CompositePushConstants {}
+
+impl CompositePushConstants {
+    /// Push constants for one composite draw
+    pub fn new(uv_scale: [f32; 2], settings: &TonemapSettings, output: DisplayOutput) -> Self {
+        Self {
+            uv_scale,
+            exposure: settings.exposure_ev.exp2(),
+            operator: settings.operator.shader_index(),
+            output_mode: output.shader_index(),
+            paper_white_nits: config::rendering::HDR_PAPER_WHITE_NITS,
+        }
+    }
+}
+
+/// Specialization constants of the HUD and debug line fragment shaders
+///
+/// Field order matches `constant_id` 0..1 in `shaders/imgui.frag` and
+/// `shaders/debug_line.frag`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct OutputSpecialization {
+    pub output_mode: u32,
+    pub paper_white_nits: f32,
+}
+
+unsafe impl bytemuck::Pod for OutputSpecialization {}
+unsafe impl bytemuck::Zeroable for OutputSpecialization {}
+
+impl OutputSpecialization {
+    /// Map entries describing where each constant lives in the data blob
+    pub fn map_entries() -> [vk::SpecializationMapEntry; 2] {
+        [
+            vk::SpecializationMapEntry::default()
+                .constant_id(0)
+                .offset(std::mem::offset_of!(OutputSpecialization, output_mode) as u32)
+                .size(std::mem::size_of::<u32>()),
+            vk::SpecializationMapEntry::default()
+                .constant_id(1)
+                .offset(std::mem::offset_of!(OutputSpecialization, paper_white_nits) as u32)
+                .size(std::mem::size_of::<f32>()),
+        ]
+    }
+}
+
+/// Decode an IEEE 754 half-precision float, as read back from FP16 images
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent as i32 - 15),
+    }
+}
+
+/// Encode an IEEE 754 half-precision float, rounding to nearest even
+///
+/// Values too large for half precision become infinity.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Subnormal or zero: shift the mantissa with its implicit bit into place
+        if half_exponent < -10 {
+            return sign;
+        }
+        let full = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let rounded = round_shift(full, shift);
+        return sign | rounded as u16;
+    }
+
+    // A mantissa rounding up carries into the exponent, which is still correct
+    let rounded = round_shift(((half_exponent as u32) << 23) | mantissa, 13);
+    sign | rounded.min(0x7c00) as u16
+}
+
+/// Shift right, rounding to nearest with ties to even
+fn round_shift(value: u32, shift: u32) -> u32 {
+    let halfway = 1 << (shift - 1);
+    let remainder = value & ((1 << shift) - 1);
+    let truncated = value >> shift;
+    if remainder > halfway || (remainder == halfway && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operators_and_exposure() {
+        let reinhard = TonemapSettings { operator: TonemapOperator::Reinhard, exposure_ev: 0.0 };
+        assert_eq!(reinhard.apply([1.0, 0.0, 3.0]), [0.5, 0.0, 0.75]);
+
+        // One stop up doubles the input before the curve
+        let brighter = TonemapSettings { exposure_ev: 1.0, ..reinhard };
+        assert_eq!(brighter.apply([0.5, 0.5, 0.5]), [0.5, 0.5, 0.5]);
+
+        let aces = TonemapSettings::default();
+        let [low, mid, high] = aces.apply([0.1, 1.0, 100.0]);
+        assert!(low < mid && mid < high && high <= 1.0);
+        assert_eq!(aces.encode_srgb8([0.0, 100.0, 0.0]), [0, 255, 0]);
+    }
+
+    #[test]
+    fn test_choose_prefers_hdr_and_falls_back_to_srgb() {
+        let surface = |format, color_space| vk::SurfaceFormatKHR { format, color_space };
+        let srgb = surface(vk::Format::B8G8R8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR);
+        let hdr10 = surface(vk::Format::A2B10G10R10_UNORM_PACK32, vk::ColorSpaceKHR::HDR10_ST2084_EXT);
+        let scrgb = surface(vk::Format::R16G16B16A16_SFLOAT, vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT);
+
+        assert_eq!(DisplayOutput::choose(&[srgb, hdr10, scrgb], true), (scrgb, DisplayOutput::ScRgb));
+        assert_eq!(DisplayOutput::choose(&[srgb, hdr10], true), (hdr10, DisplayOutput::Hdr10));
+        assert_eq!(DisplayOutput::choose(&[hdr10, srgb], false), (srgb, DisplayOutput::Sdr));
+        assert_eq!(DisplayOutput::choose(&[srgb], true), (srgb, DisplayOutput::Sdr));
+
+        // Without an sRGB format the shaders encode
+        let unorm = surface(vk::Format::B8G8R8A8_UNORM, vk::ColorSpaceKHR::SRGB_NONLINEAR);
+        let packed = surface(vk::Format::A8B8G8R8_SRGB_PACK32, vk::ColorSpaceKHR::SRGB_NONLINEAR);
+        assert_eq!(DisplayOutput::choose(&[unorm, packed], false), (packed, DisplayOutput::Sdr));
+        assert_eq!(DisplayOutput::choose(&[unorm], false), (unorm, DisplayOutput::SdrUnorm));
+    }
+
+    #[test]
+    fn test_f16_decoding() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x7bff), 65504.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert!(f16_to_f32(0x7c00).is_infinite());
+    }
+
+    #[test]
+    fn test_f16_encoding_round_trips() {
+        for bits in (0..0x7c00u16).step_by(7).chain([0x7bff, 0x0001, 0x03ff]) {
+            assert_eq!(f32_to_f16(f16_to_f32(bits)), bits);
+            assert_eq!(f32_to_f16(-f16_to_f32(bits)), bits | 0x8000);
+        }
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 4096.0), 0x3c00);
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert_eq!(f32_to_f16(1e-9), 0);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+}
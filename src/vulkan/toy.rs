@@ -0,0 +1,666 @@
+//! Shadertoy mode with feedback buffers
+//!
+//! A toy is a shadertoy style fragment shader drawn instead of the SDF scene
+//! (see `fullscreen_pass`). Besides the image, a toy may declare up to four
+//! offscreen buffer passes, like shadertoy's Buffer A to D. Every buffer has
+//! its own hot reloaded shader and reads up to four channels: other buffers,
+//! or itself for feedback. The buffers are drawn in order before the image;
+//! a channel naming a buffer that was already drawn this frame reads this
+//! frame's output, one naming the same or a later buffer reads the previous
+//! frame's, which each buffer keeps in the other image of a ping-pong pair.
+//!
+//! Buffers are declared in a manifest next to the shaders, given to `--toy`
+//! instead of a shader file:
+//!
+//! ```toml
+//! [[buffers]]
+//! name = "A"
+//! shader = "feedback_a.frag"   # relative to the manifest
+//! channels = ["A"]             # iChannel0 = Buffer A of the previous frame
+//!
+//! [image]
+//! shader = "feedback.frag"
+//! channels = ["A"]             # iChannel0 = Buffer A of this frame
+//! ```
+//!
+//! Buffer images have the size and half float format of the offscreen scene
+//! target and are drawn with its pass. Resolution scaling only applies to the
+//! image, so the feedback state survives scale changes. The buffers start
+//! out cleared to zero, and are cleared again when the window is resized.
+
+use std::path::{Path, PathBuf};
+use ash::vk;
+use ash::Device;
+use log::{debug, info};
+use serde::Deserialize;
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem, VulkanError};
+use crate::vulkan::deletion_queue::DeletionQueue;
+use crate::vulkan::depth::{DepthBuffer, DepthUsage};
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::gbuffer::GBuffer;
+use crate::vulkan::fullscreen_pass::{FullscreenInterface, FullscreenOutput, FullscreenPass, FullscreenUniforms, MouseState};
+use crate::vulkan::rendering::{PassLayout, PassTarget};
+use crate::vulkan::scene_buffer::find_memory_type;
+
+/// A pass of a toy manifest
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ToyPass {
+    /// Name the channels of other passes refer to, buffers only
+    #[serde(default)]
+    pub name: String,
+    /// Shadertoy style fragment shader, relative to the manifest
+    pub shader: PathBuf,
+    /// Names of the buffers read as `iChannel0`, `iChannel1`, ...
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+/// Passes of a toy
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ToyManifest {
+    /// Buffer passes, drawn in order before the image
+    #[serde(default)]
+    pub buffers: Vec<ToyPass>,
+    /// The pass drawn instead of the scene
+    pub image: ToyPass,
+}
+
+/// Buffer image a channel reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelSource {
+    /// Index of the buffer
+    pub buffer: usize,
+    /// Whether the previous frame is read, since the buffer is not drawn yet
+    /// when the pass runs
+    pub previous_frame: bool,
+}
+
+impl ChannelSource {
+    /// Image of the buffer's ping-pong pair that is read
+    ///
+    /// # Arguments
+    /// * `parity` - Image of every pair the buffers are drawn into this frame
+    fn image_index(self, parity: usize) -> usize {
+        if self.previous_frame {
+            1 - parity
+        } else {
+            parity
+        }
+    }
+}
+
+impl ToyManifest {
+    /// Read a toy: a `.toml` manifest, or a fragment shader drawn without buffers
+    ///
+    /// Shader paths of a manifest are resolved against its directory.
+    ///
+    /// # Errors
+    /// Returns an error if the manifest cannot be read or parsed, or is invalid
+    pub fn load(path: &Path) -> Result<Self> {
+        if path.extension().and_then(|extension| extension.to_str()) != Some("toml") {
+            return Ok(Self::single(path));
+        }
+        let text = std::fs::read_to_string(path).context(Subsystem::Io, format!("read toy manifest {}", path.display()))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&text, directory)
+            .map_err(|e| AppError::Generic(format!("Invalid toy manifest {}: {}", path.display(), e)))
+    }
+
+    /// A toy of a single fragment shader
+    fn single(fragment_path: &Path) -> Self {
+        Self {
+            buffers: Vec::new(),
+            image: ToyPass { name: String::new(), shader: fragment_path.to_path_buf(), channels: Vec::new() },
+        }
+    }
+
+    /// Parse and check a manifest, resolving its shaders against `directory`
+    fn parse(text: &str, directory: &Path) -> std::result::Result<Self, String> {
+        let mut manifest: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        for pass in manifest.buffers.iter_mut().chain(std::iter::once(&mut manifest.image)) {
+            pass.shader = directory.join(&pass.shader);
+        }
+
+        if manifest.buffers.len() > config::toy::MAX_BUFFERS {
+            return Err(format!("{} buffers, at most {} are supported", manifest.buffers.len(), config::toy::MAX_BUFFERS));
+        }
+        for (index, buffer) in manifest.buffers.iter().enumerate() {
+            if buffer.name.is_empty() {
+                return Err(format!("buffer {} has no name", index + 1));
+            }
+            if manifest.buffers[..index].iter().any(|other| other.name == buffer.name) {
+                return Err(format!("buffer name \"{}\" is used twice", buffer.name));
+            }
+        }
+        for pass in manifest.passes() {
+            if pass.channels.len() > config::toy::MAX_CHANNELS {
+                return Err(format!("{} reads {} channels, at most {} are supported",
+                    pass.shader.display(), pass.channels.len(), config::toy::MAX_CHANNELS));
+            }
+            if let Some(unknown) = pass.channels.iter().find(|name| manifest.buffer_index(name).is_none()) {
+                return Err(format!("{} reads unknown buffer \"{}\"", pass.shader.display(), unknown));
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// The buffers, then the image
+    pub fn passes(&self) -> impl Iterator<Item = &ToyPass> {
+        self.buffers.iter().chain(std::iter::once(&self.image))
+    }
+
+    /// Index of a buffer by name
+    fn buffer_index(&self, name: &str) -> Option<usize> {
+        self.buffers.iter().position(|buffer| buffer.name == name)
+    }
+
+    /// Images the channels of a pass read
+    ///
+    /// # Arguments
+    /// * `pass` - Index into `passes`, the image last
+    pub fn channel_sources(&self, pass: usize) -> Vec<ChannelSource> {
+        let Some(toy_pass) = self.passes().nth(pass) else {
+            return Vec::new();
+        };
+        toy_pass.channels.iter()
+            .filter_map(|name| self.buffer_index(name))
+            .map(|buffer| ChannelSource { buffer, previous_frame: buffer >= pass })
+            .collect()
+    }
+}
+
+/// One image of a buffer's ping-pong pair
+struct BufferImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+}
+
+/// A running toy: its passes, the buffer images and the channel bindings
+pub struct Toy {
+    /// File given to `--toy`
+    path: PathBuf,
+    manifest: ToyManifest,
+    /// Shaders of the buffers, then of the image
+    passes: Vec<FullscreenPass>,
+    /// Ping-pong images of every buffer
+    images: Vec<[BufferImage; 2]>,
+    /// Depth attachment the buffer passes share, None without buffers
+    depth: Option<DepthBuffer>,
+    /// G-buffer the buffer passes share, None without buffers; toy shaders leave it undefined
+    gbuffer: Option<GBuffer>,
+    /// Size of the buffer images, the full offscreen target
+    extent: vk::Extent2D,
+    sampler: vk::Sampler,
+    /// Channel set layout of every pass, null for passes without channels
+    set_layouts: Vec<vk::DescriptorSetLayout>,
+    descriptor_pool: vk::DescriptorPool,
+    /// Channel sets of every pass, one per parity
+    descriptor_sets: Vec<[vk::DescriptorSet; 2]>,
+    /// Image of every pair the buffers are drawn into this frame
+    parity: usize,
+    /// Whether the buffer images still have to be cleared
+    needs_clear: bool,
+    /// Seconds the toy has run (`iTime`)
+    time: f32,
+    /// Frames drawn so far (`iFrame`)
+    frame: u32,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    device: Device,
+}
+
+impl Toy {
+    /// Compile the passes of a toy and create its buffers
+    ///
+    /// # Arguments
+    /// * `device` - The logical device
+    /// * `memory_properties` - Memory properties of the physical device
+    /// * `scene_pass` - Offscreen scene pass; the image and the buffers are drawn with it
+    /// * `path` - Manifest or fragment shader given to `--toy`
+    /// * `extent` - Full size of the offscreen target
+    ///
+    /// # Errors
+    /// Returns an error if the manifest is invalid, a shader does not compile
+    /// or a Vulkan object cannot be created
+    pub fn new(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        scene_pass: &PassLayout,
+        path: &Path,
+        extent: vk::Extent2D,
+    ) -> Result<Self> {
+        let manifest = ToyManifest::load(path)?;
+        info!("Starting toy {} with {} buffer(s)", path.display(), manifest.buffers.len());
+
+        // From here on dropping `toy` cleans up whatever was created
+        let mut toy = Self {
+            path: path.to_path_buf(),
+            manifest,
+            passes: Vec::new(),
+            images: Vec::new(),
+            depth: None,
+            gbuffer: None,
+            extent: vk::Extent2D { width: extent.width.max(1), height: extent.height.max(1) },
+            sampler: vk::Sampler::null(),
+            set_layouts: Vec::new(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_sets: Vec::new(),
+            parity: 0,
+            needs_clear: true,
+            time: 0.0,
+            frame: 0,
+            memory_properties: *memory_properties,
+            device: device.clone(),
+        };
+        toy.create_bindings()?;
+
+        let passes: Vec<ToyPass> = toy.manifest.passes().cloned().collect();
+        let buffer_count = toy.manifest.buffers.len();
+        for (index, pass) in passes.iter().enumerate() {
+            let interface = FullscreenInterface {
+                output: if index < buffer_count { FullscreenOutput::Buffer } else { FullscreenOutput::Image },
+                channels: pass.channels.len(),
+                set_layout: toy.set_layouts[index],
+            };
+            toy.passes.push(FullscreenPass::new(device, scene_pass, &pass.shader, interface)?);
+        }
+
+        toy.create_images(scene_pass)?;
+        toy.write_descriptor_sets();
+        Ok(toy)
+    }
+
+    /// Create the sampler, the channel set layouts and the descriptor sets
+    fn create_bindings(&mut self) -> Result<()> {
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+        self.sampler = unsafe { self.device.create_sampler(&sampler_info, None) }
+            .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create toy channel sampler: {:?}", e)))?;
+
+        let channel_counts: Vec<usize> = self.manifest.passes().map(|pass| pass.channels.len()).collect();
+        for &count in &channel_counts {
+            if count == 0 {
+                self.set_layouts.push(vk::DescriptorSetLayout::null());
+                continue;
+            }
+            let bindings: Vec<_> = (0..count as u32)
+                .map(|binding| vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT))
+                .collect();
+            let layout = unsafe {
+                self.device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings), None)
+            }
+            .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create toy channel set layout: {:?}", e)))?;
+            self.set_layouts.push(layout);
+        }
+
+        let set_count = channel_counts.iter().filter(|&&count| count > 0).count() as u32;
+        let channel_count: usize = channel_counts.iter().sum();
+        if set_count > 0 {
+            let pool_sizes = [vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(2 * channel_count as u32)];
+            self.descriptor_pool = unsafe {
+                self.device.create_descriptor_pool(&vk::DescriptorPoolCreateInfo::default()
+                    .pool_sizes(&pool_sizes)
+                    .max_sets(2 * set_count), None)
+            }
+            .map_err(|e| VulkanError::PipelineCreation(format!("Failed to create toy descriptor pool: {:?}", e)))?;
+        }
+
+        for &layout in &self.set_layouts {
+            if layout == vk::DescriptorSetLayout::null() {
+                self.descriptor_sets.push([vk::DescriptorSet::null(); 2]);
+                continue;
+            }
+            let layouts = [layout, layout];
+            let sets = unsafe {
+                self.device.allocate_descriptor_sets(&vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(self.descriptor_pool)
+                    .set_layouts(&layouts))
+            }
+            .map_err(|e| VulkanError::PipelineCreation(format!("Failed to allocate toy channel sets: {:?}", e)))?;
+            self.descriptor_sets.push([sets[0], sets[1]]);
+        }
+        Ok(())
+    }
+
+    /// Create the ping-pong images of every buffer and their shared depth and G-buffer
+    fn create_images(&mut self, scene_pass: &PassLayout) -> Result<()> {
+        if self.manifest.buffers.is_empty() {
+            return Ok(());
+        }
+        let depth = DepthBuffer::new(&self.device, &self.memory_properties, self.extent, DepthUsage::Sampled)?;
+        let depth_view = depth.view;
+        self.depth = Some(depth);
+        let gbuffer = GBuffer::new(&self.device, &self.memory_properties, self.extent)?;
+        let gbuffer_views = gbuffer.views();
+        self.gbuffer = Some(gbuffer);
+
+        for _ in 0..self.manifest.buffers.len() {
+            let first = self.create_image(scene_pass, &gbuffer_views, depth_view)?;
+            let second = match self.create_image(scene_pass, &gbuffer_views, depth_view) {
+                Ok(image) => image,
+                Err(e) => {
+                    self.destroy_image(&first);
+                    return Err(e);
+                }
+            };
+            self.images.push([first, second]);
+        }
+        self.needs_clear = true;
+        debug!("Toy buffers created: {} x 2 images of {}x{}", self.images.len(), self.extent.width, self.extent.height);
+        Ok(())
+    }
+
+    /// Create one buffer image with its framebuffer
+    fn create_image(&self, scene_pass: &PassLayout, gbuffer_views: &[vk::ImageView], depth_view: vk::ImageView) -> Result<BufferImage> {
+        let format = config::rendering::SCENE_FORMAT;
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D { width: self.extent.width, height: self.extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+        let mut buffer_image = BufferImage {
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            framebuffer: vk::Framebuffer::null(),
+        };
+
+        let result = (|| -> Result<()> {
+            buffer_image.image = unsafe { self.device.create_image(&image_info, None) }
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create toy buffer image: {:?}", e)))?;
+
+            let requirements = unsafe { self.device.get_image_memory_requirements(buffer_image.image) };
+            let memory_type_index = find_memory_type(&self.memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .ok_or_else(|| VulkanError::MemoryAllocation("No device local memory type for toy buffer image".to_string()))?;
+            buffer_image.memory = memory_usage::allocate(&self.device, &vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index), MemoryCategory::Swapchain, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to allocate toy buffer memory: {:?}", e)))?;
+            unsafe { self.device.bind_image_memory(buffer_image.image, buffer_image.memory, 0) }
+                .map_err(|e| VulkanError::MemoryAllocation(format!("Failed to bind toy buffer memory: {:?}", e)))?;
+
+            let view_info = vk::ImageViewCreateInfo::default()
+                .image(buffer_image.image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                });
+            buffer_image.view = unsafe { self.device.create_image_view(&view_info, None) }
+                .map_err(|e| VulkanError::BufferCreation(format!("Failed to create toy buffer view: {:?}", e)))?;
+
+            buffer_image.framebuffer = scene_pass.create_framebuffer_with_extras(&self.device, buffer_image.view, gbuffer_views, depth_view, self.extent)?;
+            Ok(())
+        })();
+
+        match result {
+            Ok(()) => Ok(buffer_image),
+            Err(e) => {
+                self.destroy_image(&buffer_image);
+                Err(e)
+            }
+        }
+    }
+
+    /// Destroy one buffer image; null handles are skipped by Vulkan
+    fn destroy_image(&self, buffer_image: &BufferImage) {
+        unsafe {
+            self.device.destroy_framebuffer(buffer_image.framebuffer, None);
+            self.device.destroy_image_view(buffer_image.view, None);
+            self.device.destroy_image(buffer_image.image, None);
+            memory_usage::free(&self.device, buffer_image.memory);
+        }
+    }
+
+    /// Destroy every buffer image, the depth and the G-buffer
+    fn destroy_images(&mut self) {
+        for pair in std::mem::take(&mut self.images) {
+            for buffer_image in &pair {
+                self.destroy_image(buffer_image);
+            }
+        }
+        if let Some(depth) = self.depth.take() {
+            depth.destroy(&self.device);
+        }
+        if let Some(gbuffer) = self.gbuffer.take() {
+            gbuffer.destroy(&self.device);
+        }
+    }
+
+    /// Point the channel sets at the buffer images they read
+    fn write_descriptor_sets(&self) {
+        for (pass, sets) in self.descriptor_sets.iter().enumerate() {
+            let sources = self.manifest.channel_sources(pass);
+            for (parity, &set) in sets.iter().enumerate() {
+                if set == vk::DescriptorSet::null() {
+                    continue;
+                }
+                let image_infos: Vec<[vk::DescriptorImageInfo; 1]> = sources.iter()
+                    .map(|source| [vk::DescriptorImageInfo::default()
+                        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                        .image_view(self.images[source.buffer][source.image_index(parity)].view)
+                        .sampler(self.sampler)])
+                    .collect();
+                let writes: Vec<_> = image_infos.iter().enumerate()
+                    .map(|(binding, image_info)| vk::WriteDescriptorSet::default()
+                        .dst_set(set)
+                        .dst_binding(binding as u32)
+                        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .image_info(image_info))
+                    .collect();
+                unsafe { self.device.update_descriptor_sets(&writes, &[]) };
+            }
+        }
+    }
+
+    /// Manifest or fragment shader given to `--toy`
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Files hot reload watches: the manifest, if any, and every shader
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.path.clone()];
+        for pass in self.manifest.passes() {
+            if !files.contains(&pass.shader) {
+                files.push(pass.shader.clone());
+            }
+        }
+        files
+    }
+
+    /// Seconds the toy has run
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Advance the clock
+    pub fn advance(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    /// Count a frame as drawn (`iFrame`)
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Compile every shader again and swap in the new pipelines
+    ///
+    /// # Errors
+    /// Returns an error at the first shader that does not compile; it and
+    /// the shaders after it keep their previous version
+    pub fn reload(&mut self, scene_pass: &PassLayout, deletion_queue: &mut DeletionQueue) -> Result<()> {
+        for pass in &mut self.passes {
+            pass.reload(scene_pass, deletion_queue)?;
+        }
+        Ok(())
+    }
+
+    /// Recreate the buffers for a new size of the offscreen target, cleared to zero
+    ///
+    /// The caller must ensure the device is idle.
+    ///
+    /// # Errors
+    /// Returns an error if the images cannot be created
+    pub fn resize(&mut self, scene_pass: &PassLayout, extent: vk::Extent2D) -> Result<()> {
+        self.destroy_images();
+        self.extent = vk::Extent2D { width: extent.width.max(1), height: extent.height.max(1) };
+        self.create_images(scene_pass)?;
+        self.write_descriptor_sets();
+        Ok(())
+    }
+
+    /// Images the scene pass draws into for one buffer image
+    fn target(&self, buffer_image: &BufferImage) -> PassTarget {
+        let (depth_image, depth_view) = self.depth.as_ref()
+            .map(|depth| (depth.image, depth.view))
+            .unwrap_or_default();
+        PassTarget {
+            framebuffer: buffer_image.framebuffer,
+            color_image: buffer_image.image,
+            color_view: buffer_image.view,
+            depth_image,
+            depth_view,
+            extra_colors: self.gbuffer.as_ref().map(GBuffer::extra_colors).unwrap_or_default(),
+        }
+    }
+
+    /// Channel set of a pass for this frame
+    fn channels(&self, pass: usize) -> Option<vk::DescriptorSet> {
+        let set = self.descriptor_sets[pass][self.parity];
+        (set != vk::DescriptorSet::null()).then_some(set)
+    }
+
+    /// Draw the buffers into the next image of their pairs
+    ///
+    /// Recorded outside of any pass, before the image is drawn.
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer being recorded
+    /// * `scene_pass` - The pass given to `new`
+    /// * `mouse` - Mouse state for `iMouse`
+    /// * `time` - Value of `iTime`
+    pub fn record_buffers(&mut self, command_buffer: vk::CommandBuffer, scene_pass: &PassLayout, mouse: &MouseState, time: f32) {
+        if self.images.is_empty() {
+            return;
+        }
+        let render_area = vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent: self.extent };
+        if self.needs_clear {
+            // An empty pass clears the image and leaves it ready for sampling
+            for buffer_image in self.images.iter().flatten() {
+                let target = self.target(buffer_image);
+                scene_pass.begin(&self.device, command_buffer, &target, render_area, [0.0; 4]);
+                scene_pass.end(&self.device, command_buffer, &target);
+            }
+            self.needs_clear = false;
+        }
+
+        self.parity = 1 - self.parity;
+        let uniforms = FullscreenUniforms::new(self.extent, time, self.frame, mouse);
+        for (index, pass) in self.passes[..self.images.len()].iter().enumerate() {
+            let target = self.target(&self.images[index][self.parity]);
+            scene_pass.begin(&self.device, command_buffer, &target, render_area, [0.0; 4]);
+            pass.record(command_buffer, render_area, &uniforms, self.channels(index));
+            scene_pass.end(&self.device, command_buffer, &target);
+        }
+    }
+
+    /// Draw the image into the current pass
+    ///
+    /// # Arguments
+    /// * `command_buffer` - Command buffer inside the scene pass
+    /// * `rect` - Rectangle to cover
+    /// * `mouse` - Mouse state for `iMouse`
+    /// * `time` - Value of `iTime`
+    pub fn record_image(&self, command_buffer: vk::CommandBuffer, rect: vk::Rect2D, mouse: &MouseState, time: f32) {
+        let image = self.passes.len() - 1;
+        let uniforms = FullscreenUniforms::new(rect.extent, time, self.frame, mouse);
+        self.passes[image].record(command_buffer, rect, &uniforms, self.channels(image));
+    }
+}
+
+impl Drop for Toy {
+    fn drop(&mut self) {
+        // The pipelines first, their layouts were created from the set layouts
+        self.passes.clear();
+        self.destroy_images();
+        unsafe {
+            self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+            for &layout in &self.set_layouts {
+                self.device.destroy_descriptor_set_layout(layout, None);
+            }
+            self.device.destroy_sampler(self.sampler, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        [[buffers]]
+        name = "A"
+        shader = "a.frag"
+        channels = ["A", "B"]
+
+        [[buffers]]
+        name = "B"
+        shader = "b.frag"
+        channels = ["A", "B"]
+
+        [image]
+        shader = "image.frag"
+        channels = ["B"]
+    "#;
+
+    #[test]
+    fn test_channels_read_the_previous_frame_until_drawn() {
+        let manifest = ToyManifest::parse(MANIFEST, Path::new("shaders/toys")).unwrap();
+        assert_eq!(manifest.image.shader, Path::new("shaders/toys").join("image.frag"));
+
+        let previous = |buffer| ChannelSource { buffer, previous_frame: true };
+        let current = |buffer| ChannelSource { buffer, previous_frame: false };
+        assert_eq!(manifest.channel_sources(0), vec![previous(0), previous(1)]);
+        assert_eq!(manifest.channel_sources(1), vec![current(0), previous(1)]);
+        assert_eq!(manifest.channel_sources(2), vec![current(1)]);
+        assert_eq!(previous(0).image_index(1), 0);
+        assert_eq!(current(0).image_index(1), 1);
+    }
+
+    #[test]
+    fn test_invalid_manifests_are_rejected() {
+        let parse = |text: &str| ToyManifest::parse(text, Path::new(""));
+        assert!(parse("[image]\nshader = \"x.frag\"\nchannels = [\"A\"]").unwrap_err().contains("unknown buffer \"A\""));
+        assert!(parse("[[buffers]]\nshader = \"a.frag\"\n[image]\nshader = \"x.frag\"").unwrap_err().contains("no name"));
+        assert!(parse("[[buffers]]\nname = \"A\"\nshader = \"a.frag\"\n[[buffers]]\nname = \"A\"\nshader = \"b.frag\"\n[image]\nshader = \"x.frag\"")
+            .unwrap_err().contains("used twice"));
+
+        let single = ToyManifest::load(Path::new("shaders/toys/plasma.frag")).unwrap();
+        assert!(single.buffers.is_empty());
+        assert_eq!(single.passes().count(), 1);
+    }
+}
@@ -0,0 +1,710 @@
+//! Asynchronous uploads on the transfer queue
+//!
+//! `UploadContext` records copy commands into its own command pool and
+//! submits them to the dedicated transfer queue when the device has one (the
+//! graphics queue otherwise). Each submission gets a fence, so callers poll or
+//! wait for just their upload instead of idling a whole queue. Staging
+//! buffers handed over with a submission are freed once it completes.
+//!
+//! Resources written here and read on the graphics queue must be created
+//! with the sharing mode from [`UploadContext::sharing`] so no queue family
+//! ownership transfer is needed.
+//!
+//! Data is staged in a ring buffer shared by all uploads; space is reused
+//! once the submissions that read it have completed. Data that does not fit
+//! gets a staging buffer of its own. An [`UploadBatch`] collects buffer and
+//! image uploads with their barriers into one submission;
+//! [`UploadContext::upload_to_buffer`] and [`UploadContext::upload_to_image`]
+//! submit a single one.
+
+use std::collections::VecDeque;
+use ash::vk;
+use ash::{Device, Instance};
+use log::{debug, info, warn};
+use crate::config;
+use crate::error::{AppError, Result, Subsystem, VkResultExt, VulkanError};
+use crate::vulkan::device::VulkanDevice;
+use crate::vulkan::memory_usage::{self, MemoryCategory};
+use crate::vulkan::scene_buffer::find_memory_type;
+
+/// Time to wait for a single upload before reporting an error
+const UPLOAD_TIMEOUT_NS: u64 = 5_000_000_000;
+
+/// Handle of a submitted upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UploadTicket(u64);
+
+/// Staging buffer owned by an upload until it completes
+pub type StagingBuffer = (vk::Buffer, vk::DeviceMemory);
+
+/// Space allocator over the staging ring
+///
+/// Allocations since the last commit belong to the next submission and are
+/// freed together when it completes. Space is only reused once every older
+/// submission has completed too, so the free space stays contiguous.
+#[derive(Debug)]
+struct RingAllocator {
+    /// Ring size in bytes
+    size: u64,
+    /// Offset of the next allocation
+    head: u64,
+    /// Bytes in use between the oldest live allocation and `head`, including padding
+    used: u64,
+    /// `head` and `used` at the last commit, restored by `rollback`
+    committed: (u64, u64),
+    /// Ticket, end offset and completion of each submission with staged data, oldest first
+    submissions: VecDeque<(UploadTicket, u64, bool)>,
+}
+
+impl RingAllocator {
+    fn new(size: u64) -> Self {
+        Self { size, head: 0, used: 0, committed: (0, 0), submissions: VecDeque::new() }
+    }
+
+    /// Allocate `size` bytes, wrapping to the start when the end is too short
+    ///
+    /// # Returns
+    /// The offset, None if the ring has no room right now
+    fn alloc(&mut self, size: u64, align: u64) -> Option<u64> {
+        if size == 0 || size > self.size {
+            return None;
+        }
+        if self.used == 0 {
+            self.head = 0;
+        }
+        let aligned = self.head.next_multiple_of(align);
+        let (offset, consumed) = if aligned + size <= self.size {
+            (aligned, aligned - self.head + size)
+        } else {
+            (0, self.size - self.head + size)
+        };
+        if self.used + consumed > self.size {
+            return None;
+        }
+        self.head = (offset + size) % self.size;
+        self.used += consumed;
+        Some(offset)
+    }
+
+    /// Assign the allocations since the last commit to a submission
+    fn commit(&mut self, ticket: UploadTicket) {
+        if (self.head, self.used) != self.committed {
+            self.submissions.push_back((ticket, self.head, false));
+            self.committed = (self.head, self.used);
+        }
+    }
+
+    /// Free the allocations since the last commit
+    fn rollback(&mut self) {
+        (self.head, self.used) = self.committed;
+    }
+
+    /// Mark a submission completed and free the space no submission still reads
+    fn release(&mut self, ticket: UploadTicket) {
+        if let Some(submission) = self.submissions.iter_mut().find(|(id, _, _)| *id == ticket) {
+            submission.2 = true;
+        }
+        while let Some(&(_, end, true)) = self.submissions.front() {
+            self.submissions.pop_front();
+            let tail = (self.head + self.size - self.used) % self.size;
+            let freed = match (end + self.size - tail) % self.size {
+                0 => self.size,
+                freed => freed,
+            };
+            self.used -= freed;
+            self.committed.1 -= freed;
+        }
+    }
+}
+
+/// Host visible buffer data is staged in before it is copied
+struct StagingRing {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    allocator: RingAllocator,
+}
+
+/// A copy recorded by a batch
+enum UploadCopy {
+    Buffer {
+        src: vk::Buffer,
+        region: vk::BufferCopy,
+        dst: vk::Buffer,
+    },
+    Image {
+        src: vk::Buffer,
+        src_offset: vk::DeviceSize,
+        image: vk::Image,
+        extent: vk::Extent2D,
+    },
+}
+
+/// Buffer and image uploads submitted together
+///
+/// Dropping a batch without submitting it frees its staging space.
+pub struct UploadBatch<'a> {
+    context: &'a mut UploadContext,
+    copies: Vec<UploadCopy>,
+    /// Staging buffers of data that did not fit into the ring
+    staging: Vec<StagingBuffer>,
+    submitted: bool,
+}
+
+impl UploadBatch<'_> {
+    /// Queue a copy of `bytes` into a buffer
+    ///
+    /// The buffer needs `TRANSFER_DST` usage and the sharing mode from
+    /// [`UploadContext::sharing`].
+    ///
+    /// # Errors
+    /// Returns an error if the data cannot be staged
+    pub fn upload_to_buffer(&mut self, bytes: &[u8], dst: vk::Buffer, dst_offset: vk::DeviceSize) -> Result<()> {
+        let (src, src_offset) = self.stage(bytes)?;
+        let region = vk::BufferCopy { src_offset, dst_offset, size: bytes.len() as vk::DeviceSize };
+        self.copies.push(UploadCopy::Buffer { src, region, dst });
+        Ok(())
+    }
+
+    /// Queue a copy of tightly packed pixels into the first mip level of a 2D color image
+    ///
+    /// The image is transitioned from `UNDEFINED` to `SHADER_READ_ONLY_OPTIMAL`;
+    /// it needs `TRANSFER_DST` usage and the sharing mode from
+    /// [`UploadContext::sharing`].
+    ///
+    /// # Errors
+    /// Returns an error if the data cannot be staged
+    pub fn upload_to_image(&mut self, pixels: &[u8], image: vk::Image, extent: vk::Extent2D) -> Result<()> {
+        let (src, src_offset) = self.stage(pixels)?;
+        self.copies.push(UploadCopy::Image { src, src_offset, image, extent });
+        Ok(())
+    }
+
+    /// Copy data into the staging ring, or a staging buffer of its own if it does not fit
+    fn stage(&mut self, bytes: &[u8]) -> Result<(vk::Buffer, vk::DeviceSize)> {
+        let size = bytes.len() as vk::DeviceSize;
+        let context = &mut *self.context;
+        if let Some(ring) = context.staging_ring.as_mut() {
+            if let Some(offset) = ring.allocator.alloc(size, config::upload::STAGING_ALIGNMENT) {
+                unsafe { write_memory(&context.device, ring.memory, offset, bytes) }?;
+                return Ok((ring.buffer, offset));
+            }
+        }
+        debug!("Staging ring full, staging {} bytes in a buffer of their own", size);
+        let staging = create_staging_buffer(&context.device, &context.memory_properties, size)?;
+        self.staging.push(staging);
+        unsafe { write_memory(&context.device, staging.1, 0, bytes) }?;
+        Ok((staging.0, 0))
+    }
+
+    /// Record the copies with their barriers and submit them
+    ///
+    /// # Returns
+    /// A ticket to poll or wait on
+    ///
+    /// # Errors
+    /// Returns an error if recording or submission fails; the staging
+    /// space is freed in that case
+    pub fn submit(mut self) -> Result<UploadTicket> {
+        self.submitted = true;
+        let copies = std::mem::take(&mut self.copies);
+        let staging = std::mem::take(&mut self.staging);
+        let final_stage = self.context.final_stage();
+        let record = |device: &Device, command_buffer: vk::CommandBuffer| unsafe {
+            record_copies(device, command_buffer, &copies, final_stage);
+        };
+        let result = self.context.submit(staging, record);
+        if let Some(ring) = self.context.staging_ring.as_mut() {
+            match result {
+                Ok(ticket) => ring.allocator.commit(ticket),
+                Err(_) => ring.allocator.rollback(),
+            }
+        }
+        result
+    }
+}
+
+impl Drop for UploadBatch<'_> {
+    fn drop(&mut self) {
+        if self.submitted {
+            return;
+        }
+        if let Some(ring) = self.context.staging_ring.as_mut() {
+            ring.allocator.rollback();
+        }
+        self.context.free_staging(&self.staging);
+    }
+}
+
+/// Record the copies of a batch between layout transitions of its images
+unsafe fn record_copies(device: &Device, command_buffer: vk::CommandBuffer, copies: &[UploadCopy], final_stage: vk::PipelineStageFlags) {
+    let subresource_range = vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+    let images = || copies.iter().filter_map(|copy| match copy {
+        UploadCopy::Image { image, .. } => Some(*image),
+        UploadCopy::Buffer { .. } => None,
+    });
+
+    let to_transfer: Vec<_> = images().map(|image| {
+        vk::ImageMemoryBarrier::default()
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .image(image)
+            .subresource_range(subresource_range)
+    }).collect();
+    if !to_transfer.is_empty() {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &to_transfer,
+        );
+    }
+
+    for copy in copies {
+        match *copy {
+            UploadCopy::Buffer { src, region, dst } => device.cmd_copy_buffer(command_buffer, src, dst, &[region]),
+            UploadCopy::Image { src, src_offset, image, extent } => {
+                let region = vk::BufferImageCopy::default()
+                    .buffer_offset(src_offset)
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 });
+                device.cmd_copy_buffer_to_image(command_buffer, src, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &[region]);
+            }
+        }
+    }
+
+    // Buffers need no layout change; one barrier covers all their writes
+    let buffer_writes: Vec<_> = copies.iter()
+        .any(|copy| matches!(copy, UploadCopy::Buffer { .. }))
+        .then(|| vk::MemoryBarrier::default().src_access_mask(vk::AccessFlags::TRANSFER_WRITE))
+        .into_iter()
+        .collect();
+    let to_shader: Vec<_> = images().map(|image| {
+        vk::ImageMemoryBarrier::default()
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .image(image)
+            .subresource_range(subresource_range)
+    }).collect();
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TRANSFER,
+        final_stage,
+        vk::DependencyFlags::empty(),
+        &buffer_writes,
+        &[],
+        &to_shader,
+    );
+}
+
+/// Copy bytes into host visible memory
+unsafe fn write_memory(device: &Device, memory: vk::DeviceMemory, offset: vk::DeviceSize, bytes: &[u8]) -> Result<()> {
+    let mapped = device.map_memory(memory, offset, bytes.len() as vk::DeviceSize, vk::MemoryMapFlags::empty())
+        .vk_context(Subsystem::Renderer, "map staging memory")?;
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapped as *mut u8, bytes.len());
+    device.unmap_memory(memory);
+    Ok(())
+}
+
+/// Create a host visible staging buffer
+fn create_staging_buffer(
+    device: &Device,
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    size: vk::DeviceSize,
+) -> Result<StagingBuffer> {
+    unsafe {
+        let buffer = device.create_buffer(&vk::BufferCreateInfo::default()
+            .size(size)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE), None)
+            .map_err(|e| VulkanError::BufferCreation(format!("Failed to create staging buffer: {:?}", e)))?;
+        let requirements = device.get_buffer_memory_requirements(buffer);
+        let Some(memory_type_index) = find_memory_type(
+            memory_properties,
+            requirements.memory_type_bits,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        ) else {
+            device.destroy_buffer(buffer, None);
+            return Err(VulkanError::MemoryAllocation("No host visible memory type for staging".to_string()).into());
+        };
+        let memory = memory_usage::allocate(device, &vk::MemoryAllocateInfo::default()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index), MemoryCategory::Buffers, vk::MemoryPropertyFlags::HOST_VISIBLE)
+            .and_then(|memory| match device.bind_buffer_memory(buffer, memory, 0) {
+                Ok(()) => Ok(memory),
+                Err(e) => {
+                    memory_usage::free(device, memory);
+                    Err(e)
+                }
+            });
+        match memory {
+            Ok(memory) => Ok((buffer, memory)),
+            Err(e) => {
+                device.destroy_buffer(buffer, None);
+                Err(VulkanError::MemoryAllocation(format!("Failed to allocate staging memory: {:?}", e)).into())
+            }
+        }
+    }
+}
+
+/// A submitted upload that has not completed yet
+struct InFlightUpload {
+    ticket: UploadTicket,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    staging: Vec<StagingBuffer>,
+}
+
+/// Command pool and queue for uploads, with per-submission fences
+pub struct UploadContext {
+    device: Device,
+    queue: vk::Queue,
+    queue_family_index: u32,
+    graphics_family_index: u32,
+    command_pool: vk::CommandPool,
+    in_flight: Vec<InFlightUpload>,
+    next_ticket: u64,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    /// None if the ring could not be allocated; every upload then stages on its own
+    staging_ring: Option<StagingRing>,
+}
+
+impl UploadContext {
+    /// Create an upload context on the device's transfer queue
+    ///
+    /// # Arguments
+    /// * `instance` - The Vulkan instance, for memory type lookup
+    /// * `device` - The Vulkan device
+    ///
+    /// # Errors
+    /// Returns an error if the command pool cannot be created
+    pub fn new(instance: &Instance, device: &VulkanDevice) -> Result<Self> {
+        let graphics_family_index = device.queue_families.graphics_family.unwrap();
+        let queue_family_index = device.queue_families.transfer_family.unwrap_or(graphics_family_index);
+
+        let pool_info = vk::CommandPoolCreateInfo::default()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(queue_family_index);
+        let command_pool = unsafe { device.device.create_command_pool(&pool_info, None) }
+            .vk_context(Subsystem::Renderer, "create upload command pool")?;
+
+        info!(
+            "Upload context created on queue family {} ({})",
+            queue_family_index,
+            if queue_family_index != graphics_family_index { "dedicated transfer" } else { "graphics" }
+        );
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(device.physical_device) };
+        let staging_ring = match create_staging_buffer(&device.device, &memory_properties, config::upload::STAGING_RING_SIZE) {
+            Ok((buffer, memory)) => Some(StagingRing {
+                buffer,
+                memory,
+                allocator: RingAllocator::new(config::upload::STAGING_RING_SIZE),
+            }),
+            Err(e) => {
+                warn!("No staging ring, uploads stage in buffers of their own: {}", e);
+                None
+            }
+        };
+
+        Ok(Self {
+            device: device.device.clone(),
+            queue: device.transfer_queue,
+            queue_family_index,
+            graphics_family_index,
+            command_pool,
+            in_flight: Vec::new(),
+            next_ticket: 0,
+            memory_properties,
+            staging_ring,
+        })
+    }
+
+    /// Start collecting uploads for one submission
+    pub fn batch(&mut self) -> UploadBatch<'_> {
+        UploadBatch { context: self, copies: Vec::new(), staging: Vec::new(), submitted: false }
+    }
+
+    /// Upload `bytes` into a buffer in a submission of its own
+    ///
+    /// # Returns
+    /// A ticket to poll or wait on
+    ///
+    /// # Errors
+    /// Returns an error if staging or submission fails
+    #[allow(dead_code)]
+    pub fn upload_to_buffer(&mut self, bytes: &[u8], dst: vk::Buffer, dst_offset: vk::DeviceSize) -> Result<UploadTicket> {
+        let mut batch = self.batch();
+        batch.upload_to_buffer(bytes, dst, dst_offset)?;
+        batch.submit()
+    }
+
+    /// Upload pixels into a 2D color image in a submission of its own
+    ///
+    /// See [`UploadBatch::upload_to_image`].
+    ///
+    /// # Returns
+    /// A ticket to poll or wait on
+    ///
+    /// # Errors
+    /// Returns an error if staging or submission fails
+    pub fn upload_to_image(&mut self, pixels: &[u8], image: vk::Image, extent: vk::Extent2D) -> Result<UploadTicket> {
+        let mut batch = self.batch();
+        batch.upload_to_image(pixels, image, extent)?;
+        batch.submit()
+    }
+
+    /// Whether uploads run on a queue family separate from graphics
+    pub fn is_dedicated(&self) -> bool {
+        self.queue_family_index != self.graphics_family_index
+    }
+
+    /// Sharing mode and queue families for resources written by uploads
+    ///
+    /// # Returns
+    /// `CONCURRENT` across the transfer and graphics families when uploads
+    /// use a dedicated queue, `EXCLUSIVE` otherwise
+    pub fn sharing(&self) -> (vk::SharingMode, Vec<u32>) {
+        if self.is_dedicated() {
+            (vk::SharingMode::CONCURRENT, vec![self.queue_family_index, self.graphics_family_index])
+        } else {
+            (vk::SharingMode::EXCLUSIVE, Vec::new())
+        }
+    }
+
+    /// Destination stage for the last barrier of an upload
+    ///
+    /// Transfer-only queues cannot name shader stages, so the barrier ends at
+    /// the bottom of the pipe there; the fence orders it before graphics use.
+    pub fn final_stage(&self) -> vk::PipelineStageFlags {
+        if self.is_dedicated() {
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE
+        } else {
+            vk::PipelineStageFlags::FRAGMENT_SHADER
+        }
+    }
+
+    /// Record and submit an upload without waiting for it
+    ///
+    /// # Arguments
+    /// * `staging` - Staging buffers to free once the upload has completed
+    /// * `record` - Records the copy commands into the given command buffer
+    ///
+    /// # Returns
+    /// A ticket to poll or wait on
+    ///
+    /// # Errors
+    /// Returns an error if recording or submission fails; the staging
+    /// buffers are freed in that case
+    pub fn submit<F>(&mut self, staging: Vec<StagingBuffer>, record: F) -> Result<UploadTicket>
+    where
+        F: FnOnce(&Device, vk::CommandBuffer),
+    {
+        self.poll();
+
+        let alloc_info = vk::CommandBufferAllocateInfo::default()
+            .command_pool(self.command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let command_buffer = match unsafe { self.device.allocate_command_buffers(&alloc_info) } {
+            Ok(buffers) => buffers[0],
+            Err(e) => {
+                self.free_staging(&staging);
+                return Err(AppError::from_vk(e, Subsystem::Renderer, "allocate upload command buffer"));
+            }
+        };
+
+        let result = unsafe { self.record_and_submit(command_buffer, record) };
+        match result {
+            Ok(fence) => {
+                let ticket = UploadTicket(self.next_ticket);
+                self.next_ticket += 1;
+                debug!("Submitted upload {:?}", ticket);
+                self.in_flight.push(InFlightUpload { ticket, command_buffer, fence, staging });
+                Ok(ticket)
+            }
+            Err(e) => {
+                unsafe { self.device.free_command_buffers(self.command_pool, &[command_buffer]) };
+                self.free_staging(&staging);
+                Err(e)
+            }
+        }
+    }
+
+    /// Record the commands and submit them with a new fence
+    unsafe fn record_and_submit<F>(&self, command_buffer: vk::CommandBuffer, record: F) -> Result<vk::Fence>
+    where
+        F: FnOnce(&Device, vk::CommandBuffer),
+    {
+        let begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        self.device.begin_command_buffer(command_buffer, &begin_info)
+            .vk_context(Subsystem::Renderer, "begin upload command buffer")?;
+        record(&self.device, command_buffer);
+        self.device.end_command_buffer(command_buffer)
+            .vk_context(Subsystem::Renderer, "end upload command buffer")?;
+
+        let fence = self.device.create_fence(&vk::FenceCreateInfo::default(), None)
+            .vk_context(Subsystem::Renderer, "create upload fence")?;
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+        if let Err(e) = self.device.queue_submit(self.queue, &[submit_info], fence) {
+            self.device.destroy_fence(fence, None);
+            return Err(AppError::from_vk(e, Subsystem::Renderer, "submit upload"));
+        }
+        Ok(fence)
+    }
+
+    /// Release every upload whose fence has signaled
+    pub fn poll(&mut self) {
+        let mut index = 0;
+        while index < self.in_flight.len() {
+            let signaled = unsafe { self.device.get_fence_status(self.in_flight[index].fence) }.unwrap_or(false);
+            if signaled {
+                let upload = self.in_flight.swap_remove(index);
+                self.release(upload);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Check whether an upload has completed
+    #[allow(dead_code)]
+    pub fn is_complete(&mut self, ticket: UploadTicket) -> bool {
+        self.poll();
+        !self.in_flight.iter().any(|upload| upload.ticket == ticket)
+    }
+
+    /// Block until an upload has completed
+    ///
+    /// # Errors
+    /// Returns an error if the wait fails or times out
+    pub fn wait(&mut self, ticket: UploadTicket) -> Result<()> {
+        let Some(index) = self.in_flight.iter().position(|upload| upload.ticket == ticket) else {
+            return Ok(());
+        };
+
+        unsafe { self.device.wait_for_fences(&[self.in_flight[index].fence], true, UPLOAD_TIMEOUT_NS) }
+            .vk_context(Subsystem::Renderer, "wait for upload")?;
+        let upload = self.in_flight.swap_remove(index);
+        self.release(upload);
+        Ok(())
+    }
+
+    /// Block until every upload has completed
+    pub fn wait_all(&mut self) {
+        let fences: Vec<vk::Fence> = self.in_flight.iter().map(|upload| upload.fence).collect();
+        if fences.is_empty() {
+            return;
+        }
+        if let Err(e) = unsafe { self.device.wait_for_fences(&fences, true, UPLOAD_TIMEOUT_NS) } {
+            warn!("Waiting for {} pending uploads failed: {:?}", fences.len(), e);
+        }
+        for upload in std::mem::take(&mut self.in_flight) {
+            self.release(upload);
+        }
+    }
+
+    /// Free the command buffer, fence and staging space of a finished upload
+    fn release(&mut self, upload: InFlightUpload) {
+        if let Some(ring) = self.staging_ring.as_mut() {
+            ring.allocator.release(upload.ticket);
+        }
+        unsafe {
+            self.device.destroy_fence(upload.fence, None);
+            self.device.free_command_buffers(self.command_pool, &[upload.command_buffer]);
+        }
+        self.free_staging(&upload.staging);
+        debug!("Upload {:?} completed", upload.ticket);
+    }
+
+    /// Free staging buffers
+    fn free_staging(&self, staging: &[StagingBuffer]) {
+        for &(buffer, memory) in staging {
+            unsafe {
+                self.device.destroy_buffer(buffer, None);
+                memory_usage::free(&self.device, memory);
+            }
+        }
+    }
+}
+
+impl Drop for UploadContext {
+    fn drop(&mut self) {
+        self.wait_all();
+        unsafe {
+            if let Some(ring) = self.staging_ring.take() {
+                self.device.destroy_buffer(ring.buffer, None);
+                memory_usage::free(&self.device, ring.memory);
+            }
+            self.device.destroy_command_pool(self.command_pool, None);
+        }
+        debug!("Upload context destroyed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_wraps_and_frees_in_submission_order() {
+        let mut ring = RingAllocator::new(100);
+        assert_eq!(ring.alloc(30, 16), Some(0));
+        ring.commit(UploadTicket(0));
+        assert_eq!(ring.alloc(30, 16), Some(32));
+        ring.commit(UploadTicket(1));
+        assert_eq!(ring.alloc(40, 16), None);
+
+        // The newer submission completing first frees nothing
+        ring.release(UploadTicket(1));
+        assert_eq!(ring.alloc(40, 16), None);
+        ring.release(UploadTicket(0));
+        assert_eq!(ring.used, 0);
+
+        // An empty ring starts over; data too long for the end wraps around
+        assert_eq!(ring.alloc(70, 16), Some(0));
+        ring.commit(UploadTicket(2));
+        assert_eq!(ring.alloc(20, 16), Some(80));
+        ring.commit(UploadTicket(3));
+        ring.release(UploadTicket(2));
+        assert_eq!(ring.alloc(50, 16), Some(0));
+        assert_eq!(ring.used, 80);
+        assert_eq!(ring.alloc(101, 1), None);
+    }
+
+    #[test]
+    fn test_ring_rollback_frees_uncommitted_space() {
+        let mut ring = RingAllocator::new(64);
+        assert_eq!(ring.alloc(16, 16), Some(0));
+        ring.commit(UploadTicket(0));
+        assert_eq!(ring.alloc(32, 16), Some(16));
+        ring.rollback();
+        assert_eq!(ring.alloc(48, 16), Some(16));
+        ring.commit(UploadTicket(1));
+
+        ring.release(UploadTicket(0));
+        ring.release(UploadTicket(1));
+        assert_eq!((ring.used, ring.submissions.len()), (0, 0));
+    }
+}
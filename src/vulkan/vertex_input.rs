@@ -0,0 +1,140 @@
+//! Vertex input layouts
+//!
+//! `VertexLayout` describes the vertex buffers a graphics pipeline reads.
+//! Each binding steps either per vertex or per instance, and its attributes
+//! take consecutive shader locations in the order they are added. A
+//! per-instance binding lets one draw call place many copies of the same
+//! geometry with data read from an instance buffer; the mesh pass draws all
+//! instances of a mesh this way.
+
+use ash::vk;
+
+/// Vertex buffer bindings and attributes of a pipeline
+#[derive(Clone, Debug, Default)]
+pub struct VertexLayout {
+    bindings: Vec<vk::VertexInputBindingDescription>,
+    attributes: Vec<vk::VertexInputAttributeDescription>,
+}
+
+impl VertexLayout {
+    /// Create a layout without bindings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a binding stepped once per vertex with elements of type `T`
+    pub fn per_vertex<T>(self) -> Self {
+        self.binding(std::mem::size_of::<T>(), vk::VertexInputRate::VERTEX)
+    }
+
+    /// Add a binding stepped once per instance with elements of type `T`
+    pub fn per_instance<T>(self) -> Self {
+        self.binding(std::mem::size_of::<T>(), vk::VertexInputRate::INSTANCE)
+    }
+
+    /// Add a binding at the next binding index
+    fn binding(mut self, stride: usize, input_rate: vk::VertexInputRate) -> Self {
+        self.bindings.push(vk::VertexInputBindingDescription {
+            binding: self.bindings.len() as u32,
+            stride: stride as u32,
+            input_rate,
+        });
+        self
+    }
+
+    /// Add an attribute of the last binding at the next location
+    ///
+    /// # Arguments
+    /// * `format` - Format of the attribute
+    /// * `offset` - Byte offset inside the binding's element
+    ///
+    /// # Panics
+    /// Panics if no binding was added before
+    pub fn attribute(mut self, format: vk::Format, offset: usize) -> Self {
+        let binding = self.bindings.last().expect("vertex attribute added before its binding").binding;
+        self.attributes.push(vk::VertexInputAttributeDescription {
+            location: self.next_location(),
+            binding,
+            format,
+            offset: offset as u32,
+        });
+        self
+    }
+
+    /// Add a column major 4x4 float matrix of the last binding
+    ///
+    /// The matrix takes four locations, one per column, like a `mat4` input
+    /// in GLSL.
+    pub fn matrix4(self, offset: usize) -> Self {
+        let column = std::mem::size_of::<[f32; 4]>();
+        (0..4).fold(self, |layout, i| layout.attribute(vk::Format::R32G32B32A32_SFLOAT, offset + i * column))
+    }
+
+    /// Location the next attribute gets
+    pub fn next_location(&self) -> u32 {
+        self.attributes.len() as u32
+    }
+
+    /// Binding descriptions in binding order
+    #[allow(dead_code)]
+    pub fn bindings(&self) -> &[vk::VertexInputBindingDescription] {
+        &self.bindings
+    }
+
+    /// Attribute descriptions in location order
+    #[allow(dead_code)]
+    pub fn attributes(&self) -> &[vk::VertexInputAttributeDescription] {
+        &self.attributes
+    }
+
+    /// Vertex input state for pipeline creation, borrowing the layout
+    pub fn create_info(&self) -> vk::PipelineVertexInputStateCreateInfo<'_> {
+        vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&self.bindings)
+            .vertex_attribute_descriptions(&self.attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    struct Vertex {
+        position: [f32; 3],
+        normal: [f32; 3],
+    }
+
+    #[repr(C)]
+    struct Instance {
+        model: [[f32; 4]; 4],
+        color: [f32; 4],
+    }
+
+    #[test]
+    fn test_attributes_take_consecutive_locations() {
+        let layout = VertexLayout::new()
+            .per_vertex::<Vertex>()
+            .attribute(vk::Format::R32G32B32_SFLOAT, std::mem::offset_of!(Vertex, position))
+            .attribute(vk::Format::R32G32B32_SFLOAT, std::mem::offset_of!(Vertex, normal))
+            .per_instance::<Instance>()
+            .matrix4(std::mem::offset_of!(Instance, model))
+            .attribute(vk::Format::R32G32B32A32_SFLOAT, std::mem::offset_of!(Instance, color));
+
+        assert_eq!(layout.bindings().len(), 2);
+        assert_eq!(layout.bindings()[0].stride, 24);
+        assert_eq!(layout.bindings()[0].input_rate, vk::VertexInputRate::VERTEX);
+        assert_eq!(layout.bindings()[1].stride, 80);
+        assert_eq!(layout.bindings()[1].input_rate, vk::VertexInputRate::INSTANCE);
+
+        let attributes: Vec<(u32, u32, u32)> = layout.attributes().iter()
+            .map(|a| (a.location, a.binding, a.offset))
+            .collect();
+        assert_eq!(attributes, vec![
+            (0, 0, 0), (1, 0, 12),
+            (2, 1, 0), (3, 1, 16), (4, 1, 32), (5, 1, 48),
+            (6, 1, 64),
+        ]);
+        assert_eq!(layout.next_location(), 7);
+    }
+}
@@ -0,0 +1,635 @@
+//! Viewport layouts and per-viewport cameras
+//!
+//! The SDF pass can draw the scene once (single view) or four times into the
+//! quadrants of the offscreen target (quad view: top, front, side and
+//! perspective). Each viewport has its own camera, render mode, clear color
+//! and shown render layers; cameras are passed to the shaders as an origin
+//! and a forward direction, with an orthographic half height for the axis
+//! views; headset eyes add a roll and their own off-axis field of view
+//! (`ViewFov`). The clear colors are applied when the scene pass begins, every
+//! frame.
+//!
+//! Stills larger than a render target are drawn in `Tile`s: every tile keeps
+//! the viewport rectangles of the whole image, shifted by the tile's offset,
+//! and clips the draws to its own area.
+
+use ash::vk;
+use cgmath::{InnerSpace, Vector3};
+use crate::config;
+
+/// How a viewport shades the scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Full lighting
+    Shaded,
+    /// Surface normals as colors
+    Normals,
+    /// Hit distance as grayscale
+    Depth,
+    /// Raymarch steps per pixel as a heatmap, relative to the quality's step limit
+    Iterations,
+    /// Distance the ray travelled (hit or miss) as a heatmap
+    Distance,
+}
+
+impl RenderMode {
+    /// All render modes in display order
+    pub const ALL: [RenderMode; 5] = [
+        RenderMode::Shaded,
+        RenderMode::Normals,
+        RenderMode::Depth,
+        RenderMode::Iterations,
+        RenderMode::Distance,
+    ];
+
+    /// Name shown in the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Shaded => "Shaded",
+            RenderMode::Normals => "Normals",
+            RenderMode::Depth => "Depth",
+            RenderMode::Iterations => "Iterations",
+            RenderMode::Distance => "Distance",
+        }
+    }
+
+    /// Value of `renderMode` in `shaders/sdf.frag`
+    pub fn shader_value(self) -> i32 {
+        match self {
+            RenderMode::Shaded => 0,
+            RenderMode::Normals => 1,
+            RenderMode::Depth => 2,
+            RenderMode::Iterations => 3,
+            RenderMode::Distance => 4,
+        }
+    }
+
+    /// Whether the mode draws a heatmap instead of surface colors
+    pub fn is_heatmap(self) -> bool {
+        matches!(self, RenderMode::Iterations | RenderMode::Distance)
+    }
+}
+
+/// Which camera a viewport uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewKind {
+    /// Orthographic, looking down the -Y axis
+    Top,
+    /// Orthographic, looking along +Z (same direction as the perspective camera)
+    Front,
+    /// Orthographic, looking along -X
+    Side,
+    /// The main perspective camera
+    Perspective,
+}
+
+impl ViewKind {
+    /// Name shown in the HUD
+    pub fn name(self) -> &'static str {
+        match self {
+            ViewKind::Top => "Top",
+            ViewKind::Front => "Front",
+            ViewKind::Side => "Side",
+            ViewKind::Perspective => "Perspective",
+        }
+    }
+
+    /// Default camera of this view
+    pub fn camera(self) -> ViewCamera {
+        let distance = config::viewports::ORTHO_DISTANCE;
+        let half_height = config::viewports::ORTHO_HALF_HEIGHT;
+        match self {
+            ViewKind::Top => ViewCamera::orthographic([0.0, distance, 0.0], Vector3::new(0.0, -1.0, 0.0), half_height),
+            ViewKind::Front => ViewCamera::orthographic([0.0, 0.0, -distance], Vector3::new(0.0, 0.0, 1.0), half_height),
+            ViewKind::Side => ViewCamera::orthographic([distance, 0.0, 0.0], Vector3::new(-1.0, 0.0, 0.0), half_height),
+            ViewKind::Perspective => ViewCamera::default(),
+        }
+    }
+}
+
+/// Field of view of a headset eye, which is usually off-axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewFov {
+    /// Tangent of the angle to the left edge (negative left of the forward axis)
+    pub left: f32,
+    /// Tangent of the angle to the right edge
+    pub right: f32,
+    /// Tangent of the angle to the edge along the camera's up axis
+    pub up: f32,
+    /// Tangent of the angle to the opposite edge (negative below the forward axis)
+    pub down: f32,
+}
+
+impl ViewFov {
+    /// Field of view from the edge angles in radians, as OpenXR reports them
+    pub fn from_angles(left: f32, right: f32, up: f32, down: f32) -> Self {
+        Self { left: left.tan(), right: right.tan(), up: up.tan(), down: down.tan() }
+    }
+}
+
+/// Camera of one viewport as seen by the shaders
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewCamera {
+    /// Ray origin (perspective) or center of the view plane (orthographic)
+    pub origin: [f32; 3],
+    /// Normalized view direction
+    pub forward: [f32; 3],
+    /// Half height of the view in world units, 0 for perspective
+    pub ortho_half_height: f32,
+    /// Rotation about the forward axis in radians, 0 keeps the horizon level
+    pub roll: f32,
+    /// Field of view of a headset eye; None for the raymarcher's fixed 90
+    /// degrees vertically, widened to the viewport's aspect ratio
+    pub fov: Option<ViewFov>,
+}
+
+impl Default for ViewCamera {
+    /// The fixed perspective camera of the raymarcher
+    fn default() -> Self {
+        Self {
+            origin: config::rendering::SDF_CAMERA_ORIGIN,
+            forward: [0.0, 0.0, 1.0],
+            ortho_half_height: 0.0,
+            roll: 0.0,
+            fov: None,
+        }
+    }
+}
+
+impl ViewCamera {
+    /// Create an orthographic camera
+    pub fn orthographic(origin: [f32; 3], forward: Vector3<f32>, half_height: f32) -> Self {
+        let forward = forward.normalize();
+        Self {
+            origin,
+            forward: [forward.x, forward.y, forward.z],
+            ortho_half_height: half_height,
+            ..Self::default()
+        }
+    }
+
+    /// Create a perspective camera with a given orientation
+    ///
+    /// # Arguments
+    /// * `origin` - Ray origin
+    /// * `forward` - View direction
+    /// * `right` - Right axis, which may be rolled against the horizon
+    pub fn oriented(origin: [f32; 3], forward: Vector3<f32>, right: Vector3<f32>) -> Self {
+        let forward = forward.normalize();
+        let mut camera = Self { origin, forward: [forward.x, forward.y, forward.z], ..Self::default() };
+        let (level_right, level_up, _) = camera.basis();
+        camera.roll = right.dot(level_up).atan2(right.dot(level_right));
+        camera
+    }
+
+    /// Origin as a shader vec4 (w = orthographic half height, 0 for perspective)
+    pub fn origin_vec4(&self) -> [f32; 4] {
+        [self.origin[0], self.origin[1], self.origin[2], self.ortho_half_height]
+    }
+
+    /// Forward direction as a shader vec4 (w = roll)
+    pub fn forward_vec4(&self) -> [f32; 4] {
+        [self.forward[0], self.forward[1], self.forward[2], self.roll]
+    }
+
+    /// Extent of the view as a shader vec4, for a viewport's aspect ratio
+    ///
+    /// xy = half width and half height, zw = center, at distance one
+    /// (perspective) or in orthographic half heights.
+    pub fn lens(&self, aspect: f32) -> [f32; 4] {
+        match self.fov {
+            Some(fov) => [
+                (fov.right - fov.left) * 0.5,
+                (fov.up - fov.down) * 0.5,
+                (fov.right + fov.left) * 0.5,
+                (fov.up + fov.down) * 0.5,
+            ],
+            None => [aspect, 1.0, 0.0, 0.0],
+        }
+    }
+
+    /// Right, up and forward axes of the camera, as the raymarcher builds them
+    ///
+    /// The top view looks along Y so it uses Z as up; the roll then turns
+    /// right and up about the forward axis.
+    pub fn basis(&self) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+        let forward = Vector3::from(self.forward).normalize();
+        let world_up = if forward.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+        let level_right = world_up.cross(forward).normalize();
+        let level_up = forward.cross(level_right);
+        let right = level_right * self.roll.cos() + level_up * self.roll.sin();
+        (right, forward.cross(right), forward)
+    }
+
+    /// Ray through a pixel of a viewport rectangle, the inverse of `project`
+    ///
+    /// # Arguments
+    /// * `pixel` - Pixel position
+    /// * `rect` - Viewport rectangle in pixels
+    ///
+    /// # Returns
+    /// Origin and normalized direction of the ray
+    pub fn ray(&self, pixel: [f32; 2], rect: &vk::Rect2D) -> (Vector3<f32>, Vector3<f32>) {
+        let (right, up, forward) = self.basis();
+        let [half_width, half_height, center_x, center_y] = self.lens(rect_aspect(rect));
+        let ndc_x = (pixel[0] - rect.offset.x as f32) / rect.extent.width.max(1) as f32 * 2.0 - 1.0;
+        let ndc_y = (pixel[1] - rect.offset.y as f32) / rect.extent.height.max(1) as f32 * 2.0 - 1.0;
+        let (x, y) = (center_x + ndc_x * half_width, center_y + ndc_y * half_height);
+        let origin = Vector3::from(self.origin);
+        if self.ortho_half_height > 0.0 {
+            (origin + (right * x + up * y) * self.ortho_half_height, forward)
+        } else {
+            (origin, (forward + right * x + up * y).normalize())
+        }
+    }
+
+    /// Project a world space point into a viewport rectangle
+    ///
+    /// Uses the same camera basis as the raymarcher and `debug_line.vert`.
+    ///
+    /// # Arguments
+    /// * `point` - World space point
+    /// * `rect` - Viewport rectangle in pixels
+    ///
+    /// # Returns
+    /// Pixel position, or None if the point is behind the camera or outside the rectangle
+    pub fn project(&self, point: Vector3<f32>, rect: &vk::Rect2D) -> Option<[f32; 2]> {
+        let (right, up, forward) = self.basis();
+        let q = point - Vector3::from(self.origin);
+        let (x, y, z) = (q.dot(right), q.dot(up), q.dot(forward));
+        let [half_width, half_height, center_x, center_y] = self.lens(rect_aspect(rect));
+        let (x, y) = if self.ortho_half_height > 0.0 {
+            (x / self.ortho_half_height, y / self.ortho_half_height)
+        } else if z > 0.0 {
+            (x / z, y / z)
+        } else {
+            return None;
+        };
+        let ndc = [(x - center_x) / half_width, (y - center_y) / half_height];
+        if ndc.iter().any(|c| c.abs() > 1.0) {
+            return None;
+        }
+
+        Some([
+            rect.offset.x as f32 + (ndc[0] * 0.5 + 0.5) * rect.extent.width as f32,
+            rect.offset.y as f32 + (ndc[1] * 0.5 + 0.5) * rect.extent.height as f32,
+        ])
+    }
+}
+
+/// One viewport of the layout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Camera used by the viewport
+    pub kind: ViewKind,
+    /// Shading of the viewport
+    pub render_mode: RenderMode,
+    /// Color the viewport is cleared to before the scene is drawn, and the
+    /// background of the solid color mode (linear RGBA)
+    pub clear_color: [f32; 4],
+    /// Mask of the render layers the viewport shows, see `RenderLayers`
+    pub visible_layers: u32,
+}
+
+impl Viewport {
+    /// Create a shaded viewport with the default clear color, showing every layer
+    pub fn new(kind: ViewKind) -> Self {
+        Self {
+            kind,
+            render_mode: RenderMode::Shaded,
+            clear_color: config::rendering::CLEAR_COLOR,
+            visible_layers: config::layers::ALL,
+        }
+    }
+}
+
+/// Single or 2x2 viewport layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewportLayout {
+    /// One perspective view
+    Single,
+    /// Top, front, side and perspective views in a 2x2 grid
+    Quad,
+}
+
+/// Reference grid on the Y=0 plane and world axes, drawn by the SDF shader
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridSettings {
+    /// Whether the grid lines are drawn
+    pub show_grid: bool,
+    /// Whether the X (red), Y (green) and Z (blue) axes are drawn
+    pub show_axes: bool,
+    /// World units between grid lines
+    pub spacing: f32,
+    /// Distance from the camera at which the grid and axes have faded out
+    pub fade_distance: f32,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            show_grid: config::grid::SHOW_BY_DEFAULT,
+            show_axes: config::grid::SHOW_BY_DEFAULT,
+            spacing: config::grid::DEFAULT_SPACING,
+            fade_distance: config::grid::DEFAULT_FADE_DISTANCE,
+        }
+    }
+}
+
+impl GridSettings {
+    /// Value of `gridFlags` in `shaders/sdf.frag` (bit 0 = grid, bit 1 = axes)
+    pub fn shader_flags(&self) -> i32 {
+        i32::from(self.show_grid) | (i32::from(self.show_axes) << 1)
+    }
+}
+
+/// Layout and per-viewport settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportSettings {
+    /// Current layout
+    pub layout: ViewportLayout,
+    /// Quad view viewports in reading order (top-left, top-right, bottom-left, bottom-right);
+    /// the last one is also the single view
+    pub viewports: [Viewport; 4],
+    /// Reference grid and axes shown in every viewport
+    pub grid: GridSettings,
+    /// Camera of the perspective view, turned with mouse look
+    pub perspective: ViewCamera,
+}
+
+impl Default for ViewportSettings {
+    fn default() -> Self {
+        Self {
+            layout: if config::viewports::QUAD_VIEW_BY_DEFAULT { ViewportLayout::Quad } else { ViewportLayout::Single },
+            viewports: [
+                Viewport::new(ViewKind::Top),
+                Viewport::new(ViewKind::Front),
+                Viewport::new(ViewKind::Side),
+                Viewport::new(ViewKind::Perspective),
+            ],
+            grid: GridSettings::default(),
+            perspective: ViewCamera::default(),
+        }
+    }
+}
+
+impl ViewportSettings {
+    /// Camera of a view; the perspective view uses the mouse look camera
+    pub fn camera(&self, kind: ViewKind) -> ViewCamera {
+        match kind {
+            ViewKind::Perspective => self.perspective,
+            _ => kind.camera(),
+        }
+    }
+
+    /// Render layers shown by at least one viewport of the current layout
+    pub fn visible_layers(&self) -> u32 {
+        let first = if self.layout == ViewportLayout::Quad { 0 } else { 3 };
+        self.viewports[first..].iter().fold(0, |mask, viewport| mask | viewport.visible_layers)
+    }
+
+    /// Rectangles of the viewports inside `extent` with their clear colors
+    pub fn clears(&self, extent: vk::Extent2D) -> Vec<(vk::Rect2D, [f32; 4])> {
+        self.rects(extent).into_iter().map(|(rect, viewport)| (rect, viewport.clear_color)).collect()
+    }
+
+    /// Viewports to draw with their rectangles inside `extent`
+    ///
+    /// # Arguments
+    /// * `extent` - Size of the area the layout covers
+    pub fn rects(&self, extent: vk::Extent2D) -> Vec<(vk::Rect2D, Viewport)> {
+        match self.layout {
+            ViewportLayout::Single => vec![(
+                vk::Rect2D { offset: vk::Offset2D { x: 0, y: 0 }, extent },
+                self.viewports[3],
+            )],
+            ViewportLayout::Quad => {
+                let half_width = (extent.width / 2).max(1);
+                let half_height = (extent.height / 2).max(1);
+                self.viewports
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &viewport)| {
+                        let column = (i % 2) as u32;
+                        let row = (i / 2) as u32;
+                        // The right column and bottom row take the odd pixel
+                        let width = if column == 0 { half_width } else { extent.width - half_width };
+                        let height = if row == 0 { half_height } else { extent.height - half_height };
+                        let rect = vk::Rect2D {
+                            offset: vk::Offset2D { x: (column * half_width) as i32, y: (row * half_height) as i32 },
+                            extent: vk::Extent2D { width: width.max(1), height: height.max(1) },
+                        };
+                        (rect, viewport)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Area of the image a render target covers
+///
+/// Realtime frames and exports that fit a target use a single full tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// Top-left corner of the tile in the image
+    pub offset: vk::Offset2D,
+    /// Size of the tile, at most the size of the target
+    pub extent: vk::Extent2D,
+}
+
+impl Tile {
+    /// Tile covering a whole image
+    pub fn full(extent: vk::Extent2D) -> Self {
+        Self { offset: vk::Offset2D { x: 0, y: 0 }, extent }
+    }
+
+    /// Vulkan viewport of an image rectangle, relative to the tile
+    ///
+    /// Keeps the size of the rectangle so the view does not change; the
+    /// viewport may reach past the tile's edges.
+    pub fn viewport(&self, rect: &vk::Rect2D) -> vk::Viewport {
+        vk::Viewport {
+            x: (rect.offset.x - self.offset.x) as f32,
+            y: (rect.offset.y - self.offset.y) as f32,
+            width: rect.extent.width as f32,
+            height: rect.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
+
+    /// Part of an image rectangle inside the tile, relative to the tile
+    ///
+    /// # Returns
+    /// The scissor rectangle, or None if the rectangle misses the tile
+    pub fn scissor(&self, rect: vk::Rect2D) -> Option<vk::Rect2D> {
+        let left = rect.offset.x.max(self.offset.x);
+        let top = rect.offset.y.max(self.offset.y);
+        let right = (rect.offset.x + rect.extent.width as i32).min(self.offset.x + self.extent.width as i32);
+        let bottom = (rect.offset.y + rect.extent.height as i32).min(self.offset.y + self.extent.height as i32);
+        (right > left && bottom > top).then(|| vk::Rect2D {
+            offset: vk::Offset2D { x: left - self.offset.x, y: top - self.offset.y },
+            extent: vk::Extent2D { width: (right - left) as u32, height: (bottom - top) as u32 },
+        })
+    }
+}
+
+/// Split an image into tiles of at most `max_tile` pixels per side
+///
+/// # Returns
+/// The tiles in rows from top to bottom; one full tile if the image fits
+pub fn tiles(extent: vk::Extent2D, max_tile: u32) -> Vec<Tile> {
+    let max_tile = max_tile.max(1);
+    let mut tiles = Vec::new();
+    for y in (0..extent.height).step_by(max_tile as usize) {
+        for x in (0..extent.width).step_by(max_tile as usize) {
+            tiles.push(Tile {
+                offset: vk::Offset2D { x: x as i32, y: y as i32 },
+                extent: vk::Extent2D { width: max_tile.min(extent.width - x), height: max_tile.min(extent.height - y) },
+            });
+        }
+    }
+    tiles
+}
+
+/// Aspect ratio of a rectangle
+pub fn rect_aspect(rect: &vk::Rect2D) -> f32 {
+    rect.extent.width as f32 / rect.extent.height.max(1) as f32
+}
+
+/// Whether a pixel position lies inside a rectangle
+pub fn rect_contains(rect: &vk::Rect2D, pixel: [f32; 2]) -> bool {
+    let (x, y) = (pixel[0] - rect.offset.x as f32, pixel[1] - rect.offset.y as f32);
+    (0.0..rect.extent.width as f32).contains(&x) && (0.0..rect.extent.height as f32).contains(&y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quad_rects_cover_extent() {
+        let settings = ViewportSettings { layout: ViewportLayout::Quad, ..ViewportSettings::default() };
+        let rects = settings.rects(vk::Extent2D { width: 801, height: 601 });
+        assert_eq!(rects.len(), 4);
+
+        let area: u32 = rects.iter().map(|(rect, _)| rect.extent.width * rect.extent.height).sum();
+        assert_eq!(area, 801 * 601);
+        assert_eq!(rects[3].0.offset, vk::Offset2D { x: 400, y: 300 });
+        assert_eq!(rects[3].1.kind, ViewKind::Perspective);
+
+        let mut settings = settings;
+        settings.viewports[1].clear_color = [0.2, 0.0, 0.0, 1.0];
+        let clears = settings.clears(vk::Extent2D { width: 801, height: 601 });
+        assert_eq!(clears[0], (rects[0].0, config::rendering::CLEAR_COLOR));
+        assert_eq!(clears[1], (rects[1].0, [0.2, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn test_single_view_uses_perspective_camera() {
+        let settings = ViewportSettings { layout: ViewportLayout::Single, ..ViewportSettings::default() };
+        let rects = settings.rects(vk::Extent2D { width: 800, height: 600 });
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].1.kind.camera(), ViewCamera::default());
+        assert_eq!(ViewKind::Top.camera().forward, [0.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_project_matches_camera_axes() {
+        let rect = vk::Rect2D { offset: vk::Offset2D { x: 100, y: 0 }, extent: vk::Extent2D { width: 200, height: 200 } };
+        let camera = ViewCamera::default();
+        assert_eq!(camera.project(Vector3::new(0.0, 0.0, 0.0), &rect), Some([200.0, 100.0]));
+        // Perspective: one unit to the side at distance two lands halfway to the edge
+        assert_eq!(camera.project(Vector3::new(1.0, 0.0, 0.0), &rect), Some([250.0, 100.0]));
+        assert_eq!(camera.project(Vector3::new(0.0, 0.0, -3.0), &rect), None);
+
+        let front = ViewKind::Front.camera();
+        let half_height = config::viewports::ORTHO_HALF_HEIGHT;
+        assert_eq!(front.project(Vector3::new(0.0, half_height * 0.5, 5.0), &rect), Some([200.0, 150.0]));
+        assert_eq!(front.project(Vector3::new(half_height * 2.0, 0.0, 0.0), &rect), None);
+    }
+
+    #[test]
+    fn test_rays_go_through_projected_points() {
+        let rect = vk::Rect2D { offset: vk::Offset2D { x: 100, y: 50 }, extent: vk::Extent2D { width: 300, height: 200 } };
+        let point = Vector3::new(0.4, -0.3, 1.0);
+        let eye = ViewCamera {
+            fov: Some(ViewFov::from_angles(-0.9, 0.7, 0.8, -0.6)),
+            ..ViewCamera::oriented([0.1, 0.0, -1.0], Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.3, 0.0))
+        };
+        for camera in [ViewCamera::default(), ViewKind::Front.camera(), ViewKind::Top.camera(), eye] {
+            let pixel = camera.project(point, &rect).unwrap();
+            assert!(rect_contains(&rect, pixel));
+            let (origin, direction) = camera.ray(pixel, &rect);
+            let to_point = point - origin;
+            let off_ray = to_point - direction * to_point.dot(direction);
+            assert!(off_ray.magnitude() < 1e-4, "{:?} misses by {:?}", camera, off_ray);
+        }
+        assert!(!rect_contains(&rect, [99.0, 60.0]));
+    }
+
+    #[test]
+    fn test_oriented_camera_keeps_its_roll() {
+        let right = Vector3::new(1.0, 1.0, 0.0).normalize();
+        let camera = ViewCamera::oriented([0.0; 3], Vector3::new(0.0, 0.0, 1.0), right);
+        assert!((camera.roll - std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+        assert!((camera.basis().0 - right).magnitude() < 1e-5);
+        // A level camera sees the raymarcher's field of view, an eye its own
+        assert_eq!(ViewCamera::default().lens(2.0), [2.0, 1.0, 0.0, 0.0]);
+        let eye = ViewCamera { fov: Some(ViewFov { left: -1.0, right: 0.5, up: 1.0, down: -1.0 }), ..camera };
+        assert_eq!(eye.lens(2.0), [0.75, 1.0, -0.25, 0.0]);
+    }
+
+    #[test]
+    fn test_tiles_cover_image_and_clip_viewports() {
+        let extent = vk::Extent2D { width: 5000, height: 3000 };
+        let tiles = tiles(extent, 2048);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles.iter().map(|tile| tile.extent.width * tile.extent.height).sum::<u32>(), 5000 * 3000);
+        assert_eq!(tiles[5], Tile { offset: vk::Offset2D { x: 4096, y: 2048 }, extent: vk::Extent2D { width: 904, height: 952 } });
+        assert_eq!(super::tiles(extent, 8192), vec![Tile::full(extent)]);
+
+        // The bottom-right quadrant seen from the second tile of the first row
+        let rect = vk::Rect2D { offset: vk::Offset2D { x: 2500, y: 1500 }, extent: vk::Extent2D { width: 2500, height: 1500 } };
+        let viewport = tiles[1].viewport(&rect);
+        assert_eq!((viewport.x, viewport.y, viewport.width), (452.0, 1500.0, 2500.0));
+        assert_eq!(
+            tiles[1].scissor(rect),
+            Some(vk::Rect2D { offset: vk::Offset2D { x: 452, y: 1500 }, extent: vk::Extent2D { width: 1596, height: 548 } })
+        );
+        assert_eq!(tiles[0].scissor(rect), None);
+    }
+
+    #[test]
+    fn test_render_mode_shader_values_are_distinct() {
+        for (index, mode) in RenderMode::ALL.iter().enumerate() {
+            assert_eq!(mode.shader_value(), index as i32);
+        }
+        assert!(RenderMode::Iterations.is_heatmap());
+        assert!(!RenderMode::Depth.is_heatmap());
+    }
+
+    #[test]
+    fn test_grid_shader_flags() {
+        let grid = GridSettings { show_grid: true, show_axes: false, ..GridSettings::default() };
+        assert_eq!(grid.shader_flags(), 1);
+        assert_eq!(GridSettings { show_grid: false, show_axes: true, ..grid }.shader_flags(), 2);
+        assert_eq!(GridSettings { show_axes: true, ..grid }.shader_flags(), 3);
+    }
+
+    #[test]
+    fn test_visible_layers_of_the_drawn_viewports() {
+        let mut settings = ViewportSettings { layout: ViewportLayout::Single, ..ViewportSettings::default() };
+        assert_eq!(settings.visible_layers(), config::layers::ALL);
+        settings.viewports[0].visible_layers = 0b100;
+        settings.viewports[3].visible_layers = 0b001;
+        // The single view draws only the perspective viewport
+        assert_eq!(settings.visible_layers(), 0b001);
+        settings.layout = ViewportLayout::Quad;
+        assert_eq!(settings.visible_layers(), config::layers::ALL);
+        settings.viewports[1].visible_layers = 0;
+        settings.viewports[2].visible_layers = 0;
+        assert_eq!(settings.visible_layers(), 0b101);
+    }
+}
@@ -0,0 +1,788 @@
+//! OpenXR headset rendering (`--xr`, built with the `xr` feature)
+//!
+//! The OpenXR runtime decides which GPU drives the headset and which Vulkan
+//! instance and device extensions it needs (`XR_KHR_vulkan_enable`), so an
+//! `XrRuntime` is created before the renderer and `VulkanRenderer::new_xr`
+//! builds the instance and device with them. `XrSession` then shares that
+//! device: every frame it waits for the headset, locates the eyes and the
+//! controllers, and the renderer draws each eye through the offscreen SDF
+//! target and the composite pass into the runtime's swapchain for that eye,
+//! like a window frame. The window keeps showing the scene from the left eye.
+//!
+//! The tracking space is anchored at the mouse look camera and turned by its
+//! yaw, so the camera still moves the viewer around the scene. Each eye is
+//! drawn with the runtime's pose, including its roll, and its off-axis field
+//! of view (`ViewFov`), so the images line up with the headset's lenses; the
+//! window mirror keeps the roll but its own field of view. Controller grip
+//! poses and select buttons are read through one action set and handed to
+//! the ECS world as the `XrInput` resource.
+
+use std::ffi::CString;
+use ash::vk::{self, Handle};
+use ash::{Device, Instance};
+use cgmath::{Quaternion, Rad, Rotation, Rotation3, Vector3};
+use log::{debug, info, warn};
+use openxr as xr;
+use crate::camera::LookCamera;
+use crate::config;
+use crate::error::{AppError, Result, ResultExt, Subsystem, VkResultExt, VulkanError};
+use crate::vulkan::depth::{DepthBuffer, DepthUsage};
+use crate::vulkan::device::VulkanDevice;
+use crate::vulkan::gpu_selection::GpuSelector;
+use crate::vulkan::rendering::{PassLayout, PassTarget};
+use crate::vulkan::viewport::{ViewCamera, ViewFov};
+
+/// Views of a head mounted display, one per eye
+const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
+
+/// Number of eyes, each with its own swapchain
+pub const EYES: usize = 2;
+
+/// Position and orientation in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XrPose {
+    /// Position in world units
+    pub position: [f32; 3],
+    /// Orientation as a quaternion (x, y, z, w); identity looks along +Z
+    pub orientation: [f32; 4],
+}
+
+impl XrPose {
+    /// Map a pose of the tracking space into the world
+    ///
+    /// OpenXR is right-handed and looks along -Z; the world looks along +Z,
+    /// so Z is mirrored before the anchor's yaw and position are applied.
+    ///
+    /// # Arguments
+    /// * `position` - Position in the tracking space in meters
+    /// * `orientation` - Orientation in the tracking space (x, y, z, w)
+    /// * `anchor` - Camera the tracking space is placed at
+    pub fn from_tracking(position: [f32; 3], orientation: [f32; 4], anchor: &LookCamera) -> Self {
+        let yaw = Quaternion::from_angle_y(Rad(anchor.yaw));
+        let [x, y, z] = position;
+        let offset = yaw.rotate_vector(Vector3::new(x, y, -z));
+        let [qx, qy, qz, qw] = orientation;
+        let rotation = yaw * Quaternion::new(qw, -qx, -qy, qz);
+        Self {
+            position: [anchor.origin[0] + offset.x, anchor.origin[1] + offset.y, anchor.origin[2] + offset.z],
+            orientation: [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s],
+        }
+    }
+
+    /// Normalized direction the pose looks or points along
+    pub fn forward(&self) -> Vector3<f32> {
+        self.rotation().rotate_vector(Vector3::unit_z())
+    }
+
+    /// Perspective camera at the pose, rolled with it, with the raymarcher's field of view
+    pub fn view_camera(&self) -> ViewCamera {
+        ViewCamera::oriented(self.position, self.forward(), self.rotation().rotate_vector(Vector3::unit_x()))
+    }
+
+    fn rotation(&self) -> Quaternion<f32> {
+        let [x, y, z, w] = self.orientation;
+        Quaternion::new(w, x, y, z)
+    }
+}
+
+/// State of one controller
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct XrHand {
+    /// Grip pose, None while the controller is not tracked
+    pub pose: Option<XrPose>,
+    /// Whether the select button (usually the trigger) is held
+    pub select: bool,
+}
+
+/// Headset and controllers of the latest XR frame, an ECS resource
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct XrInput {
+    /// Pose of the left eye, the view mirrored in the window
+    pub head: Option<XrPose>,
+    /// Left and right controller
+    pub hands: [XrHand; 2],
+}
+
+/// OpenXR instance and headset, created before the Vulkan device
+pub struct XrRuntime {
+    instance: xr::Instance,
+    system: xr::SystemId,
+}
+
+impl XrRuntime {
+    /// Load the OpenXR runtime and find a head mounted display
+    ///
+    /// # Errors
+    /// Returns an error if no runtime is installed, it lacks Vulkan support
+    /// or no headset is connected
+    pub fn new() -> Result<Self> {
+        info!("Loading OpenXR runtime");
+        let entry = unsafe { xr::Entry::load() }
+            .map_err(|e| AppError::Generic(format!("Failed to load the OpenXR loader: {}", e)))?;
+        let available = entry.enumerate_extensions().context(Subsystem::Renderer, "enumerate OpenXR extensions")?;
+        if !available.khr_vulkan_enable {
+            return Err(AppError::Generic("The OpenXR runtime does not support Vulkan (XR_KHR_vulkan_enable)".to_string()));
+        }
+        let mut extensions = xr::ExtensionSet::default();
+        extensions.khr_vulkan_enable = true;
+        let instance = entry.create_instance(
+            &xr::ApplicationInfo {
+                application_name: config::vulkan::APP_NAME,
+                application_version: config::vulkan::APP_VERSION,
+                engine_name: config::vulkan::ENGINE_NAME,
+                engine_version: config::vulkan::ENGINE_VERSION,
+                api_version: xr::Version::new(1, 0, 0),
+            },
+            &extensions,
+            &[],
+        ).context(Subsystem::Renderer, "create OpenXR instance")?;
+        let properties = instance.properties().context(Subsystem::Renderer, "read OpenXR runtime properties")?;
+        info!("OpenXR runtime {} {}", properties.runtime_name, properties.runtime_version);
+
+        let system = instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+            .context(Subsystem::Renderer, "find a headset")?;
+        Ok(Self { instance, system })
+    }
+
+    /// Vulkan instance extensions the runtime needs
+    ///
+    /// # Errors
+    /// Returns an error if the runtime cannot be queried
+    pub fn instance_extensions(&self) -> Result<Vec<CString>> {
+        let names = self.instance.vulkan_legacy_instance_extensions(self.system)
+            .context(Subsystem::Renderer, "query OpenXR Vulkan instance extensions")?;
+        Ok(extension_list(&names))
+    }
+
+    /// Vulkan device extensions the runtime needs
+    ///
+    /// # Errors
+    /// Returns an error if the runtime cannot be queried
+    pub fn device_extensions(&self) -> Result<Vec<CString>> {
+        let names = self.instance.vulkan_legacy_device_extensions(self.system)
+            .context(Subsystem::Renderer, "query OpenXR Vulkan device extensions")?;
+        Ok(extension_list(&names))
+    }
+
+    /// Check the Vulkan version against the range the runtime supports
+    ///
+    /// # Arguments
+    /// * `api_version` - Version the Vulkan instance was created with
+    ///
+    /// # Errors
+    /// Returns an error if the version is older than the runtime's minimum
+    pub fn check_api_version(&self, api_version: u32) -> Result<()> {
+        let requirements = self.instance.graphics_requirements::<xr::Vulkan>(self.system)
+            .context(Subsystem::Renderer, "query OpenXR Vulkan requirements")?;
+        let min = requirements.min_api_version_supported;
+        let version = xr::Version::new(
+            vk::api_version_major(api_version) as u16,
+            vk::api_version_minor(api_version) as u16,
+            0,
+        );
+        if version < xr::Version::new(min.major(), min.minor(), 0) {
+            return Err(AppError::Generic(format!(
+                "The OpenXR runtime needs Vulkan {}.{}, the instance has {}.{}",
+                min.major(), min.minor(), version.major(), version.minor()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Physical device the headset is connected to
+    ///
+    /// # Arguments
+    /// * `instance` - Vulkan instance created with `instance_extensions`
+    ///
+    /// # Errors
+    /// Returns an error if the runtime cannot be queried
+    pub fn physical_device(&self, instance: &Instance) -> Result<vk::PhysicalDevice> {
+        let physical_device = unsafe {
+            self.instance.vulkan_graphics_device(self.system, instance.handle().as_raw() as _)
+        }.context(Subsystem::Renderer, "query the headset's GPU")?;
+        Ok(vk::PhysicalDevice::from_raw(physical_device as u64))
+    }
+
+    /// Selector that makes device selection pick the headset's GPU
+    ///
+    /// # Arguments
+    /// * `instance` - Vulkan instance created with `instance_extensions`
+    ///
+    /// # Errors
+    /// Returns an error if the runtime names a device the instance does not list
+    pub fn gpu_selector(&self, instance: &Instance) -> Result<GpuSelector> {
+        let physical_device = self.physical_device(instance)?;
+        let devices = unsafe { instance.enumerate_physical_devices() }
+            .vk_context(Subsystem::Renderer, "enumerate physical devices")?;
+        devices.iter()
+            .position(|&device| device == physical_device)
+            .map(GpuSelector::Index)
+            .ok_or_else(|| VulkanError::DeviceCreation("The OpenXR runtime named an unknown GPU".to_string()).into())
+    }
+}
+
+/// Size of the offscreen target area an eye is drawn at
+///
+/// Keeps the eye's aspect ratio, shrunk to fit the target if it is smaller.
+///
+/// # Arguments
+/// * `eye` - Resolution of the eye
+/// * `target` - Size of the offscreen SDF target
+pub fn fit_extent(eye: vk::Extent2D, target: vk::Extent2D) -> vk::Extent2D {
+    let scale = (target.width as f32 / eye.width as f32)
+        .min(target.height as f32 / eye.height as f32)
+        .min(1.0);
+    vk::Extent2D {
+        width: ((eye.width as f32 * scale).round() as u32).clamp(1, target.width),
+        height: ((eye.height as f32 * scale).round() as u32).clamp(1, target.height),
+    }
+}
+
+/// Split the space separated extension list of `XR_KHR_vulkan_enable`
+fn extension_list(names: &str) -> Vec<CString> {
+    names.split_whitespace().filter_map(|name| CString::new(name).ok()).collect()
+}
+
+/// Swapchain of one eye with a framebuffer per image
+struct EyeSwapchain {
+    swapchain: xr::Swapchain<xr::Vulkan>,
+    images: Vec<vk::Image>,
+    views: Vec<vk::ImageView>,
+    framebuffers: Vec<vk::Framebuffer>,
+}
+
+/// An XR frame being rendered
+pub struct XrFrame {
+    state: xr::FrameState,
+    views: Vec<xr::View>,
+    /// World space cameras of the left and right eye
+    pub eyes: [ViewCamera; EYES],
+    /// Headset and controllers at the predicted display time
+    pub input: XrInput,
+}
+
+/// Running OpenXR session sharing the renderer's Vulkan device
+pub struct XrSession {
+    session: xr::Session<xr::Vulkan>,
+    frame_waiter: xr::FrameWaiter,
+    frame_stream: xr::FrameStream<xr::Vulkan>,
+    space: xr::Space,
+    action_set: xr::ActionSet,
+    grip_action: xr::Action<xr::Posef>,
+    select_action: xr::Action<bool>,
+    hand_paths: [xr::Path; 2],
+    /// Spaces following the grip pose of each hand
+    hand_spaces: [xr::Space; 2],
+    eyes: Vec<EyeSwapchain>,
+    /// Resolution of each eye
+    extent: vk::Extent2D,
+    depth: DepthBuffer,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    queue: vk::Queue,
+    blend_mode: xr::EnvironmentBlendMode,
+    /// Whether the runtime asked for frames (between READY and STOPPING)
+    running: bool,
+    /// Whether the runtime is closing the session, e.g. the headset was removed from the app
+    exiting: bool,
+    /// Kept alive for the session; destroyed after it
+    instance: xr::Instance,
+    device: Device,
+}
+
+impl XrSession {
+    /// Create the session, eye swapchains and controller actions
+    ///
+    /// The eye swapchains use the window's format, so the composite pass
+    /// draws into them unchanged; the window must use an sRGB format.
+    ///
+    /// # Arguments
+    /// * `runtime` - The runtime the instance and device were created for
+    /// * `instance` - The Vulkan instance
+    /// * `device` - The Vulkan device on the headset's GPU
+    /// * `present_pass` - Pass the composite pipeline draws in
+    /// * `format` - Format of the window's swapchain
+    ///
+    /// # Errors
+    /// Returns an error if the runtime rejects the device or the format, or
+    /// any OpenXR or Vulkan object cannot be created
+    pub fn new(
+        runtime: XrRuntime,
+        instance: &Instance,
+        device: &VulkanDevice,
+        present_pass: &PassLayout,
+        format: vk::Format,
+    ) -> Result<Self> {
+        let XrRuntime { instance: xr_instance, system } = runtime;
+        let queue_family_index = device.queue_families.graphics_family.unwrap_or(0);
+        let (session, frame_waiter, frame_stream) = unsafe {
+            xr_instance.create_session::<xr::Vulkan>(system, &xr::vulkan::SessionCreateInfo {
+                instance: instance.handle().as_raw() as _,
+                physical_device: device.physical_device.as_raw() as _,
+                device: device.device.handle().as_raw() as _,
+                queue_family_index,
+                queue_index: 0,
+            })
+        }.context(Subsystem::Renderer, "create OpenXR session")?;
+
+        let formats = session.enumerate_swapchain_formats().context(Subsystem::Renderer, "enumerate OpenXR swapchain formats")?;
+        if !formats.contains(&(format.as_raw() as u32)) {
+            return Err(AppError::Generic(format!("The OpenXR runtime does not accept the window's format {:?}", format)));
+        }
+        let view_configs = xr_instance.enumerate_view_configuration_views(system, VIEW_TYPE)
+            .context(Subsystem::Renderer, "enumerate headset views")?;
+        let blend_mode = xr_instance.enumerate_environment_blend_modes(system, VIEW_TYPE)
+            .context(Subsystem::Renderer, "enumerate blend modes")?
+            .first()
+            .copied()
+            .unwrap_or(xr::EnvironmentBlendMode::OPAQUE);
+        // Both eyes share the depth buffer, so they use the larger size
+        let extent = vk::Extent2D {
+            width: view_configs.iter().map(|view| view.recommended_image_rect_width).max().unwrap_or(1),
+            height: view_configs.iter().map(|view| view.recommended_image_rect_height).max().unwrap_or(1),
+        };
+        info!("Headset eyes {}x{}, blend mode {:?}", extent.width, extent.height, blend_mode);
+
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(device.physical_device) };
+        let depth = DepthBuffer::new(&device.device, &memory_properties, extent, DepthUsage::Discard)?;
+        let eyes = (0..EYES)
+            .map(|_| Self::create_eye_swapchain(&session, &device.device, present_pass, format, extent, depth.view))
+            .collect::<Result<Vec<_>>>()?;
+
+        let space = session.create_reference_space(xr::ReferenceSpaceType::LOCAL, xr::Posef::IDENTITY)
+            .context(Subsystem::Renderer, "create OpenXR reference space")?;
+
+        let action_set = xr_instance.create_action_set("controllers", "Controllers", 0)
+            .context(Subsystem::Renderer, "create OpenXR action set")?;
+        let hand_paths = [
+            xr_instance.string_to_path("/user/hand/left").context(Subsystem::Renderer, "create OpenXR path")?,
+            xr_instance.string_to_path("/user/hand/right").context(Subsystem::Renderer, "create OpenXR path")?,
+        ];
+        let grip_action = action_set.create_action::<xr::Posef>("grip", "Grip pose", &hand_paths)
+            .context(Subsystem::Renderer, "create grip action")?;
+        let select_action = action_set.create_action::<bool>("select", "Select", &hand_paths)
+            .context(Subsystem::Renderer, "create select action")?;
+        let path = |path: &str| xr_instance.string_to_path(path).context(Subsystem::Renderer, "create OpenXR path");
+        xr_instance.suggest_interaction_profile_bindings(path(config::xr::INTERACTION_PROFILE)?, &[
+            xr::Binding::new(&grip_action, path("/user/hand/left/input/grip/pose")?),
+            xr::Binding::new(&grip_action, path("/user/hand/right/input/grip/pose")?),
+            xr::Binding::new(&select_action, path("/user/hand/left/input/select/click")?),
+            xr::Binding::new(&select_action, path("/user/hand/right/input/select/click")?),
+        ]).context(Subsystem::Renderer, "suggest controller bindings")?;
+        session.attach_action_sets(&[&action_set]).context(Subsystem::Renderer, "attach OpenXR action set")?;
+        let hand_spaces = [
+            grip_action.create_space(session.clone(), hand_paths[0], xr::Posef::IDENTITY)
+                .context(Subsystem::Renderer, "create left hand space")?,
+            grip_action.create_space(session.clone(), hand_paths[1], xr::Posef::IDENTITY)
+                .context(Subsystem::Renderer, "create right hand space")?,
+        ];
+
+        let (command_pool, command_buffer, fence) = unsafe {
+            let command_pool = device.device.create_command_pool(&vk::CommandPoolCreateInfo::default()
+                .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                .queue_family_index(queue_family_index), None)
+                .vk_context(Subsystem::Renderer, "create XR command pool")?;
+            let command_buffer = device.device.allocate_command_buffers(&vk::CommandBufferAllocateInfo::default()
+                .command_pool(command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1))
+                .vk_context(Subsystem::Renderer, "allocate XR command buffer")?[0];
+            let fence = device.device.create_fence(&vk::FenceCreateInfo::default(), None)
+                .vk_context(Subsystem::Renderer, "create XR fence")?;
+            (command_pool, command_buffer, fence)
+        };
+
+        info!("OpenXR session created");
+        Ok(Self {
+            session,
+            frame_waiter,
+            frame_stream,
+            space,
+            action_set,
+            grip_action,
+            select_action,
+            hand_paths,
+            hand_spaces,
+            eyes,
+            extent,
+            depth,
+            command_pool,
+            command_buffer,
+            fence,
+            queue: device.graphics_queue,
+            blend_mode,
+            running: false,
+            exiting: false,
+            instance: xr_instance,
+            device: device.device.clone(),
+        })
+    }
+
+    /// Create the swapchain of one eye and a framebuffer per image
+    fn create_eye_swapchain(
+        session: &xr::Session<xr::Vulkan>,
+        device: &Device,
+        present_pass: &PassLayout,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        depth_view: vk::ImageView,
+    ) -> Result<EyeSwapchain> {
+        let swapchain = session.create_swapchain(&xr::SwapchainCreateInfo {
+            create_flags: xr::SwapchainCreateFlags::EMPTY,
+            usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT,
+            format: format.as_raw() as u32,
+            sample_count: 1,
+            width: extent.width,
+            height: extent.height,
+            face_count: 1,
+            array_size: 1,
+            mip_count: 1,
+        }).context(Subsystem::Renderer, "create eye swapchain")?;
+        let images: Vec<vk::Image> = swapchain.enumerate_images()
+            .context(Subsystem::Renderer, "enumerate eye swapchain images")?
+            .into_iter()
+            .map(vk::Image::from_raw)
+            .collect();
+        let mut views = Vec::with_capacity(images.len());
+        let mut framebuffers = Vec::with_capacity(images.len());
+        for &image in &images {
+            let view = unsafe {
+                device.create_image_view(&vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(format)
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    }), None)
+            }.vk_context(Subsystem::Renderer, "create eye image view")?;
+            views.push(view);
+            framebuffers.push(present_pass.create_framebuffer(device, view, depth_view, extent)?);
+        }
+        debug!("Eye swapchain with {} images", images.len());
+        Ok(EyeSwapchain { swapchain, images, views, framebuffers })
+    }
+
+    /// Resolution of each eye
+    pub fn eye_extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Whether the runtime is closing the session and the app should quit
+    pub fn is_exiting(&self) -> bool {
+        self.exiting
+    }
+
+    /// Handle session state changes; starts and stops the session as the runtime asks
+    fn poll_events(&mut self) -> Result<()> {
+        let mut buffer = xr::EventDataBuffer::new();
+        while let Some(event) = self.instance.poll_event(&mut buffer).context(Subsystem::Renderer, "poll OpenXR events")? {
+            match event {
+                xr::Event::SessionStateChanged(change) => {
+                    info!("OpenXR session {:?}", change.state());
+                    match change.state() {
+                        xr::SessionState::READY => {
+                            self.session.begin(VIEW_TYPE).context(Subsystem::Renderer, "begin OpenXR session")?;
+                            self.running = true;
+                        }
+                        xr::SessionState::STOPPING => {
+                            self.session.end().context(Subsystem::Renderer, "end OpenXR session")?;
+                            self.running = false;
+                        }
+                        xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => {
+                            self.running = false;
+                            self.exiting = true;
+                        }
+                        _ => {}
+                    }
+                }
+                xr::Event::InstanceLossPending(_) => {
+                    warn!("OpenXR runtime is going away");
+                    self.running = false;
+                    self.exiting = true;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for the headset's next frame and locate the eyes and controllers
+    ///
+    /// Blocks until the runtime wants a frame, which paces the app to the
+    /// headset's refresh rate.
+    ///
+    /// # Arguments
+    /// * `anchor` - Camera the tracking space is placed at
+    ///
+    /// # Returns
+    /// The frame to render with `acquire`, `eye_target`, `submit`, `release`
+    /// and `end_frame`; None while the session is not running or the runtime
+    /// skips rendering this frame
+    ///
+    /// # Errors
+    /// Returns an error if an OpenXR call fails; a frame that was begun is
+    /// ended without layers first
+    pub fn begin_frame(&mut self, anchor: &LookCamera) -> Result<Option<XrFrame>> {
+        self.poll_events()?;
+        if !self.running {
+            return Ok(None);
+        }
+        let state = self.frame_waiter.wait().context(Subsystem::Renderer, "wait for headset frame")?;
+        self.frame_stream.begin().context(Subsystem::Renderer, "begin headset frame")?;
+        if !state.should_render {
+            self.end_empty_frame(&state)?;
+            return Ok(None);
+        }
+        match self.locate(state, anchor) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(e) => {
+                self.end_empty_frame(&state)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Locate the eyes and controllers at a frame's predicted display time
+    fn locate(&mut self, state: xr::FrameState, anchor: &LookCamera) -> Result<XrFrame> {
+        self.session.sync_actions(&[xr::ActiveActionSet::new(&self.action_set)])
+            .context(Subsystem::Renderer, "sync controller actions")?;
+        let time = state.predicted_display_time;
+        let (_, views) = self.session.locate_views(VIEW_TYPE, time, &self.space)
+            .context(Subsystem::Renderer, "locate headset views")?;
+        let to_world = |pose: xr::Posef| XrPose::from_tracking(
+            [pose.position.x, pose.position.y, pose.position.z],
+            [pose.orientation.x, pose.orientation.y, pose.orientation.z, pose.orientation.w],
+            anchor,
+        );
+        let eye_poses: Vec<XrPose> = views.iter().map(|view| to_world(view.pose)).collect();
+        let mut eyes = [ViewCamera::default(); EYES];
+        for ((eye, eye_pose), view) in eyes.iter_mut().zip(&eye_poses).zip(&views) {
+            let fov = ViewFov::from_angles(view.fov.angle_left, view.fov.angle_right, view.fov.angle_up, view.fov.angle_down);
+            *eye = ViewCamera { fov: Some(fov), ..eye_pose.view_camera() };
+        }
+
+        let mut input = XrInput { head: eye_poses.first().copied(), ..XrInput::default() };
+        for (hand, (space, path)) in input.hands.iter_mut().zip(self.hand_spaces.iter().zip(self.hand_paths)) {
+            let location = space.locate(&self.space, time).context(Subsystem::Renderer, "locate controller")?;
+            let tracked = xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+            let active = self.grip_action.is_active(&self.session, path).context(Subsystem::Renderer, "read grip pose")?;
+            if active && location.location_flags.contains(tracked) {
+                hand.pose = Some(to_world(location.pose));
+            }
+            hand.select = self.select_action.state(&self.session, path)
+                .context(Subsystem::Renderer, "read select button")?
+                .current_state;
+        }
+
+        Ok(XrFrame { state, views, eyes, input })
+    }
+
+    /// Acquire the next image of an eye's swapchain and wait until it can be drawn
+    ///
+    /// # Returns
+    /// The index to pass to `eye_target`
+    ///
+    /// # Errors
+    /// Returns an error if the runtime fails to hand out an image
+    pub fn acquire(&mut self, eye: usize) -> Result<usize> {
+        let swapchain = &mut self.eyes[eye].swapchain;
+        let index = swapchain.acquire_image().context(Subsystem::Renderer, "acquire eye image")?;
+        swapchain.wait_image(xr::Duration::INFINITE).context(Subsystem::Renderer, "wait for eye image")?;
+        Ok(index as usize)
+    }
+
+    /// Images the composite pass draws an eye into
+    pub fn eye_target(&self, eye: usize, image_index: usize) -> PassTarget {
+        let swapchain = &self.eyes[eye];
+        PassTarget {
+            framebuffer: swapchain.framebuffers[image_index],
+            color_image: swapchain.images[image_index],
+            color_view: swapchain.views[image_index],
+            depth_image: self.depth.image,
+            depth_view: self.depth.view,
+            extra_colors: Default::default(),
+        }
+    }
+
+    /// Begin recording the commands of one eye
+    ///
+    /// # Errors
+    /// Returns an error if the command buffer cannot be begun
+    pub fn begin_commands(&self) -> Result<vk::CommandBuffer> {
+        unsafe {
+            self.device.reset_command_buffer(self.command_buffer, vk::CommandBufferResetFlags::empty())
+                .vk_context(Subsystem::Renderer, "reset XR command buffer")?;
+            self.device.begin_command_buffer(self.command_buffer, &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT))
+                .vk_context(Subsystem::Renderer, "begin XR command buffer")?;
+        }
+        Ok(self.command_buffer)
+    }
+
+    /// Submit an eye's commands and wait for them
+    ///
+    /// The present pass leaves the image ready for presenting; the runtime
+    /// expects it as a color attachment, so it is transitioned first.
+    ///
+    /// # Arguments
+    /// * `eye` - The eye drawn
+    /// * `image_index` - Image returned by `acquire`
+    ///
+    /// # Errors
+    /// Returns an error if submission or the wait fails
+    pub fn submit(&mut self, eye: usize, image_index: usize) -> Result<()> {
+        let cb = self.command_buffer;
+        unsafe {
+            let to_runtime = vk::ImageMemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::empty())
+                .old_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(self.eyes[eye].images[image_index])
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                });
+            self.device.cmd_pipeline_barrier(
+                cb,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_runtime],
+            );
+            self.device.end_command_buffer(cb)
+                .vk_context(Subsystem::Renderer, "end XR command buffer")?;
+            let command_buffers = [cb];
+            self.device.reset_fences(&[self.fence])
+                .vk_context(Subsystem::Renderer, "reset XR fence")?;
+            self.device.queue_submit(self.queue, &[vk::SubmitInfo::default().command_buffers(&command_buffers)], self.fence)
+                .vk_context(Subsystem::Renderer, "submit eye")?;
+            self.device.wait_for_fences(&[self.fence], true, config::xr::FRAME_TIMEOUT_NS)
+                .vk_context(Subsystem::Renderer, "wait for eye")?;
+        }
+        Ok(())
+    }
+
+    /// Hand an eye's image back to the runtime, drawn or not
+    ///
+    /// Every image returned by `acquire` must be released, also when drawing
+    /// it failed, or the next `acquire` of the eye fails.
+    ///
+    /// # Errors
+    /// Returns an error if the runtime rejects the release
+    pub fn release(&mut self, eye: usize) -> Result<()> {
+        self.eyes[eye].swapchain.release_image().context(Subsystem::Renderer, "release eye image")
+    }
+
+    /// Show the rendered eyes in the headset
+    ///
+    /// # Arguments
+    /// * `frame` - Frame returned by `begin_frame`
+    /// * `drawn` - Whether every eye was submitted; otherwise the frame is
+    ///   ended without layers, which keeps the runtime's frame loop going
+    ///
+    /// # Errors
+    /// Returns an error if the runtime rejects the frame
+    pub fn end_frame(&mut self, frame: &XrFrame, drawn: bool) -> Result<()> {
+        if !drawn {
+            return self.end_empty_frame(&frame.state);
+        }
+        let rect = xr::Rect2Di {
+            offset: xr::Offset2Di { x: 0, y: 0 },
+            extent: xr::Extent2Di { width: self.extent.width as i32, height: self.extent.height as i32 },
+        };
+        let projection_views: Vec<_> = frame.views.iter().zip(&self.eyes)
+            .map(|(view, eye)| xr::CompositionLayerProjectionView::new()
+                .pose(view.pose)
+                .fov(view.fov)
+                .sub_image(xr::SwapchainSubImage::new().swapchain(&eye.swapchain).image_rect(rect)))
+            .collect();
+        let layer = xr::CompositionLayerProjection::new().space(&self.space).views(&projection_views);
+        self.frame_stream.end(frame.state.predicted_display_time, self.blend_mode, &[&layer])
+            .context(Subsystem::Renderer, "end headset frame")?;
+        Ok(())
+    }
+
+    /// End a begun frame without showing anything
+    fn end_empty_frame(&mut self, state: &xr::FrameState) -> Result<()> {
+        self.frame_stream.end(state.predicted_display_time, self.blend_mode, &[])
+            .context(Subsystem::Renderer, "end headset frame")
+    }
+}
+
+impl Drop for XrSession {
+    fn drop(&mut self) {
+        debug!("Destroying OpenXR session resources");
+        unsafe {
+            self.device.destroy_fence(self.fence, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+            for eye in &self.eyes {
+                for &framebuffer in &eye.framebuffers {
+                    self.device.destroy_framebuffer(framebuffer, None);
+                }
+                for &view in &eye.views {
+                    self.device.destroy_image_view(view, None);
+                }
+            }
+        }
+        self.depth.destroy(&self.device);
+        // The swapchains and the session are destroyed by their own drops, before the Vulkan device
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_tracking_poses_follow_the_anchor() {
+        let anchor = LookCamera { origin: [1.0, 2.0, 3.0], yaw: 0.0, ..LookCamera::default() };
+        // Identity looks along OpenXR's -Z, the world's +Z
+        let pose = XrPose::from_tracking([0.0, 0.0, -1.0], [0.0, 0.0, 0.0, 1.0], &anchor);
+        assert_close(pose.position, [1.0, 2.0, 4.0]);
+        assert_close(pose.view_camera().forward, [0.0, 0.0, 1.0]);
+
+        // Turning the anchor a quarter to the right turns the tracking space with it
+        let turned = LookCamera { yaw: std::f32::consts::FRAC_PI_2, ..anchor };
+        let pose = XrPose::from_tracking([0.0, 0.0, -1.0], [0.0, 0.0, 0.0, 1.0], &turned);
+        assert_close(pose.position, [2.0, 2.0, 3.0]);
+        assert_close(pose.view_camera().forward, [1.0, 0.0, 0.0]);
+
+        // A head turned left by 90 degrees in OpenXR (about +Y) looks along the world's -X
+        let half = std::f32::consts::FRAC_PI_4;
+        let pose = XrPose::from_tracking([0.0; 3], [0.0, half.sin(), 0.0, half.cos()], &anchor);
+        assert_close(pose.view_camera().forward, [-1.0, 0.0, 0.0]);
+
+        // Tilting the head sideways by 90 degrees rolls the eye but keeps its view direction
+        let pose = XrPose::from_tracking([0.0; 3], [0.0, 0.0, half.sin(), half.cos()], &anchor);
+        assert_close(pose.view_camera().forward, [0.0, 0.0, 1.0]);
+        assert!((pose.view_camera().roll.abs() - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_eyes_fit_the_offscreen_target() {
+        let eye = vk::Extent2D { width: 2000, height: 2000 };
+        let target = vk::Extent2D { width: 1920, height: 1080 };
+        assert_eq!(fit_extent(eye, target), vk::Extent2D { width: 1080, height: 1080 });
+        let small = vk::Extent2D { width: 800, height: 900 };
+        assert_eq!(fit_extent(small, target), small);
+    }
+}